@@ -0,0 +1,122 @@
+//! BOML WebAssembly 绑定
+//!
+//! 通过 wasm-bindgen 导出 BOML 编解码接口,供浏览器端管理工具在客户端检查文档。
+//! 以 `wasm32-unknown-unknown` 为目标构建:
+//!
+//! ```text
+//! cargo build -p mikudb-boml-wasm --target wasm32-unknown-unknown --release
+//! wasm-bindgen --target web target/wasm32-unknown-unknown/release/mikudb_boml_wasm.wasm --out-dir pkg
+//! ```
+//!
+//! cdylib 只在本 crate 中生成,`mikudb-boml` 本身保持为普通库。
+//!
+//! 输入的 BOML 数据既可以是带魔数和校验和的完整文档(`encode_document` 的输出),
+//! 也可以是裸值编码(`encode_to_vec` 的输出),通过魔数自动识别。
+
+use mikudb_boml::codec;
+use mikudb_boml::json::{from_json_string, to_json};
+use mikudb_boml::spec::BOML_MAGIC;
+use mikudb_boml::{BomlError, BomlResult, BomlValue};
+use wasm_bindgen::prelude::*;
+
+/// # Brief
+/// 解码 BOML 数据,自动识别完整文档格式和裸值格式
+fn decode_any(data: &[u8]) -> BomlResult<BomlValue> {
+    if data.len() >= 4 && data[0..4] == BOML_MAGIC {
+        codec::decode_document(data)
+    } else {
+        codec::decode(data)
+    }
+}
+
+fn encode_json_impl(json: &str) -> BomlResult<Vec<u8>> {
+    codec::encode_document(&from_json_string(json)?)
+}
+
+fn decode_to_json_impl(data: &[u8], pretty: bool) -> BomlResult<String> {
+    let json = to_json(&decode_any(data)?)?;
+    let result = if pretty {
+        serde_json::to_string_pretty(&json)
+    } else {
+        serde_json::to_string(&json)
+    };
+    result.map_err(|e| BomlError::Serialization(format!("JSON serialization failed: {}", e)))
+}
+
+fn to_js_error(e: BomlError) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// # Brief
+/// 将 JSON 字符串编码为 BOML 文档(带魔数和校验和)
+///
+/// # Arguments
+/// * `json` - JSON 字符串
+///
+/// # Returns
+/// BOML 字节(在 JS 中为 `Uint8Array`)
+#[wasm_bindgen(js_name = encodeJson)]
+pub fn encode_json(json: &str) -> Result<Vec<u8>, JsError> {
+    encode_json_impl(json).map_err(to_js_error)
+}
+
+/// # Brief
+/// 将 BOML 数据解码为紧凑 JSON 字符串
+///
+/// # Arguments
+/// * `data` - BOML 字节
+#[wasm_bindgen(js_name = decodeToJson)]
+pub fn decode_to_json(data: &[u8]) -> Result<String, JsError> {
+    decode_to_json_impl(data, false).map_err(to_js_error)
+}
+
+/// # Brief
+/// 将 BOML 数据解码为带缩进的 JSON 字符串,用于展示
+///
+/// # Arguments
+/// * `data` - BOML 字节
+#[wasm_bindgen(js_name = prettyPrint)]
+pub fn pretty_print(data: &[u8]) -> Result<String, JsError> {
+    decode_to_json_impl(data, true).map_err(to_js_error)
+}
+
+/// # Brief
+/// 校验 BOML 数据是否可以成功解码(含校验和验证)
+///
+/// # Arguments
+/// * `data` - BOML 字节
+#[wasm_bindgen(js_name = isValid)]
+pub fn is_valid(data: &[u8]) -> bool {
+    decode_any(data).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let bytes = encode_json_impl(r#"{"name": "Miku", "age": 16}"#).unwrap();
+        assert_eq!(&bytes[0..4], &BOML_MAGIC);
+        assert!(is_valid(&bytes));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&decode_to_json_impl(&bytes, false).unwrap()).unwrap();
+        assert_eq!(json["name"], "Miku");
+        assert_eq!(json["age"], 16);
+    }
+
+    #[test]
+    fn test_decode_bare_value() {
+        let bytes = codec::encode_to_vec(&BomlValue::Int32(39)).unwrap();
+        assert_eq!(decode_to_json_impl(&bytes, true).unwrap(), "39");
+    }
+
+    #[test]
+    fn test_corrupted_document_is_invalid() {
+        let mut bytes = encode_json_impl(r#"{"a": 1}"#).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        assert!(!is_valid(&bytes));
+    }
+}
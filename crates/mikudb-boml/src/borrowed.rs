@@ -0,0 +1,217 @@
+//! 借用解码值模块
+//!
+//! 定义 [`BomlValueRef`],由 [`crate::codec::decode_borrowed`] 产生:
+//! 字符串、二进制数据、正则和 JavaScript 代码直接借用输入缓冲区,
+//! 不为每个字段分配 `CompactString`/`Vec<u8>`,适合只读取少数字段的读多写少场景。
+//! 需要长期持有或修改时通过 `to_boml_value` 转换为拥有所有权的 [`BomlValue`]。
+
+use crate::value::{BomlValue, JavaScriptValue, RegexValue};
+use chrono::{DateTime, Utc};
+use compact_str::CompactString;
+use indexmap::IndexMap;
+use mikudb_common::ObjectId;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+/// 借用输入缓冲区的 BOML 值
+///
+/// 文档保存为按编码顺序排列的键值对列表,按键查找为线性扫描,
+/// 对字段不多的文档比构建哈希表更省。
+#[derive(Debug, Clone, PartialEq)]
+pub enum BomlValueRef<'a> {
+    /// 空值
+    Null,
+    /// 布尔值
+    Boolean(bool),
+    /// 32位有符号整数
+    Int32(i32),
+    /// 64位有符号整数
+    Int64(i64),
+    /// 128位有符号整数
+    Int128(i128),
+    /// 32位浮点数
+    Float32(f32),
+    /// 64位浮点数
+    Float64(f64),
+    /// 高精度十进制数
+    Decimal(Decimal),
+    /// 借用的 UTF-8 字符串
+    String(&'a str),
+    /// 借用的二进制数据
+    Binary(&'a [u8]),
+    /// 12字节的唯一对象标识符
+    ObjectId(ObjectId),
+    /// UUID
+    Uuid(Uuid),
+    /// UTC 日期时间
+    DateTime(DateTime<Utc>),
+    /// Unix 时间戳（毫秒）
+    Timestamp(i64),
+    /// 值数组
+    Array(Vec<BomlValueRef<'a>>),
+    /// 文档（有序键值对）
+    Document(Vec<(&'a str, BomlValueRef<'a>)>),
+    /// 正则表达式
+    Regex {
+        /// 模式
+        pattern: &'a str,
+        /// 选项
+        options: &'a str,
+    },
+    /// JavaScript 代码
+    JavaScript {
+        /// 代码
+        code: &'a str,
+        /// 作用域（可选的变量绑定）
+        scope: Option<Vec<(&'a str, BomlValueRef<'a>)>>,
+    },
+}
+
+impl<'a> BomlValueRef<'a> {
+    /// # Brief
+    /// 获取值的类型名称,与 [`BomlValue::type_name`] 一致
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            BomlValueRef::Null => "null",
+            BomlValueRef::Boolean(_) => "boolean",
+            BomlValueRef::Int32(_) => "int32",
+            BomlValueRef::Int64(_) => "int64",
+            BomlValueRef::Int128(_) => "int128",
+            BomlValueRef::Float32(_) => "float32",
+            BomlValueRef::Float64(_) => "float64",
+            BomlValueRef::Decimal(_) => "decimal",
+            BomlValueRef::String(_) => "string",
+            BomlValueRef::Binary(_) => "binary",
+            BomlValueRef::ObjectId(_) => "objectId",
+            BomlValueRef::Uuid(_) => "uuid",
+            BomlValueRef::DateTime(_) => "dateTime",
+            BomlValueRef::Timestamp(_) => "timestamp",
+            BomlValueRef::Array(_) => "array",
+            BomlValueRef::Document(_) => "document",
+            BomlValueRef::Regex { .. } => "regex",
+            BomlValueRef::JavaScript { .. } => "javascript",
+        }
+    }
+
+    /// # Brief
+    /// 检查值是否为 Null
+    pub fn is_null(&self) -> bool {
+        matches!(self, BomlValueRef::Null)
+    }
+
+    /// # Brief
+    /// 尝试获取布尔值
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            BomlValueRef::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 尝试获取 i64 值(Int32 或 Int64)
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            BomlValueRef::Int32(n) => Some(*n as i64),
+            BomlValueRef::Int64(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 尝试获取 f64 值(支持整数和浮点数)
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            BomlValueRef::Float32(n) => Some(*n as f64),
+            BomlValueRef::Float64(n) => Some(*n),
+            BomlValueRef::Int32(n) => Some(*n as f64),
+            BomlValueRef::Int64(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 尝试获取字符串,生命周期与输入缓冲区相同
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            BomlValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 尝试获取二进制数据,生命周期与输入缓冲区相同
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            BomlValueRef::Binary(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 获取文档中指定键的值,或数组中指定索引的值
+    ///
+    /// # Arguments
+    /// * `key` - 键名（文档）或索引字符串（数组）
+    pub fn get(&self, key: &str) -> Option<&BomlValueRef<'a>> {
+        match self {
+            BomlValueRef::Document(fields) => fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v),
+            BomlValueRef::Array(arr) => key.parse::<usize>().ok().and_then(|i| arr.get(i)),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 按点分隔的路径获取嵌套值,如 "user.address.city"
+    pub fn get_path(&self, path: &str) -> Option<&BomlValueRef<'a>> {
+        let mut current = self;
+        for part in path.split('.') {
+            current = current.get(part)?;
+        }
+        Some(current)
+    }
+
+    /// # Brief
+    /// 复制为拥有所有权的 BomlValue
+    pub fn to_boml_value(&self) -> BomlValue {
+        match self {
+            BomlValueRef::Null => BomlValue::Null,
+            BomlValueRef::Boolean(b) => BomlValue::Boolean(*b),
+            BomlValueRef::Int32(n) => BomlValue::Int32(*n),
+            BomlValueRef::Int64(n) => BomlValue::Int64(*n),
+            BomlValueRef::Int128(n) => BomlValue::Int128(*n),
+            BomlValueRef::Float32(n) => BomlValue::Float32(*n),
+            BomlValueRef::Float64(n) => BomlValue::Float64(*n),
+            BomlValueRef::Decimal(d) => BomlValue::Decimal(*d),
+            BomlValueRef::String(s) => BomlValue::String(CompactString::new(s)),
+            BomlValueRef::Binary(b) => BomlValue::Binary(b.to_vec()),
+            BomlValueRef::ObjectId(id) => BomlValue::ObjectId(*id),
+            BomlValueRef::Uuid(u) => BomlValue::Uuid(*u),
+            BomlValueRef::DateTime(dt) => BomlValue::DateTime(*dt),
+            BomlValueRef::Timestamp(ts) => BomlValue::Timestamp(*ts),
+            BomlValueRef::Array(arr) => BomlValue::Array(arr.iter().map(BomlValueRef::to_boml_value).collect()),
+            BomlValueRef::Document(fields) => BomlValue::Document(owned_fields(fields)),
+            BomlValueRef::Regex { pattern, options } => BomlValue::Regex(RegexValue {
+                pattern: CompactString::new(pattern),
+                options: CompactString::new(options),
+            }),
+            BomlValueRef::JavaScript { code, scope } => BomlValue::JavaScript(JavaScriptValue {
+                code: CompactString::new(code),
+                scope: scope.as_deref().map(owned_fields),
+            }),
+        }
+    }
+}
+
+fn owned_fields(fields: &[(&str, BomlValueRef<'_>)]) -> IndexMap<CompactString, BomlValue> {
+    fields
+        .iter()
+        .map(|(k, v)| (CompactString::new(k), v.to_boml_value()))
+        .collect()
+}
+
+impl<'a> From<&BomlValueRef<'a>> for BomlValue {
+    fn from(value: &BomlValueRef<'a>) -> Self {
+        value.to_boml_value()
+    }
+}
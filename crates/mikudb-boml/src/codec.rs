@@ -2,9 +2,15 @@
 //!
 //! 提供 BOML 格式的二进制序列化和反序列化功能。
 //! 使用 xxHash3 进行校验和计算，在 ARM64 (鲲鹏) 上有优秀性能。
+//! `StreamDecoder` 支持增量解码分块到达的数据;
+//! `decode_borrowed` 返回借用输入缓冲区的 `BomlValueRef`,读多写少时减少内存分配。
+//! `encode_document_with` 可以用 LZ4/Zstd 压缩较大的文档: 压缩后的文档使用版本 2 的文档头,
+//! 版本号后多一个压缩算法字节;不压缩的文档仍是版本 1,与旧数据格式相同。
+//! `decode_document_projected` 只解码需要的顶层字段,其余字段按类型标记和长度跳过。
 
 use crate::spec::*;
-use crate::value::{BomlValue, JavaScriptValue, RegexValue};
+use crate::borrowed::BomlValueRef;
+use crate::value::{BomlValue, JavaScriptValue};
 use crate::{BomlError, BomlResult};
 use bytes::{Buf, BufMut, BytesMut};
 use chrono::{TimeZone, Utc};
@@ -12,6 +18,8 @@ use compact_str::CompactString;
 use indexmap::IndexMap;
 use mikudb_common::ObjectId;
 use rust_decimal::Decimal;
+use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::io::{Read, Write};
 use uuid::Uuid;
 
@@ -60,6 +68,20 @@ pub fn decode(data: &[u8]) -> BomlResult<BomlValue> {
     Decoder::new(data).decode_value()
 }
 
+/// 借用解码二进制数据
+///
+/// # Brief
+/// 与 [`decode`] 相同,但字符串和二进制数据直接借用 `data`,不逐字段分配内存
+///
+/// # Arguments
+/// * `data` - 要解码的字节切片,解码结果的生命周期不超过它
+///
+/// # Returns
+/// 成功返回 BomlValueRef, 失败返回错误
+pub fn decode_borrowed(data: &[u8]) -> BomlResult<BomlValueRef<'_>> {
+    Decoder::new(data).decode_value_ref()
+}
+
 /// 编码文档（带魔数和校验和）
 ///
 /// # Brief
@@ -83,10 +105,39 @@ pub fn encode_document(value: &BomlValue) -> BomlResult<Vec<u8>> {
     Ok(buf.to_vec())
 }
 
+/// 编码文档并按需压缩
+///
+/// # Brief
+/// 编码后不小于 [`COMPRESSION_MIN_SIZE`] 且压缩后更小时写入压缩的文档体,
+/// 文档头记录压缩算法;否则与 [`encode_document`] 的输出相同
+///
+/// # Arguments
+/// * `value` - 要编码的文档值
+/// * `compression` - 压缩算法
+///
+/// # Returns
+/// 成功返回带校验和的字节向量, 失败返回错误
+pub fn encode_document_with(value: &BomlValue, compression: DocumentCompression) -> BomlResult<Vec<u8>> {
+    if compression == DocumentCompression::None {
+        return encode_document(value);
+    }
+
+    let mut body = BytesMut::with_capacity(256);
+    encode(value, &mut body)?;
+    if body.len() < COMPRESSION_MIN_SIZE {
+        return finish_document(BOML_VERSION, &[], &body);
+    }
+    let compressed = compress(compression, &body)?;
+    if compressed.len() >= body.len() {
+        return finish_document(BOML_VERSION, &[], &body);
+    }
+    finish_document(BOML_VERSION_COMPRESSED, &[compression as u8], &compressed)
+}
+
 /// 解码文档（带魔数和校验和验证）
 ///
 /// # Brief
-/// 解码完整的 BOML 文档，验证魔数、版本号和 xxHash3 校验和
+/// 解码完整的 BOML 文档，验证魔数、版本号和 xxHash3 校验和，压缩的文档先解压
 ///
 /// # Arguments
 /// * `data` - 要解码的字节切片
@@ -94,6 +145,110 @@ pub fn encode_document(value: &BomlValue) -> BomlResult<Vec<u8>> {
 /// # Returns
 /// 成功返回 BomlValue, 校验失败或格式错误返回错误
 pub fn decode_document(data: &[u8]) -> BomlResult<BomlValue> {
+    decode(&document_payload(data)?)
+}
+
+/// 借用解码文档（带魔数和校验和验证）
+///
+/// # Brief
+/// 与 [`decode_document`] 相同的校验,解码部分使用 [`decode_borrowed`]。
+/// 压缩的文档无法借用输入缓冲区,需要先用 [`document_payload`] 解压
+///
+/// # Arguments
+/// * `data` - 要解码的字节切片
+///
+/// # Returns
+/// 成功返回 BomlValueRef, 校验失败、格式错误或文档被压缩时返回错误
+pub fn decode_document_borrowed(data: &[u8]) -> BomlResult<BomlValueRef<'_>> {
+    match verify_document(data)? {
+        (BOML_VERSION, body) => decode_borrowed(body),
+        _ => Err(BomlError::InvalidDocument(
+            "Compressed document cannot be decoded borrowed".to_string(),
+        )),
+    }
+}
+
+/// 按投影解码文档（带魔数和校验和验证）
+///
+/// # Brief
+/// 与 [`decode_document`] 相同的校验,但只解码 `fields` 用到的顶层字段,
+/// 其余字段按类型标记和长度跳过而不构造值。嵌套路径 `a.b` 会保留整个顶层字段 `a`
+///
+/// # Arguments
+/// * `data` - 要解码的字节切片
+/// * `fields` - 需要的字段路径
+///
+/// # Returns
+/// 只包含所需字段的文档, 校验失败或格式错误返回错误
+pub fn decode_document_projected<S: AsRef<str>>(data: &[u8], fields: &[S]) -> BomlResult<BomlValue> {
+    let payload = document_payload(data)?;
+    let mut decoder = Decoder::new(&payload);
+    let len = match decoder.decode_token()? {
+        Token::Document(len) => len,
+        Token::Value(BomlValueRef::Document(_)) => return Ok(BomlValue::Document(IndexMap::new())),
+        _ => return Err(BomlError::InvalidDocument("Expected document type".to_string())),
+    };
+
+    decoder.depth += 1;
+    let mut doc = IndexMap::with_capacity(fields.len().min(len));
+    for _ in 0..len {
+        let key = decoder.decode_key()?;
+        if fields.iter().any(|field| path_starts_with(field.as_ref(), key)) {
+            let value = decoder.decode_value()?;
+            doc.insert(CompactString::new(key), value);
+        } else {
+            decoder.skip_value()?;
+        }
+    }
+    Ok(BomlValue::Document(doc))
+}
+
+/// # Brief
+/// 字段路径 `path` 是否等于 `key` 或位于 `key` 之下
+fn path_starts_with(path: &str, key: &str) -> bool {
+    path.strip_prefix(key)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// 获取文档的编码值部分
+///
+/// # Brief
+/// 校验文档后返回编码后的值: 未压缩时借用 `data`,压缩时返回解压后的数据
+///
+/// # Arguments
+/// * `data` - 完整的文档字节
+///
+/// # Returns
+/// 可以交给 [`decode`] 或 [`decode_borrowed`] 的字节
+pub fn document_payload(data: &[u8]) -> BomlResult<Cow<'_, [u8]>> {
+    match verify_document(data)? {
+        (BOML_VERSION, body) => Ok(Cow::Borrowed(body)),
+        (_, body) => {
+            let (&byte, compressed) = body.split_first().ok_or(BomlError::UnexpectedEof)?;
+            let compression = DocumentCompression::from_byte(byte).ok_or_else(|| {
+                BomlError::InvalidDocument(format!("Unknown compression: {}", byte))
+            })?;
+            decompress(compression, compressed).map(Cow::Owned)
+        }
+    }
+}
+
+/// # Brief
+/// 写入文档头、文档体和校验和
+fn finish_document(version: u8, header: &[u8], body: &[u8]) -> BomlResult<Vec<u8>> {
+    let mut buf = Vec::with_capacity(5 + header.len() + body.len() + 8);
+    buf.extend_from_slice(&BOML_MAGIC);
+    buf.push(version);
+    buf.extend_from_slice(header);
+    buf.extend_from_slice(body);
+    let checksum = xxhash_rust::xxh3::xxh3_64(&buf[5..]);
+    buf.extend_from_slice(&checksum.to_le_bytes());
+    Ok(buf)
+}
+
+/// # Brief
+/// 校验魔数、版本号和校验和,返回版本号和文档头之后、校验和之前的部分
+fn verify_document(data: &[u8]) -> BomlResult<(u8, &[u8])> {
     if data.len() < 13 {
         return Err(BomlError::UnexpectedEof);
     }
@@ -101,7 +256,7 @@ pub fn decode_document(data: &[u8]) -> BomlResult<BomlValue> {
         return Err(BomlError::InvalidDocument("Invalid magic number".to_string()));
     }
     let version = data[4];
-    if version != BOML_VERSION {
+    if version != BOML_VERSION && version != BOML_VERSION_COMPRESSED {
         return Err(BomlError::InvalidDocument(format!(
             "Unsupported version: {}",
             version
@@ -113,7 +268,42 @@ pub fn decode_document(data: &[u8]) -> BomlResult<BomlValue> {
     if stored_checksum != computed_checksum {
         return Err(BomlError::InvalidDocument("Checksum mismatch".to_string()));
     }
-    decode(&data[5..checksum_offset])
+    Ok((version, &data[5..checksum_offset]))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn compress(compression: DocumentCompression, body: &[u8]) -> BomlResult<Vec<u8>> {
+    match compression {
+        DocumentCompression::None => Ok(body.to_vec()),
+        DocumentCompression::Lz4 => Ok(lz4::block::compress(body, None, true)?),
+        DocumentCompression::Zstd => Ok(zstd::bulk::compress(body, 0)?),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn decompress(compression: DocumentCompression, data: &[u8]) -> BomlResult<Vec<u8>> {
+    match compression {
+        DocumentCompression::None => Ok(data.to_vec()),
+        DocumentCompression::Lz4 => {
+            // 前 4 字节是原始长度,先检查再分配
+            let size = data.get(..4).ok_or(BomlError::UnexpectedEof)?;
+            if i32::from_le_bytes(size.try_into().unwrap()) as usize > MAX_DOCUMENT_SIZE {
+                return Err(BomlError::DocumentTooLarge(MAX_DOCUMENT_SIZE));
+            }
+            Ok(lz4::block::decompress(data, None)?)
+        }
+        DocumentCompression::Zstd => Ok(zstd::bulk::decompress(data, MAX_DOCUMENT_SIZE)?),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn compress(_compression: DocumentCompression, _body: &[u8]) -> BomlResult<Vec<u8>> {
+    Err(BomlError::InvalidDocument("Compression is not supported on this target".to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn decompress(_compression: DocumentCompression, _data: &[u8]) -> BomlResult<Vec<u8>> {
+    Err(BomlError::InvalidDocument("Compression is not supported on this target".to_string()))
 }
 
 /// BOML 编码器
@@ -309,13 +499,27 @@ impl<'a> Encoder<'a> {
 
 /// BOML 解码器
 ///
-/// 内部结构，用于从二进制数据反序列化 BomlValue
+/// 内部结构，用于从二进制数据反序列化 BomlValue。
+/// 标量统一先解码为借用输入的 BomlValueRef,拥有所有权的解码路径再复制,
+/// 借用解码与普通解码共用同一套类型标记解析。
 struct Decoder<'a> {
     data: &'a [u8],
     pos: usize,
     depth: usize,
 }
 
+/// 解码器读到的单个标量值或容器头部
+enum Token<'a> {
+    /// 完整的标量值(包括空数组、空文档等单字节容器)
+    Value(BomlValueRef<'a>),
+    /// 数组头部,携带元素个数
+    Array(usize),
+    /// 文档头部,携带字段个数
+    Document(usize),
+    /// 带作用域的 JavaScript 代码,携带代码和作用域字段个数
+    JavaScriptScope(&'a str, usize),
+}
+
 impl<'a> Decoder<'a> {
     fn new(data: &'a [u8]) -> Self {
         Self {
@@ -330,133 +534,209 @@ impl<'a> Decoder<'a> {
             return Err(BomlError::NestingTooDeep(MAX_NESTING_DEPTH));
         }
 
+        match self.decode_token()? {
+            Token::Value(value) => Ok(value.to_boml_value()),
+            Token::Array(len) => self.decode_array_items(len),
+            Token::Document(len) => self.decode_document_items(len),
+            Token::JavaScriptScope(code, len) => match self.decode_document_items(len)? {
+                BomlValue::Document(scope) => Ok(BomlValue::JavaScript(JavaScriptValue {
+                    code: CompactString::new(code),
+                    scope: Some(scope),
+                })),
+                _ => Err(BomlError::InvalidDocument("Expected document for JavaScript scope".to_string())),
+            },
+        }
+    }
+
+    fn decode_value_ref(&mut self) -> BomlResult<BomlValueRef<'a>> {
+        if self.depth > MAX_NESTING_DEPTH {
+            return Err(BomlError::NestingTooDeep(MAX_NESTING_DEPTH));
+        }
+
+        match self.decode_token()? {
+            Token::Value(value) => Ok(value),
+            Token::Array(len) => {
+                check_array_len(len)?;
+                self.depth += 1;
+                let mut arr = Vec::with_capacity(len.min(self.data.len() - self.pos));
+                for _ in 0..len {
+                    arr.push(self.decode_value_ref()?);
+                }
+                self.depth -= 1;
+                Ok(BomlValueRef::Array(arr))
+            }
+            Token::Document(len) => self.decode_fields_ref(len).map(BomlValueRef::Document),
+            Token::JavaScriptScope(code, len) => Ok(BomlValueRef::JavaScript {
+                code,
+                scope: Some(self.decode_fields_ref(len)?),
+            }),
+        }
+    }
+
+    fn decode_fields_ref(&mut self, len: usize) -> BomlResult<Vec<(&'a str, BomlValueRef<'a>)>> {
+        self.depth += 1;
+        let mut fields = Vec::with_capacity(len.min(self.data.len() - self.pos));
+        for _ in 0..len {
+            let key = self.decode_key()?;
+            let value = self.decode_value_ref()?;
+            fields.push((key, value));
+        }
+        self.depth -= 1;
+        Ok(fields)
+    }
+
+    /// # Brief
+    /// 读取一个标量值或容器头部
+    ///
+    /// 容器只读取到长度为止,元素由调用方继续解码。
+    fn decode_token(&mut self) -> BomlResult<Token<'a>> {
         let marker = self.read_u8()?;
 
         if TypeMarker::is_small_string(marker) {
             let len = TypeMarker::small_string_len(marker);
-            return self.read_string(len);
+            return self.read_string(len).map(|s| Token::Value(BomlValueRef::String(s)));
         }
 
         if TypeMarker::is_small_int(marker) {
             let val = TypeMarker::small_int_value(marker);
-            return Ok(BomlValue::Int32(val as i32));
+            return Ok(Token::Value(BomlValueRef::Int32(val as i32)));
         }
 
         if TypeMarker::is_small_array(marker) {
-            let len = TypeMarker::small_array_len(marker);
-            return self.decode_array_items(len);
-        }
-
-        match TypeMarker::from_u8(marker) {
-            Some(TypeMarker::Null) => Ok(BomlValue::Null),
-            Some(TypeMarker::BooleanTrue) => Ok(BomlValue::Boolean(true)),
-            Some(TypeMarker::BooleanFalse) => Ok(BomlValue::Boolean(false)),
-            Some(TypeMarker::Int32Zero) => Ok(BomlValue::Int32(0)),
-            Some(TypeMarker::Int32One) => Ok(BomlValue::Int32(1)),
-            Some(TypeMarker::Int32NegOne) => Ok(BomlValue::Int32(-1)),
-            Some(TypeMarker::Int64Zero) => Ok(BomlValue::Int64(0)),
-            Some(TypeMarker::Float64Zero) => Ok(BomlValue::Float64(0.0)),
-            Some(TypeMarker::EmptyString) => Ok(BomlValue::String(CompactString::new(""))),
-            Some(TypeMarker::EmptyArray) => Ok(BomlValue::Array(vec![])),
-            Some(TypeMarker::EmptyDocument) => Ok(BomlValue::Document(IndexMap::new())),
+            return Ok(Token::Array(TypeMarker::small_array_len(marker)));
+        }
+
+        let value = match TypeMarker::from_u8(marker) {
+            Some(TypeMarker::Null) => Ok(BomlValueRef::Null),
+            Some(TypeMarker::BooleanTrue) => Ok(BomlValueRef::Boolean(true)),
+            Some(TypeMarker::BooleanFalse) => Ok(BomlValueRef::Boolean(false)),
+            Some(TypeMarker::Int32Zero) => Ok(BomlValueRef::Int32(0)),
+            Some(TypeMarker::Int32One) => Ok(BomlValueRef::Int32(1)),
+            Some(TypeMarker::Int32NegOne) => Ok(BomlValueRef::Int32(-1)),
+            Some(TypeMarker::Int64Zero) => Ok(BomlValueRef::Int64(0)),
+            Some(TypeMarker::Float64Zero) => Ok(BomlValueRef::Float64(0.0)),
+            Some(TypeMarker::EmptyString) => Ok(BomlValueRef::String("")),
+            Some(TypeMarker::EmptyArray) => Ok(BomlValueRef::Array(vec![])),
+            Some(TypeMarker::EmptyDocument) => Ok(BomlValueRef::Document(vec![])),
             Some(TypeMarker::Int32) => {
                 let n = self.read_i32()?;
-                Ok(BomlValue::Int32(n))
+                Ok(BomlValueRef::Int32(n))
             }
             Some(TypeMarker::Int64) => {
                 let n = self.read_i64()?;
-                Ok(BomlValue::Int64(n))
+                Ok(BomlValueRef::Int64(n))
             }
             Some(TypeMarker::Int128) => {
                 let n = self.read_i128()?;
-                Ok(BomlValue::Int128(n))
+                Ok(BomlValueRef::Int128(n))
             }
             Some(TypeMarker::Float32) => {
                 let n = self.read_f32()?;
-                Ok(BomlValue::Float32(n))
+                Ok(BomlValueRef::Float32(n))
             }
             Some(TypeMarker::Float64) => {
                 let n = self.read_f64()?;
-                Ok(BomlValue::Float64(n))
+                Ok(BomlValueRef::Float64(n))
             }
             Some(TypeMarker::Decimal) => {
                 let mut bytes = [0u8; 16];
                 self.read_exact(&mut bytes)?;
                 let d = Decimal::deserialize(bytes);
-                Ok(BomlValue::Decimal(d))
+                Ok(BomlValueRef::Decimal(d))
             }
             Some(TypeMarker::String) => {
                 let len = self.read_varint()? as usize;
-                self.read_string(len)
+                self.read_string(len).map(BomlValueRef::String)
             }
             Some(TypeMarker::Binary) => {
                 let len = self.read_varint()? as usize;
-                let bytes = self.read_bytes(len)?;
-                Ok(BomlValue::Binary(bytes))
+                let bytes = self.read_slice(len)?;
+                Ok(BomlValueRef::Binary(bytes))
             }
             Some(TypeMarker::ObjectId) => {
                 let mut bytes = [0u8; 12];
                 self.read_exact(&mut bytes)?;
-                Ok(BomlValue::ObjectId(ObjectId::from_bytes(bytes)))
+                Ok(BomlValueRef::ObjectId(ObjectId::from_bytes(bytes)))
             }
             Some(TypeMarker::Uuid) => {
                 let mut bytes = [0u8; 16];
                 self.read_exact(&mut bytes)?;
-                Ok(BomlValue::Uuid(Uuid::from_bytes(bytes)))
+                Ok(BomlValueRef::Uuid(Uuid::from_bytes(bytes)))
             }
             Some(TypeMarker::DateTime) => {
                 let millis = self.read_i64()?;
                 let dt = Utc.timestamp_millis_opt(millis).single().ok_or_else(|| {
                     BomlError::InvalidDocument("Invalid datetime".to_string())
                 })?;
-                Ok(BomlValue::DateTime(dt))
+                Ok(BomlValueRef::DateTime(dt))
             }
             Some(TypeMarker::Timestamp) => {
                 let ts = self.read_i64()?;
-                Ok(BomlValue::Timestamp(ts))
+                Ok(BomlValueRef::Timestamp(ts))
             }
             Some(TypeMarker::Array) => {
                 let len = self.read_varint()? as usize;
-                self.decode_array_items(len)
+                return Ok(Token::Array(len));
             }
             Some(TypeMarker::Document) => {
                 let len = self.read_varint()? as usize;
-                self.decode_document_items(len)
+                return Ok(Token::Document(len));
             }
             Some(TypeMarker::Regex) => {
                 let pattern_len = self.read_varint()? as usize;
-                let pattern = self.read_compact_string(pattern_len)?;
+                let pattern = self.read_str(pattern_len)?;
                 let options_len = self.read_varint()? as usize;
-                let options = self.read_compact_string(options_len)?;
-                Ok(BomlValue::Regex(RegexValue { pattern, options }))
+                let options = self.read_str(options_len)?;
+                Ok(BomlValueRef::Regex { pattern, options })
             }
             Some(TypeMarker::JavaScript) => {
                 let code_len = self.read_varint()? as usize;
-                let code = self.read_compact_string(code_len)?;
-                Ok(BomlValue::JavaScript(JavaScriptValue { code, scope: None }))
+                let code = self.read_str(code_len)?;
+                Ok(BomlValueRef::JavaScript { code, scope: None })
             }
             Some(TypeMarker::JavaScriptWithScope) => {
                 let code_len = self.read_varint()? as usize;
-                let code = self.read_compact_string(code_len)?;
+                let code = self.read_str(code_len)?;
                 let scope_len = self.read_varint()? as usize;
-                let scope_val = self.decode_document_items(scope_len)?;
-                if let BomlValue::Document(scope) = scope_val {
-                    Ok(BomlValue::JavaScript(JavaScriptValue { code, scope: Some(scope) }))
-                } else {
-                    Err(BomlError::InvalidDocument("Expected document for JavaScript scope".to_string()))
+                return Ok(Token::JavaScriptScope(code, scope_len));
+            }
+            _ => return Err(BomlError::InvalidTypeMarker(marker)),
+        }?;
+        Ok(Token::Value(value))
+    }
+
+    /// # Brief
+    /// 跳过一个值,容器逐个跳过元素,不分配内存
+    fn skip_value(&mut self) -> BomlResult<()> {
+        if self.depth > MAX_NESTING_DEPTH {
+            return Err(BomlError::NestingTooDeep(MAX_NESTING_DEPTH));
+        }
+
+        match self.decode_token()? {
+            Token::Value(_) => Ok(()),
+            Token::Array(len) => {
+                check_array_len(len)?;
+                self.depth += 1;
+                for _ in 0..len {
+                    self.skip_value()?;
                 }
+                self.depth -= 1;
+                Ok(())
+            }
+            Token::Document(len) | Token::JavaScriptScope(_, len) => {
+                self.depth += 1;
+                for _ in 0..len {
+                    self.decode_key()?;
+                    self.skip_value()?;
+                }
+                self.depth -= 1;
+                Ok(())
             }
-            _ => Err(BomlError::InvalidTypeMarker(marker)),
         }
     }
 
     fn decode_array_items(&mut self, len: usize) -> BomlResult<BomlValue> {
-        if len > MAX_ARRAY_LENGTH {
-            return Err(BomlError::InvalidDocument(format!(
-                "Array too large: {} > {}",
-                len, MAX_ARRAY_LENGTH
-            )));
-        }
+        check_array_len(len)?;
 
         self.depth += 1;
         let mut arr = Vec::with_capacity(len);
@@ -471,20 +751,7 @@ impl<'a> Decoder<'a> {
         self.depth += 1;
         let mut doc = IndexMap::with_capacity(len);
         for _ in 0..len {
-            let key_marker = self.read_u8()?;
-            let key = if TypeMarker::is_small_string(key_marker) {
-                let key_len = TypeMarker::small_string_len(key_marker);
-                self.read_compact_string(key_len)?
-            } else if key_marker == TypeMarker::EmptyString as u8 {
-                CompactString::new("")
-            } else if key_marker == TypeMarker::String as u8 {
-                let key_len = self.read_varint()? as usize;
-                self.read_compact_string(key_len)?
-            } else {
-                return Err(BomlError::InvalidDocument(
-                    "Expected string key in document".to_string(),
-                ));
-            };
+            let key = CompactString::new(self.decode_key()?);
             let value = self.decode_value()?;
             doc.insert(key, value);
         }
@@ -492,6 +759,23 @@ impl<'a> Decoder<'a> {
         Ok(BomlValue::Document(doc))
     }
 
+    fn decode_key(&mut self) -> BomlResult<&'a str> {
+        let key_marker = self.read_u8()?;
+        if TypeMarker::is_small_string(key_marker) {
+            let key_len = TypeMarker::small_string_len(key_marker);
+            self.read_str(key_len)
+        } else if key_marker == TypeMarker::EmptyString as u8 {
+            Ok("")
+        } else if key_marker == TypeMarker::String as u8 {
+            let key_len = self.read_varint()? as usize;
+            self.read_str(key_len)
+        } else {
+            Err(BomlError::InvalidDocument(
+                "Expected string key in document".to_string(),
+            ))
+        }
+    }
+
     fn read_u8(&mut self) -> BomlResult<u8> {
         if self.pos >= self.data.len() {
             return Err(BomlError::UnexpectedEof);
@@ -510,11 +794,12 @@ impl<'a> Decoder<'a> {
         Ok(())
     }
 
-    fn read_bytes(&mut self, len: usize) -> BomlResult<Vec<u8>> {
-        if self.pos + len > self.data.len() {
+    fn read_slice(&mut self, len: usize) -> BomlResult<&'a [u8]> {
+        if len > self.data.len() - self.pos {
             return Err(BomlError::UnexpectedEof);
         }
-        let bytes = self.data[self.pos..self.pos + len].to_vec();
+        let data = self.data;
+        let bytes = &data[self.pos..self.pos + len];
         self.pos += len;
         Ok(bytes)
     }
@@ -566,22 +851,269 @@ impl<'a> Decoder<'a> {
         Ok(result)
     }
 
-    fn read_string(&mut self, len: usize) -> BomlResult<BomlValue> {
+    fn read_string(&mut self, len: usize) -> BomlResult<&'a str> {
         if len > MAX_STRING_LENGTH {
             return Err(BomlError::InvalidDocument(format!(
                 "String too large: {} > {}",
                 len, MAX_STRING_LENGTH
             )));
         }
-        let bytes = self.read_bytes(len)?;
-        let s = String::from_utf8(bytes)?;
-        Ok(BomlValue::String(CompactString::from(s)))
+        self.read_str(len)
+    }
+
+    fn read_str(&mut self, len: usize) -> BomlResult<&'a str> {
+        let bytes = self.read_slice(len)?;
+        std::str::from_utf8(bytes)
+            .map_err(|_| BomlError::InvalidUtf8(String::from_utf8(bytes.to_vec()).unwrap_err()))
+    }
+}
+
+fn check_array_len(len: usize) -> BomlResult<()> {
+    if len > MAX_ARRAY_LENGTH {
+        return Err(BomlError::InvalidDocument(format!(
+            "Array too large: {} > {}",
+            len, MAX_ARRAY_LENGTH
+        )));
     }
+    Ok(())
+}
+
+/// 流式 BOML 解码器
+///
+/// # Brief
+/// 增量解码分块到达的 BOML 数据,不要求整个值先落在一块连续内存中
+///
+/// 解码器只缓存当前尚未读完的单个标量(或容器头部)的字节,已读完的部分
+/// 立即解码并挂到正在构建的容器上,因此大文档不必先完整缓冲再解析。
+/// 输入可以是首尾相接的多个值(与 [`encode`] 的输出格式相同,不带魔数和校验和)。
+///
+/// 解码出错后内部状态会被清空,之后的输入按新值的开头处理。
+///
+/// ```rust,ignore
+/// let mut decoder = StreamDecoder::new();
+/// for chunk in chunks {
+///     if let Some(value) = decoder.feed(chunk)? {
+///         handle(value);
+///     }
+///     while let Some(value) = decoder.next_value() {
+///         handle(value);
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct StreamDecoder {
+    /// 尚未组成完整标记的字节
+    pending: Vec<u8>,
+    /// 正在构建的容器,栈顶为最内层
+    stack: Vec<Frame>,
+    /// 已解码完成、尚未取走的顶层值
+    ready: VecDeque<BomlValue>,
+}
+
+/// 流式解码中正在构建的容器
+#[derive(Debug)]
+enum Frame {
+    Array {
+        remaining: usize,
+        items: Vec<BomlValue>,
+    },
+    Document {
+        remaining: usize,
+        items: IndexMap<CompactString, BomlValue>,
+        key: Option<CompactString>,
+        /// 不为 None 时表示这是 JavaScript 代码的作用域
+        code: Option<CompactString>,
+    },
+}
 
-    fn read_compact_string(&mut self, len: usize) -> BomlResult<CompactString> {
-        let bytes = self.read_bytes(len)?;
-        let s = String::from_utf8(bytes)?;
-        Ok(CompactString::from(s))
+/// 流式解码中一次读完的单元,不再借用输入缓冲区
+enum Step {
+    /// 文档字段名
+    Key(CompactString),
+    /// 完整的标量值
+    Value(BomlValue),
+    /// 新的容器
+    Open(Frame),
+}
+
+impl Step {
+    fn from_token(token: Token<'_>) -> BomlResult<Step> {
+        Ok(match token {
+            Token::Value(value) => Step::Value(value.to_boml_value()),
+            Token::Array(len) => {
+                check_array_len(len)?;
+                Step::Open(Frame::Array {
+                    remaining: len,
+                    items: Vec::with_capacity(len.min(STREAM_PREALLOCATE_LIMIT)),
+                })
+            }
+            Token::Document(len) => Step::Open(Frame::Document {
+                remaining: len,
+                items: IndexMap::with_capacity(len.min(STREAM_PREALLOCATE_LIMIT)),
+                key: None,
+                code: None,
+            }),
+            Token::JavaScriptScope(code, len) => Step::Open(Frame::Document {
+                remaining: len,
+                items: IndexMap::with_capacity(len.min(STREAM_PREALLOCATE_LIMIT)),
+                key: None,
+                code: Some(CompactString::new(code)),
+            }),
+        })
+    }
+}
+
+/// 容器元素的预分配上限,长度来自未校验的输入
+const STREAM_PREALLOCATE_LIMIT: usize = 1024;
+
+impl StreamDecoder {
+    /// 创建流式解码器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Brief
+    /// 输入一块数据并尝试解码
+    ///
+    /// # Arguments
+    /// * `chunk` - 新到达的数据,可以为空
+    ///
+    /// # Returns
+    /// 有值解码完成时返回最早完成的一个,其余可通过 `next_value` 取走;
+    /// 数据不足时返回 Ok(None)
+    pub fn feed(&mut self, chunk: &[u8]) -> BomlResult<Option<BomlValue>> {
+        self.pending.extend_from_slice(chunk);
+
+        let mut consumed = 0;
+        let result = loop {
+            let mut decoder = Decoder::new(&self.pending[consumed..]);
+            let step = if self.expects_key() {
+                decoder.decode_key().map(|key| Step::Key(CompactString::new(key)))
+            } else {
+                decoder.decode_token().and_then(Step::from_token)
+            };
+            match step {
+                Ok(step) => {
+                    consumed += decoder.pos;
+                    if let Err(e) = self.push_step(step) {
+                        break Err(e);
+                    }
+                }
+                Err(BomlError::UnexpectedEof) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        if let Err(e) = result {
+            self.reset();
+            return Err(e);
+        }
+        self.pending.drain(..consumed);
+        Ok(self.ready.pop_front())
+    }
+
+    /// # Brief
+    /// 取走下一个已解码完成的值
+    pub fn next_value(&mut self) -> Option<BomlValue> {
+        self.ready.pop_front()
+    }
+
+    /// # Brief
+    /// 是否有解码到一半的值
+    ///
+    /// 输入结束时仍返回 true 说明数据被截断。
+    pub fn in_progress(&self) -> bool {
+        !self.pending.is_empty() || !self.stack.is_empty()
+    }
+
+    /// # Brief
+    /// 当前缓存的未解码字节数
+    pub fn buffered_bytes(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// # Brief
+    /// 丢弃所有未完成和未取走的数据
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.stack.clear();
+        self.ready.clear();
+    }
+
+    fn expects_key(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Document { key: None, .. }))
+    }
+
+    fn set_key(&mut self, new_key: CompactString) {
+        if let Some(Frame::Document { key, .. }) = self.stack.last_mut() {
+            *key = Some(new_key);
+        }
+    }
+
+    fn push_step(&mut self, step: Step) -> BomlResult<()> {
+        let frame = match step {
+            Step::Key(key) => {
+                self.set_key(key);
+                return Ok(());
+            }
+            Step::Value(value) => return self.complete(value),
+            Step::Open(frame) => frame,
+        };
+
+        if self.stack.len() > MAX_NESTING_DEPTH {
+            return Err(BomlError::NestingTooDeep(MAX_NESTING_DEPTH));
+        }
+        match frame {
+            Frame::Array { remaining: 0, .. } | Frame::Document { remaining: 0, .. } => {
+                self.complete(frame.into_value())
+            }
+            frame => {
+                self.stack.push(frame);
+                Ok(())
+            }
+        }
+    }
+
+    /// # Brief
+    /// 把完成的值挂到栈顶容器上,容器因此完成时继续向外层传递
+    fn complete(&mut self, mut value: BomlValue) -> BomlResult<()> {
+        loop {
+            let done = match self.stack.last_mut() {
+                None => {
+                    self.ready.push_back(value);
+                    return Ok(());
+                }
+                Some(Frame::Array { remaining, items }) => {
+                    items.push(value);
+                    *remaining -= 1;
+                    *remaining == 0
+                }
+                Some(Frame::Document { remaining, items, key, .. }) => {
+                    let key = key.take().ok_or_else(|| {
+                        BomlError::InvalidDocument("Expected string key in document".to_string())
+                    })?;
+                    items.insert(key, value);
+                    *remaining -= 1;
+                    *remaining == 0
+                }
+            };
+            if !done {
+                return Ok(());
+            }
+            value = self.stack.pop().map(Frame::into_value).unwrap_or(BomlValue::Null);
+        }
+    }
+}
+
+impl Frame {
+    fn into_value(self) -> BomlValue {
+        match self {
+            Frame::Array { items, .. } => BomlValue::Array(items),
+            Frame::Document { items, code: None, .. } => BomlValue::Document(items),
+            Frame::Document { items, code: Some(code), .. } => {
+                BomlValue::JavaScript(JavaScriptValue { code, scope: Some(items) })
+            }
+        }
     }
 }
 
@@ -650,6 +1182,66 @@ mod tests {
         assert_eq!(value, decoded);
     }
 
+    #[test]
+    fn test_stream_decoder_chunks() {
+        let mut inner = IndexMap::new();
+        inner.insert(CompactString::from("tags"), BomlValue::Array(vec![
+            BomlValue::String(CompactString::from("a".repeat(300))),
+            BomlValue::Int64(1 << 40),
+            BomlValue::Array(vec![]),
+        ]));
+        inner.insert(CompactString::from("empty"), BomlValue::Document(IndexMap::new()));
+        let mut doc = IndexMap::new();
+        doc.insert(CompactString::from("name"), BomlValue::String(CompactString::from("miku")));
+        doc.insert(CompactString::from("inner"), BomlValue::Document(inner));
+        let first = BomlValue::Document(doc);
+        let second = BomlValue::Int32(39);
+
+        let mut bytes = encode_to_vec(&first).unwrap();
+        bytes.extend(encode_to_vec(&second).unwrap());
+
+        for chunk_size in [1, 3, 64, bytes.len()] {
+            let mut decoder = StreamDecoder::new();
+            let mut values = Vec::new();
+            for chunk in bytes.chunks(chunk_size) {
+                values.extend(decoder.feed(chunk).unwrap());
+                values.extend(std::iter::from_fn(|| decoder.next_value()));
+            }
+            assert_eq!(values, vec![first.clone(), second.clone()]);
+            assert!(!decoder.in_progress());
+        }
+
+        let mut decoder = StreamDecoder::new();
+        assert_eq!(decoder.feed(&bytes[..bytes.len() / 2]).unwrap(), None);
+        assert!(decoder.in_progress());
+
+        let mut decoder = StreamDecoder::new();
+        assert!(decoder.feed(&[0xFF]).is_err());
+        assert!(!decoder.in_progress());
+    }
+
+    #[test]
+    fn test_decode_borrowed() {
+        let mut inner = IndexMap::new();
+        inner.insert(CompactString::from("city"), BomlValue::String(CompactString::from("Sapporo")));
+        let mut doc = IndexMap::new();
+        doc.insert(CompactString::from("name"), BomlValue::String(CompactString::from("x".repeat(40))));
+        doc.insert(CompactString::from("blob"), BomlValue::Binary(vec![1, 2, 3]));
+        doc.insert(CompactString::from("address"), BomlValue::Document(inner));
+        doc.insert(CompactString::from("tags"), BomlValue::Array(vec![BomlValue::Int32(3), BomlValue::Null]));
+        let value = BomlValue::Document(doc);
+
+        let encoded = encode_document(&value).unwrap();
+        let borrowed = decode_document_borrowed(&encoded).unwrap();
+        assert_eq!(borrowed.get_path("address.city").and_then(|v| v.as_str()), Some("Sapporo"));
+        assert_eq!(borrowed.get("blob").and_then(|v| v.as_bytes()), Some(&[1u8, 2, 3][..]));
+        assert_eq!(borrowed.get_path("tags.0").and_then(|v| v.as_i64()), Some(3));
+
+        let name = borrowed.get("name").and_then(|v| v.as_str()).unwrap();
+        assert!(encoded.as_ptr_range().contains(&name.as_ptr()));
+        assert_eq!(borrowed.to_boml_value(), value);
+    }
+
     #[test]
     fn test_document_with_checksum() {
         let mut doc = IndexMap::new();
@@ -659,4 +1251,54 @@ mod tests {
         let decoded = decode_document(&encoded).unwrap();
         assert_eq!(value, decoded);
     }
+
+    #[test]
+    fn test_document_compression() {
+        let mut doc = IndexMap::new();
+        doc.insert(CompactString::from("text"), BomlValue::String("miku ".repeat(400).into()));
+        let value = BomlValue::Document(doc);
+        let plain = encode_document(&value).unwrap();
+
+        for compression in [DocumentCompression::Lz4, DocumentCompression::Zstd] {
+            let encoded = encode_document_with(&value, compression).unwrap();
+            assert_eq!(encoded[4], BOML_VERSION_COMPRESSED);
+            assert_eq!(encoded[5], compression as u8);
+            assert!(encoded.len() < plain.len() / 4);
+            assert_eq!(decode_document(&encoded).unwrap(), value);
+            assert!(decode_document_borrowed(&encoded).is_err());
+            let payload = document_payload(&encoded).unwrap();
+            assert_eq!(decode_borrowed(&payload).unwrap().to_boml_value(), value);
+        }
+
+        // 小文档不压缩,与旧格式相同
+        let small = BomlValue::Document(IndexMap::from([(CompactString::from("a"), BomlValue::Int32(1))]));
+        assert_eq!(encode_document_with(&small, DocumentCompression::Zstd).unwrap(), encode_document(&small).unwrap());
+
+        let mut corrupted = encode_document_with(&value, DocumentCompression::Lz4).unwrap();
+        corrupted[5] = 9;
+        assert!(decode_document(&corrupted).is_err());
+    }
+
+    #[test]
+    fn test_decode_document_projected() {
+        let value = BomlValue::Document(IndexMap::from([
+            (CompactString::from("name"), BomlValue::String("Miku".into())),
+            (CompactString::from("tags"), BomlValue::Array(vec![BomlValue::Int32(3), BomlValue::String("x".repeat(40).into())])),
+            (CompactString::from("address"), BomlValue::Document(IndexMap::from([
+                (CompactString::from("city"), BomlValue::String("Sapporo".into())),
+            ]))),
+            (CompactString::from("age"), BomlValue::Int32(16)),
+            (CompactString::from("agent"), BomlValue::Boolean(true)),
+        ]));
+        let encoded = encode_document_with(&value, DocumentCompression::Lz4).unwrap();
+
+        let projected = decode_document_projected(&encoded, &["age", "address.city"]).unwrap();
+        let BomlValue::Document(fields) = projected else { panic!("expected document") };
+        assert_eq!(fields.keys().map(|k| k.as_str()).collect::<Vec<_>>(), ["address", "age"]);
+        assert_eq!(fields["age"], BomlValue::Int32(16));
+
+        let empty: [&str; 0] = [];
+        assert_eq!(decode_document_projected(&encoded, &empty).unwrap(), BomlValue::Document(IndexMap::new()));
+        assert!(decode_document_projected(&encoded[..encoded.len() - 1], &["age"]).is_err());
+    }
 }
@@ -1,9 +1,18 @@
 //! BOML 文档结构模块
 //!
 //! 提供高级 Document API，包装 BomlValue 并提供便捷的文档操作方法。
+//!
+//! - 深度合并: `Document::merge` 按 [`MergeStrategy`] 递归合并嵌套文档和数组
+//! - 文档差异: `Document::diff` 生成 [`BomlPatch`]，`BomlPatch::apply` 把旧版本变为新版本
+//! - 部分更新: [`BomlPatch`] 只记录改变的字段路径(set/unset/inc/push)，`encode` / `decode`
+//!   使用紧凑的二进制格式，`Document::apply_patch` 应用到文档上
+//! - 路径读写: `get_path` / `set_path` / `remove_path` 使用点分隔路径，数字段为数组下标
+//! - 展开与还原: `flatten` 把嵌套文档展开为 `点分隔路径 -> 叶子值`，`unflatten` 按路径重建嵌套结构
+//! - 没有 ObjectId 的文档可以把 `_id` 作为普通字段(例如分组结果的 `_id.<字段>`)，路径读写同样适用
 
+use crate::codec;
 use crate::value::BomlValue;
-use crate::BomlResult;
+use crate::{BomlError, BomlResult};
 use compact_str::CompactString;
 use indexmap::IndexMap;
 use mikudb_common::ObjectId;
@@ -106,7 +115,8 @@ impl Document {
     /// 插入字段
     ///
     /// # Brief
-    /// 向文档中插入或更新一个字段
+    /// 向文档中插入或更新一个字段。字段名按字面使用，不解析点分隔路径，
+    /// 写入嵌套字段使用 `set_path`
     ///
     /// # Arguments
     /// * `key` - 字段名
@@ -126,11 +136,9 @@ impl Document {
     /// # Returns
     /// `Some(&BomlValue)` 如果字段存在，否则 `None`
     pub fn get(&self, key: &str) -> Option<&BomlValue> {
-        if key == "_id" {
-            self.id.as_ref().map(|id| {
-                static NULL: BomlValue = BomlValue::Null;
-                &NULL
-            })
+        if key == "_id" && self.id.is_some() {
+            static NULL: BomlValue = BomlValue::Null;
+            Some(&NULL)
         } else {
             self.fields.get(key)
         }
@@ -248,7 +256,7 @@ impl Document {
         let mut parts = path.split('.');
         let first = parts.next()?;
 
-        let mut current = if first == "_id" {
+        let mut current = if first == "_id" && self.id.is_some() {
             return None;
         } else {
             self.fields.get(first)?
@@ -261,6 +269,91 @@ impl Document {
         Some(current)
     }
 
+    /// 按路径设置嵌套值
+    ///
+    /// # Brief
+    /// 与 `get_path` 使用相同的点分隔路径,数字段表示数组下标。
+    /// 缺失或为 Null 的中间字段创建为文档,数组下标超出长度时用 Null 补齐。
+    ///
+    /// # Arguments
+    /// * `path` - 点分隔的路径,如 "user.address.city" 或 "items.0.qty"
+    /// * `value` - 要写入的值
+    ///
+    /// # Returns
+    /// 路径经过标量值或在数组上使用非数字下标时返回 `BomlError::PathConflict`
+    pub fn set_path(&mut self, path: &str, value: impl Into<BomlValue>) -> BomlResult<()> {
+        self.set_path_with(path, value.into(), false)
+    }
+
+    /// `numeric_arrays` 为 true 时,下一段为数字的缺失中间字段创建为数组而不是文档
+    fn set_path_with(&mut self, path: &str, value: BomlValue, numeric_arrays: bool) -> BomlResult<()> {
+        let Some((first, rest)) = path.split_once('.') else {
+            self.insert(path, value);
+            return Ok(());
+        };
+        if first == "_id" && self.id.is_some() {
+            return Err(BomlError::PathConflict { path: path.to_string(), found: "objectId" });
+        }
+        let slot = self.fields.entry(first.into()).or_insert(BomlValue::Null);
+        set_in_value(slot, rest, value, path, numeric_arrays)
+    }
+
+    /// 按路径移除嵌套值
+    ///
+    /// # Brief
+    /// 路径规则同 `get_path`;数组元素置为 Null 以保持其他元素的下标
+    ///
+    /// # Arguments
+    /// * `path` - 点分隔的路径
+    ///
+    /// # Returns
+    /// 被移除的值,路径不存在时返回 `None`
+    pub fn remove_path(&mut self, path: &str) -> Option<BomlValue> {
+        match path.split_once('.') {
+            Some((first, rest)) => remove_in_value(self.fields.get_mut(first)?, rest),
+            None => self.remove(path),
+        }
+    }
+
+    /// 展开为叶子路径
+    ///
+    /// # Brief
+    /// 递归展开嵌套文档和数组，键为 `get_path` 可用的点分隔路径(数组元素用下标)。
+    /// 空文档和空数组作为叶子值保留，`_id` 为 ObjectId 时不包含在结果中。
+    ///
+    /// # Returns
+    /// 按字段顺序排列的 `路径 -> 叶子值`
+    pub fn flatten(&self) -> IndexMap<CompactString, BomlValue> {
+        let mut result = IndexMap::new();
+        for (key, value) in &self.fields {
+            flatten_into(key.to_string(), value, &mut result);
+        }
+        result
+    }
+
+    /// 从叶子路径还原文档
+    ///
+    /// # Brief
+    /// 按 `set_path` 的规则依次写入，是 `flatten` 的逆操作；
+    /// 与 `set_path` 不同，下一段为数字的缺失中间字段创建为数组
+    ///
+    /// # Arguments
+    /// * `paths` - `点分隔路径 -> 值`
+    ///
+    /// # Returns
+    /// 还原的文档，路径互相冲突(例如同时有 `a` 为标量和 `a.b`)时返回 `BomlError::PathConflict`
+    pub fn unflatten<K, I>(paths: I) -> BomlResult<Self>
+    where
+        K: AsRef<str>,
+        I: IntoIterator<Item = (K, BomlValue)>,
+    {
+        let mut doc = Self::without_id();
+        for (path, value) in paths {
+            doc.set_path_with(path.as_ref(), value, true)?;
+        }
+        Ok(doc)
+    }
+
     /// 转换为 BomlValue
     ///
     /// # Brief
@@ -289,12 +382,7 @@ impl Document {
     pub fn from_boml_value(value: BomlValue) -> BomlResult<Self> {
         match value {
             BomlValue::Document(mut fields) => {
-                let id = fields.shift_remove("_id").and_then(|v| {
-                    match v {
-                        BomlValue::ObjectId(id) => Some(id),
-                        _ => None,
-                    }
-                });
+                let id = take_object_id(&mut fields);
                 Ok(Self { id, fields })
             }
             _ => Err(crate::BomlError::InvalidDocument(
@@ -306,14 +394,53 @@ impl Document {
     /// 合并另一个文档
     ///
     /// # Brief
-    /// 将另一个文档的所有字段合并到当前文档中
+    /// 按合并策略将另一个文档的字段合并到当前文档中
+    ///
+    /// 当前文档的 `_id` 保持不变，当前文档没有 `_id` 时采用另一个文档的。
     ///
     /// # Arguments
     /// * `other` - 要合并的文档
-    pub fn merge(&mut self, other: Document) {
-        for (k, v) in other.fields {
-            self.fields.insert(k, v);
+    /// * `strategy` - 合并策略
+    pub fn merge(&mut self, other: &Document, strategy: MergeStrategy) {
+        if self.id.is_none() {
+            self.id = other.id;
         }
+        if strategy == MergeStrategy::Shallow {
+            for (k, v) in &other.fields {
+                self.fields.insert(k.clone(), v.clone());
+            }
+        } else {
+            merge_fields(&mut self.fields, &other.fields, strategy);
+        }
+    }
+
+    /// 应用补丁
+    ///
+    /// 与 `BomlPatch::apply` 相同，失败时文档可能已应用了前面的操作。
+    ///
+    /// # Arguments
+    /// * `patch` - 要应用的补丁
+    pub fn apply_patch(&mut self, patch: &BomlPatch) -> BomlResult<()> {
+        patch.apply(self)
+    }
+
+    /// 计算文档差异
+    ///
+    /// # Brief
+    /// 生成把当前文档变为另一个文档的补丁
+    ///
+    /// 嵌套文档逐字段比较；长度相同的数组逐元素比较，只在末尾追加元素的数组生成 `Push`，
+    /// 其他数组变化整体替换。`_id` 不参与比较。
+    ///
+    /// # Arguments
+    /// * `other` - 目标文档
+    ///
+    /// # Returns
+    /// 补丁，两个文档相同时为空
+    pub fn diff(&self, other: &Document) -> BomlPatch {
+        let mut patch = BomlPatch::default();
+        diff_fields(&mut Vec::new(), &self.fields, &other.fields, &mut patch.ops);
+        patch
     }
 
     /// 从 JSON 字符串创建文档
@@ -358,16 +485,477 @@ impl Document {
     }
 }
 
+/// 文档合并策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// 只合并顶层字段，同名字段整体覆盖
+    Shallow,
+    /// 递归合并嵌套文档，数组整体覆盖
+    #[default]
+    Deep,
+    /// 递归合并嵌套文档，数组追加对方的全部元素
+    DeepConcatArrays,
+    /// 递归合并嵌套文档，数组只追加当前没有的元素
+    DeepUnionArrays,
+    /// JSON Merge Patch (RFC 7386) 语义: 递归合并嵌套文档，值为 null 的字段被删除，数组整体覆盖
+    Patch,
+}
+
+fn merge_fields(
+    target: &mut IndexMap<CompactString, BomlValue>,
+    source: &IndexMap<CompactString, BomlValue>,
+    strategy: MergeStrategy,
+) {
+    for (key, value) in source {
+        if strategy == MergeStrategy::Patch && value.is_null() {
+            target.shift_remove(key);
+            continue;
+        }
+        match (target.get_mut(key), value) {
+            (Some(BomlValue::Document(existing)), BomlValue::Document(incoming)) => {
+                merge_fields(existing, incoming, strategy);
+            }
+            (Some(BomlValue::Array(existing)), BomlValue::Array(incoming))
+                if strategy == MergeStrategy::DeepConcatArrays =>
+            {
+                existing.extend(incoming.iter().cloned());
+            }
+            (Some(BomlValue::Array(existing)), BomlValue::Array(incoming))
+                if strategy == MergeStrategy::DeepUnionArrays =>
+            {
+                for item in incoming {
+                    if !existing.contains(item) {
+                        existing.push(item.clone());
+                    }
+                }
+            }
+            (_, BomlValue::Document(incoming)) if strategy == MergeStrategy::Patch => {
+                // 新增的嵌套文档中的 null 同样表示删除
+                let mut fields = IndexMap::new();
+                merge_fields(&mut fields, incoming, strategy);
+                target.insert(key.clone(), BomlValue::Document(fields));
+            }
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn diff_fields(
+    path: &mut Vec<CompactString>,
+    old: &IndexMap<CompactString, BomlValue>,
+    new: &IndexMap<CompactString, BomlValue>,
+    ops: &mut Vec<PatchOp>,
+) {
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            path.push(key.clone());
+            ops.push(PatchOp::Unset { path: path.clone() });
+            path.pop();
+        }
+    }
+    for (key, value) in new {
+        path.push(key.clone());
+        match old.get(key) {
+            Some(existing) => diff_values(path, existing, value, ops),
+            None => ops.push(PatchOp::Set {
+                path: path.clone(),
+                value: value.clone(),
+            }),
+        }
+        path.pop();
+    }
+}
+
+fn diff_values(path: &mut Vec<CompactString>, old: &BomlValue, new: &BomlValue, ops: &mut Vec<PatchOp>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (BomlValue::Document(old), BomlValue::Document(new)) => diff_fields(path, old, new, ops),
+        (BomlValue::Array(old), BomlValue::Array(new)) if old.len() == new.len() => {
+            for (i, (a, b)) in old.iter().zip(new).enumerate() {
+                path.push(CompactString::from(i.to_string()));
+                diff_values(path, a, b, ops);
+                path.pop();
+            }
+        }
+        (BomlValue::Array(old), BomlValue::Array(new))
+            if !old.is_empty() && new.len() > old.len() && new.starts_with(old) =>
+        {
+            ops.push(PatchOp::Push {
+                path: path.clone(),
+                values: new[old.len()..].to_vec(),
+            });
+        }
+        _ => ops.push(PatchOp::Set {
+            path: path.clone(),
+            value: new.clone(),
+        }),
+    }
+}
+
+/// 补丁操作
+///
+/// 路径由字段名逐级组成，数组元素用十进制下标表示。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PatchOp {
+    /// 设置字段或数组元素(新增或修改)
+    Set { path: Vec<CompactString>, value: BomlValue },
+    /// 删除字段
+    Unset { path: Vec<CompactString> },
+    /// 向数组末尾追加元素
+    Push { path: Vec<CompactString>, values: Vec<BomlValue> },
+    /// 数值字段加上 delta，字段不存在时设为 delta
+    Inc { path: Vec<CompactString>, delta: BomlValue },
+}
+
+impl PatchOp {
+    /// 操作的路径
+    pub fn path(&self) -> &[CompactString] {
+        match self {
+            PatchOp::Set { path, .. }
+            | PatchOp::Unset { path }
+            | PatchOp::Push { path, .. }
+            | PatchOp::Inc { path, .. } => path,
+        }
+    }
+
+    /// 点分隔的路径字符串，如 "address.city"、"tags.2"
+    pub fn path_string(&self) -> String {
+        self.path().join(".")
+    }
+
+    /// 二进制格式中的操作类型
+    fn tag(&self) -> i32 {
+        match self {
+            PatchOp::Set { .. } => PATCH_SET,
+            PatchOp::Unset { .. } => PATCH_UNSET,
+            PatchOp::Push { .. } => PATCH_PUSH,
+            PatchOp::Inc { .. } => PATCH_INC,
+        }
+    }
+}
+
+/// 补丁二进制格式的版本号
+const PATCH_FORMAT_VERSION: u8 = 1;
+
+const PATCH_SET: i32 = 0;
+const PATCH_UNSET: i32 = 1;
+const PATCH_PUSH: i32 = 2;
+const PATCH_INC: i32 = 3;
+
+/// 文档补丁
+///
+/// 由 `Document::diff` 生成或手工构造的有序操作列表，可以应用到旧版本文档上得到新版本。
+/// 更新只改动少数字段时，传输补丁比传输整个文档小得多。
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BomlPatch {
+    /// 按顺序执行的操作
+    pub ops: Vec<PatchOp>,
+}
+
+impl BomlPatch {
+    /// 补丁是否为空(两个文档相同)
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// 操作数量
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// 应用补丁
+    ///
+    /// # Brief
+    /// 按顺序执行所有操作，`Set` 会自动创建缺失的中间文档
+    ///
+    /// # Arguments
+    /// * `doc` - 要修改的文档
+    ///
+    /// # Returns
+    /// 路径穿过非容器值、数组下标越界或 `Push` 的目标不是数组时返回错误，
+    /// 此时文档可能已应用了前面的操作
+    pub fn apply(&self, doc: &mut Document) -> BomlResult<()> {
+        let mut root = BomlValue::Document(std::mem::take(&mut doc.fields));
+        let result = self.ops.iter().try_for_each(|op| apply_op(&mut root, op));
+        if let BomlValue::Document(fields) = root {
+            doc.fields = fields;
+        }
+        result
+    }
+
+    /// 编码为紧凑的二进制格式
+    ///
+    /// # Brief
+    /// 格式为 1 字节版本号加上一个 BOML 数组，每个操作编码为 `[类型, [路径段...], 值]`，
+    /// `Unset` 没有值，`Push` 的值为追加元素组成的数组
+    pub fn encode(&self) -> BomlResult<Vec<u8>> {
+        let ops = self
+            .ops
+            .iter()
+            .map(|op| {
+                let path = op.path().iter().map(|segment| BomlValue::String(segment.clone())).collect();
+                let mut entry = vec![BomlValue::Int32(op.tag()), BomlValue::Array(path)];
+                match op {
+                    PatchOp::Set { value, .. } => entry.push(value.clone()),
+                    PatchOp::Inc { delta, .. } => entry.push(delta.clone()),
+                    PatchOp::Push { values, .. } => entry.push(BomlValue::Array(values.clone())),
+                    PatchOp::Unset { .. } => {}
+                }
+                BomlValue::Array(entry)
+            })
+            .collect();
+
+        let mut out = vec![PATCH_FORMAT_VERSION];
+        out.extend(codec::encode_to_vec(&BomlValue::Array(ops))?);
+        Ok(out)
+    }
+
+    /// 从 `encode` 的输出解码补丁
+    ///
+    /// # Returns
+    /// 版本号未知或结构不符时返回错误
+    pub fn decode(data: &[u8]) -> BomlResult<Self> {
+        let invalid = |reason: &str| BomlError::InvalidDocument(format!("Invalid patch: {}", reason));
+        let (&version, body) = data.split_first().ok_or(BomlError::UnexpectedEof)?;
+        if version != PATCH_FORMAT_VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)));
+        }
+        let BomlValue::Array(entries) = codec::decode(body)? else {
+            return Err(invalid("expected an array of operations"));
+        };
+
+        let ops = entries
+            .into_iter()
+            .map(|entry| {
+                let BomlValue::Array(entry) = entry else {
+                    return Err(invalid("expected an operation array"));
+                };
+                let mut parts = entry.into_iter();
+                let (Some(BomlValue::Int32(tag)), Some(BomlValue::Array(segments))) = (parts.next(), parts.next()) else {
+                    return Err(invalid("expected operation type and path"));
+                };
+                let path = segments
+                    .into_iter()
+                    .map(|segment| match segment {
+                        BomlValue::String(segment) => Ok(segment),
+                        _ => Err(invalid("path segments must be strings")),
+                    })
+                    .collect::<BomlResult<Vec<_>>>()?;
+                let op = match (tag, parts.next()) {
+                    (PATCH_SET, Some(value)) => PatchOp::Set { path, value },
+                    (PATCH_UNSET, None) => PatchOp::Unset { path },
+                    (PATCH_PUSH, Some(BomlValue::Array(values))) => PatchOp::Push { path, values },
+                    (PATCH_INC, Some(delta)) => PatchOp::Inc { path, delta },
+                    _ => return Err(invalid(&format!("malformed operation of type {}", tag))),
+                };
+                if parts.next().is_some() {
+                    return Err(invalid("trailing operation fields"));
+                }
+                Ok(op)
+            })
+            .collect::<BomlResult<Vec<_>>>()?;
+        Ok(Self { ops })
+    }
+}
+
+/// 数值相加，规则与查询的 `+=` 相同，类型不能相加或整数溢出时返回 None
+///
+/// 浮点字段可以加整数增量；整数字段加浮点增量同样返回 None，不会把字段提升为 Float64。
+fn add_numeric(a: &BomlValue, b: &BomlValue) -> Option<BomlValue> {
+    match (a, b) {
+        (BomlValue::Int32(x), BomlValue::Int32(y)) => x.checked_add(*y).map(BomlValue::Int32),
+        (BomlValue::Int64(x), BomlValue::Int64(y)) => x.checked_add(*y).map(BomlValue::Int64),
+        (BomlValue::Int32(x), BomlValue::Int64(y)) => (*x as i64).checked_add(*y).map(BomlValue::Int64),
+        (BomlValue::Int64(x), BomlValue::Int32(y)) => x.checked_add(*y as i64).map(BomlValue::Int64),
+        (BomlValue::Float64(x), BomlValue::Float64(y)) => Some(BomlValue::Float64(x + y)),
+        (BomlValue::Float64(x), BomlValue::Int32(y)) => Some(BomlValue::Float64(x + *y as f64)),
+        (BomlValue::Float64(x), BomlValue::Int64(y)) => Some(BomlValue::Float64(x + *y as f64)),
+        _ => None,
+    }
+}
+
+fn invalid_path(op: &PatchOp) -> BomlError {
+    BomlError::InvalidDocument(format!("Invalid patch path: {}", op.path_string()))
+}
+
+/// 沿路径找到容器值，`create` 为 true 时自动创建缺失的中间文档
+fn walk_mut<'a>(
+    mut current: &'a mut BomlValue,
+    path: &[CompactString],
+    create: bool,
+) -> Option<&'a mut BomlValue> {
+    for segment in path {
+        current = match current {
+            BomlValue::Document(fields) => {
+                if create {
+                    fields
+                        .entry(segment.clone())
+                        .or_insert_with(|| BomlValue::Document(IndexMap::new()))
+                } else {
+                    fields.get_mut(segment)?
+                }
+            }
+            BomlValue::Array(items) => items.get_mut(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn apply_op(root: &mut BomlValue, op: &PatchOp) -> BomlResult<()> {
+    let (last, parent) = op.path().split_last().ok_or_else(|| invalid_path(op))?;
+    match op {
+        PatchOp::Set { value, .. } => match walk_mut(root, parent, true) {
+            Some(BomlValue::Document(fields)) => {
+                fields.insert(last.clone(), value.clone());
+            }
+            Some(BomlValue::Array(items)) => match last.parse::<usize>() {
+                Ok(i) if i < items.len() => items[i] = value.clone(),
+                Ok(i) if i == items.len() => items.push(value.clone()),
+                _ => return Err(invalid_path(op)),
+            },
+            _ => return Err(invalid_path(op)),
+        },
+        PatchOp::Unset { .. } => match walk_mut(root, parent, false) {
+            Some(BomlValue::Document(fields)) => {
+                fields.shift_remove(last);
+            }
+            // 父路径不存在时字段本来就不存在
+            None => {}
+            Some(_) => return Err(invalid_path(op)),
+        },
+        PatchOp::Push { values, .. } => match walk_mut(root, op.path(), false) {
+            Some(BomlValue::Array(items)) => items.extend(values.iter().cloned()),
+            _ => return Err(invalid_path(op)),
+        },
+        PatchOp::Inc { delta, .. } => {
+            if !matches!(delta, BomlValue::Int32(_) | BomlValue::Int64(_) | BomlValue::Float64(_)) {
+                return Err(BomlError::InvalidDocument(format!(
+                    "Cannot increment {} by a {} value",
+                    op.path_string(),
+                    delta.type_name()
+                )));
+            }
+            let slot = match walk_mut(root, parent, true) {
+                Some(BomlValue::Document(fields)) => fields.entry(last.clone()).or_insert(BomlValue::Null),
+                Some(BomlValue::Array(items)) => {
+                    last.parse::<usize>().ok().and_then(|i| items.get_mut(i)).ok_or_else(|| invalid_path(op))?
+                }
+                _ => return Err(invalid_path(op)),
+            };
+            let sum = match &*slot {
+                BomlValue::Null => Some(delta.clone()),
+                current => add_numeric(current, delta),
+            };
+            *slot = sum.ok_or_else(|| {
+                BomlError::InvalidDocument(format!("Cannot increment {}: not a number or overflow", op.path_string()))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn set_in_value(
+    target: &mut BomlValue,
+    path: &str,
+    value: BomlValue,
+    full_path: &str,
+    numeric_arrays: bool,
+) -> BomlResult<()> {
+    let (part, rest) = match path.split_once('.') {
+        Some((part, rest)) => (part, Some(rest)),
+        None => (path, None),
+    };
+    if matches!(target, BomlValue::Null) {
+        *target = if numeric_arrays && part.parse::<usize>().is_ok() {
+            BomlValue::Array(Vec::new())
+        } else {
+            BomlValue::Document(IndexMap::new())
+        };
+    }
+    let slot = match target {
+        BomlValue::Document(fields) => fields.entry(part.into()).or_insert(BomlValue::Null),
+        BomlValue::Array(items) => {
+            let index = part.parse::<usize>().map_err(|_| BomlError::PathConflict {
+                path: full_path.to_string(),
+                found: "array",
+            })?;
+            if index >= items.len() {
+                items.resize(index + 1, BomlValue::Null);
+            }
+            &mut items[index]
+        }
+        other => {
+            return Err(BomlError::PathConflict { path: full_path.to_string(), found: other.type_name() })
+        }
+    };
+    match rest {
+        Some(rest) => set_in_value(slot, rest, value, full_path, numeric_arrays),
+        None => {
+            *slot = value;
+            Ok(())
+        }
+    }
+}
+
+fn remove_in_value(target: &mut BomlValue, path: &str) -> Option<BomlValue> {
+    let (part, rest) = match path.split_once('.') {
+        Some((part, rest)) => (part, Some(rest)),
+        None => (path, None),
+    };
+    match (target, rest) {
+        (BomlValue::Document(fields), None) => fields.shift_remove(part),
+        (BomlValue::Array(items), None) => {
+            let slot = items.get_mut(part.parse::<usize>().ok()?)?;
+            Some(std::mem::replace(slot, BomlValue::Null))
+        }
+        (BomlValue::Document(fields), Some(rest)) => remove_in_value(fields.get_mut(part)?, rest),
+        (BomlValue::Array(items), Some(rest)) => remove_in_value(items.get_mut(part.parse::<usize>().ok()?)?, rest),
+        _ => None,
+    }
+}
+
 impl From<IndexMap<CompactString, BomlValue>> for Document {
     fn from(mut fields: IndexMap<CompactString, BomlValue>) -> Self {
-        let id = fields.shift_remove("_id").and_then(|v| match v {
-            BomlValue::ObjectId(id) => Some(id),
-            _ => None,
-        });
+        let id = take_object_id(&mut fields);
         Self { id, fields }
     }
 }
 
+/// 取出 ObjectId 类型的 `_id`，其他类型的 `_id` 作为普通字段保留
+fn take_object_id(fields: &mut IndexMap<CompactString, BomlValue>) -> Option<ObjectId> {
+    match fields.get("_id") {
+        Some(BomlValue::ObjectId(id)) => {
+            let id = *id;
+            fields.shift_remove("_id");
+            Some(id)
+        }
+        _ => None,
+    }
+}
+
+fn flatten_into(prefix: String, value: &BomlValue, result: &mut IndexMap<CompactString, BomlValue>) {
+    match value {
+        BomlValue::Document(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                flatten_into(format!("{}.{}", prefix, key), value, result);
+            }
+        }
+        BomlValue::Array(items) if !items.is_empty() => {
+            for (i, value) in items.iter().enumerate() {
+                flatten_into(format!("{}.{}", prefix, i), value, result);
+            }
+        }
+        _ => {
+            result.insert(prefix.into(), value.clone());
+        }
+    }
+}
+
 impl From<Document> for BomlValue {
     fn from(doc: Document) -> Self {
         doc.to_boml_value()
@@ -402,3 +990,191 @@ macro_rules! doc {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Document {
+        Document::from_json(
+            r#"{"name": "miku", "profile": {"city": "Sapporo", "age": 16}, "tags": ["vocaloid", "teal"], "old": 1}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let patch = Document::from_json(
+            r#"{"profile": {"age": 17, "nick": null}, "tags": ["teal", "leek"], "old": null}"#,
+        )
+        .unwrap();
+
+        let mut shallow = sample();
+        shallow.merge(&patch, MergeStrategy::Shallow);
+        assert!(shallow.get_path("profile.city").is_none());
+
+        let mut deep = sample();
+        deep.merge(&patch, MergeStrategy::Deep);
+        assert_eq!(deep.get_path("profile.city").and_then(|v| v.as_str()), Some("Sapporo"));
+        assert_eq!(deep.get_path("profile.age").and_then(|v| v.as_i64()), Some(17));
+        assert_eq!(deep.get_array("tags").unwrap().len(), 2);
+        assert!(deep.get("old").unwrap().is_null());
+
+        let mut union = sample();
+        union.merge(&patch, MergeStrategy::DeepUnionArrays);
+        assert_eq!(union.get_array("tags").unwrap().len(), 3);
+
+        let mut concat = sample();
+        concat.merge(&patch, MergeStrategy::DeepConcatArrays);
+        assert_eq!(concat.get_array("tags").unwrap().len(), 4);
+
+        let mut merge_patch = sample();
+        merge_patch.merge(&patch, MergeStrategy::Patch);
+        assert!(!merge_patch.contains_key("old"));
+        assert!(merge_patch.get_path("profile.nick").is_none());
+        assert_eq!(merge_patch.get_path("profile.city").and_then(|v| v.as_str()), Some("Sapporo"));
+    }
+
+    #[test]
+    fn test_diff_apply() {
+        let old = sample();
+        let new = Document::from_json(
+            r#"{"name": "miku", "profile": {"city": "Tokyo", "age": 16, "height": 158}, "tags": ["vocaloid", "teal", "leek"], "songs": [{"title": "a"}]}"#,
+        )
+        .unwrap();
+
+        assert!(old.diff(&old).is_empty());
+
+        let patch = old.diff(&new);
+        let paths: Vec<String> = patch.ops.iter().map(PatchOp::path_string).collect();
+        assert!(paths.contains(&"old".to_string()));
+        assert!(paths.contains(&"profile.city".to_string()));
+        assert!(patch
+            .ops
+            .iter()
+            .any(|op| matches!(op, PatchOp::Push { values, .. } if values.len() == 1)));
+
+        let mut patched = old.clone();
+        patch.apply(&mut patched).unwrap();
+        assert!(patched.diff(&new).is_empty());
+        assert_eq!(patched.get_path("profile.height").and_then(|v| v.as_i64()), Some(158));
+        assert_eq!(patched.id(), old.id());
+
+        let mut changed = new.clone();
+        if let Some(BomlValue::Array(songs)) = changed.get_mut("songs") {
+            songs[0] = BomlValue::Document(IndexMap::from([("title".into(), BomlValue::from("b"))]));
+        }
+        let patch = new.diff(&changed);
+        assert_eq!(patch.ops[0].path_string(), "songs.0.title");
+        let mut target = new.clone();
+        patch.apply(&mut target).unwrap();
+        assert_eq!(target, changed);
+
+        let bad = BomlPatch {
+            ops: vec![PatchOp::Push { path: vec!["name".into()], values: vec![] }],
+        };
+        assert!(bad.apply(&mut target).is_err());
+    }
+
+    #[test]
+    fn test_patch_encode_inc() {
+        let patch = BomlPatch {
+            ops: vec![
+                PatchOp::Set { path: vec!["profile".into(), "city".into()], value: BomlValue::from("Tokyo") },
+                PatchOp::Unset { path: vec!["old".into()] },
+                PatchOp::Inc { path: vec!["profile".into(), "age".into()], delta: BomlValue::Int32(1) },
+                PatchOp::Inc { path: vec!["plays".into()], delta: BomlValue::Int32(3) },
+                PatchOp::Push { path: vec!["tags".into()], values: vec![BomlValue::from("leek")] },
+            ],
+        };
+        let encoded = patch.encode().unwrap();
+        assert_eq!(BomlPatch::decode(&encoded).unwrap(), patch);
+        assert!(BomlPatch::decode(&encoded[1..]).is_err());
+
+        let mut doc = sample();
+        doc.apply_patch(&patch).unwrap();
+        assert_eq!(doc.get_path("profile.city").and_then(|v| v.as_str()), Some("Tokyo"));
+        assert_eq!(doc.get_path("profile.age").and_then(|v| v.as_i64()), Some(17));
+        assert_eq!(doc.get_path("plays"), Some(&BomlValue::Int32(3)));
+        assert!(doc.get("old").is_none());
+
+        let overflow = BomlPatch {
+            ops: vec![PatchOp::Inc { path: vec!["plays".into()], delta: BomlValue::Int32(i32::MAX) }],
+        };
+        assert!(doc.apply_patch(&overflow).is_err());
+        let not_number = BomlPatch {
+            ops: vec![PatchOp::Inc { path: vec!["name".into()], delta: BomlValue::Int32(1) }],
+        };
+        assert!(doc.apply_patch(&not_number).is_err());
+    }
+
+    #[test]
+    fn test_patch_inc_mixed_numbers() {
+        let inc = |field: &str, delta: BomlValue| BomlPatch {
+            ops: vec![PatchOp::Inc { path: vec![field.into()], delta }],
+        };
+        let mut doc = Document::new();
+        doc.insert("count", 1i32);
+        doc.insert("ratio", 0.5f64);
+
+        doc.apply_patch(&inc("ratio", BomlValue::Int32(1))).unwrap();
+        assert_eq!(doc.get("ratio"), Some(&BomlValue::Float64(1.5)));
+
+        // 整数字段加浮点增量被拒绝，字段保持原值
+        assert!(doc.apply_patch(&inc("count", BomlValue::Float64(0.5))).is_err());
+        assert_eq!(doc.get("count"), Some(&BomlValue::Int32(1)));
+    }
+
+    #[test]
+    fn test_path_set_remove() {
+        let mut doc = sample();
+        doc.set_path("profile.city", "Tokyo").unwrap();
+        doc.set_path("profile.address.zip", "060-0001").unwrap();
+        doc.set_path("tags.3", "leek").unwrap();
+        assert_eq!(doc.get_path("profile.city"), Some(&BomlValue::from("Tokyo")));
+        assert_eq!(doc.get_path("profile.address.zip"), Some(&BomlValue::from("060-0001")));
+        assert_eq!(doc.get_path("tags.2"), Some(&BomlValue::Null));
+        assert_eq!(doc.get_path("tags.3"), Some(&BomlValue::from("leek")));
+        assert!(!doc.contains_key("profile.city"));
+
+        assert!(matches!(doc.set_path("old.x", 1), Err(BomlError::PathConflict { found: "int32", .. })));
+        assert!(doc.set_path("tags.first", 1).is_err());
+
+        assert_eq!(doc.remove_path("tags.0"), Some(BomlValue::from("vocaloid")));
+        assert_eq!(doc.get_path("tags.1"), Some(&BomlValue::from("teal")));
+        assert_eq!(doc.remove_path("profile.age"), Some(BomlValue::Int32(16)));
+        assert_eq!(doc.remove_path("profile.missing.x"), None);
+        assert!(doc.get_path("profile.age").is_none());
+    }
+
+    #[test]
+    fn test_flatten_unflatten() {
+        let doc = Document::from_json(
+            r#"{"name": "miku", "profile": {"city": "Sapporo", "links": {}}, "items": [{"sku": "a"}, 2], "tags": []}"#,
+        )
+        .unwrap();
+        let flat = doc.flatten();
+        let paths: Vec<&str> = flat.keys().map(CompactString::as_str).collect();
+        assert_eq!(paths, vec!["name", "profile.city", "profile.links", "items.0.sku", "items.1", "tags"]);
+        assert_eq!(flat.get("items.0.sku"), Some(&BomlValue::from("a")));
+
+        let restored = Document::unflatten(flat).unwrap();
+        assert_eq!(restored.to_boml_value(), doc.to_boml_value());
+        assert!(Document::unflatten([("a", BomlValue::Int32(1)), ("a.b", BomlValue::Int32(2))]).is_err());
+    }
+
+    #[test]
+    fn test_non_object_id_field() {
+        let mut group = Document::without_id();
+        group.set_path("_id.address.city", "Sapporo").unwrap();
+        assert_eq!(group.get_path("_id.address.city"), Some(&BomlValue::from("Sapporo")));
+
+        let restored = Document::from_boml_value(group.to_boml_value()).unwrap();
+        assert!(restored.id().is_none());
+        assert_eq!(restored.get_path("_id.address.city"), Some(&BomlValue::from("Sapporo")));
+
+        let mut doc = Document::new();
+        assert!(doc.set_path("_id.x", 1).is_err());
+        assert!(doc.get_path("_id.x").is_none());
+    }
+}
@@ -2,16 +2,26 @@
 //!
 //! 提供 BOML 格式与 JSON 格式之间的相互转换功能。
 //! 由于 JSON 类型系统较简单，某些 BOML 类型会转换为扩展 JSON 格式。
+//!
+//! `to_json`/`from_json` 使用宽松格式,数值类型在往返后可能改变(如 Int64 变为 Int32)。
+//! 导出和重新导入数据应使用规范扩展 JSON(`to_extended_json`/`from_extended_json`):
+//! 每个数值都带类型包装,所有 BOML 类型都能无损往返。
 
 use crate::value::{BomlValue, JavaScriptValue, RegexValue};
 use crate::{BomlError, BomlResult};
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
-use chrono::TimeZone;
+use chrono::{DateTime, TimeZone, Utc};
 use compact_str::CompactString;
 use indexmap::IndexMap;
 use mikudb_common::ObjectId;
 use serde_json::{json, Map, Number, Value as JsonValue};
+use uuid::Uuid;
+
+/// 普通二进制数据的 `$binary` 子类型
+const BINARY_SUBTYPE_GENERIC: &str = "00";
+/// UUID 的 `$binary` 子类型
+const BINARY_SUBTYPE_UUID: &str = "04";
 
 /// 将 BomlValue 转换为 JSON
 ///
@@ -282,6 +292,292 @@ pub fn from_json_string(json_str: &str) -> BomlResult<BomlValue> {
     from_json(&json_value)
 }
 
+/// 将 BomlValue 转换为规范扩展 JSON
+///
+/// # Brief
+/// 按 Canonical Extended JSON v2 输出,BSON 没有的类型使用 MikuDB 扩展包装
+///
+/// # 格式
+/// - Int32 / Int64: `{"$numberInt": "1"}` / `{"$numberLong": "1"}`
+/// - Float64: `{"$numberDouble": "1.5"}`,支持 `Infinity`、`-Infinity`、`NaN`
+/// - Decimal: `{"$numberDecimal": "1.10"}`
+/// - Binary / Uuid: `{"$binary": {"base64": "...", "subType": "00"}}`,UUID 的子类型为 `04`
+/// - ObjectId: `{"$oid": "..."}`
+/// - DateTime: `{"$date": {"$numberLong": "<毫秒>"}}`
+/// - Regex: `{"$regularExpression": {"pattern": "...", "options": "..."}}`
+/// - JavaScript: `{"$code": "...", "$scope": {...}}`
+/// - 扩展: Int128 `{"$numberInt128": "..."}`、Float32 `{"$numberFloat": "..."}`、
+///   Timestamp `{"$timestamp": {"$numberLong": "<毫秒>"}}`
+///
+/// # Arguments
+/// * `value` - 要转换的 BOML 值
+pub fn to_extended_json(value: &BomlValue) -> JsonValue {
+    match value {
+        BomlValue::Null => JsonValue::Null,
+        BomlValue::Boolean(b) => JsonValue::Bool(*b),
+        BomlValue::Int32(n) => json!({"$numberInt": n.to_string()}),
+        BomlValue::Int64(n) => json!({"$numberLong": n.to_string()}),
+        BomlValue::Int128(n) => json!({"$numberInt128": n.to_string()}),
+        BomlValue::Float32(f) => json!({"$numberFloat": float_text(*f as f64, f)}),
+        BomlValue::Float64(f) => json!({"$numberDouble": float_text(*f, f)}),
+        BomlValue::Decimal(d) => json!({"$numberDecimal": d.to_string()}),
+        BomlValue::String(s) => JsonValue::String(s.to_string()),
+        BomlValue::Binary(b) => binary_json(b, BINARY_SUBTYPE_GENERIC),
+        BomlValue::Uuid(uuid) => binary_json(uuid.as_bytes(), BINARY_SUBTYPE_UUID),
+        BomlValue::ObjectId(oid) => json!({"$oid": oid.to_string()}),
+        BomlValue::DateTime(dt) => json!({"$date": {"$numberLong": dt.timestamp_millis().to_string()}}),
+        BomlValue::Timestamp(ts) => json!({"$timestamp": {"$numberLong": ts.to_string()}}),
+        BomlValue::Array(arr) => JsonValue::Array(arr.iter().map(to_extended_json).collect()),
+        BomlValue::Document(doc) => JsonValue::Object(extended_json_object(doc)),
+        BomlValue::Regex(r) => json!({
+            "$regularExpression": {"pattern": r.pattern.as_str(), "options": r.options.as_str()}
+        }),
+        BomlValue::JavaScript(js) => match &js.scope {
+            Some(scope) => json!({
+                "$code": js.code.as_str(),
+                "$scope": JsonValue::Object(extended_json_object(scope))
+            }),
+            None => json!({"$code": js.code.as_str()}),
+        },
+    }
+}
+
+/// 从扩展 JSON 转换为 BomlValue
+///
+/// # Brief
+/// 识别 `to_extended_json` 输出的规范格式,同时兼容宽松格式:
+/// 裸数字按 `from_json` 的规则转换,`$date` 可以是毫秒数或 RFC 3339 字符串,
+/// 以及 `to_json` 输出的 `$binary` 字符串、`$regex`/`$options`、`$uuid` 等旧格式。
+///
+/// 类型包装格式错误时返回错误,不会当作普通文档静默导入。
+///
+/// # Arguments
+/// * `value` - JSON 值
+///
+/// # Returns
+/// 成功返回 BOML 值，失败返回错误
+pub fn from_extended_json(value: &JsonValue) -> BomlResult<BomlValue> {
+    match value {
+        JsonValue::Array(arr) => Ok(BomlValue::Array(
+            arr.iter().map(from_extended_json).collect::<BomlResult<_>>()?,
+        )),
+        JsonValue::Object(obj) => {
+            if let Some(value) = extended_json_wrapper(obj)? {
+                return Ok(value);
+            }
+            Ok(BomlValue::Document(extended_json_fields(obj)?))
+        }
+        other => from_json(other),
+    }
+}
+
+/// 将 BOML 值序列化为规范扩展 JSON 字符串
+///
+/// # Arguments
+/// * `value` - 要序列化的 BOML 值
+pub fn to_extended_json_string(value: &BomlValue) -> String {
+    to_extended_json(value).to_string()
+}
+
+/// 从扩展 JSON 字符串反序列化为 BOML 值
+///
+/// # Arguments
+/// * `json_str` - 扩展 JSON 字符串
+///
+/// # Returns
+/// 成功返回 BOML 值，失败返回错误
+pub fn from_extended_json_string(json_str: &str) -> BomlResult<BomlValue> {
+    let json_value: JsonValue = serde_json::from_str(json_str).map_err(|e| {
+        BomlError::Deserialization(format!("JSON parsing failed: {}", e))
+    })?;
+    from_extended_json(&json_value)
+}
+
+/// # Brief
+/// 判断表/映射的键是否构成扩展 JSON 类型包装(非空且全部以 `$` 开头)
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub(crate) fn is_extended_json_wrapper<'a>(mut keys: impl Iterator<Item = &'a str>) -> bool {
+    let mut any = false;
+    keys.all(|k| {
+        any = true;
+        k.starts_with('$')
+    }) && any
+}
+
+fn extended_json_object(doc: &IndexMap<CompactString, BomlValue>) -> Map<String, JsonValue> {
+    doc.iter()
+        .map(|(k, v)| (k.to_string(), to_extended_json(v)))
+        .collect()
+}
+
+fn extended_json_fields(obj: &Map<String, JsonValue>) -> BomlResult<IndexMap<CompactString, BomlValue>> {
+    obj.iter()
+        .map(|(k, v)| Ok((CompactString::new(k), from_extended_json(v)?)))
+        .collect()
+}
+
+fn binary_json(bytes: &[u8], subtype: &str) -> JsonValue {
+    json!({"$binary": {"base64": STANDARD.encode(bytes), "subType": subtype}})
+}
+
+/// # Brief
+/// 浮点数的扩展 JSON 文本,非有限值使用规范名称
+///
+/// # Arguments
+/// * `f` - 用于判断是否有限的值
+/// * `display` - 有限值的原始类型,保证 Float32 输出最短的往返表示
+fn float_text(f: f64, display: &impl std::fmt::Display) -> String {
+    if f.is_nan() {
+        "NaN".to_string()
+    } else if f.is_infinite() {
+        if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        display.to_string()
+    }
+}
+
+fn parse_float<T: std::str::FromStr>(s: &str, wrapper: &str) -> BomlResult<T> {
+    let normalized = match s {
+        "Infinity" => "inf",
+        "-Infinity" => "-inf",
+        other => other,
+    };
+    normalized
+        .parse()
+        .map_err(|_| invalid_wrapper(wrapper))
+}
+
+fn invalid_wrapper(wrapper: &str) -> BomlError {
+    BomlError::Deserialization(format!("Invalid {}", wrapper))
+}
+
+/// # Brief
+/// 读取包装值中的字符串,如 `{"$numberInt": "1"}` 中的 "1"
+fn wrapper_str<'a>(value: &'a JsonValue, wrapper: &str) -> BomlResult<&'a str> {
+    value.as_str().ok_or_else(|| invalid_wrapper(wrapper))
+}
+
+/// # Brief
+/// 读取毫秒数: `{"$numberLong": "..."}`、裸整数或数字字符串
+fn wrapper_millis(value: &JsonValue, wrapper: &str) -> BomlResult<i64> {
+    match value {
+        JsonValue::Number(n) => n.as_i64().ok_or_else(|| invalid_wrapper(wrapper)),
+        JsonValue::Object(obj) if obj.len() == 1 => wrapper_millis(
+            obj.get("$numberLong").ok_or_else(|| invalid_wrapper(wrapper))?,
+            wrapper,
+        ),
+        JsonValue::String(s) => s.parse().map_err(|_| invalid_wrapper(wrapper)),
+        _ => Err(invalid_wrapper(wrapper)),
+    }
+}
+
+fn parse_date(value: &JsonValue) -> BomlResult<DateTime<Utc>> {
+    if let JsonValue::String(s) = value {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Ok(dt.with_timezone(&Utc));
+        }
+    }
+    let millis = wrapper_millis(value, "$date")?;
+    Utc.timestamp_millis_opt(millis)
+        .single()
+        .ok_or_else(|| invalid_wrapper("$date"))
+}
+
+fn parse_binary(value: &JsonValue) -> BomlResult<BomlValue> {
+    let (encoded, subtype) = match value {
+        // to_json 输出的旧格式
+        JsonValue::String(s) => (s.as_str(), BINARY_SUBTYPE_GENERIC),
+        JsonValue::Object(obj) => (
+            obj.get("base64").and_then(JsonValue::as_str).ok_or_else(|| invalid_wrapper("$binary"))?,
+            obj.get("subType").and_then(JsonValue::as_str).unwrap_or(BINARY_SUBTYPE_GENERIC),
+        ),
+        _ => return Err(invalid_wrapper("$binary")),
+    };
+    let bytes = STANDARD.decode(encoded).map_err(|_| invalid_wrapper("$binary"))?;
+    if subtype == BINARY_SUBTYPE_UUID {
+        let uuid = Uuid::from_slice(&bytes).map_err(|_| invalid_wrapper("$binary"))?;
+        Ok(BomlValue::Uuid(uuid))
+    } else {
+        Ok(BomlValue::Binary(bytes))
+    }
+}
+
+/// # Brief
+/// 解析扩展 JSON 类型包装
+///
+/// # Returns
+/// 对象不是已知的类型包装时返回 None,按普通文档处理
+fn extended_json_wrapper(obj: &Map<String, JsonValue>) -> BomlResult<Option<BomlValue>> {
+    // 带两个键的包装: $regex + $options(旧格式)、$code + $scope
+    if obj.len() == 2 {
+        if let (Some(pattern), Some(options)) = (obj.get("$regex"), obj.get("$options")) {
+            return Ok(Some(BomlValue::Regex(RegexValue {
+                pattern: CompactString::new(wrapper_str(pattern, "$regex")?),
+                options: CompactString::new(wrapper_str(options, "$options")?),
+            })));
+        }
+        if let (Some(code), Some(scope)) = (obj.get("$code"), obj.get("$scope")) {
+            let scope = match scope {
+                JsonValue::Object(scope) => extended_json_fields(scope)?,
+                _ => return Err(invalid_wrapper("$scope")),
+            };
+            return Ok(Some(BomlValue::JavaScript(JavaScriptValue {
+                code: CompactString::new(wrapper_str(code, "$code")?),
+                scope: Some(scope),
+            })));
+        }
+        return Ok(None);
+    }
+
+    let Some((key, value)) = obj.iter().next().filter(|_| obj.len() == 1) else {
+        return Ok(None);
+    };
+    let parsed = match key.as_str() {
+        "$oid" => BomlValue::ObjectId(
+            ObjectId::from_hex(wrapper_str(value, key)?).map_err(|_| BomlError::InvalidObjectId)?,
+        ),
+        "$numberInt" => BomlValue::Int32(wrapper_str(value, key)?.parse().map_err(|_| invalid_wrapper(key))?),
+        "$numberLong" => {
+            // to_json 把 Int128 也写成 $numberLong,超出 i64 范围时还原为 Int128
+            let s = wrapper_str(value, key)?;
+            match s.parse::<i64>() {
+                Ok(n) => BomlValue::Int64(n),
+                Err(_) => BomlValue::Int128(s.parse().map_err(|_| invalid_wrapper(key))?),
+            }
+        }
+        "$numberInt128" => BomlValue::Int128(wrapper_str(value, key)?.parse().map_err(|_| invalid_wrapper(key))?),
+        "$numberDouble" => BomlValue::Float64(parse_float(wrapper_str(value, key)?, key)?),
+        "$numberFloat" => BomlValue::Float32(parse_float(wrapper_str(value, key)?, key)?),
+        "$numberDecimal" => {
+            BomlValue::Decimal(wrapper_str(value, key)?.parse().map_err(|_| invalid_wrapper(key))?)
+        }
+        "$binary" => parse_binary(value)?,
+        "$uuid" => BomlValue::Uuid(wrapper_str(value, key)?.parse().map_err(|_| invalid_wrapper(key))?),
+        "$date" => BomlValue::DateTime(parse_date(value)?),
+        "$timestamp" => BomlValue::Timestamp(wrapper_millis(value, key)?),
+        "$regularExpression" => {
+            let JsonValue::Object(regex) = value else {
+                return Err(invalid_wrapper(key));
+            };
+            let field = |name: &str| regex.get(name).and_then(JsonValue::as_str).ok_or_else(|| invalid_wrapper(key));
+            BomlValue::Regex(RegexValue {
+                pattern: CompactString::new(field("pattern")?),
+                options: CompactString::new(field("options")?),
+            })
+        }
+        "$regex" => BomlValue::Regex(RegexValue {
+            pattern: CompactString::new(wrapper_str(value, key)?),
+            options: CompactString::default(),
+        }),
+        "$code" => BomlValue::JavaScript(JavaScriptValue {
+            code: CompactString::new(wrapper_str(value, key)?),
+            scope: None,
+        }),
+        _ => return Ok(None),
+    };
+    Ok(Some(parsed))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -340,4 +636,50 @@ mod tests {
 
         assert_eq!(original, restored);
     }
+
+    #[test]
+    fn test_extended_json_round_trip() {
+        let mut doc = Document::new();
+        doc.insert("int32", BomlValue::Int32(7));
+        doc.insert("int64", BomlValue::Int64(7));
+        doc.insert("int128", BomlValue::Int128(i128::MAX));
+        doc.insert("float32", BomlValue::Float32(1.5));
+        doc.insert("float64", BomlValue::Float64(2.0));
+        doc.insert("infinity", BomlValue::Float64(f64::NEG_INFINITY));
+        doc.insert("decimal", BomlValue::Decimal("12.340".parse().unwrap()));
+        doc.insert("binary", BomlValue::Binary(vec![0, 1, 2, 255]));
+        doc.insert("uuid", BomlValue::Uuid(Uuid::new_v4()));
+        doc.insert("date", BomlValue::DateTime(Utc.timestamp_millis_opt(1_700_000_000_123).unwrap()));
+        doc.insert("timestamp", BomlValue::Timestamp(42));
+        doc.insert("regex", BomlValue::Regex(RegexValue {
+            pattern: CompactString::new("^a.*"),
+            options: CompactString::new("i"),
+        }));
+        doc.insert("tags", BomlValue::Array(vec![BomlValue::Int64(1), BomlValue::Null]));
+        let original = doc.to_boml_value();
+
+        let text = original.to_extended_json_string();
+        assert!(text.contains(r#""int32":{"$numberInt":"7"}"#));
+        assert!(text.contains(r#""subType":"04""#));
+        assert_eq!(BomlValue::from_extended_json(&text).unwrap(), original);
+    }
+
+    #[test]
+    fn test_extended_json_legacy_input() {
+        let value = from_extended_json_string(
+            r#"{"n": 5, "d": {"$date": "2024-01-01T00:00:00Z"}, "b": {"$binary": "AAE="},
+                "r": {"$regex": "x", "$options": "m"}, "big": {"$numberLong": "170141183460469231731687303715884105727"},
+                "plain": {"$unknown": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(value.get("n"), Some(&BomlValue::Int32(5)));
+        assert!(matches!(value.get("d"), Some(BomlValue::DateTime(_))));
+        assert_eq!(value.get("b"), Some(&BomlValue::Binary(vec![0, 1])));
+        assert!(matches!(value.get("r"), Some(BomlValue::Regex(r)) if r.options == "m"));
+        assert_eq!(value.get("big"), Some(&BomlValue::Int128(i128::MAX)));
+        assert!(matches!(value.get("plain"), Some(BomlValue::Document(_))));
+
+        assert!(from_extended_json_string(r#"{"$numberInt": "abc"}"#).is_err());
+        assert!(from_extended_json_string(r#"{"$oid": 12}"#).is_err());
+    }
 }
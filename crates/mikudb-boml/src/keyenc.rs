@@ -0,0 +1,303 @@
+//! 保序索引键编码
+//!
+//! 把 BomlValue 编码为可按字节比较的键(memcomparable),
+//! 编码后的字节序与值的逻辑顺序一致,可以直接作为 RocksDB 等有序存储的键:
+//!
+//! - 不同类型按固定顺序排列: Null < 数值 < 字符串 < 文档 < 数组 < 二进制 < ObjectId < UUID
+//!   < 布尔 < 日期 < 时间戳 < 正则 < JavaScript
+//! - 所有数值类型共用一种编码,`Int32(5)`、`Int64(5)` 和 `Float64(5.0)` 得到相同的键,
+//!   负数和浮点数同样保序
+//! - 字符串和二进制转义 0x00 并以 `0x00 0x00` 结尾,短串排在以它为前缀的长串之前
+//! - 每个值的编码自定界,复合键直接拼接,不需要分隔符
+//! - `encode_descending` 对编码按位取反,用于降序索引字段
+//!
+//! 编码是单向的,只用于比较和查找,不能解码回原值。
+
+use crate::BomlValue;
+use rust_decimal::prelude::ToPrimitive;
+use std::cmp::Ordering;
+
+const TAG_NULL: u8 = 0x05;
+const TAG_NUMBER: u8 = 0x10;
+const TAG_STRING: u8 = 0x20;
+const TAG_DOCUMENT: u8 = 0x30;
+const TAG_ARRAY: u8 = 0x38;
+const TAG_BINARY: u8 = 0x40;
+const TAG_OBJECT_ID: u8 = 0x48;
+const TAG_UUID: u8 = 0x4C;
+const TAG_BOOLEAN: u8 = 0x50;
+const TAG_DATETIME: u8 = 0x58;
+const TAG_TIMESTAMP: u8 = 0x5C;
+const TAG_REGEX: u8 = 0x60;
+const TAG_JAVASCRIPT: u8 = 0x68;
+
+/// 数组和文档的结束标记,小于所有类型标记
+const END: u8 = 0x00;
+/// 文档字段的起始标记
+const FIELD: u8 = 0x01;
+
+/// # Brief
+/// 编码复合键
+///
+/// # Arguments
+/// * `values` - 键的各个字段值,按顺序拼接
+///
+/// # Returns
+/// 可按字节比较的键
+pub fn encode_key(values: &[BomlValue]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for value in values {
+        encode_value(value, &mut out);
+    }
+    out
+}
+
+/// # Brief
+/// 把单个值的升序编码追加到 `out`
+///
+/// # Arguments
+/// * `value` - 要编码的值
+/// * `out` - 输出缓冲区
+pub fn encode_value(value: &BomlValue, out: &mut Vec<u8>) {
+    match value {
+        BomlValue::Null => out.push(TAG_NULL),
+        BomlValue::Int32(n) => encode_number(*n as f64, *n as i128, out),
+        BomlValue::Int64(n) => encode_number(*n as f64, *n as i128, out),
+        BomlValue::Int128(n) => encode_number(*n as f64, *n, out),
+        BomlValue::Float32(n) => encode_float(*n as f64, out),
+        BomlValue::Float64(n) => encode_float(*n, out),
+        BomlValue::Decimal(d) => encode_float(d.to_f64().unwrap_or(f64::NAN), out),
+        BomlValue::String(s) => {
+            out.push(TAG_STRING);
+            put_escaped(s.as_bytes(), out);
+        }
+        BomlValue::Document(fields) => {
+            out.push(TAG_DOCUMENT);
+            for (key, value) in fields {
+                out.push(FIELD);
+                put_escaped(key.as_bytes(), out);
+                encode_value(value, out);
+            }
+            out.push(END);
+        }
+        BomlValue::Array(items) => {
+            out.push(TAG_ARRAY);
+            for item in items {
+                encode_value(item, out);
+            }
+            out.push(END);
+        }
+        BomlValue::Binary(bytes) => {
+            out.push(TAG_BINARY);
+            put_escaped(bytes, out);
+        }
+        BomlValue::ObjectId(id) => {
+            out.push(TAG_OBJECT_ID);
+            out.extend_from_slice(id.as_bytes());
+        }
+        BomlValue::Uuid(uuid) => {
+            out.push(TAG_UUID);
+            out.extend_from_slice(uuid.as_bytes());
+        }
+        BomlValue::Boolean(b) => {
+            out.push(TAG_BOOLEAN);
+            out.push(*b as u8);
+        }
+        BomlValue::DateTime(dt) => {
+            out.push(TAG_DATETIME);
+            out.extend_from_slice(&ordered_i64(dt.timestamp_micros()));
+        }
+        BomlValue::Timestamp(ts) => {
+            out.push(TAG_TIMESTAMP);
+            out.extend_from_slice(&ordered_i64(*ts));
+        }
+        BomlValue::Regex(regex) => {
+            out.push(TAG_REGEX);
+            put_escaped(regex.pattern.as_bytes(), out);
+            put_escaped(regex.options.as_bytes(), out);
+        }
+        BomlValue::JavaScript(js) => {
+            out.push(TAG_JAVASCRIPT);
+            put_escaped(js.code.as_bytes(), out);
+        }
+    }
+}
+
+/// # Brief
+/// 把单个值的降序编码追加到 `out`
+///
+/// 升序编码自定界,按位取反后顺序恰好相反,可以与升序字段混合拼接成复合键。
+pub fn encode_descending(value: &BomlValue, out: &mut Vec<u8>) {
+    let start = out.len();
+    encode_value(value, out);
+    for byte in &mut out[start..] {
+        *byte = !*byte;
+    }
+}
+
+/// 数值编码: 保序的 f64 加上整数相对 f64 近似值的偏移
+///
+/// 超过 2^53 的整数转换为 f64 会丢失精度,偏移保证这些整数仍然互不相同且有序;
+/// 浮点数偏移为 0,因此整数和值相等的浮点数编码相同。
+fn encode_number(approx: f64, exact: i128, out: &mut Vec<u8>) {
+    out.push(TAG_NUMBER);
+    out.extend_from_slice(&ordered_f64(approx));
+    let offset = exact - approx as i128;
+    out.extend_from_slice(&ordered_i64(offset.clamp(i64::MIN as i128, i64::MAX as i128) as i64));
+}
+
+fn encode_float(n: f64, out: &mut Vec<u8>) {
+    out.push(TAG_NUMBER);
+    out.extend_from_slice(&ordered_f64(n));
+    out.extend_from_slice(&ordered_i64(0));
+}
+
+/// 翻转符号位,使有符号整数的大端字节序与数值顺序一致
+fn ordered_i64(n: i64) -> [u8; 8] {
+    ((n as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+/// 正数翻转符号位,负数按位取反;NaN 排在所有数值之前,-0.0 与 0.0 相同
+fn ordered_f64(n: f64) -> [u8; 8] {
+    if n.is_nan() {
+        return [0; 8];
+    }
+    let bits = if n == 0.0 { 0 } else { n.to_bits() };
+    let ordered = if bits >> 63 == 1 { !bits } else { bits ^ (1 << 63) };
+    ordered.to_be_bytes()
+}
+
+/// # Brief
+/// 按索引键的顺序比较两个浮点数
+///
+/// 与编码后的字节序一致的全序:NaN 之间相等且小于所有数值,-0.0 与 0.0 相等。
+/// 查询比较、排序和聚合都使用这一顺序,保证与索引扫描的结果相同。
+pub fn compare_f64(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
+/// 0x00 转义为 `0x00 0xFF`,以 `0x00 0x00` 结尾
+fn put_escaped(bytes: &[u8], out: &mut Vec<u8>) {
+    for &byte in bytes {
+        out.push(byte);
+        if byte == 0 {
+            out.push(0xFF);
+        }
+    }
+    out.extend_from_slice(&[0, 0]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use mikudb_common::ObjectId;
+
+    fn key(value: BomlValue) -> Vec<u8> {
+        encode_key(&[value])
+    }
+
+    fn assert_ascending(values: Vec<BomlValue>) {
+        for pair in values.windows(2) {
+            assert!(
+                key(pair[0].clone()) < key(pair[1].clone()),
+                "{:?} should sort before {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn test_numbers_sort_by_value() {
+        assert_ascending(vec![
+            BomlValue::Float64(f64::NAN),
+            BomlValue::Float64(f64::NEG_INFINITY),
+            BomlValue::Int64(i64::MIN),
+            BomlValue::Int64(-1_000_000),
+            BomlValue::Float64(-2.5),
+            BomlValue::Int32(-2),
+            BomlValue::Float64(-0.5),
+            BomlValue::Int32(0),
+            BomlValue::Float64(0.25),
+            BomlValue::Int64(1),
+            BomlValue::Float32(1.5),
+            BomlValue::Int32(i32::MAX),
+            BomlValue::Int64((1 << 53) + 1),
+            BomlValue::Int64((1 << 53) + 2),
+            BomlValue::Int64(i64::MAX),
+            BomlValue::Float64(f64::INFINITY),
+        ]);
+
+        assert_eq!(key(BomlValue::Int32(5)), key(BomlValue::Int64(5)));
+        assert_eq!(key(BomlValue::Int64(5)), key(BomlValue::Float64(5.0)));
+        assert_eq!(key(BomlValue::Float64(-0.0)), key(BomlValue::Float64(0.0)));
+    }
+
+    #[test]
+    fn test_compare_f64_matches_key_order() {
+        let values = [f64::NAN, -f64::NAN, f64::NEG_INFINITY, -1.5, -0.0, 0.0, 1e-300, 2.0, f64::INFINITY];
+        for &a in &values {
+            for &b in &values {
+                let keys = key(BomlValue::Float64(a)).cmp(&key(BomlValue::Float64(b)));
+                assert_eq!(compare_f64(a, b), keys, "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_strings_and_types() {
+        assert_ascending(vec![
+            BomlValue::String("".into()),
+            BomlValue::String("a".into()),
+            BomlValue::String("a\0".into()),
+            BomlValue::String("a\0b".into()),
+            BomlValue::String("ab".into()),
+            BomlValue::String("b".into()),
+        ]);
+
+        assert_ascending(vec![
+            BomlValue::Null,
+            BomlValue::Int64(i64::MAX),
+            BomlValue::String("zzz".into()),
+            BomlValue::Document(Default::default()),
+            BomlValue::Array(vec![]),
+            BomlValue::Array(vec![BomlValue::Int32(1)]),
+            BomlValue::Array(vec![BomlValue::Int32(1), BomlValue::Int32(0)]),
+            BomlValue::Array(vec![BomlValue::Int32(2)]),
+            BomlValue::Binary(vec![0xFF]),
+            BomlValue::ObjectId(ObjectId::from_bytes([0; 12])),
+            BomlValue::Boolean(false),
+            BomlValue::Boolean(true),
+            BomlValue::DateTime(Utc.with_ymd_and_hms(1960, 1, 1, 0, 0, 0).unwrap()),
+            BomlValue::DateTime(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+        ]);
+    }
+
+    #[test]
+    fn test_composite_and_descending() {
+        // 第一个字段较短的字符串不会因为拼接而排到后面
+        let a = encode_key(&[BomlValue::String("a".into()), BomlValue::Int32(9)]);
+        let b = encode_key(&[BomlValue::String("ab".into()), BomlValue::Int32(1)]);
+        assert!(a < b);
+
+        let mut low = Vec::new();
+        let mut high = Vec::new();
+        encode_descending(&BomlValue::String("a".into()), &mut low);
+        encode_descending(&BomlValue::String("ab".into()), &mut high);
+        assert!(high < low);
+
+        let mut mixed_a = Vec::new();
+        encode_value(&BomlValue::Int32(1), &mut mixed_a);
+        encode_descending(&BomlValue::Int32(5), &mut mixed_a);
+        let mut mixed_b = Vec::new();
+        encode_value(&BomlValue::Int32(1), &mut mixed_b);
+        encode_descending(&BomlValue::Int32(3), &mut mixed_b);
+        assert!(mixed_a < mixed_b);
+    }
+}
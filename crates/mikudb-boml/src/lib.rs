@@ -6,7 +6,15 @@
 //! - **更紧凑的编码**：小整数、短字符串使用特殊标记，减少存储空间
 //! - **更快的解析**：使用变长整数编码，减少内存拷贝
 //! - **校验和支持**：内置 xxHash3 校验，保证数据完整性
+//! - **文档压缩**：`encode_document_with` 可选 LZ4/Zstd 压缩较大的文档，压缩算法记录在文档头中
+//! - **流式解码**：`StreamDecoder` 增量解码分块到达的数据，无需先缓冲整个文档
+//! - **借用解码**：`decode_borrowed` 返回借用输入缓冲区的 `BomlValueRef`，不为每个字段分配内存
+//! - **部分更新**：`BomlPatch` 记录字段级 set/unset/inc/push 操作，可紧凑编码并用 `Document::apply_patch` 应用
+//! - **合并与差异**：`Document::merge` 按策略深度合并文档，`Document::diff` 生成可应用的 `BomlPatch`
+//! - **保序键编码**：`keyenc` 把值编码为可按字节比较的索引键，负数、浮点数和复合键保持排序
+//! - **扩展 JSON**：规范扩展 JSON 导入导出，所有类型无损往返
 //! - **Serde 集成**：完整支持 Rust 的 Serde 序列化框架
+//! - **TOML / YAML**：启用 `toml` / `yaml` feature 后与配置类数据互转，保持整数与浮点数的区分和时间戳
 //!
 //! ## 快速开始
 //!
@@ -25,12 +33,18 @@
 //! let value = decode(&bytes).unwrap();
 //! ```
 //!
+//! ## WebAssembly
+//!
+//! 本 crate 可编译到 `wasm32-unknown-unknown`,wasm-bindgen 导出的编码/解码/格式化接口
+//! 位于独立的 `mikudb-boml-wasm` crate。
+//!
 //! ## OpenEuler 适配亮点
 //!
 //! - 使用 xxHash3 进行校验和计算，在 ARM64 (鲲鹏) 上有优秀性能
 //! - 内存对齐优化，适配 OpenEuler 的内存分配器
 
 pub mod value;
+pub mod borrowed;
 pub mod document;
 pub mod codec;
 pub mod ser;
@@ -38,12 +52,26 @@ pub mod de;
 pub mod spec;
 pub mod json;
 pub mod bson;
+pub mod keyenc;
+#[cfg(feature = "toml")]
+pub mod toml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
 
-pub use codec::{decode, encode, encode_to_vec};
-pub use document::Document;
+pub use borrowed::BomlValueRef;
+pub use codec::{decode, decode_borrowed, encode, encode_to_vec, StreamDecoder};
+pub use spec::DocumentCompression;
+pub use document::{BomlPatch, Document, MergeStrategy, PatchOp};
 pub use value::{BomlValue, JavaScriptValue, RegexValue};
-pub use json::{from_json, from_json_string, to_json, to_json_string};
+pub use json::{
+    from_extended_json, from_extended_json_string, from_json, from_json_string, to_extended_json,
+    to_extended_json_string, to_json, to_json_string,
+};
 pub use bson::{from_bson, from_bson_bytes, to_bson, to_bson_bytes};
+#[cfg(feature = "toml")]
+pub use crate::toml::{from_toml, from_toml_str, to_toml, to_toml_string};
+#[cfg(feature = "yaml")]
+pub use yaml::{from_yaml, from_yaml_str, to_yaml, to_yaml_string};
 
 use thiserror::Error;
 
@@ -95,6 +123,10 @@ pub enum BomlError {
     /// 反序列化过程错误
     #[error("Deserialization error: {0}")]
     Deserialization(String),
+
+    /// 按路径写入时,路径经过标量值或在数组上使用了非数字下标
+    #[error("Path conflict at '{path}': cannot descend into {found}")]
+    PathConflict { path: String, found: &'static str },
 }
 
 /// BOML 操作的 Result 类型别名
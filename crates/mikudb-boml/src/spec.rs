@@ -8,6 +8,38 @@ pub const BOML_MAGIC: [u8; 4] = [0x42, 0x4F, 0x4D, 0x4C];
 /// BOML 格式版本号
 pub const BOML_VERSION: u8 = 1;
 
+/// 带压缩字节的文档格式版本号,版本号之后是一个 [`DocumentCompression`] 字节
+pub const BOML_VERSION_COMPRESSED: u8 = 2;
+
+/// 编码后小于该大小的文档不压缩
+pub const COMPRESSION_MIN_SIZE: usize = 512;
+
+/// 文档压缩算法
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocumentCompression {
+    /// 不压缩
+    #[default]
+    None = 0,
+    /// LZ4,压缩和解压都很快
+    Lz4 = 1,
+    /// Zstd,压缩率更高
+    Zstd = 2,
+}
+
+impl DocumentCompression {
+    /// # Brief
+    /// 从文档头中的压缩字节解析
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::None),
+            1 => Some(Self::Lz4),
+            2 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
 /// 单个文档最大大小 (16MB)
 pub const MAX_DOCUMENT_SIZE: usize = 16 * 1024 * 1024;
 
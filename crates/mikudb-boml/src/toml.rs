@@ -0,0 +1,211 @@
+//! BOML 与 TOML 互转模块
+//!
+//! 启用 `toml` feature 后可用,用于导入导出配置类数据:
+//! - 整数和浮点数保持区分: TOML 整数按 `from_json` 的规则转为 Int32/Int64,浮点数转为 Float64
+//! - 带时区的日期时间转为 DateTime;不带时区的日期时间和日期按 UTC 处理,只有时间时保留为字符串
+//! - TOML 没有 null,导出时文档中的 Null 字段被省略,数组中的 Null 返回错误
+//! - TOML 没有对应类型的值(ObjectId、Decimal、Binary 等)使用扩展 JSON 的 `$` 包装表,导入时还原
+
+use crate::json::{from_extended_json, is_extended_json_wrapper, to_extended_json};
+use crate::value::BomlValue;
+use crate::{BomlError, BomlResult};
+use ::toml::value::{Datetime, Table};
+use ::toml::Value as TomlValue;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use compact_str::CompactString;
+use indexmap::IndexMap;
+
+/// 将 BomlValue 转换为 TOML 值
+///
+/// # Brief
+/// 原生类型直接映射,其余类型转为扩展 JSON 包装表
+///
+/// # Arguments
+/// * `value` - 要转换的 BOML 值
+///
+/// # Returns
+/// 成功返回 TOML 值;值为 Null 或数组包含 Null 时返回错误
+pub fn to_toml(value: &BomlValue) -> BomlResult<TomlValue> {
+    match value {
+        BomlValue::Null => Err(BomlError::Serialization("TOML has no null value".to_string())),
+        BomlValue::Boolean(b) => Ok(TomlValue::Boolean(*b)),
+        BomlValue::Int32(n) => Ok(TomlValue::Integer(*n as i64)),
+        BomlValue::Int64(n) => Ok(TomlValue::Integer(*n)),
+        BomlValue::Float32(f) => Ok(TomlValue::Float(*f as f64)),
+        BomlValue::Float64(f) => Ok(TomlValue::Float(*f)),
+        BomlValue::String(s) => Ok(TomlValue::String(s.to_string())),
+        BomlValue::DateTime(dt) => {
+            let text = dt.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+            let datetime = text
+                .parse::<Datetime>()
+                .map_err(|e| BomlError::Serialization(format!("Invalid TOML datetime: {}", e)))?;
+            Ok(TomlValue::Datetime(datetime))
+        }
+        BomlValue::Array(arr) => Ok(TomlValue::Array(arr.iter().map(to_toml).collect::<BomlResult<_>>()?)),
+        BomlValue::Document(doc) => {
+            let mut table = Table::new();
+            for (k, v) in doc {
+                if !v.is_null() {
+                    table.insert(k.to_string(), to_toml(v)?);
+                }
+            }
+            Ok(TomlValue::Table(table))
+        }
+        other => TomlValue::try_from(to_extended_json(other))
+            .map_err(|e| BomlError::Serialization(format!("TOML conversion failed: {}", e))),
+    }
+}
+
+/// 从 TOML 值转换为 BomlValue
+///
+/// # Brief
+/// 识别 `to_toml` 输出的扩展 JSON 包装表
+///
+/// # Arguments
+/// * `value` - TOML 值
+///
+/// # Returns
+/// 成功返回 BOML 值,包装表格式错误时返回错误
+pub fn from_toml(value: &TomlValue) -> BomlResult<BomlValue> {
+    match value {
+        TomlValue::String(s) => Ok(BomlValue::String(CompactString::new(s))),
+        TomlValue::Integer(n) => Ok(match i32::try_from(*n) {
+            Ok(n) => BomlValue::Int32(n),
+            Err(_) => BomlValue::Int64(*n),
+        }),
+        TomlValue::Float(f) => Ok(BomlValue::Float64(*f)),
+        TomlValue::Boolean(b) => Ok(BomlValue::Boolean(*b)),
+        TomlValue::Datetime(dt) => from_toml_datetime(dt),
+        TomlValue::Array(arr) => Ok(BomlValue::Array(arr.iter().map(from_toml).collect::<BomlResult<_>>()?)),
+        TomlValue::Table(table) => {
+            if is_extended_json_wrapper(table.keys().map(String::as_str)) {
+                let json = serde_json::to_value(table)
+                    .map_err(|e| BomlError::Deserialization(format!("Invalid type wrapper: {}", e)))?;
+                return from_extended_json(&json);
+            }
+            let fields = table
+                .iter()
+                .map(|(k, v)| Ok((CompactString::new(k), from_toml(v)?)))
+                .collect::<BomlResult<IndexMap<_, _>>>()?;
+            Ok(BomlValue::Document(fields))
+        }
+    }
+}
+
+/// 将 BOML 文档序列化为 TOML 字符串
+///
+/// # Arguments
+/// * `value` - 要序列化的值,必须是文档(TOML 的根是表)
+pub fn to_toml_string(value: &BomlValue) -> BomlResult<String> {
+    if !matches!(value, BomlValue::Document(_)) {
+        return Err(BomlError::Serialization(format!(
+            "TOML root must be a document, got {}",
+            value.type_name()
+        )));
+    }
+    ::toml::to_string(&to_toml(value)?).map_err(|e| BomlError::Serialization(format!("TOML serialization failed: {}", e)))
+}
+
+/// 从 TOML 字符串反序列化为 BOML 文档
+///
+/// # Arguments
+/// * `toml_str` - TOML 文本
+pub fn from_toml_str(toml_str: &str) -> BomlResult<BomlValue> {
+    let table: Table = ::toml::from_str(toml_str)
+        .map_err(|e| BomlError::Deserialization(format!("TOML parsing failed: {}", e)))?;
+    from_toml(&TomlValue::Table(table))
+}
+
+impl BomlValue {
+    /// 从 TOML 文本解析
+    ///
+    /// # Brief
+    /// 等同于 [`from_toml_str`],结果总是文档
+    ///
+    /// # Arguments
+    /// * `toml_str` - TOML 文本
+    pub fn from_toml(toml_str: &str) -> BomlResult<Self> {
+        from_toml_str(toml_str)
+    }
+
+    /// 序列化为 TOML 文本
+    ///
+    /// # Brief
+    /// 等同于 [`to_toml_string`],只有文档可以序列化
+    pub fn to_toml(&self) -> BomlResult<String> {
+        to_toml_string(self)
+    }
+}
+
+fn from_toml_datetime(dt: &Datetime) -> BomlResult<BomlValue> {
+    let text = dt.to_string();
+    let invalid = || BomlError::Deserialization(format!("Invalid TOML datetime: {}", text));
+    let parsed = match (&dt.date, &dt.time, &dt.offset) {
+        (Some(_), Some(_), Some(_)) => DateTime::parse_from_rfc3339(&text).map_err(|_| invalid())?.with_timezone(&Utc),
+        (Some(_), Some(_), None) => NaiveDateTime::parse_from_str(&text, "%Y-%m-%dT%H:%M:%S%.f")
+            .map_err(|_| invalid())?
+            .and_utc(),
+        (Some(_), None, _) => NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+            .map_err(|_| invalid())?
+            .and_hms_opt(0, 0, 0)
+            .ok_or_else(invalid)?
+            .and_utc(),
+        (None, _, _) => return Ok(BomlValue::String(CompactString::new(&text))),
+    };
+    Ok(BomlValue::DateTime(parsed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mikudb_common::ObjectId;
+
+    #[test]
+    fn test_toml_round_trip() {
+        let value = BomlValue::from_toml(
+            r#"
+            name = "miku"
+            port = 3939
+            big = 5000000000
+            ratio = 0.5
+            released = 2007-08-31T12:00:00+09:00
+            birthday = 2007-08-31
+            alarm = 07:30:00
+
+            [limits]
+            tags = ["vocaloid", "teal"]
+            "#,
+        )
+        .unwrap();
+        let doc = value.as_document().unwrap();
+        assert_eq!(doc.get("port"), Some(&BomlValue::Int32(3939)));
+        assert_eq!(doc.get("big"), Some(&BomlValue::Int64(5_000_000_000)));
+        assert_eq!(doc.get("ratio"), Some(&BomlValue::Float64(0.5)));
+        let released = DateTime::parse_from_rfc3339("2007-08-31T03:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(doc.get("released"), Some(&BomlValue::DateTime(released)));
+        assert!(matches!(doc.get("birthday"), Some(BomlValue::DateTime(_))));
+        assert_eq!(doc.get("alarm"), Some(&BomlValue::String("07:30:00".into())));
+
+        let text = value.to_toml().unwrap();
+        assert_eq!(BomlValue::from_toml(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn test_toml_wrapped_types_and_null() {
+        let oid = ObjectId::new();
+        let mut fields = IndexMap::new();
+        fields.insert(CompactString::new("id"), BomlValue::ObjectId(oid));
+        fields.insert(CompactString::new("price"), BomlValue::Decimal("19.90".parse().unwrap()));
+        fields.insert(CompactString::new("missing"), BomlValue::Null);
+        let value = BomlValue::Document(fields);
+
+        let restored = BomlValue::from_toml(&value.to_toml().unwrap()).unwrap();
+        let doc = restored.as_document().unwrap();
+        assert_eq!(doc.get("id"), Some(&BomlValue::ObjectId(oid)));
+        assert_eq!(doc.get("price"), Some(&BomlValue::Decimal("19.90".parse().unwrap())));
+        assert!(doc.get("missing").is_none());
+
+        assert!(to_toml(&BomlValue::Array(vec![BomlValue::Null])).is_err());
+        assert!(BomlValue::Int32(1).to_toml().is_err());
+    }
+}
@@ -3,6 +3,7 @@
 //! 定义了 BOML 格式支持的所有数据类型，包括基础类型和复合类型。
 //! 使用 `CompactString` 优化短字符串的内存占用。
 
+use crate::BomlResult;
 use chrono::{DateTime, Utc};
 use compact_str::CompactString;
 use indexmap::IndexMap;
@@ -280,6 +281,26 @@ impl BomlValue {
         }
         Some(current)
     }
+
+    /// 从规范扩展 JSON 字符串解析
+    ///
+    /// # Brief
+    /// 还原 `to_extended_json_string` 的输出,ObjectId、DateTime、Decimal、Binary、
+    /// Uuid、Regex 以及各数值类型都不会丢失,格式见 [`crate::json::to_extended_json`]
+    ///
+    /// # Arguments
+    /// * `json` - 扩展 JSON 字符串
+    pub fn from_extended_json(json: &str) -> BomlResult<Self> {
+        crate::json::from_extended_json_string(json)
+    }
+
+    /// 序列化为规范扩展 JSON 字符串
+    ///
+    /// # Brief
+    /// 输出可由 `from_extended_json` 无损还原的紧凑 JSON,适合数据导出
+    pub fn to_extended_json_string(&self) -> String {
+        crate::json::to_extended_json_string(self)
+    }
 }
 
 impl Default for BomlValue {
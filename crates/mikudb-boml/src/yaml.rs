@@ -0,0 +1,191 @@
+//! BOML 与 YAML 互转模块
+//!
+//! 启用 `yaml` feature 后可用,用于导入导出配置类数据:
+//! - 整数和浮点数保持区分: 整数按 `from_json` 的规则转为 Int32/Int64,浮点数转为 Float64
+//! - RFC 3339 格式的字符串(如 `2007-08-31T12:00:00+09:00`)转为 DateTime,导出时写回 RFC 3339 字符串
+//! - 映射的数字、布尔键转为字符串键,标签(`!tag`)被忽略,只保留标签下的值
+//! - YAML 没有对应类型的值(ObjectId、Decimal、Binary 等)使用扩展 JSON 的 `$` 包装映射,导入时还原
+
+use crate::json::{from_extended_json, is_extended_json_wrapper, to_extended_json};
+use crate::value::BomlValue;
+use crate::{BomlError, BomlResult};
+use chrono::{DateTime, SecondsFormat, Utc};
+use compact_str::CompactString;
+use indexmap::IndexMap;
+use serde_yaml::{Mapping, Number, Value as YamlValue};
+
+/// 将 BomlValue 转换为 YAML 值
+///
+/// # Brief
+/// 原生类型直接映射,其余类型转为扩展 JSON 包装映射
+///
+/// # Arguments
+/// * `value` - 要转换的 BOML 值
+///
+/// # Returns
+/// 成功返回 YAML 值
+pub fn to_yaml(value: &BomlValue) -> BomlResult<YamlValue> {
+    match value {
+        BomlValue::Null => Ok(YamlValue::Null),
+        BomlValue::Boolean(b) => Ok(YamlValue::Bool(*b)),
+        BomlValue::Int32(n) => Ok(YamlValue::Number(Number::from(*n))),
+        BomlValue::Int64(n) => Ok(YamlValue::Number(Number::from(*n))),
+        BomlValue::Float32(f) => Ok(YamlValue::Number(Number::from(*f as f64))),
+        BomlValue::Float64(f) => Ok(YamlValue::Number(Number::from(*f))),
+        BomlValue::String(s) => Ok(YamlValue::String(s.to_string())),
+        BomlValue::DateTime(dt) => Ok(YamlValue::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true))),
+        BomlValue::Array(arr) => Ok(YamlValue::Sequence(arr.iter().map(to_yaml).collect::<BomlResult<_>>()?)),
+        BomlValue::Document(doc) => {
+            let mut mapping = Mapping::with_capacity(doc.len());
+            for (k, v) in doc {
+                mapping.insert(YamlValue::String(k.to_string()), to_yaml(v)?);
+            }
+            Ok(YamlValue::Mapping(mapping))
+        }
+        other => serde_yaml::to_value(to_extended_json(other))
+            .map_err(|e| BomlError::Serialization(format!("YAML conversion failed: {}", e))),
+    }
+}
+
+/// 从 YAML 值转换为 BomlValue
+///
+/// # Brief
+/// 识别 `to_yaml` 输出的扩展 JSON 包装映射
+///
+/// # Arguments
+/// * `value` - YAML 值
+///
+/// # Returns
+/// 成功返回 BOML 值;整数超出 i64 范围、映射键不是标量或包装映射格式错误时返回错误
+pub fn from_yaml(value: &YamlValue) -> BomlResult<BomlValue> {
+    match value {
+        YamlValue::Null => Ok(BomlValue::Null),
+        YamlValue::Bool(b) => Ok(BomlValue::Boolean(*b)),
+        YamlValue::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(match i32::try_from(i) {
+                    Ok(i) => BomlValue::Int32(i),
+                    Err(_) => BomlValue::Int64(i),
+                })
+            } else if n.is_f64() {
+                Ok(BomlValue::Float64(n.as_f64().unwrap_or_default()))
+            } else {
+                Err(BomlError::Deserialization(format!("YAML integer out of range: {}", n)))
+            }
+        }
+        YamlValue::String(s) => Ok(match DateTime::parse_from_rfc3339(s) {
+            Ok(dt) => BomlValue::DateTime(dt.with_timezone(&Utc)),
+            Err(_) => BomlValue::String(CompactString::new(s)),
+        }),
+        YamlValue::Sequence(seq) => Ok(BomlValue::Array(seq.iter().map(from_yaml).collect::<BomlResult<_>>()?)),
+        YamlValue::Mapping(mapping) => {
+            let fields = mapping
+                .iter()
+                .map(|(k, v)| Ok((yaml_key(k)?, v)))
+                .collect::<BomlResult<Vec<_>>>()?;
+            if is_extended_json_wrapper(fields.iter().map(|(k, _)| k.as_str())) {
+                let json = serde_json::to_value(mapping)
+                    .map_err(|e| BomlError::Deserialization(format!("Invalid type wrapper: {}", e)))?;
+                return from_extended_json(&json);
+            }
+            let fields = fields
+                .into_iter()
+                .map(|(k, v)| Ok((k, from_yaml(v)?)))
+                .collect::<BomlResult<IndexMap<_, _>>>()?;
+            Ok(BomlValue::Document(fields))
+        }
+        YamlValue::Tagged(tagged) => from_yaml(&tagged.value),
+    }
+}
+
+/// 将 BOML 值序列化为 YAML 字符串
+///
+/// # Arguments
+/// * `value` - 要序列化的 BOML 值
+pub fn to_yaml_string(value: &BomlValue) -> BomlResult<String> {
+    serde_yaml::to_string(&to_yaml(value)?).map_err(|e| BomlError::Serialization(format!("YAML serialization failed: {}", e)))
+}
+
+/// 从 YAML 字符串反序列化为 BOML 值
+///
+/// # Arguments
+/// * `yaml_str` - YAML 文本,只读取第一个文档
+pub fn from_yaml_str(yaml_str: &str) -> BomlResult<BomlValue> {
+    let value: YamlValue = serde_yaml::from_str(yaml_str)
+        .map_err(|e| BomlError::Deserialization(format!("YAML parsing failed: {}", e)))?;
+    from_yaml(&value)
+}
+
+impl BomlValue {
+    /// 从 YAML 文本解析
+    ///
+    /// # Brief
+    /// 等同于 [`from_yaml_str`]
+    ///
+    /// # Arguments
+    /// * `yaml_str` - YAML 文本
+    pub fn from_yaml(yaml_str: &str) -> BomlResult<Self> {
+        from_yaml_str(yaml_str)
+    }
+
+    /// 序列化为 YAML 文本
+    ///
+    /// # Brief
+    /// 等同于 [`to_yaml_string`]
+    pub fn to_yaml(&self) -> BomlResult<String> {
+        to_yaml_string(self)
+    }
+}
+
+fn yaml_key(key: &YamlValue) -> BomlResult<CompactString> {
+    match key {
+        YamlValue::String(s) => Ok(CompactString::new(s)),
+        YamlValue::Number(n) => Ok(CompactString::new(n.to_string())),
+        YamlValue::Bool(b) => Ok(CompactString::new(b.to_string())),
+        YamlValue::Tagged(tagged) => yaml_key(&tagged.value),
+        other => Err(BomlError::Deserialization(format!("Unsupported YAML mapping key: {:?}", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mikudb_common::ObjectId;
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let value = BomlValue::from_yaml(
+            r#"
+name: miku
+port: 3939
+big: 5000000000
+ratio: 1.0
+released: 2007-08-31T12:00:00+09:00
+nickname: ~
+tags: [vocaloid, teal]
+limits:
+  2007: debut
+"#,
+        )
+        .unwrap();
+        let doc = value.as_document().unwrap();
+        assert_eq!(doc.get("port"), Some(&BomlValue::Int32(3939)));
+        assert_eq!(doc.get("big"), Some(&BomlValue::Int64(5_000_000_000)));
+        assert_eq!(doc.get("ratio"), Some(&BomlValue::Float64(1.0)));
+        let released = DateTime::parse_from_rfc3339("2007-08-31T03:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(doc.get("released"), Some(&BomlValue::DateTime(released)));
+        assert_eq!(doc.get("nickname"), Some(&BomlValue::Null));
+        assert_eq!(value.get_path("limits.2007"), Some(&BomlValue::String("debut".into())));
+
+        let text = value.to_yaml().unwrap();
+        assert_eq!(BomlValue::from_yaml(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn test_yaml_wrapped_types() {
+        let oid = ObjectId::new();
+        let value = BomlValue::Array(vec![BomlValue::ObjectId(oid), BomlValue::Binary(vec![1, 2, 3])]);
+        assert_eq!(BomlValue::from_yaml(&value.to_yaml().unwrap()).unwrap(), value);
+        assert!(BomlValue::from_yaml("[1, 2").is_err());
+    }
+}
@@ -5,10 +5,15 @@
 //! - 批量执行 MQL 脚本文件
 //! - 静默模式和结果格式化
 //! - 注释过滤(-- 和 // 风格)
+//! - 紧凑 JSON 输出(每条语句一行)和出错即停(可选继续执行),便于脚本和 CI 使用
 
 use crate::client::Client;
-use crate::formatter::Formatter;
-use crate::{CliResult, Config};
+use crate::diagnostic;
+use crate::formatter::{Formatter, QueryResult};
+use crate::{CliError, CliResult, Config};
+use colored::Colorize;
+use mikudb_boml::BomlValue;
+use serde_json::json;
 use std::fs;
 use std::path::Path;
 
@@ -24,6 +29,10 @@ pub struct Cli {
     formatter: Formatter,
     /// 静默模式(不输出结果)
     quiet: bool,
+    /// 紧凑 JSON 输出
+    compact: bool,
+    /// 遇到错误后继续执行后续语句
+    continue_on_error: bool,
 }
 
 impl Cli {
@@ -46,6 +55,8 @@ impl Cli {
             client,
             formatter,
             quiet: config.quiet,
+            compact: config.compact,
+            continue_on_error: config.continue_on_error,
         })
     }
 
@@ -53,6 +64,8 @@ impl Cli {
     /// 执行单条 MQL 查询
     ///
     /// 发送查询到服务器并格式化输出结果(除非在静默模式)。
+    /// 失败时错误已输出(紧凑模式输出到 stdout,否则输出到 stderr),
+    /// 调用方只需根据返回的错误决定退出码。
     ///
     /// # Arguments
     /// * `query` - MQL 查询语句
@@ -61,14 +74,46 @@ impl Cli {
     /// 执行结果
     pub async fn execute(&mut self, query: &str) -> CliResult<()> {
         // 发送查询到服务器
-        let result = self.client.query(query).await?;
+        let result = self.client.query(query).await;
+        self.report(query, result)
+    }
 
-        // 非静默模式下输出结果
-        if !self.quiet {
-            self.formatter.print(&result);
-        }
+    /// # Brief
+    /// 执行带位置参数的 MQL 查询
+    ///
+    /// 参数值与语句分开发送,由服务器绑定到 `$1`、`$2`……,输出方式与 `execute` 相同。
+    ///
+    /// # Arguments
+    /// * `query` - 带 `$n` 占位符的 MQL 语句
+    /// * `params` - 参数值
+    ///
+    /// # Returns
+    /// 执行结果
+    pub async fn execute_with_params(&mut self, query: &str, params: &[BomlValue]) -> CliResult<()> {
+        let result = self.client.execute_with_params(query, params).await;
+        self.report(query, result)
+    }
 
-        Ok(())
+    /// # Brief
+    /// 输出单条语句的执行结果或错误
+    fn report(&self, query: &str, result: CliResult<QueryResult>) -> CliResult<()> {
+        match result {
+            Ok(result) => {
+                // 非静默模式下输出结果
+                if !self.quiet {
+                    if self.compact {
+                        println!("{}", result_to_json(query, &result));
+                    } else {
+                        self.formatter.print(&result);
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => {
+                self.report_error(Some(query), &e);
+                Err(e)
+            }
+        }
     }
 
     /// # Brief
@@ -83,10 +128,18 @@ impl Cli {
     /// * `path` - 脚本文件路径
     ///
     /// # Returns
-    /// 执行结果(遇到错误立即停止)
+    /// 执行结果。默认遇到第一个错误立即停止;启用 continue-on-error 时执行完所有语句后返回第一个错误
     pub async fn execute_file(&mut self, path: &Path) -> CliResult<()> {
         // 读取整个文件内容
-        let content = fs::read_to_string(path)?;
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                let e = CliError::Io(e);
+                self.report_error(None, &e);
+                return Err(e);
+            }
+        };
+        let mut first_error = None;
 
         // 逐行处理
         for line in content.lines() {
@@ -96,15 +149,115 @@ impl Cli {
                 continue;
             }
 
-            // 非静默模式下显示正在执行的语句
-            if !self.quiet {
+            // 非静默模式下显示正在执行的语句(紧凑模式保持每行一个 JSON 对象)
+            if !self.quiet && !self.compact {
                 println!("> {}", line);
             }
 
-            // 执行查询,遇到错误立即返回
-            self.execute(line).await?;
+            // 执行查询,错误已在 execute 中输出
+            if let Err(e) = self.execute(line).await {
+                if !self.continue_on_error {
+                    return Err(e);
+                }
+                first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// # Brief
+    /// 输出错误信息
+    ///
+    /// 紧凑模式下输出 JSON 对象到 stdout,否则输出到 stderr。
+    ///
+    /// # Arguments
+    /// * `statement` - 出错的语句(若有)
+    /// * `error` - 错误
+    fn report_error(&self, statement: Option<&str>, error: &CliError) {
+        if self.compact {
+            println!("{}", error_to_json(statement, error));
+        } else {
+            eprintln!("{} {}", "Error:".red().bold(), error);
+            if let (Some(query), CliError::Syntax(msg)) = (statement, error) {
+                if let Some(hint) = diagnostic::render_syntax_error(query, msg) {
+                    eprintln!("{}", hint);
+                }
+            }
         }
+    }
+}
 
-        Ok(())
+/// # Brief
+/// 将查询结果转换为单行 JSON
+///
+/// # Arguments
+/// * `statement` - 执行的语句
+/// * `result` - 查询结果
+pub fn result_to_json(statement: &str, result: &QueryResult) -> String {
+    json!({
+        "statement": statement,
+        "success": result.success,
+        "affected": result.affected,
+        "documents": result.documents,
+        "message": result.message,
+        "stats": result.stats,
+    })
+    .to_string()
+}
+
+/// # Brief
+/// 将错误转换为单行 JSON
+///
+/// # Arguments
+/// * `statement` - 出错的语句(若有)
+/// * `error` - 错误
+pub fn error_to_json(statement: Option<&str>, error: &CliError) -> String {
+    json!({
+        "statement": statement,
+        "success": false,
+        "error": {
+            "kind": error.kind(),
+            "code": error.exit_code(),
+            "message": error.to_string(),
+        },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exit_code;
+
+    #[test]
+    fn test_compact_output_is_one_json_line() {
+        let result = QueryResult {
+            success: true,
+            affected: 1,
+            documents: vec![json!({"name": "miku\nhatsune"})],
+            message: Some("ok".to_string()),
+            stats: None,
+        };
+        let line = result_to_json("FIND users", &result);
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["statement"], "FIND users");
+        assert_eq!(value["affected"], 1);
+        assert_eq!(value["documents"][0]["name"], "miku\nhatsune");
+    }
+
+    #[test]
+    fn test_compact_error_carries_exit_code() {
+        let error = CliError::Syntax("Parse error: unexpected token".to_string());
+        let line = error_to_json(Some("FIND"), &error);
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["success"], false);
+        assert_eq!(value["error"]["kind"], "parse");
+        assert_eq!(value["error"]["code"], exit_code::PARSE_ERROR);
     }
-}
\ No newline at end of file
+}
@@ -3,16 +3,30 @@
 //! 本模块实现 MikuDB 客户端的网络连接和协议通信:
 //! - TCP 连接管理
 //! - MikuWire 协议编解码
+//! - 连接时握手(Hello),按服务器返回的协议版本和能力工作
+//! - 记录连接各阶段耗时,测量往返延迟(Ping)
 //! - 用户认证
 //! - 查询请求/响应处理
+//! - 查询超时和中断(KillOp)
+//! - 请求多路复用: 后台任务按 `response_to` 分发响应,同一连接上可以同时有多个请求在等待;
+//!   服务器支持时查询以并发标志发送,中断请求可在同一连接上发送
+//! - 分批返回结果的游标读取,以扩展 JSON 扫描集合和批量插入(dump/restore)
 //! - 自动重连和错误处理
 
 use crate::formatter::QueryResult;
 use crate::{CliError, CliResult, Config};
 use bytes::BytesMut;
-use std::sync::atomic::{AtomicU32, Ordering};
+use mikudb_boml::BomlValue;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
 /// 全局请求 ID 计数器,为每个请求生成唯一标识
 static REQUEST_ID: AtomicU32 = AtomicU32::new(1);
@@ -21,13 +35,62 @@ static REQUEST_ID: AtomicU32 = AtomicU32::new(1);
 const MAGIC_BYTES: &[u8; 4] = b"MIKU";
 /// 协议版本号
 const PROTOCOL_VERSION: u8 = 1;
+/// 服务器未返回握手信息时假定的最大消息大小(64 MB)
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+/// 消息头标志位: 请求可以与同一连接上的其他请求并发执行
+const FLAG_CONCURRENT: u16 = 0x0002;
+
+/// 服务器握手(Hello)返回的版本和能力
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerInfo {
+    /// 服务器版本
+    pub server_version: String,
+    /// 支持的协议版本
+    pub protocol_versions: Vec<u8>,
+    /// 单条消息负载的最大字节数
+    pub max_message_size: usize,
+    /// 服务器启用的功能
+    #[serde(default)]
+    pub features: ServerFeatures,
+    /// 执行数据操作前是否需要认证
+    pub auth_required: bool,
+}
+
+/// 服务器启用的功能
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerFeatures {
+    /// 是否启用 TLS
+    pub tls: bool,
+    /// 支持的消息压缩算法
+    pub compression: Vec<String>,
+    /// 是否作为集群成员运行
+    pub cluster: bool,
+    /// 是否可以使用 WASM 自定义函数
+    pub wasm_udf: bool,
+    /// 是否支持游标分批返回
+    pub cursors: bool,
+    /// 是否支持请求多路复用
+    pub multiplexing: bool,
+}
+
+/// 建立连接各阶段的耗时
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectTimings {
+    /// TCP 连接
+    pub tcp: Duration,
+    /// 握手(Hello),旧版服务器不支持握手时为 None
+    pub hello: Option<Duration>,
+    /// 认证,服务器不要求认证时为 None
+    pub auth: Option<Duration>,
+}
 
 /// MikuDB 客户端
 ///
 /// 管理与 MikuDB 服务器的连接,处理认证和查询请求。
 pub struct Client {
-    /// TCP 连接流
-    stream: TcpStream,
+    /// 多路复用的连接
+    connection: Connection,
     /// 服务器主机名
     host: String,
     /// 服务器端口
@@ -36,13 +99,19 @@ pub struct Client {
     user: String,
     /// 会话 ID(认证成功后设置)
     session_id: Option<u64>,
+    /// 握手得到的服务器信息,旧版服务器不支持握手时为 None
+    server_info: Option<ServerInfo>,
+    /// 建立连接各阶段的耗时
+    timings: ConnectTimings,
+    /// 写入的应用标签
+    app_tag: Option<String>,
 }
 
 impl Client {
     /// # Brief
     /// 连接到 MikuDB 服务器并认证
     ///
-    /// 建立 TCP 连接,执行认证,如果配置了数据库则自动切换。
+    /// 建立 TCP 连接并握手,服务器要求时执行认证,如果配置了数据库则自动切换。
     ///
     /// # Arguments
     /// * `config` - 客户端配置
@@ -52,19 +121,50 @@ impl Client {
     pub async fn connect(config: &Config) -> CliResult<Self> {
         // 连接到服务器
         let addr = format!("{}:{}", config.host, config.port);
+        let started = Instant::now();
         let stream = TcpStream::connect(&addr).await
             .map_err(|e| CliError::Connection(format!("Failed to connect to {}: {}", addr, e)))?;
+        let mut timings = ConnectTimings { tcp: started.elapsed(), ..Default::default() };
 
         let mut client = Self {
-            stream,
+            connection: Connection::new(stream),
             host: config.host.clone(),
             port: config.port,
             user: config.user.clone(),
             session_id: None,
+            server_info: None,
+            timings,
+            app_tag: config.app_tag.clone(),
         };
 
-        // 执行认证
-        client.authenticate(&config.user, &config.password).await?;
+        let started = Instant::now();
+        match client.hello().await {
+            Ok(info) => {
+                timings.hello = Some(started.elapsed());
+                if !info.protocol_versions.contains(&PROTOCOL_VERSION) {
+                    return Err(CliError::Connection(format!(
+                        "Server supports protocol versions {:?}, this client uses {}",
+                        info.protocol_versions, PROTOCOL_VERSION
+                    )));
+                }
+                client.connection.set_max_message_size(info.max_message_size);
+                client.server_info = Some(info);
+            }
+            // 旧版服务器不认识 Hello,可能直接关闭连接: 重新连接后按默认能力继续
+            Err(_) => {
+                let stream = TcpStream::connect(&addr).await
+                    .map_err(|e| CliError::Connection(format!("Failed to connect to {}: {}", addr, e)))?;
+                client.connection = Connection::new(stream);
+            }
+        }
+
+        // 服务器要求认证(或无法确定)时执行认证
+        if client.server_info.as_ref().map_or(true, |info| info.auth_required) {
+            let started = Instant::now();
+            client.authenticate(&config.user, &config.password).await?;
+            timings.auth = Some(started.elapsed());
+        }
+        client.timings = timings;
 
         // 如果指定了数据库,自动切换
         if let Some(ref db) = config.database {
@@ -92,6 +192,57 @@ impl Client {
         &self.user
     }
 
+    /// # Brief
+    /// 获取会话 ID(服务器未启用认证时为 None)
+    pub fn session_id(&self) -> Option<u64> {
+        self.session_id
+    }
+
+    /// # Brief
+    /// 获取握手得到的服务器信息(旧版服务器不支持握手时为 None)
+    pub fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
+    /// # Brief
+    /// 获取建立连接各阶段的耗时
+    pub fn connect_timings(&self) -> ConnectTimings {
+        self.timings
+    }
+
+    /// # Brief
+    /// 服务器是否支持请求多路复用
+    pub fn multiplexing(&self) -> bool {
+        self.server_info.as_ref().is_some_and(|info| info.features.multiplexing)
+    }
+
+    /// # Brief
+    /// 发送心跳请求(OpCode 0x01),测量一次协议往返延迟
+    ///
+    /// # Returns
+    /// 从发送请求到收到 Pong 的耗时
+    pub async fn ping(&self) -> CliResult<Duration> {
+        let started = Instant::now();
+        self.send_request(0x01, &[]).await?;
+        Ok(started.elapsed())
+    }
+
+    /// # Brief
+    /// 发送握手请求(OpCode 0x03),获取服务器版本和能力
+    async fn hello(&self) -> CliResult<ServerInfo> {
+        let response = self.send_request(0x03, &[]).await?;
+        serde_json::from_slice(&response).map_err(|e| CliError::Parse(format!("Invalid hello response: {}", e)))
+    }
+
+    /// # Brief
+    /// 分配一个新的请求 ID
+    ///
+    /// 调用方需要在请求完成前引用其 ID 时(例如发送 KillOp)先分配 ID,
+    /// 再通过 `query_with_timeout` 发送请求。
+    pub fn next_request_id() -> u32 {
+        REQUEST_ID.fetch_add(1, Ordering::SeqCst)
+    }
+
     /// # Brief
     /// 执行用户认证
     ///
@@ -134,7 +285,10 @@ impl Client {
     ///
     /// # Arguments
     /// * `database` - 数据库名称
-    async fn use_database(&mut self, database: &str) -> CliResult<()> {
+    ///
+    /// # Returns
+    /// 数据库不存在时返回服务器错误
+    pub async fn use_database(&self, database: &str) -> CliResult<()> {
         // 发送 USE DATABASE 请求 (OpCode 0x43)
         let _ = self.send_request(0x43, database.as_bytes()).await?;
         Ok(())
@@ -150,15 +304,81 @@ impl Client {
     ///
     /// # Returns
     /// 查询结果
-    pub async fn query(&mut self, query: &str) -> CliResult<QueryResult> {
+    pub async fn query(&self, query: &str) -> CliResult<QueryResult> {
+        self.query_with_timeout(query, None, Self::next_request_id()).await
+    }
+
+    /// # Brief
+    /// 以指定请求 ID 执行 MQL 查询,可选服务器端超时
+    ///
+    /// 超时由服务器计时,超时后返回 `CliError::Timeout`;
+    /// 请求被 KillOp 中断时返回 `CliError::Interrupted`。
+    /// 服务器支持多路复用时以并发标志发送,等待期间可以在同一连接上发送 `kill_request`。
+    ///
+    /// # Arguments
+    /// * `query` - MQL 查询语句
+    /// * `timeout_ms` - 超时时长(毫秒),None 表示不限制
+    /// * `request_id` - 由 `next_request_id` 分配的请求 ID
+    ///
+    /// # Returns
+    /// 查询结果
+    pub async fn query_with_timeout(
+        &self,
+        query: &str,
+        timeout_ms: Option<u64>,
+        request_id: u32,
+    ) -> CliResult<QueryResult> {
         // 构造查询 JSON payload
+        let mut query_payload = serde_json::json!({
+            "database": "default",
+            "query": query,
+        });
+        if let Some(ms) = timeout_ms {
+            query_payload["timeout_ms"] = ms.into();
+        }
+        self.send_query(query_payload, request_id).await
+    }
+
+    /// # Brief
+    /// 执行带位置参数的 MQL 查询
+    ///
+    /// 语句中的 `$1`、`$2`…… 由服务器依次绑定为 `params` 中的值,
+    /// 值不拼接进语句文本,用户输入中的引号等字符不会改变语句结构。
+    ///
+    /// # Arguments
+    /// * `query` - 带 `$n` 占位符的 MQL 语句
+    /// * `params` - 参数值,按扩展 JSON 发送,保留日期、ObjectId 等类型
+    ///
+    /// # Returns
+    /// 查询结果;参数个数与占位符不符时返回 `CliError::Query`
+    pub async fn execute_with_params(&self, query: &str, params: &[BomlValue]) -> CliResult<QueryResult> {
         let query_payload = serde_json::json!({
             "database": "default",
             "query": query,
+            "params": params.iter().map(mikudb_boml::to_extended_json).collect::<Vec<_>>(),
         });
+        self.send_query(query_payload, Self::next_request_id()).await
+    }
+
+    /// # Brief
+    /// 设置了应用标签时在写入请求中附带写入元数据 `{"app": ...}`
+    fn attach_metadata(&self, payload: &mut serde_json::Value) {
+        if let Some(app) = &self.app_tag {
+            payload["meta"] = serde_json::json!({ "app": app });
+        }
+    }
+
+    /// # Brief
+    /// 发送查询请求并解析响应,读取游标的剩余批次
+    async fn send_query(&self, mut query_payload: serde_json::Value, request_id: u32) -> CliResult<QueryResult> {
+        self.attach_metadata(&mut query_payload);
 
         // 发送查询请求 (OpCode 0x20)
-        let response = self.send_request(0x20, &serde_json::to_vec(&query_payload).unwrap()).await?;
+        let flags = if self.multiplexing() { FLAG_CONCURRENT } else { 0 };
+        let response = self
+            .connection
+            .request(0x20, request_id, flags, &serde_json::to_vec(&query_payload).unwrap())
+            .await?;
 
         // 解析查询响应
         let result: serde_json::Value = serde_json::from_slice(&response)
@@ -167,41 +387,327 @@ impl Client {
         let success = result["success"].as_bool().unwrap_or(false);
         let message = result["message"].as_str().map(String::from);
 
-        // 检查查询是否失败
+        // 检查查询是否失败,按服务器给出的分类区分语法错误、超时、中断和执行错误
         if !success {
-            if let Some(msg) = message {
-                return Err(CliError::Query(msg));
-            }
+            return Err(query_error(&result));
+        }
+
+        // 语句带 BATCH SIZE 时服务器分批返回,继续读取剩余批次
+        let mut documents = result["documents"].as_array().cloned().unwrap_or_default();
+        if let Some(cursor_id) = result["cursor_id"].as_u64() {
+            self.drain_cursor(cursor_id, &mut documents).await?;
         }
 
         // 返回查询结果
         Ok(QueryResult {
             success,
             affected: result["affected"].as_u64().unwrap_or(0),
-            documents: result["documents"].as_array().cloned().unwrap_or_default(),
+            documents,
             message,
+            stats: result.get("stats").filter(|stats| !stats.is_null()).cloned(),
         })
     }
 
     /// # Brief
-    /// 发送 MikuWire 协议请求并接收响应
+    /// 由服务器解析并格式化 MQL 语句,不执行
+    ///
+    /// # Arguments
+    /// * `query` - MQL 语句
+    ///
+    /// # Returns
+    /// 格式化后的语句文本;语法错误时返回 `CliError::Syntax`
+    pub async fn format_query(&self, query: &str) -> CliResult<String> {
+        let payload = serde_json::json!({
+            "database": "default",
+            "query": query,
+            "format_only": true,
+        });
+        let response = self.send_request(0x20, &serde_json::to_vec(&payload).unwrap()).await?;
+        let result: serde_json::Value = serde_json::from_slice(&response)
+            .map_err(|e| CliError::Parse(format!("Invalid response: {}", e)))?;
+
+        if !result["success"].as_bool().unwrap_or(false) {
+            return Err(query_error(&result));
+        }
+        Ok(result["message"].as_str().unwrap_or_default().to_string())
+    }
+
+    /// # Brief
+    /// 读取游标剩余的所有批次
+    ///
+    /// 发送 GetMore 请求(OpCode 0x85),不指定批量大小,由服务器自适应增长。
+    ///
+    /// # Arguments
+    /// * `cursor_id` - 服务器返回的游标 ID
+    /// * `documents` - 追加结果的文档列表
+    async fn drain_cursor(&self, cursor_id: u64, documents: &mut Vec<serde_json::Value>) -> CliResult<()> {
+        let payload = serde_json::to_vec(&serde_json::json!({ "cursor_id": cursor_id })).unwrap();
+        loop {
+            let response = self.send_request(0x85, &payload).await?;
+            let batch: serde_json::Value = serde_json::from_slice(&response)
+                .map_err(|e| CliError::Parse(format!("Invalid response: {}", e)))?;
+
+            if !batch["success"].as_bool().unwrap_or(false) {
+                let msg = batch["message"].as_str().unwrap_or("Unknown error");
+                return Err(CliError::Query(msg.to_string()));
+            }
+            if let Some(batch_docs) = batch["documents"].as_array() {
+                documents.extend(batch_docs.iter().cloned());
+            }
+            if batch["cursor_id"].as_u64().is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// # Brief
+    /// 以规范扩展 JSON 分批扫描当前数据库中的集合
+    ///
+    /// 发送 Find 请求(OpCode 0x24),服务器按主键顺序读取,只返回第一批和游标 ID,
+    /// 之后用 `next_page` 逐批读取,内存中只保留一批文档。
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `batch_size` - 每批文档数
+    ///
+    /// # Returns
+    /// 本批文档和游标 ID,没有后续批次时游标 ID 为 None
+    pub async fn scan_extended(&self, collection: &str, batch_size: u32) -> CliResult<(Vec<serde_json::Value>, Option<u64>)> {
+        let payload = serde_json::json!({
+            "database": "default",
+            "collection": collection,
+            "filter": null,
+            "projection": null,
+            "sort": null,
+            "limit": null,
+            "skip": null,
+            "batch_size": batch_size,
+            "extended_json": true,
+        });
+        let response = self.send_request(0x24, &serde_json::to_vec(&payload).unwrap()).await?;
+        Self::page(&response)
+    }
+
+    /// # Brief
+    /// 读取游标的下一批文档(GetMore,OpCode 0x85)
+    ///
+    /// # Arguments
+    /// * `cursor_id` - `scan_extended` 返回的游标 ID
+    /// * `batch_size` - 本批文档数
+    pub async fn next_page(&self, cursor_id: u64, batch_size: u32) -> CliResult<(Vec<serde_json::Value>, Option<u64>)> {
+        let payload = serde_json::json!({ "cursor_id": cursor_id, "batch_size": batch_size });
+        let response = self.send_request(0x85, &serde_json::to_vec(&payload).unwrap()).await?;
+        Self::page(&response)
+    }
+
+    fn page(response: &[u8]) -> CliResult<(Vec<serde_json::Value>, Option<u64>)> {
+        let result: serde_json::Value = serde_json::from_slice(response)
+            .map_err(|e| CliError::Parse(format!("Invalid response: {}", e)))?;
+        if !result["success"].as_bool().unwrap_or(false) {
+            let msg = result["message"].as_str().unwrap_or("Unknown error");
+            return Err(CliError::Query(msg.to_string()));
+        }
+        let documents = result["documents"].as_array().cloned().unwrap_or_default();
+        Ok((documents, result["cursor_id"].as_u64()))
+    }
+
+    /// # Brief
+    /// 批量插入规范扩展 JSON 文档
+    ///
+    /// 发送无序的 BulkWrite 请求(OpCode 0x27),单个文档失败(如 `_id` 重复)不影响其余文档。
+    ///
+    /// # Arguments
+    /// * `collection` - 当前数据库中的集合
+    /// * `documents` - 规范扩展 JSON 文档
+    ///
+    /// # Returns
+    /// 服务器返回的批量写入结果(`inserted`、`write_errors` 等)
+    pub async fn bulk_insert_extended(
+        &self,
+        collection: &str,
+        documents: Vec<serde_json::Value>,
+    ) -> CliResult<serde_json::Value> {
+        let operations: Vec<_> = documents
+            .into_iter()
+            .map(|document| serde_json::json!({ "insert": { "document": document } }))
+            .collect();
+        let mut payload = serde_json::json!({
+            "database": "default",
+            "collection": collection,
+            "operations": operations,
+            "ordered": false,
+            "extended_json": true,
+        });
+        self.attach_metadata(&mut payload);
+        let response = self.send_request(0x27, &serde_json::to_vec(&payload).unwrap()).await?;
+        let result: serde_json::Value = serde_json::from_slice(&response)
+            .map_err(|e| CliError::Parse(format!("Invalid response: {}", e)))?;
+        match result["documents"].get(0) {
+            Some(summary) => Ok(summary.clone()),
+            None => Err(CliError::Query(result["message"].as_str().unwrap_or("Unknown error").to_string())),
+        }
+    }
+
+    /// # Brief
+    /// 中断本连接上正在执行的请求
+    ///
+    /// 服务器支持多路复用时 KillOp 以并发标志在本连接上发送;
+    /// 否则服务器按顺序处理本连接的消息,改为通过新建的连接发送(见 `kill_op`)。
+    ///
+    /// # Arguments
+    /// * `config` - 客户端配置(不支持多路复用时用于建立新连接)
+    /// * `request_id` - 目标请求 ID
+    ///
+    /// # Returns
+    /// true 表示服务器找到并中断了该请求;服务器未启用认证(没有会话)时为 false
+    pub async fn kill_request(&self, config: &Config, request_id: u32) -> CliResult<bool> {
+        let Some(session_id) = self.session_id else {
+            return Ok(false);
+        };
+        if !self.multiplexing() {
+            return Self::kill_op(config, session_id, request_id).await;
+        }
+
+        let payload = serde_json::json!({
+            "session_id": session_id,
+            "request_id": request_id,
+        });
+        let response = self
+            .connection
+            .request(0x26, Self::next_request_id(), FLAG_CONCURRENT, &serde_json::to_vec(&payload).unwrap())
+            .await?;
+        let result: serde_json::Value = serde_json::from_slice(&response)
+            .map_err(|e| CliError::Parse(format!("Invalid response: {}", e)))?;
+        Ok(result["success"].as_bool().unwrap_or(false))
+    }
+
+    /// # Brief
+    /// 中断另一个连接上正在执行的请求
+    ///
+    /// 不支持多路复用的服务器按顺序处理同一连接上的消息,因此 KillOp (OpCode 0x26)
+    /// 需要通过新建的连接发送,使用相同的账号认证。
+    ///
+    /// # Arguments
+    /// * `config` - 客户端配置(用于建立新连接)
+    /// * `session_id` - 目标会话 ID
+    /// * `request_id` - 目标请求 ID
+    ///
+    /// # Returns
+    /// true 表示服务器找到并中断了该请求
+    pub async fn kill_op(config: &Config, session_id: u64, request_id: u32) -> CliResult<bool> {
+        let config = Config { database: None, ..config.clone() };
+        let client = Self::connect(&config).await?;
+
+        let payload = serde_json::json!({
+            "session_id": session_id,
+            "request_id": request_id,
+        });
+        let response = client.send_request(0x26, &serde_json::to_vec(&payload).unwrap()).await?;
+
+        let result: serde_json::Value = serde_json::from_slice(&response)
+            .map_err(|e| CliError::Parse(format!("Invalid response: {}", e)))?;
+        Ok(result["success"].as_bool().unwrap_or(false))
+    }
+
+    /// # Brief
+    /// 按顺序发送 MikuWire 协议请求并接收响应
+    ///
+    /// # Arguments
+    /// * `opcode` - 操作码
+    /// * `payload` - 请求负载
+    ///
+    /// # Returns
+    /// 响应 payload
+    async fn send_request(&self, opcode: u8, payload: &[u8]) -> CliResult<Vec<u8>> {
+        self.connection.request(opcode, Self::next_request_id(), 0, payload).await
+    }
+}
+
+/// # Brief
+/// 将失败的查询响应转换为错误
+///
+/// 按响应的 `error_kind` 区分语法错误、超时、中断和执行错误,
+/// 旧版本服务器不返回该字段时视为执行错误。
+///
+/// # Arguments
+/// * `response` - `success` 为 false 的查询响应
+fn query_error(response: &serde_json::Value) -> CliError {
+    let message = response["message"].as_str().unwrap_or("Unknown error").to_string();
+    match response["error_kind"].as_str() {
+        Some("parse") => CliError::Syntax(message),
+        Some("timeout") => CliError::Timeout(message),
+        Some("interrupted") => CliError::Interrupted,
+        _ => CliError::Query(message),
+    }
+}
+
+/// 服务器响应的操作码和负载
+type Frame = (u8, Vec<u8>);
+
+/// 等待响应的请求(请求 ID -> 响应发送端),连接关闭后为 None
+type PendingRequests = Arc<Mutex<Option<HashMap<u32, oneshot::Sender<CliResult<Frame>>>>>>;
+
+/// 多路复用的连接
+///
+/// 写入由异步互斥锁串行化;后台任务读取响应,按消息头的 `response_to`
+/// 交给等待该请求的调用方,同一连接上可以同时有多个请求在等待响应。
+struct Connection {
+    /// 写入端
+    writer: tokio::sync::Mutex<OwnedWriteHalf>,
+    /// 等待响应的请求
+    pending: PendingRequests,
+    /// 单条响应负载的最大字节数,握手后更新为服务器返回的值
+    max_message_size: Arc<AtomicUsize>,
+    /// 读取响应的后台任务
+    reader: JoinHandle<()>,
+}
+
+impl Connection {
+    /// # Brief
+    /// 拆分 TCP 连接并启动读取响应的后台任务
+    fn new(stream: TcpStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        let pending: PendingRequests = Arc::new(Mutex::new(Some(HashMap::new())));
+        let max_message_size = Arc::new(AtomicUsize::new(DEFAULT_MAX_MESSAGE_SIZE));
+        let reader = tokio::spawn(dispatch_responses(reader, pending.clone(), max_message_size.clone()));
+        Self {
+            writer: tokio::sync::Mutex::new(writer),
+            pending,
+            max_message_size,
+            reader,
+        }
+    }
+
+    /// # Brief
+    /// 设置单条响应负载的最大字节数
+    fn set_max_message_size(&self, size: usize) {
+        self.max_message_size.store(size, Ordering::Relaxed);
+    }
+
+    /// # Brief
+    /// 发送 MikuWire 协议请求并等待对应的响应
     ///
     /// 实现完整的请求-响应周期:
-    /// 1. 编码消息头(20 字节)和 payload
-    /// 2. 发送到服务器
-    /// 3. 读取响应头并验证
-    /// 4. 读取响应 payload
-    /// 5. 处理错误响应(OpCode 0x81)
+    /// 1. 登记请求 ID,由后台任务按 `response_to` 交回响应
+    /// 2. 编码消息头(20 字节)和 payload 并发送
+    /// 3. 等待响应,处理错误响应(OpCode 0x81)
     ///
     /// # Arguments
     /// * `opcode` - 操作码
+    /// * `request_id` - 请求 ID
+    /// * `flags` - 消息头标志位
     /// * `payload` - 请求负载
     ///
     /// # Returns
     /// 响应 payload
-    async fn send_request(&mut self, opcode: u8, payload: &[u8]) -> CliResult<Vec<u8>> {
-        // 生成唯一请求 ID
-        let request_id = REQUEST_ID.fetch_add(1, Ordering::SeqCst);
+    async fn request(&self, opcode: u8, request_id: u32, flags: u16, payload: &[u8]) -> CliResult<Vec<u8>> {
+        let (sender, receiver) = oneshot::channel();
+        match self.pending.lock().unwrap().as_mut() {
+            Some(pending) => {
+                pending.insert(request_id, sender);
+            }
+            None => return Err(CliError::Connection("Server closed the connection".into())),
+        }
 
         // 构造 MikuWire 消息头 (20 字节)
         let mut buf = BytesMut::with_capacity(20 + payload.len());
@@ -210,43 +716,26 @@ impl Client {
         buf.extend_from_slice(&[opcode]);                               // 操作码 (1 字节)
         buf.extend_from_slice(&request_id.to_le_bytes());               // 请求 ID (4 字节,小端)
         buf.extend_from_slice(&0u32.to_le_bytes());                     // response_to (4 字节)
-        buf.extend_from_slice(&0u16.to_le_bytes());                     // flags (2 字节)
+        buf.extend_from_slice(&flags.to_le_bytes());                    // flags (2 字节)
         buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());   // payload 长度 (4 字节)
         buf.extend_from_slice(payload);                                 // payload 数据
 
-        // 发送请求
-        self.stream.write_all(&buf).await.map_err(|e| {
-            CliError::Connection(format!("Failed to send request: {}. Connection may be closed.", e))
-        })?;
-        self.stream.flush().await.map_err(|e| {
-            CliError::Connection(format!("Failed to flush: {}. Connection may be closed.", e))
-        })?;
-
-        // 读取响应头 (20 字节)
-        let mut header_buf = [0u8; 20];
-        self.stream.read_exact(&mut header_buf).await.map_err(|e| {
-            CliError::Connection(format!("Failed to read response header: {}. Server may have closed the connection.", e))
-        })?;
-
-        // 验证魔术字节
-        if &header_buf[0..4] != MAGIC_BYTES {
-            return Err(CliError::Parse("Invalid response magic bytes. Protocol mismatch or corrupted data.".into()));
-        }
-
-        // 解析响应头字段
-        let response_opcode = header_buf[5];
-        let payload_len = u32::from_le_bytes([header_buf[16], header_buf[17], header_buf[18], header_buf[19]]) as usize;
-
-        // 检查 payload 大小限制 (防止内存耗尽)
-        if payload_len > 64 * 1024 * 1024 {
-            return Err(CliError::Parse(format!("Response payload too large: {} bytes", payload_len)));
+        // 发送请求,多个请求的消息不会交错
+        let sent = async {
+            let mut writer = self.writer.lock().await;
+            writer.write_all(&buf).await?;
+            writer.flush().await
+        };
+        if let Err(e) = sent.await {
+            if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+                pending.remove(&request_id);
+            }
+            return Err(CliError::Connection(format!("Failed to send request: {}. Connection may be closed.", e)));
         }
 
-        // 读取响应 payload
-        let mut payload_buf = vec![0u8; payload_len];
-        self.stream.read_exact(&mut payload_buf).await.map_err(|e| {
-            CliError::Connection(format!("Failed to read response payload: {}. Expected {} bytes.", e, payload_len))
-        })?;
+        let (response_opcode, payload_buf) = receiver
+            .await
+            .map_err(|_| CliError::Connection("Server closed the connection".into()))??;
 
         // 检查是否为错误响应 (OpCode 0x81)
         if response_opcode == 0x81 {
@@ -257,3 +746,106 @@ impl Client {
         Ok(payload_buf)
     }
 }
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// # Brief
+/// 持续读取响应并交给等待的请求
+///
+/// 连接出错或被关闭时以连接错误结束所有等待中的请求,之后的请求立即失败。
+/// 没有请求在等待的响应(调用方已放弃等待)直接丢弃。
+async fn dispatch_responses(mut reader: OwnedReadHalf, pending: PendingRequests, max_message_size: Arc<AtomicUsize>) {
+    let error = loop {
+        match read_response(&mut reader, max_message_size.load(Ordering::Relaxed)).await {
+            Ok((response_to, frame)) => {
+                let waiter = pending.lock().unwrap().as_mut().and_then(|pending| pending.remove(&response_to));
+                if let Some(waiter) = waiter {
+                    let _ = waiter.send(Ok(frame));
+                }
+            }
+            Err(e) => break e,
+        }
+    };
+    let waiters = pending.lock().unwrap().take().unwrap_or_default();
+    for waiter in waiters.into_values() {
+        let _ = waiter.send(Err(CliError::Connection(error.clone())));
+    }
+}
+
+/// # Brief
+/// 读取一条响应
+///
+/// # Returns
+/// 响应对应的请求 ID、操作码和负载;连接关闭或数据无效时返回错误说明
+async fn read_response(reader: &mut OwnedReadHalf, max_message_size: usize) -> Result<(u32, Frame), String> {
+    // 读取响应头 (20 字节)
+    let mut header_buf = [0u8; 20];
+    reader.read_exact(&mut header_buf).await.map_err(|e| {
+        format!("Failed to read response header: {}. Server may have closed the connection.", e)
+    })?;
+
+    // 验证魔术字节
+    if &header_buf[0..4] != MAGIC_BYTES {
+        return Err("Invalid response magic bytes. Protocol mismatch or corrupted data.".into());
+    }
+
+    // 解析响应头字段
+    let response_opcode = header_buf[5];
+    let response_to = u32::from_le_bytes([header_buf[10], header_buf[11], header_buf[12], header_buf[13]]);
+    let payload_len = u32::from_le_bytes([header_buf[16], header_buf[17], header_buf[18], header_buf[19]]) as usize;
+
+    // 检查 payload 大小限制 (防止内存耗尽)
+    if payload_len > max_message_size {
+        return Err(format!("Response payload too large: {} bytes", payload_len));
+    }
+
+    // 读取响应 payload
+    let mut payload_buf = vec![0u8; payload_len];
+    reader.read_exact(&mut payload_buf).await.map_err(|e| {
+        format!("Failed to read response payload: {}. Expected {} bytes.", e, payload_len)
+    })?;
+
+    Ok((response_to, (response_opcode, payload_buf)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exit_code;
+
+    fn failed(kind: Option<&str>, message: &str) -> serde_json::Value {
+        serde_json::json!({ "success": false, "message": message, "error_kind": kind })
+    }
+
+    #[test]
+    fn test_query_error_exit_codes() {
+        // 分类决定退出码,与消息文本无关
+        let parse = query_error(&failed(Some("parse"), "Invalid query request: missing field"));
+        assert!(matches!(parse, CliError::Syntax(_)));
+        assert_eq!(parse.exit_code(), exit_code::PARSE_ERROR);
+
+        let execution = query_error(&failed(Some("execution"), "Parse error in stored document"));
+        assert!(matches!(execution, CliError::Query(_)));
+        assert_eq!(execution.exit_code(), exit_code::EXECUTION_ERROR);
+
+        let permission = query_error(&failed(Some("permission"), "Permission denied"));
+        assert_eq!(permission.exit_code(), exit_code::EXECUTION_ERROR);
+
+        let timeout = query_error(&failed(Some("timeout"), "Query timed out after 10 ms"));
+        assert!(matches!(timeout, CliError::Timeout(_)));
+        assert_eq!(timeout.exit_code(), exit_code::EXECUTION_ERROR);
+
+        let interrupted = query_error(&failed(Some("interrupted"), "Operation killed"));
+        assert!(matches!(interrupted, CliError::Interrupted));
+        assert_eq!(interrupted.exit_code(), exit_code::FAILURE);
+
+        // 不返回分类的服务器按执行错误处理
+        let unknown = query_error(&serde_json::json!({ "success": false, "message": "Parse error: x" }));
+        assert!(matches!(unknown, CliError::Query(_)));
+        assert_eq!(unknown.exit_code(), exit_code::EXECUTION_ERROR);
+    }
+}
@@ -41,8 +41,9 @@ impl MqlCompleter {
                 // 聚合操作
                 "AGGREGATE", "MATCH", "GROUP", "SORT", "PROJECT", "LOOKUP",
                 "UNWIND", "AS", "ON", "UNIQUE", "TEXT", "TTL",
+                "ARCHIVE", "ADVISOR", "DRY", "RUN", "ALTER", "SCHEMA", "TOKENIZER", "STOPWORDS", "STEMMER", "MAX_KEY_SIZE", "OVERSIZED", "TRUNCATE", "REJECT", "BATCH", "SIZE", "BACKUP", "RESTORE", "WITHOUT", "SYSTEM", "METADATA", "ONLY", "ADMIN", "CLUSTER", "INIT", "JOIN", "LOG", "LEVEL", "TARGET", "RESET", "STATS", "EXPIRE", "AFTER", "FIELD", "OFF", "OLDER", "THAN", "SEQUENCE", "SEQUENCES", "NEXTVAL", "START", "INCREMENT", "RETURNING", "MODIFY", "OLD", "NEW", "ANALYZE", "COMPACT", "FUNCTION", "FUNCTIONS", "CALL", "WASM", "ADDTOSET", "PULLALL", "POP", "RENAME", "GRAPH", "CONNECT", "DEPTH", "FACET", "BUCKET", "BUCKETS", "BOUNDARIES", "AUTO", "SNAPSHOT", "ROLLUP", "ROLLUPS", "DATE_TRUNC", "MANIFEST", "ADD", "IMPORT", "EXPORT", "INFER", "DELIMITER",
                 // 字面量
-                "TRUE", "FALSE",
+                "TRUE", "FALSE", "ISODATE", "OBJECTID", "UUID",
             ],
             commands: vec![
                 "help", "exit", "quit", "clear", "status", "use", "lang", "language",
@@ -50,6 +51,11 @@ impl MqlCompleter {
         }
     }
 
+    /// MQL 关键字列表(大写)
+    pub fn keywords(&self) -> &[&'static str] {
+        &self.keywords
+    }
+
     /// # Brief
     /// 执行自动补全
     ///
@@ -150,6 +156,7 @@ impl MqlCompleter {
                 "INDEX".to_string(),
                 "STATUS".to_string(),
                 "USERS".to_string(),
+                "MANIFEST".to_string(),
             ]);
         }
 
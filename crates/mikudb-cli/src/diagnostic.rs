@@ -0,0 +1,87 @@
+//! 语法错误诊断模块
+//!
+//! 服务器返回的语法错误带有行号和列号,本模块据此在本地输出更友好的提示:
+//! - 打印出错的语句行,并在出错位置下方标出 `^`
+//! - 出错位置是与关键字拼写相近的单词时给出 "did you mean" 建议
+
+use crate::completer::MqlCompleter;
+use crate::i18n::t;
+use colored::Colorize;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+static POSITION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"at line (\d+), column (\d+)").unwrap());
+
+/// # Brief
+/// 根据语法错误信息渲染出错位置
+///
+/// # Arguments
+/// * `query` - 发送到服务器的语句
+/// * `message` - 服务器返回的错误信息
+///
+/// # Returns
+/// 语句行、`^` 标记和可选的关键字建议;错误信息不带位置时返回 None
+pub fn render_syntax_error(query: &str, message: &str) -> Option<String> {
+    let caps = POSITION_RE.captures(message)?;
+    let line_no: usize = caps[1].parse().ok()?;
+    let column: usize = caps[2].parse().ok()?;
+    let line = query.lines().nth(line_no.checked_sub(1)?).unwrap_or("");
+
+    // 列号按字符计数,标记位置按显示宽度计算
+    let before: String = line.chars().take(column.saturating_sub(1)).collect();
+    let rest: String = line.chars().skip(column.saturating_sub(1)).collect();
+    let mut out = format!("  {}\n  {}{}", line, " ".repeat(before.width()), "^".red().bold());
+
+    let word: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+    if let Some(keyword) = suggest_keyword(&word) {
+        out.push_str(&format!("\n{} {}?", t!("syntax.did_you_mean"), keyword.yellow()));
+    }
+    Some(out)
+}
+
+/// # Brief
+/// 查找与单词拼写最接近的关键字
+///
+/// 单词本身是关键字时不给建议;允许的编辑距离随单词长度增加,最多为 2。
+///
+/// # Arguments
+/// * `word` - 出错位置的单词
+fn suggest_keyword(word: &str) -> Option<&'static str> {
+    if word.len() < 2 {
+        return None;
+    }
+    let upper = word.to_uppercase();
+    let completer = MqlCompleter::new();
+    let keywords = completer.keywords();
+    if keywords.contains(&upper.as_str()) {
+        return None;
+    }
+    let max_distance = if upper.chars().count() <= 4 { 1 } else { 2 };
+    keywords
+        .iter()
+        .map(|k| (edit_distance(&upper, k), *k))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k)
+}
+
+/// 编辑距离(相邻字符交换计为一次编辑)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev2 = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        prev2 = std::mem::replace(&mut prev, cur);
+    }
+    prev[b.len()]
+}
@@ -0,0 +1,319 @@
+//! 数据比对模块
+//!
+//! 实现 `mikudb-cli diff` 子命令,用于迁移或复制事故后校验两端数据是否一致:
+//! - 比较两端集合的文档数
+//! - 比较按 `_id` 排序的 (`_id`, 文档哈希) 序列的校验和
+//! - 抽样列出缺失和内容不一致的文档
+//!
+//! 文档哈希基于字段按名称排序后的 JSON 文本,因此字段顺序不同不会被视为差异。
+
+use crate::client::Client;
+use crate::formatter::format_value;
+use crate::{CliError, CliResult, Config};
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// FNV-1a 64 位初始值
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+/// FNV-1a 64 位素数
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 服务器连接 URI
+///
+/// 格式: `mikudb://[user[:password]@]host[:port][/database]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerUri {
+    /// 主机名
+    pub host: String,
+    /// 端口
+    pub port: u16,
+    /// 用户名
+    pub user: Option<String>,
+    /// 密码
+    pub password: Option<String>,
+    /// 数据库
+    pub database: Option<String>,
+}
+
+impl ServerUri {
+    /// # Brief
+    /// 解析连接 URI
+    ///
+    /// # Arguments
+    /// * `uri` - 连接 URI,可省略 `mikudb://` 前缀
+    ///
+    /// # Returns
+    /// 解析结果,格式错误时返回 Other 错误
+    pub fn parse(uri: &str) -> CliResult<Self> {
+        let rest = uri.strip_prefix("mikudb://").unwrap_or(uri);
+        let (authority, database) = match rest.split_once('/') {
+            Some((authority, db)) if !db.is_empty() => (authority, Some(db.to_string())),
+            Some((authority, _)) => (authority, None),
+            None => (rest, None),
+        };
+
+        let (credentials, address) = match authority.rsplit_once('@') {
+            Some((credentials, address)) => (Some(credentials), address),
+            None => (None, authority),
+        };
+        let (user, password) = match credentials {
+            Some(credentials) => match credentials.split_once(':') {
+                Some((user, password)) => (Some(user.to_string()), Some(password.to_string())),
+                None => (Some(credentials.to_string()), None),
+            },
+            None => (None, None),
+        };
+
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| CliError::Other(format!("Invalid port in URI '{}'", uri)))?;
+                (host.to_string(), port)
+            }
+            None => (address.to_string(), 3939),
+        };
+        if host.is_empty() {
+            return Err(CliError::Other(format!("Missing host in URI '{}'", uri)));
+        }
+
+        Ok(Self { host, port, user, password, database })
+    }
+
+    /// # Brief
+    /// 生成连接配置
+    ///
+    /// URI 中未给出的用户名、密码和数据库使用 `fallback` 中的值。
+    pub fn to_config(&self, fallback: &Config) -> Config {
+        Config {
+            host: self.host.clone(),
+            port: self.port,
+            user: self.user.clone().unwrap_or_else(|| fallback.user.clone()),
+            password: self.password.clone().unwrap_or_else(|| fallback.password.clone()),
+            database: self.database.clone().or_else(|| fallback.database.clone()),
+            ..fallback.clone()
+        }
+    }
+}
+
+/// 集合摘要: 每个 `_id` 对应的文档哈希
+pub struct CollectionDigest {
+    hashes: BTreeMap<String, u64>,
+}
+
+impl CollectionDigest {
+    /// # Brief
+    /// 从查询返回的文档构建摘要
+    pub fn from_documents(documents: &[Value]) -> Self {
+        let hashes = documents
+            .iter()
+            .map(|doc| (document_key(doc), fnv1a(doc.to_string().as_bytes())))
+            .collect();
+        Self { hashes }
+    }
+
+    /// # Brief
+    /// 文档数
+    pub fn count(&self) -> u64 {
+        self.hashes.len() as u64
+    }
+
+    /// # Brief
+    /// 按 `_id` 排序的 (`_id`, 哈希) 序列的校验和
+    pub fn checksum(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        for (id, doc_hash) in &self.hashes {
+            hash = fnv1a_update(hash, id.as_bytes());
+            hash = fnv1a_update(hash, &doc_hash.to_le_bytes());
+        }
+        hash
+    }
+}
+
+/// 比对结果
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffReport {
+    /// 源端集合
+    pub source_collection: String,
+    /// 目标端集合
+    pub target_collection: String,
+    /// 源端文档数
+    pub source_count: u64,
+    /// 目标端文档数
+    pub target_count: u64,
+    /// 源端校验和(十六进制)
+    pub source_checksum: String,
+    /// 目标端校验和(十六进制)
+    pub target_checksum: String,
+    /// 只存在于源端的文档数
+    pub missing_in_target: u64,
+    /// 只存在于目标端的文档数
+    pub missing_in_source: u64,
+    /// 两端都存在但内容不同的文档数
+    pub mismatched: u64,
+    /// 只存在于源端的文档 `_id` 样例
+    pub missing_in_target_sample: Vec<String>,
+    /// 只存在于目标端的文档 `_id` 样例
+    pub missing_in_source_sample: Vec<String>,
+    /// 内容不同的文档 `_id` 样例
+    pub mismatched_sample: Vec<String>,
+}
+
+impl DiffReport {
+    /// # Brief
+    /// 比较两端摘要
+    ///
+    /// # Arguments
+    /// * `source` / `target` - 两端集合名称及摘要
+    /// * `sample` - 每类差异最多列出的 `_id` 数
+    pub fn compare(
+        source: (&str, &CollectionDigest),
+        target: (&str, &CollectionDigest),
+        sample: usize,
+    ) -> Self {
+        let (source_collection, source) = source;
+        let (target_collection, target) = target;
+
+        let mut report = Self {
+            source_collection: source_collection.to_string(),
+            target_collection: target_collection.to_string(),
+            source_count: source.count(),
+            target_count: target.count(),
+            source_checksum: format!("{:016x}", source.checksum()),
+            target_checksum: format!("{:016x}", target.checksum()),
+            missing_in_target: 0,
+            missing_in_source: 0,
+            mismatched: 0,
+            missing_in_target_sample: Vec::new(),
+            missing_in_source_sample: Vec::new(),
+            mismatched_sample: Vec::new(),
+        };
+
+        for (id, hash) in &source.hashes {
+            match target.hashes.get(id) {
+                None => {
+                    report.missing_in_target += 1;
+                    push_sample(&mut report.missing_in_target_sample, id, sample);
+                }
+                Some(other) if other != hash => {
+                    report.mismatched += 1;
+                    push_sample(&mut report.mismatched_sample, id, sample);
+                }
+                Some(_) => {}
+            }
+        }
+        for id in target.hashes.keys() {
+            if !source.hashes.contains_key(id) {
+                report.missing_in_source += 1;
+                push_sample(&mut report.missing_in_source_sample, id, sample);
+            }
+        }
+
+        report
+    }
+
+    /// # Brief
+    /// 两端是否完全一致
+    pub fn is_identical(&self) -> bool {
+        self.source_count == self.target_count && self.source_checksum == self.target_checksum
+    }
+
+    /// # Brief
+    /// 以文本形式输出比对结果
+    pub fn print(&self) {
+        let status = if self.is_identical() {
+            "IDENTICAL".green().bold()
+        } else {
+            "DIFFERENT".red().bold()
+        };
+        println!("{} {} -> {}", status, self.source_collection, self.target_collection);
+        println!("  documents: {} / {}", self.source_count, self.target_count);
+        println!("  checksum:  {} / {}", self.source_checksum, self.target_checksum);
+
+        if self.is_identical() {
+            return;
+        }
+        print_section("missing in target", self.missing_in_target, &self.missing_in_target_sample);
+        print_section("missing in source", self.missing_in_source, &self.missing_in_source_sample);
+        print_section("content mismatch", self.mismatched, &self.mismatched_sample);
+    }
+}
+
+/// # Brief
+/// 执行 diff 子命令
+///
+/// # Arguments
+/// * `source` / `target` - 两端连接配置
+/// * `collection` - 源端集合名称
+/// * `target_collection` - 目标端集合名称
+/// * `sample` - 每类差异最多列出的 `_id` 数
+///
+/// # Returns
+/// 比对结果
+pub async fn run(
+    source: &Config,
+    target: &Config,
+    collection: &str,
+    target_collection: &str,
+    sample: usize,
+) -> CliResult<DiffReport> {
+    let source_docs = fetch_all(source, collection).await?;
+    let target_docs = fetch_all(target, target_collection).await?;
+
+    let source_digest = CollectionDigest::from_documents(&source_docs);
+    let target_digest = CollectionDigest::from_documents(&target_docs);
+
+    Ok(DiffReport::compare(
+        (collection, &source_digest),
+        (target_collection, &target_digest),
+        sample,
+    ))
+}
+
+async fn fetch_all(config: &Config, collection: &str) -> CliResult<Vec<Value>> {
+    let client = Client::connect(config).await?;
+    let result = client.query(&format!("FIND {}", collection)).await?;
+    Ok(result.documents)
+}
+
+/// # Brief
+/// 获取文档的比对键(`_id` 的文本形式,ObjectId 显示为十六进制)
+fn document_key(doc: &Value) -> String {
+    match doc.get("_id") {
+        Some(id) => format_value(id),
+        None => format!("<no _id:{:016x}>", fnv1a(doc.to_string().as_bytes())),
+    }
+}
+
+fn push_sample(sample: &mut Vec<String>, id: &str, limit: usize) {
+    if sample.len() < limit {
+        sample.push(id.to_string());
+    }
+}
+
+fn print_section(label: &str, count: u64, sample: &[String]) {
+    if count == 0 {
+        return;
+    }
+    println!("  {}: {}", label.yellow(), count);
+    for id in sample {
+        println!("    {}", id);
+    }
+    if count > sample.len() as u64 {
+        println!("    ... {} more", count - sample.len() as u64);
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    fnv1a_update(FNV_OFFSET, bytes)
+}
+
+fn fnv1a_update(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
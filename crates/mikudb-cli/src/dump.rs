@@ -0,0 +1,527 @@
+//! 导出与恢复模块
+//!
+//! 实现 `mikudb-cli dump` 和 `mikudb-cli restore` 子命令,类似 mongodump/mongorestore:
+//! - 通过游标协议按批读取集合,内存中只保留一批文档,大集合也不会耗尽内存
+//! - 文档以 BOML 归档(默认)或规范扩展 JSON 行保存,ObjectId、日期等类型不丢失
+//! - 每个数据库的集合选项和索引保存为清单,用户及其角色单独保存,恢复时按清单重建
+//! - 恢复以无序批量写入插入文档,`_id` 已存在等失败只跳过该文档
+//!
+//! 用户的密码不会导出;恢复时目标服务器上不存在的用户需要先在 `users.yaml`
+//! 中补充 `password` 或 `password_env`,否则只给出警告。
+//!
+//! # 目录结构
+//!
+//! ```text
+//! out/
+//!   users.yaml          用户及其角色(清单格式)
+//!   shop/
+//!     manifest.yaml     集合选项和索引(清单格式)
+//!     orders.boml       每个文档为 4 字节小端长度 + BOML 编码
+//!     customers.json    --format json 时每行一个扩展 JSON 文档
+//! ```
+
+use crate::client::Client;
+use crate::manifest::{self, DatabaseSpec, Manifest, Plan};
+use crate::{CliError, CliResult, Config};
+use colored::Colorize;
+use mikudb_boml::codec;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 每批读取或写入的默认文档数
+pub const DEFAULT_BATCH_SIZE: u32 = 1000;
+
+/// 每个数据库目录中的清单文件
+const MANIFEST_FILE: &str = "manifest.yaml";
+/// 用户清单文件
+const USERS_FILE: &str = "users.yaml";
+/// 每个集合最多列出的写入失败
+const MAX_REPORTED_ERRORS: usize = 5;
+
+/// 集合数据文件的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// 长度前缀的 BOML 文档(`.boml`)
+    Boml,
+    /// 每行一个规范扩展 JSON 文档(`.json`)
+    Json,
+}
+
+impl ArchiveFormat {
+    /// # Brief
+    /// 解析 `--format` 参数
+    pub fn parse(name: &str) -> CliResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "boml" => Ok(Self::Boml),
+            "json" => Ok(Self::Json),
+            other => Err(CliError::Other(format!("Unknown dump format '{}', expected boml or json", other))),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Boml => "boml",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// 写入集合数据文件
+struct ArchiveWriter {
+    format: ArchiveFormat,
+    out: BufWriter<File>,
+}
+
+impl ArchiveWriter {
+    fn create(path: &Path, format: ArchiveFormat) -> CliResult<Self> {
+        Ok(Self { format, out: BufWriter::new(File::create(path)?) })
+    }
+
+    fn write(&mut self, document: &serde_json::Value) -> CliResult<()> {
+        match self.format {
+            ArchiveFormat::Boml => {
+                let value = mikudb_boml::from_extended_json(document)
+                    .map_err(|e| CliError::Parse(format!("Invalid document from server: {}", e)))?;
+                let bytes = codec::encode_document(&value)
+                    .map_err(|e| CliError::Other(format!("Failed to encode document: {}", e)))?;
+                self.out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+                self.out.write_all(&bytes)?;
+            }
+            ArchiveFormat::Json => {
+                serde_json::to_writer(&mut self.out, document).map_err(|e| CliError::Other(e.to_string()))?;
+                self.out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> CliResult<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// 读取集合数据文件
+struct ArchiveReader {
+    format: ArchiveFormat,
+    input: BufReader<File>,
+    path: PathBuf,
+}
+
+impl ArchiveReader {
+    fn open(path: &Path, format: ArchiveFormat) -> CliResult<Self> {
+        Ok(Self { format, input: BufReader::new(File::open(path)?), path: path.to_path_buf() })
+    }
+
+    /// 读取下一个文档,文件结束时返回 None
+    fn next(&mut self) -> CliResult<Option<serde_json::Value>> {
+        match self.format {
+            ArchiveFormat::Boml => {
+                let mut len = [0u8; 4];
+                match self.input.read_exact(&mut len) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                }
+                let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+                self.input.read_exact(&mut bytes)?;
+                let value = codec::decode_document(&bytes).map_err(|e| self.invalid(e))?;
+                Ok(Some(mikudb_boml::to_extended_json(&value)))
+            }
+            ArchiveFormat::Json => loop {
+                let mut line = String::new();
+                if self.input.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                if !line.trim().is_empty() {
+                    return serde_json::from_str(&line).map(Some).map_err(|e| self.invalid(e));
+                }
+            },
+        }
+    }
+
+    fn invalid(&self, error: impl std::fmt::Display) -> CliError {
+        CliError::Parse(format!("Invalid archive {}: {}", self.path.display(), error))
+    }
+}
+
+/// 导出结果
+#[derive(Debug, Clone)]
+pub struct DumpReport {
+    /// 每个集合(`db.collection`)导出的文档数
+    pub collections: Vec<(String, u64)>,
+    /// 导出的用户数
+    pub users: usize,
+    /// 总耗时
+    pub elapsed: Duration,
+}
+
+impl DumpReport {
+    /// # Brief
+    /// 打印每个集合的文档数和总计
+    pub fn print(&self) {
+        for (namespace, count) in &self.collections {
+            println!("{} {} ({} documents)", "✓".green(), namespace, count);
+        }
+        let total: u64 = self.collections.iter().map(|(_, count)| count).sum();
+        println!(
+            "{} {} documents from {} collection(s) and {} user(s) in {:.2}s",
+            "DUMPED".green().bold(),
+            total,
+            self.collections.len(),
+            self.users,
+            self.elapsed.as_secs_f64()
+        );
+    }
+}
+
+/// 单个集合的恢复结果
+#[derive(Debug, Clone)]
+pub struct RestoredCollection {
+    /// `db.collection`
+    pub namespace: String,
+    /// 插入的文档数
+    pub inserted: u64,
+    /// 插入失败的文档数
+    pub failed: u64,
+    /// 前几条失败原因
+    pub errors: Vec<String>,
+}
+
+/// 恢复结果
+#[derive(Debug, Clone)]
+pub struct RestoreReport {
+    /// 每个集合的恢复结果
+    pub collections: Vec<RestoredCollection>,
+    /// 无法恢复的清单差异(如缺少密码的用户)
+    pub warnings: Vec<String>,
+    /// 总耗时
+    pub elapsed: Duration,
+}
+
+impl RestoreReport {
+    /// # Brief
+    /// 插入失败的文档总数
+    pub fn failed(&self) -> u64 {
+        self.collections.iter().map(|collection| collection.failed).sum()
+    }
+
+    /// # Brief
+    /// 打印每个集合的插入数、失败原因和警告
+    pub fn print(&self) {
+        for collection in &self.collections {
+            let mark = if collection.failed == 0 { "✓".green() } else { "!".yellow() };
+            println!(
+                "{} {} ({} inserted, {} failed)",
+                mark, collection.namespace, collection.inserted, collection.failed
+            );
+            for error in &collection.errors {
+                println!("    {}", error);
+            }
+        }
+        for warning in &self.warnings {
+            eprintln!("warning: {}", warning);
+        }
+        let inserted: u64 = self.collections.iter().map(|collection| collection.inserted).sum();
+        println!(
+            "{} {} documents into {} collection(s), {} failed, in {:.2}s",
+            "RESTORED".green().bold(),
+            inserted,
+            self.collections.len(),
+            self.failed(),
+            self.elapsed.as_secs_f64()
+        );
+    }
+}
+
+/// # Brief
+/// 执行 dump 子命令
+///
+/// # Arguments
+/// * `config` - 连接配置
+/// * `database` - 只导出该数据库,None 表示全部
+/// * `out` - 输出目录,不存在时创建
+/// * `format` - 集合数据文件的格式
+/// * `batch_size` - 每批读取的文档数
+///
+/// # Returns
+/// 导出结果;指定的数据库不存在时返回错误
+pub async fn dump(
+    config: &Config,
+    database: Option<&str>,
+    out: &Path,
+    format: ArchiveFormat,
+    batch_size: u32,
+) -> CliResult<DumpReport> {
+    let started = Instant::now();
+    let client = Client::connect(config).await?;
+    let live = Manifest::fetch(&client).await?;
+    let databases: Vec<&DatabaseSpec> = match database {
+        Some(name) => match live.databases.iter().find(|spec| spec.name == name) {
+            Some(spec) => vec![spec],
+            None => return Err(CliError::Query(format!("Database '{}' not found", name))),
+        },
+        None => live.databases.iter().collect(),
+    };
+
+    let mut report = DumpReport { collections: Vec::new(), users: live.users.len(), elapsed: Duration::ZERO };
+    std::fs::create_dir_all(out)?;
+    for spec in databases {
+        let dir = out.join(&spec.name);
+        std::fs::create_dir_all(&dir)?;
+        let manifest = Manifest { databases: vec![spec.clone()], users: Vec::new() };
+        std::fs::write(dir.join(MANIFEST_FILE), manifest.to_yaml()?)?;
+
+        client.use_database(&spec.name).await?;
+        for collection in &spec.collections {
+            let path = dir.join(format!("{}.{}", collection.name, format.extension()));
+            let count = dump_collection(&client, &collection.name, &path, format, batch_size).await?;
+            report.collections.push((format!("{}.{}", spec.name, collection.name), count));
+        }
+    }
+    let users = Manifest { databases: Vec::new(), users: live.users };
+    std::fs::write(out.join(USERS_FILE), users.to_yaml()?)?;
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
+
+async fn dump_collection(
+    client: &Client,
+    collection: &str,
+    path: &Path,
+    format: ArchiveFormat,
+    batch_size: u32,
+) -> CliResult<u64> {
+    let mut writer = ArchiveWriter::create(path, format)?;
+    let mut count = 0u64;
+    let (mut documents, mut cursor) = client.scan_extended(collection, batch_size).await?;
+    loop {
+        for document in &documents {
+            writer.write(document)?;
+        }
+        count += documents.len() as u64;
+        match cursor {
+            Some(id) => (documents, cursor) = client.next_page(id, batch_size).await?,
+            None => break,
+        }
+    }
+    writer.finish()?;
+    Ok(count)
+}
+
+/// # Brief
+/// 执行 restore 子命令
+///
+/// 按每个数据库的清单创建缺少的数据库、集合和索引,再批量插入集合数据,
+/// 最后按 `users.yaml` 创建用户或调整角色。索引在插入前创建,唯一索引冲突的文档会被跳过。
+///
+/// # Arguments
+/// * `config` - 连接配置
+/// * `dir` - dump 的输出目录
+/// * `database` - 只恢复该数据库(同时跳过用户),None 表示全部
+/// * `drop` - 插入前删除目标服务器上同名的集合
+/// * `batch_size` - 每批写入的文档数
+pub async fn restore(
+    config: &Config,
+    dir: &Path,
+    database: Option<&str>,
+    drop: bool,
+    batch_size: u32,
+) -> CliResult<RestoreReport> {
+    let started = Instant::now();
+    let mut databases = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.join(MANIFEST_FILE).is_file() {
+            databases.push(path);
+        }
+    }
+    databases.sort();
+
+    let client = Client::connect(config).await?;
+    let mut report = RestoreReport { collections: Vec::new(), warnings: Vec::new(), elapsed: Duration::ZERO };
+    let mut found = false;
+    for path in databases {
+        let desired = Manifest::load(&path.join(MANIFEST_FILE))?;
+        let Some(spec) = desired.databases.first() else { continue };
+        if database.is_some_and(|name| name != spec.name) {
+            continue;
+        }
+        found = true;
+
+        if drop {
+            let live = Manifest::fetch(&client).await?;
+            if let Some(existing) = live.databases.iter().find(|existing| existing.name == spec.name) {
+                client.use_database(&spec.name).await?;
+                for collection in &spec.collections {
+                    if existing.collections.iter().any(|c| c.name == collection.name) {
+                        client.query(&format!("DROP COLLECTION `{}`", collection.name)).await?;
+                    }
+                }
+            }
+        }
+        apply_manifest(&client, config, &desired, &mut report).await?;
+
+        client.use_database(&spec.name).await?;
+        for collection in &spec.collections {
+            let Some((file, format)) = [ArchiveFormat::Boml, ArchiveFormat::Json].iter().find_map(|format| {
+                let file = path.join(format!("{}.{}", collection.name, format.extension()));
+                file.is_file().then_some((file, *format))
+            }) else {
+                continue;
+            };
+            let restored = restore_collection(&client, &spec.name, &collection.name, &file, format, batch_size).await?;
+            report.collections.push(restored);
+        }
+    }
+    if let Some(name) = database.filter(|_| !found) {
+        return Err(CliError::Other(format!("Database '{}' not found in {}", name, dir.display())));
+    }
+
+    let users = dir.join(USERS_FILE);
+    if database.is_none() && users.is_file() {
+        apply_manifest(&client, config, &Manifest::load(&users)?, &mut report).await?;
+    }
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
+
+/// 创建清单中缺少的对象,不删除目标服务器上已有的对象
+async fn apply_manifest(
+    client: &Client,
+    config: &Config,
+    desired: &Manifest,
+    report: &mut RestoreReport,
+) -> CliResult<()> {
+    let live = Manifest::fetch(client).await?;
+    let plan = Plan::compute(desired, &live, false);
+    report.warnings.extend(plan.warnings.iter().cloned());
+    if !plan.is_empty() {
+        manifest::apply(config, &plan).await?;
+    }
+    Ok(())
+}
+
+async fn restore_collection(
+    client: &Client,
+    database: &str,
+    collection: &str,
+    path: &Path,
+    format: ArchiveFormat,
+    batch_size: u32,
+) -> CliResult<RestoredCollection> {
+    let mut restored = RestoredCollection {
+        namespace: format!("{}.{}", database, collection),
+        inserted: 0,
+        failed: 0,
+        errors: Vec::new(),
+    };
+    let mut reader = ArchiveReader::open(path, format)?;
+    let mut batch = Vec::with_capacity(batch_size as usize);
+    let mut finished = false;
+    while !finished {
+        match reader.next()? {
+            Some(document) => batch.push(document),
+            None => finished = true,
+        }
+        if batch.is_empty() || (!finished && batch.len() < batch_size as usize) {
+            continue;
+        }
+
+        let summary = client.bulk_insert_extended(collection, std::mem::take(&mut batch)).await?;
+        restored.inserted += summary["inserted"].as_u64().unwrap_or(0);
+        for error in summary["write_errors"].as_array().into_iter().flatten() {
+            restored.failed += 1;
+            if restored.errors.len() < MAX_REPORTED_ERRORS {
+                restored.errors.push(error["message"].as_str().unwrap_or("unknown error").to_string());
+            }
+        }
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mikudb_boml::BomlValue;
+    use serde_json::json;
+
+    /// 服务器以规范扩展 JSON 返回的文档,覆盖 JSON 无法直接表示的类型
+    fn documents() -> Vec<serde_json::Value> {
+        let documents = [
+            json!({
+                "_id": {"$oid": "65f0c0ffee0000000000a001"},
+                "created": {"$date": {"$numberLong": "1717200000000"}},
+                // BOML 把 i32 范围内的 Int64 按 Int32 存储,服务器返回的 $numberLong 都超出该范围
+                "count": {"$numberLong": "39390000000"},
+                "age": {"$numberInt": "16"},
+                "score": {"$numberDouble": "1.5"},
+                "note": "line one\nline two, \"quoted\"",
+                "tags": ["vocaloid", {"$numberInt": "1"}, null],
+                "address": {"city": "Sapporo", "zip": {"$numberInt": "60"}},
+                "blob": mikudb_boml::to_extended_json(&BomlValue::Binary(vec![0, 39, 255])),
+            }),
+            json!({"_id": {"$oid": "65f0c0ffee0000000000a002"}, "empty": {}, "list": []}),
+        ];
+        // 与服务器输出一致的规范形式
+        documents
+            .iter()
+            .map(|document| mikudb_boml::to_extended_json(&mikudb_boml::from_extended_json(document).unwrap()))
+            .collect()
+    }
+
+    fn round_trip(format: ArchiveFormat) -> Vec<serde_json::Value> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(format!("users.{}", format.extension()));
+
+        let mut writer = ArchiveWriter::create(&path, format).unwrap();
+        for document in documents() {
+            writer.write(&document).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut reader = ArchiveReader::open(&path, format).unwrap();
+        let mut restored = Vec::new();
+        while let Some(document) = reader.next().unwrap() {
+            restored.push(document);
+        }
+        restored
+    }
+
+    #[test]
+    fn test_boml_archive_round_trip() {
+        assert_eq!(round_trip(ArchiveFormat::Boml), documents());
+    }
+
+    #[test]
+    fn test_json_archive_round_trip() {
+        assert_eq!(round_trip(ArchiveFormat::Json), documents());
+    }
+
+    #[test]
+    fn test_truncated_archive_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.boml");
+        let mut writer = ArchiveWriter::create(&path, ArchiveFormat::Boml).unwrap();
+        writer.write(&documents()[0]).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(ArchiveReader::open(&path, ArchiveFormat::Boml).unwrap().next().is_err());
+
+        std::fs::write(&path, "{\"_id\": 1}\nnot json\n").unwrap();
+        let mut reader = ArchiveReader::open(&path, ArchiveFormat::Json).unwrap();
+        assert_eq!(reader.next().unwrap(), Some(json!({"_id": 1})));
+        assert!(matches!(reader.next(), Err(CliError::Parse(_))));
+    }
+
+    #[test]
+    fn test_archive_format_parse() {
+        assert_eq!(ArchiveFormat::parse("BOML").unwrap(), ArchiveFormat::Boml);
+        assert_eq!(ArchiveFormat::parse("json").unwrap(), ArchiveFormat::Json);
+        assert!(ArchiveFormat::parse("csv").is_err());
+    }
+}
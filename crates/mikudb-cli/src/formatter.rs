@@ -11,6 +11,12 @@
 //! - ObjectId 自动识别并格式化为十六进制
 //! - 单个文档且字段 >8 时自动切换到 Line 格式
 //! - ANSI 颜色支持
+//!
+//! 宽文档处理:
+//! - 列选择(`\fields name,age`),支持 `a.b` 形式的嵌套路径,按给定顺序输出
+//! - 表格中嵌套值摘要为 `{...}` / `[n items]`
+//! - 表格单元格超过最大列宽时截断并以 `…` 结尾
+//! - 展开模式(`\expand`)纵向显示完整值
 
 use crate::i18n::t;
 use colored::Colorize;
@@ -27,6 +33,26 @@ fn visible_width(s: &str) -> usize {
     UnicodeWidthStr::width(strip_ansi(s).as_str())
 }
 
+/// 按可见宽度截断字符串,超出时以省略号结尾;`max` 为 0 表示不限制
+fn truncate_cell(s: &str, max: usize) -> String {
+    if max == 0 || visible_width(s) <= max {
+        return s.to_string();
+    }
+    let plain = strip_ansi(s);
+    let mut out = String::new();
+    let mut width = 0;
+    for c in plain.chars() {
+        let w = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
+        if width + w + 1 > max {
+            break;
+        }
+        width += w;
+        out.push(c);
+    }
+    out.push('…');
+    out
+}
+
 fn pad_cell(s: &str, width: usize) -> String {
     // MySQL 风格一般不允许单元格出现换行/制表符影响布局
     let s = s.replace('\t', "    ").replace('\n', " ");
@@ -35,6 +61,17 @@ fn pad_cell(s: &str, width: usize) -> String {
     format!(" {}{} ", s, " ".repeat(pad))
 }
 
+/// 向输出缓冲追加一行
+macro_rules! outln {
+    ($out:expr) => {
+        $out.push('\n')
+    };
+    ($out:expr, $($arg:tt)*) => {{
+        $out.push_str(&format!($($arg)*));
+        $out.push('\n');
+    }};
+}
+
 /// 格式化器
 ///
 /// 根据配置格式化查询结果并输出。
@@ -43,8 +80,17 @@ pub struct Formatter {
     format: OutputFormat,
     /// 是否启用颜色
     color: bool,
+    /// 选择输出的列,None 表示全部
+    fields: Option<Vec<String>>,
+    /// 表格单元格最大宽度,0 表示不限制
+    max_width: usize,
+    /// 展开模式: 纵向显示完整值
+    expanded: bool,
 }
 
+/// 表格单元格默认最大宽度
+pub const DEFAULT_MAX_COLUMN_WIDTH: usize = 40;
+
 /// 输出格式枚举
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
@@ -60,6 +106,31 @@ pub enum OutputFormat {
     Line,
 }
 
+impl OutputFormat {
+    /// 从格式名称解析("table", "json", "json-pretty", "csv", "line")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "table" => Some(OutputFormat::Table),
+            "json" => Some(OutputFormat::Json),
+            "jsonpretty" | "json-pretty" => Some(OutputFormat::JsonPretty),
+            "csv" => Some(OutputFormat::Csv),
+            "line" => Some(OutputFormat::Line),
+            _ => None,
+        }
+    }
+
+    /// 格式名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Json => "json",
+            OutputFormat::JsonPretty => "json-pretty",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Line => "line",
+        }
+    }
+}
+
 impl Formatter {
     /// # Brief
     /// 创建格式化器
@@ -68,21 +139,96 @@ impl Formatter {
     /// * `format` - 格式名称("table", "json", "json-pretty", "csv", "line")
     /// * `color` - 是否启用 ANSI 颜色
     pub fn new(format: &str, color: bool) -> Self {
-        let format = match format.to_lowercase().as_str() {
-            "json" => OutputFormat::Json,
-            "jsonpretty" | "json-pretty" => OutputFormat::JsonPretty,
-            "csv" => OutputFormat::Csv,
-            "line" => OutputFormat::Line,
-            _ => OutputFormat::Table,  // 默认为表格格式
-        };
+        // 默认为表格格式
+        let format = OutputFormat::from_name(format).unwrap_or(OutputFormat::Table);
 
-        Self { format, color }
+        Self {
+            format,
+            color,
+            fields: None,
+            max_width: DEFAULT_MAX_COLUMN_WIDTH,
+            expanded: false,
+        }
+    }
+
+    /// # Brief
+    /// 切换输出格式
+    ///
+    /// # Arguments
+    /// * `format` - 输出格式
+    pub fn set_format(&mut self, format: OutputFormat) {
+        self.format = format;
+    }
+
+    /// 当前输出格式
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    /// # Brief
+    /// 设置输出的列
+    ///
+    /// # Arguments
+    /// * `fields` - 列名(支持 `a.b` 嵌套路径),None 或空列表表示全部
+    pub fn set_fields(&mut self, fields: Option<Vec<String>>) {
+        self.fields = fields.filter(|f| !f.is_empty());
+    }
+
+    /// 当前选择的列
+    pub fn fields(&self) -> Option<&[String]> {
+        self.fields.as_deref()
+    }
+
+    /// # Brief
+    /// 设置表格单元格最大宽度
+    ///
+    /// # Arguments
+    /// * `width` - 最大可见宽度,0 表示不限制
+    pub fn set_max_width(&mut self, width: usize) {
+        self.max_width = width;
+    }
+
+    /// 当前表格单元格最大宽度
+    pub fn max_width(&self) -> usize {
+        self.max_width
+    }
+
+    /// # Brief
+    /// 设置展开模式
+    ///
+    /// # Arguments
+    /// * `expanded` - 为 true 时表格结果纵向显示完整值
+    pub fn set_expanded(&mut self, expanded: bool) {
+        self.expanded = expanded;
+    }
+
+    /// 是否处于展开模式
+    pub fn expanded(&self) -> bool {
+        self.expanded
+    }
+
+    /// 获取要输出的列: 已选择时按选择顺序,否则合并所有文档的字段
+    fn columns(&self, documents: &[Value]) -> Vec<String> {
+        if let Some(fields) = &self.fields {
+            return fields.clone();
+        }
+        let mut columns: Vec<String> = Vec::new();
+        for doc in documents {
+            if let Value::Object(map) = doc {
+                for key in map.keys() {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+        }
+        columns
     }
 
     /// # Brief
     /// 打印查询结果
     ///
-    /// 根据配置的格式和颜色设置输出结果。
+    /// 根据配置的格式和颜色设置输出结果。错误信息输出到 stderr。
     ///
     /// # Arguments
     /// * `result` - 查询结果
@@ -95,79 +241,85 @@ impl Formatter {
             return;
         }
 
+        print!("{}", self.render(result));
+    }
+
+    /// # Brief
+    /// 将查询结果渲染为字符串
+    ///
+    /// 与 `print` 输出相同的内容,供分页器等需要完整输出的场景使用。
+    /// 失败的结果渲染为空字符串。
+    ///
+    /// # Arguments
+    /// * `result` - 查询结果
+    pub fn render(&self, result: &QueryResult) -> String {
+        let mut out = String::new();
+        if !result.success {
+            return out;
+        }
+
         // 处理空结果集
         if result.documents.is_empty() {
             if let Some(msg) = &result.message {
-                println!("{}", msg);
+                outln!(out, "{}", msg);
             } else {
-                println!("{}", t!("result.no_documents").dimmed());
+                outln!(out, "{}", t!("result.no_documents").dimmed());
             }
-            self.print_affected(result.affected);
-            return;
+            self.write_affected(&mut out, result.affected);
+            self.write_stats(&mut out, result.stats.as_ref());
+            return out;
         }
 
-        // 自动切换到 Line 格式(单个文档且字段 >8)
+        // 展开模式或单个文档且字段 >8 时自动切换到 Line 格式
         let use_line_format = if let OutputFormat::Table = self.format {
-            if result.documents.len() == 1 {
-                if let Value::Object(map) = &result.documents[0] {
-                    map.len() > 8  // 超过 8 个字段时使用纵向显示
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
+            self.expanded
+                || (result.documents.len() == 1 && self.columns(&result.documents).len() > 8)
         } else {
             false
         };
 
         // 选择格式化方法
         if use_line_format {
-            self.print_line(&result.documents);
+            self.write_line(&mut out, &result.documents);
         } else {
             match self.format {
-                OutputFormat::Table => self.print_table(&result.documents),
-                OutputFormat::Json => self.print_json(&result.documents, false),
-                OutputFormat::JsonPretty => self.print_json(&result.documents, true),
-                OutputFormat::Csv => self.print_csv(&result.documents),
-                OutputFormat::Line => self.print_line(&result.documents),
+                OutputFormat::Table => self.write_table(&mut out, &result.documents),
+                OutputFormat::Json => self.write_json(&mut out, &result.documents, false),
+                OutputFormat::JsonPretty => self.write_json(&mut out, &result.documents, true),
+                OutputFormat::Csv => self.write_csv(&mut out, &result.documents),
+                OutputFormat::Line => self.write_line(&mut out, &result.documents),
             }
         }
 
-        self.print_affected(result.affected);
+        self.write_affected(&mut out, result.affected);
+        self.write_stats(&mut out, result.stats.as_ref());
+        out
     }
 
     /// # Brief
-    /// 打印 ASCII 表格
+    /// 渲染 ASCII 表格
     ///
     /// 自动提取所有字段作为列,_id 字段自动移到第一列。
     ///
     /// # Arguments
+    /// * `out` - 输出缓冲
     /// * `documents` - 文档数组
-    fn print_table(&self, documents: &[Value]) {
+    fn write_table(&self, out: &mut String, documents: &[Value]) {
         if documents.is_empty() {
             return;
         }
 
-        // 提取所有字段名(合并所有文档的字段)
-        let mut columns: Vec<String> = Vec::new();
-        for doc in documents {
-            if let Value::Object(map) = doc {
-                for key in map.keys() {
-                    if !columns.contains(key) {
-                        columns.push(key.clone());
-                    }
-                }
-            }
-        }
-
-        // 排序字段名
-        columns.sort();
+        // 提取所有字段名;用户选择了列时保持选择的顺序
+        let mut columns = self.columns(documents);
+        if self.fields.is_none() {
+            // 排序字段名
+            columns.sort();
 
-        // _id 字段移到最前
-        if columns.contains(&"_id".to_string()) {
-            columns.retain(|c| c != "_id");
-            columns.insert(0, "_id".to_string());
+            // _id 字段移到最前
+            if columns.contains(&"_id".to_string()) {
+                columns.retain(|c| c != "_id");
+                columns.insert(0, "_id".to_string());
+            }
         }
 
         // 构造表格数据
@@ -177,13 +329,9 @@ impl Formatter {
                 columns
                     .iter()
                     .map(|col| {
-                        if let Value::Object(map) = doc {
-                            map.get(col)
-                                .map(|v| format_value(v))
-                                .unwrap_or_default()
-                        } else {
-                            String::new()
-                        }
+                        lookup(doc, col)
+                            .map(|v| truncate_cell(&summarize_value(v), self.max_width))
+                            .unwrap_or_default()
                     })
                     .collect()
             })
@@ -191,27 +339,48 @@ impl Formatter {
 
         // 表头着色(青色加粗)
         let header: Vec<String> = columns.iter().map(|c| {
+            let c = truncate_cell(c, self.max_width);
             if self.color {
                 c.cyan().bold().to_string()
             } else {
-                c.clone()
+                c
             }
         }).collect();
 
-        println!();
-        print_simple_table(&header, &rows);
-        println!();
+        outln!(out);
+        write_simple_table(out, &header, &rows);
+        outln!(out);
     }
 
     /// # Brief
-    /// 打印 JSON 格式
+    /// 渲染 JSON 格式
     ///
     /// 单个文档时直接输出对象,多个文档时输出数组。
     ///
     /// # Arguments
+    /// * `out` - 输出缓冲
     /// * `documents` - 文档数组
     /// * `pretty` - 是否格式化输出
-    fn print_json(&self, documents: &[Value], pretty: bool) {
+    fn write_json(&self, out: &mut String, documents: &[Value], pretty: bool) {
+        // 选择了列时只输出这些字段
+        let projected: Vec<Value>;
+        let documents = match &self.fields {
+            Some(fields) => {
+                projected = documents
+                    .iter()
+                    .map(|doc| {
+                        let map = fields
+                            .iter()
+                            .filter_map(|f| lookup(doc, f).map(|v| (f.clone(), v.clone())))
+                            .collect();
+                        Value::Object(map)
+                    })
+                    .collect();
+                &projected[..]
+            }
+            None => documents,
+        };
+
         let output = if documents.len() == 1 {
             // 单个文档直接输出对象
             if pretty {
@@ -229,89 +398,90 @@ impl Formatter {
         };
 
         if let Ok(json) = output {
-            println!("{}", json);
+            outln!(out, "{}", json);
         }
     }
 
     /// # Brief
-    /// 打印 CSV 格式
+    /// 渲染 CSV 格式
     ///
     /// 第一行为列名,后续行为数据。处理逗号和引号转义。
     ///
     /// # Arguments
+    /// * `out` - 输出缓冲
     /// * `documents` - 文档数组
-    fn print_csv(&self, documents: &[Value]) {
+    fn write_csv(&self, out: &mut String, documents: &[Value]) {
         if documents.is_empty() {
             return;
         }
 
         // 提取所有字段名
-        let mut columns: Vec<String> = Vec::new();
-        for doc in documents {
-            if let Value::Object(map) = doc {
-                for key in map.keys() {
-                    if !columns.contains(key) {
-                        columns.push(key.clone());
-                    }
-                }
-            }
+        let mut columns = self.columns(documents);
+        if self.fields.is_none() {
+            columns.sort();
         }
-        columns.sort();
 
         // 打印表头
-        println!("{}", columns.join(","));
+        outln!(out, "{}", columns.join(","));
 
         // 打印数据行
         for doc in documents {
-            if let Value::Object(map) = doc {
+            if doc.is_object() {
                 let row: Vec<String> = columns
                     .iter()
                     .map(|col| {
-                        map.get(col)
+                        lookup(doc, col)
                             .map(|v| csv_escape(&format_value(v)))
                             .unwrap_or_default()
                     })
                     .collect();
-                println!("{}", row.join(","));
+                outln!(out, "{}", row.join(","));
             }
         }
     }
 
     /// # Brief
-    /// 打印行格式
+    /// 渲染行格式
     ///
     /// 每个字段占据一行,适用于字段多或内容宽的文档。
     ///
     /// # Arguments
+    /// * `out` - 输出缓冲
     /// * `documents` - 文档数组
-    fn print_line(&self, documents: &[Value]) {
+    fn write_line(&self, out: &mut String, documents: &[Value]) {
         for (i, doc) in documents.iter().enumerate() {
             // 文档间用分割线
             if i > 0 {
-                println!("{}", "-".repeat(40));
+                outln!(out, "{}", "-".repeat(40));
             }
             if let Value::Object(map) = doc {
-                for (key, value) in map {
+                let fields: Vec<(&str, Option<&Value>)> = match &self.fields {
+                    Some(fields) => fields.iter().map(|f| (f.as_str(), lookup(doc, f))).collect(),
+                    None => map.iter().map(|(k, v)| (k.as_str(), Some(v))).collect(),
+                };
+                for (key, value) in fields {
                     // 字段名着色(青色)
                     let key_str = if self.color {
                         key.cyan().to_string()
                     } else {
-                        key.clone()
+                        key.to_string()
                     };
-                    println!("{}: {}", key_str, format_value(value));
+                    let value = value.map(format_value).unwrap_or_default();
+                    outln!(out, "{}: {}", key_str, value);
                 }
             }
         }
     }
 
     /// # Brief
-    /// 打印受影响文档数
+    /// 渲染受影响文档数
     ///
     /// 用于 INSERT/UPDATE/DELETE 操作。
     ///
     /// # Arguments
+    /// * `out` - 输出缓冲
     /// * `affected` - 受影响的文档数量
-    fn print_affected(&self, affected: u64) {
+    fn write_affected(&self, out: &mut String, affected: u64) {
         if affected > 0 {
             let doc_word = if affected == 1 {
                 t!("result.document")
@@ -320,12 +490,70 @@ impl Formatter {
             };
             let msg = format!("{} {} {}", affected, doc_word, t!("result.affected"));
             if self.color {
-                println!("{}", msg.dimmed());
+                outln!(out, "{}", msg.dimmed());
             } else {
-                println!("{}", msg);
+                outln!(out, "{}", msg);
             }
         }
     }
+
+    /// # Brief
+    /// 渲染语句的资源统计
+    ///
+    /// 一行显示检查/返回的文档数、访问路径、各阶段耗时和读取字节数。
+    ///
+    /// # Arguments
+    /// * `out` - 输出缓冲
+    /// * `stats` - 服务器返回的 `stats`,None 时不输出
+    fn write_stats(&self, out: &mut String, stats: Option<&Value>) {
+        let Some(stats) = stats else {
+            return;
+        };
+        let num = |key: &str| stats[key].as_u64().unwrap_or(0);
+        let ms = |key: &str| num(key) as f64 / 1000.0;
+        let access = match stats["index_used"].as_str() {
+            Some(index) => format!("{} {}", t!("result.index"), index),
+            None => t!("result.full_scan").to_string(),
+        };
+        let msg = format!(
+            "{} {}, {} {}, {}, {} {:.3} ms, {} {:.3} ms, {} {:.3} ms, {} {}",
+            t!("result.examined"),
+            num("docs_examined"),
+            t!("result.returned"),
+            num("docs_returned"),
+            access,
+            t!("result.parse"),
+            ms("parse_us"),
+            t!("result.plan"),
+            ms("plan_us"),
+            t!("result.execute"),
+            ms("execute_us"),
+            t!("result.read"),
+            format_bytes(num("bytes_read")),
+        );
+        if self.color {
+            outln!(out, "{}", msg.dimmed());
+        } else {
+            outln!(out, "{}", msg);
+        }
+    }
+}
+
+/// # Brief
+/// 以 B/KB/MB/GB 格式化字节数
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
 }
 
 /// # Brief
@@ -340,7 +568,7 @@ impl Formatter {
 ///
 /// # Returns
 /// 格式化后的字符串
-fn format_value(value: &Value) -> String {
+pub(crate) fn format_value(value: &Value) -> String {
     match value {
         Value::Null => "null".to_string(),
         Value::Bool(b) => b.to_string(),
@@ -373,6 +601,49 @@ fn format_value(value: &Value) -> String {
     }
 }
 
+/// # Brief
+/// 按字段路径取值
+///
+/// 先按完整名称查找,找不到时把 `a.b` 视为嵌套路径,数组用数字下标访问。
+///
+/// # Arguments
+/// * `doc` - 文档
+/// * `path` - 字段名或点分路径
+fn lookup<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    if let Some(v) = doc.get(path) {
+        return Some(v);
+    }
+    path.split('.').try_fold(doc, |v, part| match v {
+        Value::Object(map) => map.get(part),
+        Value::Array(arr) => part.parse::<usize>().ok().and_then(|i| arr.get(i)),
+        _ => None,
+    })
+}
+
+/// # Brief
+/// 生成表格单元格的摘要
+///
+/// 嵌套对象显示为 `{...}`,数组显示为 `[n items]`,ObjectId 和标量与 `format_value` 相同。
+///
+/// # Arguments
+/// * `value` - JSON 值
+fn summarize_value(value: &Value) -> String {
+    match value {
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        Value::Object(_) => "{...}".to_string(),
+        Value::Array(arr) if is_object_id(arr) => format_value(value),
+        Value::Array(arr) if arr.is_empty() => "[]".to_string(),
+        Value::Array(arr) if arr.len() == 1 => "[1 item]".to_string(),
+        Value::Array(arr) => format!("[{} items]", arr.len()),
+        _ => format_value(value),
+    }
+}
+
+/// 12 字节数组识别为 ObjectId
+fn is_object_id(arr: &[Value]) -> bool {
+    arr.len() == 12 && arr.iter().all(|v| v.as_u64().is_some_and(|n| n <= u8::MAX as u64))
+}
+
 /// # Brief
 /// CSV 转义
 ///
@@ -393,14 +664,15 @@ fn csv_escape(s: &str) -> String {
 }
 
 /// # Brief
-/// 打印简单 ASCII 表格
+/// 渲染简单 ASCII 表格
 ///
 /// 自动计算列宽,绘制边框和分隔线。
 ///
 /// # Arguments
+/// * `out` - 输出缓冲
 /// * `headers` - 表头
 /// * `rows` - 数据行
-fn print_simple_table(headers: &[String], rows: &[Vec<String>]) {
+fn write_simple_table(out: &mut String, headers: &[String], rows: &[Vec<String>]) {
     let col_count = headers.len();
 
     // 1) 统一用可见宽度计算列宽
@@ -420,7 +692,7 @@ fn print_simple_table(headers: &[String], rows: &[Vec<String>]) {
         .collect::<Vec<_>>()
         .join("+");
 
-    println!("+{}+", separator);
+    outln!(out, "+{}+", separator);
 
     // 3) 表头
     let header_row = headers
@@ -429,9 +701,9 @@ fn print_simple_table(headers: &[String], rows: &[Vec<String>]) {
         .map(|(i, h)| pad_cell(h, widths[i]))
         .collect::<Vec<_>>()
         .join("|");
-    println!("|{}|", header_row);
+    outln!(out, "|{}|", header_row);
 
-    println!("+{}+", separator);
+    outln!(out, "+{}+", separator);
 
     // 4) 数据行
     for row in rows {
@@ -442,10 +714,10 @@ fn print_simple_table(headers: &[String], rows: &[Vec<String>]) {
             })
             .collect::<Vec<_>>()
             .join("|");
-        println!("|{}|", row_str);
+        outln!(out, "|{}|", row_str);
     }
 
-    println!("+{}+", separator);
+    outln!(out, "+{}+", separator);
 }
 
 
@@ -481,6 +753,8 @@ pub struct QueryResult {
     pub documents: Vec<Value>,
     /// 消息(成功或错误提示)
     pub message: Option<String>,
+    /// 语句的资源统计(会话中 SET return_stats = true 后由服务器返回)
+    pub stats: Option<Value>,
 }
 
 impl Default for QueryResult {
@@ -490,6 +764,7 @@ impl Default for QueryResult {
             affected: 0,
             documents: vec![],
             message: None,
+            stats: None,
         }
     }
 }
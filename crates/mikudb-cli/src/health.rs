@@ -0,0 +1,166 @@
+//! 健康检查模块
+//!
+//! 实现 `healthcheck` 子命令,供容器入口脚本和 CI 流水线等待数据库就绪:
+//! - 反复新建连接(握手、认证)并发送一次心跳,成功即视为可以接受查询
+//! - `--wait` 内按 `--interval` 重试,超时后以连接错误退出
+//! - 认证失败说明服务器已经启动但凭据错误,不再重试
+//! - 结果以单行 JSON 输出到 stdout,便于脚本解析
+
+use crate::client::Client;
+use crate::{exit_code, CliError, CliResult, Config};
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// 单次尝试的最长耗时,避免无响应的地址阻塞到等待时间结束
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 健康检查结论
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// 服务器可以接受查询
+    Ready,
+    /// 等待时间内无法连接或服务器未响应
+    Unavailable,
+    /// 服务器拒绝了凭据
+    AuthFailed,
+}
+
+impl HealthStatus {
+    /// # Brief
+    /// 输出中使用的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HealthStatus::Ready => "ready",
+            HealthStatus::Unavailable => "unavailable",
+            HealthStatus::AuthFailed => "auth_failed",
+        }
+    }
+}
+
+/// 健康检查结果
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// 结论
+    pub status: HealthStatus,
+    /// 服务器地址(host:port)
+    pub address: String,
+    /// 服务器版本(握手成功时)
+    pub server_version: Option<String>,
+    /// 尝试次数
+    pub attempts: u32,
+    /// 从开始到得出结论的耗时
+    pub elapsed: Duration,
+    /// 就绪时心跳的往返延迟
+    pub rtt: Option<Duration>,
+    /// 最后一次失败的原因
+    pub error: Option<String>,
+}
+
+impl HealthReport {
+    /// # Brief
+    /// 转换为单行输出的 JSON 对象
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "status": self.status.as_str(),
+            "address": self.address,
+            "server_version": self.server_version,
+            "attempts": self.attempts,
+            "elapsed_ms": self.elapsed.as_millis() as u64,
+            "rtt_ms": self.rtt.map(|rtt| rtt.as_secs_f64() * 1000.0),
+            "error": self.error,
+        })
+    }
+
+    /// # Brief
+    /// 进程退出码: 就绪为 0,否则为连接错误
+    pub fn exit_code(&self) -> i32 {
+        match self.status {
+            HealthStatus::Ready => exit_code::SUCCESS,
+            _ => exit_code::CONNECTION_ERROR,
+        }
+    }
+}
+
+/// # Brief
+/// 等待服务器就绪
+///
+/// 至少尝试一次;`wait` 为 0 时只检查一次。
+///
+/// # Arguments
+/// * `config` - 连接配置
+/// * `wait` - 最长等待时间
+/// * `interval` - 两次尝试之间的间隔
+///
+/// # Returns
+/// 健康检查结果
+pub async fn wait_ready(config: &Config, wait: Duration, interval: Duration) -> HealthReport {
+    let started = Instant::now();
+    let deadline = started + wait;
+    let mut report = HealthReport {
+        status: HealthStatus::Unavailable,
+        address: format!("{}:{}", config.host, config.port),
+        server_version: None,
+        attempts: 0,
+        elapsed: Duration::ZERO,
+        rtt: None,
+        error: None,
+    };
+
+    loop {
+        report.attempts += 1;
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let limit = if remaining.is_zero() { ATTEMPT_TIMEOUT } else { remaining.min(ATTEMPT_TIMEOUT) };
+        match tokio::time::timeout(limit, probe(config)).await {
+            Ok(Ok((version, rtt))) => {
+                report.status = HealthStatus::Ready;
+                report.server_version = version;
+                report.rtt = Some(rtt);
+                report.error = None;
+                break;
+            }
+            Ok(Err(e @ CliError::AuthFailed(_))) => {
+                report.status = HealthStatus::AuthFailed;
+                report.error = Some(e.to_string());
+                break;
+            }
+            Ok(Err(e)) => report.error = Some(e.to_string()),
+            Err(_) => report.error = Some(format!("No response within {} ms", limit.as_millis())),
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        tokio::time::sleep(interval.min(remaining)).await;
+    }
+
+    report.elapsed = started.elapsed();
+    report
+}
+
+/// # Brief
+/// 新建连接并发送一次心跳
+///
+/// # Returns
+/// 服务器版本和心跳往返延迟
+async fn probe(config: &Config) -> CliResult<(Option<String>, Duration)> {
+    let client = Client::connect(config).await?;
+    let rtt = client.ping().await?;
+    Ok((client.server_info().map(|info| info.server_version.clone()), rtt))
+}
+
+/// # Brief
+/// 解析时长,支持 `ms`/`s`/`m`/`h` 后缀(如 `500ms`、`60s`、`2m`),不带后缀时为秒
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let value = digits.parse::<u64>().map_err(|_| format!("invalid duration '{}'", s))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value.saturating_mul(60))),
+        "h" => Ok(Duration::from_secs(value.saturating_mul(3600))),
+        _ => Err(format!("invalid duration '{}', expected a number with ms, s, m or h", s)),
+    }
+}
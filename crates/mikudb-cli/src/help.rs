@@ -32,12 +32,26 @@ fn print_main_help_en() {
     println!("  {}        - Update documents in collection", "UPDATE".yellow());
     println!("  {}        - Delete documents from collection", "DELETE".yellow());
     println!("  {}     - Aggregation pipeline operations", "AGGREGATE".yellow());
+    println!("  {}       - Move old documents into an archive collection", "ARCHIVE".yellow());
+    println!("  {}       - Preview UPDATE/DELETE without changing data", "DRY RUN".yellow());
     println!();
 
     println!("{}", "DATABASE & COLLECTION MANAGEMENT".cyan().bold());
-    println!("  {} - Show databases/collections/indexes/users/status", "SHOW".yellow());
+    println!("  {} - Show databases/collections/indexes/users/status/advisor", "SHOW".yellow());
     println!("  {}       - Create collection/database/index/user", "CREATE".yellow());
     println!("  {}         - Drop collection/database/index/user", "DROP".yellow());
+    println!("  {}        - Change collection options (type tracking, expiry)", "ALTER".yellow());
+    println!("  {}       - Back up data, users and roles (or a snapshot) to a server directory", "BACKUP".yellow());
+    println!("  {}      - Restore a backup (optionally metadata only)", "RESTORE".yellow());
+    println!("  {}        - Change server log level at runtime", "ADMIN".yellow());
+    println!("  {}      - Bootstrap or join a cluster (CLUSTER INIT / CLUSTER JOIN)", "CLUSTER".yellow());
+    println!("  {}        - Show per-collection operation counters (RESET STATS clears)", "STATS".yellow());
+    println!("  {}      - Collect collection statistics for the query optimizer", "ANALYZE".yellow());
+    println!("  {}      - Compact a collection and its indexes to reclaim disk space", "COMPACT".yellow());
+    println!("  {}          - Set a session variable (SET return_stats = true)", "SET".yellow());
+    println!("  {}     - Crash-safe counters: CREATE SEQUENCE, NEXTVAL('name') in INSERT", "SEQUENCE".yellow());
+    println!("  {}       - Pre-aggregated collections kept up to date on write", "ROLLUP".yellow());
+    println!("  {}     - Sandboxed WASM functions: CREATE FUNCTION, CALL FUNCTION name(doc)", "FUNCTION".yellow());
     println!();
 
     println!("{}", "TRANSACTION COMMANDS".cyan().bold());
@@ -52,12 +66,26 @@ fn print_main_help_en() {
     println!("  {}         - Grant privileges to user", "GRANT".yellow());
     println!("  {}        - Revoke privileges from user", "REVOKE".yellow());
     println!("  {}    - List all users", "SHOW USERS".yellow());
+    println!("  {}    - Change a user's roles (ADD ROLE / DROP ROLE)", "ALTER USER".yellow());
+    println!("  {} - Export databases, collections, indexes and users as one document", "SHOW MANIFEST".yellow());
     println!();
 
     println!("{}", "BUILT-IN COMMANDS".cyan().bold());
     println!("  {}            - Switch database", "USE <db>".yellow());
     println!("  {}        - Change language (en/zh)", "LANG <lang>".yellow());
+    println!("  {}      - Change output format (saved)", "FORMAT <fmt>".yellow());
+    println!("  {}  - Configure result pager (saved)", "PAGER [on|off|cmd]".yellow());
+    println!("  {}    - Customize prompt: {{db}} {{user}} {{host}} {{port}}", "PROMPT <tpl>".yellow());
+    println!("  {} - Server-side query timeout, e.g. 5s (saved)", "\\TIMEOUT <dur|off>".yellow());
+    println!("  {} - Show only these columns (off resets)", "\\FIELDS <a,b.c|off>".yellow());
+    println!("  {}    - Toggle expanded display of full values (saved)", "\\EXPAND [on|off]".yellow());
+    println!("  {}   - Truncate table cells wider than n (saved)", "\\MAXWIDTH <n|off>".yellow());
+    println!("  {}     - Pretty-print a statement (default: the last one)", "\\FORMAT [mql]".yellow());
+    println!("  {}  - Import a CSV/NDJSON file into a collection (IMPORT ?)", "IMPORT <coll> FROM".yellow());
+    println!("  {}    - Export a collection to a CSV/NDJSON file (EXPORT ?)", "EXPORT <coll> TO".yellow());
     println!("  {}         - Show connection status", "STATUS".yellow());
+    println!("  {}      - Measure protocol round-trip latency (default 4)", "\\PING [n]".yellow());
+    println!("  {}     - Show negotiated protocol, server features and connect timings", "\\CONNINFO".yellow());
     println!("  {}           - Show this help", "HELP".yellow());
     println!("  {}          - Clear screen", "CLEAR".yellow());
     println!("  {}           - Exit CLI", "EXIT".yellow());
@@ -65,7 +93,7 @@ fn print_main_help_en() {
 
     println!("{}", "KEYBOARD SHORTCUTS".cyan().bold());
     println!("  Tab         - Auto-complete");
-    println!("  Ctrl+C      - Cancel current input");
+    println!("  Ctrl+C      - Cancel current input / running query");
     println!("  Ctrl+D      - Exit CLI");
     println!("  Up/Down     - Navigate history");
     println!();
@@ -95,12 +123,26 @@ fn print_main_help_zh() {
     println!("  {}        - 更新集合中的文档", "UPDATE".yellow());
     println!("  {}        - 从集合删除文档", "DELETE".yellow());
     println!("  {}     - 聚合管道操作", "AGGREGATE".yellow());
+    println!("  {}       - 将历史文档迁移到归档集合", "ARCHIVE".yellow());
+    println!("  {}       - 预览 UPDATE/DELETE 的影响,不修改数据", "DRY RUN".yellow());
     println!();
 
     println!("{}", "数据库和集合管理".cyan().bold());
-    println!("  {}   - 显示数据库/集合/索引/用户/状态", "SHOW".yellow());
+    println!("  {}   - 显示数据库/集合/索引/用户/状态/索引建议", "SHOW".yellow());
     println!("  {}       - 创建集合/数据库/索引/用户", "CREATE".yellow());
     println!("  {}         - 删除集合/数据库/索引/用户", "DROP".yellow());
+    println!("  {}        - 修改集合选项(字段类型登记、文档过期)", "ALTER".yellow());
+    println!("  {}       - 将数据、用户和角色(或物理快照)备份到服务器目录", "BACKUP".yellow());
+    println!("  {}      - 从备份恢复(可只恢复元数据)", "RESTORE".yellow());
+    println!("  {}        - 运行时调整服务器日志级别", "ADMIN".yellow());
+    println!("  {}      - 初始化或加入集群(CLUSTER INIT / CLUSTER JOIN)", "CLUSTER".yellow());
+    println!("  {}        - 显示集合的操作统计(RESET STATS 清零)", "STATS".yellow());
+    println!("  {}      - 收集集合统计信息供查询优化器使用", "ANALYZE".yellow());
+    println!("  {}      - 压缩集合及其索引,回收磁盘空间", "COMPACT".yellow());
+    println!("  {}          - 设置会话变量(SET return_stats = true)", "SET".yellow());
+    println!("  {}     - 崩溃安全的序列: CREATE SEQUENCE,在 INSERT 中使用 NEXTVAL('name')", "SEQUENCE".yellow());
+    println!("  {}       - 写入时维护的预聚合集合", "ROLLUP".yellow());
+    println!("  {}     - 沙箱中执行的 WASM 函数: CREATE FUNCTION,CALL FUNCTION name(doc)", "FUNCTION".yellow());
     println!();
 
     println!("{}", "事务命令".cyan().bold());
@@ -115,12 +157,25 @@ fn print_main_help_zh() {
     println!("  {}         - 授予用户权限", "GRANT".yellow());
     println!("  {}        - 撤销用户权限", "REVOKE".yellow());
     println!("  {}    - 列出所有用户", "SHOW USERS".yellow());
+    println!("  {}    - 修改用户角色(ADD ROLE / DROP ROLE)", "ALTER USER".yellow());
+    println!("  {} - 以一个文档导出数据库、集合、索引和用户", "SHOW MANIFEST".yellow());
     println!();
 
     println!("{}", "内置命令".cyan().bold());
     println!("  {}         - 切换数据库", "USE <数据库>".yellow());
     println!("  {}     - 切换语言 (en/zh)", "LANG <语言>".yellow());
+    println!("  {}      - 切换输出格式(自动保存)", "FORMAT <格式>".yellow());
+    println!("  {}  - 设置结果分页器(自动保存)", "PAGER [on|off|cmd]".yellow());
+    println!("  {}    - 自定义提示符: {{db}} {{user}} {{host}} {{port}}", "PROMPT <模板>".yellow());
+    println!("  {} - 只显示这些列(off 恢复全部)", "\\FIELDS <a,b.c|off>".yellow());
+    println!("  {}    - 切换展开显示完整值(自动保存)", "\\EXPAND [on|off]".yellow());
+    println!("  {}   - 截断超过 n 的表格单元格(自动保存)", "\\MAXWIDTH <n|off>".yellow());
+    println!("  {}     - 格式化语句(默认为最近执行的语句)", "\\FORMAT [mql]".yellow());
+    println!("  {}  - 把 CSV/NDJSON 文件导入集合(IMPORT ?)", "IMPORT <集合> FROM".yellow());
+    println!("  {}    - 把集合导出为 CSV/NDJSON 文件(EXPORT ?)", "EXPORT <集合> TO".yellow());
     println!("  {}         - 显示连接状态", "STATUS".yellow());
+    println!("  {}      - 测量协议往返延迟(默认 4 次)", "\\PING [n]".yellow());
+    println!("  {}     - 显示协商的协议、服务器功能和建立连接的耗时", "\\CONNINFO".yellow());
     println!("  {}           - 显示此帮助", "HELP".yellow());
     println!("  {}          - 清空屏幕", "CLEAR".yellow());
     println!("  {}           - 退出命令行", "EXIT".yellow());
@@ -157,7 +212,7 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
     let help = match cmd {
         "FIND" => {
             format!(
-                "\n{}\n\n{}\n  FIND <collection> [WHERE <condition>] [ORDER BY <field>] [LIMIT <n>]\n\n{}\n  Query documents from a collection with optional filtering and sorting.\n\n{}\n  - collection: Name of the collection to query\n  - WHERE: Optional filter condition (supports =, !=, >, <, >=, <=, AND, OR)\n  - ORDER BY: Optional sorting (ASC or DESC)\n  - LIMIT: Limit number of results\n\n{}\n  FIND users\n  FIND users WHERE age > 18\n  FIND users WHERE age > 18 AND city = \"Beijing\"\n  FIND users WHERE age > 18 ORDER BY name ASC LIMIT 10\n",
+                "\n{}\n\n{}\n  FIND <collection> [WHERE <condition>] [ORDER BY <field>] [LIMIT <n>] [BATCH SIZE <n>]\n\n{}\n  Query documents from a collection with optional filtering and sorting.\n\n{}\n  - collection: Name of the collection to query\n  - WHERE: Optional filter condition (supports =, !=, >, <, >=, <=, AND, OR)\n  - ORDER BY: Optional sorting (ASC or DESC)\n  - LIMIT: Limit number of results\n  - BATCH SIZE: Documents per cursor batch; later batches grow adaptively\n\n{}\n  FIND users\n  FIND users WHERE age > 18\n  FIND users WHERE age > 18 AND city = \"Beijing\"\n  FIND users WHERE age > 18 ORDER BY name ASC LIMIT 10\n  FIND events BATCH SIZE 500\n",
                 "FIND - Query Documents".green().bold(),
                 "SYNTAX".cyan().bold(),
                 "DESCRIPTION".cyan().bold(),
@@ -167,7 +222,7 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
         }
         "INSERT" | "INSERT INTO" => {
             format!(
-                "\n{}\n\n{}\n  INSERT INTO <collection> {{field1: value1, field2: value2, ...}}\n\n{}\n  Insert a new document into a collection.\n  An _id field will be automatically generated if not provided.\n\n{}\n  - collection: Name of the collection\n  - {{...}}: Document to insert (BOML format)\n\n{}\n  INSERT INTO users {{name: \"Miku\", age: 16, city: \"Tokyo\"}}\n  INSERT INTO products {{name: \"Laptop\", price: 999.99, stock: 50}}\n  INSERT INTO users {{_id: \"custom_id\", name: \"Test\"}}\n",
+                "\n{}\n\n{}\n  INSERT INTO <collection> {{field1: value1, field2: value2, ...}} [RETURNING * | <field>, ...]\n\n{}\n  Insert a new document into a collection.\n  An _id field will be automatically generated if not provided.\n\n{}\n  - collection: Name of the collection\n  - {{...}}: Document to insert (BOML format)\n  - RETURNING: Return the inserted documents (all fields or only the listed ones)\n\n{}\n  INSERT INTO users {{name: \"Miku\", age: 16, city: \"Tokyo\"}}\n  INSERT INTO products {{name: \"Laptop\", price: 999.99, stock: 50}}\n  INSERT INTO users {{_id: \"custom_id\", name: \"Test\"}}\n  INSERT INTO users {{name: \"Rin\"}} RETURNING _id\n",
                 "INSERT - Insert Document".green().bold(),
                 "SYNTAX".cyan().bold(),
                 "DESCRIPTION".cyan().bold(),
@@ -177,7 +232,7 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
         }
         "UPDATE" => {
             format!(
-                "\n{}\n\n{}\n  UPDATE <collection> SET <field> = <value> [, ...] WHERE <condition>\n\n{}\n  Update existing documents in a collection.\n\n{}\n  - collection: Name of the collection\n  - SET: Fields to update with new values\n  - WHERE: Condition to match documents\n\n{}\n  UPDATE users SET age = 17 WHERE name = \"Miku\"\n  UPDATE products SET price = 899.99, stock = 45 WHERE name = \"Laptop\"\n  UPDATE users SET status = \"active\" WHERE age >= 18\n",
+                "\n{}\n\n{}\n  UPDATE <collection> SET <field> = <value> [, ...] WHERE <condition> [RETURNING [OLD | NEW] * | <field>, ...]\n  FIND AND MODIFY <collection> SET ... [WHERE <condition>] [ORDER BY <field>] [RETURNING [OLD | NEW] ...]\n\n{}\n  Update existing documents in a collection.\n  FIND AND MODIFY atomically updates the first matching document (same as LIMIT 1)\n  and returns it; concurrent callers never pick the same document.\n\n{}\n  - collection: Name of the collection\n  - SET: Fields to update with new values (field += n increments)\n  - UNSET / RENAME a TO b: Remove or rename fields\n  - PUSH / ADDTOSET / PULL <field> = <value>, PULLALL <field> = [...], POP FIRST | LAST <field>:\n    Array operators; ADDTOSET skips values already present\n  - Fields can be paths: items.0.qty targets an array index, items.$.qty the element matched by WHERE\n  - WHERE: Condition to match documents\n  - RETURNING: Return the updated documents (NEW, default) or the documents before the update (OLD)\n\n{}\n  UPDATE users SET age = 17 WHERE name = \"Miku\"\n  UPDATE products SET price = 899.99, stock = 45 WHERE name = \"Laptop\"\n  UPDATE users SET status = \"active\" WHERE age >= 18 RETURNING *\n  UPDATE stats SET views += 1 WHERE _id = \"65a1f0c2e4b0a1b2c3d4e5f6\"\n  UPDATE orders SET items.$.qty = 5 WHERE items.sku = \"A1\"\n  UPDATE users ADDTOSET tags = \"vip\" POP FIRST inbox WHERE name = \"Miku\"\n  FIND AND MODIFY jobs SET state = \"running\" WHERE state = \"queued\" ORDER BY priority DESC\n  FIND AND MODIFY counters SET n += 1 WHERE name = \"orders\" RETURNING OLD n\n\n  Only += by _id writes just the increment, without rewriting the document.\n",
                 "UPDATE - Update Documents".green().bold(),
                 "SYNTAX".cyan().bold(),
                 "DESCRIPTION".cyan().bold(),
@@ -187,7 +242,7 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
         }
         "DELETE" | "DELETE FROM" => {
             format!(
-                "\n{}\n\n{}\n  DELETE FROM <collection> WHERE <condition>\n\n{}\n  Delete documents from a collection.\n\n{}\n  - collection: Name of the collection\n  - WHERE: Condition to match documents to delete\n\n{}\n  DELETE FROM users WHERE age < 13\n  DELETE FROM products WHERE stock = 0\n  DELETE FROM logs WHERE timestamp < \"2024-01-01\"\n",
+                "\n{}\n\n{}\n  DELETE FROM <collection> [OLDER THAN <duration>] [WHERE <condition>] [RETURNING * | <field>, ...]\n\n{}\n  Delete documents from a collection.\n\n{}\n  - collection: Name of the collection\n  - OLDER THAN: Only documents created (by _id) before now - duration (s/m/h/d/w);\n    without WHERE the whole key range is dropped at once instead of one by one\n  - WHERE: Condition to match documents to delete\n  - RETURNING: Return the deleted documents as they were before deletion\n\n{}\n  DELETE FROM users WHERE age < 13\n  DELETE FROM products WHERE stock = 0 RETURNING _id, name\n  DELETE FROM logs WHERE timestamp < \"2024-01-01\"\n  DELETE FROM logs OLDER THAN 30d\n",
                 "DELETE - Delete Documents".green().bold(),
                 "SYNTAX".cyan().bold(),
                 "DESCRIPTION".cyan().bold(),
@@ -206,7 +261,7 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
         }
         "CREATE" => {
             format!(
-                "\n{}\n\n{}\n  CREATE COLLECTION <name>\n  CREATE DATABASE <name>\n  CREATE INDEX <name> ON <collection> (field1, field2, ...)\n\n{}\n  Create a new collection, database, or index.\n\n{}\n  CREATE COLLECTION users\n  CREATE DATABASE myapp\n  CREATE INDEX idx_name ON users (name)\n  CREATE UNIQUE INDEX idx_email ON users (email)\n",
+                "\n{}\n\n{}\n  CREATE COLLECTION <name> [(CAPPED [SIZE <n>[KB|MB|GB]] [MAX <n>])]\n  CREATE DATABASE <name>\n  CREATE INDEX <name> ON <collection> (field1, field2, ...) [WITH MAX_KEY_SIZE <n> OVERSIZED REJECT|TRUNCATE|SKIP]\n  CREATE TEXT INDEX <name> ON <collection> (field) [WITH TOKENIZER '<name>' STOPWORDS '<list>' STEMMER '<language>']\n\n{}\n  Create a new collection, database, or index.\n  Text index tokenizers: unicode (default), simple, ngram, mixed, jieba.\n  STOPWORDS accepts 'english', 'chinese' or a list such as ('a', 'the').\n  CAPPED collections evict their oldest documents once SIZE or MAX is exceeded.\n  Index keys are limited to 1024 bytes by default; OVERSIZED decides whether longer keys are rejected, truncated or not indexed.\n\n{}\n  CREATE COLLECTION users\n  CREATE COLLECTION logs (CAPPED SIZE 10MB MAX 1000)\n  CREATE DATABASE myapp\n  CREATE INDEX idx_name ON users (name)\n  CREATE UNIQUE INDEX idx_email ON users (email)\n  CREATE INDEX idx_bio ON users (bio) WITH MAX_KEY_SIZE 256 OVERSIZED TRUNCATE\n  CREATE TEXT INDEX idx_body ON articles (body) WITH TOKENIZER 'jieba' STOPWORDS 'chinese'\n",
                 "CREATE - Create Object".green().bold(),
                 "SYNTAX".cyan().bold(),
                 "DESCRIPTION".cyan().bold(),
@@ -223,6 +278,24 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
                 "EXAMPLES".cyan().bold()
             )
         }
+        "IMPORT" => {
+            format!(
+                "\n{}\n\n{}\n  IMPORT <collection> FROM '<file>' [FORMAT CSV|NDJSON] [NO INFER]\n      [TYPES field type, ...] [MAP column AS field, ...] [BATCH SIZE n] [DELIMITER ',']\n\n{}\n  Insert the documents of a CSV or NDJSON file into a collection of the current database.\n  The format follows the file extension unless FORMAT is given. CSV headers name the fields,\n  dots create nested documents; cell types are inferred unless NO INFER is given.\n  Types: string, int, double, bool, date, objectid, json. Rows that cannot be converted\n  or inserted are reported with their line number and skipped.\n\n{}\n  IMPORT users FROM 'users.csv'\n  IMPORT users FROM 'users.csv' TYPES joined date, zip string MAP full_name AS name\n  IMPORT events FROM 'events.ndjson' BATCH SIZE 5000\n",
+                "IMPORT - Import File".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "EXPORT" => {
+            format!(
+                "\n{}\n\n{}\n  EXPORT <collection> TO '<file>' [FORMAT CSV|NDJSON] [FIELDS field, ...]\n      [MAP field AS column, ...] [BATCH SIZE n] [DELIMITER ',']\n\n{}\n  Write all documents of a collection to a CSV or NDJSON file, reading in batches.\n  CSV columns default to the top-level fields of the first batch; nested documents\n  and arrays are written as JSON text. NDJSON uses relaxed extended JSON.\n\n{}\n  EXPORT users TO 'users.csv'\n  EXPORT users TO 'users.csv' FIELDS _id, name, address.city MAP address.city AS city\n  EXPORT events TO 'events.ndjson'\n",
+                "EXPORT - Export Collection".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
         "USE" => {
             format!(
                 "\n{}\n\n{}\n  USE <database>\n\n{}\n  Switch to a different database.\n  All subsequent commands will operate on this database.\n\n{}\n  USE myapp\n  USE test\n",
@@ -252,7 +325,7 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
         }
         "AGGREGATE" => {
             format!(
-                "\n{}\n\n{}\n  AGGREGATE <collection> [<pipeline>]\n\n{}\n  Perform aggregation operations on documents using a pipeline of stages.\n  Supports: $match, $group, $sort, $project, $limit, $skip, $lookup, $unwind\n\n{}\n  - collection: Name of the collection\n  - pipeline: Array of aggregation stages\n\n{}\n  AGGREGATE users [{{$match: {{age: {{$gt: 18}}}}}}\n  AGGREGATE sales [{{$group: {{_id: \"$product\", total: {{$sum: \"$amount\"}}}}}}\n  AGGREGATE orders [{{$lookup: {{from: \"products\", localField: \"productId\", foreignField: \"_id\", as: \"product\"}}}}]\n",
+                "\n{}\n\n{}\n  AGGREGATE <collection> [<pipeline>]\n\n{}\n  Perform aggregation operations on documents using a pipeline of stages.\n  Supports: $match, $group, $sort, $project, $limit, $skip, $lookup, $unwind, $graphLookup, $facet, $bucket, $bucketAuto\n\n{}\n  - collection: Name of the collection\n  - pipeline: Array of aggregation stages\n\n{}\n  AGGREGATE users [{{$match: {{age: {{$gt: 18}}}}}}\n  AGGREGATE sales [{{$group: {{_id: \"$product\", total: {{$sum: \"$amount\"}}}}}}\n  AGGREGATE orders [{{$lookup: {{from: \"products\", localField: \"productId\", foreignField: \"_id\", as: \"product\"}}}}]\n  AGGREGATE categories | GRAPH LOOKUP categories START WITH parent_id CONNECT FROM parent_id TO _id AS ancestors MAX DEPTH 5\n  AGGREGATE products | FACET by_category (GROUP BY category AS {{n: COUNT()}}), top (SORT score DESC | LIMIT 10)\n  AGGREGATE products | BUCKET price BOUNDARIES [0, 10, 100] DEFAULT 'other' AS {{n: COUNT()}}\n  AGGREGATE products | BUCKET AUTO price BUCKETS 4\n",
                 "AGGREGATE - Aggregation Pipeline".green().bold(),
                 "SYNTAX".cyan().bold(),
                 "DESCRIPTION".cyan().bold(),
@@ -260,6 +333,115 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
                 "EXAMPLES".cyan().bold()
             )
         }
+        "ARCHIVE" => {
+            format!(
+                "\n{}\n\n{}\n  ARCHIVE [DOCUMENTS] OLDER THAN <duration> OF <collection> TO <archive>\n      [COMPRESSION zstd|lz4|none] [PATH '<dir>']\n\n{}\n  Move documents created before the given age into a compressed archive collection.\n  The policy is saved, so FIND ... WITH ARCHIVE can read hot and archived data together.\n\n{}\n  - duration: Integer with unit s, m, h, d or w (e.g. 90d)\n  - COMPRESSION: Compression for the archive collection (default: zstd)\n  - PATH: Directory for the archive, e.g. on a slower disk\n\n{}\n  ARCHIVE documents OLDER THAN 90d OF events TO events_archive COMPRESSION zstd\n  ARCHIVE OLDER THAN 30d OF logs TO logs_cold PATH '/mnt/cold/mikudb'\n  FIND events WHERE level = \"error\" WITH ARCHIVE\n",
+                "ARCHIVE - Cold Data Tiering".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "PARAMETERS".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "DRY RUN" => {
+            format!(
+                "\n{}\n\n{}\n  DRY RUN UPDATE <collection> SET ... [WHERE <condition>]\n  DRY RUN DELETE FROM <collection> [WHERE <condition>]\n\n{}\n  Match documents exactly like the real statement but change nothing.\n  Reports the matched count, how many documents would be modified and up to 10 sample ids.\n\n{}\n  DRY RUN UPDATE users SET active = false WHERE last_login < \"2024-01-01\"\n  DRY RUN DELETE FROM logs WHERE level = \"debug\"\n",
+                "DRY RUN - Preview Write Statements".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "BACKUP" | "RESTORE" => {
+            format!(
+                "\n{}\n\n{}\n  BACKUP TO '<dir>' [WITHOUT SYSTEM]\n  BACKUP SNAPSHOT TO '<dir>'\n  RESTORE FROM '<dir>' [METADATA ONLY]\n\n{}\n  Write a consistent snapshot of all collections and metadata to a directory on the server.\n  System data (users, roles, internal collections) is included unless WITHOUT SYSTEM is given.\n  Restoring a collection replaces its contents. METADATA ONLY restores just users, roles\n  and system metadata, so a rebuilt server gets the same access control state.\n  BACKUP SNAPSHOT writes a physical RocksDB checkpoint instead; it is restored offline by\n  starting the server with --restore-snapshot '<dir>' on an empty data directory.\n\n{}\n  BACKUP TO '/var/backups/mikudb/2024-06-01'\n  BACKUP SNAPSHOT TO '/var/backups/mikudb/snap-2024-06-01'\n  BACKUP TO '/var/backups/mikudb/data' WITHOUT SYSTEM\n  RESTORE FROM '/var/backups/mikudb/2024-06-01' METADATA ONLY\n",
+                "BACKUP / RESTORE - Backup and Snapshots".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "ADMIN" => {
+            format!(
+                "\n{}\n\n{}\n  ADMIN SET LOG LEVEL <level> [TARGET <module>]\n  ADMIN RESET LOG LEVEL\n\n{}\n  Change the server log filter without restarting. Levels: trace, debug, info, warn, error, off.\n  TARGET limits the change to a module path prefix; quote paths containing '::'.\n  RESET restores the filter the server was started with. Sending SIGUSR1 to the server\n  toggles debug logging for all MikuDB modules.\n\n{}\n  ADMIN SET LOG LEVEL debug TARGET mikudb_storage\n  ADMIN SET LOG LEVEL trace TARGET 'mikudb_server::handler'\n  ADMIN RESET LOG LEVEL\n",
+                "ADMIN - Server Administration".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "CLUSTER" => {
+            format!(
+                "\n{}\n\n{}\n  CLUSTER INIT\n  CLUSTER JOIN 'mikudb://<host>:<cluster port>[,<host>:<port>...]'\n\n{}\n  CLUSTER INIT bootstraps a single-node cluster with this server as its only member.\n  CLUSTER JOIN adds this server to an existing cluster through any member's cluster port\n  (default 3941); the member assigns the node's ObjectId machine id. The cluster identity\n  is saved in the data directory, so a restarted server rejoins automatically.\n  Equivalent to starting mikudb-server with --cluster-init or --join. Requires the root role.\n\n{}\n  CLUSTER INIT\n  CLUSTER JOIN 'mikudb://10.0.0.1:3941'\n",
+                "CLUSTER - Cluster Membership".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "STATS" | "RESET STATS" => {
+            format!(
+                "\n{}\n\n{}\n  STATS <collection>\n  RESET STATS [<collection>]\n\n{}\n  Show operation counters the server collected for a collection since startup or the last reset:\n  finds, inserts, updates, deletes, aggregates, full scans vs index hits, documents examined,\n  returned and written, and average latency. RESET STATS without a collection clears all counters.\n\n{}\n  STATS orders\n  RESET STATS orders\n",
+                "STATS - Collection Operation Statistics".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "ANALYZE" => {
+            format!(
+                "\n{}\n\n{}\n  ANALYZE <collection>\n\n{}\n  Scan a collection and save its document count and per-field statistics: value counts,\n  NULLs, distinct values and histograms of numbers and dates. FIND uses them to choose\n  between an index and a full scan and to evaluate the most selective conditions first.\n  Statistics are not updated by writes; run ANALYZE again after the data changes a lot.\n  Collections without statistics use the rule-based index choice.\n\n{}\n  ANALYZE orders\n",
+                "ANALYZE - Collection Statistics".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "COMPACT" => {
+            format!(
+                "\n{}\n\n{}\n  COMPACT <collection>\n\n{}\n  Flush and fully compact the collection and all of its indexes, dropping deleted and\n  overwritten documents from disk. Returns the on-disk size before and after and the\n  reclaimed bytes. Requires the root role. Set storage.compaction_interval_secs\n  to compact every collection periodically in the background.\n\n{}\n  COMPACT logs\n",
+                "COMPACT - Collection Compaction".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "SET" | "RETURN_STATS" => {
+            format!(
+                "\n{}\n\n{}\n  SET <variable> = <value>\n\n{}\n  Set a variable of the current session. return_stats = true makes the server attach\n  resource usage to every query result: documents examined and returned, the index used,\n  time spent parsing, planning and executing, and bytes read from storage.\n  The CLI prints it below each result.\n\n{}\n  SET return_stats = true\n  FIND orders WHERE state = \"paid\"\n  SET return_stats = false\n",
+                "SET - Session Variables".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "SEQUENCE" | "CREATE SEQUENCE" | "DROP SEQUENCE" | "SHOW SEQUENCES" | "NEXTVAL" => {
+            format!(
+                "\n{}\n\n{}\n  CREATE SEQUENCE <name> [START [WITH] <n>] [INCREMENT [BY] <n>]\n  DROP SEQUENCE <name>\n  SHOW SEQUENCES\n  NEXTVAL('<name>')\n\n{}\n  A sequence hands out unique, ordered numbers without read-modify-write in the application.\n  NEXTVAL('<name>') can be used as a value in INSERT documents and UPDATE SET; every\n  inserted or updated document gets its own number. Numbers are persisted through the\n  write-ahead log, so they are never reused after a crash. START and INCREMENT default to 1.\n\n{}\n  CREATE SEQUENCE order_no START 1000\n  INSERT INTO orders {{no: NEXTVAL('order_no'), item: \"book\"}}\n  SHOW SEQUENCES\n",
+                "SEQUENCE - Sequences".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "ROLLUP" | "CREATE ROLLUP" | "DROP ROLLUP" | "SHOW ROLLUPS" => {
+            format!(
+                "\n{}\n\n{}\n  CREATE ROLLUP <name> ON <collection> [GROUP BY <key> [AS <field>], ...]\n      AS {{<field>: COUNT() | SUM(f) | AVG(f) | MIN(f) | MAX(f), ...}}\n  <key> = <field path> | DATE_TRUNC('minute|hour|day|week|month|year', <field path>)\n  DROP ROLLUP <name>\n  SHOW ROLLUPS\n\n{}\n  A rollup is a collection with one document per group, built from the existing data when it\n  is created and then updated by every INSERT, UPDATE and DELETE statement on the source, so\n  dashboards read a few small documents instead of aggregating on each request. Each group\n  also stores a _count field; AVG keeps its running sum and count under _avg. Removing the\n  current MIN or MAX re-aggregates that group from the source.\n\n{}\n  CREATE ROLLUP daily_sales ON sales GROUP BY DATE_TRUNC('day', ts) AS day, product\n      AS {{total: SUM(amount), orders: COUNT()}}\n  FIND daily_sales WHERE product = \"book\"\n  SHOW ROLLUPS\n",
+                "ROLLUP - Pre-aggregated collections".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "FUNCTION" | "CREATE FUNCTION" | "DROP FUNCTION" | "SHOW FUNCTIONS" | "CALL" | "CALL FUNCTION" => {
+            format!(
+                "\n{}\n\n{}\n  CREATE FUNCTION <name> WASM '<base64 module>'\n  DROP FUNCTION <name>\n  SHOW FUNCTIONS\n  CALL FUNCTION <name>(doc | <expr>, ...)\n\n{}\n  Registers a WebAssembly module as a scalar function that runs in a sandbox on the server:\n  no imports (no file, network or clock access), a fuel limit on CPU and a memory limit per\n  call. Use it in WHERE / MATCH conditions and as computed fields in PROJECT; a bare `doc`\n  argument passes the whole current document. The module exports memory, alloc(len) and\n  call(ptr, len); arguments and result are JSON. Disabled unless [udf] enabled = true is set\n  in the server config and the server is built with the wasm-udf feature.\n\n{}\n  CREATE FUNCTION score WASM 'AGFzbQEAAAAB...'\n  FIND users WHERE CALL FUNCTION score(doc) > 0.8\n  AGGREGATE users | PROJECT name, s: CALL FUNCTION score(doc) | SORT s DESC\n",
+                "FUNCTION - Sandboxed functions".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
         "BEGIN" | "BEGIN TRANSACTION" => {
             format!(
                 "\n{}\n\n{}\n  BEGIN TRANSACTION\n  BEGIN\n\n{}\n  Start a new transaction. All subsequent operations will be part of this transaction\n  until COMMIT or ROLLBACK is executed.\n\n{}\n  BEGIN TRANSACTION\n  INSERT INTO users {{name: \"Test\"}}\n  UPDATE users SET status = \"active\" WHERE name = \"Test\"\n  COMMIT\n",
@@ -297,6 +479,24 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
                 "EXAMPLES".cyan().bold()
             )
         }
+        "ALTER USER" => {
+            format!(
+                "\n{}\n\n{}\n  ALTER USER <username> [PASSWORD <password>] [ADD ROLE <role>, ...] [DROP ROLE <role>, ...]\n\n{}\n  Change a user's password and roles. Added roles apply to all databases; adding a role the\n  user already has or dropping one it does not have is ignored.\n\n{}\n  ALTER USER \"john\" PASSWORD \"new_secret\"\n  ALTER USER \"john\" ADD ROLE readWrite DROP ROLE read\n",
+                "ALTER USER - Change User".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "MANIFEST" | "SHOW MANIFEST" => {
+            format!(
+                "\n{}\n\n{}\n  SHOW MANIFEST\n  mikudb-cli manifest [--json] > manifest.yaml\n  mikudb-cli apply manifest.yaml [--dry-run] [--prune]\n\n{}\n  Returns one document describing every database, its collections (capped limits, type\n  tracking, expiry field), their indexes and the users with their roles. `apply` compares a\n  YAML or JSON manifest with the server and runs only the statements needed to match it;\n  --dry-run prints them instead, --prune also drops what the manifest does not list. New\n  users take their password from `password` or the environment variable in `password_env`.\n\n{}\n  SHOW MANIFEST\n  mikudb-cli -u root -P secret apply schema.yaml --dry-run\n",
+                "MANIFEST - Declarative schema".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
         "DROP USER" => {
             format!(
                 "\n{}\n\n{}\n  DROP USER <username>\n\n{}\n  Delete a database user.\n  {} This operation cannot be undone!\n\n{}\n  DROP USER john\n  DROP USER old_admin\n",
@@ -344,6 +544,33 @@ fn get_command_help_en(cmd: &str) -> Option<String> {
                 "EXAMPLES".cyan().bold()
             )
         }
+        "SHOW ADVISOR" => {
+            format!(
+                "\n{}\n\n{}\n  SHOW ADVISOR [ON <collection>]\n\n{}\n  List index recommendations produced by the server's index advisor,\n  ordered by estimated benefit. Requires [advisor] enabled = true on the server.\n\n{}\n  SHOW ADVISOR\n  SHOW ADVISOR ON orders\n",
+                "SHOW ADVISOR - Index Recommendations".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "SHOW SCHEMA" => {
+            format!(
+                "\n{}\n\n{}\n  SHOW SCHEMA ON <collection>\n\n{}\n  Show the field type registry of a collection: the dominant type of each\n  field and how often every type was seen. Requires track_types.\n\n{}\n  SHOW SCHEMA ON users\n",
+                "SHOW SCHEMA - Field Types".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
+        "ALTER" | "ALTER COLLECTION" => {
+            format!(
+                "\n{}\n\n{}\n  ALTER COLLECTION <collection> SET <option> = <bool> [, ...]\n  ALTER COLLECTION <collection> EXPIRE AFTER FIELD '<field>'\n  ALTER COLLECTION <collection> EXPIRE OFF\n\n{}\n  track_types   record field types and warn when a write changes a field's dominant type\n  strict_types  reject such writes instead of warning\n  EXPIRE        documents whose field (DateTime, Timestamp or RFC 3339 string) is in the past\n                are removed by the server's expiry task, no TTL index needed\n\n{}\n  ALTER COLLECTION users SET track_types = true\n  ALTER COLLECTION users SET strict_types = true\n  ALTER COLLECTION sessions EXPIRE AFTER FIELD 'expires_at'\n",
+                "ALTER COLLECTION - Collection Options".green().bold(),
+                "SYNTAX".cyan().bold(),
+                "DESCRIPTION".cyan().bold(),
+                "EXAMPLES".cyan().bold()
+            )
+        }
         "HELP" => {
             format!(
                 "\n{}\n\n{}\n  HELP\n  <command> ?\n\n{}\n  Display help information.\n  Use 'HELP' for general help, or 'command ?' for specific command help.\n\n{}\n  HELP\n  FIND ?\n  INSERT ?\n  LANG ?\n",
@@ -364,7 +591,7 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
     let help = match cmd {
         "FIND" => {
             format!(
-                "\n{}\n\n{}\n  FIND <集合名> [WHERE <条件>] [ORDER BY <字段>] [LIMIT <数量>]\n\n{}\n  从集合中查询文档,支持可选的过滤和排序。\n\n{}\n  - 集合名: 要查询的集合名称\n  - WHERE: 可选的过滤条件 (支持 =, !=, >, <, >=, <=, AND, OR)\n  - ORDER BY: 可选的排序 (ASC 升序或 DESC 降序)\n  - LIMIT: 限制结果数量\n\n{}\n  FIND users\n  FIND users WHERE age > 18\n  FIND users WHERE age > 18 AND city = \"北京\"\n  FIND users WHERE age > 18 ORDER BY name ASC LIMIT 10\n",
+                "\n{}\n\n{}\n  FIND <集合名> [WHERE <条件>] [ORDER BY <字段>] [LIMIT <数量>] [BATCH SIZE <数量>]\n\n{}\n  从集合中查询文档,支持可选的过滤和排序。\n\n{}\n  - 集合名: 要查询的集合名称\n  - WHERE: 可选的过滤条件 (支持 =, !=, >, <, >=, <=, AND, OR)\n  - ORDER BY: 可选的排序 (ASC 升序或 DESC 降序)\n  - LIMIT: 限制结果数量\n  - BATCH SIZE: 游标每批返回的文档数,后续批次自适应增长\n\n{}\n  FIND users\n  FIND users WHERE age > 18\n  FIND users WHERE age > 18 AND city = \"北京\"\n  FIND users WHERE age > 18 ORDER BY name ASC LIMIT 10\n  FIND events BATCH SIZE 500\n",
                 "FIND - 查询文档".green().bold(),
                 "语法".cyan().bold(),
                 "描述".cyan().bold(),
@@ -374,7 +601,7 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
         }
         "INSERT" | "INSERT INTO" => {
             format!(
-                "\n{}\n\n{}\n  INSERT INTO <集合名> {{字段1: 值1, 字段2: 值2, ...}}\n\n{}\n  向集合中插入新文档。\n  如果未提供 _id 字段,系统会自动生成。\n\n{}\n  - 集合名: 集合的名称\n  - {{...}}: 要插入的文档 (BOML 格式)\n\n{}\n  INSERT INTO users {{name: \"初音未来\", age: 16, city: \"东京\"}}\n  INSERT INTO products {{name: \"笔记本电脑\", price: 999.99, stock: 50}}\n  INSERT INTO users {{_id: \"custom_id\", name: \"测试\"}}\n",
+                "\n{}\n\n{}\n  INSERT INTO <集合名> {{字段1: 值1, 字段2: 值2, ...}} [RETURNING * | <字段>, ...]\n\n{}\n  向集合中插入新文档。\n  如果未提供 _id 字段,系统会自动生成。\n\n{}\n  - 集合名: 集合的名称\n  - {{...}}: 要插入的文档 (BOML 格式)\n  - RETURNING: 返回插入后的文档(全部字段或列出的字段)\n\n{}\n  INSERT INTO users {{name: \"初音未来\", age: 16, city: \"东京\"}}\n  INSERT INTO products {{name: \"笔记本电脑\", price: 999.99, stock: 50}}\n  INSERT INTO users {{_id: \"custom_id\", name: \"测试\"}}\n  INSERT INTO users {{name: \"镜音铃\"}} RETURNING _id\n",
                 "INSERT - 插入文档".green().bold(),
                 "语法".cyan().bold(),
                 "描述".cyan().bold(),
@@ -384,7 +611,7 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
         }
         "UPDATE" => {
             format!(
-                "\n{}\n\n{}\n  UPDATE <集合名> SET <字段> = <值> [, ...] WHERE <条件>\n\n{}\n  更新集合中的现有文档。\n\n{}\n  - 集合名: 集合的名称\n  - SET: 要更新的字段及新值\n  - WHERE: 匹配文档的条件\n\n{}\n  UPDATE users SET age = 17 WHERE name = \"初音未来\"\n  UPDATE products SET price = 899.99, stock = 45 WHERE name = \"笔记本电脑\"\n  UPDATE users SET status = \"active\" WHERE age >= 18\n",
+                "\n{}\n\n{}\n  UPDATE <集合名> SET <字段> = <值> [, ...] WHERE <条件> [RETURNING [OLD | NEW] * | <字段>, ...]\n  FIND AND MODIFY <集合名> SET ... [WHERE <条件>] [ORDER BY <字段>] [RETURNING [OLD | NEW] ...]\n\n{}\n  更新集合中的现有文档。\n  FIND AND MODIFY 原子地更新第一个匹配的文档(等同于 LIMIT 1)并返回它,并发调用不会选中同一个文档。\n\n{}\n  - 集合名: 集合的名称\n  - SET: 要更新的字段及新值(字段 += n 为增量)\n  - UNSET / RENAME a TO b: 删除或重命名字段\n  - PUSH / ADDTOSET / PULL <字段> = <值>, PULLALL <字段> = [...], POP FIRST | LAST <字段>:\n    数组操作符,ADDTOSET 跳过已存在的值\n  - 字段可以是路径: items.0.qty 指定数组下标,items.$.qty 指定 WHERE 匹配的元素\n  - WHERE: 匹配文档的条件\n  - RETURNING: 返回更新后(NEW,默认)或更新前(OLD)的文档\n\n{}\n  UPDATE users SET age = 17 WHERE name = \"初音未来\"\n  UPDATE products SET price = 899.99, stock = 45 WHERE name = \"笔记本电脑\"\n  UPDATE users SET status = \"active\" WHERE age >= 18 RETURNING *\n  UPDATE stats SET views += 1 WHERE _id = \"65a1f0c2e4b0a1b2c3d4e5f6\"\n  UPDATE orders SET items.$.qty = 5 WHERE items.sku = \"A1\"\n  UPDATE users ADDTOSET tags = \"vip\" POP FIRST inbox WHERE name = \"初音未来\"\n  FIND AND MODIFY jobs SET state = \"running\" WHERE state = \"queued\" ORDER BY priority DESC\n  FIND AND MODIFY counters SET n += 1 WHERE name = \"orders\" RETURNING OLD n\n\n  按 _id 且只有 += 时只写入增量,不改写整个文档。\n",
                 "UPDATE - 更新文档".green().bold(),
                 "语法".cyan().bold(),
                 "描述".cyan().bold(),
@@ -394,7 +621,7 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
         }
         "DELETE" | "DELETE FROM" => {
             format!(
-                "\n{}\n\n{}\n  DELETE FROM <集合名> WHERE <条件>\n\n{}\n  从集合中删除文档。\n\n{}\n  - 集合名: 集合的名称\n  - WHERE: 匹配要删除文档的条件\n\n{}\n  DELETE FROM users WHERE age < 13\n  DELETE FROM products WHERE stock = 0\n  DELETE FROM logs WHERE timestamp < \"2024-01-01\"\n",
+                "\n{}\n\n{}\n  DELETE FROM <集合名> [OLDER THAN <时长>] [WHERE <条件>] [RETURNING * | <字段>, ...]\n\n{}\n  从集合中删除文档。\n\n{}\n  - 集合名: 集合的名称\n  - OLDER THAN: 只删除创建时间(按 _id)早于 now - 时长(s/m/h/d/w)的文档;\n    没有 WHERE 时整段键范围一次删除,不逐个删除\n  - WHERE: 匹配要删除文档的条件\n  - RETURNING: 返回被删除的文档(删除前的内容)\n\n{}\n  DELETE FROM users WHERE age < 13\n  DELETE FROM products WHERE stock = 0 RETURNING _id, name\n  DELETE FROM logs WHERE timestamp < \"2024-01-01\"\n  DELETE FROM logs OLDER THAN 30d\n",
                 "DELETE - 删除文档".green().bold(),
                 "语法".cyan().bold(),
                 "描述".cyan().bold(),
@@ -413,7 +640,7 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
         }
         "CREATE" => {
             format!(
-                "\n{}\n\n{}\n  CREATE COLLECTION <名称>\n  CREATE DATABASE <名称>\n  CREATE INDEX <索引名> ON <集合> (字段1, 字段2, ...)\n\n{}\n  创建新的集合、数据库或索引。\n\n{}\n  CREATE COLLECTION users\n  CREATE DATABASE myapp\n  CREATE INDEX idx_name ON users (name)\n  CREATE UNIQUE INDEX idx_email ON users (email)\n",
+                "\n{}\n\n{}\n  CREATE COLLECTION <名称> [(CAPPED [SIZE <n>[KB|MB|GB]] [MAX <n>])]\n  CREATE DATABASE <名称>\n  CREATE INDEX <索引名> ON <集合> (字段1, 字段2, ...) [WITH MAX_KEY_SIZE <n> OVERSIZED REJECT|TRUNCATE|SKIP]\n  CREATE TEXT INDEX <索引名> ON <集合> (字段) [WITH TOKENIZER '<名称>' STOPWORDS '<词表>' STEMMER '<语言>']\n\n{}\n  创建新的集合、数据库或索引。\n  全文索引分词器: unicode(默认)、simple、ngram、mixed、jieba。\n  STOPWORDS 可为 'english'、'chinese' 或自定义列表,如 ('的', '了')。\n  CAPPED 集合超出 SIZE 或 MAX 时按插入顺序删除最早的文档。\n  索引键默认不超过 1024 字节,OVERSIZED 指定超长键拒绝写入、截断或不索引。\n\n{}\n  CREATE COLLECTION users\n  CREATE COLLECTION logs (CAPPED SIZE 10MB MAX 1000)\n  CREATE DATABASE myapp\n  CREATE INDEX idx_name ON users (name)\n  CREATE UNIQUE INDEX idx_email ON users (email)\n  CREATE INDEX idx_bio ON users (bio) WITH MAX_KEY_SIZE 256 OVERSIZED TRUNCATE\n  CREATE TEXT INDEX idx_body ON articles (body) WITH TOKENIZER 'jieba' STOPWORDS 'chinese'\n",
                 "CREATE - 创建对象".green().bold(),
                 "语法".cyan().bold(),
                 "描述".cyan().bold(),
@@ -430,6 +657,24 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
                 "示例".cyan().bold()
             )
         }
+        "IMPORT" => {
+            format!(
+                "\n{}\n\n{}\n  IMPORT <集合> FROM '<文件>' [FORMAT CSV|NDJSON] [NO INFER]\n      [TYPES 字段 类型, ...] [MAP 列 AS 字段, ...] [BATCH SIZE n] [DELIMITER ',']\n\n{}\n  把 CSV 或 NDJSON 文件中的文档插入当前数据库的集合。\n  未指定 FORMAT 时按扩展名判断格式。CSV 第一行为字段名,`.` 表示嵌套字段;\n  单元格默认推断类型,NO INFER 时全部作为字符串。\n  类型: string、int、double、bool、date、objectid、json。无法转换或插入失败的行\n  按行号列出并跳过。\n\n{}\n  IMPORT users FROM 'users.csv'\n  IMPORT users FROM 'users.csv' TYPES joined date, zip string MAP full_name AS name\n  IMPORT events FROM 'events.ndjson' BATCH SIZE 5000\n",
+                "IMPORT - 导入文件".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "EXPORT" => {
+            format!(
+                "\n{}\n\n{}\n  EXPORT <集合> TO '<文件>' [FORMAT CSV|NDJSON] [FIELDS 字段, ...]\n      [MAP 字段 AS 列, ...] [BATCH SIZE n] [DELIMITER ',']\n\n{}\n  按批读取集合,把全部文档写入 CSV 或 NDJSON 文件。\n  CSV 默认以第一批文档的顶层字段为列,嵌套文档和数组写为 JSON 文本;\n  NDJSON 使用宽松扩展 JSON。\n\n{}\n  EXPORT users TO 'users.csv'\n  EXPORT users TO 'users.csv' FIELDS _id, name, address.city MAP address.city AS city\n  EXPORT events TO 'events.ndjson'\n",
+                "EXPORT - 导出集合".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
         "USE" => {
             format!(
                 "\n{}\n\n{}\n  USE <数据库名>\n\n{}\n  切换到不同的数据库。\n  后续所有命令将在该数据库上操作。\n\n{}\n  USE myapp\n  USE test\n",
@@ -459,7 +704,7 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
         }
         "AGGREGATE" => {
             format!(
-                "\n{}\n\n{}\n  AGGREGATE <集合名> [<管道>]\n\n{}\n  使用管道阶段对文档执行聚合操作。\n  支持: $match, $group, $sort, $project, $limit, $skip, $lookup, $unwind\n\n{}\n  - 集合名: 集合的名称\n  - 管道: 聚合阶段数组\n\n{}\n  AGGREGATE users [{{$match: {{age: {{$gt: 18}}}}}}\n  AGGREGATE sales [{{$group: {{_id: \"$product\", total: {{$sum: \"$amount\"}}}}}}\n  AGGREGATE orders [{{$lookup: {{from: \"products\", localField: \"productId\", foreignField: \"_id\", as: \"product\"}}}}]\n",
+                "\n{}\n\n{}\n  AGGREGATE <集合名> [<管道>]\n\n{}\n  使用管道阶段对文档执行聚合操作。\n  支持: $match, $group, $sort, $project, $limit, $skip, $lookup, $unwind, $graphLookup, $facet, $bucket, $bucketAuto\n\n{}\n  - 集合名: 集合的名称\n  - 管道: 聚合阶段数组\n\n{}\n  AGGREGATE users [{{$match: {{age: {{$gt: 18}}}}}}\n  AGGREGATE sales [{{$group: {{_id: \"$product\", total: {{$sum: \"$amount\"}}}}}}\n  AGGREGATE orders [{{$lookup: {{from: \"products\", localField: \"productId\", foreignField: \"_id\", as: \"product\"}}}}]\n  AGGREGATE categories | GRAPH LOOKUP categories START WITH parent_id CONNECT FROM parent_id TO _id AS ancestors MAX DEPTH 5\n  AGGREGATE products | FACET by_category (GROUP BY category AS {{n: COUNT()}}), top (SORT score DESC | LIMIT 10)\n  AGGREGATE products | BUCKET price BOUNDARIES [0, 10, 100] DEFAULT 'other' AS {{n: COUNT()}}\n  AGGREGATE products | BUCKET AUTO price BUCKETS 4\n",
                 "AGGREGATE - 聚合管道".green().bold(),
                 "语法".cyan().bold(),
                 "描述".cyan().bold(),
@@ -467,6 +712,115 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
                 "示例".cyan().bold()
             )
         }
+        "ARCHIVE" => {
+            format!(
+                "\n{}\n\n{}\n  ARCHIVE [DOCUMENTS] OLDER THAN <时长> OF <集合名> TO <归档集合名>\n      [COMPRESSION zstd|lz4|none] [PATH '<目录>']\n\n{}\n  将创建时间早于指定时长的文档迁移到压缩的归档集合。\n  归档策略会被保存,FIND ... WITH ARCHIVE 可同时查询热数据和归档数据。\n\n{}\n  - 时长: 整数加单位 s、m、h、d、w (例如 90d)\n  - COMPRESSION: 归档集合的压缩算法 (默认: zstd)\n  - PATH: 归档集合所在目录,例如较慢的大容量磁盘\n\n{}\n  ARCHIVE documents OLDER THAN 90d OF events TO events_archive COMPRESSION zstd\n  ARCHIVE OLDER THAN 30d OF logs TO logs_cold PATH '/mnt/cold/mikudb'\n  FIND events WHERE level = \"error\" WITH ARCHIVE\n",
+                "ARCHIVE - 冷热数据分层".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "参数".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "DRY RUN" => {
+            format!(
+                "\n{}\n\n{}\n  DRY RUN UPDATE <集合名> SET ... [WHERE <条件>]\n  DRY RUN DELETE FROM <集合名> [WHERE <条件>]\n\n{}\n  按与实际语句相同的方式匹配文档,但不做任何修改。\n  返回匹配数、会被修改的文档数以及最多 10 个样例文档 ID。\n\n{}\n  DRY RUN UPDATE users SET active = false WHERE last_login < \"2024-01-01\"\n  DRY RUN DELETE FROM logs WHERE level = \"debug\"\n",
+                "DRY RUN - 预览写操作".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "BACKUP" | "RESTORE" => {
+            format!(
+                "\n{}\n\n{}\n  BACKUP TO '<目录>' [WITHOUT SYSTEM]\n  BACKUP SNAPSHOT TO '<目录>'\n  RESTORE FROM '<目录>' [METADATA ONLY]\n\n{}\n  把所有集合和元数据的一致快照写入服务器上的目录。\n  默认包含系统数据(用户、角色、内部集合),WITHOUT SYSTEM 时排除。\n  恢复集合时会替换其现有内容。METADATA ONLY 只恢复用户、角色和系统元数据,\n  使重建的服务器拥有相同的访问控制状态。\n  BACKUP SNAPSHOT 改为写入 RocksDB 物理检查点,需要离线恢复:\n  在空数据目录上以 --restore-snapshot '<目录>' 启动服务器。\n\n{}\n  BACKUP TO '/var/backups/mikudb/2024-06-01'\n  BACKUP SNAPSHOT TO '/var/backups/mikudb/snap-2024-06-01'\n  BACKUP TO '/var/backups/mikudb/data' WITHOUT SYSTEM\n  RESTORE FROM '/var/backups/mikudb/2024-06-01' METADATA ONLY\n",
+                "BACKUP / RESTORE - 备份与快照".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "ADMIN" => {
+            format!(
+                "\n{}\n\n{}\n  ADMIN SET LOG LEVEL <级别> [TARGET <模块>]\n  ADMIN RESET LOG LEVEL\n\n{}\n  无需重启即可调整服务器日志过滤规则。级别: trace、debug、info、warn、error、off。\n  TARGET 只调整指定模块路径前缀的级别,包含 '::' 的路径需要加引号。\n  RESET 恢复服务器启动时的过滤规则。向服务器进程发送 SIGUSR1 可切换\n  所有 MikuDB 模块的 debug 日志。\n\n{}\n  ADMIN SET LOG LEVEL debug TARGET mikudb_storage\n  ADMIN SET LOG LEVEL trace TARGET 'mikudb_server::handler'\n  ADMIN RESET LOG LEVEL\n",
+                "ADMIN - 服务器运维".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "CLUSTER" => {
+            format!(
+                "\n{}\n\n{}\n  CLUSTER INIT\n  CLUSTER JOIN 'mikudb://<主机>:<集群端口>[,<主机>:<端口>...]'\n\n{}\n  CLUSTER INIT 以本服务器为唯一成员初始化集群。\n  CLUSTER JOIN 通过任一成员的集群端口(默认 3941)加入已有集群,由该成员分配 ObjectId 机器标识。\n  集群身份保存在数据目录,服务器重启后自动重新加入。\n  等同于以 --cluster-init 或 --join 启动 mikudb-server。需要 root 角色。\n\n{}\n  CLUSTER INIT\n  CLUSTER JOIN 'mikudb://10.0.0.1:3941'\n",
+                "CLUSTER - 集群成员".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "STATS" | "RESET STATS" => {
+            format!(
+                "\n{}\n\n{}\n  STATS <集合>\n  RESET STATS [<集合>]\n\n{}\n  显示服务器启动或上次重置以来集合的操作计数: FIND、INSERT、UPDATE、DELETE、\n  AGGREGATE 次数,全集合扫描与索引命中次数,扫描、返回和写入的文档数以及平均耗时。\n  RESET STATS 不带集合名时清零所有集合的统计。\n\n{}\n  STATS orders\n  RESET STATS orders\n",
+                "STATS - 集合操作统计".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "ANALYZE" => {
+            format!(
+                "\n{}\n\n{}\n  ANALYZE <集合>\n\n{}\n  扫描集合并保存文档数和每个字段的统计信息: 值个数、NULL 个数、不同值个数,\n  以及数值和日期的直方图。FIND 据此在索引扫描和全集合扫描之间选择,并先求值选择性高的条件。\n  写入不会更新统计信息,数据变化较大后需要重新执行 ANALYZE。\n  没有统计信息的集合按规则选择索引。\n\n{}\n  ANALYZE orders\n",
+                "ANALYZE - 集合统计信息".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "COMPACT" => {
+            format!(
+                "\n{}\n\n{}\n  COMPACT <集合>\n\n{}\n  刷写并完整压缩集合及其所有索引,从磁盘上删除已删除和被覆盖的文档。\n  返回压缩前后的磁盘占用和回收的字节数。需要 root 角色。\n  设置 storage.compaction_interval_secs 后后台定时压缩所有集合。\n\n{}\n  COMPACT logs\n",
+                "COMPACT - 集合压缩".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "SET" | "RETURN_STATS" => {
+            format!(
+                "\n{}\n\n{}\n  SET <变量> = <值>\n\n{}\n  设置当前会话的变量。return_stats = true 时服务器在每个查询结果中附带资源统计:\n  检查和返回的文档数、使用的索引、解析/计划/执行各阶段耗时以及从存储读取的字节数,\n  CLI 在结果下方显示。\n\n{}\n  SET return_stats = true\n  FIND orders WHERE state = \"paid\"\n  SET return_stats = false\n",
+                "SET - 会话变量".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "SEQUENCE" | "CREATE SEQUENCE" | "DROP SEQUENCE" | "SHOW SEQUENCES" | "NEXTVAL" => {
+            format!(
+                "\n{}\n\n{}\n  CREATE SEQUENCE <名称> [START [WITH] <n>] [INCREMENT [BY] <n>]\n  DROP SEQUENCE <名称>\n  SHOW SEQUENCES\n  NEXTVAL('<名称>')\n\n{}\n  序列发放唯一且有序的编号,应用无需自己读取-加一-写回。\n  NEXTVAL('<名称>') 可作为 INSERT 文档和 UPDATE SET 中的值,每个插入或更新的文档各取一个编号。\n  编号通过预写日志持久化,崩溃后不会重复发放。START 和 INCREMENT 默认均为 1。\n\n{}\n  CREATE SEQUENCE order_no START 1000\n  INSERT INTO orders {{no: NEXTVAL('order_no'), item: \"book\"}}\n  SHOW SEQUENCES\n",
+                "SEQUENCE - 序列".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "ROLLUP" | "CREATE ROLLUP" | "DROP ROLLUP" | "SHOW ROLLUPS" => {
+            format!(
+                "\n{}\n\n{}\n  CREATE ROLLUP <名称> ON <集合> [GROUP BY <键> [AS <字段>], ...]\n      AS {{<字段>: COUNT() | SUM(f) | AVG(f) | MIN(f) | MAX(f), ...}}\n  <键> = <字段路径> | DATE_TRUNC('minute|hour|day|week|month|year', <字段路径>)\n  DROP ROLLUP <名称>\n  SHOW ROLLUPS\n\n{}\n  预聚合是每个分组一个文档的集合,创建时由已有数据生成,之后源集合上的每条 INSERT、\n  UPDATE 和 DELETE 语句都会增量更新它,看板读取少量小文档即可,无需每次聚合。\n  每个分组还保存 _count 字段;AVG 的累计和与计数保存在 _avg 下。\n  删除当前的 MIN 或 MAX 值时会从源集合重新聚合该分组。\n\n{}\n  CREATE ROLLUP daily_sales ON sales GROUP BY DATE_TRUNC('day', ts) AS day, product\n      AS {{total: SUM(amount), orders: COUNT()}}\n  FIND daily_sales WHERE product = \"book\"\n  SHOW ROLLUPS\n",
+                "ROLLUP - 预聚合".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "FUNCTION" | "CREATE FUNCTION" | "DROP FUNCTION" | "SHOW FUNCTIONS" | "CALL" | "CALL FUNCTION" => {
+            format!(
+                "\n{}\n\n{}\n  CREATE FUNCTION <名称> WASM '<Base64 模块>'\n  DROP FUNCTION <名称>\n  SHOW FUNCTIONS\n  CALL FUNCTION <名称>(doc | <表达式>, ...)\n\n{}\n  把 WebAssembly 模块注册为在服务器沙箱中执行的标量函数: 不能有导入(无法访问文件、\n  网络和时钟),每次调用限制燃料(CPU)和内存。可用于 WHERE / MATCH 条件以及 PROJECT\n  的计算字段,单独的 `doc` 参数传入整个当前文档。模块导出 memory、alloc(len) 和\n  call(ptr, len),参数和结果为 JSON。需要在服务器配置中设置 [udf] enabled = true,\n  并以 wasm-udf 特性构建服务器,默认关闭。\n\n{}\n  CREATE FUNCTION score WASM 'AGFzbQEAAAAB...'\n  FIND users WHERE CALL FUNCTION score(doc) > 0.8\n  AGGREGATE users | PROJECT name, s: CALL FUNCTION score(doc) | SORT s DESC\n",
+                "FUNCTION - 沙箱函数".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
         "BEGIN" | "BEGIN TRANSACTION" => {
             format!(
                 "\n{}\n\n{}\n  BEGIN TRANSACTION\n  BEGIN\n\n{}\n  开始一个新事务。所有后续操作将成为此事务的一部分,\n  直到执行 COMMIT 或 ROLLBACK。\n\n{}\n  BEGIN TRANSACTION\n  INSERT INTO users {{name: \"测试\"}}\n  UPDATE users SET status = \"active\" WHERE name = \"测试\"\n  COMMIT\n",
@@ -504,6 +858,24 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
                 "示例".cyan().bold()
             )
         }
+        "ALTER USER" => {
+            format!(
+                "\n{}\n\n{}\n  ALTER USER <用户名> [PASSWORD <密码>] [ADD ROLE <角色>, ...] [DROP ROLE <角色>, ...]\n\n{}\n  修改用户的密码和角色。添加的角色作用于所有数据库;添加已有的角色或删除没有的角色时忽略。\n\n{}\n  ALTER USER \"john\" PASSWORD \"new_secret\"\n  ALTER USER \"john\" ADD ROLE readWrite DROP ROLE read\n",
+                "ALTER USER - 修改用户".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "MANIFEST" | "SHOW MANIFEST" => {
+            format!(
+                "\n{}\n\n{}\n  SHOW MANIFEST\n  mikudb-cli manifest [--json] > manifest.yaml\n  mikudb-cli apply manifest.yaml [--dry-run] [--prune]\n\n{}\n  返回一个文档,列出所有数据库、其中的集合(固定大小限制、类型记录、过期字段)、\n  集合的索引以及用户和角色。`apply` 比较 YAML 或 JSON 清单与服务器的差异,只执行\n  使两者一致所需的语句;--dry-run 只输出这些语句,--prune 还会删除清单中没有列出的对象。\n  新用户的密码取自 `password`,或 `password_env` 指定的环境变量。\n\n{}\n  SHOW MANIFEST\n  mikudb-cli -u root -P secret apply schema.yaml --dry-run\n",
+                "MANIFEST - 声明式模式管理".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
         "DROP USER" => {
             format!(
                 "\n{}\n\n{}\n  DROP USER <用户名>\n\n{}\n  删除数据库用户。\n  {} 此操作无法撤销!\n\n{}\n  DROP USER john\n  DROP USER old_admin\n",
@@ -551,6 +923,33 @@ fn get_command_help_zh(cmd: &str) -> Option<String> {
                 "示例".cyan().bold()
             )
         }
+        "SHOW ADVISOR" => {
+            format!(
+                "\n{}\n\n{}\n  SHOW ADVISOR [ON <集合名>]\n\n{}\n  列出服务器索引顾问生成的索引建议,按估算收益降序排列。\n  需要在服务器配置中启用 [advisor] enabled = true。\n\n{}\n  SHOW ADVISOR\n  SHOW ADVISOR ON orders\n",
+                "SHOW ADVISOR - 索引建议".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "SHOW SCHEMA" => {
+            format!(
+                "\n{}\n\n{}\n  SHOW SCHEMA ON <集合名>\n\n{}\n  显示集合的字段类型登记表: 每个字段的主类型及各类型出现次数。\n  需要先开启 track_types。\n\n{}\n  SHOW SCHEMA ON users\n",
+                "SHOW SCHEMA - 字段类型".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
+        "ALTER" | "ALTER COLLECTION" => {
+            format!(
+                "\n{}\n\n{}\n  ALTER COLLECTION <集合名> SET <选项> = <bool> [, ...]\n  ALTER COLLECTION <集合名> EXPIRE AFTER FIELD '<字段>'\n  ALTER COLLECTION <集合名> EXPIRE OFF\n\n{}\n  track_types   记录字段类型,写入改变字段主类型时发出警告\n  strict_types  直接拒绝这类写入\n  EXPIRE        字段值(DateTime、Timestamp 或 RFC 3339 字符串)早于当前时间的文档\n                由服务器后台任务删除,无需创建 TTL 索引\n\n{}\n  ALTER COLLECTION users SET track_types = true\n  ALTER COLLECTION users SET strict_types = true\n  ALTER COLLECTION sessions EXPIRE AFTER FIELD 'expires_at'\n",
+                "ALTER COLLECTION - 集合选项".green().bold(),
+                "语法".cyan().bold(),
+                "描述".cyan().bold(),
+                "示例".cyan().bold()
+            )
+        }
         "HELP" => {
             format!(
                 "\n{}\n\n{}\n  HELP\n  <命令> ?\n\n{}\n  显示帮助信息。\n  使用 'HELP' 查看通用帮助,或使用 '命令 ?' 查看特定命令帮助。\n\n{}\n  HELP\n  FIND ?\n  INSERT ?\n  LANG ?\n",
@@ -3,7 +3,8 @@
 //! 本模块实现 MQL 查询语句的语法高亮:
 //! - 关键字高亮 (FIND, INSERT, WHERE 等)
 //! - 函数高亮 (COUNT, SUM, AVG 等)
-//! - 字符串、数字、操作符着色
+//! - 字符串、数字(含十六进制、二进制)、操作符着色
+//! - 注释(--、//、/* */)暗色显示
 //! - 括号和特殊字符标记
 //! - 转义字符处理
 
@@ -38,15 +39,16 @@ impl MqlHighlighter {
                 "BEGIN", "COMMIT", "ROLLBACK", "TRANSACTION",
                 "AGGREGATE", "MATCH", "GROUP", "SORT", "PROJECT", "LOOKUP",
                 "UNWIND", "AS", "ON", "UNIQUE", "TEXT", "TTL",
+                "ARCHIVE", "ADVISOR", "DRY", "RUN", "ALTER", "SCHEMA", "TOKENIZER", "STOPWORDS", "STEMMER", "MAX_KEY_SIZE", "OVERSIZED", "TRUNCATE", "REJECT", "BATCH", "SIZE", "BACKUP", "RESTORE", "WITHOUT", "SYSTEM", "METADATA", "ONLY", "ADMIN", "CLUSTER", "INIT", "JOIN", "LOG", "LEVEL", "TARGET", "RESET", "STATS", "EXPIRE", "AFTER", "FIELD", "OFF", "OLDER", "THAN", "SEQUENCE", "SEQUENCES", "NEXTVAL", "START", "INCREMENT", "RETURNING", "MODIFY", "OLD", "NEW", "ANALYZE", "COMPACT", "FUNCTION", "FUNCTIONS", "CALL", "WASM", "GRAPH", "CONNECT", "DEPTH", "FACET", "BUCKET", "BUCKETS", "BOUNDARIES", "AUTO", "SNAPSHOT", "ROLLUP", "ROLLUPS", "MANIFEST", "ADD", "IMPORT", "EXPORT", "INFER", "DELIMITER",
                 "TRUE", "FALSE",
             ],
             // 内置函数(聚合、更新操作符、日期、字符串等)
             functions: vec![
                 "COUNT", "SUM", "AVG", "MIN", "MAX", "FIRST", "LAST",
-                "PUSH", "PULL", "ADDTOSET", "POP", "UNSET", "INC", "MUL",
-                "NOW", "DATE", "YEAR", "MONTH", "DAY", "HOUR", "MINUTE", "SECOND",
+                "PUSH", "PULL", "PULLALL", "ADDTOSET", "POP", "UNSET", "RENAME", "INC", "MUL",
+                "NOW", "DATE", "DATE_TRUNC", "YEAR", "MONTH", "DAY", "HOUR", "MINUTE", "SECOND",
                 "UPPER", "LOWER", "TRIM", "SUBSTR", "CONCAT", "SPLIT",
-                "SIZE", "TYPE", "OBJECTID",
+                "SIZE", "TYPE", "OBJECTID", "ISODATE", "UUID", "APPROX_EQ",
             ],
             // 比较和算术操作符
             operators: vec![
@@ -66,6 +68,7 @@ impl MqlHighlighter {
     /// - 操作符: 红色
     /// - 括号: 品红加粗
     /// - $ 字段: 绿色
+    /// - 注释: 暗色
     ///
     /// # Arguments
     /// * `input` - 原始 MQL 语句
@@ -129,6 +132,27 @@ impl MqlHighlighter {
                     current_word.clear();
                 }
                 result.push(ch);
+            // 处理注释: 单行注释到行尾,多行注释到 */
+            } else if matches!((ch, chars.peek()), ('-', Some('-')) | ('/', Some('/')) | ('/', Some('*'))) {
+                if !current_word.is_empty() {
+                    result.push_str(&self.highlight_word(&current_word));
+                    current_word.clear();
+                }
+
+                let block = chars.peek() == Some(&'*');
+                let mut comment = String::new();
+                comment.push(ch);
+                comment.push(chars.next().unwrap());
+                while let Some(&c) = chars.peek() {
+                    if !block && c == '\n' {
+                        break;
+                    }
+                    comment.push(chars.next().unwrap());
+                    if block && comment.len() > 3 && comment.ends_with("*/") {
+                        break;
+                    }
+                }
+                result.push_str(&comment.dimmed().to_string());
             // 处理操作符(可能是多字符,如 !=, <=)
             } else if self.is_operator_char(ch) {
                 if !current_word.is_empty() {
@@ -196,7 +220,7 @@ impl MqlHighlighter {
         }
 
         // 数字字面量
-        if word.parse::<f64>().is_ok() {
+        if word.parse::<f64>().is_ok() || is_radix_integer(word) {
             return word.bright_magenta().to_string();
         }
 
@@ -219,6 +243,16 @@ impl MqlHighlighter {
     }
 }
 
+/// 是否为 0x 十六进制或 0b 二进制整数
+fn is_radix_integer(word: &str) -> bool {
+    let lower = word.to_ascii_lowercase();
+    match (lower.strip_prefix("0x"), lower.strip_prefix("0b")) {
+        (Some(hex), _) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        (_, Some(bin)) => !bin.is_empty() && bin.chars().all(|c| c == '0' || c == '1'),
+        _ => false,
+    }
+}
+
 impl Default for MqlHighlighter {
     fn default() -> Self {
         Self::new()
@@ -2,9 +2,8 @@
 //!
 //! 提供多语言支持,支持中文和英文切换。
 
+use crate::settings;
 use std::sync::RwLock;
-use std::path::PathBuf;
-use std::fs;
 
 /// 支持的语言
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -50,31 +49,20 @@ pub fn current_language() -> Language {
     *CURRENT_LANGUAGE.read().unwrap()
 }
 
-/// 设置当前语言
-pub fn set_language(lang: Language) {
+/// 设置当前语言并保存到用户设置
+///
+/// 保存失败时语言仍在本次会话中生效,返回写入配置文件的错误。
+pub fn set_language(lang: Language) -> std::io::Result<()> {
     *CURRENT_LANGUAGE.write().unwrap() = lang;
-    let _ = save_language_config(lang);
-}
-
-/// 获取配置文件路径
-fn get_config_path() -> PathBuf {
-    dirs::home_dir()
-        .map(|h| h.join(".mikudb_config"))
-        .unwrap_or_else(|| PathBuf::from(".mikudb_config"))
-}
-
-/// 保存语言配置
-fn save_language_config(lang: Language) -> std::io::Result<()> {
-    let config_path = get_config_path();
-    fs::write(config_path, lang.to_config_str())
+    settings::update(|s| s.language = Some(lang.to_config_str().to_string()))
 }
 
 /// 加载语言配置
+///
+/// 从已加载的用户设置中读取语言偏好,需在 `settings::load()` 之后调用。
 pub fn load_language_config() {
-    if let Ok(content) = fs::read_to_string(get_config_path()) {
-        if let Some(lang) = Language::from_str(content.trim()) {
-            *CURRENT_LANGUAGE.write().unwrap() = lang;
-        }
+    if let Some(lang) = settings::current().language.as_deref().and_then(Language::from_str) {
+        *CURRENT_LANGUAGE.write().unwrap() = lang;
     }
 }
 
@@ -131,6 +119,7 @@ fn translate_en(key: &str) -> &'static str {
         // 状态信息
         "status.title" => "Connection Status",
         "status.server" => "Server",
+        "status.version" => "Server Version",
         "status.connected" => "Connected",
         "status.database" => "Current Database",
         "status.user" => "User",
@@ -148,12 +137,61 @@ fn translate_en(key: &str) -> &'static str {
         "result.affected" => "affected",
         "result.document" => "document",
         "result.documents" => "documents",
+        "result.examined" => "examined",
+        "result.returned" => "returned",
+        "result.full_scan" => "full scan",
+        "result.index" => "index",
+        "result.parse" => "parse",
+        "result.plan" => "plan",
+        "result.execute" => "execute",
+        "result.read" => "read",
 
         // 语言切换
         "lang.switched" => "Language switched to",
         "lang.current" => "Current language",
         "lang.usage" => "Usage: LANG <en|zh>",
 
+        // 输出格式
+        "format.switched" => "Output format switched to",
+        "format.current" => "Current output format",
+        "format.usage" => "Usage: FORMAT <table|json|json-pretty|csv|line>",
+
+        // 分页器
+        "pager.current" => "Pager",
+        "pager.disabled" => "Pager disabled",
+
+        // 提示符
+        "prompt.current" => "Prompt template",
+        "prompt.default" => "Using default prompt",
+
+        // 查询超时与中断
+        "timeout.current" => "Query timeout",
+        "timeout.disabled" => "Query timeout disabled",
+        "timeout.usage" => "Usage: \\timeout <5s|500ms|2m|off>",
+        "timeout.hint" => "Use \\timeout to change or disable the query timeout",
+        "query.running" => "Running query... (Ctrl+C to cancel)",
+        "query.cancelling" => "Cancelling query... (Ctrl+C again to abandon)",
+        "query.kill_failed" => "Could not cancel query on server (Ctrl+C again to abandon)",
+
+        // 语法错误提示
+        "syntax.did_you_mean" => "Did you mean",
+
+        // 宽文档显示
+        "fields.current" => "Selected fields",
+        "fields.all" => "Showing all fields",
+        "expand.on" => "Expanded display is on",
+        "expand.off" => "Expanded display is off",
+        "expand.usage" => "Usage: \\expand [on|off]",
+        "maxwidth.current" => "Max column width",
+        "maxwidth.unlimited" => "Column width is unlimited",
+        "maxwidth.usage" => "Usage: \\maxwidth <n|off> (n >= 2)",
+
+        // 语句格式化
+        "format_stmt.none" => "No statement to format yet",
+
+        // 用户设置
+        "settings.save_failed" => "Could not save settings to",
+
         _ => "",
     }
 }
@@ -194,6 +232,7 @@ fn translate_zh(key: &str) -> &'static str {
         // 状态信息
         "status.title" => "连接状态",
         "status.server" => "服务器",
+        "status.version" => "服务器版本",
         "status.connected" => "已连接",
         "status.database" => "当前数据库",
         "status.user" => "用户",
@@ -211,12 +250,61 @@ fn translate_zh(key: &str) -> &'static str {
         "result.affected" => "受影响",
         "result.document" => "文档",
         "result.documents" => "文档",
+        "result.examined" => "检查",
+        "result.returned" => "返回",
+        "result.full_scan" => "全集合扫描",
+        "result.index" => "索引",
+        "result.parse" => "解析",
+        "result.plan" => "计划",
+        "result.execute" => "执行",
+        "result.read" => "读取",
 
         // 语言切换
         "lang.switched" => "语言已切换到",
         "lang.current" => "当前语言",
         "lang.usage" => "用法: LANG <en|zh>",
 
+        // 输出格式
+        "format.switched" => "输出格式已切换到",
+        "format.current" => "当前输出格式",
+        "format.usage" => "用法: FORMAT <table|json|json-pretty|csv|line>",
+
+        // 分页器
+        "pager.current" => "分页器",
+        "pager.disabled" => "分页器已关闭",
+
+        // 提示符
+        "prompt.current" => "提示符模板",
+        "prompt.default" => "使用默认提示符",
+
+        // 查询超时与中断
+        "timeout.current" => "查询超时",
+        "timeout.disabled" => "查询超时已关闭",
+        "timeout.usage" => "用法: \\timeout <5s|500ms|2m|off>",
+        "timeout.hint" => "使用 \\timeout 调整或关闭查询超时",
+        "query.running" => "正在执行查询...(Ctrl+C 取消)",
+        "query.cancelling" => "正在取消查询...(再次 Ctrl+C 放弃等待)",
+        "query.kill_failed" => "无法在服务器上取消查询(再次 Ctrl+C 放弃等待)",
+
+        // 语法错误提示
+        "syntax.did_you_mean" => "你是否想输入",
+
+        // 宽文档显示
+        "fields.current" => "已选择的列",
+        "fields.all" => "显示全部列",
+        "expand.on" => "展开显示已开启",
+        "expand.off" => "展开显示已关闭",
+        "expand.usage" => "用法: \\expand [on|off]",
+        "maxwidth.current" => "最大列宽",
+        "maxwidth.unlimited" => "列宽不限制",
+        "maxwidth.usage" => "用法: \\maxwidth <n|off> (n >= 2)",
+
+        // 语句格式化
+        "format_stmt.none" => "还没有可以格式化的语句",
+
+        // 用户设置
+        "settings.save_failed" => "无法保存设置到",
+
         _ => "",
     }
 }
@@ -7,6 +7,15 @@
 //! - 多种输出格式(Table, JSON, CSV, Line)
 //! - 连接管理和认证
 //! - 多语言支持(中文/英文)
+//! - 会话偏好持久化(上次数据库、输出格式、分页器、提示符、查询超时)
+//! - 跨服务器/集合的数据比对(diff 子命令)
+//! - 连通性诊断(ping 子命令、`\ping`、`\conninfo`)
+//! - 等待服务器就绪的健康检查(healthcheck 子命令)
+//! - 按模板生成测试数据(seed 子命令)
+//! - 声明式清单的导出与同步(manifest、apply 子命令)
+//! - 数据库的导出与恢复(dump、restore 子命令)
+//! - CSV/NDJSON 文件的导入与导出(IMPORT、EXPORT 命令和 import、export 子命令)
+//! - 语法错误位置标记和关键字拼写建议
 
 pub mod cli;
 pub mod repl;
@@ -16,6 +25,15 @@ pub mod formatter;
 pub mod client;
 pub mod i18n;
 pub mod help;
+pub mod settings;
+pub mod diff;
+pub mod diagnostic;
+pub mod ping;
+pub mod health;
+pub mod seed;
+pub mod manifest;
+pub mod dump;
+pub mod transfer;
 
 pub use cli::Cli;
 pub use repl::Repl;
@@ -24,8 +42,9 @@ use thiserror::Error;
 
 /// 初始化 CLI 环境
 ///
-/// 加载用户配置(如语言设置)
+/// 加载用户配置(语言、上次使用的数据库、输出格式等)
 pub fn init() {
+    settings::load();
     i18n::load_language_config();
 }
 
@@ -50,6 +69,12 @@ pub struct Config {
     pub color: bool,
     /// 是否静默模式
     pub quiet: bool,
+    /// 紧凑输出: 每条语句输出一行 JSON 对象(用于脚本和 CI)
+    pub compact: bool,
+    /// 脚本模式下遇到错误后继续执行后续语句(默认在第一个错误处停止)
+    pub continue_on_error: bool,
+    /// 写入的应用标签,随查询发送,出现在变更事件和写入通知的 `meta.app` 中
+    pub app_tag: Option<String>,
 }
 
 impl Default for Config {
@@ -63,6 +88,9 @@ impl Default for Config {
             format: "table".to_string(),
             color: true,
             quiet: false,
+            compact: false,
+            continue_on_error: false,
+            app_tag: None,
         }
     }
 }
@@ -84,6 +112,14 @@ pub enum CliError {
     #[error("Query error: {0}")]
     Query(String),
 
+    /// MQL 语法错误(服务器无法解析语句或请求,响应的 `error_kind` 为 `parse`)
+    #[error("{0}")]
+    Syntax(String),
+
+    /// 查询超过服务器端超时限制(消息已包含超时时长)
+    #[error("{0}")]
+    Timeout(String),
+
     /// 服务器错误
     #[error("Server error: {0}")]
     Server(String),
@@ -105,5 +141,56 @@ pub enum CliError {
     Other(String),
 }
 
+impl CliError {
+    /// # Brief
+    /// 错误类别名称
+    ///
+    /// 用于紧凑 JSON 输出中的 `error.kind` 字段。
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CliError::Syntax(_) => "parse",
+            CliError::Query(_) | CliError::Server(_) => "execution",
+            CliError::Timeout(_) => "timeout",
+            CliError::Connection(_) | CliError::AuthFailed(_) | CliError::Parse(_) => "connection",
+            CliError::Io(_) => "io",
+            CliError::Interrupted => "interrupted",
+            CliError::Other(_) => "other",
+        }
+    }
+
+    /// # Brief
+    /// 对应的进程退出码
+    ///
+    /// 协议响应无法解析视为连接层问题。
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Syntax(_) => exit_code::PARSE_ERROR,
+            CliError::Query(_) | CliError::Server(_) | CliError::Timeout(_) => {
+                exit_code::EXECUTION_ERROR
+            }
+            CliError::Connection(_) | CliError::AuthFailed(_) | CliError::Parse(_) => {
+                exit_code::CONNECTION_ERROR
+            }
+            CliError::Io(_) | CliError::Interrupted | CliError::Other(_) => exit_code::FAILURE,
+        }
+    }
+}
+
+/// 非交互模式的进程退出码
+pub mod exit_code {
+    /// 全部语句执行成功
+    pub const SUCCESS: i32 = 0;
+    /// 其他错误(参数错误、脚本文件无法读取等)
+    pub const FAILURE: i32 = 1;
+    /// MQL 语法错误
+    pub const PARSE_ERROR: i32 = 2;
+    /// 语句执行失败
+    pub const EXECUTION_ERROR: i32 = 3;
+    /// 连接或认证失败
+    pub const CONNECTION_ERROR: i32 = 4;
+    /// diff 子命令发现两端数据不一致,或 apply --dry-run 发现待执行的变更
+    pub const DIFFERENCES_FOUND: i32 = 5;
+}
+
 /// CLI 结果类型
 pub type CliResult<T> = Result<T, CliError>;
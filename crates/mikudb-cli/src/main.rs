@@ -2,12 +2,30 @@
 //!
 //! 命令行入口,支持三种模式:
 //! - 交互式 REPL 模式(默认)
-//! - 单条查询执行模式(-e 参数)
+//! - 单条查询执行模式(-e 参数,可用 --param 绑定 `$n` 参数)
 //! - 脚本文件执行模式(-f 参数)
+//!
+//! 另外提供 `diff` 子命令比对两个服务器/集合的数据,`ping` 子命令诊断连通性和延迟,
+//! `healthcheck` 子命令等待服务器就绪,
+//! `seed` 子命令按模板生成测试数据,`manifest` / `apply` 子命令导出和同步声明式清单,
+//! `dump` / `restore` 子命令导出和恢复数据库,`import` / `export` 子命令导入导出 CSV、NDJSON 文件。
+//!
+//! 非交互模式的退出码: 0 成功, 2 语法错误, 3 执行错误, 4 连接错误,
+//! 5 diff 发现差异或 apply --dry-run 发现待执行的变更。
 
-use clap::Parser;
-use mikudb_cli::{Cli, Config, Repl};
+use clap::{Parser, Subcommand};
+use mikudb_cli::diff::{self, ServerUri};
+use mikudb_cli::dump::{self, ArchiveFormat};
+use mikudb_cli::manifest::{self, Manifest};
+use mikudb_cli::health;
+use mikudb_cli::ping;
+use mikudb_cli::seed::{self, Template};
+use mikudb_cli::transfer::{self, Export, FieldType, FileFormat, Import};
+use mikudb_cli::client::Client;
+use mikudb_boml::BomlValue;
+use mikudb_cli::{exit_code, Cli, CliResult, Config, Repl};
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// MikuDB CLI 命令行参数
 #[derive(Parser, Debug)]
@@ -32,7 +50,7 @@ struct Args {
     #[arg(short = 'P', long)]
     password: Option<String>,
 
-    /// 默认数据库
+    /// 默认数据库(REPL 模式下未指定时使用上次的数据库)
     #[arg(short, long)]
     database: Option<String>,
 
@@ -40,13 +58,21 @@ struct Args {
     #[arg(short, long)]
     execute: Option<String>,
 
+    /// 与 -e 一起使用:依次绑定语句中 `$1`、`$2`…… 的参数值(可重复)
+    ///
+    /// 值按扩展 JSON 解析(如 `42`、`true`、`{"$date": "..."}`),不是合法 JSON 时作为字符串
+    #[arg(long = "param", value_name = "VALUE", requires = "execute", value_parser = parse_param)]
+    params: Vec<BomlValue>,
+
     /// 执行脚本文件后退出
     #[arg(short, long)]
     file: Option<PathBuf>,
 
     /// 输出格式(table, json, json-pretty, csv, line)
-    #[arg(long, default_value = "table")]
-    format: String,
+    ///
+    /// 未指定时 REPL 使用上次保存的格式,否则为 table
+    #[arg(long)]
+    format: Option<String>,
 
     /// 禁用颜色输出
     #[arg(long)]
@@ -55,6 +81,209 @@ struct Args {
     /// 静默模式(不输出结果)
     #[arg(long)]
     quiet: bool,
+
+    /// 紧凑输出: 每条语句输出一行 JSON 对象(隐含 --format json)
+    #[arg(long)]
+    compact: bool,
+
+    /// 脚本模式下遇到错误后继续执行后续语句(默认在第一个错误处停止)
+    #[arg(long)]
+    continue_on_error: bool,
+
+    /// 写入的应用标签,出现在变更事件和写入通知的 `meta.app` 中,
+    /// 同步程序可据此忽略自己产生的写入
+    #[arg(long, value_name = "NAME")]
+    app_tag: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 子命令
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 比对两个服务器/集合的数据(迁移或复制后校验)
+    Diff {
+        /// 源端 URI: mikudb://[user[:password]@]host[:port][/database]
+        #[arg(long)]
+        source: String,
+
+        /// 目标端 URI,格式同 --source
+        #[arg(long)]
+        target: String,
+
+        /// 要比对的集合
+        #[arg(long)]
+        collection: String,
+
+        /// 目标端集合名称(默认与 --collection 相同)
+        #[arg(long)]
+        target_collection: Option<String>,
+
+        /// 每类差异最多列出的文档数
+        #[arg(long, default_value_t = 10)]
+        sample: usize,
+
+        /// 以 JSON 输出比对结果
+        #[arg(long)]
+        json: bool,
+    },
+    /// 测量连接各阶段耗时和协议往返延迟
+    Ping {
+        /// 心跳次数
+        #[arg(short, long, default_value_t = ping::DEFAULT_COUNT)]
+        count: u32,
+    },
+    /// 等待服务器就绪(容器入口、CI),以单行 JSON 输出结果
+    Healthcheck {
+        /// 最长等待时间,如 500ms、60s、2m;不指定时只检查一次
+        #[arg(long, value_parser = health::parse_duration)]
+        wait: Option<Duration>,
+
+        /// 两次尝试之间的间隔
+        #[arg(long, value_parser = health::parse_duration, default_value = "1s")]
+        interval: Duration,
+    },
+    /// 按模板生成测试数据并并行批量写入
+    Seed {
+        /// 目标集合
+        #[arg(long)]
+        collection: String,
+
+        /// 生成的文档数,支持 k/M 后缀(如 50k、1M)
+        #[arg(long, value_parser = seed::parse_count)]
+        count: u64,
+
+        /// JSON 模板文件,字符串中的 {{...}} 占位符替换为随机值
+        #[arg(long)]
+        template: PathBuf,
+
+        /// 每批写入的文档数
+        #[arg(long, default_value_t = seed::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+
+        /// 并行连接数
+        #[arg(long, default_value_t = seed::DEFAULT_WORKERS)]
+        workers: usize,
+
+        /// 随机数种子,相同种子生成相同的数据
+        #[arg(long, default_value_t = 3939)]
+        seed: u64,
+    },
+    /// 导出数据库、集合、索引和用户的声明式清单(YAML)
+    Manifest {
+        /// 以 JSON 输出
+        #[arg(long)]
+        json: bool,
+    },
+    /// 比较清单与服务器,只执行使两者一致所需的语句
+    Apply {
+        /// YAML 或 JSON 清单文件
+        manifest: PathBuf,
+
+        /// 只输出要执行的语句,不修改服务器
+        #[arg(long)]
+        dry_run: bool,
+
+        /// 同时删除清单中没有列出的数据库、集合、索引和用户
+        #[arg(long)]
+        prune: bool,
+    },
+    /// 导出数据库的集合数据、索引定义和用户到目录
+    Dump {
+        /// 只导出该数据库(默认全部)
+        #[arg(long = "db")]
+        db: Option<String>,
+
+        /// 输出目录
+        #[arg(long)]
+        out: PathBuf,
+
+        /// 集合数据格式: boml 或 json(扩展 JSON 行)
+        #[arg(long, default_value = "boml")]
+        format: String,
+
+        /// 每批读取的文档数
+        #[arg(long, default_value_t = dump::DEFAULT_BATCH_SIZE)]
+        batch_size: u32,
+    },
+    /// 从 CSV 或 NDJSON 文件导入文档到集合
+    Import {
+        /// 目标集合
+        collection: String,
+
+        /// 源文件
+        file: PathBuf,
+
+        /// 文件格式: csv 或 ndjson(默认按扩展名判断)
+        #[arg(long)]
+        format: Option<String>,
+
+        /// CSV 单元格不推断类型,全部作为字符串
+        #[arg(long)]
+        no_infer: bool,
+
+        /// 为字段指定类型,可重复: --type age=int(string/int/double/bool/date/objectid/json)
+        #[arg(long = "type", value_name = "FIELD=TYPE")]
+        types: Vec<String>,
+
+        /// 把文件中的列写为另一个字段,可重复: --map full_name=name
+        #[arg(long = "map", value_name = "COLUMN=FIELD")]
+        map: Vec<String>,
+
+        /// 每批插入的文档数
+        #[arg(long, default_value_t = transfer::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+
+        /// CSV 分隔符(`\t` 表示制表符)
+        #[arg(long, default_value = ",")]
+        delimiter: String,
+    },
+    /// 把集合导出为 CSV 或 NDJSON 文件
+    Export {
+        /// 源集合
+        collection: String,
+
+        /// 目标文件
+        file: PathBuf,
+
+        /// 文件格式: csv 或 ndjson(默认按扩展名判断)
+        #[arg(long)]
+        format: Option<String>,
+
+        /// 导出的字段,逗号分隔(CSV 默认取第一批文档的顶层字段)
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+
+        /// 把字段写为另一个列名,可重复: --map name=full_name
+        #[arg(long = "map", value_name = "FIELD=COLUMN")]
+        map: Vec<String>,
+
+        /// 每批读取的文档数
+        #[arg(long, default_value_t = transfer::DEFAULT_BATCH_SIZE as u32)]
+        batch_size: u32,
+
+        /// CSV 分隔符(`\t` 表示制表符)
+        #[arg(long, default_value = ",")]
+        delimiter: String,
+    },
+    /// 从 dump 输出目录恢复集合、索引、数据和用户
+    Restore {
+        /// dump 的输出目录
+        dir: PathBuf,
+
+        /// 只恢复该数据库(同时跳过用户)
+        #[arg(long = "db")]
+        db: Option<String>,
+
+        /// 插入前删除目标服务器上同名的集合
+        #[arg(long)]
+        drop: bool,
+
+        /// 每批写入的文档数
+        #[arg(long, default_value_t = dump::DEFAULT_BATCH_SIZE)]
+        batch_size: u32,
+    },
 }
 
 /// # Brief
@@ -72,6 +301,294 @@ async fn main() -> anyhow::Result<()> {
     // 解析命令行参数
     let args = Args::parse();
 
+    if let Some(Command::Diff { source, target, collection, target_collection, sample, json }) = args.command {
+        // URI 中未给出的凭据使用 -u/-P,再回退到默认账号
+        let defaults = Config::default();
+        let fallback = Config {
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            database: args.database,
+            ..defaults
+        };
+        let (source, target) = match (ServerUri::parse(&source), ServerUri::parse(&target)) {
+            (Ok(source), Ok(target)) => (source.to_config(&fallback), target.to_config(&fallback)),
+            (Err(e), _) | (_, Err(e)) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        };
+        let target_collection = target_collection.unwrap_or_else(|| collection.clone());
+
+        let report = match diff::run(&source, &target, &collection, &target_collection, sample).await {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        };
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            report.print();
+        }
+        std::process::exit(if report.is_identical() {
+            exit_code::SUCCESS
+        } else {
+            exit_code::DIFFERENCES_FOUND
+        });
+    }
+
+    if let Some(Command::Ping { count }) = args.command {
+        // 未给出凭据时使用默认账号,服务器不要求认证时不会用到
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            ..defaults
+        };
+        match ping::run(&config, count.max(1)).await {
+            Ok(report) => {
+                report.print();
+                std::process::exit(if report.failed == 0 { exit_code::SUCCESS } else { exit_code::CONNECTION_ERROR });
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if let Some(Command::Healthcheck { wait, interval }) = args.command {
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            ..defaults
+        };
+        let report = health::wait_ready(&config, wait.unwrap_or_default(), interval).await;
+        println!("{}", report.to_json());
+        std::process::exit(report.exit_code());
+    }
+
+    if let Some(Command::Seed { collection, count, template, batch_size, workers, seed }) = args.command {
+        let template = match Template::load(&template) {
+            Ok(template) => template,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        };
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            database: args.database,
+            ..defaults
+        };
+        match seed::run(&config, &collection, template, count, batch_size, workers, seed).await {
+            Ok(report) => {
+                report.print();
+                std::process::exit(exit_code::SUCCESS);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if let Some(Command::Manifest { json }) = args.command {
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            ..defaults
+        };
+        let output = match manifest::export(&config).await {
+            Ok(manifest) if json => serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string()),
+            Ok(manifest) => manifest.to_yaml().map_err(|e| e.to_string()),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        };
+        match output {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        }
+        std::process::exit(exit_code::SUCCESS);
+    }
+
+    if let Some(Command::Apply { manifest, dry_run, prune }) = args.command {
+        let desired = match Manifest::load(&manifest) {
+            Ok(desired) => desired,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        };
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            ..defaults
+        };
+        let plan = match manifest::plan(&config, &desired, prune).await {
+            Ok(plan) => plan,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        };
+        if dry_run || plan.is_empty() {
+            plan.print();
+            std::process::exit(if plan.is_empty() { exit_code::SUCCESS } else { exit_code::DIFFERENCES_FOUND });
+        }
+        for warning in &plan.warnings {
+            eprintln!("warning: {}", warning);
+        }
+        match manifest::apply(&config, &plan).await {
+            Ok(report) => {
+                println!("Applied {} change(s)", report.applied);
+                std::process::exit(exit_code::SUCCESS);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if let Some(Command::Dump { db, out, format, batch_size }) = args.command {
+        let format = match ArchiveFormat::parse(&format) {
+            Ok(format) => format,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        };
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            ..defaults
+        };
+        match dump::dump(&config, db.as_deref(), &out, format, batch_size.max(1)).await {
+            Ok(report) => {
+                report.print();
+                std::process::exit(exit_code::SUCCESS);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if let Some(Command::Restore { dir, db, drop, batch_size }) = args.command {
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            ..defaults
+        };
+        match dump::restore(&config, &dir, db.as_deref(), drop, batch_size.max(1)).await {
+            Ok(report) => {
+                report.print();
+                std::process::exit(if report.failed() == 0 { exit_code::SUCCESS } else { exit_code::EXECUTION_ERROR });
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if let Some(Command::Import { collection, file, format, no_infer, types, map, batch_size, delimiter }) = args.command {
+        let options = import_command(collection, file, format, no_infer, &types, &map, batch_size, &delimiter);
+        let import = match options {
+            Ok(import) => import,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        };
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            database: args.database,
+            ..defaults
+        };
+        let result = match Client::connect(&config).await {
+            Ok(client) => import.run(&client).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(report) => {
+                report.print();
+                std::process::exit(if report.failed == 0 { exit_code::SUCCESS } else { exit_code::EXECUTION_ERROR });
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
+    if let Some(Command::Export { collection, file, format, fields, map, batch_size, delimiter }) = args.command {
+        let options = export_command(collection, file, format, fields, &map, batch_size, &delimiter);
+        let export = match options {
+            Ok(export) => export,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(exit_code::FAILURE);
+            }
+        };
+        let defaults = Config::default();
+        let config = Config {
+            host: args.host,
+            port: args.port,
+            user: args.user.unwrap_or(defaults.user.clone()),
+            password: args.password.unwrap_or(defaults.password.clone()),
+            database: args.database,
+            ..defaults
+        };
+        let result = match Client::connect(&config).await {
+            Ok(client) => export.run(&client).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(report) => {
+                report.print();
+                std::process::exit(exit_code::SUCCESS);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
     let user = match args.user {
         Some(u) => u,
         None => {
@@ -97,29 +614,58 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    // REPL 模式下,未显式指定的数据库和格式回退到上次会话的设置;
+    // 脚本模式保持可重复的默认行为
+    let interactive = args.execute.is_none() && args.file.is_none();
+    let saved = mikudb_cli::settings::current();
+    let database = match args.database {
+        Some(db) => Some(db),
+        None if interactive => saved.last_database,
+        None => None,
+    };
+    let saved_format = if interactive { saved.format } else { None };
+    let format = output_format(args.format, args.compact, saved_format);
+
     let config = Config {
         host: args.host,
         port: args.port,
         user,
         password,
-        database: args.database,
-        format: args.format,
+        database,
+        format,
         color: !args.no_color,
         quiet: args.quiet,
+        compact: args.compact,
+        continue_on_error: args.continue_on_error,
+        app_tag: args.app_tag,
     };
 
-    // 单条查询模式
-    if let Some(query) = args.execute {
-        let mut cli = Cli::new(config).await?;
-        cli.execute(&query).await?;
-        return Ok(());
-    }
+    // 非交互模式: 单条查询(-e)或脚本文件(-f),以退出码报告结果
+    if !interactive {
+        let compact = config.compact;
+        let mut cli = match Cli::new(config).await {
+            Ok(cli) => cli,
+            Err(e) => {
+                if compact {
+                    println!("{}", mikudb_cli::cli::error_to_json(None, &e));
+                } else {
+                    eprintln!("Error: {}", e);
+                }
+                std::process::exit(e.exit_code());
+            }
+        };
 
-    // 脚本文件模式
-    if let Some(file) = args.file {
-        let mut cli = Cli::new(config).await?;
-        cli.execute_file(&file).await?;
-        return Ok(());
+        let result = match (args.execute, args.file) {
+            (Some(query), _) if !args.params.is_empty() => cli.execute_with_params(&query, &args.params).await,
+            (Some(query), _) => cli.execute(&query).await,
+            (None, Some(file)) => cli.execute_file(&file).await,
+            (None, None) => unreachable!("non-interactive mode requires -e or -f"),
+        };
+
+        std::process::exit(match result {
+            Ok(()) => exit_code::SUCCESS,
+            Err(e) => e.exit_code(),
+        });
     }
 
     // 默认进入 REPL 交互模式
@@ -128,3 +674,100 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// # Brief
+/// 确定结果的输出格式
+///
+/// `--compact` 隐含 JSON;未指定格式时使用上次会话保存的格式,再回退到表格。
+///
+/// # Arguments
+/// * `format` - `--format` 参数
+/// * `compact` - 是否启用紧凑输出
+/// * `saved` - 上次会话保存的格式(仅 REPL 模式)
+fn output_format(format: Option<String>, compact: bool, saved: Option<String>) -> String {
+    if compact {
+        return "json".to_string();
+    }
+    format.or(saved).unwrap_or_else(|| "table".to_string())
+}
+
+/// # Brief
+/// 由 import 子命令的参数构造导入命令
+#[allow(clippy::too_many_arguments)]
+fn import_command(
+    collection: String,
+    file: PathBuf,
+    format: Option<String>,
+    no_infer: bool,
+    types: &[String],
+    map: &[String],
+    batch_size: usize,
+    delimiter: &str,
+) -> CliResult<Import> {
+    let mut import = Import::new(collection, file);
+    import.format = format.as_deref().map(FileFormat::parse).transpose()?;
+    import.infer = !no_infer;
+    for pair in types {
+        let (field, field_type) = transfer::parse_pair(pair)?;
+        import.types.push((field, FieldType::parse(&field_type)?));
+    }
+    import.mapping = map.iter().map(|pair| transfer::parse_pair(pair)).collect::<CliResult<_>>()?;
+    import.batch_size = batch_size;
+    import.delimiter = transfer::parse_delimiter(delimiter)?;
+    Ok(import)
+}
+
+/// # Brief
+/// 由 export 子命令的参数构造导出命令
+fn export_command(
+    collection: String,
+    file: PathBuf,
+    format: Option<String>,
+    fields: Vec<String>,
+    map: &[String],
+    batch_size: u32,
+    delimiter: &str,
+) -> CliResult<Export> {
+    let mut export = Export::new(collection, file);
+    export.format = format.as_deref().map(FileFormat::parse).transpose()?;
+    export.fields = fields;
+    export.mapping = map.iter().map(|pair| transfer::parse_pair(pair)).collect::<CliResult<_>>()?;
+    export.batch_size = batch_size;
+    export.delimiter = transfer::parse_delimiter(delimiter)?;
+    Ok(export)
+}
+
+/// # Brief
+/// 解析 --param 的参数值
+///
+/// 值按扩展 JSON 解析,不是合法 JSON 时作为字符串,便于在命令行直接传递文本。
+fn parse_param(value: &str) -> Result<BomlValue, String> {
+    match serde_json::from_str::<serde_json::Value>(value) {
+        Ok(json) => mikudb_boml::from_extended_json(&json).map_err(|e| e.to_string()),
+        Err(_) => Ok(BomlValue::String(value.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_implies_json() {
+        let args = Args::try_parse_from(["mikudb-cli", "--compact", "--format", "table", "-e", "SHOW STATUS"]).unwrap();
+        assert_eq!(output_format(args.format, args.compact, None), "json");
+
+        let args = Args::try_parse_from(["mikudb-cli", "-e", "SHOW STATUS"]).unwrap();
+        assert_eq!(output_format(args.format, args.compact, Some("csv".to_string())), "csv");
+        assert_eq!(output_format(None, false, None), "table");
+    }
+
+    #[test]
+    fn test_stop_on_error_by_default() {
+        let args = Args::try_parse_from(["mikudb-cli", "-f", "script.mql"]).unwrap();
+        assert!(!args.continue_on_error);
+        let args = Args::try_parse_from(["mikudb-cli", "-f", "script.mql", "--continue-on-error"]).unwrap();
+        assert!(args.continue_on_error);
+        assert!(Args::try_parse_from(["mikudb-cli", "-f", "script.mql", "--fail-fast"]).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+//! 声明式清单模块
+//!
+//! 实现 `mikudb-cli manifest` 和 `mikudb-cli apply` 子命令,以 GitOps 的方式管理模式:
+//! - `manifest` 把服务器 `SHOW MANIFEST` 返回的清单输出为 YAML 或 JSON
+//! - `apply` 读取 YAML/JSON 清单,与服务器的当前状态比较,只执行使两者一致所需的语句
+//!
+//! 清单覆盖数据库、集合(固定大小限制、类型记录选项、过期字段)、索引以及用户的角色。
+//! 默认只创建和修改,`--prune` 时还删除清单中没有列出的数据库、集合、索引和用户。
+//! 固定大小限制在集合创建后不能修改,已有集合的限制与清单不同时只给出警告。
+//!
+//! # 清单格式
+//!
+//! ```yaml
+//! databases:
+//!   - name: shop
+//!     collections:
+//!       - name: orders
+//!         track_types: true
+//!         expire_after_field: expires_at
+//!         indexes:
+//!           - name: idx_customer
+//!             fields: [customer_id, created_at DESC]
+//!             unique: false
+//! users:
+//!   - username: app
+//!     roles: [readWrite]
+//!     password_env: APP_PASSWORD
+//! ```
+
+use crate::client::Client;
+use crate::{CliError, CliResult, Config};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// 默认数据库,总是存在且不能删除
+const DEFAULT_DATABASE: &str = "default";
+/// 不会被 `--prune` 删除的用户
+const ROOT_USER: &str = "root";
+
+/// 声明式清单
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// 数据库及其集合
+    #[serde(default)]
+    pub databases: Vec<DatabaseSpec>,
+    /// 用户及其角色
+    #[serde(default)]
+    pub users: Vec<UserSpec>,
+}
+
+/// 数据库
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseSpec {
+    /// 数据库名称
+    pub name: String,
+    /// 数据库中的集合
+    #[serde(default)]
+    pub collections: Vec<CollectionSpec>,
+}
+
+/// 集合
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionSpec {
+    /// 集合在数据库中的名称
+    pub name: String,
+    /// 固定大小集合的限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capped: Option<CappedSpec>,
+    /// 是否记录字段类型
+    #[serde(default)]
+    pub track_types: bool,
+    /// 是否拒绝改变字段主类型的写入
+    #[serde(default)]
+    pub strict_types: bool,
+    /// 文档按该字段的时间过期
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire_after_field: Option<String>,
+    /// 集合的索引
+    #[serde(default)]
+    pub indexes: Vec<IndexSpec>,
+}
+
+/// 固定大小集合的限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CappedSpec {
+    /// 文档存储字节数之和的上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    /// 文档数上限
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_documents: Option<u64>,
+}
+
+/// 索引
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexSpec {
+    /// 索引名称
+    pub name: String,
+    /// 索引字段,降序字段写作 `field DESC`
+    pub fields: Vec<String>,
+    /// 是否唯一索引
+    #[serde(default)]
+    pub unique: bool,
+}
+
+/// 用户
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserSpec {
+    /// 用户名
+    pub username: String,
+    /// 角色,作用于所有数据库
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// 创建用户时使用的密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// 创建用户时从该环境变量读取密码,避免把密码写进清单
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+}
+
+impl Manifest {
+    /// # Brief
+    /// 读取清单文件
+    ///
+    /// YAML 是 JSON 的超集,两种格式都按 YAML 解析。
+    pub fn load(path: &Path) -> CliResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_yaml::from_str(&content)
+            .map_err(|e| CliError::Other(format!("Invalid manifest {}: {}", path.display(), e)))
+    }
+
+    /// # Brief
+    /// 通过 `SHOW MANIFEST` 获取服务器的当前清单
+    pub async fn fetch(client: &Client) -> CliResult<Self> {
+        let result = client.query("SHOW MANIFEST").await?;
+        let document = match result.documents.into_iter().next() {
+            Some(document) => document,
+            None => {
+                return Err(CliError::Server(
+                    result.message.unwrap_or_else(|| "SHOW MANIFEST returned no document".to_string()),
+                ))
+            }
+        };
+        serde_json::from_value(document).map_err(|e| CliError::Parse(format!("Invalid manifest: {}", e)))
+    }
+
+    /// # Brief
+    /// 输出为 YAML 文本
+    pub fn to_yaml(&self) -> CliResult<String> {
+        serde_yaml::to_string(self).map_err(|e| CliError::Other(e.to_string()))
+    }
+
+    fn database(&self, name: &str) -> Option<&DatabaseSpec> {
+        self.databases.iter().find(|database| database.name == name)
+    }
+
+    fn user(&self, username: &str) -> Option<&UserSpec> {
+        self.users.iter().find(|user| user.username == username)
+    }
+}
+
+impl DatabaseSpec {
+    fn collection(&self, name: &str) -> Option<&CollectionSpec> {
+        self.collections.iter().find(|collection| collection.name == name)
+    }
+}
+
+/// 使服务器与清单一致所需执行的一条语句
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// 语句所在的数据库,None 表示与当前数据库无关
+    pub database: Option<String>,
+    /// 要执行的 MQL 语句
+    pub statement: String,
+    /// 显示用的语句,密码已隐藏
+    pub display: String,
+}
+
+/// 清单与服务器的差异
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    /// 按执行顺序排列的语句
+    pub changes: Vec<Change>,
+    /// 无法通过语句消除的差异
+    pub warnings: Vec<String>,
+}
+
+impl Plan {
+    /// # Brief
+    /// 比较期望清单和服务器的当前清单
+    ///
+    /// # Arguments
+    /// * `desired` - 期望的清单
+    /// * `live` - 服务器的当前清单
+    /// * `prune` - 是否删除期望清单中没有列出的对象
+    ///
+    /// # Returns
+    /// 需要执行的语句和无法处理的差异
+    pub fn compute(desired: &Manifest, live: &Manifest, prune: bool) -> Self {
+        let mut plan = Self::default();
+        for database in &desired.databases {
+            let current = live.database(&database.name);
+            if current.is_none() && database.name != DEFAULT_DATABASE {
+                plan.push(None, format!("CREATE DATABASE {}", ident(&database.name)));
+            }
+            plan.diff_database(database, current, prune);
+        }
+        if prune {
+            for database in &live.databases {
+                if desired.database(&database.name).is_some() {
+                    continue;
+                }
+                if database.name == DEFAULT_DATABASE {
+                    // 默认数据库不能删除,逐个删除其中的集合
+                    let empty = DatabaseSpec { name: database.name.clone(), collections: Vec::new() };
+                    plan.diff_database(&empty, Some(database), true);
+                } else {
+                    plan.push(None, format!("DROP DATABASE {}", ident(&database.name)));
+                }
+            }
+        }
+
+        for user in &desired.users {
+            match live.user(&user.username) {
+                Some(current) => plan.diff_user(user, current),
+                None => plan.create_user(user),
+            }
+        }
+        if prune {
+            for user in &live.users {
+                if user.username != ROOT_USER && desired.user(&user.username).is_none() {
+                    plan.push(None, format!("DROP USER {}", string(&user.username)));
+                }
+            }
+        }
+        plan
+    }
+
+    /// # Brief
+    /// 是否没有需要执行的语句
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// # Brief
+    /// 以文本形式输出计划
+    pub fn print(&self) {
+        for warning in &self.warnings {
+            println!("{} {}", "warning:".yellow().bold(), warning);
+        }
+        if self.changes.is_empty() {
+            println!("{}", "Server matches the manifest, nothing to apply".green());
+            return;
+        }
+        println!("{} change(s):", self.changes.len());
+        for change in &self.changes {
+            match &change.database {
+                Some(database) => println!("  [{}] {}", database.cyan(), change.display),
+                None => println!("  {}", change.display),
+            }
+        }
+    }
+
+    fn push(&mut self, database: Option<&str>, statement: String) {
+        self.changes.push(Change {
+            database: database.map(str::to_string),
+            display: statement.clone(),
+            statement,
+        });
+    }
+
+    fn diff_database(&mut self, desired: &DatabaseSpec, current: Option<&DatabaseSpec>, prune: bool) {
+        let database = Some(desired.name.as_str());
+        for collection in &desired.collections {
+            match current.and_then(|current| current.collection(&collection.name)) {
+                Some(existing) => self.diff_collection(&desired.name, collection, existing, prune),
+                None => self.create_collection(&desired.name, collection),
+            }
+        }
+        if let (true, Some(current)) = (prune, current) {
+            for collection in &current.collections {
+                if desired.collection(&collection.name).is_none() {
+                    self.push(database, format!("DROP COLLECTION {}", ident(&collection.name)));
+                }
+            }
+        }
+    }
+
+    fn create_collection(&mut self, database: &str, collection: &CollectionSpec) {
+        let mut statement = format!("CREATE COLLECTION {}", ident(&collection.name));
+        if let Some(capped) = collection.capped {
+            statement.push_str(" (CAPPED");
+            if let Some(size) = capped.max_size {
+                statement.push_str(&format!(" SIZE {}", size));
+            }
+            if let Some(max) = capped.max_documents {
+                statement.push_str(&format!(" MAX {}", max));
+            }
+            statement.push(')');
+        }
+        self.push(Some(database), statement);
+        if collection.track_types || collection.strict_types {
+            self.push(Some(database), set_type_options(collection));
+        }
+        if let Some(field) = &collection.expire_after_field {
+            self.push(Some(database), expire_after(&collection.name, field));
+        }
+        for index in &collection.indexes {
+            self.push(Some(database), create_index(&collection.name, index));
+        }
+    }
+
+    fn diff_collection(&mut self, database: &str, desired: &CollectionSpec, current: &CollectionSpec, prune: bool) {
+        if desired.capped != current.capped {
+            self.warnings.push(format!(
+                "Capped limits of {}.{} differ from the manifest and cannot be changed; recreate the collection to apply them",
+                database, desired.name
+            ));
+        }
+        if (desired.track_types, desired.strict_types) != (current.track_types, current.strict_types) {
+            self.push(Some(database), set_type_options(desired));
+        }
+        if desired.expire_after_field != current.expire_after_field {
+            let statement = match &desired.expire_after_field {
+                Some(field) => expire_after(&desired.name, field),
+                None => format!("ALTER COLLECTION {} EXPIRE OFF", ident(&desired.name)),
+            };
+            self.push(Some(database), statement);
+        }
+
+        for index in &desired.indexes {
+            match current.indexes.iter().find(|existing| existing.name == index.name) {
+                None => self.push(Some(database), create_index(&desired.name, index)),
+                Some(existing) if !same_index(index, existing) => {
+                    self.push(Some(database), drop_index(&desired.name, &index.name));
+                    self.push(Some(database), create_index(&desired.name, index));
+                }
+                Some(_) => {}
+            }
+        }
+        if prune {
+            for index in &current.indexes {
+                if !desired.indexes.iter().any(|wanted| wanted.name == index.name) {
+                    self.push(Some(database), drop_index(&desired.name, &index.name));
+                }
+            }
+        }
+    }
+
+    fn create_user(&mut self, user: &UserSpec) {
+        let password = match (&user.password, &user.password_env) {
+            (Some(password), _) => password.clone(),
+            (None, Some(var)) => match std::env::var(var) {
+                Ok(password) => password,
+                Err(_) => {
+                    self.warnings.push(format!(
+                        "User {} does not exist and environment variable {} is not set; skipped",
+                        user.username, var
+                    ));
+                    return;
+                }
+            },
+            (None, None) => {
+                self.warnings.push(format!(
+                    "User {} does not exist and the manifest gives no password or password_env; skipped",
+                    user.username
+                ));
+                return;
+            }
+        };
+
+        let head = format!("CREATE USER {} WITH PASSWORD ", string(&user.username));
+        let roles = match user.roles.is_empty() {
+            true => String::new(),
+            false => format!(" ROLE {}", idents(&user.roles)),
+        };
+        self.changes.push(Change {
+            database: None,
+            statement: format!("{}{}{}", head, string(&password), roles),
+            display: format!("{}'***'{}", head, roles),
+        });
+    }
+
+    fn diff_user(&mut self, desired: &UserSpec, current: &UserSpec) {
+        let wanted: BTreeSet<&String> = desired.roles.iter().collect();
+        let existing: BTreeSet<&String> = current.roles.iter().collect();
+        let add: Vec<String> = wanted.difference(&existing).map(|role| role.to_string()).collect();
+        let remove: Vec<String> = existing.difference(&wanted).map(|role| role.to_string()).collect();
+        if add.is_empty() && remove.is_empty() {
+            return;
+        }
+
+        let mut statement = format!("ALTER USER {}", string(&desired.username));
+        if !add.is_empty() {
+            statement.push_str(&format!(" ADD ROLE {}", idents(&add)));
+        }
+        if !remove.is_empty() {
+            statement.push_str(&format!(" DROP ROLE {}", idents(&remove)));
+        }
+        self.push(None, statement);
+    }
+}
+
+/// 执行结果
+#[derive(Debug, Clone)]
+pub struct ApplyReport {
+    /// 执行的语句数
+    pub applied: usize,
+}
+
+/// # Brief
+/// 获取服务器的当前清单
+///
+/// # Arguments
+/// * `config` - 连接配置
+pub async fn export(config: &Config) -> CliResult<Manifest> {
+    let client = Client::connect(config).await?;
+    Manifest::fetch(&client).await
+}
+
+/// # Brief
+/// 计算清单与服务器的差异
+///
+/// # Arguments
+/// * `config` - 连接配置
+/// * `desired` - 期望的清单
+/// * `prune` - 是否删除清单中没有列出的对象
+pub async fn plan(config: &Config, desired: &Manifest, prune: bool) -> CliResult<Plan> {
+    let client = Client::connect(config).await?;
+    let live = Manifest::fetch(&client).await?;
+    Ok(Plan::compute(desired, &live, prune))
+}
+
+/// # Brief
+/// 按顺序执行计划中的语句,遇到第一个失败的语句时停止
+///
+/// # Arguments
+/// * `config` - 连接配置
+/// * `plan` - 由 [`plan`] 计算的计划
+///
+/// # Returns
+/// 执行结果;语句失败时返回 Query 错误,消息中包含失败的语句
+pub async fn apply(config: &Config, plan: &Plan) -> CliResult<ApplyReport> {
+    let client = Client::connect(config).await?;
+    let mut current: Option<&str> = None;
+    for (applied, change) in plan.changes.iter().enumerate() {
+        if let Some(database) = change.database.as_deref() {
+            if current != Some(database) {
+                client.use_database(database).await?;
+                current = Some(database);
+            }
+        }
+        let failed = |message: String| {
+            CliError::Query(format!("{} failed after {} change(s): {}", change.display, applied, message))
+        };
+        let result = client.query(&change.statement).await.map_err(|e| failed(e.to_string()))?;
+        // 用户管理语句以消息报告失败
+        if let Some(message) = result.message.filter(|message| message.starts_with("Error")) {
+            return Err(failed(message));
+        }
+        println!("{} {}", "✓".green(), change.display);
+    }
+    Ok(ApplyReport { applied: plan.changes.len() })
+}
+
+fn set_type_options(collection: &CollectionSpec) -> String {
+    format!(
+        "ALTER COLLECTION {} SET track_types = {}, strict_types = {}",
+        ident(&collection.name),
+        collection.track_types,
+        collection.strict_types
+    )
+}
+
+fn expire_after(collection: &str, field: &str) -> String {
+    format!("ALTER COLLECTION {} EXPIRE AFTER FIELD {}", ident(collection), string(field))
+}
+
+fn create_index(collection: &str, index: &IndexSpec) -> String {
+    let fields: Vec<String> = index
+        .fields
+        .iter()
+        .map(|field| match index_field(field) {
+            (path, true) => format!("{} DESC", ident(path)),
+            (path, false) => ident(path),
+        })
+        .collect();
+    format!(
+        "CREATE {}INDEX {} ON {} ({})",
+        if index.unique { "UNIQUE " } else { "" },
+        ident(&index.name),
+        ident(collection),
+        fields.join(", ")
+    )
+}
+
+fn drop_index(collection: &str, index: &str) -> String {
+    format!("DROP INDEX {} ON {}", ident(index), ident(collection))
+}
+
+fn same_index(a: &IndexSpec, b: &IndexSpec) -> bool {
+    a.unique == b.unique
+        && a.fields.len() == b.fields.len()
+        && a.fields.iter().zip(&b.fields).all(|(x, y)| index_field(x) == index_field(y))
+}
+
+/// # Brief
+/// 拆分索引字段的路径和方向
+///
+/// # Returns
+/// (字段路径, 是否降序)
+fn index_field(field: &str) -> (&str, bool) {
+    let field = field.trim();
+    match field.rsplit_once(char::is_whitespace) {
+        Some((path, order)) if order.eq_ignore_ascii_case("desc") => (path.trim_end(), true),
+        Some((path, order)) if order.eq_ignore_ascii_case("asc") => (path.trim_end(), false),
+        _ => (field, false),
+    }
+}
+
+/// 集合、索引等名称,始终加反引号以免与关键字冲突
+fn ident(name: &str) -> String {
+    format!("`{}`", name)
+}
+
+fn idents(names: &[String]) -> String {
+    names.iter().map(|name| ident(name)).collect::<Vec<_>>().join(", ")
+}
+
+/// 字符串字面量,包含双引号时使用单引号
+fn string(s: &str) -> String {
+    if s.contains('"') {
+        format!("'{}'", s)
+    } else {
+        format!("\"{}\"", s)
+    }
+}
@@ -0,0 +1,149 @@
+//! 连通性诊断模块
+//!
+//! 实现 `ping` 子命令和 REPL 的 `\ping`、`\conninfo` 命令,用于排查“为什么慢”:
+//! - 建立连接各阶段的耗时: TCP 连接、握手(Hello)、认证
+//! - 多次心跳(Ping)的协议往返延迟及 min/avg/max/p50
+//! - 握手协商得到的协议版本和服务器功能
+//!
+//! CLI 通过明文 TCP 连接服务器,TLS 握手耗时显示为未使用。
+
+use crate::client::{Client, ConnectTimings};
+use crate::{CliResult, Config};
+use colored::Colorize;
+use std::time::Duration;
+
+/// 未指定次数时发送的心跳数
+pub const DEFAULT_COUNT: u32 = 4;
+
+/// 多次心跳的测量结果
+#[derive(Debug, Clone)]
+pub struct PingReport {
+    /// 服务器地址(host:port)
+    pub address: String,
+    /// 建立连接各阶段的耗时
+    pub timings: ConnectTimings,
+    /// 每次成功心跳的往返延迟
+    pub rtts: Vec<Duration>,
+    /// 失败的心跳次数
+    pub failed: u32,
+}
+
+impl PingReport {
+    /// # Brief
+    /// 打印连接耗时、每次往返延迟和统计
+    pub fn print(&self) {
+        println!("{} {}", "PING".green().bold(), self.address);
+        print_timings(&self.timings);
+        for (seq, rtt) in self.rtts.iter().enumerate() {
+            println!("  seq={} time={}", seq + 1, format_duration(*rtt));
+        }
+        print_rtt_summary(&self.rtts, self.failed);
+    }
+}
+
+/// # Brief
+/// 执行 ping 子命令: 新建连接并发送 `count` 次心跳
+///
+/// 连接中途断开时停止,剩余次数计为失败。
+///
+/// # Arguments
+/// * `config` - 连接配置
+/// * `count` - 心跳次数
+///
+/// # Returns
+/// 测量结果;无法建立连接或认证失败时返回错误
+pub async fn run(config: &Config, count: u32) -> CliResult<PingReport> {
+    let client = Client::connect(config).await?;
+    let rtts = ping_times(&client, count).await;
+    Ok(PingReport {
+        address: format!("{}:{}", config.host, config.port),
+        timings: client.connect_timings(),
+        failed: count - rtts.len() as u32,
+        rtts,
+    })
+}
+
+/// # Brief
+/// 在已有连接上发送 `count` 次心跳,返回成功的往返延迟
+pub async fn ping_times(client: &Client, count: u32) -> Vec<Duration> {
+    let mut rtts = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        match client.ping().await {
+            Ok(rtt) => rtts.push(rtt),
+            Err(e) => {
+                eprintln!("  {}", e.to_string().red());
+                break;
+            }
+        }
+    }
+    rtts
+}
+
+/// # Brief
+/// 打印往返延迟统计: 成功/失败次数和 min/avg/max/p50
+pub fn print_rtt_summary(rtts: &[Duration], failed: u32) {
+    println!(
+        "--- {} pings, {} ok, {} failed ---",
+        rtts.len() as u32 + failed,
+        rtts.len(),
+        failed
+    );
+    if rtts.is_empty() {
+        return;
+    }
+    let mut sorted = rtts.to_vec();
+    sorted.sort();
+    let avg = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+    println!(
+        "rtt min/avg/max/p50 = {}/{}/{}/{}",
+        format_duration(sorted[0]),
+        format_duration(avg),
+        format_duration(sorted[sorted.len() - 1]),
+        format_duration(sorted[sorted.len() / 2])
+    );
+}
+
+/// # Brief
+/// 打印当前连接的协商结果和建立连接的耗时(`\conninfo`)
+pub fn print_conninfo(client: &Client) {
+    println!("{}", "Connection".green().bold());
+    println!("  server:        {}:{}", client.host(), client.port());
+    println!("  user:          {}", client.user());
+    match client.session_id() {
+        Some(id) => println!("  session:       {}", id),
+        None => println!("  session:       (none)"),
+    }
+    match client.server_info() {
+        Some(info) => {
+            let features = &info.features;
+            let compression = if features.compression.is_empty() {
+                "none".to_string()
+            } else {
+                features.compression.join(", ")
+            };
+            println!("  version:       {}", info.server_version);
+            println!("  protocols:     {:?}", info.protocol_versions);
+            println!("  max message:   {} bytes", info.max_message_size);
+            println!("  auth required: {}", info.auth_required);
+            println!("  tls:           {}", features.tls);
+            println!("  compression:   {}", compression);
+            println!("  cluster:       {}", features.cluster);
+            println!("  wasm udf:      {}", features.wasm_udf);
+            println!("  cursors:       {}", features.cursors);
+        }
+        None => println!("  {}", "server does not support Hello, capabilities unknown".yellow()),
+    }
+    print_timings(&client.connect_timings());
+}
+
+fn print_timings(timings: &ConnectTimings) {
+    let optional = |d: Option<Duration>, missing: &str| d.map_or_else(|| missing.to_string(), format_duration);
+    println!("  tcp connect:   {}", format_duration(timings.tcp));
+    println!("  tls handshake: not used (plain TCP)");
+    println!("  hello:         {}", optional(timings.hello, "not supported"));
+    println!("  auth:          {}", optional(timings.auth, "not required"));
+}
+
+fn format_duration(d: Duration) -> String {
+    format!("{:.3} ms", d.as_secs_f64() * 1000.0)
+}
@@ -6,25 +6,40 @@
 //! - 命令历史管理
 //! - 内置命令处理 (help, exit, status 等)
 //! - 输入验证(括号匹配)
+//! - 会话偏好(数据库、输出格式、分页器、提示符、查询超时)持久化
+//! - 长查询进度提示,Ctrl+C 通过 KillOp 中断正在执行的查询
 
 use crate::client::Client;
 use crate::completer::MqlCompleter;
-use crate::formatter::Formatter;
+use crate::diagnostic;
+use crate::formatter::{Formatter, OutputFormat, QueryResult};
 use crate::help;
 use crate::highlighter::MqlHighlighter;
 use crate::i18n::{current_language, set_language, t, Language};
+use crate::ping;
+use crate::settings;
+use crate::transfer::{Export, Import};
 use crate::{CliError, CliResult, Config};
 use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
 use rustyline::config::Configurer;
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
 use rustyline::{CompletionType, EditMode, Editor};
 use std::borrow::Cow;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// 查询执行超过该时长后显示进度提示
+const SPINNER_DELAY: Duration = Duration::from_millis(300);
 
 /// REPL 交互式环境
 ///
 /// 管理客户端连接、命令行编辑器和结果格式化。
 pub struct Repl {
+    /// 连接配置(发送 KillOp 或重连时使用)
+    config: Config,
     /// 数据库客户端连接
     client: Client,
     /// 结果格式化器
@@ -35,6 +50,8 @@ pub struct Repl {
     current_database: Option<String>,
     /// 历史记录文件路径
     history_file: String,
+    /// 最近执行的 MQL 语句(供 \format 使用)
+    last_statement: Option<String>,
 }
 
 /// Rustyline Helper
@@ -179,7 +196,12 @@ impl Repl {
     pub async fn new(config: Config) -> CliResult<Self> {
         // 连接到数据库
         let client = Client::connect(&config).await?;
-        let formatter = Formatter::new(&config.format, config.color);
+        let mut formatter = Formatter::new(&config.format, config.color);
+        let saved = settings::current();
+        if let Some(width) = saved.max_column_width {
+            formatter.set_max_width(width);
+        }
+        formatter.set_expanded(saved.expanded);
 
         // 创建 MQL Helper (补全 + 高亮)
         let helper = MqlHelper {
@@ -203,11 +225,13 @@ impl Repl {
         let _ = editor.load_history(&history_file);
 
         Ok(Self {
+            current_database: config.database.clone(),
+            config,
             client,
             formatter,
             editor,
-            current_database: config.database,
             history_file,
+            last_statement: None,
         })
     }
 
@@ -248,12 +272,22 @@ impl Repl {
                     }
 
                     // 执行 MQL 查询
-                    match self.client.query(line).await {
+                    self.last_statement = Some(line.to_string());
+                    match self.run_query(line).await {
                         Ok(result) => {
-                            self.formatter.print(&result);
+                            self.print_result(&result);
+                        }
+                        Err(e @ CliError::Timeout(_)) => {
+                            eprintln!("{} {}", "Timeout:".red().bold(), e);
+                            eprintln!("{}", t!("timeout.hint").dimmed());
                         }
                         Err(e) => {
                             eprintln!("{} {}", "Error:".red().bold(), e);
+                            if let CliError::Syntax(msg) = &e {
+                                if let Some(hint) = diagnostic::render_syntax_error(line, msg) {
+                                    eprintln!("{}", hint);
+                                }
+                            }
                         }
                     }
                 }
@@ -279,6 +313,76 @@ impl Repl {
         Ok(())
     }
 
+    /// # Brief
+    /// 执行查询并在等待期间处理进度提示和 Ctrl+C
+    ///
+    /// 查询超过 `SPINNER_DELAY` 仍未返回时显示带耗时的进度提示。
+    /// 第一次 Ctrl+C 发送 KillOp(服务器支持多路复用时在本连接上发送,否则通过新连接)并继续等待服务器返回;
+    /// 再次 Ctrl+C 则放弃等待并重新建立连接。
+    ///
+    /// # Arguments
+    /// * `line` - MQL 查询语句
+    ///
+    /// # Returns
+    /// 查询结果
+    async fn run_query(&mut self, line: &str) -> CliResult<QueryResult> {
+        let timeout_ms = settings::current().query_timeout_ms;
+        let request_id = Client::next_request_id();
+
+        let mut spinner: Option<ProgressBar> = None;
+        let mut kill_sent = false;
+        let mut abandoned = false;
+
+        let result = {
+            let query = self.client.query_with_timeout(line, timeout_ms, request_id);
+            tokio::pin!(query);
+
+            let show_spinner = tokio::time::sleep(SPINNER_DELAY);
+            tokio::pin!(show_spinner);
+
+            loop {
+                tokio::select! {
+                    result = &mut query => break result,
+                    _ = &mut show_spinner, if spinner.is_none() => {
+                        let pb = ProgressBar::new_spinner();
+                        pb.set_style(
+                            ProgressStyle::with_template("{spinner:.cyan} {msg} [{elapsed}]")
+                                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+                        );
+                        pb.set_message(t!("query.running"));
+                        pb.enable_steady_tick(Duration::from_millis(100));
+                        spinner = Some(pb);
+                    }
+                    _ = tokio::signal::ctrl_c() => {
+                        if kill_sent {
+                            abandoned = true;
+                            break Err(CliError::Interrupted);
+                        }
+                        kill_sent = true;
+                        let killed = self.client.kill_request(&self.config, request_id).await.unwrap_or(false);
+                        let message = if killed { t!("query.cancelling") } else { t!("query.kill_failed") };
+                        match &spinner {
+                            Some(pb) => pb.set_message(message),
+                            None => eprintln!("^C {}", message),
+                        }
+                    }
+                }
+            }
+        };
+
+        if let Some(pb) = spinner {
+            pb.finish_and_clear();
+        }
+
+        // 放弃等待后连接上还有未读取的响应,需要重新连接
+        if abandoned {
+            let config = Config { database: self.current_database.clone(), ..self.config.clone() };
+            self.client = Client::connect(&config).await?;
+        }
+
+        result
+    }
+
     /// # Brief
     /// 打印欢迎信息
     fn print_welcome(&self) {
@@ -304,18 +408,45 @@ impl Repl {
     /// # Brief
     /// 生成命令行提示符
     ///
-    /// 格式: "mikudb:database_name> " 或 "mikudb> "
+    /// 默认格式: "mikudb:database_name> " 或 "mikudb> "。
+    /// 配置了提示符模板时替换其中的 {db}、{user}、{host}、{port} 占位符,
+    /// 末尾补一个空格,与默认提示符保持一致。
     fn get_prompt(&self) -> String {
+        if let Some(template) = settings::current().prompt {
+            return template
+                .replace("{db}", self.current_database.as_deref().unwrap_or(""))
+                .replace("{user}", self.client.user())
+                .replace("{host}", self.client.host())
+                .replace("{port}", &self.client.port().to_string())
+                + " ";
+        }
+
         match &self.current_database {
             Some(db) => format!("mikudb:{}> ", db.cyan()),
             None => "mikudb> ".to_string(),
         }
     }
 
+    /// # Brief
+    /// 输出查询结果
+    ///
+    /// 启用分页器时将渲染结果交给分页器显示,分页器启动失败则直接输出。
+    fn print_result(&self, result: &QueryResult) {
+        if result.success {
+            if let Some(pager) = settings::current().pager_command() {
+                let output = self.formatter.render(result);
+                if page_output(&pager, &output).is_ok() {
+                    return;
+                }
+            }
+        }
+        self.formatter.print(result);
+    }
+
     /// # Brief
     /// 处理内置命令
     ///
-    /// 支持: exit, quit, help, clear, use, status
+    /// 支持: exit, quit, help, clear, use, status, format, pager, prompt, timeout, fields, expand, maxwidth, ping, conninfo, import, export 等
     ///
     /// # Returns
     /// true 表示命令已处理,false 表示需要发送到服务器
@@ -340,8 +471,14 @@ impl Repl {
             }
             "use" => {
                 if parts.len() > 1 {
-                    self.current_database = Some(parts[1].to_string());
-                    println!("Switched to database {}", parts[1].cyan());
+                    match self.client.use_database(parts[1]).await {
+                        Ok(()) => {
+                            self.current_database = Some(parts[1].to_string());
+                            save_settings(|s| s.last_database = Some(parts[1].to_string()));
+                            println!("Switched to database {}", parts[1].cyan());
+                        }
+                        Err(e) => println!("{} {}", "[X]".red(), e),
+                    }
                 } else {
                     println!("Usage: use <database>");
                 }
@@ -351,6 +488,38 @@ impl Repl {
                 self.print_status().await;
                 Ok(true)
             }
+            "\\ping" => {
+                match parts.get(1).map(|n| n.parse::<u32>()) {
+                    None => self.ping(ping::DEFAULT_COUNT).await,
+                    Some(Ok(count)) if count > 0 => self.ping(count).await,
+                    Some(_) => println!("Usage: \\ping [count]"),
+                }
+                Ok(true)
+            }
+            "import" => {
+                match Import::parse(line) {
+                    Ok(import) => match import.run(&self.client).await {
+                        Ok(report) => report.print(),
+                        Err(e) => println!("{} {}", "[X]".red(), e),
+                    },
+                    Err(e) => println!("{} {}", "[X]".red(), e),
+                }
+                Ok(true)
+            }
+            "export" => {
+                match Export::parse(line) {
+                    Ok(export) => match export.run(&self.client).await {
+                        Ok(report) => report.print(),
+                        Err(e) => println!("{} {}", "[X]".red(), e),
+                    },
+                    Err(e) => println!("{} {}", "[X]".red(), e),
+                }
+                Ok(true)
+            }
+            "\\conninfo" => {
+                ping::print_conninfo(&self.client);
+                Ok(true)
+            }
             "passwd" | "password" => {
                 self.change_password().await?;
                 Ok(true)
@@ -362,7 +531,9 @@ impl Repl {
             "lang" | "language" => {
                 if parts.len() > 1 {
                     if let Some(lang) = Language::from_str(parts[1]) {
-                        set_language(lang);
+                        if let Err(e) = set_language(lang) {
+                            report_save_error(&e);
+                        }
                         println!("{}: {}", t!("lang.switched"), lang.as_str());
                     } else {
                         println!("{}", t!("lang.usage"));
@@ -372,6 +543,171 @@ impl Repl {
                 }
                 Ok(true)
             }
+            "format" => {
+                if parts.len() > 1 {
+                    if let Some(format) = OutputFormat::from_name(parts[1]) {
+                        self.formatter.set_format(format);
+                        save_settings(|s| s.format = Some(format.name().to_string()));
+                        println!("{}: {}", t!("format.switched"), format.name());
+                    } else {
+                        println!("{}", t!("format.usage"));
+                    }
+                } else {
+                    println!("{}: {}", t!("format.current"), self.formatter.format().name());
+                }
+                Ok(true)
+            }
+            "pager" => {
+                match parts.get(1).map(|p| p.to_lowercase()) {
+                    None => {
+                        let current = settings::current();
+                        match current.pager_command() {
+                            Some(cmd) => println!("{}: {}", t!("pager.current"), cmd),
+                            None => println!("{}", t!("pager.disabled")),
+                        }
+                    }
+                    Some(arg) if arg == "off" => {
+                        save_settings(|s| s.pager_enabled = false);
+                        println!("{}", t!("pager.disabled"));
+                    }
+                    Some(arg) => {
+                        // "pager on" 沿用已保存的命令,其他参数视为分页器命令
+                        let command = (arg != "on").then(|| parts[1..].join(" "));
+                        save_settings(|s| {
+                            s.pager_enabled = true;
+                            if command.is_some() {
+                                s.pager = command;
+                            }
+                        });
+                        if let Some(cmd) = settings::current().pager_command() {
+                            println!("{}: {}", t!("pager.current"), cmd);
+                        }
+                    }
+                }
+                Ok(true)
+            }
+            "prompt" => {
+                // 保留模板中的原始空白,仅去掉命令名
+                let template = line[parts[0].len()..].trim();
+                if template.is_empty() {
+                    match settings::current().prompt {
+                        Some(p) => println!("{}: {}", t!("prompt.current"), p),
+                        None => println!("{}", t!("prompt.default")),
+                    }
+                } else if template.eq_ignore_ascii_case("reset") {
+                    save_settings(|s| s.prompt = None);
+                    println!("{}", t!("prompt.default"));
+                } else {
+                    // 配置文件中的值会去掉首尾空白,显示时再补上末尾的空格
+                    let template = template.trim_matches('"').trim().to_string();
+                    save_settings(|s| s.prompt = Some(template));
+                }
+                Ok(true)
+            }
+            "timeout" | "\\timeout" => {
+                match parts.get(1).map(|p| p.to_lowercase()) {
+                    None => match settings::current().query_timeout_ms {
+                        Some(ms) => println!("{}: {}", t!("timeout.current"), format_timeout(ms)),
+                        None => println!("{}", t!("timeout.disabled")),
+                    },
+                    Some(arg) if arg == "off" || arg == "0" => {
+                        save_settings(|s| s.query_timeout_ms = None);
+                        println!("{}", t!("timeout.disabled"));
+                    }
+                    Some(arg) => match parse_timeout(&arg) {
+                        Some(ms) => {
+                            save_settings(|s| s.query_timeout_ms = Some(ms));
+                            println!("{}: {}", t!("timeout.current"), format_timeout(ms));
+                        }
+                        None => println!("{}", t!("timeout.usage")),
+                    },
+                }
+                Ok(true)
+            }
+            "fields" | "\\fields" => {
+                // 列名之间允许逗号和空格
+                let list = line[parts[0].len()..].trim();
+                if list.eq_ignore_ascii_case("off") || list == "*" {
+                    self.formatter.set_fields(None);
+                } else if !list.is_empty() {
+                    let fields = list
+                        .split([',', ' '])
+                        .filter(|f| !f.is_empty())
+                        .map(String::from)
+                        .collect();
+                    self.formatter.set_fields(Some(fields));
+                }
+                match self.formatter.fields() {
+                    Some(fields) => println!("{}: {}", t!("fields.current"), fields.join(", ")),
+                    None => println!("{}", t!("fields.all")),
+                }
+                Ok(true)
+            }
+            "expand" | "\\expand" => {
+                let expanded = match parts.get(1).map(|p| p.to_lowercase()).as_deref() {
+                    None => !self.formatter.expanded(),
+                    Some("on") => true,
+                    Some("off") => false,
+                    Some(_) => {
+                        println!("{}", t!("expand.usage"));
+                        return Ok(true);
+                    }
+                };
+                self.formatter.set_expanded(expanded);
+                save_settings(|s| s.expanded = expanded);
+                println!("{}", if expanded { t!("expand.on") } else { t!("expand.off") });
+                Ok(true)
+            }
+            "\\format" => {
+                // 格式化参数中的语句,没有参数时格式化最近执行的语句
+                let statement = match line.split_once(char::is_whitespace) {
+                    Some((_, rest)) => rest.trim().to_string(),
+                    None => match &self.last_statement {
+                        Some(last) => last.clone(),
+                        None => {
+                            println!("{}", t!("format_stmt.none"));
+                            return Ok(true);
+                        }
+                    },
+                };
+                match self.client.format_query(&statement).await {
+                    Ok(formatted) => println!("{}", formatted),
+                    Err(e) => {
+                        eprintln!("{} {}", "Error:".red().bold(), e);
+                        if let CliError::Syntax(msg) = &e {
+                            if let Some(hint) = diagnostic::render_syntax_error(&statement, msg) {
+                                eprintln!("{}", hint);
+                            }
+                        }
+                    }
+                }
+                Ok(true)
+            }
+            "maxwidth" | "\\maxwidth" => {
+                match parts.get(1).map(|p| p.to_lowercase()) {
+                    None => {}
+                    Some(arg) if arg == "off" => {
+                        self.formatter.set_max_width(0);
+                        save_settings(|s| s.max_column_width = Some(0));
+                    }
+                    Some(arg) => match arg.parse::<usize>() {
+                        // 至少保留一个字符和省略号
+                        Ok(width) if width >= 2 => {
+                            self.formatter.set_max_width(width);
+                            save_settings(|s| s.max_column_width = Some(width));
+                        }
+                        _ => {
+                            println!("{}", t!("maxwidth.usage"));
+                            return Ok(true);
+                        }
+                    },
+                }
+                match self.formatter.max_width() {
+                    0 => println!("{}", t!("maxwidth.unlimited")),
+                    width => println!("{}: {}", t!("maxwidth.current"), width),
+                }
+                Ok(true)
+            }
             _ => Ok(false),
         }
     }
@@ -417,11 +753,21 @@ impl Repl {
         Ok(())
     }
 
+    /// # Brief
+    /// 在当前连接上发送心跳并打印往返延迟统计
+    async fn ping(&mut self, count: u32) {
+        let rtts = ping::ping_times(&self.client, count).await;
+        ping::print_rtt_summary(&rtts, count - rtts.len() as u32);
+    }
+
     /// # Brief
     /// 打印连接状态
     async fn print_status(&self) {
         println!("{}", t!("status.title").green().bold());
         println!("  {}: {}:{}", t!("status.server"), self.client.host(), self.client.port());
+        if let Some(info) = self.client.server_info() {
+            println!("  {}: {}", t!("status.version"), info.server_version);
+        }
         println!("  {}: {}", t!("status.user"), self.client.user());
         println!(
             "  {}: {}",
@@ -430,6 +776,89 @@ impl Repl {
                 .as_deref()
                 .unwrap_or("(none)")
         );
+        println!("  {}: {}", t!("status.format"), self.formatter.format().name());
         println!("  {}: {}", t!("status.connected"), t!("status.connected").green());
     }
 }
+
+/// # Brief
+/// 修改用户设置并写回配置文件,写入失败时提示用户
+///
+/// 设置在本次会话中仍然生效。
+fn save_settings<F: FnOnce(&mut settings::Settings)>(f: F) {
+    if let Err(e) = settings::update(f) {
+        report_save_error(&e);
+    }
+}
+
+/// # Brief
+/// 提示设置无法保存到配置文件
+fn report_save_error(error: &std::io::Error) {
+    println!(
+        "{} {} {}: {}",
+        "[!]".yellow(),
+        t!("settings.save_failed"),
+        settings::config_path().display(),
+        error
+    );
+}
+
+/// # Brief
+/// 解析超时时长
+///
+/// 支持 `500ms`、`5s`、`2m` 形式,不带单位时按秒计算。
+///
+/// # Arguments
+/// * `input` - 时长文本
+///
+/// # Returns
+/// 毫秒数,格式错误或为 0 时返回 None
+fn parse_timeout(input: &str) -> Option<u64> {
+    let input = input.trim();
+    let (number, scale) = if let Some(n) = input.strip_suffix("ms") {
+        (n, 1)
+    } else if let Some(n) = input.strip_suffix('s') {
+        (n, 1000)
+    } else if let Some(n) = input.strip_suffix('m') {
+        (n, 60_000)
+    } else {
+        (input, 1000)
+    };
+    let ms = number.trim().parse::<u64>().ok()?.checked_mul(scale)?;
+    (ms > 0).then_some(ms)
+}
+
+/// # Brief
+/// 将毫秒数格式化为可读的超时时长
+fn format_timeout(ms: u64) -> String {
+    if ms % 1000 == 0 {
+        format!("{}s", ms / 1000)
+    } else {
+        format!("{}ms", ms)
+    }
+}
+
+/// # Brief
+/// 通过分页器显示输出
+///
+/// # Arguments
+/// * `pager` - 分页器命令(以空白分隔参数)
+/// * `output` - 待显示的内容
+fn page_output(pager: &str, output: &str) -> std::io::Result<()> {
+    let mut parts = pager.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty pager command"))?;
+
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // 用户提前退出分页器时写入会失败,忽略即可
+        let _ = stdin.write_all(output.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
@@ -0,0 +1,576 @@
+//! 测试数据生成模块
+//!
+//! 实现 `mikudb-cli seed` 子命令,按模板批量生成假数据并并行写入,用于压测和演示:
+//! - 模板是一个 JSON 文档,字符串值中的 `{{...}}` 占位符替换为随机生成的值
+//! - 整个字符串只有一个占位符时生成对应类型的值(整数、浮点数、日期等),否则拼接为字符串
+//! - `{"$array": [min, max], "$of": <模板>}` 生成长度在范围内的嵌套数组
+//! - 多个连接并行执行 `INSERT INTO <collection> [...]` 批量写入
+//!
+//! 每个文档的随机数种子由 `--seed` 和文档序号决定,相同参数生成的数据完全相同,与并发度无关。
+//!
+//! # 占位符
+//!
+//! - `{{seq}}`: 文档序号,从 1 开始
+//! - `{{first_name}}`、`{{last_name}}`、`{{name}}`、`{{email}}`、`{{phone}}`
+//! - `{{city}}`、`{{country}}`、`{{word}}`、`{{sentence}}`、`{{uuid}}`、`{{bool}}`
+//! - `{{int:MIN:MAX}}`、`{{float:MIN:MAX}}`: 闭区间内的数值,浮点数保留两位小数
+//! - `{{date:FROM:TO}}`: 两个日期(`YYYY-MM-DD`)之间的时间点
+//! - `{{pick:a|b|c}}`: 从列表中任选一个
+
+use crate::client::Client;
+use crate::{CliError, CliResult, Config};
+use chrono::{DateTime, NaiveDate, SecondsFormat};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 默认每批写入的文档数
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+/// 默认并行连接数
+pub const DEFAULT_WORKERS: usize = 4;
+
+const FIRST_NAMES: &[&str] = &[
+    "Miku", "Rin", "Len", "Luka", "Kaito", "Meiko", "Gumi", "Teto", "Haku", "Neru", "Alice", "Bob",
+    "Carol", "David", "Emma", "Frank", "Grace", "Henry", "Ivy", "Jack", "Kate", "Liam", "Mia", "Noah",
+    "Olivia", "Paul", "Quinn", "Ruby", "Sam", "Tina", "Wei", "Xin", "Yuki", "Zoe",
+];
+const LAST_NAMES: &[&str] = &[
+    "Hatsune", "Kagamine", "Megurine", "Shion", "Sakine", "Kasane", "Smith", "Johnson", "Brown",
+    "Garcia", "Miller", "Davis", "Wilson", "Taylor", "Clark", "Lee", "Wang", "Zhang", "Li", "Chen",
+    "Tanaka", "Suzuki", "Sato", "Kim", "Park", "Nguyen", "Martin", "Bernard", "Muller", "Rossi",
+];
+const CITIES: &[&str] = &[
+    "Sapporo", "Tokyo", "Osaka", "Beijing", "Shanghai", "Shenzhen", "Hangzhou", "Seoul", "Singapore",
+    "London", "Paris", "Berlin", "Madrid", "Rome", "New York", "Chicago", "Toronto", "Sydney",
+    "Melbourne", "Sao Paulo",
+];
+const COUNTRIES: &[&str] = &[
+    "Japan", "China", "Korea", "Singapore", "United Kingdom", "France", "Germany", "Spain", "Italy",
+    "United States", "Canada", "Australia", "Brazil", "India",
+];
+const WORDS: &[&str] = &[
+    "alpha", "beta", "gamma", "delta", "cloud", "stream", "index", "vector", "melody", "rhythm",
+    "signal", "cache", "shard", "replica", "query", "record", "green", "blue", "orange", "silver",
+    "fast", "quiet", "bright", "smart", "simple", "remote", "local", "prime", "stable", "daily",
+];
+const EMAIL_DOMAINS: &[&str] = &["example.com", "example.org", "example.net", "mail.test"];
+
+/// 数据模板
+///
+/// 由 JSON 模板编译得到,生成文档时不再解析占位符。
+#[derive(Debug, Clone)]
+pub struct Template {
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    /// 已渲染为 MQL 文本的常量
+    Literal(String),
+    Object(Vec<(String, Node)>),
+    Array(Vec<Node>),
+    Repeat { min: u64, max: u64, item: Box<Node> },
+    /// 整个字符串只有一个占位符,生成对应类型的值
+    Value(Generator),
+    /// 包含占位符的字符串
+    Text(Vec<Part>),
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Generated(Generator),
+}
+
+#[derive(Debug, Clone)]
+enum Generator {
+    Seq,
+    FirstName,
+    LastName,
+    Name,
+    Email,
+    Phone,
+    City,
+    Country,
+    Word,
+    Sentence,
+    Uuid,
+    Bool,
+    Int(i64, i64),
+    Float(f64, f64),
+    /// Unix 时间戳(秒)闭区间
+    Date(i64, i64),
+    Pick(Vec<String>),
+}
+
+impl Template {
+    /// # Brief
+    /// 编译 JSON 模板
+    ///
+    /// # Arguments
+    /// * `template` - 模板文档,顶层必须是对象
+    ///
+    /// # Returns
+    /// 编译后的模板,占位符未知或参数无效时返回 Other 错误
+    pub fn parse(template: &Value) -> CliResult<Self> {
+        if !template.is_object() {
+            return Err(CliError::Other("Seed template must be a JSON object".to_string()));
+        }
+        Ok(Self { root: compile(template)? })
+    }
+
+    /// # Brief
+    /// 读取并编译模板文件
+    pub fn load(path: &std::path::Path) -> CliResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let template: Value = serde_json::from_str(&content)
+            .map_err(|e| CliError::Other(format!("Invalid seed template {}: {}", path.display(), e)))?;
+        Self::parse(&template)
+    }
+
+    /// # Brief
+    /// 生成第 `seq` 个文档的 MQL 文本
+    ///
+    /// # Arguments
+    /// * `seed` - 随机数种子
+    /// * `seq` - 文档序号,从 1 开始
+    pub fn render(&self, seed: u64, seq: u64) -> String {
+        let mut rng = Rng::new(seed ^ seq.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let mut out = String::new();
+        render(&self.root, &mut rng, seq, &mut out);
+        out
+    }
+}
+
+/// 生成结果
+#[derive(Debug, Clone)]
+pub struct SeedReport {
+    /// 目标集合
+    pub collection: String,
+    /// 写入的文档数
+    pub inserted: u64,
+    /// 总耗时
+    pub elapsed: Duration,
+}
+
+impl SeedReport {
+    /// # Brief
+    /// 打印写入数量和吞吐
+    pub fn print(&self) {
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        println!(
+            "{} {} documents into {} in {:.2}s ({:.0} docs/s)",
+            "SEEDED".green().bold(),
+            self.inserted,
+            self.collection,
+            secs,
+            self.inserted as f64 / secs
+        );
+    }
+}
+
+/// # Brief
+/// 执行 seed 子命令
+///
+/// 每个工作连接循环领取一批序号,生成文档后以一条 INSERT 写入;
+/// 任一批失败时其他连接在当前批结束后停止,已写入的数据保留。
+///
+/// # Arguments
+/// * `config` - 连接配置
+/// * `collection` - 目标集合
+/// * `template` - 数据模板
+/// * `count` - 生成的文档数
+/// * `batch_size` - 每批文档数
+/// * `workers` - 并行连接数
+/// * `seed` - 随机数种子
+///
+/// # Returns
+/// 生成结果
+pub async fn run(
+    config: &Config,
+    collection: &str,
+    template: Template,
+    count: u64,
+    batch_size: usize,
+    workers: usize,
+    seed: u64,
+) -> CliResult<SeedReport> {
+    let started = Instant::now();
+    let template = Arc::new(template);
+    let next = Arc::new(AtomicU64::new(0));
+    let batch_size = batch_size.max(1) as u64;
+
+    let progress = ProgressBar::new(count);
+    progress.set_style(
+        ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} [{elapsed}] {per_sec}")
+            .unwrap_or_else(|_| ProgressStyle::default_bar()),
+    );
+
+    let mut handles = Vec::new();
+    for _ in 0..workers.max(1) {
+        let client = Client::connect(config).await?;
+        let (template, next, progress) = (template.clone(), next.clone(), progress.clone());
+        let collection = collection.to_string();
+        handles.push(tokio::spawn(async move {
+            let mut inserted = 0u64;
+            loop {
+                let start = next.fetch_add(batch_size, Ordering::SeqCst);
+                if start >= count {
+                    return Ok(inserted);
+                }
+                let end = (start + batch_size).min(count);
+                let docs: Vec<String> = (start + 1..=end).map(|seq| template.render(seed, seq)).collect();
+                let query = format!("INSERT INTO {} [{}]", collection, docs.join(", "));
+                if let Err(e) = client.query(&query).await {
+                    // 让其他连接不再领取新的批次
+                    next.store(count, Ordering::SeqCst);
+                    return Err(e);
+                }
+                inserted += end - start;
+                progress.inc(end - start);
+            }
+        }));
+    }
+
+    let mut inserted = 0;
+    let mut first_error = None;
+    for handle in handles {
+        match handle.await {
+            Ok(Ok(n)) => inserted += n,
+            Ok(Err(e)) => {
+                first_error.get_or_insert(e);
+            }
+            Err(e) => {
+                first_error.get_or_insert(CliError::Other(format!("Seed worker failed: {}", e)));
+            }
+        }
+    }
+    progress.finish_and_clear();
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+
+    Ok(SeedReport {
+        collection: collection.to_string(),
+        inserted,
+        elapsed: started.elapsed(),
+    })
+}
+
+/// # Brief
+/// 解析文档数,支持 `k`/`m` 后缀(如 `50k`、`1M`)
+pub fn parse_count(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (digits, factor) = match s.chars().last() {
+        Some('k' | 'K') => (&s[..s.len() - 1], 1_000),
+        Some('m' | 'M') => (&s[..s.len() - 1], 1_000_000),
+        _ => (s, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .ok_or_else(|| format!("invalid document count '{}'", s))
+}
+
+fn compile(value: &Value) -> CliResult<Node> {
+    Ok(match value {
+        Value::Object(map) if map.contains_key("$array") => {
+            let range = match map.get("$array") {
+                Some(Value::Array(range)) if range.len() == 2 => (range[0].as_u64(), range[1].as_u64()),
+                Some(len) => (len.as_u64(), len.as_u64()),
+                None => (None, None),
+            };
+            let (min, max) = match range {
+                (Some(min), Some(max)) if min <= max => (min, max),
+                _ => return Err(invalid("$array", "expected a length or [min, max] with min <= max")),
+            };
+            let item = map.get("$of").ok_or_else(|| invalid("$array", "missing \"$of\" item template"))?;
+            Node::Repeat { min, max, item: Box::new(compile(item)?) }
+        }
+        Value::Object(map) => Node::Object(
+            map.iter()
+                .map(|(key, value)| Ok((quote(key)?, compile(value)?)))
+                .collect::<CliResult<_>>()?,
+        ),
+        Value::Array(items) => Node::Array(items.iter().map(compile).collect::<CliResult<_>>()?),
+        Value::String(s) => compile_string(s)?,
+        other => Node::Literal(other.to_string()),
+    })
+}
+
+fn compile_string(s: &str) -> CliResult<Node> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(open) = rest.find("{{") {
+        let close = rest[open..]
+            .find("}}")
+            .ok_or_else(|| CliError::Other(format!("Unclosed placeholder in \"{}\"", s)))?;
+        if open > 0 {
+            parts.push(Part::Text(rest[..open].to_string()));
+        }
+        parts.push(Part::Generated(parse_generator(rest[open + 2..open + close].trim())?));
+        rest = &rest[open + close + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest.to_string()));
+    }
+
+    match parts.as_slice() {
+        [] => Ok(Node::Literal(quote("")?)),
+        [Part::Generated(generator)] => Ok(Node::Value(generator.clone())),
+        [Part::Text(text)] => Ok(Node::Literal(quote(text)?)),
+        _ => {
+            // 生成的值不含引号,只需检查模板中的常量部分
+            for part in &parts {
+                if let Part::Text(text) = part {
+                    if text.contains('"') {
+                        return Err(CliError::Other(format!(
+                            "Placeholder strings cannot contain double quotes: \"{}\"",
+                            s
+                        )));
+                    }
+                }
+            }
+            Ok(Node::Text(parts))
+        }
+    }
+}
+
+fn parse_generator(spec: &str) -> CliResult<Generator> {
+    let (name, args) = match spec.split_once(':') {
+        Some((name, args)) => (name, Some(args)),
+        None => (spec, None),
+    };
+    let range = args
+        .and_then(|args| args.split_once(':'))
+        .ok_or_else(|| invalid(spec, "expected MIN:MAX"));
+
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "seq" => Generator::Seq,
+        "first_name" => Generator::FirstName,
+        "last_name" => Generator::LastName,
+        "name" => Generator::Name,
+        "email" => Generator::Email,
+        "phone" => Generator::Phone,
+        "city" => Generator::City,
+        "country" => Generator::Country,
+        "word" => Generator::Word,
+        "sentence" => Generator::Sentence,
+        "uuid" => Generator::Uuid,
+        "bool" => Generator::Bool,
+        "int" => {
+            let (min, max) = range?;
+            match (min.parse::<i64>(), max.parse::<i64>()) {
+                (Ok(min), Ok(max)) if min <= max => Generator::Int(min, max),
+                _ => return Err(invalid(spec, "expected integers with MIN <= MAX")),
+            }
+        }
+        "float" => {
+            let (min, max) = range?;
+            match (min.parse::<f64>(), max.parse::<f64>()) {
+                (Ok(min), Ok(max)) if min <= max && min.is_finite() && max.is_finite() => {
+                    Generator::Float(min, max)
+                }
+                _ => return Err(invalid(spec, "expected numbers with MIN <= MAX")),
+            }
+        }
+        "date" => {
+            let (from, to) = range?;
+            let parse = |s: &str| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .map(|time| time.and_utc().timestamp())
+            };
+            match (parse(from), parse(to)) {
+                // 截止日期包含当天
+                (Some(from), Some(to)) if from <= to => Generator::Date(from, to + 86_399),
+                _ => return Err(invalid(spec, "expected YYYY-MM-DD:YYYY-MM-DD")),
+            }
+        }
+        "pick" => {
+            let options: Vec<String> = args
+                .unwrap_or_default()
+                .split('|')
+                .filter(|option| !option.is_empty())
+                .map(str::to_string)
+                .collect();
+            if options.is_empty() || options.iter().any(|option| option.contains('"')) {
+                return Err(invalid(spec, "expected a|b|c without double quotes"));
+            }
+            Generator::Pick(options)
+        }
+        _ => return Err(CliError::Other(format!("Unknown placeholder {{{{{}}}}}", spec))),
+    })
+}
+
+fn invalid(spec: &str, reason: &str) -> CliError {
+    CliError::Other(format!("Invalid placeholder {}: {}", spec, reason))
+}
+
+/// # Brief
+/// 把字符串写成 MQL 字符串字面量
+///
+/// MQL 字符串不处理转义,含双引号时改用单引号,两种引号都有时无法表示。
+fn quote(s: &str) -> CliResult<String> {
+    if !s.contains('"') && !s.ends_with('\\') {
+        Ok(format!("\"{}\"", s))
+    } else if !s.contains('\'') && !s.ends_with('\\') {
+        Ok(format!("'{}'", s))
+    } else {
+        Err(CliError::Other(format!("Cannot represent string in template: {}", s)))
+    }
+}
+
+fn render(node: &Node, rng: &mut Rng, seq: u64, out: &mut String) {
+    match node {
+        Node::Literal(text) => out.push_str(text),
+        Node::Object(fields) => {
+            out.push('{');
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(key);
+                out.push_str(": ");
+                render(value, rng, seq, out);
+            }
+            out.push('}');
+        }
+        Node::Array(items) => render_array(items.iter(), rng, seq, out),
+        Node::Repeat { min, max, item } => {
+            let len = rng.range(*min as i64, *max as i64) as usize;
+            render_array(std::iter::repeat(item.as_ref()).take(len), rng, seq, out);
+        }
+        Node::Value(generator) => render_value(generator, rng, seq, out),
+        Node::Text(parts) => {
+            out.push('"');
+            for part in parts {
+                match part {
+                    Part::Text(text) => out.push_str(text),
+                    Part::Generated(generator) => out.push_str(&generate(generator, rng, seq)),
+                }
+            }
+            out.push('"');
+        }
+    }
+}
+
+fn render_array<'a>(items: impl Iterator<Item = &'a Node>, rng: &mut Rng, seq: u64, out: &mut String) {
+    out.push('[');
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        render(item, rng, seq, out);
+    }
+    out.push(']');
+}
+
+/// # Brief
+/// 以值的类型写出单独的占位符: 数值和布尔值不加引号,日期和 UUID 使用类型构造器
+fn render_value(generator: &Generator, rng: &mut Rng, seq: u64, out: &mut String) {
+    let text = generate(generator, rng, seq);
+    match generator {
+        Generator::Seq | Generator::Int(..) | Generator::Float(..) | Generator::Bool => out.push_str(&text),
+        Generator::Date(..) => out.push_str(&format!("ISODate('{}')", text)),
+        Generator::Uuid => out.push_str(&format!("UUID('{}')", text)),
+        _ => {
+            out.push('"');
+            out.push_str(&text);
+            out.push('"');
+        }
+    }
+}
+
+fn generate(generator: &Generator, rng: &mut Rng, seq: u64) -> String {
+    match generator {
+        Generator::Seq => seq.to_string(),
+        Generator::FirstName => rng.pick(FIRST_NAMES).to_string(),
+        Generator::LastName => rng.pick(LAST_NAMES).to_string(),
+        Generator::Name => format!("{} {}", rng.pick(FIRST_NAMES), rng.pick(LAST_NAMES)),
+        Generator::Email => format!(
+            "{}.{}{}@{}",
+            rng.pick(FIRST_NAMES).to_lowercase(),
+            rng.pick(LAST_NAMES).to_lowercase(),
+            seq,
+            rng.pick(EMAIL_DOMAINS)
+        ),
+        Generator::Phone => format!(
+            "+1-{:03}-{:03}-{:04}",
+            rng.range(200, 999),
+            rng.range(200, 999),
+            rng.range(0, 9999)
+        ),
+        Generator::City => rng.pick(CITIES).to_string(),
+        Generator::Country => rng.pick(COUNTRIES).to_string(),
+        Generator::Word => rng.pick(WORDS).to_string(),
+        Generator::Sentence => {
+            let len = rng.range(4, 10) as usize;
+            let words: Vec<&str> = (0..len).map(|_| *rng.pick(WORDS)).collect();
+            let mut sentence = words.join(" ");
+            if let Some(first) = sentence.get_mut(0..1) {
+                first.make_ascii_uppercase();
+            }
+            sentence.push('.');
+            sentence
+        }
+        Generator::Uuid => {
+            let (hi, lo) = (rng.next(), rng.next());
+            // 版本 4 / RFC 4122 变体
+            let hi = (hi & !0xf000) | 0x4000;
+            let lo = (lo & !(0b11 << 62)) | (0b10 << 62);
+            format!(
+                "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+                hi >> 32,
+                (hi >> 16) & 0xffff,
+                hi & 0xffff,
+                lo >> 48,
+                lo & 0xffff_ffff_ffff
+            )
+        }
+        Generator::Bool => (rng.next() & 1 == 1).to_string(),
+        Generator::Int(min, max) => rng.range(*min, *max).to_string(),
+        Generator::Float(min, max) => format!("{:.2}", min + rng.unit() * (max - min)),
+        Generator::Date(from, to) => DateTime::from_timestamp(rng.range(*from, *to), 0)
+            .unwrap_or_default()
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        Generator::Pick(options) => rng.pick(options).to_string(),
+    }
+}
+
+/// SplitMix64 伪随机数生成器,只用于生成测试数据
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// 闭区间 [min, max] 内的整数
+    fn range(&mut self, min: i64, max: i64) -> i64 {
+        let span = (max as i128 - min as i128 + 1) as u128;
+        (min as i128 + (self.next() as u128 % span) as i128) as i64
+    }
+
+    /// [0, 1) 内的浮点数
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.next() as usize % items.len()]
+    }
+}
@@ -0,0 +1,262 @@
+//! 用户偏好设置模块
+//!
+//! 将 REPL 会话状态持久化到 ~/.mikudb_config,启动时加载:
+//! - 界面语言
+//! - 上次使用的数据库
+//! - 输出格式
+//! - 分页器设置
+//! - 提示符模板
+//! - 查询超时
+//! - 表格最大列宽与展开模式
+//!
+//! 配置文件为 `key = value` 格式,兼容旧版本只保存语言代码的单行格式。
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// 用户偏好设置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    /// 界面语言("en" / "zh")
+    pub language: Option<String>,
+    /// 上次使用的数据库
+    pub last_database: Option<String>,
+    /// 输出格式("table", "json", "json-pretty", "csv", "line")
+    pub format: Option<String>,
+    /// 分页器命令(例如 "less -SR")
+    pub pager: Option<String>,
+    /// 是否启用分页器
+    pub pager_enabled: bool,
+    /// 提示符模板,支持 {db}、{user}、{host}、{port} 占位符
+    pub prompt: Option<String>,
+    /// 查询超时(毫秒),由服务器端计时
+    pub query_timeout_ms: Option<u64>,
+    /// 表格单元格最大宽度,None 表示默认值,0 表示不限制
+    pub max_column_width: Option<usize>,
+    /// 是否以展开模式显示表格结果
+    pub expanded: bool,
+}
+
+impl Settings {
+    /// # Brief
+    /// 从配置文件内容解析设置
+    ///
+    /// 未知的键会被忽略;只有一行且不含 `=` 时视为旧版语言配置。
+    pub fn parse(content: &str) -> Self {
+        let mut settings = Settings::default();
+        let trimmed = content.trim();
+
+        // 兼容旧格式: 文件只包含语言代码
+        if !trimmed.is_empty() && !trimmed.contains('=') && !trimmed.contains('\n') {
+            settings.language = Some(trimmed.to_string());
+            return settings;
+        }
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            let value = if value.is_empty() { None } else { Some(value.to_string()) };
+            match key.trim() {
+                "language" => settings.language = value,
+                "last_database" => settings.last_database = value,
+                "format" => settings.format = value,
+                "pager" => settings.pager = value,
+                "pager_enabled" => {
+                    settings.pager_enabled = matches!(value.as_deref(), Some("true" | "on" | "1"))
+                }
+                "prompt" => settings.prompt = value,
+                "query_timeout_ms" => {
+                    settings.query_timeout_ms = value.and_then(|v| v.parse().ok()).filter(|ms| *ms > 0)
+                }
+                "max_column_width" => {
+                    settings.max_column_width = value.and_then(|v| v.parse().ok())
+                }
+                "expanded" => settings.expanded = matches!(value.as_deref(), Some("true" | "on" | "1")),
+                _ => {}
+            }
+        }
+
+        settings
+    }
+
+    /// # Brief
+    /// 序列化为配置文件内容
+    pub fn to_config_string(&self) -> String {
+        let mut out = String::new();
+        let mut push = |key: &str, value: Option<&str>| {
+            if let Some(v) = value {
+                out.push_str(key);
+                out.push_str(" = ");
+                out.push_str(v);
+                out.push('\n');
+            }
+        };
+        push("language", self.language.as_deref());
+        push("last_database", self.last_database.as_deref());
+        push("format", self.format.as_deref());
+        push("pager", self.pager.as_deref());
+        push("pager_enabled", Some(if self.pager_enabled { "true" } else { "false" }));
+        push("prompt", self.prompt.as_deref());
+        push("query_timeout_ms", self.query_timeout_ms.map(|ms| ms.to_string()).as_deref());
+        push("max_column_width", self.max_column_width.map(|w| w.to_string()).as_deref());
+        push("expanded", Some(if self.expanded { "true" } else { "false" }));
+        out
+    }
+
+    /// # Brief
+    /// 获取实际使用的分页器命令
+    ///
+    /// 分页器未启用时返回 None;未配置命令时依次回退到 $PAGER 和 "less -SR"。
+    pub fn pager_command(&self) -> Option<String> {
+        if !self.pager_enabled {
+            return None;
+        }
+        self.pager
+            .clone()
+            .or_else(|| std::env::var("PAGER").ok().filter(|p| !p.trim().is_empty()))
+            .or_else(|| Some("less -SR".to_string()))
+    }
+}
+
+/// 全局偏好设置
+static SETTINGS: RwLock<Settings> = RwLock::new(Settings {
+    language: None,
+    last_database: None,
+    format: None,
+    pager: None,
+    pager_enabled: false,
+    prompt: None,
+    query_timeout_ms: None,
+    max_column_width: None,
+    expanded: false,
+});
+
+/// 获取配置文件路径
+pub fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .map(|h| h.join(".mikudb_config"))
+        .unwrap_or_else(|| PathBuf::from(".mikudb_config"))
+}
+
+/// 从配置文件加载设置
+pub fn load() {
+    if let Ok(content) = fs::read_to_string(config_path()) {
+        *SETTINGS.write().unwrap() = Settings::parse(&content);
+    }
+}
+
+/// 获取当前设置的副本
+pub fn current() -> Settings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// # Brief
+/// 修改设置并立即写回配置文件
+///
+/// 写入失败时本次会话中的设置仍然生效,只是不会保留到下次启动。
+///
+/// # Arguments
+/// * `f` - 修改设置的闭包
+///
+/// # Returns
+/// 写入配置文件的结果
+pub fn update<F: FnOnce(&mut Settings)>(f: F) -> io::Result<()> {
+    let snapshot = {
+        let mut settings = SETTINGS.write().unwrap();
+        f(&mut settings);
+        settings.clone()
+    };
+    fs::write(config_path(), snapshot.to_config_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let settings = Settings::parse(
+            "# MikuDB CLI\n\
+             language = zh\n\
+             last_database = shop\n\
+             format=json-pretty\n\
+             pager = less -SR\n\
+             pager_enabled = on\n\
+             prompt = {user}@{db} = \n\
+             query_timeout_ms = 5000\n\
+             max_column_width = 0\n\
+             expanded = 1\n\
+             unknown = ignored\n\
+             not a setting\n",
+        );
+        assert_eq!(
+            settings,
+            Settings {
+                language: Some("zh".to_string()),
+                last_database: Some("shop".to_string()),
+                format: Some("json-pretty".to_string()),
+                pager: Some("less -SR".to_string()),
+                pager_enabled: true,
+                prompt: Some("{user}@{db} =".to_string()),
+                query_timeout_ms: Some(5000),
+                max_column_width: Some(0),
+                expanded: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_and_empty_values() {
+        let settings = Settings::parse(
+            "language =\nquery_timeout_ms = 0\nmax_column_width = wide\npager_enabled = yes\nexpanded = false\n",
+        );
+        assert_eq!(settings, Settings::default());
+        assert_eq!(Settings::parse("query_timeout_ms = soon").query_timeout_ms, None);
+        assert_eq!(Settings::parse(""), Settings::default());
+    }
+
+    #[test]
+    fn test_parse_legacy_language_file() {
+        let settings = Settings::parse("zh\n");
+        assert_eq!(settings.language.as_deref(), Some("zh"));
+        assert_eq!(settings.last_database, None);
+    }
+
+    #[test]
+    fn test_to_config_string() {
+        let settings = Settings {
+            language: Some("en".to_string()),
+            query_timeout_ms: Some(30000),
+            ..Settings::default()
+        };
+        assert_eq!(
+            settings.to_config_string(),
+            "language = en\npager_enabled = false\nquery_timeout_ms = 30000\nexpanded = false\n"
+        );
+    }
+
+    #[test]
+    fn test_config_string_round_trip() {
+        let settings = Settings {
+            language: Some("zh".to_string()),
+            last_database: Some("shop".to_string()),
+            format: Some("csv".to_string()),
+            pager: Some("less -SR".to_string()),
+            pager_enabled: true,
+            prompt: Some("{user}@{host}:{port}/{db}>".to_string()),
+            query_timeout_ms: Some(1500),
+            max_column_width: Some(40),
+            expanded: true,
+        };
+        assert_eq!(Settings::parse(&settings.to_config_string()), settings);
+        assert_eq!(Settings::parse(&Settings::default().to_config_string()), Settings::default());
+    }
+}
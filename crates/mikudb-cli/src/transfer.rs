@@ -0,0 +1,1093 @@
+//! 导入与导出模块
+//!
+//! 实现 REPL 的 `IMPORT` / `EXPORT` 命令和 `mikudb-cli import` / `mikudb-cli export` 子命令,
+//! 在当前数据库的集合与 CSV、NDJSON 文件之间批量传输文档:
+//! - CSV 第一行为列名,列名中的 `.` 表示嵌套字段;导出时嵌套文档和数组写为 JSON 文本
+//! - CSV 单元格默认推断类型(整数、浮点数、布尔值、JSON 文档和数组,`_id` 列的 ObjectId),`NO INFER` 时全部作为字符串,
+//!   `TYPES` 为单个字段指定类型;空单元格不写入字段(字符串类型除外)
+//! - NDJSON 每行一个 JSON 文档,导入时接受扩展 JSON(`$oid`、`$date` 等),导出为宽松扩展 JSON
+//! - `MAP a AS b` 按数据流向重命名字段:导入时把文件中的 `a` 写为文档的 `b`,导出时把文档的 `a` 写为 `b`
+//! - 导入以无序批量写入插入,单个文档失败不影响其余文档;导入和导出都显示进度
+//!
+//! # 语法
+//!
+//! ```text
+//! IMPORT <集合> FROM '<文件>' [FORMAT CSV|NDJSON] [NO INFER] [TYPES 字段 类型, ...]
+//!     [MAP 列 AS 字段, ...] [BATCH SIZE n] [DELIMITER ';']
+//! EXPORT <集合> TO '<文件>' [FORMAT CSV|NDJSON] [FIELDS 字段, ...]
+//!     [MAP 字段 AS 列, ...] [BATCH SIZE n] [DELIMITER ';']
+//! ```
+
+use crate::client::Client;
+use crate::{CliError, CliResult};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 每批读取或写入的默认文档数
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// 每次导入最多列出的失败原因
+const MAX_REPORTED_ERRORS: usize = 10;
+
+const IMPORT_USAGE: &str = "IMPORT <collection> FROM '<file>' [FORMAT CSV|NDJSON] [NO INFER] \
+     [TYPES field type, ...] [MAP column AS field, ...] [BATCH SIZE n] [DELIMITER ',']";
+const EXPORT_USAGE: &str = "EXPORT <collection> TO '<file>' [FORMAT CSV|NDJSON] [FIELDS field, ...] \
+     [MAP field AS column, ...] [BATCH SIZE n] [DELIMITER ',']";
+
+/// 文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// 带列名行的 CSV
+    Csv,
+    /// 每行一个 JSON 文档
+    Ndjson,
+}
+
+impl FileFormat {
+    /// # Brief
+    /// 解析格式名称(csv、ndjson、jsonl)
+    pub fn parse(name: &str) -> CliResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "ndjson" | "jsonl" | "json" => Ok(Self::Ndjson),
+            other => Err(CliError::Other(format!("Unknown file format '{}', expected csv or ndjson", other))),
+        }
+    }
+
+    /// # Brief
+    /// 按文件扩展名判断格式
+    fn detect(path: &Path) -> CliResult<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        Self::parse(extension).map_err(|_| {
+            CliError::Other(format!("Cannot tell the format of {}, specify FORMAT CSV or NDJSON", path.display()))
+        })
+    }
+}
+
+/// 导入时为字段指定的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    String,
+    Int,
+    Double,
+    Bool,
+    /// RFC 3339 时间或 `YYYY-MM-DD` 日期(UTC 零点)
+    Date,
+    /// 24 位十六进制 ObjectId
+    ObjectId,
+    /// JSON 文本(文档、数组或扩展 JSON)
+    Json,
+}
+
+impl FieldType {
+    /// # Brief
+    /// 解析类型名称
+    pub fn parse(name: &str) -> CliResult<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "string" | "str" => Ok(Self::String),
+            "int" | "integer" | "long" => Ok(Self::Int),
+            "double" | "float" | "number" => Ok(Self::Double),
+            "bool" | "boolean" => Ok(Self::Bool),
+            "date" | "datetime" => Ok(Self::Date),
+            "objectid" | "oid" => Ok(Self::ObjectId),
+            "json" => Ok(Self::Json),
+            other => Err(CliError::Other(format!(
+                "Unknown field type '{}', expected string, int, double, bool, date, objectid or json",
+                other
+            ))),
+        }
+    }
+
+    fn convert(self, text: &str) -> Result<Value, String> {
+        let invalid = |kind: &str| format!("'{}' is not a valid {}", text, kind);
+        match self {
+            Self::String => Ok(Value::String(text.to_string())),
+            Self::Int => text.trim().parse::<i64>().map(Value::from).map_err(|_| invalid("integer")),
+            Self::Double => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| invalid("number")),
+            Self::Bool => match text.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => Ok(Value::Bool(true)),
+                "false" | "0" | "no" => Ok(Value::Bool(false)),
+                _ => Err(invalid("boolean")),
+            },
+            Self::Date => {
+                let text = text.trim();
+                let millis = match DateTime::parse_from_rfc3339(text) {
+                    Ok(datetime) => datetime.timestamp_millis(),
+                    Err(_) => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                        .map_err(|_| invalid("date"))?
+                        .and_hms_opt(0, 0, 0)
+                        .map(|datetime| datetime.and_utc().timestamp_millis())
+                        .ok_or_else(|| invalid("date"))?,
+                };
+                Ok(serde_json::json!({"$date": {"$numberLong": millis.to_string()}}))
+            }
+            Self::ObjectId => match is_object_id(text.trim()) {
+                true => Ok(serde_json::json!({"$oid": text.trim().to_ascii_lowercase()})),
+                false => Err(invalid("ObjectId")),
+            },
+            Self::Json => serde_json::from_str(text).map_err(|_| invalid("JSON value")),
+        }
+    }
+}
+
+fn is_object_id(text: &str) -> bool {
+    text.len() == 24 && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// # Brief
+/// 推断 CSV 单元格的类型
+///
+/// 规范写法的整数和有限浮点数(不含前导零和正号,避免改变邮编、编号等)、`true` / `false`、
+/// 能解析的 JSON 文档和数组按对应类型导入,其余作为字符串。
+fn infer(text: &str) -> Value {
+    match text {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(n) = text.parse::<i64>() {
+        if n.to_string() == text {
+            return Value::from(n);
+        }
+    }
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+    let numeric = text.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'-' | b'+'));
+    if numeric && digits.starts_with(|c: char| c.is_ascii_digit()) && !leading_zero {
+        if let Some(n) = text.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+            return Value::Number(n);
+        }
+    }
+    if text.starts_with('{') || text.starts_with('[') {
+        if let Ok(value) = serde_json::from_str::<Value>(text) {
+            return value;
+        }
+    }
+    Value::String(text.to_string())
+}
+
+/// 导入命令
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// 目标集合
+    pub collection: String,
+    /// 源文件
+    pub path: PathBuf,
+    /// 文件格式,None 时按扩展名判断
+    pub format: Option<FileFormat>,
+    /// CSV 单元格是否推断类型
+    pub infer: bool,
+    /// 指定类型的字段(映射后的字段名)
+    pub types: Vec<(String, FieldType)>,
+    /// 文件中的列或字段 → 文档字段
+    pub mapping: Vec<(String, String)>,
+    /// 每批插入的文档数
+    pub batch_size: usize,
+    /// CSV 分隔符
+    pub delimiter: u8,
+}
+
+/// 导入结果
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    /// 目标集合
+    pub collection: String,
+    /// 插入的文档数
+    pub inserted: u64,
+    /// 无法转换或插入失败的行数
+    pub failed: u64,
+    /// 前几条失败原因(含行号)
+    pub errors: Vec<String>,
+    /// 总耗时
+    pub elapsed: Duration,
+}
+
+impl ImportReport {
+    /// # Brief
+    /// 打印插入数、失败数和失败原因
+    pub fn print(&self) {
+        for error in &self.errors {
+            eprintln!("  {}", error);
+        }
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        println!(
+            "{} {} documents into {}, {} failed, in {:.2}s ({:.0} docs/s)",
+            "IMPORTED".green().bold(),
+            self.inserted,
+            self.collection,
+            self.failed,
+            secs,
+            self.inserted as f64 / secs
+        );
+    }
+
+    fn reject(&mut self, line: u64, message: impl std::fmt::Display) {
+        self.failed += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(format!("line {}: {}", line, message));
+        }
+    }
+}
+
+/// 待插入的一批文档及其所在行号
+struct Batch<'a> {
+    client: &'a Client,
+    collection: &'a str,
+    size: usize,
+    documents: Vec<Value>,
+    lines: Vec<u64>,
+}
+
+impl Batch<'_> {
+    async fn push(&mut self, line: u64, document: Map<String, Value>, report: &mut ImportReport) -> CliResult<()> {
+        self.documents.push(Value::Object(document));
+        self.lines.push(line);
+        if self.documents.len() >= self.size {
+            self.flush(report).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self, report: &mut ImportReport) -> CliResult<()> {
+        if self.documents.is_empty() {
+            return Ok(());
+        }
+        let documents = std::mem::take(&mut self.documents);
+        let lines = std::mem::take(&mut self.lines);
+        let summary = self.client.bulk_insert_extended(self.collection, documents).await?;
+        report.inserted += summary["inserted"].as_u64().unwrap_or(0);
+        for error in summary["write_errors"].as_array().into_iter().flatten() {
+            let line = error["index"].as_u64().and_then(|i| lines.get(i as usize)).copied().unwrap_or(0);
+            report.reject(line, error["message"].as_str().unwrap_or("unknown error"));
+        }
+        Ok(())
+    }
+}
+
+/// 源文件中的一行:行号和转换后的文档,无法转换时为失败原因
+type Row = (u64, Result<Map<String, Value>, String>);
+
+/// 源文件的逐行读取状态
+enum Rows<R: Read> {
+    /// CSV 读取器、列名行确定的字段及其类型
+    Csv { reader: csv::Reader<R>, columns: Vec<(String, Option<FieldType>)>, record: csv::StringRecord },
+    /// NDJSON 读取器、当前行号和行缓冲
+    Ndjson { reader: BufReader<R>, line: u64, text: String },
+}
+
+impl Import {
+    /// # Brief
+    /// 创建使用默认选项的导入命令
+    pub fn new(collection: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            collection: collection.into(),
+            path: path.into(),
+            format: None,
+            infer: true,
+            types: Vec::new(),
+            mapping: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            delimiter: b',',
+        }
+    }
+
+    /// # Brief
+    /// 解析 REPL 中的 `IMPORT` 命令
+    pub fn parse(statement: &str) -> CliResult<Self> {
+        let mut tokens = Tokens::new(statement, IMPORT_USAGE)?;
+        tokens.expect("IMPORT")?;
+        let collection = tokens.value("collection name")?;
+        tokens.expect("FROM")?;
+        let mut import = Self::new(collection, tokens.value("file path")?);
+        while !tokens.done() {
+            if tokens.keyword("FORMAT") {
+                import.format = Some(FileFormat::parse(&tokens.value("format")?)?);
+            } else if tokens.keyword("NO") {
+                tokens.expect("INFER")?;
+                import.infer = false;
+            } else if tokens.keyword("TYPES") {
+                import.types = tokens.list(|t| Ok((t.value("field")?, FieldType::parse(&t.value("type")?)?)))?;
+            } else if tokens.keyword("MAP") {
+                import.mapping = tokens.list(Tokens::rename)?;
+            } else if tokens.keyword("BATCH") {
+                tokens.expect("SIZE")?;
+                import.batch_size = tokens.number()?;
+            } else if tokens.keyword("DELIMITER") {
+                import.delimiter = parse_delimiter(&tokens.value("delimiter")?)?;
+            } else {
+                return Err(tokens.unexpected());
+            }
+        }
+        Ok(import)
+    }
+
+    /// # Brief
+    /// 把文件导入当前数据库的集合
+    ///
+    /// 文件按批读取和插入,内存中只保留一批文档。无法转换的行和插入失败的文档计入失败数,
+    /// 不会中止导入;连接或文件读取错误时停止并返回错误,之前的批次已写入。
+    ///
+    /// # Arguments
+    /// * `client` - 已切换到目标数据库的连接
+    pub async fn run(&self, client: &Client) -> CliResult<ImportReport> {
+        let started = Instant::now();
+        let format = match self.format {
+            Some(format) => format,
+            None => FileFormat::detect(&self.path)?,
+        };
+        let file = File::open(&self.path)?;
+        let progress = ProgressBar::new(file.metadata()?.len());
+        progress.set_style(
+            ProgressStyle::with_template("{bar:40.cyan/blue} {bytes}/{total_bytes} [{elapsed}] {msg}")
+                .unwrap_or_else(|_| ProgressStyle::default_bar()),
+        );
+        let input = progress.wrap_read(file);
+
+        let mut report = ImportReport {
+            collection: self.collection.clone(),
+            inserted: 0,
+            failed: 0,
+            errors: Vec::new(),
+            elapsed: Duration::ZERO,
+        };
+        let mut batch = Batch {
+            client,
+            collection: &self.collection,
+            size: self.batch_size.max(1),
+            documents: Vec::new(),
+            lines: Vec::new(),
+        };
+        let result = match self.import_rows(format, input, &mut batch, &mut report, &progress).await {
+            Ok(()) => batch.flush(&mut report).await,
+            Err(e) => Err(e),
+        };
+        progress.finish_and_clear();
+        result?;
+        report.elapsed = started.elapsed();
+        Ok(report)
+    }
+
+    async fn import_rows(
+        &self,
+        format: FileFormat,
+        input: impl Read,
+        batch: &mut Batch<'_>,
+        report: &mut ImportReport,
+        progress: &ProgressBar,
+    ) -> CliResult<()> {
+        let mut rows = self.rows(format, input)?;
+        let mut count = 0u64;
+        while let Some((line, document)) = self.next_row(&mut rows)? {
+            match document {
+                Ok(document) => batch.push(line, document, report).await?,
+                Err(message) => report.reject(line, message),
+            }
+            count += 1;
+            progress.set_message(format!("{} rows", count));
+        }
+        Ok(())
+    }
+
+    /// # Brief
+    /// 按格式打开源文件的逐行读取器,CSV 读取列名行
+    fn rows<R: Read>(&self, format: FileFormat, input: R) -> CliResult<Rows<R>> {
+        match format {
+            FileFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new()
+                    .delimiter(self.delimiter)
+                    .flexible(true)
+                    .from_reader(input);
+                let columns = reader
+                    .headers()
+                    .map_err(csv_error)?
+                    .iter()
+                    .map(|column| {
+                        let field = self.target(column).to_string();
+                        let field_type = self.field_type(&field);
+                        (field, field_type)
+                    })
+                    .collect();
+                Ok(Rows::Csv { reader, columns, record: csv::StringRecord::new() })
+            }
+            FileFormat::Ndjson => Ok(Rows::Ndjson { reader: BufReader::new(input), line: 0, text: String::new() }),
+        }
+    }
+
+    /// # Brief
+    /// 读取下一行并转换为文档
+    ///
+    /// # Returns
+    /// 行号和文档(无法转换时为失败原因),文件结束时返回 None;读取文件出错时返回错误
+    fn next_row<R: Read>(&self, rows: &mut Rows<R>) -> CliResult<Option<Row>> {
+        match rows {
+            Rows::Csv { reader, columns, record } => {
+                let line = reader.position().line();
+                match reader.read_record(record) {
+                    Ok(true) => {}
+                    Ok(false) => return Ok(None),
+                    Err(e) if e.is_io_error() => return Err(csv_error(e)),
+                    Err(e) => return Ok(Some((line, Err(e.to_string())))),
+                }
+                if record.len() != columns.len() {
+                    let message = format!("expected {} fields, found {}", columns.len(), record.len());
+                    return Ok(Some((line, Err(message))));
+                }
+                Ok(Some((line, self.csv_document(columns, record))))
+            }
+            Rows::Ndjson { reader, line, text } => loop {
+                text.clear();
+                if reader.read_line(text)? == 0 {
+                    return Ok(None);
+                }
+                *line += 1;
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let document = match serde_json::from_str::<Value>(text) {
+                    Ok(Value::Object(document)) => self.ndjson_document(document),
+                    Ok(_) => Err("not a JSON object".to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                return Ok(Some((*line, document)));
+            },
+        }
+    }
+
+    fn csv_document(
+        &self,
+        columns: &[(String, Option<FieldType>)],
+        record: &csv::StringRecord,
+    ) -> Result<Map<String, Value>, String> {
+        let mut document = Map::new();
+        for ((field, field_type), cell) in columns.iter().zip(record.iter()) {
+            let value = match field_type {
+                Some(FieldType::String) => Value::String(cell.to_string()),
+                _ if cell.is_empty() => continue,
+                Some(field_type) => field_type.convert(cell).map_err(|e| format!("{}: {}", field, e))?,
+                // 与导出对称: `_id` 列中的十六进制 ObjectId 还原为 ObjectId
+                None if self.infer && field == "_id" && is_object_id(cell) => FieldType::ObjectId.convert(cell)?,
+                None if self.infer => infer(cell),
+                None => Value::String(cell.to_string()),
+            };
+            set_path(&mut document, field, value);
+        }
+        Ok(document)
+    }
+
+    fn ndjson_document(&self, mut document: Map<String, Value>) -> Result<Map<String, Value>, String> {
+        for (from, to) in &self.mapping {
+            if let Some(value) = take_path(&mut document, from) {
+                set_path(&mut document, to, value);
+            }
+        }
+        // NDJSON 已带有类型,只转换字符串值
+        for (field, field_type) in &self.types {
+            if let Some(Value::String(text)) = get_path(&document, field) {
+                let value = field_type.convert(text).map_err(|e| format!("{}: {}", field, e))?;
+                set_path(&mut document, field, value);
+            }
+        }
+        Ok(document)
+    }
+
+    fn target<'a>(&'a self, column: &'a str) -> &'a str {
+        self.mapping.iter().find(|(from, _)| from == column).map_or(column, |(_, to)| to)
+    }
+
+    fn field_type(&self, field: &str) -> Option<FieldType> {
+        self.types.iter().find(|(name, _)| name == field).map(|(_, field_type)| *field_type)
+    }
+}
+
+/// 导出命令
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// 源集合
+    pub collection: String,
+    /// 目标文件,已存在时覆盖
+    pub path: PathBuf,
+    /// 文件格式,None 时按扩展名判断
+    pub format: Option<FileFormat>,
+    /// 导出的字段,为空时 CSV 使用第一批文档的顶层字段、NDJSON 导出整个文档
+    pub fields: Vec<String>,
+    /// 文档字段 → 文件中的列或字段
+    pub mapping: Vec<(String, String)>,
+    /// 每批读取的文档数
+    pub batch_size: u32,
+    /// CSV 分隔符
+    pub delimiter: u8,
+}
+
+/// 导出结果
+#[derive(Debug, Clone)]
+pub struct ExportReport {
+    /// 源集合
+    pub collection: String,
+    /// 目标文件
+    pub path: PathBuf,
+    /// 导出的文档数
+    pub exported: u64,
+    /// 总耗时
+    pub elapsed: Duration,
+}
+
+impl ExportReport {
+    /// # Brief
+    /// 打印导出数量
+    pub fn print(&self) {
+        println!(
+            "{} {} documents from {} to {} in {:.2}s",
+            "EXPORTED".green().bold(),
+            self.exported,
+            self.collection,
+            self.path.display(),
+            self.elapsed.as_secs_f64()
+        );
+    }
+}
+
+impl Export {
+    /// # Brief
+    /// 创建使用默认选项的导出命令
+    pub fn new(collection: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            collection: collection.into(),
+            path: path.into(),
+            format: None,
+            fields: Vec::new(),
+            mapping: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE as u32,
+            delimiter: b',',
+        }
+    }
+
+    /// # Brief
+    /// 解析 REPL 中的 `EXPORT` 命令
+    pub fn parse(statement: &str) -> CliResult<Self> {
+        let mut tokens = Tokens::new(statement, EXPORT_USAGE)?;
+        tokens.expect("EXPORT")?;
+        let collection = tokens.value("collection name")?;
+        tokens.expect("TO")?;
+        let mut export = Self::new(collection, tokens.value("file path")?);
+        while !tokens.done() {
+            if tokens.keyword("FORMAT") {
+                export.format = Some(FileFormat::parse(&tokens.value("format")?)?);
+            } else if tokens.keyword("FIELDS") {
+                export.fields = tokens.list(|t| t.value("field"))?;
+            } else if tokens.keyword("MAP") {
+                export.mapping = tokens.list(Tokens::rename)?;
+            } else if tokens.keyword("BATCH") {
+                tokens.expect("SIZE")?;
+                export.batch_size = tokens.number()?;
+            } else if tokens.keyword("DELIMITER") {
+                export.delimiter = parse_delimiter(&tokens.value("delimiter")?)?;
+            } else {
+                return Err(tokens.unexpected());
+            }
+        }
+        Ok(export)
+    }
+
+    /// # Brief
+    /// 把当前数据库的集合导出到文件
+    ///
+    /// 通过游标逐批读取集合,内存中只保留一批文档。
+    ///
+    /// # Arguments
+    /// * `client` - 已切换到源数据库的连接
+    pub async fn run(&self, client: &Client) -> CliResult<ExportReport> {
+        let started = Instant::now();
+        let format = match self.format {
+            Some(format) => format,
+            None => FileFormat::detect(&self.path)?,
+        };
+        let batch_size = self.batch_size.max(1);
+        let (mut documents, mut cursor) = client.scan_extended(&self.collection, batch_size).await?;
+        let file = File::create(&self.path)?;
+        let mut sink = match format {
+            FileFormat::Csv => Sink::Csv {
+                writer: Box::new(csv::WriterBuilder::new().delimiter(self.delimiter).from_writer(file)),
+                fields: None,
+            },
+            FileFormat::Ndjson => Sink::Ndjson(BufWriter::new(file)),
+        };
+        let progress = ProgressBar::new_spinner();
+        progress.set_style(
+            ProgressStyle::with_template("{spinner} {pos} documents [{elapsed}] {per_sec}")
+                .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+        );
+
+        let mut exported = 0u64;
+        loop {
+            let batch = documents.iter().map(relaxed).collect::<CliResult<Vec<_>>>()?;
+            self.write_batch(&mut sink, batch)?;
+            exported += documents.len() as u64;
+            progress.set_position(exported);
+            match cursor {
+                Some(id) => (documents, cursor) = client.next_page(id, batch_size).await?,
+                None => break,
+            }
+        }
+        match sink {
+            Sink::Csv { mut writer, .. } => writer.flush()?,
+            Sink::Ndjson(mut out) => out.flush()?,
+        }
+        progress.finish_and_clear();
+        Ok(ExportReport {
+            collection: self.collection.clone(),
+            path: self.path.clone(),
+            exported,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    fn write_batch(&self, sink: &mut Sink, documents: Vec<Map<String, Value>>) -> CliResult<()> {
+        match sink {
+            Sink::Csv { writer, fields } => {
+                if fields.is_none() && !documents.is_empty() {
+                    let columns = match self.fields.is_empty() {
+                        true => top_level_fields(&documents),
+                        false => self.fields.clone(),
+                    };
+                    writer.write_record(columns.iter().map(|field| self.target(field))).map_err(csv_error)?;
+                    *fields = Some(columns);
+                }
+                for document in &documents {
+                    let cells = fields.iter().flatten().map(|field| cell_text(get_path(document, field)));
+                    writer.write_record(cells).map_err(csv_error)?;
+                }
+            }
+            Sink::Ndjson(out) => {
+                for document in documents {
+                    serde_json::to_writer(&mut *out, &self.project(document))
+                        .map_err(|e| CliError::Other(e.to_string()))?;
+                    out.write_all(b"\n")?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn project(&self, document: Map<String, Value>) -> Map<String, Value> {
+        let mut document = match self.fields.is_empty() {
+            true => document,
+            false => {
+                let mut projected = Map::new();
+                for field in &self.fields {
+                    if let Some(value) = get_path(&document, field) {
+                        set_path(&mut projected, field, value.clone());
+                    }
+                }
+                projected
+            }
+        };
+        for (from, to) in &self.mapping {
+            if let Some(value) = take_path(&mut document, from) {
+                set_path(&mut document, to, value);
+            }
+        }
+        document
+    }
+
+    fn target<'a>(&'a self, field: &'a str) -> &'a str {
+        self.mapping.iter().find(|(from, _)| from == field).map_or(field, |(_, to)| to)
+    }
+}
+
+/// 导出目标文件
+enum Sink {
+    /// CSV 写入器和列(第一批文档到达时确定)
+    Csv { writer: Box<csv::Writer<File>>, fields: Option<Vec<String>> },
+    Ndjson(BufWriter<File>),
+}
+
+/// # Brief
+/// 解析子命令的 `from=to` 形式参数
+pub fn parse_pair(text: &str) -> CliResult<(String, String)> {
+    match text.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => Ok((from.to_string(), to.to_string())),
+        _ => Err(CliError::Other(format!("Expected name=value, got '{}'", text))),
+    }
+}
+
+/// # Brief
+/// 解析 CSV 分隔符,只接受单个 ASCII 字符或 `\t`
+pub fn parse_delimiter(text: &str) -> CliResult<u8> {
+    match text {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if text.len() == 1 && text.is_ascii() => Ok(text.as_bytes()[0]),
+        _ => Err(CliError::Other(format!("Delimiter must be a single ASCII character, got '{}'", text))),
+    }
+}
+
+fn csv_error(error: csv::Error) -> CliError {
+    CliError::Other(format!("CSV error: {}", error))
+}
+
+/// 规范扩展 JSON 转为宽松扩展 JSON(数字不带包装,日期为毫秒数)
+fn relaxed(document: &Value) -> CliResult<Map<String, Value>> {
+    let value = mikudb_boml::from_extended_json(document)
+        .and_then(|value| mikudb_boml::to_json(&value))
+        .map_err(|e| CliError::Parse(format!("Invalid document from server: {}", e)))?;
+    match value {
+        Value::Object(document) => Ok(document),
+        other => Err(CliError::Parse(format!("Expected a document from server, got {}", other))),
+    }
+}
+
+/// 一批文档中出现的顶层字段,`_id` 在前,其余按出现顺序
+fn top_level_fields(documents: &[Map<String, Value>]) -> Vec<String> {
+    let mut fields = vec!["_id".to_string()];
+    for document in documents {
+        for key in document.keys() {
+            if !fields.contains(key) {
+                fields.push(key.clone());
+            }
+        }
+    }
+    fields
+}
+
+/// CSV 单元格文本:ObjectId 为十六进制,日期为 RFC 3339,文档和数组为 JSON
+fn cell_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Object(object)) if object.len() == 1 => match object.iter().next() {
+            Some((key, Value::String(oid))) if key == "$oid" => oid.clone(),
+            Some((key, Value::Number(millis))) if key == "$date" => millis
+                .as_i64()
+                .and_then(DateTime::<Utc>::from_timestamp_millis)
+                .map(|datetime| datetime.to_rfc3339_opts(SecondsFormat::Millis, true))
+                .unwrap_or_else(|| millis.to_string()),
+            _ => Value::Object(object.clone()).to_string(),
+        },
+        Some(other) => other.to_string(),
+    }
+}
+
+fn get_path<'a>(document: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let mut value = document.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_object()?.get(part)?;
+    }
+    Some(value)
+}
+
+/// 取出字段,取出后变为空的上层文档一并删除
+fn take_path(document: &mut Map<String, Value>, path: &str) -> Option<Value> {
+    match path.split_once('.') {
+        None => document.remove(path),
+        Some((head, rest)) => {
+            let child = document.get_mut(head)?.as_object_mut()?;
+            let value = take_path(child, rest)?;
+            if child.is_empty() {
+                document.remove(head);
+            }
+            Some(value)
+        }
+    }
+}
+
+fn set_path(document: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            document.insert(path.to_string(), value);
+        }
+        Some((head, rest)) => {
+            let entry = document.entry(head.to_string()).or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            if let Value::Object(child) = entry {
+                set_path(child, rest, value);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Comma,
+}
+
+/// `IMPORT` / `EXPORT` 命令的词法单元序列
+struct Tokens {
+    tokens: Vec<Token>,
+    pos: usize,
+    usage: &'static str,
+}
+
+impl Tokens {
+    fn new(statement: &str, usage: &'static str) -> CliResult<Self> {
+        let mut tokens = Vec::new();
+        let mut chars = statement.trim().trim_end_matches(';').chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == ',' {
+                chars.next();
+                tokens.push(Token::Comma);
+            } else if c == '\'' || c == '"' {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        // 连续两个引号表示引号本身
+                        Some(ch) if ch == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                            text.push(c);
+                        }
+                        Some(ch) if ch == c => break,
+                        Some(ch) => text.push(ch),
+                        None => return Err(Self::usage_error(usage, "Unterminated string")),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            } else {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || matches!(ch, ',' | '\'' | '"') {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word.trim_matches('`').to_string()));
+            }
+        }
+        Ok(Self { tokens, pos: 0, usage })
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, keyword: &str) -> CliResult<()> {
+        match self.keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.error(&format!("Expected {}", keyword))),
+        }
+    }
+
+    fn value(&mut self, what: &str) -> CliResult<String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(text)) | Some(Token::Quoted(text)) => {
+                self.pos += 1;
+                Ok(text.clone())
+            }
+            _ => Err(self.error(&format!("Expected {}", what))),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> CliResult<T> {
+        let text = self.value("a number")?;
+        text.parse().map_err(|_| self.error(&format!("Expected a number, got '{}'", text)))
+    }
+
+    fn rename(&mut self) -> CliResult<(String, String)> {
+        let from = self.value("field")?;
+        self.expect("AS")?;
+        Ok((from, self.value("field")?))
+    }
+
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> CliResult<T>) -> CliResult<Vec<T>> {
+        let mut items = vec![item(self)?];
+        while self.tokens.get(self.pos) == Some(&Token::Comma) {
+            self.pos += 1;
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn unexpected(&self) -> CliError {
+        let token = match &self.tokens[self.pos] {
+            Token::Word(text) | Token::Quoted(text) => text.clone(),
+            Token::Comma => ",".to_string(),
+        };
+        self.error(&format!("Unexpected '{}'", token))
+    }
+
+    fn error(&self, message: &str) -> CliError {
+        Self::usage_error(self.usage, message)
+    }
+
+    fn usage_error(usage: &str, message: &str) -> CliError {
+        CliError::Other(format!("{}\nUsage: {}", message, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 读取源文件的所有行
+    fn read(import: &Import, format: FileFormat, input: &str) -> Vec<Row> {
+        let mut rows = import.rows(format, input.as_bytes()).unwrap();
+        let mut read = Vec::new();
+        while let Some(row) = import.next_row(&mut rows).unwrap() {
+            read.push(row);
+        }
+        read
+    }
+
+    fn document(row: &Row) -> Value {
+        Value::Object(row.1.clone().unwrap())
+    }
+
+    #[test]
+    fn test_csv_quoting_and_embedded_newlines() {
+        let import = Import::new("users", "users.csv");
+        let input = "name,bio,score\n\
+                     \"Hatsune, Miku\",\"says \"\"hi\"\"\nand sings\",39\n\
+                     Rin,plain,2\n";
+        let rows = read(&import, FileFormat::Csv, input);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0, 2);
+        assert_eq!(document(&rows[0]), json!({"name": "Hatsune, Miku", "bio": "says \"hi\"\nand sings", "score": 39}));
+        // 跨行的记录之后行号按文件中的实际行计算
+        assert_eq!(rows[1].0, 4);
+        assert_eq!(document(&rows[1]), json!({"name": "Rin", "bio": "plain", "score": 2}));
+    }
+
+    #[test]
+    fn test_csv_type_inference() {
+        let import = Import::new("users", "users.csv");
+        let input = "_id,age,ratio,zip,phone,active,tags,address.city,note,missing\n\
+                     65f0c0ffee0000000000a001,16,1.5,060,+81,true,\"[\"\"a\"\",1]\",Sapporo,{not json,\n";
+        let rows = read(&import, FileFormat::Csv, input);
+        assert_eq!(
+            document(&rows[0]),
+            json!({
+                "_id": {"$oid": "65f0c0ffee0000000000a001"},
+                "age": 16,
+                "ratio": 1.5,
+                "zip": "060",
+                "phone": "+81",
+                "active": true,
+                "tags": ["a", 1],
+                "address": {"city": "Sapporo"},
+                "note": "{not json",
+            })
+        );
+
+        // NO INFER 时全部作为字符串,空单元格仍然不写入
+        let mut import = Import::new("users", "users.csv");
+        import.infer = false;
+        let rows = read(&import, FileFormat::Csv, "age,active,missing\n16,true,\n");
+        assert_eq!(document(&rows[0]), json!({"age": "16", "active": "true"}));
+    }
+
+    #[test]
+    fn test_csv_declared_types_and_mapping() {
+        let import = Import::parse(
+            "IMPORT users FROM 'users.csv' TYPES born date, code string, age int MAP years AS age DELIMITER ';'",
+        )
+        .unwrap();
+        let input = "years;born;code\n16;2007-08-31;\n17;yesterday;007\n";
+        let rows = read(&import, FileFormat::Csv, input);
+        assert_eq!(
+            document(&rows[0]),
+            json!({"age": 16, "born": {"$date": {"$numberLong": "1188518400000"}}, "code": ""})
+        );
+        assert_eq!(rows[1].0, 3);
+        assert_eq!(rows[1].1, Err("born: 'yesterday' is not a valid date".to_string()));
+    }
+
+    #[test]
+    fn test_csv_malformed_rows_are_rejected() {
+        let import = Import::new("users", "users.csv");
+        let input = "name,age\nMiku,16\nLen\nRin,14,extra\nLuka,20\n";
+        let rows = read(&import, FileFormat::Csv, input);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[1], (3, Err("expected 2 fields, found 1".to_string())));
+        assert_eq!(rows[2], (4, Err("expected 2 fields, found 3".to_string())));
+        // 出错的行不影响之后的行
+        assert_eq!(document(&rows[3]), json!({"name": "Luka", "age": 20}));
+    }
+
+    #[test]
+    fn test_ndjson_rows() {
+        let import = Import::parse("IMPORT users FROM 'users.ndjson' TYPES joined date MAP name AS profile.name").unwrap();
+        let input = concat!(
+            "{\"_id\": {\"$oid\": \"65f0c0ffee0000000000a001\"}, \"name\": \"Miku\", \"bio\": \"line one\\nline two\"}\n",
+            "\n",
+            "{\"name\": \"Rin\", \"joined\": \"2024-06-01\"}\n",
+            "{\"name\": \"Len\"\n",
+            "[1, 2]\n",
+            "{\"joined\": \"soon\"}\n",
+        );
+        let rows = read(&import, FileFormat::Ndjson, input);
+        assert_eq!(rows.iter().map(|row| row.0).collect::<Vec<_>>(), vec![1, 3, 4, 5, 6]);
+        assert_eq!(
+            document(&rows[0]),
+            json!({"_id": {"$oid": "65f0c0ffee0000000000a001"}, "bio": "line one\nline two", "profile": {"name": "Miku"}})
+        );
+        assert_eq!(
+            document(&rows[1]),
+            json!({"joined": {"$date": {"$numberLong": "1717200000000"}}, "profile": {"name": "Rin"}})
+        );
+        assert!(rows[2].1.is_err());
+        assert_eq!(rows[3].1, Err("not a JSON object".to_string()));
+        assert_eq!(rows[4].1, Err("joined: 'soon' is not a valid date".to_string()));
+    }
+
+    #[test]
+    fn test_csv_export_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.csv");
+        let export = Export::new("users", &path);
+        let documents = [
+            json!({"_id": {"$oid": "65f0c0ffee0000000000a001"}, "name": "Hatsune, \"Miku\"", "bio": "one\ntwo", "age": 16}),
+            json!({"_id": {"$oid": "65f0c0ffee0000000000a002"}, "name": "Rin", "tags": ["a", "b"]}),
+        ];
+        let mut sink = Sink::Csv {
+            writer: Box::new(csv::WriterBuilder::new().from_writer(File::create(&path).unwrap())),
+            fields: None,
+        };
+        let batch = documents.iter().map(|d| d.as_object().unwrap().clone()).collect();
+        export.write_batch(&mut sink, batch).unwrap();
+        drop(sink);
+
+        let import = Import::new("users", &path);
+        let rows = read(&import, FileFormat::Csv, &std::fs::read_to_string(&path).unwrap());
+        assert_eq!(document(&rows[0]), documents[0]);
+        assert_eq!(document(&rows[1]), documents[1]);
+    }
+
+    #[test]
+    fn test_export_cell_text() {
+        assert_eq!(cell_text(None), "");
+        assert_eq!(cell_text(Some(&json!({"$oid": "65f0c0ffee0000000000a001"}))), "65f0c0ffee0000000000a001");
+        assert_eq!(cell_text(Some(&json!({"$date": 1717200000000i64}))), "2024-06-01T00:00:00.000Z");
+        assert_eq!(cell_text(Some(&json!({"city": "Sapporo"}))), "{\"city\":\"Sapporo\"}");
+        assert_eq!(cell_text(Some(&json!([1, "a"]))), "[1,\"a\"]");
+        assert_eq!(cell_text(Some(&json!("a,\"b\"\nc"))), "a,\"b\"\nc");
+    }
+}
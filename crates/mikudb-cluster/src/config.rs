@@ -3,6 +3,7 @@
 use crate::error::{ClusterError, ClusterResult};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::time::Duration;
 
 /// 集群配置
@@ -12,14 +13,23 @@ pub struct ClusterConfig {
     pub cluster_name: String,
     /// 本节点 ID
     pub node_id: String,
-    /// 监听地址
+    /// 集群端口的监听地址,节点间的 Raft RPC 使用该端口
     pub bind_addr: SocketAddr,
+    /// 其他节点连接本节点使用的地址,未配置时使用集群端口实际监听的地址
+    #[serde(default)]
+    pub advertise_addr: Option<String>,
     /// 种子节点列表
     pub seeds: Vec<String>,
+    /// 手动指定的 ObjectId 机器标识,未指定时加入集群时自动分配
+    #[serde(default)]
+    pub machine_id: Option<u32>,
     /// Raft 配置
     pub raft: RaftConfig,
     /// 复制配置
     pub replication: ReplicationConfig,
+    /// 节点间传输配置
+    #[serde(default)]
+    pub transport: TransportConfig,
 }
 
 impl ClusterConfig {
@@ -51,12 +61,40 @@ impl ClusterConfig {
             cluster_name: "mikudb".to_string(),
             node_id: format!("node_{}", bind_addr.port()),
             bind_addr,
+            advertise_addr: None,
             seeds,
+            machine_id: None,
             raft: RaftConfig::default(),
             replication: ReplicationConfig::default(),
+            transport: TransportConfig::default(),
         })
     }
 
+    /// # Brief
+    /// 解析加入集群时指定的种子地址
+    ///
+    /// # Arguments
+    /// * `uri` - 格式: "mikudb://host:port[,host:port...]",端口为成员的集群端口
+    ///
+    /// # Returns
+    /// `host:port` 列表;缺少前缀、地址为空或端口无效时返回 Config 错误
+    pub fn parse_seed_uri(uri: &str) -> ClusterResult<Vec<String>> {
+        let hosts = uri
+            .strip_prefix("mikudb://")
+            .ok_or_else(|| ClusterError::Config("Seed address must start with 'mikudb://'".into()))?;
+        hosts
+            .trim_end_matches('/')
+            .split(',')
+            .map(|seed| {
+                let seed = seed.trim();
+                match seed.rsplit_once(':') {
+                    Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(seed.to_string()),
+                    _ => Err(ClusterError::Config(format!("Invalid seed address '{}', expected host:port", seed))),
+                }
+            })
+            .collect()
+    }
+
     /// 从文件加载配置
     pub fn load(_path: &str) -> ClusterResult<Self> {
         // TODO: 实现从文件加载
@@ -70,9 +108,12 @@ impl Default for ClusterConfig {
             cluster_name: "mikudb".to_string(),
             node_id: "node1".to_string(),
             bind_addr: "127.0.0.1:3940".parse().unwrap(),
+            advertise_addr: None,
             seeds: vec![],
+            machine_id: None,
             raft: RaftConfig::default(),
             replication: ReplicationConfig::default(),
+            transport: TransportConfig::default(),
         }
     }
 }
@@ -101,6 +142,53 @@ impl Default for RaftConfig {
     }
 }
 
+/// 节点间传输配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransportConfig {
+    /// 负载不小于该字节数时以 LZ4 压缩,0 表示不压缩
+    pub compression_threshold: usize,
+    /// 发送队列中积压的帧合并为一次写入的最大字节数
+    pub max_batch_bytes: usize,
+    /// 发送快照时每个 InstallSnapshot 请求携带的字节数
+    pub snapshot_chunk_bytes: usize,
+    /// 建立连接的超时 (毫秒)
+    pub connect_timeout_ms: u64,
+    /// 单次 RPC 的超时 (毫秒)
+    pub request_timeout_ms: u64,
+    /// 双向 TLS 配置,未配置时明文传输
+    pub tls: Option<ClusterTlsConfig>,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            compression_threshold: 4096,
+            max_batch_bytes: 1024 * 1024,
+            snapshot_chunk_bytes: 1024 * 1024,
+            connect_timeout_ms: 3000,
+            request_timeout_ms: 5000,
+            tls: None,
+        }
+    }
+}
+
+/// 节点间双向 TLS 配置
+///
+/// 每个节点使用同一个集群 CA 签发的证书,既作为服务端证书也作为客户端证书
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterTlsConfig {
+    /// 本节点证书 (PEM)
+    pub cert_path: PathBuf,
+    /// 本节点私钥 (PEM)
+    pub key_path: PathBuf,
+    /// 集群 CA 证书 (PEM),用于验证对端证书
+    pub ca_path: PathBuf,
+    /// 校验对端证书时使用的名称,未指定时使用对端地址中的主机名或 IP
+    #[serde(default)]
+    pub server_name: Option<String>,
+}
+
 /// 复制配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplicationConfig {
@@ -160,4 +248,15 @@ mod tests {
         let result = ClusterConfig::from_connection_string("invalid://localhost:3940");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_seed_uri() {
+        assert_eq!(
+            ClusterConfig::parse_seed_uri("mikudb://db1.local:3941, 10.0.0.2:3941/").unwrap(),
+            vec!["db1.local:3941".to_string(), "10.0.0.2:3941".to_string()]
+        );
+        assert!(ClusterConfig::parse_seed_uri("db1.local:3941").is_err());
+        assert!(ClusterConfig::parse_seed_uri("mikudb://db1.local").is_err());
+        assert!(ClusterConfig::parse_seed_uri("mikudb://:3941").is_err());
+    }
 }
@@ -0,0 +1,92 @@
+//! 集群身份持久化
+//!
+//! 节点初始化或加入集群后,把集群 ID、本节点 ID、机器标识和已知成员地址写入
+//! 数据目录下的 `cluster.json`。重启时读取该文件,以相同的身份重新加入集群,
+//! 不需要再次指定初始化或加入参数。
+
+use crate::{ClusterError, ClusterResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 集群身份文件名
+pub const IDENTITY_FILE: &str = "cluster.json";
+
+/// 持久化的集群身份
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterIdentity {
+    /// 集群 ID,初始化集群时生成
+    pub cluster_id: String,
+    /// 本节点 ID
+    pub node_id: String,
+    /// 本节点的 ObjectId 机器标识
+    pub machine_id: u32,
+    /// 其他节点连接本节点使用的地址
+    pub addr: String,
+    /// 其他成员的集群端口地址,重启时依次尝试从这些节点重新加入;
+    /// 为空表示本节点初始化了集群且没有其他已知成员
+    #[serde(default)]
+    pub peers: Vec<String>,
+}
+
+impl ClusterIdentity {
+    /// # Brief
+    /// 数据目录下集群身份文件的路径
+    pub fn path(data_dir: &Path) -> PathBuf {
+        data_dir.join(IDENTITY_FILE)
+    }
+
+    /// # Brief
+    /// 读取数据目录中的集群身份
+    ///
+    /// # Returns
+    /// 文件不存在时返回 None;文件损坏时返回 Config 错误
+    pub fn load(data_dir: &Path) -> ClusterResult<Option<Self>> {
+        let path = Self::path(data_dir);
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| ClusterError::Config(format!("Invalid cluster identity file {}: {}", path.display(), e)))
+    }
+
+    /// # Brief
+    /// 写入集群身份
+    ///
+    /// 先写临时文件再改名,写入中途崩溃不会留下不完整的身份文件。
+    pub fn save(&self, data_dir: &Path) -> ClusterResult<()> {
+        let content = serde_json::to_vec_pretty(self).map_err(|e| ClusterError::Serialization(e.to_string()))?;
+        let path = Self::path(data_dir);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(ClusterIdentity::load(dir.path()).unwrap(), None);
+
+        let identity = ClusterIdentity {
+            cluster_id: "c1".to_string(),
+            node_id: "n2".to_string(),
+            machine_id: 3,
+            addr: "10.0.0.2:3941".to_string(),
+            peers: vec!["10.0.0.1:3941".to_string()],
+        };
+        identity.save(dir.path()).unwrap();
+        assert_eq!(ClusterIdentity::load(dir.path()).unwrap(), Some(identity));
+
+        fs::write(ClusterIdentity::path(dir.path()), b"{").unwrap();
+        assert!(matches!(ClusterIdentity::load(dir.path()), Err(ClusterError::Config(_))));
+    }
+}
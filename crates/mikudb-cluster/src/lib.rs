@@ -6,6 +6,13 @@
 //! - **故障转移**: 自动检测节点故障并触发 Leader 选举
 //! - **读写分离**: 智能路由读写请求到不同节点
 //! - **节点管理**: 动态添加/移除集群节点
+//! - **节点间传输**: 独立集群端口上的紧凑二进制 RPC(AppendEntries/Vote/InstallSnapshot),
+//!   连接复用、批量写入、LZ4 压缩、双向 TLS 和传输统计
+//! - **集群初始化与加入**: 单节点初始化集群,其他节点通过任一成员加入;集群身份持久化在数据目录,
+//!   重启后自动重新加入
+//! - **集合复制策略**: 每个集合可复制到所有节点、只保存在 Leader 或只复制到指定节点,
+//!   读请求只路由到保存了该集合的节点
+//! - **ObjectId 机器标识**: 节点加入时分配唯一的机器标识并记录在 Raft 成员配置中,启动时检查重复标识
 //!
 //! # OpenEuler 优化
 //!
@@ -19,16 +26,26 @@ pub mod node;
 pub mod router;
 pub mod config;
 pub mod error;
+pub mod identity;
+pub mod transport;
 
-pub use config::{ClusterConfig, RaftConfig, ReplicationConfig};
+pub use config::{ClusterConfig, ClusterTlsConfig, RaftConfig, ReplicationConfig, TransportConfig};
 pub use error::{ClusterError, ClusterResult};
+pub use identity::ClusterIdentity;
 pub use node::{Node, NodeRole, NodeState, HealthStatus};
-pub use raft::{RaftNode, LogEntry, Command};
-pub use replication::{ReplicationManager, ReplicationMode, WriteConcern, ReadPreference};
+pub use raft::{
+    RaftNode, RaftStatus, LogEntry, Command, Membership, Member, AppendEntriesRequest, AppendEntriesResponse,
+    VoteRequest, VoteResponse, InstallSnapshotRequest, InstallSnapshotResponse, JoinRequest, JoinResponse,
+};
+pub use replication::{
+    ReplicationCatalog, ReplicationManager, ReplicationMode, ReplicationPolicy, WriteConcern, ReadPreference,
+};
 pub use router::QueryRouter;
+pub use transport::{RaftRpcHandler, RaftTransport, TransportServer, TransportStats};
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
@@ -54,6 +71,8 @@ pub struct Cluster {
     replication_manager: Arc<ReplicationManager>,
     /// 查询路由器
     query_router: Arc<QueryRouter>,
+    /// 初始化或加入集群后持久化的身份
+    identity: RwLock<Option<ClusterIdentity>>,
 }
 
 impl Cluster {
@@ -76,7 +95,150 @@ impl Cluster {
 
         // 解析连接字符串
         let config = ClusterConfig::from_connection_string(connection_string)?;
+        let cluster = Self::create(config).await?;
 
+        // 启动集群服务
+        cluster.start().await?;
+
+        Ok(cluster)
+    }
+
+    /// # Brief
+    /// 以本节点为唯一成员初始化集群
+    ///
+    /// 生成集群 ID,启动集群端口并把集群身份写入数据目录,重启后由 [`Cluster::resume`] 恢复。
+    ///
+    /// # Arguments
+    /// * `config` - 集群配置
+    /// * `data_dir` - 保存集群身份的数据目录
+    ///
+    /// # Returns
+    /// 数据目录中已有集群身份时返回 Config 错误
+    pub async fn init(config: ClusterConfig, data_dir: &Path) -> ClusterResult<Self> {
+        Self::ensure_uninitialized(data_dir)?;
+        let cluster = Self::create(config).await?;
+        cluster.raft_node.start().await?;
+
+        let cluster_id = ObjectId::new().to_hex();
+        let machine_id = cluster.raft_node.bootstrap(&cluster_id, &cluster.advertised_addr()).await?;
+        info!("Initialized cluster {}", cluster_id);
+        cluster.finish_join(machine_id, data_dir).await?;
+        Ok(cluster)
+    }
+
+    /// # Brief
+    /// 通过已在集群中的节点加入集群
+    ///
+    /// 依次尝试各个种子节点,第一个接受请求的节点分配机器标识并返回成员配置。
+    ///
+    /// # Arguments
+    /// * `config` - 集群配置
+    /// * `seeds` - 集群成员的集群端口地址,见 [`ClusterConfig::parse_seed_uri`]
+    /// * `data_dir` - 保存集群身份的数据目录
+    ///
+    /// # Returns
+    /// 数据目录中已有集群身份时返回 Config 错误;所有种子节点都失败时返回最后一个错误
+    pub async fn join(config: ClusterConfig, seeds: &[String], data_dir: &Path) -> ClusterResult<Self> {
+        Self::ensure_uninitialized(data_dir)?;
+        let cluster = Self::create(config).await?;
+        cluster.raft_node.start().await?;
+
+        let response = cluster.join_any(seeds).await?;
+        info!("Joined cluster {}", response.cluster_id);
+        cluster.finish_join(response.machine_id, data_dir).await?;
+        Ok(cluster)
+    }
+
+    /// # Brief
+    /// 按数据目录中持久化的集群身份重新加入集群
+    ///
+    /// 以原来的节点 ID 和机器标识向已知成员重新加入;初始化集群的节点或所有已知成员都不可达时,
+    /// 以持久化的身份单独启动,等待其他成员重新加入。
+    ///
+    /// # Arguments
+    /// * `config` - 集群配置,节点 ID 和机器标识被持久化的值覆盖
+    /// * `data_dir` - 保存集群身份的数据目录
+    ///
+    /// # Returns
+    /// 没有集群身份时返回 None;已知成员属于其他集群时返回 Config 错误
+    pub async fn resume(mut config: ClusterConfig, data_dir: &Path) -> ClusterResult<Option<Self>> {
+        let Some(identity) = ClusterIdentity::load(data_dir)? else {
+            return Ok(None);
+        };
+        config.node_id = identity.node_id.clone();
+        config.machine_id = Some(identity.machine_id);
+        let cluster = Self::create(config).await?;
+        cluster.raft_node.start().await?;
+
+        let rejoined = match identity.peers.is_empty() {
+            true => None,
+            false => match cluster.join_any(&identity.peers).await {
+                Ok(response) if response.cluster_id != identity.cluster_id => {
+                    return Err(ClusterError::Config(format!(
+                        "Known members belong to cluster {}, but this node was part of cluster {}",
+                        response.cluster_id, identity.cluster_id
+                    )));
+                }
+                Ok(response) => Some(response.machine_id),
+                Err(e) => {
+                    warn!("No known member of cluster {} is reachable: {}", identity.cluster_id, e);
+                    None
+                }
+            },
+        };
+        let machine_id = match rejoined {
+            Some(machine_id) => machine_id,
+            None => {
+                let addr = cluster.advertised_addr();
+                cluster.raft_node.bootstrap(&identity.cluster_id, &addr).await?
+            }
+        };
+        info!("Rejoined cluster {} as node {}", identity.cluster_id, identity.node_id);
+        cluster.finish_join(machine_id, data_dir).await?;
+        Ok(Some(cluster))
+    }
+
+    /// 持久化的集群身份,初始化、加入或恢复集群后存在
+    pub fn identity(&self) -> Option<ClusterIdentity> {
+        self.identity.read().clone()
+    }
+
+    /// Raft 节点
+    pub fn raft_node(&self) -> &Arc<RaftNode> {
+        &self.raft_node
+    }
+
+    /// # Brief
+    /// 设置集合的复制策略
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名
+    /// * `policy` - 复制策略,[`ReplicationPolicy::All`] 恢复默认
+    ///
+    /// # Returns
+    /// 策略指定了不是集群成员的节点时返回 Config 错误
+    pub async fn set_replication_policy(&self, collection: &str, policy: ReplicationPolicy) -> ClusterResult<()> {
+        self.raft_node.set_replication_policy(collection, policy).await?;
+        info!("Replication policy of {} is {:?}", collection, self.replication_manager.policy(collection));
+        Ok(())
+    }
+
+    /// 集合的复制策略
+    pub fn replication_policy(&self, collection: &str) -> ReplicationPolicy {
+        self.replication_manager.policy(collection)
+    }
+
+    /// # Brief
+    /// 路由集合的读请求到保存了该集合的节点
+    ///
+    /// # Returns
+    /// 节点 ID
+    pub async fn route_read(&self, collection: &str, preference: ReadPreference) -> ClusterResult<String> {
+        self.query_router.route_collection_read(collection, preference).await
+    }
+
+    /// 创建集群各组件,尚未启动
+    async fn create(config: ClusterConfig) -> ClusterResult<Self> {
         // 初始化节点列表
         let nodes = Arc::new(DashMap::new());
 
@@ -84,24 +246,83 @@ impl Cluster {
         let raft_node = Arc::new(RaftNode::new(config.clone()).await?);
 
         // 创建复制管理器
-        let replication_manager = Arc::new(ReplicationManager::new(config.clone()).await?);
+        let replication_manager = Arc::new(ReplicationManager::new(config.clone(), raft_node.catalog_handle()).await?);
 
         // 创建查询路由器
-        let query_router = Arc::new(QueryRouter::new(nodes.clone()).await?);
+        let query_router = Arc::new(QueryRouter::new(nodes.clone(), raft_node.catalog_handle()).await?);
 
-        let cluster = Self {
+        Ok(Self {
             config,
             nodes,
             leader_id: Arc::new(RwLock::new(None)),
             raft_node,
             replication_manager,
             query_router,
-        };
+            identity: RwLock::new(None),
+        })
+    }
 
-        // 启动集群服务
-        cluster.start().await?;
+    fn ensure_uninitialized(data_dir: &Path) -> ClusterResult<()> {
+        match ClusterIdentity::load(data_dir)? {
+            Some(identity) => Err(ClusterError::Config(format!(
+                "Node {} is already part of cluster {}",
+                identity.node_id, identity.cluster_id
+            ))),
+            None => Ok(()),
+        }
+    }
 
-        Ok(cluster)
+    /// 其他节点连接本节点使用的地址
+    fn advertised_addr(&self) -> String {
+        self.config
+            .advertise_addr
+            .clone()
+            .or_else(|| self.raft_node.local_addr().map(|addr| addr.to_string()))
+            .unwrap_or_else(|| self.config.bind_addr.to_string())
+    }
+
+    /// # Brief
+    /// 依次向种子节点请求加入集群
+    async fn join_any(&self, seeds: &[String]) -> ClusterResult<JoinResponse> {
+        let addr = self.advertised_addr();
+        let mut last_error = ClusterError::Config("No seed address given".into());
+        for seed in seeds {
+            match self.raft_node.join_via(seed, &addr).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    debug!("Joining through {} failed: {}", seed, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    /// # Brief
+    /// 加入集群后使用分配的机器标识,启动其余服务并持久化集群身份
+    async fn finish_join(&self, machine_id: u32, data_dir: &Path) -> ClusterResult<()> {
+        self.adopt_machine_id(machine_id)?;
+        self.replication_manager.start().await?;
+        self.start_health_check().await?;
+
+        let peers = self
+            .raft_node
+            .membership()
+            .members
+            .into_iter()
+            .filter(|(node_id, _)| *node_id != self.config.node_id)
+            .map(|(_, member)| member.addr)
+            .collect();
+        let identity = ClusterIdentity {
+            cluster_id: self.raft_node.cluster_id().unwrap_or_default(),
+            node_id: self.config.node_id.clone(),
+            machine_id,
+            addr: self.advertised_addr(),
+            peers,
+        };
+        identity.save(data_dir)?;
+        *self.identity.write() = Some(identity);
+        Ok(())
     }
 
     /// 启动集群服务
@@ -111,6 +332,9 @@ impl Cluster {
         // 启动 Raft 节点
         self.raft_node.start().await?;
 
+        // 加入集群并使用分配的机器标识生成 ObjectId
+        self.join_cluster().await?;
+
         // 启动复制管理器
         self.replication_manager.start().await?;
 
@@ -121,6 +345,34 @@ impl Cluster {
         Ok(())
     }
 
+    /// # Brief
+    /// 加入集群并设置本节点的 ObjectId 机器标识
+    async fn join_cluster(&self) -> ClusterResult<()> {
+        let machine_id = self
+            .raft_node
+            .join(&self.config.node_id, &self.config.bind_addr.to_string(), self.config.machine_id)
+            .await?;
+        self.adopt_machine_id(machine_id)
+    }
+
+    /// # Brief
+    /// 使用分配的机器标识生成 ObjectId
+    ///
+    /// 成员配置中有多个节点共用同一机器标识时输出警告,这些节点生成的 ObjectId 可能冲突。
+    fn adopt_machine_id(&self, machine_id: u32) -> ClusterResult<()> {
+        ObjectId::set_machine_id(machine_id).map_err(|e| ClusterError::Config(e.to_string()))?;
+        info!("Node {} uses machine id {}", self.config.node_id, machine_id);
+
+        for (machine_id, nodes) in self.raft_node.membership().duplicate_machine_ids() {
+            warn!(
+                "Machine id {} is shared by nodes {}; ObjectIds generated on them may collide",
+                machine_id,
+                nodes.join(", ")
+            );
+        }
+        Ok(())
+    }
+
     /// 启动健康检查
     async fn start_health_check(&self) -> ClusterResult<()> {
         // 实现健康检查逻辑
@@ -164,4 +416,61 @@ mod tests {
         // 测试集群创建
         // TODO: 实现测试
     }
+
+    fn node_config(node_id: &str) -> ClusterConfig {
+        ClusterConfig {
+            node_id: node_id.to_string(),
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_init_join_and_resume() {
+        let (dir1, dir2) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let first = Cluster::init(node_config("n1"), dir1.path()).await.unwrap();
+        let first_identity = first.identity().unwrap();
+        assert!(first_identity.peers.is_empty());
+        assert!(Cluster::init(node_config("n1"), dir1.path()).await.is_err());
+
+        let seed = first.raft_node().local_addr().unwrap().to_string();
+        let second = Cluster::join(node_config("n2"), std::slice::from_ref(&seed), dir2.path()).await.unwrap();
+        let second_identity = second.identity().unwrap();
+        assert_eq!(second_identity.cluster_id, first_identity.cluster_id);
+        assert_ne!(second_identity.machine_id, first_identity.machine_id);
+        assert_eq!(second_identity.peers, vec![seed]);
+        assert_eq!(first.raft_node().membership().members.len(), 2);
+        drop(second);
+
+        // 重启后以持久化的身份重新加入,配置中的节点 ID 被覆盖
+        let resumed = Cluster::resume(node_config("other"), dir2.path()).await.unwrap().unwrap();
+        assert_eq!(resumed.identity().unwrap().node_id, "n2");
+        assert_eq!(resumed.identity().unwrap().machine_id, second_identity.machine_id);
+        assert_eq!(first.raft_node().membership().members.len(), 2);
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(Cluster::resume(node_config("n3"), empty.path()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_replication_policy() {
+        let (dir1, dir2) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let first = Cluster::init(node_config("n1"), dir1.path()).await.unwrap();
+        first.set_replication_policy("audit", ReplicationPolicy::LeaderOnly).await.unwrap();
+        let unknown = ReplicationPolicy::Nodes(["n9".to_string()].into());
+        assert!(first.set_replication_policy("reports", unknown).await.is_err());
+        assert_eq!(first.replication_policy("reports"), ReplicationPolicy::All);
+
+        // 加入的节点从成员处获得策略目录
+        let seed = first.raft_node().local_addr().unwrap().to_string();
+        let second = Cluster::join(node_config("n2"), &[seed], dir2.path()).await.unwrap();
+        assert_eq!(second.replication_policy("audit"), ReplicationPolicy::LeaderOnly);
+
+        let followers = vec!["n2".to_string()];
+        let write = Command::Delete { collection: "audit".to_string(), doc_id: ObjectId::new(), meta: None };
+        assert!(first.replication_manager.targets(&write, &followers).is_empty());
+        first.set_replication_policy("audit", ReplicationPolicy::All).await.unwrap();
+        assert_eq!(first.replication_manager.targets(&write, &followers).len(), 1);
+        assert!(first.raft_node().catalog().policies.is_empty());
+    }
 }
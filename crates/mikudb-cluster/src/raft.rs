@@ -1,28 +1,205 @@
 //! Raft 共识算法实现
+//!
+//! 节点间的 AppendEntries、RequestVote 和 InstallSnapshot 通过集群端口传输,见 [`crate::transport`]。
+//! 接收端按 Raft 的规则处理这些请求: 更新任期、投票、检查日志一致性后追加日志、分块接收快照。
+//!
+//! 集群由一个节点初始化([`RaftNode::bootstrap`]),其他节点通过任一成员的集群端口加入
+//! ([`RaftNode::join_via`]),由该成员分配机器标识并返回集群 ID、成员配置和集合复制策略目录。
 
+use crate::replication::{ReplicationCatalog, ReplicationPolicy};
+use crate::transport::{RaftRpcHandler, RaftTransport, TransportServer};
 use crate::{ClusterConfig, ClusterError, ClusterResult};
+use async_trait::async_trait;
 use mikudb_boml::Document;
-use mikudb_common::ObjectId;
+use mikudb_common::{ObjectId, MAX_MACHINE_ID};
+use mikudb_storage::WriteMetadata;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{debug, info};
 
 /// Raft 节点
 pub struct RaftNode {
     config: ClusterConfig,
+    /// 成员配置,随 ConfigChange 日志条目更新
+    membership: Arc<RwLock<Membership>>,
+    /// 集合复制策略,随 SetReplicationPolicy 日志条目更新
+    catalog: Arc<RwLock<ReplicationCatalog>>,
+    /// 所属集群的 ID,初始化或加入集群后存在
+    cluster_id: Arc<RwLock<Option<String>>>,
+    /// 任期、投票和日志
+    state: Arc<Mutex<RaftState>>,
+    /// 发往其他节点的 RPC 客户端
+    transport: Arc<RaftTransport>,
+    /// 集群端口上的 RPC 服务,启动后存在
+    server: Mutex<Option<TransportServer>>,
 }
 
 impl RaftNode {
     /// 创建 Raft 节点
     pub async fn new(config: ClusterConfig) -> ClusterResult<Self> {
         info!("Creating Raft node: {}", config.node_id);
-        Ok(Self { config })
+        let transport = Arc::new(RaftTransport::new(config.transport.clone())?);
+        Ok(Self {
+            config,
+            membership: Arc::new(RwLock::new(Membership::default())),
+            catalog: Arc::new(RwLock::new(ReplicationCatalog::default())),
+            cluster_id: Arc::new(RwLock::new(None)),
+            state: Arc::new(Mutex::new(RaftState::default())),
+            transport,
+            server: Mutex::new(None),
+        })
+    }
+
+    /// 发往其他节点的 RPC 客户端
+    pub fn transport(&self) -> &Arc<RaftTransport> {
+        &self.transport
+    }
+
+    /// 集群端口实际监听的地址,启动前为 None
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.lock().as_ref().map(TransportServer::local_addr)
+    }
+
+    /// 当前任期、日志和提交位置
+    pub fn status(&self) -> RaftStatus {
+        let state = self.state.lock();
+        RaftStatus {
+            current_term: state.current_term,
+            voted_for: state.voted_for.clone(),
+            last_log_index: state.last_log().0,
+            commit_index: state.commit_index,
+            snapshot_index: state.snapshot_index,
+            snapshot_len: state.snapshot.len(),
+        }
+    }
+
+    /// # Brief
+    /// 节点加入集群
+    ///
+    /// 为节点分配机器标识,通过 ConfigChange 写入 Raft 日志,提交后应用到成员配置。
+    /// 机器标识随日志复制到所有节点,重启后回放日志得到相同的分配。
+    ///
+    /// # Arguments
+    /// * `node_id` - 加入的节点 ID
+    /// * `addr` - 节点地址
+    /// * `requested` - 配置中手动指定的机器标识
+    ///
+    /// # Returns
+    /// 节点的机器标识
+    pub async fn join(&self, node_id: &str, addr: &str, requested: Option<u32>) -> ClusterResult<u32> {
+        let machine_id = self.membership.read().assign_machine_id(node_id, requested)?;
+        let command = Command::ConfigChange {
+            node_id: node_id.to_string(),
+            addr: addr.to_string(),
+            action: ConfigAction::Add,
+            machine_id: Some(machine_id),
+        };
+        self.propose(command.clone()).await?;
+        self.membership.write().apply(&command);
+        Ok(machine_id)
+    }
+
+    /// 当前成员配置
+    pub fn membership(&self) -> Membership {
+        self.membership.read().clone()
+    }
+
+    /// 当前集合复制策略目录
+    pub fn catalog(&self) -> ReplicationCatalog {
+        self.catalog.read().clone()
+    }
+
+    /// 与复制管理器和查询路由器共享的集合复制策略目录
+    pub fn catalog_handle(&self) -> Arc<RwLock<ReplicationCatalog>> {
+        self.catalog.clone()
+    }
+
+    /// # Brief
+    /// 设置集合的复制策略
+    ///
+    /// 策略通过 SetReplicationPolicy 写入 Raft 日志,提交后应用到所有节点的策略目录。
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名
+    /// * `policy` - 复制策略
+    ///
+    /// # Returns
+    /// 策略指定了不是集群成员的节点时返回 Config 错误
+    pub async fn set_replication_policy(&self, collection: &str, policy: ReplicationPolicy) -> ClusterResult<()> {
+        if let ReplicationPolicy::Nodes(nodes) = &policy {
+            let membership = self.membership.read();
+            if let Some(unknown) = nodes.iter().find(|node_id| !membership.members.contains_key(*node_id)) {
+                return Err(ClusterError::Config(format!("Node {} is not a cluster member", unknown)));
+            }
+        }
+        let command = Command::SetReplicationPolicy {
+            collection: collection.to_string(),
+            policy,
+        };
+        self.propose(command.clone()).await?;
+        self.catalog.write().apply(&command);
+        Ok(())
+    }
+
+    /// 所属集群的 ID,初始化或加入集群前为 None
+    pub fn cluster_id(&self) -> Option<String> {
+        self.cluster_id.read().clone()
+    }
+
+    /// # Brief
+    /// 以本节点为唯一成员初始化集群
+    ///
+    /// # Arguments
+    /// * `cluster_id` - 集群 ID,重启时传入之前持久化的 ID
+    /// * `addr` - 其他节点连接本节点使用的地址
+    ///
+    /// # Returns
+    /// 本节点的机器标识
+    pub async fn bootstrap(&self, cluster_id: &str, addr: &str) -> ClusterResult<u32> {
+        *self.cluster_id.write() = Some(cluster_id.to_string());
+        self.join(&self.config.node_id, addr, self.config.machine_id).await
+    }
+
+    /// # Brief
+    /// 通过已在集群中的节点加入集群
+    ///
+    /// 对端为本节点分配机器标识,本节点采用对端返回的集群 ID、成员配置和集合复制策略目录。
+    /// 已是成员的节点重新加入时沿用原来的机器标识。
+    ///
+    /// # Arguments
+    /// * `seed` - 集群成员的集群端口地址
+    /// * `addr` - 其他节点连接本节点使用的地址
+    pub async fn join_via(&self, seed: &str, addr: &str) -> ClusterResult<JoinResponse> {
+        let request = JoinRequest {
+            node_id: self.config.node_id.clone(),
+            addr: addr.to_string(),
+            machine_id: self.config.machine_id,
+        };
+        let response = self.transport.join(seed, &request).await?;
+        *self.cluster_id.write() = Some(response.cluster_id.clone());
+        *self.membership.write() = response.membership.clone();
+        *self.catalog.write() = response.catalog.clone();
+        Ok(response)
     }
 
     /// 启动 Raft 节点
+    ///
+    /// 在 `bind_addr` 上启动集群端口,接收其他节点的 RPC
     pub async fn start(&self) -> ClusterResult<()> {
         info!("Starting Raft node: {}", self.config.node_id);
-        // TODO: 实现 Raft 启动逻辑
+        let service = Arc::new(RaftRpcService {
+            node_id: self.config.node_id.clone(),
+            state: self.state.clone(),
+            membership: self.membership.clone(),
+            catalog: self.catalog.clone(),
+            cluster_id: self.cluster_id.clone(),
+        });
+        let server = TransportServer::bind(self.config.bind_addr, &self.config.transport, service).await?;
+        info!("Raft node {} listening on {}", self.config.node_id, server.local_addr());
+        *self.server.lock() = Some(server);
         Ok(())
     }
 
@@ -50,21 +227,336 @@ pub enum Command {
     /// 写入文档
     Write {
         collection: String,
+        #[serde(with = "boml_document")]
         doc: Document,
+        /// 写入元数据,随日志复制到其他节点,不写入文档
+        meta: Option<WriteMetadata>,
     },
     /// 删除文档
     Delete {
         collection: String,
         doc_id: ObjectId,
+        /// 写入元数据,随日志复制到其他节点
+        meta: Option<WriteMetadata>,
     },
     /// 配置变更
     ConfigChange {
         node_id: String,
         addr: String,
         action: ConfigAction,
+        /// 添加节点时分配的 ObjectId 机器标识
+        machine_id: Option<u32>,
+    },
+    /// 集合复制策略变更
+    SetReplicationPolicy {
+        collection: String,
+        policy: ReplicationPolicy,
     },
 }
 
+impl Command {
+    /// 命令写入的集合,配置变更和策略变更为 None
+    pub fn collection(&self) -> Option<&str> {
+        match self {
+            Self::Write { collection, .. } | Self::Delete { collection, .. } => Some(collection),
+            Self::ConfigChange { .. } | Self::SetReplicationPolicy { .. } => None,
+        }
+    }
+
+    /// 文档写入附带的元数据,应用日志时在 `WriteMetadata::scope` 中写入,
+    /// 使本节点的变更事件带有发起节点等信息
+    pub fn metadata(&self) -> Option<&WriteMetadata> {
+        match self {
+            Self::Write { meta, .. } | Self::Delete { meta, .. } => meta.as_ref(),
+            Self::ConfigChange { .. } | Self::SetReplicationPolicy { .. } => None,
+        }
+    }
+}
+
+/// Raft 节点状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RaftStatus {
+    /// 当前任期
+    pub current_term: u64,
+    /// 当前任期投票给的节点
+    pub voted_for: Option<String>,
+    /// 最后一条日志的索引(包括快照覆盖的部分)
+    pub last_log_index: u64,
+    /// 已提交的日志索引
+    pub commit_index: u64,
+    /// 最近安装的快照覆盖到的日志索引
+    pub snapshot_index: u64,
+    /// 最近安装的快照大小(字节)
+    pub snapshot_len: usize,
+}
+
+/// AppendEntries 请求,`entries` 为空时是心跳
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppendEntriesRequest {
+    pub term: u64,
+    pub leader_id: String,
+    /// 新日志之前一条日志的索引和任期
+    pub prev_log_index: u64,
+    pub prev_log_term: u64,
+    /// 要追加的日志,一次请求可以携带多条
+    pub entries: Vec<LogEntry>,
+    pub leader_commit: u64,
+}
+
+/// AppendEntries 响应
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendEntriesResponse {
+    pub term: u64,
+    pub success: bool,
+    /// 接收端最后一条日志的索引,Leader 据此回退 next_index
+    pub last_log_index: u64,
+}
+
+/// RequestVote 请求
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteRequest {
+    pub term: u64,
+    pub candidate_id: String,
+    pub last_log_index: u64,
+    pub last_log_term: u64,
+}
+
+/// RequestVote 响应
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteResponse {
+    pub term: u64,
+    pub vote_granted: bool,
+}
+
+/// InstallSnapshot 请求,快照按 `offset` 分块发送,最后一块 `done` 为 true
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallSnapshotRequest {
+    pub term: u64,
+    pub leader_id: String,
+    /// 快照覆盖的最后一条日志的索引和任期
+    pub last_included_index: u64,
+    pub last_included_term: u64,
+    pub offset: u64,
+    pub data: Vec<u8>,
+    pub done: bool,
+}
+
+/// InstallSnapshot 响应
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallSnapshotResponse {
+    pub term: u64,
+}
+
+/// 加入集群请求
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinRequest {
+    pub node_id: String,
+    /// 其他节点连接新节点使用的地址
+    pub addr: String,
+    /// 手动指定的机器标识
+    pub machine_id: Option<u32>,
+}
+
+/// 加入集群响应
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinResponse {
+    pub cluster_id: String,
+    /// 分配给新节点的机器标识
+    pub machine_id: u32,
+    /// 加入后的成员配置
+    pub membership: Membership,
+    /// 集合复制策略目录
+    pub catalog: ReplicationCatalog,
+}
+
+/// 任期、投票和日志
+#[derive(Debug, Default)]
+struct RaftState {
+    current_term: u64,
+    voted_for: Option<String>,
+    /// 快照覆盖到的日志索引和任期
+    snapshot_index: u64,
+    snapshot_term: u64,
+    /// `snapshot_index` 之后的日志
+    log: Vec<LogEntry>,
+    commit_index: u64,
+    /// 最近安装的快照
+    snapshot: Vec<u8>,
+    /// 正在分块接收的快照
+    receiving: Vec<u8>,
+}
+
+impl RaftState {
+    /// 最后一条日志的 (索引, 任期)
+    fn last_log(&self) -> (u64, u64) {
+        self.log
+            .last()
+            .map_or((self.snapshot_index, self.snapshot_term), |entry| (entry.index, entry.term))
+    }
+
+    /// 指定索引处日志的任期,已被快照截断或不存在时为 None
+    fn term_at(&self, index: u64) -> Option<u64> {
+        if index == self.snapshot_index {
+            return Some(self.snapshot_term);
+        }
+        let offset = index.checked_sub(self.snapshot_index + 1)?;
+        self.log.get(offset as usize).map(|entry| entry.term)
+    }
+
+    /// 看到更大的任期时转为该任期并清除投票
+    fn observe_term(&mut self, term: u64) {
+        if term > self.current_term {
+            self.current_term = term;
+            self.voted_for = None;
+        }
+    }
+}
+
+/// 集群端口上的 RPC 处理,按 Raft 的接收端规则更新节点状态
+struct RaftRpcService {
+    node_id: String,
+    state: Arc<Mutex<RaftState>>,
+    membership: Arc<RwLock<Membership>>,
+    catalog: Arc<RwLock<ReplicationCatalog>>,
+    cluster_id: Arc<RwLock<Option<String>>>,
+}
+
+#[async_trait]
+impl RaftRpcHandler for RaftRpcService {
+    async fn append_entries(&self, request: AppendEntriesRequest) -> ClusterResult<AppendEntriesResponse> {
+        let mut state = self.state.lock();
+        state.observe_term(request.term);
+        let reject = |state: &RaftState| AppendEntriesResponse {
+            term: state.current_term,
+            success: false,
+            last_log_index: state.last_log().0,
+        };
+        if request.term < state.current_term || state.term_at(request.prev_log_index) != Some(request.prev_log_term) {
+            return Ok(reject(&state));
+        }
+
+        for entry in request.entries {
+            if entry.index <= state.snapshot_index {
+                continue;
+            }
+            match state.term_at(entry.index) {
+                Some(term) if term == entry.term => continue,
+                // 与 Leader 冲突的日志及其之后的日志全部删除
+                Some(_) => {
+                    let keep = (entry.index - state.snapshot_index - 1) as usize;
+                    state.log.truncate(keep);
+                }
+                None => {}
+            }
+            state.log.push(entry);
+        }
+        if request.leader_commit > state.commit_index {
+            state.commit_index = request.leader_commit.min(state.last_log().0);
+        }
+        Ok(AppendEntriesResponse {
+            term: state.current_term,
+            success: true,
+            last_log_index: state.last_log().0,
+        })
+    }
+
+    async fn vote(&self, request: VoteRequest) -> ClusterResult<VoteResponse> {
+        let mut state = self.state.lock();
+        state.observe_term(request.term);
+        let (last_index, last_term) = state.last_log();
+        let vote_granted = request.term == state.current_term
+            && state.voted_for.as_ref().map_or(true, |voted| *voted == request.candidate_id)
+            && (request.last_log_term, request.last_log_index) >= (last_term, last_index);
+        if vote_granted {
+            state.voted_for = Some(request.candidate_id.clone());
+        }
+        debug!(
+            "Vote for {} in term {}: {}",
+            request.candidate_id, request.term, vote_granted
+        );
+        Ok(VoteResponse {
+            term: state.current_term,
+            vote_granted,
+        })
+    }
+
+    async fn install_snapshot(&self, request: InstallSnapshotRequest) -> ClusterResult<InstallSnapshotResponse> {
+        let mut state = self.state.lock();
+        state.observe_term(request.term);
+        if request.term < state.current_term {
+            return Ok(InstallSnapshotResponse { term: state.current_term });
+        }
+
+        if request.offset == 0 {
+            state.receiving.clear();
+        }
+        if request.offset != state.receiving.len() as u64 {
+            return Err(ClusterError::Raft(format!(
+                "Snapshot chunk at offset {}, expected {}",
+                request.offset,
+                state.receiving.len()
+            )));
+        }
+        state.receiving.extend_from_slice(&request.data);
+
+        if request.done {
+            let snapshot = std::mem::take(&mut state.receiving);
+            if request.last_included_index <= state.snapshot_index {
+                debug!("Ignoring stale snapshot up to index {}", request.last_included_index);
+                return Ok(InstallSnapshotResponse { term: state.current_term });
+            }
+            state.snapshot = snapshot;
+            // 快照之后仍一致的日志保留,否则全部由快照替代
+            if state.term_at(request.last_included_index) == Some(request.last_included_term) {
+                let covered = ((request.last_included_index - state.snapshot_index) as usize).min(state.log.len());
+                state.log.drain(..covered);
+            } else {
+                state.log.clear();
+            }
+            state.snapshot_index = request.last_included_index;
+            state.snapshot_term = request.last_included_term;
+            state.commit_index = state.commit_index.max(request.last_included_index);
+            info!(
+                "Installed snapshot up to index {} ({} bytes)",
+                request.last_included_index,
+                state.snapshot.len()
+            );
+        }
+        Ok(InstallSnapshotResponse { term: state.current_term })
+    }
+
+    async fn join(&self, request: JoinRequest) -> ClusterResult<JoinResponse> {
+        let cluster_id = self
+            .cluster_id
+            .read()
+            .clone()
+            .ok_or_else(|| ClusterError::Config(format!("Node {} is not part of a cluster", self.node_id)))?;
+        if request.node_id == self.node_id {
+            return Err(ClusterError::Config(format!(
+                "Node id {} is already used by the node being joined",
+                request.node_id
+            )));
+        }
+
+        // 分配和登记在同一把锁内完成,同时加入的节点不会选中同一个空闲标识
+        let mut membership = self.membership.write();
+        let machine_id = membership.assign_machine_id(&request.node_id, request.machine_id)?;
+        membership.apply(&Command::ConfigChange {
+            node_id: request.node_id.clone(),
+            addr: request.addr.clone(),
+            action: ConfigAction::Add,
+            machine_id: Some(machine_id),
+        });
+        info!("Node {} ({}) joined with machine id {}", request.node_id, request.addr, machine_id);
+        Ok(JoinResponse {
+            cluster_id,
+            machine_id,
+            membership: membership.clone(),
+            catalog: self.catalog.read().clone(),
+        })
+    }
+}
+
 /// 配置变更操作
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConfigAction {
@@ -73,3 +565,164 @@ pub enum ConfigAction {
     /// 移除节点
     Remove,
 }
+
+/// Raft 成员配置
+///
+/// 记录每个节点的地址和分配给它的 ObjectId 机器标识。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Membership {
+    /// 节点 ID -> 成员信息
+    pub members: BTreeMap<String, Member>,
+}
+
+/// 集群成员
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Member {
+    /// 节点地址
+    pub addr: String,
+    /// ObjectId 机器标识
+    pub machine_id: u32,
+}
+
+impl Membership {
+    /// # Brief
+    /// 为加入的节点选择机器标识
+    ///
+    /// 已是成员的节点沿用原标识;手动指定的标识不能超出范围或已分配给其他节点;
+    /// 未指定时取最小的空闲标识。
+    ///
+    /// # Returns
+    /// 机器标识;指定的标识无效或冲突时返回 Config 错误
+    pub fn assign_machine_id(&self, node_id: &str, requested: Option<u32>) -> ClusterResult<u32> {
+        if let Some(member) = self.members.get(node_id) {
+            if requested.map_or(true, |id| id == member.machine_id) {
+                return Ok(member.machine_id);
+            }
+        }
+        let owner = |id: u32| {
+            self.members
+                .iter()
+                .find(|(other, member)| other.as_str() != node_id && member.machine_id == id)
+                .map(|(other, _)| other.clone())
+        };
+        match requested {
+            Some(id) if id > MAX_MACHINE_ID => Err(ClusterError::Config(format!(
+                "Machine id {} exceeds the maximum of {}",
+                id, MAX_MACHINE_ID
+            ))),
+            Some(id) => match owner(id) {
+                Some(other) => Err(ClusterError::Config(format!(
+                    "Machine id {} is already assigned to node {}",
+                    id, other
+                ))),
+                None => Ok(id),
+            },
+            None => (0..=MAX_MACHINE_ID)
+                .find(|&id| owner(id).is_none())
+                .ok_or_else(|| ClusterError::Config("No free machine id left".into())),
+        }
+    }
+
+    /// # Brief
+    /// 应用已提交的配置变更
+    pub fn apply(&mut self, command: &Command) {
+        let Command::ConfigChange { node_id, addr, action, machine_id } = command else {
+            return;
+        };
+        match (action, machine_id) {
+            (ConfigAction::Add, Some(machine_id)) => {
+                self.members.insert(
+                    node_id.clone(),
+                    Member { addr: addr.clone(), machine_id: *machine_id },
+                );
+            }
+            (ConfigAction::Add, None) => {}
+            (ConfigAction::Remove, _) => {
+                self.members.remove(node_id);
+            }
+        }
+    }
+
+    /// # Brief
+    /// 查找被多个节点共用的机器标识
+    ///
+    /// 并发加入的节点可能在对方的分配提交前选中同一个空闲标识,
+    /// 共用标识的节点生成的 ObjectId 可能冲突。
+    ///
+    /// # Returns
+    /// (机器标识, 共用它的节点 ID 列表),按标识排序
+    pub fn duplicate_machine_ids(&self) -> Vec<(u32, Vec<String>)> {
+        let mut owners: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for (node_id, member) in &self.members {
+            owners.entry(member.machine_id).or_default().push(node_id.clone());
+        }
+        owners.into_iter().filter(|(_, nodes)| nodes.len() > 1).collect()
+    }
+}
+
+/// 日志中的文档按 BOML 编码,二进制 RPC 编码无法直接表示文档的动态结构
+mod boml_document {
+    use mikudb_boml::{codec, Document};
+    use serde::de::Error as _;
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(doc: &Document, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = codec::encode_document(&doc.to_boml_value()).map_err(S::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Document, D::Error> {
+        let bytes = <Vec<u8>>::deserialize(deserializer)?;
+        let value = codec::decode_document(&bytes).map_err(D::Error::custom)?;
+        Document::from_boml_value(value).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(membership: &mut Membership, node_id: &str, machine_id: u32) {
+        membership.apply(&Command::ConfigChange {
+            node_id: node_id.to_string(),
+            addr: format!("{}:3940", node_id),
+            action: ConfigAction::Add,
+            machine_id: Some(machine_id),
+        });
+    }
+
+    #[test]
+    fn test_assign_machine_id() {
+        let mut membership = Membership::default();
+        assert_eq!(membership.assign_machine_id("n1", None).unwrap(), 0);
+        add(&mut membership, "n1", 0);
+        add(&mut membership, "n2", 2);
+
+        assert_eq!(membership.assign_machine_id("n3", None).unwrap(), 1);
+        assert_eq!(membership.assign_machine_id("n2", None).unwrap(), 2);
+        assert_eq!(membership.assign_machine_id("n3", Some(7)).unwrap(), 7);
+        assert!(membership.assign_machine_id("n3", Some(2)).is_err());
+        assert!(membership.assign_machine_id("n3", Some(MAX_MACHINE_ID + 1)).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_machine_ids() {
+        let mut membership = Membership::default();
+        add(&mut membership, "n1", 1);
+        add(&mut membership, "n2", 1);
+        add(&mut membership, "n3", 2);
+        assert_eq!(
+            membership.duplicate_machine_ids(),
+            vec![(1, vec!["n1".to_string(), "n2".to_string()])]
+        );
+
+        membership.apply(&Command::ConfigChange {
+            node_id: "n2".to_string(),
+            addr: String::new(),
+            action: ConfigAction::Remove,
+            machine_id: None,
+        });
+        assert!(membership.duplicate_machine_ids().is_empty());
+    }
+}
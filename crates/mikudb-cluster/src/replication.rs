@@ -1,20 +1,57 @@
 //! 数据复制管理
+//!
+//! 每个集合可以设置复制策略([`ReplicationPolicy`]): 复制到所有节点、只保存在 Leader,
+//! 或只复制到指定的节点。策略保存在随 Raft 日志复制的 [`ReplicationCatalog`] 中,
+//! 复制管理器按策略决定日志条目发往哪些节点,查询路由器只把读请求路由到保存了该集合的节点。
+//! Leader 总是保存所有集合,其他节点上的副本只读。
 
-use crate::{ClusterConfig, ClusterError, ClusterResult, LogEntry};
+use crate::{ClusterConfig, ClusterError, ClusterResult, Command, LogEntry};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
-use tracing::info;
+use tracing::{debug, info};
 
 /// 复制管理器
 pub struct ReplicationManager {
     config: ClusterConfig,
+    /// 集合复制策略,与 Raft 节点共享
+    catalog: Arc<RwLock<ReplicationCatalog>>,
 }
 
 impl ReplicationManager {
     /// 创建复制管理器
-    pub async fn new(config: ClusterConfig) -> ClusterResult<Self> {
+    ///
+    /// # Arguments
+    /// * `config` - 集群配置
+    /// * `catalog` - 集合复制策略,见 [`RaftNode::catalog_handle`](crate::RaftNode::catalog_handle)
+    pub async fn new(config: ClusterConfig, catalog: Arc<RwLock<ReplicationCatalog>>) -> ClusterResult<Self> {
         info!("Creating replication manager for: {}", config.node_id);
-        Ok(Self { config })
+        Ok(Self { config, catalog })
+    }
+
+    /// 集合的复制策略,未设置时为 [`ReplicationPolicy::All`]
+    pub fn policy(&self, collection: &str) -> ReplicationPolicy {
+        self.catalog.read().policy(collection)
+    }
+
+    /// # Brief
+    /// 选出需要接收日志条目的从节点
+    ///
+    /// 文档写入和删除只发往保存了该集合的从节点;配置变更和策略变更发往所有从节点。
+    ///
+    /// # Arguments
+    /// * `command` - 日志条目中的命令
+    /// * `followers` - 所有从节点 ID
+    pub fn targets<'a>(&self, command: &Command, followers: &'a [String]) -> Vec<&'a String> {
+        let catalog = self.catalog.read();
+        followers
+            .iter()
+            .filter(|node_id| match command.collection() {
+                Some(collection) => catalog.policy(collection).holds(node_id, false),
+                None => true,
+            })
+            .collect()
     }
 
     /// 启动复制管理器
@@ -25,12 +62,74 @@ impl ReplicationManager {
     }
 
     /// 复制日志到从节点
-    pub async fn replicate(&self, _log_entry: LogEntry) -> ClusterResult<()> {
+    ///
+    /// # Arguments
+    /// * `log_entry` - 已写入 Leader 的日志条目
+    /// * `followers` - 所有从节点 ID,按集合的复制策略过滤
+    pub async fn replicate(&self, log_entry: LogEntry, followers: &[String]) -> ClusterResult<()> {
+        let targets = self.targets(&log_entry.command, followers);
+        debug!("Replicating log entry {} to {} node(s)", log_entry.index, targets.len());
         // TODO: 实现日志复制逻辑
         Ok(())
     }
 }
 
+/// 集合复制策略
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplicationPolicy {
+    /// 复制到所有节点
+    #[default]
+    All,
+    /// 只保存在 Leader,不复制
+    LeaderOnly,
+    /// 只复制到指定的节点(Leader 之外)
+    Nodes(BTreeSet<String>),
+}
+
+impl ReplicationPolicy {
+    /// # Brief
+    /// 节点是否保存按此策略复制的集合
+    ///
+    /// # Arguments
+    /// * `node_id` - 节点 ID
+    /// * `is_leader` - 节点是否为 Leader,Leader 保存所有集合
+    pub fn holds(&self, node_id: &str, is_leader: bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::LeaderOnly => is_leader,
+            Self::Nodes(nodes) => is_leader || nodes.contains(node_id),
+        }
+    }
+}
+
+/// 集合复制策略目录
+///
+/// 随 Raft 日志中的 SetReplicationPolicy 命令更新,加入集群的节点从成员处获得完整目录。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationCatalog {
+    /// 集合名 -> 复制策略,只记录非默认策略
+    pub policies: BTreeMap<String, ReplicationPolicy>,
+}
+
+impl ReplicationCatalog {
+    /// 集合的复制策略,未设置时为 [`ReplicationPolicy::All`]
+    pub fn policy(&self, collection: &str) -> ReplicationPolicy {
+        self.policies.get(collection).cloned().unwrap_or_default()
+    }
+
+    /// # Brief
+    /// 应用已提交的策略变更,其他命令被忽略
+    pub fn apply(&mut self, command: &Command) {
+        let Command::SetReplicationPolicy { collection, policy } = command else {
+            return;
+        };
+        match policy {
+            ReplicationPolicy::All => self.policies.remove(collection),
+            policy => self.policies.insert(collection.clone(), policy.clone()),
+        };
+    }
+}
+
 /// 复制模式
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ReplicationMode {
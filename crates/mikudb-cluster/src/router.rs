@@ -1,31 +1,54 @@
 //! 查询路由器
+//!
+//! 集合的读请求只路由到按复制策略保存了该集合的节点,见 [`QueryRouter::route_collection_read`]。
 
 use crate::{ClusterError, ClusterResult, HealthStatus, Node, NodeRole};
-use crate::replication::ReadPreference;
+use crate::replication::{ReadPreference, ReplicationCatalog, ReplicationPolicy};
 use dashmap::DashMap;
+use parking_lot::RwLock;
 use std::sync::Arc;
 use tracing::debug;
 
 /// 查询路由器
 pub struct QueryRouter {
     nodes: Arc<DashMap<String, Node>>,
+    /// 集合复制策略,与 Raft 节点共享
+    catalog: Arc<RwLock<ReplicationCatalog>>,
 }
 
 impl QueryRouter {
     /// 创建查询路由器
-    pub async fn new(nodes: Arc<DashMap<String, Node>>) -> ClusterResult<Self> {
-        Ok(Self { nodes })
+    pub async fn new(nodes: Arc<DashMap<String, Node>>, catalog: Arc<RwLock<ReplicationCatalog>>) -> ClusterResult<Self> {
+        Ok(Self { nodes, catalog })
     }
 
     /// 路由读请求
     pub async fn route_read(&self, preference: ReadPreference) -> ClusterResult<String> {
+        self.route(preference, &ReplicationPolicy::All)
+    }
+
+    /// # Brief
+    /// 路由集合的读请求
+    ///
+    /// 只选择按集合复制策略保存了该集合的节点;没有符合读偏好的从节点保存该集合时,
+    /// `SecondaryPreferred` 和 `Nearest` 回退到 Leader,`Secondary` 返回错误。
+    ///
+    /// # Returns
+    /// 节点 ID
+    pub async fn route_collection_read(&self, collection: &str, preference: ReadPreference) -> ClusterResult<String> {
+        let policy = self.catalog.read().policy(collection);
+        debug!("Routing read of {} with policy {:?}", collection, policy);
+        self.route(preference, &policy)
+    }
+
+    fn route(&self, preference: ReadPreference, policy: &ReplicationPolicy) -> ClusterResult<String> {
         match preference {
             ReadPreference::Primary => self.get_leader(),
-            ReadPreference::Secondary => self.get_follower(),
+            ReadPreference::Secondary => self.get_follower(policy),
             ReadPreference::SecondaryPreferred => {
-                self.get_follower().or_else(|_| self.get_leader())
+                self.get_follower(policy).or_else(|_| self.get_leader())
             }
-            ReadPreference::Nearest => self.get_nearest(),
+            ReadPreference::Nearest => self.get_nearest(policy),
         }
     }
 
@@ -43,11 +66,11 @@ impl QueryRouter {
             .ok_or(ClusterError::NodeNotFound("No leader found".into()))
     }
 
-    /// 获取 Follower 节点
-    fn get_follower(&self) -> ClusterResult<String> {
+    /// 获取保存了集合的 Follower 节点
+    fn get_follower(&self, policy: &ReplicationPolicy) -> ClusterResult<String> {
         self.nodes
             .iter()
-            .find(|n| n.role == NodeRole::Follower && n.health == HealthStatus::Healthy)
+            .find(|n| n.role == NodeRole::Follower && n.health == HealthStatus::Healthy && policy.holds(&n.id, false))
             .map(|n| n.id.clone())
             .ok_or(ClusterError::NodeNotFound(
                 "No healthy follower found".into(),
@@ -55,9 +78,9 @@ impl QueryRouter {
     }
 
     /// 获取最近节点
-    fn get_nearest(&self) -> ClusterResult<String> {
+    fn get_nearest(&self, policy: &ReplicationPolicy) -> ClusterResult<String> {
         // 简单实现: 优先 Follower,否则 Leader
-        self.get_follower().or_else(|_| self.get_leader())
+        self.get_follower(policy).or_else(|_| self.get_leader())
     }
 }
 
@@ -69,7 +92,32 @@ mod tests {
     #[tokio::test]
     async fn test_router_creation() {
         let nodes = Arc::new(DashMap::new());
-        let router = QueryRouter::new(nodes).await.unwrap();
+        let router = QueryRouter::new(nodes, Default::default()).await.unwrap();
         // 基本创建测试
     }
+
+    #[tokio::test]
+    async fn test_route_collection_read() {
+        let nodes = Arc::new(DashMap::new());
+        for (id, port) in [("n1", 3941), ("n2", 3942), ("n3", 3943)] {
+            let addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+            nodes.insert(id.to_string(), Node::new(id.to_string(), addr));
+        }
+        nodes.get_mut("n1").unwrap().role = NodeRole::Leader;
+        let catalog = Arc::new(RwLock::new(ReplicationCatalog::default()));
+        catalog.write().apply(&crate::Command::SetReplicationPolicy {
+            collection: "audit".to_string(),
+            policy: ReplicationPolicy::LeaderOnly,
+        });
+        catalog.write().apply(&crate::Command::SetReplicationPolicy {
+            collection: "reports".to_string(),
+            policy: ReplicationPolicy::Nodes(["n3".to_string()].into()),
+        });
+        let router = QueryRouter::new(nodes, catalog).await.unwrap();
+
+        assert_eq!(router.route_collection_read("audit", ReadPreference::SecondaryPreferred).await.unwrap(), "n1");
+        assert!(router.route_collection_read("audit", ReadPreference::Secondary).await.is_err());
+        assert_eq!(router.route_collection_read("reports", ReadPreference::Secondary).await.unwrap(), "n3");
+        assert_ne!(router.route_collection_read("users", ReadPreference::Secondary).await.unwrap(), "n1");
+    }
 }
@@ -0,0 +1,1170 @@
+//! 节点间传输模块
+//!
+//! Raft 节点之间通过独立的集群端口通信,帧格式沿用 MikuWire 的风格:
+//! - 帧头: 魔术字节 `MKRF`、版本、帧类型、标志位、请求 ID、负载长度,共 20 字节,小端序
+//! - 负载: bincode 编码的 AppendEntries / RequestVote / InstallSnapshot / Join 请求或响应
+//! - 负载不小于 `compression_threshold` 且压缩后更小时以 LZ4 压缩,标志位记录
+//!
+//! 客户端为每个对端维护一条复用的连接,请求按 ID 多路复用,多个请求可以同时等待响应。
+//! 发送队列中积压的帧合并为一次写入(批量),AppendEntries 本身也可以携带多条日志。
+//! 服务端按到达顺序依次处理同一连接上的请求,保证同一 Leader 发出的日志按顺序追加。
+//!
+//! 配置 `TransportConfig::tls` 后节点间使用双向 TLS: 双方都出示集群 CA 签发的证书,
+//! 并用同一个 CA 验证对端。该功能需要以 `tls` 特性构建。
+//!
+//! 发送和接收的帧数、字节数、批量写入次数、压缩节省的字节数和 RPC 延迟见 [`TransportStats`]。
+
+use crate::config::TransportConfig;
+use crate::raft::{
+    AppendEntriesRequest, AppendEntriesResponse, InstallSnapshotRequest, InstallSnapshotResponse, JoinRequest,
+    JoinResponse, VoteRequest, VoteResponse,
+};
+use crate::{ClusterError, ClusterResult};
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, BytesMut};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+
+/// 集群帧魔术字节
+pub const FRAME_MAGIC: &[u8; 4] = b"MKRF";
+
+/// 集群传输协议版本
+pub const TRANSPORT_VERSION: u8 = 1;
+
+/// 单帧负载上限(64 MB),快照按块发送,不会超过该限制
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// 帧标志位: 负载以 LZ4 压缩
+pub const FLAG_LZ4: u16 = 0x0001;
+
+/// 帧类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameKind {
+    AppendEntries = 0x01,
+    AppendEntriesResponse = 0x02,
+    Vote = 0x03,
+    VoteResponse = 0x04,
+    InstallSnapshot = 0x05,
+    InstallSnapshotResponse = 0x06,
+    /// 新节点请求加入集群
+    Join = 0x07,
+    JoinResponse = 0x08,
+    /// 处理请求失败,负载为 UTF-8 错误信息
+    Error = 0x7F,
+}
+
+impl FrameKind {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x01 => Some(Self::AppendEntries),
+            0x02 => Some(Self::AppendEntriesResponse),
+            0x03 => Some(Self::Vote),
+            0x04 => Some(Self::VoteResponse),
+            0x05 => Some(Self::InstallSnapshot),
+            0x06 => Some(Self::InstallSnapshotResponse),
+            0x07 => Some(Self::Join),
+            0x08 => Some(Self::JoinResponse),
+            0x7F => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+/// 集群帧头
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub kind: FrameKind,
+    pub flags: u16,
+    /// 请求 ID,响应使用对应请求的 ID
+    pub request_id: u64,
+    pub payload_len: u32,
+}
+
+impl FrameHeader {
+    /// 帧头固定大小 (20 字节)
+    pub const SIZE: usize = 4 + 1 + 1 + 2 + 8 + 4;
+
+    /// # Brief
+    /// 将帧头编码为字节序列
+    pub fn encode(&self, buf: &mut BytesMut) {
+        buf.put_slice(FRAME_MAGIC);
+        buf.put_u8(TRANSPORT_VERSION);
+        buf.put_u8(self.kind as u8);
+        buf.put_u16_le(self.flags);
+        buf.put_u64_le(self.request_id);
+        buf.put_u32_le(self.payload_len);
+    }
+
+    /// # Brief
+    /// 从字节缓冲区解码帧头
+    ///
+    /// # Returns
+    /// 魔术字节、版本或帧类型无效时返回 InvalidData 错误
+    pub fn decode(mut buf: &[u8]) -> io::Result<Self> {
+        if buf.len() < Self::SIZE {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Incomplete frame header"));
+        }
+        if &buf[..4] != FRAME_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid cluster frame magic"));
+        }
+        buf.advance(4);
+        let version = buf.get_u8();
+        if version != TRANSPORT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported cluster transport version {}", version),
+            ));
+        }
+        let kind = buf.get_u8();
+        let kind = FrameKind::from_u8(kind)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Unknown frame kind {:#x}", kind)))?;
+        Ok(Self {
+            kind,
+            flags: buf.get_u16_le(),
+            request_id: buf.get_u64_le(),
+            payload_len: buf.get_u32_le(),
+        })
+    }
+}
+
+/// 解码后的帧,负载已解压
+#[derive(Debug)]
+struct Frame {
+    kind: FrameKind,
+    request_id: u64,
+    payload: Vec<u8>,
+}
+
+/// 处理集群端口收到的 Raft RPC
+#[async_trait]
+pub trait RaftRpcHandler: Send + Sync + 'static {
+    async fn append_entries(&self, request: AppendEntriesRequest) -> ClusterResult<AppendEntriesResponse>;
+
+    async fn vote(&self, request: VoteRequest) -> ClusterResult<VoteResponse>;
+
+    async fn install_snapshot(&self, request: InstallSnapshotRequest) -> ClusterResult<InstallSnapshotResponse>;
+
+    async fn join(&self, request: JoinRequest) -> ClusterResult<JoinResponse>;
+}
+
+/// 传输统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportStats {
+    /// 建立的连接数(客户端为发起的连接,服务端为接受的连接)
+    pub connections_opened: u64,
+    pub frames_sent: u64,
+    pub frames_received: u64,
+    /// 写入和读取的字节数(含帧头,压缩后)
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// 批量写入次数,小于 `frames_sent` 说明有多个帧合并为一次写入
+    pub batches_flushed: u64,
+    pub compressed_frames: u64,
+    /// 压缩节省的负载字节数
+    pub compression_saved_bytes: u64,
+    /// 收到响应的 RPC 数和累计耗时
+    pub rpcs: u64,
+    pub rpc_micros: u64,
+    /// 超时、连接断开或对端返回错误的 RPC 数
+    pub rpc_failures: u64,
+}
+
+impl TransportStats {
+    /// # Brief
+    /// RPC 的平均耗时
+    pub fn average_rpc_latency(&self) -> Duration {
+        match self.rpcs {
+            0 => Duration::ZERO,
+            rpcs => Duration::from_micros(self.rpc_micros / rpcs),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TransportMetrics {
+    connections_opened: AtomicU64,
+    frames_sent: AtomicU64,
+    frames_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    batches_flushed: AtomicU64,
+    compressed_frames: AtomicU64,
+    compression_saved_bytes: AtomicU64,
+    rpcs: AtomicU64,
+    rpc_micros: AtomicU64,
+    rpc_failures: AtomicU64,
+}
+
+impl TransportMetrics {
+    fn snapshot(&self) -> TransportStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        TransportStats {
+            connections_opened: load(&self.connections_opened),
+            frames_sent: load(&self.frames_sent),
+            frames_received: load(&self.frames_received),
+            bytes_sent: load(&self.bytes_sent),
+            bytes_received: load(&self.bytes_received),
+            batches_flushed: load(&self.batches_flushed),
+            compressed_frames: load(&self.compressed_frames),
+            compression_saved_bytes: load(&self.compression_saved_bytes),
+            rpcs: load(&self.rpcs),
+            rpc_micros: load(&self.rpc_micros),
+            rpc_failures: load(&self.rpc_failures),
+        }
+    }
+}
+
+trait Stream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Stream for T {}
+
+type BoxStream = Box<dyn Stream>;
+
+fn encode_payload<T: Serialize>(value: &T) -> ClusterResult<Vec<u8>> {
+    bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .map_err(|e| ClusterError::Serialization(e.to_string()))
+}
+
+fn decode_payload<T: DeserializeOwned>(payload: &[u8]) -> ClusterResult<T> {
+    bincode::serde::decode_from_slice(payload, bincode::config::standard())
+        .map(|(value, _)| value)
+        .map_err(|e| ClusterError::Serialization(e.to_string()))
+}
+
+/// # Brief
+/// 编码一帧,负载足够大且压缩后更小时以 LZ4 压缩
+fn encode_frame(
+    kind: FrameKind,
+    request_id: u64,
+    payload: &[u8],
+    compression_threshold: usize,
+    metrics: &TransportMetrics,
+) -> ClusterResult<BytesMut> {
+    let compressed = if compression_threshold > 0 && payload.len() >= compression_threshold {
+        Some(lz4::block::compress(payload, None, true)?).filter(|compressed| compressed.len() < payload.len())
+    } else {
+        None
+    };
+    let (flags, body) = match &compressed {
+        Some(compressed) => {
+            metrics.compressed_frames.fetch_add(1, Ordering::Relaxed);
+            metrics
+                .compression_saved_bytes
+                .fetch_add((payload.len() - compressed.len()) as u64, Ordering::Relaxed);
+            (FLAG_LZ4, compressed.as_slice())
+        }
+        None => (0, payload),
+    };
+    if body.len() > MAX_FRAME_SIZE {
+        return Err(ClusterError::Network(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Cluster frame of {} bytes exceeds the limit", body.len()),
+        )));
+    }
+
+    let mut buf = BytesMut::with_capacity(FrameHeader::SIZE + body.len());
+    FrameHeader {
+        kind,
+        flags,
+        request_id,
+        payload_len: body.len() as u32,
+    }
+    .encode(&mut buf);
+    buf.put_slice(body);
+    Ok(buf)
+}
+
+/// # Brief
+/// 读取一帧
+///
+/// # Returns
+/// 对端在帧边界关闭连接时返回 None
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R, metrics: &TransportMetrics) -> ClusterResult<Option<Frame>> {
+    let mut header = [0u8; FrameHeader::SIZE];
+    match reader.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let header = FrameHeader::decode(&header)?;
+    let len = header.payload_len as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(ClusterError::Network(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Cluster frame of {} bytes exceeds the limit", len),
+        )));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    metrics.frames_received.fetch_add(1, Ordering::Relaxed);
+    metrics
+        .bytes_received
+        .fetch_add((FrameHeader::SIZE + len) as u64, Ordering::Relaxed);
+
+    if header.flags & FLAG_LZ4 != 0 {
+        payload = lz4::block::decompress(&payload, None)?;
+    }
+    Ok(Some(Frame {
+        kind: header.kind,
+        request_id: header.request_id,
+        payload,
+    }))
+}
+
+/// # Brief
+/// 把发送队列中的帧写入连接
+///
+/// 取到一帧后把队列中已积压的帧一起写入,最多 `max_batch_bytes` 字节,再刷新一次
+async fn write_loop<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut frames: mpsc::UnboundedReceiver<BytesMut>,
+    max_batch_bytes: usize,
+    metrics: Arc<TransportMetrics>,
+) {
+    while let Some(mut batch) = frames.recv().await {
+        let mut count = 1u64;
+        while batch.len() < max_batch_bytes {
+            match frames.try_recv() {
+                Ok(frame) => {
+                    batch.extend_from_slice(&frame);
+                    count += 1;
+                }
+                Err(_) => break,
+            }
+        }
+        if let Err(e) = async {
+            writer.write_all(&batch).await?;
+            writer.flush().await
+        }
+        .await
+        {
+            debug!("Cluster connection write failed: {}", e);
+            break;
+        }
+        metrics.batches_flushed.fetch_add(1, Ordering::Relaxed);
+        metrics.frames_sent.fetch_add(count, Ordering::Relaxed);
+        metrics.bytes_sent.fetch_add(batch.len() as u64, Ordering::Relaxed);
+    }
+}
+
+/// 到某个对端的复用连接
+struct PeerConnection {
+    frames: mpsc::UnboundedSender<BytesMut>,
+    /// 等待响应的请求
+    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Frame>>>>,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    reader: JoinHandle<()>,
+    writer: JoinHandle<()>,
+}
+
+impl PeerConnection {
+    fn new(stream: BoxStream, config: &TransportConfig, metrics: Arc<TransportMetrics>) -> Self {
+        let (mut reader, writer) = tokio::io::split(stream);
+        let (frames, queue) = mpsc::unbounded_channel();
+        let pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Frame>>>> = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let writer = tokio::spawn(write_loop(writer, queue, config.max_batch_bytes, metrics.clone()));
+        let reader = {
+            let pending = pending.clone();
+            let closed = closed.clone();
+            tokio::spawn(async move {
+                loop {
+                    match read_frame(&mut reader, &metrics).await {
+                        Ok(Some(frame)) => {
+                            if let Some(waiter) = pending.lock().remove(&frame.request_id) {
+                                let _ = waiter.send(frame);
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            debug!("Cluster connection read failed: {}", e);
+                            break;
+                        }
+                    }
+                }
+                // 连接断开,等待中的请求立即失败,下次调用重新连接
+                closed.store(true, Ordering::SeqCst);
+                pending.lock().clear();
+            })
+        };
+
+        Self {
+            frames,
+            pending,
+            next_id: AtomicU64::new(1),
+            closed,
+            reader,
+            writer,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.frames.is_closed()
+    }
+}
+
+impl Drop for PeerConnection {
+    fn drop(&mut self) {
+        self.reader.abort();
+        self.writer.abort();
+    }
+}
+
+/// 发往其他节点的 Raft RPC 客户端
+///
+/// 每个对端地址复用一条连接,连接断开后下一次调用时重新建立
+pub struct RaftTransport {
+    config: TransportConfig,
+    #[cfg(feature = "tls")]
+    connector: Option<tokio_rustls::TlsConnector>,
+    peers: DashMap<String, Arc<tokio::sync::Mutex<Option<Arc<PeerConnection>>>>>,
+    metrics: Arc<TransportMetrics>,
+}
+
+impl RaftTransport {
+    /// # Brief
+    /// 创建 RPC 客户端
+    ///
+    /// # Returns
+    /// 配置了 TLS 但证书无法加载,或未以 `tls` 特性构建时返回 Config 错误
+    pub fn new(config: TransportConfig) -> ClusterResult<Self> {
+        #[cfg(feature = "tls")]
+        let connector = config
+            .tls
+            .as_ref()
+            .map(|tls| tls::client_config(tls).map(tokio_rustls::TlsConnector::from))
+            .transpose()?;
+        #[cfg(not(feature = "tls"))]
+        tls_unavailable(&config)?;
+
+        Ok(Self {
+            config,
+            #[cfg(feature = "tls")]
+            connector,
+            peers: DashMap::new(),
+            metrics: Arc::new(TransportMetrics::default()),
+        })
+    }
+
+    /// # Brief
+    /// 发送 AppendEntries
+    ///
+    /// # Arguments
+    /// * `peer` - 对端集群端口地址
+    /// * `request` - 请求,可以携带多条日志
+    pub async fn append_entries(&self, peer: &str, request: &AppendEntriesRequest) -> ClusterResult<AppendEntriesResponse> {
+        self.call(peer, FrameKind::AppendEntries, FrameKind::AppendEntriesResponse, request).await
+    }
+
+    /// # Brief
+    /// 发送 RequestVote
+    pub async fn vote(&self, peer: &str, request: &VoteRequest) -> ClusterResult<VoteResponse> {
+        self.call(peer, FrameKind::Vote, FrameKind::VoteResponse, request).await
+    }
+
+    /// # Brief
+    /// 发送一个快照块
+    pub async fn install_snapshot(
+        &self,
+        peer: &str,
+        request: &InstallSnapshotRequest,
+    ) -> ClusterResult<InstallSnapshotResponse> {
+        self.call(peer, FrameKind::InstallSnapshot, FrameKind::InstallSnapshotResponse, request).await
+    }
+
+    /// # Brief
+    /// 请求对端把本节点加入集群
+    ///
+    /// # Arguments
+    /// * `peer` - 已在集群中的节点的集群端口地址
+    /// * `request` - 本节点的 ID、地址和手动指定的机器标识
+    pub async fn join(&self, peer: &str, request: &JoinRequest) -> ClusterResult<JoinResponse> {
+        self.call(peer, FrameKind::Join, FrameKind::JoinResponse, request).await
+    }
+
+    /// # Brief
+    /// 按 `snapshot_chunk_bytes` 分块发送整个快照
+    ///
+    /// 对端返回更大的任期时停止发送,调用方据此退位
+    ///
+    /// # Arguments
+    /// * `peer` - 对端集群端口地址
+    /// * `template` - 任期、Leader 和快照位置,`offset`、`data`、`done` 由分块填充
+    /// * `data` - 快照内容
+    ///
+    /// # Returns
+    /// 最后一个块的响应
+    pub async fn send_snapshot(
+        &self,
+        peer: &str,
+        template: &InstallSnapshotRequest,
+        data: &[u8],
+    ) -> ClusterResult<InstallSnapshotResponse> {
+        let chunk_size = self.config.snapshot_chunk_bytes.max(1);
+        let mut offset = 0;
+        loop {
+            let end = (offset + chunk_size).min(data.len());
+            let request = InstallSnapshotRequest {
+                offset: offset as u64,
+                data: data[offset..end].to_vec(),
+                done: end == data.len(),
+                ..template.clone()
+            };
+            let response = self.install_snapshot(peer, &request).await?;
+            if request.done || response.term > template.term {
+                return Ok(response);
+            }
+            offset = end;
+        }
+    }
+
+    /// # Brief
+    /// 关闭到对端的连接,下一次调用重新连接
+    pub fn disconnect(&self, peer: &str) {
+        self.peers.remove(peer);
+    }
+
+    /// # Brief
+    /// 传输统计
+    pub fn stats(&self) -> TransportStats {
+        self.metrics.snapshot()
+    }
+
+    async fn call<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        peer: &str,
+        kind: FrameKind,
+        expected: FrameKind,
+        request: &Req,
+    ) -> ClusterResult<Resp> {
+        let result = self.round_trip(peer, kind, request).await.and_then(|frame| match frame.kind {
+            kind if kind == expected => decode_payload(&frame.payload),
+            FrameKind::Error => Err(ClusterError::Raft(String::from_utf8_lossy(&frame.payload).into_owned())),
+            other => Err(ClusterError::Internal(format!(
+                "Unexpected {:?} frame in response to {:?}",
+                other, kind
+            ))),
+        });
+        if result.is_err() {
+            self.metrics.rpc_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    async fn round_trip<Req: Serialize>(&self, peer: &str, kind: FrameKind, request: &Req) -> ClusterResult<Frame> {
+        let payload = encode_payload(request)?;
+        let connection = self.connection(peer).await?;
+        let request_id = connection.next_id.fetch_add(1, Ordering::Relaxed);
+        let frame = encode_frame(
+            kind,
+            request_id,
+            &payload,
+            self.config.compression_threshold,
+            &self.metrics,
+        )?;
+
+        let (waiter, response) = oneshot::channel();
+        connection.pending.lock().insert(request_id, waiter);
+        let started = Instant::now();
+        if connection.frames.send(frame).is_err() {
+            connection.pending.lock().remove(&request_id);
+            return Err(connection_closed(peer));
+        }
+
+        match tokio::time::timeout(Duration::from_millis(self.config.request_timeout_ms), response).await {
+            Ok(Ok(frame)) => {
+                self.metrics.rpcs.fetch_add(1, Ordering::Relaxed);
+                self.metrics
+                    .rpc_micros
+                    .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+                Ok(frame)
+            }
+            Ok(Err(_)) => Err(connection_closed(peer)),
+            Err(_) => {
+                connection.pending.lock().remove(&request_id);
+                Err(ClusterError::Timeout(format!("{:?} RPC to {} timed out", kind, peer)))
+            }
+        }
+    }
+
+    /// # Brief
+    /// 获取到对端的连接,没有可用连接时建立新连接
+    ///
+    /// 同一对端的并发调用只建立一条连接
+    async fn connection(&self, peer: &str) -> ClusterResult<Arc<PeerConnection>> {
+        let slot = self.peers.entry(peer.to_string()).or_default().clone();
+        let mut slot = slot.lock().await;
+        if let Some(connection) = slot.as_ref().filter(|connection| !connection.is_closed()) {
+            return Ok(connection.clone());
+        }
+
+        let connect = TcpStream::connect(peer);
+        let stream = tokio::time::timeout(Duration::from_millis(self.config.connect_timeout_ms), connect)
+            .await
+            .map_err(|_| ClusterError::Timeout(format!("Connecting to {} timed out", peer)))??;
+        stream.set_nodelay(true)?;
+        let stream = self.secure(peer, stream).await?;
+
+        let connection = Arc::new(PeerConnection::new(stream, &self.config, self.metrics.clone()));
+        self.metrics.connections_opened.fetch_add(1, Ordering::Relaxed);
+        debug!("Opened cluster connection to {}", peer);
+        *slot = Some(connection.clone());
+        Ok(connection)
+    }
+
+    #[cfg(feature = "tls")]
+    async fn secure(&self, peer: &str, stream: TcpStream) -> ClusterResult<BoxStream> {
+        let (Some(connector), Some(tls)) = (&self.connector, &self.config.tls) else {
+            return Ok(Box::new(stream));
+        };
+        let server_name = tls::server_name(tls, peer)?;
+        let stream = connector.connect(server_name, stream).await?;
+        Ok(Box::new(stream))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    async fn secure(&self, _peer: &str, stream: TcpStream) -> ClusterResult<BoxStream> {
+        Ok(Box::new(stream))
+    }
+}
+
+fn connection_closed(peer: &str) -> ClusterError {
+    ClusterError::Network(io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        format!("Cluster connection to {} closed", peer),
+    ))
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_unavailable(config: &TransportConfig) -> ClusterResult<()> {
+    match config.tls {
+        Some(_) => Err(ClusterError::Config(
+            "Cluster TLS requires building mikudb-cluster with the `tls` feature".into(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// 集群端口上的 RPC 服务
+///
+/// 释放时停止接受新连接并关闭已有连接
+pub struct TransportServer {
+    local_addr: SocketAddr,
+    accept: JoinHandle<()>,
+    metrics: Arc<TransportMetrics>,
+}
+
+impl TransportServer {
+    /// # Brief
+    /// 绑定集群端口并开始接受连接
+    ///
+    /// # Arguments
+    /// * `addr` - 监听地址,端口为 0 时由系统分配
+    /// * `config` - 传输配置
+    /// * `handler` - RPC 处理
+    pub async fn bind(
+        addr: SocketAddr,
+        config: &TransportConfig,
+        handler: Arc<dyn RaftRpcHandler>,
+    ) -> ClusterResult<Self> {
+        #[cfg(feature = "tls")]
+        let acceptor = config
+            .tls
+            .as_ref()
+            .map(|tls| tls::server_config(tls).map(tokio_rustls::TlsAcceptor::from))
+            .transpose()?;
+        #[cfg(not(feature = "tls"))]
+        tls_unavailable(config)?;
+
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let metrics = Arc::new(TransportMetrics::default());
+        let config = config.clone();
+
+        let accept = {
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                // 连接任务随接受任务一起取消
+                let mut connections = JoinSet::new();
+                loop {
+                    tokio::select! {
+                        accepted = listener.accept() => {
+                            let (stream, remote) = match accepted {
+                                Ok(accepted) => accepted,
+                                Err(e) => {
+                                    warn!("Failed to accept cluster connection: {}", e);
+                                    continue;
+                                }
+                            };
+                            let _ = stream.set_nodelay(true);
+                            metrics.connections_opened.fetch_add(1, Ordering::Relaxed);
+                            let handler = handler.clone();
+                            let config = config.clone();
+                            let metrics = metrics.clone();
+                            #[cfg(feature = "tls")]
+                            let acceptor = acceptor.clone();
+                            connections.spawn(async move {
+                                #[cfg(feature = "tls")]
+                                let stream: BoxStream = match acceptor {
+                                    Some(acceptor) => match acceptor.accept(stream).await {
+                                        Ok(stream) => Box::new(stream),
+                                        Err(e) => {
+                                            warn!("Rejected cluster connection from {}: {}", remote, e);
+                                            return;
+                                        }
+                                    },
+                                    None => Box::new(stream),
+                                };
+                                #[cfg(not(feature = "tls"))]
+                                let stream: BoxStream = Box::new(stream);
+                                debug!("Accepted cluster connection from {}", remote);
+                                serve_connection(stream, handler, &config, metrics).await;
+                            });
+                        }
+                        Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                    }
+                }
+            })
+        };
+
+        info!("Cluster transport listening on {}", local_addr);
+        Ok(Self {
+            local_addr,
+            accept,
+            metrics,
+        })
+    }
+
+    /// # Brief
+    /// 实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// # Brief
+    /// 传输统计
+    pub fn stats(&self) -> TransportStats {
+        self.metrics.snapshot()
+    }
+}
+
+impl Drop for TransportServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+/// # Brief
+/// 依次处理一条连接上的请求,响应经批量写入任务发回
+async fn serve_connection(
+    stream: BoxStream,
+    handler: Arc<dyn RaftRpcHandler>,
+    config: &TransportConfig,
+    metrics: Arc<TransportMetrics>,
+) {
+    let (mut reader, writer) = tokio::io::split(stream);
+    let (responses, queue) = mpsc::unbounded_channel();
+    let writer = tokio::spawn(write_loop(writer, queue, config.max_batch_bytes, metrics.clone()));
+
+    loop {
+        let frame = match read_frame(&mut reader, &metrics).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break,
+            Err(e) => {
+                debug!("Cluster connection read failed: {}", e);
+                break;
+            }
+        };
+        let (kind, payload) = match dispatch(handler.as_ref(), &frame).await {
+            Ok(response) => response,
+            Err(e) => (FrameKind::Error, e.to_string().into_bytes()),
+        };
+        let response = match encode_frame(kind, frame.request_id, &payload, config.compression_threshold, &metrics) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Failed to encode {:?} response: {}", kind, e);
+                break;
+            }
+        };
+        if responses.send(response).is_err() {
+            break;
+        }
+    }
+
+    drop(responses);
+    let _ = writer.await;
+}
+
+async fn dispatch(handler: &dyn RaftRpcHandler, frame: &Frame) -> ClusterResult<(FrameKind, Vec<u8>)> {
+    match frame.kind {
+        FrameKind::AppendEntries => {
+            let response = handler.append_entries(decode_payload(&frame.payload)?).await?;
+            Ok((FrameKind::AppendEntriesResponse, encode_payload(&response)?))
+        }
+        FrameKind::Vote => {
+            let response = handler.vote(decode_payload(&frame.payload)?).await?;
+            Ok((FrameKind::VoteResponse, encode_payload(&response)?))
+        }
+        FrameKind::InstallSnapshot => {
+            let response = handler.install_snapshot(decode_payload(&frame.payload)?).await?;
+            Ok((FrameKind::InstallSnapshotResponse, encode_payload(&response)?))
+        }
+        FrameKind::Join => {
+            let response = handler.join(decode_payload(&frame.payload)?).await?;
+            Ok((FrameKind::JoinResponse, encode_payload(&response)?))
+        }
+        other => Err(ClusterError::Internal(format!("Unexpected {:?} request frame", other))),
+    }
+}
+
+#[cfg(feature = "tls")]
+mod tls {
+    //! 节点间双向 TLS 配置
+
+    use crate::config::ClusterTlsConfig;
+    use crate::{ClusterError, ClusterResult};
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+    use rustls::server::WebPkiClientVerifier;
+    use rustls::{ClientConfig, RootCertStore, ServerConfig};
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    /// # Brief
+    /// 服务端配置: 出示本节点证书,要求并验证对端的客户端证书
+    pub(super) fn server_config(tls: &ClusterTlsConfig) -> ClusterResult<Arc<ServerConfig>> {
+        let verifier = WebPkiClientVerifier::builder(Arc::new(load_roots(&tls.ca_path)?))
+            .build()
+            .map_err(|e| ClusterError::Config(format!("Failed to build cluster client verifier: {}", e)))?;
+        let config = ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(load_certs(&tls.cert_path)?, load_private_key(&tls.key_path)?)
+            .map_err(|e| ClusterError::Config(format!("Failed to create cluster TLS config: {}", e)))?;
+        Ok(Arc::new(config))
+    }
+
+    /// # Brief
+    /// 客户端配置: 用集群 CA 验证对端,并出示本节点证书
+    pub(super) fn client_config(tls: &ClusterTlsConfig) -> ClusterResult<Arc<ClientConfig>> {
+        let config = ClientConfig::builder()
+            .with_root_certificates(load_roots(&tls.ca_path)?)
+            .with_client_auth_cert(load_certs(&tls.cert_path)?, load_private_key(&tls.key_path)?)
+            .map_err(|e| ClusterError::Config(format!("Failed to create cluster TLS config: {}", e)))?;
+        Ok(Arc::new(config))
+    }
+
+    /// # Brief
+    /// 校验对端证书使用的名称: 配置的 `server_name`,否则为对端地址的主机部分
+    pub(super) fn server_name(tls: &ClusterTlsConfig, peer: &str) -> ClusterResult<ServerName<'static>> {
+        let name = match &tls.server_name {
+            Some(name) => name.clone(),
+            None => peer
+                .rsplit_once(':')
+                .map_or(peer, |(host, _)| host)
+                .trim_matches(|c| c == '[' || c == ']')
+                .to_string(),
+        };
+        ServerName::try_from(name).map_err(|e| ClusterError::Config(format!("Invalid TLS server name: {}", e)))
+    }
+
+    fn load_roots(path: &Path) -> ClusterResult<RootCertStore> {
+        let mut roots = RootCertStore::empty();
+        for cert in load_certs(path)? {
+            roots
+                .add(cert)
+                .map_err(|e| ClusterError::Config(format!("Failed to add cluster CA certificate: {}", e)))?;
+        }
+        Ok(roots)
+    }
+
+    fn load_certs(path: &Path) -> ClusterResult<Vec<CertificateDer<'static>>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        rustls_pemfile::certs(&mut reader)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ClusterError::Config(format!("Failed to parse certificates in {}: {}", path.display(), e)))
+    }
+
+    fn load_private_key(path: &Path) -> ClusterResult<PrivateKeyDer<'static>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        rustls_pemfile::private_key(&mut reader)
+            .map_err(|e| ClusterError::Config(format!("Failed to parse private key in {}: {}", path.display(), e)))?
+            .ok_or_else(|| ClusterError::Config(format!("No private key found in {}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raft::{Command, LogEntry};
+    use crate::{ClusterConfig, RaftNode};
+    use mikudb_boml::Document;
+    use mikudb_storage::WriteMetadata;
+
+    async fn start_node(transport: TransportConfig) -> RaftNode {
+        let node = RaftNode::new(ClusterConfig {
+            bind_addr: "127.0.0.1:0".parse().unwrap(),
+            transport,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        node.start().await.unwrap();
+        node
+    }
+
+    fn entry(index: u64, term: u64, body: &str) -> LogEntry {
+        let mut doc = Document::new();
+        doc.insert("body", body);
+        LogEntry {
+            index,
+            term,
+            command: Command::Write {
+                collection: "c".to_string(),
+                doc,
+                meta: Some(WriteMetadata { origin: Some("n1".to_string()), ..Default::default() }),
+            },
+        }
+    }
+
+    #[test]
+    fn test_frame_header_roundtrip() {
+        let header = FrameHeader {
+            kind: FrameKind::InstallSnapshot,
+            flags: FLAG_LZ4,
+            request_id: 42,
+            payload_len: 7,
+        };
+        let mut buf = BytesMut::new();
+        header.encode(&mut buf);
+        assert_eq!(buf.len(), FrameHeader::SIZE);
+        assert_eq!(FrameHeader::decode(&buf).unwrap(), header);
+
+        buf[0] = b'X';
+        assert!(FrameHeader::decode(&buf).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rpcs_over_reused_connection() {
+        let node = start_node(TransportConfig::default()).await;
+        let peer = node.local_addr().unwrap().to_string();
+        let client = RaftTransport::new(TransportConfig {
+            snapshot_chunk_bytes: 1000,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let vote = client
+            .vote(
+                &peer,
+                &VoteRequest {
+                    term: 1,
+                    candidate_id: "n1".to_string(),
+                    last_log_index: 0,
+                    last_log_term: 0,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(vote.vote_granted);
+
+        // 一次 AppendEntries 携带多条日志,大负载以 LZ4 压缩
+        let body = "replicated ".repeat(1000);
+        let append = AppendEntriesRequest {
+            term: 1,
+            leader_id: "n1".to_string(),
+            prev_log_index: 0,
+            prev_log_term: 0,
+            entries: (1..=3).map(|index| entry(index, 1, &body)).collect(),
+            leader_commit: 2,
+        };
+        let response = client.append_entries(&peer, &append).await.unwrap();
+        assert!(response.success);
+        assert_eq!(response.last_log_index, 3);
+
+        // 前一条日志不匹配时拒绝
+        let mismatched = AppendEntriesRequest {
+            prev_log_index: 5,
+            prev_log_term: 1,
+            entries: Vec::new(),
+            ..append.clone()
+        };
+        assert!(!client.append_entries(&peer, &mismatched).await.unwrap().success);
+
+        let snapshot: Vec<u8> = (0..4500u32).map(|i| (i % 251) as u8).collect();
+        let template = InstallSnapshotRequest {
+            term: 1,
+            leader_id: "n1".to_string(),
+            last_included_index: 10,
+            last_included_term: 1,
+            offset: 0,
+            data: Vec::new(),
+            done: false,
+        };
+        client.send_snapshot(&peer, &template, &snapshot).await.unwrap();
+
+        let status = node.status();
+        assert_eq!(status.current_term, 1);
+        assert_eq!(status.voted_for.as_deref(), Some("n1"));
+        assert_eq!(status.snapshot_index, 10);
+        assert_eq!(status.last_log_index, 10);
+        assert_eq!(status.commit_index, 10);
+        assert_eq!(status.snapshot_len, snapshot.len());
+
+        let stats = client.stats();
+        assert_eq!(stats.connections_opened, 1);
+        assert_eq!(stats.rpcs, 8);
+        assert_eq!(stats.rpc_failures, 0);
+        assert!(stats.compressed_frames >= 1);
+        assert!(stats.compression_saved_bytes > 0);
+        assert_eq!(stats.frames_received, 8);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_batched() {
+        let node = start_node(TransportConfig::default()).await;
+        let peer = node.local_addr().unwrap().to_string();
+        let client = Arc::new(RaftTransport::new(TransportConfig::default()).unwrap());
+
+        let calls = (0..64u64).map(|term| {
+            let client = client.clone();
+            let peer = peer.clone();
+            async move {
+                let request = VoteRequest {
+                    term,
+                    candidate_id: format!("n{}", term),
+                    last_log_index: 0,
+                    last_log_term: 0,
+                };
+                client.vote(&peer, &request).await
+            }
+        });
+        let responses = futures::future::join_all(calls).await;
+        assert!(responses.iter().all(Result::is_ok));
+
+        let stats = client.stats();
+        assert_eq!(stats.connections_opened, 1);
+        assert_eq!(stats.frames_sent, 64);
+        assert!(stats.batches_flushed <= stats.frames_sent);
+        assert_eq!(node.status().current_term, 63);
+
+        // 服务端关闭后连接失败,重新启动的服务在新连接上继续处理
+        client.disconnect(&peer);
+        drop(node);
+        assert!(client.vote(&peer, &VoteRequest {
+            term: 64,
+            candidate_id: "n64".to_string(),
+            last_log_index: 0,
+            last_log_term: 0,
+        })
+        .await
+        .is_err());
+        assert!(client.stats().rpc_failures >= 1);
+    }
+
+    #[cfg(not(feature = "tls"))]
+    #[test]
+    fn test_tls_requires_feature() {
+        let config = TransportConfig {
+            tls: Some(crate::ClusterTlsConfig {
+                cert_path: "node.pem".into(),
+                key_path: "node.key".into(),
+                ca_path: "ca.pem".into(),
+                server_name: None,
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(RaftTransport::new(config), Err(ClusterError::Config(_))));
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn test_mutual_tls() {
+        use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, pem: String| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, pem).unwrap();
+            path
+        };
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_key = KeyPair::generate().unwrap();
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let issue = |name: &str, ca: &rcgen::Certificate, ca_key: &KeyPair| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec!["127.0.0.1".to_string()])
+                .unwrap()
+                .signed_by(&key, ca, ca_key)
+                .unwrap();
+            crate::ClusterTlsConfig {
+                cert_path: write(&format!("{}.pem", name), cert.pem()),
+                key_path: write(&format!("{}.key", name), key.serialize_pem()),
+                ca_path: write("ca.pem", ca.pem()),
+                server_name: None,
+            }
+        };
+        let server_tls = issue("server", &ca, &ca_key);
+        let client_tls = issue("client", &ca, &ca_key);
+
+        let node = start_node(TransportConfig {
+            tls: Some(server_tls),
+            ..Default::default()
+        })
+        .await;
+        let peer = node.local_addr().unwrap().to_string();
+        let request = VoteRequest {
+            term: 1,
+            candidate_id: "n1".to_string(),
+            last_log_index: 0,
+            last_log_term: 0,
+        };
+
+        let client = RaftTransport::new(TransportConfig {
+            tls: Some(client_tls),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(client.vote(&peer, &request).await.unwrap().vote_granted);
+
+        // 其他 CA 签发的证书被拒绝
+        let other_key = KeyPair::generate().unwrap();
+        let mut other_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        other_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let other_ca = other_params.self_signed(&other_key).unwrap();
+        let mut outsider_tls = issue("outsider", &other_ca, &other_key);
+        outsider_tls.ca_path = write("trusted.pem", ca.pem());
+        let outsider = RaftTransport::new(TransportConfig {
+            tls: Some(outsider_tls),
+            request_timeout_ms: 1000,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(outsider.vote(&peer, &request).await.is_err());
+
+        // 明文客户端无法完成握手
+        let plain = RaftTransport::new(TransportConfig {
+            request_timeout_ms: 1000,
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(plain.vote(&peer, &request).await.is_err());
+    }
+}
@@ -1,6 +1,12 @@
 //! 错误类型定义模块
 //!
 //! 定义 MikuDB 的统一错误类型 MikuError 和 Result 别名。
+//!
+//! 错误分为可重试和不可重试两类,应用和重试逻辑通过 `is_retryable` / `is_transient` 判断,
+//! 不需要解析错误消息:
+//! - 暂时性错误: 网络连接、超时、节点不是主节点,条件消失后原操作即可成功
+//! - 可重试错误: 暂时性错误加上写冲突,重新执行整个操作(或事务)可能成功
+//! - 其余错误(语法、校验、权限、数据不存在等)重试也不会成功
 
 use thiserror::Error;
 
@@ -37,6 +43,14 @@ pub enum MikuError {
     #[error("Transaction error: {0}")]
     Transaction(String),
 
+    /// 写冲突: 并发写入修改了同一文档,重新执行事务可能成功
+    #[error("Write conflict: {0}")]
+    WriteConflict(String),
+
+    /// 节点不是主节点,不能接受写入
+    #[error("Not primary: {0}")]
+    NotPrimary(String),
+
     /// 文档不存在
     #[error("Document not found: {0}")]
     NotFound(String),
@@ -45,6 +59,10 @@ pub enum MikuError {
     #[error("Document already exists: {0}")]
     AlreadyExists(String),
 
+    /// 唯一键重复,消息包含 `E11000` 错误码、集合、索引和重复的键
+    #[error("{0}")]
+    DuplicateKey(String),
+
     /// BOML 格式错误
     #[error("Invalid BOML: {0}")]
     InvalidBoml(String),
@@ -82,5 +100,45 @@ pub enum MikuError {
     Platform(String),
 }
 
+impl MikuError {
+    /// # Brief
+    /// 判断是否为暂时性错误
+    ///
+    /// 网络连接、超时、不是主节点,以及表示连接中断或超时的 I/O 错误
+    ///
+    /// # Returns
+    /// 稍后以相同参数重试可能成功时返回 true
+    pub fn is_transient(&self) -> bool {
+        use std::io::ErrorKind;
+        match self {
+            MikuError::Connection(_) | MikuError::Timeout(_) | MikuError::NotPrimary(_) => true,
+            MikuError::Io(e) => matches!(
+                e.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::NotConnected
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::WouldBlock
+                    | ErrorKind::UnexpectedEof
+            ),
+            _ => false,
+        }
+    }
+
+    /// # Brief
+    /// 判断操作是否值得重试
+    ///
+    /// 暂时性错误和写冲突可以重试;写冲突需要重新执行整个事务,而不只是重新提交
+    ///
+    /// # Returns
+    /// 可重试时返回 true,其余错误为致命错误
+    pub fn is_retryable(&self) -> bool {
+        self.is_transient() || matches!(self, MikuError::WriteConflict(_))
+    }
+}
+
 /// MikuDB Result 类型别名
 pub type MikuResult<T> = Result<T, MikuError>;
@@ -1,20 +1,39 @@
 //! 公共类型定义模块
 //!
 //! 定义 MikuDB 的核心类型:
-//! - ObjectId: 12 字节唯一标识符(类似 MongoDB ObjectId)
+//! - ObjectId: 12 字节唯一标识符(类似 MongoDB ObjectId),集群中按节点的机器标识区分
 //! - DocumentId: 文档 ID 封装
 //! - CollectionName: 集合名称(带验证)
 //! - DatabaseName: 数据库名称(带验证)
 //! - Timestamp: 毫秒级时间戳
 
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// 机器标识的最大值(3 字节)
+pub const MAX_MACHINE_ID: u32 = 0xFF_FFFF;
+
+/// 尚未确定机器标识
+const MACHINE_ID_UNSET: u32 = u32::MAX;
+
+/// 本进程生成 ObjectId 使用的机器标识
+static MACHINE_ID: AtomicU32 = AtomicU32::new(MACHINE_ID_UNSET);
+/// 进程启动时选取的随机值,区分同一机器标识下的多个进程
+static PROCESS_UNIQUE: OnceLock<[u8; 2]> = OnceLock::new();
+/// 自增计数器,起始值随机
+static COUNTER: OnceLock<AtomicU32> = OnceLock::new();
+
 /// ObjectId - 12 字节唯一标识符
 ///
 /// 格式:
 /// - 前 4 字节: 时间戳(秒,大端)
-/// - 后 8 字节: 随机数(/dev/urandom 或系统熵)
+/// - 3 字节: 机器标识(集群节点加入时分配,未设置时为进程启动时的随机值)
+/// - 2 字节: 进程随机值
+/// - 后 3 字节: 自增计数器(大端)
+///
+/// 不同机器标识生成的 ObjectId 不会重复;同一进程每秒最多生成 2^24 个不重复的 ObjectId。
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ObjectId([u8; 12]);
 
@@ -26,11 +45,54 @@ impl ObjectId {
             .unwrap()
             .as_secs() as u32;
         bytes[0..4].copy_from_slice(&timestamp.to_be_bytes());
-        let random: [u8; 8] = rand_bytes();
-        bytes[4..12].copy_from_slice(&random);
+        bytes[4..7].copy_from_slice(&Self::machine_id().to_be_bytes()[1..]);
+        bytes[7..9].copy_from_slice(PROCESS_UNIQUE.get_or_init(rand_bytes));
+        let counter = COUNTER
+            .get_or_init(|| AtomicU32::new(u32::from_be_bytes(rand_bytes())))
+            .fetch_add(1, Ordering::Relaxed);
+        bytes[9..12].copy_from_slice(&counter.to_be_bytes()[1..]);
         Self(bytes)
     }
 
+    /// # Brief
+    /// 设置本进程生成 ObjectId 使用的机器标识
+    ///
+    /// 集群中每个节点在加入集群时分配到唯一的机器标识,保证各节点生成的 ObjectId 不会冲突。
+    ///
+    /// # Arguments
+    /// * `machine_id` - 机器标识,不超过 `MAX_MACHINE_ID`
+    pub fn set_machine_id(machine_id: u32) -> Result<(), crate::error::MikuError> {
+        if machine_id > MAX_MACHINE_ID {
+            return Err(crate::error::MikuError::Validation(format!(
+                "Machine id {} exceeds the maximum of {}",
+                machine_id, MAX_MACHINE_ID
+            )));
+        }
+        MACHINE_ID.store(machine_id, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// # Brief
+    /// 本进程当前的机器标识
+    ///
+    /// 未通过 `set_machine_id` 设置时,第一次调用随机选取一个并在进程内保持不变。
+    pub fn machine_id() -> u32 {
+        let current = MACHINE_ID.load(Ordering::Relaxed);
+        if current != MACHINE_ID_UNSET {
+            return current;
+        }
+        let random = u32::from_be_bytes(rand_bytes()) & MAX_MACHINE_ID;
+        match MACHINE_ID.compare_exchange(MACHINE_ID_UNSET, random, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => random,
+            Err(existing) => existing,
+        }
+    }
+
+    /// 生成此 ObjectId 的机器标识
+    pub fn machine(&self) -> u32 {
+        u32::from_be_bytes([0, self.0[4], self.0[5], self.0[6]])
+    }
+
     pub fn from_bytes(bytes: [u8; 12]) -> Self {
         Self(bytes)
     }
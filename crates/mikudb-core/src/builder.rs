@@ -12,13 +12,23 @@
 //!     .cache_size(2 * 1024 * 1024 * 1024)
 //!     .enable_compression(true)
 //!     .build()?;
+//!
+//! // 在服务器运行时以从实例打开同一数据目录做分析查询
+//! let replica = DatabaseBuilder::new("mydb")
+//!     .data_dir("/var/lib/mikudb/data")
+//!     .secondary("/tmp/mikudb-secondary")
+//!     .build()?;
+//! replica.storage().catch_up_with_primary()?;
 //! ```
 
+use crate::boml::DocumentCompression;
 use crate::common::config::CompressionType;
 use crate::common::MikuResult;
-use crate::storage::StorageOptions;
+use crate::storage::{EvictionPolicy, OpenMode, StorageOptions, WalSyncPolicy};
 use crate::Database;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 pub struct DatabaseBuilder {
     name: String,
@@ -31,6 +41,9 @@ pub struct DatabaseBuilder {
     enable_statistics: bool,
     paranoid_checks: bool,
     for_openeuler: bool,
+    open_mode: OpenMode,
+    document_cache_size: usize,
+    document_cache_policy: EvictionPolicy,
 
     #[cfg(target_os = "linux")]
     use_direct_reads: bool,
@@ -53,6 +66,9 @@ impl DatabaseBuilder {
             enable_statistics: defaults.enable_statistics,
             paranoid_checks: defaults.paranoid_checks,
             for_openeuler: false,
+            open_mode: defaults.open_mode,
+            document_cache_size: defaults.document_cache_size,
+            document_cache_policy: defaults.document_cache_policy,
 
             #[cfg(target_os = "linux")]
             use_direct_reads: defaults.use_direct_reads,
@@ -81,6 +97,18 @@ impl DatabaseBuilder {
         self
     }
 
+    /// # Brief
+    /// 启用解码后的文档缓存,按 `_id` 读取的热点文档不再读取和解码
+    ///
+    /// # Arguments
+    /// * `size` - 缓存容量(字节),0 表示不缓存
+    /// * `policy` - 淘汰策略
+    pub fn document_cache(mut self, size: usize, policy: EvictionPolicy) -> Self {
+        self.document_cache_size = size;
+        self.document_cache_policy = policy;
+        self
+    }
+
     pub fn write_buffer_size(mut self, size: usize) -> Self {
         self.write_buffer_size = size;
         self
@@ -145,6 +173,25 @@ impl DatabaseBuilder {
         self
     }
 
+    /// # Brief
+    /// 以只读方式打开,用于离线工具和备份校验
+    pub fn read_only(mut self) -> Self {
+        self.open_mode = OpenMode::ReadOnly;
+        self
+    }
+
+    /// # Brief
+    /// 以 RocksDB 从实例方式打开,读取同一主机上正在运行的主实例的数据目录
+    ///
+    /// # Arguments
+    /// * `secondary_dir` - 从实例自己的日志目录,不能与数据目录相同
+    pub fn secondary(mut self, secondary_dir: impl AsRef<Path>) -> Self {
+        self.open_mode = OpenMode::Secondary {
+            secondary_dir: secondary_dir.as_ref().to_path_buf(),
+        };
+        self
+    }
+
     #[cfg(target_os = "linux")]
     pub fn use_direct_io(mut self, enable: bool) -> Self {
         self.use_direct_reads = enable;
@@ -177,10 +224,14 @@ impl DatabaseBuilder {
             opts.compression = self.compression;
             opts.enable_statistics = self.enable_statistics;
             opts.paranoid_checks = self.paranoid_checks;
+            opts.open_mode = self.open_mode;
+            opts.document_cache_size = self.document_cache_size;
+            opts.document_cache_policy = self.document_cache_policy;
             opts
         } else {
             StorageOptions {
                 data_dir: data_path,
+                open_mode: self.open_mode,
                 cache_size: self.cache_size,
                 write_buffer_size: self.write_buffer_size,
                 max_write_buffer_number: self.max_write_buffer_number,
@@ -189,7 +240,15 @@ impl DatabaseBuilder {
                 enable_statistics: self.enable_statistics,
                 paranoid_checks: self.paranoid_checks,
                 enable_wal: true,
-                wal_sync_on_write: false,
+                wal_sync: WalSyncPolicy::default(),
+                wal_dir: None,
+                wal_archive_dir: None,
+                document_compression: DocumentCompression::None,
+                collection_compression: HashMap::new(),
+                ttl_sweep_interval: Duration::from_secs(60),
+                compaction_interval: None,
+                document_cache_size: self.document_cache_size,
+                document_cache_policy: self.document_cache_policy,
 
                 #[cfg(target_os = "linux")]
                 use_direct_reads: self.use_direct_reads,
@@ -233,6 +292,14 @@ impl StorageOptionsBuilder {
         self
     }
 
+    /// # Brief
+    /// 设置解码后文档缓存的容量(字节,0 表示不缓存)和淘汰策略
+    pub fn document_cache(mut self, size: usize, policy: EvictionPolicy) -> Self {
+        self.options.document_cache_size = size;
+        self.options.document_cache_policy = policy;
+        self
+    }
+
     pub fn write_buffer_size(mut self, size: usize) -> Self {
         self.options.write_buffer_size = size;
         self
@@ -253,6 +320,20 @@ impl StorageOptionsBuilder {
         self
     }
 
+    /// # Brief
+    /// 设置集合文档的默认压缩算法
+    pub fn document_compression(mut self, compression: DocumentCompression) -> Self {
+        self.options.document_compression = compression;
+        self
+    }
+
+    /// # Brief
+    /// 为指定集合单独设置文档压缩算法
+    pub fn collection_compression(mut self, collection: impl Into<String>, compression: DocumentCompression) -> Self {
+        self.options.collection_compression.insert(collection.into(), compression);
+        self
+    }
+
     pub fn enable_statistics(mut self, enable: bool) -> Self {
         self.options.enable_statistics = enable;
         self
@@ -263,6 +344,13 @@ impl StorageOptionsBuilder {
         self
     }
 
+    /// # Brief
+    /// 设置打开方式(读写、只读或从实例)
+    pub fn open_mode(mut self, mode: OpenMode) -> Self {
+        self.options.open_mode = mode;
+        self
+    }
+
     #[cfg(target_os = "linux")]
     pub fn use_direct_io(mut self, enable: bool) -> Self {
         self.options.use_direct_reads = enable;
@@ -324,6 +412,21 @@ mod tests {
         assert_eq!(db.name(), "test");
     }
 
+    #[test]
+    fn test_database_builder_read_only() {
+        let dir = tempdir().unwrap();
+        drop(DatabaseBuilder::new("test").data_dir(dir.path()).build().unwrap());
+
+        let db = DatabaseBuilder::new("test")
+            .data_dir(dir.path())
+            .read_only()
+            .build()
+            .unwrap();
+
+        assert_eq!(db.storage().open_mode(), &OpenMode::ReadOnly);
+        assert!(db.storage().create_collection("users").is_err());
+    }
+
     #[test]
     fn test_storage_options_builder() {
         let dir = tempdir().unwrap();
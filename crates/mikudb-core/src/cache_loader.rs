@@ -0,0 +1,333 @@
+//! 缓存加载器模块
+//!
+//! 把一个集合(或满足条件的子集)加载到进程内的 `DashMap`,并通过存储层的变更流保持更新,
+//! 适合需要微秒级查找参考数据(字典表、配置、汇率等)的服务:
+//! - 先记下变更流的恢复令牌再拉取快照,快照期间的写入不会丢失
+//! - 后台线程从令牌继续读取变更,按文档 ID 回读最新内容,不匹配过滤条件的文档从缓存移除
+//! - 令牌过期或收到 `Invalidate`(清空、范围删除、删除集合、从备份恢复)时重新拉取快照,
+//!   重新加载期间缓存保持可读,只在加载完成后移除已不存在的文档
+//!
+//! # 示例
+//!
+//! ```rust,ignore
+//! use mikudb_core::CacheLoader;
+//!
+//! let cache = CacheLoader::new(&db, "currencies").start()?;
+//! let rate = cache.get(&id);
+//! ```
+
+use crate::boml::Document;
+use crate::common::{MikuError, MikuResult, ObjectId};
+use crate::database::Database;
+use crate::query::filter::Filter;
+use crate::query::Expression;
+use crate::storage::{ChangeKind, StorageEngine, StorageError};
+use dashmap::mapref::one::Ref;
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// 默认的变更等待时间,也是停止缓存时的最长响应时间
+pub const DEFAULT_CACHE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 缓存加载器
+///
+/// 配置要缓存的集合和过滤条件,`start` 后返回持续更新的 [`CachedCollection`]。
+pub struct CacheLoader {
+    storage: Arc<StorageEngine>,
+    collection: String,
+    filter: Option<Expression>,
+    poll_interval: Duration,
+}
+
+impl CacheLoader {
+    /// # Brief
+    /// 创建缓存加载器
+    ///
+    /// # Arguments
+    /// * `db` - 数据库实例
+    /// * `collection` - 要缓存的集合名称
+    pub fn new(db: &Database, collection: impl Into<String>) -> Self {
+        Self {
+            storage: db.storage().clone(),
+            collection: collection.into(),
+            filter: None,
+            poll_interval: DEFAULT_CACHE_POLL_INTERVAL,
+        }
+    }
+
+    /// # Brief
+    /// 只缓存满足条件的文档
+    pub fn filter(mut self, filter: Expression) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// # Brief
+    /// 设置没有变更时的等待时间
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// # Brief
+    /// 拉取初始快照并启动后台同步线程
+    ///
+    /// # Returns
+    /// 初始快照加载完成的缓存;集合不存在或读取失败时返回错误
+    pub fn start(self) -> MikuResult<CachedCollection> {
+        let state = Arc::new(CacheState {
+            storage: self.storage,
+            collection: self.collection,
+            filter: self.filter.map(Filter::new),
+            map: Arc::new(DashMap::new()),
+            token: AtomicU64::new(0),
+            reloads: AtomicU64::new(0),
+            stop: AtomicBool::new(false),
+        });
+
+        state
+            .storage
+            .get_collection(&state.collection)
+            .map_err(MikuError::from)?;
+        state.reload().map_err(MikuError::from)?;
+
+        let worker = state.clone();
+        let poll_interval = self.poll_interval;
+        let handle = std::thread::Builder::new()
+            .name(format!("mikudb-cache-{}", state.collection))
+            .spawn(move || worker.run(poll_interval))
+            .map_err(|e| MikuError::Internal(e.to_string()))?;
+
+        Ok(CachedCollection {
+            state,
+            handle: Some(handle),
+        })
+    }
+}
+
+struct CacheState {
+    storage: Arc<StorageEngine>,
+    collection: String,
+    filter: Option<Filter>,
+    map: Arc<DashMap<ObjectId, Document>>,
+    /// 已应用到缓存的最后一个恢复令牌
+    token: AtomicU64,
+    reloads: AtomicU64,
+    stop: AtomicBool,
+}
+
+impl CacheState {
+    fn matches(&self, doc: &Document) -> bool {
+        self.filter.as_ref().map_or(true, |f| f.matches(doc).unwrap_or(false))
+    }
+
+    /// 重新拉取快照: 先记下令牌,再扫描集合,最后移除快照中已不存在的文档
+    fn reload(&self) -> Result<(), StorageError> {
+        let token = self.storage.change_stream().current_token();
+        let docs = match self.storage.get_collection(&self.collection) {
+            Ok(collection) => collection.find_all()?,
+            Err(StorageError::CollectionNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+
+        let mut loaded = HashSet::with_capacity(docs.len());
+        for doc in docs {
+            if let Some(id) = doc.id().copied() {
+                if self.matches(&doc) {
+                    loaded.insert(id);
+                    self.map.insert(id, doc);
+                }
+            }
+        }
+        self.map.retain(|id, _| loaded.contains(id));
+
+        self.reloads.fetch_add(1, Ordering::Relaxed);
+        self.token.store(token, Ordering::Release);
+        debug!("Loaded {} documents of {} into cache", loaded.len(), self.collection);
+        Ok(())
+    }
+
+    /// 回读文档的最新内容
+    fn refresh(&self, id: ObjectId) -> Result<(), StorageError> {
+        let doc = match self.storage.get_collection(&self.collection) {
+            Ok(collection) => collection.get(&id)?,
+            Err(StorageError::CollectionNotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        match doc {
+            Some(doc) if self.matches(&doc) => {
+                self.map.insert(id, doc);
+            }
+            _ => {
+                self.map.remove(&id);
+            }
+        }
+        Ok(())
+    }
+
+    fn sync_once(&self, poll_interval: Duration) -> Result<(), StorageError> {
+        // 读取所有集合的事件,其他集合的事件只推进令牌
+        let token = self.token.load(Ordering::Acquire);
+        let events = match self.storage.change_stream().changes_since(token, None, poll_interval) {
+            Ok(events) => events,
+            Err(StorageError::ResumeTokenExpired(_)) => {
+                debug!("Resume token of {} cache expired, reloading", self.collection);
+                return self.reload();
+            }
+            Err(e) => return Err(e),
+        };
+
+        for event in events {
+            if event.collection == self.collection {
+                match (event.kind, event.id) {
+                    (ChangeKind::Invalidate, _) | (_, None) => return self.reload(),
+                    (_, Some(id)) => self.refresh(id)?,
+                }
+            }
+            self.token.store(event.token, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    fn run(&self, poll_interval: Duration) {
+        while !self.stop.load(Ordering::Acquire) {
+            if let Err(e) = self.sync_once(poll_interval) {
+                warn!("Failed to sync cache of {}: {}", self.collection, e);
+                std::thread::sleep(poll_interval);
+            }
+        }
+    }
+}
+
+/// 持续更新的集合缓存
+///
+/// 读取直接访问内存中的 `DashMap`,不经过存储引擎。丢弃时停止后台同步线程。
+pub struct CachedCollection {
+    state: Arc<CacheState>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl CachedCollection {
+    /// # Brief
+    /// 按 ID 查找缓存的文档
+    pub fn get(&self, id: &ObjectId) -> Option<Ref<'_, ObjectId, Document>> {
+        self.state.map.get(id)
+    }
+
+    /// # Brief
+    /// 获取底层的 DashMap,可以交给其他组件共享
+    pub fn map(&self) -> &Arc<DashMap<ObjectId, Document>> {
+        &self.state.map
+    }
+
+    /// # Brief
+    /// 缓存的文档数量
+    pub fn len(&self) -> usize {
+        self.state.map.len()
+    }
+
+    /// # Brief
+    /// 缓存是否为空
+    pub fn is_empty(&self) -> bool {
+        self.state.map.is_empty()
+    }
+
+    /// # Brief
+    /// 已应用到缓存的最后一个恢复令牌
+    pub fn resume_token(&self) -> u64 {
+        self.state.token.load(Ordering::Acquire)
+    }
+
+    /// # Brief
+    /// 拉取快照的次数(包括初始快照)
+    pub fn reload_count(&self) -> u64 {
+        self.state.reloads.load(Ordering::Relaxed)
+    }
+
+    /// # Brief
+    /// 等待缓存追上调用时刻之前的所有写入,用于写后立即读取
+    ///
+    /// # Arguments
+    /// * `timeout` - 最长等待时间
+    ///
+    /// # Returns
+    /// 在超时前追上返回 `true`
+    pub fn wait_synced(&self, timeout: Duration) -> bool {
+        let target = self.state.storage.change_stream().current_token();
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.resume_token() >= target {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+impl Drop for CachedCollection {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cache_follows_changes() {
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        let collection = db.collection("currencies").unwrap();
+
+        let mut usd = Document::new();
+        usd.insert("code", "USD");
+        usd.insert("active", true);
+        let usd_id = collection.insert(&mut usd).unwrap();
+
+        let cache = CacheLoader::new(&db, "currencies")
+            .filter(Expression::eq(Expression::field("active"), Expression::literal(true)))
+            .poll_interval(Duration::from_millis(10))
+            .start()
+            .unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&usd_id).unwrap().get_str("code"), Some("USD"));
+
+        let mut eur = Document::new();
+        eur.insert("code", "EUR");
+        eur.insert("active", true);
+        let eur_id = collection.insert(&mut eur).unwrap();
+        let mut old = Document::new();
+        old.insert("code", "DEM");
+        old.insert("active", false);
+        collection.insert(&mut old).unwrap();
+        assert!(cache.wait_synced(Duration::from_secs(5)));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&eur_id).is_some());
+
+        let mut inactive = Document::with_id(usd_id);
+        inactive.insert("code", "USD");
+        inactive.insert("active", false);
+        collection.update(&usd_id, &inactive).unwrap();
+        assert!(cache.wait_synced(Duration::from_secs(5)));
+        assert!(cache.get(&usd_id).is_none());
+
+        let reloads = cache.reload_count();
+        collection.clear().unwrap();
+        assert!(cache.wait_synced(Duration::from_secs(5)));
+        assert!(cache.is_empty());
+        assert_eq!(cache.reload_count(), reloads + 1);
+    }
+}
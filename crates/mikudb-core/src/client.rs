@@ -2,6 +2,14 @@
 //!
 //! 提供异步的数据库客户端，支持连接池和自动重连。
 //!
+//! `execute` 遇到可重试错误(见 [`MikuError::is_retryable`])时按 `retry_reads` / `retry_writes`
+//! 重试一次: 读语句遇到任何可重试错误都重试,写语句只在写冲突时重试,
+//! 因为超时等错误发生时写入可能已经部分生效。
+//!
+//! 设置 `timeout_budget` 后,一次 `execute` 的全部尝试和重试间隔共用这段时间:
+//! 可重试错误在预算内按指数退避反复重试,预算用完时返回 `MikuError::Timeout`,
+//! 错误消息列出每次尝试的耗时和错误。超时的尝试不会被中止,写语句可能仍会生效。
+//!
 //! # 示例
 //!
 //! ```rust,ignore
@@ -17,7 +25,7 @@
 //! ```
 
 use crate::common::{MikuError, MikuResult};
-use crate::query::QueryResponse;
+use crate::query::{Parser, QueryResponse, Statement};
 use crate::storage::{StorageEngine, StorageOptions};
 use crate::transaction::{Session, SessionManager};
 use crate::{Database, DatabaseBuilder};
@@ -25,7 +33,8 @@ use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
 use tokio::sync::Semaphore;
 use tracing::{debug, info};
 
@@ -42,6 +51,8 @@ pub struct ClientOptions {
     pub server_selection_timeout: Duration,
     pub heartbeat_frequency: Duration,
     pub app_name: Option<String>,
+    /// 一次操作包括重试在内的总时间上限,None 表示不限制且只重试一次
+    pub timeout_budget: Option<Duration>,
 }
 
 impl Default for ClientOptions {
@@ -58,6 +69,7 @@ impl Default for ClientOptions {
             server_selection_timeout: Duration::from_secs(30),
             heartbeat_frequency: Duration::from_secs(10),
             app_name: None,
+            timeout_budget: None,
         }
     }
 }
@@ -103,6 +115,11 @@ impl ClientOptions {
                                 "appName" => {
                                     options.app_name = Some(value.to_string());
                                 }
+                                "timeoutBudgetMS" => {
+                                    if let Ok(v) = value.parse::<u64>() {
+                                        options.timeout_budget = Some(Duration::from_millis(v));
+                                    }
+                                }
                                 _ => {}
                             }
                         }
@@ -172,6 +189,11 @@ impl ClientOptionsBuilder {
         self
     }
 
+    pub fn timeout_budget(mut self, budget: Duration) -> Self {
+        self.options.timeout_budget = Some(budget);
+        self
+    }
+
     pub fn build(self) -> ClientOptions {
         self.options
     }
@@ -208,7 +230,7 @@ impl Client {
         let storage = tokio::task::spawn_blocking(move || StorageEngine::open(storage_options))
             .await
             .map_err(|e| MikuError::Internal(e.to_string()))?
-            .map_err(|e| MikuError::Storage(e.to_string()))?;
+            .map_err(MikuError::from)?;
 
         let storage = Arc::new(storage);
         let session_manager = Arc::new(SessionManager::new(storage.clone()));
@@ -256,13 +278,59 @@ impl Client {
         &self.session_manager
     }
 
+    /// # Brief
+    /// 在指定数据库上执行 MQL,可重试错误按客户端选项重试
+    ///
+    /// 未设置 `timeout_budget` 时重试一次;设置后在预算内反复重试,见模块文档。
+    ///
+    /// # Arguments
+    /// * `db_name` - 数据库名称
+    /// * `query` - MQL 查询字符串
+    ///
+    /// # Returns
+    /// 查询结果;重试后仍失败时返回最后一次的错误,预算用完时返回 Timeout
     pub async fn execute(&self, db_name: &str, query: &str) -> MikuResult<QueryResponse> {
         let db = self.database(db_name);
+        let stmt = Arc::new(Parser::parse(query).map_err(MikuError::from)?);
 
-        let query = query.to_string();
-        tokio::task::spawn_blocking(move || db.execute(&query))
-            .await
-            .map_err(|e| MikuError::Internal(e.to_string()))?
+        let Some(budget) = self.options.timeout_budget else {
+            return match execute_blocking(db.clone(), stmt.clone()).await {
+                Err(e) if self.should_retry(&stmt, &e) => {
+                    debug!("Retrying statement after retryable error: {}", e);
+                    execute_blocking(db, stmt).await
+                }
+                result => result,
+            };
+        };
+
+        let mut spend = BudgetSpend::new(budget);
+        let mut backoff = RETRY_BACKOFF;
+        loop {
+            let started = Instant::now();
+            let attempt = execute_blocking(db.clone(), stmt.clone());
+            let error = match tokio::time::timeout(spend.remaining(), attempt).await {
+                Ok(Ok(response)) => return Ok(response),
+                Ok(Err(e)) if !self.should_retry(&stmt, &e) => return Err(e),
+                Ok(Err(e)) => e,
+                Err(_) => MikuError::Timeout("attempt did not finish within the remaining budget".to_string()),
+            };
+            spend.record_attempt(started.elapsed(), &error);
+            if spend.remaining() <= backoff {
+                return Err(spend.exhausted());
+            }
+            debug!("Retrying statement in {:?} after retryable error: {}", backoff, error);
+            tokio::time::sleep(backoff).await;
+            spend.record_backoff(backoff);
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+        }
+    }
+
+    fn should_retry(&self, stmt: &Statement, error: &MikuError) -> bool {
+        if is_read_statement(stmt) {
+            self.options.retry_reads && error.is_retryable()
+        } else {
+            self.options.retry_writes && matches!(error, MikuError::WriteConflict(_))
+        }
     }
 
     pub async fn close(&self) -> MikuResult<()> {
@@ -272,7 +340,7 @@ impl Client {
 
         self.storage
             .flush()
-            .map_err(|e| MikuError::Storage(e.to_string()))?;
+            .map_err(MikuError::from)?;
 
         Ok(())
     }
@@ -286,6 +354,92 @@ impl Client {
     }
 }
 
+/// 设置超时预算时第一次重试前的等待时间,之后每次加倍
+const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// 重试间隔的上限
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// 一次操作的超时预算及其使用情况
+struct BudgetSpend {
+    budget: Duration,
+    started: Instant,
+    /// 每次尝试的耗时和错误
+    attempts: Vec<(Duration, String)>,
+    /// 重试前等待的总时间
+    backoff: Duration,
+}
+
+impl BudgetSpend {
+    fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            started: Instant::now(),
+            attempts: Vec::new(),
+            backoff: Duration::ZERO,
+        }
+    }
+
+    fn remaining(&self) -> Duration {
+        self.budget.saturating_sub(self.started.elapsed())
+    }
+
+    fn record_attempt(&mut self, elapsed: Duration, error: &MikuError) {
+        self.attempts.push((elapsed, error.to_string()));
+    }
+
+    fn record_backoff(&mut self, waited: Duration) {
+        self.backoff += waited;
+    }
+
+    /// # Brief
+    /// 预算用完时的错误,列出每次尝试的耗时和错误
+    fn exhausted(&self) -> MikuError {
+        let mut message = format!(
+            "Timeout budget of {:.1?} exhausted after {} attempt(s) in {:.1?}",
+            self.budget,
+            self.attempts.len(),
+            self.started.elapsed()
+        );
+        for (i, (elapsed, error)) in self.attempts.iter().enumerate() {
+            let _ = write!(message, "; attempt {}: {:.1?} ({})", i + 1, elapsed, error);
+        }
+        if !self.backoff.is_zero() {
+            let _ = write!(message, "; backoff {:.1?}", self.backoff);
+        }
+        MikuError::Timeout(message)
+    }
+}
+
+async fn execute_blocking(db: Arc<Database>, stmt: Arc<Statement>) -> MikuResult<QueryResponse> {
+    tokio::task::spawn_blocking(move || db.execute_statement(&stmt))
+        .await
+        .map_err(|e| MikuError::Internal(e.to_string()))?
+}
+
+/// 只读语句: 重复执行没有副作用
+fn is_read_statement(stmt: &Statement) -> bool {
+    matches!(
+        stmt,
+        Statement::Find(_)
+            | Statement::Aggregate(_)
+            | Statement::ShowDatabases
+            | Statement::ShowCollections
+            | Statement::ShowIndexes(_)
+            | Statement::ShowStatus
+            | Statement::ShowUsers
+            | Statement::ShowAdvisor(_)
+            | Statement::ShowSchema(_)
+            | Statement::ShowSequences
+            | Statement::ShowRollups
+            | Statement::ShowManifest
+            | Statement::ShowFunctions
+            | Statement::ShowGrants(_)
+            | Statement::Stats(_)
+            | Statement::DryRun(_)
+    )
+}
+
 pub struct AsyncDatabase {
     inner: Arc<Database>,
 }
@@ -404,6 +558,14 @@ impl AsyncCollection {
         self.inner.delete(id)
     }
 
+    pub async fn bulk_write(
+        &self,
+        operations: Vec<crate::storage::WriteOperation>,
+        ordered: bool,
+    ) -> MikuResult<crate::storage::BulkWriteResult> {
+        self.inner.bulk_write(operations, ordered)
+    }
+
     pub async fn count(&self) -> MikuResult<u64> {
         self.inner.count()
     }
@@ -452,12 +614,43 @@ mod tests {
     #[test]
     fn test_client_options_parse() {
         let options = ClientOptions::parse(
-            "mikudb://localhost:3939/mydb?maxPoolSize=50&retryWrites=true",
+            "mikudb://localhost:3939/mydb?maxPoolSize=50&retryWrites=true&timeoutBudgetMS=2500",
         )
         .unwrap();
 
         assert_eq!(options.max_pool_size, 50);
         assert!(options.retry_writes);
+        assert_eq!(options.timeout_budget, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn test_budget_exhausted_report() {
+        let mut spend = BudgetSpend::new(Duration::from_millis(200));
+        spend.record_attempt(Duration::from_millis(120), &MikuError::Timeout("slow".to_string()));
+        spend.record_backoff(Duration::from_millis(50));
+        spend.record_attempt(Duration::from_millis(30), &MikuError::WriteConflict("users".to_string()));
+
+        let error = spend.exhausted();
+        assert!(matches!(error, MikuError::Timeout(_)));
+        let message = error.to_string();
+        assert!(message.contains("after 2 attempt(s)"), "{}", message);
+        assert!(message.contains("attempt 1: 120.0ms (Timeout: slow)"), "{}", message);
+        assert!(message.contains("backoff 50.0ms"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_execute_within_budget() {
+        let dir = tempdir().unwrap();
+        let options = ClientOptions::builder()
+            .data_dir(dir.path())
+            .timeout_budget(Duration::from_secs(5))
+            .build();
+        let client = Client::connect_with_options(options).await.unwrap();
+
+        client.execute("default", "CREATE COLLECTION users").await.unwrap();
+        // 不可重试的错误直接返回,不消耗整个预算
+        let error = client.execute("default", "CREATE COLLECTION users").await.unwrap_err();
+        assert!(!matches!(error, MikuError::Timeout(_)));
     }
 
     #[test]
@@ -1,17 +1,22 @@
 //! 游标模块
 //!
 //! 提供查询结果的迭代器接口，支持批量获取、流式处理和游标管理。
+//! 启用自适应批量时，每取一批后批量大小翻倍（不超过 `MAX_ADAPTIVE_BATCH_SIZE`），
+//! 以减少大结果集的往返次数。
 
 use crate::boml::Document;
 use crate::common::{MikuError, MikuResult};
 use parking_lot::Mutex;
 use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 static CURSOR_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
+/// 自适应批量增长的上限
+pub const MAX_ADAPTIVE_BATCH_SIZE: u32 = 16384;
+
 #[derive(Debug, Clone)]
 pub struct CursorOptions {
     pub batch_size: u32,
@@ -19,6 +24,8 @@ pub struct CursorOptions {
     pub no_cursor_timeout: bool,
     pub allow_partial_results: bool,
     pub max_await_time: Option<Duration>,
+    /// 每取一批后批量大小翻倍
+    pub adaptive_batch_size: bool,
 }
 
 impl Default for CursorOptions {
@@ -29,6 +36,7 @@ impl Default for CursorOptions {
             no_cursor_timeout: false,
             allow_partial_results: false,
             max_await_time: None,
+            adaptive_batch_size: false,
         }
     }
 }
@@ -42,6 +50,7 @@ pub struct Cursor<T = Document> {
     created_at: Instant,
     last_accessed: Mutex<Instant>,
     total_returned: AtomicU64,
+    next_batch_size: AtomicU32,
     _marker: std::marker::PhantomData<T>,
 }
 
@@ -55,16 +64,22 @@ impl<T> Cursor<T> {
             collection: collection.into(),
             buffer: Mutex::new(VecDeque::new()),
             exhausted: AtomicBool::new(false),
-            options,
             created_at: now,
             last_accessed: Mutex::new(now),
             total_returned: AtomicU64::new(0),
+            next_batch_size: AtomicU32::new(options.batch_size.max(1)),
+            options,
             _marker: std::marker::PhantomData,
         }
     }
 
     pub fn from_vec(collection: impl Into<String>, items: Vec<T>) -> Self {
-        let cursor = Self::new(collection, CursorOptions::default());
+        Self::from_vec_with_options(collection, items, CursorOptions::default())
+    }
+
+    /// 用已物化的结果创建游标，按 `options` 分批返回
+    pub fn from_vec_with_options(collection: impl Into<String>, items: Vec<T>, options: CursorOptions) -> Self {
+        let cursor = Self::new(collection, options);
         cursor.buffer.lock().extend(items);
         cursor.exhausted.store(true, Ordering::SeqCst);
         cursor
@@ -96,6 +111,16 @@ impl<T> Cursor<T> {
         self.total_returned.load(Ordering::SeqCst)
     }
 
+    /// 下一批将返回的文档数
+    pub fn next_batch_size(&self) -> u32 {
+        self.next_batch_size.load(Ordering::SeqCst)
+    }
+
+    /// 由客户端指定后续批量大小；启用自适应时从该值继续增长
+    pub fn set_batch_size(&self, size: u32) {
+        self.next_batch_size.store(size.max(1), Ordering::SeqCst);
+    }
+
     fn touch(&self) {
         *self.last_accessed.lock() = Instant::now();
     }
@@ -149,6 +174,17 @@ impl<T: Clone> Cursor<T> {
         items
     }
 
+    /// 取出下一批结果，启用自适应批量时随后将批量大小翻倍
+    pub fn next_batch(&self) -> Vec<T> {
+        let size = self.next_batch_size();
+        let batch = self.take(size as usize);
+        if self.options.adaptive_batch_size {
+            let grown = size.saturating_mul(2).min(MAX_ADAPTIVE_BATCH_SIZE).max(size);
+            self.next_batch_size.store(grown, Ordering::SeqCst);
+        }
+        batch
+    }
+
     pub fn collect_all(&self) -> Vec<T> {
         self.touch();
         let mut buffer = self.buffer.lock();
@@ -212,13 +248,13 @@ impl<T: Clone> Iterator for CursorIterator<T> {
     }
 }
 
-pub struct CursorManager {
-    cursors: Mutex<std::collections::HashMap<u64, Arc<Cursor<Document>>>>,
+pub struct CursorManager<T = Document> {
+    cursors: Mutex<std::collections::HashMap<u64, Arc<Cursor<T>>>>,
     cleanup_interval: Duration,
     last_cleanup: Mutex<Instant>,
 }
 
-impl CursorManager {
+impl<T> CursorManager<T> {
     pub fn new() -> Self {
         Self {
             cursors: Mutex::new(std::collections::HashMap::new()),
@@ -227,18 +263,18 @@ impl CursorManager {
         }
     }
 
-    pub fn register(&self, cursor: Cursor<Document>) -> Arc<Cursor<Document>> {
+    pub fn register(&self, cursor: Cursor<T>) -> Arc<Cursor<T>> {
         self.maybe_cleanup();
         let cursor = Arc::new(cursor);
         self.cursors.lock().insert(cursor.id(), cursor.clone());
         cursor
     }
 
-    pub fn get(&self, id: u64) -> Option<Arc<Cursor<Document>>> {
+    pub fn get(&self, id: u64) -> Option<Arc<Cursor<T>>> {
         self.cursors.lock().get(&id).cloned()
     }
 
-    pub fn remove(&self, id: u64) -> Option<Arc<Cursor<Document>>> {
+    pub fn remove(&self, id: u64) -> Option<Arc<Cursor<T>>> {
         self.cursors.lock().remove(&id)
     }
 
@@ -299,7 +335,7 @@ impl CursorManager {
     }
 }
 
-impl Default for CursorManager {
+impl<T> Default for CursorManager<T> {
     fn default() -> Self {
         Self::new()
     }
@@ -353,6 +389,11 @@ impl CursorBuilder {
         self
     }
 
+    pub fn adaptive_batch_size(mut self, adaptive: bool) -> Self {
+        self.options.adaptive_batch_size = adaptive;
+        self
+    }
+
     pub fn build(self) -> Cursor<Document> {
         Cursor::new(self.collection, self.options)
     }
@@ -493,6 +534,37 @@ mod tests {
         assert_eq!(manager.active_count(), 0);
     }
 
+    #[test]
+    fn test_cursor_next_batch_fixed() {
+        let options = CursorOptions { batch_size: 2, ..Default::default() };
+        let cursor: Cursor<i32> = Cursor::from_vec_with_options("test", vec![1, 2, 3, 4, 5], options);
+
+        assert_eq!(cursor.next_batch(), vec![1, 2]);
+        assert_eq!(cursor.next_batch(), vec![3, 4]);
+        assert_eq!(cursor.next_batch(), vec![5]);
+        assert!(cursor.is_exhausted());
+    }
+
+    #[test]
+    fn test_cursor_next_batch_adaptive() {
+        let options = CursorOptions {
+            batch_size: 2,
+            adaptive_batch_size: true,
+            ..Default::default()
+        };
+        let cursor: Cursor<i32> = Cursor::from_vec_with_options("test", (0..20).collect(), options);
+
+        assert_eq!(cursor.next_batch().len(), 2);
+        assert_eq!(cursor.next_batch().len(), 4);
+        assert_eq!(cursor.next_batch().len(), 8);
+        assert_eq!(cursor.next_batch().len(), 6);
+        assert!(cursor.is_exhausted());
+
+        cursor.set_batch_size(MAX_ADAPTIVE_BATCH_SIZE);
+        cursor.next_batch();
+        assert_eq!(cursor.next_batch_size(), MAX_ADAPTIVE_BATCH_SIZE);
+    }
+
     #[test]
     fn test_cursor_timeout() {
         let mut options = CursorOptions::default();
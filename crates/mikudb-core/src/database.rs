@@ -16,8 +16,8 @@
 //! collection.insert(&mut doc)?;
 //! ```
 
-use crate::query::{Parser, QueryExecutor, QueryResponse, Statement};
-use crate::storage::{StorageEngine, StorageOptions};
+use crate::query::{FunctionRegistry, Parser, QueryExecutor, QueryResponse, Statement};
+use crate::storage::{BulkWriteResult, StorageEngine, StorageOptions, WriteOperation};
 use crate::transaction::{Session, SessionManager};
 use mikudb_common::{MikuError, MikuResult};
 use std::path::Path;
@@ -75,7 +75,7 @@ impl Database {
         info!("Opening database: {}", name);
 
         let storage = StorageEngine::open(options)
-            .map_err(|e| MikuError::Storage(e.to_string()))?;
+            .map_err(MikuError::from)?;
 
         let storage = Arc::new(storage);
         let executor = QueryExecutor::new(storage.clone());
@@ -157,11 +157,11 @@ impl Database {
         debug!("Executing query: {}", query);
 
         let stmt = Parser::parse(query)
-            .map_err(|e| MikuError::Query(e.to_string()))?;
+            .map_err(MikuError::from)?;
 
         self.executor
             .execute(&stmt)
-            .map_err(|e| MikuError::Query(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     /// 执行已解析的语句
@@ -177,7 +177,7 @@ impl Database {
     pub fn execute_statement(&self, stmt: &Statement) -> MikuResult<QueryResponse> {
         self.executor
             .execute(stmt)
-            .map_err(|e| MikuError::Query(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     /// 创建集合
@@ -193,7 +193,7 @@ impl Database {
     pub fn create_collection(&self, name: &str) -> MikuResult<()> {
         self.storage
             .create_collection(name)
-            .map_err(|e| MikuError::Storage(e.to_string()))?;
+            .map_err(MikuError::from)?;
         Ok(())
     }
 
@@ -210,7 +210,7 @@ impl Database {
     pub fn drop_collection(&self, name: &str) -> MikuResult<()> {
         self.storage
             .drop_collection(name)
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     /// 列出所有集合
@@ -223,7 +223,7 @@ impl Database {
     pub fn list_collections(&self) -> MikuResult<Vec<String>> {
         self.storage
             .list_collections()
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     /// 获取集合
@@ -240,8 +240,8 @@ impl Database {
         let inner = self
             .storage
             .get_or_create_collection(name)
-            .map_err(|e| MikuError::Storage(e.to_string()))?;
-        Ok(Collection { inner })
+            .map_err(MikuError::from)?;
+        Ok(Collection { inner, storage: self.storage.clone() })
     }
 
     /// 压缩数据库
@@ -254,7 +254,7 @@ impl Database {
     pub fn compact(&self) -> MikuResult<()> {
         self.storage
             .compact()
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     /// 刷新数据到磁盘
@@ -267,7 +267,7 @@ impl Database {
     pub fn flush(&self) -> MikuResult<()> {
         self.storage
             .flush()
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     /// 获取数据库统计信息
@@ -317,6 +317,23 @@ impl Database {
     pub fn storage(&self) -> &Arc<StorageEngine> {
         &self.storage
     }
+
+    /// 获取自定义函数注册表
+    ///
+    /// # Brief
+    /// 注册的标量函数可以在 `execute` 的过滤条件中调用,累加器函数可以在 GROUP 阶段调用
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// db.functions().register_scalar("double", |args| match args {
+    ///     [BomlValue::Int32(n)] => Ok(BomlValue::Int32(n * 2)),
+    ///     _ => Err(QueryError::TypeError("double requires an int".to_string())),
+    /// })?;
+    /// let result = db.execute("FIND items WHERE double(qty) > 10")?;
+    /// ```
+    pub fn functions(&self) -> &Arc<FunctionRegistry> {
+        self.executor.functions()
+    }
 }
 
 /// 集合包装器
@@ -324,6 +341,7 @@ impl Database {
 /// 提供文档集合的高级 API
 pub struct Collection {
     inner: Arc<crate::storage::Collection>,
+    storage: Arc<StorageEngine>,
 }
 
 impl Collection {
@@ -334,49 +352,67 @@ impl Collection {
     pub fn insert(&self, doc: &mut crate::boml::Document) -> MikuResult<crate::common::ObjectId> {
         self.inner
             .insert(doc)
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     pub fn insert_many(&self, docs: &mut [crate::boml::Document]) -> MikuResult<Vec<crate::common::ObjectId>> {
         self.inner
             .insert_many(docs)
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     pub fn find_one(&self, id: &crate::common::ObjectId) -> MikuResult<Option<crate::boml::Document>> {
         self.inner
             .get(id)
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     pub fn find_all(&self) -> MikuResult<Vec<crate::boml::Document>> {
         self.inner
             .find_all()
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     pub fn update(&self, id: &crate::common::ObjectId, doc: &crate::boml::Document) -> MikuResult<()> {
         self.inner
             .update(id, doc)
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     pub fn delete(&self, id: &crate::common::ObjectId) -> MikuResult<bool> {
         self.inner
             .delete(id)
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
+    }
+
+    /// 批量写入
+    ///
+    /// # Brief
+    /// 执行一组插入、替换、删除操作并维护集合索引,类似 MongoDB 的 bulkWrite。
+    /// 有序执行在第一个失败的操作处停止,无序执行跳过失败的操作。
+    ///
+    /// # Arguments
+    /// * `operations` - 写操作列表
+    /// * `ordered` - 是否有序执行
+    ///
+    /// # Returns
+    /// 每个操作的结果和 `write_errors`
+    pub fn bulk_write(&self, operations: Vec<WriteOperation>, ordered: bool) -> MikuResult<BulkWriteResult> {
+        self.storage
+            .bulk_write(self.inner.name(), operations, ordered)
+            .map_err(MikuError::from)
     }
 
     pub fn count(&self) -> MikuResult<u64> {
         self.inner
             .count()
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 
     pub fn clear(&self) -> MikuResult<u64> {
         self.inner
             .clear()
-            .map_err(|e| MikuError::Storage(e.to_string()))
+            .map_err(MikuError::from)
     }
 }
 
@@ -431,4 +467,448 @@ mod tests {
         assert!(collection.delete(&id).unwrap());
         assert!(collection.find_one(&id).unwrap().is_none());
     }
+
+    #[test]
+    fn test_find_and_modify_claims_each_document_once() {
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        for n in 0..20 {
+            db.execute(&format!("INSERT INTO jobs {{n: {}, state: 'queued'}}", n)).unwrap();
+        }
+
+        let claimed: Vec<i64> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut claimed = Vec::new();
+                        loop {
+                            let query = "FIND AND MODIFY jobs SET state = 'running' WHERE state = 'queued' RETURNING OLD *";
+                            match db.execute(query).unwrap() {
+                                QueryResponse::Returning { documents, .. } if documents.is_empty() => break claimed,
+                                QueryResponse::Returning { documents, .. } => {
+                                    assert_eq!(documents[0].get_str("state"), Some("queued"));
+                                    claimed.push(documents[0].get("n").and_then(|v| v.as_i64()).unwrap());
+                                }
+                                other => panic!("unexpected response: {:?}", other),
+                            }
+                        }
+                    })
+                })
+                .collect();
+            workers.into_iter().flat_map(|w| w.join().unwrap()).collect()
+        });
+
+        let mut sorted = claimed.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_custom_functions() {
+        use crate::boml::BomlValue;
+        use crate::query::QueryError;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        for (city, qty) in [("a", 2), ("a", 7), ("b", 4)] {
+            db.execute(&format!("INSERT INTO items {{city: '{}', qty: {}}}", city, qty)).unwrap();
+        }
+
+        let functions = db.functions();
+        functions
+            .register_scalar("double", |args| match args {
+                [BomlValue::Int32(n)] => Ok(BomlValue::Int32(n * 2)),
+                _ => Err(QueryError::TypeError("double requires an int".to_string())),
+            })
+            .unwrap();
+        functions
+            .register_accumulator("product", |values| {
+                Ok(BomlValue::Int64(values.iter().filter_map(|v| v.as_i64()).product()))
+            })
+            .unwrap();
+        assert!(functions.register_scalar("Upper", |_| Ok(BomlValue::Null)).is_err());
+        assert!(functions.register_accumulator("sum", |_| Ok(BomlValue::Null)).is_err());
+        assert!(functions.register_scalar("PRODUCT", |_| Ok(BomlValue::Null)).is_err());
+
+        match db.execute("FIND items WHERE DOUBLE(qty) > 6").unwrap() {
+            QueryResponse::Documents(docs) => assert_eq!(docs.len(), 2),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        match db.execute("AGGREGATE items | MATCH city = 'a' | GROUP BY city AS {p: product(qty)}").unwrap() {
+            QueryResponse::Documents(docs) => assert_eq!(docs[0].get("p"), Some(&BomlValue::Int64(14))),
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        // 过滤条件求值出错的文档视为不匹配
+        assert!(functions.unregister("double"));
+        match db.execute("FIND items WHERE double(qty) > 6").unwrap() {
+            QueryResponse::Documents(docs) => assert!(docs.is_empty()),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_array_updates() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        db.execute("INSERT INTO orders {n: 1, items: [{sku: 'a', qty: 1}, {sku: 'b', qty: 2}], tags: ['x'], queue: [1, 2, 3]}")
+            .unwrap();
+        let order = || match db.execute("FIND orders WHERE n = 1").unwrap() {
+            QueryResponse::Documents(mut docs) => docs.remove(0),
+            other => panic!("unexpected response: {:?}", other),
+        };
+        let qty = |doc: &crate::boml::Document, path: &str| doc.get_path(path).and_then(BomlValue::as_i64);
+
+        db.execute("UPDATE orders SET items.$.qty = 5 WHERE items.sku = 'b'").unwrap();
+        db.execute("UPDATE orders SET items.0.qty = 11, items.1.sku = 'c' WHERE n = 1").unwrap();
+        let doc = order();
+        assert_eq!(qty(&doc, "items.1.qty"), Some(5));
+        assert_eq!(qty(&doc, "items.0.qty"), Some(11));
+        assert_eq!(doc.get_path("items.1.sku").and_then(BomlValue::as_str), Some("c"));
+
+        db.execute("UPDATE orders ADDTOSET tags = 'x', tags = 'y' POP FIRST queue PULLALL queue = [3] WHERE n = 1")
+            .unwrap();
+        let doc = order();
+        assert_eq!(doc.get_array("tags").map(Vec::len), Some(2));
+        assert_eq!(doc.get_array("queue"), Some(&vec![BomlValue::Int32(2)]));
+
+        // 没有元素匹配 WHERE 条件时 `$` 无法确定位置
+        assert!(db.execute("UPDATE orders SET items.$.qty = 0 WHERE n = 1").is_err());
+    }
+
+    #[test]
+    fn test_nested_path_updates() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        db.execute("INSERT INTO orders {n: 1, items: [{sku: 'a', qty: 1}, {sku: 'b', qty: 2}], nick: 'm'}").unwrap();
+        let order = || match db.execute("FIND orders WHERE n = 1").unwrap() {
+            QueryResponse::Documents(mut docs) => docs.remove(0),
+            other => panic!("unexpected response: {:?}", other),
+        };
+
+        db.execute("UPDATE orders SET items.0.qty += 10, items.3.sku = 'd', meta.source.kind = 'web' WHERE n = 1")
+            .unwrap();
+        db.execute("UPDATE orders RENAME nick TO profile.nickname WHERE n = 1").unwrap();
+        let doc = order();
+        assert_eq!(doc.get_path("items.0.qty").and_then(BomlValue::as_i64), Some(11));
+        assert_eq!(doc.get_path("items.2"), Some(&BomlValue::Null));
+        assert_eq!(doc.get_path("items.3.sku").and_then(BomlValue::as_str), Some("d"));
+        assert_eq!(doc.get_path("meta.source.kind").and_then(BomlValue::as_str), Some("web"));
+        assert_eq!(doc.get_path("profile.nickname").and_then(BomlValue::as_str), Some("m"));
+        assert!(!doc.contains_key("meta.source.kind"));
+
+        db.execute("UPDATE orders UNSET meta.source.kind WHERE n = 1").unwrap();
+        assert!(order().get_path("meta.source.kind").is_none());
+
+        // 路径经过标量值
+        assert!(db.execute("UPDATE orders SET n.x = 1 WHERE n = 1").is_err());
+    }
+
+    #[test]
+    fn test_increment_overflow() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        db.execute("INSERT INTO counters {n: 1, hits: 9223372036854775806}").unwrap();
+        let hits = || match db.execute("FIND counters WHERE n = 1").unwrap() {
+            QueryResponse::Documents(docs) => docs[0].get("hits").cloned(),
+            other => panic!("unexpected response: {:?}", other),
+        };
+
+        db.execute("UPDATE counters SET hits += 1 WHERE n = 1").unwrap();
+        assert_eq!(hits(), Some(BomlValue::Int64(i64::MAX)));
+
+        // 溢出时整条更新失败,字段保持原值
+        let err = db.execute("UPDATE counters SET hits += 1 WHERE n = 1").unwrap_err();
+        assert!(matches!(err, MikuError::Validation(_)));
+        assert_eq!(hits(), Some(BomlValue::Int64(i64::MAX)));
+    }
+
+    #[test]
+    fn test_index_key_size() {
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        db.execute("CREATE INDEX idx_bio ON users (bio)").unwrap();
+        db.execute("CREATE INDEX idx_note ON users (note) WITH MAX_KEY_SIZE 64 OVERSIZED TRUNCATE").unwrap();
+        let long = "x".repeat(2000);
+
+        // 默认上限 1024 字节,超长的键拒绝写入
+        let err = db.execute(&format!("INSERT INTO users {{bio: '{}'}}", long)).unwrap_err();
+        assert!(matches!(err, MikuError::Validation(_)));
+        assert_eq!(db.collection("users").unwrap().count().unwrap(), 0);
+
+        db.execute(&format!("INSERT INTO users {{name: 'miku', note: '{}'}}", long)).unwrap();
+        db.execute(&format!("INSERT INTO users {{name: 'rin', note: '{}y'}}", long)).unwrap();
+        match db.execute(&format!("FIND users WHERE note = '{}'", long)).unwrap() {
+            QueryResponse::Documents(docs) => assert_eq!(docs.len(), 1),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        match db.execute(&format!("FIND users WHERE note > '{}'", long)).unwrap() {
+            QueryResponse::Documents(docs) => assert_eq!(docs.len(), 1),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_nested_paths_in_output() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        db.execute("INSERT INTO users {name: 'miku', address: {city: 'Sapporo', zip: '060'}, items: [{sku: 'a'}]}")
+            .unwrap();
+        db.execute("INSERT INTO users {name: 'rin', address: {city: 'Sapporo', zip: '061'}, items: []}")
+            .unwrap();
+
+        match db.execute("FIND users WHERE name = 'miku' SELECT address.city, items.0.sku").unwrap() {
+            QueryResponse::Documents(docs) => {
+                assert_eq!(docs[0].get_path("address.city").and_then(BomlValue::as_str), Some("Sapporo"));
+                assert!(docs[0].get_path("address.zip").is_none());
+                assert_eq!(docs[0].get_path("items.0.sku").and_then(BomlValue::as_str), Some("a"));
+                assert!(!docs[0].contains_key("address.city"));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+        match db.execute("AGGREGATE users | GROUP BY address.city AS {n: count()} | SORT _id.address.city").unwrap() {
+            QueryResponse::Documents(docs) => {
+                assert_eq!(docs.len(), 1);
+                assert_eq!(docs[0].get_path("_id.address.city").and_then(BomlValue::as_str), Some("Sapporo"));
+                assert_eq!(docs[0].get("n").and_then(BomlValue::as_i64), Some(2));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_graph_lookup() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        for stmt in [
+            "INSERT INTO categories {id: 1, name: 'root'}",
+            "INSERT INTO categories {id: 2, parent: 1}",
+            "INSERT INTO categories {id: 3, parent: 2}",
+            "INSERT INTO categories {id: 4, parent: 3}",
+            "INSERT INTO categories {id: 5, parent: 6}",
+            "INSERT INTO categories {id: 6, parent: 5}",
+        ] {
+            db.execute(stmt).unwrap();
+        }
+        let chain = |query: &str, field: &str| match db.execute(query).unwrap() {
+            QueryResponse::Documents(docs) => docs[0]
+                .get_array(field)
+                .unwrap()
+                .iter()
+                .map(|v| v.as_document().unwrap().get("id").and_then(BomlValue::as_i64).unwrap())
+                .collect::<Vec<_>>(),
+            other => panic!("unexpected response: {:?}", other),
+        };
+
+        let ancestors = "AGGREGATE categories | MATCH id = 4 \
+            | GRAPH LOOKUP categories START WITH parent CONNECT FROM parent TO id AS ancestors";
+        assert_eq!(chain(ancestors, "ancestors"), vec![3, 2, 1]);
+        assert_eq!(chain(&format!("{} MAX DEPTH 1", ancestors), "ancestors"), vec![3, 2]);
+
+        let descendants = "AGGREGATE categories | MATCH id = 1 \
+            | GRAPH LOOKUP categories START WITH id CONNECT FROM id TO parent AS children DEPTH FIELD level";
+        assert_eq!(chain(descendants, "children"), vec![2, 3, 4]);
+        match db.execute(descendants).unwrap() {
+            QueryResponse::Documents(docs) => {
+                assert_eq!(docs[0].get_path("children.2.level"), Some(&BomlValue::Int64(2)));
+            }
+            other => panic!("unexpected response: {:?}", other),
+        }
+
+        // 环形引用只展开到重复出现的文档为止
+        let cycle = "AGGREGATE categories | MATCH id = 5 \
+            | GRAPH LOOKUP categories START WITH parent CONNECT FROM parent TO id AS ancestors";
+        assert_eq!(chain(cycle, "ancestors"), vec![6, 5]);
+    }
+
+    #[test]
+    fn test_facet() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        for (category, score, stock) in [("book", 5, 1), ("book", 9, 3), ("pen", 7, 2), ("pen", 1, 0), ("cup", 3, 4)] {
+            db.execute(&format!("INSERT INTO products {{category: '{}', score: {}, stock: {}}}", category, score, stock))
+                .unwrap();
+        }
+
+        let query = "AGGREGATE products | MATCH stock > 0 \
+            | FACET by_category (GROUP BY category AS {n: COUNT()} | SORT n DESC | LIMIT 1), \
+                    top (SORT score DESC | LIMIT 2 | PROJECT score), \
+                    cheap (MATCH score < 6 | SORT score | PROJECT score)";
+        let docs = match db.execute(query).unwrap() {
+            QueryResponse::Documents(docs) => docs,
+            other => panic!("unexpected response: {:?}", other),
+        };
+        assert_eq!(docs.len(), 1);
+        let result = &docs[0];
+        assert_eq!(result.get_path("by_category.0._id.category"), Some(&BomlValue::String("book".into())));
+        assert_eq!(result.get_path("by_category.0.n"), Some(&BomlValue::Int64(2)));
+        let top: Vec<_> = result
+            .get_array("top")
+            .unwrap()
+            .iter()
+            .map(|v| v.as_document().unwrap().get("score").and_then(BomlValue::as_i64).unwrap())
+            .collect();
+        assert_eq!(top, vec![9, 7]);
+        assert_eq!(result.get_path("cheap.0.score"), Some(&BomlValue::Int32(3)));
+        assert_eq!(result.get_array("cheap").map(|a| a.len()), Some(2));
+
+        // 后续阶段作用于 FACET 输出的单个文档
+        match db.execute("AGGREGATE products | FACET none (MATCH stock > 100) | PROJECT none").unwrap() {
+            QueryResponse::Documents(docs) => assert_eq!(docs[0].get_array("none").map(|a| a.len()), Some(0)),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bucket() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        for price in ["1", "5", "12", "30", "99.5", "150", "'n/a'"] {
+            db.execute(&format!("INSERT INTO products {{price: {}}}", price)).unwrap();
+        }
+        let run = |query: &str| match db.execute(query).unwrap() {
+            QueryResponse::Documents(docs) => docs,
+            other => panic!("unexpected response: {:?}", other),
+        };
+
+        let docs = run("AGGREGATE products | BUCKET price BOUNDARIES [0, 10, 100] DEFAULT 'other' AS {n: COUNT(), total: SUM(price)}");
+        let buckets: Vec<_> = docs.iter().map(|doc| (doc.get("_id").cloned(), doc.get("n").cloned())).collect();
+        assert_eq!(buckets, vec![
+            (Some(BomlValue::Int64(0)), Some(BomlValue::Int64(2))),
+            (Some(BomlValue::Int64(10)), Some(BomlValue::Int64(3))),
+            (Some(BomlValue::String("other".into())), Some(BomlValue::Int64(2))),
+        ]);
+        assert_eq!(docs[1].get("total"), Some(&BomlValue::Float64(141.5)));
+
+        // 没有 DEFAULT 时,落在边界外的文档使整个查询失败
+        assert!(db.execute("AGGREGATE products | BUCKET price BOUNDARIES [0, 10, 100]").is_err());
+        assert!(db.execute("AGGREGATE products | BUCKET price BOUNDARIES [10, 0] DEFAULT 'x'").is_err());
+
+        let docs = run("AGGREGATE products | MATCH price >= 0 | BUCKET AUTO price BUCKETS 2");
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].get_path("_id.min"), Some(&BomlValue::Int32(1)));
+        assert_eq!(docs[0].get_path("_id.max"), Some(&BomlValue::Int32(30)));
+        assert_eq!(docs[1].get_path("_id.max"), Some(&BomlValue::Int32(150)));
+        assert_eq!(docs[1].get("count"), Some(&BomlValue::Int64(3)));
+    }
+
+    #[test]
+    fn test_rollup_maintained_on_write() {
+        use crate::boml::BomlValue;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        db.execute("INSERT INTO sales {product: 'book', amount: 10}").unwrap();
+        db.execute("CREATE ROLLUP by_product ON sales GROUP BY product AS {total: SUM(amount), low: MIN(amount)}")
+            .unwrap();
+        db.execute("INSERT INTO sales {product: 'book', amount: 5}").unwrap();
+        db.execute("INSERT INTO sales {product: 'pen', amount: 2}").unwrap();
+
+        let group = |product: &str| match db.execute(&format!("FIND by_product WHERE product = '{}'", product)).unwrap() {
+            QueryResponse::Documents(docs) => docs.into_iter().next(),
+            other => panic!("unexpected response: {:?}", other),
+        };
+        let book = group("book").unwrap();
+        assert_eq!(book.get("total").and_then(BomlValue::as_f64), Some(15.0));
+        assert_eq!(book.get("low").and_then(BomlValue::as_i64), Some(5));
+        assert_eq!(book.get("_count").and_then(BomlValue::as_i64), Some(2));
+
+        // 更新移走了分组的最小值,分组从源集合重新计算
+        db.execute("UPDATE sales SET amount = 20 WHERE amount = 5").unwrap();
+        let book = group("book").unwrap();
+        assert_eq!(book.get("total").and_then(BomlValue::as_f64), Some(30.0));
+        assert_eq!(book.get("low").and_then(BomlValue::as_i64), Some(10));
+
+        db.execute("DELETE FROM sales WHERE product = 'pen'").unwrap();
+        assert!(group("pen").is_none());
+
+        db.execute("DROP ROLLUP by_product").unwrap();
+        assert!(!db.list_collections().unwrap().contains(&"by_product".to_string()));
+    }
+
+    #[test]
+    fn test_bulk_write() {
+        use crate::storage::WriteOperation;
+
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+        db.execute("CREATE UNIQUE INDEX idx_email ON users (email)").unwrap();
+        let users = db.collection("users").unwrap();
+        let user = |email: &str| {
+            let mut doc = crate::boml::Document::new();
+            doc.insert("email", email);
+            doc
+        };
+
+        let operations = vec![
+            WriteOperation::Insert(user("a")),
+            WriteOperation::Insert(user("a")),
+            WriteOperation::Insert(user("b")),
+        ];
+        let result = users.bulk_write(operations.clone(), false).unwrap();
+        assert_eq!(result.inserted, 2);
+        assert_eq!(result.write_errors.len(), 1);
+        assert_eq!(result.write_errors[0].index, 1);
+
+        let id = *result.results[0].ids.first().unwrap();
+        let result = users
+            .bulk_write(vec![WriteOperation::Delete(id), WriteOperation::Insert(user("a"))], true)
+            .unwrap();
+        assert!(result.is_ok());
+        assert_eq!(users.count().unwrap(), 2);
+        match db.execute("FIND users WHERE email = 'a'").unwrap() {
+            QueryResponse::Documents(docs) => assert_eq!(docs.len(), 1),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_classification() {
+        let dir = tempdir().unwrap();
+        let db = Database::open("test", dir.path()).unwrap();
+
+        let syntax = db.execute("FIND").unwrap_err();
+        assert!(matches!(syntax, MikuError::Query(_)));
+        assert!(!syntax.is_retryable());
+
+        let conflict = MikuError::from(mikudb_storage::StorageError::WriteConflict);
+        assert!(conflict.is_retryable());
+        assert!(!conflict.is_transient());
+        assert!(MikuError::NotPrimary("node-2".to_string()).is_transient());
+
+        let sessions = crate::transaction::SessionManager::new(db.storage().clone());
+        let session = sessions.create_session();
+        let mut attempts = 0;
+        let result = session.with_transaction_retry(3, |_| {
+            attempts += 1;
+            if attempts < 2 {
+                Err(MikuError::WriteConflict("users".to_string()))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+
+        attempts = 0;
+        let result: MikuResult<()> = session.with_transaction_retry(3, |_| {
+            attempts += 1;
+            Err(MikuError::Validation("age".to_string()))
+        });
+        assert!(matches!(result, Err(MikuError::Validation(_))));
+        assert_eq!(attempts, 1);
+    }
 }
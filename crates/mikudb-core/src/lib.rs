@@ -8,6 +8,7 @@
 //! - **Pipeline**: 聚合管道构建器
 //! - **Connection**: 连接字符串解析和选项
 //! - **Builder**: 流式构建器模式
+//! - **CacheLoader**: 通过变更流保持更新的进程内集合缓存
 //!
 //! # 快速开始
 //!
@@ -28,6 +29,7 @@ pub mod builder;
 pub mod connection;
 pub mod cursor;
 pub mod pipeline;
+pub mod cache_loader;
 
 pub use mikudb_boml as boml;
 pub use mikudb_common as common;
@@ -35,15 +37,19 @@ pub use mikudb_query as query;
 pub use mikudb_storage as storage;
 
 pub use builder::{DatabaseBuilder, StorageOptionsBuilder};
+pub use cache_loader::{CacheLoader, CachedCollection};
 pub use client::{AsyncCollection, AsyncDatabase, Client, ClientOptions};
 pub use connection::{
     AuthMechanism, ConnectionMode, ConnectionOptions,
     ConnectionString, Credentials, Host, ReadConcern,
     ReadPreference, TlsOptions, WriteConcern,
 };
-pub use cursor::{Cursor, CursorBuilder, CursorInfo, CursorIterator, CursorManager, CursorOptions};
+pub use cursor::{
+    Cursor, CursorBuilder, CursorInfo, CursorIterator, CursorManager, CursorOptions,
+    MAX_ADAPTIVE_BATCH_SIZE,
+};
 pub use database::{Collection, Database, DatabaseStats};
-pub use pipeline::{GroupBuilder, LookupBuilder, MatchBuilder, Pipeline, ProjectBuilder, SortBuilder};
+pub use pipeline::{FacetBuilder, GraphLookupBuilder, GroupBuilder, LookupBuilder, MatchBuilder, Pipeline, ProjectBuilder, SortBuilder};
 pub use transaction::{
     IsolationLevel, Session, SessionManager, Transaction,
     TransactionOptions, TransactionState,
@@ -51,8 +57,8 @@ pub use transaction::{
 
 pub use boml::{BomlValue, Document};
 pub use common::{MikuError, MikuResult, ObjectId};
-pub use query::{Parser, QueryExecutor, QueryResponse, Statement};
-pub use storage::{StorageEngine, StorageOptions};
+pub use query::{FunctionRegistry, Parser, QueryExecutor, QueryResponse, Statement};
+pub use storage::{OpenMode, StorageEngine, StorageOptions};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const DEFAULT_PORT: u16 = 3939;
@@ -18,8 +18,8 @@
 
 use crate::boml::BomlValue;
 use crate::query::{
-    Accumulator, AggregateFunction, AggregateStage, Expression, ProjectField, SortField,
-    SortOrder,
+    Accumulator, AggregateFunction, AggregateStage, Bucket, BucketAuto, Expression, Facet, GraphLookup,
+    ProjectField, SortField, SortOrder,
 };
 
 #[derive(Debug, Clone, Default)]
@@ -132,6 +132,48 @@ impl Pipeline {
         self.add_lookup(builder.build())
     }
 
+    pub fn graph_lookup<F>(self, f: F) -> Self
+    where
+        F: FnOnce(GraphLookupBuilder) -> GraphLookupBuilder,
+    {
+        let builder = f(GraphLookupBuilder::new());
+        self.add_lookup(builder.build())
+    }
+
+    pub fn facet<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(FacetBuilder) -> FacetBuilder,
+    {
+        let builder = f(FacetBuilder::new());
+        self.stages.push(builder.build());
+        self
+    }
+
+    pub fn bucket(
+        mut self,
+        group_by: impl Into<String>,
+        boundaries: Vec<BomlValue>,
+        default: Option<BomlValue>,
+        output: Vec<Accumulator>,
+    ) -> Self {
+        self.stages.push(AggregateStage::Bucket(Bucket {
+            group_by: group_by.into(),
+            boundaries,
+            default,
+            output,
+        }));
+        self
+    }
+
+    pub fn bucket_auto(mut self, group_by: impl Into<String>, buckets: u32, output: Vec<Accumulator>) -> Self {
+        self.stages.push(AggregateStage::BucketAuto(BucketAuto {
+            group_by: group_by.into(),
+            buckets,
+            output,
+        }));
+        self
+    }
+
     fn add_lookup(mut self, lookup: AggregateStage) -> Self {
         self.stages.push(lookup);
         self
@@ -567,6 +609,75 @@ impl Default for LookupBuilder {
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct GraphLookupBuilder {
+    stage: GraphLookup,
+}
+
+impl GraphLookupBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from(mut self, collection: impl Into<String>) -> Self {
+        self.stage.from = collection.into();
+        self
+    }
+
+    pub fn start_with(mut self, field: impl Into<String>) -> Self {
+        self.stage.start_with = field.into();
+        self
+    }
+
+    pub fn connect(mut self, from_field: impl Into<String>, to_field: impl Into<String>) -> Self {
+        self.stage.connect_from = from_field.into();
+        self.stage.connect_to = to_field.into();
+        self
+    }
+
+    pub fn as_field(mut self, name: impl Into<String>) -> Self {
+        self.stage.as_field = name.into();
+        self
+    }
+
+    pub fn max_depth(mut self, depth: u32) -> Self {
+        self.stage.max_depth = Some(depth);
+        self
+    }
+
+    pub fn depth_field(mut self, name: impl Into<String>) -> Self {
+        self.stage.depth_field = Some(name.into());
+        self
+    }
+
+    pub fn build(self) -> AggregateStage {
+        AggregateStage::GraphLookup(self.stage)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FacetBuilder {
+    facets: Vec<Facet>,
+}
+
+impl FacetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pipeline(mut self, name: impl Into<String>, pipeline: Pipeline) -> Self {
+        self.facets.push(Facet {
+            name: name.into(),
+            pipeline: pipeline.into_stages(),
+        });
+        self
+    }
+
+    pub fn build(self) -> AggregateStage {
+        AggregateStage::Facet(self.facets)
+    }
+}
+
 pub fn field(name: impl Into<String>) -> Expression {
     Expression::Field(name.into())
 }
@@ -628,6 +739,64 @@ mod tests {
         assert_eq!(pipeline.stages().len(), 1);
     }
 
+    #[test]
+    fn test_pipeline_graph_lookup() {
+        let pipeline = Pipeline::new()
+            .graph_lookup(|g| {
+                g.from("employees")
+                    .start_with("manager_id")
+                    .connect("manager_id", "_id")
+                    .as_field("chain")
+                    .max_depth(3)
+            });
+
+        match &pipeline.stages()[0] {
+            AggregateStage::GraphLookup(graph) => {
+                assert_eq!(graph.connect_to, "_id");
+                assert_eq!(graph.max_depth, Some(3));
+                assert!(graph.depth_field.is_none());
+            }
+            other => panic!("unexpected stage: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pipeline_facet() {
+        let pipeline = Pipeline::new()
+            .match_expr(|m| m.field("stock").gt(0))
+            .facet(|f| {
+                f.pipeline("by_category", Pipeline::new().group_by(|g| g.by("category").count("n")))
+                    .pipeline("top", Pipeline::new().sort_by(|s| s.desc("score")).limit(10))
+            });
+
+        match &pipeline.stages()[1] {
+            AggregateStage::Facet(facets) => {
+                assert_eq!(facets.len(), 2);
+                assert_eq!(facets[0].name, "by_category");
+                assert_eq!(facets[1].pipeline.len(), 2);
+            }
+            other => panic!("unexpected stage: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pipeline_bucket() {
+        let (_, output) = GroupBuilder::new().count("n").avg("price", "avg_price").build();
+        let pipeline = Pipeline::new()
+            .bucket("price", vec![0.into(), 10.into(), 100.into()], Some("other".into()), output)
+            .bucket_auto("score", 4, Vec::new());
+
+        match &pipeline.stages()[0] {
+            AggregateStage::Bucket(bucket) => {
+                assert_eq!(bucket.boundaries.len(), 3);
+                assert_eq!(bucket.default, Some(BomlValue::from("other")));
+                assert_eq!(bucket.output.len(), 2);
+            }
+            other => panic!("unexpected stage: {:?}", other),
+        }
+        assert!(matches!(&pipeline.stages()[1], AggregateStage::BucketAuto(b) if b.buckets == 4));
+    }
+
     #[test]
     fn test_complex_pipeline() {
         let pipeline = Pipeline::new()
@@ -2,6 +2,14 @@
 //!
 //! 提供 ACID 事务支持，包括多文档事务、会话管理和隔离级别控制。
 //!
+//! 事务采用乐观并发控制: 读取时记录文档的版本(变更流令牌),写入缓存在事务内,
+//! 提交时检查读写过的文档是否被其他写入修改过,有冲突时返回可重试的写冲突错误。
+//! 隔离级别决定检查哪些文档、从哪个版本开始检查,见 [`IsolationLevel`]。
+//! Snapshot 和 Serializable 事务持有存储的读快照,读取的是事务开始时的文档版本,
+//! 并发写入产生的新版本对事务不可见,见 `mikudb_storage::mvcc`。
+//! 会话在 BEGIN 之后执行的 INSERT / UPDATE / DELETE 暂存在事务中,提交时才写入存储(包括索引和预聚合);
+//! 同一事务中的 FIND 和 AGGREGATE 可见这些尚未提交的写入。
+//!
 //! # 示例
 //!
 //! ```rust,ignore
@@ -17,10 +25,11 @@
 
 use crate::boml::Document;
 use crate::common::{MikuError, MikuResult, ObjectId};
-use crate::query::{Parser, QueryResponse, Statement};
-use crate::storage::StorageEngine;
+use crate::query::{Parser, QueryError, QueryResponse, QueryResult, Statement, TransactionContext};
+use crate::storage::{ReadSnapshot, StorageEngine, StorageError};
+use dashmap::DashMap;
 use parking_lot::{Mutex, RwLock};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -29,12 +38,23 @@ use tracing::{debug, info, warn};
 static TRANSACTION_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
 static SESSION_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
 
+/// 提交时的冲突检查和写入在所有事务间依次执行
+static COMMIT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 事务隔离级别
+///
+/// 决定提交时检查哪些文档是否被其他写入修改过:
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IsolationLevel {
+    /// 不做冲突检查,后提交的写入覆盖先提交的
     ReadUncommitted,
+    /// 写入的文档从事务读取它之后没有被修改(防止丢失更新)
     ReadCommitted,
+    /// 读取和写入的文档从事务第一次读取之后没有被修改
     RepeatableRead,
+    /// 读取事务开始时的文档版本;读取和写入的文档从事务开始之后没有被修改
     Snapshot,
+    /// 在 Snapshot 的基础上,遍历过的集合从事务开始之后没有任何写入(包括插入)
     Serializable,
 }
 
@@ -81,9 +101,11 @@ struct WriteOperation {
     operation: WriteOpType,
     old_value: Option<Document>,
     new_value: Option<Document>,
+    /// 读取旧文档前的变更流令牌
+    version: u64,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum WriteOpType {
     Insert,
     Update,
@@ -98,8 +120,25 @@ pub struct Transaction {
     start_time: Instant,
     storage: Arc<StorageEngine>,
     write_set: Mutex<Vec<WriteOperation>>,
-    read_set: Mutex<HashMap<String, Vec<ObjectId>>>,
+    read_set: Mutex<ReadSet>,
     snapshot_version: u64,
+    /// Snapshot 和 Serializable 事务的读快照,提交或中止时释放
+    snapshot: Mutex<Option<ReadSnapshot>>,
+}
+
+#[derive(Debug, Default)]
+struct ReadSet {
+    /// 读取过的文档及第一次读取前的变更流令牌
+    documents: HashMap<(String, ObjectId), u64>,
+    /// 遍历过的集合
+    collections: HashSet<String>,
+}
+
+impl ReadSet {
+    fn clear(&mut self) {
+        self.documents.clear();
+        self.collections.clear();
+    }
 }
 
 impl Transaction {
@@ -110,6 +149,10 @@ impl Transaction {
     ) -> Self {
         let id = TRANSACTION_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
         debug!("Creating transaction {} for session {}", id, session_id);
+        let snapshot_version = storage.change_stream().current_token();
+        // 先取变更流令牌再开始读快照,两者之间的写入在提交检查时按冲突处理
+        let snapshot = matches!(options.isolation_level, IsolationLevel::Snapshot | IsolationLevel::Serializable)
+            .then(|| storage.begin_snapshot());
 
         Self {
             id,
@@ -119,8 +162,9 @@ impl Transaction {
             start_time: Instant::now(),
             storage,
             write_set: Mutex::new(Vec::new()),
-            read_set: Mutex::new(HashMap::new()),
-            snapshot_version: id,
+            read_set: Mutex::new(ReadSet::default()),
+            snapshot_version,
+            snapshot: Mutex::new(snapshot),
         }
     }
 
@@ -143,6 +187,10 @@ impl Transaction {
         self.start_time.elapsed() > self.options.timeout
     }
 
+    pub fn isolation_level(&self) -> IsolationLevel {
+        self.options.isolation_level
+    }
+
     pub fn start(&self) -> MikuResult<()> {
         let mut state = self.state.write();
         if *state != TransactionState::None {
@@ -168,12 +216,25 @@ impl Transaction {
 
         if self.is_timed_out() {
             *state = TransactionState::Aborted;
-            return Err(MikuError::Transaction("Transaction timed out".to_string()));
+            return Err(MikuError::Timeout("Transaction timed out".to_string()));
         }
 
         *state = TransactionState::Committing;
         debug!("Committing transaction {}", self.id);
 
+        let _commit = COMMIT_LOCK.lock();
+        let validated = self.validate();
+        // 检查完成后不再读取快照,提前释放,避免提交的写入也保存旧版本
+        self.snapshot.lock().take();
+        if let Err(e) = validated {
+            self.write_set.lock().clear();
+            self.read_set.lock().clear();
+            *state = TransactionState::Aborted;
+            return Err(e);
+        }
+
+        // 与直接写入一样维护索引项和预聚合,旧文档取自事务内读取或前一个写入的结果
+        let indexes = self.storage.indexes();
         let write_set = self.write_set.lock();
         for op in write_set.iter() {
             match op.operation {
@@ -182,12 +243,14 @@ impl Transaction {
                         let collection = self
                             .storage
                             .get_or_create_collection(&op.collection)
-                            .map_err(|e| MikuError::Storage(e.to_string()))?;
+                            .map_err(MikuError::from)?;
 
                         let mut doc_clone = doc.clone();
+                        indexes.index_document(&op.collection, &doc_clone)?;
                         collection
                             .insert(&mut doc_clone)
-                            .map_err(|e| MikuError::Storage(e.to_string()))?;
+                            .map_err(MikuError::from)?;
+                        self.storage.apply_rollups(&op.collection, None, Some(&doc_clone))?;
                     }
                 }
                 WriteOpType::Update => {
@@ -195,22 +258,31 @@ impl Transaction {
                         let collection = self
                             .storage
                             .get_collection(&op.collection)
-                            .map_err(|e| MikuError::Storage(e.to_string()))?;
+                            .map_err(MikuError::from)?;
 
+                        if let Some(ref old) = op.old_value {
+                            indexes.unindex_document(&op.collection, old)?;
+                        }
+                        indexes.index_document(&op.collection, doc)?;
                         collection
                             .update(&op.document_id, doc)
-                            .map_err(|e| MikuError::Storage(e.to_string()))?;
+                            .map_err(MikuError::from)?;
+                        self.storage.apply_rollups(&op.collection, op.old_value.as_ref(), Some(doc))?;
                     }
                 }
                 WriteOpType::Delete => {
                     let collection = self
                         .storage
                         .get_collection(&op.collection)
-                        .map_err(|e| MikuError::Storage(e.to_string()))?;
+                        .map_err(MikuError::from)?;
 
                     collection
                         .delete(&op.document_id)
-                        .map_err(|e| MikuError::Storage(e.to_string()))?;
+                        .map_err(MikuError::from)?;
+                    if let Some(ref old) = op.old_value {
+                        indexes.unindex_document(&op.collection, old)?;
+                        self.storage.apply_rollups(&op.collection, Some(old), None)?;
+                    }
                 }
             }
         }
@@ -237,6 +309,7 @@ impl Transaction {
 
         self.write_set.lock().clear();
         self.read_set.lock().clear();
+        self.snapshot.lock().take();
 
         *state = TransactionState::Aborted;
         info!("Transaction {} aborted", self.id);
@@ -248,6 +321,193 @@ impl Transaction {
         self.abort()
     }
 
+    /// # Brief
+    /// 在事务中读取文档
+    ///
+    /// 先返回事务内尚未提交的写入,否则从存储读取并记录文档的版本,提交时据此检查冲突。
+    /// 持有读快照时读取事务开始时的文档。
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `id` - 文档 ID
+    ///
+    /// # Returns
+    /// 文档不存在或已在事务内删除时返回 None
+    pub fn get(&self, collection: &str, id: &ObjectId) -> MikuResult<Option<Document>> {
+        if let Some(op) = self
+            .write_set
+            .lock()
+            .iter()
+            .rev()
+            .find(|op| op.collection == collection && op.document_id == *id)
+        {
+            return Ok(op.new_value.clone());
+        }
+
+        let version = self.storage.change_stream().current_token();
+        let doc = self.read_committed(collection, id)?;
+        self.track_read(collection, *id, version);
+        Ok(doc)
+    }
+
+    /// # Brief
+    /// 在事务中读取集合的全部文档
+    ///
+    /// 记录每个文档的版本,并记录遍历过该集合(Serializable 级别下集合的任何写入都算冲突)。
+    /// 持有读快照时返回事务开始时的文档。结果叠加了事务内尚未提交的写入:
+    /// 更新的文档替换原位置的文档,删除的文档被移除,插入的文档追加在末尾。
+    pub fn find_all(&self, collection: &str) -> MikuResult<Vec<Document>> {
+        let version = self.storage.change_stream().current_token();
+        let mut docs = match self.storage.get_collection(collection) {
+            Ok(coll) => match self.snapshot.lock().as_ref() {
+                Some(snapshot) => coll.find_all_as_of(snapshot).map_err(MikuError::from)?,
+                None => coll.find_all().map_err(MikuError::from)?,
+            },
+            Err(StorageError::CollectionNotFound(_)) => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        {
+            let mut read_set = self.read_set.lock();
+            read_set.collections.insert(collection.to_string());
+            for id in docs.iter().filter_map(|doc| doc.id()) {
+                read_set.documents.entry((collection.to_string(), *id)).or_insert(version);
+            }
+        }
+
+        for op in self.write_set.lock().iter().filter(|op| op.collection == collection) {
+            let position = docs.iter().position(|doc| doc.id() == Some(&op.document_id));
+            match (position, &op.new_value) {
+                (Some(i), Some(doc)) => docs[i] = doc.clone(),
+                (Some(i), None) => {
+                    docs.remove(i);
+                }
+                (None, Some(doc)) => docs.push(doc.clone()),
+                (None, None) => {}
+            }
+        }
+        Ok(docs)
+    }
+
+    /// # Brief
+    /// 在事务中插入文档,提交时写入
+    ///
+    /// # Returns
+    /// 文档 ID(没有 `_id` 时生成)
+    pub fn insert(&self, collection: &str, mut doc: Document) -> MikuResult<ObjectId> {
+        let id = *doc.ensure_id();
+        self.add_insert(collection, id, doc)?;
+        Ok(id)
+    }
+
+    /// # Brief
+    /// 在事务中替换文档,提交时写入
+    ///
+    /// # Returns
+    /// 文档不存在时返回 false,不记录写入
+    pub fn update(&self, collection: &str, id: &ObjectId, doc: Document) -> MikuResult<bool> {
+        let Some(old_value) = self.get(collection, id)? else {
+            return Ok(false);
+        };
+        self.add_update(collection, *id, Some(old_value), doc)?;
+        Ok(true)
+    }
+
+    /// # Brief
+    /// 在事务中删除文档,提交时写入
+    ///
+    /// # Returns
+    /// 文档不存在时返回 false,不记录写入
+    pub fn delete(&self, collection: &str, id: &ObjectId) -> MikuResult<bool> {
+        let Some(old_value) = self.get(collection, id)? else {
+            return Ok(false);
+        };
+        self.add_delete(collection, *id, Some(old_value))?;
+        Ok(true)
+    }
+
+    fn read_committed(&self, collection: &str, id: &ObjectId) -> MikuResult<Option<Document>> {
+        match self.storage.get_collection(collection) {
+            Ok(coll) => match self.snapshot.lock().as_ref() {
+                Some(snapshot) => coll.get_as_of(id, snapshot).map_err(MikuError::from),
+                None => coll.get(id).map_err(MikuError::from),
+            },
+            Err(StorageError::CollectionNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// # Brief
+    /// 检查读写过的文档是否在记录的版本之后被修改
+    ///
+    /// 按隔离级别选出要检查的文档及起始版本,读取其后的变更事件;
+    /// 事件已被变更流淘汰时无法判断,同样视为冲突。
+    fn validate(&self) -> MikuResult<()> {
+        let level = self.options.isolation_level;
+        if level == IsolationLevel::ReadUncommitted {
+            return Ok(());
+        }
+        let from_start = matches!(level, IsolationLevel::Snapshot | IsolationLevel::Serializable);
+
+        let read_set = self.read_set.lock();
+        let write_set = self.write_set.lock();
+        let writes = write_set
+            .iter()
+            .filter(|op| op.operation != WriteOpType::Insert)
+            .map(|op| (op.collection.as_str(), op.document_id, op.version));
+        let reads = read_set
+            .documents
+            .iter()
+            .filter(|_| level != IsolationLevel::ReadCommitted)
+            .map(|((collection, id), version)| (collection.as_str(), *id, *version));
+        let mut watched: HashMap<(&str, ObjectId), u64> = HashMap::new();
+        for (collection, id, version) in writes.chain(reads) {
+            let version = if from_start { self.snapshot_version } else { version };
+            let entry = watched.entry((collection, id)).or_insert(version);
+            *entry = (*entry).min(version);
+        }
+        let unscanned = HashSet::new();
+        let scanned = match level {
+            IsolationLevel::Serializable => &read_set.collections,
+            _ => &unscanned,
+        };
+        let Some(oldest) = watched
+            .values()
+            .copied()
+            .chain((!scanned.is_empty()).then_some(self.snapshot_version))
+            .min()
+        else {
+            return Ok(());
+        };
+
+        let events = match self.storage.change_stream().changes_since(oldest, None, Duration::ZERO) {
+            Ok(events) => events,
+            Err(StorageError::ResumeTokenExpired(_)) => {
+                debug!("Transaction {} conflict: change history since {} expired", self.id, oldest);
+                return Err(StorageError::WriteConflict.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        for event in events {
+            let conflict = scanned.contains(&event.collection)
+                || match event.id {
+                    Some(id) => watched
+                        .get(&(event.collection.as_str(), id))
+                        .is_some_and(|version| event.token > *version),
+                    None => watched
+                        .iter()
+                        .any(|((collection, _), version)| *collection == event.collection && event.token > *version),
+                };
+            if conflict {
+                debug!(
+                    "Transaction {} conflict: {}/{:?} modified by another writer",
+                    self.id, event.collection, event.id
+                );
+                return Err(StorageError::WriteConflict.into());
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn add_insert(
         &self,
         collection: &str,
@@ -266,6 +526,7 @@ impl Transaction {
             operation: WriteOpType::Insert,
             old_value: None,
             new_value: Some(document),
+            version: self.storage.change_stream().current_token(),
         });
 
         Ok(())
@@ -290,6 +551,7 @@ impl Transaction {
             operation: WriteOpType::Update,
             old_value,
             new_value: Some(new_value),
+            version: self.read_version(collection, &document_id),
         });
 
         Ok(())
@@ -313,20 +575,54 @@ impl Transaction {
             operation: WriteOpType::Delete,
             old_value,
             new_value: None,
+            version: self.read_version(collection, &document_id),
         });
 
         Ok(())
     }
 
-    pub(crate) fn track_read(&self, collection: &str, document_id: ObjectId) {
+    pub(crate) fn track_read(&self, collection: &str, document_id: ObjectId, version: u64) {
         self.read_set
             .lock()
-            .entry(collection.to_string())
-            .or_default()
-            .push(document_id);
+            .documents
+            .entry((collection.to_string(), document_id))
+            .or_insert(version);
+    }
+
+    /// 写入的文档在事务内读取过时使用读取时的版本,否则使用当前版本
+    fn read_version(&self, collection: &str, document_id: &ObjectId) -> u64 {
+        self.read_set
+            .lock()
+            .documents
+            .get(&(collection.to_string(), *document_id))
+            .copied()
+            .unwrap_or_else(|| self.storage.change_stream().current_token())
     }
 }
 
+/// 供查询执行器在事务中读写文档
+impl TransactionContext for Transaction {
+    fn find_all(&self, collection: &str) -> QueryResult<Vec<Document>> {
+        Transaction::find_all(self, collection).map_err(query_error)
+    }
+
+    fn insert(&self, collection: &str, doc: Document) -> QueryResult<ObjectId> {
+        Transaction::insert(self, collection, doc).map_err(query_error)
+    }
+
+    fn update(&self, collection: &str, id: &ObjectId, doc: Document) -> QueryResult<bool> {
+        Transaction::update(self, collection, id, doc).map_err(query_error)
+    }
+
+    fn delete(&self, collection: &str, id: &ObjectId) -> QueryResult<bool> {
+        Transaction::delete(self, collection, id).map_err(query_error)
+    }
+}
+
+fn query_error(e: MikuError) -> QueryError {
+    QueryError::Execution(e.to_string())
+}
+
 impl Drop for Transaction {
     fn drop(&mut self) {
         let state = *self.state.read();
@@ -340,6 +636,8 @@ impl Drop for Transaction {
 pub struct Session {
     id: u64,
     storage: Arc<StorageEngine>,
+    /// USE 切换到的数据库,None 表示默认数据库
+    database: Mutex<Option<String>>,
     current_transaction: Mutex<Option<Arc<Transaction>>>,
     default_transaction_options: TransactionOptions,
     created_at: Instant,
@@ -355,6 +653,7 @@ impl Session {
         Self {
             id,
             storage,
+            database: Mutex::new(None),
             current_transaction: Mutex::new(None),
             default_transaction_options: TransactionOptions::default(),
             created_at: Instant::now(),
@@ -375,6 +674,15 @@ impl Session {
         *self.last_active.lock() = Instant::now();
     }
 
+    /// # Brief
+    /// 会话的当前数据库,未执行 USE 时为默认数据库
+    pub fn database(&self) -> String {
+        self.database
+            .lock()
+            .clone()
+            .unwrap_or_else(|| crate::storage::DEFAULT_DATABASE.to_string())
+    }
+
     pub fn start_transaction(&self) -> MikuResult<Arc<Transaction>> {
         self.start_transaction_with_options(self.default_transaction_options.clone())
     }
@@ -417,11 +725,10 @@ impl Session {
     pub fn commit_transaction(&self) -> MikuResult<()> {
         self.touch();
 
+        // 提交失败(如写冲突)时事务已经结束,不再是会话的当前事务
         let mut current = self.current_transaction.lock();
-        if let Some(ref txn) = *current {
-            txn.commit()?;
-            *current = None;
-            Ok(())
+        if let Some(txn) = current.take() {
+            txn.commit()
         } else {
             Err(MikuError::Transaction("No active transaction".to_string()))
         }
@@ -443,7 +750,7 @@ impl Session {
     pub fn execute(&self, query: &str) -> MikuResult<QueryResponse> {
         self.touch();
 
-        let stmt = Parser::parse(query).map_err(|e| MikuError::Query(e.to_string()))?;
+        let stmt = Parser::parse(query).map_err(MikuError::from)?;
 
         self.execute_statement(&stmt)
     }
@@ -471,10 +778,20 @@ impl Session {
                 })
             }
             _ => {
-                let executor = crate::query::QueryExecutor::new(self.storage.clone());
-                executor
+                let mut executor = crate::query::QueryExecutor::new(self.storage.clone());
+                if let Some(database) = self.database.lock().clone() {
+                    executor = executor.with_database(database);
+                }
+                if let Some(txn) = self.current_transaction().filter(|txn| txn.is_active()) {
+                    executor = executor.with_transaction(txn);
+                }
+                let response = executor
                     .execute(stmt)
-                    .map_err(|e| MikuError::Query(e.to_string()))
+                    .map_err(MikuError::from)?;
+                if let Statement::Use(use_stmt) = stmt {
+                    *self.database.lock() = Some(use_stmt.database.clone());
+                }
+                Ok(response)
             }
         }
     }
@@ -496,6 +813,18 @@ impl Session {
         }
     }
 
+    /// # Brief
+    /// 在事务中执行闭包,遇到可重试错误时重新执行整个事务
+    ///
+    /// 闭包或提交返回 [`MikuError::is_retryable`] 为 true 的错误(写冲突、超时、连接错误等)时
+    /// 中止当前事务并从头重试,其余错误直接返回。
+    ///
+    /// # Arguments
+    /// * `max_retries` - 最多执行的次数
+    /// * `f` - 事务内执行的操作
+    ///
+    /// # Returns
+    /// 闭包的结果;重试次数用尽时返回最后一次的错误
     pub fn with_transaction_retry<F, T>(&self, max_retries: u32, mut f: F) -> MikuResult<T>
     where
         F: FnMut(&Transaction) -> MikuResult<T>,
@@ -504,30 +833,16 @@ impl Session {
 
         loop {
             let txn = self.start_transaction()?;
-            match f(&txn) {
-                Ok(result) => {
-                    match self.commit_transaction() {
-                        Ok(()) => return Ok(result),
-                        Err(MikuError::Storage(ref msg)) if msg.contains("conflict") => {
-                            attempts += 1;
-                            if attempts >= max_retries {
-                                return Err(MikuError::Transaction(format!(
-                                    "Transaction failed after {} retries",
-                                    max_retries
-                                )));
-                            }
-                            warn!("Transaction conflict, retrying (attempt {}/{})", attempts, max_retries);
-                            continue;
-                        }
-                        Err(e) => {
-                            let _ = self.abort_transaction();
-                            return Err(e);
-                        }
-                    }
-                }
+            let result = f(&txn).and_then(|result| self.commit_transaction().map(|()| result));
+            match result {
+                Ok(result) => return Ok(result),
                 Err(e) => {
                     let _ = self.abort_transaction();
-                    return Err(e);
+                    attempts += 1;
+                    if !e.is_retryable() || attempts >= max_retries {
+                        return Err(e);
+                    }
+                    warn!("Transaction failed with retryable error, retrying (attempt {}/{}): {}", attempts, max_retries, e);
                 }
             }
         }
@@ -544,9 +859,12 @@ impl Drop for Session {
     }
 }
 
+/// 会话管理器
+///
+/// 会话按 ID 分片存放,大量连接并发创建、查找会话时不争用同一把锁。
 pub struct SessionManager {
     storage: Arc<StorageEngine>,
-    sessions: RwLock<HashMap<u64, Arc<Session>>>,
+    sessions: DashMap<u64, Arc<Session>>,
     session_timeout: Duration,
 }
 
@@ -554,23 +872,23 @@ impl SessionManager {
     pub fn new(storage: Arc<StorageEngine>) -> Self {
         Self {
             storage,
-            sessions: RwLock::new(HashMap::new()),
+            sessions: DashMap::new(),
             session_timeout: Duration::from_secs(30 * 60),
         }
     }
 
     pub fn create_session(&self) -> Arc<Session> {
         let session = Arc::new(Session::new(self.storage.clone()));
-        self.sessions.write().insert(session.id(), session.clone());
+        self.sessions.insert(session.id(), session.clone());
         session
     }
 
     pub fn get_session(&self, id: u64) -> Option<Arc<Session>> {
-        self.sessions.read().get(&id).cloned()
+        self.sessions.get(&id).map(|session| session.clone())
     }
 
     pub fn end_session(&self, id: u64) -> MikuResult<()> {
-        if let Some(session) = self.sessions.write().remove(&id) {
+        if let Some((_, session)) = self.sessions.remove(&id) {
             if session.has_active_transaction() {
                 session.abort_transaction()?;
             }
@@ -579,15 +897,15 @@ impl SessionManager {
     }
 
     pub fn cleanup_expired_sessions(&self) {
-        let mut sessions = self.sessions.write();
-        let expired: Vec<u64> = sessions
+        let expired: Vec<u64> = self
+            .sessions
             .iter()
-            .filter(|(_, s)| s.is_expired())
-            .map(|(id, _)| *id)
+            .filter(|s| s.is_expired())
+            .map(|s| *s.key())
             .collect();
 
         for id in expired {
-            if let Some(session) = sessions.remove(&id) {
+            if let Some((_, session)) = self.sessions.remove_if(&id, |_, s| s.is_expired()) {
                 if session.has_active_transaction() {
                     let _ = session.abort_transaction();
                 }
@@ -597,13 +915,12 @@ impl SessionManager {
     }
 
     pub fn active_session_count(&self) -> usize {
-        self.sessions.read().len()
+        self.sessions.len()
     }
 
     pub fn active_transaction_count(&self) -> usize {
         self.sessions
-            .read()
-            .values()
+            .iter()
             .filter(|s| s.has_active_transaction())
             .count()
     }
@@ -680,6 +997,203 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_statements_run_in_transaction() {
+        let storage = create_test_storage();
+        let session = Session::new(storage.clone());
+        session.execute("INSERT INTO users {name: 'Miku', age: 16}").unwrap();
+        let names = |query: &str| match session.execute(query).unwrap() {
+            QueryResponse::Documents(docs) => {
+                let mut names: Vec<String> = docs
+                    .iter()
+                    .filter_map(|doc| doc.get_str("name").map(str::to_string))
+                    .collect();
+                names.sort();
+                names
+            }
+            other => panic!("unexpected response: {:?}", other),
+        };
+        let stored = || storage.get_collection("users").unwrap().count().unwrap();
+
+        session.execute("BEGIN TRANSACTION").unwrap();
+        session.execute("INSERT INTO users {name: 'Rin', age: 14}").unwrap();
+        session.execute("UPDATE users SET age = 17 WHERE name = 'Miku'").unwrap();
+        assert_eq!(names("FIND users WHERE age > 15"), vec!["Miku"]);
+        assert_eq!(names("FIND users"), vec!["Miku", "Rin"]);
+        assert_eq!(stored(), 1);
+        session.execute("COMMIT").unwrap();
+        assert_eq!(names("FIND users WHERE age = 17"), vec!["Miku"]);
+        assert_eq!(stored(), 2);
+
+        session.execute("BEGIN TRANSACTION").unwrap();
+        session.execute("DELETE FROM users WHERE name = 'Rin'").unwrap();
+        assert_eq!(names("FIND users"), vec!["Miku"]);
+        session.execute("ROLLBACK").unwrap();
+        assert_eq!(names("FIND users"), vec!["Miku", "Rin"]);
+    }
+
+    #[test]
+    fn test_use_database() {
+        let storage = create_test_storage();
+        let session = Session::new(storage.clone());
+        session.execute("INSERT INTO users {name: 'Miku'}").unwrap();
+        assert!(session.execute("USE shop").is_err());
+        assert_eq!(session.database(), "default");
+
+        session.execute("CREATE DATABASE shop").unwrap();
+        session.execute("USE shop").unwrap();
+        assert_eq!(session.database(), "shop");
+        session.execute("INSERT INTO users {name: 'Rin'}").unwrap();
+        let count = |session: &Session| match session.execute("FIND users").unwrap() {
+            QueryResponse::Documents(docs) => docs.len(),
+            other => panic!("unexpected response: {:?}", other),
+        };
+        assert_eq!(count(&session), 1);
+        match session.execute("SHOW DATABASE").unwrap() {
+            QueryResponse::Databases(databases) => assert_eq!(databases, vec!["default", "shop"]),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        assert_eq!(storage.get_collection("shop.users").unwrap().count().unwrap(), 1);
+
+        session.execute("USE default").unwrap();
+        assert_eq!(count(&session), 1);
+        session.execute("DROP DATABASE shop").unwrap();
+        assert!(storage.get_collection("shop.users").is_err());
+    }
+
+    #[test]
+    fn test_commit_detects_conflicts() {
+        let storage = create_test_storage();
+        let setup = Session::new(storage.clone());
+        let id = setup
+            .with_transaction(|txn| txn.insert("accounts", Document::new()))
+            .unwrap();
+        let balance = |doc: Option<Document>| doc.unwrap().get_i64("balance").unwrap_or(0);
+
+        // 两个事务读取同一个文档后各自更新,后提交的发生冲突
+        let first = Session::new(storage.clone());
+        let second = Session::new(storage.clone());
+        let t1 = first.start_transaction().unwrap();
+        let t2 = second.start_transaction().unwrap();
+        for txn in [&t1, &t2] {
+            let mut doc = txn.get("accounts", &id).unwrap().unwrap();
+            doc.insert("balance", balance(Some(doc.clone())) + 10);
+            assert!(txn.update("accounts", &id, doc).unwrap());
+        }
+        first.commit_transaction().unwrap();
+        let err = second.commit_transaction().unwrap_err();
+        assert!(err.is_retryable());
+        assert_eq!(t2.state(), TransactionState::Aborted);
+
+        // 重试时重新读取,基于最新的值更新
+        let mut attempts = 0;
+        second
+            .with_transaction_retry(3, |txn| {
+                attempts += 1;
+                let mut doc = txn.get("accounts", &id)?.unwrap();
+                doc.insert("balance", balance(Some(doc.clone())) + 10);
+                txn.update("accounts", &id, doc)?;
+                if attempts == 1 {
+                    let other = Session::new(storage.clone());
+                    other.with_transaction(|t| {
+                        let doc = t.get("accounts", &id)?.unwrap();
+                        t.update("accounts", &id, doc)
+                    })?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(attempts, 2);
+        let reader = storage.get_collection("accounts").unwrap();
+        assert_eq!(balance(reader.get(&id).unwrap()), 20);
+    }
+
+    #[test]
+    fn test_isolation_levels_change_validation() {
+        let storage = create_test_storage();
+        let writer = Session::new(storage.clone());
+        let id = writer.with_transaction(|txn| txn.insert("items", Document::new())).unwrap();
+
+        // 只读取、不写入的文档: ReadCommitted 不检查,RepeatableRead 检查
+        let run = |level: IsolationLevel, scan: bool| {
+            let session = Session::new(storage.clone());
+            let options = TransactionOptions { isolation_level: level, ..Default::default() };
+            let txn = session.start_transaction_with_options(options).unwrap();
+            if scan {
+                txn.find_all("items").unwrap();
+            } else {
+                txn.get("items", &id).unwrap();
+            }
+            txn.insert("log", Document::new()).unwrap();
+            writer.with_transaction(|t| t.insert("items", Document::new())).unwrap();
+            writer
+                .with_transaction(|t| {
+                    let doc = t.get("items", &id)?.unwrap();
+                    t.update("items", &id, doc)
+                })
+                .unwrap();
+            session.commit_transaction()
+        };
+        assert!(run(IsolationLevel::ReadCommitted, false).is_ok());
+        assert!(run(IsolationLevel::RepeatableRead, false).is_err());
+        assert!(run(IsolationLevel::ReadUncommitted, false).is_ok());
+
+        // Serializable 遍历过的集合有新文档插入也算冲突
+        let run_insert_only = |level: IsolationLevel| {
+            let session = Session::new(storage.clone());
+            let options = TransactionOptions { isolation_level: level, ..Default::default() };
+            let txn = session.start_transaction_with_options(options).unwrap();
+            txn.find_all("items").unwrap();
+            writer.with_transaction(|t| t.insert("items", Document::new())).unwrap();
+            session.commit_transaction()
+        };
+        assert!(run_insert_only(IsolationLevel::Snapshot).is_ok());
+        assert!(run_insert_only(IsolationLevel::Serializable).is_err());
+        assert!(run(IsolationLevel::Serializable, true).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_reads_start_version() {
+        let storage = create_test_storage();
+        let writer = Session::new(storage.clone());
+        let id = writer
+            .with_transaction(|txn| {
+                let mut doc = Document::new();
+                doc.insert("n", 1i64);
+                txn.insert("items", doc)
+            })
+            .unwrap();
+        let n = |doc: Option<Document>| doc.unwrap().get_i64("n").unwrap();
+        let bump = || {
+            writer
+                .with_transaction(|t| {
+                    let mut doc = t.get("items", &id)?.unwrap();
+                    doc.insert("n", n(Some(doc.clone())) + 1);
+                    t.update("items", &id, doc)?;
+                    t.insert("items", Document::new())
+                })
+                .unwrap();
+        };
+
+        let snapshot = Session::new(storage.clone());
+        let options = TransactionOptions { isolation_level: IsolationLevel::Snapshot, ..Default::default() };
+        let txn = snapshot.start_transaction_with_options(options).unwrap();
+        let committed = Session::new(storage.clone());
+        let other = committed.start_transaction().unwrap();
+        bump();
+
+        // 快照事务看不到开始之后的修改和插入,ReadCommitted 事务看到最新版本
+        assert_eq!(n(txn.get("items", &id).unwrap()), 1);
+        assert_eq!(txn.find_all("items").unwrap().len(), 1);
+        assert_eq!(n(other.get("items", &id).unwrap()), 2);
+        assert_eq!(storage.versions().active_snapshots(), 1);
+
+        // 读取过的文档在事务开始后被修改,提交时按冲突处理
+        assert!(snapshot.commit_transaction().is_err());
+        committed.commit_transaction().unwrap();
+        assert_eq!(storage.versions().active_snapshots(), 0);
+    }
+
     #[test]
     fn test_session_manager() {
         let storage = create_test_storage();
@@ -0,0 +1,27 @@
+//! 构建脚本: 使用 cbindgen 生成 C 头文件
+//!
+//! 头文件写入 `OUT_DIR/mikudb.h`,不修改源码目录。仓库中的 `include/mikudb.h`
+//! 随源码提交,修改 C API 后设置 `MIKUDB_UPDATE_HEADER=1` 构建一次即可更新。
+
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=MIKUDB_UPDATE_HEADER");
+
+    match cbindgen::generate(&crate_dir) {
+        Ok(bindings) => {
+            bindings.write_to_file(out_dir.join("mikudb.h"));
+            if env::var_os("MIKUDB_UPDATE_HEADER").is_some() {
+                bindings.write_to_file(crate_dir.join("include").join("mikudb.h"));
+            }
+        }
+        // 头文件生成失败不应阻断库本身的构建
+        Err(e) => println!("cargo:warning=failed to generate C header: {}", e),
+    }
+}
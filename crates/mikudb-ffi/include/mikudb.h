@@ -0,0 +1,200 @@
+/* MikuDB C API - generated by cbindgen, do not edit. */
+
+#ifndef MIKUDB_H
+#define MIKUDB_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ C API 调用状态码
+ */
+typedef enum MikuStatus {
+  /*
+   调用成功
+   */
+  MIKU_STATUS_OK = 0,
+  /*
+   调用失败,详见 `mikudb_last_error()`
+   */
+  MIKU_STATUS_ERROR = 1,
+  /*
+   结果集已迭代完毕
+   */
+  MIKU_STATUS_END = 2,
+} MikuStatus;
+
+/*
+ 嵌入式数据库句柄(不透明类型)
+ */
+typedef struct MikuDatabase MikuDatabase;
+
+/*
+ 查询结果集(不透明类型)
+ */
+typedef struct MikuResultSet MikuResultSet;
+
+/*
+ 由本库分配的字节缓冲区
+
+ 必须通过 `mikudb_buffer_free` 释放。
+ */
+typedef struct MikuBuffer {
+  /*
+   数据指针
+   */
+  uint8_t *data;
+  /*
+   数据长度(字节)
+   */
+  size_t len;
+} MikuBuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ 获取库版本号(静态字符串,无需释放)
+ */
+const char *mikudb_version(void);
+
+/*
+ # Brief
+ 获取当前线程最近一次错误信息
+
+ 返回的指针在当前线程下一次调用本库函数前有效;没有错误时返回 NULL。
+ */
+const char *mikudb_last_error(void);
+
+/*
+ # Brief
+ 打开或创建嵌入式数据库
+
+ # Arguments
+ * `name` - 数据库名称
+ * `data_dir` - 数据目录,数据库实际存储在 `data_dir/name` 下
+
+ # Returns
+ 数据库句柄,失败返回 NULL。需通过 `mikudb_close` 释放。
+
+ # Safety
+ `name` 和 `data_dir` 必须是有效的 NUL 结尾字符串。
+ */
+struct MikuDatabase *mikudb_open(const char *name, const char *data_dir);
+
+/*
+ # Brief
+ 关闭数据库并释放句柄
+
+ # Safety
+ `db` 必须是 `mikudb_open` 返回的句柄或 NULL,且只能关闭一次。
+ */
+void mikudb_close(struct MikuDatabase *db);
+
+/*
+ # Brief
+ 执行 MQL 语句
+
+ # Arguments
+ * `db` - 数据库句柄
+ * `query` - MQL 语句
+
+ # Returns
+ 结果集,失败返回 NULL。需通过 `mikudb_result_free` 释放。
+
+ # Safety
+ `db` 必须是有效的数据库句柄,`query` 必须是有效的 NUL 结尾字符串。
+ */
+struct MikuResultSet *mikudb_execute(const struct MikuDatabase *db, const char *query);
+
+/*
+ # Brief
+ 获取结果集中的文档数量
+
+ # Safety
+ `rs` 必须是有效的结果集或 NULL。
+ */
+size_t mikudb_result_count(const struct MikuResultSet *rs);
+
+/*
+ # Brief
+ 获取受影响的文档数量(INSERT/UPDATE/DELETE)
+
+ # Safety
+ `rs` 必须是有效的结果集或 NULL。
+ */
+uint64_t mikudb_result_affected(const struct MikuResultSet *rs);
+
+/*
+ # Brief
+ 获取结果消息
+
+ 返回的字符串由结果集持有,在 `mikudb_result_free` 前有效;没有消息时返回 NULL。
+
+ # Safety
+ `rs` 必须是有效的结果集或 NULL。
+ */
+const char *mikudb_result_message(const struct MikuResultSet *rs);
+
+/*
+ # Brief
+ 以 JSON(UTF-8,不含 NUL 结尾)取出下一条文档
+
+ # Returns
+ `MIKU_STATUS_OK` 时 `out` 指向新分配的缓冲区,需通过 `mikudb_buffer_free` 释放;
+ 迭代完毕返回 `MIKU_STATUS_END`。
+
+ # Safety
+ `rs` 必须是有效的结果集,`out` 必须指向可写的 `MikuBuffer`。
+ */
+enum MikuStatus mikudb_result_next_json(struct MikuResultSet *rs, struct MikuBuffer *out);
+
+/*
+ # Brief
+ 以 BOML 二进制格式(带校验和的文档编码)取出下一条文档
+
+ # Returns
+ `MIKU_STATUS_OK` 时 `out` 指向新分配的缓冲区,需通过 `mikudb_buffer_free` 释放;
+ 迭代完毕返回 `MIKU_STATUS_END`。
+
+ # Safety
+ `rs` 必须是有效的结果集,`out` 必须指向可写的 `MikuBuffer`。
+ */
+enum MikuStatus mikudb_result_next_boml(struct MikuResultSet *rs, struct MikuBuffer *out);
+
+/*
+ # Brief
+ 将结果集迭代位置重置到开头
+
+ # Safety
+ `rs` 必须是有效的结果集或 NULL。
+ */
+void mikudb_result_reset(struct MikuResultSet *rs);
+
+/*
+ # Brief
+ 释放结果集
+
+ # Safety
+ `rs` 必须是 `mikudb_execute` 返回的结果集或 NULL,且只能释放一次。
+ */
+void mikudb_result_free(struct MikuResultSet *rs);
+
+/*
+ # Brief
+ 释放由本库分配的缓冲区
+
+ # Safety
+ `buf` 必须来自 `mikudb_result_next_json` / `mikudb_result_next_boml`,且只能释放一次。
+ */
+void mikudb_buffer_free(struct MikuBuffer buf);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MIKUDB_H */
@@ -0,0 +1,518 @@
+//! MikuDB C FFI 模块
+//!
+//! 为嵌入式场景提供稳定的 C API,供 Python/Go/C++ 等语言绑定使用:
+//! - **数据库**: 打开/关闭嵌入式数据库
+//! - **查询**: 执行 MQL 语句
+//! - **结果集**: 以 BOML 或 JSON 缓冲区逐条迭代文档
+//! - **内存管理**: 所有由本库分配的对象都有对应的 free 函数
+//!
+//! C 头文件 `include/mikudb.h` 由 cbindgen 生成,设置 `MIKUDB_UPDATE_HEADER=1` 构建时更新。
+//!
+//! # 错误处理
+//!
+//! 返回指针的函数失败时返回 NULL,返回 `MikuStatus` 的函数失败时返回
+//! `MIKU_STATUS_ERROR`;错误详情通过 `mikudb_last_error()` 获取(线程局部)。
+//! 以 `panic = "unwind"` 构建时,引擎内部的 panic 在 C ABI 边界被捕获,按失败处理,
+//! 不会展开到调用方。工作区的 release 配置使用 `panic = "abort"`,panic 会直接终止进程;
+//! 发布 C 库时使用 `cargo build -p mikudb-ffi --profile release-ffi`。
+//!
+//! # 示例
+//!
+//! ```c
+//! MikuDatabase *db = mikudb_open("test", "/tmp/mikudb");
+//! MikuResultSet *rs = mikudb_execute(db, "FIND users");
+//! MikuBuffer buf;
+//! while (mikudb_result_next_json(rs, &buf) == MIKU_STATUS_OK) {
+//!     fwrite(buf.data, 1, buf.len, stdout);
+//!     mikudb_buffer_free(buf);
+//! }
+//! mikudb_result_free(rs);
+//! mikudb_close(db);
+//! ```
+
+use mikudb_boml::{codec, Document};
+use mikudb_core::{Database, QueryResponse};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// C API 调用状态码
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MikuStatus {
+    /// 调用成功
+    Ok = 0,
+    /// 调用失败,详见 `mikudb_last_error()`
+    Error = 1,
+    /// 结果集已迭代完毕
+    End = 2,
+}
+
+/// 由本库分配的字节缓冲区
+///
+/// 必须通过 `mikudb_buffer_free` 释放。
+#[repr(C)]
+#[derive(Debug)]
+pub struct MikuBuffer {
+    /// 数据指针
+    pub data: *mut u8,
+    /// 数据长度(字节)
+    pub len: usize,
+}
+
+impl MikuBuffer {
+    fn empty() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let boxed = bytes.into_boxed_slice();
+        let len = boxed.len();
+        let data = Box::into_raw(boxed) as *mut u8;
+        Self { data, len }
+    }
+}
+
+/// 嵌入式数据库句柄(不透明类型)
+pub struct MikuDatabase {
+    inner: Database,
+}
+
+/// 查询结果集(不透明类型)
+pub struct MikuResultSet {
+    documents: Vec<Document>,
+    position: usize,
+    affected: u64,
+    message: Option<CString>,
+}
+
+thread_local! {
+    /// 当前线程最近一次错误信息
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = message.into().replace('\0', " ");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+/// # Brief
+/// 在 C ABI 边界执行函数体并捕获 panic
+///
+/// panic 不能展开穿过 `extern "C"` 函数,捕获后记录错误信息并返回 `on_panic`。
+/// 只在 unwind 构建中生效(如 `release-ffi` 配置);`panic = "abort"` 时进程直接终止。
+///
+/// # Arguments
+/// * `on_panic` - 发生 panic 时的返回值
+/// * `body` - 函数体
+fn ffi_guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("panic in mikudb: {}", message));
+            on_panic
+        }
+    }
+}
+
+/// # Brief
+/// 将 C 字符串转换为 Rust 字符串切片
+///
+/// 空指针或非 UTF-8 时记录错误并返回 None。
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Option<&'a str> {
+    if ptr.is_null() {
+        set_last_error(format!("{} must not be NULL", name));
+        return None;
+    }
+    match CStr::from_ptr(ptr).to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_last_error(format!("{} is not valid UTF-8", name));
+            None
+        }
+    }
+}
+
+/// # Brief
+/// 将查询响应转换为结果集
+///
+/// 与服务器协议共用 `QueryResponse::into_parts` 的转换,非文档类响应转换为 `{name: ...}` 等形式的文档。
+fn result_set_from_response(response: QueryResponse) -> MikuResultSet {
+    let (documents, affected, message) = response.into_parts();
+
+    MikuResultSet {
+        documents,
+        position: 0,
+        affected,
+        message: message.and_then(|m| CString::new(m.replace('\0', " ")).ok()),
+    }
+}
+
+/// 获取库版本号(静态字符串,无需释放)
+#[no_mangle]
+pub extern "C" fn mikudb_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// # Brief
+/// 获取当前线程最近一次错误信息
+///
+/// 返回的指针在当前线程下一次调用本库函数前有效;没有错误时返回 NULL。
+#[no_mangle]
+pub extern "C" fn mikudb_last_error() -> *const c_char {
+    ffi_guard(ptr::null(), || {
+        LAST_ERROR.with(|e| {
+            e.borrow()
+                .as_ref()
+                .map(|s| s.as_ptr())
+                .unwrap_or(ptr::null())
+        })
+    })
+}
+
+/// # Brief
+/// 打开或创建嵌入式数据库
+///
+/// # Arguments
+/// * `name` - 数据库名称
+/// * `data_dir` - 数据目录,数据库实际存储在 `data_dir/name` 下
+///
+/// # Returns
+/// 数据库句柄,失败返回 NULL。需通过 `mikudb_close` 释放。
+///
+/// # Safety
+/// `name` 和 `data_dir` 必须是有效的 NUL 结尾字符串。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_open(
+    name: *const c_char,
+    data_dir: *const c_char,
+) -> *mut MikuDatabase {
+    ffi_guard(ptr::null_mut(), || {
+        clear_last_error();
+        let (Some(name), Some(data_dir)) = (str_arg(name, "name"), str_arg(data_dir, "data_dir")) else {
+            return ptr::null_mut();
+        };
+
+        match Database::open(name, data_dir) {
+            Ok(db) => Box::into_raw(Box::new(MikuDatabase { inner: db })),
+            Err(e) => {
+                set_last_error(e.to_string());
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// # Brief
+/// 关闭数据库并释放句柄
+///
+/// # Safety
+/// `db` 必须是 `mikudb_open` 返回的句柄或 NULL,且只能关闭一次。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_close(db: *mut MikuDatabase) {
+    ffi_guard((), || {
+        if !db.is_null() {
+            let db = Box::from_raw(db);
+            let _ = db.inner.flush();
+        }
+    })
+}
+
+/// # Brief
+/// 执行 MQL 语句
+///
+/// # Arguments
+/// * `db` - 数据库句柄
+/// * `query` - MQL 语句
+///
+/// # Returns
+/// 结果集,失败返回 NULL。需通过 `mikudb_result_free` 释放。
+///
+/// # Safety
+/// `db` 必须是有效的数据库句柄,`query` 必须是有效的 NUL 结尾字符串。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_execute(
+    db: *const MikuDatabase,
+    query: *const c_char,
+) -> *mut MikuResultSet {
+    ffi_guard(ptr::null_mut(), || {
+        clear_last_error();
+        if db.is_null() {
+            set_last_error("db must not be NULL");
+            return ptr::null_mut();
+        }
+        let Some(query) = str_arg(query, "query") else {
+            return ptr::null_mut();
+        };
+
+        match (*db).inner.execute(query) {
+            Ok(response) => Box::into_raw(Box::new(result_set_from_response(response))),
+            Err(e) => {
+                set_last_error(e.to_string());
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// # Brief
+/// 获取结果集中的文档数量
+///
+/// # Safety
+/// `rs` 必须是有效的结果集或 NULL。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_result_count(rs: *const MikuResultSet) -> usize {
+    ffi_guard(0, || {
+        if rs.is_null() {
+            return 0;
+        }
+        (*rs).documents.len()
+    })
+}
+
+/// # Brief
+/// 获取受影响的文档数量(INSERT/UPDATE/DELETE)
+///
+/// # Safety
+/// `rs` 必须是有效的结果集或 NULL。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_result_affected(rs: *const MikuResultSet) -> u64 {
+    ffi_guard(0, || {
+        if rs.is_null() {
+            return 0;
+        }
+        (*rs).affected
+    })
+}
+
+/// # Brief
+/// 获取结果消息
+///
+/// 返回的字符串由结果集持有,在 `mikudb_result_free` 前有效;没有消息时返回 NULL。
+///
+/// # Safety
+/// `rs` 必须是有效的结果集或 NULL。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_result_message(rs: *const MikuResultSet) -> *const c_char {
+    ffi_guard(ptr::null(), || {
+        if rs.is_null() {
+            return ptr::null();
+        }
+        (*rs)
+            .message
+            .as_ref()
+            .map(|m| m.as_ptr())
+            .unwrap_or(ptr::null())
+    })
+}
+
+/// # Brief
+/// 取出下一条文档并编码到缓冲区
+unsafe fn result_next(
+    rs: *mut MikuResultSet,
+    out: *mut MikuBuffer,
+    encode: impl FnOnce(&Document) -> Result<Vec<u8>, String>,
+) -> MikuStatus {
+    clear_last_error();
+    if rs.is_null() || out.is_null() {
+        set_last_error("result set and output buffer must not be NULL");
+        return MikuStatus::Error;
+    }
+    *out = MikuBuffer::empty();
+
+    let rs = &mut *rs;
+    let Some(doc) = rs.documents.get(rs.position) else {
+        return MikuStatus::End;
+    };
+
+    match encode(doc) {
+        Ok(bytes) => {
+            rs.position += 1;
+            *out = MikuBuffer::from_vec(bytes);
+            MikuStatus::Ok
+        }
+        Err(e) => {
+            set_last_error(e);
+            MikuStatus::Error
+        }
+    }
+}
+
+/// # Brief
+/// 以 JSON(UTF-8,不含 NUL 结尾)取出下一条文档
+///
+/// # Returns
+/// `MIKU_STATUS_OK` 时 `out` 指向新分配的缓冲区,需通过 `mikudb_buffer_free` 释放;
+/// 迭代完毕返回 `MIKU_STATUS_END`。
+///
+/// # Safety
+/// `rs` 必须是有效的结果集,`out` 必须指向可写的 `MikuBuffer`。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_result_next_json(
+    rs: *mut MikuResultSet,
+    out: *mut MikuBuffer,
+) -> MikuStatus {
+    ffi_guard(MikuStatus::Error, || {
+        result_next(rs, out, |doc| {
+            serde_json::to_vec(doc).map_err(|e| e.to_string())
+        })
+    })
+}
+
+/// # Brief
+/// 以 BOML 二进制格式(带校验和的文档编码)取出下一条文档
+///
+/// # Returns
+/// `MIKU_STATUS_OK` 时 `out` 指向新分配的缓冲区,需通过 `mikudb_buffer_free` 释放;
+/// 迭代完毕返回 `MIKU_STATUS_END`。
+///
+/// # Safety
+/// `rs` 必须是有效的结果集,`out` 必须指向可写的 `MikuBuffer`。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_result_next_boml(
+    rs: *mut MikuResultSet,
+    out: *mut MikuBuffer,
+) -> MikuStatus {
+    ffi_guard(MikuStatus::Error, || {
+        result_next(rs, out, |doc| {
+            codec::encode_document(&doc.to_boml_value()).map_err(|e| e.to_string())
+        })
+    })
+}
+
+/// # Brief
+/// 将结果集迭代位置重置到开头
+///
+/// # Safety
+/// `rs` 必须是有效的结果集或 NULL。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_result_reset(rs: *mut MikuResultSet) {
+    ffi_guard((), || {
+        if !rs.is_null() {
+            (*rs).position = 0;
+        }
+    })
+}
+
+/// # Brief
+/// 释放结果集
+///
+/// # Safety
+/// `rs` 必须是 `mikudb_execute` 返回的结果集或 NULL,且只能释放一次。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_result_free(rs: *mut MikuResultSet) {
+    ffi_guard((), || {
+        if !rs.is_null() {
+            drop(Box::from_raw(rs));
+        }
+    })
+}
+
+/// # Brief
+/// 释放由本库分配的缓冲区
+///
+/// # Safety
+/// `buf` 必须来自 `mikudb_result_next_json` / `mikudb_result_next_boml`,且只能释放一次。
+#[no_mangle]
+pub unsafe extern "C" fn mikudb_buffer_free(buf: MikuBuffer) {
+    ffi_guard((), || {
+        if !buf.data.is_null() {
+            let slice = ptr::slice_from_raw_parts_mut(buf.data, buf.len);
+            drop(Box::from_raw(slice));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mikudb_boml::BomlValue;
+
+    fn cstr(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_execute_and_iterate_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = cstr("ffi_test");
+        let data_dir = cstr(dir.path().to_str().unwrap());
+
+        unsafe {
+            let db = mikudb_open(name.as_ptr(), data_dir.as_ptr());
+            assert!(!db.is_null());
+
+            let insert = cstr("INSERT INTO users {name: \"Miku\", age: 16}");
+            let rs = mikudb_execute(db, insert.as_ptr());
+            assert!(!rs.is_null());
+            assert_eq!(mikudb_result_affected(rs), 1);
+            mikudb_result_free(rs);
+
+            let find = cstr("FIND users");
+            let rs = mikudb_execute(db, find.as_ptr());
+            assert!(!rs.is_null());
+            assert_eq!(mikudb_result_count(rs), 1);
+
+            let mut buf = MikuBuffer::empty();
+            assert_eq!(mikudb_result_next_json(rs, &mut buf), MikuStatus::Ok);
+            let json: serde_json::Value =
+                serde_json::from_slice(std::slice::from_raw_parts(buf.data, buf.len)).unwrap();
+            assert_eq!(json["name"], "Miku");
+            mikudb_buffer_free(buf);
+
+            let mut buf = MikuBuffer::empty();
+            assert_eq!(mikudb_result_next_json(rs, &mut buf), MikuStatus::End);
+
+            mikudb_result_reset(rs);
+            let mut buf = MikuBuffer::empty();
+            assert_eq!(mikudb_result_next_boml(rs, &mut buf), MikuStatus::Ok);
+            let value =
+                codec::decode_document(std::slice::from_raw_parts(buf.data, buf.len)).unwrap();
+            assert!(matches!(value, BomlValue::Document(_)));
+            mikudb_buffer_free(buf);
+
+            mikudb_result_free(rs);
+            mikudb_close(db);
+        }
+    }
+
+    #[test]
+    fn test_panic_does_not_cross_boundary() {
+        let status = ffi_guard(MikuStatus::Error, || panic!("engine bug"));
+        assert_eq!(status, MikuStatus::Error);
+        let message = unsafe { CStr::from_ptr(mikudb_last_error()) };
+        assert!(message.to_str().unwrap().contains("engine bug"));
+    }
+
+    #[test]
+    fn test_parse_error_sets_last_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = cstr("ffi_err");
+        let data_dir = cstr(dir.path().to_str().unwrap());
+
+        unsafe {
+            let db = mikudb_open(name.as_ptr(), data_dir.as_ptr());
+            assert!(!db.is_null());
+
+            let bad = cstr("FIND WHERE");
+            let rs = mikudb_execute(db, bad.as_ptr());
+            assert!(rs.is_null());
+            assert!(!mikudb_last_error().is_null());
+
+            mikudb_close(db);
+        }
+    }
+}
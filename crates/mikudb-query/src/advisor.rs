@@ -0,0 +1,630 @@
+//! 索引顾问模块
+//!
+//! 基于启发式规则的自动索引建议(不依赖 AI 功能):
+//! - **QueryLog**: 记录查询形态(等值谓词、范围谓词、排序字段)及出现频率
+//! - **IndexAdvisor**: 结合集合文档数和字段基数(抽样估算)生成索引建议
+//!
+//! 复合索引字段按 ESR 规则排列: 等值字段 -> 排序字段 -> 范围字段。
+//! 每轮分析的结果覆盖写入 `_advisor` 集合,通过 `SHOW ADVISOR [ON <collection>]` 查看。
+
+use crate::ast::*;
+use crate::QueryResult;
+use mikudb_boml::{BomlValue, Document};
+use mikudb_storage::StorageEngine;
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// 索引建议集合名称
+pub const ADVISOR_COLLECTION: &str = "_advisor";
+
+/// 范围谓词的默认选择率估计
+const RANGE_SELECTIVITY: f64 = 0.3;
+
+/// 查询形态
+///
+/// 忽略字面量,只保留与索引选择相关的字段信息。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QueryShape {
+    /// 集合名称
+    pub collection: String,
+    /// 等值谓词字段(已排序去重)
+    pub equality: Vec<String>,
+    /// 范围谓词字段(已排序去重)
+    pub range: Vec<String>,
+    /// 排序字段
+    pub sort: Vec<(String, SortOrder)>,
+}
+
+impl QueryShape {
+    /// # Brief
+    /// 从语句提取查询形态
+    ///
+    /// # Returns
+    /// 不涉及过滤/排序的语句、系统集合(以 `_` 开头)返回 None
+    pub fn from_statement(statement: &Statement) -> Option<Self> {
+        let (collection, filter, sort) = match statement {
+            Statement::Find(find) => (&find.collection, find.filter.as_ref(), find.sort.as_deref()),
+            Statement::Update(update) => (&update.collection, update.filter.as_ref(), None),
+            Statement::Delete(delete) => (&delete.collection, delete.filter.as_ref(), None),
+            Statement::Aggregate(agg) => {
+                let filter = agg.pipeline.iter().find_map(|stage| match stage {
+                    AggregateStage::Match(expr) => Some(expr),
+                    _ => None,
+                });
+                let sort = agg.pipeline.iter().find_map(|stage| match stage {
+                    AggregateStage::Sort(fields) => Some(fields.as_slice()),
+                    _ => None,
+                });
+                (&agg.collection, filter, sort)
+            }
+            _ => return None,
+        };
+
+        if collection.starts_with('_') {
+            return None;
+        }
+
+        let mut equality = Vec::new();
+        let mut range = Vec::new();
+        if let Some(filter) = filter {
+            collect_predicates(filter, &mut equality, &mut range);
+        }
+        equality.sort();
+        equality.dedup();
+        range.sort();
+        range.dedup();
+        range.retain(|f| !equality.contains(f));
+
+        let sort: Vec<(String, SortOrder)> = sort
+            .unwrap_or_default()
+            .iter()
+            .map(|f| (f.field.clone(), f.order))
+            .collect();
+
+        if equality.is_empty() && range.is_empty() && sort.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            collection: collection.clone(),
+            equality,
+            range,
+            sort,
+        })
+    }
+
+    /// # Brief
+    /// 按 ESR 规则生成候选索引字段
+    fn index_fields(&self) -> Vec<IndexField> {
+        let mut fields: Vec<IndexField> = self
+            .equality
+            .iter()
+            .map(|name| IndexField { name: name.clone(), order: SortOrder::Ascending })
+            .collect();
+        for (name, order) in &self.sort {
+            if !fields.iter().any(|f| &f.name == name) {
+                fields.push(IndexField { name: name.clone(), order: *order });
+            }
+        }
+        for name in &self.range {
+            if !fields.iter().any(|f| &f.name == name) {
+                fields.push(IndexField { name: name.clone(), order: SortOrder::Ascending });
+            }
+        }
+        fields
+    }
+}
+
+/// # Brief
+/// 从 AND 连接的过滤条件中收集等值和范围谓词字段
+///
+/// OR 分支无法用单个复合索引覆盖,直接忽略。
+fn collect_predicates(expr: &Expression, equality: &mut Vec<String>, range: &mut Vec<String>) {
+    match expr {
+        Expression::Binary { left, op: BinaryOp::And, right } => {
+            collect_predicates(left, equality, range);
+            collect_predicates(right, equality, range);
+        }
+        Expression::Binary { left, op, right } => {
+            let field = match (left.as_ref(), right.as_ref()) {
+                (Expression::Field(f), Expression::Literal(_))
+                | (Expression::Literal(_), Expression::Field(f)) => f,
+                _ => return,
+            };
+            match op {
+                BinaryOp::Eq => equality.push(field.clone()),
+                BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => range.push(field.clone()),
+                _ => {}
+            }
+        }
+        Expression::In { expr, .. } => {
+            if let Expression::Field(f) = expr.as_ref() {
+                equality.push(f.clone());
+            }
+        }
+        Expression::Between { expr, .. } => {
+            if let Expression::Field(f) = expr.as_ref() {
+                range.push(f.clone());
+            }
+        }
+        Expression::Like { expr, pattern } => {
+            // 只有前缀匹配可以利用 B-Tree 索引
+            if let Expression::Field(f) = expr.as_ref() {
+                if !pattern.starts_with('%') && !pattern.starts_with('_') {
+                    range.push(f.clone());
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 查询日志
+///
+/// 按查询形态聚合计数,超出容量时淘汰出现次数最少的形态。
+pub struct QueryLog {
+    capacity: usize,
+    shapes: Mutex<HashMap<QueryShape, u64>>,
+}
+
+impl QueryLog {
+    /// # Brief
+    /// 创建查询日志
+    ///
+    /// # Arguments
+    /// * `capacity` - 最多保留的查询形态数
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            shapes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// # Brief
+    /// 记录一条已解析的语句
+    pub fn record(&self, statement: &Statement) {
+        let Some(shape) = QueryShape::from_statement(statement) else {
+            return;
+        };
+
+        let mut shapes = self.shapes.lock();
+        if !shapes.contains_key(&shape) && shapes.len() >= self.capacity {
+            if let Some(victim) = shapes.iter().min_by_key(|(_, count)| **count).map(|(s, _)| s.clone()) {
+                shapes.remove(&victim);
+            }
+        }
+        *shapes.entry(shape).or_insert(0) += 1;
+    }
+
+    /// # Brief
+    /// 获取当前记录的查询形态及出现次数
+    pub fn snapshot(&self) -> Vec<(QueryShape, u64)> {
+        self.shapes.lock().iter().map(|(s, c)| (s.clone(), *c)).collect()
+    }
+
+    /// # Brief
+    /// 恢复保存的查询形态(服务器重启时),与已记录的次数累加
+    ///
+    /// 超过容量时保留出现次数最多的形态。
+    ///
+    /// # Arguments
+    /// * `saved` - `snapshot` 返回的查询形态及出现次数
+    pub fn restore(&self, saved: Vec<(QueryShape, u64)>) {
+        let mut shapes = self.shapes.lock();
+        for (shape, count) in saved {
+            *shapes.entry(shape).or_insert(0) += count;
+        }
+        if shapes.len() > self.capacity {
+            let mut all: Vec<_> = shapes.drain().collect();
+            all.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            all.truncate(self.capacity);
+            shapes.extend(all);
+        }
+    }
+
+    /// # Brief
+    /// 当前记录的查询形态数
+    pub fn len(&self) -> usize {
+        self.shapes.lock().len()
+    }
+
+    /// # Brief
+    /// 查询日志是否为空
+    pub fn is_empty(&self) -> bool {
+        self.shapes.lock().is_empty()
+    }
+}
+
+/// 索引建议
+#[derive(Debug, Clone)]
+pub struct IndexRecommendation {
+    /// 集合名称
+    pub collection: String,
+    /// 建议的索引字段(按顺序)
+    pub fields: Vec<IndexField>,
+    /// 可受益的查询次数
+    pub query_count: u64,
+    /// 集合文档数
+    pub collection_docs: u64,
+    /// 估算的选择率(0-1,越小越好)
+    pub selectivity: f64,
+    /// 估算收益: 每轮查询可少扫描的文档数总和
+    pub estimated_benefit: f64,
+    /// 建议原因
+    pub reason: String,
+}
+
+impl IndexRecommendation {
+    /// # Brief
+    /// 生成建议的索引名称,例如 `idx_status_created_at`
+    pub fn index_name(&self) -> String {
+        let parts: Vec<String> = self.fields.iter().map(|f| f.name.replace('.', "_")).collect();
+        format!("idx_{}", parts.join("_"))
+    }
+
+    /// # Brief
+    /// 生成可直接执行的 CREATE INDEX 语句
+    pub fn create_statement(&self) -> String {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|f| match f.order {
+                SortOrder::Ascending => f.name.clone(),
+                SortOrder::Descending => format!("{} DESC", f.name),
+            })
+            .collect();
+        format!("CREATE INDEX {} ON {} ({})", self.index_name(), self.collection, fields.join(", "))
+    }
+
+    /// # Brief
+    /// 转换为可写入 `_advisor` 集合的文档
+    pub fn to_document(&self, generated_at: i64) -> Document {
+        let mut doc = Document::new();
+        doc.insert("collection", self.collection.as_str());
+        doc.insert("index_name", self.index_name());
+        let fields: Vec<BomlValue> = self
+            .fields
+            .iter()
+            .map(|f| {
+                let mut d = Document::without_id();
+                d.insert("field", f.name.as_str());
+                d.insert("order", match f.order {
+                    SortOrder::Ascending => "asc",
+                    SortOrder::Descending => "desc",
+                });
+                BomlValue::from(d)
+            })
+            .collect();
+        doc.insert("fields", BomlValue::Array(fields));
+        doc.insert("statement", self.create_statement());
+        doc.insert("query_count", self.query_count as i64);
+        doc.insert("collection_docs", self.collection_docs as i64);
+        doc.insert("selectivity", self.selectivity);
+        doc.insert("estimated_benefit", self.estimated_benefit);
+        doc.insert("reason", self.reason.as_str());
+        doc.insert("generated_at", BomlValue::Timestamp(generated_at));
+        doc
+    }
+}
+
+/// 索引顾问配置
+#[derive(Debug, Clone)]
+pub struct AdvisorOptions {
+    /// 两轮分析之间的间隔
+    pub interval: Duration,
+    /// 估算字段基数时每个集合抽样的文档数
+    pub sample_size: usize,
+    /// 文档数低于此值的集合不给出建议(全表扫描足够快)
+    pub min_documents: u64,
+    /// 查询形态至少出现的次数
+    pub min_queries: u64,
+}
+
+impl Default for AdvisorOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(600),
+            sample_size: 1000,
+            min_documents: 1000,
+            min_queries: 5,
+        }
+    }
+}
+
+/// 索引顾问
+pub struct IndexAdvisor {
+    storage: Arc<StorageEngine>,
+    log: Arc<QueryLog>,
+    options: AdvisorOptions,
+    stop: AtomicBool,
+    wakeup: (Mutex<()>, Condvar),
+}
+
+impl IndexAdvisor {
+    /// # Brief
+    /// 创建索引顾问
+    ///
+    /// # Arguments
+    /// * `storage` - 存储引擎
+    /// * `log` - 共享的查询日志
+    /// * `options` - 顾问配置
+    pub fn new(storage: Arc<StorageEngine>, log: Arc<QueryLog>, options: AdvisorOptions) -> Self {
+        Self {
+            storage,
+            log,
+            options,
+            stop: AtomicBool::new(false),
+            wakeup: (Mutex::new(()), Condvar::new()),
+        }
+    }
+
+    /// # Brief
+    /// 分析查询日志并生成索引建议
+    ///
+    /// # Returns
+    /// 按估算收益降序排列的建议列表
+    pub fn analyze(&self) -> QueryResult<Vec<IndexRecommendation>> {
+        let mut by_collection: HashMap<String, Vec<(QueryShape, u64)>> = HashMap::new();
+        for (shape, count) in self.log.snapshot() {
+            if count >= self.options.min_queries {
+                by_collection.entry(shape.collection.clone()).or_default().push((shape, count));
+            }
+        }
+
+        let mut recommendations = Vec::new();
+        for (name, shapes) in by_collection {
+            let collection = match self.storage.get_collection(&name) {
+                Ok(collection) => collection,
+                Err(_) => continue,
+            };
+            let docs = collection.count_scan()?;
+            if docs < self.options.min_documents {
+                continue;
+            }
+
+            let fields: HashSet<&str> = shapes
+                .iter()
+                .flat_map(|(s, _)| s.equality.iter().map(String::as_str))
+                .collect();
+            let cardinality = self.sample_cardinality(&collection, &fields)?;
+
+            let mut candidates: Vec<IndexRecommendation> = shapes
+                .iter()
+                .map(|(shape, count)| self.recommend(shape, *count, docs, &cardinality))
+                .collect();
+            merge_prefixes(&mut candidates);
+            recommendations.extend(candidates.into_iter().filter(|r| r.estimated_benefit > 0.0));
+        }
+
+        recommendations.sort_by(|a, b| {
+            b.estimated_benefit
+                .partial_cmp(&a.estimated_benefit)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Ok(recommendations)
+    }
+
+    fn sample_cardinality(
+        &self,
+        collection: &mikudb_storage::Collection,
+        fields: &HashSet<&str>,
+    ) -> QueryResult<HashMap<String, (usize, usize)>> {
+        let mut distinct: HashMap<String, HashSet<String>> = HashMap::new();
+        let mut sampled = 0usize;
+        for doc in collection.iter()?.take(self.options.sample_size) {
+            let doc = doc?;
+            sampled += 1;
+            for field in fields {
+                if let Some(value) = doc.get_path(field) {
+                    distinct.entry(field.to_string()).or_default().insert(format!("{}", value));
+                }
+            }
+        }
+        Ok(fields
+            .iter()
+            .map(|f| (f.to_string(), (distinct.get(*f).map_or(0, HashSet::len), sampled)))
+            .collect())
+    }
+
+    fn recommend(
+        &self,
+        shape: &QueryShape,
+        count: u64,
+        docs: u64,
+        cardinality: &HashMap<String, (usize, usize)>,
+    ) -> IndexRecommendation {
+        // 等值字段的选择率近似为 1 / 基数,多个字段假设相互独立
+        let mut selectivity = 1.0f64;
+        for field in &shape.equality {
+            let (distinct, sampled) = cardinality.get(field).copied().unwrap_or((0, 0));
+            if sampled > 0 {
+                selectivity *= 1.0 / distinct.max(1) as f64;
+            }
+        }
+        if !shape.range.is_empty() {
+            selectivity *= RANGE_SELECTIVITY;
+        }
+
+        let mut reason = Vec::new();
+        if !shape.equality.is_empty() {
+            reason.push(format!("equality on {}", shape.equality.join(", ")));
+        }
+        if !shape.sort.is_empty() {
+            let sort: Vec<&str> = shape.sort.iter().map(|(f, _)| f.as_str()).collect();
+            reason.push(format!("sort on {}", sort.join(", ")));
+        }
+        if !shape.range.is_empty() {
+            reason.push(format!("range on {}", shape.range.join(", ")));
+        }
+
+        // 只有排序的查询: 索引可避免排序,按扫描量的一半估算收益
+        let saved_per_query = if shape.equality.is_empty() && shape.range.is_empty() {
+            docs as f64 * 0.5
+        } else {
+            docs as f64 * (1.0 - selectivity)
+        };
+
+        IndexRecommendation {
+            collection: shape.collection.clone(),
+            fields: shape.index_fields(),
+            query_count: count,
+            collection_docs: docs,
+            selectivity,
+            estimated_benefit: saved_per_query * count as f64,
+            reason: format!("{} query(ies) with {}", count, reason.join("; ")),
+        }
+    }
+
+    /// # Brief
+    /// 执行一轮分析并覆盖写入 `_advisor` 集合
+    ///
+    /// # Returns
+    /// 写入的建议条数
+    pub fn run_once(&self) -> QueryResult<usize> {
+        let recommendations = self.analyze()?;
+        let generated_at = chrono::Utc::now().timestamp_millis();
+
+        let collection = self.storage.get_or_create_collection(ADVISOR_COLLECTION)?;
+        collection.clear()?;
+        for recommendation in &recommendations {
+            collection.insert(&mut recommendation.to_document(generated_at))?;
+        }
+
+        debug!("Index advisor wrote {} recommendation(s)", recommendations.len());
+        Ok(recommendations.len())
+    }
+
+    /// # Brief
+    /// 启动后台分析线程
+    ///
+    /// 按 `interval` 周期执行分析,直到调用 `stop`。
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        std::thread::Builder::new()
+            .name("mikudb-advisor".to_string())
+            .spawn(move || {
+                info!("Index advisor started (interval {:?})", self.options.interval);
+                loop {
+                    let mut guard = self.wakeup.0.lock();
+                    if !self.stop.load(Ordering::SeqCst) {
+                        self.wakeup.1.wait_for(&mut guard, self.options.interval);
+                    }
+                    drop(guard);
+                    if self.stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Err(e) = self.run_once() {
+                        error!("Index advisor failed: {}", e);
+                    }
+                }
+                info!("Index advisor stopped");
+            })
+            .expect("failed to spawn index advisor thread")
+    }
+
+    /// # Brief
+    /// 停止后台分析
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _guard = self.wakeup.0.lock();
+        self.wakeup.1.notify_all();
+    }
+}
+
+/// # Brief
+/// 合并同一集合中字段为其他建议前缀的建议
+///
+/// 复合索引 (a, b) 可以服务只用到 a 的查询,因此把前缀建议的收益并入更长的建议。
+fn merge_prefixes(candidates: &mut Vec<IndexRecommendation>) {
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.fields.len()));
+    let mut merged: Vec<IndexRecommendation> = Vec::new();
+    for candidate in candidates.drain(..) {
+        let covering = merged.iter_mut().find(|m| {
+            m.fields.len() >= candidate.fields.len()
+                && m.fields.iter().zip(&candidate.fields).all(|(a, b)| a == b)
+        });
+        match covering {
+            Some(existing) => {
+                existing.query_count += candidate.query_count;
+                existing.estimated_benefit += candidate.estimated_benefit;
+            }
+            None => merged.push(candidate),
+        }
+    }
+    *candidates = merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    #[test]
+    fn test_shape_orders_fields_by_esr() {
+        let stmt = Parser::parse(
+            "FIND orders WHERE created_at > 100 AND state = 'paid' ORDER BY total DESC",
+        )
+        .unwrap();
+        let shape = QueryShape::from_statement(&stmt).unwrap();
+        assert_eq!(shape.equality, vec!["state".to_string()]);
+        assert_eq!(shape.range, vec!["created_at".to_string()]);
+
+        let fields: Vec<(String, SortOrder)> =
+            shape.index_fields().into_iter().map(|f| (f.name, f.order)).collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("state".to_string(), SortOrder::Ascending),
+                ("total".to_string(), SortOrder::Descending),
+                ("created_at".to_string(), SortOrder::Ascending),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_log_ignores_system_and_unfiltered() {
+        let log = QueryLog::new(16);
+        log.record(&Parser::parse("FIND users").unwrap());
+        log.record(&Parser::parse("FIND _advisor WHERE owner = 'users'").unwrap());
+        assert!(log.is_empty());
+
+        log.record(&Parser::parse("FIND users WHERE email = 'a@b.c'").unwrap());
+        log.record(&Parser::parse("FIND users WHERE email = 'x@y.z'").unwrap());
+        let snapshot = log.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].1, 2);
+
+        // 恢复时与已有次数累加,超过容量保留出现最多的形态
+        let restored = QueryLog::new(1);
+        restored.record(&Parser::parse("FIND users WHERE name = 'miku'").unwrap());
+        restored.restore(snapshot);
+        assert_eq!(restored.snapshot()[0].0.equality, vec!["email".to_string()]);
+    }
+
+    #[test]
+    fn test_merge_prefixes() {
+        let rec = |fields: &[&str], benefit: f64| IndexRecommendation {
+            collection: "orders".to_string(),
+            fields: fields
+                .iter()
+                .map(|f| IndexField { name: f.to_string(), order: SortOrder::Ascending })
+                .collect(),
+            query_count: 1,
+            collection_docs: 10_000,
+            selectivity: 0.1,
+            estimated_benefit: benefit,
+            reason: String::new(),
+        };
+        let mut candidates = vec![rec(&["status"], 10.0), rec(&["status", "created_at"], 5.0)];
+        merge_prefixes(&mut candidates);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].fields.len(), 2);
+        assert_eq!(candidates[0].query_count, 2);
+        assert_eq!(candidates[0].create_statement(), "CREATE INDEX idx_status_created_at ON orders (status, created_at)");
+    }
+}
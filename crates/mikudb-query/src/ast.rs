@@ -7,10 +7,13 @@
 //! - DDL 操作结构
 //! - 聚合管道结构
 //! - 用户管理结构
+//! - 数据分层(归档)结构
 //!
 //! AST 节点设计为可序列化,支持网络传输和持久化。
 
 use mikudb_boml::BomlValue;
+use mikudb_common::config::CompressionType;
+use mikudb_storage::{CappedOptions, OversizedKeyPolicy, RollupDefinition};
 use serde::{Deserialize, Serialize};
 
 /// MQL 语句
@@ -31,6 +34,10 @@ pub enum Statement {
     ShowStatus,
     /// 显示所有用户
     ShowUsers,
+    /// 显示索引顾问的建议,可选按集合过滤
+    ShowAdvisor(Option<String>),
+    /// 导出数据库、集合选项、索引和用户的声明式清单
+    ShowManifest,
 
     // DDL 操作
     /// 创建数据库
@@ -38,17 +45,41 @@ pub enum Statement {
     /// 删除数据库
     DropDatabase(String),
     /// 创建集合
-    CreateCollection(String),
+    CreateCollection(CreateCollectionStatement),
     /// 删除集合
     DropCollection(String),
+    /// 修改集合选项
+    AlterCollection(AlterCollectionStatement),
+    /// 显示集合的字段类型登记表
+    ShowSchema(String),
     /// 创建索引
     CreateIndex(CreateIndexStatement),
     /// 删除索引
     DropIndex(DropIndexStatement),
+    /// 创建序列
+    CreateSequence(CreateSequenceStatement),
+    /// 删除序列
+    DropSequence(String),
+    /// 显示所有序列及其当前值
+    ShowSequences,
+    /// 创建写入时维护的预聚合集合
+    CreateRollup(RollupDefinition),
+    /// 删除预聚合及其集合
+    DropRollup(String),
+    /// 显示所有预聚合定义
+    ShowRollups,
+    /// 创建在沙箱中执行的 WASM 自定义函数
+    CreateFunction(CreateFunctionStatement),
+    /// 删除 WASM 自定义函数
+    DropFunction(String),
+    /// 显示已注册的自定义函数
+    ShowFunctions,
 
     // CRUD 操作
     /// 插入文档
     Insert(InsertStatement),
+    /// 把查询结果插入另一个集合(INSERT INTO ... FIND ...)
+    InsertSelect(InsertSelectStatement),
     /// 查询文档
     Find(FindStatement),
     /// 更新文档
@@ -57,6 +88,38 @@ pub enum Statement {
     Delete(DeleteStatement),
     /// 聚合查询
     Aggregate(AggregateStatement),
+    /// 试运行写语句(只匹配不修改,内部只能是 UPDATE 或 DELETE)
+    DryRun(Box<Statement>),
+
+    // 数据分层
+    /// 归档历史数据
+    Archive(ArchiveStatement),
+
+    // 备份与恢复
+    /// 创建备份
+    Backup(BackupStatement),
+    /// 从备份恢复
+    Restore(RestoreStatement),
+
+    // 运维管理
+    /// 调整日志级别(ADMIN SET LOG LEVEL)
+    SetLogLevel(SetLogLevelStatement),
+    /// 恢复启动时的日志过滤规则(ADMIN RESET LOG LEVEL)
+    ResetLogLevel,
+    /// 以本节点为唯一成员初始化集群(CLUSTER INIT)
+    ClusterInit,
+    /// 通过集群成员加入集群(CLUSTER JOIN '<mikudb://host:port>')
+    ClusterJoin(String),
+    /// 设置会话变量(SET <name> = <value>)
+    SetVariable(SetVariableStatement),
+    /// 显示集合的操作统计(STATS <collection>)
+    Stats(String),
+    /// 重置操作统计,None 表示所有集合(RESET STATS [<collection>])
+    ResetStats(Option<String>),
+    /// 收集集合的统计信息供查询优化使用(ANALYZE <collection>)
+    Analyze(String),
+    /// 手动压缩集合及其索引,回收存储空间(COMPACT <collection>)
+    Compact(String),
 
     // 事务
     /// 开始事务
@@ -89,6 +152,104 @@ pub enum Statement {
     AiSuggestIndex(String),
 }
 
+/// # Brief
+/// 对聚合管道中 LOOKUP / GRAPH LOOKUP 关联的集合名调用 `f`,包括 FACET 子管道
+fn rewrite_pipeline_collections(pipeline: &mut [AggregateStage], f: &mut impl FnMut(&mut String)) {
+    for stage in pipeline {
+        match stage {
+            AggregateStage::Lookup { from, .. } => f(from),
+            AggregateStage::GraphLookup(graph) => f(&mut graph.from),
+            AggregateStage::Facet(facets) => {
+                for facet in facets {
+                    rewrite_pipeline_collections(&mut facet.pipeline, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Statement {
+    /// # Brief
+    /// 对语句引用的每个集合名调用 `f`,可以原地改写集合名
+    ///
+    /// 包括 INSERT ... FIND 的源集合、LOOKUP / GRAPH LOOKUP 关联的集合、预聚合和归档集合。
+    /// 索引、序列、函数和用户名不是集合名,不会传给 `f`。
+    pub fn rewrite_collections(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Statement::ShowIndexes(collection)
+            | Statement::DropCollection(collection)
+            | Statement::ShowSchema(collection)
+            | Statement::DropRollup(collection)
+            | Statement::Stats(collection)
+            | Statement::Analyze(collection)
+            | Statement::Compact(collection) => f(collection),
+            Statement::ShowAdvisor(collection) | Statement::ResetStats(collection) => {
+                collection.iter_mut().for_each(f)
+            }
+            Statement::CreateCollection(create) => f(&mut create.name),
+            Statement::AlterCollection(alter) => f(&mut alter.collection),
+            Statement::CreateIndex(create) => f(&mut create.collection),
+            Statement::DropIndex(drop) => f(&mut drop.collection),
+            Statement::CreateRollup(rollup) => {
+                f(&mut rollup.name);
+                f(&mut rollup.source);
+            }
+            Statement::Insert(insert) => f(&mut insert.collection),
+            Statement::InsertSelect(insert) => {
+                f(&mut insert.collection);
+                f(&mut insert.source.collection);
+            }
+            Statement::Find(find) => f(&mut find.collection),
+            Statement::Update(update) => f(&mut update.collection),
+            Statement::Delete(delete) => f(&mut delete.collection),
+            Statement::Aggregate(agg) => {
+                f(&mut agg.collection);
+                rewrite_pipeline_collections(&mut agg.pipeline, f);
+            }
+            Statement::DryRun(inner) => inner.rewrite_collections(f),
+            Statement::Archive(archive) => {
+                f(&mut archive.collection);
+                f(&mut archive.archive);
+            }
+            Statement::Use(_)
+            | Statement::ShowDatabases
+            | Statement::ShowCollections
+            | Statement::ShowStatus
+            | Statement::ShowUsers
+            | Statement::ShowManifest
+            | Statement::CreateDatabase(_)
+            | Statement::DropDatabase(_)
+            | Statement::CreateSequence(_)
+            | Statement::DropSequence(_)
+            | Statement::ShowSequences
+            | Statement::ShowRollups
+            | Statement::CreateFunction(_)
+            | Statement::DropFunction(_)
+            | Statement::ShowFunctions
+            | Statement::Backup(_)
+            | Statement::Restore(_)
+            | Statement::SetLogLevel(_)
+            | Statement::ResetLogLevel
+            | Statement::ClusterInit
+            | Statement::ClusterJoin(_)
+            | Statement::SetVariable(_)
+            | Statement::BeginTransaction
+            | Statement::Commit
+            | Statement::Rollback
+            | Statement::CreateUser(_)
+            | Statement::AlterUser(_)
+            | Statement::DropUser(_)
+            | Statement::Grant(_)
+            | Statement::Revoke(_)
+            | Statement::ShowGrants(_)
+            | Statement::AiQuery(_)
+            | Statement::AiAnalyze(_)
+            | Statement::AiSuggestIndex(_) => {}
+        }
+    }
+}
+
 /// USE 语句
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UseStatement {
@@ -111,6 +272,39 @@ pub struct CreateIndexStatement {
     pub unique: bool,
     /// 索引类型
     pub index_type: IndexType,
+    /// 全文索引的分词选项(仅 TEXT 索引)
+    #[serde(default)]
+    pub text_options: Option<TextIndexOptions>,
+    /// 索引键长度上限(字节),None 表示使用默认上限
+    ///
+    /// 语法: `WITH MAX_KEY_SIZE 512 OVERSIZED TRUNCATE`
+    #[serde(default)]
+    pub max_key_size: Option<usize>,
+    /// 索引键超过上限时的处理方式
+    #[serde(default)]
+    pub oversized_keys: OversizedKeyPolicy,
+}
+
+/// 全文索引的分词选项
+///
+/// 语法: `WITH TOKENIZER 'jieba' STOPWORDS 'chinese' STEMMER 'english'`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextIndexOptions {
+    /// 分词器名称,None 表示默认 Unicode 分词
+    pub tokenizer: Option<String>,
+    /// 停用词
+    pub stopwords: Option<TextStopWords>,
+    /// 词干提取语言
+    pub stemmer: Option<String>,
+}
+
+/// 全文索引停用词
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TextStopWords {
+    /// 内置停用词表名称 (`STOPWORDS 'english'`)
+    Builtin(String),
+    /// 自定义停用词列表 (`STOPWORDS ('a', 'the')`)
+    Custom(Vec<String>),
 }
 
 /// 索引字段
@@ -152,6 +346,92 @@ pub struct DropIndexStatement {
     pub collection: String,
 }
 
+/// CREATE COLLECTION 语句
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateCollectionStatement {
+    /// 集合名称
+    pub name: String,
+    /// 固定大小集合的限制,普通集合为 None
+    pub capped: Option<CappedOptions>,
+}
+
+/// CREATE SEQUENCE 语句
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateSequenceStatement {
+    /// 序列名称
+    pub name: String,
+    /// 第一个编号,默认 1
+    pub start: i64,
+    /// 相邻编号的差,默认 1
+    pub increment: i64,
+}
+
+/// CREATE FUNCTION 语句
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CreateFunctionStatement {
+    /// 函数名
+    pub name: String,
+    /// WASM 二进制模块
+    pub module: Vec<u8>,
+}
+
+/// 文档字面量中 NEXTVAL('name') 的占位键
+///
+/// 解析器把 `NEXTVAL('name')` 转换为 `{"$nextval": "name"}`,
+/// 执行 INSERT 和 UPDATE SET 时替换为序列的下一个编号。
+pub const NEXTVAL_KEY: &str = "$nextval";
+
+/// 参数占位符的占位键
+///
+/// 解析器把 `$1` 转换为 `{"$param": 1}`,把 `:name` 转换为 `{"$param": "name"}`,
+/// 执行前由 PreparedStatement 替换为绑定的值。
+pub const PARAM_KEY: &str = "$param";
+
+/// 参数占位符
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    /// 位置参数 `$1`、`$2`...,从 1 开始编号
+    Positional(usize),
+    /// 命名参数 `:name`
+    Named(String),
+}
+
+impl Parameter {
+    /// # Brief
+    /// 转换为 AST 中的占位文档
+    pub fn placeholder(&self) -> BomlValue {
+        let key = match self {
+            Parameter::Positional(index) => BomlValue::Int64(*index as i64),
+            Parameter::Named(name) => BomlValue::String(name.as_str().into()),
+        };
+        let mut doc = indexmap::IndexMap::new();
+        doc.insert(PARAM_KEY.into(), key);
+        BomlValue::Document(doc)
+    }
+
+    /// # Brief
+    /// 如果值是参数占位文档,返回对应的参数
+    pub fn from_placeholder(value: &BomlValue) -> Option<Parameter> {
+        match value {
+            BomlValue::Document(fields) if fields.len() == 1 => match fields.get(PARAM_KEY)? {
+                BomlValue::Int64(index) => Some(Parameter::Positional(*index as usize)),
+                BomlValue::String(name) => Some(Parameter::Named(name.to_string())),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Parameter::Positional(index) => write!(f, "${}", index),
+            Parameter::Named(name) => write!(f, ":{}", name),
+        }
+    }
+}
+
 /// INSERT 语句
 ///
 /// 向集合插入一个或多个文档。
@@ -161,6 +441,20 @@ pub struct InsertStatement {
     pub collection: String,
     /// 要插入的文档列表
     pub documents: Vec<BomlValue>,
+    /// 返回插入后的文档(RETURNING 子句)
+    #[serde(default)]
+    pub returning: Option<Returning>,
+}
+
+/// INSERT ... FIND 语句
+///
+/// 在服务端把源查询的结果分批写入目标集合,文档不经过客户端。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InsertSelectStatement {
+    /// 目标集合名称
+    pub collection: String,
+    /// 源查询
+    pub source: FindStatement,
 }
 
 /// FIND 语句
@@ -180,6 +474,12 @@ pub struct FindStatement {
     pub limit: Option<u64>,
     /// 跳过记录数(分页偏移)
     pub skip: Option<u64>,
+    /// 是否同时查询归档集合(WITH ARCHIVE 子句)
+    #[serde(default)]
+    pub include_archive: bool,
+    /// 游标每批返回的文档数(BATCH SIZE 子句)
+    #[serde(default)]
+    pub batch_size: Option<u32>,
 }
 
 impl Default for FindStatement {
@@ -191,6 +491,8 @@ impl Default for FindStatement {
             sort: None,
             limit: None,
             skip: None,
+            include_archive: false,
+            batch_size: None,
         }
     }
 }
@@ -205,7 +507,7 @@ pub struct SortField {
 }
 
 /// 排序顺序
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SortOrder {
     /// 升序(ASC)
     Ascending,
@@ -219,6 +521,19 @@ impl Default for SortOrder {
     }
 }
 
+/// RETURNING 子句
+///
+/// 写入语句在同一次请求中返回受影响的文档:
+/// INSERT 和 UPDATE 返回写入后的内容(UPDATE 可用 RETURNING OLD 返回更新前的内容),
+/// DELETE 返回删除前的内容。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Returning {
+    /// RETURNING *: 返回完整文档
+    All,
+    /// RETURNING field1, field2: 只返回 `_id` 和列出的字段
+    Fields(Vec<String>),
+}
+
 /// UPDATE 语句
 ///
 /// 更新集合中匹配条件的文档。
@@ -234,11 +549,24 @@ pub struct UpdateStatement {
     pub upsert: bool,
     /// 是否更新多条记录
     pub multi: bool,
+    /// 按此顺序选取要更新的文档(ORDER BY 子句)
+    #[serde(default)]
+    pub sort: Option<Vec<SortField>>,
+    /// 最多更新的文档数(LIMIT 子句)
+    #[serde(default)]
+    pub limit: Option<u64>,
+    /// 返回更新后的文档(RETURNING 子句)
+    #[serde(default)]
+    pub returning: Option<Returning>,
+    /// RETURNING OLD: 返回更新前的文档
+    #[serde(default)]
+    pub return_old: bool,
 }
 
 /// 更新操作
 ///
-/// 类似 MongoDB 的更新操作符。
+/// 类似 MongoDB 的更新操作符。字段为点分隔的路径,数字段表示数组下标(如 `items.0.qty`),
+/// `$` 段表示 WHERE 条件匹配的第一个数组元素(如 `items.$.qty`)。
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UpdateOperation {
     /// $set - 设置字段值
@@ -253,6 +581,30 @@ pub enum UpdateOperation {
     Pull { field: String, value: BomlValue },
     /// $rename - 重命名字段
     Rename { from: String, to: String },
+    /// $addToSet - 数组中没有相同元素时才添加
+    AddToSet { field: String, value: BomlValue },
+    /// $pop - 移除数组的第一个(first 为 true)或最后一个元素
+    Pop { field: String, first: bool },
+    /// $pullAll - 从数组移除与任一给定值相等的元素
+    PullAll { field: String, values: Vec<BomlValue> },
+}
+
+impl UpdateOperation {
+    /// # Brief
+    /// 操作涉及的字段路径,RENAME 依次返回原字段和新字段
+    pub fn fields(&self) -> Vec<&str> {
+        match self {
+            UpdateOperation::Set { field, .. }
+            | UpdateOperation::Unset { field }
+            | UpdateOperation::Inc { field, .. }
+            | UpdateOperation::Push { field, .. }
+            | UpdateOperation::Pull { field, .. }
+            | UpdateOperation::AddToSet { field, .. }
+            | UpdateOperation::Pop { field, .. }
+            | UpdateOperation::PullAll { field, .. } => vec![field],
+            UpdateOperation::Rename { from, to } => vec![from, to],
+        }
+    }
 }
 
 /// DELETE 语句
@@ -266,6 +618,79 @@ pub struct DeleteStatement {
     pub filter: Option<Expression>,
     /// 是否删除多条记录
     pub multi: bool,
+    /// 只删除创建时间(`_id` 中的时间戳)早于 now - older_than_secs 的文档
+    #[serde(default)]
+    pub older_than_secs: Option<u64>,
+    /// 按此顺序选取要删除的文档(ORDER BY 子句)
+    #[serde(default)]
+    pub sort: Option<Vec<SortField>>,
+    /// 最多删除的文档数(LIMIT 子句)
+    #[serde(default)]
+    pub limit: Option<u64>,
+    /// 返回被删除的文档(RETURNING 子句)
+    #[serde(default)]
+    pub returning: Option<Returning>,
+}
+
+/// ARCHIVE 语句
+///
+/// 把早于指定时间的文档迁移到压缩的归档集合。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveStatement {
+    /// 源集合名称
+    pub collection: String,
+    /// 归档集合名称
+    pub archive: String,
+    /// 归档阈值(秒),按文档 _id 中的创建时间判断
+    pub older_than_secs: u64,
+    /// 归档集合的压缩算法(默认 zstd)
+    pub compression: CompressionType,
+    /// 归档集合所在目录(可选,用于放到其他磁盘)
+    pub path: Option<String>,
+}
+
+/// BACKUP 语句
+///
+/// 在服务器端目录中创建一致的逻辑备份,或 SNAPSHOT 时创建物理快照。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupStatement {
+    /// 备份目录(服务器端路径)
+    pub path: String,
+    /// 是否包含用户、角色等系统数据(WITHOUT SYSTEM 时为 false)
+    pub include_system: bool,
+    /// 创建 RocksDB 检查点形式的物理快照(BACKUP SNAPSHOT),总是包含系统数据
+    pub snapshot: bool,
+}
+
+/// RESTORE 语句
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreStatement {
+    /// 备份目录(服务器端路径)
+    pub path: String,
+    /// 只恢复系统数据和元数据(METADATA ONLY)
+    pub metadata_only: bool,
+}
+
+/// ADMIN SET LOG LEVEL 语句
+///
+/// 运行时调整服务器日志级别,无需重启。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetLogLevelStatement {
+    /// 日志级别(trace/debug/info/warn/error/off)
+    pub level: String,
+    /// 只作用于指定模块路径前缀(例如 mikudb_storage),为空时调整默认级别
+    pub target: Option<String>,
+}
+
+/// SET 语句
+///
+/// 设置当前会话的变量,例如 `SET return_stats = true`,只在服务器连接上有效。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetVariableStatement {
+    /// 变量名(小写)
+    pub name: String,
+    /// 变量值
+    pub value: BomlValue,
 }
 
 /// AGGREGATE 语句
@@ -277,6 +702,9 @@ pub struct AggregateStatement {
     pub collection: String,
     /// 聚合管道阶段
     pub pipeline: Vec<AggregateStage>,
+    /// 游标每批返回的文档数(BATCH SIZE 子句)
+    #[serde(default)]
+    pub batch_size: Option<u32>,
 }
 
 /// 聚合管道阶段
@@ -311,8 +739,80 @@ pub enum AggregateStage {
         foreign_field: String,
         as_field: String,
     },
+    /// $graphLookup - 在集合内沿字段递归关联(如 parent_id 组成的树)
+    GraphLookup(GraphLookup),
     /// $count - 计数
     Count(String),
+    /// $facet - 对同一组输入文档执行多个子管道,结果合并为一个文档
+    Facet(Vec<Facet>),
+    /// $bucket - 按给定边界把文档分到区间中
+    Bucket(Bucket),
+    /// $bucketAuto - 按文档数把文档均分到若干区间中
+    BucketAuto(BucketAuto),
+}
+
+/// BUCKET 阶段
+///
+/// 第 i 个桶包含 `boundaries[i] <= group_by < boundaries[i + 1]` 的文档,输出文档的 `_id` 为下界。
+/// 不落在任何桶中(包括字段缺失或类型无法比较)的文档归入 `default` 桶,未指定时报错。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    /// 分桶字段
+    pub group_by: String,
+    /// 升序的桶边界,至少两个
+    pub boundaries: Vec<BomlValue>,
+    /// 其他文档所在桶的 `_id`
+    pub default: Option<BomlValue>,
+    /// 每个桶的累加器,为空时输出文档数 `count`
+    pub output: Vec<Accumulator>,
+}
+
+/// BUCKET AUTO 阶段
+///
+/// 按分桶字段排序后把文档分成 `buckets` 个文档数相近的桶,相同的值不会跨桶,
+/// 因此实际桶数可能更少。输出文档的 `_id` 为 `{min, max}`,`max` 是下一个桶的 `min`
+/// (最后一个桶为其中的最大值)。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BucketAuto {
+    /// 分桶字段
+    pub group_by: String,
+    /// 期望的桶数
+    pub buckets: u32,
+    /// 每个桶的累加器,为空时输出文档数 `count`
+    pub output: Vec<Accumulator>,
+}
+
+/// FACET 阶段中的一个子管道
+///
+/// 子管道的输入是 FACET 阶段的全部输入文档,输出写入结果文档的 `name` 字段(数组)。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Facet {
+    /// 结果文档中的字段名
+    pub name: String,
+    /// 子管道阶段,不能包含 FACET
+    pub pipeline: Vec<AggregateStage>,
+}
+
+/// GRAPH LOOKUP 阶段
+///
+/// 以输入文档的 `start_with` 值为起点,在 `from` 集合中查找 `connect_to` 与之相等的文档,
+/// 再以这些文档的 `connect_from` 值继续查找,直到没有新文档或达到 `max_depth`。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphLookup {
+    /// 被关联的集合
+    pub from: String,
+    /// 输入文档中作为起点的字段
+    pub start_with: String,
+    /// 关联到的文档中继续递归的字段
+    pub connect_from: String,
+    /// 被关联集合中与起点/递归值匹配的字段
+    pub connect_to: String,
+    /// 结果数组写入的字段
+    pub as_field: String,
+    /// 最大递归深度,0 表示只关联一层,None 表示不限
+    pub max_depth: Option<u32>,
+    /// 结果文档中记录递归深度(从 0 开始)的字段
+    pub depth_field: Option<String>,
 }
 
 /// 投影字段
@@ -358,6 +858,8 @@ pub enum AggregateFunction {
     Push,
     /// ADDTOSET - 收集到集合(去重)
     AddToSet,
+    /// 注册到执行器的自定义累加器函数,保存小写的函数名
+    Custom(String),
 }
 
 /// 表达式
@@ -415,6 +917,8 @@ pub enum Expression {
     Array(Vec<Expression>),
     /// 文档字面量
     Document(Vec<(String, Expression)>),
+    /// 当前文档,CALL FUNCTION 参数中的 `doc`
+    CurrentDocument,
 }
 
 impl Expression {
@@ -518,6 +1022,74 @@ impl Expression {
             expr: Box::new(expr),
         }
     }
+
+    /// # Brief
+    /// 收集表达式引用的所有字段路径
+    ///
+    /// 引用整个文档(`CurrentDocument`)时收集空路径,表示需要所有字段。
+    ///
+    /// # Arguments
+    /// * `fields` - 输出列表,字段按出现顺序追加,可能重复
+    pub fn collect_fields(&self, fields: &mut Vec<String>) {
+        match self {
+            Expression::Literal(_) => {}
+            Expression::CurrentDocument => fields.push(String::new()),
+            Expression::Field(field) | Expression::Exists { field, .. } => fields.push(field.clone()),
+            Expression::Binary { left, right, .. } => {
+                left.collect_fields(fields);
+                right.collect_fields(fields);
+            }
+            Expression::Unary { expr, .. }
+            | Expression::Like { expr, .. }
+            | Expression::IsNull { expr, .. } => expr.collect_fields(fields),
+            Expression::In { expr, list } => {
+                expr.collect_fields(fields);
+                list.iter().for_each(|item| item.collect_fields(fields));
+            }
+            Expression::Between { expr, low, high } => {
+                expr.collect_fields(fields);
+                low.collect_fields(fields);
+                high.collect_fields(fields);
+            }
+            Expression::Call { args: items, .. } | Expression::Array(items) => {
+                items.iter().for_each(|item| item.collect_fields(fields));
+            }
+            Expression::Document(entries) => {
+                entries.iter().for_each(|(_, value)| value.collect_fields(fields));
+            }
+        }
+    }
+
+    /// # Brief
+    /// 对表达式引用的每个字段路径调用 `f`,可以原地改写路径
+    pub fn rewrite_fields(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Expression::Literal(_) | Expression::CurrentDocument => {}
+            Expression::Field(field) | Expression::Exists { field, .. } => f(field),
+            Expression::Binary { left, right, .. } => {
+                left.rewrite_fields(f);
+                right.rewrite_fields(f);
+            }
+            Expression::Unary { expr, .. }
+            | Expression::Like { expr, .. }
+            | Expression::IsNull { expr, .. } => expr.rewrite_fields(f),
+            Expression::In { expr, list } => {
+                expr.rewrite_fields(f);
+                list.iter_mut().for_each(|item| item.rewrite_fields(f));
+            }
+            Expression::Between { expr, low, high } => {
+                expr.rewrite_fields(f);
+                low.rewrite_fields(f);
+                high.rewrite_fields(f);
+            }
+            Expression::Call { args: items, .. } | Expression::Array(items) => {
+                items.iter_mut().for_each(|item| item.rewrite_fields(f));
+            }
+            Expression::Document(entries) => {
+                entries.iter_mut().for_each(|(_, value)| value.rewrite_fields(f));
+            }
+        }
+    }
 }
 
 /// 二元操作符
@@ -594,6 +1166,31 @@ pub struct CreateUserStatement {
     pub roles: Vec<String>,
 }
 
+/// ALTER COLLECTION 语句
+///
+/// 未指定的选项保持不变。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlterCollectionStatement {
+    /// 集合名称
+    pub collection: String,
+    /// 是否记录字段类型并警告类型漂移
+    pub track_types: Option<bool>,
+    /// 是否拒绝改变字段主类型的写入
+    pub strict_types: Option<bool>,
+    /// 按字段的文档过期设置
+    #[serde(default)]
+    pub expire: Option<ExpireSetting>,
+}
+
+/// 集合的文档过期设置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExpireSetting {
+    /// 字段时间早于当前时间的文档被后台删除
+    AfterField(String),
+    /// 关闭过期
+    Off,
+}
+
 /// ALTER USER 语句
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AlterUserStatement {
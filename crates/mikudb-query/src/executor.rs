@@ -1,15 +1,55 @@
 //! 查询执行器模块
 //!
 //! 负责执行解析后的 MQL 语句，包括 CRUD 操作、聚合查询等。
-
+//! INSERT 和 UPDATE SET 中的 `NEXTVAL('name')` 在写入前替换为序列的下一个编号。
+//! INSERT 的全部文档及其索引项通过一个 WriteBatch 原子写入。
+//! INSERT INTO ... FIND 在服务端把查询结果分批写入目标集合,每批是一次原子写入。
+//! 按 `_id` 更新且只有数值 `+=` 的 UPDATE 通过存储层的合并算子只写入增量。
+//! 其他按 `_id` 等值过滤的 UPDATE / DELETE 直接按 ID 读取目标文档,不扫描集合。
+//! 只更新一个文档的 UPDATE(FIND AND MODIFY)持有集合的修改锁原子地读取-修改-写入,可返回更新前或更新后的文档。
+//! 启用语句统计后记录每次执行检查/返回的文档数、使用的索引、各阶段耗时和读取字节数。
+//! 带投影的 FIND 全集合扫描时只解码投影、过滤和排序用到的字段。
+//! 过滤条件和 GROUP 阶段可以调用注册到 `functions()` 的自定义函数。
+//! 设置事务上下文后,INSERT / UPDATE / DELETE 记录到事务中,FIND 和 AGGREGATE 可见事务自己的写入。
+//! 设置当前数据库后,语句中的集合名按数据库加前缀,只访问该数据库的集合。
+
+use crate::advisor::ADVISOR_COLLECTION;
 use crate::ast::*;
 use crate::filter;
-use crate::planner::QueryPlanner;
+use crate::opstats::{CollectionOpStats, OpKind, OpStats};
+use crate::planner::{CollectionStats, HistogramKind, IndexLookup, PlanNode, QueryPlanner, StatsCollector};
+use crate::stmtstats::StatementStats;
+use crate::txn::TransactionContext;
+use crate::udf::FunctionRegistry;
 use crate::{QueryError, QueryResult};
-use mikudb_boml::{BomlValue, Document};
-use mikudb_storage::StorageEngine;
-use std::collections::HashMap;
+use mikudb_boml::{BomlError, BomlValue, Document};
+use mikudb_common::ObjectId;
+use mikudb_storage::backup::{self, BackupOptions, RestoreOptions, RestoreScope};
+use mikudb_storage::merge::{self, RULE_UPDATE_INC};
+use mikudb_storage::{
+    qualified_collection_name, ArchivePolicy, CacheStats, Collection, IndexDefinition, IndexField as StorageIndexField, IndexOrder,
+    IndexType as StorageIndexType, KeyEncoding, OversizedKeyPolicy, StopWords, StorageEngine, StorageError, TextAnalyzer,
+    ReadBytesMeter, TokenizerType, ValidationDetail, DEFAULT_DATABASE,
+};
+use parking_lot::Mutex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
+
+/// DRY RUN 返回的样例文档 ID 数量上限
+const DRY_RUN_SAMPLE_SIZE: usize = 10;
+
+/// INSERT ... FIND 未指定 BATCH SIZE 时每批写入的文档数
+const INSERT_SELECT_BATCH_SIZE: usize = 1000;
+
+/// 对非数组字段执行 PUSH / ADDTOSET 时的校验规则 ID
+const RULE_UPDATE_PUSH: &str = "update.push";
+
+/// 更新路径经过标量值或在数组上使用非数字下标时的校验规则 ID
+const RULE_UPDATE_PATH: &str = "update.path";
 
 /// 查询执行器
 ///
@@ -17,6 +57,14 @@ use std::sync::Arc;
 pub struct QueryExecutor {
     storage: Arc<StorageEngine>,
     planner: QueryPlanner,
+    interrupt: Option<Arc<AtomicBool>>,
+    op_stats: Option<Arc<OpStats>>,
+    auto_create_collections: bool,
+    statement_stats: Option<Mutex<StatementStats>>,
+    functions: Arc<FunctionRegistry>,
+    transaction: Option<Arc<dyn TransactionContext>>,
+    database: Option<String>,
+    backup_dir: Option<PathBuf>,
 }
 
 impl QueryExecutor {
@@ -34,6 +82,208 @@ impl QueryExecutor {
         Self {
             storage,
             planner: QueryPlanner::new(),
+            interrupt: None,
+            op_stats: None,
+            auto_create_collections: true,
+            statement_stats: None,
+            functions: Arc::new(FunctionRegistry::new()),
+            transaction: None,
+            database: None,
+            backup_dir: None,
+        }
+    }
+
+    /// # Brief
+    /// 设置中断标志
+    ///
+    /// 标志被置位后,执行器会在扫描、过滤、写入等阶段之间尽快返回 Interrupted 错误,
+    /// 用于实现查询超时和 KillOp。已写入的文档不会回滚。
+    ///
+    /// # Arguments
+    /// * `interrupt` - 由调用方持有并在需要中断时置位的标志
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// # Brief
+    /// 设置按集合的操作统计
+    ///
+    /// 设置后执行器记录每个集合的操作次数、扫描方式、文档数和耗时,
+    /// 并支持 STATS / RESET STATS 语句。
+    ///
+    /// # Arguments
+    /// * `op_stats` - 由调用方共享的统计
+    pub fn with_op_stats(mut self, op_stats: Arc<OpStats>) -> Self {
+        self.op_stats = Some(op_stats);
+        self
+    }
+
+    /// # Brief
+    /// 设置写入不存在的集合时是否自动创建
+    ///
+    /// 默认自动创建。关闭后 INSERT 和 CREATE INDEX 要求集合已通过 CREATE COLLECTION 创建,
+    /// 否则返回 CollectionNotFound。
+    ///
+    /// # Arguments
+    /// * `enabled` - 是否自动创建
+    pub fn with_auto_create_collections(mut self, enabled: bool) -> Self {
+        self.auto_create_collections = enabled;
+        self
+    }
+
+    /// # Brief
+    /// 记录语句的资源统计
+    ///
+    /// 启用后每次 `execute` 重新统计,执行结束后通过 `statement_stats` 读取。
+    pub fn with_statement_stats(mut self) -> Self {
+        self.statement_stats = Some(Mutex::new(StatementStats::default()));
+        self
+    }
+
+    /// # Brief
+    /// 获取最近一次执行的资源统计
+    ///
+    /// # Returns
+    /// 未启用语句统计时为 None;解析耗时需由调用方填写
+    pub fn statement_stats(&self) -> Option<StatementStats> {
+        self.statement_stats.as_ref().map(|stats| stats.lock().clone())
+    }
+
+    /// # Brief
+    /// 使用共享的自定义函数注册表
+    ///
+    /// 默认每个执行器有自己的空注册表。多个执行器需要调用同一组函数时共享一个注册表。
+    ///
+    /// # Arguments
+    /// * `functions` - 自定义函数注册表
+    pub fn with_functions(mut self, functions: Arc<FunctionRegistry>) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// # Brief
+    /// 获取自定义函数注册表,用于注册标量和累加器函数
+    pub fn functions(&self) -> &Arc<FunctionRegistry> {
+        &self.functions
+    }
+
+    /// # Brief
+    /// 在事务中执行语句
+    ///
+    /// 设置后 INSERT、INSERT ... FIND、UPDATE、DELETE 记录到事务的写集合,提交时才写入存储;
+    /// FIND、AGGREGATE 以及 UPDATE / DELETE 选取文档时读取事务视图,可见事务自己尚未提交的写入。
+    /// 事务内的读取不使用索引,UPDATE 也不走增量合并。
+    ///
+    /// # Arguments
+    /// * `transaction` - 当前活动的事务
+    pub fn with_transaction(mut self, transaction: Arc<dyn TransactionContext>) -> Self {
+        self.transaction = Some(transaction);
+        self
+    }
+
+    /// # Brief
+    /// 设置 BACKUP / RESTORE 的根目录
+    ///
+    /// 设置后语句中的路径按根目录下的相对路径解析,绝对路径和包含 `..` 的路径被拒绝;
+    /// 未设置时(嵌入式使用)路径按原样使用。
+    ///
+    /// # Arguments
+    /// * `dir` - 备份根目录
+    pub fn with_backup_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.backup_dir = Some(dir.into());
+        self
+    }
+
+    /// # Brief
+    /// 设置当前数据库
+    ///
+    /// 语句中的集合名按 `qualified_collection_name` 换成存储中的名称,SHOW COLLECTIONS
+    /// 只列出该数据库的集合。未设置时使用默认数据库。
+    ///
+    /// # Arguments
+    /// * `database` - 数据库名称
+    pub fn with_database(mut self, database: impl Into<String>) -> Self {
+        self.database = Some(database.into());
+        self
+    }
+
+    /// # Brief
+    /// 当前数据库名称
+    pub fn database(&self) -> &str {
+        self.database.as_deref().unwrap_or(DEFAULT_DATABASE)
+    }
+
+    /// # Brief
+    /// 把语句中的集合名换成当前数据库中集合在存储中的名称
+    ///
+    /// 管理数据库本身的语句原样返回;当前数据库已被删除时返回 DatabaseNotFound。
+    fn in_database<'a>(&self, stmt: &'a Statement) -> QueryResult<Cow<'a, Statement>> {
+        let database = self.database();
+        if database == DEFAULT_DATABASE
+            || matches!(
+                stmt,
+                Statement::Use(_)
+                    | Statement::ShowDatabases
+                    | Statement::ShowManifest
+                    | Statement::CreateDatabase(_)
+                    | Statement::DropDatabase(_)
+            )
+        {
+            return Ok(Cow::Borrowed(stmt));
+        }
+        if !self.storage.database_exists(database)? {
+            return Err(StorageError::DatabaseNotFound(database.to_string()).into());
+        }
+        let mut stmt = stmt.clone();
+        stmt.rewrite_collections(&mut |collection| *collection = qualified_collection_name(database, collection));
+        Ok(Cow::Owned(stmt))
+    }
+
+    /// # Brief
+    /// 存储中的集合名在当前数据库中的名称
+    fn local_name<'a>(&self, collection: &'a str) -> &'a str {
+        match self.database() {
+            DEFAULT_DATABASE => collection,
+            database => collection
+                .strip_prefix(database)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .unwrap_or(collection),
+        }
+    }
+
+    /// # Brief
+    /// 创建可以调用自定义函数的过滤器
+    fn filter(&self, expr: &Expression) -> filter::Filter {
+        filter::Filter::new(expr.clone()).with_functions(self.functions.clone())
+    }
+
+    /// # Brief
+    /// 启用语句统计时更新统计
+    fn track(&self, f: impl FnOnce(&mut StatementStats)) {
+        if let Some(stats) = &self.statement_stats {
+            f(&mut stats.lock());
+        }
+    }
+
+    /// # Brief
+    /// 获取写入的目标集合,按配置决定不存在时是否自动创建
+    fn target_collection(&self, name: &str) -> QueryResult<Arc<Collection>> {
+        if self.auto_create_collections {
+            return Ok(self.storage.get_or_create_collection(name)?);
+        }
+        match self.storage.get_collection(name) {
+            Err(StorageError::CollectionNotFound(_)) => Err(QueryError::CollectionNotFound(name.to_string())),
+            result => Ok(result?),
+        }
+    }
+
+    /// # Brief
+    /// 检查是否已被请求中断
+    fn check_interrupt(&self) -> QueryResult<()> {
+        match &self.interrupt {
+            Some(flag) if flag.load(Ordering::Relaxed) => Err(QueryError::Interrupted),
+            _ => Ok(()),
         }
     }
 
@@ -48,24 +298,64 @@ impl QueryExecutor {
     /// # Returns
     /// 执行结果 QueryResponse，或错误
     pub fn execute(&self, stmt: &Statement) -> QueryResult<QueryResponse> {
+        let stmt = self.in_database(stmt)?;
+        let stmt = stmt.as_ref();
+        let Some(stats) = &self.statement_stats else {
+            return self.dispatch(stmt);
+        };
+
+        *stats.lock() = StatementStats::default();
+        let meter = ReadBytesMeter::start();
+        let start = Instant::now();
+        let result = self.dispatch(stmt);
+        let mut stats = stats.lock();
+        stats.execute_us = (start.elapsed().as_micros() as u64).saturating_sub(stats.plan_us);
+        stats.bytes_read = meter.bytes_read();
+        stats.docs_returned = match &result {
+            Ok(QueryResponse::Documents(docs)) => docs.len() as u64,
+            Ok(QueryResponse::Returning { documents, .. }) => documents.len() as u64,
+            _ => 0,
+        };
+        result
+    }
+
+    fn dispatch(&self, stmt: &Statement) -> QueryResult<QueryResponse> {
         match stmt {
             Statement::Use(use_stmt) => {
+                if !self.storage.database_exists(&use_stmt.database)? {
+                    return Err(StorageError::DatabaseNotFound(use_stmt.database.clone()).into());
+                }
                 Ok(QueryResponse::Ok {
                     message: format!("Switched to database: {}", use_stmt.database),
                 })
             }
 
             Statement::ShowDatabases => {
-                Ok(QueryResponse::Databases(vec!["default".to_string()]))
+                Ok(QueryResponse::Databases(self.storage.list_databases()?))
             }
 
             Statement::ShowCollections => {
-                let collections = self.storage.list_collections()?;
+                let collections = self.storage.database_collections(self.database())?;
                 Ok(QueryResponse::Collections(collections))
             }
 
             Statement::ShowIndexes(collection) => {
-                Ok(QueryResponse::Indexes(vec![]))
+                let engine = self.storage.indexes();
+                let mut indexes: Vec<IndexInfo> = engine
+                    .list_indexes(collection)
+                    .into_iter()
+                    .map(|definition| IndexInfo {
+                        collection: self.local_name(&definition.collection).to_string(),
+                        oversized_keys: engine.oversized_keys(&definition.name),
+                        name: definition.name,
+                        fields: definition.fields.into_iter().map(|field| field.path).collect(),
+                        unique: definition.unique,
+                        max_key_size: definition.max_key_size,
+                        oversized_policy: definition.oversized_keys,
+                    })
+                    .collect();
+                indexes.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(QueryResponse::Indexes(indexes))
             }
 
             Statement::ShowStatus => {
@@ -74,52 +364,175 @@ impl QueryExecutor {
                 Ok(QueryResponse::Status {
                     size,
                     stats: stats.unwrap_or_default(),
+                    document_cache: self.storage.document_cache_stats(),
                 })
             }
 
             Statement::CreateDatabase(name) => {
+                self.storage.create_database(name)?;
                 Ok(QueryResponse::Ok {
                     message: format!("Created database: {}", name),
                 })
             }
 
             Statement::DropDatabase(name) => {
+                let dropped = self.storage.drop_database(name)?;
                 Ok(QueryResponse::Ok {
-                    message: format!("Dropped database: {}", name),
+                    message: format!("Dropped database: {} ({} collection(s))", name, dropped),
                 })
             }
 
-            Statement::CreateCollection(name) => {
-                self.storage.create_collection(name)?;
+            Statement::CreateCollection(create) => {
+                match create.capped {
+                    Some(capped) => self.storage.create_capped_collection(&create.name, capped)?,
+                    None => self.storage.create_collection(&create.name)?,
+                };
                 Ok(QueryResponse::Ok {
-                    message: format!("Created collection: {}", name),
+                    message: format!("Created collection: {}", create.name),
                 })
             }
 
             Statement::DropCollection(name) => {
                 self.storage.drop_collection(name)?;
+                if let Some(op_stats) = &self.op_stats {
+                    op_stats.remove(name);
+                }
                 Ok(QueryResponse::Ok {
                     message: format!("Dropped collection: {}", name),
                 })
             }
 
-            Statement::CreateIndex(create_idx) => {
+            Statement::AlterCollection(alter) => self.execute_alter_collection(alter),
+            Statement::ShowSchema(collection) => self.execute_show_schema(collection),
+
+            Statement::CreateIndex(create_idx) => self.execute_create_index(create_idx),
+
+            Statement::DropIndex(drop_idx) => {
+                let indexes = self.storage.indexes();
+                match indexes.get_index(&drop_idx.name) {
+                    Some(definition) if definition.collection == drop_idx.collection => {
+                        indexes.drop_index(&drop_idx.name)?;
+                    }
+                    // 全文索引不经过索引引擎
+                    None => {}
+                    Some(_) => {
+                        return Err(QueryError::Execution(format!(
+                            "Index {} does not belong to collection {}",
+                            drop_idx.name, drop_idx.collection
+                        )))
+                    }
+                }
+                Ok(QueryResponse::Ok {
+                    message: format!("Dropped index: {}", drop_idx.name),
+                })
+            }
+
+            Statement::CreateSequence(seq) => {
+                self.storage.create_sequence(&seq.name, seq.start, seq.increment)?;
                 Ok(QueryResponse::Ok {
-                    message: format!("Created index: {}", create_idx.name),
+                    message: format!("Created sequence: {}", seq.name),
                 })
             }
 
-            Statement::DropIndex(drop_idx) => {
+            Statement::DropSequence(name) => {
+                self.storage.drop_sequence(name)?;
                 Ok(QueryResponse::Ok {
-                    message: format!("Dropped index: {}", drop_idx.name),
+                    message: format!("Dropped sequence: {}", name),
+                })
+            }
+
+            Statement::ShowSequences => self.execute_show_sequences(),
+
+            Statement::CreateRollup(rollup) => {
+                let groups = self.storage.create_rollup(rollup.clone())?;
+                Ok(QueryResponse::Ok {
+                    message: format!("Created rollup: {} ({} groups)", rollup.name, groups),
+                })
+            }
+
+            Statement::DropRollup(name) => {
+                self.storage.drop_rollup(name)?;
+                Ok(QueryResponse::Ok {
+                    message: format!("Dropped rollup: {}", name),
                 })
             }
 
-            Statement::Insert(insert) => self.execute_insert(insert),
-            Statement::Find(find) => self.execute_find(find),
-            Statement::Update(update) => self.execute_update(update),
-            Statement::Delete(delete) => self.execute_delete(delete),
-            Statement::Aggregate(agg) => self.execute_aggregate(agg),
+            Statement::ShowRollups => self.execute_show_rollups(),
+            Statement::ShowManifest => self.execute_show_manifest(),
+
+            Statement::CreateFunction(function) => {
+                let name = function.name.to_lowercase();
+                self.functions.register_wasm(&name, &function.module)?;
+                if let Err(e) = self.storage.save_function(&name, &function.module) {
+                    self.functions.unregister(&name);
+                    return Err(e.into());
+                }
+                Ok(QueryResponse::Ok {
+                    message: format!("Created function: {}", name),
+                })
+            }
+
+            Statement::DropFunction(name) => {
+                // Rust 注册的函数属于嵌入程序,只能删除 WASM 函数
+                if !self.functions.is_wasm(name) {
+                    return Err(QueryError::Execution(format!("WASM function not found: {}", name)));
+                }
+                let name = name.to_lowercase();
+                self.storage.drop_function(&name)?;
+                self.functions.unregister(&name);
+                Ok(QueryResponse::Ok {
+                    message: format!("Dropped function: {}", name),
+                })
+            }
+
+            Statement::ShowFunctions => {
+                let docs = self
+                    .functions
+                    .list()
+                    .into_iter()
+                    .map(|(name, kind)| {
+                        let mut doc = Document::without_id();
+                        doc.insert("name", name);
+                        doc.insert("kind", kind);
+                        doc
+                    })
+                    .collect();
+                Ok(QueryResponse::Documents(docs))
+            }
+
+            Statement::Insert(insert) => {
+                self.timed(&insert.collection, OpKind::Insert, || self.execute_insert(insert))
+            }
+            Statement::InsertSelect(insert) => {
+                self.timed(&insert.collection, OpKind::Insert, || self.execute_insert_select(insert))
+            }
+            Statement::Find(find) => self.timed(&find.collection, OpKind::Find, || self.execute_find(find)),
+            Statement::Update(update) => {
+                self.timed(&update.collection, OpKind::Update, || self.execute_update(update))
+            }
+            Statement::Delete(delete) => {
+                self.timed(&delete.collection, OpKind::Delete, || self.execute_delete(delete))
+            }
+            Statement::Aggregate(agg) => {
+                self.timed(&agg.collection, OpKind::Aggregate, || self.execute_aggregate(agg))
+            }
+            Statement::Archive(archive) => self.execute_archive(archive),
+            Statement::Backup(backup) => self.execute_backup(backup),
+            Statement::Restore(restore) => self.execute_restore(restore),
+            Statement::DryRun(inner) => self.execute_dry_run(inner),
+            Statement::ShowAdvisor(collection) => self.execute_show_advisor(collection.as_deref()),
+            Statement::Stats(collection) => self.execute_stats(collection),
+            Statement::Analyze(collection) => self.execute_analyze(collection),
+            Statement::Compact(collection) => self.execute_compact(collection),
+            Statement::ResetStats(collection) => {
+                let reset = self.require_op_stats()?.reset(collection.as_deref());
+                Ok(QueryResponse::Ok {
+                    message: match collection {
+                        Some(name) => format!("Reset operation statistics of {}", name),
+                        None => format!("Reset operation statistics of {} collection(s)", reset),
+                    },
+                })
+            }
 
             Statement::BeginTransaction => {
                 Ok(QueryResponse::Ok {
@@ -149,145 +562,1266 @@ impl QueryExecutor {
                 "User management statements are only supported in server mode".to_string(),
             )),
 
+            Statement::SetLogLevel(_) | Statement::ResetLogLevel => Err(QueryError::Execution(
+                "Log level statements are only supported in server mode".to_string(),
+            )),
+
+            Statement::ClusterInit | Statement::ClusterJoin(_) => Err(QueryError::Execution(
+                "Cluster statements are only supported in server mode".to_string(),
+            )),
+
+            Statement::SetVariable(_) => Err(QueryError::Execution(
+                "Session variables are only supported in server mode".to_string(),
+            )),
+
             _ => Err(QueryError::Internal("Not implemented".to_string())),
         }
     }
 
+    /// # Brief
+    /// 执行一次集合操作并记录操作统计
+    ///
+    /// 只统计成功执行的操作;返回/写入的文档数从执行结果中获取。
+    fn timed<F>(&self, collection: &str, kind: OpKind, f: F) -> QueryResult<QueryResponse>
+    where
+        F: FnOnce() -> QueryResult<QueryResponse>,
+    {
+        let start = Instant::now();
+        let result = f()?;
+        if let Some(op_stats) = &self.op_stats {
+            match &result {
+                QueryResponse::Documents(docs) => op_stats.record_returned(collection, docs.len() as u64),
+                QueryResponse::Insert { inserted_count, .. } => op_stats.record_written(collection, *inserted_count),
+                QueryResponse::Update { modified_count, .. } => op_stats.record_written(collection, *modified_count),
+                QueryResponse::Delete { deleted_count } => op_stats.record_written(collection, *deleted_count),
+                QueryResponse::Returning { affected_count, .. } => op_stats.record_written(collection, *affected_count),
+                _ => {}
+            }
+            op_stats.record_op(collection, kind, start.elapsed());
+        }
+        Ok(result)
+    }
+
+    /// # Brief
+    /// 全集合扫描,并记录到操作统计
+    fn scan(&self, collection: &Collection) -> QueryResult<Vec<Document>> {
+        let docs = collection.find_all()?;
+        if let Some(op_stats) = &self.op_stats {
+            op_stats.record_scan(collection.name(), docs.len() as u64);
+        }
+        self.track(|stats| stats.docs_examined += docs.len() as u64);
+        Ok(docs)
+    }
+
+    /// # Brief
+    /// 全集合扫描,只解码 `fields` 用到的字段
+    fn scan_projected(&self, collection: &Collection, fields: &[String]) -> QueryResult<Vec<Document>> {
+        let docs = collection.find_all_projected(fields)?;
+        if let Some(op_stats) = &self.op_stats {
+            op_stats.record_scan(collection.name(), docs.len() as u64);
+        }
+        self.track(|stats| stats.docs_examined += docs.len() as u64);
+        Ok(docs)
+    }
+
+    /// # Brief
+    /// 通过索引读取候选文档,并记录到操作统计
+    ///
+    /// 索引读取是乐观的,调用方需要重新应用过滤条件。
+    fn index_scan(&self, collection: &Collection, index_name: &str, lookup: &IndexLookup) -> QueryResult<Vec<Document>> {
+        let indexes = self.storage.indexes();
+        let docs = match lookup {
+            IndexLookup::Eq(values) => indexes.lookup_documents(collection, index_name, values)?,
+            IndexLookup::Range { start, end, inclusive } => indexes.range_query_documents(
+                collection,
+                index_name,
+                start.as_deref(),
+                end.as_deref(),
+                *inclusive,
+            )?,
+        };
+        if let Some(op_stats) = &self.op_stats {
+            op_stats.record_index_hit(collection.name(), docs.len() as u64);
+        }
+        self.track(|stats| {
+            stats.docs_examined += docs.len() as u64;
+            stats.index_used = Some(index_name.to_string());
+        });
+        Ok(docs)
+    }
+
+    /// # Brief
+    /// 只扫描创建时间早于 now - older_than_secs 的文档
+    fn scan_created_before(&self, collection: &Collection, older_than_secs: u64) -> QueryResult<Vec<Document>> {
+        let docs = collection.find_created_before(created_before_cutoff(older_than_secs))?;
+        if let Some(op_stats) = &self.op_stats {
+            op_stats.record_scan(collection.name(), docs.len() as u64);
+        }
+        self.track(|stats| stats.docs_examined += docs.len() as u64);
+        Ok(docs)
+    }
+
+    /// # Brief
+    /// 读取事务视图中集合的全部文档,并记录到操作统计
+    fn transaction_scan(&self, txn: &dyn TransactionContext, collection: &str) -> QueryResult<Vec<Document>> {
+        let docs = txn.find_all(collection)?;
+        if let Some(op_stats) = &self.op_stats {
+            op_stats.record_scan(collection, docs.len() as u64);
+        }
+        self.track(|stats| stats.docs_examined += docs.len() as u64);
+        Ok(docs)
+    }
+
+    /// # Brief
+    /// 在事务视图中选出 UPDATE / DELETE 要修改的文档
+    ///
+    /// 参数含义同 `mutation_targets`,创建时间取自文档 `_id` 的时间戳。
+    fn transaction_targets(
+        &self,
+        txn: &dyn TransactionContext,
+        collection: &str,
+        older_than_secs: Option<u64>,
+        filter: Option<&Expression>,
+        sort: Option<&[SortField]>,
+        limit: Option<u64>,
+    ) -> QueryResult<Vec<Document>> {
+        let mut docs = self.transaction_scan(txn, collection)?;
+        if let Some(secs) = older_than_secs {
+            let cutoff = created_before_cutoff(secs);
+            docs.retain(|doc| doc.id().is_some_and(|id| u64::from(id.timestamp()) < cutoff));
+        }
+        if let Some(filter) = filter {
+            let filter = self.filter(filter);
+            docs.retain(|doc| filter.matches(doc).unwrap_or(false));
+        }
+        self.check_interrupt()?;
+        if let Some(sort) = sort {
+            sort_documents(&mut docs, sort);
+        }
+        if let Some(limit) = limit {
+            docs.truncate(limit as usize);
+        }
+        Ok(docs)
+    }
+
+    /// # Brief
+    /// 选出 UPDATE / DELETE 要修改的文档
+    ///
+    /// 过滤条件为 `_id = <id>` 时按 ID 直接读取,不扫描集合。
+    /// 有 LIMIT 且没有 ORDER BY(或只按 `_id` 升序)时按键顺序逐个扫描,凑够 LIMIT 个匹配文档即停止,
+    /// 分批清理时每轮只读取集合开头的一小段;其他情况扫描全部候选文档后排序再截断。
+    ///
+    /// # Arguments
+    /// * `collection` - 目标集合
+    /// * `older_than_secs` - 只考虑创建时间早于 now - older_than_secs 的文档
+    /// * `filter` - WHERE 条件
+    /// * `sort` - ORDER BY 字段
+    /// * `limit` - 文档数上限
+    ///
+    /// # Returns
+    /// 按修改顺序排列的文档
+    fn mutation_targets(
+        &self,
+        collection: &Collection,
+        older_than_secs: Option<u64>,
+        filter: Option<&Expression>,
+        sort: Option<&[SortField]>,
+        limit: Option<u64>,
+    ) -> QueryResult<Vec<Document>> {
+        if let (Some(id), None) = (filter.and_then(id_equality), older_than_secs) {
+            let mut docs: Vec<Document> = collection.get(&id)?.into_iter().collect();
+            self.track(|stats| stats.docs_examined += docs.len() as u64);
+            if limit == Some(0) {
+                docs.clear();
+            }
+            return Ok(docs);
+        }
+
+        let filter = filter.map(|expr| self.filter(expr));
+        let matches = |doc: &Document| filter.as_ref().map_or(true, |f| f.matches(doc).unwrap_or(false));
+
+        if let (Some(limit), None) = (limit, older_than_secs) {
+            if sort.map_or(true, is_id_order) {
+                let mut docs = Vec::new();
+                let mut scanned = 0u64;
+                for doc in collection.iter()? {
+                    if docs.len() as u64 >= limit {
+                        break;
+                    }
+                    self.check_interrupt()?;
+                    let doc = doc?;
+                    scanned += 1;
+                    if matches(&doc) {
+                        docs.push(doc);
+                    }
+                }
+                if let Some(op_stats) = &self.op_stats {
+                    op_stats.record_scan(collection.name(), scanned);
+                }
+                self.track(|stats| stats.docs_examined += scanned);
+                return Ok(docs);
+            }
+        }
+
+        let mut docs = match older_than_secs {
+            Some(secs) => self.scan_created_before(collection, secs)?,
+            None => self.scan(collection)?,
+        };
+        docs.retain(matches);
+        self.check_interrupt()?;
+        if let Some(sort) = sort {
+            sort_documents(&mut docs, sort);
+        }
+        if let Some(limit) = limit {
+            docs.truncate(limit as usize);
+        }
+        Ok(docs)
+    }
+
+    fn require_op_stats(&self) -> QueryResult<&OpStats> {
+        self.op_stats
+            .as_deref()
+            .ok_or_else(|| QueryError::Execution("Operation statistics are not enabled".to_string()))
+    }
+
     fn execute_insert(&self, insert: &InsertStatement) -> QueryResult<QueryResponse> {
-        let collection = self.storage.get_or_create_collection(&insert.collection)?;
+        // 事务内的插入在提交时才创建集合,关闭自动创建时仍要求集合已存在
+        let collection = match &self.transaction {
+            Some(_) if self.auto_create_collections => None,
+            _ => Some(self.target_collection(&insert.collection)?),
+        };
 
-        let mut inserted_ids = Vec::new();
+        let mut docs = Vec::with_capacity(insert.documents.len());
         for doc_value in &insert.documents {
-            let mut doc = Document::from_boml_value(doc_value.clone())?;
-            let id = collection.insert(&mut doc)?;
-            inserted_ids.push(id.to_string());
+            let mut doc_value = doc_value.clone();
+            self.resolve_nextval(&mut doc_value)?;
+            docs.push(Document::from_boml_value(doc_value)?);
+        }
+
+        let ids = match (&self.transaction, &collection) {
+            (Some(txn), _) => docs
+                .iter_mut()
+                .map(|doc| {
+                    doc.ensure_id();
+                    txn.insert(&insert.collection, doc.clone())
+                })
+                .collect::<QueryResult<Vec<_>>>()?,
+            (None, Some(collection)) => self.insert_batch(collection, &mut docs)?,
+            (None, None) => unreachable!("collection is resolved outside transactions"),
+        };
+
+        if let Some(returning) = &insert.returning {
+            return Ok(returning_response("insert", docs, returning));
+        }
+        Ok(QueryResponse::Insert {
+            inserted_count: ids.len() as u64,
+            inserted_ids: ids.iter().map(ToString::to_string).collect(),
+        })
+    }
+
+    /// # Brief
+    /// 把源查询的结果分批写入目标集合
+    ///
+    /// 源文档保留 `_id`,目标集合中已有相同 `_id` 时所在批次返回唯一键重复错误。每批通过一次 WriteBatch 原子写入,
+    /// 批次之间检查中断;中断或出错时已提交的批次不会回滚。
+    /// 结果只返回写入数量,不返回 ID 列表。
+    fn execute_insert_select(&self, insert: &InsertSelectStatement) -> QueryResult<QueryResponse> {
+        if insert.collection == insert.source.collection {
+            return Err(QueryError::Execution(format!(
+                "INSERT ... FIND cannot read from its target collection {}",
+                insert.collection
+            )));
+        }
+
+        let QueryResponse::Documents(mut docs) = self.execute_find(&insert.source)? else {
+            return Err(QueryError::Internal("FIND did not return documents".to_string()));
+        };
+        if let Some(txn) = &self.transaction {
+            if !self.auto_create_collections {
+                self.target_collection(&insert.collection)?;
+            }
+            let inserted_count = docs.len() as u64;
+            for doc in docs {
+                self.check_interrupt()?;
+                txn.insert(&insert.collection, doc)?;
+            }
+            return Ok(QueryResponse::Insert {
+                inserted_count,
+                inserted_ids: Vec::new(),
+            });
+        }
+        let collection = self.target_collection(&insert.collection)?;
+        let batch_size = insert
+            .source
+            .batch_size
+            .map_or(INSERT_SELECT_BATCH_SIZE, |size| size.max(1) as usize);
+
+        let mut inserted_count = 0u64;
+        for batch in docs.chunks_mut(batch_size) {
+            self.check_interrupt()?;
+            inserted_count += self.insert_batch(&collection, batch)?.len() as u64;
         }
 
         Ok(QueryResponse::Insert {
-            inserted_count: inserted_ids.len() as u64,
-            inserted_ids,
+            inserted_count,
+            inserted_ids: Vec::new(),
         })
     }
 
+    /// # Brief
+    /// 在一个 WriteBatch 中写入一批文档和它们的索引项
+    ///
+    /// 违反唯一索引或 `_id` 重复时整批都不写入。写入成功后更新以该集合为源的预聚合。
+    fn insert_batch(&self, collection: &Collection, docs: &mut [Document]) -> QueryResult<Vec<ObjectId>> {
+        let ids = collection.insert_many_indexed(docs, self.storage.indexes())?;
+        for doc in docs.iter() {
+            self.storage.apply_rollups(collection.name(), None, Some(doc))?;
+        }
+        Ok(ids)
+    }
+
+    /// # Brief
+    /// 更新文档并把索引项从旧内容换成新内容
+    ///
+    /// 新内容违反唯一索引或文档写入失败时恢复旧的索引项,成功时更新预聚合。
+    fn update_indexed(&self, collection: &Collection, original: &Document, doc: &Document) -> QueryResult<()> {
+        let Some(id) = doc.id() else {
+            return Ok(());
+        };
+        let indexes = self.storage.indexes();
+        indexes.unindex_document(collection.name(), original)?;
+        let result = indexes
+            .index_document(collection.name(), doc)
+            .and_then(|()| match collection.update(id, doc) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    indexes.unindex_document(collection.name(), doc)?;
+                    Err(e)
+                }
+            });
+        if let Err(e) = result {
+            indexes.index_document(collection.name(), original)?;
+            return Err(e.into());
+        }
+        self.storage.apply_rollups(collection.name(), Some(original), Some(doc))?;
+        Ok(())
+    }
+
     fn execute_find(&self, find: &FindStatement) -> QueryResult<QueryResponse> {
+        if let Some(txn) = &self.transaction {
+            let mut docs = self.transaction_scan(txn.as_ref(), &find.collection)?;
+            if find.include_archive {
+                for archive in self.storage.archives_of(&find.collection)? {
+                    self.check_interrupt()?;
+                    docs.extend(self.scan(&archive)?);
+                }
+            }
+            return self.finish_find(find, docs, find.filter.as_ref());
+        }
+
         let collection = self.storage.get_collection(&find.collection)?;
 
-        let mut docs = collection.find_all()?;
+        let indexes = self.storage.indexes().list_indexes(&find.collection);
+        let stats = match &find.filter {
+            Some(_) => self.collection_stats(&find.collection)?,
+            None => None,
+        };
+        let planning = Instant::now();
+        let plan = self.planner.plan_find(find, &indexes, stats.as_ref())?;
+        self.track(|stats| stats.plan_us += planning.elapsed().as_micros() as u64);
+        // 计划中的过滤条件已按选择率重新排列
+        // 有投影时全集合扫描只解码投影、过滤和排序用到的字段,过滤条件引用整个文档时除外
+        let needed = find.projection.as_ref().map(|projection| {
+            let mut fields = projection.clone();
+            if let Some(filter) = &find.filter {
+                filter.collect_fields(&mut fields);
+            }
+            fields.extend(find.sort.iter().flatten().map(|sort| sort.field.clone()));
+            fields
+        })
+        .filter(|fields| !fields.iter().any(String::is_empty));
+        let scan = |collection: &Collection| match &needed {
+            Some(fields) => self.scan_projected(collection, fields),
+            None => self.scan(collection),
+        };
+        let (mut docs, plan_filter) = match plan.access_path() {
+            PlanNode::IndexScan { index_name, lookup, filter, .. } => {
+                (self.index_scan(&collection, index_name, lookup)?, filter.as_ref())
+            }
+            PlanNode::Scan { filter, .. } => (scan(&collection)?, filter.as_ref()),
+            _ => (scan(&collection)?, None),
+        };
+
+        if find.include_archive {
+            for archive in self.storage.archives_of(&find.collection)? {
+                self.check_interrupt()?;
+                docs.extend(scan(&archive)?);
+            }
+        }
+        self.finish_find(find, docs, plan_filter.or(find.filter.as_ref()))
+    }
 
-        if let Some(filter_expr) = &find.filter {
-            let filter = filter::Filter::new(filter_expr.clone());
+    /// # Brief
+    /// 对 FIND 读取的候选文档应用过滤、排序、SKIP、LIMIT 和投影
+    fn finish_find(
+        &self,
+        find: &FindStatement,
+        mut docs: Vec<Document>,
+        filter: Option<&Expression>,
+    ) -> QueryResult<QueryResponse> {
+        self.check_interrupt()?;
+
+        if let Some(filter_expr) = filter {
+            let filter = self.filter(filter_expr);
             docs = docs
                 .into_iter()
                 .filter(|doc| filter.matches(doc).unwrap_or(false))
                 .collect();
+            self.check_interrupt()?;
         }
 
         if let Some(sort_fields) = &find.sort {
-            docs.sort_by(|a, b| {
-                for sort_field in sort_fields {
-                    let a_val = a.get_path(&sort_field.field);
-                    let b_val = b.get_path(&sort_field.field);
-
-                    let cmp = compare_boml_values(a_val, b_val);
-                    if cmp != std::cmp::Ordering::Equal {
-                        return match sort_field.order {
-                            SortOrder::Ascending => cmp,
-                            SortOrder::Descending => cmp.reverse(),
-                        };
+            sort_documents(&mut docs, sort_fields);
+        }
+
+        if let Some(skip) = find.skip {
+            docs = docs.into_iter().skip(skip as usize).collect();
+        }
+
+        if let Some(limit) = find.limit {
+            docs = docs.into_iter().take(limit as usize).collect();
+        }
+
+        if let Some(projection) = &find.projection {
+            docs = docs
+                .into_iter()
+                .map(|doc| project_document(doc, projection))
+                .collect();
+        }
+
+        Ok(QueryResponse::Documents(docs))
+    }
+
+    fn execute_update(&self, update: &UpdateStatement) -> QueryResult<QueryResponse> {
+        if let Some(txn) = &self.transaction {
+            let docs = self.transaction_targets(
+                txn.as_ref(),
+                &update.collection,
+                None,
+                update.filter.as_ref(),
+                update.sort.as_deref(),
+                update.limit,
+            )?;
+            return self.apply_update(update, docs, |_, doc| {
+                if let Some(id) = doc.id() {
+                    txn.update(&update.collection, id, doc.clone())?;
+                }
+                Ok(())
+            });
+        }
+
+        let collection = self.storage.get_collection(&update.collection)?;
+
+        // 增量合并不读取旧文档,无法维护受影响字段上的索引和预聚合,也无法返回更新后的文档
+        let has_rollups = !self.storage.rollups_on(&update.collection)?.is_empty();
+        let increment = increment_only(update).filter(|(_, deltas)| {
+            if update.returning.is_some() || has_rollups {
+                return false;
+            }
+            let indexes = self.storage.indexes().list_indexes(&update.collection);
+            !deltas.iter().any(|(field, _)| {
+                indexes.iter().flat_map(|d| &d.fields).any(|f| paths_overlap(&f.path, field))
+            })
+        });
+        if let Some((id, deltas)) = increment {
+            let found = match collection.increment(&id, &deltas) {
+                Err(StorageError::SchemaViolation { details, .. }) => return Err(QueryError::Validation(details)),
+                Err(StorageError::Overflow(message)) => return Err(QueryError::Overflow(message)),
+                result => result?,
+            };
+            let count = found as u64;
+            return Ok(QueryResponse::Update {
+                matched_count: count,
+                modified_count: count,
+            });
+        }
+
+        // 只更新一个文档时(FIND AND MODIFY)从选取到写入都持有集合的修改锁,
+        // 并发的同类更新不会选中同一个文档
+        let _guard = (!update.multi || update.limit == Some(1)).then(|| collection.lock_for_modify());
+        let docs = self.mutation_targets(
+            &collection,
+            None,
+            update.filter.as_ref(),
+            update.sort.as_deref(),
+            update.limit,
+        )?;
+
+        self.apply_update(update, docs, |original, doc| self.update_indexed(&collection, original, doc))
+    }
+
+    /// # Brief
+    /// 对选出的文档应用 UPDATE 的更新操作并逐个写入
+    ///
+    /// # Arguments
+    /// * `update` - UPDATE 语句
+    /// * `docs` - 按修改顺序排列的目标文档
+    /// * `write` - 写入更新后的文档,参数为更新前和更新后的文档
+    fn apply_update(
+        &self,
+        update: &UpdateStatement,
+        docs: Vec<Document>,
+        mut write: impl FnMut(&Document, &Document) -> QueryResult<()>,
+    ) -> QueryResult<QueryResponse> {
+        let mut modified_count = 0u64;
+        let mut returned = Vec::new();
+        for mut doc in docs {
+            self.check_interrupt()?;
+            let original = doc.clone();
+            for op in &update.updates {
+                let op = self.resolve_positional(op, &original, update.filter.as_ref())?;
+                match op.as_ref() {
+                    // 每个匹配的文档各取一个编号
+                    UpdateOperation::Set { field, value } if contains_nextval(value) => {
+                        let mut value = value.clone();
+                        self.resolve_nextval(&mut value)?;
+                        apply_update_operation(&mut doc, &UpdateOperation::Set { field: field.clone(), value })?;
                     }
+                    op => apply_update_operation(&mut doc, op)?,
                 }
-                std::cmp::Ordering::Equal
+            }
+
+            if doc.id().is_some() {
+                write(&original, &doc)?;
+                modified_count += 1;
+                if update.returning.is_some() {
+                    returned.push(if update.return_old { original } else { doc });
+                }
+            }
+
+            if !update.multi {
+                break;
+            }
+        }
+
+        if let Some(returning) = &update.returning {
+            return Ok(returning_response("update", returned, returning));
+        }
+        Ok(QueryResponse::Update {
+            matched_count: modified_count,
+            modified_count,
+        })
+    }
+
+    /// # Brief
+    /// 把更新字段路径中的位置占位符 `$` 替换为数组下标
+    ///
+    /// 依次把 WHERE 条件中引用该数组的字段(如 `items.sku`)改写为指向第 i 个元素
+    /// (`items.i.sku`),第一个使条件成立的 i 即为匹配的元素。
+    ///
+    /// # Arguments
+    /// * `op` - 更新操作
+    /// * `doc` - 更新前的文档
+    /// * `filter` - UPDATE 的 WHERE 条件
+    ///
+    /// # Returns
+    /// 不含 `$` 时原样返回;条件没有引用该数组或没有元素匹配时返回错误
+    fn resolve_positional<'a>(
+        &self,
+        op: &'a UpdateOperation,
+        doc: &Document,
+        filter: Option<&Expression>,
+    ) -> QueryResult<Cow<'a, UpdateOperation>> {
+        if !op.fields().iter().any(|field| field.split('.').any(|part| part == "$")) {
+            return Ok(Cow::Borrowed(op));
+        }
+        let mut op = op.clone();
+        for field in update_fields_mut(&mut op) {
+            let mut parts: Vec<String> = field.split('.').map(str::to_string).collect();
+            let Some(pos) = parts.iter().position(|part| part == "$") else {
+                continue;
+            };
+            let prefix = parts[..pos].join(".");
+            let len = match doc.get_path(&prefix) {
+                Some(BomlValue::Array(items)) => items.len(),
+                _ => 0,
+            };
+            let no_match = || {
+                QueryError::Execution(format!(
+                    "Positional update of {} needs a WHERE condition matching an element of {}",
+                    field, prefix
+                ))
+            };
+            let filter = filter.ok_or_else(no_match)?;
+
+            let mut index = None;
+            for i in 0..len {
+                let mut referenced = false;
+                let mut condition = filter.clone();
+                condition.rewrite_fields(&mut |path: &mut String| {
+                    let Some(rest) = path.strip_prefix(prefix.as_str()) else {
+                        return;
+                    };
+                    if rest.is_empty() {
+                        *path = format!("{}.{}", prefix, i);
+                    } else if rest.starts_with('.') && !rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+                        *path = format!("{}.{}{}", prefix, i, rest);
+                    } else {
+                        return;
+                    }
+                    referenced = true;
+                });
+                if !referenced {
+                    break;
+                }
+                if filter::evaluate_with(&condition, doc, Some(&self.functions))? {
+                    index = Some(i);
+                    break;
+                }
+            }
+            parts[pos] = index.ok_or_else(no_match)?.to_string();
+            *field = parts.join(".");
+        }
+        Ok(Cow::Owned(op))
+    }
+
+    fn execute_delete(&self, delete: &DeleteStatement) -> QueryResult<QueryResponse> {
+        if let Some(txn) = &self.transaction {
+            let docs = self.transaction_targets(
+                txn.as_ref(),
+                &delete.collection,
+                delete.older_than_secs,
+                delete.filter.as_ref(),
+                delete.sort.as_deref(),
+                delete.limit,
+            )?;
+            let mut deleted_count = 0u64;
+            let mut returned = Vec::new();
+            for doc in docs.into_iter().take(if delete.multi { usize::MAX } else { 1 }) {
+                self.check_interrupt()?;
+                if let Some(id) = doc.id() {
+                    if txn.delete(&delete.collection, id)? {
+                        deleted_count += 1;
+                        if delete.returning.is_some() {
+                            returned.push(doc);
+                        }
+                    }
+                }
+            }
+            if let Some(returning) = &delete.returning {
+                return Ok(returning_response("delete", returned, returning));
+            }
+            return Ok(QueryResponse::Delete { deleted_count });
+        }
+
+        let collection = self.storage.get_collection(&delete.collection)?;
+
+        // OLDER THAN 对应一段连续的键范围: 没有其他条件时整段删除,否则只扫描这一段;
+        // 整段删除不读取文档,集合上有预聚合时逐条删除
+        if let Some(secs) = delete.older_than_secs {
+            if delete.filter.is_none()
+                && delete.limit.is_none()
+                && delete.returning.is_none()
+                && delete.multi
+                && self.storage.rollups_on(&delete.collection)?.is_empty()
+            {
+                let deleted_count = collection.delete_created_before(created_before_cutoff(secs))?;
+                return Ok(QueryResponse::Delete { deleted_count });
+            }
+        }
+        let docs = self.mutation_targets(
+            &collection,
+            delete.older_than_secs,
+            delete.filter.as_ref(),
+            delete.sort.as_deref(),
+            delete.limit,
+        )?;
+
+        let mut deleted_count = 0u64;
+        let mut returned = Vec::new();
+        for doc in docs {
+            self.check_interrupt()?;
+            if let Some(id) = doc.id() {
+                if collection.delete(id)? {
+                    self.storage.indexes().unindex_document(collection.name(), &doc)?;
+                    self.storage.apply_rollups(collection.name(), Some(&doc), None)?;
+                    deleted_count += 1;
+                    if delete.returning.is_some() {
+                        returned.push(doc);
+                    }
+                }
+            }
+
+            if !delete.multi {
+                break;
+            }
+        }
+
+        if let Some(returning) = &delete.returning {
+            return Ok(returning_response("delete", returned, returning));
+        }
+        Ok(QueryResponse::Delete { deleted_count })
+    }
+
+    fn execute_dry_run(&self, stmt: &Statement) -> QueryResult<QueryResponse> {
+        let (operation, collection, filter_expr, multi, sort, limit) = match stmt {
+            Statement::Update(update) => (
+                "update",
+                &update.collection,
+                &update.filter,
+                update.multi,
+                &update.sort,
+                update.limit,
+            ),
+            Statement::Delete(delete) => (
+                "delete",
+                &delete.collection,
+                &delete.filter,
+                delete.multi,
+                &delete.sort,
+                delete.limit,
+            ),
+            _ => {
+                return Err(QueryError::Execution(
+                    "DRY RUN only supports UPDATE and DELETE".to_string(),
+                ))
+            }
+        };
+
+        let collection = self.storage.get_collection(collection)?;
+        let mut docs = match stmt {
+            Statement::Delete(DeleteStatement { older_than_secs: Some(secs), .. }) => {
+                collection.find_created_before(created_before_cutoff(*secs))?
+            }
+            _ => collection.find_all()?,
+        };
+        self.check_interrupt()?;
+
+        if let Some(filter_expr) = filter_expr {
+            let filter = self.filter(filter_expr);
+            docs.retain(|doc| filter.matches(doc).unwrap_or(false));
+        }
+        if let Some(sort) = sort {
+            sort_documents(&mut docs, sort);
+        }
+        if let Some(limit) = limit {
+            docs.truncate(limit as usize);
+        }
+        if !multi {
+            docs.truncate(1);
+        }
+
+        // 更新时在副本上应用操作,只统计内容确实会变化的文档
+        let mut modified_count = 0u64;
+        for doc in &docs {
+            match stmt {
+                Statement::Update(update) => {
+                    let mut updated = doc.clone();
+                    for op in &update.updates {
+                        let op = self.resolve_positional(op, doc, update.filter.as_ref())?;
+                        apply_update_operation(&mut updated, &op)?;
+                    }
+                    if updated != *doc {
+                        modified_count += 1;
+                    }
+                }
+                _ => modified_count += 1,
+            }
+        }
+
+        Ok(QueryResponse::DryRun {
+            operation: operation.to_string(),
+            matched_count: docs.len() as u64,
+            modified_count,
+            sample_ids: docs
+                .iter()
+                .filter_map(|doc| doc.id())
+                .take(DRY_RUN_SAMPLE_SIZE)
+                .map(|id| id.to_string())
+                .collect(),
+        })
+    }
+
+    fn execute_archive(&self, archive: &ArchiveStatement) -> QueryResult<QueryResponse> {
+        let policy = ArchivePolicy {
+            source: archive.collection.clone(),
+            archive: archive.archive.clone(),
+            older_than_secs: archive.older_than_secs,
+            compression: archive.compression,
+            path: archive.path.as_ref().map(PathBuf::from),
+        };
+
+        self.storage.create_archive(&policy)?;
+        let moved = self.storage.apply_archive_policy(&policy)?;
+
+        Ok(QueryResponse::Ok {
+            message: format!(
+                "Archived {} document(s) from {} to {}",
+                moved, archive.collection, archive.archive
+            ),
+        })
+    }
+
+    fn execute_backup(&self, stmt: &BackupStatement) -> QueryResult<QueryResponse> {
+        if stmt.snapshot {
+            let manifest = self.storage.create_snapshot(self.backup_path(&stmt.path)?)?;
+            let wal = manifest.wal_lsn.map_or(String::new(), |lsn| format!(", WAL LSN {}", lsn));
+            return Ok(QueryResponse::Ok {
+                message: format!(
+                    "Snapshot written to {}: {} collection(s), sequence {}{}",
+                    stmt.path,
+                    manifest.collections.len(),
+                    manifest.sequence,
+                    wal
+                ),
+            });
+        }
+        let options = BackupOptions { include_system: stmt.include_system };
+        let manifest = backup::create_backup(&self.storage, &self.backup_path(&stmt.path)?, &options)?;
+
+        Ok(QueryResponse::Ok {
+            message: format!(
+                "Backup written to {}: {} collection(s), system data {}",
+                stmt.path,
+                manifest.collections.len(),
+                if manifest.include_system { "included" } else { "excluded" }
+            ),
+        })
+    }
+
+    /// # Brief
+    /// 解析语句中的备份路径,设置了备份根目录时限制在根目录下
+    fn backup_path(&self, path: &str) -> QueryResult<PathBuf> {
+        match &self.backup_dir {
+            Some(root) => Ok(backup::resolve_path(root, path)?),
+            None => Ok(PathBuf::from(path)),
+        }
+    }
+
+    fn execute_restore(&self, stmt: &RestoreStatement) -> QueryResult<QueryResponse> {
+        let options = RestoreOptions {
+            scope: if stmt.metadata_only { RestoreScope::Metadata } else { RestoreScope::All },
+        };
+        let report = backup::restore_backup(&self.storage, &self.backup_path(&stmt.path)?, &options)?;
+
+        Ok(QueryResponse::Ok {
+            message: format!(
+                "Restored {} collection(s) from {} ({} entries, {} metadata entries)",
+                report.collections.len(),
+                stmt.path,
+                report.entries,
+                report.metadata_entries
+            ),
+        })
+    }
+
+    fn execute_alter_collection(&self, alter: &AlterCollectionStatement) -> QueryResult<QueryResponse> {
+        let collection = self.storage.get_collection(&alter.collection)?;
+        if let Some(expire) = &alter.expire {
+            let message = match expire {
+                ExpireSetting::AfterField(field) => {
+                    self.storage.set_expire_policy(&alter.collection, Some(field))?;
+                    format!("Documents in {} expire after field {}", alter.collection, field)
+                }
+                ExpireSetting::Off => {
+                    self.storage.set_expire_policy(&alter.collection, None)?;
+                    format!("Document expiry disabled for {}", alter.collection)
+                }
+            };
+            return Ok(QueryResponse::Ok { message });
+        }
+
+        let mut options = collection.schema_options();
+        if let Some(track) = alter.track_types {
+            options.track_types = track;
+        }
+        if let Some(strict) = alter.strict_types {
+            options.strict_types = strict;
+        }
+        self.storage.set_schema_options(&alter.collection, options)?;
+
+        Ok(QueryResponse::Ok {
+            message: format!(
+                "Collection {} altered (track_types = {}, strict_types = {})",
+                alter.collection, options.track_types, options.strict_types
+            ),
+        })
+    }
+
+    fn execute_show_schema(&self, name: &str) -> QueryResult<QueryResponse> {
+        let collection = self.storage.get_collection(name)?;
+        if !collection.schema_options().enabled() {
+            return Ok(QueryResponse::Ok {
+                message: format!(
+                    "Type tracking is disabled for {}; enable it with ALTER COLLECTION {} SET track_types = true",
+                    name, name
+                ),
             });
         }
 
-        if let Some(skip) = find.skip {
-            docs = docs.into_iter().skip(skip as usize).collect();
+        let docs = collection
+            .schema_summary()?
+            .into_iter()
+            .map(|field| {
+                let mut doc = Document::without_id();
+                doc.insert("field", field.field);
+                doc.insert("type", field.dominant);
+                let mut types = Document::without_id();
+                for (type_name, count) in field.types {
+                    types.insert(type_name, count as i64);
+                }
+                doc.insert("types", BomlValue::from(types));
+                doc
+            })
+            .collect();
+
+        Ok(QueryResponse::Documents(docs))
+    }
+
+    fn execute_create_index(&self, create_idx: &CreateIndexStatement) -> QueryResult<QueryResponse> {
+        let Some(options) = &create_idx.text_options else {
+            return self.create_key_index(create_idx);
+        };
+
+        let analyzer = build_text_analyzer(options)?;
+        Ok(QueryResponse::Ok {
+            message: format!(
+                "Created text index: {} (tokenizer: {})",
+                create_idx.name,
+                analyzer.tokenizer_name()
+            ),
+        })
+    }
+
+    /// # Brief
+    /// 创建 BTree 或哈希索引并用集合中已有的文档填充
+    ///
+    /// 填充失败(如唯一索引遇到重复键)时删除刚创建的索引。
+    fn create_key_index(&self, create_idx: &CreateIndexStatement) -> QueryResult<QueryResponse> {
+        let index_type = match create_idx.index_type {
+            IndexType::BTree => StorageIndexType::BTree,
+            IndexType::Hash => StorageIndexType::Hash,
+            other => {
+                return Err(QueryError::Execution(format!("{:?} indexes are not supported", other)));
+            }
+        };
+
+        let collection = self.target_collection(&create_idx.collection)?;
+        let indexes = self.storage.indexes();
+        indexes.create_index(IndexDefinition {
+            name: create_idx.name.clone(),
+            collection: create_idx.collection.clone(),
+            fields: create_idx
+                .fields
+                .iter()
+                .map(|field| StorageIndexField {
+                    path: field.name.clone(),
+                    order: match field.order {
+                        SortOrder::Ascending => IndexOrder::Ascending,
+                        SortOrder::Descending => IndexOrder::Descending,
+                    },
+                })
+                .collect(),
+            index_type,
+            unique: create_idx.unique,
+            sparse: false,
+            ttl_seconds: None,
+            key_encoding: KeyEncoding::Memcomparable,
+            max_key_size: create_idx.max_key_size,
+            oversized_keys: create_idx.oversized_keys,
+        })?;
+
+        match indexes.build_index(&create_idx.name, &collection) {
+            Ok(count) => Ok(QueryResponse::Ok {
+                message: format!("Created index: {} ({} document(s) indexed)", create_idx.name, count),
+            }),
+            Err(e) => {
+                indexes.drop_index(&create_idx.name)?;
+                Err(e.into())
+            }
         }
+    }
 
-        if let Some(limit) = find.limit {
-            docs = docs.into_iter().take(limit as usize).collect();
+    fn execute_show_sequences(&self) -> QueryResult<QueryResponse> {
+        let mut docs = Vec::new();
+        for seq in self.storage.list_sequences()? {
+            let current = self.storage.current_sequence_value(&seq.name)?;
+            let mut doc = Document::without_id();
+            doc.insert("name", seq.name);
+            doc.insert("start", seq.start);
+            doc.insert("increment", seq.increment);
+            doc.insert("current", current.map_or(BomlValue::Null, BomlValue::Int64));
+            docs.push(doc);
         }
+        Ok(QueryResponse::Documents(docs))
+    }
 
-        if let Some(projection) = &find.projection {
-            docs = docs
-                .into_iter()
-                .map(|doc| project_document(doc, projection))
+    fn execute_show_rollups(&self) -> QueryResult<QueryResponse> {
+        let mut docs = Vec::new();
+        for rollup in self.storage.list_rollups()? {
+            let group_by: Vec<BomlValue> = rollup
+                .group_by
+                .iter()
+                .map(|key| {
+                    let mut doc = Document::without_id();
+                    doc.insert("field", key.field.clone());
+                    doc.insert("path", key.path.clone());
+                    doc.insert("bucket", key.bucket.map_or(BomlValue::Null, |b| BomlValue::from(b.as_str())));
+                    BomlValue::from(doc)
+                })
+                .collect();
+            let aggregates: Vec<BomlValue> = rollup
+                .aggregates
+                .iter()
+                .map(|agg| {
+                    let mut doc = Document::without_id();
+                    doc.insert("name", agg.name.clone());
+                    doc.insert("function", agg.function.as_str());
+                    doc.insert("path", agg.path.clone().map_or(BomlValue::Null, BomlValue::from));
+                    BomlValue::from(doc)
+                })
                 .collect();
+            let mut doc = Document::without_id();
+            doc.insert("name", rollup.name);
+            doc.insert("source", rollup.source);
+            doc.insert("group_by", BomlValue::Array(group_by));
+            doc.insert("aggregates", BomlValue::Array(aggregates));
+            docs.push(doc);
         }
-
         Ok(QueryResponse::Documents(docs))
     }
 
-    fn execute_update(&self, update: &UpdateStatement) -> QueryResult<QueryResponse> {
-        let collection = self.storage.get_collection(&update.collection)?;
+    /// # Brief
+    /// 生成所有数据库的声明式清单
+    ///
+    /// 返回一个文档,`databases` 中每个数据库列出集合及其固定大小限制、类型检查选项、
+    /// 过期字段和索引。系统集合和全文索引不在清单中;用户由服务器追加到 `users` 字段。
+    fn execute_show_manifest(&self) -> QueryResult<QueryResponse> {
+        let indexes = self.storage.indexes();
+        let mut databases = Vec::new();
+        for database in self.storage.list_databases()? {
+            let mut names = self.storage.database_collections(&database)?;
+            names.sort();
+            let mut collections = Vec::new();
+            for name in names {
+                let qualified = qualified_collection_name(&database, &name);
+                if backup::is_system_collection(&qualified) {
+                    continue;
+                }
+                let mut doc = Document::without_id();
+                doc.insert("name", name);
+                if let Some(capped) = self.storage.capped_options(&qualified)? {
+                    let mut limits = Document::without_id();
+                    if let Some(max_size) = capped.max_size {
+                        limits.insert("max_size", max_size as i64);
+                    }
+                    if let Some(max_documents) = capped.max_documents {
+                        limits.insert("max_documents", max_documents as i64);
+                    }
+                    doc.insert("capped", BomlValue::from(limits));
+                }
+                let options = self.storage.get_collection(&qualified)?.schema_options();
+                doc.insert("track_types", options.track_types);
+                doc.insert("strict_types", options.strict_types);
+                if let Some(policy) = self.storage.expire_policy(&qualified)? {
+                    doc.insert("expire_after_field", policy.field);
+                }
 
-        let mut docs = collection.find_all()?;
+                let mut definitions = indexes.list_indexes(&qualified);
+                definitions.sort_by(|a, b| a.name.cmp(&b.name));
+                let index_docs: Vec<BomlValue> = definitions
+                    .into_iter()
+                    .map(|definition| {
+                        let fields: Vec<BomlValue> = definition
+                            .fields
+                            .into_iter()
+                            .map(|field| match field.order {
+                                IndexOrder::Ascending => BomlValue::from(field.path),
+                                IndexOrder::Descending => BomlValue::from(format!("{} DESC", field.path)),
+                            })
+                            .collect();
+                        let mut index = Document::without_id();
+                        index.insert("name", definition.name);
+                        index.insert("fields", BomlValue::Array(fields));
+                        index.insert("unique", definition.unique);
+                        BomlValue::from(index)
+                    })
+                    .collect();
+                doc.insert("indexes", BomlValue::Array(index_docs));
+                collections.push(BomlValue::from(doc));
+            }
 
-        if let Some(filter_expr) = &update.filter {
-            let filter = filter::Filter::new(filter_expr.clone());
-            docs = docs
-                .into_iter()
-                .filter(|doc| filter.matches(doc).unwrap_or(false))
-                .collect();
+            let mut doc = Document::without_id();
+            doc.insert("name", database);
+            doc.insert("collections", BomlValue::Array(collections));
+            databases.push(BomlValue::from(doc));
         }
 
-        let mut modified_count = 0u64;
-        for mut doc in docs {
-            for op in &update.updates {
-                apply_update_operation(&mut doc, op)?;
-            }
+        let mut manifest = Document::without_id();
+        manifest.insert("databases", BomlValue::Array(databases));
+        Ok(QueryResponse::Documents(vec![manifest]))
+    }
 
-            if let Some(id) = doc.id() {
-                collection.update(id, &doc)?;
-                modified_count += 1;
+    /// # Brief
+    /// 把值中的 NEXTVAL 占位文档替换为序列的下一个编号
+    fn resolve_nextval(&self, value: &mut BomlValue) -> QueryResult<()> {
+        if let Some(name) = nextval_sequence(value) {
+            let next = self.storage.next_sequence_value(name)?;
+            *value = BomlValue::Int64(next);
+            return Ok(());
+        }
+        match value {
+            BomlValue::Document(fields) => {
+                for field in fields.values_mut() {
+                    self.resolve_nextval(field)?;
+                }
             }
-
-            if !update.multi {
-                break;
+            BomlValue::Array(items) => {
+                for item in items {
+                    self.resolve_nextval(item)?;
+                }
             }
+            _ => {}
         }
-
-        Ok(QueryResponse::Update {
-            matched_count: modified_count,
-            modified_count,
-        })
+        Ok(())
     }
 
-    fn execute_delete(&self, delete: &DeleteStatement) -> QueryResult<QueryResponse> {
-        let collection = self.storage.get_collection(&delete.collection)?;
+    fn execute_show_advisor(&self, collection: Option<&str>) -> QueryResult<QueryResponse> {
+        let advisor = match self.storage.get_collection(ADVISOR_COLLECTION) {
+            Ok(advisor) => advisor,
+            Err(StorageError::CollectionNotFound(_)) => return Ok(QueryResponse::Documents(vec![])),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut docs: Vec<Document> = advisor
+            .find_all()?
+            .into_iter()
+            .filter(|doc| collection.map_or(true, |c| doc.get_str("collection") == Some(c)))
+            .collect();
+        docs.sort_by(|a, b| {
+            let benefit = |doc: &Document| doc.get_f64("estimated_benefit").unwrap_or(0.0);
+            benefit(b).partial_cmp(&benefit(a)).unwrap_or(std::cmp::Ordering::Equal)
+        });
 
-        let mut docs = collection.find_all()?;
+        Ok(QueryResponse::Documents(docs))
+    }
 
-        if let Some(filter_expr) = &delete.filter {
-            let filter = filter::Filter::new(filter_expr.clone());
-            docs = docs
-                .into_iter()
-                .filter(|doc| filter.matches(doc).unwrap_or(false))
-                .collect();
+    fn execute_stats(&self, name: &str) -> QueryResult<QueryResponse> {
+        let op_stats = self.require_op_stats()?;
+        let collection = self.storage.get_collection(name)?;
+        let stats = op_stats.snapshot(name).unwrap_or_else(|| CollectionOpStats {
+            collection: name.to_string(),
+            ..Default::default()
+        });
+
+        let mut doc = Document::without_id();
+        doc.insert("collection", stats.collection);
+        doc.insert("documents", collection.count()? as i64);
+        for (key, value) in [
+            ("finds", stats.finds),
+            ("inserts", stats.inserts),
+            ("updates", stats.updates),
+            ("deletes", stats.deletes),
+            ("aggregates", stats.aggregates),
+            ("scans", stats.scans),
+            ("index_hits", stats.index_hits),
+            ("docs_examined", stats.docs_examined),
+            ("docs_returned", stats.docs_returned),
+            ("docs_written", stats.docs_written),
+            ("total_latency_us", stats.total_latency_us),
+        ] {
+            doc.insert(key, value as i64);
         }
+        doc.insert("avg_latency_us", stats.avg_latency_us);
+        doc.insert("since", stats.since as i64);
 
-        let mut deleted_count = 0u64;
-        for doc in docs {
-            if let Some(id) = doc.id() {
-                if collection.delete(id)? {
-                    deleted_count += 1;
-                }
-            }
+        Ok(QueryResponse::Documents(vec![doc]))
+    }
 
-            if !delete.multi {
-                break;
+    /// # Brief
+    /// 读取 ANALYZE 保存的集合统计信息
+    ///
+    /// 统计信息损坏时记录警告并按没有统计信息处理,不影响查询。
+    fn collection_stats(&self, name: &str) -> QueryResult<Option<CollectionStats>> {
+        let Some(data) = self.storage.collection_stats(name)? else {
+            return Ok(None);
+        };
+        match serde_json::from_slice(&data) {
+            Ok(stats) => Ok(Some(stats)),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid statistics of {}: {}", name, e);
+                Ok(None)
             }
         }
+    }
 
-        Ok(QueryResponse::Delete { deleted_count })
+    /// # Brief
+    /// 扫描集合收集统计信息并保存,返回每个字段的统计摘要
+    fn execute_analyze(&self, name: &str) -> QueryResult<QueryResponse> {
+        let collection = self.storage.get_collection(name)?;
+        let mut collector = StatsCollector::new();
+        for doc in collection.iter()? {
+            self.check_interrupt()?;
+            collector.add(&doc?);
+        }
+        let stats = collector.finish();
+        let data = serde_json::to_vec(&stats).map_err(|e| QueryError::Internal(e.to_string()))?;
+        self.storage.set_collection_stats(name, &data)?;
+
+        let docs = stats
+            .fields
+            .iter()
+            .map(|(field, field_stats)| {
+                let mut doc = Document::without_id();
+                doc.insert("field", field.as_str());
+                doc.insert("documents", stats.row_count as i64);
+                doc.insert("count", field_stats.count as i64);
+                doc.insert("nulls", field_stats.null_count as i64);
+                doc.insert("distinct", field_stats.distinct as i64);
+                if let Some(histogram) = &field_stats.histogram {
+                    let bound = |x: f64| match histogram.kind {
+                        HistogramKind::Number => BomlValue::Float64(x),
+                        HistogramKind::DateTime => chrono::DateTime::from_timestamp_micros(x as i64)
+                            .map_or(BomlValue::Null, BomlValue::DateTime),
+                    };
+                    if let (Some(&min), Some(&max)) = (histogram.bounds.first(), histogram.bounds.last()) {
+                        doc.insert("min", bound(min));
+                        doc.insert("max", bound(max));
+                    }
+                    doc.insert("buckets", histogram.bounds.len().saturating_sub(1) as i64);
+                }
+                doc
+            })
+            .collect();
+        Ok(QueryResponse::Documents(docs))
     }
 
-    fn execute_aggregate(&self, agg: &AggregateStatement) -> QueryResult<QueryResponse> {
-        let collection = self.storage.get_collection(&agg.collection)?;
+    fn execute_compact(&self, name: &str) -> QueryResult<QueryResponse> {
+        let report = self.storage.compact_collection(name)?;
+        let mut doc = Document::without_id();
+        doc.insert("collection", report.collection.as_str());
+        doc.insert(
+            "column_families",
+            BomlValue::Array(report.column_families.iter().map(|cf| BomlValue::from(cf.as_str())).collect()),
+        );
+        doc.insert("bytes_before", report.bytes_before as i64);
+        doc.insert("bytes_after", report.bytes_after as i64);
+        doc.insert("reclaimed_bytes", report.reclaimed_bytes() as i64);
+        doc.insert("duration_ms", report.duration_ms as i64);
+        Ok(QueryResponse::Documents(vec![doc]))
+    }
 
-        let mut docs = collection.find_all()?;
+    fn execute_aggregate(&self, agg: &AggregateStatement) -> QueryResult<QueryResponse> {
+        let mut docs = match &self.transaction {
+            Some(txn) => self.transaction_scan(txn.as_ref(), &agg.collection)?,
+            None => {
+                let collection = self.storage.get_collection(&agg.collection)?;
+                self.scan(&collection)?
+            }
+        };
 
         for stage in &agg.pipeline {
+            self.check_interrupt()?;
             docs = self.apply_aggregate_stage(docs, stage)?;
         }
 
@@ -301,7 +1835,7 @@ impl QueryExecutor {
     ) -> QueryResult<Vec<Document>> {
         match stage {
             AggregateStage::Match(expr) => {
-                let filter = filter::Filter::new(expr.clone());
+                let filter = self.filter(expr);
                 Ok(docs
                     .into_iter()
                     .filter(|doc| filter.matches(doc).unwrap_or(false))
@@ -335,6 +1869,10 @@ impl QueryExecutor {
                 Ok(docs.into_iter().skip(*n as usize).collect())
             }
 
+            AggregateStage::Project(fields) if fields.iter().any(|f| f.expression.is_some()) => {
+                docs.iter().map(|doc| self.project_computed(doc, fields)).collect()
+            }
+
             AggregateStage::Project(fields) => {
                 let field_names: Vec<String> = fields.iter().map(|f| f.name.clone()).collect();
                 Ok(docs
@@ -347,6 +1885,14 @@ impl QueryExecutor {
                 self.execute_group(docs, by, accumulators)
             }
 
+            AggregateStage::GraphLookup(graph) => self.graph_lookup(docs, graph),
+
+            AggregateStage::Facet(facets) => self.facet(docs, facets),
+
+            AggregateStage::Bucket(bucket) => self.bucket(docs, bucket),
+
+            AggregateStage::BucketAuto(bucket) => self.bucket_auto(docs, bucket),
+
             AggregateStage::Count(field_name) => {
                 let count = docs.len() as i64;
                 let mut result = Document::without_id();
@@ -358,6 +1904,202 @@ impl QueryExecutor {
         }
     }
 
+    /// # Brief
+    /// 执行 GRAPH LOOKUP 阶段
+    ///
+    /// 被关联集合只扫描一次,按 `connect_to` 的值建立映射后对每个输入文档广度优先展开。
+    /// 同一文档在一个结果数组中只出现一次,环形引用因此也会终止。
+    fn graph_lookup(&self, docs: Vec<Document>, graph: &GraphLookup) -> QueryResult<Vec<Document>> {
+        let from = self.storage.get_collection(&graph.from)?;
+        let candidates = self.scan(&from)?;
+        let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, candidate) in candidates.iter().enumerate() {
+            if let Some(value) = candidate.get_path(&graph.connect_to) {
+                for key in graph_keys(value) {
+                    by_key.entry(key).or_default().push(i);
+                }
+            }
+        }
+
+        let mut results = Vec::with_capacity(docs.len());
+        for mut doc in docs {
+            self.check_interrupt()?;
+            let mut frontier = doc.get_path(&graph.start_with).map(graph_keys).unwrap_or_default();
+            let mut visited = HashSet::new();
+            let mut found = Vec::new();
+            let mut depth = 0u32;
+            while !frontier.is_empty() {
+                let mut next = Vec::new();
+                for key in &frontier {
+                    for &i in by_key.get(key).into_iter().flatten() {
+                        if !visited.insert(i) {
+                            continue;
+                        }
+                        let mut matched = candidates[i].clone();
+                        if let Some(value) = matched.get_path(&graph.connect_from) {
+                            next.extend(graph_keys(value));
+                        }
+                        if let Some(field) = &graph.depth_field {
+                            matched.insert(field.clone(), depth as i64);
+                        }
+                        found.push(BomlValue::from(matched));
+                    }
+                }
+                if graph.max_depth.is_some_and(|max| depth >= max) {
+                    break;
+                }
+                frontier = next;
+                depth += 1;
+            }
+            set_path(&mut doc, &graph.as_field, BomlValue::Array(found))?;
+            results.push(doc);
+        }
+        Ok(results)
+    }
+
+    /// # Brief
+    /// 执行 FACET 阶段
+    ///
+    /// 每个子管道都以全部输入文档为输入,结果作为数组写入输出文档的同名字段。
+    /// 输出只有一个文档,输入为空时各字段为空数组。
+    fn facet(&self, mut docs: Vec<Document>, facets: &[Facet]) -> QueryResult<Vec<Document>> {
+        let mut result = Document::without_id();
+        for (i, facet) in facets.iter().enumerate() {
+            // 最后一个子管道直接使用输入文档,不再复制
+            let mut output = if i + 1 < facets.len() { docs.clone() } else { std::mem::take(&mut docs) };
+            for stage in &facet.pipeline {
+                self.check_interrupt()?;
+                output = self.apply_aggregate_stage(output, stage)?;
+            }
+            result.insert(facet.name.clone(), BomlValue::Array(output.into_iter().map(BomlValue::from).collect()));
+        }
+        Ok(vec![result])
+    }
+
+    /// # Brief
+    /// 执行 BUCKET 阶段
+    ///
+    /// 边界必须严格升序。按边界顺序输出非空的桶,DEFAULT 桶在最后。
+    fn bucket(&self, docs: Vec<Document>, bucket: &Bucket) -> QueryResult<Vec<Document>> {
+        let ascending = bucket
+            .boundaries
+            .windows(2)
+            .all(|pair| filter::compare_values(&pair[0], &pair[1]).is_some_and(|cmp| cmp < 0));
+        if bucket.boundaries.len() < 2 || !ascending {
+            return Err(QueryError::Execution(
+                "BUCKET boundaries must be at least two values in ascending order".to_string(),
+            ));
+        }
+
+        let mut buckets: Vec<Vec<Document>> = vec![Vec::new(); bucket.boundaries.len() - 1];
+        let mut others = Vec::new();
+        for doc in docs {
+            // 与任一边界的类型无法比较时视为不在任何桶中
+            let index = doc.get_path(&bucket.group_by).and_then(|value| {
+                let cmps: Vec<i32> = bucket
+                    .boundaries
+                    .iter()
+                    .map(|boundary| filter::compare_values(value, boundary))
+                    .collect::<Option<_>>()?;
+                cmps.windows(2).position(|pair| pair[0] >= 0 && pair[1] < 0)
+            });
+            match index {
+                Some(index) => buckets[index].push(doc),
+                None if bucket.default.is_some() => others.push(doc),
+                None => {
+                    return Err(QueryError::Execution(format!(
+                        "Value of '{}' is outside the BUCKET boundaries and no DEFAULT is given",
+                        bucket.group_by
+                    )))
+                }
+            }
+        }
+
+        let mut results = Vec::new();
+        for (lower, docs) in bucket.boundaries.iter().zip(buckets) {
+            if !docs.is_empty() {
+                results.push(self.bucket_output(lower.clone(), &docs, &bucket.output)?);
+            }
+        }
+        if let (Some(default), false) = (&bucket.default, others.is_empty()) {
+            results.push(self.bucket_output(default.clone(), &others, &bucket.output)?);
+        }
+        Ok(results)
+    }
+
+    /// # Brief
+    /// 执行 BUCKET AUTO 阶段
+    ///
+    /// 按分桶字段排序后依次切分,每个桶的目标文档数为剩余文档数除以剩余桶数;
+    /// 切分点上的值与下一个文档相同时继续向后扩展,保证相同的值在同一个桶中。
+    fn bucket_auto(&self, docs: Vec<Document>, bucket: &BucketAuto) -> QueryResult<Vec<Document>> {
+        // 缺失的字段按 NULL 处理
+        let mut keyed: Vec<(BomlValue, Document)> = docs
+            .into_iter()
+            .map(|doc| (doc.get_path(&bucket.group_by).cloned().unwrap_or(BomlValue::Null), doc))
+            .collect();
+        keyed.sort_by(|(a, _), (b, _)| compare_boml_values(Some(a), Some(b)));
+        let (values, docs): (Vec<BomlValue>, Vec<Document>) = keyed.into_iter().unzip();
+
+        let mut results = Vec::new();
+        let mut start = 0;
+        let mut remaining_buckets = bucket.buckets.max(1) as usize;
+        while start < docs.len() {
+            self.check_interrupt()?;
+            let mut end = start + (docs.len() - start).div_ceil(remaining_buckets);
+            while end < docs.len()
+                && compare_boml_values(Some(&values[end - 1]), Some(&values[end])) == std::cmp::Ordering::Equal
+            {
+                end += 1;
+            }
+            let mut id = Document::without_id();
+            id.insert("min", values[start].clone());
+            id.insert("max", values[end.min(docs.len() - 1)].clone());
+            results.push(self.bucket_output(BomlValue::from(id), &docs[start..end], &bucket.output)?);
+            start = end;
+            remaining_buckets = remaining_buckets.saturating_sub(1).max(1);
+        }
+        Ok(results)
+    }
+
+    /// # Brief
+    /// 生成一个桶的输出文档:`_id` 加累加器结果,没有累加器时为文档数 `count`
+    fn bucket_output(&self, id: BomlValue, docs: &[Document], output: &[Accumulator]) -> QueryResult<Document> {
+        let mut result = Document::without_id();
+        result.insert("_id", id);
+        if output.is_empty() {
+            result.insert("count", docs.len() as i64);
+        }
+        for acc in output {
+            let value = self.compute_aggregate(docs, acc)?;
+            result.insert(acc.name.clone(), value);
+        }
+        Ok(result)
+    }
+
+    /// # Brief
+    /// 执行带计算字段的 PROJECT 阶段,计算字段可以调用自定义函数,求值错误直接返回
+    fn project_computed(&self, doc: &Document, fields: &[ProjectField]) -> QueryResult<Document> {
+        let mut result = Document::without_id();
+        if let Some(id) = doc.id() {
+            result.set_id(*id);
+        }
+        for field in fields {
+            match &field.expression {
+                Some(expr) => {
+                    let value = filter::evaluate_value_with(expr, doc, Some(&self.functions))?;
+                    set_path(&mut result, &field.name, value)?;
+                }
+                None => {
+                    if let Some(value) = doc.get_path(&field.name) {
+                        set_path(&mut result, &field.name, value.clone())?;
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
     fn execute_group(
         &self,
         docs: Vec<Document>,
@@ -382,14 +2124,14 @@ impl QueryExecutor {
 
         let mut results = Vec::new();
 
-        for (key, group_docs) in groups {
+        for group_docs in groups.into_values() {
             let mut result = Document::without_id();
 
-            let key_parts: Vec<&str> = key.split('|').collect();
-            for (i, field) in group_by.iter().enumerate() {
+            // 分组键写入嵌套的 `_id` 文档,例如 BY address.city 得到 `_id: {address: {city: ...}}`
+            for field in group_by {
                 if let Some(first_doc) = group_docs.first() {
                     if let Some(value) = first_doc.get_path(field) {
-                        result.insert(format!("_id.{}", field), value.clone());
+                        set_path(&mut result, &format!("_id.{}", field), value.clone())?;
                     }
                 }
             }
@@ -548,7 +2290,136 @@ impl QueryExecutor {
                 }
                 Ok(BomlValue::Array(values))
             }
+
+            AggregateFunction::Custom(name) => {
+                let function = self.functions.accumulator(name).ok_or_else(|| {
+                    QueryError::Execution(format!("Unknown aggregate function: {}", name))
+                })?;
+                let values: Vec<BomlValue> = match &acc.field {
+                    Some(field) => docs.iter().filter_map(|doc| doc.get_path(field).cloned()).collect(),
+                    None => Vec::new(),
+                };
+                function(&values)
+            }
+        }
+    }
+}
+
+/// # Brief
+/// 计算 OLDER THAN 的截止时间(Unix 秒)
+fn created_before_cutoff(older_than_secs: u64) -> u64 {
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    now.saturating_sub(older_than_secs)
+}
+
+/// # Brief
+/// 判断 UPDATE 能否只写入增量: 过滤条件为 `_id = <id>`、不 UPSERT、只有数值 `+=`
+///
+/// # Returns
+/// 可以时返回文档 ID 和各字段的增量
+fn increment_only(update: &UpdateStatement) -> Option<(ObjectId, Vec<(String, BomlValue)>)> {
+    if update.upsert || update.updates.is_empty() {
+        return None;
+    }
+    let id = id_equality(update.filter.as_ref()?)?;
+    let deltas = update
+        .updates
+        .iter()
+        .map(|op| match op {
+            // 增量按顶层字段名合并,路径字段需要读出文档后更新
+            UpdateOperation::Inc { field, value } if merge::is_numeric(value) && !field.contains('.') => {
+                Some((field.clone(), value.clone()))
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((id, deltas))
+}
+
+/// # Brief
+/// 两个字段路径是否指向同一个值或互相嵌套(如 `a` 与 `a.b`)
+fn paths_overlap(a: &str, b: &str) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    long.strip_prefix(short).is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// # Brief
+/// 如果过滤条件是 `_id = <ObjectId 或十六进制字符串>`,返回该 ID
+fn id_equality(filter: &Expression) -> Option<ObjectId> {
+    let Expression::Binary { left, op: BinaryOp::Eq, right } = filter else {
+        return None;
+    };
+    let literal = match (left.as_ref(), right.as_ref()) {
+        (Expression::Field(field), Expression::Literal(value))
+        | (Expression::Literal(value), Expression::Field(field)) if field == "_id" => value,
+        _ => return None,
+    };
+    match literal {
+        BomlValue::ObjectId(id) => Some(*id),
+        BomlValue::String(hex) => ObjectId::from_hex(hex).ok(),
+        _ => None,
+    }
+}
+
+/// # Brief
+/// 如果值是 NEXTVAL 占位文档 `{"$nextval": "name"}`,返回序列名称
+fn nextval_sequence(value: &BomlValue) -> Option<&str> {
+    match value {
+        BomlValue::Document(fields) if fields.len() == 1 => fields.get(NEXTVAL_KEY)?.as_str(),
+        _ => None,
+    }
+}
+
+fn contains_nextval(value: &BomlValue) -> bool {
+    match value {
+        BomlValue::Document(fields) => nextval_sequence(value).is_some() || fields.values().any(contains_nextval),
+        BomlValue::Array(items) => items.iter().any(contains_nextval),
+        _ => false,
+    }
+}
+
+/// # Brief
+/// 按 ORDER BY 字段原地排序文档
+fn sort_documents(docs: &mut [Document], sort_fields: &[SortField]) {
+    docs.sort_by(|a, b| {
+        for sort_field in sort_fields {
+            let a_val = a.get_path(&sort_field.field);
+            let b_val = b.get_path(&sort_field.field);
+
+            let cmp = compare_boml_values(a_val, b_val);
+            if cmp != std::cmp::Ordering::Equal {
+                return match sort_field.order {
+                    SortOrder::Ascending => cmp,
+                    SortOrder::Descending => cmp.reverse(),
+                };
+            }
         }
+        std::cmp::Ordering::Equal
+    });
+}
+
+/// # Brief
+/// 排序是否与文档键顺序一致(只按 `_id` 升序)
+fn is_id_order(sort_fields: &[SortField]) -> bool {
+    matches!(sort_fields, [SortField { field, order: SortOrder::Ascending }] if field == "_id")
+}
+
+/// # Brief
+/// 按 RETURNING 子句构造写入结果
+///
+/// # Arguments
+/// * `operation` - 写入操作名称(insert/update/delete)
+/// * `docs` - 受影响的文档
+/// * `returning` - RETURNING 子句
+fn returning_response(operation: &str, docs: Vec<Document>, returning: &Returning) -> QueryResponse {
+    let documents: Vec<Document> = match returning {
+        Returning::All => docs,
+        Returning::Fields(fields) => docs.into_iter().map(|doc| project_document(doc, fields)).collect(),
+    };
+    QueryResponse::Returning {
+        operation: operation.to_string(),
+        affected_count: documents.len() as u64,
+        documents,
     }
 }
 
@@ -559,9 +2430,10 @@ fn project_document(doc: Document, fields: &[String]) -> Document {
         result.set_id(*id);
     }
 
+    // 嵌套路径按原结构写回,结果中的值都取自同一文档,路径不会互相冲突
     for field in fields {
         if let Some(value) = doc.get_path(field) {
-            result.insert(field.clone(), value.clone());
+            let _ = result.set_path(field, value.clone());
         }
     }
 
@@ -571,66 +2443,113 @@ fn project_document(doc: Document, fields: &[String]) -> Document {
 fn apply_update_operation(doc: &mut Document, op: &UpdateOperation) -> QueryResult<()> {
     match op {
         UpdateOperation::Set { field, value } => {
-            doc.insert(field.clone(), value.clone());
+            set_path(doc, field, value.clone())?;
         }
         UpdateOperation::Unset { field } => {
-            doc.remove(field);
+            doc.remove_path(field);
         }
         UpdateOperation::Inc { field, value } => {
-            let current = doc.get(field).cloned().unwrap_or(BomlValue::Int64(0));
-            let new_value = add_values(&current, value)?;
-            doc.insert(field.clone(), new_value);
-        }
-        UpdateOperation::Push { field, value } => {
-            let current = doc.get(field).cloned();
-            match current {
-                Some(BomlValue::Array(mut arr)) => {
-                    arr.push(value.clone());
-                    doc.insert(field.clone(), BomlValue::Array(arr));
-                }
-                None => {
-                    doc.insert(field.clone(), BomlValue::Array(vec![value.clone()]));
+            let current = doc.get_path(field).cloned().unwrap_or(BomlValue::Int64(0));
+            let new_value = merge::add_numeric(&current, value).ok_or_else(|| {
+                if merge::is_numeric(&current) && merge::is_numeric(value) {
+                    return QueryError::Overflow(format!("{} += {} on field '{}'", current, value, field));
                 }
-                _ => {
-                    return Err(QueryError::TypeError(format!(
-                        "Cannot push to non-array field: {}",
-                        field
-                    )));
+                // 字段本身不是数值时报告字段类型,否则报告增量的类型
+                let actual = if merge::is_numeric(&current) { value } else { &current };
+                QueryError::Validation(vec![ValidationDetail::new(
+                    field,
+                    "number",
+                    actual.type_name(),
+                    RULE_UPDATE_INC,
+                )])
+            })?;
+            set_path(doc, field, new_value)?;
+        }
+        UpdateOperation::Push { field, value } | UpdateOperation::AddToSet { field, value } => {
+            let mut arr = match doc.get_path(field).cloned() {
+                Some(BomlValue::Array(arr)) => arr,
+                None => Vec::new(),
+                Some(other) => {
+                    return Err(QueryError::Validation(vec![ValidationDetail::new(
+                        field,
+                        "array",
+                        other.type_name(),
+                        RULE_UPDATE_PUSH,
+                    )]));
                 }
+            };
+            if matches!(op, UpdateOperation::Push { .. }) || !arr.iter().any(|v| filter::values_equal(v, value)) {
+                arr.push(value.clone());
             }
+            set_path(doc, field, BomlValue::Array(arr))?;
         }
         UpdateOperation::Pull { field, value } => {
-            if let Some(BomlValue::Array(arr)) = doc.get(field).cloned() {
+            if let Some(BomlValue::Array(arr)) = doc.get_path(field).cloned() {
                 let filtered: Vec<BomlValue> = arr
                     .into_iter()
-                    .filter(|v| v != value)
+                    .filter(|v| !filter::values_equal(v, value))
                     .collect();
-                doc.insert(field.clone(), BomlValue::Array(filtered));
+                set_path(doc, field, BomlValue::Array(filtered))?;
+            }
+        }
+        UpdateOperation::PullAll { field, values } => {
+            if let Some(BomlValue::Array(mut arr)) = doc.get_path(field).cloned() {
+                arr.retain(|v| !values.iter().any(|value| filter::values_equal(v, value)));
+                set_path(doc, field, BomlValue::Array(arr))?;
+            }
+        }
+        UpdateOperation::Pop { field, first } => {
+            if let Some(BomlValue::Array(mut arr)) = doc.get_path(field).cloned() {
+                if *first && !arr.is_empty() {
+                    arr.remove(0);
+                } else {
+                    arr.pop();
+                }
+                set_path(doc, field, BomlValue::Array(arr))?;
             }
         }
         UpdateOperation::Rename { from, to } => {
-            if let Some(value) = doc.remove(from) {
-                doc.insert(to.clone(), value);
+            if let Some(value) = doc.remove_path(from) {
+                set_path(doc, to, value)?;
             }
         }
     }
     Ok(())
 }
 
-fn add_values(a: &BomlValue, b: &BomlValue) -> QueryResult<BomlValue> {
-    match (a, b) {
-        (BomlValue::Int32(x), BomlValue::Int32(y)) => Ok(BomlValue::Int32(x + y)),
-        (BomlValue::Int64(x), BomlValue::Int64(y)) => Ok(BomlValue::Int64(x + y)),
-        (BomlValue::Int32(x), BomlValue::Int64(y)) => Ok(BomlValue::Int64(*x as i64 + y)),
-        (BomlValue::Int64(x), BomlValue::Int32(y)) => Ok(BomlValue::Int64(x + *y as i64)),
-        (BomlValue::Float64(x), BomlValue::Float64(y)) => Ok(BomlValue::Float64(x + y)),
-        (BomlValue::Float64(x), BomlValue::Int32(y)) => Ok(BomlValue::Float64(x + *y as f64)),
-        (BomlValue::Float64(x), BomlValue::Int64(y)) => Ok(BomlValue::Float64(x + *y as f64)),
-        _ => Err(QueryError::TypeError(format!(
-            "Cannot add {:?} and {:?}",
-            a.type_name(),
-            b.type_name()
-        ))),
+/// # Brief
+/// 更新操作中的所有字段路径
+fn update_fields_mut(op: &mut UpdateOperation) -> Vec<&mut String> {
+    match op {
+        UpdateOperation::Set { field, .. }
+        | UpdateOperation::Unset { field }
+        | UpdateOperation::Inc { field, .. }
+        | UpdateOperation::Push { field, .. }
+        | UpdateOperation::Pull { field, .. }
+        | UpdateOperation::AddToSet { field, .. }
+        | UpdateOperation::Pop { field, .. }
+        | UpdateOperation::PullAll { field, .. } => vec![field],
+        UpdateOperation::Rename { from, to } => vec![from, to],
+    }
+}
+
+/// # Brief
+/// 按路径写入字段,路径冲突转换为校验错误
+fn set_path(doc: &mut Document, path: &str, value: BomlValue) -> QueryResult<()> {
+    doc.set_path(path, value).map_err(|e| match e {
+        BomlError::PathConflict { path, found } => {
+            QueryError::Validation(vec![ValidationDetail::new(&path, "document", found, RULE_UPDATE_PATH)])
+        }
+        other => other.into(),
+    })
+}
+
+/// GRAPH LOOKUP 的匹配键,数组按元素展开,Null 不参与匹配
+fn graph_keys(value: &BomlValue) -> Vec<String> {
+    match value {
+        BomlValue::Null => Vec::new(),
+        BomlValue::Array(items) => items.iter().filter(|v| !v.is_null()).map(|v| v.to_string()).collect(),
+        other => vec![other.to_string()],
     }
 }
 
@@ -644,24 +2563,14 @@ fn value_to_f64(val: &BomlValue) -> f64 {
     }
 }
 
+/// 排序和 MIN/MAX 使用的顺序:缺失的字段最小,其余与过滤条件的比较(`filter::compare_values`)一致,
+/// 无法比较的类型视为相等
 fn compare_boml_values(a: Option<&BomlValue>, b: Option<&BomlValue>) -> std::cmp::Ordering {
     match (a, b) {
         (None, None) => std::cmp::Ordering::Equal,
         (None, Some(_)) => std::cmp::Ordering::Less,
         (Some(_), None) => std::cmp::Ordering::Greater,
-        (Some(BomlValue::Null), Some(BomlValue::Null)) => std::cmp::Ordering::Equal,
-        (Some(BomlValue::Null), _) => std::cmp::Ordering::Less,
-        (_, Some(BomlValue::Null)) => std::cmp::Ordering::Greater,
-        (Some(BomlValue::Int32(a)), Some(BomlValue::Int32(b))) => a.cmp(b),
-        (Some(BomlValue::Int64(a)), Some(BomlValue::Int64(b))) => a.cmp(b),
-        (Some(BomlValue::Int32(a)), Some(BomlValue::Int64(b))) => (*a as i64).cmp(b),
-        (Some(BomlValue::Int64(a)), Some(BomlValue::Int32(b))) => a.cmp(&(*b as i64)),
-        (Some(BomlValue::Float64(a)), Some(BomlValue::Float64(b))) => {
-            a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
-        }
-        (Some(BomlValue::String(a)), Some(BomlValue::String(b))) => a.cmp(b),
-        (Some(BomlValue::DateTime(a)), Some(BomlValue::DateTime(b))) => a.cmp(b),
-        _ => std::cmp::Ordering::Equal,
+        (Some(a), Some(b)) => filter::compare_values(a, b).map_or(std::cmp::Ordering::Equal, |cmp| cmp.cmp(&0)),
     }
 }
 
@@ -685,12 +2594,27 @@ pub enum QueryResponse {
     Delete {
         deleted_count: u64,
     },
+    /// DRY RUN 结果: 会被匹配/修改的文档数及部分文档 ID
+    DryRun {
+        operation: String,
+        matched_count: u64,
+        modified_count: u64,
+        sample_ids: Vec<String>,
+    },
+    /// 带 RETURNING 子句的写入结果: 受影响的文档数及这些文档
+    Returning {
+        operation: String,
+        affected_count: u64,
+        documents: Vec<Document>,
+    },
     Databases(Vec<String>),
     Collections(Vec<String>),
     Indexes(Vec<IndexInfo>),
     Status {
         size: u64,
         stats: String,
+        /// 文档缓存的命中统计,未启用文档缓存时为 None
+        document_cache: Option<CacheStats>,
     },
 }
 
@@ -703,6 +2627,12 @@ pub struct IndexInfo {
     pub collection: String,
     pub fields: Vec<String>,
     pub unique: bool,
+    /// 索引键长度上限(字节),None 表示不限制
+    pub max_key_size: Option<usize>,
+    /// 超长键的处理方式
+    pub oversized_policy: OversizedKeyPolicy,
+    /// 自启动以来被截断或跳过的超长键数量
+    pub oversized_keys: u64,
 }
 
 impl QueryResponse {
@@ -748,6 +2678,30 @@ impl QueryResponse {
                 })
                 .to_string()
             }
+            QueryResponse::DryRun { operation, matched_count, modified_count, sample_ids } => {
+                serde_json::json!({
+                    "ok": 1,
+                    "dryRun": true,
+                    "operation": operation,
+                    "matchedCount": matched_count,
+                    "modifiedCount": modified_count,
+                    "sampleIds": sample_ids
+                })
+                .to_string()
+            }
+            QueryResponse::Returning { operation, affected_count, documents } => {
+                let values: Vec<serde_json::Value> = documents
+                    .iter()
+                    .map(|d| serde_json::from_str(&d.to_json()).unwrap_or(serde_json::Value::Null))
+                    .collect();
+                serde_json::json!({
+                    "ok": 1,
+                    "operation": operation,
+                    "affectedCount": affected_count,
+                    "documents": values
+                })
+                .to_string()
+            }
             QueryResponse::Databases(dbs) => {
                 serde_json::json!({ "databases": dbs }).to_string()
             }
@@ -762,19 +2716,160 @@ impl QueryResponse {
                             "name": i.name,
                             "collection": i.collection,
                             "fields": i.fields,
-                            "unique": i.unique
+                            "unique": i.unique,
+                            "max_key_size": i.max_key_size,
+                            "oversized_policy": i.oversized_policy.as_str(),
+                            "oversized_keys": i.oversized_keys
                         })
                     })
                     .collect();
                 serde_json::json!({ "indexes": info }).to_string()
             }
-            QueryResponse::Status { size, stats } => {
+            QueryResponse::Status { size, stats, document_cache } => {
                 serde_json::json!({
                     "size": size,
-                    "stats": stats
+                    "stats": stats,
+                    "document_cache": document_cache.as_ref().map(|cache| serde_json::json!({
+                        "hits": cache.hits,
+                        "misses": cache.misses,
+                        "hit_rate": cache.hit_rate,
+                        "entries": cache.entries,
+                        "size": cache.size,
+                        "capacity": cache.capacity,
+                        "policy": cache.policy.as_str(),
+                    })),
                 })
                 .to_string()
             }
         }
     }
+
+    /// # Brief
+    /// 转换为文档列表、影响的文档数和提示信息
+    ///
+    /// 服务器协议和 C API 共用此转换:非文档类响应转换为 `{name: ...}` 等形式的文档。
+    ///
+    /// # Returns
+    /// (文档, 影响的文档数, 提示信息)
+    pub fn into_parts(self) -> (Vec<Document>, u64, Option<String>) {
+        let named = |name: String| {
+            let mut doc = Document::without_id();
+            doc.insert("name", name);
+            doc
+        };
+
+        match self {
+            QueryResponse::Ok { message } => (vec![], 0, Some(message)),
+            QueryResponse::Documents(docs) => {
+                let count = docs.len() as u64;
+                (docs, count, None)
+            }
+            QueryResponse::Insert { inserted_count, .. } => (
+                vec![],
+                inserted_count,
+                Some(format!("Inserted {} document(s)", inserted_count)),
+            ),
+            QueryResponse::Update {
+                matched_count,
+                modified_count,
+            } => (
+                vec![],
+                modified_count,
+                Some(format!("Matched {}, modified {}", matched_count, modified_count)),
+            ),
+            QueryResponse::Delete { deleted_count } => (
+                vec![],
+                deleted_count,
+                Some(format!("Deleted {} document(s)", deleted_count)),
+            ),
+            QueryResponse::DryRun {
+                operation,
+                matched_count,
+                modified_count,
+                sample_ids,
+            } => {
+                let mut doc = Document::without_id();
+                doc.insert("operation", operation.as_str());
+                doc.insert("matched_count", matched_count as i64);
+                doc.insert("modified_count", modified_count as i64);
+                doc.insert("sample_ids", BomlValue::from(sample_ids));
+                (
+                    vec![doc],
+                    0,
+                    Some(format!(
+                        "Dry run: {} would affect {} of {} matched document(s)",
+                        operation, modified_count, matched_count
+                    )),
+                )
+            }
+            QueryResponse::Returning {
+                operation,
+                affected_count,
+                documents,
+            } => {
+                let message = format!("{} returned {} document(s)", operation.to_uppercase(), affected_count);
+                (documents, affected_count, Some(message))
+            }
+            QueryResponse::Databases(names) | QueryResponse::Collections(names) => {
+                let count = names.len() as u64;
+                (names.into_iter().map(named).collect(), count, None)
+            }
+            QueryResponse::Indexes(indexes) => {
+                let count = indexes.len() as u64;
+                let docs = indexes
+                    .into_iter()
+                    .map(|index| {
+                        let mut doc = named(index.name);
+                        doc.insert("collection", index.collection);
+                        doc.insert(
+                            "fields",
+                            BomlValue::Array(index.fields.into_iter().map(BomlValue::from).collect()),
+                        );
+                        doc.insert("unique", index.unique);
+                        doc.insert(
+                            "max_key_size",
+                            index.max_key_size.map_or(BomlValue::Null, |size| BomlValue::Int64(size as i64)),
+                        );
+                        doc.insert("oversized_policy", index.oversized_policy.as_str());
+                        doc.insert("oversized_keys", index.oversized_keys as i64);
+                        doc
+                    })
+                    .collect();
+                (docs, count, None)
+            }
+            QueryResponse::Status { size, stats, .. } => {
+                let mut doc = Document::without_id();
+                doc.insert("storage_size_bytes", size as i64);
+                doc.insert("stats", stats);
+                (vec![doc], 1, None)
+            }
+        }
+    }
+}
+
+/// # Brief
+/// 根据 CREATE TEXT INDEX 的 WITH 选项构建文本分析器,同时校验选项是否可用
+///
+/// # Returns
+/// 文本分析器;未知的分词器返回 Execution 错误,分词器不可用或停用词、词干选项无效返回存储层错误
+fn build_text_analyzer(options: &TextIndexOptions) -> QueryResult<TextAnalyzer> {
+    let tokenizer = match options.tokenizer.as_deref() {
+        Some(name) => TokenizerType::from_name(name)
+            .ok_or_else(|| QueryError::Execution(format!("Unknown tokenizer '{}'", name)))?,
+        None => TokenizerType::default(),
+    };
+    let stopwords = match &options.stopwords {
+        Some(TextStopWords::Builtin(name)) => StopWords::Builtin(name.clone()),
+        Some(TextStopWords::Custom(words)) => StopWords::Custom(words.clone()),
+        None => StopWords::None,
+    };
+
+    let analyzer = TextAnalyzer::new(
+        tokenizer.build()?,
+        &stopwords,
+        options.stemmer.as_deref(),
+        1,
+        usize::MAX,
+    )?;
+    Ok(analyzer)
 }
@@ -6,18 +6,22 @@
 //! - 特殊运算符 (IN, BETWEEN, LIKE, IS NULL, EXISTS)
 //! - 算术运算 (+, -, *, /, %)
 //! - 内置函数 (UPPER, LOWER, LENGTH, ABS, FLOOR, CEIL, ROUND, COALESCE)
+//! - 通过 `Filter::with_functions` 调用自定义标量函数
 //! - 正则表达式匹配
 //!
 //! 求值规则:
-//! - 字段路径支持嵌套(使用点分隔,如 "user.profile.name")
+//! - 字段路径支持嵌套(使用点分隔,如 "user.profile.name"),数字段为数组下标(如 "items.0.qty")
+//! - 路径经过数组时比较任一元素(如 "items.sku" = 'a' 匹配任一元素的 sku 为 'a')
 //! - 类型自动转换(Int32/Int64, Float64)
 //! - 浮点数相等比较使用 EPSILON 精度
 //! - Null 值排序始终在最前面
 
 use crate::ast::*;
+use crate::udf::FunctionRegistry;
 use crate::{QueryError, QueryResult};
-use mikudb_boml::{BomlValue, Document};
+use mikudb_boml::{keyenc, BomlValue, Document};
 use regex::Regex;
+use std::sync::Arc;
 
 /// # Brief
 /// 求值表达式为布尔值
@@ -38,6 +42,31 @@ use regex::Regex;
 /// # Returns
 /// 布尔值结果
 pub fn evaluate(expr: &Expression, doc: &Document) -> QueryResult<bool> {
+    evaluate_with(expr, doc, None)
+}
+
+/// # Brief
+/// 求值表达式为 BOML 值,用于聚合 PROJECT 阶段的计算字段
+///
+/// # Arguments
+/// * `expr` - 表达式
+/// * `doc` - 文档
+/// * `functions` - 自定义函数注册表
+pub fn evaluate_value_with(expr: &Expression, doc: &Document, functions: Option<&FunctionRegistry>) -> QueryResult<BomlValue> {
+    evaluate_value(expr, doc, functions)
+}
+
+/// # Brief
+/// 求值表达式为布尔值,可以调用 `functions` 中注册的自定义函数
+///
+/// # Arguments
+/// * `expr` - 表达式
+/// * `doc` - 文档
+/// * `functions` - 自定义函数注册表
+///
+/// # Returns
+/// 布尔值结果
+pub fn evaluate_with(expr: &Expression, doc: &Document, functions: Option<&FunctionRegistry>) -> QueryResult<bool> {
     match expr {
         Expression::Literal(BomlValue::Boolean(b)) => Ok(*b),
         Expression::Literal(_) => Ok(true),
@@ -49,11 +78,11 @@ pub fn evaluate(expr: &Expression, doc: &Document) -> QueryResult<bool> {
         }
 
         Expression::Binary { left, op, right } => {
-            evaluate_binary(left, *op, right, doc)
+            evaluate_binary(left, *op, right, doc, functions)
         }
 
         Expression::Unary { op, expr } => match op {
-            UnaryOp::Not => Ok(!evaluate(expr, doc)?),
+            UnaryOp::Not => Ok(!evaluate_with(expr, doc, functions)?),
             UnaryOp::Neg => Err(QueryError::TypeError(
                 "Cannot negate in boolean context".to_string(),
             )),
@@ -61,9 +90,9 @@ pub fn evaluate(expr: &Expression, doc: &Document) -> QueryResult<bool> {
 
         // IN 运算符: value IN [list]
         Expression::In { expr, list } => {
-            let value = evaluate_value(expr, doc)?;
+            let value = evaluate_value(expr, doc, functions)?;
             for item in list {
-                let item_value = evaluate_value(item, doc)?;
+                let item_value = evaluate_value(item, doc, functions)?;
                 if values_equal(&value, &item_value) {
                     return Ok(true);
                 }
@@ -73,17 +102,17 @@ pub fn evaluate(expr: &Expression, doc: &Document) -> QueryResult<bool> {
 
         // BETWEEN 运算符: value BETWEEN low AND high
         Expression::Between { expr, low, high } => {
-            let value = evaluate_value(expr, doc)?;
-            let low_val = evaluate_value(low, doc)?;
-            let high_val = evaluate_value(high, doc)?;
-            Ok(compare_values(&value, &low_val) >= 0
-                && compare_values(&value, &high_val) <= 0)
+            let value = evaluate_value(expr, doc, functions)?;
+            let low_val = evaluate_value(low, doc, functions)?;
+            let high_val = evaluate_value(high, doc, functions)?;
+            Ok(compare_values(&value, &low_val).is_some_and(|c| c >= 0)
+                && compare_values(&value, &high_val).is_some_and(|c| c <= 0))
         }
 
         // LIKE 模式匹配: value LIKE "pattern"
         // % 匹配任意字符序列, _ 匹配单个字符
         Expression::Like { expr, pattern } => {
-            let value = evaluate_value(expr, doc)?;
+            let value = evaluate_value(expr, doc, functions)?;
             if let BomlValue::String(s) = value {
                 // 将 SQL LIKE 模式转换为正则表达式
                 let regex_pattern = pattern
@@ -99,7 +128,7 @@ pub fn evaluate(expr: &Expression, doc: &Document) -> QueryResult<bool> {
 
         // IS NULL / IS NOT NULL
         Expression::IsNull { expr, negated } => {
-            let value = evaluate_value(expr, doc)?;
+            let value = evaluate_value(expr, doc, functions)?;
             let is_null = matches!(value, BomlValue::Null);
             Ok(if *negated { !is_null } else { is_null })
         }
@@ -111,10 +140,10 @@ pub fn evaluate(expr: &Expression, doc: &Document) -> QueryResult<bool> {
         }
 
         Expression::Call { function, args } => {
-            evaluate_function(function, args, doc)
+            evaluate_function(function, args, doc, functions)
         }
 
-        Expression::Array(_) | Expression::Document(_) => Ok(true),
+        Expression::Array(_) | Expression::Document(_) | Expression::CurrentDocument => Ok(true),
     }
 }
 
@@ -131,6 +160,7 @@ pub fn evaluate(expr: &Expression, doc: &Document) -> QueryResult<bool> {
 /// * `op` - 二元操作符
 /// * `right` - 右操作数
 /// * `doc` - 文档
+/// * `functions` - 自定义函数注册表
 ///
 /// # Returns
 /// 布尔值结果
@@ -139,44 +169,88 @@ fn evaluate_binary(
     op: BinaryOp,
     right: &Expression,
     doc: &Document,
+    functions: Option<&FunctionRegistry>,
 ) -> QueryResult<bool> {
     match op {
         // 逻辑运算使用短路求值
-        BinaryOp::And => Ok(evaluate(left, doc)? && evaluate(right, doc)?),
-        BinaryOp::Or => Ok(evaluate(left, doc)? || evaluate(right, doc)?),
+        BinaryOp::And => Ok(evaluate_with(left, doc, functions)? && evaluate_with(right, doc, functions)?),
+        BinaryOp::Or => Ok(evaluate_with(left, doc, functions)? || evaluate_with(right, doc, functions)?),
         _ => {
-            let left_val = evaluate_value(left, doc)?;
-            let right_val = evaluate_value(right, doc)?;
-
-            match op {
-                BinaryOp::Eq => Ok(values_equal(&left_val, &right_val)),
-                BinaryOp::Ne => Ok(!values_equal(&left_val, &right_val)),
-                BinaryOp::Lt => Ok(compare_values(&left_val, &right_val) < 0),
-                BinaryOp::Le => Ok(compare_values(&left_val, &right_val) <= 0),
-                BinaryOp::Gt => Ok(compare_values(&left_val, &right_val) > 0),
-                BinaryOp::Ge => Ok(compare_values(&left_val, &right_val) >= 0),
-                // 正则表达式匹配
-                BinaryOp::Regex => {
-                    if let (BomlValue::String(s), BomlValue::String(pattern)) =
-                        (&left_val, &right_val)
-                    {
-                        let regex = Regex::new(pattern.as_str()).map_err(|e| {
-                            QueryError::InvalidOperator(format!("Invalid regex: {}", e))
-                        })?;
-                        Ok(regex.is_match(s.as_str()))
-                    } else {
-                        Ok(false)
+            // 路径经过数组时(如 items.sku),任一元素满足即匹配;!= 要求所有元素都不相等
+            let elements = match left {
+                Expression::Field(path) if path != "_id" && doc.get_path(path).is_none() => {
+                    array_path_values(doc, path)
+                }
+                _ => Vec::new(),
+            };
+            if !elements.is_empty() {
+                let right_val = evaluate_value(right, doc, functions)?;
+                if op == BinaryOp::Ne {
+                    return Ok(!elements.iter().any(|value| values_equal(value, &right_val)));
+                }
+                for value in elements {
+                    if compare(value, op, &right_val)? {
+                        return Ok(true);
                     }
                 }
-                _ => Err(QueryError::InvalidOperator(format!(
-                    "Operator {} not supported in filter",
-                    op
-                ))),
+                return Ok(false);
+            }
+
+            let left_val = evaluate_value(left, doc, functions)?;
+            let right_val = evaluate_value(right, doc, functions)?;
+            compare(&left_val, op, &right_val)
+        }
+    }
+}
+
+/// # Brief
+/// 比较运算和正则匹配
+fn compare(left_val: &BomlValue, op: BinaryOp, right_val: &BomlValue) -> QueryResult<bool> {
+    match op {
+        BinaryOp::Eq => Ok(values_equal(left_val, right_val)),
+        BinaryOp::Ne => Ok(!values_equal(left_val, right_val)),
+        BinaryOp::Lt => Ok(compare_values(left_val, right_val).is_some_and(|c| c < 0)),
+        BinaryOp::Le => Ok(compare_values(left_val, right_val).is_some_and(|c| c <= 0)),
+        BinaryOp::Gt => Ok(compare_values(left_val, right_val).is_some_and(|c| c > 0)),
+        BinaryOp::Ge => Ok(compare_values(left_val, right_val).is_some_and(|c| c >= 0)),
+        // 正则表达式匹配
+        BinaryOp::Regex => {
+            if let (BomlValue::String(s), BomlValue::String(pattern)) = (left_val, right_val) {
+                let regex = Regex::new(pattern.as_str())
+                    .map_err(|e| QueryError::InvalidOperator(format!("Invalid regex: {}", e)))?;
+                Ok(regex.is_match(s.as_str()))
+            } else {
+                Ok(false)
             }
         }
+        _ => Err(QueryError::InvalidOperator(format!(
+            "Operator {} not supported in filter",
+            op
+        ))),
     }
 }
 
+/// # Brief
+/// 沿路径展开数组,收集所有元素上该路径的值
+///
+/// 如 `items.sku` 在 `items` 为文档数组时返回每个元素的 `sku`;数字段仍按下标取值。
+fn array_path_values<'a>(doc: &'a Document, path: &str) -> Vec<&'a BomlValue> {
+    let mut parts = path.split('.');
+    let mut values: Vec<&BomlValue> = parts.next().and_then(|first| doc.get(first)).into_iter().collect();
+    for part in parts {
+        values = values
+            .into_iter()
+            .flat_map(|value| match value {
+                BomlValue::Array(items) if part.parse::<usize>().is_err() => {
+                    items.iter().filter_map(|item| item.get(part)).collect()
+                }
+                other => other.get(part).into_iter().collect::<Vec<_>>(),
+            })
+            .collect();
+    }
+    values
+}
+
 /// # Brief
 /// 求值表达式为 BOML 值
 ///
@@ -185,23 +259,26 @@ fn evaluate_binary(
 /// # Arguments
 /// * `expr` - 表达式
 /// * `doc` - 文档
+/// * `functions` - 自定义函数注册表
 ///
 /// # Returns
 /// BomlValue 结果
-fn evaluate_value(expr: &Expression, doc: &Document) -> QueryResult<BomlValue> {
+fn evaluate_value(expr: &Expression, doc: &Document, functions: Option<&FunctionRegistry>) -> QueryResult<BomlValue> {
     match expr {
         Expression::Literal(v) => Ok(v.clone()),
         // 字段路径解析(支持嵌套路径,如 "user.name")
+        // `_id` 不在字段表中,单独取出
+        Expression::Field(path) if path == "_id" => Ok(doc.id().map_or(BomlValue::Null, |id| BomlValue::ObjectId(*id))),
         Expression::Field(path) => Ok(doc.get_path(path).cloned().unwrap_or(BomlValue::Null)),
         // 算术运算
         Expression::Binary { left, op, right } => {
-            let left_val = evaluate_value(left, doc)?;
-            let right_val = evaluate_value(right, doc)?;
+            let left_val = evaluate_value(left, doc, functions)?;
+            let right_val = evaluate_value(right, doc, functions)?;
             compute_arithmetic(&left_val, *op, &right_val)
         }
         // 一元运算
         Expression::Unary { op, expr } => {
-            let val = evaluate_value(expr, doc)?;
+            let val = evaluate_value(expr, doc, functions)?;
             match op {
                 UnaryOp::Neg => negate_value(&val),
                 UnaryOp::Not => {
@@ -214,21 +291,22 @@ fn evaluate_value(expr: &Expression, doc: &Document) -> QueryResult<BomlValue> {
             }
         }
         Expression::Call { function, args } => {
-            evaluate_function_value(function, args, doc)
+            evaluate_function_value(function, args, doc, functions)
         }
         // 数组字面量(递归求值所有元素)
         Expression::Array(items) => {
             let values: QueryResult<Vec<BomlValue>> = items
                 .iter()
-                .map(|e| evaluate_value(e, doc))
+                .map(|e| evaluate_value(e, doc, functions))
                 .collect();
             Ok(BomlValue::Array(values?))
         }
+        Expression::CurrentDocument => Ok(BomlValue::from(doc.clone())),
         // 文档字面量(递归求值所有字段值)
         Expression::Document(fields) => {
             let mut map = indexmap::IndexMap::new();
             for (key, expr) in fields {
-                let value = evaluate_value(expr, doc)?;
+                let value = evaluate_value(expr, doc, functions)?;
                 map.insert(compact_str::CompactString::from(key.as_str()), value);
             }
             Ok(BomlValue::Document(map))
@@ -243,8 +321,8 @@ fn evaluate_value(expr: &Expression, doc: &Document) -> QueryResult<BomlValue> {
 /// 判断两个 BOML 值是否相等
 ///
 /// 相等规则:
-/// - 浮点数使用 EPSILON 精度比较
-/// - Int32/Int64 自动类型转换
+/// - 数值之间精确比较,与 `compare_values` 的结果为 0 等价(`5 = 5.0`,`-0.0 = 0.0`,NaN 与 NaN 相等),
+///   与索引键一致;需要容差时使用 `APPROX_EQ`
 /// - 数组按元素逐个比较
 /// - 不同类型返回 false
 ///
@@ -254,24 +332,21 @@ fn evaluate_value(expr: &Expression, doc: &Document) -> QueryResult<BomlValue> {
 ///
 /// # Returns
 /// 是否相等
-fn values_equal(a: &BomlValue, b: &BomlValue) -> bool {
+pub(crate) fn values_equal(a: &BomlValue, b: &BomlValue) -> bool {
     match (a, b) {
         (BomlValue::Null, BomlValue::Null) => true,
         (BomlValue::Boolean(a), BomlValue::Boolean(b)) => a == b,
-        (BomlValue::Int32(a), BomlValue::Int32(b)) => a == b,
-        (BomlValue::Int64(a), BomlValue::Int64(b)) => a == b,
-        // Int32/Int64 混合比较
-        (BomlValue::Int32(a), BomlValue::Int64(b)) => (*a as i64) == *b,
-        (BomlValue::Int64(a), BomlValue::Int32(b)) => *a == (*b as i64),
-        // 浮点数使用 EPSILON 精度
-        (BomlValue::Float64(a), BomlValue::Float64(b)) => (a - b).abs() < f64::EPSILON,
         (BomlValue::String(a), BomlValue::String(b)) => a == b,
         (BomlValue::ObjectId(a), BomlValue::ObjectId(b)) => a == b,
+        // ObjectId 与十六进制字符串比较,如 `_id = '65a1...'`
+        (BomlValue::ObjectId(id), BomlValue::String(hex)) | (BomlValue::String(hex), BomlValue::ObjectId(id)) => {
+            id.to_hex().eq_ignore_ascii_case(hex)
+        }
         // 数组按元素递归比较
         (BomlValue::Array(a), BomlValue::Array(b)) => {
             a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| values_equal(x, y))
         }
-        _ => false,
+        _ => float_value(a).is_some() && float_value(b).is_some() && compare_values(a, b) == Some(0),
     }
 }
 
@@ -280,46 +355,63 @@ fn values_equal(a: &BomlValue, b: &BomlValue) -> bool {
 ///
 /// 比较规则:
 /// - Null < 所有其他值
-/// - 同类型值按自然顺序比较
-/// - Int32/Int64/Float64 混合比较
-/// - 浮点数 NaN 视为 Equal
-/// - 不同类型返回 0 (Equal)
+/// - 数值之间按数值精确比较(包括整数与浮点数之间);浮点数使用 `keyenc::compare_f64` 的全序,
+///   NaN 小于所有数值,-0.0 等于 0.0
+/// - 字符串、日期与同类型的值按自然顺序比较
+/// - 其他组合不可比较,比较运算的结果为 false
+///
+/// 与索引键(`mikudb_boml::keyenc`)的顺序一致,因此使用索引的范围查询与全集合扫描结果相同。
 ///
 /// # Arguments
 /// * `a` - 第一个值
 /// * `b` - 第二个值
 ///
 /// # Returns
-/// 比较结果: -1 (小于), 0 (等于), 1 (大于)
-fn compare_values(a: &BomlValue, b: &BomlValue) -> i32 {
+/// 比较结果: -1 (小于), 0 (等于), 1 (大于);不可比较时为 None
+pub(crate) fn compare_values(a: &BomlValue, b: &BomlValue) -> Option<i32> {
     match (a, b) {
-        (BomlValue::Null, BomlValue::Null) => 0,
-        (BomlValue::Null, _) => -1,
-        (_, BomlValue::Null) => 1,
-
-        (BomlValue::Int32(a), BomlValue::Int32(b)) => a.cmp(b) as i32,
-        (BomlValue::Int64(a), BomlValue::Int64(b)) => a.cmp(b) as i32,
-        // Int32/Int64 混合比较
-        (BomlValue::Int32(a), BomlValue::Int64(b)) => (*a as i64).cmp(b) as i32,
-        (BomlValue::Int64(a), BomlValue::Int32(b)) => a.cmp(&(*b as i64)) as i32,
-
-        // 浮点数比较(NaN 视为 Equal)
-        (BomlValue::Float64(a), BomlValue::Float64(b)) => {
-            a.partial_cmp(b).map(|o| o as i32).unwrap_or(0)
-        }
-        // 整数与浮点数混合比较
-        (BomlValue::Int32(a), BomlValue::Float64(b)) => {
-            (*a as f64).partial_cmp(b).map(|o| o as i32).unwrap_or(0)
-        }
-        (BomlValue::Float64(a), BomlValue::Int32(b)) => {
-            a.partial_cmp(&(*b as f64)).map(|o| o as i32).unwrap_or(0)
-        }
+        (BomlValue::Null, BomlValue::Null) => Some(0),
+        (BomlValue::Null, _) => Some(-1),
+        (_, BomlValue::Null) => Some(1),
+
+        (BomlValue::String(a), BomlValue::String(b)) => Some(a.cmp(b) as i32),
+        (BomlValue::DateTime(a), BomlValue::DateTime(b)) => Some(a.cmp(b) as i32),
+
+        _ => match (integer_value(a), integer_value(b)) {
+            (Some(a), Some(b)) => Some(a.cmp(&b) as i32),
+            (Some(a), None) => Some(compare_integer_float(a, float_value(b)?) as i32),
+            (None, Some(b)) => Some(compare_integer_float(b, float_value(a)?).reverse() as i32),
+            (None, None) => Some(keyenc::compare_f64(float_value(a)?, float_value(b)?) as i32),
+        },
+    }
+}
 
-        (BomlValue::String(a), BomlValue::String(b)) => a.cmp(b) as i32,
+/// # Brief
+/// 精确比较整数与浮点数
+///
+/// 转换为 f64 后相等时浮点数必然是整数,再按整数比较,避免超过 2^53 的整数因精度丢失被判为相等。
+fn compare_integer_float(a: i64, b: f64) -> std::cmp::Ordering {
+    match keyenc::compare_f64(a as f64, b) {
+        std::cmp::Ordering::Equal => (a as i128).cmp(&(b as i128)),
+        other => other,
+    }
+}
 
-        (BomlValue::DateTime(a), BomlValue::DateTime(b)) => a.cmp(b) as i32,
+fn integer_value(value: &BomlValue) -> Option<i64> {
+    match value {
+        BomlValue::Int32(n) => Some(*n as i64),
+        BomlValue::Int64(n) => Some(*n),
+        _ => None,
+    }
+}
 
-        _ => 0,
+fn float_value(value: &BomlValue) -> Option<f64> {
+    match value {
+        BomlValue::Int32(n) => Some(*n as f64),
+        BomlValue::Int64(n) => Some(*n as f64),
+        BomlValue::Float32(n) => Some(*n as f64),
+        BomlValue::Float64(n) => Some(*n),
+        _ => None,
     }
 }
 
@@ -343,49 +435,16 @@ fn compare_values(a: &BomlValue, b: &BomlValue) -> i32 {
 /// 计算结果
 fn compute_arithmetic(a: &BomlValue, op: BinaryOp, b: &BomlValue) -> QueryResult<BomlValue> {
     match (a, b) {
-        (BomlValue::Int32(a), BomlValue::Int32(b)) => {
-            let result = match op {
-                BinaryOp::Add => a + b,
-                BinaryOp::Sub => a - b,
-                BinaryOp::Mul => a * b,
-                BinaryOp::Div => {
-                    // 除零检查
-                    if *b == 0 {
-                        return Err(QueryError::Execution("Division by zero".to_string()));
-                    }
-                    a / b
-                }
-                BinaryOp::Mod => {
-                    if *b == 0 {
-                        return Err(QueryError::Execution("Division by zero".to_string()));
-                    }
-                    a % b
-                }
-                _ => return Err(QueryError::InvalidOperator(format!("Invalid operator: {}", op))),
-            };
-            Ok(BomlValue::Int32(result))
-        }
-        (BomlValue::Int64(a), BomlValue::Int64(b)) => {
-            let result = match op {
-                BinaryOp::Add => a + b,
-                BinaryOp::Sub => a - b,
-                BinaryOp::Mul => a * b,
-                BinaryOp::Div => {
-                    if *b == 0 {
-                        return Err(QueryError::Execution("Division by zero".to_string()));
-                    }
-                    a / b
-                }
-                BinaryOp::Mod => {
-                    if *b == 0 {
-                        return Err(QueryError::Execution("Division by zero".to_string()));
-                    }
-                    a % b
-                }
-                _ => return Err(QueryError::InvalidOperator(format!("Invalid operator: {}", op))),
-            };
-            Ok(BomlValue::Int64(result))
+        // Int32 在 i64 中计算不会溢出,结果超出 Int32 范围时报告溢出而不是截断
+        (BomlValue::Int32(x), BomlValue::Int32(y)) => {
+            let result = integer_arithmetic(*x as i64, op, *y as i64)?;
+            i32::try_from(result)
+                .map(BomlValue::Int32)
+                .map_err(|_| QueryError::Overflow(format!("{} {} {} exceeds the int32 range", x, op, y)))
         }
+        (BomlValue::Int64(x), BomlValue::Int64(y)) => integer_arithmetic(*x, op, *y).map(BomlValue::Int64),
+        (BomlValue::Int32(x), BomlValue::Int64(y)) => integer_arithmetic(*x as i64, op, *y).map(BomlValue::Int64),
+        (BomlValue::Int64(x), BomlValue::Int32(y)) => integer_arithmetic(*x, op, *y as i64).map(BomlValue::Int64),
         (BomlValue::Float64(a), BomlValue::Float64(b)) => {
             let result = match op {
                 BinaryOp::Add => a + b,
@@ -412,6 +471,26 @@ fn compute_arithmetic(a: &BomlValue, op: BinaryOp, b: &BomlValue) -> QueryResult
     }
 }
 
+/// # Brief
+/// 整数四则运算和取模,结果超出 i64 范围时返回 `Overflow`
+///
+/// 除数为 0 时返回执行错误;`i64::MIN / -1` 的商超出范围,同样报告溢出。
+fn integer_arithmetic(a: i64, op: BinaryOp, b: i64) -> QueryResult<i64> {
+    let result = match op {
+        BinaryOp::Add => a.checked_add(b),
+        BinaryOp::Sub => a.checked_sub(b),
+        BinaryOp::Mul => a.checked_mul(b),
+        BinaryOp::Div | BinaryOp::Mod if b == 0 => {
+            return Err(QueryError::Execution("Division by zero".to_string()));
+        }
+        BinaryOp::Div => a.checked_div(b),
+        // 余数的绝对值小于除数,只有 MIN % -1 会让 checked_rem 失败,其结果为 0
+        BinaryOp::Mod => Some(a.wrapping_rem(b)),
+        _ => return Err(QueryError::InvalidOperator(format!("Invalid operator: {}", op))),
+    };
+    result.ok_or_else(|| QueryError::Overflow(format!("{} {} {} exceeds the int64 range", a, op, b)))
+}
+
 /// # Brief
 /// 对数值取反
 ///
@@ -423,9 +502,10 @@ fn compute_arithmetic(a: &BomlValue, op: BinaryOp, b: &BomlValue) -> QueryResult
 /// # Returns
 /// 取反后的值
 fn negate_value(v: &BomlValue) -> QueryResult<BomlValue> {
+    let overflow = || QueryError::Overflow(format!("-({}) exceeds the {} range", v, v.type_name()));
     match v {
-        BomlValue::Int32(n) => Ok(BomlValue::Int32(-n)),
-        BomlValue::Int64(n) => Ok(BomlValue::Int64(-n)),
+        BomlValue::Int32(n) => n.checked_neg().map(BomlValue::Int32).ok_or_else(overflow),
+        BomlValue::Int64(n) => n.checked_neg().map(BomlValue::Int64).ok_or_else(overflow),
         BomlValue::Float64(n) => Ok(BomlValue::Float64(-n)),
         _ => Err(QueryError::TypeError(format!(
             "Cannot negate {:?}",
@@ -435,10 +515,25 @@ fn negate_value(v: &BomlValue) -> QueryResult<BomlValue> {
 }
 
 /// # Brief
-/// 在布尔上下文中求值函数(不支持)
+/// 在布尔上下文中求值函数
 ///
-/// 函数调用应返回值,不应在布尔上下文中使用。
-fn evaluate_function(name: &str, _args: &[Expression], _doc: &Document) -> QueryResult<bool> {
+/// 只有 APPROX_EQ 和返回布尔值的自定义函数可以直接作为条件,其他内置函数应返回值后参与比较。
+fn evaluate_function(
+    name: &str,
+    args: &[Expression],
+    doc: &Document,
+    functions: Option<&FunctionRegistry>,
+) -> QueryResult<bool> {
+    if name.eq_ignore_ascii_case("approx_eq") || functions.and_then(|f| f.scalar(name)).is_some() {
+        return match evaluate_function_value(name, args, doc, functions)? {
+            BomlValue::Boolean(b) => Ok(b),
+            other => Err(QueryError::TypeError(format!(
+                "Function {} returned {} in boolean context",
+                name,
+                other.type_name()
+            ))),
+        };
+    }
     Err(QueryError::Execution(format!(
         "Function {} not supported in boolean context",
         name
@@ -451,12 +546,15 @@ fn evaluate_function(name: &str, _args: &[Expression], _doc: &Document) -> Query
 /// 支持的函数:
 /// - 字符串函数: UPPER, LOWER, LENGTH
 /// - 数学函数: ABS, FLOOR, CEIL, ROUND
+/// - 浮点数近似相等: APPROX_EQ(a, b [, tolerance]),默认容差为 `f64::EPSILON` 乘以两数中较大的绝对值(至少为 1)
 /// - 工具函数: COALESCE (返回第一个非 Null 值)
+/// - `functions` 中注册的自定义标量函数
 ///
 /// # Arguments
 /// * `name` - 函数名(大小写不敏感)
 /// * `args` - 参数列表
 /// * `doc` - 文档
+/// * `functions` - 自定义函数注册表
 ///
 /// # Returns
 /// 函数返回值
@@ -464,6 +562,7 @@ fn evaluate_function_value(
     name: &str,
     args: &[Expression],
     doc: &Document,
+    functions: Option<&FunctionRegistry>,
 ) -> QueryResult<BomlValue> {
     let name_lower = name.to_lowercase();
     match name_lower.as_str() {
@@ -472,7 +571,7 @@ fn evaluate_function_value(
             if args.len() != 1 {
                 return Err(QueryError::Execution("UPPER requires 1 argument".to_string()));
             }
-            let val = evaluate_value(&args[0], doc)?;
+            let val = evaluate_value(&args[0], doc, functions)?;
             if let BomlValue::String(s) = val {
                 Ok(BomlValue::String(compact_str::CompactString::from(
                     s.to_uppercase(),
@@ -486,7 +585,7 @@ fn evaluate_function_value(
             if args.len() != 1 {
                 return Err(QueryError::Execution("LOWER requires 1 argument".to_string()));
             }
-            let val = evaluate_value(&args[0], doc)?;
+            let val = evaluate_value(&args[0], doc, functions)?;
             if let BomlValue::String(s) = val {
                 Ok(BomlValue::String(compact_str::CompactString::from(
                     s.to_lowercase(),
@@ -500,7 +599,7 @@ fn evaluate_function_value(
             if args.len() != 1 {
                 return Err(QueryError::Execution("LENGTH requires 1 argument".to_string()));
             }
-            let val = evaluate_value(&args[0], doc)?;
+            let val = evaluate_value(&args[0], doc, functions)?;
             match val {
                 BomlValue::String(s) => Ok(BomlValue::Int64(s.len() as i64)),
                 BomlValue::Array(a) => Ok(BomlValue::Int64(a.len() as i64)),
@@ -514,7 +613,7 @@ fn evaluate_function_value(
             if args.len() != 1 {
                 return Err(QueryError::Execution("ABS requires 1 argument".to_string()));
             }
-            let val = evaluate_value(&args[0], doc)?;
+            let val = evaluate_value(&args[0], doc, functions)?;
             match val {
                 BomlValue::Int32(n) => Ok(BomlValue::Int32(n.abs())),
                 BomlValue::Int64(n) => Ok(BomlValue::Int64(n.abs())),
@@ -527,7 +626,7 @@ fn evaluate_function_value(
             if args.len() != 1 {
                 return Err(QueryError::Execution("FLOOR requires 1 argument".to_string()));
             }
-            let val = evaluate_value(&args[0], doc)?;
+            let val = evaluate_value(&args[0], doc, functions)?;
             match val {
                 BomlValue::Float64(n) => Ok(BomlValue::Float64(n.floor())),
                 BomlValue::Int32(n) => Ok(BomlValue::Int32(n)),
@@ -540,7 +639,7 @@ fn evaluate_function_value(
             if args.len() != 1 {
                 return Err(QueryError::Execution("CEIL requires 1 argument".to_string()));
             }
-            let val = evaluate_value(&args[0], doc)?;
+            let val = evaluate_value(&args[0], doc, functions)?;
             match val {
                 BomlValue::Float64(n) => Ok(BomlValue::Float64(n.ceil())),
                 BomlValue::Int32(n) => Ok(BomlValue::Int32(n)),
@@ -553,7 +652,7 @@ fn evaluate_function_value(
             if args.len() != 1 {
                 return Err(QueryError::Execution("ROUND requires 1 argument".to_string()));
             }
-            let val = evaluate_value(&args[0], doc)?;
+            let val = evaluate_value(&args[0], doc, functions)?;
             match val {
                 BomlValue::Float64(n) => Ok(BomlValue::Float64(n.round())),
                 BomlValue::Int32(n) => Ok(BomlValue::Int32(n)),
@@ -561,17 +660,53 @@ fn evaluate_function_value(
                 _ => Err(QueryError::TypeError("ROUND requires numeric argument".to_string())),
             }
         }
+        // 浮点数近似相等,NULL 或 NaN 参与时为 false
+        "approx_eq" => {
+            if args.len() != 2 && args.len() != 3 {
+                return Err(QueryError::Execution("APPROX_EQ requires 2 or 3 arguments".to_string()));
+            }
+            let values = args
+                .iter()
+                .map(|arg| evaluate_value(arg, doc, functions))
+                .collect::<QueryResult<Vec<_>>>()?;
+            if values.iter().any(|value| matches!(value, BomlValue::Null)) {
+                return Ok(BomlValue::Boolean(false));
+            }
+            let numbers = values
+                .iter()
+                .map(float_value)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| QueryError::TypeError("APPROX_EQ requires numeric arguments".to_string()))?;
+            let (a, b) = (numbers[0], numbers[1]);
+            let tolerance = match numbers.get(2) {
+                Some(&tolerance) if tolerance >= 0.0 => tolerance,
+                Some(_) => {
+                    return Err(QueryError::Execution("APPROX_EQ tolerance must be non-negative".to_string()));
+                }
+                None => f64::EPSILON * a.abs().max(b.abs()).max(1.0),
+            };
+            Ok(BomlValue::Boolean(a == b || (a - b).abs() <= tolerance))
+        }
         // 返回第一个非 Null 值
         "coalesce" => {
             for arg in args {
-                let val = evaluate_value(arg, doc)?;
+                let val = evaluate_value(arg, doc, functions)?;
                 if !matches!(val, BomlValue::Null) {
                     return Ok(val);
                 }
             }
             Ok(BomlValue::Null)
         }
-        _ => Err(QueryError::Execution(format!("Unknown function: {}", name))),
+        _ => match functions.and_then(|f| f.scalar(name)) {
+            Some(function) => {
+                let values = args
+                    .iter()
+                    .map(|arg| evaluate_value(arg, doc, functions))
+                    .collect::<QueryResult<Vec<_>>>()?;
+                function(&values)
+            }
+            None => Err(QueryError::Execution(format!("Unknown function: {}", name))),
+        },
     }
 }
 
@@ -581,6 +716,8 @@ fn evaluate_function_value(
 pub struct Filter {
     /// 过滤表达式
     expression: Expression,
+    /// 自定义函数注册表
+    functions: Option<Arc<FunctionRegistry>>,
 }
 
 impl Filter {
@@ -590,7 +727,14 @@ impl Filter {
     /// # Arguments
     /// * `expression` - 过滤表达式
     pub fn new(expression: Expression) -> Self {
-        Self { expression }
+        Self { expression, functions: None }
+    }
+
+    /// # Brief
+    /// 允许过滤条件调用 `functions` 中注册的自定义函数
+    pub fn with_functions(mut self, functions: Arc<FunctionRegistry>) -> Self {
+        self.functions = Some(functions);
+        self
     }
 
     /// # Brief
@@ -602,7 +746,7 @@ impl Filter {
     /// # Returns
     /// 是否匹配
     pub fn matches(&self, doc: &Document) -> QueryResult<bool> {
-        evaluate(&self.expression, doc)
+        evaluate_with(&self.expression, doc, self.functions.as_deref())
     }
 
     /// # Brief
@@ -624,8 +768,9 @@ impl Filter {
         Self: 'a,
     {
         let expr = self.expression.clone();
+        let functions = self.functions.clone();
         docs.filter_map(move |doc| {
-            match evaluate(&expr, &doc) {
+            match evaluate_with(&expr, &doc, functions.as_deref()) {
                 Ok(true) => Some(Ok(doc)),   // 匹配,返回文档
                 Ok(false) => None,            // 不匹配,跳过
                 Err(e) => Some(Err(e)),       // 错误,传播
@@ -657,11 +802,56 @@ mod tests {
         assert!(evaluate(&expr, &doc).unwrap());
     }
 
+    #[test]
+    fn test_id_equality() {
+        let doc = make_doc();
+        let hex = doc.id().unwrap().to_hex();
+        let expr = Expression::eq(Expression::field("_id"), Expression::literal(hex.as_str()));
+        assert!(evaluate(&expr, &doc).unwrap());
+        let other = Expression::eq(Expression::field("_id"), Expression::literal("000000000000000000000000"));
+        assert!(!evaluate(&other, &doc).unwrap());
+    }
+
     #[test]
     fn test_numeric_comparison() {
         let doc = make_doc();
         let expr = Expression::gt(Expression::field("age"), Expression::literal(25));
         assert!(evaluate(&expr, &doc).unwrap());
+        let expr = Expression::lt(Expression::field("age"), Expression::literal(30.5));
+        assert!(evaluate(&expr, &doc).unwrap());
+
+        // 不同类型不可比较
+        let expr = Expression::ge(Expression::field("name"), Expression::literal(18));
+        assert!(!evaluate(&expr, &doc).unwrap());
+    }
+
+    #[test]
+    fn test_float_semantics() {
+        let float = BomlValue::Float64;
+        assert!(values_equal(&float(-0.0), &float(0.0)));
+        assert!(values_equal(&BomlValue::Int32(5), &float(5.0)));
+        assert!(values_equal(&float(f64::NAN), &float(f64::NAN)));
+        assert!(!values_equal(&float(0.1 + 0.2), &float(0.3)));
+        assert_eq!(compare_values(&float(f64::NAN), &float(f64::NEG_INFINITY)), Some(-1));
+        assert_eq!(compare_values(&BomlValue::Int64(i64::MIN), &float(f64::NAN)), Some(1));
+        // 超过 2^53 的整数转换为 f64 后与相邻的浮点数相同,仍按精确值比较
+        let big = (1i64 << 53) + 1;
+        assert_eq!(compare_values(&BomlValue::Int64(big), &float((1i64 << 53) as f64)), Some(1));
+        assert!(!values_equal(&float(big as f64), &BomlValue::Int64(big)));
+
+        let mut doc = Document::new();
+        doc.insert("x", 0.1 + 0.2);
+        let approx = |args: &str| {
+            let expr = crate::Parser::parse(&format!("FIND t WHERE APPROX_EQ({})", args)).unwrap();
+            let crate::Statement::Find(find) = expr else { panic!("Expected FIND") };
+            evaluate(find.filter.as_ref().unwrap(), &doc)
+        };
+        assert!(approx("x, 0.3").unwrap());
+        assert!(!approx("x, 0.31").unwrap());
+        assert!(approx("x, 0.31, 0.05").unwrap());
+        assert!(!approx("missing, 0.3").unwrap());
+        assert!(approx("x, 'a'").is_err());
+        assert!(approx("x, 0.3, -1").is_err());
     }
 
     #[test]
@@ -697,4 +887,36 @@ mod tests {
         };
         assert!(evaluate(&expr, &doc).unwrap());
     }
+
+    #[test]
+    fn test_arithmetic_overflow() {
+        let int32 = |n: i32| BomlValue::Int32(n);
+        let int64 = |n: i64| BomlValue::Int64(n);
+        assert_eq!(compute_arithmetic(&int32(i32::MAX - 1), BinaryOp::Add, &int32(1)).unwrap(), int32(i32::MAX));
+        assert_eq!(compute_arithmetic(&int32(i32::MAX), BinaryOp::Add, &int64(1)).unwrap(), int64(1 << 31));
+        assert_eq!(compute_arithmetic(&int64(i64::MIN), BinaryOp::Mod, &int64(-1)).unwrap(), int64(0));
+
+        let overflows = [
+            (int32(i32::MAX), BinaryOp::Add, int32(1)),
+            (int32(i32::MIN), BinaryOp::Sub, int32(1)),
+            (int32(65536), BinaryOp::Mul, int32(65536)),
+            (int32(i32::MIN), BinaryOp::Div, int32(-1)),
+            (int64(i64::MAX), BinaryOp::Add, int64(1)),
+            (int64(i64::MIN), BinaryOp::Sub, int32(1)),
+            (int64(i64::MAX), BinaryOp::Mul, int64(2)),
+            (int64(i64::MIN), BinaryOp::Div, int64(-1)),
+        ];
+        for (a, op, b) in overflows {
+            let result = compute_arithmetic(&a, op, &b);
+            assert!(matches!(result, Err(QueryError::Overflow(_))), "{} {} {}: {:?}", a, op, b, result);
+        }
+        assert!(matches!(
+            compute_arithmetic(&int64(1), BinaryOp::Div, &int64(0)),
+            Err(QueryError::Execution(_))
+        ));
+
+        assert_eq!(negate_value(&int32(i32::MAX)).unwrap(), int32(-i32::MAX));
+        assert!(matches!(negate_value(&int32(i32::MIN)), Err(QueryError::Overflow(_))));
+        assert!(matches!(negate_value(&int64(i64::MIN)), Err(QueryError::Overflow(_))));
+    }
 }
@@ -0,0 +1,960 @@
+//! MQL 语句格式化模块
+//!
+//! 从 AST 重新生成 MQL 文本,关键字统一大写,子句顺序固定:
+//! - `format_statement`: 多行美化输出,每个子句一行并缩进,过长的 AND/OR 条件逐项换行
+//! - `format_compact`: 单行规范形式,与美化输出只差空白
+//! - `fingerprint`: 单行规范形式,字面量替换为 `?`,用于慢查询日志和计划缓存键
+//!
+//! 美化和单行输出可以重新解析为相同的 AST。
+
+use crate::ast::*;
+use crate::lexer::Token;
+use base64::Engine as _;
+use logos::Logos;
+use mikudb_boml::BomlValue;
+use mikudb_common::config::CompressionType;
+use mikudb_storage::{OversizedKeyPolicy, RollupDefinition};
+
+/// 条件超过该长度时按顶层 AND/OR 换行
+const WRAP_WIDTH: usize = 60;
+
+/// # Brief
+/// 多行美化输出语句
+///
+/// # Arguments
+/// * `stmt` - 语句
+///
+/// # Returns
+/// 每个子句一行、以两个空格缩进的 MQL 文本
+pub fn format_statement(stmt: &Statement) -> String {
+    Printer { pretty: true, mask: false }.statement(stmt)
+}
+
+/// # Brief
+/// 单行规范形式输出语句
+pub fn format_compact(stmt: &Statement) -> String {
+    Printer { pretty: false, mask: false }.statement(stmt)
+}
+
+/// # Brief
+/// 生成语句指纹
+///
+/// 字面量、文档、更新值和密码替换为 `?`,只保留语句结构、集合和字段名,
+/// 只差字面量的语句得到相同的指纹。
+///
+/// # Arguments
+/// * `stmt` - 语句
+pub fn fingerprint(stmt: &Statement) -> String {
+    Printer { pretty: false, mask: true }.statement(stmt)
+}
+
+/// 优先级,与解析器的递归下降层次一致
+fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Binary { op, .. } => match op {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Add | BinaryOp::Sub => 5,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 6,
+            _ => 4,
+        },
+        Expression::Unary { op: UnaryOp::Not, .. } => 3,
+        Expression::Unary { op: UnaryOp::Neg, .. } => 7,
+        Expression::In { .. }
+        | Expression::Between { .. }
+        | Expression::Like { .. }
+        | Expression::IsNull { .. } => 4,
+        _ => 8,
+    }
+}
+
+/// 名称能否不加引号作为单个标识符出现
+fn is_plain_identifier(name: &str) -> bool {
+    let mut lexer = Token::lexer(name);
+    matches!(lexer.next(), Some(Ok(Token::Identifier(ref s))) if s == name) && lexer.next().is_none()
+}
+
+/// 集合名、索引名等(解析器允许的关键字不需要引号)
+fn name(name: &str) -> String {
+    const KEYWORD_NAMES: [&str; 7] = ["users", "user", "status", "index", "collection", "database", "archive"];
+    if is_plain_identifier(name) || KEYWORD_NAMES.contains(&name) {
+        name.to_string()
+    } else {
+        format!("`{}`", name)
+    }
+}
+
+/// 表达式中的字段路径,逐段判断是否需要引号,首段之后的数组下标不加引号
+fn field(path: &str) -> String {
+    path.split('.')
+        .enumerate()
+        .map(|(i, part)| {
+            if is_plain_identifier(part) || (i > 0 && is_array_index(part)) {
+                part.to_string()
+            } else {
+                format!("`{}`", part)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// 更新的目标字段路径: 首段按集合名规则,之后的段还可以是下标或位置占位符 `$`
+fn update_path(path: &str) -> String {
+    match path.split_once('.') {
+        Some((first, rest)) => {
+            let rest = rest
+                .split('.')
+                .map(|part| if part == "$" || is_array_index(part) { part.to_string() } else { field(part) })
+                .collect::<Vec<_>>()
+                .join(".");
+            format!("{}.{}", name(first), rest)
+        }
+        None => name(path),
+    }
+}
+
+fn is_array_index(part: &str) -> bool {
+    !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) && part.parse::<i64>().is_ok()
+}
+
+/// 字符串字面量;词法分析器保留转义序列,原样输出即可往返
+fn string(s: &str) -> String {
+    if has_unescaped(s, '"') {
+        format!("'{}'", s)
+    } else {
+        format!("\"{}\"", s)
+    }
+}
+
+/// 是否包含未转义的引号字符(来自另一种引号的字符串)
+fn has_unescaped(s: &str, quote: char) -> bool {
+    let mut escaped = false;
+    for c in s.chars() {
+        if c == quote && !escaped {
+            return true;
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    false
+}
+
+fn float(n: f64) -> String {
+    let s = n.to_string();
+    if n.is_finite() && !s.contains('.') {
+        format!("{}.0", s)
+    } else {
+        s
+    }
+}
+
+fn json_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "NULL".to_string(),
+        serde_json::Value::Bool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => i.to_string(),
+            (None, Some(f)) => float(f),
+            _ => n.to_string(),
+        },
+        serde_json::Value::String(s) => string(s),
+        serde_json::Value::Array(items) => {
+            format!("[{}]", items.iter().map(json_value).collect::<Vec<_>>().join(", "))
+        }
+        serde_json::Value::Object(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|(k, v)| format!("{}: {}", string(k), json_value(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// 时长字面量,选择能整除的最大单位
+fn duration(secs: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [(7 * 86400, "w"), (86400, "d"), (3600, "h"), (60, "m")];
+    UNITS
+        .iter()
+        .find(|(size, _)| secs > 0 && secs % size == 0)
+        .map(|(size, unit)| format!("{}{}", secs / size, unit))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+struct Printer {
+    pretty: bool,
+    mask: bool,
+}
+
+impl Printer {
+    /// 拼接语句头和子句
+    fn clauses(&self, head: String, clauses: Vec<String>) -> String {
+        let separator = if self.pretty { "\n  " } else { " " };
+        std::iter::once(head).chain(clauses).collect::<Vec<_>>().join(separator)
+    }
+
+    fn literal(&self, value: &BomlValue) -> String {
+        if self.mask {
+            return "?".to_string();
+        }
+        self.value(value)
+    }
+
+    fn value(&self, value: &BomlValue) -> String {
+        match value {
+            BomlValue::Null => "NULL".to_string(),
+            BomlValue::Boolean(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+            BomlValue::Int32(n) => n.to_string(),
+            BomlValue::Int64(n) => n.to_string(),
+            BomlValue::Float32(n) => float(*n as f64),
+            BomlValue::Float64(n) => float(*n),
+            BomlValue::String(s) => string(s),
+            BomlValue::Array(items) => {
+                format!("[{}]", items.iter().map(|v| self.value(v)).collect::<Vec<_>>().join(", "))
+            }
+            BomlValue::Document(fields) => {
+                if let (1, Some(BomlValue::String(seq))) = (fields.len(), fields.get(NEXTVAL_KEY)) {
+                    return format!("NEXTVAL({})", string(seq));
+                }
+                if let Some(parameter) = Parameter::from_placeholder(value) {
+                    return parameter.to_string();
+                }
+                format!(
+                    "{{{}}}",
+                    fields
+                        .iter()
+                        .map(|(k, v)| format!("{}: {}", string(k), self.value(v)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+            BomlValue::DateTime(dt) => {
+                format!("ISODate(\"{}\")", dt.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true))
+            }
+            BomlValue::ObjectId(id) => format!("ObjectId(\"{}\")", id.to_hex()),
+            BomlValue::Uuid(uuid) => format!("UUID(\"{}\")", uuid),
+            // MQL 没有其他类型的字面量语法,按 JSON 形式输出
+            other => json_value(&other.clone().into()),
+        }
+    }
+
+    /// 子表达式,优先级低于 `min` 时加括号
+    fn operand(&self, expr: &Expression, min: u8) -> String {
+        let text = self.expression(expr);
+        if precedence(expr) < min {
+            format!("({})", text)
+        } else {
+            text
+        }
+    }
+
+    fn expression(&self, expr: &Expression) -> String {
+        match expr {
+            Expression::Literal(value) => self.literal(value),
+            Expression::Field(path) => field(path),
+            Expression::Binary { left, op, right } => {
+                let level = precedence(expr);
+                // 左结合: 右侧同级的子表达式需要括号;比较运算两侧都是加减层
+                let (left_min, right_min) = if level == 4 { (5, 5) } else { (level, level + 1) };
+                format!("{} {} {}", self.operand(left, left_min), op, self.operand(right, right_min))
+            }
+            Expression::Unary { op: UnaryOp::Not, expr } => format!("NOT {}", self.operand(expr, 4)),
+            Expression::Unary { op: UnaryOp::Neg, expr } => format!("-{}", self.operand(expr, 8)),
+            Expression::In { expr, list } => {
+                let list = if self.mask && list.iter().all(|e| matches!(e, Expression::Literal(_))) {
+                    "?".to_string()
+                } else {
+                    list.iter().map(|e| self.expression(e)).collect::<Vec<_>>().join(", ")
+                };
+                format!("{} IN [{}]", self.operand(expr, 5), list)
+            }
+            Expression::Between { expr, low, high } => format!(
+                "{} BETWEEN {} AND {}",
+                self.operand(expr, 5),
+                self.operand(low, 5),
+                self.operand(high, 5)
+            ),
+            Expression::Like { expr, pattern } => {
+                let pattern = if self.mask { "?".to_string() } else { string(pattern) };
+                format!("{} LIKE {}", self.operand(expr, 5), pattern)
+            }
+            Expression::IsNull { expr, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                format!("{} IS {}NULL", self.operand(expr, 5), not)
+            }
+            Expression::Exists { field, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                format!("{}EXISTS({})", not, name(field))
+            }
+            Expression::Call { function, args } => format!(
+                "{}{}({})",
+                // `doc` 参数只在 CALL FUNCTION 中表示当前文档
+                if args.contains(&Expression::CurrentDocument) { "CALL FUNCTION " } else { "" },
+                function,
+                args.iter().map(|e| self.expression(e)).collect::<Vec<_>>().join(", ")
+            ),
+            Expression::Array(items) => {
+                if self.mask && items.iter().all(|e| matches!(e, Expression::Literal(_))) {
+                    return "[?]".to_string();
+                }
+                format!("[{}]", items.iter().map(|e| self.expression(e)).collect::<Vec<_>>().join(", "))
+            }
+            Expression::Document(fields) => format!(
+                "{{{}}}",
+                fields
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", string(k), self.expression(v)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expression::CurrentDocument => "doc".to_string(),
+        }
+    }
+
+    /// 条件子句,美化输出时过长的顶层 AND/OR 逐项换行
+    fn condition(&self, keyword: &str, expr: &Expression) -> String {
+        let text = self.expression(expr);
+        let op = match expr {
+            Expression::Binary { op: op @ (BinaryOp::And | BinaryOp::Or), .. } => *op,
+            _ => return format!("{} {}", keyword, text),
+        };
+        if !self.pretty || text.len() <= WRAP_WIDTH {
+            return format!("{} {}", keyword, text);
+        }
+
+        // 左结合的同级链: ((a AND b) AND c)
+        let mut terms = Vec::new();
+        let mut current = expr;
+        while let Expression::Binary { left, op: inner, right } = current {
+            if *inner != op {
+                break;
+            }
+            terms.push(right.as_ref());
+            current = left;
+        }
+        terms.push(current);
+        terms.reverse();
+
+        let level = precedence(expr);
+        let mut out = format!("{} {}", keyword, self.operand(terms[0], level));
+        for term in &terms[1..] {
+            out.push_str(&format!("\n    {} {}", op, self.operand(term, level + 1)));
+        }
+        out
+    }
+
+    fn sort_fields(&self, fields: &[SortField]) -> String {
+        fields
+            .iter()
+            .map(|f| match f.order {
+                SortOrder::Ascending => name(&f.field),
+                SortOrder::Descending => format!("{} DESC", name(&f.field)),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn names(&self, names: &[String]) -> String {
+        names.iter().map(|n| name(n)).collect::<Vec<_>>().join(", ")
+    }
+
+    fn secret(&self, s: &str) -> String {
+        if self.mask {
+            "?".to_string()
+        } else {
+            string(s)
+        }
+    }
+
+    fn statement(&self, stmt: &Statement) -> String {
+        match stmt {
+            Statement::Use(u) => format!("USE {}", name(&u.database)),
+            Statement::ShowDatabases => "SHOW DATABASE".to_string(),
+            Statement::ShowCollections => "SHOW COLLECTION".to_string(),
+            Statement::ShowIndexes(c) => format!("SHOW INDEX ON {}", name(c)),
+            Statement::ShowStatus => "SHOW STATUS".to_string(),
+            Statement::ShowUsers => "SHOW USERS".to_string(),
+            Statement::ShowAdvisor(None) => "SHOW ADVISOR".to_string(),
+            Statement::ShowAdvisor(Some(c)) => format!("SHOW ADVISOR ON {}", name(c)),
+            Statement::CreateDatabase(db) => format!("CREATE DATABASE {}", name(db)),
+            Statement::DropDatabase(db) => format!("DROP DATABASE {}", name(db)),
+            Statement::CreateCollection(create) => {
+                let head = format!("CREATE COLLECTION {}", name(&create.name));
+                match create.capped {
+                    Some(capped) => {
+                        let mut out = format!("{} (CAPPED", head);
+                        if let Some(size) = capped.max_size {
+                            out.push_str(&format!(" SIZE {}", size));
+                        }
+                        if let Some(max) = capped.max_documents {
+                            out.push_str(&format!(" MAX {}", max));
+                        }
+                        out.push(')');
+                        out
+                    }
+                    None => head,
+                }
+            }
+            Statement::DropCollection(c) => format!("DROP COLLECTION {}", name(c)),
+            Statement::AlterCollection(alter) => self.alter_collection(alter),
+            Statement::ShowSchema(c) => format!("SHOW SCHEMA ON {}", name(c)),
+            Statement::CreateIndex(index) => self.create_index(index),
+            Statement::DropIndex(index) => {
+                format!("DROP INDEX {} ON {}", name(&index.name), name(&index.collection))
+            }
+            Statement::CreateSequence(seq) => {
+                let mut out = format!("CREATE SEQUENCE {}", name(&seq.name));
+                if seq.start != 1 {
+                    out.push_str(&format!(" START WITH {}", seq.start));
+                }
+                if seq.increment != 1 {
+                    out.push_str(&format!(" INCREMENT BY {}", seq.increment));
+                }
+                out
+            }
+            Statement::DropSequence(seq) => format!("DROP SEQUENCE {}", name(seq)),
+            Statement::ShowSequences => "SHOW SEQUENCES".to_string(),
+            Statement::CreateRollup(rollup) => self.create_rollup(rollup),
+            Statement::DropRollup(rollup) => format!("DROP ROLLUP {}", name(rollup)),
+            Statement::ShowRollups => "SHOW ROLLUPS".to_string(),
+            Statement::ShowManifest => "SHOW MANIFEST".to_string(),
+            Statement::CreateFunction(function) => format!(
+                "CREATE FUNCTION {} WASM {}",
+                name(&function.name),
+                self.secret(&base64::engine::general_purpose::STANDARD.encode(&function.module))
+            ),
+            Statement::DropFunction(function) => format!("DROP FUNCTION {}", name(function)),
+            Statement::ShowFunctions => "SHOW FUNCTIONS".to_string(),
+            Statement::Insert(insert) => self.insert(insert),
+            Statement::InsertSelect(insert) => {
+                format!("INSERT INTO {} {}", name(&insert.collection), self.find(&insert.source))
+            }
+            Statement::Find(find) => self.find(find),
+            Statement::Update(update) => self.update(update),
+            Statement::Delete(delete) => {
+                let mut clauses = Vec::new();
+                if let Some(secs) = delete.older_than_secs {
+                    clauses.push(format!("OLDER THAN {}", duration(secs)));
+                }
+                if let Some(filter) = &delete.filter {
+                    clauses.push(self.condition("WHERE", filter));
+                }
+                self.order_and_limit(&delete.sort, delete.limit, &mut clauses);
+                self.returning(&delete.returning, false, &mut clauses);
+                self.clauses(format!("DELETE FROM {}", name(&delete.collection)), clauses)
+            }
+            Statement::Aggregate(agg) => self.aggregate(agg),
+            Statement::DryRun(inner) => format!("DRY RUN {}", self.statement(inner)),
+            Statement::Archive(archive) => {
+                let mut clauses = vec![
+                    format!("OF {}", name(&archive.collection)),
+                    format!("TO {}", name(&archive.archive)),
+                ];
+                let compression = match archive.compression {
+                    CompressionType::None => Some("none"),
+                    CompressionType::Lz4 => Some("lz4"),
+                    CompressionType::Zstd => None,
+                };
+                if let Some(compression) = compression {
+                    clauses.push(format!("COMPRESSION {}", compression));
+                }
+                if let Some(path) = &archive.path {
+                    clauses.push(format!("PATH {}", string(path)));
+                }
+                self.clauses(format!("ARCHIVE OLDER THAN {}", duration(archive.older_than_secs)), clauses)
+            }
+            Statement::Backup(backup) => {
+                let without = if backup.include_system { "" } else { " WITHOUT SYSTEM" };
+                let snapshot = if backup.snapshot { " SNAPSHOT" } else { "" };
+                format!("BACKUP{} TO {}{}", snapshot, string(&backup.path), without)
+            }
+            Statement::Restore(restore) => {
+                let only = if restore.metadata_only { " METADATA ONLY" } else { "" };
+                format!("RESTORE FROM {}{}", string(&restore.path), only)
+            }
+            Statement::SetLogLevel(log) => match &log.target {
+                Some(target) => format!("ADMIN SET LOG LEVEL {} TARGET {}", log.level, name(target)),
+                None => format!("ADMIN SET LOG LEVEL {}", log.level),
+            },
+            Statement::ResetLogLevel => "ADMIN RESET LOG LEVEL".to_string(),
+            Statement::ClusterInit => "CLUSTER INIT".to_string(),
+            Statement::ClusterJoin(seed) => format!("CLUSTER JOIN {}", string(seed)),
+            Statement::SetVariable(set) => format!("SET {} = {}", name(&set.name), self.value(&set.value)),
+            Statement::Stats(c) => format!("STATS {}", name(c)),
+            Statement::ResetStats(None) => "RESET STATS".to_string(),
+            Statement::ResetStats(Some(c)) => format!("RESET STATS {}", name(c)),
+            Statement::Analyze(c) => format!("ANALYZE {}", name(c)),
+            Statement::Compact(c) => format!("COMPACT {}", name(c)),
+            Statement::BeginTransaction => "BEGIN TRANSACTION".to_string(),
+            Statement::Commit => "COMMIT".to_string(),
+            Statement::Rollback => "ROLLBACK".to_string(),
+            Statement::CreateUser(user) => {
+                let mut out = format!(
+                    "CREATE USER {} WITH PASSWORD {}",
+                    string(&user.username),
+                    self.secret(&user.password)
+                );
+                if !user.roles.is_empty() {
+                    out.push_str(&format!(" ROLE {}", self.names(&user.roles)));
+                }
+                out
+            }
+            Statement::AlterUser(user) => {
+                let mut out = format!("ALTER USER {}", string(&user.username));
+                if let Some(password) = &user.password {
+                    out.push_str(&format!(" PASSWORD {}", self.secret(password)));
+                }
+                if let Some(roles) = &user.add_roles {
+                    out.push_str(&format!(" ADD ROLE {}", self.names(roles)));
+                }
+                if let Some(roles) = &user.remove_roles {
+                    out.push_str(&format!(" DROP ROLE {}", self.names(roles)));
+                }
+                out
+            }
+            Statement::DropUser(user) => format!("DROP USER {}", string(user)),
+            Statement::Grant(grant) => format!(
+                "GRANT {} ON {} TO {}",
+                name(&grant.privilege),
+                name(&grant.resource),
+                string(&grant.username)
+            ),
+            Statement::Revoke(revoke) => format!(
+                "REVOKE {} ON {} FROM {}",
+                name(&revoke.privilege),
+                name(&revoke.resource),
+                string(&revoke.username)
+            ),
+            Statement::ShowGrants(None) => "SHOW GRANTS".to_string(),
+            Statement::ShowGrants(Some(user)) => format!("SHOW GRANTS {}", string(user)),
+            Statement::AiQuery(query) => format!("AI QUERY {}", self.secret(query)),
+            Statement::AiAnalyze(c) => format!("AI ANALYZE {}", name(c)),
+            Statement::AiSuggestIndex(c) => format!("AI SUGGEST INDEX {}", name(c)),
+        }
+    }
+
+    fn alter_collection(&self, alter: &AlterCollectionStatement) -> String {
+        let head = format!("ALTER COLLECTION {}", name(&alter.collection));
+        match &alter.expire {
+            Some(ExpireSetting::Off) => format!("{} EXPIRE OFF", head),
+            Some(ExpireSetting::AfterField(f)) => format!("{} EXPIRE AFTER FIELD {}", head, string(f)),
+            None => {
+                let bool_text = |b: bool| if b { "TRUE" } else { "FALSE" };
+                let mut options = Vec::new();
+                if let Some(track) = alter.track_types {
+                    options.push(format!("track_types = {}", bool_text(track)));
+                }
+                if let Some(strict) = alter.strict_types {
+                    options.push(format!("strict_types = {}", bool_text(strict)));
+                }
+                format!("{} SET {}", head, options.join(", "))
+            }
+        }
+    }
+
+    fn create_rollup(&self, rollup: &RollupDefinition) -> String {
+        let mut out = format!("CREATE ROLLUP {} ON {}", name(&rollup.name), name(&rollup.source));
+        if !rollup.group_by.is_empty() {
+            let keys = rollup
+                .group_by
+                .iter()
+                .map(|key| {
+                    let expr = match key.bucket {
+                        Some(bucket) => format!("DATE_TRUNC({}, {})", string(bucket.as_str()), field(&key.path)),
+                        None => field(&key.path),
+                    };
+                    if key.field == key.path {
+                        expr
+                    } else {
+                        format!("{} AS {}", expr, field(&key.field))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!(" GROUP BY {}", keys));
+        }
+        let aggregates = rollup
+            .aggregates
+            .iter()
+            .map(|agg| {
+                format!(
+                    "{}: {}({})",
+                    name(&agg.name),
+                    agg.function.as_str(),
+                    agg.path.as_deref().map(field).unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!(" AS {{{}}}", aggregates));
+        out
+    }
+
+    fn create_index(&self, index: &CreateIndexStatement) -> String {
+        let mut out = String::from("CREATE ");
+        if index.unique {
+            out.push_str("UNIQUE ");
+        }
+        if index.index_type == IndexType::Text {
+            out.push_str("TEXT ");
+        }
+        let fields = index
+            .fields
+            .iter()
+            .map(|f| match f.order {
+                SortOrder::Ascending => name(&f.name),
+                SortOrder::Descending => format!("{} DESC", name(&f.name)),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!(
+            "INDEX {} ON {} ({})",
+            name(&index.name),
+            name(&index.collection),
+            fields
+        ));
+
+        if let Some(options) = &index.text_options {
+            let mut parts = Vec::new();
+            if let Some(tokenizer) = &options.tokenizer {
+                parts.push(format!("TOKENIZER {}", string(tokenizer)));
+            }
+            match &options.stopwords {
+                Some(TextStopWords::Builtin(list)) => parts.push(format!("STOPWORDS {}", string(list))),
+                Some(TextStopWords::Custom(words)) => parts.push(format!(
+                    "STOPWORDS ({})",
+                    words.iter().map(|w| string(w)).collect::<Vec<_>>().join(", ")
+                )),
+                None => {}
+            }
+            if let Some(stemmer) = &options.stemmer {
+                parts.push(format!("STEMMER {}", string(stemmer)));
+            }
+            if !parts.is_empty() {
+                out.push_str(&format!(" WITH {}", parts.join(", ")));
+            }
+        }
+
+        let mut key_options = Vec::new();
+        if let Some(size) = index.max_key_size {
+            key_options.push(format!("MAX_KEY_SIZE {}", size));
+        }
+        if index.oversized_keys != OversizedKeyPolicy::Reject {
+            key_options.push(format!("OVERSIZED {}", index.oversized_keys.as_str().to_uppercase()));
+        }
+        if !key_options.is_empty() {
+            out.push_str(&format!(" WITH {}", key_options.join(" ")));
+        }
+        out
+    }
+
+    fn insert(&self, insert: &InsertStatement) -> String {
+        let mut clauses = Vec::new();
+        self.returning(&insert.returning, false, &mut clauses);
+        self.clauses(self.insert_documents(insert), clauses)
+    }
+
+    fn insert_documents(&self, insert: &InsertStatement) -> String {
+        let head = format!("INSERT INTO {}", name(&insert.collection));
+        if self.mask {
+            return format!("{} ?", head);
+        }
+        match insert.documents.as_slice() {
+            [doc] => format!("{} {}", head, self.value(doc)),
+            docs if self.pretty => format!(
+                "{} [\n{}\n]",
+                head,
+                docs.iter().map(|d| format!("  {}", self.value(d))).collect::<Vec<_>>().join(",\n")
+            ),
+            docs => format!(
+                "{} [{}]",
+                head,
+                docs.iter().map(|d| self.value(d)).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    fn find(&self, find: &FindStatement) -> String {
+        let mut clauses = Vec::new();
+        if let Some(filter) = &find.filter {
+            clauses.push(self.condition("WHERE", filter));
+        }
+        if let Some(projection) = &find.projection {
+            clauses.push(format!("SELECT {}", self.names(projection)));
+        }
+        if let Some(sort) = &find.sort {
+            clauses.push(format!("ORDER BY {}", self.sort_fields(sort)));
+        }
+        if let Some(limit) = find.limit {
+            clauses.push(format!("LIMIT {}", limit));
+        }
+        if let Some(skip) = find.skip {
+            clauses.push(format!("SKIP {}", skip));
+        }
+        if find.include_archive {
+            clauses.push("WITH ARCHIVE".to_string());
+        }
+        if let Some(size) = find.batch_size {
+            clauses.push(format!("BATCH SIZE {}", size));
+        }
+        self.clauses(format!("FIND {}", name(&find.collection)), clauses)
+    }
+
+    fn update(&self, update: &UpdateStatement) -> String {
+        let mut sets = Vec::new();
+        let mut unsets = Vec::new();
+        let mut clauses = Vec::new();
+        for op in &update.updates {
+            match op {
+                UpdateOperation::Set { field, value } => {
+                    sets.push(format!("{} = {}", update_path(field), self.literal(value)))
+                }
+                UpdateOperation::Inc { field, value } => {
+                    sets.push(format!("{} += {}", update_path(field), self.literal(value)))
+                }
+                UpdateOperation::Unset { field } => unsets.push(update_path(field)),
+                UpdateOperation::Push { field, value } => {
+                    clauses.push(format!("PUSH {} = {}", update_path(field), self.literal(value)))
+                }
+                UpdateOperation::Pull { field, value } => {
+                    clauses.push(format!("PULL {} = {}", update_path(field), self.literal(value)))
+                }
+                UpdateOperation::Rename { from, to } => {
+                    clauses.push(format!("RENAME {} TO {}", update_path(from), update_path(to)))
+                }
+                UpdateOperation::AddToSet { field, value } => {
+                    clauses.push(format!("ADDTOSET {} = {}", update_path(field), self.literal(value)))
+                }
+                UpdateOperation::Pop { field, first } => {
+                    clauses.push(format!("POP {} {}", if *first { "FIRST" } else { "LAST" }, update_path(field)))
+                }
+                UpdateOperation::PullAll { field, values } => clauses.push(format!(
+                    "PULLALL {} = {}",
+                    update_path(field),
+                    self.literal(&BomlValue::Array(values.clone()))
+                )),
+            }
+        }
+        if !unsets.is_empty() {
+            clauses.insert(0, format!("UNSET {}", unsets.join(", ")));
+        }
+        if !sets.is_empty() {
+            clauses.insert(0, format!("SET {}", sets.join(", ")));
+        }
+        if let Some(filter) = &update.filter {
+            clauses.push(self.condition("WHERE", filter));
+        }
+        self.order_and_limit(&update.sort, update.limit, &mut clauses);
+        self.returning(&update.returning, update.return_old, &mut clauses);
+        self.clauses(format!("UPDATE {}", name(&update.collection)), clauses)
+    }
+
+    fn order_and_limit(&self, sort: &Option<Vec<SortField>>, limit: Option<u64>, clauses: &mut Vec<String>) {
+        if let Some(sort) = sort {
+            clauses.push(format!("ORDER BY {}", self.sort_fields(sort)));
+        }
+        if let Some(limit) = limit {
+            clauses.push(format!("LIMIT {}", limit));
+        }
+    }
+
+    fn returning(&self, returning: &Option<Returning>, old: bool, clauses: &mut Vec<String>) {
+        let keyword = if old { "RETURNING OLD" } else { "RETURNING" };
+        match returning {
+            Some(Returning::All) => clauses.push(format!("{} *", keyword)),
+            Some(Returning::Fields(fields)) => clauses.push(format!("{} {}", keyword, self.names(fields))),
+            None => {}
+        }
+    }
+
+    fn aggregate(&self, agg: &AggregateStatement) -> String {
+        let mut clauses: Vec<String> = agg.pipeline.iter().map(|s| format!("| {}", self.stage(s))).collect();
+        if let Some(size) = agg.batch_size {
+            clauses.push(format!("BATCH SIZE {}", size));
+        }
+        self.clauses(format!("AGGREGATE {}", name(&agg.collection)), clauses)
+    }
+
+    fn stage(&self, stage: &AggregateStage) -> String {
+        match stage {
+            AggregateStage::Match(expr) => format!("MATCH {}", self.expression(expr)),
+            AggregateStage::Project(fields) => format!(
+                "PROJECT {}",
+                fields
+                    .iter()
+                    .map(|f| match &f.expression {
+                        Some(expr) => format!("{}: {}", name(&f.name), self.expression(expr)),
+                        None => name(&f.name),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            AggregateStage::Group { by, accumulators } => {
+                format!("GROUP BY {}{}", self.names(by), accumulators_clause(accumulators))
+            }
+            AggregateStage::Sort(fields) => format!("SORT {}", self.sort_fields(fields)),
+            AggregateStage::Limit(n) => format!("LIMIT {}", n),
+            AggregateStage::Skip(n) => format!("SKIP {}", n),
+            AggregateStage::Unwind { path, .. } => format!("UNWIND {}", name(path)),
+            AggregateStage::Lookup { from, local_field, foreign_field, as_field } => format!(
+                "LOOKUP {} ON {} = {} AS {}",
+                name(from),
+                name(local_field),
+                name(foreign_field),
+                name(as_field)
+            ),
+            AggregateStage::GraphLookup(graph) => {
+                let mut out = format!(
+                    "GRAPH LOOKUP {} START WITH {} CONNECT FROM {} TO {} AS {}",
+                    name(&graph.from),
+                    name(&graph.start_with),
+                    name(&graph.connect_from),
+                    name(&graph.connect_to),
+                    name(&graph.as_field)
+                );
+                if let Some(depth) = graph.max_depth {
+                    out.push_str(&format!(" MAX DEPTH {}", depth));
+                }
+                if let Some(field) = &graph.depth_field {
+                    out.push_str(&format!(" DEPTH FIELD {}", name(field)));
+                }
+                out
+            }
+            AggregateStage::Count(field) => format!("COUNT AS {}", name(field)),
+            AggregateStage::Facet(facets) => {
+                let facets = facets
+                    .iter()
+                    .map(|facet| {
+                        let stages: Vec<String> = facet.pipeline.iter().map(|s| self.stage(s)).collect();
+                        format!("{} ({})", name(&facet.name), stages.join(" | "))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("FACET {}", facets)
+            }
+            AggregateStage::Bucket(bucket) => {
+                let boundaries: Vec<String> = bucket.boundaries.iter().map(|v| self.literal(v)).collect();
+                let mut out = format!("BUCKET {} BOUNDARIES [{}]", name(&bucket.group_by), boundaries.join(", "));
+                if let Some(default) = &bucket.default {
+                    out.push_str(&format!(" DEFAULT {}", self.literal(default)));
+                }
+                out.push_str(&accumulators_clause(&bucket.output));
+                out
+            }
+            AggregateStage::BucketAuto(bucket) => format!(
+                "BUCKET AUTO {} BUCKETS {}{}",
+                name(&bucket.group_by),
+                bucket.buckets,
+                accumulators_clause(&bucket.output)
+            ),
+        }
+    }
+}
+
+/// 格式化 GROUP / BUCKET 的 ` AS {...}` 子句,没有累加器时为空
+fn accumulators_clause(accumulators: &[Accumulator]) -> String {
+    if accumulators.is_empty() {
+        return String::new();
+    }
+    let accumulators = accumulators
+        .iter()
+        .map(|acc| {
+            let function = match &acc.function {
+                AggregateFunction::Count => "COUNT",
+                AggregateFunction::Sum => "SUM",
+                AggregateFunction::Avg => "AVG",
+                AggregateFunction::Min => "MIN",
+                AggregateFunction::Max => "MAX",
+                AggregateFunction::First => "FIRST",
+                AggregateFunction::Last => "LAST",
+                AggregateFunction::Push => "PUSH",
+                AggregateFunction::AddToSet => "ADDTOSET",
+                AggregateFunction::Custom(function) => function,
+            };
+            let field = acc.field.as_deref().map(name).unwrap_or_default();
+            format!("{}: {}({})", name(&acc.name), function, field)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(" AS {{{}}}", accumulators)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    fn round_trip(input: &str) {
+        let stmt = Parser::parse(input).unwrap();
+        let pretty = format_statement(&stmt);
+        let compact = format_compact(&stmt);
+        assert_eq!(Parser::parse(&pretty).unwrap(), stmt, "{}", pretty);
+        assert_eq!(Parser::parse(&compact).unwrap(), stmt, "{}", compact);
+        assert!(!compact.contains('\n'));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip("find users where age > 18 and (`status` = 'active' or vip = true) order by age desc limit 10 skip 5");
+        round_trip("FIND users WHERE NOT (a = 1 OR b = 2) AND c - (d - 1) * 2 >= -3.5 SELECT name, age");
+        round_trip("FIND t WHERE name LIKE 'a%' AND x IN [1, 2, 3] AND y BETWEEN 1 AND 5 AND z IS NOT NULL");
+        round_trip("FIND `my coll` WHERE `order`.total > 1 AND EXISTS(tags) WITH ARCHIVE BATCH SIZE 100");
+        round_trip(r#"INSERT INTO users [{"name": "a", "tags": ["x"], "n": NEXTVAL('ids')}, {"name": "b"}]"#);
+        round_trip("INSERT INTO archive FIND events WHERE ts < '2024-01-01' SELECT ts, kind BATCH SIZE 500");
+        round_trip("UPDATE users SET a = 1, b += 2.0 UNSET c PUSH tags = 'x' WHERE note = 'say \\'hi\\'' OR q = \"it's\"");
+        round_trip("DELETE FROM logs OLDER THAN 7d WHERE level = 'debug'");
+        round_trip("DELETE FROM logs WHERE level = 'debug' ORDER BY ts LIMIT 10000");
+        round_trip("UPDATE jobs SET state = 'queued' WHERE state = 'stale' ORDER BY priority DESC, _id LIMIT 100");
+        round_trip("INSERT INTO users {\"name\": \"a\"} RETURNING _id, created_at");
+        round_trip("UPDATE users SET n += 1 WHERE n > 0 RETURNING *");
+        round_trip("UPDATE orders SET items.0.qty = 5, items.$.price += 1 ADDTOSET tags = 'x' POP FIRST queue PULLALL scores = [1, 2] WHERE items.sku = 'a'");
+        round_trip("UPDATE users RENAME nick TO profile.nickname PULL tags = 'old' WHERE history.0 = 'signup'");
+        round_trip("DELETE FROM users WHERE n = 0 LIMIT 1 RETURNING _id");
+        round_trip("AGGREGATE orders | MATCH state = 'completed' | GROUP BY customer_id AS {total: SUM(amount), n: COUNT()} | SORT total DESC | LIMIT 10");
+        round_trip("CREATE UNIQUE INDEX idx_email ON users (email, created DESC)");
+        round_trip("CREATE TEXT INDEX idx ON articles (body) WITH TOKENIZER 'jieba' STOPWORDS ('a', 'b'), STEMMER 'english'");
+        round_trip("CREATE INDEX idx_bio ON users (bio) WITH MAX_KEY_SIZE 512 OVERSIZED SKIP");
+        round_trip("ARCHIVE OLDER THAN 90d OF events TO events_archive COMPRESSION lz4 PATH '/mnt/cold'");
+        round_trip("FIND t WHERE at >= ISODate('2024-01-01T08:30:00.5Z') AND ref = ObjectId('65a1b2c3d4e5f60718293a4b') -- recent\n AND key != UUID('67e55044-10b1-426f-9247-bb680e5fe0c8') AND mask = 0x1F");
+        round_trip("CREATE SEQUENCE ids START WITH 100 INCREMENT BY -2");
+        round_trip("CREATE COLLECTION logs (CAPPED SIZE 10MB MAX 1000)");
+        round_trip("COMPACT logs");
+        round_trip("ALTER COLLECTION users SET track_types = true, strict_types = false");
+        round_trip("ADMIN SET LOG LEVEL debug TARGET 'mikudb_storage::engine'");
+        round_trip("CLUSTER JOIN 'mikudb://db1:3941'");
+        round_trip("CREATE USER \"bob\" WITH PASSWORD \"secret\" ROLE read, write");
+        round_trip("ALTER USER \"bob\" PASSWORD \"secret\" ADD ROLE backup DROP ROLE read, write");
+        round_trip("SHOW MANIFEST");
+        round_trip("DRY RUN DELETE FROM users WHERE active = false");
+        round_trip("FIND users WHERE CALL FUNCTION score(doc, 2) > 0.5 AND slugify(doc) = 'a'");
+        round_trip("AGGREGATE users | PROJECT name, s: CALL FUNCTION score(doc) | SORT s DESC");
+        round_trip("AGGREGATE categories | GRAPH LOOKUP categories START WITH parent_id CONNECT FROM parent_id TO _id AS ancestors MAX DEPTH 5 DEPTH FIELD level");
+        round_trip("AGGREGATE products | BUCKET price BOUNDARIES [0, 10, 100] DEFAULT 'other' AS {n: COUNT(), mean: AVG(price)} | BUCKET AUTO score BUCKETS 4");
+        round_trip("AGGREGATE products | FACET by_category (GROUP BY category AS {n: COUNT()} | SORT n DESC), top (SORT score DESC | LIMIT 10), all () | PROJECT top");
+        round_trip("CREATE FUNCTION score WASM 'AGFzbQEAAAA='");
+        round_trip("CREATE ROLLUP daily_sales ON sales GROUP BY DATE_TRUNC('day', ts), `order`.region AS region AS {total: SUM(amount), n: COUNT()}");
+    }
+
+    #[test]
+    fn test_pretty_layout() {
+        let stmt = Parser::parse(
+            "find users where state = 'active' and age > 18 and country = 'jp' and city = 'sapporo' limit 5",
+        )
+        .unwrap();
+        assert_eq!(
+            format_statement(&stmt),
+            "FIND users\n  WHERE state = \"active\"\n    AND age > 18\n    AND country = \"jp\"\n    AND city = \"sapporo\"\n  LIMIT 5"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let a = Parser::parse("FIND users WHERE age > 18 AND id IN [1, 2] LIMIT 5").unwrap();
+        let b = Parser::parse("find users where age > 30 and id in [7] limit 5").unwrap();
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_eq!(fingerprint(&a), "FIND users WHERE age > ? AND id IN [?] LIMIT 5");
+
+        let user = Parser::parse("CREATE USER \"bob\" WITH PASSWORD \"secret\"").unwrap();
+        assert!(!fingerprint(&user).contains("secret"));
+    }
+}
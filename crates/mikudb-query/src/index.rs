@@ -12,15 +12,10 @@
 //! - Text: 全文索引(待实现)
 //! - Geo2d/Geo2dsphere: 地理空间索引(待实现)
 //!
-//! 索引键序列化格式:
-//! - Null: 0x00
-//! - Boolean: 0x01 + 值
-//! - Integer: 0x02 + 大端字节序
-//! - String: 0x03 + UTF-8 字节
-//! - Binary: 0x04 + 原始字节
+//! 索引键序列化使用 `mikudb_boml::keyenc` 的保序编码,与存储层索引键一致。
 
 use crate::{QueryError, QueryResult};
-use mikudb_boml::BomlValue;
+use mikudb_boml::{keyenc, BomlValue};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::sync::Arc;
@@ -185,18 +180,14 @@ impl IndexKey {
     /// # Brief
     /// 序列化为字节数组
     ///
-    /// 使用 0x00 分隔多个键部分。
+    /// 各部分的编码自定界,直接拼接,字节序与键的顺序一致。
     ///
     /// # Returns
     /// 序列化后的字节数组
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
-        for (i, part) in self.0.iter().enumerate() {
-            // 使用 0x00 分隔多个键部分
-            if i > 0 {
-                bytes.push(0x00);
-            }
-            bytes.extend(part.to_bytes());
+        for part in &self.0 {
+            keyenc::encode_value(&part.to_value(), &mut bytes);
         }
         bytes
     }
@@ -247,41 +238,24 @@ impl KeyPart {
     }
 
     /// # Brief
-    /// 序列化为字节数组
-    ///
-    /// 格式:
-    /// - Null: 0x00
-    /// - Boolean(false): 0x01 0x00
-    /// - Boolean(true): 0x01 0x01
-    /// - Integer: 0x02 + 大端 8 字节
-    /// - String: 0x03 + UTF-8 字节
-    /// - Binary: 0x04 + 原始字节
-    ///
-    /// # Returns
-    /// 序列化后的字节数组
-    pub fn to_bytes(&self) -> Vec<u8> {
+    /// 转换回 BOML 值
+    pub fn to_value(&self) -> BomlValue {
         match self {
-            KeyPart::Null => vec![0x00],
-            KeyPart::Boolean(false) => vec![0x01, 0x00],
-            KeyPart::Boolean(true) => vec![0x01, 0x01],
-            KeyPart::Integer(n) => {
-                let mut bytes = vec![0x02];
-                // 使用大端字节序保证排序正确
-                bytes.extend(&n.to_be_bytes());
-                bytes
-            }
-            KeyPart::String(s) => {
-                let mut bytes = vec![0x03];
-                bytes.extend(s.as_bytes());
-                bytes
-            }
-            KeyPart::Binary(b) => {
-                let mut bytes = vec![0x04];
-                bytes.extend(b);
-                bytes
-            }
+            KeyPart::Null => BomlValue::Null,
+            KeyPart::Boolean(b) => BomlValue::Boolean(*b),
+            KeyPart::Integer(n) => BomlValue::Int64(*n),
+            KeyPart::String(s) => BomlValue::String(s.as_str().into()),
+            KeyPart::Binary(b) => BomlValue::Binary(b.clone()),
         }
     }
+
+    /// # Brief
+    /// 序列化为保序的字节数组(`mikudb_boml::keyenc`)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        keyenc::encode_value(&self.to_value(), &mut bytes);
+        bytes
+    }
 }
 
 /// BTree 索引实现
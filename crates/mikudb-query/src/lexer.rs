@@ -2,9 +2,9 @@
 //!
 //! 本模块使用 logos 库实现高性能词法分析:
 //! - 自动跳过空白字符和注释
-//! - 支持单行注释(//)和多行注释(/* */)
+//! - 支持单行注释(// 和 --)和多行注释(/* */)
 //! - 大小写不敏感的关键字
-//! - 字符串、数字、标识符的词法规则
+//! - 字符串、数字(含 0x 十六进制和 0b 二进制整数)、标识符的词法规则
 //! - 所有 MQL 操作符和符号
 
 use logos::Logos;
@@ -15,6 +15,7 @@ use logos::Logos;
 #[derive(Logos, Debug, Clone, PartialEq)]
 #[logos(skip r"[ \t\r\n]+")]          // 跳过空白字符
 #[logos(skip r"//[^\n]*")]            // 跳过单行注释
+#[logos(skip r"--[^\n]*")]            // 跳过 SQL 风格单行注释
 #[logos(skip r"/\*[^*]*\*+([^/*][^*]*\*+)*/")]  // 跳过多行注释
 pub enum Token {
     // 数据库管理关键字
@@ -99,6 +100,10 @@ pub enum Token {
     #[token("AS", ignore(ascii_case))]
     As,
 
+    // 数据分层关键字
+    #[token("ARCHIVE", ignore(ascii_case))]
+    Archive,
+
     // 逻辑操作符
     #[token("AND", ignore(ascii_case))]
     And,
@@ -256,8 +261,10 @@ pub enum Token {
     #[regex(r"-?[0-9]+\.[0-9]+([eE][+-]?[0-9]+)?", |lex| lex.slice().parse::<f64>().ok())]
     Float(f64),
 
-    // 整数(优先级高于浮点数)
+    // 整数(优先级高于浮点数),支持 0x1F 十六进制和 0b1010 二进制
     #[regex(r"-?[0-9]+", |lex| lex.slice().parse::<i64>().ok(), priority = 2)]
+    #[regex(r"0[xX][0-9a-fA-F]+", |lex| i64::from_str_radix(&lex.slice()[2..], 16).ok())]
+    #[regex(r"0[bB][01]+", |lex| i64::from_str_radix(&lex.slice()[2..], 2).ok())]
     Integer(i64),
 
     // 标识符(字段名、集合名等)
@@ -358,4 +365,22 @@ mod tests {
         assert!(tokens.iter().any(|(t, _)| matches!(t, Token::Integer(10))));
         assert!(tokens.iter().any(|(t, _)| matches!(t, Token::Float(n) if (*n - 3.14).abs() < 0.001)));
     }
+
+    #[test]
+    fn test_comments_and_radix() {
+        let tokens = Lexer::tokenize("FIND t -- trailing\n/* block\n comment */ LIMIT 0x1F SKIP 0b101 // done");
+        let tokens: Vec<Token> = tokens.into_iter().map(|(t, _)| t).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Find,
+                Token::Identifier("t".to_string()),
+                Token::Limit,
+                Token::Integer(31),
+                Token::Skip,
+                Token::Integer(5),
+            ]
+        );
+        assert_eq!(Lexer::tokenize("a - -3").len(), 3);
+    }
 }
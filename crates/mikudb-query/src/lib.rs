@@ -11,8 +11,16 @@
 //! - CRUD 操作 (FIND, INSERT, UPDATE, DELETE)
 //! - DDL 操作 (CREATE/DROP COLLECTION/INDEX)
 //! - 聚合管道 (AGGREGATE)
-//! - 事务 (BEGIN/COMMIT/ROLLBACK)
+//! - 事务 (BEGIN/COMMIT/ROLLBACK),事务内的写入暂存在事务中,读取可见事务自己的写入 (TransactionContext)
 //! - 用户管理 (CREATE USER, GRANT, REVOKE)
+//! - 冷热数据分层 (ARCHIVE, FIND ... WITH ARCHIVE)
+//! - 索引顾问 (SHOW ADVISOR)
+//! - 按集合的操作统计 (STATS, RESET STATS)
+//! - 单条语句的资源统计 (SET return_stats = true)
+//! - 语句格式化和指纹 (format_statement, fingerprint)
+//! - 带 `$1` / `:name` 参数占位符的预编译语句 (PreparedStatement)
+//! - 嵌入式使用时注册自定义标量和累加器函数 (FunctionRegistry)
+//! - 沙箱中执行的 WASM 自定义函数 (CREATE FUNCTION, CALL FUNCTION, 需启用 `wasm-udf` 特性)
 
 pub mod lexer;
 pub mod parser;
@@ -21,10 +29,25 @@ pub mod planner;
 pub mod executor;
 pub mod filter;
 pub mod index;
+pub mod advisor;
+pub mod opstats;
+pub mod stmtstats;
+pub mod format;
+pub mod udf;
+pub mod sandbox;
+pub mod txn;
+pub mod prepared;
 
 pub use ast::*;
 pub use executor::{QueryExecutor, QueryResponse};
 pub use parser::Parser;
+pub use advisor::{IndexAdvisor, QueryLog, QueryShape};
+pub use opstats::{CollectionOpStats, OpStats, SavedOpStats};
+pub use stmtstats::StatementStats;
+pub use format::{fingerprint, format_compact, format_statement};
+pub use udf::FunctionRegistry;
+pub use txn::TransactionContext;
+pub use prepared::{Params, PreparedStatement};
 
 use thiserror::Error;
 
@@ -38,8 +61,17 @@ pub enum QueryError {
     Syntax(String),
 
     /// 解析错误(带位置信息)
-    #[error("Parse error at {position}: {message}")]
-    Parse { position: usize, message: String },
+    ///
+    /// `position` 为字节偏移,`line`/`column` 从 1 开始(列按字符计数),
+    /// `token` 为出错位置的原始文本,到达输入末尾时为 None
+    #[error("Syntax error at line {line}, column {column} near {}: {message}", near_token(.token))]
+    Parse {
+        position: usize,
+        line: usize,
+        column: usize,
+        token: Option<String>,
+        message: String,
+    },
 
     /// 未知关键字
     #[error("Unknown keyword: {0}")]
@@ -53,6 +85,14 @@ pub enum QueryError {
     #[error("Type error: {0}")]
     TypeError(String),
 
+    /// 文档校验错误(字段类型不满足写入操作或约束)
+    #[error("Validation failed: {}", mikudb_storage::schema::join_details(.0))]
+    Validation(Vec<mikudb_storage::ValidationDetail>),
+
+    /// 参数绑定错误(参数个数、名称或种类与语句不符)
+    #[error("Parameter binding error: {0}")]
+    Binding(String),
+
     /// 无效操作符
     #[error("Invalid operator: {0}")]
     InvalidOperator(String),
@@ -69,6 +109,10 @@ pub enum QueryError {
     #[error("Execution error: {0}")]
     Execution(String),
 
+    /// 整数运算溢出,结果超出操作数类型的范围
+    #[error("Arithmetic overflow: {0}")]
+    Overflow(String),
+
     /// 存储层错误
     #[error("Storage error: {0}")]
     Storage(#[from] mikudb_storage::StorageError),
@@ -81,10 +125,46 @@ pub enum QueryError {
     #[error("Timeout")]
     Timeout,
 
+    /// 操作被中断(超时或 KillOp)
+    #[error("Operation interrupted")]
+    Interrupted,
+
     /// 内部错误
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+impl QueryError {
+    /// # Brief
+    /// 获取结构化的校验错误,包括存储层的模式约束错误
+    pub fn validation_details(&self) -> Option<&[mikudb_storage::ValidationDetail]> {
+        match self {
+            QueryError::Validation(details) => Some(details),
+            QueryError::Storage(e) => e.validation_details(),
+            _ => None,
+        }
+    }
+}
+
+/// 转换为统一错误类型,存储层错误沿用存储层的分类
+impl From<QueryError> for mikudb_common::MikuError {
+    fn from(e: QueryError) -> Self {
+        use mikudb_common::MikuError;
+        match e {
+            QueryError::Storage(e) => e.into(),
+            QueryError::Timeout => MikuError::Timeout(e.to_string()),
+            QueryError::Validation(_) | QueryError::Overflow(_) => MikuError::Validation(e.to_string()),
+            other => MikuError::Query(other.to_string()),
+        }
+    }
+}
+
+fn near_token(token: &Option<String>) -> String {
+    match token {
+        Some(t) => format!("'{}'", t),
+        None => "end of input".to_string(),
+    }
+}
+
 /// 查询结果类型
 pub type QueryResult<T> = Result<T, QueryError>;
@@ -0,0 +1,562 @@
+//! 集合操作统计模块
+//!
+//! 按集合记录执行器的操作计数,类似 MySQL 的表 I/O 统计:
+//! - **操作计数**: FIND、INSERT、UPDATE、DELETE、AGGREGATE 的执行次数
+//! - **访问路径**: 全集合扫描次数与索引命中次数
+//! - **文档数**: 扫描检查的文档数与返回/写入的文档数
+//! - **耗时**: 累计与平均执行耗时,以及按操作类型的耗时分布(直方图)
+//!
+//! 统计保存在内存中,执行 `RESET STATS` 后清零;服务器正常关闭时通过 `OpStats::save`
+//! 导出,下次启动时用 `OpStats::restore` 恢复。
+//! 通过 `STATS <collection>` 和 `/api/metrics` 的 `collection_ops` 查看。
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 耗时直方图的桶上界(秒),另有一个不设上界的桶
+pub const LATENCY_BUCKETS_SECS: [f64; 10] = [0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0];
+
+/// 统计的操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpKind {
+    /// FIND
+    Find,
+    /// INSERT
+    Insert,
+    /// UPDATE
+    Update,
+    /// DELETE
+    Delete,
+    /// AGGREGATE
+    Aggregate,
+}
+
+impl OpKind {
+    /// 全部操作类型
+    pub const ALL: [OpKind; 5] = [OpKind::Find, OpKind::Insert, OpKind::Update, OpKind::Delete, OpKind::Aggregate];
+
+    /// # Brief
+    /// 小写名称,用作指标标签
+    pub fn name(self) -> &'static str {
+        match self {
+            OpKind::Find => "find",
+            OpKind::Insert => "insert",
+            OpKind::Update => "update",
+            OpKind::Delete => "delete",
+            OpKind::Aggregate => "aggregate",
+        }
+    }
+}
+
+/// 单个操作类型的耗时直方图
+#[derive(Debug)]
+struct Histogram {
+    /// 各桶的计数(不累计),最后一个为不设上界的桶
+    buckets: [AtomicU64; LATENCY_BUCKETS_SECS.len() + 1],
+    total_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            total_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS_SECS
+            .iter()
+            .position(|bound| secs <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_SECS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.total_micros
+            .fetch_add(elapsed.as_micros().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.buckets.iter().for_each(|bucket| bucket.store(0, Ordering::Relaxed));
+        self.total_micros.store(0, Ordering::Relaxed);
+    }
+
+    fn save(&self) -> SavedHistogram {
+        SavedHistogram {
+            buckets: self.buckets.iter().map(|bucket| bucket.load(Ordering::Relaxed)).collect(),
+            total_micros: self.total_micros.load(Ordering::Relaxed),
+        }
+    }
+
+    fn restore(&self, saved: &SavedHistogram) {
+        // 桶边界改变后旧的分布无法对应,丢弃
+        if saved.buckets.len() != self.buckets.len() {
+            return;
+        }
+        for (bucket, count) in self.buckets.iter().zip(&saved.buckets) {
+            bucket.fetch_add(*count, Ordering::Relaxed);
+        }
+        self.total_micros.fetch_add(saved.total_micros, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencyHistogram {
+        let mut cumulative = 0;
+        let buckets = self
+            .buckets
+            .iter()
+            .map(|bucket| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                cumulative
+            })
+            .collect();
+        LatencyHistogram {
+            buckets,
+            count: cumulative,
+            sum_secs: self.total_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        }
+    }
+}
+
+/// 耗时直方图快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyHistogram {
+    /// 与 `LATENCY_BUCKETS_SECS` 对应的累计计数(耗时不超过该上界的次数),最后一个为总次数
+    pub buckets: Vec<u64>,
+    /// 总次数
+    pub count: u64,
+    /// 总耗时(秒)
+    pub sum_secs: f64,
+}
+
+/// 单个集合的计数器
+#[derive(Debug)]
+struct Counters {
+    finds: AtomicU64,
+    inserts: AtomicU64,
+    updates: AtomicU64,
+    deletes: AtomicU64,
+    aggregates: AtomicU64,
+    scans: AtomicU64,
+    index_hits: AtomicU64,
+    docs_examined: AtomicU64,
+    docs_returned: AtomicU64,
+    docs_written: AtomicU64,
+    total_micros: AtomicU64,
+    /// 按 `OpKind::ALL` 顺序的耗时直方图
+    latency: [Histogram; 5],
+    since: AtomicU64,
+}
+
+impl Counters {
+    fn new() -> Self {
+        Self {
+            finds: AtomicU64::new(0),
+            inserts: AtomicU64::new(0),
+            updates: AtomicU64::new(0),
+            deletes: AtomicU64::new(0),
+            aggregates: AtomicU64::new(0),
+            scans: AtomicU64::new(0),
+            index_hits: AtomicU64::new(0),
+            docs_examined: AtomicU64::new(0),
+            docs_returned: AtomicU64::new(0),
+            docs_written: AtomicU64::new(0),
+            total_micros: AtomicU64::new(0),
+            latency: std::array::from_fn(|_| Histogram::new()),
+            since: AtomicU64::new(now_secs()),
+        }
+    }
+
+    fn reset(&self) {
+        for counter in self.values() {
+            counter.store(0, Ordering::Relaxed);
+        }
+        self.latency.iter().for_each(Histogram::reset);
+        self.since.store(now_secs(), Ordering::Relaxed);
+    }
+
+    /// 计数器按固定顺序排列,`save`/`restore` 共用
+    fn values(&self) -> [&AtomicU64; 11] {
+        [
+            &self.finds,
+            &self.inserts,
+            &self.updates,
+            &self.deletes,
+            &self.aggregates,
+            &self.scans,
+            &self.index_hits,
+            &self.docs_examined,
+            &self.docs_returned,
+            &self.docs_written,
+            &self.total_micros,
+        ]
+    }
+
+    fn save(&self, collection: &str) -> SavedOpStats {
+        SavedOpStats {
+            collection: collection.to_string(),
+            counters: self.values().iter().map(|counter| counter.load(Ordering::Relaxed)).collect(),
+            latency: self.latency.iter().map(Histogram::save).collect(),
+            since: self.since.load(Ordering::Relaxed),
+        }
+    }
+
+    fn restore(&self, saved: &SavedOpStats) {
+        for (counter, value) in self.values().iter().zip(&saved.counters) {
+            counter.fetch_add(*value, Ordering::Relaxed);
+        }
+        for (histogram, saved) in self.latency.iter().zip(&saved.latency) {
+            histogram.restore(saved);
+        }
+        self.since.fetch_min(saved.since, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, collection: &str) -> CollectionOpStats {
+        let finds = self.finds.load(Ordering::Relaxed);
+        let inserts = self.inserts.load(Ordering::Relaxed);
+        let updates = self.updates.load(Ordering::Relaxed);
+        let deletes = self.deletes.load(Ordering::Relaxed);
+        let aggregates = self.aggregates.load(Ordering::Relaxed);
+        let total_micros = self.total_micros.load(Ordering::Relaxed);
+        let ops = finds + inserts + updates + deletes + aggregates;
+
+        CollectionOpStats {
+            collection: collection.to_string(),
+            finds,
+            inserts,
+            updates,
+            deletes,
+            aggregates,
+            scans: self.scans.load(Ordering::Relaxed),
+            index_hits: self.index_hits.load(Ordering::Relaxed),
+            docs_examined: self.docs_examined.load(Ordering::Relaxed),
+            docs_returned: self.docs_returned.load(Ordering::Relaxed),
+            docs_written: self.docs_written.load(Ordering::Relaxed),
+            total_latency_us: total_micros,
+            avg_latency_us: if ops > 0 { total_micros as f64 / ops as f64 } else { 0.0 },
+            since: self.since.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 单个集合的操作统计快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CollectionOpStats {
+    /// 集合名称
+    pub collection: String,
+    /// FIND 次数
+    pub finds: u64,
+    /// INSERT 次数
+    pub inserts: u64,
+    /// UPDATE 次数
+    pub updates: u64,
+    /// DELETE 次数
+    pub deletes: u64,
+    /// AGGREGATE 次数
+    pub aggregates: u64,
+    /// 全集合扫描次数
+    pub scans: u64,
+    /// 通过索引定位文档的次数
+    pub index_hits: u64,
+    /// 扫描检查的文档数
+    pub docs_examined: u64,
+    /// 返回给客户端的文档数
+    pub docs_returned: u64,
+    /// 插入、修改或删除的文档数
+    pub docs_written: u64,
+    /// 累计执行耗时(微秒)
+    pub total_latency_us: u64,
+    /// 平均每次操作的执行耗时(微秒)
+    pub avg_latency_us: f64,
+    /// 开始统计的时间(Unix 秒),重置后更新
+    pub since: u64,
+}
+
+/// 单个集合的原始计数,用于在服务器重启之间保存统计
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedOpStats {
+    /// 集合名称
+    pub collection: String,
+    /// 操作次数、访问路径、文档数和累计耗时,顺序与内部计数器一致
+    pub counters: Vec<u64>,
+    /// 按 `OpKind::ALL` 顺序的耗时直方图
+    pub latency: Vec<SavedHistogram>,
+    /// 开始统计的时间(Unix 秒)
+    pub since: u64,
+}
+
+/// 耗时直方图的原始计数
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedHistogram {
+    /// 各桶的计数(不累计)
+    pub buckets: Vec<u64>,
+    /// 总耗时(微秒)
+    pub total_micros: u64,
+}
+
+/// 按集合的操作统计
+///
+/// 在服务器内共享,执行器通过 `QueryExecutor::with_op_stats` 接入。
+#[derive(Debug, Default)]
+pub struct OpStats {
+    collections: RwLock<HashMap<String, Arc<Counters>>>,
+}
+
+impl OpStats {
+    /// 创建空的统计
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn counters(&self, collection: &str) -> Arc<Counters> {
+        if let Some(counters) = self.collections.read().get(collection) {
+            return counters.clone();
+        }
+        self.collections
+            .write()
+            .entry(collection.to_string())
+            .or_insert_with(|| Arc::new(Counters::new()))
+            .clone()
+    }
+
+    /// # Brief
+    /// 记录一次成功执行的操作
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `kind` - 操作类型
+    /// * `elapsed` - 执行耗时
+    pub fn record_op(&self, collection: &str, kind: OpKind, elapsed: Duration) {
+        let counters = self.counters(collection);
+        let counter = match kind {
+            OpKind::Find => &counters.finds,
+            OpKind::Insert => &counters.inserts,
+            OpKind::Update => &counters.updates,
+            OpKind::Delete => &counters.deletes,
+            OpKind::Aggregate => &counters.aggregates,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        counters
+            .total_micros
+            .fetch_add(elapsed.as_micros().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+        counters.latency[kind as usize].observe(elapsed);
+    }
+
+    /// # Brief
+    /// 记录一次全集合扫描
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `examined` - 扫描的文档数
+    pub fn record_scan(&self, collection: &str, examined: u64) {
+        let counters = self.counters(collection);
+        counters.scans.fetch_add(1, Ordering::Relaxed);
+        counters.docs_examined.fetch_add(examined, Ordering::Relaxed);
+    }
+
+    /// # Brief
+    /// 记录一次索引命中
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `examined` - 通过索引读取的文档数
+    pub fn record_index_hit(&self, collection: &str, examined: u64) {
+        let counters = self.counters(collection);
+        counters.index_hits.fetch_add(1, Ordering::Relaxed);
+        counters.docs_examined.fetch_add(examined, Ordering::Relaxed);
+    }
+
+    /// # Brief
+    /// 记录返回给客户端的文档数
+    pub fn record_returned(&self, collection: &str, count: u64) {
+        self.counters(collection).docs_returned.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// # Brief
+    /// 记录插入、修改或删除的文档数
+    pub fn record_written(&self, collection: &str, count: u64) {
+        self.counters(collection).docs_written.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// # Brief
+    /// 获取单个集合的统计快照
+    ///
+    /// # Returns
+    /// 集合没有任何记录时返回 None
+    pub fn snapshot(&self, collection: &str) -> Option<CollectionOpStats> {
+        self.collections
+            .read()
+            .get(collection)
+            .map(|counters| counters.snapshot(collection))
+    }
+
+    /// # Brief
+    /// 获取所有集合的统计快照,按集合名称排序
+    pub fn snapshot_all(&self) -> Vec<CollectionOpStats> {
+        let mut all: Vec<_> = self
+            .collections
+            .read()
+            .iter()
+            .map(|(name, counters)| counters.snapshot(name))
+            .collect();
+        all.sort_by(|a, b| a.collection.cmp(&b.collection));
+        all
+    }
+
+    /// # Brief
+    /// 获取所有集合各操作类型的耗时直方图,按集合名称排序,没有记录的操作类型不返回
+    pub fn latency_histograms(&self) -> Vec<(String, OpKind, LatencyHistogram)> {
+        let mut all = Vec::new();
+        for (name, counters) in self.collections.read().iter() {
+            for (kind, histogram) in OpKind::ALL.iter().zip(&counters.latency) {
+                let snapshot = histogram.snapshot();
+                if snapshot.count > 0 {
+                    all.push((name.clone(), *kind, snapshot));
+                }
+            }
+        }
+        all.sort_by(|a, b| a.0.cmp(&b.0));
+        all
+    }
+
+    /// # Brief
+    /// 重置统计
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称,为 None 时重置所有集合
+    ///
+    /// # Returns
+    /// 被重置的集合数
+    pub fn reset(&self, collection: Option<&str>) -> usize {
+        let collections = self.collections.read();
+        match collection {
+            Some(name) => match collections.get(name) {
+                Some(counters) => {
+                    counters.reset();
+                    1
+                }
+                None => 0,
+            },
+            None => {
+                collections.values().for_each(|counters| counters.reset());
+                collections.len()
+            }
+        }
+    }
+
+    /// # Brief
+    /// 导出所有集合的原始计数,用于服务器关闭时保存
+    pub fn save(&self) -> Vec<SavedOpStats> {
+        self.collections
+            .read()
+            .iter()
+            .map(|(name, counters)| counters.save(name))
+            .collect()
+    }
+
+    /// # Brief
+    /// 恢复 `save` 导出的计数,与启动后已记录的计数累加
+    ///
+    /// # Arguments
+    /// * `saved` - 保存的各集合计数
+    pub fn restore(&self, saved: &[SavedOpStats]) {
+        for stats in saved {
+            self.counters(&stats.collection).restore(stats);
+        }
+    }
+
+    /// # Brief
+    /// 删除集合的统计(集合被删除时调用)
+    pub fn remove(&self, collection: &str) {
+        self.collections.write().remove(collection);
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_reset() {
+        let stats = OpStats::new();
+        stats.record_scan("users", 100);
+        stats.record_returned("users", 3);
+        stats.record_op("users", OpKind::Find, Duration::from_micros(300));
+        stats.record_written("users", 2);
+        stats.record_op("users", OpKind::Insert, Duration::from_micros(100));
+        stats.record_op("orders", OpKind::Delete, Duration::from_micros(50));
+
+        let users = stats.snapshot("users").unwrap();
+        assert_eq!((users.finds, users.inserts, users.deletes), (1, 1, 0));
+        assert_eq!((users.scans, users.docs_examined, users.docs_returned), (1, 100, 3));
+        assert_eq!(users.docs_written, 2);
+        assert_eq!(users.avg_latency_us, 200.0);
+        assert!(stats.snapshot("missing").is_none());
+
+        let names: Vec<_> = stats.snapshot_all().into_iter().map(|s| s.collection).collect();
+        assert_eq!(names, vec!["orders", "users"]);
+
+        assert_eq!(stats.reset(Some("users")), 1);
+        let users = stats.snapshot("users").unwrap();
+        assert_eq!((users.finds, users.scans, users.total_latency_us), (0, 0, 0));
+        assert_eq!(stats.snapshot("orders").unwrap().deletes, 1);
+
+        assert_eq!(stats.reset(None), 2);
+        assert_eq!(stats.snapshot("orders").unwrap().deletes, 0);
+    }
+
+    #[test]
+    fn test_latency_histograms() {
+        let stats = OpStats::new();
+        stats.record_op("users", OpKind::Find, Duration::from_micros(300));
+        stats.record_op("users", OpKind::Find, Duration::from_millis(20));
+        stats.record_op("users", OpKind::Find, Duration::from_secs(30));
+        stats.record_op("orders", OpKind::Insert, Duration::from_millis(1));
+
+        let histograms = stats.latency_histograms();
+        assert_eq!(histograms.len(), 2);
+        let (name, kind, orders) = &histograms[0];
+        assert_eq!((name.as_str(), *kind), ("orders", OpKind::Insert));
+        // 恰好等于上界的耗时计入该桶
+        assert_eq!(orders.buckets[..3], [0, 1, 1]);
+
+        let (_, kind, users) = &histograms[1];
+        assert_eq!(*kind, OpKind::Find);
+        assert_eq!(users.buckets.len(), LATENCY_BUCKETS_SECS.len() + 1);
+        assert_eq!(users.buckets, vec![1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 3]);
+        assert_eq!(users.count, 3);
+        assert!((users.sum_secs - 30.0203).abs() < 1e-9);
+
+        stats.reset(None);
+        assert!(stats.latency_histograms().is_empty());
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let stats = OpStats::new();
+        stats.record_scan("users", 100);
+        stats.record_op("users", OpKind::Find, Duration::from_millis(2));
+        let saved = stats.save();
+
+        let restored = OpStats::new();
+        restored.record_op("users", OpKind::Find, Duration::from_millis(2));
+        restored.restore(&saved);
+        let users = restored.snapshot("users").unwrap();
+        assert_eq!((users.finds, users.scans, users.docs_examined), (2, 1, 100));
+        assert_eq!(users.total_latency_us, 4000);
+        assert_eq!(users.since, stats.snapshot("users").unwrap().since);
+        assert_eq!(restored.latency_histograms()[0].2.count, 2);
+
+        let json = serde_json::to_string(&saved).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<SavedOpStats>>(&json).unwrap(), saved);
+    }
+}
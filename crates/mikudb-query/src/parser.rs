@@ -5,22 +5,35 @@
 //! - 支持 MongoDB 风格的聚合管道 (AGGREGATE)
 //! - 表达式优先级: OR < AND < NOT < 比较 < 加减 < 乘除模 < 一元 < 主表达式
 //! - 错误处理: 语法错误时提供详细的位置和错误信息
+//! - 位置跟踪: 记录已查看的最远 Token 位置,错误附带行号、列号和出错的原始文本
 //! - Peekable 迭代器: 支持前向查看 Token 而不消费
+//! - 参数占位符: 值的位置可以写 `$1` 或 `:name`,只能通过 PreparedStatement 绑定后执行
 
 use crate::ast::*;
-use crate::lexer::{Lexer, Token};
+use crate::lexer::Token;
 use crate::{QueryError, QueryResult};
+use base64::Engine as _;
 use compact_str::CompactString;
 use indexmap::IndexMap;
 use mikudb_boml::BomlValue;
+use logos::Logos;
+use mikudb_common::config::CompressionType;
+use mikudb_storage::{CappedOptions, OversizedKeyPolicy, RollupAggregate, RollupDefinition, RollupFunction, RollupKey, TimeBucket};
 use std::iter::Peekable;
+use std::ops::Range;
 
 /// MQL 解析器
 ///
 /// 使用递归下降算法将 MQL 查询字符串解析为 AST
 pub struct Parser<'a> {
-    tokens: Peekable<std::vec::IntoIter<(Token, std::ops::Range<usize>)>>,
+    tokens: Peekable<std::vec::IntoIter<(Token, Range<usize>)>>,
     input: &'a str,
+    /// 已查看(peek 或消费)的最远 Token 位置,语法错误即发生在这里
+    furthest: Range<usize>,
+    /// 第一个无法识别的输入位置
+    invalid: Option<Range<usize>>,
+    /// 解析到的参数占位符及其位置,按出现顺序
+    parameters: Vec<(Parameter, Range<usize>)>,
 }
 
 impl<'a> Parser<'a> {
@@ -35,10 +48,23 @@ impl<'a> Parser<'a> {
     /// # Returns
     /// 新的 Parser 实例
     pub fn new(input: &'a str) -> Self {
-        let tokens = Lexer::tokenize(input);
+        let mut invalid = None;
+        let tokens: Vec<_> = Token::lexer(input)
+            .spanned()
+            .filter_map(|(result, span)| match result {
+                Ok(token) => Some((token, span)),
+                Err(_) => {
+                    invalid.get_or_insert(span);
+                    None
+                }
+            })
+            .collect();
         Self {
             tokens: tokens.into_iter().peekable(),
             input,
+            furthest: 0..0,
+            invalid,
+            parameters: Vec::new(),
         }
     }
 
@@ -54,7 +80,26 @@ impl<'a> Parser<'a> {
     /// 成功返回 Statement，语法错误返回 QueryError
     pub fn parse(input: &str) -> QueryResult<Statement> {
         let mut parser = Parser::new(input);
-        parser.parse_statement()
+        parser.check_invalid()?;
+        let statement = parser.parse_statement().map_err(|e| parser.locate(e))?;
+        parser.check_unbound()?;
+        Ok(statement)
+    }
+
+    /// # Brief
+    /// 解析可能带参数占位符的单个语句
+    ///
+    /// # Arguments
+    /// * `input` - MQL 查询字符串
+    ///
+    /// # Returns
+    /// 语句和按出现顺序排列的占位符(同一参数出现多次时重复列出)
+    pub(crate) fn parse_with_parameters(input: &str) -> QueryResult<(Statement, Vec<Parameter>)> {
+        let mut parser = Parser::new(input);
+        parser.check_invalid()?;
+        let statement = parser.parse_statement().map_err(|e| parser.locate(e))?;
+        let parameters = parser.parameters.into_iter().map(|(parameter, _)| parameter).collect();
+        Ok((statement, parameters))
     }
 
     /// 解析多个语句
@@ -69,12 +114,15 @@ impl<'a> Parser<'a> {
     /// 成功返回 Statement 向量，语法错误返回 QueryError
     pub fn parse_multiple(input: &str) -> QueryResult<Vec<Statement>> {
         let mut parser = Parser::new(input);
+        parser.check_invalid()?;
         let mut statements = Vec::new();
 
         while parser.peek().is_some() {
-            statements.push(parser.parse_statement()?);
+            let statement = parser.parse_statement().map_err(|e| parser.locate(e))?;
+            statements.push(statement);
             parser.skip_if(Token::Semicolon);
         }
+        parser.check_unbound()?;
 
         Ok(statements)
     }
@@ -84,15 +132,97 @@ impl<'a> Parser<'a> {
     ///
     /// 用于判断下一步的解析方向,不移动迭代器位置。
     fn peek(&mut self) -> Option<&Token> {
+        let span = self.tokens.peek().map(|(_, span)| span.clone());
+        self.touch(span);
         self.tokens.peek().map(|(t, _)| t)
     }
 
+    /// # Brief
+    /// 前向查看下一个之后的 Token 而不消费
+    ///
+    /// 用于需要两个 Token 才能确定解析方向的语法,如 FIND AND MODIFY。
+    fn peek_second(&self) -> Option<Token> {
+        self.tokens.clone().nth(1).map(|(t, _)| t)
+    }
+
     /// # Brief
     /// 消费并返回下一个 Token
     ///
     /// 移动迭代器位置,返回当前 Token。
     fn next(&mut self) -> Option<Token> {
-        self.tokens.next().map(|(t, _)| t)
+        let next = self.tokens.next();
+        self.touch(next.as_ref().map(|(_, span)| span.clone()));
+        next.map(|(t, _)| t)
+    }
+
+    /// # Brief
+    /// 记录已查看的 Token 位置
+    ///
+    /// 解析失败时出错的 Token 总是最后查看的那个: 消费后不匹配,或前向查看后没有可选分支。
+    ///
+    /// # Arguments
+    /// * `span` - Token 位置,None 表示输入末尾
+    fn touch(&mut self, span: Option<Range<usize>>) {
+        let end = self.input.len();
+        let span = span.unwrap_or(end..end);
+        if span.start >= self.furthest.start {
+            self.furthest = span;
+        }
+    }
+
+    /// # Brief
+    /// 生成带位置信息的解析错误
+    ///
+    /// # Arguments
+    /// * `span` - 出错的输入范围
+    /// * `message` - 错误信息
+    fn error_at(&self, span: Range<usize>, message: String) -> QueryError {
+        let before = &self.input[..span.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        QueryError::Parse {
+            position: span.start,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            token: (!span.is_empty()).then(|| self.input[span].to_string()),
+            message,
+        }
+    }
+
+    /// # Brief
+    /// 为语法错误附加最远查看位置
+    fn locate(&self, error: QueryError) -> QueryError {
+        match error {
+            QueryError::Syntax(message) => self.error_at(self.furthest.clone(), message),
+            other => other,
+        }
+    }
+
+    /// # Brief
+    /// 检查词法分析阶段是否遇到无法识别的输入
+    fn check_invalid(&self) -> QueryResult<()> {
+        match self.invalid.clone() {
+            Some(span) => {
+                let message = if self.input[span.clone()].starts_with(['"', '\'', '`']) {
+                    "Unterminated string literal"
+                } else {
+                    "Unrecognized input"
+                };
+                Err(self.error_at(span, message.to_string()))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// # Brief
+    /// 不经过 PreparedStatement 解析的语句不能带参数占位符
+    fn check_unbound(&self) -> QueryResult<()> {
+        match self.parameters.first() {
+            Some((parameter, span)) => Err(self.error_at(
+                span.clone(),
+                format!("Parameter {} has no bound value; execute the query with parameters", parameter),
+            )),
+            None => Ok(()),
+        }
     }
 
     /// # Brief
@@ -158,6 +288,7 @@ impl<'a> Parser<'a> {
             Some(Token::Index) => Ok("index".to_string()),
             Some(Token::Collection) => Ok("collection".to_string()),
             Some(Token::Database) => Ok("database".to_string()),
+            Some(Token::Archive) => Ok("archive".to_string()),
             Some(t) => Err(QueryError::Syntax(format!(
                 "Expected identifier, got {:?}",
                 t
@@ -166,6 +297,33 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// # Brief
+    /// 期望下一个 Token 为指定的上下文关键字
+    ///
+    /// 上下文关键字(如 OLDER、THAN)不是保留字,以普通标识符的形式出现,
+    /// 因此不会影响同名字段或集合。
+    ///
+    /// # Arguments
+    /// * `word` - 期望的关键字(大写)
+    fn expect_word(&mut self, word: &str) -> QueryResult<()> {
+        match self.next() {
+            Some(Token::Identifier(ref s)) if s.eq_ignore_ascii_case(word) => Ok(()),
+            Some(t) => Err(QueryError::Syntax(format!("Expected {}, got {:?}", word, t))),
+            None => Err(QueryError::Syntax(format!("Expected {}, got end of input", word))),
+        }
+    }
+
+    /// # Brief
+    /// 如果下一个 Token 是指定的上下文关键字则跳过
+    fn skip_word(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Identifier(s)) if s.eq_ignore_ascii_case(word)) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
     fn parse_string_literal(&mut self, label: &str) -> QueryResult<String> {
         match self.next() {
             Some(Token::String(s)) => Ok(s),
@@ -186,6 +344,10 @@ impl<'a> Parser<'a> {
     /// - CREATE/DROP: DDL 操作
     /// - INSERT/FIND/UPDATE/DELETE: CRUD 操作
     /// - AGGREGATE: 聚合管道
+    /// - ARCHIVE: 冷热数据归档
+    /// - BACKUP/RESTORE: 备份与恢复
+    /// - ADMIN: 运维管理(日志级别)
+    /// - COMPACT: 手动压缩集合
     /// - BEGIN/COMMIT/ROLLBACK: 事务
     /// - GRANT/REVOKE: 权限管理
     /// - AI: AI 功能
@@ -201,6 +363,32 @@ impl<'a> Parser<'a> {
             Some(Token::Update) => self.parse_update(),
             Some(Token::Delete) => self.parse_delete(),
             Some(Token::Aggregate) => self.parse_aggregate(),
+            Some(Token::Archive) => self.parse_archive(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("dry") => self.parse_dry_run(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("backup") => self.parse_backup(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("restore") => self.parse_restore(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("admin") => self.parse_admin(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("cluster") => self.parse_cluster(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("stats") => {
+                self.next();
+                Ok(Statement::Stats(self.parse_identifier()?))
+            }
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("reset") => self.parse_reset_stats(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("compact") => {
+                self.next();
+                Ok(Statement::Compact(self.parse_identifier()?))
+            }
+            Some(Token::Set) => {
+                self.next();
+                let name = self.parse_identifier()?.to_lowercase();
+                self.expect(Token::Eq)?;
+                let value = self.parse_value()?;
+                Ok(Statement::SetVariable(SetVariableStatement { name, value }))
+            }
+            Some(Token::Analyze) => {
+                self.next();
+                Ok(Statement::Analyze(self.parse_identifier()?))
+            }
             Some(Token::Begin) => {
                 self.next();
                 self.expect(Token::Transaction)?;
@@ -241,8 +429,37 @@ impl<'a> Parser<'a> {
     /// - SHOW INDEX ON <collection>: 列出集合的索引
     /// - SHOW STATUS: 显示数据库状态
     /// - SHOW USERS: 列出所有用户
+    /// - SHOW ADVISOR [ON <collection>]: 列出索引顾问的建议
+    /// - SHOW SCHEMA ON <collection>: 列出集合的字段类型登记表
+    /// - SHOW FUNCTIONS: 列出已注册的自定义函数
+    /// - SHOW ROLLUPS: 列出预聚合定义
+    /// - SHOW MANIFEST: 导出数据库、集合、索引和用户的声明式清单
     fn parse_show(&mut self) -> QueryResult<Statement> {
         self.expect(Token::Show)?;
+        if self.skip_word("schema") {
+            self.expect(Token::On)?;
+            return Ok(Statement::ShowSchema(self.parse_identifier()?));
+        }
+        if self.skip_word("sequences") {
+            return Ok(Statement::ShowSequences);
+        }
+        if self.skip_word("functions") {
+            return Ok(Statement::ShowFunctions);
+        }
+        if self.skip_word("rollups") {
+            return Ok(Statement::ShowRollups);
+        }
+        if self.skip_word("manifest") {
+            return Ok(Statement::ShowManifest);
+        }
+        if self.skip_word("advisor") {
+            let collection = if self.skip_if(Token::On) {
+                Some(self.parse_identifier()?)
+            } else {
+                None
+            };
+            return Ok(Statement::ShowAdvisor(collection));
+        }
         match self.peek() {
             Some(Token::Database) => {
                 self.next();
@@ -278,7 +495,7 @@ impl<'a> Parser<'a> {
                 Ok(Statement::ShowGrants(username))
             }
             _ => Err(QueryError::Syntax(
-                "Expected DATABASE, COLLECTION, INDEX, STATUS, USERS, or GRANTS".to_string(),
+                "Expected DATABASE, COLLECTION, INDEX, STATUS, USERS, GRANTS, or ADVISOR".to_string(),
             )),
         }
     }
@@ -288,9 +505,12 @@ impl<'a> Parser<'a> {
     ///
     /// 语法:
     /// - CREATE DATABASE <name>
-    /// - CREATE COLLECTION <name>
+    /// - CREATE COLLECTION <name> [(CAPPED [SIZE n[B|KB|MB|GB]] [MAX n])]
     /// - CREATE [UNIQUE] [TEXT] INDEX <name> ON <collection> (fields)
     /// - CREATE USER <name> WITH PASSWORD <password> [ROLE roles]
+    /// - CREATE SEQUENCE <name> [START [WITH] n] [INCREMENT [BY] n]
+    /// - CREATE FUNCTION <name> WASM '<base64>'
+    /// - CREATE ROLLUP <name> ON <collection> [GROUP BY keys] AS {aggregates}
     fn parse_create(&mut self) -> QueryResult<Statement> {
         self.expect(Token::Create)?;
         match self.peek() {
@@ -302,24 +522,210 @@ impl<'a> Parser<'a> {
             Some(Token::Collection) => {
                 self.next();
                 let name = self.parse_identifier()?;
-                Ok(Statement::CreateCollection(name))
+                let capped = if self.skip_if(Token::LParen) {
+                    let capped = self.parse_capped_options()?;
+                    self.expect(Token::RParen)?;
+                    Some(capped)
+                } else {
+                    None
+                };
+                Ok(Statement::CreateCollection(CreateCollectionStatement { name, capped }))
             }
             Some(Token::Index) | Some(Token::Unique) | Some(Token::Text) => {
                 self.parse_create_index()
             }
             Some(Token::User) => self.parse_create_user(),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("sequence") => {
+                self.next();
+                self.parse_create_sequence()
+            }
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("function") => {
+                self.next();
+                self.parse_create_function()
+            }
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("rollup") => {
+                self.next();
+                self.parse_create_rollup()
+            }
             _ => Err(QueryError::Syntax(
-                "Expected DATABASE, COLLECTION, INDEX, or USER".to_string(),
+                "Expected DATABASE, COLLECTION, INDEX, USER, SEQUENCE, FUNCTION, or ROLLUP".to_string(),
             )),
         }
     }
 
+    /// # Brief
+    /// 解析固定大小集合的限制(括号内的部分)
+    ///
+    /// 语法: CAPPED [SIZE n[B|KB|MB|GB]] [MAX n]
+    /// - SIZE 和 MAX 至少指定一项,单位按 1024 进位,省略时为字节
+    fn parse_capped_options(&mut self) -> QueryResult<CappedOptions> {
+        self.expect_word("capped")?;
+        let mut capped = CappedOptions::default();
+        loop {
+            if self.skip_word("size") {
+                capped.max_size = Some(self.parse_byte_size()?);
+            } else if self.skip_if(Token::Max) {
+                let max = self.parse_integer()?;
+                if max < 0 {
+                    return Err(QueryError::Syntax("MAX must not be negative".to_string()));
+                }
+                capped.max_documents = Some(max as u64);
+            } else {
+                break;
+            }
+        }
+        capped.validate().map_err(QueryError::Syntax)?;
+        Ok(capped)
+    }
+
+    /// # Brief
+    /// 解析带可选单位的字节数,例如 `10MB`
+    fn parse_byte_size(&mut self) -> QueryResult<u64> {
+        let value = self.parse_integer()?;
+        if value < 0 {
+            return Err(QueryError::Syntax("Size must not be negative".to_string()));
+        }
+        let multiplier: u64 = match self.peek() {
+            Some(Token::Identifier(unit)) => match unit.to_ascii_lowercase().as_str() {
+                "b" => 1,
+                "kb" => 1 << 10,
+                "mb" => 1 << 20,
+                "gb" => 1 << 30,
+                _ => return Ok(value as u64),
+            },
+            _ => return Ok(value as u64),
+        };
+        self.next();
+        (value as u64)
+            .checked_mul(multiplier)
+            .ok_or_else(|| QueryError::Syntax("Size is too large".to_string()))
+    }
+
+    /// # Brief
+    /// 解析 CREATE SEQUENCE 语句(SEQUENCE 关键字之后的部分)
+    ///
+    /// 语法: CREATE SEQUENCE <name> [START [WITH] n] [INCREMENT [BY] n]
+    /// - START 和 INCREMENT 默认都为 1,可以为负数,INCREMENT 不能为 0
+    fn parse_create_sequence(&mut self) -> QueryResult<Statement> {
+        let name = self.parse_identifier()?;
+        let mut stmt = CreateSequenceStatement {
+            name,
+            start: 1,
+            increment: 1,
+        };
+
+        loop {
+            if self.skip_word("start") {
+                self.skip_if(Token::With);
+                stmt.start = self.parse_signed_integer()?;
+            } else if self.skip_word("increment") {
+                self.skip_if(Token::By);
+                stmt.increment = self.parse_signed_integer()?;
+            } else {
+                break;
+            }
+        }
+
+        if stmt.increment == 0 {
+            return Err(QueryError::Syntax("Sequence INCREMENT must not be 0".to_string()));
+        }
+        Ok(Statement::CreateSequence(stmt))
+    }
+
+    /// # Brief
+    /// 解析 CREATE ROLLUP 语句(ROLLUP 关键字之后的部分)
+    ///
+    /// 语法: CREATE ROLLUP <name> ON <collection> [GROUP BY <key> [AS <field>], ...] AS {<field>: FUNC(<field>), ...}
+    /// - 分组键为字段路径或 `DATE_TRUNC('<minute|hour|day|week|month|year>', <field>)`,
+    ///   默认以源字段路径作为预聚合文档中的字段名
+    /// - 聚合函数只支持 COUNT、SUM、AVG、MIN、MAX
+    fn parse_create_rollup(&mut self) -> QueryResult<Statement> {
+        let name = self.parse_identifier()?;
+        self.expect(Token::On)?;
+        let source = self.parse_identifier()?;
+
+        let mut group_by = Vec::new();
+        if self.skip_if(Token::Group) {
+            self.expect(Token::By)?;
+            loop {
+                let is_trunc = matches!(self.peek(), Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("date_trunc"))
+                    && self.peek_second() == Some(Token::LParen);
+                let (path, bucket) = if is_trunc {
+                    self.next();
+                    self.expect(Token::LParen)?;
+                    let unit = self.parse_string_literal("DATE_TRUNC unit")?;
+                    let bucket = TimeBucket::parse(&unit)
+                        .ok_or_else(|| QueryError::Syntax(format!("Unknown DATE_TRUNC unit: {}", unit)))?;
+                    self.expect(Token::Comma)?;
+                    let path = self.parse_field_path()?;
+                    self.expect(Token::RParen)?;
+                    (path, Some(bucket))
+                } else {
+                    (self.parse_field_path()?, None)
+                };
+                let field = if self.peek() == Some(&Token::As) && self.peek_second() != Some(Token::LBrace) {
+                    self.next();
+                    self.parse_field_path()?
+                } else {
+                    path.clone()
+                };
+                group_by.push(RollupKey { field, path, bucket });
+                if !self.skip_if(Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        self.expect(Token::As)?;
+        self.expect(Token::LBrace)?;
+        let mut aggregates = Vec::new();
+        loop {
+            let name = self.parse_identifier()?;
+            self.expect(Token::Colon)?;
+            let (function, path) = match self.parse_aggregate_function()? {
+                (AggregateFunction::Count, path) => (RollupFunction::Count, path),
+                (AggregateFunction::Sum, path) => (RollupFunction::Sum, path),
+                (AggregateFunction::Avg, path) => (RollupFunction::Avg, path),
+                (AggregateFunction::Min, path) => (RollupFunction::Min, path),
+                (AggregateFunction::Max, path) => (RollupFunction::Max, path),
+                (other, _) => {
+                    return Err(QueryError::Syntax(format!("{:?} is not supported in a rollup", other)))
+                }
+            };
+            aggregates.push(RollupAggregate { name, function, path });
+            if !self.skip_if(Token::Comma) {
+                break;
+            }
+        }
+        self.expect(Token::RBrace)?;
+
+        Ok(Statement::CreateRollup(RollupDefinition { name, source, group_by, aggregates }))
+    }
+
+    /// # Brief
+    /// 解析 CREATE FUNCTION 语句(FUNCTION 关键字之后的部分)
+    ///
+    /// 语法: CREATE FUNCTION <name> WASM '<base64>'
+    /// - 模块以 Base64 编码的字符串给出,接口要求见 `sandbox` 模块
+    fn parse_create_function(&mut self) -> QueryResult<Statement> {
+        let name = self.parse_identifier()?;
+        self.expect_word("wasm")?;
+        let encoded = self.parse_string_literal("WASM module")?;
+        let module = base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| QueryError::Syntax(format!("Invalid base64 WASM module: {}", e)))?;
+        Ok(Statement::CreateFunction(CreateFunctionStatement { name, module }))
+    }
+
     /// # Brief
     /// 解析 CREATE INDEX 语句
     ///
     /// 语法: CREATE [UNIQUE] [TEXT] INDEX <name> ON <collection> (field1 [ASC|DESC], field2, ...)
+    ///       [WITH TOKENIZER '<name>' STOPWORDS '<list>' | ('w1', ...) STEMMER '<language>']
+    ///       [WITH MAX_KEY_SIZE <bytes> OVERSIZED REJECT | TRUNCATE | SKIP]
     /// - UNIQUE: 唯一索引
-    /// - TEXT: 全文索引
+    /// - TEXT: 全文索引,可通过 WITH 子句指定分词器、停用词和词干提取
+    /// - 其他索引可通过 WITH 子句指定键长度上限和超长键的处理方式
     /// - 默认索引类型为 BTree
     fn parse_create_index(&mut self) -> QueryResult<Statement> {
         let mut unique = false;
@@ -359,13 +765,98 @@ impl<'a> Parser<'a> {
 
         self.expect(Token::RParen)?;
 
-        Ok(Statement::CreateIndex(CreateIndexStatement {
+        let mut statement = CreateIndexStatement {
             name,
             collection,
             fields,
             unique,
             index_type,
-        }))
+            text_options: None,
+            max_key_size: None,
+            oversized_keys: OversizedKeyPolicy::default(),
+        };
+        if self.skip_if(Token::With) {
+            if index_type == IndexType::Text {
+                statement.text_options = Some(self.parse_text_index_options()?);
+            } else {
+                self.parse_index_key_options(&mut statement)?;
+            }
+        }
+
+        Ok(Statement::CreateIndex(statement))
+    }
+
+    /// # Brief
+    /// 解析 BTree 和哈希索引的 WITH 选项
+    ///
+    /// 语法: `MAX_KEY_SIZE <bytes> [OVERSIZED REJECT | TRUNCATE | SKIP]`,两项可任选其一、任意顺序,
+    /// 字节数可带 KB 等单位
+    fn parse_index_key_options(&mut self, statement: &mut CreateIndexStatement) -> QueryResult<()> {
+        let mut parsed = false;
+        loop {
+            if self.skip_word("MAX_KEY_SIZE") {
+                let size = self.parse_byte_size()?;
+                statement.max_key_size = Some(
+                    usize::try_from(size).map_err(|_| QueryError::Syntax("MAX_KEY_SIZE is too large".to_string()))?,
+                );
+            } else if self.skip_word("OVERSIZED") {
+                statement.oversized_keys = if self.skip_word("REJECT") {
+                    OversizedKeyPolicy::Reject
+                } else if self.skip_word("TRUNCATE") {
+                    OversizedKeyPolicy::Truncate
+                } else if self.skip_if(Token::Skip) {
+                    OversizedKeyPolicy::Skip
+                } else {
+                    return Err(QueryError::Syntax(
+                        "Expected REJECT, TRUNCATE or SKIP after OVERSIZED".to_string(),
+                    ));
+                };
+            } else if !parsed {
+                return Err(QueryError::Syntax(
+                    "Expected MAX_KEY_SIZE or OVERSIZED after WITH".to_string(),
+                ));
+            } else {
+                break;
+            }
+            parsed = true;
+            self.skip_if(Token::Comma);
+        }
+        Ok(())
+    }
+
+    /// # Brief
+    /// 解析全文索引的 WITH 选项
+    ///
+    /// 选项可按任意顺序出现,可用逗号分隔:
+    /// TOKENIZER '<name>'、STOPWORDS '<list>' 或 STOPWORDS ('w1', 'w2')、STEMMER '<language>'
+    fn parse_text_index_options(&mut self) -> QueryResult<TextIndexOptions> {
+        let mut options = TextIndexOptions::default();
+        loop {
+            if self.skip_word("TOKENIZER") {
+                options.tokenizer = Some(self.parse_string_literal("tokenizer")?);
+            } else if self.skip_word("STOPWORDS") {
+                options.stopwords = Some(if self.skip_if(Token::LParen) {
+                    let mut words = vec![self.parse_string_literal("stopword")?];
+                    while self.skip_if(Token::Comma) {
+                        words.push(self.parse_string_literal("stopword")?);
+                    }
+                    self.expect(Token::RParen)?;
+                    TextStopWords::Custom(words)
+                } else {
+                    TextStopWords::Builtin(self.parse_string_literal("stopword list")?)
+                });
+            } else if self.skip_word("STEMMER") {
+                options.stemmer = Some(self.parse_string_literal("stemmer")?);
+            } else if options == TextIndexOptions::default() {
+                return Err(QueryError::Syntax(
+                    "Expected TOKENIZER, STOPWORDS or STEMMER after WITH".to_string(),
+                ));
+            } else {
+                break;
+            }
+            self.skip_if(Token::Comma);
+        }
+        Ok(options)
     }
 
     /// # Brief
@@ -402,6 +893,8 @@ impl<'a> Parser<'a> {
     /// - DROP COLLECTION <name>
     /// - DROP INDEX <name> ON <collection>
     /// - DROP USER <name>
+    /// - DROP FUNCTION <name>
+    /// - DROP ROLLUP <name>
     fn parse_drop(&mut self) -> QueryResult<Statement> {
         self.expect(Token::Drop)?;
         match self.peek() {
@@ -427,8 +920,20 @@ impl<'a> Parser<'a> {
                 let name = self.parse_string_literal("username")?;
                 Ok(Statement::DropUser(name))
             }
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("sequence") => {
+                self.next();
+                Ok(Statement::DropSequence(self.parse_identifier()?))
+            }
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("function") => {
+                self.next();
+                Ok(Statement::DropFunction(self.parse_identifier()?))
+            }
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("rollup") => {
+                self.next();
+                Ok(Statement::DropRollup(self.parse_identifier()?))
+            }
             _ => Err(QueryError::Syntax(
-                "Expected DATABASE, COLLECTION, INDEX, or USER".to_string(),
+                "Expected DATABASE, COLLECTION, INDEX, USER, SEQUENCE, FUNCTION, or ROLLUP".to_string(),
             )),
         }
     }
@@ -437,35 +942,73 @@ impl<'a> Parser<'a> {
     /// 解析 INSERT 语句
     ///
     /// 语法:
-    /// - INSERT INTO <collection> {doc}
-    /// - INSERT INTO <collection> [{doc1}, {doc2}, ...]
+    /// - INSERT INTO <collection> {doc} [RETURNING fields]
+    /// - INSERT INTO <collection> [{doc1}, {doc2}, ...] [RETURNING fields]
+    /// - INSERT INTO <collection> FIND <source> ...
     fn parse_insert(&mut self) -> QueryResult<Statement> {
         self.expect(Token::Insert)?;
         self.expect(Token::Into)?;
         let collection = self.parse_identifier()?;
 
+        if self.peek() == Some(&Token::Find) {
+            let source = self.parse_find_statement()?;
+            return Ok(Statement::InsertSelect(InsertSelectStatement { collection, source }));
+        }
+
         let documents = if self.peek() == Some(&Token::LBracket) {
             self.parse_array_literal()?
         } else {
             vec![self.parse_document_literal()?]
         };
+        let returning = self.parse_returning()?;
 
         Ok(Statement::Insert(InsertStatement {
             collection,
             documents,
+            returning,
         }))
     }
 
     /// # Brief
     /// 解析 FIND 语句
     ///
-    /// 语法: FIND <collection> [WHERE expr] [SELECT fields] [ORDER BY fields] [LIMIT n] [SKIP n]
+    /// 语法: FIND <collection> [WHERE expr] [SELECT fields] [ORDER BY fields] [LIMIT n] [SKIP n] [WITH ARCHIVE]
     /// - WHERE: 过滤条件
     /// - SELECT: 投影字段
     /// - ORDER BY: 排序
     /// - LIMIT: 限制返回数量
     /// - SKIP: 跳过记录数
+    /// - WITH ARCHIVE: 同时查询源集合的归档集合
     fn parse_find(&mut self) -> QueryResult<Statement> {
+        if self.peek_second() == Some(Token::And) {
+            return self.parse_find_and_modify();
+        }
+        Ok(Statement::Find(self.parse_find_statement()?))
+    }
+
+    /// # Brief
+    /// 解析 FIND AND MODIFY 语句
+    ///
+    /// 语法: FIND AND MODIFY <collection> SET ... [WHERE expr] [ORDER BY fields] [RETURNING [OLD | NEW] * | fields]
+    ///
+    /// 等价于 `UPDATE ... LIMIT 1 RETURNING ...`: 原子地更新第一个匹配的文档,
+    /// 省略 RETURNING 时返回更新后的完整文档。
+    fn parse_find_and_modify(&mut self) -> QueryResult<Statement> {
+        self.expect(Token::Find)?;
+        self.expect(Token::And)?;
+        self.expect_word("MODIFY")?;
+        let collection = self.parse_identifier()?;
+
+        let mut update = self.parse_update_body(collection)?;
+        if update.limit.is_some() {
+            return Err(QueryError::Syntax("FIND AND MODIFY does not accept LIMIT".to_string()));
+        }
+        update.limit = Some(1);
+        update.returning.get_or_insert(Returning::All);
+        Ok(Statement::Update(update))
+    }
+
+    fn parse_find_statement(&mut self) -> QueryResult<FindStatement> {
         self.expect(Token::Find)?;
         let collection = self.parse_identifier()?;
 
@@ -497,111 +1040,471 @@ impl<'a> Parser<'a> {
                     self.next();
                     stmt.skip = Some(self.parse_integer()? as u64);
                 }
+                Some(Token::With) => {
+                    self.next();
+                    self.expect(Token::Archive)?;
+                    stmt.include_archive = true;
+                }
+                Some(Token::Identifier(w)) if w.eq_ignore_ascii_case("BATCH") => {
+                    self.next();
+                    stmt.batch_size = Some(self.parse_batch_size()?);
+                }
                 _ => break,
             }
         }
 
-        Ok(Statement::Find(stmt))
+        Ok(stmt)
+    }
+
+    /// # Brief
+    /// 解析 ARCHIVE 语句
+    ///
+    /// 语法: ARCHIVE [DOCUMENTS] OLDER THAN <duration> OF <collection> TO <archive> [COMPRESSION zstd|lz4|none] [PATH '<dir>']
+    /// - duration: 整数加单位 s/m/h/d/w,例如 90d
+    /// - COMPRESSION: 归档集合的压缩算法,默认 zstd
+    /// - PATH: 归档集合所在目录,可放到其他磁盘
+    fn parse_archive(&mut self) -> QueryResult<Statement> {
+        self.expect(Token::Archive)?;
+        self.skip_word("DOCUMENTS");
+        self.expect_word("OLDER")?;
+        self.expect_word("THAN")?;
+        let older_than_secs = self.parse_duration()?;
+        self.expect_word("OF")?;
+        let collection = self.parse_identifier()?;
+        self.expect(Token::To)?;
+        let archive = self.parse_identifier()?;
+
+        let mut compression = CompressionType::Zstd;
+        let mut path = None;
+        loop {
+            if self.skip_word("COMPRESSION") {
+                let name = self.parse_identifier()?;
+                compression = mikudb_storage::tiering::parse_compression(&name).ok_or_else(|| {
+                    QueryError::Syntax(format!("Unknown compression: {}", name))
+                })?;
+            } else if self.skip_word("PATH") {
+                path = Some(self.parse_string_literal("path")?);
+            } else {
+                break;
+            }
+        }
+
+        Ok(Statement::Archive(ArchiveStatement {
+            collection,
+            archive,
+            older_than_secs,
+            compression,
+            path,
+        }))
+    }
+
+    /// # Brief
+    /// 解析时长字面量
+    ///
+    /// 格式为整数加单位: s(秒)、m(分钟)、h(小时)、d(天)、w(周),例如 `90d`。
+    ///
+    /// # Returns
+    /// 时长的秒数
+    fn parse_duration(&mut self) -> QueryResult<u64> {
+        let value = self.parse_integer()?;
+        if value < 0 {
+            return Err(QueryError::Syntax("Duration must not be negative".to_string()));
+        }
+        let unit = match self.next() {
+            Some(Token::Identifier(unit)) => unit,
+            Some(t) => return Err(QueryError::Syntax(format!("Expected duration unit, got {:?}", t))),
+            None => return Err(QueryError::Syntax("Expected duration unit".to_string())),
+        };
+        let multiplier = match unit.to_ascii_lowercase().as_str() {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            "d" => 86400,
+            "w" => 7 * 86400,
+            _ => return Err(QueryError::Syntax(format!("Unknown duration unit: {}", unit))),
+        };
+        Ok(value as u64 * multiplier)
+    }
+
+    /// # Brief
+    /// 解析 DRY RUN 语句
+    ///
+    /// 语法: DRY RUN UPDATE ... | DRY RUN DELETE FROM ...
+    fn parse_dry_run(&mut self) -> QueryResult<Statement> {
+        self.expect_word("dry")?;
+        self.expect_word("run")?;
+        let statement = match self.peek() {
+            Some(Token::Update) => self.parse_update()?,
+            Some(Token::Delete) => self.parse_delete()?,
+            _ => {
+                return Err(QueryError::Syntax(
+                    "DRY RUN only supports UPDATE and DELETE".to_string(),
+                ))
+            }
+        };
+        Ok(Statement::DryRun(Box::new(statement)))
+    }
+
+    /// # Brief
+    /// 解析 BACKUP 语句
+    ///
+    /// 语法: BACKUP [SNAPSHOT] TO '<dir>' [WITHOUT SYSTEM]
+    /// - SNAPSHOT: 创建物理快照,不能与 WITHOUT SYSTEM 同时使用
+    /// - WITHOUT SYSTEM: 不包含用户、角色等系统集合
+    fn parse_backup(&mut self) -> QueryResult<Statement> {
+        self.expect_word("BACKUP")?;
+        let snapshot = self.skip_word("SNAPSHOT");
+        self.expect(Token::To)?;
+        let path = self.parse_string_literal("backup path")?;
+        let include_system = if self.skip_word("WITHOUT") {
+            self.expect_word("SYSTEM")?;
+            false
+        } else {
+            true
+        };
+        if snapshot && !include_system {
+            return Err(QueryError::Syntax("BACKUP SNAPSHOT always includes system data".to_string()));
+        }
+        Ok(Statement::Backup(BackupStatement { path, include_system, snapshot }))
+    }
+
+    /// # Brief
+    /// 解析 RESTORE 语句
+    ///
+    /// 语法: RESTORE FROM '<dir>' [METADATA ONLY]
+    /// - METADATA ONLY: 只恢复用户、角色等系统数据及其元数据
+    fn parse_restore(&mut self) -> QueryResult<Statement> {
+        self.expect_word("RESTORE")?;
+        self.expect(Token::From)?;
+        let path = self.parse_string_literal("backup path")?;
+        let metadata_only = if self.skip_word("METADATA") {
+            self.expect_word("ONLY")?;
+            true
+        } else {
+            false
+        };
+        Ok(Statement::Restore(RestoreStatement { path, metadata_only }))
+    }
+
+    /// # Brief
+    /// 解析 ADMIN 运维语句
+    ///
+    /// 语法:
+    /// - ADMIN SET LOG LEVEL <level> [TARGET <target>]
+    /// - ADMIN RESET LOG LEVEL
+    ///
+    /// 包含 `::` 的模块路径需要用引号,例如 TARGET 'mikudb_storage::engine'。
+    fn parse_admin(&mut self) -> QueryResult<Statement> {
+        self.expect_word("ADMIN")?;
+        if self.skip_word("RESET") {
+            self.expect_word("LOG")?;
+            self.expect_word("LEVEL")?;
+            return Ok(Statement::ResetLogLevel);
+        }
+
+        self.expect(Token::Set)?;
+        self.expect_word("LOG")?;
+        self.expect_word("LEVEL")?;
+        let level = self.parse_identifier()?.to_lowercase();
+        let target = if self.skip_word("TARGET") {
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+        Ok(Statement::SetLogLevel(SetLogLevelStatement { level, target }))
+    }
+
+    /// # Brief
+    /// 解析 CLUSTER 语句
+    ///
+    /// 语法:
+    /// - CLUSTER INIT
+    /// - CLUSTER JOIN '<mikudb://host:port>': 地址为集群成员的集群端口
+    fn parse_cluster(&mut self) -> QueryResult<Statement> {
+        self.expect_word("CLUSTER")?;
+        if self.skip_word("INIT") {
+            return Ok(Statement::ClusterInit);
+        }
+        self.expect_word("JOIN")?;
+        Ok(Statement::ClusterJoin(self.parse_string_literal("seed address")?))
+    }
+
+    /// # Brief
+    /// 解析 RESET STATS 语句
+    ///
+    /// 语法: RESET STATS [<collection>]
+    fn parse_reset_stats(&mut self) -> QueryResult<Statement> {
+        self.expect_word("RESET")?;
+        self.expect_word("STATS")?;
+        let collection = if !matches!(self.peek(), None | Some(Token::Semicolon)) {
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+        Ok(Statement::ResetStats(collection))
     }
 
     /// # Brief
     /// 解析 UPDATE 语句
     ///
-    /// 语法: UPDATE <collection> SET field1 = value1, field2 += value2 [UNSET field3] [PUSH field4 = value4] [WHERE expr]
+    /// 语法: UPDATE <collection> SET field1 = value1, field2 += value2 [UNSET field3] [PUSH field4 = value4] ... [WHERE expr] [ORDER BY fields] [LIMIT n] [RETURNING fields]
     /// - SET field = value: 设置字段值
     /// - SET field += value: 增加数值 ($inc)
     /// - UNSET field: 删除字段
     /// - PUSH field = value: 向数组添加元素
+    /// - ADDTOSET field = value: 数组中没有相同元素时才添加
+    /// - PULL field = value / PULLALL field = [values]: 从数组移除等于该值 / 任一值的元素
+    /// - POP FIRST | LAST field: 移除数组的第一个 / 最后一个元素
+    /// - RENAME field TO new_field: 重命名字段
+    /// - 字段可以是路径: `items.0.qty` 指定数组下标,`items.$.qty` 指定 WHERE 匹配的元素
+    /// - ORDER BY / LIMIT: 按顺序只更新前 n 个匹配的文档
+    /// - RETURNING [OLD | NEW] * | fields: 返回更新后(NEW,默认)或更新前(OLD)的文档
     fn parse_update(&mut self) -> QueryResult<Statement> {
         self.expect(Token::Update)?;
         let collection = self.parse_identifier()?;
+        Ok(Statement::Update(self.parse_update_body(collection)?))
+    }
 
+    /// # Brief
+    /// 解析 UPDATE 和 FIND AND MODIFY 集合名之后的部分
+    ///
+    /// 更新子句可以按任意顺序出现多次,每个子句可以用逗号列出多个字段。
+    fn parse_update_body(&mut self, collection: String) -> QueryResult<UpdateStatement> {
         let mut updates = Vec::new();
 
-        if self.skip_if(Token::Set) {
-            loop {
-                let field = self.parse_identifier()?;
-
-                let op = match self.peek() {
-                    Some(Token::PlusEq) => {
-                        self.next();
-                        let value = self.parse_value()?;
-                        UpdateOperation::Inc { field, value }
-                    }
-                    Some(Token::Eq) => {
-                        self.next();
-                        let value = self.parse_value()?;
-                        UpdateOperation::Set { field, value }
+        loop {
+            if self.skip_if(Token::Set) {
+                self.parse_update_list(&mut updates, |parser, field| match parser.next() {
+                    Some(Token::PlusEq) => Ok(UpdateOperation::Inc { field, value: parser.parse_value()? }),
+                    Some(Token::Eq) => Ok(UpdateOperation::Set { field, value: parser.parse_value()? }),
+                    other => Err(QueryError::Syntax(format!("Expected = or +=, got {:?}", other))),
+                })?;
+            } else if self.skip_if(Token::Unset) {
+                self.parse_update_list(&mut updates, |_, field| Ok(UpdateOperation::Unset { field }))?;
+            } else if self.skip_if(Token::Push) {
+                self.parse_update_list(&mut updates, |parser, field| {
+                    parser.expect(Token::Eq)?;
+                    Ok(UpdateOperation::Push { field, value: parser.parse_value()? })
+                })?;
+            } else if self.skip_if(Token::Pull) {
+                self.parse_update_list(&mut updates, |parser, field| {
+                    parser.expect(Token::Eq)?;
+                    Ok(UpdateOperation::Pull { field, value: parser.parse_value()? })
+                })?;
+            } else if self.skip_word("ADDTOSET") {
+                self.parse_update_list(&mut updates, |parser, field| {
+                    parser.expect(Token::Eq)?;
+                    Ok(UpdateOperation::AddToSet { field, value: parser.parse_value()? })
+                })?;
+            } else if self.skip_word("PULLALL") {
+                self.parse_update_list(&mut updates, |parser, field| {
+                    parser.expect(Token::Eq)?;
+                    match parser.parse_value()? {
+                        BomlValue::Array(values) => Ok(UpdateOperation::PullAll { field, values }),
+                        other => Err(QueryError::Syntax(format!(
+                            "PULLALL expects an array, got {}",
+                            other.type_name()
+                        ))),
                     }
-                    _ => {
-                        self.expect(Token::Eq)?;
-                        let value = self.parse_value()?;
-                        UpdateOperation::Set { field, value }
+                })?;
+            } else if self.skip_word("POP") {
+                loop {
+                    let first = match self.next() {
+                        Some(Token::First) => true,
+                        Some(Token::Last) => false,
+                        other => {
+                            return Err(QueryError::Syntax(format!("Expected FIRST or LAST, got {:?}", other)))
+                        }
+                    };
+                    let field = self.parse_update_path()?;
+                    updates.push(UpdateOperation::Pop { field, first });
+                    if !self.skip_if(Token::Comma) {
+                        break;
                     }
-                };
-
-                updates.push(op);
-
-                if !self.skip_if(Token::Comma) {
-                    break;
-                }
-            }
-        }
-
-        if self.skip_if(Token::Unset) {
-            loop {
-                let field = self.parse_identifier()?;
-                updates.push(UpdateOperation::Unset { field });
-                if !self.skip_if(Token::Comma) {
-                    break;
                 }
+            } else if self.skip_word("RENAME") {
+                self.parse_update_list(&mut updates, |parser, from| {
+                    parser.expect(Token::To)?;
+                    Ok(UpdateOperation::Rename { from, to: parser.parse_update_path()? })
+                })?;
+            } else {
+                break;
             }
         }
 
-        if self.skip_if(Token::Push) {
-            let field = self.parse_identifier()?;
-            self.expect(Token::Eq)?;
-            let value = self.parse_value()?;
-            updates.push(UpdateOperation::Push { field, value });
-        }
-
         let filter = if self.skip_if(Token::Where) {
             Some(self.parse_expression()?)
         } else {
             None
         };
+        let (sort, limit) = self.parse_order_and_limit()?;
+        let (returning, return_old) = self.parse_update_returning()?;
 
-        Ok(Statement::Update(UpdateStatement {
+        Ok(UpdateStatement {
             collection,
             filter,
             updates,
             upsert: false,
             multi: true,
-        }))
+            sort,
+            limit,
+            returning,
+            return_old,
+        })
+    }
+
+    /// # Brief
+    /// 解析逗号分隔的更新项,每项以字段路径开头
+    ///
+    /// # Arguments
+    /// * `updates` - 输出列表
+    /// * `parse_rest` - 解析字段路径之后的部分
+    fn parse_update_list(
+        &mut self,
+        updates: &mut Vec<UpdateOperation>,
+        mut parse_rest: impl FnMut(&mut Self, String) -> QueryResult<UpdateOperation>,
+    ) -> QueryResult<()> {
+        loop {
+            let field = self.parse_update_path()?;
+            updates.push(parse_rest(self, field)?);
+            if !self.skip_if(Token::Comma) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// # Brief
+    /// 解析更新的目标字段路径
+    ///
+    /// 语法: field[.sub | .<下标> | .$]...,如 `items.0.qty`、`items.$.qty`
+    fn parse_update_path(&mut self) -> QueryResult<String> {
+        let mut path = self.parse_identifier()?;
+        while self.skip_if(Token::Dot) {
+            let segment = match self.peek() {
+                Some(Token::Dollar) => {
+                    self.next();
+                    "$".to_string()
+                }
+                _ => self.parse_path_segment()?,
+            };
+            path = format!("{}.{}", path, segment);
+        }
+        Ok(path)
+    }
+
+    /// # Brief
+    /// 解析字段路径中 `.` 之后的一段: 标识符或数组下标
+    fn parse_path_segment(&mut self) -> QueryResult<String> {
+        match self.peek() {
+            Some(Token::Integer(index)) if *index >= 0 => {
+                let index = *index;
+                self.next();
+                Ok(index.to_string())
+            }
+            _ => self.parse_identifier(),
+        }
     }
 
     /// # Brief
     /// 解析 DELETE 语句
     ///
-    /// 语法: DELETE FROM <collection> [WHERE expr]
+    /// 语法: DELETE FROM <collection> [OLDER THAN <duration>] [WHERE expr] [ORDER BY fields] [LIMIT n] [RETURNING fields]
+    /// - OLDER THAN: 只删除创建时间早于 now - duration 的文档,没有 WHERE 和 LIMIT 时按键范围整段删除
+    /// - ORDER BY / LIMIT: 按顺序只删除前 n 个匹配的文档
+    /// - RETURNING * | fields: 返回被删除的文档
     fn parse_delete(&mut self) -> QueryResult<Statement> {
         self.expect(Token::Delete)?;
         self.expect(Token::From)?;
         let collection = self.parse_identifier()?;
 
+        let older_than_secs = if self.skip_word("OLDER") {
+            self.expect_word("THAN")?;
+            Some(self.parse_duration()?)
+        } else {
+            None
+        };
+
         let filter = if self.skip_if(Token::Where) {
             Some(self.parse_expression()?)
         } else {
             None
         };
+        let (sort, limit) = self.parse_order_and_limit()?;
+        let returning = self.parse_returning()?;
 
         Ok(Statement::Delete(DeleteStatement {
             collection,
             filter,
             multi: true,
+            older_than_secs,
+            sort,
+            limit,
+            returning,
         }))
     }
 
+    /// # Brief
+    /// 解析 UPDATE / DELETE 末尾可选的 ORDER BY 和 LIMIT 子句
+    ///
+    /// # Returns
+    /// (排序字段, 文档数上限)
+    fn parse_order_and_limit(&mut self) -> QueryResult<(Option<Vec<SortField>>, Option<u64>)> {
+        let sort = if self.skip_if(Token::Order) {
+            self.expect(Token::By)?;
+            Some(self.parse_sort_fields()?)
+        } else {
+            None
+        };
+        let limit = if self.skip_if(Token::Limit) {
+            Some(self.parse_integer()? as u64)
+        } else {
+            None
+        };
+        Ok((sort, limit))
+    }
+
+    /// # Brief
+    /// 解析写入语句末尾可选的 RETURNING 子句
+    ///
+    /// 语法: RETURNING * | RETURNING field1, field2, ...
+    fn parse_returning(&mut self) -> QueryResult<Option<Returning>> {
+        if !self.skip_word("RETURNING") {
+            return Ok(None);
+        }
+        if self.skip_if(Token::Star) {
+            return Ok(Some(Returning::All));
+        }
+        Ok(Some(Returning::Fields(self.parse_field_list()?)))
+    }
+
+    /// # Brief
+    /// 解析 UPDATE 的 RETURNING 子句
+    ///
+    /// 语法: RETURNING [OLD | NEW] * | fields。OLD / NEW 后面还有 `*` 或字段名时才是修饰词,
+    /// 否则按字段名处理(如 `RETURNING old, name`)
+    ///
+    /// # Returns
+    /// (RETURNING 子句, 是否返回更新前的文档)
+    fn parse_update_returning(&mut self) -> QueryResult<(Option<Returning>, bool)> {
+        if !self.skip_word("RETURNING") {
+            return Ok((None, false));
+        }
+        let mut return_old = false;
+        if let Some(Token::Identifier(word)) = self.peek() {
+            let old = word.eq_ignore_ascii_case("OLD");
+            if (old || word.eq_ignore_ascii_case("NEW"))
+                && matches!(self.peek_second(), Some(Token::Star | Token::Identifier(_) | Token::QuotedIdentifier(_)))
+            {
+                self.next();
+                return_old = old;
+            }
+        }
+        if self.skip_if(Token::Star) {
+            return Ok((Some(Returning::All), return_old));
+        }
+        Ok((Some(Returning::Fields(self.parse_field_list()?)), return_old))
+    }
+
     /// # Brief
     /// 解析 AGGREGATE 语句
     ///
@@ -619,12 +1522,33 @@ impl<'a> Parser<'a> {
             pipeline.push(stage);
         }
 
+        let batch_size = if self.skip_word("BATCH") {
+            Some(self.parse_batch_size()?)
+        } else {
+            None
+        };
+
         Ok(Statement::Aggregate(AggregateStatement {
             collection,
             pipeline,
+            batch_size,
         }))
     }
 
+    /// # Brief
+    /// 解析 BATCH 之后的 `SIZE <n>`
+    ///
+    /// # Returns
+    /// 每批文档数,必须为正整数
+    fn parse_batch_size(&mut self) -> QueryResult<u32> {
+        self.expect_word("SIZE")?;
+        let size = self.parse_integer()?;
+        if size <= 0 || size > u32::MAX as i64 {
+            return Err(QueryError::Syntax(format!("Invalid batch size: {}", size)));
+        }
+        Ok(size as u32)
+    }
+
     /// # Brief
     /// 解析单个聚合管道阶段
     ///
@@ -635,7 +1559,20 @@ impl<'a> Parser<'a> {
     /// - LIMIT/SKIP: 分页
     /// - PROJECT: 投影
     /// - UNWIND: 展开数组
+    /// - GRAPH LOOKUP: 集合内递归关联
+    /// - FACET: 多个子管道
+    /// - BUCKET / BUCKET AUTO: 分桶统计
     fn parse_aggregate_stage(&mut self) -> QueryResult<AggregateStage> {
+        if self.skip_word("GRAPH") {
+            self.expect(Token::Lookup)?;
+            return self.parse_graph_lookup();
+        }
+        if self.skip_word("FACET") {
+            return self.parse_facet();
+        }
+        if self.skip_word("BUCKET") {
+            return self.parse_bucket();
+        }
         match self.peek() {
             Some(Token::Match) => {
                 self.next();
@@ -646,30 +1583,8 @@ impl<'a> Parser<'a> {
                 self.next();
                 self.expect(Token::By)?;
 
-                let mut by = Vec::new();
-                by.push(self.parse_identifier()?);
-                while self.skip_if(Token::Comma) {
-                    by.push(self.parse_identifier()?);
-                }
-
-                let mut accumulators = Vec::new();
-                if self.skip_if(Token::As) {
-                    self.expect(Token::LBrace)?;
-                    loop {
-                        let name = self.parse_identifier()?;
-                        self.expect(Token::Colon)?;
-                        let (function, field) = self.parse_aggregate_function()?;
-                        accumulators.push(Accumulator {
-                            name,
-                            function,
-                            field,
-                        });
-                        if !self.skip_if(Token::Comma) {
-                            break;
-                        }
-                    }
-                    self.expect(Token::RBrace)?;
-                }
+                let by = self.parse_field_list()?;
+                let accumulators = self.parse_accumulators()?;
 
                 Ok(AggregateStage::Group { by, accumulators })
             }
@@ -705,11 +1620,158 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// # Brief
+    /// 解析可选的 `AS {name: FUNC(field), ...}` 累加器列表
+    fn parse_accumulators(&mut self) -> QueryResult<Vec<Accumulator>> {
+        let mut accumulators = Vec::new();
+        if self.skip_if(Token::As) {
+            self.expect(Token::LBrace)?;
+            loop {
+                let name = self.parse_identifier()?;
+                self.expect(Token::Colon)?;
+                let (function, field) = self.parse_aggregate_function()?;
+                accumulators.push(Accumulator {
+                    name,
+                    function,
+                    field,
+                });
+                if !self.skip_if(Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(Token::RBrace)?;
+        }
+        Ok(accumulators)
+    }
+
+    /// # Brief
+    /// 解析 BUCKET 之后的部分
+    ///
+    /// 语法: `<field> BOUNDARIES [<v>, <v>, ...] [DEFAULT <v>] [AS {...}]` 或
+    /// `AUTO <field> BUCKETS <n> [AS {...}]`
+    fn parse_bucket(&mut self) -> QueryResult<AggregateStage> {
+        if self.skip_word("AUTO") {
+            let group_by = self.parse_identifier()?;
+            self.expect_word("BUCKETS")?;
+            let buckets = self.parse_integer()?;
+            if buckets < 1 || buckets > u32::MAX as i64 {
+                return Err(QueryError::Syntax(format!("Invalid bucket count: {}", buckets)));
+            }
+            let output = self.parse_accumulators()?;
+            return Ok(AggregateStage::BucketAuto(BucketAuto {
+                group_by,
+                buckets: buckets as u32,
+                output,
+            }));
+        }
+
+        let group_by = self.parse_identifier()?;
+        self.expect_word("BOUNDARIES")?;
+        let boundaries = self.parse_array_literal()?;
+        if boundaries.len() < 2 {
+            return Err(QueryError::Syntax("BUCKET requires at least two boundaries".to_string()));
+        }
+        let default = if self.skip_word("DEFAULT") {
+            Some(self.parse_value()?)
+        } else {
+            None
+        };
+        let output = self.parse_accumulators()?;
+        Ok(AggregateStage::Bucket(Bucket {
+            group_by,
+            boundaries,
+            default,
+            output,
+        }))
+    }
+
+    /// # Brief
+    /// 解析 GRAPH LOOKUP 之后的部分
+    ///
+    /// 语法: `<from> START WITH <field> CONNECT FROM <field> TO <field> AS <field>
+    /// [MAX DEPTH <n>] [DEPTH FIELD <field>]`
+    fn parse_graph_lookup(&mut self) -> QueryResult<AggregateStage> {
+        let from = self.parse_identifier()?;
+        self.expect_word("START")?;
+        self.expect(Token::With)?;
+        let start_with = self.parse_identifier()?;
+        self.expect_word("CONNECT")?;
+        self.expect(Token::From)?;
+        let connect_from = self.parse_identifier()?;
+        self.expect(Token::To)?;
+        let connect_to = self.parse_identifier()?;
+        self.expect(Token::As)?;
+        let as_field = self.parse_identifier()?;
+
+        let max_depth = if self.skip_if(Token::Max) {
+            self.expect_word("DEPTH")?;
+            let depth = self.parse_integer()?;
+            if depth < 0 || depth > u32::MAX as i64 {
+                return Err(QueryError::Syntax(format!("Invalid max depth: {}", depth)));
+            }
+            Some(depth as u32)
+        } else {
+            None
+        };
+        let depth_field = if self.skip_word("DEPTH") {
+            self.expect_word("FIELD")?;
+            Some(self.parse_identifier()?)
+        } else {
+            None
+        };
+
+        Ok(AggregateStage::GraphLookup(GraphLookup {
+            from,
+            start_with,
+            connect_from,
+            connect_to,
+            as_field,
+            max_depth,
+            depth_field,
+        }))
+    }
+
+    /// # Brief
+    /// 解析 FACET 之后的部分
+    ///
+    /// 语法: `<name> (<stage> [| <stage>]...) [, <name> (...)]...`,子管道可以为空,
+    /// 不能再包含 FACET
+    fn parse_facet(&mut self) -> QueryResult<AggregateStage> {
+        let mut facets: Vec<Facet> = Vec::new();
+        loop {
+            let name = self.parse_identifier()?;
+            if facets.iter().any(|facet| facet.name == name) {
+                return Err(QueryError::Syntax(format!("Duplicate facet name: {}", name)));
+            }
+            self.expect(Token::LParen)?;
+            let mut pipeline = Vec::new();
+            if self.peek() != Some(&Token::RParen) {
+                loop {
+                    let stage = self.parse_aggregate_stage()?;
+                    if matches!(stage, AggregateStage::Facet(_)) {
+                        return Err(QueryError::Syntax("FACET cannot be nested".to_string()));
+                    }
+                    pipeline.push(stage);
+                    if !self.skip_if(Token::Pipe) {
+                        break;
+                    }
+                }
+            }
+            self.expect(Token::RParen)?;
+            facets.push(Facet { name, pipeline });
+            if !self.skip_if(Token::Comma) {
+                break;
+            }
+        }
+        Ok(AggregateStage::Facet(facets))
+    }
+
     /// # Brief
     /// 解析聚合函数
     ///
     /// 语法: FUNCTION(field)
-    /// 支持的函数: COUNT, SUM, AVG, MIN, MAX, FIRST, LAST
+    /// 支持的函数: COUNT, SUM, AVG, MIN, MAX, FIRST, LAST,
+    /// 其他函数名解析为自定义累加器,执行时在注册表中查找
     ///
     /// # Returns
     /// (聚合函数类型, 可选的字段名)
@@ -743,6 +1805,7 @@ impl<'a> Parser<'a> {
                 self.next();
                 AggregateFunction::Last
             }
+            Some(Token::Identifier(_)) => AggregateFunction::Custom(self.parse_identifier()?.to_lowercase()),
             _ => return Err(QueryError::Syntax("Expected aggregate function".to_string())),
         };
 
@@ -796,11 +1859,16 @@ impl<'a> Parser<'a> {
     }
 
     /// # Brief
-    /// 解析 ALTER USER 语句
+    /// 解析 ALTER 语句
     ///
-    /// 语法: ALTER USER <username> PASSWORD <new_password>
+    /// 语法:
+    /// - ALTER USER <username> [PASSWORD <new_password>] [ADD ROLE r1, ...] [DROP ROLE r1, ...]
+    /// - ALTER COLLECTION <name> SET track_types = true|false [, strict_types = true|false]
     fn parse_alter(&mut self) -> QueryResult<Statement> {
         self.expect(Token::Alter)?;
+        if self.skip_if(Token::Collection) {
+            return self.parse_alter_collection();
+        }
         self.expect(Token::User)?;
         let username = self.parse_string_literal("username")?;
 
@@ -808,16 +1876,88 @@ impl<'a> Parser<'a> {
         let mut add_roles = None;
         let mut remove_roles = None;
 
-        if self.skip_if(Token::Password) {
-            password = Some(self.parse_string_literal("password")?);
+        if self.skip_if(Token::Password) {
+            password = Some(self.parse_string_literal("password")?);
+        }
+        if self.skip_word("add") {
+            self.expect(Token::Role)?;
+            add_roles = Some(self.parse_role_list()?);
+        }
+        if self.skip_if(Token::Drop) {
+            self.expect(Token::Role)?;
+            remove_roles = Some(self.parse_role_list()?);
+        }
+
+        Ok(Statement::AlterUser(AlterUserStatement {
+            username,
+            password,
+            add_roles,
+            remove_roles,
+        }))
+    }
+
+    /// # Brief
+    /// 解析逗号分隔的角色名列表
+    fn parse_role_list(&mut self) -> QueryResult<Vec<String>> {
+        let mut roles = vec![self.parse_identifier()?];
+        while self.skip_if(Token::Comma) {
+            roles.push(self.parse_identifier()?);
+        }
+        Ok(roles)
+    }
+
+    /// # Brief
+    /// 解析 ALTER COLLECTION 语句
+    ///
+    /// 语法:
+    /// - ALTER COLLECTION <name> SET track_types = true, strict_types = false
+    /// - ALTER COLLECTION <name> EXPIRE AFTER FIELD 'expires_at'
+    /// - ALTER COLLECTION <name> EXPIRE OFF
+    fn parse_alter_collection(&mut self) -> QueryResult<Statement> {
+        let collection = self.parse_identifier()?;
+        let mut stmt = AlterCollectionStatement {
+            collection,
+            track_types: None,
+            strict_types: None,
+            expire: None,
+        };
+
+        if self.skip_word("EXPIRE") {
+            stmt.expire = Some(if self.skip_word("OFF") {
+                ExpireSetting::Off
+            } else {
+                self.expect_word("AFTER")?;
+                self.expect_word("FIELD")?;
+                ExpireSetting::AfterField(self.parse_string_literal("field")?)
+            });
+            return Ok(Statement::AlterCollection(stmt));
+        }
+
+        self.expect(Token::Set)?;
+        loop {
+            let option = self.parse_identifier()?;
+            self.expect(Token::Eq)?;
+            let value = match self.next() {
+                Some(Token::True) => true,
+                Some(Token::False) => false,
+                other => {
+                    return Err(QueryError::Syntax(format!(
+                        "Expected true or false for {}, got {:?}",
+                        option, other
+                    )))
+                }
+            };
+            match option.to_ascii_lowercase().as_str() {
+                "track_types" => stmt.track_types = Some(value),
+                "strict_types" => stmt.strict_types = Some(value),
+                _ => return Err(QueryError::Syntax(format!("Unknown collection option: {}", option))),
+            }
+            if !self.skip_if(Token::Comma) {
+                break;
+            }
         }
 
-        Ok(Statement::AlterUser(AlterUserStatement {
-            username,
-            password,
-            add_roles,
-            remove_roles,
-        }))
+        Ok(Statement::AlterCollection(stmt))
     }
 
     /// # Brief
@@ -1096,8 +2236,16 @@ impl<'a> Parser<'a> {
     /// - 文档字面量: {field1: value1, field2: value2, ...}
     /// - 字段引用: field 或 field.subfield
     /// - 函数调用: function(args)
+    /// - 自定义函数调用: CALL FUNCTION name(args),参数 `doc` 表示当前文档
     /// - EXISTS(field): 字段存在性检查
     fn parse_primary_expression(&mut self) -> QueryResult<Expression> {
+        if matches!(self.peek(), Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("call"))
+            && matches!(self.peek_second(), Some(Token::Identifier(w)) if w.eq_ignore_ascii_case("function"))
+        {
+            self.next();
+            self.next();
+            return self.parse_call_function();
+        }
         match self.peek() {
             Some(Token::LParen) => {
                 self.next();
@@ -1134,6 +2282,11 @@ impl<'a> Parser<'a> {
             Some(Token::Identifier(_)) | Some(Token::QuotedIdentifier(_)) => {
                 let name = self.parse_identifier()?;
 
+                if is_typed_literal(&name) && self.skip_if(Token::LParen) {
+                    let value = self.parse_string_literal(&name)?;
+                    self.expect(Token::RParen)?;
+                    return Ok(Expression::Literal(typed_literal(&name, &value)?));
+                }
                 if self.skip_if(Token::LParen) {
                     let mut args = Vec::new();
                     if self.peek() != Some(&Token::RParen) {
@@ -1150,12 +2303,13 @@ impl<'a> Parser<'a> {
                 } else {
                     let mut path = name;
                     while self.skip_if(Token::Dot) {
-                        let next = self.parse_identifier()?;
+                        let next = self.parse_path_segment()?;
                         path = format!("{}.{}", path, next);
                     }
                     Ok(Expression::Field(path))
                 }
             }
+            Some(Token::Dollar) | Some(Token::Colon) => Ok(Expression::Literal(self.parse_parameter()?)),
             Some(Token::Exists) => {
                 self.next();
                 self.expect(Token::LParen)?;
@@ -1170,6 +2324,32 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// # Brief
+    /// 解析 CALL FUNCTION 之后的函数调用
+    ///
+    /// 语法: CALL FUNCTION name([doc | expr], ...)
+    /// - 单独的 `doc` 参数传入当前文档,`doc.field` 仍是字段引用
+    fn parse_call_function(&mut self) -> QueryResult<Expression> {
+        let function = self.parse_identifier()?;
+        self.expect(Token::LParen)?;
+        let mut args = Vec::new();
+        while self.peek() != Some(&Token::RParen) {
+            let is_doc = matches!(self.peek(), Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("doc"))
+                && matches!(self.peek_second(), Some(Token::Comma) | Some(Token::RParen));
+            if is_doc {
+                self.next();
+                args.push(Expression::CurrentDocument);
+            } else {
+                args.push(self.parse_expression()?);
+            }
+            if !self.skip_if(Token::Comma) {
+                break;
+            }
+        }
+        self.expect(Token::RParen)?;
+        Ok(Expression::Call { function, args })
+    }
+
     /// # Brief
     /// 解析 BOML 值
     ///
@@ -1177,17 +2357,37 @@ impl<'a> Parser<'a> {
     /// - 基本类型: 整数, 浮点数, 字符串, 布尔值, null
     /// - 数组: [value1, value2, ...]
     /// - 文档: {field1: value1, field2: value2, ...}
+    /// - 序列取号: NEXTVAL('name'),转换为 `{"$nextval": "name"}` 占位文档
+    /// - 类型构造: ISODate('...')、ObjectId('...')、UUID('...')
+    /// - 参数占位符: `$1`、`:name`
     ///
     /// # Returns
     /// BomlValue 实例
     fn parse_value(&mut self) -> QueryResult<BomlValue> {
+        if matches!(self.peek(), Some(Token::Dollar) | Some(Token::Colon)) {
+            return self.parse_parameter();
+        }
         match self.next() {
+            Some(Token::Identifier(s)) if is_typed_literal(&s) => {
+                self.expect(Token::LParen)?;
+                let value = self.parse_string_literal(&s)?;
+                self.expect(Token::RParen)?;
+                typed_literal(&s, &value)
+            }
             Some(Token::Integer(n)) => Ok(BomlValue::Int64(n)),
             Some(Token::Float(n)) => Ok(BomlValue::Float64(n)),
             Some(Token::String(s)) => Ok(BomlValue::String(CompactString::from(s))),
             Some(Token::True) => Ok(BomlValue::Boolean(true)),
             Some(Token::False) => Ok(BomlValue::Boolean(false)),
             Some(Token::Null) => Ok(BomlValue::Null),
+            Some(Token::Identifier(s)) if s.eq_ignore_ascii_case("nextval") => {
+                self.expect(Token::LParen)?;
+                let name = self.parse_string_literal("sequence name")?;
+                self.expect(Token::RParen)?;
+                let mut doc = IndexMap::new();
+                doc.insert(CompactString::from(NEXTVAL_KEY), BomlValue::String(CompactString::from(name)));
+                Ok(BomlValue::Document(doc))
+            }
             Some(Token::LBracket) => {
                 let mut arr = Vec::new();
                 if self.peek() != Some(&Token::RBracket) {
@@ -1222,6 +2422,35 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// # Brief
+    /// 解析参数占位符,转换为 `{"$param": ...}` 占位文档
+    ///
+    /// 语法: `$` 加从 1 开始的编号,或 `:` 加参数名
+    fn parse_parameter(&mut self) -> QueryResult<BomlValue> {
+        let start = self.tokens.peek().map_or(self.input.len(), |(_, span)| span.start);
+        let parameter = match self.next() {
+            Some(Token::Dollar) => match self.next() {
+                Some(Token::Integer(n)) if n >= 1 => Parameter::Positional(n as usize),
+                _ => return Err(QueryError::Syntax("Expected parameter number starting at 1 after $".to_string())),
+            },
+            Some(Token::Colon) => {
+                // 参数名可以与关键字同名,取原始文本
+                self.next();
+                let name = &self.input[self.furthest.clone()];
+                let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid {
+                    return Err(QueryError::Syntax("Expected parameter name after :".to_string()));
+                }
+                Parameter::Named(name.to_string())
+            }
+            _ => return Err(QueryError::Syntax("Expected parameter".to_string())),
+        };
+        let value = parameter.placeholder();
+        self.parameters.push((parameter, start..self.furthest.end));
+        Ok(value)
+    }
+
     /// # Brief
     /// 解析表达式列表
     ///
@@ -1290,17 +2519,29 @@ impl<'a> Parser<'a> {
     /// # Brief
     /// 解析字段列表
     ///
-    /// 语法: field1, field2, field3, ...
-    /// 用于 SELECT 子句。
+    /// 语法: field1, field2.sub, items.0.name, ...
+    /// 用于 SELECT、RETURNING 和 GROUP BY 子句。
     fn parse_field_list(&mut self) -> QueryResult<Vec<String>> {
         let mut fields = Vec::new();
-        fields.push(self.parse_identifier()?);
+        fields.push(self.parse_field_path()?);
         while self.skip_if(Token::Comma) {
-            fields.push(self.parse_identifier()?);
+            fields.push(self.parse_field_path()?);
         }
         Ok(fields)
     }
 
+    /// # Brief
+    /// 解析字段路径
+    ///
+    /// 语法: field[.sub | .<下标>]...,如 `address.city`、`items.0.name`
+    fn parse_field_path(&mut self) -> QueryResult<String> {
+        let mut path = self.parse_identifier()?;
+        while self.skip_if(Token::Dot) {
+            path = format!("{}.{}", path, self.parse_path_segment()?);
+        }
+        Ok(path)
+    }
+
     /// # Brief
     /// 解析排序字段列表
     ///
@@ -1310,7 +2551,7 @@ impl<'a> Parser<'a> {
     fn parse_sort_fields(&mut self) -> QueryResult<Vec<SortField>> {
         let mut fields = Vec::new();
         loop {
-            let field = self.parse_identifier()?;
+            let field = self.parse_field_path()?;
             let order = if self.skip_if(Token::Desc) {
                 SortOrder::Descending
             } else {
@@ -1328,15 +2569,21 @@ impl<'a> Parser<'a> {
     /// # Brief
     /// 解析投影字段列表
     ///
-    /// 语法: field1, field2, field3, ...
-    /// 用于聚合管道的 PROJECT 阶段。
+    /// 语法: field1, alias: expression, ...
+    /// 用于聚合管道的 PROJECT 阶段,`alias: expression` 为计算字段,
+    /// 如 `score: CALL FUNCTION score(doc)`。
     fn parse_project_fields(&mut self) -> QueryResult<Vec<ProjectField>> {
         let mut fields = Vec::new();
         loop {
             let name = self.parse_identifier()?;
+            let expression = if self.skip_if(Token::Colon) {
+                Some(self.parse_expression()?)
+            } else {
+                None
+            };
             fields.push(ProjectField {
                 name,
-                expression: None,
+                expression,
                 include: true,
             });
             if !self.skip_if(Token::Comma) {
@@ -1359,6 +2606,56 @@ impl<'a> Parser<'a> {
             _ => Err(QueryError::Syntax("Expected integer".to_string())),
         }
     }
+
+    /// # Brief
+    /// 解析可带负号的整数
+    fn parse_signed_integer(&mut self) -> QueryResult<i64> {
+        if self.skip_if(Token::Minus) {
+            Ok(-self.parse_integer()?)
+        } else {
+            self.parse_integer()
+        }
+    }
+}
+
+/// 是否为类型字面量构造器(ISODate、ObjectId、UUID)
+fn is_typed_literal(name: &str) -> bool {
+    ["isodate", "objectid", "uuid"].iter().any(|c| name.eq_ignore_ascii_case(c))
+}
+
+/// # Brief
+/// 把类型字面量构造器的字符串参数转换为对应的 BomlValue
+///
+/// ISODate 接受 RFC 3339 时间(`2024-01-01T00:00:00Z`)、不带时区的时间(按 UTC)
+/// 或只有日期(`2024-01-01`,当天零点 UTC)。
+///
+/// # Arguments
+/// * `constructor` - 构造器名称(大小写不敏感)
+/// * `value` - 字符串参数
+fn typed_literal(constructor: &str, value: &str) -> QueryResult<BomlValue> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        QueryError::Syntax(format!("Invalid {} literal '{}': {}", constructor, value, e))
+    };
+    match constructor.to_ascii_lowercase().as_str() {
+        "isodate" => {
+            let datetime = chrono::DateTime::parse_from_rfc3339(value)
+                .map(|dt| dt.with_timezone(&chrono::Utc))
+                .or_else(|_| {
+                    chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f").map(|dt| dt.and_utc())
+                })
+                .or_else(|_| {
+                    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+                })
+                .map_err(|e| invalid(&e))?;
+            Ok(BomlValue::DateTime(datetime))
+        }
+        "objectid" => mikudb_common::ObjectId::from_hex(value)
+            .map(BomlValue::ObjectId)
+            .map_err(|e| invalid(&e)),
+        "uuid" => uuid::Uuid::parse_str(value).map(BomlValue::Uuid).map_err(|e| invalid(&e)),
+        _ => Err(QueryError::Syntax(format!("Unknown literal constructor: {}", constructor))),
+    }
 }
 
 #[cfg(test)]
@@ -1375,6 +2672,112 @@ mod tests {
     fn test_parse_insert() {
         let stmt = Parser::parse(r#"INSERT INTO users {"name": "test", "age": 25}"#).unwrap();
         assert!(matches!(stmt, Statement::Insert(_)));
+
+        match Parser::parse("INSERT INTO archive FIND events WHERE ts < '2024-01-01' BATCH SIZE 500").unwrap() {
+            Statement::InsertSelect(insert) => {
+                assert_eq!(insert.collection, "archive");
+                assert_eq!(insert.source.collection, "events");
+                assert!(insert.source.filter.is_some());
+                assert_eq!(insert.source.batch_size, Some(500));
+            }
+            other => panic!("Expected InsertSelect, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_returning() {
+        match Parser::parse(r#"INSERT INTO users [{"name": "a"}, {"name": "b"}] RETURNING _id, created_at"#).unwrap() {
+            Statement::Insert(insert) => assert_eq!(
+                insert.returning,
+                Some(Returning::Fields(vec!["_id".to_string(), "created_at".to_string()]))
+            ),
+            other => panic!("Expected Insert, got {:?}", other),
+        }
+
+        match Parser::parse("UPDATE users SET n += 1 WHERE n > 0 LIMIT 5 RETURNING *").unwrap() {
+            Statement::Update(update) => {
+                assert_eq!(update.limit, Some(5));
+                assert_eq!(update.returning, Some(Returning::All));
+            }
+            other => panic!("Expected Update, got {:?}", other),
+        }
+
+        match Parser::parse("DELETE FROM users WHERE n = 0").unwrap() {
+            Statement::Delete(delete) => assert_eq!(delete.returning, None),
+            other => panic!("Expected Delete, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_find_and_modify() {
+        match Parser::parse("FIND AND MODIFY jobs SET state = 'running' WHERE state = 'queued' ORDER BY priority DESC").unwrap() {
+            Statement::Update(update) => {
+                assert_eq!(update.collection, "jobs");
+                assert_eq!(update.limit, Some(1));
+                assert_eq!(update.returning, Some(Returning::All));
+                assert!(!update.return_old);
+            }
+            other => panic!("Expected Update, got {:?}", other),
+        }
+
+        match Parser::parse("UPDATE counters SET n += 1 WHERE name = 'a' LIMIT 1 RETURNING OLD n").unwrap() {
+            Statement::Update(update) => {
+                assert_eq!(update.returning, Some(Returning::Fields(vec!["n".to_string()])));
+                assert!(update.return_old);
+            }
+            other => panic!("Expected Update, got {:?}", other),
+        }
+
+        // 后面没有字段时 old 是字段名
+        match Parser::parse("UPDATE users SET a = 1 RETURNING old, new").unwrap() {
+            Statement::Update(update) => {
+                assert_eq!(update.returning, Some(Returning::Fields(vec!["old".to_string(), "new".to_string()])));
+                assert!(!update.return_old);
+            }
+            other => panic!("Expected Update, got {:?}", other),
+        }
+
+        assert!(Parser::parse("FIND AND MODIFY jobs SET a = 1 LIMIT 2").is_err());
+    }
+
+    #[test]
+    fn test_parse_call_function() {
+        match Parser::parse("FIND users WHERE CALL FUNCTION score(doc, doc.age) > 0.5").unwrap() {
+            Statement::Find(find) => match find.filter {
+                Some(Expression::Binary { left, .. }) => assert_eq!(
+                    *left,
+                    Expression::Call {
+                        function: "score".to_string(),
+                        args: vec![Expression::CurrentDocument, Expression::Field("doc.age".to_string())],
+                    }
+                ),
+                other => panic!("Expected comparison, got {:?}", other),
+            },
+            other => panic!("Expected Find, got {:?}", other),
+        }
+
+        match Parser::parse("AGGREGATE users | PROJECT name, s: CALL FUNCTION score(doc)").unwrap() {
+            Statement::Aggregate(agg) => match &agg.pipeline[0] {
+                AggregateStage::Project(fields) => {
+                    assert!(fields[0].expression.is_none());
+                    assert_eq!(fields[1].name, "s");
+                    assert!(fields[1].expression.is_some());
+                }
+                other => panic!("Expected Project, got {:?}", other),
+            },
+            other => panic!("Expected Aggregate, got {:?}", other),
+        }
+
+        assert_eq!(
+            Parser::parse("CREATE FUNCTION score WASM 'AGFzbQEAAAA='").unwrap(),
+            Statement::CreateFunction(CreateFunctionStatement {
+                name: "score".to_string(),
+                module: b"\0asm\x01\0\0\0".to_vec(),
+            })
+        );
+        assert!(Parser::parse("CREATE FUNCTION score WASM 'not base64!'").is_err());
+        assert_eq!(Parser::parse("DROP FUNCTION score").unwrap(), Statement::DropFunction("score".to_string()));
+        assert_eq!(Parser::parse("SHOW FUNCTIONS").unwrap(), Statement::ShowFunctions);
     }
 
     #[test]
@@ -1383,10 +2786,59 @@ mod tests {
         assert!(matches!(stmt, Statement::Update(_)));
     }
 
+    #[test]
+    fn test_parse_array_updates() {
+        let query = "UPDATE orders SET items.$.qty = 5, items.0.price += 1 ADDTOSET tags = 'x', tags = 'y' \
+                     POP LAST queue PULLALL scores = [1, 2] PUSH log = 'a' WHERE items.sku = 'a'";
+        match Parser::parse(query).unwrap() {
+            Statement::Update(update) => assert_eq!(
+                update.updates,
+                vec![
+                    UpdateOperation::Set { field: "items.$.qty".to_string(), value: BomlValue::Int64(5) },
+                    UpdateOperation::Inc { field: "items.0.price".to_string(), value: BomlValue::Int64(1) },
+                    UpdateOperation::AddToSet { field: "tags".to_string(), value: BomlValue::String("x".into()) },
+                    UpdateOperation::AddToSet { field: "tags".to_string(), value: BomlValue::String("y".into()) },
+                    UpdateOperation::Pop { field: "queue".to_string(), first: false },
+                    UpdateOperation::PullAll {
+                        field: "scores".to_string(),
+                        values: vec![BomlValue::Int64(1), BomlValue::Int64(2)],
+                    },
+                    UpdateOperation::Push { field: "log".to_string(), value: BomlValue::String("a".into()) },
+                ]
+            ),
+            other => panic!("Expected Update, got {:?}", other),
+        }
+        assert!(Parser::parse("UPDATE t PULLALL tags = 'x'").is_err());
+        assert!(Parser::parse("UPDATE t POP tags").is_err());
+    }
+
     #[test]
     fn test_parse_delete() {
         let stmt = Parser::parse("DELETE FROM users WHERE active = false").unwrap();
         assert!(matches!(stmt, Statement::Delete(_)));
+
+        match Parser::parse("DELETE FROM logs OLDER THAN 7d").unwrap() {
+            Statement::Delete(delete) => {
+                assert_eq!(delete.older_than_secs, Some(7 * 86400));
+                assert!(delete.filter.is_none());
+            }
+            other => panic!("Expected Delete, got {:?}", other),
+        }
+        match Parser::parse("DELETE FROM logs OLDER THAN 12h WHERE level = 'debug'").unwrap() {
+            Statement::Delete(delete) => {
+                assert_eq!(delete.older_than_secs, Some(12 * 3600));
+                assert!(delete.filter.is_some());
+            }
+            other => panic!("Expected Delete, got {:?}", other),
+        }        match Parser::parse("DELETE FROM logs WHERE level = 'debug' ORDER BY ts DESC LIMIT 10000").unwrap() {
+            Statement::Delete(delete) => {
+                let sort = delete.sort.unwrap();
+                assert_eq!(sort[0].field, "ts");
+                assert_eq!(sort[0].order, SortOrder::Descending);
+                assert_eq!(delete.limit, Some(10000));
+            }
+            other => panic!("Expected Delete, got {:?}", other),
+        }
     }
 
     #[test]
@@ -1397,12 +2849,348 @@ mod tests {
         assert!(matches!(stmt, Statement::Aggregate(_)));
     }
 
+    #[test]
+    fn test_parse_facet() {
+        let stmt = Parser::parse(
+            "AGGREGATE products | MATCH stock > 0 \
+             | FACET by_category (GROUP BY category AS {n: COUNT()} | SORT n DESC), top (SORT score DESC | LIMIT 10), all () \
+             | PROJECT by_category",
+        )
+        .unwrap();
+        let Statement::Aggregate(agg) = stmt else {
+            panic!("Expected Aggregate statement");
+        };
+        assert_eq!(agg.pipeline.len(), 3);
+        let AggregateStage::Facet(facets) = &agg.pipeline[1] else {
+            panic!("Expected FACET stage");
+        };
+        let names: Vec<_> = facets.iter().map(|facet| facet.name.as_str()).collect();
+        assert_eq!(names, vec!["by_category", "top", "all"]);
+        assert_eq!(facets[1].pipeline, vec![
+            AggregateStage::Sort(vec![SortField { field: "score".to_string(), order: SortOrder::Descending }]),
+            AggregateStage::Limit(10),
+        ]);
+        assert!(facets[2].pipeline.is_empty());
+
+        assert!(Parser::parse("AGGREGATE p | FACET a (LIMIT 1), a (LIMIT 2)").is_err());
+        assert!(Parser::parse("AGGREGATE p | FACET a (FACET b (LIMIT 1))").is_err());
+        assert!(Parser::parse("AGGREGATE p | FACET a LIMIT 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_bucket() {
+        let stmt = Parser::parse(
+            "AGGREGATE products | BUCKET price BOUNDARIES [0, 10.5, 100] DEFAULT 'other' AS {n: COUNT(), total: SUM(price)} \
+             | BUCKET AUTO n BUCKETS 3",
+        )
+        .unwrap();
+        let Statement::Aggregate(agg) = stmt else {
+            panic!("Expected Aggregate statement");
+        };
+        let AggregateStage::Bucket(bucket) = &agg.pipeline[0] else {
+            panic!("Expected BUCKET stage");
+        };
+        assert_eq!(bucket.group_by, "price");
+        assert_eq!(bucket.boundaries, vec![BomlValue::Int64(0), BomlValue::Float64(10.5), BomlValue::Int64(100)]);
+        assert_eq!(bucket.default, Some(BomlValue::String("other".into())));
+        assert_eq!(bucket.output.len(), 2);
+        assert_eq!(agg.pipeline[1], AggregateStage::BucketAuto(BucketAuto {
+            group_by: "n".to_string(),
+            buckets: 3,
+            output: Vec::new(),
+        }));
+
+        assert!(Parser::parse("AGGREGATE p | BUCKET price BOUNDARIES [0]").is_err());
+        assert!(Parser::parse("AGGREGATE p | BUCKET AUTO price BUCKETS 0").is_err());
+        assert!(Parser::parse("AGGREGATE p | BUCKET price").is_err());
+    }
+
     #[test]
     fn test_parse_create_index() {
         let stmt = Parser::parse("CREATE UNIQUE INDEX idx_email ON users (email ASC)").unwrap();
         assert!(matches!(stmt, Statement::CreateIndex(_)));
     }
 
+    #[test]
+    fn test_parse_create_text_index_options() {
+        let stmt = Parser::parse(
+            "CREATE TEXT INDEX idx_body ON articles (body) WITH TOKENIZER 'jieba' STOPWORDS ('的', '了'), STEMMER 'english'",
+        ).unwrap();
+        match stmt {
+            Statement::CreateIndex(index) => {
+                assert_eq!(index.index_type, IndexType::Text);
+                let options = index.text_options.unwrap();
+                assert_eq!(options.tokenizer.as_deref(), Some("jieba"));
+                assert_eq!(
+                    options.stopwords,
+                    Some(TextStopWords::Custom(vec!["的".to_string(), "了".to_string()]))
+                );
+                assert_eq!(options.stemmer.as_deref(), Some("english"));
+            }
+            _ => panic!("Expected CreateIndex statement"),
+        }
+
+        assert!(Parser::parse("CREATE INDEX idx ON users (name) WITH TOKENIZER 'jieba'").is_err());
+    }
+
+    #[test]
+    fn test_parse_create_index_key_options() {
+        match Parser::parse("CREATE INDEX idx_bio ON users (bio) WITH MAX_KEY_SIZE 2KB OVERSIZED TRUNCATE").unwrap() {
+            Statement::CreateIndex(index) => {
+                assert_eq!(index.max_key_size, Some(2048));
+                assert_eq!(index.oversized_keys, OversizedKeyPolicy::Truncate);
+            }
+            _ => panic!("Expected CreateIndex statement"),
+        }
+        match Parser::parse("CREATE INDEX idx_bio ON users (bio) WITH OVERSIZED SKIP").unwrap() {
+            Statement::CreateIndex(index) => {
+                assert_eq!(index.max_key_size, None);
+                assert_eq!(index.oversized_keys, OversizedKeyPolicy::Skip);
+            }
+            _ => panic!("Expected CreateIndex statement"),
+        }
+
+        assert!(Parser::parse("CREATE INDEX idx_bio ON users (bio) WITH OVERSIZED DROP").is_err());
+    }
+
+    #[test]
+    fn test_parse_archive() {
+        let stmt = Parser::parse(
+            "ARCHIVE documents OLDER THAN 90d OF events TO events_archive COMPRESSION zstd",
+        ).unwrap();
+        match stmt {
+            Statement::Archive(archive) => {
+                assert_eq!(archive.collection, "events");
+                assert_eq!(archive.archive, "events_archive");
+                assert_eq!(archive.older_than_secs, 90 * 86400);
+                assert_eq!(archive.compression, CompressionType::Zstd);
+                assert_eq!(archive.path, None);
+            }
+            _ => panic!("Expected Archive statement"),
+        }
+
+        let stmt = Parser::parse("ARCHIVE OLDER THAN 12h OF logs TO cold_logs PATH '/mnt/cold'").unwrap();
+        match stmt {
+            Statement::Archive(archive) => {
+                assert_eq!(archive.older_than_secs, 12 * 3600);
+                assert_eq!(archive.path.as_deref(), Some("/mnt/cold"));
+            }
+            _ => panic!("Expected Archive statement"),
+        }
+
+        assert!(Parser::parse("ARCHIVE OLDER THAN 3y OF logs TO cold_logs").is_err());
+    }
+
+    #[test]
+    fn test_parse_backup_restore() {
+        assert_eq!(
+            Parser::parse("BACKUP TO '/var/backups/mikudb'").unwrap(),
+            Statement::Backup(BackupStatement {
+                path: "/var/backups/mikudb".to_string(),
+                include_system: true,
+                snapshot: false,
+            })
+        );
+        assert_eq!(
+            Parser::parse("backup to '/tmp/b' without system").unwrap(),
+            Statement::Backup(BackupStatement { path: "/tmp/b".to_string(), include_system: false, snapshot: false })
+        );
+        assert_eq!(
+            Parser::parse("BACKUP SNAPSHOT TO '/tmp/s'").unwrap(),
+            Statement::Backup(BackupStatement { path: "/tmp/s".to_string(), include_system: true, snapshot: true })
+        );
+        assert!(Parser::parse("BACKUP SNAPSHOT TO '/tmp/s' WITHOUT SYSTEM").is_err());
+        assert_eq!(
+            Parser::parse("RESTORE FROM '/tmp/b' METADATA ONLY").unwrap(),
+            Statement::Restore(RestoreStatement { path: "/tmp/b".to_string(), metadata_only: true })
+        );
+        assert!(Parser::parse("RESTORE FROM /tmp/b").is_err());
+    }
+
+    #[test]
+    fn test_parse_admin_log_level() {
+        let stmt = Parser::parse("ADMIN SET LOG LEVEL debug TARGET mikudb_storage").unwrap();
+        assert_eq!(
+            stmt,
+            Statement::SetLogLevel(SetLogLevelStatement {
+                level: "debug".to_string(),
+                target: Some("mikudb_storage".to_string()),
+            })
+        );
+
+        let stmt = Parser::parse("admin set log level WARN").unwrap();
+        assert_eq!(
+            stmt,
+            Statement::SetLogLevel(SetLogLevelStatement { level: "warn".to_string(), target: None })
+        );
+
+        assert_eq!(Parser::parse("ADMIN RESET LOG LEVEL").unwrap(), Statement::ResetLogLevel);
+        assert!(Parser::parse("ADMIN SET LOG debug").is_err());
+    }
+
+    #[test]
+    fn test_parse_cluster() {
+        assert_eq!(Parser::parse("CLUSTER INIT").unwrap(), Statement::ClusterInit);
+        assert_eq!(
+            Parser::parse("cluster join 'mikudb://db1:3941'").unwrap(),
+            Statement::ClusterJoin("mikudb://db1:3941".to_string())
+        );
+        assert!(Parser::parse("CLUSTER JOIN db1").is_err());
+        assert!(Parser::parse("CLUSTER LEAVE").is_err());
+    }
+
+    #[test]
+    fn test_parse_typed_literals() {
+        let stmt = Parser::parse(
+            "INSERT INTO t {at: ISODate('2024-01-01T08:30:00Z'), day: isodate('2024-01-02'), \
+             ref: ObjectId('65a1b2c3d4e5f60718293a4b'), key: UUID('67e55044-10b1-426f-9247-bb680e5fe0c8'), mask: 0xFF}",
+        )
+        .unwrap();
+        let doc = match stmt {
+            Statement::Insert(insert) => insert.documents.into_iter().next().unwrap(),
+            other => panic!("unexpected statement: {:?}", other),
+        };
+        let field = |name: &str| match &doc {
+            BomlValue::Document(fields) => fields.get(name).cloned().unwrap(),
+            _ => unreachable!(),
+        };
+        assert!(matches!(field("at"), BomlValue::DateTime(at) if at.to_rfc3339() == "2024-01-01T08:30:00+00:00"));
+        assert!(matches!(field("day"), BomlValue::DateTime(day) if day.to_rfc3339() == "2024-01-02T00:00:00+00:00"));
+        assert!(matches!(field("ref"), BomlValue::ObjectId(id) if id.to_hex() == "65a1b2c3d4e5f60718293a4b"));
+        assert!(matches!(field("key"), BomlValue::Uuid(_)));
+        assert_eq!(field("mask"), BomlValue::Int64(255));
+
+        match Parser::parse("FIND t WHERE created >= ISODate('2024-01-01') -- since new year").unwrap() {
+            Statement::Find(find) => match find.filter {
+                Some(Expression::Binary { right, .. }) => {
+                    assert!(matches!(*right, Expression::Literal(BomlValue::DateTime(_))))
+                }
+                other => panic!("unexpected filter: {:?}", other),
+            },
+            other => panic!("unexpected statement: {:?}", other),
+        }
+        assert!(Parser::parse("FIND t WHERE id = ObjectId('xyz')").is_err());
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        assert_eq!(Parser::parse("STATS users").unwrap(), Statement::Stats("users".to_string()));
+        assert_eq!(
+            Parser::parse("reset stats users").unwrap(),
+            Statement::ResetStats(Some("users".to_string()))
+        );
+        assert_eq!(Parser::parse("RESET STATS").unwrap(), Statement::ResetStats(None));
+        assert_eq!(Parser::parse("ANALYZE users").unwrap(), Statement::Analyze("users".to_string()));
+        assert!(Parser::parse("ANALYZE").is_err());
+        assert!(Parser::parse("STATS").is_err());
+        assert!(Parser::parse("RESET users").is_err());
+    }
+
+    #[test]
+    fn test_parse_compact() {
+        assert_eq!(Parser::parse("COMPACT logs").unwrap(), Statement::Compact("logs".to_string()));
+        assert_eq!(Parser::parse("compact logs;").unwrap(), Statement::Compact("logs".to_string()));
+        assert!(Parser::parse("COMPACT").is_err());
+    }
+
+    #[test]
+    fn test_parse_set_variable() {
+        assert_eq!(
+            Parser::parse("SET Return_Stats = true").unwrap(),
+            Statement::SetVariable(SetVariableStatement {
+                name: "return_stats".to_string(),
+                value: BomlValue::Boolean(true),
+            })
+        );
+        assert!(Parser::parse("SET return_stats").is_err());
+    }
+
+    #[test]
+    fn test_parse_dry_run() {
+        let stmt = Parser::parse("DRY RUN UPDATE users SET active = false WHERE age > 60").unwrap();
+        match stmt {
+            Statement::DryRun(inner) => assert!(matches!(*inner, Statement::Update(_))),
+            _ => panic!("Expected DryRun statement"),
+        }
+
+        let stmt = Parser::parse("dry run DELETE FROM users WHERE active = false").unwrap();
+        match stmt {
+            Statement::DryRun(inner) => assert!(matches!(*inner, Statement::Delete(_))),
+            _ => panic!("Expected DryRun statement"),
+        }
+
+        assert!(Parser::parse("DRY RUN FIND users").is_err());
+    }
+
+    #[test]
+    fn test_parse_alter_collection() {
+        let stmt = Parser::parse("ALTER COLLECTION users SET track_types = true, strict_types = false").unwrap();
+        assert_eq!(
+            stmt,
+            Statement::AlterCollection(AlterCollectionStatement {
+                collection: "users".to_string(),
+                track_types: Some(true),
+                strict_types: Some(false),
+                expire: None,
+            })
+        );
+        match Parser::parse("ALTER COLLECTION sessions EXPIRE AFTER FIELD 'expires_at'").unwrap() {
+            Statement::AlterCollection(stmt) => {
+                assert_eq!(stmt.expire, Some(ExpireSetting::AfterField("expires_at".to_string())))
+            }
+            other => panic!("Expected AlterCollection, got {:?}", other),
+        }
+        match Parser::parse("ALTER COLLECTION sessions EXPIRE OFF").unwrap() {
+            Statement::AlterCollection(stmt) => assert_eq!(stmt.expire, Some(ExpireSetting::Off)),
+            other => panic!("Expected AlterCollection, got {:?}", other),
+        }
+        assert!(Parser::parse("ALTER COLLECTION sessions EXPIRE AFTER 'expires_at'").is_err());
+        assert_eq!(Parser::parse("SHOW SCHEMA ON users").unwrap(), Statement::ShowSchema("users".to_string()));
+        assert!(Parser::parse("ALTER COLLECTION users SET validate = true").is_err());
+    }
+
+    #[test]
+    fn test_parse_show_advisor() {
+        assert_eq!(Parser::parse("SHOW ADVISOR").unwrap(), Statement::ShowAdvisor(None));
+        assert_eq!(
+            Parser::parse("SHOW ADVISOR ON orders").unwrap(),
+            Statement::ShowAdvisor(Some("orders".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_find_with_archive() {
+        let stmt = Parser::parse("FIND events WHERE level = 'error' WITH ARCHIVE LIMIT 10").unwrap();
+        match stmt {
+            Statement::Find(find) => {
+                assert!(find.include_archive);
+                assert_eq!(find.limit, Some(10));
+            }
+            _ => panic!("Expected Find statement"),
+        }
+    }
+
+    #[test]
+    fn test_parse_batch_size() {
+        let stmt = Parser::parse("FIND events WHERE level = 'error' BATCH SIZE 500 LIMIT 10").unwrap();
+        match stmt {
+            Statement::Find(find) => {
+                assert_eq!(find.batch_size, Some(500));
+                assert_eq!(find.limit, Some(10));
+            }
+            _ => panic!("Expected Find statement"),
+        }
+
+        let stmt = Parser::parse("AGGREGATE orders | MATCH state = 'paid' BATCH SIZE 100").unwrap();
+        match stmt {
+            Statement::Aggregate(agg) => assert_eq!(agg.batch_size, Some(100)),
+            _ => panic!("Expected Aggregate statement"),
+        }
+
+        assert!(Parser::parse("FIND events BATCH SIZE 0").is_err());
+        assert!(Parser::parse("FIND events BATCH 10").is_err());
+    }
+
     #[test]
     fn test_parse_create_user_string() {
         let stmt = Parser::parse(r#"CREATE USER "alice" WITH PASSWORD "secret""#).unwrap();
@@ -1414,4 +3202,122 @@ mod tests {
             _ => panic!("Expected CreateUser statement"),
         }
     }
+
+    #[test]
+    fn test_parse_capped_collection() {
+        match Parser::parse("CREATE COLLECTION logs (CAPPED SIZE 10MB MAX 1000)").unwrap() {
+            Statement::CreateCollection(create) => {
+                assert_eq!(create.name, "logs");
+                let capped = create.capped.unwrap();
+                assert_eq!(capped.max_size, Some(10 * 1024 * 1024));
+                assert_eq!(capped.max_documents, Some(1000));
+            }
+            _ => panic!("Expected CreateCollection statement"),
+        }
+        match Parser::parse("CREATE COLLECTION oplog (CAPPED MAX 50)").unwrap() {
+            Statement::CreateCollection(create) => {
+                assert_eq!(create.capped.unwrap().max_size, None);
+            }
+            _ => panic!("Expected CreateCollection statement"),
+        }
+        assert!(Parser::parse("CREATE COLLECTION logs (CAPPED)").is_err());
+        assert!(Parser::parse("CREATE COLLECTION logs (CAPPED SIZE 0)").is_err());
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        match Parser::parse("CREATE SEQUENCE order_no START 1000").unwrap() {
+            Statement::CreateSequence(seq) => {
+                assert_eq!(seq.name, "order_no");
+                assert_eq!(seq.start, 1000);
+                assert_eq!(seq.increment, 1);
+            }
+            _ => panic!("Expected CreateSequence statement"),
+        }
+        match Parser::parse("CREATE SEQUENCE countdown START WITH 10 INCREMENT BY -1").unwrap() {
+            Statement::CreateSequence(seq) => {
+                assert_eq!(seq.start, 10);
+                assert_eq!(seq.increment, -1);
+            }
+            _ => panic!("Expected CreateSequence statement"),
+        }
+        assert!(Parser::parse("CREATE SEQUENCE bad INCREMENT 0").is_err());
+        assert_eq!(
+            Parser::parse("DROP SEQUENCE order_no").unwrap(),
+            Statement::DropSequence("order_no".to_string())
+        );
+        assert_eq!(Parser::parse("SHOW SEQUENCES").unwrap(), Statement::ShowSequences);
+
+        match Parser::parse("INSERT INTO orders {no: NEXTVAL('order_no'), item: 'book'}").unwrap() {
+            Statement::Insert(insert) => {
+                let no = insert.documents[0].get("no").unwrap();
+                assert_eq!(no.get(NEXTVAL_KEY).and_then(|v| v.as_str()), Some("order_no"));
+            }
+            _ => panic!("Expected Insert statement"),
+        }
+    }
+
+    #[test]
+    fn test_parse_rollup() {
+        let stmt = Parser::parse(
+            "CREATE ROLLUP daily_sales ON sales GROUP BY DATE_TRUNC('day', ts) AS day, product AS {total: SUM(amount), n: COUNT()}",
+        )
+        .unwrap();
+        match stmt {
+            Statement::CreateRollup(rollup) => {
+                assert_eq!(rollup.source, "sales");
+                assert_eq!(rollup.group_by[0], RollupKey {
+                    field: "day".to_string(),
+                    path: "ts".to_string(),
+                    bucket: Some(TimeBucket::Day),
+                });
+                assert_eq!(rollup.group_by[1].field, "product");
+                assert_eq!(rollup.aggregates[1].function, RollupFunction::Count);
+            }
+            _ => panic!("Expected CreateRollup statement"),
+        }
+        assert!(Parser::parse("CREATE ROLLUP r ON s GROUP BY DATE_TRUNC('fortnight', ts) AS {n: COUNT()}").is_err());
+        assert!(Parser::parse("CREATE ROLLUP r ON s AS {first: FIRST(a)}").is_err());
+        assert_eq!(Parser::parse("DROP ROLLUP daily_sales").unwrap(), Statement::DropRollup("daily_sales".to_string()));
+        assert_eq!(Parser::parse("SHOW ROLLUPS").unwrap(), Statement::ShowRollups);
+    }
+
+    #[test]
+    fn test_parse_manifest_and_user_roles() {
+        assert_eq!(Parser::parse("SHOW MANIFEST").unwrap(), Statement::ShowManifest);
+        assert_eq!(
+            Parser::parse("ALTER USER 'alice' ADD ROLE readWrite, backup DROP ROLE read").unwrap(),
+            Statement::AlterUser(AlterUserStatement {
+                username: "alice".to_string(),
+                password: None,
+                add_roles: Some(vec!["readWrite".to_string(), "backup".to_string()]),
+                remove_roles: Some(vec!["read".to_string()]),
+            })
+        );
+        assert!(Parser::parse("ALTER USER 'alice' ADD readWrite").is_err());
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        match Parser::parse("FIND users\nWHERE age >") {
+            Err(QueryError::Parse { position, line, column, token, .. }) => {
+                assert_eq!(position, 22);
+                assert_eq!((line, column), (2, 12));
+                assert!(token.is_none());
+            }
+            other => panic!("Expected Parse error, got {:?}", other),
+        }
+
+        match Parser::parse("DELETE FORM users") {
+            Err(QueryError::Parse { line, column, token, .. }) => {
+                assert_eq!((line, column), (1, 8));
+                assert_eq!(token.as_deref(), Some("FORM"));
+            }
+            other => panic!("Expected Parse error, got {:?}", other),
+        }
+
+        let err = Parser::parse("FIND users WHERE name = \"abc").unwrap_err();
+        assert!(err.to_string().contains("column 25"), "{}", err);
+        assert!(err.to_string().contains("Unterminated string literal"), "{}", err);
+    }
 }
@@ -4,7 +4,10 @@
 //! - 将 MQL 语句转换为执行计划树
 //! - 查询优化:过滤器下推、连续过滤器合并、LIMIT 下推
 //! - 成本估算:估算执行计划的代价
-//! - 索引选择(待实现)
+//! - 索引选择:从 WHERE 中 AND 连接的条件选出可用索引,等值条件优先哈希索引,
+//!   范围条件使用 BTree 索引(可带复合索引前缀字段的等值条件)
+//! - 代价优化:集合有 ANALYZE 统计信息时按估算行数在索引扫描和全表扫描之间选择,
+//!   并把 AND 条件按选择率从低到高排列
 //!
 //! 执行计划节点类型:
 //! - Scan: 全表扫描
@@ -18,6 +21,17 @@
 
 use crate::ast::*;
 use crate::{QueryError, QueryResult};
+use mikudb_boml::BomlValue;
+use mikudb_common::ObjectId;
+use mikudb_storage::{IndexDefinition, IndexOrder, IndexType as StorageIndexType, KeyEncoding, OversizedKeyPolicy};
+use std::collections::HashMap;
+
+mod stats;
+
+pub use stats::{CollectionStats, FieldStats, Histogram, HistogramKind, StatsCollector};
+
+/// 通过索引读取一个文档相对顺序扫描一个文档的代价
+const INDEX_ROW_COST: f64 = 4.0;
 
 /// 查询执行计划
 ///
@@ -30,6 +44,41 @@ pub struct QueryPlan {
     pub estimated_cost: f64,
 }
 
+impl QueryPlan {
+    /// # Brief
+    /// 获取计划树最底层的数据访问节点(Scan 或 IndexScan)
+    pub fn access_path(&self) -> &PlanNode {
+        let mut node = &self.root;
+        loop {
+            match node {
+                PlanNode::Filter { input, .. }
+                | PlanNode::Project { input, .. }
+                | PlanNode::Sort { input, .. }
+                | PlanNode::Limit { input, .. }
+                | PlanNode::Skip { input, .. }
+                | PlanNode::HashAggregate { input, .. } => node = input,
+                _ => return node,
+            }
+        }
+    }
+}
+
+/// 索引访问方式
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexLookup {
+    /// 等值查找(`IndexEngine::lookup`),给出索引全部字段的值
+    Eq(Vec<BomlValue>),
+    /// 范围扫描(`IndexEngine::range_query`),边界按索引顺序给出,可以只包含复合索引的前几个字段
+    Range {
+        /// 起点,None 表示从头开始
+        start: Option<Vec<BomlValue>>,
+        /// 终点,None 表示到末尾
+        end: Option<Vec<BomlValue>>,
+        /// 是否包含与边界相等的键
+        inclusive: bool,
+    },
+}
+
 /// 执行计划节点
 ///
 /// 表示查询执行计划树的节点,每个节点代表一种关系代数操作。
@@ -48,7 +97,9 @@ pub enum PlanNode {
         collection: String,
         /// 索引名称
         index_name: String,
-        /// 可选的过滤器
+        /// 索引访问方式
+        lookup: IndexLookup,
+        /// 回表后重新应用的过滤器(索引读取是乐观的,只用于缩小候选范围)
         filter: Option<Expression>,
     },
     /// 过滤器
@@ -222,7 +273,7 @@ impl QueryPlanner {
     /// 执行计划(包含成本估算)
     pub fn plan(&self, stmt: &Statement) -> QueryResult<QueryPlan> {
         match stmt {
-            Statement::Find(find) => self.plan_find(find),
+            Statement::Find(find) => self.plan_find(find, &[], None),
             Statement::Aggregate(agg) => self.plan_aggregate(agg),
             _ => Err(QueryError::Internal("Statement not supported for planning".to_string())),
         }
@@ -232,7 +283,7 @@ impl QueryPlanner {
     /// 为 FIND 语句生成执行计划
     ///
     /// 执行计划构建顺序:
-    /// 1. Scan 节点(带下推过滤器)
+    /// 1. IndexScan 节点(有可用索引时)或 Scan 节点(带下推过滤器)
     /// 2. Sort 节点
     /// 3. Skip 节点
     /// 4. Limit 节点
@@ -240,18 +291,44 @@ impl QueryPlanner {
     ///
     /// # Arguments
     /// * `find` - FIND 语句
+    /// * `indexes` - 集合上的索引
+    /// * `stats` - 集合的统计信息,没有时按规则选择索引
     ///
     /// # Returns
     /// 执行计划
-    fn plan_find(&self, find: &FindStatement) -> QueryResult<QueryPlan> {
+    pub fn plan_find(
+        &self,
+        find: &FindStatement,
+        indexes: &[IndexDefinition],
+        stats: Option<&CollectionStats>,
+    ) -> QueryResult<QueryPlan> {
         let mut node = PlanNode::Scan {
             collection: find.collection.clone(),
             filter: None,
         };
 
+        let index = find
+            .filter
+            .as_ref()
+            .filter(|_| self.use_index_optimization)
+            .and_then(|filter| choose_index(filter, indexes, stats));
+
+        // 选择率低的条件先求值,尽早排除不匹配的文档
+        let filter = match (&find.filter, stats) {
+            (Some(filter), Some(stats)) => Some(order_conjuncts(filter, stats)),
+            (filter, _) => filter.clone(),
+        };
+
         // 过滤器下推优化:将过滤条件下推到 Scan 节点
-        if let Some(filter) = &find.filter {
-            if self.push_down_filters {
+        if let Some(filter) = &filter {
+            if let Some((index_name, lookup)) = index {
+                node = PlanNode::IndexScan {
+                    collection: find.collection.clone(),
+                    index_name,
+                    lookup,
+                    filter: Some(filter.clone()),
+                };
+            } else if self.push_down_filters {
                 node = PlanNode::Scan {
                     collection: find.collection.clone(),
                     filter: Some(filter.clone()),
@@ -572,8 +649,12 @@ impl PlanNode {
                 }
                 Ok(())
             }
-            PlanNode::IndexScan { collection, index_name, .. } => {
-                write!(f, "{}IndexScan({}, {})", prefix, collection, index_name)
+            PlanNode::IndexScan { collection, index_name, lookup, .. } => {
+                let kind = match lookup {
+                    IndexLookup::Eq(_) => "eq",
+                    IndexLookup::Range { .. } => "range",
+                };
+                write!(f, "{}IndexScan({}, {}) [{}]", prefix, collection, index_name, kind)
             }
             PlanNode::Filter { input, .. } => {
                 writeln!(f, "{}Filter", prefix)?;
@@ -608,3 +689,396 @@ impl PlanNode {
         }
     }
 }
+
+/// 单个字段上可以交给索引的条件
+#[derive(Debug, Default)]
+struct FieldBounds {
+    eq: Option<BomlValue>,
+    /// 下界和是否包含边界
+    lower: Option<(BomlValue, bool)>,
+    /// 上界和是否包含边界
+    upper: Option<(BomlValue, bool)>,
+}
+
+/// 索引评分: (等值字段数, 是否有范围条件, 是否唯一, 是否哈希)
+type IndexScore = (usize, bool, bool, bool);
+
+/// # Brief
+/// 为过滤条件选择索引
+///
+/// 只考虑顶层 AND 连接的 `字段 比较 字面量` 和 BETWEEN 条件。候选按以下顺序比较:
+/// 等值字段数、是否还有范围条件、是否唯一索引、是否哈希索引,
+/// 因此全字段等值时哈希索引优先,范围条件只能使用 BTree 索引。
+/// 早期键编码的索引、稀疏索引和 SKIP 超长键策略索引的部分匹配不参与选择,
+/// SKIP 策略索引的等值查找只在键未超长时使用。
+///
+///
+/// 有统计信息时改为比较估算代价: 索引扫描为估算行数乘以 `INDEX_ROW_COST`,
+/// 全表扫描为集合文档数,所有索引都不比全表扫描便宜时不使用索引。
+///
+/// # Arguments
+/// * `filter` - WHERE 条件
+/// * `indexes` - 集合上的索引
+/// * `stats` - 集合的统计信息
+///
+/// # Returns
+/// 选中的索引名称和访问方式
+fn choose_index(
+    filter: &Expression,
+    indexes: &[IndexDefinition],
+    stats: Option<&CollectionStats>,
+) -> Option<(String, IndexLookup)> {
+    let mut bounds = HashMap::new();
+    collect_bounds(filter, &mut bounds);
+    if bounds.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(f64, IndexScore, String, IndexLookup)> = None;
+    for definition in indexes {
+        if definition.key_encoding != KeyEncoding::Memcomparable {
+            continue;
+        }
+        let Some((score, lookup)) = index_lookup(definition, &bounds) else {
+            continue;
+        };
+        let cost = stats.map_or(0.0, |stats| index_cost(definition, score, &bounds, stats));
+        let better = best.as_ref().map_or(true, |(best_cost, best_score, ..)| {
+            cost < *best_cost || (cost == *best_cost && score > *best_score)
+        });
+        if better {
+            best = Some((cost, score, definition.name.clone(), lookup));
+        }
+    }
+    if let (Some((cost, ..)), Some(stats)) = (&best, stats) {
+        if *cost >= stats.row_count as f64 {
+            return None;
+        }
+    }
+    best.map(|(_, _, name, lookup)| (name, lookup))
+}
+
+/// # Brief
+/// 按统计信息估算索引扫描的代价
+///
+/// 估算行数为集合文档数乘以索引使用的等值前缀和范围字段的选择率。
+fn index_cost(
+    definition: &IndexDefinition,
+    (eq_count, has_range, ..): IndexScore,
+    bounds: &HashMap<String, FieldBounds>,
+    stats: &CollectionStats,
+) -> f64 {
+    let used = eq_count + has_range as usize;
+    let selectivity: f64 = definition
+        .fields
+        .iter()
+        .take(used)
+        .filter_map(|field| Some((&field.path, bounds.get(&field.path)?)))
+        .map(|(path, bounds)| match &bounds.eq {
+            Some(value) => stats.eq_selectivity(path, value),
+            None => stats.range_selectivity(
+                path,
+                bounds.lower.as_ref().map(|(value, inclusive)| (value, *inclusive)),
+                bounds.upper.as_ref().map(|(value, inclusive)| (value, *inclusive)),
+            ),
+        })
+        .product();
+    stats.row_count as f64 * selectivity * INDEX_ROW_COST
+}
+
+/// # Brief
+/// 把顶层 AND 连接的条件按估算选择率从低到高重新排列
+///
+/// 选择率相同的条件保持原有顺序。
+fn order_conjuncts(filter: &Expression, stats: &CollectionStats) -> Expression {
+    fn flatten<'a>(expr: &'a Expression, out: &mut Vec<&'a Expression>) {
+        match expr {
+            Expression::Binary { left, op: BinaryOp::And, right } => {
+                flatten(left, out);
+                flatten(right, out);
+            }
+            expr => out.push(expr),
+        }
+    }
+
+    let mut conjuncts = Vec::new();
+    flatten(filter, &mut conjuncts);
+    if conjuncts.len() < 2 {
+        return filter.clone();
+    }
+    let mut scored: Vec<(f64, &Expression)> = conjuncts.into_iter().map(|c| (stats.selectivity(c), c)).collect();
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+    scored
+        .into_iter()
+        .map(|(_, c)| c.clone())
+        .reduce(Expression::and)
+        .unwrap_or_else(|| filter.clone())
+}
+
+/// # Brief
+/// 计算单个索引对条件的访问方式和评分
+fn index_lookup(
+    definition: &IndexDefinition,
+    bounds: &HashMap<String, FieldBounds>,
+) -> Option<(IndexScore, IndexLookup)> {
+    let field_bounds = |i: usize| definition.fields.get(i).and_then(|f| bounds.get(&f.path));
+
+    let prefix: Vec<BomlValue> = (0..definition.fields.len())
+        .map_while(|i| {
+            field_bounds(i)?
+                .eq
+                .clone()
+                .filter(|value| !(definition.sparse && matches!(value, BomlValue::Null)))
+        })
+        .collect();
+    let full = !prefix.is_empty() && prefix.len() == definition.fields.len();
+    let is_hash = definition.index_type == StorageIndexType::Hash;
+
+    if full {
+        // 超长键被 SKIP 的文档不在索引中
+        if !definition.accepts(&prefix) {
+            return None;
+        }
+        return Some(((prefix.len(), false, definition.unique, is_hash), IndexLookup::Eq(prefix)));
+    }
+    // 哈希索引只能全字段等值查找;稀疏索引和 SKIP 策略索引缺少部分文档
+    if definition.index_type != StorageIndexType::BTree
+        || definition.sparse
+        || definition.oversized_keys == OversizedKeyPolicy::Skip
+    {
+        return None;
+    }
+
+    let (lower, upper) = match field_bounds(prefix.len()) {
+        Some(FieldBounds { lower, upper, .. }) if lower.is_some() || upper.is_some() => (lower.clone(), upper.clone()),
+        _ if !prefix.is_empty() => (None, None),
+        _ => return None,
+    };
+    let has_range = lower.is_some() || upper.is_some();
+    let (start, end) = match definition.fields[prefix.len()].order {
+        IndexOrder::Ascending => (lower, upper),
+        IndexOrder::Descending => (upper, lower),
+    };
+
+    // 只有等值前缀的一端需要包含边界才能覆盖前缀相同的键
+    let mut needs_inclusive = false;
+    let mut any_exclusive = false;
+    let mut bound = |end: Option<(BomlValue, bool)>| -> Option<Vec<BomlValue>> {
+        match end {
+            Some((value, inclusive)) => {
+                if inclusive {
+                    needs_inclusive = true;
+                } else {
+                    any_exclusive = true;
+                }
+                let mut key = prefix.clone();
+                key.push(value);
+                Some(key)
+            }
+            None if !prefix.is_empty() => {
+                needs_inclusive = true;
+                Some(prefix.clone())
+            }
+            None => None,
+        }
+    };
+    let start = bound(start);
+    let end = bound(end);
+
+    Some((
+        (prefix.len(), has_range, false, false),
+        IndexLookup::Range {
+            start,
+            end,
+            inclusive: needs_inclusive || !any_exclusive,
+        },
+    ))
+}
+
+/// # Brief
+/// 从顶层 AND 连接的条件中收集各字段的等值和范围条件
+fn collect_bounds(expr: &Expression, bounds: &mut HashMap<String, FieldBounds>) {
+    match expr {
+        Expression::Binary { left, op: BinaryOp::And, right } => {
+            collect_bounds(left, bounds);
+            collect_bounds(right, bounds);
+        }
+        Expression::Binary { left, op, right } => {
+            let (field, value, op) = match (left.as_ref(), right.as_ref()) {
+                (Expression::Field(field), Expression::Literal(value)) => (field, value, *op),
+                (Expression::Literal(value), Expression::Field(field)) => match op {
+                    BinaryOp::Lt => (field, value, BinaryOp::Gt),
+                    BinaryOp::Le => (field, value, BinaryOp::Ge),
+                    BinaryOp::Gt => (field, value, BinaryOp::Lt),
+                    BinaryOp::Ge => (field, value, BinaryOp::Le),
+                    op => (field, value, *op),
+                },
+                _ => return,
+            };
+            match op {
+                BinaryOp::Eq if indexable_eq(value) => {
+                    bounds.entry(field.clone()).or_default().eq = Some(value.clone());
+                }
+                BinaryOp::Gt | BinaryOp::Ge if indexable_range(value) => {
+                    bounds.entry(field.clone()).or_default().lower = Some((value.clone(), op == BinaryOp::Ge));
+                }
+                BinaryOp::Lt | BinaryOp::Le if indexable_range(value) => {
+                    bounds.entry(field.clone()).or_default().upper = Some((value.clone(), op == BinaryOp::Le));
+                }
+                _ => {}
+            }
+        }
+        Expression::Between { expr, low, high } => {
+            if let (Expression::Field(field), Expression::Literal(low), Expression::Literal(high)) =
+                (expr.as_ref(), low.as_ref(), high.as_ref())
+            {
+                if indexable_range(low) && indexable_range(high) {
+                    let entry = bounds.entry(field.clone()).or_default();
+                    entry.lower = Some((low.clone(), true));
+                    entry.upper = Some((high.clone(), true));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 等值条件能否使用索引: 过滤条件认为相等的值必须有相同的索引键。
+/// 浮点数按误差比较,十六进制字符串与 ObjectId 相等,这两类不使用索引。
+fn indexable_eq(value: &BomlValue) -> bool {
+    match value {
+        BomlValue::Null | BomlValue::Boolean(_) | BomlValue::Int32(_) | BomlValue::Int64(_) => true,
+        BomlValue::String(s) => ObjectId::from_hex(s).is_err(),
+        _ => false,
+    }
+}
+
+/// 范围条件能否使用索引: 过滤条件只在这些类型之间按与索引键相同的顺序比较
+fn indexable_range(value: &BomlValue) -> bool {
+    matches!(
+        value,
+        BomlValue::Int32(_) | BomlValue::Int64(_) | BomlValue::Float64(_) | BomlValue::String(_) | BomlValue::DateTime(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+    use mikudb_storage::IndexField as StorageIndexField;
+
+    fn index(name: &str, fields: &[&str], index_type: StorageIndexType) -> IndexDefinition {
+        IndexDefinition {
+            name: name.to_string(),
+            collection: "users".to_string(),
+            fields: fields
+                .iter()
+                .map(|path| StorageIndexField { path: path.to_string(), order: IndexOrder::Ascending })
+                .collect(),
+            index_type,
+            unique: false,
+            sparse: false,
+            ttl_seconds: None,
+            key_encoding: KeyEncoding::Memcomparable,
+            max_key_size: None,
+            oversized_keys: OversizedKeyPolicy::Reject,
+        }
+    }
+
+    fn access(query: &str, indexes: &[IndexDefinition]) -> PlanNode {
+        let Statement::Find(find) = Parser::parse(query).unwrap() else {
+            panic!("not a FIND statement");
+        };
+        QueryPlanner::new().plan_find(&find, indexes, None).unwrap().access_path().clone()
+    }
+
+    #[test]
+    fn test_choose_index() {
+        let indexes = vec![
+            index("age_idx", &["age"], StorageIndexType::BTree),
+            index("name_hash", &["name"], StorageIndexType::Hash),
+            index("city_age", &["city", "age"], StorageIndexType::BTree),
+        ];
+
+        match access("FIND users WHERE name = \"miku\"", &indexes) {
+            PlanNode::IndexScan { index_name, lookup: IndexLookup::Eq(key), .. } => {
+                assert_eq!(index_name, "name_hash");
+                assert_eq!(key, vec![BomlValue::String("miku".into())]);
+            }
+            other => panic!("unexpected plan {:?}", other),
+        }
+
+        match access("FIND users WHERE city = \"tokyo\" AND age > 16", &indexes) {
+            PlanNode::IndexScan { index_name, lookup: IndexLookup::Range { .. }, .. } => assert_eq!(index_name, "city_age"),
+            other => panic!("unexpected plan {:?}", other),
+        }
+
+        // 哈希索引不支持范围查询,OR 条件不使用索引
+        assert!(matches!(access("FIND users WHERE name > \"m\"", &indexes), PlanNode::Scan { .. }));
+        assert!(matches!(
+            access("FIND users WHERE age = 1 OR age = 2", &indexes),
+            PlanNode::Scan { .. }
+        ));
+    }
+
+    #[test]
+    fn test_skip_oversized_index() {
+        let mut bio = index("bio_idx", &["bio"], StorageIndexType::BTree);
+        bio.max_key_size = Some(32);
+        bio.oversized_keys = OversizedKeyPolicy::Skip;
+        let indexes = vec![bio];
+
+        assert!(matches!(
+            access("FIND users WHERE bio = \"short\"", &indexes),
+            PlanNode::IndexScan { lookup: IndexLookup::Eq(_), .. }
+        ));
+        // 超长的键没有索引项,范围查找可能漏掉被跳过的文档
+        let long = "x".repeat(64);
+        assert!(matches!(
+            access(&format!("FIND users WHERE bio = \"{}\"", long), &indexes),
+            PlanNode::Scan { .. }
+        ));
+        assert!(matches!(access("FIND users WHERE bio > \"m\"", &indexes), PlanNode::Scan { .. }));
+    }
+
+    #[test]
+    fn test_cost_based_plan() {
+        let mut collector = StatsCollector::new();
+        for i in 0..1000i64 {
+            let mut doc = mikudb_boml::Document::new();
+            doc.insert("age", i);
+            doc.insert("kind", if i % 2 == 0 { "even" } else { "odd" });
+            collector.add(&doc);
+        }
+        let stats = collector.finish();
+        let indexes = vec![
+            index("age_idx", &["age"], StorageIndexType::BTree),
+            index("kind_idx", &["kind"], StorageIndexType::BTree),
+        ];
+        let plan = |query: &str| {
+            let Statement::Find(find) = Parser::parse(query).unwrap() else {
+                panic!("not a FIND statement");
+            };
+            QueryPlanner::new().plan_find(&find, &indexes, Some(&stats)).unwrap().access_path().clone()
+        };
+
+        // 半数文档匹配时全表扫描更便宜
+        assert!(matches!(plan("FIND users WHERE kind = \"odd\""), PlanNode::Scan { .. }));
+        assert!(matches!(plan("FIND users WHERE age >= 100"), PlanNode::Scan { .. }));
+        match plan("FIND users WHERE kind = \"odd\" AND age < 50") {
+            PlanNode::IndexScan { index_name, filter: Some(filter), .. } => {
+                assert_eq!(index_name, "age_idx");
+                // 选择率更低的 age 条件排在前面
+                let Expression::Binary { left, .. } = filter else {
+                    panic!("unexpected filter {:?}", filter);
+                };
+                assert_eq!(*left, Expression::Binary {
+                    left: Box::new(Expression::Field("age".into())),
+                    op: BinaryOp::Lt,
+                    right: Box::new(Expression::Literal(BomlValue::Int64(50))),
+                });
+            }
+            other => panic!("unexpected plan {:?}", other),
+        }
+    }
+}
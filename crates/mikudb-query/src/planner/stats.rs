@@ -0,0 +1,463 @@
+//! 集合统计信息
+//!
+//! `ANALYZE <collection>` 扫描集合,收集代价估算使用的统计信息:
+//! - 文档数
+//! - 每个字段(嵌套文档按点号路径展开)的出现次数、NULL 数和不同值个数
+//! - 数值和日期字段的等深直方图
+//!
+//! 统计信息序列化为 JSON,通过存储层保存在元数据中 (`stats:{collection}`)。
+//! 写入不会更新统计信息,数据分布变化较大后需要重新执行 ANALYZE;
+//! 没有统计信息的集合按规则选择执行计划。
+
+use crate::ast::{BinaryOp, Expression};
+use mikudb_boml::{keyenc, BomlValue, Document};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 直方图的桶数
+const HISTOGRAM_BUCKETS: usize = 32;
+/// 每个字段为直方图抽样的最大值数
+const HISTOGRAM_SAMPLE_SIZE: usize = 10_000;
+/// 每个字段精确统计的不同值个数上限,超过后按比例估算
+const DISTINCT_LIMIT: usize = 10_000;
+
+/// 无法估算时等值条件的选择率
+const DEFAULT_EQ_SELECTIVITY: f64 = 0.1;
+/// 无法估算时范围条件的选择率
+const DEFAULT_RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+/// LIKE、函数调用等其他条件的选择率
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+
+/// 集合统计信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollectionStats {
+    /// 文档数
+    pub row_count: u64,
+    /// 字段路径 -> 字段统计
+    pub fields: BTreeMap<String, FieldStats>,
+    /// 收集时间(毫秒时间戳)
+    pub analyzed_at: i64,
+}
+
+/// 单个字段的统计信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldStats {
+    /// 字段值不为 NULL 的文档数
+    pub count: u64,
+    /// 字段值为 NULL 的文档数
+    pub null_count: u64,
+    /// 不同值个数(超过统计上限时为估算值)
+    pub distinct: u64,
+    /// 数值或日期的等深直方图
+    pub histogram: Option<Histogram>,
+}
+
+/// 直方图的值类型,数值和日期分开统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistogramKind {
+    /// 数值
+    Number,
+    /// 日期(微秒时间戳)
+    DateTime,
+}
+
+/// 等深直方图
+///
+/// 相邻边界之间的值个数大致相同,桶内假设均匀分布。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Histogram {
+    /// 值类型
+    pub kind: HistogramKind,
+    /// 该类型的值的个数
+    pub count: u64,
+    /// 桶边界,第一个为最小值,最后一个为最大值
+    pub bounds: Vec<f64>,
+}
+
+impl Histogram {
+    /// # Brief
+    /// 估算小于(或小于等于) x 的值所占比例
+    ///
+    /// # Returns
+    /// 0.0 ~ 1.0
+    pub fn fraction_below(&self, x: f64, inclusive: bool) -> f64 {
+        let bounds = &self.bounds;
+        let (Some(&min), Some(&max)) = (bounds.first(), bounds.last()) else {
+            return 0.0;
+        };
+        if x < min || (x == min && !inclusive && min < max) {
+            return 0.0;
+        }
+        if x > max || (x == max && inclusive) || bounds.len() < 2 {
+            return 1.0;
+        }
+        let buckets = (bounds.len() - 1) as f64;
+        let i = bounds.partition_point(|&b| b <= x).clamp(1, bounds.len() - 1);
+        let (low, high) = (bounds[i - 1], bounds[i]);
+        let within = if high > low { (x - low) / (high - low) } else { 0.0 };
+        ((i - 1) as f64 + within) / buckets
+    }
+}
+
+/// 直方图使用的数值,不支持的类型返回 None
+fn histogram_value(value: &BomlValue) -> Option<(HistogramKind, f64)> {
+    match value {
+        BomlValue::Int32(n) => Some((HistogramKind::Number, *n as f64)),
+        BomlValue::Int64(n) => Some((HistogramKind::Number, *n as f64)),
+        BomlValue::Float32(n) => Some((HistogramKind::Number, *n as f64)),
+        BomlValue::Float64(n) if !n.is_nan() => Some((HistogramKind::Number, *n)),
+        BomlValue::DateTime(dt) => Some((HistogramKind::DateTime, dt.timestamp_micros() as f64)),
+        _ => None,
+    }
+}
+
+impl CollectionStats {
+    /// # Brief
+    /// 估算过滤条件的选择率(满足条件的文档比例)
+    ///
+    /// AND 按独立事件相乘,OR 按容斥合并;字段与常量的比较使用字段统计,
+    /// 无法估算的条件使用固定的默认选择率。
+    ///
+    /// # Arguments
+    /// * `expr` - 过滤条件
+    ///
+    /// # Returns
+    /// 0.0 ~ 1.0
+    pub fn selectivity(&self, expr: &Expression) -> f64 {
+        let s = match expr {
+            Expression::Binary { left, op: BinaryOp::And, right } => self.selectivity(left) * self.selectivity(right),
+            Expression::Binary { left, op: BinaryOp::Or, right } => {
+                let (a, b) = (self.selectivity(left), self.selectivity(right));
+                a + b - a * b
+            }
+            Expression::Binary { left, op, right } => match (left.as_ref(), right.as_ref()) {
+                (Expression::Field(field), Expression::Literal(value)) => self.compare_selectivity(field, *op, value),
+                (Expression::Literal(value), Expression::Field(field)) => {
+                    let op = match op {
+                        BinaryOp::Lt => BinaryOp::Gt,
+                        BinaryOp::Le => BinaryOp::Ge,
+                        BinaryOp::Gt => BinaryOp::Lt,
+                        BinaryOp::Ge => BinaryOp::Le,
+                        op => *op,
+                    };
+                    self.compare_selectivity(field, op, value)
+                }
+                _ => DEFAULT_SELECTIVITY,
+            },
+            Expression::Unary { op: crate::ast::UnaryOp::Not, expr } => 1.0 - self.selectivity(expr),
+            Expression::In { expr, list } => match expr.as_ref() {
+                Expression::Field(field) => list
+                    .iter()
+                    .map(|item| match item {
+                        Expression::Literal(value) => self.eq_selectivity(field, value),
+                        _ => DEFAULT_EQ_SELECTIVITY,
+                    })
+                    .sum(),
+                _ => DEFAULT_SELECTIVITY,
+            },
+            Expression::Between { expr, low, high } => match (expr.as_ref(), low.as_ref(), high.as_ref()) {
+                (Expression::Field(field), Expression::Literal(low), Expression::Literal(high)) => {
+                    self.range_selectivity(field, Some((low, true)), Some((high, true)))
+                }
+                _ => DEFAULT_RANGE_SELECTIVITY,
+            },
+            Expression::IsNull { expr, negated } => match expr.as_ref() {
+                Expression::Field(field) => {
+                    let s = self.eq_selectivity(field, &BomlValue::Null);
+                    if *negated { 1.0 - s } else { s }
+                }
+                _ => DEFAULT_SELECTIVITY,
+            },
+            Expression::Exists { field, negated } => {
+                let s = self.present_fraction(field);
+                if *negated { 1.0 - s } else { s }
+            }
+            Expression::Literal(BomlValue::Boolean(b)) => if *b { 1.0 } else { 0.0 },
+            _ => DEFAULT_SELECTIVITY,
+        };
+        s.clamp(0.0, 1.0)
+    }
+
+    /// # Brief
+    /// 估算 `field = value` 的选择率
+    pub fn eq_selectivity(&self, field: &str, value: &BomlValue) -> f64 {
+        if self.row_count == 0 {
+            return 0.0;
+        }
+        let rows = self.row_count as f64;
+        let Some(stats) = self.fields.get(field) else {
+            // 统计时没有文档包含该字段: NULL 匹配全部文档,其他值按一个文档估算
+            return if value.is_null() { 1.0 } else { 1.0 / rows };
+        };
+        if value.is_null() {
+            // 缺失字段与 NULL 相等
+            return (rows - stats.count as f64) / rows;
+        }
+        if stats.distinct == 0 {
+            return 1.0 / rows;
+        }
+        (stats.count as f64 / stats.distinct as f64 / rows).max(1.0 / rows)
+    }
+
+    /// # Brief
+    /// 估算范围条件的选择率
+    ///
+    /// # Arguments
+    /// * `field` - 字段路径
+    /// * `lower` - 下界和是否包含边界
+    /// * `upper` - 上界和是否包含边界
+    pub fn range_selectivity(
+        &self,
+        field: &str,
+        lower: Option<(&BomlValue, bool)>,
+        upper: Option<(&BomlValue, bool)>,
+    ) -> f64 {
+        if self.row_count == 0 {
+            return 0.0;
+        }
+        let histogram = self.fields.get(field).and_then(|stats| stats.histogram.as_ref());
+        let Some(histogram) = histogram else {
+            return match (lower, upper) {
+                (Some(_), Some(_)) => DEFAULT_RANGE_SELECTIVITY * DEFAULT_RANGE_SELECTIVITY * 2.0,
+                _ => DEFAULT_RANGE_SELECTIVITY,
+            };
+        };
+        let bound = |bound: Option<(&BomlValue, bool)>| match bound {
+            None => Some(None),
+            Some((value, inclusive)) => match histogram_value(value) {
+                Some((kind, x)) if kind == histogram.kind => Some(Some((x, inclusive))),
+                // 与直方图类型不同的值不可比较,范围条件不匹配任何文档
+                _ => None,
+            },
+        };
+        let (Some(lower), Some(upper)) = (bound(lower), bound(upper)) else {
+            return 0.0;
+        };
+        let below_upper = upper.map_or(1.0, |(x, inclusive)| histogram.fraction_below(x, inclusive));
+        let below_lower = lower.map_or(0.0, |(x, inclusive)| histogram.fraction_below(x, !inclusive));
+        let fraction = (below_upper - below_lower).max(0.0);
+        fraction * histogram.count as f64 / self.row_count as f64
+    }
+
+    /// 包含该字段(值不为 NULL)的文档比例
+    fn present_fraction(&self, field: &str) -> f64 {
+        match (self.fields.get(field), self.row_count) {
+            (_, 0) => 0.0,
+            (Some(stats), rows) => stats.count as f64 / rows as f64,
+            (None, _) => 0.0,
+        }
+    }
+
+    fn compare_selectivity(&self, field: &str, op: BinaryOp, value: &BomlValue) -> f64 {
+        match op {
+            BinaryOp::Eq => self.eq_selectivity(field, value),
+            BinaryOp::Ne => 1.0 - self.eq_selectivity(field, value),
+            BinaryOp::Lt => self.range_selectivity(field, None, Some((value, false))),
+            BinaryOp::Le => self.range_selectivity(field, None, Some((value, true))),
+            BinaryOp::Gt => self.range_selectivity(field, Some((value, false)), None),
+            BinaryOp::Ge => self.range_selectivity(field, Some((value, true)), None),
+            _ => DEFAULT_SELECTIVITY,
+        }
+    }
+}
+
+/// 统计信息收集器
+///
+/// 逐个接收集合中的文档,最后生成 `CollectionStats`。
+#[derive(Debug)]
+pub struct StatsCollector {
+    row_count: u64,
+    fields: HashMap<String, FieldCollector>,
+    /// 直方图蓄水池抽样使用的伪随机数状态
+    rng: u64,
+}
+
+#[derive(Debug, Default)]
+struct FieldCollector {
+    count: u64,
+    null_count: u64,
+    /// 已见过的不同值的编码,达到上限后停止记录
+    distinct: HashSet<Vec<u8>>,
+    /// 不同值个数达到上限时已见过的值个数
+    distinct_saturated_at: Option<u64>,
+    /// 直方图的值类型,取第一个可统计的值的类型
+    kind: Option<HistogramKind>,
+    /// 该类型的值的个数
+    histogram_count: u64,
+    /// 抽样的值
+    sample: Vec<f64>,
+}
+
+impl Default for StatsCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatsCollector {
+    /// # Brief
+    /// 创建收集器
+    pub fn new() -> Self {
+        Self {
+            row_count: 0,
+            fields: HashMap::new(),
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// # Brief
+    /// 统计一个文档
+    pub fn add(&mut self, doc: &Document) {
+        self.row_count += 1;
+        for (key, value) in doc.iter() {
+            self.add_value(key.to_string(), value);
+        }
+    }
+
+    fn add_value(&mut self, path: String, value: &BomlValue) {
+        if let BomlValue::Document(fields) = value {
+            for (key, nested) in fields {
+                self.add_value(format!("{}.{}", path, key), nested);
+            }
+        }
+
+        let random = self.next_random();
+        let field = self.fields.entry(path).or_default();
+        if value.is_null() {
+            field.null_count += 1;
+            return;
+        }
+        field.count += 1;
+
+        if field.distinct_saturated_at.is_none() {
+            field.distinct.insert(keyenc::encode_key(std::slice::from_ref(value)));
+            if field.distinct.len() >= DISTINCT_LIMIT {
+                field.distinct_saturated_at = Some(field.count);
+            }
+        }
+
+        if let Some((kind, x)) = histogram_value(value) {
+            if *field.kind.get_or_insert(kind) != kind {
+                return;
+            }
+            field.histogram_count += 1;
+            // 蓄水池抽样: 第 n 个值以 k/n 的概率替换样本中的一个值
+            if field.sample.len() < HISTOGRAM_SAMPLE_SIZE {
+                field.sample.push(x);
+            } else {
+                let slot = (random % field.histogram_count) as usize;
+                if slot < HISTOGRAM_SAMPLE_SIZE {
+                    field.sample[slot] = x;
+                }
+            }
+        }
+    }
+
+    /// xorshift64
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// # Brief
+    /// 生成统计信息
+    pub fn finish(self) -> CollectionStats {
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|(path, field)| {
+                let distinct = match field.distinct_saturated_at {
+                    // 假设之后的值中新值出现的比例不变
+                    Some(seen) => (field.distinct.len() as f64 * field.count as f64 / seen as f64) as u64,
+                    None => field.distinct.len() as u64,
+                };
+                let histogram = field.kind.map(|kind| Histogram {
+                    kind,
+                    count: field.histogram_count,
+                    bounds: equi_depth_bounds(field.sample),
+                });
+                let stats = FieldStats {
+                    count: field.count,
+                    null_count: field.null_count,
+                    distinct,
+                    histogram,
+                };
+                (path, stats)
+            })
+            .collect();
+        CollectionStats {
+            row_count: self.row_count,
+            fields,
+            analyzed_at: chrono::Utc::now().timestamp_millis(),
+        }
+    }
+}
+
+/// 计算等深直方图的桶边界
+fn equi_depth_bounds(mut sample: Vec<f64>) -> Vec<f64> {
+    if sample.is_empty() {
+        return Vec::new();
+    }
+    sample.sort_by(f64::total_cmp);
+    let last = sample.len() - 1;
+    let buckets = HISTOGRAM_BUCKETS.min(sample.len());
+    let mut bounds: Vec<f64> = (0..=buckets).map(|i| sample[i * last / buckets]).collect();
+    bounds.dedup();
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+    use crate::ast::Statement;
+
+    fn analyze(docs: impl IntoIterator<Item = Document>) -> CollectionStats {
+        let mut collector = StatsCollector::new();
+        for doc in docs {
+            collector.add(&doc);
+        }
+        collector.finish()
+    }
+
+    fn filter(condition: &str) -> Expression {
+        match Parser::parse(&format!("FIND t WHERE {}", condition)).unwrap() {
+            Statement::Find(find) => find.filter.unwrap(),
+            other => panic!("unexpected statement {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_collect_stats() {
+        let stats = analyze((0..1000).map(|i| {
+            let mut doc = Document::new();
+            doc.insert("age", i as i64);
+            doc.insert("kind", if i % 2 == 0 { "even" } else { "odd" });
+            if i % 10 == 0 {
+                doc.insert("note", BomlValue::Null);
+            }
+            doc.insert("meta", BomlValue::Document([("v".into(), BomlValue::Int32(1))].into_iter().collect()));
+            doc
+        }));
+
+        assert_eq!(stats.row_count, 1000);
+        assert_eq!(stats.fields["kind"].distinct, 2);
+        assert_eq!(stats.fields["age"].distinct, 1000);
+        assert_eq!(stats.fields["note"].null_count, 100);
+        assert_eq!(stats.fields["meta.v"].count, 1000);
+
+        let histogram = stats.fields["age"].histogram.as_ref().unwrap();
+        assert_eq!(histogram.bounds.first(), Some(&0.0));
+        assert_eq!(histogram.bounds.last(), Some(&999.0));
+        assert!((histogram.fraction_below(250.0, false) - 0.25).abs() < 0.01);
+
+        assert!((stats.selectivity(&filter("kind = 'odd'")) - 0.5).abs() < 1e-9);
+        assert!((stats.selectivity(&filter("age >= 900")) - 0.1).abs() < 0.01);
+        assert!((stats.selectivity(&filter("age BETWEEN 100 AND 199")) - 0.1).abs() < 0.01);
+        assert!((stats.selectivity(&filter("age < 500 AND kind = 'odd'")) - 0.25).abs() < 0.01);
+        assert_eq!(stats.selectivity(&filter("age > 'x'")), 0.0);
+        assert_eq!(stats.selectivity(&filter("age > 5000")), 0.0);
+    }
+}
@@ -0,0 +1,355 @@
+//! 预编译语句模块
+//!
+//! 带参数占位符的语句只解析一次,之后每次执行绑定不同的值:
+//! - 位置参数 `$1`、`$2`... 和命名参数 `:name`,同一语句中不能混用
+//! - 占位符可以出现在值的位置: FIND/UPDATE/DELETE 的 WHERE 条件、INSERT 文档、
+//!   UPDATE 的 SET/INC/PUSH 等操作值、AGGREGATE 的 MATCH 和 PROJECT 表达式
+//! - 绑定的值直接替换进 AST,不经过文本拼接,用户输入不会被当作 MQL 解析
+//!
+//! 执行计划依赖绑定的值(索引范围、按统计信息估算的代价),因此每次执行时重新生成;
+//! 预编译省去的是词法和语法分析。
+
+use crate::ast::*;
+use crate::parser::Parser;
+use crate::{QueryError, QueryResult};
+use mikudb_boml::BomlValue;
+use std::collections::{BTreeSet, HashMap};
+
+/// 执行时绑定的参数值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Params {
+    /// 按 `$1`、`$2`... 的顺序给出
+    Positional(Vec<BomlValue>),
+    /// 按 `:name` 的名称给出
+    Named(HashMap<String, BomlValue>),
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params::Positional(Vec::new())
+    }
+}
+
+impl Params {
+    /// # Brief
+    /// 是否没有任何参数
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Params::Positional(values) => values.is_empty(),
+            Params::Named(values) => values.is_empty(),
+        }
+    }
+}
+
+/// 预编译语句
+///
+/// 保存解析后的 AST 和占位符,`bind` 每次复制 AST 并替换占位符。
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    query: String,
+    statement: Statement,
+    /// 语句中的参数,位置参数按编号排列,命名参数按首次出现的顺序排列,不重复
+    parameters: Vec<Parameter>,
+}
+
+impl PreparedStatement {
+    /// # Brief
+    /// 解析语句并检查占位符
+    ///
+    /// # Arguments
+    /// * `query` - 可能带 `$1` 或 `:name` 占位符的 MQL 语句
+    ///
+    /// # Returns
+    /// 预编译语句;混用位置参数和命名参数、位置参数编号不连续,
+    /// 或占位符出现在不支持的位置时返回错误
+    pub fn prepare(query: &str) -> QueryResult<Self> {
+        let (mut statement, occurrences) = Parser::parse_with_parameters(query)?;
+
+        let mut positional = BTreeSet::new();
+        let mut parameters = Vec::new();
+        for parameter in &occurrences {
+            match parameter {
+                Parameter::Positional(index) => {
+                    positional.insert(*index);
+                }
+                Parameter::Named(_) if !parameters.contains(parameter) => parameters.push(parameter.clone()),
+                Parameter::Named(_) => {}
+            }
+        }
+        if !positional.is_empty() && !parameters.is_empty() {
+            return Err(QueryError::Syntax("Cannot mix positional ($1) and named (:name) parameters".to_string()));
+        }
+        if let Some(missing) = (1..=positional.len()).find(|index| !positional.contains(index)) {
+            return Err(QueryError::Syntax(format!("Parameter ${} is missing; parameters are numbered from $1", missing)));
+        }
+        if !positional.is_empty() {
+            parameters = positional.into_iter().map(Parameter::Positional).collect();
+        }
+
+        // 绑定只会访问值的位置,占位符出现在其他位置(如 CREATE 的选项)时无法替换
+        let mut reached = 0;
+        walk_statement(&mut statement, &mut |value| {
+            reached += count_placeholders(value);
+            Ok(())
+        })?;
+        if reached != occurrences.len() {
+            return Err(QueryError::Syntax("Parameters are not supported in this statement".to_string()));
+        }
+
+        Ok(Self {
+            query: query.to_string(),
+            statement,
+            parameters,
+        })
+    }
+
+    /// # Brief
+    /// 获取原始语句文本
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// # Brief
+    /// 获取带占位符的语句
+    pub fn statement(&self) -> &Statement {
+        &self.statement
+    }
+
+    /// # Brief
+    /// 获取语句中的参数,位置参数按编号排列,命名参数按首次出现的顺序排列
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// # Brief
+    /// 绑定参数值,得到可以执行的语句
+    ///
+    /// # Arguments
+    /// * `params` - 参数值,个数或名称必须与语句中的参数完全一致
+    ///
+    /// # Returns
+    /// 占位符替换为参数值的语句
+    pub fn bind(&self, params: &Params) -> QueryResult<Statement> {
+        let named = matches!(self.parameters.first(), Some(Parameter::Named(_)));
+        match params {
+            _ if self.parameters.is_empty() && params.is_empty() => return Ok(self.statement.clone()),
+            Params::Positional(values) if !named => {
+                if values.len() != self.parameters.len() {
+                    return Err(QueryError::Binding(format!(
+                        "Statement has {} parameter(s), got {} value(s)",
+                        self.parameters.len(),
+                        values.len()
+                    )));
+                }
+            }
+            Params::Named(values) if named || self.parameters.is_empty() => {
+                if let Some(missing) = self.parameters.iter().find(|p| !matches!(p, Parameter::Named(name) if values.contains_key(name))) {
+                    return Err(QueryError::Binding(format!("No value for parameter {}", missing)));
+                }
+                if let Some(unknown) = values.keys().find(|name| !self.parameters.contains(&Parameter::Named((*name).clone()))) {
+                    return Err(QueryError::Binding(format!("Statement has no parameter :{}", unknown)));
+                }
+            }
+            Params::Positional(_) => {
+                return Err(QueryError::Binding("Statement uses named parameters, got positional values".to_string()))
+            }
+            Params::Named(_) => {
+                return Err(QueryError::Binding("Statement uses positional parameters, got named values".to_string()))
+            }
+        }
+
+        let mut statement = self.statement.clone();
+        walk_statement(&mut statement, &mut |value| {
+            substitute(value, params);
+            Ok(())
+        })?;
+        Ok(statement)
+    }
+}
+
+/// # Brief
+/// 访问语句中所有可以出现占位符的值
+fn walk_statement(statement: &mut Statement, visit: &mut dyn FnMut(&mut BomlValue) -> QueryResult<()>) -> QueryResult<()> {
+    match statement {
+        Statement::Find(find) => walk_filter(&mut find.filter, visit),
+        Statement::InsertSelect(insert) => walk_filter(&mut insert.source.filter, visit),
+        Statement::Insert(insert) => insert.documents.iter_mut().try_for_each(&mut *visit),
+        Statement::Update(update) => {
+            walk_filter(&mut update.filter, visit)?;
+            for operation in &mut update.updates {
+                match operation {
+                    UpdateOperation::Set { value, .. }
+                    | UpdateOperation::Inc { value, .. }
+                    | UpdateOperation::Push { value, .. }
+                    | UpdateOperation::Pull { value, .. }
+                    | UpdateOperation::AddToSet { value, .. } => visit(value)?,
+                    UpdateOperation::PullAll { values, .. } => values.iter_mut().try_for_each(&mut *visit)?,
+                    UpdateOperation::Unset { .. } | UpdateOperation::Rename { .. } | UpdateOperation::Pop { .. } => {}
+                }
+            }
+            Ok(())
+        }
+        Statement::Delete(delete) => walk_filter(&mut delete.filter, visit),
+        Statement::Aggregate(aggregate) => walk_pipeline(&mut aggregate.pipeline, visit),
+        Statement::DryRun(inner) => walk_statement(inner, visit),
+        _ => Ok(()),
+    }
+}
+
+fn walk_pipeline(pipeline: &mut [AggregateStage], visit: &mut dyn FnMut(&mut BomlValue) -> QueryResult<()>) -> QueryResult<()> {
+    for stage in pipeline {
+        match stage {
+            AggregateStage::Match(expr) => walk_expression(expr, visit)?,
+            AggregateStage::Project(fields) => {
+                for expr in fields.iter_mut().filter_map(|field| field.expression.as_mut()) {
+                    walk_expression(expr, visit)?;
+                }
+            }
+            AggregateStage::Facet(facets) => {
+                for facet in facets {
+                    walk_pipeline(&mut facet.pipeline, visit)?;
+                }
+            }
+            AggregateStage::Bucket(bucket) => {
+                for value in bucket.boundaries.iter_mut().chain(bucket.default.as_mut()) {
+                    visit(value)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn walk_filter(filter: &mut Option<Expression>, visit: &mut dyn FnMut(&mut BomlValue) -> QueryResult<()>) -> QueryResult<()> {
+    match filter {
+        Some(expr) => walk_expression(expr, visit),
+        None => Ok(()),
+    }
+}
+
+fn walk_expression(expr: &mut Expression, visit: &mut dyn FnMut(&mut BomlValue) -> QueryResult<()>) -> QueryResult<()> {
+    match expr {
+        Expression::Literal(value) => visit(value),
+        Expression::Binary { left, right, .. } => {
+            walk_expression(left, visit)?;
+            walk_expression(right, visit)
+        }
+        Expression::Unary { expr, .. } | Expression::Like { expr, .. } | Expression::IsNull { expr, .. } => {
+            walk_expression(expr, visit)
+        }
+        Expression::In { expr, list } => {
+            walk_expression(expr, visit)?;
+            list.iter_mut().try_for_each(|item| walk_expression(item, visit))
+        }
+        Expression::Between { expr, low, high } => {
+            walk_expression(expr, visit)?;
+            walk_expression(low, visit)?;
+            walk_expression(high, visit)
+        }
+        Expression::Call { args: items, .. } | Expression::Array(items) => {
+            items.iter_mut().try_for_each(|item| walk_expression(item, visit))
+        }
+        Expression::Document(fields) => fields.iter_mut().try_for_each(|(_, value)| walk_expression(value, visit)),
+        Expression::Field(_) | Expression::Exists { .. } | Expression::CurrentDocument => Ok(()),
+    }
+}
+
+/// # Brief
+/// 统计值中(包括嵌套文档和数组中)的占位符个数
+fn count_placeholders(value: &BomlValue) -> usize {
+    if Parameter::from_placeholder(value).is_some() {
+        return 1;
+    }
+    match value {
+        BomlValue::Document(fields) => fields.values().map(count_placeholders).sum(),
+        BomlValue::Array(items) => items.iter().map(count_placeholders).sum(),
+        _ => 0,
+    }
+}
+
+/// # Brief
+/// 把值中的占位符替换为参数值,调用前已检查所有参数都有值
+fn substitute(value: &mut BomlValue, params: &Params) {
+    if let Some(parameter) = Parameter::from_placeholder(value) {
+        let bound = match (&parameter, params) {
+            (Parameter::Positional(index), Params::Positional(values)) => values.get(index - 1),
+            (Parameter::Named(name), Params::Named(values)) => values.get(name),
+            _ => None,
+        };
+        if let Some(bound) = bound {
+            *value = bound.clone();
+        }
+        return;
+    }
+    match value {
+        BomlValue::Document(fields) => fields.values_mut().for_each(|field| substitute(field, params)),
+        BomlValue::Array(items) => items.iter_mut().for_each(|item| substitute(item, params)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_positional() {
+        let prepared = PreparedStatement::prepare("FIND users WHERE age > $1 AND name IN [$2, $1]").unwrap();
+        assert_eq!(prepared.parameters(), &[Parameter::Positional(1), Parameter::Positional(2)]);
+
+        let bound = prepared
+            .bind(&Params::Positional(vec![BomlValue::Int64(18), BomlValue::String("x' OR '1' = '1".into())]))
+            .unwrap();
+        let expected = Parser::parse(r#"FIND users WHERE age > 18 AND name IN ["x' OR '1' = '1", 18]"#).unwrap();
+        assert_eq!(bound, expected);
+
+        assert!(matches!(prepared.bind(&Params::Positional(vec![BomlValue::Int64(1)])), Err(QueryError::Binding(_))));
+        assert!(matches!(prepared.bind(&Params::Named(HashMap::new())), Err(QueryError::Binding(_))));
+
+        // FACET 子管道中的占位符同样绑定
+        let facet = PreparedStatement::prepare("AGGREGATE users | FACET adults (MATCH age >= $1), named (MATCH name = $2)")
+            .unwrap();
+        assert_eq!(facet.parameters().len(), 2);
+        let bound = facet
+            .bind(&Params::Positional(vec![BomlValue::Int64(18), BomlValue::String("miku".into())]))
+            .unwrap();
+        let expected = Parser::parse(r#"AGGREGATE users | FACET adults (MATCH age >= 18), named (MATCH name = "miku")"#);
+        assert_eq!(bound, expected.unwrap());
+    }
+
+    #[test]
+    fn test_bind_named_in_writes() {
+        let prepared =
+            PreparedStatement::prepare("UPDATE users SET score = :score, tags = [:tag] WHERE name = :name").unwrap();
+        assert_eq!(prepared.parameters().len(), 3);
+        let params = Params::Named(HashMap::from([
+            ("score".to_string(), BomlValue::Int64(7)),
+            ("tag".to_string(), BomlValue::String("vip".into())),
+            ("name".to_string(), BomlValue::String("miku".into())),
+        ]));
+        let expected = Parser::parse(r#"UPDATE users SET score = 7, tags = ["vip"] WHERE name = "miku""#).unwrap();
+        assert_eq!(prepared.bind(&params).unwrap(), expected);
+
+        let insert = PreparedStatement::prepare(r#"INSERT INTO users {"name": :name, "meta": {"n": :n}}"#).unwrap();
+        let bound = insert
+            .bind(&Params::Named(HashMap::from([
+                ("name".to_string(), BomlValue::String("a".into())),
+                ("n".to_string(), BomlValue::Int64(1)),
+            ])))
+            .unwrap();
+        assert_eq!(bound, Parser::parse(r#"INSERT INTO users {"name": "a", "meta": {"n": 1}}"#).unwrap());
+    }
+
+    #[test]
+    fn test_prepare_errors() {
+        assert!(PreparedStatement::prepare("FIND users WHERE a = $1 AND b = :b").is_err());
+        assert!(PreparedStatement::prepare("FIND users WHERE a = $2").is_err());
+        assert!(PreparedStatement::prepare("SET return_stats = $1").is_err());
+        // 未绑定的占位符不能直接执行
+        let err = Parser::parse("FIND users WHERE age > $1").unwrap_err();
+        assert!(err.to_string().contains("$1"), "{}", err);
+        // 文档字面量中紧凑写法的冒号不受影响
+        assert!(Parser::parse(r#"INSERT INTO t {"a":true,"b":null}"#).is_ok());
+    }
+}
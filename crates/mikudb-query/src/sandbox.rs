@@ -0,0 +1,259 @@
+//! 函数沙箱模块
+//!
+//! 在服务器端执行用户上传的 WebAssembly 自定义函数(需启用 `wasm-udf` 特性):
+//! - 模块不能有任何导入,函数无法访问文件、网络、时钟等宿主资源
+//! - 每次调用使用全新的实例,调用之间不共享状态
+//! - 燃料计量限制 CPU:指令数超过 `fuel` 时调用中止
+//! - 线性内存不能超过 `memory_bytes`,只允许一个内存和一个表
+//!
+//! 模块必须导出:
+//! - `memory`: 线性内存
+//! - `alloc(len: i32) -> i32`: 分配 `len` 字节,返回起始地址
+//! - `call(ptr: i32, len: i32) -> i64`: 参数为 JSON 数组,返回值高 32 位为结果地址、
+//!   低 32 位为结果长度,结果为单个 JSON 值
+//!
+//! JSON 使用扩展格式(`$oid`、`$date` 等)表示 BOML 特有类型。
+
+use serde::{Deserialize, Serialize};
+
+/// 沙箱资源限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxLimits {
+    /// 单次调用可消耗的燃料(约等于执行的指令数)
+    pub fuel: u64,
+    /// 单次调用的线性内存上限(字节)
+    pub memory_bytes: usize,
+    /// 模块大小上限(字节)
+    pub max_module_bytes: usize,
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            memory_bytes: 16 * 1024 * 1024,
+            max_module_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+/// # Brief
+/// 当前构建是否包含 WASM 运行时
+pub fn available() -> bool {
+    cfg!(feature = "wasm-udf")
+}
+
+#[cfg(feature = "wasm-udf")]
+pub use runtime::WasmFunction;
+
+#[cfg(feature = "wasm-udf")]
+mod runtime {
+    use super::SandboxLimits;
+    use crate::{QueryError, QueryResult};
+    use mikudb_boml::BomlValue;
+    use wasmi::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+    /// 已编译的 WASM 函数
+    ///
+    /// 编译一次,每次调用实例化一个带燃料和内存限制的新实例。
+    pub struct WasmFunction {
+        engine: Engine,
+        module: Module,
+        limits: SandboxLimits,
+    }
+
+    impl WasmFunction {
+        /// # Brief
+        /// 校验并编译模块
+        ///
+        /// # Arguments
+        /// * `bytes` - WASM 二进制模块
+        /// * `limits` - 资源限制
+        ///
+        /// # Returns
+        /// 模块过大、无法编译、有导入或缺少必需导出时返回错误
+        pub fn compile(bytes: &[u8], limits: SandboxLimits) -> QueryResult<Self> {
+            if bytes.len() > limits.max_module_bytes {
+                return Err(QueryError::Execution(format!(
+                    "WASM module is {} bytes, limit is {}",
+                    bytes.len(),
+                    limits.max_module_bytes
+                )));
+            }
+            let mut config = Config::default();
+            config.consume_fuel(true);
+            let engine = Engine::new(&config);
+            let module = Module::new(&engine, bytes)
+                .map_err(|e| QueryError::Execution(format!("Invalid WASM module: {}", e)))?;
+
+            if let Some(import) = module.imports().next() {
+                return Err(QueryError::Execution(format!(
+                    "WASM module must not import anything, found {}.{}",
+                    import.module(),
+                    import.name()
+                )));
+            }
+            for name in ["memory", "alloc", "call"] {
+                if module.get_export(name).is_none() {
+                    return Err(QueryError::Execution(format!("WASM module must export '{}'", name)));
+                }
+            }
+            Ok(Self { engine, module, limits })
+        }
+
+        /// # Brief
+        /// 在新实例中调用函数
+        ///
+        /// # Arguments
+        /// * `args` - 已求值的参数
+        ///
+        /// # Returns
+        /// 函数结果;燃料耗尽、内存超限、陷入或结果无效时返回错误
+        pub fn call(&self, args: &[BomlValue]) -> QueryResult<BomlValue> {
+            let input = BomlValue::Array(args.to_vec()).to_extended_json_string().into_bytes();
+            let len = i32::try_from(input.len())
+                .map_err(|_| QueryError::Execution("Function arguments too large".to_string()))?;
+
+            let limits = StoreLimitsBuilder::new()
+                .memory_size(self.limits.memory_bytes)
+                .instances(1)
+                .memories(1)
+                .tables(1)
+                .build();
+            let mut store = Store::new(&self.engine, limits);
+            store.limiter(|limits: &mut StoreLimits| limits);
+            store.add_fuel(self.limits.fuel).map_err(trap)?;
+
+            let instance = Linker::<StoreLimits>::new(&self.engine)
+                .instantiate(&mut store, &self.module)
+                .and_then(|pre| pre.start(&mut store))
+                .map_err(trap)?;
+            let memory = instance
+                .get_memory(&store, "memory")
+                .ok_or_else(|| QueryError::Execution("WASM export 'memory' is not a memory".to_string()))?;
+            let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc").map_err(trap)?;
+            let call = instance.get_typed_func::<(i32, i32), i64>(&store, "call").map_err(trap)?;
+
+            let ptr = alloc.call(&mut store, len).map_err(trap)?;
+            memory.write(&mut store, ptr as u32 as usize, &input).map_err(trap)?;
+            let packed = call.call(&mut store, (ptr, len)).map_err(trap)? as u64;
+
+            // 结果地址和长度由模块给出,先确认位于线性内存内再读取
+            let start = (packed >> 32) as usize;
+            let end = start + (packed & 0xFFFF_FFFF) as usize;
+            let output = memory.data(&store).get(start..end).ok_or_else(|| {
+                QueryError::Execution("WASM function returned a result outside its memory".to_string())
+            })?;
+            let value: serde_json::Value = serde_json::from_slice(output)
+                .map_err(|e| QueryError::Execution(format!("WASM function returned invalid JSON: {}", e)))?;
+            mikudb_boml::from_extended_json(&value).map_err(|e| QueryError::Execution(e.to_string()))
+        }
+    }
+
+    impl std::fmt::Debug for WasmFunction {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("WasmFunction").field("limits", &self.limits).finish()
+        }
+    }
+
+    fn trap(e: impl std::fmt::Display) -> QueryError {
+        QueryError::Execution(format!("WASM function failed: {}", e))
+    }
+}
+
+#[cfg(all(test, feature = "wasm-udf"))]
+mod tests {
+    use super::*;
+    use crate::QueryResult;
+    use mikudb_boml::BomlValue;
+
+    /// 只有一个参数时返回该参数:跳过开头的 '[' 并去掉结尾的 ']'
+    const FIRST_ARG: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (func (export "alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "call") (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+              (i64.shl (i64.extend_i32_u (i32.add (local.get $ptr) (i32.const 1))) (i64.const 32))
+              (i64.extend_i32_u (i32.sub (local.get $len) (i32.const 2))))))
+    "#;
+
+    const SPIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "call") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    const GROW: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "call") (param i32 i32) (result i64)
+            (if (i32.eq (memory.grow (i32.const 1000)) (i32.const -1))
+              (then (unreachable)))
+            (i64.const 0)))
+    "#;
+
+    const IMPORTS: &str = r#"
+        (module
+          (import "env" "read_file" (func (param i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "call") (param i32 i32) (result i64) (i64.const 0)))
+    "#;
+
+    fn compile(wat: &str) -> QueryResult<WasmFunction> {
+        WasmFunction::compile(&wat::parse_str(wat).unwrap(), SandboxLimits::default())
+    }
+
+    #[test]
+    fn test_wasm_function_call() {
+        let function = compile(FIRST_ARG).unwrap();
+        let mut doc = mikudb_boml::Document::new();
+        doc.insert("score", 42);
+        let result = function.call(&[BomlValue::from(doc)]).unwrap();
+        assert_eq!(result.get("score").and_then(BomlValue::as_i64), Some(42));
+    }
+
+    #[test]
+    fn test_wasm_function_limits() {
+        let error = compile(SPIN).unwrap().call(&[]).unwrap_err().to_string();
+        assert!(error.contains("fuel"), "{}", error);
+
+        let grow = compile(GROW).unwrap();
+        let error = grow.call(&[]).unwrap_err().to_string();
+        assert!(error.contains("unreachable"), "{}", error);
+
+        assert!(compile(IMPORTS).is_err());
+        let tiny = SandboxLimits { max_module_bytes: 8, ..Default::default() };
+        assert!(WasmFunction::compile(&wat::parse_str(FIRST_ARG).unwrap(), tiny).is_err());
+    }
+
+    #[test]
+    fn test_registry_wasm_function() {
+        let module = wat::parse_str(FIRST_ARG).unwrap();
+        let registry = crate::FunctionRegistry::new();
+        assert!(registry.register_wasm("pick", &module).is_err());
+
+        registry.enable_sandbox(SandboxLimits::default());
+        registry.register_wasm("pick", &module).unwrap();
+        assert!(registry.is_wasm("PICK"));
+
+        let mut doc = mikudb_boml::Document::new();
+        doc.insert("score", 42);
+        let expr = match crate::Parser::parse("FIND t WHERE CALL FUNCTION pick(score) > 40").unwrap() {
+            crate::Statement::Find(find) => find.filter.unwrap(),
+            other => panic!("Expected Find, got {:?}", other),
+        };
+        assert!(crate::filter::evaluate_with(&expr, &doc, Some(&registry)).unwrap());
+        assert!(crate::filter::evaluate(&expr, &doc).is_err());
+    }
+}
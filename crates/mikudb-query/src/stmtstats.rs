@@ -0,0 +1,30 @@
+//! 单条语句资源统计模块
+//!
+//! 记录一条语句执行时消耗的资源,随响应返回给客户端(会话中 `SET return_stats = true` 后启用):
+//! - **文档数**: 扫描检查的文档数与返回的文档数
+//! - **访问路径**: 使用的索引名称,全集合扫描时为空
+//! - **耗时**: 解析、计划、执行三个阶段各自的耗时
+//! - **读取字节**: 执行期间从存储读取的字节数
+//!
+//! 与 [`crate::opstats`] 的按集合累计统计不同,这里只描述单次执行。
+
+use serde::{Deserialize, Serialize};
+
+/// 单条语句的资源统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatementStats {
+    /// 扫描检查的文档数
+    pub docs_examined: u64,
+    /// 返回给客户端的文档数
+    pub docs_returned: u64,
+    /// 使用的索引,全集合扫描时为 None
+    pub index_used: Option<String>,
+    /// 解析耗时(微秒),由解析语句的调用方填写
+    pub parse_us: u64,
+    /// 生成查询计划的耗时(微秒)
+    pub plan_us: u64,
+    /// 执行耗时(微秒),不含计划耗时
+    pub execute_us: u64,
+    /// 从存储读取的字节数
+    pub bytes_read: u64,
+}
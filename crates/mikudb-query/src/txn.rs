@@ -0,0 +1,42 @@
+//! 事务上下文
+//!
+//! 执行器设置事务上下文后(见 [`QueryExecutor::with_transaction`](crate::QueryExecutor::with_transaction)),
+//! INSERT / UPDATE / DELETE 不直接写入存储,而是记录到事务的写集合中,提交时一起写入;
+//! FIND 和 AGGREGATE 读取的集合内容包含事务内尚未提交的写入。
+//!
+//! 事务由上层(`mikudb-core` 的 `Transaction`)实现,查询模块只依赖本 trait。
+
+use crate::QueryResult;
+use mikudb_boml::Document;
+use mikudb_common::ObjectId;
+
+/// 执行器在事务中读写文档的接口
+pub trait TransactionContext: Send + Sync {
+    /// # Brief
+    /// 读取集合的全部文档
+    ///
+    /// # Returns
+    /// 已提交的文档叠加事务内的插入、更新和删除;集合不存在时为空
+    fn find_all(&self, collection: &str) -> QueryResult<Vec<Document>>;
+
+    /// # Brief
+    /// 在事务中插入文档
+    ///
+    /// # Returns
+    /// 文档 ID(没有 `_id` 时生成)
+    fn insert(&self, collection: &str, doc: Document) -> QueryResult<ObjectId>;
+
+    /// # Brief
+    /// 在事务中替换文档
+    ///
+    /// # Returns
+    /// 文档不存在时返回 false
+    fn update(&self, collection: &str, id: &ObjectId, doc: Document) -> QueryResult<bool>;
+
+    /// # Brief
+    /// 在事务中删除文档
+    ///
+    /// # Returns
+    /// 文档不存在时返回 false
+    fn delete(&self, collection: &str, id: &ObjectId) -> QueryResult<bool>;
+}
@@ -0,0 +1,242 @@
+//! 自定义函数模块
+//!
+//! 嵌入式使用时,Rust 调用方可以向 `QueryExecutor` 注册自定义函数,在 MQL 中直接调用:
+//! - **标量函数**: 在 WHERE 等表达式中调用,如 `FIND users WHERE slugify(name) = 'miku'`
+//! - **累加器函数**: 在 AGGREGATE 的 GROUP 阶段调用,如 `GROUP BY city AS {p90: p90(age)}`
+//!
+//! 函数名不区分大小写,不能与内置函数、类型字面量构造器或已注册的函数重名。
+//! Rust 函数只保存在内存中;启用沙箱后还可以注册 WASM 函数(见 `sandbox` 模块),
+//! 服务器通过 `CREATE FUNCTION` 注册并持久化。
+
+use crate::sandbox::SandboxLimits;
+use crate::{QueryError, QueryResult};
+use mikudb_boml::BomlValue;
+use mikudb_storage::StorageEngine;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 内置标量函数及 MQL 中以函数调用形式出现的保留名称
+pub const BUILTIN_SCALAR_FUNCTIONS: &[&str] = &[
+    "upper", "toupper", "lower", "tolower", "length", "len", "abs", "floor", "ceil", "round",
+    "coalesce", "approx_eq", "isodate", "objectid", "uuid", "nextval",
+];
+
+/// 内置累加器函数
+pub const BUILTIN_ACCUMULATORS: &[&str] = &[
+    "count", "sum", "avg", "min", "max", "first", "last", "push", "addtoset",
+];
+
+/// 标量函数: 接收已求值的参数,返回结果
+pub type ScalarFunction = Arc<dyn Fn(&[BomlValue]) -> QueryResult<BomlValue> + Send + Sync>;
+
+/// 累加器函数: 接收分组内所有文档中该字段的值(跳过缺失字段),返回聚合结果
+pub type AccumulatorFunction = Arc<dyn Fn(&[BomlValue]) -> QueryResult<BomlValue> + Send + Sync>;
+
+/// 已注册的函数
+#[derive(Clone)]
+enum Function {
+    Scalar(ScalarFunction),
+    Accumulator(AccumulatorFunction),
+    /// 在沙箱中执行的 WASM 标量函数
+    #[cfg_attr(not(feature = "wasm-udf"), allow(dead_code))]
+    Wasm(ScalarFunction),
+}
+
+impl Function {
+    fn kind(&self) -> &'static str {
+        match self {
+            Function::Scalar(_) => "scalar",
+            Function::Accumulator(_) => "accumulator",
+            Function::Wasm(_) => "wasm",
+        }
+    }
+}
+
+/// 自定义函数注册表
+///
+/// 内部加锁,可以在执行器运行期间通过共享引用注册或注销函数。
+#[derive(Default)]
+pub struct FunctionRegistry {
+    functions: RwLock<HashMap<String, Function>>,
+    /// WASM 沙箱的资源限制,None 表示未启用沙箱
+    sandbox: RwLock<Option<SandboxLimits>>,
+}
+
+impl FunctionRegistry {
+    /// # Brief
+    /// 创建空的注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Brief
+    /// 注册标量函数
+    ///
+    /// # Arguments
+    /// * `name` - 函数名,不区分大小写
+    /// * `function` - 函数实现
+    ///
+    /// # Returns
+    /// 函数名无效或与内置函数、已注册的函数重名时返回错误
+    pub fn register_scalar<F>(&self, name: &str, function: F) -> QueryResult<()>
+    where
+        F: Fn(&[BomlValue]) -> QueryResult<BomlValue> + Send + Sync + 'static,
+    {
+        self.register(name, Function::Scalar(Arc::new(function)))
+    }
+
+    /// # Brief
+    /// 注册累加器函数
+    ///
+    /// # Arguments
+    /// * `name` - 函数名,不区分大小写
+    /// * `function` - 函数实现
+    ///
+    /// # Returns
+    /// 函数名无效或与内置函数、已注册的函数重名时返回错误
+    pub fn register_accumulator<F>(&self, name: &str, function: F) -> QueryResult<()>
+    where
+        F: Fn(&[BomlValue]) -> QueryResult<BomlValue> + Send + Sync + 'static,
+    {
+        self.register(name, Function::Accumulator(Arc::new(function)))
+    }
+
+    /// # Brief
+    /// 启用 WASM 沙箱,之后可以注册 WASM 函数
+    ///
+    /// # Arguments
+    /// * `limits` - 每次调用的资源限制,只对之后注册的函数生效
+    pub fn enable_sandbox(&self, limits: SandboxLimits) {
+        *self.sandbox.write() = Some(limits);
+    }
+
+    /// # Brief
+    /// 沙箱的资源限制,未启用时返回 None
+    pub fn sandbox_limits(&self) -> Option<SandboxLimits> {
+        *self.sandbox.read()
+    }
+
+    /// # Brief
+    /// 编译并注册 WASM 标量函数
+    ///
+    /// # Arguments
+    /// * `name` - 函数名,不区分大小写
+    /// * `module` - WASM 二进制模块,接口见 `sandbox` 模块
+    ///
+    /// # Returns
+    /// 未启用沙箱、构建不含 WASM 运行时、模块无效或函数名冲突时返回错误
+    pub fn register_wasm(&self, name: &str, module: &[u8]) -> QueryResult<()> {
+        let limits = self.sandbox_limits().ok_or_else(|| {
+            QueryError::Execution("WASM functions are disabled".to_string())
+        })?;
+        #[cfg(feature = "wasm-udf")]
+        {
+            let function = crate::sandbox::WasmFunction::compile(module, limits)?;
+            self.register(name, Function::Wasm(Arc::new(move |args: &[BomlValue]| function.call(args))))
+        }
+        #[cfg(not(feature = "wasm-udf"))]
+        {
+            let _ = (name, module, limits);
+            Err(QueryError::Execution(
+                "WASM functions require a build with the wasm-udf feature".to_string(),
+            ))
+        }
+    }
+
+    /// # Brief
+    /// 注册存储中保存的所有 WASM 函数,服务器启动时调用
+    ///
+    /// 无法编译或与已注册函数重名的模块记录警告后跳过。
+    ///
+    /// # Returns
+    /// 注册成功的函数数量
+    pub fn load_stored(&self, storage: &StorageEngine) -> QueryResult<usize> {
+        let mut loaded = 0;
+        for (name, module) in storage.stored_functions()? {
+            match self.register_wasm(&name, &module) {
+                Ok(()) => loaded += 1,
+                Err(e) => tracing::warn!("Skipping stored function {}: {}", name, e),
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// # Brief
+    /// 注销函数
+    ///
+    /// # Returns
+    /// 函数存在时返回 true
+    pub fn unregister(&self, name: &str) -> bool {
+        self.functions.write().remove(&name.to_lowercase()).is_some()
+    }
+
+    /// # Brief
+    /// 查找标量函数
+    pub fn scalar(&self, name: &str) -> Option<ScalarFunction> {
+        match self.functions.read().get(&name.to_lowercase()) {
+            Some(Function::Scalar(function)) | Some(Function::Wasm(function)) => Some(function.clone()),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 查找累加器函数
+    pub fn accumulator(&self, name: &str) -> Option<AccumulatorFunction> {
+        match self.functions.read().get(&name.to_lowercase()) {
+            Some(Function::Accumulator(function)) => Some(function.clone()),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 已注册的函数名,按字母排序
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.read().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// # Brief
+    /// 已注册的函数及其类型(scalar、accumulator 或 wasm),按函数名排序
+    pub fn list(&self) -> Vec<(String, &'static str)> {
+        let mut list: Vec<_> = self
+            .functions
+            .read()
+            .iter()
+            .map(|(name, function)| (name.clone(), function.kind()))
+            .collect();
+        list.sort();
+        list
+    }
+
+    /// # Brief
+    /// 是否为 WASM 函数
+    pub fn is_wasm(&self, name: &str) -> bool {
+        matches!(self.functions.read().get(&name.to_lowercase()), Some(Function::Wasm(_)))
+    }
+
+    /// # Brief
+    /// 检查函数名后注册,函数名统一转为小写
+    fn register(&self, name: &str, function: Function) -> QueryResult<()> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(QueryError::Execution(format!("Invalid function name: {}", name)));
+        }
+        let name = name.to_lowercase();
+        let builtin = BUILTIN_SCALAR_FUNCTIONS.contains(&name.as_str())
+            || BUILTIN_ACCUMULATORS.contains(&name.as_str());
+
+        let mut functions = self.functions.write();
+        if builtin || functions.contains_key(&name) {
+            return Err(QueryError::Execution(format!("Function already exists: {}", name)));
+        }
+        functions.insert(name, function);
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionRegistry").field("functions", &self.names()).finish()
+    }
+}
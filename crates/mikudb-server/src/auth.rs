@@ -9,6 +9,7 @@
 use crate::config::AuthConfig;
 use crate::{ServerError, ServerResult};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use mikudb_query::Statement;
 use mikudb_storage::StorageEngine;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -165,7 +166,7 @@ impl User {
     /// 检查用户是否可访问指定数据库
     ///
     /// root 用户可访问所有数据库。
-    /// 如果 databases 列表为空,表示可访问所有数据库。
+    /// 如果 databases 列表为空或包含 `*`(CREATE USER 分配的角色),表示可访问所有数据库。
     ///
     /// # Arguments
     /// * `database` - 数据库名称
@@ -174,7 +175,7 @@ impl User {
     /// true 表示有访问权限
     pub fn can_access_database(&self, database: &str) -> bool {
         // root 角色或空数据库列表或数据库在列表中
-        self.has_role("root") || self.databases.is_empty() || self.databases.contains(&database.to_string())
+        self.has_role("root") || self.databases.is_empty() || self.databases.iter().any(|db| db == "*" || db == database)
     }
 }
 
@@ -225,15 +226,20 @@ pub enum Permission {
 /// - Write: 需要 readWrite 或 root 角色
 /// - Admin: 需要 root 角色
 ///
+/// 指定数据库时还要求用户可访问该数据库(见 `User::can_access_database`)。
+///
 /// # Arguments
 /// * `user` - 用户实例
-/// * `_database` - 数据库名(预留,当前未使用)
+/// * `database` - 数据库名,空字符串表示不针对具体数据库
 /// * `_collection` - 集合名(预留,当前未使用)
 /// * `permission` - 需要检查的权限类型
 ///
 /// # Returns
 /// true 表示具有权限,false 表示无权限
-pub fn check_permission(user: &User, _database: &str, _collection: &str, permission: Permission) -> bool {
+pub fn check_permission(user: &User, database: &str, _collection: &str, permission: Permission) -> bool {
+    if !database.is_empty() && !user.can_access_database(database) {
+        return false;
+    }
     match permission {
         Permission::Read => user.has_role("read") || user.has_role("readWrite") || user.has_role("root"),
         Permission::Write => user.has_role("readWrite") || user.has_role("root"),
@@ -241,6 +247,71 @@ pub fn check_permission(user: &User, _database: &str, _collection: &str, permiss
     }
 }
 
+/// # Brief
+/// 获取执行语句所需的权限级别
+///
+/// 二进制协议和 HTTP API 共用此分类。新增语句必须在这里归类,否则无法编译。
+pub fn statement_permission(statement: &Statement) -> Permission {
+    match statement {
+        Statement::Use(_)
+        | Statement::ShowDatabases
+        | Statement::ShowCollections
+        | Statement::ShowIndexes(_)
+        | Statement::ShowStatus
+        | Statement::ShowAdvisor(_)
+        | Statement::ShowSchema(_)
+        | Statement::Stats(_)
+        | Statement::SetVariable(_)
+        | Statement::ShowSequences
+        | Statement::ShowRollups
+        | Statement::ShowFunctions
+        | Statement::Find(_)
+        | Statement::Aggregate(_)
+        | Statement::AiQuery(_)
+        | Statement::AiAnalyze(_)
+        | Statement::AiSuggestIndex(_) => Permission::Read,
+        Statement::Insert(_)
+        | Statement::InsertSelect(_)
+        | Statement::Update(_)
+        | Statement::Delete(_)
+        | Statement::Archive(_)
+        | Statement::BeginTransaction
+        | Statement::Commit
+        | Statement::Rollback => Permission::Write,
+        Statement::DryRun(inner) => statement_permission(inner),
+        Statement::ShowUsers
+        | Statement::ShowManifest
+        | Statement::CreateUser(_)
+        | Statement::AlterUser(_)
+        | Statement::DropUser(_)
+        | Statement::Grant(_)
+        | Statement::Revoke(_)
+        | Statement::ShowGrants(_)
+        | Statement::DropDatabase(_)
+        | Statement::Backup(_)
+        | Statement::Restore(_)
+        | Statement::SetLogLevel(_)
+        | Statement::ResetLogLevel
+        | Statement::ClusterInit
+        | Statement::ClusterJoin(_)
+        | Statement::ResetStats(_)
+        | Statement::Compact(_)
+        | Statement::CreateFunction(_)
+        | Statement::DropFunction(_)
+        | Statement::CreateDatabase(_)
+        | Statement::CreateCollection(_)
+        | Statement::DropCollection(_)
+        | Statement::AlterCollection(_)
+        | Statement::CreateIndex(_)
+        | Statement::DropIndex(_)
+        | Statement::CreateSequence(_)
+        | Statement::DropSequence(_)
+        | Statement::CreateRollup(_)
+        | Statement::DropRollup(_)
+        | Statement::Analyze(_) => Permission::Admin,
+    }
+}
+
 /// 角色分配
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RoleAssignment {
@@ -441,6 +512,61 @@ impl UserManager {
         Ok(())
     }
 
+    /// # Brief
+    /// 为用户添加和移除角色
+    ///
+    /// 添加的角色作用于所有数据库;已拥有的角色不会重复添加,移除时忽略用户没有的角色。
+    ///
+    /// # Arguments
+    /// * `username` - 用户名
+    /// * `add` - 要添加的角色
+    /// * `remove` - 要移除的角色
+    pub async fn alter_user_roles(
+        &self,
+        username: &str,
+        add: &[String],
+        remove: &[String],
+    ) -> ServerResult<()> {
+        use mikudb_boml::{Document, BomlValue};
+
+        let admin_db = "admin";
+        let users_collection = "users";
+        let collection = self.storage.get_or_create_collection(&format!("{}:{}", admin_db, users_collection))?;
+
+        let mut docs = collection.find_all()?;
+        let doc = docs
+            .iter_mut()
+            .find(|doc| matches!(doc.get("username"), Some(BomlValue::String(name)) if name.as_str() == username))
+            .ok_or_else(|| ServerError::Internal(format!("User '{}' not found", username)))?;
+
+        let mut roles = match doc.get("roles") {
+            Some(BomlValue::Array(roles)) => roles.clone(),
+            _ => Vec::new(),
+        };
+        let role_name = |value: &BomlValue| match value {
+            BomlValue::Document(role) => match role.get("role") {
+                Some(BomlValue::String(name)) => Some(name.to_string()),
+                _ => None,
+            },
+            _ => None,
+        };
+        roles.retain(|role| !role_name(role).is_some_and(|name| remove.contains(&name)));
+        for role in add {
+            if roles.iter().any(|existing| role_name(existing).as_deref() == Some(role.as_str())) {
+                continue;
+            }
+            let mut role_doc = Document::new();
+            role_doc.insert("role".to_string(), BomlValue::String(role.clone().into()));
+            role_doc.insert("db".to_string(), BomlValue::String("*".into()));
+            roles.push(BomlValue::from(role_doc));
+        }
+        doc.insert("roles".to_string(), BomlValue::Array(roles));
+
+        let obj_id = doc.id().ok_or_else(|| ServerError::Internal("Document has no _id".to_string()))?;
+        collection.update(obj_id, doc)?;
+        Ok(())
+    }
+
     pub async fn drop_user(&self, username: &str) -> ServerResult<()> {
         use mikudb_boml::BomlValue;
 
@@ -683,3 +809,32 @@ fn sha256(data: &[u8]) -> Vec<u8> {
     hasher.update(data);
     hasher.finalize().to_vec()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: &str, databases: &[&str]) -> User {
+        User {
+            username: "u".to_string(),
+            password_hash: String::new(),
+            roles: vec![role.to_string()],
+            databases: databases.iter().map(|db| db.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_permission_database_access() {
+        let scoped = user("readWrite", &["sales"]);
+        assert!(check_permission(&scoped, "sales", "", Permission::Write));
+        assert!(!check_permission(&scoped, "hr", "", Permission::Read));
+        // 服务器级操作不检查数据库
+        assert!(check_permission(&scoped, "", "", Permission::Read));
+        assert!(!check_permission(&scoped, "", "", Permission::Admin));
+
+        assert!(check_permission(&user("read", &["*"]), "hr", "", Permission::Read));
+        assert!(check_permission(&user("read", &[]), "hr", "", Permission::Read));
+        assert!(!check_permission(&user("read", &["*"]), "hr", "", Permission::Write));
+        assert!(check_permission(&user("root", &["sales"]), "hr", "", Permission::Admin));
+    }
+}
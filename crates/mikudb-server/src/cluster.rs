@@ -0,0 +1,196 @@
+//! 集群成员管理
+//!
+//! 服务器以 `--cluster-init` / `--join` 启动或执行 `CLUSTER INIT` / `CLUSTER JOIN` 时
+//! 初始化或加入集群。集群身份保存在数据目录,服务器重启时自动以相同身份重新加入。
+
+use crate::config::ServerConfig;
+use crate::protocol::{ErrorKind, QueryResponse};
+use crate::{ServerError, ServerResult};
+use mikudb_cluster::{Cluster, ClusterIdentity};
+use mikudb_query::Statement;
+use mikudb_storage::{StorageResult, WriteMetadata};
+use parking_lot::RwLock;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// 本节点的集群成员身份
+pub struct ClusterManager {
+    /// 服务器配置(集群端口、节点 ID 和数据目录)
+    config: ServerConfig,
+    /// 运行中的集群服务,初始化或加入集群后存在
+    cluster: tokio::sync::Mutex<Option<Arc<Cluster>>>,
+    /// 集群身份,供握手等同步路径读取
+    identity: RwLock<Option<ClusterIdentity>>,
+}
+
+impl ClusterManager {
+    /// # Brief
+    /// 创建集群成员管理器,尚未加入任何集群
+    pub fn new(config: &ServerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            cluster: tokio::sync::Mutex::new(None),
+            identity: RwLock::new(None),
+        }
+    }
+
+    /// # Brief
+    /// 本节点的集群身份,不是集群成员时为 None
+    pub fn identity(&self) -> Option<ClusterIdentity> {
+        self.identity.read().clone()
+    }
+
+    /// # Brief
+    /// 整理请求附带的写入元数据
+    ///
+    /// 本节点是集群成员且请求没有指定发起节点时,以本节点 ID 作为发起节点。
+    ///
+    /// # Arguments
+    /// * `meta` - 请求中的写入元数据
+    ///
+    /// # Returns
+    /// 写入期间使用的元数据;字段超过长度上限时返回 `InvalidArgument`
+    pub fn write_metadata(&self, meta: Option<WriteMetadata>) -> StorageResult<Option<Arc<WriteMetadata>>> {
+        let mut meta = meta.unwrap_or_default();
+        if meta.origin.is_none() {
+            meta.origin = self.identity.read().as_ref().map(|identity| identity.node_id.clone());
+        }
+        meta.validate()?;
+        Ok((!meta.is_empty()).then(|| Arc::new(meta)))
+    }
+
+    /// # Brief
+    /// 按数据目录中保存的集群身份重新加入集群
+    ///
+    /// # Returns
+    /// 重新加入后的集群身份;从未初始化或加入过集群时返回 None
+    pub async fn resume(&self) -> ServerResult<Option<ClusterIdentity>> {
+        let mut slot = self.cluster.lock().await;
+        let Some(cluster) = Cluster::resume(self.cluster_config()?, &self.config.data_dir).await? else {
+            return Ok(None);
+        };
+        Ok(Some(self.install(&mut slot, cluster)))
+    }
+
+    /// # Brief
+    /// 以本节点为唯一成员初始化集群
+    ///
+    /// # Returns
+    /// 新集群中本节点的身份;已是集群成员时返回错误
+    pub async fn init(&self) -> ServerResult<ClusterIdentity> {
+        let mut slot = self.cluster.lock().await;
+        self.ensure_not_member()?;
+        let cluster = Cluster::init(self.cluster_config()?, &self.config.data_dir).await?;
+        let identity = self.install(&mut slot, cluster);
+        info!("Initialized cluster {} as node {}", identity.cluster_id, identity.node_id);
+        Ok(identity)
+    }
+
+    /// # Brief
+    /// 通过集群成员加入集群
+    ///
+    /// # Arguments
+    /// * `uri` - 成员的集群端口,格式 `mikudb://host:port[,host:port...]`
+    ///
+    /// # Returns
+    /// 本节点在集群中的身份;已是集群成员或所有成员都无法加入时返回错误
+    pub async fn join(&self, uri: &str) -> ServerResult<ClusterIdentity> {
+        let seeds = mikudb_cluster::ClusterConfig::parse_seed_uri(uri)?;
+        let mut slot = self.cluster.lock().await;
+        self.ensure_not_member()?;
+        let cluster = Cluster::join(self.cluster_config()?, &seeds, &self.config.data_dir).await?;
+        let identity = self.install(&mut slot, cluster);
+        info!(
+            "Joined cluster {} as node {} with machine id {}",
+            identity.cluster_id, identity.node_id, identity.machine_id
+        );
+        Ok(identity)
+    }
+
+    /// # Brief
+    /// 执行 CLUSTER INIT / CLUSTER JOIN 语句
+    ///
+    /// # Returns
+    /// 成功时文档为本节点的集群身份;其他语句返回失败响应
+    pub async fn execute(&self, statement: &Statement) -> QueryResponse {
+        let result = match statement {
+            Statement::ClusterInit => self.init().await.map(|identity| {
+                (format!("Initialized cluster {}", identity.cluster_id), identity)
+            }),
+            Statement::ClusterJoin(uri) => self.join(uri).await.map(|identity| {
+                (format!("Joined cluster {}", identity.cluster_id), identity)
+            }),
+            _ => Err(ServerError::Internal("Not a cluster statement".to_string())),
+        };
+        match result {
+            Ok((message, identity)) => QueryResponse {
+                success: true,
+                affected: 0,
+                documents: serde_json::to_value(&identity).into_iter().collect(),
+                cursor_id: None,
+                message: Some(message),
+                errors: vec![],
+                stats: None,
+                error_kind: None,
+            },
+            Err(e) => QueryResponse {
+                success: false,
+                affected: 0,
+                documents: vec![],
+                cursor_id: None,
+                message: Some(e.to_string()),
+                errors: vec![],
+                stats: None,
+                error_kind: Some(ErrorKind::Execution),
+            },
+        }
+    }
+
+    fn ensure_not_member(&self) -> ServerResult<()> {
+        match self.identity() {
+            Some(identity) => Err(ServerError::Config(format!(
+                "Node {} is already part of cluster {}",
+                identity.node_id, identity.cluster_id
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn install(&self, slot: &mut Option<Arc<Cluster>>, cluster: Cluster) -> ClusterIdentity {
+        let identity = cluster.identity().expect("joined cluster has an identity");
+        *self.identity.write() = Some(identity.clone());
+        *slot = Some(Arc::new(cluster));
+        identity
+    }
+
+    /// # Brief
+    /// 由服务器配置生成集群配置
+    ///
+    /// 未配置节点 ID 时生成一个,加入集群后随集群身份持久化,重启时沿用。
+    fn cluster_config(&self) -> ServerResult<mikudb_cluster::ClusterConfig> {
+        let cluster = &self.config.cluster;
+        let bind = cluster.bind.as_deref().unwrap_or(&self.config.bind);
+        let bind_addr: SocketAddr = format!("{}:{}", bind, cluster.port)
+            .parse()
+            .map_err(|e| ServerError::Config(format!("Invalid cluster bind address '{}': {}", bind, e)))?;
+        if cluster.advertise.is_none() && bind_addr.ip().is_unspecified() {
+            warn!(
+                "Cluster port binds {} without cluster.advertise; other nodes cannot reach this node by that address",
+                bind_addr
+            );
+        }
+        let node_id = cluster.node_id.clone().unwrap_or_else(|| {
+            let id = uuid::Uuid::new_v4().simple().to_string();
+            format!("node-{}", &id[..12])
+        });
+        Ok(mikudb_cluster::ClusterConfig {
+            node_id,
+            bind_addr,
+            advertise_addr: cluster.advertise.clone(),
+            machine_id: cluster.machine_id,
+            transport: cluster.transport.clone(),
+            ..Default::default()
+        })
+    }
+}
@@ -2,16 +2,26 @@
 //!
 //! 本模块定义了 MikuDB 服务器的所有配置选项:
 //! - 服务器网络配置(绑定地址、端口、Unix Socket)
+//! - 游标配置(空闲超时、每个连接的游标数与缓冲内存上限)
+//! - 运行状态快照(关闭时保存统计与查询形态,启动时恢复)
 //! - 存储引擎配置(页大小、缓存、压缩)
 //! - 认证配置(用户、密码)
 //! - TLS 加密配置
+//! - HTTP 接口配置(REST API 与 Web 管理控制台)
+//! - 存储完整性巡检配置
+//! - 请求调度配置(优先级队列、集合写入限速)
+//! - 文档过期配置(按字段过期的后台清理间隔)
+//! - 自定义函数沙箱配置(WASM 函数的开关与资源限制)
 //! - 日志配置
 //! - OpenEuler 系统优化配置(NUMA, io_uring, Direct I/O)
 //!
 //! 支持从 TOML 文件加载配置。
 
+use crate::cursor::CursorLimits;
 use crate::ServerError;
+use mikudb_storage::{EvictionPolicy, WalSyncPolicy};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -40,10 +50,41 @@ pub struct ServerConfig {
     #[serde(default = "default_max_connections")]
     pub max_connections: usize,
 
-    /// 连接超时时间(毫秒) (默认: 30000)
+    /// 连接超时时间(毫秒),也是 HTTP 接口读取一个请求的时间上限 (默认: 30000)
     #[serde(default = "default_timeout")]
     pub timeout_ms: u64,
 
+    /// 未执行 USE 的会话使用的数据库 (默认: 不设置)
+    #[serde(default)]
+    pub default_database: Option<String>,
+
+    /// 写入不存在的集合时是否自动创建 (默认: true)
+    /// 关闭后必须先执行 CREATE COLLECTION,否则返回集合不存在
+    #[serde(default = "default_auto_create_collections")]
+    pub auto_create_collections: bool,
+
+    /// 服务器端游标的空闲超时(秒) (默认: 600)
+    /// 超时未读取的游标被回收,之后的 GetMore 返回游标不存在
+    #[serde(default = "default_cursor_timeout_secs")]
+    pub cursor_timeout_secs: u64,
+
+    /// 每个连接同时打开的游标数上限 (默认: 100, 0 表示不限制)
+    #[serde(default = "default_max_cursors_per_connection")]
+    pub max_cursors_per_connection: usize,
+
+    /// 每个连接的游标缓冲结果占用的内存上限,格式同 `storage.cache_size` (默认: "64MB", "0" 表示不限制)
+    #[serde(default = "default_max_cursor_buffer_size")]
+    pub max_cursor_buffer_size: String,
+
+    /// 超过游标上限时的处理: "close_oldest" 关闭该连接最早打开的游标,
+    /// "reject" 拒绝新游标 (默认: "close_oldest")
+    #[serde(default = "default_cursor_limit_policy")]
+    pub cursor_limit_policy: String,
+
+    /// 正常关闭时保存统计、查询形态等运行状态,启动时恢复 (默认: true)
+    #[serde(default = "default_persist_state")]
+    pub persist_state: bool,
+
     /// 存储引擎配置
     #[serde(default)]
     pub storage: StorageConfig,
@@ -56,6 +97,42 @@ pub struct ServerConfig {
     #[serde(default)]
     pub tls: TlsConfig,
 
+    /// HTTP 接口配置
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    /// 存储巡检配置
+    #[serde(default)]
+    pub scrub: ScrubConfig,
+
+    /// 请求调度配置
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+
+    /// 冷热数据分层配置
+    #[serde(default)]
+    pub tiering: TieringConfig,
+
+    /// 文档过期配置
+    #[serde(default)]
+    pub expiry: ExpiryConfig,
+
+    /// 索引顾问配置
+    #[serde(default)]
+    pub advisor: AdvisorConfig,
+
+    /// 自定义函数沙箱配置
+    #[serde(default)]
+    pub udf: UdfConfig,
+
+    /// 启动预检配置
+    #[serde(default)]
+    pub preflight: PreflightConfig,
+
+    /// 写入通知配置
+    #[serde(default)]
+    pub notify: NotifyConfig,
+
     /// 日志配置
     #[serde(default)]
     pub log: LogConfig,
@@ -63,6 +140,10 @@ pub struct ServerConfig {
     /// OpenEuler 系统优化配置
     #[serde(default)]
     pub openeuler: OpenEulerConfig,
+
+    /// 集群配置
+    #[serde(default)]
+    pub cluster: ClusterConfig,
 }
 
 fn default_bind() -> String { "0.0.0.0".to_string() }
@@ -70,6 +151,12 @@ fn default_port() -> u16 { 3939 }
 fn default_data_dir() -> PathBuf { PathBuf::from("./data") }
 fn default_max_connections() -> usize { 10000 }
 fn default_timeout() -> u64 { 30000 }
+fn default_auto_create_collections() -> bool { true }
+fn default_cursor_timeout_secs() -> u64 { 600 }
+fn default_max_cursors_per_connection() -> usize { 100 }
+fn default_max_cursor_buffer_size() -> String { "64MB".to_string() }
+fn default_cursor_limit_policy() -> String { "close_oldest".to_string() }
+fn default_persist_state() -> bool { true }
 
 /// 存储引擎配置
 ///
@@ -85,17 +172,56 @@ pub struct StorageConfig {
     #[serde(default = "default_compression")]
     pub compression: String,
 
+    /// WAL 目录,默认为 `data_dir/wal`
     #[serde(default)]
     pub wal_dir: Option<PathBuf>,
 
+    /// WAL 段归档目录,设置后检查点保留写满的 WAL 段,供增量备份使用
+    #[serde(default)]
+    pub wal_archive_dir: Option<PathBuf>,
+
+    /// BACKUP / RESTORE 的根目录,默认为 `data_dir/backups`
+    /// 语句中的路径只能是该目录下的相对路径
+    #[serde(default)]
+    pub backup_dir: Option<PathBuf>,
+
+    /// 为 true 时等同于 `wal_sync = "always"`
     #[serde(default = "default_sync_writes")]
     pub sync_writes: bool,
+
+    /// WAL 落盘策略: "always"、"interval" 或 "never" (默认: "interval")
+    #[serde(default = "default_wal_sync")]
+    pub wal_sync: String,
+
+    /// `wal_sync = "interval"` 时两次 fsync 的最小间隔(毫秒)
+    #[serde(default = "default_wal_sync_interval_ms")]
+    pub wal_sync_interval_ms: u64,
+
+    /// 存储线程池的工作线程数,0 表示使用 CPU 核数
+    #[serde(default)]
+    pub blocking_threads: usize,
+
+    /// 定时压缩所有集合的间隔(秒),0 表示只在执行 COMPACT 时压缩
+    #[serde(default)]
+    pub compaction_interval_secs: u64,
+
+    /// 解码后文档缓存的容量,格式同 `cache_size`,"0" 表示不缓存
+    #[serde(default = "default_document_cache_size")]
+    pub document_cache_size: String,
+
+    /// 文档缓存的淘汰策略: "lru" 或 "lfu" (默认: "lru")
+    #[serde(default = "default_document_cache_policy")]
+    pub document_cache_policy: String,
 }
 
 fn default_page_size() -> usize { 16384 }
 fn default_cache_size() -> String { "1GB".to_string() }
+fn default_document_cache_size() -> String { "64MB".to_string() }
+fn default_document_cache_policy() -> String { "lru".to_string() }
 fn default_compression() -> String { "lz4".to_string() }
 fn default_sync_writes() -> bool { false }
+fn default_wal_sync() -> String { "interval".to_string() }
+fn default_wal_sync_interval_ms() -> u64 { 100 }
 
 /// 认证配置
 ///
@@ -227,6 +353,370 @@ impl TlsConfig {
     }
 }
 
+/// HTTP 接口配置
+///
+/// REST API 监听设置;启用 `console` feature 时同一端口还提供 Web 管理控制台。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// 是否启用 HTTP 接口 (默认: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 绑定地址 (默认: 127.0.0.1)
+    #[serde(default = "default_http_bind")]
+    pub bind: String,
+
+    /// 端口号 (默认: 3940)
+    #[serde(default = "default_http_port")]
+    pub port: u16,
+}
+
+fn default_http_bind() -> String { "127.0.0.1".to_string() }
+fn default_http_port() -> u16 { 3940 }
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_http_bind(),
+            port: default_http_port(),
+        }
+    }
+}
+
+/// 集群配置
+///
+/// 节点间的 Raft RPC 使用独立的集群端口。以 `--cluster-init` / `--join` 启动或执行
+/// `CLUSTER INIT` / `CLUSTER JOIN` 后,集群身份保存在数据目录,重启后自动重新加入。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterConfig {
+    /// 集群端口绑定地址 (默认: 与 bind 相同)
+    #[serde(default)]
+    pub bind: Option<String>,
+
+    /// 集群端口 (默认: 3941)
+    #[serde(default = "default_cluster_port")]
+    pub port: u16,
+
+    /// 其他节点连接本节点使用的地址 (默认: 集群端口的监听地址)
+    /// 绑定 0.0.0.0 时需要设置为其他节点可以访问的地址
+    #[serde(default)]
+    pub advertise: Option<String>,
+
+    /// 本节点 ID (默认: 首次初始化或加入集群时生成)
+    #[serde(default)]
+    pub node_id: Option<String>,
+
+    /// 手动指定的 ObjectId 机器标识 (默认: 加入集群时自动分配)
+    #[serde(default)]
+    pub machine_id: Option<u32>,
+
+    /// 节点间传输配置(压缩、批量写入、超时和双向 TLS)
+    #[serde(default)]
+    pub transport: mikudb_cluster::TransportConfig,
+}
+
+fn default_cluster_port() -> u16 { 3941 }
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            bind: None,
+            port: default_cluster_port(),
+            advertise: None,
+            node_id: None,
+            machine_id: None,
+            transport: mikudb_cluster::TransportConfig::default(),
+        }
+    }
+}
+
+/// 存储巡检配置
+///
+/// 后台巡检器按 I/O 预算校验文档校验和与索引一致性。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrubConfig {
+    /// 是否启用后台巡检 (默认: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 每秒最多读取的字节数,0 表示不限速 (默认: 8MB)
+    #[serde(default = "default_scrub_bytes_per_sec")]
+    pub max_bytes_per_sec: u64,
+
+    /// 两轮巡检的间隔秒数 (默认: 86400)
+    #[serde(default = "default_scrub_interval")]
+    pub interval_secs: u64,
+
+    /// 是否检查索引一致性 (默认: true)
+    #[serde(default = "default_scrub_check_indexes")]
+    pub check_indexes: bool,
+}
+
+fn default_scrub_bytes_per_sec() -> u64 { 8 * 1024 * 1024 }
+fn default_scrub_interval() -> u64 { 86400 }
+fn default_scrub_check_indexes() -> bool { true }
+
+impl Default for ScrubConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_bytes_per_sec: default_scrub_bytes_per_sec(),
+            interval_secs: default_scrub_interval(),
+            check_indexes: default_scrub_check_indexes(),
+        }
+    }
+}
+
+/// 冷热数据分层配置
+///
+/// 启用后按间隔重新执行所有已保存的归档策略 (ARCHIVE 语句创建)。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieringConfig {
+    /// 是否周期执行归档策略 (默认: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 两次执行的间隔秒数 (默认: 3600)
+    #[serde(default = "default_tiering_interval")]
+    pub interval_secs: u64,
+}
+
+fn default_tiering_interval() -> u64 { 3600 }
+
+impl Default for TieringConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_tiering_interval(),
+        }
+    }
+}
+
+/// 文档过期配置
+///
+/// 按间隔执行所有集合的过期策略 (ALTER COLLECTION ... EXPIRE AFTER FIELD 设置),
+/// 并清理 TTL 索引中已过期的文档。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiryConfig {
+    /// 是否周期删除过期文档 (默认: true)
+    #[serde(default = "default_expiry_enabled")]
+    pub enabled: bool,
+
+    /// 两次执行的间隔秒数 (默认: 60)
+    #[serde(default = "default_expiry_interval")]
+    pub interval_secs: u64,
+
+    /// TTL 索引清理的间隔秒数 (默认: 60)
+    #[serde(default = "default_expiry_interval")]
+    pub ttl_interval_secs: u64,
+}
+
+fn default_expiry_enabled() -> bool { true }
+fn default_expiry_interval() -> u64 { 60 }
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_expiry_enabled(),
+            interval_secs: default_expiry_interval(),
+            ttl_interval_secs: default_expiry_interval(),
+        }
+    }
+}
+
+/// 自定义函数沙箱配置
+///
+/// 启用后可以用 CREATE FUNCTION 上传 WASM 模块,在查询中以 CALL FUNCTION 调用。
+/// 需要以 `wasm-udf` 特性构建服务器。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UdfConfig {
+    /// 是否启用 (默认: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 单次调用可消耗的燃料,约等于执行的指令数 (默认: 10000000)
+    #[serde(default = "default_udf_fuel")]
+    pub fuel: u64,
+
+    /// 单次调用的线性内存上限(MB) (默认: 16)
+    #[serde(default = "default_udf_memory_mb")]
+    pub memory_limit_mb: usize,
+
+    /// 模块大小上限(KB) (默认: 4096)
+    #[serde(default = "default_udf_module_kb")]
+    pub max_module_kb: usize,
+}
+
+fn default_udf_fuel() -> u64 { 10_000_000 }
+fn default_udf_memory_mb() -> usize { 16 }
+fn default_udf_module_kb() -> usize { 4096 }
+
+impl Default for UdfConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fuel: default_udf_fuel(),
+            memory_limit_mb: default_udf_memory_mb(),
+            max_module_kb: default_udf_module_kb(),
+        }
+    }
+}
+
+/// 索引顾问配置
+///
+/// 启用后记录查询形态,并周期性地把索引建议写入 `_advisor` 集合 (SHOW ADVISOR 查看)。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdvisorConfig {
+    /// 是否启用索引顾问 (默认: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// 两轮分析的间隔秒数 (默认: 600)
+    #[serde(default = "default_advisor_interval")]
+    pub interval_secs: u64,
+
+    /// 估算字段基数时每个集合抽样的文档数 (默认: 1000)
+    #[serde(default = "default_advisor_sample_size")]
+    pub sample_size: usize,
+
+    /// 文档数低于此值的集合不给出建议 (默认: 1000)
+    #[serde(default = "default_advisor_min_documents")]
+    pub min_documents: u64,
+
+    /// 查询形态至少出现多少次才给出建议 (默认: 5)
+    #[serde(default = "default_advisor_min_queries")]
+    pub min_queries: u64,
+
+    /// 查询日志最多保留的查询形态数 (默认: 1024)
+    #[serde(default = "default_advisor_max_shapes")]
+    pub max_query_shapes: usize,
+}
+
+fn default_advisor_interval() -> u64 { 600 }
+fn default_advisor_sample_size() -> usize { 1000 }
+fn default_advisor_min_documents() -> u64 { 1000 }
+fn default_advisor_min_queries() -> u64 { 5 }
+fn default_advisor_max_shapes() -> usize { 1024 }
+
+impl Default for AdvisorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_advisor_interval(),
+            sample_size: default_advisor_sample_size(),
+            min_documents: default_advisor_min_documents(),
+            min_queries: default_advisor_min_queries(),
+            max_query_shapes: default_advisor_max_shapes(),
+        }
+    }
+}
+
+/// 请求调度配置
+///
+/// 交互式/批处理请求分队列按权重调度,并可按集合限制写入速率。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    /// 同时执行的最大请求数 (默认: 64)
+    #[serde(default = "default_max_concurrent")]
+    pub max_concurrent: usize,
+
+    /// 交互式队列权重 (默认: 4)
+    #[serde(default = "default_interactive_weight")]
+    pub interactive_weight: u32,
+
+    /// 批处理队列权重 (默认: 1)
+    #[serde(default = "default_batch_weight")]
+    pub batch_weight: u32,
+
+    /// 未单独配置的集合的写入上限(文档/秒),不设置表示不限速
+    #[serde(default)]
+    pub default_collection_write_limit: Option<u64>,
+
+    /// 按集合的写入上限(文档/秒)
+    #[serde(default)]
+    pub collection_write_limits: HashMap<String, u64>,
+}
+
+fn default_max_concurrent() -> usize { 64 }
+fn default_interactive_weight() -> u32 { 4 }
+fn default_batch_weight() -> u32 { 1 }
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+            interactive_weight: default_interactive_weight(),
+            batch_weight: default_batch_weight(),
+            default_collection_write_limit: None,
+            collection_write_limits: HashMap::new(),
+        }
+    }
+}
+
+/// 启动预检配置
+///
+/// 打开存储引擎前检查数据目录、磁盘空间、文件描述符上限、时钟、端口和数据目录锁。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreflightConfig {
+    /// 是否在启动时执行预检 (默认: true)
+    #[serde(default = "default_preflight_enabled")]
+    pub enabled: bool,
+
+    /// 数据目录所在磁盘的最小可用空间(MB),低于该值拒绝启动 (默认: 1024)
+    #[serde(default = "default_min_free_disk_mb")]
+    pub min_free_disk_mb: u64,
+}
+
+fn default_preflight_enabled() -> bool { true }
+fn default_min_free_disk_mb() -> u64 { 1024 }
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_preflight_enabled(),
+            min_free_disk_mb: default_min_free_disk_mb(),
+        }
+    }
+}
+
+/// 写入通知配置
+///
+/// 启用后把已提交的写入以 `{db, collection, id, op}` 的 JSON 发布到 Redis 频道,
+/// 供外部缓存失效使用。需要以 `redis-notify` 特性构建服务器。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    /// 是否启用 (默认: false)
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Redis 连接地址 (默认: redis://127.0.0.1:6379)
+    #[serde(default = "default_notify_redis_url")]
+    pub redis_url: String,
+
+    /// 发布的频道 (默认: mikudb:changes)
+    #[serde(default = "default_notify_channel")]
+    pub channel: String,
+
+    /// 只通知这些数据库的写入,为空表示全部
+    #[serde(default)]
+    pub databases: Vec<String>,
+}
+
+fn default_notify_redis_url() -> String { "redis://127.0.0.1:6379".to_string() }
+fn default_notify_channel() -> String { "mikudb:changes".to_string() }
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            redis_url: default_notify_redis_url(),
+            channel: default_notify_channel(),
+            databases: Vec::new(),
+        }
+    }
+}
+
 /// 日志配置
 ///
 /// 日志级别、输出文件和轮转策略。
@@ -243,11 +733,16 @@ pub struct LogConfig {
 
     #[serde(default = "default_max_files")]
     pub max_files: usize,
+
+    /// 慢查询阈值(毫秒),执行时间超过该值的语句以指纹形式记录警告日志,0 表示关闭
+    #[serde(default = "default_slow_query_ms")]
+    pub slow_query_ms: u64,
 }
 
 fn default_log_level() -> String { "info".to_string() }
 fn default_rotation() -> String { "daily".to_string() }
 fn default_max_files() -> usize { 7 }
+fn default_slow_query_ms() -> u64 { 1000 }
 
 impl Default for LogConfig {
     fn default() -> Self {
@@ -256,6 +751,7 @@ impl Default for LogConfig {
             file: None,
             rotation: default_rotation(),
             max_files: default_max_files(),
+            slow_query_ms: default_slow_query_ms(),
         }
     }
 }
@@ -321,11 +817,28 @@ impl Default for ServerConfig {
             data_dir: default_data_dir(),
             max_connections: default_max_connections(),
             timeout_ms: default_timeout(),
+            default_database: None,
+            auto_create_collections: default_auto_create_collections(),
+            cursor_timeout_secs: default_cursor_timeout_secs(),
+            max_cursors_per_connection: default_max_cursors_per_connection(),
+            max_cursor_buffer_size: default_max_cursor_buffer_size(),
+            cursor_limit_policy: default_cursor_limit_policy(),
+            persist_state: default_persist_state(),
             storage: StorageConfig::default(),
             auth: AuthConfig::default(),
             tls: TlsConfig::default(),
+            http: HttpConfig::default(),
+            scrub: ScrubConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            tiering: TieringConfig::default(),
+            expiry: ExpiryConfig::default(),
+            advisor: AdvisorConfig::default(),
+            udf: UdfConfig::default(),
+            preflight: PreflightConfig::default(),
+            notify: NotifyConfig::default(),
             log: LogConfig::default(),
             openeuler: OpenEulerConfig::default(),
+            cluster: ClusterConfig::default(),
         }
     }
 }
@@ -367,18 +880,77 @@ impl ServerConfig {
     /// # Returns
     /// 缓存大小(字节数)
     pub fn parse_cache_size(&self) -> usize {
-        let s = self.storage.cache_size.to_uppercase();
-        // 判断单位后缀
-        let (num, mult) = if s.ends_with("GB") {
-            (s.trim_end_matches("GB").trim(), 1024 * 1024 * 1024)
-        } else if s.ends_with("MB") {
-            (s.trim_end_matches("MB").trim(), 1024 * 1024)
-        } else if s.ends_with("KB") {
-            (s.trim_end_matches("KB").trim(), 1024)
-        } else {
-            (s.as_str(), 1)  // 无后缀默认为字节
-        };
-        // 解析数字并乘以单位,失败则使用默认值 1GB
-        num.parse::<usize>().unwrap_or(1024 * 1024 * 1024) * mult
+        // 解析失败则使用默认值 1GB
+        parse_size(&self.storage.cache_size).unwrap_or(1024 * 1024 * 1024)
+    }
+
+    /// # Brief
+    /// 解析文档缓存大小,格式同 `parse_cache_size`
+    ///
+    /// # Returns
+    /// 缓存大小(字节数),解析失败时为默认的 64MB
+    pub fn parse_document_cache_size(&self) -> usize {
+        parse_size(&self.storage.document_cache_size).unwrap_or(64 * 1024 * 1024)
+    }
+
+    /// # Brief
+    /// 解析文档缓存的淘汰策略,无法识别的取值按 "lru" 处理
+    pub fn document_cache_policy(&self) -> EvictionPolicy {
+        self.storage.document_cache_policy.parse().unwrap_or_default()
+    }
+
+    /// # Brief
+    /// BACKUP / RESTORE 的根目录,未配置时为 `data_dir/backups`
+    pub fn backup_dir(&self) -> PathBuf {
+        self.storage.backup_dir.clone().unwrap_or_else(|| self.data_dir.join("backups"))
+    }
+
+    /// # Brief
+    /// 解析每个连接的游标上限,缓冲大小无法解析时为默认的 64MB,
+    /// 无法识别的策略按 "close_oldest" 处理
+    pub fn cursor_limits(&self) -> CursorLimits {
+        CursorLimits {
+            max_cursors: self.max_cursors_per_connection,
+            max_buffered_bytes: parse_size(&self.max_cursor_buffer_size).unwrap_or(64 * 1024 * 1024),
+            policy: self.cursor_limit_policy.parse().unwrap_or_default(),
+        }
+    }
+
+    /// # Brief
+    /// 解析 WAL 落盘策略
+    ///
+    /// `sync_writes = true` 时总是每次提交同步;无法识别的取值按 "interval" 处理。
+    ///
+    /// # Returns
+    /// WAL 落盘策略
+    pub fn wal_sync_policy(&self) -> WalSyncPolicy {
+        if self.storage.sync_writes {
+            return WalSyncPolicy::Always;
+        }
+        match self.storage.wal_sync.to_lowercase().as_str() {
+            "always" => WalSyncPolicy::Always,
+            "never" => WalSyncPolicy::Never,
+            _ => WalSyncPolicy::Interval(std::time::Duration::from_millis(self.storage.wal_sync_interval_ms)),
+        }
     }
 }
+
+/// # Brief
+/// 解析带 GB/MB/KB 后缀的大小字符串,例如 "1GB", "512MB",无后缀时为字节数
+///
+/// # Returns
+/// 字节数,数字无法解析时返回 None
+fn parse_size(value: &str) -> Option<usize> {
+    let s = value.to_uppercase();
+    // 判断单位后缀
+    let (num, mult) = if s.ends_with("GB") {
+        (s.trim_end_matches("GB").trim(), 1024 * 1024 * 1024)
+    } else if s.ends_with("MB") {
+        (s.trim_end_matches("MB").trim(), 1024 * 1024)
+    } else if s.ends_with("KB") {
+        (s.trim_end_matches("KB").trim(), 1024)
+    } else {
+        (s.as_str(), 1)  // 无后缀默认为字节
+    };
+    num.parse::<usize>().ok().map(|n| n * mult)
+}
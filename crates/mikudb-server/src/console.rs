@@ -0,0 +1,14 @@
+//! Web 管理控制台
+//!
+//! 内嵌的单页管理界面,由 HTTP 接口在 `/` 和 `/console` 提供,
+//! 所有数据均通过 `/api/*` REST 接口获取,权限由服务端 RBAC 控制。
+//!
+//! 功能:
+//! - 集合浏览器
+//! - 查询编辑器与结果表格
+//! - 索引列表
+//! - 服务器指标图表
+//! - 用户管理
+
+/// 控制台页面(HTML + 内联 JS/CSS)
+pub const INDEX_HTML: &str = include_str!("../console/index.html");
@@ -0,0 +1,534 @@
+//! 服务器端游标模块
+//!
+//! 查询结果超过批量大小时,服务器保留剩余结果并在响应中返回 `cursor_id`,
+//! 客户端通过 GetMore 逐批读取、KillCursor 提前释放:
+//! - 缓冲游标:执行器已物化的 FIND/AGGREGATE 语句结果,只负责分批发送
+//! - 扫描游标:Find 请求按主键顺序分批读取存储,服务器同一时间只持有一批文档
+//! - 自适应批量:未指定批量大小时每批文档数翻倍(上限 `MAX_ADAPTIVE_BATCH_SIZE`)
+//! - 游标归属于会话(未启用认证时归属于连接),其他会话无法读取或关闭
+//! - 连接关闭时释放其游标,空闲超时的游标在打开新游标时回收
+//! - 按所有者统计打开的游标数和缓冲结果占用的内存,超过上限时关闭最早的游标或拒绝新游标
+
+use crate::{ServerError, ServerResult};
+use dashmap::DashMap;
+use mikudb_common::ObjectId;
+use mikudb_core::MAX_ADAPTIVE_BATCH_SIZE;
+use mikudb_storage::StorageEngine;
+use std::collections::VecDeque;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 游标所有者
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CursorOwner {
+    /// 已认证连接所属的会话
+    Session(u64),
+    /// 未启用认证时的连接
+    Connection(u64),
+}
+
+/// 超过游标上限时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorLimitPolicy {
+    /// 关闭同一所有者最早打开的游标,为新游标腾出空间
+    #[default]
+    CloseOldest,
+    /// 拒绝打开新游标,已有游标不受影响
+    Reject,
+}
+
+impl std::str::FromStr for CursorLimitPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "close_oldest" => Ok(CursorLimitPolicy::CloseOldest),
+            "reject" => Ok(CursorLimitPolicy::Reject),
+            other => Err(format!("Unknown cursor limit policy: {}", other)),
+        }
+    }
+}
+
+/// 每个所有者的游标上限,0 表示不限制
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CursorLimits {
+    /// 同时打开的游标数
+    pub max_cursors: usize,
+    /// 缓冲游标持有的结果总字节数
+    pub max_buffered_bytes: usize,
+    /// 超过上限时的处理方式
+    pub policy: CursorLimitPolicy,
+}
+
+/// 一个所有者的游标占用
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CursorUsage {
+    pub open: usize,
+    pub buffered_bytes: usize,
+}
+
+/// 游标统计
+#[derive(Debug, Clone, Serialize)]
+pub struct CursorStats {
+    pub open: usize,
+    /// 所有游标缓冲结果的总字节数(估算)
+    pub buffered_bytes: usize,
+    /// 因超过上限被关闭的游标数
+    pub closed_by_limit: u64,
+    /// 因超过上限被拒绝的游标数
+    pub rejected: u64,
+}
+
+/// 游标的数据来源
+#[derive(Debug)]
+pub enum CursorSource {
+    /// 已物化的结果,按批取出
+    Buffered(VecDeque<serde_json::Value>),
+    /// 按主键顺序扫描集合,记录上一批最后一个文档的 ID
+    Scan { after: Option<ObjectId> },
+}
+
+/// 服务器端游标
+#[derive(Debug)]
+pub struct ServerCursor {
+    id: u64,
+    owner: CursorOwner,
+    collection: String,
+    source: CursorSource,
+    batch_size: u32,
+    extended_json: bool,
+    exhausted: bool,
+    buffered_bytes: usize,
+    last_access: Instant,
+}
+
+impl ServerCursor {
+    /// # Brief
+    /// 创建游标,ID 在注册到 CursorManager 时分配
+    ///
+    /// # Arguments
+    /// * `owner` - 游标所有者
+    /// * `collection` - 查询的集合名称
+    /// * `source` - 数据来源
+    /// * `batch_size` - 首批文档数,之后按批翻倍
+    pub fn new(owner: CursorOwner, collection: impl Into<String>, source: CursorSource, batch_size: u32) -> Self {
+        let buffered_bytes = match &source {
+            CursorSource::Buffered(buffer) => buffer.iter().map(json_size).sum(),
+            CursorSource::Scan { .. } => 0,
+        };
+        Self {
+            id: 0,
+            owner,
+            collection: collection.into(),
+            source,
+            batch_size: batch_size.max(1),
+            extended_json: false,
+            exhausted: false,
+            buffered_bytes,
+            last_access: Instant::now(),
+        }
+    }
+
+    /// # Brief
+    /// 扫描游标以规范扩展 JSON 返回文档,类型不丢失,适合导出
+    pub fn with_extended_json(mut self, extended_json: bool) -> Self {
+        self.extended_json = extended_json;
+        self
+    }
+
+    /// # Brief
+    /// 获取游标 ID
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// # Brief
+    /// 获取游标所有者
+    pub fn owner(&self) -> CursorOwner {
+        self.owner
+    }
+
+    /// # Brief
+    /// 获取查询的集合名称
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    /// # Brief
+    /// 缓冲结果占用的字节数(按 JSON 编码长度估算),扫描游标为 0
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes
+    }
+
+    /// # Brief
+    /// 结果是否已全部返回
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// # Brief
+    /// 取出下一批结果
+    ///
+    /// 扫描游标会读取存储,应在存储线程池中调用。每批多读一个文档用于判断
+    /// 是否还有后续结果,该文档留到下一批重新读取。
+    ///
+    /// # Arguments
+    /// * `storage` - 存储引擎
+    /// * `batch_size` - 客户端指定的本批大小,None 时使用自适应批量
+    ///
+    /// # Returns
+    /// 本批文档
+    pub fn next_batch(&mut self, storage: &StorageEngine, batch_size: Option<u32>) -> ServerResult<Vec<serde_json::Value>> {
+        if let Some(size) = batch_size {
+            self.batch_size = size.max(1);
+        }
+        let size = self.batch_size as usize;
+        self.batch_size = self.batch_size.saturating_mul(2).min(MAX_ADAPTIVE_BATCH_SIZE).max(self.batch_size);
+
+        let documents = match &mut self.source {
+            CursorSource::Buffered(buffer) => {
+                let documents: Vec<_> = buffer.drain(..size.min(buffer.len())).collect();
+                let drained: usize = documents.iter().map(json_size).sum();
+                self.buffered_bytes = self.buffered_bytes.saturating_sub(drained);
+                self.exhausted = buffer.is_empty();
+                documents
+            }
+            CursorSource::Scan { after } => {
+                let collection = storage.get_collection(&self.collection)?;
+                let mut batch = collection.scan_after(after.as_ref(), size + 1)?;
+                self.exhausted = batch.len() <= size;
+                batch.truncate(size);
+                *after = batch.last().map(|(id, _)| *id);
+                if self.extended_json {
+                    batch
+                        .iter()
+                        .map(|(_, doc)| mikudb_boml::to_extended_json(&doc.to_boml_value()))
+                        .collect()
+                } else {
+                    batch
+                        .iter()
+                        .filter_map(|(_, doc)| serde_json::to_value(doc).ok())
+                        .collect()
+                }
+            }
+        };
+        Ok(documents)
+    }
+}
+
+/// # Brief
+/// 估算 JSON 值编码后的字节数,不实际序列化
+fn json_size(value: &serde_json::Value) -> usize {
+    match value {
+        serde_json::Value::Null => 4,
+        serde_json::Value::Bool(_) => 5,
+        serde_json::Value::Number(_) => 8,
+        serde_json::Value::String(s) => s.len() + 2,
+        serde_json::Value::Array(items) => items.iter().map(|item| json_size(item) + 1).sum::<usize>() + 2,
+        serde_json::Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| key.len() + 4 + json_size(value))
+            .sum::<usize>() + 2,
+    }
+}
+
+/// 游标管理器
+///
+/// 服务器共享一个实例。处理 GetMore 时用 `checkout` 取出游标,读取完成后
+/// 用 `checkin` 放回,读取存储期间不持有 DashMap 的锁。
+pub struct CursorManager {
+    cursors: DashMap<u64, ServerCursor>,
+    next_id: AtomicU64,
+    timeout: Duration,
+    limits: CursorLimits,
+    closed_by_limit: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl CursorManager {
+    /// # Brief
+    /// 创建游标管理器
+    ///
+    /// # Arguments
+    /// * `timeout` - 游标空闲超时时间
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            cursors: DashMap::new(),
+            next_id: AtomicU64::new(1),
+            timeout,
+            limits: CursorLimits::default(),
+            closed_by_limit: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// # Brief
+    /// 设置每个所有者的游标上限
+    pub fn with_limits(mut self, limits: CursorLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// # Brief
+    /// 注册游标并分配 ID,同时回收空闲超时的游标
+    ///
+    /// 所有者的游标数或缓冲字节数超过上限时,按策略关闭其最早打开的游标,
+    /// 或拒绝新游标。单个游标的缓冲结果超过字节上限时总是拒绝。
+    ///
+    /// # Returns
+    /// 游标 ID;拒绝时返回 `ServerError::CursorLimit`
+    pub fn register(&self, mut cursor: ServerCursor) -> ServerResult<u64> {
+        self.cleanup_expired();
+        self.make_room(&cursor)?;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        cursor.id = id;
+        cursor.last_access = Instant::now();
+        self.cursors.insert(id, cursor);
+        Ok(id)
+    }
+
+    /// # Brief
+    /// 检查上限,需要时关闭所有者最早打开的游标
+    fn make_room(&self, cursor: &ServerCursor) -> ServerResult<()> {
+        let limits = self.limits;
+        let max_bytes = if limits.max_buffered_bytes == 0 { usize::MAX } else { limits.max_buffered_bytes };
+        let max_cursors = if limits.max_cursors == 0 { usize::MAX } else { limits.max_cursors };
+        if cursor.buffered_bytes > max_bytes {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(ServerError::CursorLimit(format!(
+                "result holds {} bytes, above the per-connection cursor buffer limit of {} bytes",
+                cursor.buffered_bytes, max_bytes
+            )));
+        }
+
+        // 按打开顺序(ID 递增)排列所有者的游标
+        let mut owned: Vec<(u64, usize)> = self
+            .cursors
+            .iter()
+            .filter(|entry| entry.owner == cursor.owner)
+            .map(|entry| (entry.id, entry.buffered_bytes))
+            .collect();
+        owned.sort_unstable();
+        let mut open = owned.len();
+        let mut bytes: usize = owned.iter().map(|(_, bytes)| bytes).sum();
+        let mut oldest = owned.into_iter();
+        while open + 1 > max_cursors || bytes.saturating_add(cursor.buffered_bytes) > max_bytes {
+            if limits.policy == CursorLimitPolicy::Reject {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(ServerError::CursorLimit(format!(
+                    "connection has {} open cursors holding {} bytes (limits: {} cursors, {} bytes)",
+                    open, bytes, limits.max_cursors, limits.max_buffered_bytes
+                )));
+            }
+            let Some((id, freed)) = oldest.next() else {
+                break;
+            };
+            if self.cursors.remove(&id).is_some() {
+                self.closed_by_limit.fetch_add(1, Ordering::Relaxed);
+            }
+            open -= 1;
+            bytes -= freed;
+        }
+        Ok(())
+    }
+
+    /// # Brief
+    /// 取出属于 `owner` 的游标
+    ///
+    /// 游标不存在、已超时或属于其他所有者时返回 None;超时的游标同时被删除。
+    pub fn checkout(&self, id: u64, owner: CursorOwner) -> Option<ServerCursor> {
+        let (_, cursor) = self.cursors.remove_if(&id, |_, cursor| cursor.owner == owner)?;
+        (cursor.last_access.elapsed() <= self.timeout).then_some(cursor)
+    }
+
+    /// # Brief
+    /// 放回游标;结果已全部返回的游标直接释放
+    ///
+    /// # Returns
+    /// 游标仍然有效时返回其 ID
+    pub fn checkin(&self, mut cursor: ServerCursor) -> Option<u64> {
+        if cursor.exhausted {
+            return None;
+        }
+        let id = cursor.id;
+        cursor.last_access = Instant::now();
+        self.cursors.insert(id, cursor);
+        Some(id)
+    }
+
+    /// # Brief
+    /// 关闭属于 `owner` 的指定游标
+    ///
+    /// # Returns
+    /// 实际关闭的游标 ID
+    pub fn kill(&self, ids: &[u64], owner: CursorOwner) -> Vec<u64> {
+        ids.iter()
+            .filter(|id| self.cursors.remove_if(id, |_, cursor| cursor.owner == owner).is_some())
+            .copied()
+            .collect()
+    }
+
+    /// # Brief
+    /// 关闭 `owner` 的所有游标,连接断开时调用
+    ///
+    /// # Returns
+    /// 关闭的游标数量
+    pub fn close_owner(&self, owner: CursorOwner) -> usize {
+        let before = self.cursors.len();
+        self.cursors.retain(|_, cursor| cursor.owner != owner);
+        before.saturating_sub(self.cursors.len())
+    }
+
+    /// # Brief
+    /// 回收空闲超时的游标
+    ///
+    /// # Returns
+    /// 回收的游标数量
+    pub fn cleanup_expired(&self) -> usize {
+        let before = self.cursors.len();
+        self.cursors.retain(|_, cursor| cursor.last_access.elapsed() <= self.timeout);
+        before.saturating_sub(self.cursors.len())
+    }
+
+    /// # Brief
+    /// 当前打开的游标数量
+    pub fn open_count(&self) -> usize {
+        self.cursors.len()
+    }
+
+    /// # Brief
+    /// 获取 `owner` 的游标占用,不含正在读取的游标
+    pub fn usage(&self, owner: CursorOwner) -> CursorUsage {
+        self.cursors
+            .iter()
+            .filter(|entry| entry.owner == owner)
+            .fold(CursorUsage::default(), |usage, entry| CursorUsage {
+                open: usage.open + 1,
+                buffered_bytes: usage.buffered_bytes + entry.buffered_bytes,
+            })
+    }
+
+    /// # Brief
+    /// 获取游标统计
+    pub fn stats(&self) -> CursorStats {
+        CursorStats {
+            open: self.cursors.len(),
+            buffered_bytes: self.cursors.iter().map(|entry| entry.buffered_bytes).sum(),
+            closed_by_limit: self.closed_by_limit.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    /// # Brief
+    /// 恢复上次运行保存的限额计数(服务器重启时),与启动后的计数累加
+    ///
+    /// # Arguments
+    /// * `closed_by_limit` - 因超过上限被关闭的游标数
+    /// * `rejected` - 因超过上限被拒绝的游标数
+    pub fn restore_counters(&self, closed_by_limit: u64, rejected: u64) {
+        self.closed_by_limit.fetch_add(closed_by_limit, Ordering::Relaxed);
+        self.rejected.fetch_add(rejected, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mikudb_boml::Document;
+    use mikudb_storage::StorageOptions;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_scan_cursor_owner_and_batches() {
+        let dir = tempdir().unwrap();
+        let storage = StorageEngine::open(StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let collection = storage.create_collection("events").unwrap();
+        let mut docs: Vec<Document> = (0..10)
+            .map(|i| {
+                let mut doc = Document::new();
+                doc.insert("n", i);
+                doc
+            })
+            .collect();
+        collection.insert_many(&mut docs).unwrap();
+
+        let manager = CursorManager::new(Duration::from_secs(60));
+        let owner = CursorOwner::Session(1);
+        let mut cursor = ServerCursor::new(owner, "events", CursorSource::Scan { after: None }, 2);
+        assert_eq!(cursor.next_batch(&storage, None).unwrap().len(), 2);
+        let id = manager.register(cursor).unwrap();
+
+        assert!(manager.checkout(id, CursorOwner::Session(2)).is_none());
+        assert!(manager.kill(&[id], CursorOwner::Connection(1)).is_empty());
+
+        let mut returned = 2;
+        let mut next = Some(id);
+        while let Some(id) = next {
+            let mut cursor = manager.checkout(id, owner).unwrap();
+            returned += cursor.next_batch(&storage, None).unwrap().len();
+            next = manager.checkin(cursor);
+        }
+        assert_eq!(returned, 10);
+        assert_eq!(manager.open_count(), 0);
+
+        let id = manager.register(ServerCursor::new(owner, "events", CursorSource::Scan { after: None }, 2)).unwrap();
+        assert_eq!(manager.close_owner(owner), 1);
+        assert!(manager.checkout(id, owner).is_none());
+    }
+
+    fn buffered(owner: CursorOwner, count: usize) -> ServerCursor {
+        let documents = (0..count).map(|i| serde_json::json!({"n": i})).collect();
+        ServerCursor::new(owner, "events", CursorSource::Buffered(documents), 1)
+    }
+
+    #[test]
+    fn test_cursor_limits() {
+        let owner = CursorOwner::Connection(1);
+        let size = buffered(owner, 10).buffered_bytes();
+        assert!(size > 0);
+
+        let manager = CursorManager::new(Duration::from_secs(60)).with_limits(CursorLimits {
+            max_cursors: 2,
+            max_buffered_bytes: size * 2,
+            policy: CursorLimitPolicy::CloseOldest,
+        });
+        let first = manager.register(buffered(owner, 10)).unwrap();
+        let second = manager.register(buffered(owner, 5)).unwrap();
+        let other = manager.register(buffered(CursorOwner::Connection(2), 10)).unwrap();
+        // 字节数超限,关闭最早的游标
+        let third = manager.register(buffered(owner, 10)).unwrap();
+        assert!(manager.checkout(first, owner).is_none());
+        assert_eq!(manager.usage(owner), CursorUsage { open: 2, buffered_bytes: size + size / 2 });
+        assert!(matches!(manager.register(buffered(owner, 30)), Err(ServerError::CursorLimit(_))));
+
+        // 缓冲游标取出一批后释放对应的字节数
+        let dir = tempdir().unwrap();
+        let storage = StorageEngine::open(StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let mut cursor = manager.checkout(second, owner).unwrap();
+        cursor.next_batch(&storage, Some(3)).unwrap();
+        assert_eq!(cursor.buffered_bytes(), size / 5);
+        manager.checkin(cursor);
+        assert!(manager.checkout(third, owner).is_some());
+        assert!(manager.checkout(other, CursorOwner::Connection(2)).is_some());
+
+        let manager = CursorManager::new(Duration::from_secs(60)).with_limits(CursorLimits {
+            max_cursors: 1,
+            max_buffered_bytes: 0,
+            policy: CursorLimitPolicy::Reject,
+        });
+        let first = manager.register(buffered(owner, 10)).unwrap();
+        assert!(matches!(manager.register(buffered(owner, 1)), Err(ServerError::CursorLimit(_))));
+        assert!(manager.checkout(first, owner).is_some());
+        let stats = manager.stats();
+        assert_eq!((stats.closed_by_limit, stats.rejected), (0, 1));
+    }
+}
@@ -2,20 +2,36 @@
 //!
 //! 本模块负责处理来自客户端的所有请求,包括认证、查询、增删改查等操作。
 //! 使用 MikuWire 二进制协议进行通信,支持异步处理和会话管理。
+//!
+//! 带 `FLAG_CONCURRENT` 标志的请求在连接内并发执行,完成即返回响应(可能乱序);
+//! 不带标志的请求等之前的请求全部完成后按顺序执行,保持原有的顺序语义。
 
-use crate::auth::UserManager;
+use crate::auth::{check_permission, statement_permission, Permission, User, UserManager};
+use crate::cluster::ClusterManager;
 use crate::config::ServerConfig;
+use crate::cursor::{CursorManager, CursorOwner, CursorSource, ServerCursor};
 use crate::protocol::*;
+use crate::scheduler::{self, Priority, RequestScheduler, SchedulerPermit};
 use crate::session::SessionManager;
+use crate::storage_pool::StoragePool;
 use crate::{ServerError, ServerResult};
 use bytes::BytesMut;
-use mikudb_query::{Parser, QueryExecutor};
-use mikudb_storage::StorageEngine;
-use std::sync::atomic::{AtomicU32, Ordering};
+use futures::stream::{FuturesUnordered, StreamExt};
+use indexmap::IndexMap;
+use mikudb_query::{FunctionRegistry, OpStats, Params, Parser, PreparedStatement, QueryExecutor, QueryLog, Statement};
+use mikudb_storage::{
+    qualified_collection_name, BulkWriteResult, OperationResult, StorageEngine, StorageError, WriteError,
+    WriteMetadata, WriteOperation, DEFAULT_DATABASE,
+};
+use parking_lot::{Mutex, RwLock};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedWriteHalf;
 use tokio::net::TcpStream;
-use tracing::{error, trace};
+use tracing::{error, trace, warn};
 
 #[cfg(feature = "tls")]
 use crate::network::StreamType;
@@ -25,6 +41,12 @@ use tokio_rustls::server::TlsStream;
 /// 全局请求 ID 计数器,用于为每个响应生成唯一 ID
 static REQUEST_ID_COUNTER: AtomicU32 = AtomicU32::new(1);
 
+/// 执行器因中断标志退出时 `execute_statement` 返回的错误消息
+const INTERRUPTED_MESSAGE: &str = "Execution error: Operation interrupted";
+
+/// 每个连接缓存的带参数语句数量上限
+const PREPARED_CACHE_SIZE: usize = 128;
+
 /// 客户端连接处理器
 ///
 /// 每个客户端连接对应一个 ClientHandler 实例,负责处理该连接的所有请求。
@@ -32,22 +54,52 @@ static REQUEST_ID_COUNTER: AtomicU32 = AtomicU32::new(1);
 pub struct ClientHandler {
     /// 连接 ID,用于日志追踪
     conn_id: u64,
-    /// TCP 连接流
-    stream: TcpStream,
+    /// TCP 连接流,`handle` 开始时取出并拆分为读写两半
+    stream: Option<TcpStream>,
     /// 存储引擎实例(共享)
     storage: Arc<StorageEngine>,
     /// 会话管理器(共享)
     session_manager: Arc<SessionManager>,
     /// 用户管理器(共享)
     user_manager: Arc<UserManager>,
+    /// 请求调度器(共享)
+    scheduler: Arc<RequestScheduler>,
+    /// 存储线程池(共享)
+    storage_pool: Arc<StoragePool>,
+    /// 查询日志(启用索引顾问时存在)
+    query_log: Option<Arc<QueryLog>>,
+    /// 按集合的操作统计(共享)
+    op_stats: Arc<OpStats>,
+    /// 自定义函数注册表(共享)
+    functions: Arc<FunctionRegistry>,
     /// 服务器配置
     config: ServerConfig,
     /// 当前会话 ID(认证成功后设置)
     session_id: Option<u64>,
-    /// 当前使用的数据库名称
-    current_database: Option<String>,
+    /// 当前使用的数据库名称,并发执行的 USE 语句也会修改
+    current_database: RwLock<Option<String>>,
     /// 是否已通过认证
     authenticated: bool,
+    /// 认证通过的用户,用于检查语句权限;未启用认证时为 None,不做权限检查
+    user: Option<User>,
+    /// 服务器端游标(共享),本连接打开的游标在连接关闭时一并释放
+    cursors: Arc<CursorManager>,
+    /// 集群成员管理(共享)
+    cluster: Arc<ClusterManager>,
+    /// 会话变量 return_stats:查询响应是否附带语句的资源统计
+    return_stats: AtomicBool,
+    /// 本连接执行过的带参数语句,按语句文本缓存解析结果,最近使用的排在最后
+    prepared: Mutex<IndexMap<String, Arc<PreparedStatement>>>,
+}
+
+impl Drop for ClientHandler {
+    /// 连接关闭时释放本连接(会话)打开的游标
+    fn drop(&mut self) {
+        let closed = self.cursors.close_owner(self.cursor_owner());
+        if closed > 0 {
+            trace!("Closed {} cursors of connection {}", closed, self.conn_id);
+        }
+    }
 }
 
 impl ClientHandler {
@@ -60,107 +112,283 @@ impl ClientHandler {
     /// * `storage` - 存储引擎实例
     /// * `session_manager` - 会话管理器
     /// * `user_manager` - 用户管理器
+    /// * `scheduler` - 请求调度器
+    /// * `storage_pool` - 存储线程池
+    /// * `query_log` - 查询日志,供索引顾问分析
+    /// * `op_stats` - 按集合的操作统计
+    /// * `functions` - 自定义函数注册表
+    /// * `cursors` - 服务器端游标管理器
+    /// * `cluster` - 集群成员管理器
     /// * `config` - 服务器配置
     ///
     /// # Returns
     /// 新的 ClientHandler 实例
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         conn_id: u64,
         stream: TcpStream,
         storage: Arc<StorageEngine>,
         session_manager: Arc<SessionManager>,
         user_manager: Arc<UserManager>,
+        scheduler: Arc<RequestScheduler>,
+        storage_pool: Arc<StoragePool>,
+        query_log: Option<Arc<QueryLog>>,
+        op_stats: Arc<OpStats>,
+        functions: Arc<FunctionRegistry>,
+        cursors: Arc<CursorManager>,
+        cluster: Arc<ClusterManager>,
         config: ServerConfig,
     ) -> Self {
         // 如果认证未启用,则默认为已认证状态
         let auth_enabled = config.auth.enabled;
+        // 未执行 USE 的会话使用配置的默认数据库
+        let current_database = config.default_database.clone();
         Self {
             conn_id,
-            stream,
+            stream: Some(stream),
             storage,
             session_manager,
             user_manager,
+            scheduler,
+            storage_pool,
+            query_log,
+            op_stats,
+            functions,
             config,
             session_id: None,
-            current_database: None,
+            current_database: RwLock::new(current_database),
             authenticated: !auth_enabled,
+            user: None,
+            cursors,
+            cluster,
+            return_stats: AtomicBool::new(false),
+            prepared: Mutex::new(IndexMap::new()),
+        }
+    }
+
+    /// # Brief
+    /// 本连接打开的游标的所有者:已认证时为会话,否则为连接
+    fn cursor_owner(&self) -> CursorOwner {
+        match self.session_id {
+            Some(id) => CursorOwner::Session(id),
+            None => CursorOwner::Connection(self.conn_id),
         }
     }
 
+    /// # Brief
+    /// 本连接的当前数据库,未执行 USE 且未配置默认数据库时为 `default`
+    fn database(&self) -> String {
+        self.current_database.read().as_deref().unwrap_or(DEFAULT_DATABASE).to_string()
+    }
+
+    /// # Brief
+    /// 切换本连接及其会话的当前数据库,调用方需确认数据库存在
+    fn use_database(&self, database: String) {
+        if let Some(session) = self.session_id.and_then(|id| self.session_manager.get_session(id)) {
+            session.set_database(database.clone());
+        }
+        *self.current_database.write() = Some(database);
+    }
+
+    /// # Brief
+    /// 检查当前用户对数据库的权限
+    ///
+    /// 未启用认证时不做检查。
+    ///
+    /// # Arguments
+    /// * `database` - 数据库名,空字符串表示服务器级操作
+    /// * `permission` - 需要的权限
+    ///
+    /// # Returns
+    /// 无权限时返回响应中的错误信息
+    fn authorize(&self, database: &str, permission: Permission) -> Result<(), String> {
+        if !self.config.auth.enabled {
+            return Ok(());
+        }
+        match &self.user {
+            Some(user) if check_permission(user, database, "", permission) => Ok(()),
+            Some(user) => Err(format!(
+                "Permission denied: user '{}' lacks {:?} permission",
+                user.username, permission
+            )),
+            None => Err("Not authenticated".to_string()),
+        }
+    }
+
+    /// # Brief
+    /// 当前数据库中的集合在存储中的名称,用于直接读写集合的请求
+    fn qualified(&self, collection: &str) -> String {
+        qualified_collection_name(&self.database(), collection)
+    }
+
     /// # Brief
     /// 处理客户端连接的主循环
     ///
     /// 持续读取客户端消息并处理,直到连接关闭或发生错误。
     /// 使用 MikuWire 协议进行消息帧解析。
     ///
+    /// 带 `FLAG_CONCURRENT` 的请求读到即开始执行,最多同时执行 `MAX_CONCURRENT_REQUESTS` 个,
+    /// 完成一个返回一个;遇到按顺序执行的请求时暂停读取,等进行中的请求全部完成后再执行它。
+    ///
     /// # Returns
     /// 连接关闭或发生错误时返回 ServerResult
     pub async fn handle(mut self) -> ServerResult<()> {
+        let stream = self.stream.take().ok_or(ServerError::ConnectionClosed)?;
+        let (mut reader, mut writer) = stream.into_split();
         // 创建 64KB 缓冲区用于接收数据
         let mut buf = BytesMut::with_capacity(64 * 1024);
+        // 已解析但 payload 尚未收全的消息头,大消息需要多次读取
+        let mut pending: Option<MessageHeader> = None;
+        // 已收全、尚未开始执行的消息
+        let mut queued: VecDeque<Message> = VecDeque::new();
 
         loop {
-            // 从 TCP 流读取数据到缓冲区
-            let bytes_read = self.stream.read_buf(&mut buf).await?;
-            if bytes_read == 0 {
-                // 客户端关闭连接
-                return Err(ServerError::ConnectionClosed);
-            }
-
-            // 尝试从缓冲区解析完整的消息
-            while let Some(header) = MessageHeader::decode(&mut buf)? {
-                // 检查缓冲区是否包含完整的 payload
-                if buf.len() < header.payload_len as usize {
-                    break; // 需要等待更多数据
+            // 队首按顺序执行的请求: 此时没有进行中的并发请求
+            while let Some(message) = queued.pop_front() {
+                if runs_concurrently(&message.header) {
+                    queued.push_front(message);
+                    break;
                 }
+                let client_request_id = message.header.request_id;
+                let result = self.process_sequential(message).await;
+                let response = self.response_message(result, client_request_id);
+                send(&mut writer, response).await?;
+            }
 
-                // 提取 payload 并构造消息
-                let payload = buf.split_to(header.payload_len as usize).to_vec();
-                let client_request_id = header.request_id;
-                let message = Message { header, payload };
-
-                // 处理消息并捕获错误
-                let response = match self.process_message(message).await {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        error!("Error processing message from conn {}: {}", self.conn_id, e);
-                        let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
-                        Message::error(request_id, client_request_id, &format!("Internal error: {}", e))
+            // 并发执行后续请求,直到遇到下一个按顺序执行的请求且进行中的请求全部完成
+            let this = &self;
+            let mut in_flight = FuturesUnordered::new();
+            loop {
+                let mut barrier = false;
+                while let Some(message) = queued.front() {
+                    if !runs_concurrently(&message.header) {
+                        barrier = true;
+                        break;
                     }
-                };
+                    if in_flight.len() >= MAX_CONCURRENT_REQUESTS {
+                        break;
+                    }
+                    let message = queued.pop_front().expect("queue is not empty");
+                    in_flight.push(async move {
+                        let client_request_id = message.header.request_id;
+                        let result = this.process_message(message).await;
+                        this.response_message(result, client_request_id)
+                    });
+                }
+                if barrier && in_flight.is_empty() {
+                    break;
+                }
+
+                let accepting = !barrier && in_flight.len() < MAX_CONCURRENT_REQUESTS;
+                tokio::select! {
+                    Some(response) = in_flight.next(), if !in_flight.is_empty() => {
+                        send(&mut writer, response).await?;
+                    }
+                    read = reader.read_buf(&mut buf), if accepting => {
+                        if read? == 0 {
+                            // 客户端关闭连接
+                            return Err(ServerError::ConnectionClosed);
+                        }
+                        decode_messages(&mut buf, &mut pending, &mut queued)?;
+                    }
+                }
+            }
+        }
+    }
 
-                // 编码并发送响应
-                let encoded = response.encode();
-                self.stream.write_all(&encoded).await?;
-                self.stream.flush().await?;
+    /// # Brief
+    /// 将处理结果转换为响应消息
+    ///
+    /// # Arguments
+    /// * `result` - 处理结果
+    /// * `client_request_id` - 客户端请求 ID
+    fn response_message(&self, result: ServerResult<Message>, client_request_id: u32) -> Message {
+        match result {
+            Ok(msg) => msg,
+            // 校验错误属于请求本身的问题,以失败的查询响应返回结构化详情
+            Err(e) if e.validation_details().is_some() => {
+                let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+                let response = QueryResponse {
+                    success: false,
+                    affected: 0,
+                    documents: vec![],
+                    cursor_id: None,
+                    message: Some(e.to_string()),
+                    errors: e.validation_details().map(<[_]>::to_vec).unwrap_or_default(),
+                    stats: None,
+                    error_kind: Some(ErrorKind::Execution),
+                };
+                let payload = serde_json::to_vec(&response).unwrap_or_default();
+                Message::response(request_id, client_request_id, payload)
+            }
+            Err(e) => {
+                error!("Error processing message from conn {}: {}", self.conn_id, e);
+                let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+                Message::error(request_id, client_request_id, &format!("Internal error: {}", e))
             }
         }
     }
 
+    /// # Brief
+    /// 处理按顺序执行的消息,认证会修改连接状态,只在这里处理
+    ///
+    /// # Arguments
+    /// * `msg` - 客户端消息
+    ///
+    /// # Returns
+    /// 响应消息
+    async fn process_sequential(&mut self, msg: Message) -> ServerResult<Message> {
+        if msg.header.opcode == OpCode::Auth {
+            let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+            return self.handle_auth(&msg.payload, request_id, msg.header.request_id).await;
+        }
+        self.process_message(msg).await
+    }
+
     /// # Brief
     /// 处理单个客户端消息
     ///
     /// 根据操作码(OpCode)分发到不同的处理函数,并进行认证检查。
+    /// 认证由 `process_sequential` 处理,这里只处理不修改认证状态的消息,可以并发调用。
     ///
     /// # Arguments
     /// * `msg` - 客户端消息
     ///
     /// # Returns
     /// 响应消息
-    async fn process_message(&mut self, msg: Message) -> ServerResult<Message> {
+    async fn process_message(&self, msg: Message) -> ServerResult<Message> {
         let request_id = REQUEST_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
 
         trace!("Processing {:?} from conn {}", msg.header.opcode, self.conn_id);
 
+        // 数据操作需要先从调度器获取执行槽位,许可在本函数返回时释放
+        let _permit = match msg.header.opcode {
+            OpCode::Query
+            | OpCode::Insert
+            | OpCode::Find
+            | OpCode::Update
+            | OpCode::Delete
+            | OpCode::BulkWrite
+            | OpCode::Execute
+                if self.authenticated =>
+            {
+                Some(self.acquire_slot(msg.header.flags).await)
+            }
+            _ => None,
+        };
+
         match msg.header.opcode {
             // Ping-Pong 心跳检测
             OpCode::Ping => {
-                Ok(Message::new(OpCode::Pong, request_id, vec![]))
+                let mut pong = Message::new(OpCode::Pong, request_id, vec![]);
+                pong.header.response_to = msg.header.request_id;
+                Ok(pong)
             }
 
-            // 用户认证
-            OpCode::Auth => {
-                self.handle_auth(&msg.payload, request_id, msg.header.request_id).await
+            // 握手: 返回服务器版本和能力,无需认证
+            OpCode::Hello => {
+                let payload = serde_json::to_vec(&self.hello()).unwrap_or_default();
+                Ok(Message::response(request_id, msg.header.request_id, payload))
             }
 
             // 以下操作均需要认证
@@ -171,6 +399,13 @@ impl ClientHandler {
                 self.handle_query(&msg.payload, request_id, msg.header.request_id).await
             }
 
+            OpCode::Execute => {
+                if !self.authenticated {
+                    return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
+                }
+                self.handle_execute(&msg.payload, request_id, msg.header.request_id).await
+            }
+
             OpCode::Insert => {
                 if !self.authenticated {
                     return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
@@ -199,19 +434,78 @@ impl ClientHandler {
                 self.handle_delete(&msg.payload, request_id, msg.header.request_id).await
             }
 
+            OpCode::BulkWrite => {
+                if !self.authenticated {
+                    return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
+                }
+                self.handle_bulk_write(&msg.payload, request_id, msg.header.request_id).await
+            }
+
+            OpCode::KillOp => {
+                if !self.authenticated {
+                    return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
+                }
+                self.handle_kill_op(&msg.payload, request_id, msg.header.request_id)
+            }
+
+            OpCode::GetMore | OpCode::CursorNext => {
+                if !self.authenticated {
+                    return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
+                }
+                self.handle_get_more(&msg.payload, request_id, msg.header.request_id).await
+            }
+
+            OpCode::KillCursor | OpCode::CursorClose => {
+                if !self.authenticated {
+                    return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
+                }
+                let cursor_ids = if msg.header.opcode == OpCode::CursorClose {
+                    let close_req: CursorCloseRequest = serde_json::from_slice(&msg.payload)
+                        .map_err(|e| ServerError::Protocol(format!("Invalid cursor close request: {}", e)))?;
+                    vec![close_req.cursor_id]
+                } else {
+                    let kill_req: KillCursorRequest = serde_json::from_slice(&msg.payload)
+                        .map_err(|e| ServerError::Protocol(format!("Invalid kill cursor request: {}", e)))?;
+                    kill_req.cursor_ids
+                };
+                let killed = self.cursors.kill(&cursor_ids, self.cursor_owner());
+                let response = QueryResponse {
+                    success: !killed.is_empty(),
+                    affected: killed.len() as u64,
+                    documents: vec![],
+                    cursor_id: None,
+                    message: killed.is_empty().then(|| "Cursor not found".to_string()),
+                    errors: vec![],
+                    stats: None,
+                    error_kind: None,
+                };
+                let payload = serde_json::to_vec(&response).unwrap_or_default();
+                Ok(Message::response(request_id, msg.header.request_id, payload))
+            }
+
             OpCode::UseDatabase => {
                 if !self.authenticated {
                     return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
                 }
                 // 切换当前数据库
                 let db_name = String::from_utf8_lossy(&msg.payload).to_string();
-                self.current_database = Some(db_name.clone());
+                if !self.storage.database_exists(&db_name)? {
+                    return Ok(Message::error(
+                        request_id,
+                        msg.header.request_id,
+                        &format!("Database not found: {}", db_name),
+                    ));
+                }
+                self.use_database(db_name.clone());
                 let response = QueryResponse {
                     success: true,
                     affected: 0,
                     documents: vec![],
                     cursor_id: None,
                     message: Some(format!("Switched to database {}", db_name)),
+                    errors: vec![],
+                    stats: None,
+                    error_kind: None,
                 };
                 let payload = serde_json::to_vec(&response).unwrap_or_default();
                 Ok(Message::response(request_id, msg.header.request_id, payload))
@@ -231,12 +525,64 @@ impl ClientHandler {
                 self.handle_list_collections(request_id, msg.header.request_id).await
             }
 
+            OpCode::BackupIncremental => {
+                if !self.authenticated {
+                    return Ok(Message::error(request_id, msg.header.request_id, "Not authenticated"));
+                }
+                if let Err(message) = self.authorize("", Permission::Admin) {
+                    return Ok(Message::error(request_id, msg.header.request_id, &message));
+                }
+                self.handle_backup_incremental(&msg.payload, request_id, msg.header.request_id).await
+            }
+
             _ => {
                 Ok(Message::error(request_id, msg.header.request_id, "Unsupported operation"))
             }
         }
     }
 
+    /// # Brief
+    /// 构造握手响应
+    fn hello(&self) -> HelloResponse {
+        HelloResponse {
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_versions: SUPPORTED_PROTOCOL_VERSIONS.to_vec(),
+            max_message_size: MAX_MESSAGE_SIZE,
+            features: ServerFeatures {
+                tls: cfg!(feature = "tls") && self.config.tls.enabled,
+                compression: vec![],
+                cluster: self.cluster.identity().is_some(),
+                wasm_udf: self.functions.sandbox_limits().is_some(),
+                cursors: true,
+                multiplexing: true,
+                bulk_write: true,
+                prepared_statements: true,
+            },
+            auth_required: self.config.auth.enabled,
+            authenticated: self.authenticated,
+            default_database: self.config.default_database.clone(),
+        }
+    }
+
+    /// # Brief
+    /// 按请求优先级获取执行槽位
+    ///
+    /// 消息头带有批处理标志时使用批处理优先级,否则使用会话默认优先级。
+    ///
+    /// # Arguments
+    /// * `flags` - 消息头标志位
+    async fn acquire_slot(&self, flags: u16) -> SchedulerPermit {
+        let priority = if flags & FLAG_BATCH_PRIORITY != 0 {
+            Priority::Batch
+        } else {
+            self.session_id
+                .and_then(|id| self.session_manager.get_session(id))
+                .map(|session| session.priority())
+                .unwrap_or_default()
+        };
+        self.scheduler.acquire(priority).await
+    }
+
     /// # Brief
     /// 处理用户认证请求
     ///
@@ -256,12 +602,21 @@ impl ClientHandler {
         match self.user_manager.authenticate(&auth_req.username, &auth_req.password).await {
             Ok(user) => {
                 let session = self.session_manager.create_session(auth_req.username.clone());
+                if let Some(priority) = auth_req.priority {
+                    session.set_priority(priority);
+                }
                 self.session_id = Some(session.id());
                 self.authenticated = true;
+                self.user = Some(user);
 
+                let mut current_database = self.current_database.write();
                 if let Some(db) = auth_req.database {
-                    self.current_database = Some(db);
+                    *current_database = Some(db);
                 }
+                if let Some(db) = current_database.as_ref() {
+                    session.set_database(db.clone());
+                }
+                drop(current_database);
 
                 let response = AuthResponse {
                     success: true,
@@ -288,6 +643,7 @@ impl ClientHandler {
     /// 处理 MQL 查询请求
     ///
     /// 解析 MQL 语句,执行查询并返回结果。支持 CRUD、DDL、聚合等各种操作。
+    /// 请求带 `params` 时与 Execute 请求相同,经连接的语句缓存绑定位置参数。
     ///
     /// # Arguments
     /// * `payload` - 查询请求数据(JSON 格式)
@@ -296,334 +652,485 @@ impl ClientHandler {
     ///
     /// # Returns
     /// 查询响应消息
-    async fn handle_query(&mut self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+    async fn handle_query(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
         // 解析查询请求
         let query_req: QueryRequest = match serde_json::from_slice(payload) {
             Ok(req) => req,
             Err(e) => {
-                let error_response = QueryResponse {
-                    success: false,
-                    affected: 0,
-                    documents: vec![],
-                    cursor_id: None,
-                    message: Some(format!("Invalid query request: {}", e)),
-                };
+                let error_response = failure(ErrorKind::Parse, format!("Invalid query request: {}", e));
                 let payload = serde_json::to_vec(&error_response).unwrap_or_default();
                 return Ok(Message::response(request_id, response_to, payload));
             }
         };
 
         // 解析 MQL 语句
-        let statement = match Parser::parse(&query_req.query) {
+        let parsing = Instant::now();
+        let parsed = if query_req.params.is_empty() {
+            Parser::parse(&query_req.query).map_err(|e| (ErrorKind::Parse, format!("Parse error: {}", e)))
+        } else {
+            query_req
+                .params()
+                .map_err(|message| (ErrorKind::Parse, message))
+                .and_then(|params| self.bind(&query_req.query, &params))
+        };
+        let statement = match parsed {
             Ok(stmt) => stmt,
-            Err(e) => {
-                let error_response = QueryResponse {
-                    success: false,
-                    affected: 0,
-                    documents: vec![],
-                    cursor_id: None,
-                    message: Some(format!("Parse error: {}", e)),
-                };
+            Err((kind, message)) => {
+                let error_response = failure(kind, message);
                 let payload = serde_json::to_vec(&error_response).unwrap_or_default();
                 return Ok(Message::response(request_id, response_to, payload));
             }
         };
 
-        use mikudb_query::Statement;
+        if query_req.format_only {
+            let response = QueryResponse {
+                success: true,
+                affected: 0,
+                documents: vec![],
+                cursor_id: None,
+                message: Some(mikudb_query::format_statement(&statement)),
+                errors: vec![],
+                stats: None,
+                error_kind: None,
+            };
+            let payload = serde_json::to_vec(&response).unwrap_or_default();
+            return Ok(Message::response(request_id, response_to, payload));
+        }
 
-        let result = match &statement {
-            Statement::CreateUser(create_user) => {
-                use crate::auth::RoleAssignment;
-                let roles: Vec<RoleAssignment> = create_user.roles.iter().map(|r| RoleAssignment {
-                    role: r.clone(),
-                    db: "*".to_string(),
-                }).collect();
+        let parse_us = parsing.elapsed().as_micros() as u64;
+        self.run_statement(
+            statement,
+            parse_us,
+            query_req.timeout_ms,
+            query_req.batch_size,
+            query_req.meta,
+            request_id,
+            response_to,
+        )
+        .await
+    }
 
-                match self.user_manager.create_user(&create_user.username, &create_user.password, roles).await {
-                    Ok(_) => mikudb_query::QueryResponse::Ok {
-                        message: format!("User '{}' created successfully", create_user.username),
-                    },
-                    Err(e) => mikudb_query::QueryResponse::Ok {
-                        message: format!("Error creating user: {}", e),
-                    },
-                }
-            }
-            Statement::AlterUser(alter_user) => {
-                if let Some(ref password) = alter_user.password {
-                    match self.user_manager.alter_user_password(&alter_user.username, password).await {
-                        Ok(_) => mikudb_query::QueryResponse::Ok {
-                            message: format!("User '{}' password updated", alter_user.username),
-                        },
-                        Err(e) => mikudb_query::QueryResponse::Ok {
-                            message: format!("Error updating password: {}", e),
-                        },
-                    }
-                } else {
-                    mikudb_query::QueryResponse::Ok {
-                        message: "No changes specified".to_string(),
-                    }
-                }
-            }
-            Statement::DropUser(username) => {
-                match self.user_manager.drop_user(username).await {
-                    Ok(_) => mikudb_query::QueryResponse::Ok {
-                        message: format!("User '{}' dropped", username),
-                    },
-                    Err(e) => mikudb_query::QueryResponse::Ok {
-                        message: format!("Error dropping user: {}", e),
-                    },
-                }
-            }
-            Statement::ShowUsers => {
-                match self.user_manager.list_users().await {
-                    Ok(users) => {
-                        let user_docs: Vec<mikudb_boml::Document> = users.iter().map(|u| {
-                            let mut doc = mikudb_boml::Document::new();
-                            doc.insert("username".to_string(), mikudb_boml::BomlValue::String(u.username.clone().into()));
-                            let roles_array: Vec<mikudb_boml::BomlValue> = u.roles.iter().map(|r| {
-                                let mut role_doc = mikudb_boml::Document::new();
-                                role_doc.insert("role".to_string(), mikudb_boml::BomlValue::String(r.role.clone().into()));
-                                role_doc.insert("db".to_string(), mikudb_boml::BomlValue::String(r.db.clone().into()));
-                                mikudb_boml::BomlValue::from(role_doc)
-                            }).collect();
-                            doc.insert("roles".to_string(), mikudb_boml::BomlValue::Array(roles_array));
-                            doc
-                        }).collect();
-                        mikudb_query::QueryResponse::Documents(user_docs)
-                    },
-                    Err(e) => mikudb_query::QueryResponse::Ok {
-                        message: format!("Error listing users: {}", e),
-                    },
-                }
-            }
-            Statement::ShowGrants(_username) => {
-                mikudb_query::QueryResponse::Ok {
-                    message: "SHOW GRANTS not yet implemented".to_string(),
-                }
-            }
-            Statement::Grant(_) => {
-                mikudb_query::QueryResponse::Ok {
-                    message: "GRANT not yet implemented".to_string(),
-                }
+    /// # Brief
+    /// 处理 Execute 请求,绑定参数后执行语句
+    ///
+    /// 解析结果按语句文本缓存在连接上(最多 `PREPARED_CACHE_SIZE` 条,淘汰最久未使用的),
+    /// 同一语句再次执行时只做参数绑定。参数值替换进语句的 AST,不参与解析。
+    ///
+    /// # Arguments
+    /// * `payload` - Execute 请求数据(JSON 格式)
+    /// * `request_id` - 服务器生成的请求 ID
+    /// * `response_to` - 客户端请求 ID
+    ///
+    /// # Returns
+    /// 查询响应消息,解析或绑定失败时 success 为 false
+    async fn handle_execute(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let parsing = Instant::now();
+        let bound = serde_json::from_slice::<ExecuteRequest>(payload)
+            .map_err(|e| (ErrorKind::Parse, format!("Invalid execute request: {}", e)))
+            .and_then(|req| {
+                let params = req.params().map_err(|message| (ErrorKind::Parse, message))?;
+                let statement = self.bind(&req.query, &params)?;
+                Ok((req, statement))
+            });
+        let (execute_req, statement) = match bound {
+            Ok(bound) => bound,
+            Err((kind, message)) => {
+                let error_response = failure(kind, message);
+                let payload = serde_json::to_vec(&error_response).unwrap_or_default();
+                return Ok(Message::response(request_id, response_to, payload));
             }
-            Statement::Revoke(_) => {
-                mikudb_query::QueryResponse::Ok {
-                    message: "REVOKE not yet implemented".to_string(),
-                }
+        };
+        let parse_us = parsing.elapsed().as_micros() as u64;
+        self.run_statement(
+            statement,
+            parse_us,
+            execute_req.timeout_ms,
+            execute_req.batch_size,
+            execute_req.meta,
+            request_id,
+            response_to,
+        )
+        .await
+    }
+
+    /// # Brief
+    /// 取出(或解析并缓存)预编译语句,绑定参数值
+    ///
+    /// # Arguments
+    /// * `query` - 带占位符的语句文本
+    /// * `params` - 参数值
+    ///
+    /// # Returns
+    /// 绑定后的语句;解析或绑定失败时返回失败分类和响应中的错误信息
+    fn bind(&self, query: &str, params: &Params) -> Result<Statement, (ErrorKind, String)> {
+        let prepared = self.prepare(query).map_err(|e| (ErrorKind::Parse, format!("Parse error: {}", e)))?;
+        prepared.bind(params).map_err(|e| (ErrorKind::Execution, e.to_string()))
+    }
+
+    /// # Brief
+    /// 从连接的缓存中取出预编译语句,不存在时解析并加入缓存
+    fn prepare(&self, query: &str) -> mikudb_query::QueryResult<Arc<PreparedStatement>> {
+        let mut prepared = self.prepared.lock();
+        if let Some(statement) = prepared.shift_remove(query) {
+            prepared.insert(query.to_string(), statement.clone());
+            return Ok(statement);
+        }
+        let statement = Arc::new(PreparedStatement::prepare(query)?);
+        if prepared.len() >= PREPARED_CACHE_SIZE {
+            prepared.shift_remove_index(0);
+        }
+        prepared.insert(query.to_string(), statement.clone());
+        Ok(statement)
+    }
+
+    /// # Brief
+    /// 执行已解析的语句
+    ///
+    /// 处理会话变量和集群语句,其余语句经过写入限速、KillOp 登记和超时控制后执行;
+    /// FIND/AGGREGATE 的结果超过批量大小时打开游标。
+    ///
+    /// # Arguments
+    /// * `statement` - 要执行的语句
+    /// * `parse_us` - 解析耗时(微秒),写入资源统计
+    /// * `timeout_ms` - 查询超时(毫秒),None 或 0 表示不限制
+    /// * `batch_hint` - 游标首批文档数提示,语句中的 BATCH SIZE 优先
+    /// * `meta` - 请求附带的写入元数据
+    /// * `request_id` - 服务器生成的请求 ID
+    /// * `response_to` - 客户端请求 ID
+    ///
+    /// # Returns
+    /// 查询响应消息
+    #[allow(clippy::too_many_arguments)]
+    async fn run_statement(
+        &self,
+        statement: Statement,
+        parse_us: u64,
+        timeout_ms: Option<u64>,
+        batch_hint: Option<u32>,
+        meta: Option<WriteMetadata>,
+        request_id: u32,
+        response_to: u32,
+    ) -> ServerResult<Message> {
+        // USE 检查目标数据库,其余语句检查当前数据库
+        let target = match &statement {
+            mikudb_query::Statement::Use(use_stmt) => use_stmt.database.clone(),
+            _ => self.database(),
+        };
+        if let Err(message) = self.authorize(&target, statement_permission(&statement)) {
+            let payload = serde_json::to_vec(&failure(ErrorKind::Permission, message)).unwrap_or_default();
+            return Ok(Message::response(request_id, response_to, payload));
+        }
+
+        if let mikudb_query::Statement::SetVariable(set) = &statement {
+            let response = self.set_variable(set);
+            let payload = serde_json::to_vec(&response).unwrap_or_default();
+            return Ok(Message::response(request_id, response_to, payload));
+        }
+
+        if matches!(statement, mikudb_query::Statement::ClusterInit | mikudb_query::Statement::ClusterJoin(_)) {
+            let response = self.cluster.execute(&statement).await;
+            let payload = serde_json::to_vec(&response).unwrap_or_default();
+            return Ok(Message::response(request_id, response_to, payload));
+        }
+
+        if let Some(ref query_log) = self.query_log {
+            query_log.record(&statement);
+        }
+
+        if let Some((collection, documents)) = scheduler::write_target(&statement) {
+            self.scheduler.throttle_write(collection, documents).await;
+        }
+
+        // 登记请求,使其可以被 KillOp 中断;超时同样通过中断标志通知存储线程尽快退出
+        let session = self.session_id.and_then(|id| self.session_manager.get_session(id));
+        let interrupt = match &session {
+            Some(session) => session.begin_operation(response_to),
+            None => Arc::new(AtomicBool::new(false)),
+        };
+
+        let metadata = match self.cluster.write_metadata(meta) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                let payload = serde_json::to_vec(&failure(ErrorKind::Execution, e.to_string())).unwrap_or_default();
+                return Ok(Message::response(request_id, response_to, payload));
             }
-            _ => {
-                let executor = QueryExecutor::new(self.storage.clone());
-                match executor.execute(&statement) {
-                    Ok(res) => res,
-                    Err(e) => {
-                        let error_response = QueryResponse {
-                            success: false,
-                            affected: 0,
-                            documents: vec![],
-                            cursor_id: None,
-                            message: Some(format!("Execution error: {}", e)),
-                        };
-                        let payload = serde_json::to_vec(&error_response).unwrap_or_default();
-                        return Ok(Message::response(request_id, response_to, payload));
-                    }
+        };
+
+        let started = Instant::now();
+        let database = self.database();
+        let execution = execute_statement(
+            &self.storage,
+            &self.storage_pool,
+            &self.user_manager,
+            &self.op_stats,
+            &self.functions,
+            &self.config,
+            self.return_stats.load(Ordering::Relaxed),
+            &database,
+            &statement,
+            Some(interrupt.clone()),
+            metadata,
+        );
+        let mut response = match timeout_ms.filter(|ms| *ms > 0) {
+            Some(ms) => match tokio::time::timeout(Duration::from_millis(ms), execution).await {
+                Ok(response) => response,
+                Err(_) => {
+                    interrupt.store(true, Ordering::Relaxed);
+                    failure(ErrorKind::Timeout, format!("Query timed out after {} ms", ms))
                 }
-            }
+            },
+            None => execution.await,
         };
 
-        use mikudb_query::QueryResponse as QR;
+        if let Some(session) = &session {
+            session.end_operation(response_to);
+        }
 
-        // 将查询结果转换为协议响应格式
-        let response = match result {
-            QR::Ok { message } => QueryResponse {
-                success: true,
-                affected: 0,
-                documents: vec![],
-                cursor_id: None,
-                message: Some(message),
-            },
-            QR::Documents(docs) => QueryResponse {
-                success: true,
-                affected: docs.len() as u64,
-                documents: docs.iter()
-                    .filter_map(|d| serde_json::to_value(d).ok())
-                    .collect(),
-                cursor_id: None,
-                message: None,
-            },
-            QR::Insert { inserted_count, .. } => QueryResponse {
-                success: true,
-                affected: inserted_count,
-                documents: vec![],
-                cursor_id: None,
-                message: Some(format!("Inserted {} document(s)", inserted_count)),
-            },
-            QR::Update { matched_count, modified_count } => QueryResponse {
-                success: true,
-                affected: modified_count,
-                documents: vec![],
-                cursor_id: None,
-                message: Some(format!("Matched {}, modified {}", matched_count, modified_count)),
-            },
-            QR::Delete { deleted_count } => QueryResponse {
-                success: true,
-                affected: deleted_count,
-                documents: vec![],
-                cursor_id: None,
-                message: Some(format!("Deleted {} document(s)", deleted_count)),
-            },
-            QR::Databases(dbs) => QueryResponse {
-                success: true,
-                affected: dbs.len() as u64,
-                documents: dbs.iter().map(|d| serde_json::json!({"name": d})).collect(),
-                cursor_id: None,
-                message: None,
-            },
-            QR::Collections(cols) => QueryResponse {
-                success: true,
-                affected: cols.len() as u64,
-                documents: cols.iter().map(|c| serde_json::json!({"name": c})).collect(),
-                cursor_id: None,
-                message: None,
-            },
-            QR::Indexes(idxs) => QueryResponse {
-                success: true,
-                affected: idxs.len() as u64,
-                documents: idxs.iter().map(|i| serde_json::json!({"name": &i.name, "fields": &i.fields})).collect(),
-                cursor_id: None,
-                message: None,
-            },
-            // SHOW STATUS 特殊处理:解析 RocksDB 统计信息
-            QR::Status { size, stats } => {
-                let mut status_info = serde_json::Map::new();
-
-                // 基本信息
-                status_info.insert("version".to_string(), serde_json::json!("0.1.1"));
-                status_info.insert("engine".to_string(), serde_json::json!("RocksDB"));
-                status_info.insert("compression".to_string(), serde_json::json!("LZ4"));
-
-                // 存储大小
-                status_info.insert("storage_size_bytes".to_string(), serde_json::json!(size));
-                status_info.insert("storage_size_mb".to_string(), serde_json::json!(format!("{:.2}", size as f64 / 1024.0 / 1024.0)));
-
-                // 遍历 RocksDB 统计信息的每一行并提取关键指标
-                for line in stats.lines() {
-                    let line = line.trim();
-
-                    // 运行时间统计: "Uptime(secs): 123.4 total, 5.6 interval"
-                    if line.starts_with("Uptime(secs):") {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() > 1 {
-                            let uptime_val = parts[1].trim_end_matches(',');
-                            if let Ok(uptime_f) = uptime_val.parse::<f64>() {
-                                status_info.insert("uptime_seconds".to_string(), serde_json::json!(format!("{:.1}", uptime_f)));
-                            }
-                        }
-                        if parts.len() > 4 {
-                            let interval_val = parts[4].trim_end_matches(',');
-                            if let Ok(interval_f) = interval_val.parse::<f64>() {
-                                status_info.insert("interval_seconds".to_string(), serde_json::json!(format!("{:.1}", interval_f)));
-                            }
-                        }
-                    }
+        // 慢查询日志按语句指纹记录,字面量已替换为 `?`,同类语句可以归并统计
+        let elapsed = started.elapsed();
+        let slow_query_ms = self.config.log.slow_query_ms;
+        if slow_query_ms > 0 && elapsed >= Duration::from_millis(slow_query_ms) {
+            warn!(
+                "Slow query on connection {} took {} ms: {}",
+                self.conn_id,
+                elapsed.as_millis(),
+                mikudb_query::fingerprint(&statement)
+            );
+        }
+        if !response.success && response.message.as_deref() == Some(INTERRUPTED_MESSAGE) {
+            response.message = Some("Operation killed".to_string());
+            response.error_kind = Some(ErrorKind::Interrupted);
+        }
+        if let Some(stats) = &mut response.stats {
+            stats.parse_us = parse_us;
+        }
+        if let mikudb_query::Statement::Use(use_stmt) = &statement {
+            if response.success {
+                self.use_database(use_stmt.database.clone());
+            }
+        }
 
-                    // 累计写入统计: "Cumulative writes: 100 writes, 200 keys, ..."
-                    else if line.starts_with("Cumulative writes:") {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() > 2 {
-                            status_info.insert("cumulative_writes".to_string(), serde_json::json!(parts[2]));
-                        }
-                        if parts.len() > 4 {
-                            status_info.insert("cumulative_keys_written".to_string(), serde_json::json!(parts[4].trim_end_matches(',')));
-                        }
-                    }
+        // FIND/AGGREGATE 结果按批返回,语句中的 BATCH SIZE 优先于请求提示
+        let cursor_target = match &statement {
+            mikudb_query::Statement::Find(find) => Some((&find.collection, find.batch_size)),
+            mikudb_query::Statement::Aggregate(agg) => Some((&agg.collection, agg.batch_size)),
+            _ => None,
+        };
+        if let Some((collection, batch_size)) = cursor_target {
+            if let Some(batch_size) = batch_size.or(batch_hint) {
+                self.open_cursor(&self.qualified(collection), batch_size, &mut response);
+            }
+        }
 
-                    // 区间写入统计: "Interval writes: 10 writes, 20 keys, ..."
-                    else if line.starts_with("Interval writes:") {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() > 2 {
-                            status_info.insert("interval_writes".to_string(), serde_json::json!(parts[2]));
-                        }
-                        if parts.len() > 4 {
-                            status_info.insert("interval_keys_written".to_string(), serde_json::json!(parts[4].trim_end_matches(',')));
-                        }
-                    }
+        let payload = serde_json::to_vec(&response).unwrap_or_default();
+        Ok(Message::response(request_id, response_to, payload))
+    }
 
-                    // 累计停顿时间: "Cumulative stall: 00:00:0.000 H:M:S, ..."
-                    else if line.starts_with("Cumulative stall:") {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() > 2 {
-                            status_info.insert("cumulative_stall_time".to_string(), serde_json::json!(parts[2].trim_end_matches(',')));
-                        }
-                    }
+    /// # Brief
+    /// 设置当前连接的会话变量
+    ///
+    /// 目前支持 `return_stats`(布尔值),开启后每个查询响应附带 `stats` 资源统计。
+    ///
+    /// # Arguments
+    /// * `set` - SET 语句
+    ///
+    /// # Returns
+    /// 设置结果,未知变量或取值类型错误时 success 为 false
+    fn set_variable(&self, set: &mikudb_query::SetVariableStatement) -> QueryResponse {
+        let result = match (set.name.as_str(), &set.value) {
+            ("return_stats", mikudb_boml::BomlValue::Boolean(enabled)) => {
+                self.return_stats.store(*enabled, Ordering::Relaxed);
+                Ok(format!("return_stats = {}", enabled))
+            }
+            ("return_stats", _) => Err("return_stats must be true or false".to_string()),
+            (name, _) => Err(format!("Unknown session variable: {}", name)),
+        };
+        QueryResponse {
+            success: result.is_ok(),
+            affected: 0,
+            documents: vec![],
+            cursor_id: None,
+            error_kind: result.is_err().then_some(ErrorKind::Execution),
+            message: Some(result.unwrap_or_else(|e| e)),
+            errors: vec![],
+            stats: None,
+        }
+    }
 
-                    // 区间停顿时间
-                    else if line.starts_with("Interval stall:") {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        if parts.len() > 2 {
-                            status_info.insert("interval_stall_time".to_string(), serde_json::json!(parts[2].trim_end_matches(',')));
-                        }
-                    }
+    /// # Brief
+    /// 结果超过批量大小时打开游标,响应中只保留第一批
+    ///
+    /// 结果已在执行阶段物化,游标只负责分批发送以控制单个响应的大小。
+    /// 后续批次启用自适应增长,客户端持续读取时每批文档数翻倍。
+    ///
+    /// # Arguments
+    /// * `collection` - 查询的集合名称
+    /// * `batch_size` - 首批文档数
+    /// * `response` - 执行结果,原地替换为第一批并设置 `cursor_id`;
+    ///   超过连接的游标上限被拒绝时改为失败响应
+    fn open_cursor(&self, collection: &str, batch_size: u32, response: &mut QueryResponse) {
+        if !response.success || response.documents.len() <= batch_size as usize {
+            return;
+        }
 
-                    // 块缓存统计: "Block cache ... usage: 0.08 KB, capacity: 32.00 MB, ..."
-                    else if line.contains("Block cache") && line.contains("usage:") {
-                        // 提取使用量和单位
-                        if let Some(usage_str) = line.split("usage:").nth(1) {
-                            if let Some(usage_part) = usage_str.split_whitespace().next() {
-                                status_info.insert("block_cache_usage".to_string(), serde_json::json!(usage_part));
-                            }
-                            if let Some(usage_remainder) = usage_str.split_whitespace().nth(1) {
-                                status_info.insert("block_cache_usage_unit".to_string(), serde_json::json!(usage_remainder.trim_end_matches(',')));
-                            }
-                        }
-                        // 提取容量和单位
-                        if let Some(capacity_str) = line.split("capacity:").nth(1) {
-                            if let Some(capacity_part) = capacity_str.split_whitespace().next() {
-                                status_info.insert("block_cache_capacity".to_string(), serde_json::json!(capacity_part));
-                            }
-                            if let Some(capacity_remainder) = capacity_str.split_whitespace().nth(1) {
-                                status_info.insert("block_cache_capacity_unit".to_string(), serde_json::json!(capacity_remainder.trim_end_matches(',')));
-                            }
-                        }
-                    }
+        let documents = std::mem::take(&mut response.documents);
+        let mut cursor = ServerCursor::new(
+            self.cursor_owner(),
+            collection,
+            CursorSource::Buffered(documents.into()),
+            batch_size,
+        );
+        // 缓冲游标不访问存储,首批直接从内存取出
+        let first = cursor.next_batch(&self.storage, None).unwrap_or_default();
+        match self.cursors.register(cursor) {
+            Ok(id) => {
+                response.documents = first;
+                response.cursor_id = Some(id);
+            }
+            Err(e) => {
+                response.success = false;
+                response.affected = 0;
+                response.message = Some(e.to_string());
+            }
+        }
+    }
 
-                    // 压缩 CPU 时间
-                    else if line.contains("compaction.CPU") {
-                        if let Some(cpu_str) = line.split(':').nth(1) {
-                            status_info.insert("compaction_cpu_time".to_string(), serde_json::json!(cpu_str.trim()));
-                        }
-                    }
+    /// # Brief
+    /// 处理 GetMore 请求,返回游标的下一批结果
+    ///
+    /// 扫描游标在存储线程池中读取下一批;最后一批返回后游标自动关闭,
+    /// 响应中的 `cursor_id` 为 None。其他会话的游标视为不存在。
+    ///
+    /// # Arguments
+    /// * `payload` - GetMore 请求数据(JSON 格式)
+    /// * `request_id` - 服务器生成的请求 ID
+    /// * `response_to` - 客户端请求 ID
+    ///
+    /// # Returns
+    /// 下一批文档
+    async fn handle_get_more(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let get_more_req: GetMoreRequest = serde_json::from_slice(payload)
+            .map_err(|e| ServerError::Protocol(format!("Invalid get more request: {}", e)))?;
+
+        let response = match self.cursors.checkout(get_more_req.cursor_id, self.cursor_owner()) {
+            Some(mut cursor) => {
+                let storage = self.storage.clone();
+                let batch_size = get_more_req.batch_size;
+                let (cursor, documents) = self.storage_pool.run(move || {
+                    let documents = cursor.next_batch(&storage, batch_size);
+                    (cursor, documents)
+                }).await?;
+                match documents {
+                    Ok(documents) => QueryResponse {
+                        success: true,
+                        affected: documents.len() as u64,
+                        documents,
+                        cursor_id: self.cursors.checkin(cursor),
+                        message: None,
+                        errors: vec![],
+                        stats: None,
+                        error_kind: None,
+                    },
+                    // 读取失败时游标已取出,不再放回
+                    Err(e) => failure(ErrorKind::Execution, e.to_string()),
+                }
+            }
+            None => failure(ErrorKind::Execution, "Cursor not found".to_string()),
+        };
 
-                    // 压缩写入字节数
-                    else if line.contains("compaction.bytes.written") {
-                        if let Some(bytes_str) = line.split(':').nth(1) {
-                            status_info.insert("compaction_bytes_written".to_string(), serde_json::json!(bytes_str.trim()));
-                        }
-                    }
+        let payload = serde_json::to_vec(&response).unwrap_or_default();
+        Ok(Message::response(request_id, response_to, payload))
+    }
 
-                    // 刷写 CPU 时间
-                    else if line.contains("flush.CPU") {
-                        if let Some(cpu_str) = line.split(':').nth(1) {
-                            status_info.insert("flush_cpu_time".to_string(), serde_json::json!(cpu_str.trim()));
-                        }
-                    }
+    /// # Brief
+    /// 处理 KillOp 请求
+    ///
+    /// 中断指定会话中正在执行的请求。执行中的连接在请求完成前不会读取新消息,
+    /// 因此客户端需要通过另一个连接发送该请求;只能中断同一用户的会话。
+    ///
+    /// # Arguments
+    /// * `payload` - KillOp 请求数据(JSON 格式)
+    /// * `request_id` - 服务器生成的请求 ID
+    /// * `response_to` - 客户端请求 ID
+    ///
+    /// # Returns
+    /// 中断结果,`affected` 为 1 表示找到并中断了该请求
+    fn handle_kill_op(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let kill_req: KillOpRequest = serde_json::from_slice(payload)
+            .map_err(|e| ServerError::Protocol(format!("Invalid killop request: {}", e)))?;
+
+        let caller = self.session_id.and_then(|id| self.session_manager.get_session(id));
+        let killed = match self.session_manager.get_session(kill_req.session_id) {
+            Some(target)
+                if caller.as_ref().map_or(true, |caller| caller.username() == target.username()) =>
+            {
+                target.kill_operation(kill_req.request_id)
+            }
+            _ => false,
+        };
 
-                    // LSM 树层级信息: "Level Files Size ..."
-                    else if line.starts_with("Level") && line.contains("Files") {
-                        let level_info = line.replace("  ", " ");
-                        status_info.insert("storage_levels".to_string(), serde_json::json!(level_info));
-                    }
-                }
+        let response = QueryResponse {
+            success: killed,
+            affected: killed as u64,
+            documents: vec![],
+            cursor_id: None,
+            message: Some(if killed {
+                format!("Killed request {} of session {}", kill_req.request_id, kill_req.session_id)
+            } else {
+                "No such operation".to_string()
+            }),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
+        };
+        let payload = serde_json::to_vec(&response).unwrap_or_default();
+        Ok(Message::response(request_id, response_to, payload))
+    }
 
-                QueryResponse {
-                    success: true,
-                    affected: 0,
-                    documents: vec![serde_json::Value::Object(status_info)],
-                    cursor_id: None,
-                    message: None,
-                }
-            },
+    /// # Brief
+    /// 处理增量备份请求
+    ///
+    /// 在存储线程池中导出 WAL 段,导出期间短暂暂停写入。
+    /// 请求中的路径与 BACKUP 相同,是备份根目录下的相对路径。
+    ///
+    /// # Arguments
+    /// * `payload` - 增量备份请求数据(JSON 格式)
+    /// * `request_id` - 服务器生成的请求 ID
+    /// * `response_to` - 客户端请求 ID
+    ///
+    /// # Returns
+    /// 响应消息,文档为增量清单
+    async fn handle_backup_incremental(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let backup_req: BackupIncrementalRequest = serde_json::from_slice(payload)
+            .map_err(|e| ServerError::Protocol(format!("Invalid backup request: {}", e)))?;
+
+        let path = match mikudb_storage::backup::resolve_path(&self.config.backup_dir(), &backup_req.path) {
+            Ok(path) => path,
+            Err(e) => return Ok(Message::error(request_id, response_to, &e.to_string())),
+        };
+        let storage = self.storage.clone();
+        let manifest = self
+            .storage_pool
+            .run(move || storage.backup_incremental(backup_req.since_lsn, &path))
+            .await?;
+        let manifest = match manifest {
+            Ok(manifest) => manifest,
+            Err(e) => return Ok(Message::error(request_id, response_to, &e.to_string())),
         };
 
+        let response = QueryResponse {
+            success: true,
+            affected: manifest.segments.len() as u64,
+            documents: vec![serde_json::to_value(&manifest).unwrap_or_default()],
+            cursor_id: None,
+            message: Some(format!(
+                "Incremental backup written to {} (LSN {}..{})",
+                backup_req.path, manifest.since_lsn, manifest.until_lsn
+            )),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
+        };
         let payload = serde_json::to_vec(&response).unwrap_or_default();
         Ok(Message::response(request_id, response_to, payload))
     }
@@ -631,7 +1138,7 @@ impl ClientHandler {
     /// # Brief
     /// 处理文档插入请求
     ///
-    /// 将 JSON 文档转换为 BOML 格式并存储到指定集合。
+    /// 将 JSON 文档转换为 BOML 格式,与集合索引项一起通过一个 WriteBatch 写入指定集合。
     ///
     /// # Arguments
     /// * `payload` - 插入请求数据(JSON 格式)
@@ -640,26 +1147,37 @@ impl ClientHandler {
     ///
     /// # Returns
     /// 插入响应消息
-    async fn handle_insert(&mut self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
-        let insert_req: InsertRequest = serde_json::from_slice(payload)
+    async fn handle_insert(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let mut insert_req: InsertRequest = serde_json::from_slice(payload)
             .map_err(|e| ServerError::Protocol(format!("Invalid insert request: {}", e)))?;
+        insert_req.collection = self.qualified(&insert_req.collection);
+
+        self.scheduler
+            .throttle_write(&insert_req.collection, insert_req.documents.len() as u64)
+            .await;
+
+        let metadata = self.cluster.write_metadata(insert_req.meta.take())?;
+        let storage = self.storage.clone();
+        let auto_create = self.config.auto_create_collections;
+        let inserted = self.storage_pool.run(move || WriteMetadata::scope(metadata, || -> ServerResult<u64> {
+            // 获取集合,按配置决定不存在时是否自动创建
+            let collection = if auto_create {
+                storage.get_or_create_collection(&insert_req.collection)?
+            } else {
+                storage.get_collection(&insert_req.collection)?
+            };
 
-        // 获取或创建集合
-        let collection = self.storage.get_or_create_collection(&insert_req.collection)?;
-        let mut inserted = 0u64;
-
-        // 遍历并插入每个文档
-        for doc_value in insert_req.documents {
-            let mut doc = mikudb_boml::Document::new();
-            if let serde_json::Value::Object(map) = doc_value {
-                // 将 JSON 对象转换为 BOML 文档
-                for (k, v) in map {
-                    doc.insert(&k, json_to_boml(v));
-                }
+            // 将 JSON 对象转换为 BOML 文档
+            let mut docs: Vec<mikudb_boml::Document> =
+                insert_req.documents.into_iter().map(json_to_document).collect();
+
+            // 文档和索引项在一个 WriteBatch 中写入
+            let ids = collection.insert_many_indexed(&mut docs, storage.indexes())?;
+            for doc in &docs {
+                storage.apply_rollups(collection.name(), None, Some(doc))?;
             }
-            collection.insert(&mut doc)?;
-            inserted += 1;
-        }
+            Ok(ids.len() as u64)
+        })).await??;
 
         let response = QueryResponse {
             success: true,
@@ -667,6 +1185,9 @@ impl ClientHandler {
             documents: vec![],
             cursor_id: None,
             message: Some(format!("Inserted {} document(s)", inserted)),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
         };
 
         let payload = serde_json::to_vec(&response).unwrap_or_default();
@@ -676,7 +1197,8 @@ impl ClientHandler {
     /// # Brief
     /// 处理文档查找请求
     ///
-    /// 从指定集合查找所有文档并返回。
+    /// 从指定集合查找所有文档并返回。指定 `batch_size` 时打开扫描游标,
+    /// 按主键顺序逐批读取存储,只返回第一批和 `cursor_id`。
     ///
     /// # Arguments
     /// * `payload` - 查找请求数据(JSON 格式)
@@ -685,23 +1207,65 @@ impl ClientHandler {
     ///
     /// # Returns
     /// 查找响应消息,包含匹配的文档列表
-    async fn handle_find(&mut self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
-        let find_req: FindRequest = serde_json::from_slice(payload)
+    async fn handle_find(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let mut find_req: FindRequest = serde_json::from_slice(payload)
             .map_err(|e| ServerError::Protocol(format!("Invalid find request: {}", e)))?;
+        find_req.collection = self.qualified(&find_req.collection);
+
+        if let Some(batch_size) = find_req.batch_size {
+            let mut cursor = ServerCursor::new(
+                self.cursor_owner(),
+                find_req.collection,
+                CursorSource::Scan { after: None },
+                batch_size,
+            )
+            .with_extended_json(find_req.extended_json);
+            let storage = self.storage.clone();
+            let (cursor, documents) = self.storage_pool.run(move || -> ServerResult<_> {
+                let documents = cursor.next_batch(&storage, None)?;
+                Ok((cursor, documents))
+            }).await??;
+
+            let registered = if cursor.is_exhausted() { Ok(None) } else { self.cursors.register(cursor).map(Some) };
+            let response = match registered {
+                Ok(cursor_id) => QueryResponse {
+                    success: true,
+                    affected: documents.len() as u64,
+                    documents,
+                    cursor_id,
+                    message: None,
+                    errors: vec![],
+                    stats: None,
+                    error_kind: None,
+                },
+                Err(e) => failure(ErrorKind::Execution, e.to_string()),
+            };
+            let payload = serde_json::to_vec(&response).unwrap_or_default();
+            return Ok(Message::response(request_id, response_to, payload));
+        }
 
-        let collection = self.storage.get_collection(&find_req.collection)?;
+        let storage = self.storage.clone();
+        let extended_json = find_req.extended_json;
+        let docs = self.storage_pool.run(move || -> ServerResult<Vec<mikudb_boml::Document>> {
+            let collection = storage.get_collection(&find_req.collection)?;
 
-        // 获取所有文档(后续可添加过滤器支持)
-        let docs = collection.find_all()?;
+            // 获取所有文档(后续可添加过滤器支持)
+            Ok(collection.find_all()?)
+        }).await??;
 
         let response = QueryResponse {
             success: true,
             affected: docs.len() as u64,
-            documents: docs.iter()
-                .filter_map(|d| serde_json::to_value(d).ok())
-                .collect(),
+            documents: if extended_json {
+                docs.iter().map(|d| mikudb_boml::to_extended_json(&d.to_boml_value())).collect()
+            } else {
+                docs.iter().filter_map(|d| serde_json::to_value(d).ok()).collect()
+            },
             cursor_id: None,
             message: None,
+            errors: vec![],
+            stats: None,
+            error_kind: None,
         };
 
         let payload = serde_json::to_vec(&response).unwrap_or_default();
@@ -720,39 +1284,47 @@ impl ClientHandler {
     ///
     /// # Returns
     /// 更新响应消息,包含匹配和修改的文档数量
-    async fn handle_update(&mut self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
-        let update_req: UpdateRequest = serde_json::from_slice(payload)
+    async fn handle_update(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let mut update_req: UpdateRequest = serde_json::from_slice(payload)
             .map_err(|e| ServerError::Protocol(format!("Invalid update request: {}", e)))?;
+        update_req.collection = self.qualified(&update_req.collection);
 
-        let collection = self.storage.get_collection(&update_req.collection)?;
-        let docs = collection.find_all()?;
+        self.scheduler.throttle_write(&update_req.collection, 1).await;
 
-        let filter_value = update_req.filter;
-        let update_value = update_req.update;
+        let metadata = self.cluster.write_metadata(update_req.meta.take())?;
+        let storage = self.storage.clone();
+        let (matched_count, modified_count) = self.storage_pool.run(move || WriteMetadata::scope(metadata, || -> ServerResult<(u64, u64)> {
+            let collection = storage.get_collection(&update_req.collection)?;
+            let docs = collection.find_all()?;
 
-        let mut modified_count = 0u64;
-        let mut matched_count = 0u64;
+            let filter_value = update_req.filter;
+            let update_value = update_req.update;
 
-        for mut doc in docs {
-            // 应用过滤条件
-            if filter_value != serde_json::Value::Null && !match_filter(&doc, &filter_value) {
-                continue;
-            }
-            matched_count += 1;
+            let mut modified_count = 0u64;
+            let mut matched_count = 0u64;
 
-            // 应用更新操作
-            if apply_update(&mut doc, &update_value) {
-                if let Some(id) = doc.id() {
-                    collection.update(id, &doc)?;
-                    modified_count += 1;
+            for mut doc in docs {
+                // 应用过滤条件
+                if filter_value != serde_json::Value::Null && !match_filter(&doc, &filter_value) {
+                    continue;
+                }
+                matched_count += 1;
+
+                // 应用更新操作
+                if apply_update(&mut doc, &update_value) {
+                    if let Some(id) = doc.id() {
+                        collection.update(id, &doc)?;
+                        modified_count += 1;
+                    }
                 }
-            }
 
-            // 如果不是多文档更新,只更新第一个匹配的文档
-            if !update_req.multi {
-                break;
+                // 如果不是多文档更新,只更新第一个匹配的文档
+                if !update_req.multi {
+                    break;
+                }
             }
-        }
+            Ok((matched_count, modified_count))
+        })).await??;
 
         let response = QueryResponse {
             success: true,
@@ -760,6 +1332,9 @@ impl ClientHandler {
             documents: vec![],
             cursor_id: None,
             message: Some(format!("Matched {}, modified {}", matched_count, modified_count)),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
         };
 
         let payload = serde_json::to_vec(&response).unwrap_or_default();
@@ -778,33 +1353,41 @@ impl ClientHandler {
     ///
     /// # Returns
     /// 删除响应消息,包含删除的文档数量
-    async fn handle_delete(&mut self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
-        let delete_req: DeleteRequest = serde_json::from_slice(payload)
+    async fn handle_delete(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let mut delete_req: DeleteRequest = serde_json::from_slice(payload)
             .map_err(|e| ServerError::Protocol(format!("Invalid delete request: {}", e)))?;
+        delete_req.collection = self.qualified(&delete_req.collection);
 
-        let collection = self.storage.get_collection(&delete_req.collection)?;
-        let docs = collection.find_all()?;
+        self.scheduler.throttle_write(&delete_req.collection, 1).await;
 
-        let filter_value = delete_req.filter;
-        let mut deleted_count = 0u64;
+        let metadata = self.cluster.write_metadata(delete_req.meta.take())?;
+        let storage = self.storage.clone();
+        let deleted_count = self.storage_pool.run(move || WriteMetadata::scope(metadata, || -> ServerResult<u64> {
+            let collection = storage.get_collection(&delete_req.collection)?;
+            let docs = collection.find_all()?;
 
-        for doc in docs {
-            // 应用过滤条件
-            if filter_value != serde_json::Value::Null && !match_filter(&doc, &filter_value) {
-                continue;
-            }
+            let filter_value = delete_req.filter;
+            let mut deleted_count = 0u64;
 
-            // 删除文档
-            if let Some(id) = doc.id() {
-                collection.delete(id)?;
-                deleted_count += 1;
-            }
+            for doc in docs {
+                // 应用过滤条件
+                if filter_value != serde_json::Value::Null && !match_filter(&doc, &filter_value) {
+                    continue;
+                }
+
+                // 删除文档
+                if let Some(id) = doc.id() {
+                    collection.delete(id)?;
+                    deleted_count += 1;
+                }
 
-            // 如果不是多文档删除,只删除第一个匹配的文档
-            if !delete_req.multi {
-                break;
+                // 如果不是多文档删除,只删除第一个匹配的文档
+                if !delete_req.multi {
+                    break;
+                }
             }
-        }
+            Ok(deleted_count)
+        })).await??;
 
         let response = QueryResponse {
             success: true,
@@ -812,6 +1395,97 @@ impl ClientHandler {
             documents: vec![],
             cursor_id: None,
             message: Some(format!("Deleted {} document(s)", deleted_count)),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
+        };
+
+        let payload = serde_json::to_vec(&response).unwrap_or_default();
+        Ok(Message::response(request_id, response_to, payload))
+    }
+
+    /// # Brief
+    /// 处理批量写入请求
+    ///
+    /// 按顺序把每个插入、更新、删除操作解析为按 `_id` 的写操作后执行,维护集合索引和预聚合。
+    /// 有序执行在第一个失败的操作处停止,无序执行跳过失败的操作继续。
+    ///
+    /// # Arguments
+    /// * `payload` - 批量写入请求数据(JSON 格式)
+    /// * `request_id` - 服务器生成的请求 ID
+    /// * `response_to` - 客户端请求 ID
+    ///
+    /// # Returns
+    /// 批量写入响应消息,第一个文档是每个操作的结果和 `write_errors`
+    async fn handle_bulk_write(&self, payload: &[u8], request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let mut bulk_req: BulkWriteRequest = serde_json::from_slice(payload)
+            .map_err(|e| ServerError::Protocol(format!("Invalid bulk write request: {}", e)))?;
+        bulk_req.collection = self.qualified(&bulk_req.collection);
+
+        self.scheduler
+            .throttle_write(&bulk_req.collection, bulk_req.operations.len() as u64)
+            .await;
+
+        let metadata = self.cluster.write_metadata(bulk_req.meta.take())?;
+        let storage = self.storage.clone();
+        let auto_create = self.config.auto_create_collections;
+        let result = self.storage_pool.run(move || WriteMetadata::scope(metadata, || -> ServerResult<BulkWriteResult> {
+            let collection = if auto_create {
+                storage.get_or_create_collection(&bulk_req.collection)?
+            } else {
+                storage.get_collection(&bulk_req.collection)?
+            };
+
+            let mut result = BulkWriteResult::default();
+            for (index, operation) in bulk_req.operations.into_iter().enumerate() {
+                let writes = match resolve_bulk_operation(&collection, operation, bulk_req.extended_json) {
+                    Ok(writes) => writes,
+                    Err(e @ StorageError::Boml(_)) => {
+                        // 无法还原的文档只让该操作失败
+                        result.record_error(index, &e);
+                        if bulk_req.ordered {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let done = storage.bulk_write(collection.name(), writes, true)?;
+                // 多文档操作中途失败时,已经写入的部分仍计入结果
+                if done.is_ok() || !done.results.is_empty() {
+                    let mut merged = OperationResult::new(index);
+                    for sub in &done.results {
+                        merged.merge(sub);
+                    }
+                    result.record(merged);
+                }
+                if let Some(error) = done.write_errors.into_iter().next() {
+                    result.write_errors.push(WriteError { index, ..error });
+                    if bulk_req.ordered {
+                        break;
+                    }
+                }
+            }
+            Ok(result)
+        })).await??;
+
+        let response = QueryResponse {
+            success: result.is_ok(),
+            affected: result.affected(),
+            documents: vec![serde_json::to_value(&result).unwrap_or_default()],
+            cursor_id: None,
+            message: Some(format!(
+                "Inserted {}, matched {}, modified {}, deleted {}, upserted {}, {} error(s)",
+                result.inserted,
+                result.matched,
+                result.modified,
+                result.deleted,
+                result.upserted,
+                result.write_errors.len()
+            )),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
         };
 
         let payload = serde_json::to_vec(&response).unwrap_or_default();
@@ -821,7 +1495,7 @@ impl ClientHandler {
     /// # Brief
     /// 处理列出数据库请求
     ///
-    /// 返回所有数据库列表(当前只有 default 数据库)。
+    /// 返回所有数据库列表,包括 default 数据库。
     ///
     /// # Arguments
     /// * `request_id` - 服务器生成的请求 ID
@@ -829,8 +1503,8 @@ impl ClientHandler {
     ///
     /// # Returns
     /// 数据库列表响应消息
-    async fn handle_list_databases(&mut self, request_id: u32, response_to: u32) -> ServerResult<Message> {
-        let databases = vec!["default".to_string()];
+    async fn handle_list_databases(&self, request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let databases = self.storage.list_databases()?;
 
         let response = QueryResponse {
             success: true,
@@ -840,6 +1514,9 @@ impl ClientHandler {
                 .collect(),
             cursor_id: None,
             message: None,
+            errors: vec![],
+            stats: None,
+            error_kind: None,
         };
 
         let payload = serde_json::to_vec(&response).unwrap_or_default();
@@ -857,8 +1534,8 @@ impl ClientHandler {
     ///
     /// # Returns
     /// 集合列表响应消息
-    async fn handle_list_collections(&mut self, request_id: u32, response_to: u32) -> ServerResult<Message> {
-        let collections = self.storage.list_collections()?;
+    async fn handle_list_collections(&self, request_id: u32, response_to: u32) -> ServerResult<Message> {
+        let collections = self.storage.database_collections(&self.database())?;
 
         let response = QueryResponse {
             success: true,
@@ -868,6 +1545,9 @@ impl ClientHandler {
                 .collect(),
             cursor_id: None,
             message: None,
+            errors: vec![],
+            stats: None,
+            error_kind: None,
         };
 
         let payload = serde_json::to_vec(&response).unwrap_or_default();
@@ -875,6 +1555,456 @@ impl ClientHandler {
     }
 }
 
+/// # Brief
+/// 构造失败的查询响应
+///
+/// # Arguments
+/// * `kind` - 失败原因的分类
+/// * `message` - 错误信息
+fn failure(kind: ErrorKind, message: String) -> QueryResponse {
+    QueryResponse {
+        success: false,
+        affected: 0,
+        documents: vec![],
+        cursor_id: None,
+        message: Some(message),
+        errors: vec![],
+        stats: None,
+        error_kind: Some(kind),
+    }
+}
+
+/// # Brief
+/// 请求是否可以与同一连接上的其他请求并发执行
+///
+/// 认证和切换数据库修改连接状态,即使带有 `FLAG_CONCURRENT` 也按顺序执行。
+fn runs_concurrently(header: &MessageHeader) -> bool {
+    header.flags & FLAG_CONCURRENT != 0 && !matches!(header.opcode, OpCode::Auth | OpCode::UseDatabase)
+}
+
+/// # Brief
+/// 从缓冲区解析所有已收全的消息,追加到队列
+///
+/// # Arguments
+/// * `buf` - 接收缓冲区
+/// * `pending` - 已解析但 payload 尚未收全的消息头
+/// * `queued` - 已收全的消息
+fn decode_messages(
+    buf: &mut BytesMut,
+    pending: &mut Option<MessageHeader>,
+    queued: &mut VecDeque<Message>,
+) -> ServerResult<()> {
+    loop {
+        let header = match pending.take() {
+            Some(header) => header,
+            None => match MessageHeader::decode(buf)? {
+                Some(header) => header,
+                None => return Ok(()),
+            },
+        };
+        // 检查缓冲区是否包含完整的 payload
+        if buf.len() < header.payload_len as usize {
+            buf.reserve(header.payload_len as usize - buf.len());
+            *pending = Some(header);
+            return Ok(()); // 需要等待更多数据
+        }
+
+        // 提取 payload 并构造消息
+        let payload = buf.split_to(header.payload_len as usize).to_vec();
+        queued.push_back(Message { header, payload });
+    }
+}
+
+/// # Brief
+/// 编码并发送响应
+async fn send(writer: &mut OwnedWriteHalf, response: Message) -> ServerResult<()> {
+    writer.write_all(&response.encode()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// # Brief
+/// 执行已解析的 MQL 语句并转换为协议响应
+///
+/// 用户管理语句由 UserManager 处理,其余语句交给 QueryExecutor。
+/// 二进制协议和 HTTP 接口共用此函数,保证两者返回格式一致。
+///
+/// # Arguments
+/// * `storage` - 存储引擎实例
+/// * `storage_pool` - 存储线程池
+/// * `user_manager` - 用户管理器
+/// * `op_stats` - 按集合的操作统计
+/// * `functions` - 自定义函数注册表
+/// * `config` - 服务器配置,提供集合自动创建开关和备份根目录
+/// * `collect_stats` - 是否在响应中附带语句的资源统计
+/// * `database` - 语句所在的数据库
+/// * `statement` - 已解析的语句
+/// * `interrupt` - 可选的中断标志,置位后执行器在下一个检查点返回 Interrupted
+/// * `metadata` - 写入元数据,附加到语句产生的变更事件
+///
+/// # Returns
+/// 协议层查询响应
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn execute_statement(
+    storage: &Arc<StorageEngine>,
+    storage_pool: &StoragePool,
+    user_manager: &UserManager,
+    op_stats: &Arc<OpStats>,
+    functions: &Arc<FunctionRegistry>,
+    config: &ServerConfig,
+    collect_stats: bool,
+    database: &str,
+    statement: &mikudb_query::Statement,
+    interrupt: Option<Arc<AtomicBool>>,
+    metadata: Option<Arc<WriteMetadata>>,
+) -> QueryResponse {
+    use mikudb_query::Statement;
+
+    let mut statement_stats = None;
+    let result = match statement {
+        Statement::CreateUser(create_user) => {
+            use crate::auth::RoleAssignment;
+            let roles: Vec<RoleAssignment> = create_user.roles.iter().map(|r| RoleAssignment {
+                role: r.clone(),
+                db: "*".to_string(),
+            }).collect();
+
+            match user_manager.create_user(&create_user.username, &create_user.password, roles).await {
+                Ok(_) => mikudb_query::QueryResponse::Ok {
+                    message: format!("User '{}' created successfully", create_user.username),
+                },
+                Err(e) => return failure(ErrorKind::Execution, format!("Error creating user: {}", e)),
+            }
+        }
+        Statement::AlterUser(alter_user) => {
+            let add_roles = alter_user.add_roles.as_deref().unwrap_or_default();
+            let remove_roles = alter_user.remove_roles.as_deref().unwrap_or_default();
+            let mut changes = Vec::new();
+            let mut error = None;
+            if let Some(ref password) = alter_user.password {
+                match user_manager.alter_user_password(&alter_user.username, password).await {
+                    Ok(_) => changes.push("password updated"),
+                    Err(e) => error = Some(format!("Error updating password: {}", e)),
+                }
+            }
+            if error.is_none() && !(add_roles.is_empty() && remove_roles.is_empty()) {
+                match user_manager.alter_user_roles(&alter_user.username, add_roles, remove_roles).await {
+                    Ok(_) => changes.push("roles updated"),
+                    Err(e) => error = Some(format!("Error updating roles: {}", e)),
+                }
+            }
+            if let Some(error) = error {
+                return failure(ErrorKind::Execution, error);
+            }
+            let message = if changes.is_empty() {
+                "No changes specified".to_string()
+            } else {
+                format!("User '{}' {}", alter_user.username, changes.join(", "))
+            };
+            mikudb_query::QueryResponse::Ok { message }
+        }
+        Statement::DropUser(username) => {
+            match user_manager.drop_user(username).await {
+                Ok(_) => mikudb_query::QueryResponse::Ok {
+                    message: format!("User '{}' dropped", username),
+                },
+                Err(e) => return failure(ErrorKind::Execution, format!("Error dropping user: {}", e)),
+            }
+        }
+        Statement::ShowUsers => {
+            match user_manager.list_users().await {
+                Ok(users) => {
+                    let user_docs: Vec<mikudb_boml::Document> = users.iter().map(|u| {
+                        let mut doc = mikudb_boml::Document::new();
+                        doc.insert("username".to_string(), mikudb_boml::BomlValue::String(u.username.clone().into()));
+                        let roles_array: Vec<mikudb_boml::BomlValue> = u.roles.iter().map(|r| {
+                            let mut role_doc = mikudb_boml::Document::new();
+                            role_doc.insert("role".to_string(), mikudb_boml::BomlValue::String(r.role.clone().into()));
+                            role_doc.insert("db".to_string(), mikudb_boml::BomlValue::String(r.db.clone().into()));
+                            mikudb_boml::BomlValue::from(role_doc)
+                        }).collect();
+                        doc.insert("roles".to_string(), mikudb_boml::BomlValue::Array(roles_array));
+                        doc
+                    }).collect();
+                    mikudb_query::QueryResponse::Documents(user_docs)
+                },
+                Err(e) => mikudb_query::QueryResponse::Ok {
+                    message: format!("Error listing users: {}", e),
+                },
+            }
+        }
+        Statement::SetLogLevel(set) => {
+            match crate::logging::set_log_level(&set.level, set.target.as_deref()) {
+                Ok(filter) => mikudb_query::QueryResponse::Ok {
+                    message: format!("Log filter set to '{}'", filter),
+                },
+                Err(e) => return failure(ErrorKind::Execution, format!("Error setting log level: {}", e)),
+            }
+        }
+        Statement::ResetLogLevel => {
+            match crate::logging::reset_log_level() {
+                Ok(filter) => mikudb_query::QueryResponse::Ok {
+                    message: format!("Log filter reset to '{}'", filter),
+                },
+                Err(e) => return failure(ErrorKind::Execution, format!("Error resetting log level: {}", e)),
+            }
+        }
+        Statement::ShowGrants(_username) => {
+            mikudb_query::QueryResponse::Ok {
+                message: "SHOW GRANTS not yet implemented".to_string(),
+            }
+        }
+        Statement::Grant(_) => {
+            mikudb_query::QueryResponse::Ok {
+                message: "GRANT not yet implemented".to_string(),
+            }
+        }
+        Statement::Revoke(_) => {
+            mikudb_query::QueryResponse::Ok {
+                message: "REVOKE not yet implemented".to_string(),
+            }
+        }
+        _ => {
+            // 查询执行会直接访问 RocksDB,放到存储线程池中避免阻塞异步执行器
+            let mut executor = QueryExecutor::new(storage.clone())
+                .with_op_stats(op_stats.clone())
+                .with_functions(functions.clone())
+                .with_auto_create_collections(config.auto_create_collections)
+                .with_backup_dir(config.backup_dir())
+                .with_database(database);
+            if let Some(interrupt) = interrupt {
+                executor = executor.with_interrupt(interrupt);
+            }
+            if collect_stats {
+                executor = executor.with_statement_stats();
+            }
+            let statement = statement.clone();
+            let result = storage_pool
+                .run(move || {
+                    let result = WriteMetadata::scope(metadata, || executor.execute(&statement));
+                    (result, executor.statement_stats())
+                })
+                .await
+                .map_err(|e| (e.to_string(), vec![]))
+                .and_then(|(r, stats)| {
+                    statement_stats = stats;
+                    r.map_err(|e| {
+                        let details = e.validation_details().map(<[_]>::to_vec).unwrap_or_default();
+                        (e.to_string(), details)
+                    })
+                });
+            match result {
+                Ok(res) => res,
+                Err((e, details)) => {
+                    return QueryResponse {
+                        success: false,
+                        affected: 0,
+                        documents: vec![],
+                        cursor_id: None,
+                        message: Some(format!("Execution error: {}", e)),
+                        errors: details,
+                        stats: statement_stats,
+                        error_kind: Some(ErrorKind::Execution),
+                    };
+                }
+            }
+        }
+    };
+
+    use mikudb_query::QueryResponse as QR;
+
+    // 清单中的用户由 UserManager 管理,追加到执行器生成的清单文档
+    let result = match (statement, result) {
+        (Statement::ShowManifest, QR::Documents(mut docs)) => match user_manager.list_users().await {
+            Ok(mut users) => {
+                users.sort_by(|a, b| a.username.cmp(&b.username));
+                let users: Vec<mikudb_boml::BomlValue> = users.into_iter().map(|u| {
+                    let mut doc = mikudb_boml::Document::without_id();
+                    doc.insert("username", u.username);
+                    let roles: Vec<mikudb_boml::BomlValue> = u.roles.into_iter()
+                        .map(|r| mikudb_boml::BomlValue::from(r.role))
+                        .collect();
+                    doc.insert("roles", mikudb_boml::BomlValue::Array(roles));
+                    mikudb_boml::BomlValue::from(doc)
+                }).collect();
+                if let Some(manifest) = docs.first_mut() {
+                    manifest.insert("users", mikudb_boml::BomlValue::Array(users));
+                }
+                QR::Documents(docs)
+            }
+            Err(e) => QR::Ok {
+                message: format!("Error listing users: {}", e),
+            },
+        },
+        (_, result) => result,
+    };
+
+    // 将查询结果转换为协议响应格式,SHOW STATUS 之外的响应与 C API 共用 `into_parts` 的转换
+    let mut response = match result {
+        // SHOW STATUS 特殊处理:解析 RocksDB 统计信息
+        QR::Status { size, stats, document_cache } => {
+            let mut status_info = serde_json::Map::new();
+
+            // 基本信息
+            status_info.insert("version".to_string(), serde_json::json!("0.1.1"));
+            status_info.insert("engine".to_string(), serde_json::json!("RocksDB"));
+            status_info.insert("compression".to_string(), serde_json::json!("LZ4"));
+
+            // 存储大小
+            status_info.insert("storage_size_bytes".to_string(), serde_json::json!(size));
+            status_info.insert("storage_size_mb".to_string(), serde_json::json!(format!("{:.2}", size as f64 / 1024.0 / 1024.0)));
+
+            // 文档缓存命中统计
+            if let Some(cache) = document_cache {
+                status_info.insert("document_cache_policy".to_string(), serde_json::json!(cache.policy.as_str()));
+                status_info.insert("document_cache_hits".to_string(), serde_json::json!(cache.hits));
+                status_info.insert("document_cache_misses".to_string(), serde_json::json!(cache.misses));
+                status_info.insert("document_cache_hit_rate".to_string(), serde_json::json!(format!("{:.4}", cache.hit_rate)));
+                status_info.insert("document_cache_entries".to_string(), serde_json::json!(cache.entries));
+                status_info.insert("document_cache_size_bytes".to_string(), serde_json::json!(cache.size));
+                status_info.insert("document_cache_capacity_bytes".to_string(), serde_json::json!(cache.capacity));
+            }
+
+            // 遍历 RocksDB 统计信息的每一行并提取关键指标
+            for line in stats.lines() {
+                let line = line.trim();
+
+                // 运行时间统计: "Uptime(secs): 123.4 total, 5.6 interval"
+                if line.starts_with("Uptime(secs):") {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() > 1 {
+                        let uptime_val = parts[1].trim_end_matches(',');
+                        if let Ok(uptime_f) = uptime_val.parse::<f64>() {
+                            status_info.insert("uptime_seconds".to_string(), serde_json::json!(format!("{:.1}", uptime_f)));
+                        }
+                    }
+                    if parts.len() > 4 {
+                        let interval_val = parts[4].trim_end_matches(',');
+                        if let Ok(interval_f) = interval_val.parse::<f64>() {
+                            status_info.insert("interval_seconds".to_string(), serde_json::json!(format!("{:.1}", interval_f)));
+                        }
+                    }
+                }
+
+                // 累计写入统计: "Cumulative writes: 100 writes, 200 keys, ..."
+                else if line.starts_with("Cumulative writes:") {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() > 2 {
+                        status_info.insert("cumulative_writes".to_string(), serde_json::json!(parts[2]));
+                    }
+                    if parts.len() > 4 {
+                        status_info.insert("cumulative_keys_written".to_string(), serde_json::json!(parts[4].trim_end_matches(',')));
+                    }
+                }
+
+                // 区间写入统计: "Interval writes: 10 writes, 20 keys, ..."
+                else if line.starts_with("Interval writes:") {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() > 2 {
+                        status_info.insert("interval_writes".to_string(), serde_json::json!(parts[2]));
+                    }
+                    if parts.len() > 4 {
+                        status_info.insert("interval_keys_written".to_string(), serde_json::json!(parts[4].trim_end_matches(',')));
+                    }
+                }
+
+                // 累计停顿时间: "Cumulative stall: 00:00:0.000 H:M:S, ..."
+                else if line.starts_with("Cumulative stall:") {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() > 2 {
+                        status_info.insert("cumulative_stall_time".to_string(), serde_json::json!(parts[2].trim_end_matches(',')));
+                    }
+                }
+
+                // 区间停顿时间
+                else if line.starts_with("Interval stall:") {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() > 2 {
+                        status_info.insert("interval_stall_time".to_string(), serde_json::json!(parts[2].trim_end_matches(',')));
+                    }
+                }
+
+                // 块缓存统计: "Block cache ... usage: 0.08 KB, capacity: 32.00 MB, ..."
+                else if line.contains("Block cache") && line.contains("usage:") {
+                    // 提取使用量和单位
+                    if let Some(usage_str) = line.split("usage:").nth(1) {
+                        if let Some(usage_part) = usage_str.split_whitespace().next() {
+                            status_info.insert("block_cache_usage".to_string(), serde_json::json!(usage_part));
+                        }
+                        if let Some(usage_remainder) = usage_str.split_whitespace().nth(1) {
+                            status_info.insert("block_cache_usage_unit".to_string(), serde_json::json!(usage_remainder.trim_end_matches(',')));
+                        }
+                    }
+                    // 提取容量和单位
+                    if let Some(capacity_str) = line.split("capacity:").nth(1) {
+                        if let Some(capacity_part) = capacity_str.split_whitespace().next() {
+                            status_info.insert("block_cache_capacity".to_string(), serde_json::json!(capacity_part));
+                        }
+                        if let Some(capacity_remainder) = capacity_str.split_whitespace().nth(1) {
+                            status_info.insert("block_cache_capacity_unit".to_string(), serde_json::json!(capacity_remainder.trim_end_matches(',')));
+                        }
+                    }
+                }
+
+                // 压缩 CPU 时间
+                else if line.contains("compaction.CPU") {
+                    if let Some(cpu_str) = line.split(':').nth(1) {
+                        status_info.insert("compaction_cpu_time".to_string(), serde_json::json!(cpu_str.trim()));
+                    }
+                }
+
+                // 压缩写入字节数
+                else if line.contains("compaction.bytes.written") {
+                    if let Some(bytes_str) = line.split(':').nth(1) {
+                        status_info.insert("compaction_bytes_written".to_string(), serde_json::json!(bytes_str.trim()));
+                    }
+                }
+
+                // 刷写 CPU 时间
+                else if line.contains("flush.CPU") {
+                    if let Some(cpu_str) = line.split(':').nth(1) {
+                        status_info.insert("flush_cpu_time".to_string(), serde_json::json!(cpu_str.trim()));
+                    }
+                }
+
+                // LSM 树层级信息: "Level Files Size ..."
+                else if line.starts_with("Level") && line.contains("Files") {
+                    let level_info = line.replace("  ", " ");
+                    status_info.insert("storage_levels".to_string(), serde_json::json!(level_info));
+                }
+            }
+
+            QueryResponse {
+                success: true,
+                affected: 0,
+                documents: vec![serde_json::Value::Object(status_info)],
+                cursor_id: None,
+                message: None,
+                errors: vec![],
+                stats: None,
+                error_kind: None,
+            }
+        },
+        result => {
+            let (documents, affected, message) = result.into_parts();
+            QueryResponse {
+                success: true,
+                affected,
+                documents: documents.iter()
+                    .filter_map(|d| serde_json::to_value(d).ok())
+                    .collect(),
+                cursor_id: None,
+                message,
+                errors: vec![],
+                stats: None,
+                error_kind: None,
+            }
+        }
+    };
+    response.stats = statement_stats;
+    response
+}
+
 /// # Brief
 /// 将 JSON 值转换为 BOML 值
 ///
@@ -917,6 +2047,81 @@ fn json_to_boml(value: serde_json::Value) -> mikudb_boml::BomlValue {
     }
 }
 
+/// # Brief
+/// 把批量写入中的一个操作解析为按 `_id` 的写操作
+///
+/// 更新对匹配的文档应用更新操作符后整体替换;没有匹配且 `upsert` 时,
+/// 以过滤条件中的等值字段为基础应用更新后插入。
+///
+/// # Arguments
+/// * `collection` - 目标集合,按执行到该操作时的内容匹配过滤条件
+/// * `operation` - 批量写入中的操作
+/// * `extended_json` - 插入的文档是否为规范扩展 JSON
+fn resolve_bulk_operation(
+    collection: &mikudb_storage::Collection,
+    operation: BulkOperation,
+    extended_json: bool,
+) -> mikudb_storage::StorageResult<Vec<WriteOperation>> {
+    let matches = |filter: &serde_json::Value, multi: bool| -> mikudb_storage::StorageResult<Vec<_>> {
+        let matched = collection
+            .find_all()?
+            .into_iter()
+            .filter(|doc| *filter == serde_json::Value::Null || match_filter(doc, filter));
+        Ok(matched.take(if multi { usize::MAX } else { 1 }).collect())
+    };
+    Ok(match operation {
+        BulkOperation::Insert { document } if extended_json => {
+            let value = mikudb_boml::from_extended_json(&document)?;
+            vec![WriteOperation::Insert(mikudb_boml::Document::from_boml_value(value)?)]
+        }
+        BulkOperation::Insert { document } => vec![WriteOperation::Insert(json_to_document(document))],
+        BulkOperation::Update { filter, update, multi, upsert } => {
+            let matched = matches(&filter, multi)?;
+            if matched.is_empty() && upsert {
+                let seed = match filter {
+                    serde_json::Value::Object(map) => serde_json::Value::Object(
+                        map.into_iter()
+                            .filter(|(k, v)| {
+                                !k.starts_with('$')
+                                    && !v.as_object().is_some_and(|o| o.keys().any(|k| k.starts_with('$')))
+                            })
+                            .collect(),
+                    ),
+                    _ => serde_json::Value::Null,
+                };
+                let mut document = json_to_document(seed);
+                apply_update(&mut document, &update);
+                let id = *document.ensure_id();
+                return Ok(vec![WriteOperation::Replace { id, document, upsert: true }]);
+            }
+            matched
+                .into_iter()
+                .filter_map(|mut doc| {
+                    let id = *doc.id()?;
+                    apply_update(&mut doc, &update);
+                    Some(WriteOperation::Replace { id, document: doc, upsert: false })
+                })
+                .collect()
+        }
+        BulkOperation::Delete { filter, multi } => matches(&filter, multi)?
+            .iter()
+            .filter_map(|doc| doc.id().map(|id| WriteOperation::Delete(*id)))
+            .collect(),
+    })
+}
+
+/// # Brief
+/// 把 JSON 对象转换为 BOML 文档,非对象值得到空文档
+fn json_to_document(value: serde_json::Value) -> mikudb_boml::Document {
+    let mut doc = mikudb_boml::Document::new();
+    if let serde_json::Value::Object(map) = value {
+        for (k, v) in map {
+            doc.insert(&k, json_to_boml(v));
+        }
+    }
+    doc
+}
+
 /// # Brief
 /// 检查文档是否匹配过滤条件
 ///
@@ -1065,3 +2270,348 @@ fn apply_update(doc: &mut mikudb_boml::Document, update: &serde_json::Value) ->
 
     modified
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::RoleAssignment;
+    use crate::server::Server;
+    use tokio::net::TcpListener;
+
+    /// 在临时数据目录中启动服务器组件
+    async fn start(auth: bool) -> (tempfile::TempDir, Server) {
+        start_with(|config| config.auth.enabled = auth).await
+    }
+
+    /// 在临时数据目录中以调整后的配置启动服务器组件
+    async fn start_with(configure: impl FnOnce(&mut ServerConfig)) -> (tempfile::TempDir, Server) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = ServerConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        config.preflight.enabled = false;
+        configure(&mut config);
+        let server = Server::new(config).await.unwrap();
+        (dir, server)
+    }
+
+    /// 打开一个由 ClientHandler 处理的连接
+    async fn open(server: &Server) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let handler = ClientHandler::new(
+            1,
+            stream,
+            server.storage().clone(),
+            server.session_manager().clone(),
+            server.user_manager().clone(),
+            server.scheduler().clone(),
+            server.storage_pool().clone(),
+            server.query_log().cloned(),
+            server.op_stats().clone(),
+            server.functions().clone(),
+            server.cursors().clone(),
+            server.cluster().clone(),
+            server.config().clone(),
+        );
+        tokio::spawn(handler.handle());
+        client
+    }
+
+    async fn connect(auth: bool) -> (tempfile::TempDir, Server, TcpStream) {
+        let (dir, server) = start(auth).await;
+        let client = open(&server).await;
+        (dir, server, client)
+    }
+
+    async fn send_message(client: &mut TcpStream, message: Message) {
+        client.write_all(&message.encode()).await.unwrap();
+    }
+
+    async fn receive(client: &mut TcpStream) -> Message {
+        let mut buf = BytesMut::new();
+        let mut pending = None;
+        let mut messages = VecDeque::new();
+        loop {
+            decode_messages(&mut buf, &mut pending, &mut messages).unwrap();
+            if let Some(message) = messages.pop_front() {
+                assert!(buf.is_empty(), "received more than one message");
+                return message;
+            }
+            assert!(client.read_buf(&mut buf).await.unwrap() > 0, "connection closed");
+        }
+    }
+
+    /// 接收指定数量的响应,按到达顺序返回
+    async fn receive_many(client: &mut TcpStream, count: usize) -> Vec<Message> {
+        let mut buf = BytesMut::new();
+        let mut pending = None;
+        let mut messages = VecDeque::new();
+        while messages.len() < count {
+            assert!(client.read_buf(&mut buf).await.unwrap() > 0, "connection closed");
+            decode_messages(&mut buf, &mut pending, &mut messages).unwrap();
+        }
+        messages.into()
+    }
+
+    fn query_message(request_id: u32, flags: u16, query: &str) -> Message {
+        let payload = serde_json::to_vec(&serde_json::json!({ "database": "", "query": query })).unwrap();
+        let mut message = Message::new(OpCode::Query, request_id, payload);
+        message.header.flags = flags;
+        message
+    }
+
+    async fn query(client: &mut TcpStream, request_id: u32, query: &str) -> QueryResponse {
+        let payload = serde_json::to_vec(&serde_json::json!({ "database": "", "query": query })).unwrap();
+        send_message(client, Message::new(OpCode::Query, request_id, payload)).await;
+        let response = receive(client).await;
+        assert_eq!(response.header.response_to, request_id);
+        serde_json::from_slice(&response.payload).unwrap()
+    }
+
+    async fn login(client: &mut TcpStream, username: &str, password: &str) {
+        let payload = serde_json::to_vec(&serde_json::json!({
+            "username": username,
+            "password": password,
+        }))
+        .unwrap();
+        send_message(client, Message::new(OpCode::Auth, 1, payload)).await;
+        let response: AuthResponse = serde_json::from_slice(&receive(client).await.payload).unwrap();
+        assert!(response.success, "{}", response.message);
+    }
+
+    /// 创建只有指定角色(限于默认数据库)、与角色同名的用户并登录
+    async fn login_with_role(server: &Server, client: &mut TcpStream, role: &str) {
+        let roles = vec![RoleAssignment { role: role.to_string(), db: DEFAULT_DATABASE.to_string() }];
+        server.user_manager().create_user(role, "secret", roles).await.unwrap();
+        login(client, role, "secret").await;
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_cannot_alter_user() {
+        let (_dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "read").await;
+
+        let response = query(&mut client, 2, "ALTER USER 'read' ADD ROLE root").await;
+        assert!(!response.success);
+        let message = response.message.unwrap();
+        assert!(message.starts_with("Permission denied"), "{}", message);
+        let user = server.user_manager().authenticate("read", "secret").await.unwrap();
+        assert_eq!(user.roles, vec!["read".to_string()]);
+
+        let response = query(&mut client, 3, "SHOW STATUS").await;
+        assert!(response.success, "{:?}", response.message);
+    }
+
+    #[tokio::test]
+    async fn test_alter_user_failure_is_error_response() {
+        let (_dir, _server, mut client) = connect(false).await;
+        let response = query(&mut client, 1, "ALTER USER 'nobody' PASSWORD 'x'").await;
+        assert!(!response.success);
+        let message = response.message.unwrap();
+        assert!(message.starts_with("Error updating password"), "{}", message);
+    }
+
+    #[tokio::test]
+    async fn test_backup_paths_stay_in_backup_dir() {
+        let (dir, server, mut client) = connect(false).await;
+        let response = query(&mut client, 1, "BACKUP TO 'daily'").await;
+        assert!(response.success, "{:?}", response.message);
+        assert!(dir.path().join("backups/daily/manifest.json").exists());
+        assert_eq!(server.config().backup_dir(), dir.path().join("backups"));
+
+        for statement in ["BACKUP TO '../escaped'", "BACKUP TO '/tmp/escaped'", "RESTORE FROM 'daily/../..'"] {
+            let response = query(&mut client, 2, statement).await;
+            assert!(!response.success, "{}", statement);
+            assert!(response.message.unwrap().contains("Invalid argument"), "{}", statement);
+        }
+        assert!(!dir.path().join("escaped").exists());
+
+        let response = query(&mut client, 3, "RESTORE FROM 'daily'").await;
+        assert!(response.success, "{:?}", response.message);
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_cannot_backup() {
+        let (dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "readWrite").await;
+
+        for (request_id, statement) in [(2, "BACKUP TO 'daily'"), (3, "RESTORE FROM 'daily'")] {
+            let response = query(&mut client, request_id, statement).await;
+            assert!(!response.success, "{}", statement);
+            assert!(response.message.unwrap().starts_with("Permission denied"), "{}", statement);
+        }
+        assert!(!dir.path().join("backups/daily").exists());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_path_stays_in_backup_dir() {
+        let (dir, _server, mut client) = connect(false).await;
+        let response = query(&mut client, 1, "BACKUP SNAPSHOT TO 'snap'").await;
+        assert!(response.success, "{:?}", response.message);
+        assert!(dir.path().join("backups/snap/snapshot.json").exists());
+
+        let response = query(&mut client, 2, "BACKUP SNAPSHOT TO '../snap'").await;
+        assert!(!response.success);
+        assert!(!dir.path().join("snap").exists());
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_cannot_snapshot() {
+        let (dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "readWrite").await;
+
+        let response = query(&mut client, 2, "BACKUP SNAPSHOT TO 'snap'").await;
+        assert!(!response.success);
+        assert!(response.message.unwrap().starts_with("Permission denied"));
+        assert!(!dir.path().join("backups/snap").exists());
+    }
+
+    #[tokio::test]
+    async fn test_incremental_backup_path_stays_in_backup_dir() {
+        let (dir, _server, mut client) = connect(false).await;
+        let payload = serde_json::to_vec(&BackupIncrementalRequest { since_lsn: 0, path: "../inc".to_string() }).unwrap();
+        send_message(&mut client, Message::new(OpCode::BackupIncremental, 1, payload)).await;
+        let response = receive(&mut client).await;
+        assert_eq!(response.header.opcode, OpCode::Error);
+        assert!(String::from_utf8_lossy(&response.payload).contains("Invalid argument"));
+        assert!(!dir.path().join("inc").exists());
+    }
+
+    #[tokio::test]
+    async fn test_set_log_level_failure_is_error_response() {
+        let (_dir, _server, mut client) = connect(false).await;
+        let response = query(&mut client, 1, "ADMIN SET LOG LEVEL loud").await;
+        assert!(!response.success);
+        assert!(response.message.unwrap().contains("Unknown log level 'loud'"));
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_cannot_change_log_level() {
+        let (_dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "readWrite").await;
+
+        for (request_id, statement) in [(2, "ADMIN SET LOG LEVEL trace"), (3, "ADMIN RESET LOG LEVEL")] {
+            let response = query(&mut client, request_id, statement).await;
+            assert!(!response.success, "{}", statement);
+            assert!(response.message.unwrap().starts_with("Permission denied"), "{}", statement);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_cannot_manage_functions() {
+        let (_dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "readWrite").await;
+
+        let statements = [(2, "CREATE FUNCTION score WASM 'AGFzbQEAAAA='"), (3, "DROP FUNCTION score")];
+        for (request_id, statement) in statements {
+            let response = query(&mut client, request_id, statement).await;
+            assert!(!response.success, "{}", statement);
+            assert!(response.message.unwrap().starts_with("Permission denied"), "{}", statement);
+        }
+        assert!(server.functions().names().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_non_admin_cannot_compact() {
+        let (_dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "readWrite").await;
+
+        let response = query(&mut client, 2, "COMPACT users").await;
+        assert!(!response.success);
+        assert!(response.message.unwrap().starts_with("Permission denied"));
+    }
+
+    /// 写入限速为每秒 2 个文档,插入 3 个文档的语句约需 0.5 秒
+    const SLOW_INSERT: &str = "INSERT INTO slow [{n: 1}, {n: 2}, {n: 3}]";
+
+    async fn connect_with_slow_writes() -> (tempfile::TempDir, Server, TcpStream) {
+        let (dir, server) = start_with(|config| {
+            config.auth.enabled = false;
+            config.scheduler.collection_write_limits.insert("slow".to_string(), 2);
+        })
+        .await;
+        let client = open(&server).await;
+        (dir, server, client)
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_respond_out_of_order() {
+        let (_dir, _server, mut client) = connect_with_slow_writes().await;
+
+        send_message(&mut client, query_message(10, FLAG_CONCURRENT, SLOW_INSERT)).await;
+        send_message(&mut client, query_message(11, FLAG_CONCURRENT, "SHOW STATUS")).await;
+        send_message(&mut client, query_message(12, FLAG_CONCURRENT, "FIND FROM WHERE")).await;
+
+        let responses = receive_many(&mut client, 3).await;
+        let order: Vec<u32> = responses.iter().map(|m| m.header.response_to).collect();
+        // 慢请求最后完成,每个响应按 response_to 对应到自己的请求
+        assert_eq!(order.last(), Some(&10), "{:?}", order);
+        for message in &responses {
+            let response: QueryResponse = serde_json::from_slice(&message.payload).unwrap();
+            match message.header.response_to {
+                10 => assert_eq!(response.affected, 3, "{:?}", response.message),
+                11 => assert!(response.success, "{:?}", response.message),
+                12 => assert_eq!(response.error_kind, Some(ErrorKind::Parse)),
+                other => panic!("unexpected response to {}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_concurrent_flag_stay_in_order() {
+        let (_dir, _server, mut client) = connect_with_slow_writes().await;
+
+        send_message(&mut client, query_message(10, 0, SLOW_INSERT)).await;
+        send_message(&mut client, query_message(11, 0, "SHOW STATUS")).await;
+
+        let responses = receive_many(&mut client, 2).await;
+        let order: Vec<u32> = responses.iter().map(|m| m.header.response_to).collect();
+        assert_eq!(order, vec![10, 11]);
+        let response: QueryResponse = serde_json::from_slice(&responses[0].payload).unwrap();
+        assert_eq!(response.affected, 3, "{:?}", response.message);
+    }
+
+    #[tokio::test]
+    async fn test_failed_responses_carry_error_kind() {
+        let (_dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "readWrite").await;
+
+        let response = query(&mut client, 2, "FIND FROM WHERE").await;
+        assert_eq!(response.error_kind, Some(ErrorKind::Parse));
+        let response = query(&mut client, 3, "COMPACT users").await;
+        assert_eq!(response.error_kind, Some(ErrorKind::Permission));
+        let response = query(&mut client, 4, "SET nonexistent = 1").await;
+        assert_eq!(response.error_kind, Some(ErrorKind::Execution));
+        let response = query(&mut client, 5, "SHOW STATUS").await;
+        assert!(response.success, "{:?}", response.message);
+        assert_eq!(response.error_kind, None);
+    }
+
+    #[tokio::test]
+    async fn test_database_scoped_user() {
+        let (_dir, server, mut client) = connect(true).await;
+        login_with_role(&server, &mut client, "read").await;
+
+        let response = query(&mut client, 2, "SHOW STATUS").await;
+        assert!(response.success, "{:?}", response.message);
+        let response = query(&mut client, 3, "USE other").await;
+        assert!(!response.success);
+        assert!(response.message.unwrap().starts_with("Permission denied"));
+    }
+
+    #[tokio::test]
+    async fn test_created_user_can_access_all_databases() {
+        let (_dir, server, mut client) = connect(true).await;
+        login(&mut client, "root", "mikudb_initial_password").await;
+        let response = query(&mut client, 2, "CREATE USER 'analyst' WITH PASSWORD 'secret' ROLE read").await;
+        assert!(response.success, "{:?}", response.message);
+
+        let mut client = open(&server).await;
+        login(&mut client, "analyst", "secret").await;
+        let response = query(&mut client, 2, "SHOW STATUS").await;
+        assert!(response.success, "{:?}", response.message);
+    }
+}
@@ -0,0 +1,701 @@
+//! HTTP 接口模块
+//!
+//! 在独立端口上提供 JSON REST API,供管理工具和 Web 控制台使用:
+//! - `GET    /api/collections`                   列出集合
+//! - `GET    /api/collections/{name}/documents`  浏览文档(支持 `limit`、`skip` 参数)
+//! - `GET    /api/collections/{name}/indexes`    列出索引
+//! - `POST   /api/query`                         执行 MQL 语句 (`{"query": "...", "meta": {...}}`,`meta` 可选)
+//! - `GET    /api/metrics`                       服务器运行指标、调度/存储线程池/游标/巡检/TTL 清理状态、按集合的操作统计、告警
+//! - `GET    /metrics`                           Prometheus 文本格式指标,名称见 `metrics` 模块
+//! - `GET    /api/users`                         列出用户
+//! - `POST   /api/users`                         创建用户 (`{"username", "password", "roles"}`)
+//! - `DELETE /api/users/{name}`                  删除用户
+//!
+//! `/` 和 `/console` 返回内嵌的 Web 管理控制台。本模块只在启用 `console` feature 时编译。
+//!
+//! 认证使用 HTTP Basic,与二进制协议共用 UserManager 和 RBAC 权限检查。
+//! 语句按所在数据库检查权限,没有列出的语句需要管理员权限。
+//! 每个连接只处理一个请求 (`Connection: close`)。
+//! 语句执行经过请求调度器,可通过 `X-MikuDB-Priority: batch` 请求头声明批处理优先级。
+//! `X-MikuDB-Database` 请求头指定语句所在的数据库,未指定时使用配置的默认数据库。
+
+use crate::auth::{check_permission, statement_permission, Permission, RoleAssignment, User};
+use crate::handler::execute_statement;
+use crate::metrics;
+use crate::protocol::{QueryResponse, MAX_MESSAGE_SIZE};
+use crate::scheduler::{self, Priority};
+use crate::server::Server;
+use crate::ServerResult;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use mikudb_query::{FindStatement, Parser, Statement};
+use mikudb_storage::{WriteMetadata, DEFAULT_DATABASE};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/// 请求头最大长度 (16 KB)
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// 文档浏览默认返回条数
+const DEFAULT_PAGE_SIZE: u64 = 50;
+
+/// HTTP 请求
+#[derive(Debug)]
+pub struct HttpRequest {
+    /// 请求方法(大写)
+    pub method: String,
+    /// 已解码的路径
+    pub path: String,
+    /// 查询参数
+    pub query: HashMap<String, String>,
+    /// 请求头(键为小写)
+    pub headers: HashMap<String, String>,
+    /// 请求体
+    pub body: Vec<u8>,
+}
+
+/// HTTP 响应
+#[derive(Debug)]
+pub struct HttpResponse {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// # Brief
+    /// 构造 JSON 响应
+    pub fn json(status: u16, value: &impl serde::Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json; charset=utf-8",
+            headers: vec![],
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+
+    /// # Brief
+    /// 构造错误响应,格式与协议层 QueryResponse 一致
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, &QueryResponse {
+            success: false,
+            affected: 0,
+            documents: vec![],
+            cursor_id: None,
+            message: Some(message.into()),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
+        })
+    }
+
+    /// # Brief
+    /// 构造 HTML 响应
+    pub fn html(body: &str) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            headers: vec![],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    /// # Brief
+    /// 构造纯文本响应
+    pub fn text(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            headers: vec![],
+            body: body.into_bytes(),
+        }
+    }
+
+    /// # Brief
+    /// 将查询结果转换为响应,失败时返回 400
+    fn query(response: QueryResponse) -> Self {
+        let status = if response.success { 200 } else { 400 };
+        Self::json(status, &response)
+    }
+
+    fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// # Brief
+    /// 编码为 HTTP/1.1 响应报文
+    pub fn encode(&self) -> Vec<u8> {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason_phrase(self.status),
+            self.content_type,
+            self.body.len()
+        );
+        for (name, value) in &self.headers {
+            head.push_str(name);
+            head.push_str(": ");
+            head.push_str(value);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+
+        let mut out = head.into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// # Brief
+/// 启动 HTTP 监听循环
+///
+/// # Arguments
+/// * `server` - 服务器实例
+///
+/// # Returns
+/// 监听失败时返回错误
+pub async fn serve(server: Arc<Server>) -> ServerResult<()> {
+    let addr = format!("{}:{}", server.config().http.bind, server.config().http.port);
+    let listener = TcpListener::bind(&addr).await?;
+
+    info!("HTTP interface listening on http://{}", addr);
+    info!("Web console available at http://{}/console", addr);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = server.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, server).await {
+                debug!("HTTP connection from {} error: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, server: Arc<Server>) -> ServerResult<()> {
+    // 限制读取整个请求的时间,避免慢速客户端长期占用连接
+    let read_timeout = Duration::from_millis(server.config().timeout_ms);
+    let response = match tokio::time::timeout(read_timeout, read_request(&mut stream)).await {
+        Ok(Ok(Ok(request))) => {
+            server.increment_requests();
+            route(&server, request).await
+        }
+        Ok(Ok(Err(response))) => response,
+        Ok(Err(e)) => return Err(e),
+        Err(_) => HttpResponse::error(408, "Timed out reading request"),
+    };
+
+    stream.write_all(&response.encode()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// # Brief
+/// 从连接读取一个完整的 HTTP 请求
+///
+/// # Returns
+/// 外层错误表示 IO 失败;内层 Err 为应直接返回给客户端的错误响应
+async fn read_request(stream: &mut TcpStream) -> ServerResult<Result<HttpRequest, HttpResponse>> {
+    let mut buf = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 4096];
+
+    let head_end = loop {
+        if let Some(pos) = find_head_end(&buf) {
+            break pos;
+        }
+        if buf.len() > MAX_HEAD_SIZE {
+            return Ok(Err(HttpResponse::error(413, "Request header too large")));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(crate::ServerError::ConnectionClosed);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let mut request = match parse_head(&buf[..head_end]) {
+        Ok(request) => request,
+        Err(msg) => return Ok(Err(HttpResponse::error(400, msg))),
+    };
+
+    let content_length = match request.headers.get("content-length") {
+        Some(v) => match v.parse::<usize>() {
+            Ok(len) => len,
+            Err(_) => return Ok(Err(HttpResponse::error(400, "Invalid Content-Length"))),
+        },
+        None => 0,
+    };
+    if content_length > MAX_MESSAGE_SIZE {
+        return Ok(Err(HttpResponse::error(413, "Request body too large")));
+    }
+
+    let mut body = buf.split_off(head_end + 4);
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(crate::ServerError::ConnectionClosed);
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+
+    Ok(Ok(request))
+}
+
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n")
+}
+
+/// # Brief
+/// 解析请求行和请求头
+///
+/// # Arguments
+/// * `head` - 不含结尾空行的请求头字节
+fn parse_head(head: &[u8]) -> Result<HttpRequest, String> {
+    let head = std::str::from_utf8(head).map_err(|_| "Request header is not valid UTF-8".to_string())?;
+    let mut lines = head.split("\r\n");
+
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(format!("Malformed request line: {}", request_line));
+    };
+
+    let (raw_path, raw_query) = target.split_once('?').unwrap_or((target, ""));
+    let query = raw_query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect();
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    Ok(HttpRequest {
+        method: method.to_ascii_uppercase(),
+        path: percent_decode(raw_path),
+        query,
+        headers,
+        body: vec![],
+    })
+}
+
+/// # Brief
+/// URL 百分号解码,`+` 视为空格
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(hi), Some(lo)) => {
+                        out.push((hi * 16 + lo) as u8);
+                        i += 3;
+                        continue;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// # Brief
+/// 根据方法和路径分发请求
+async fn route(server: &Arc<Server>, request: HttpRequest) -> HttpResponse {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').filter(|s| !s.is_empty()).collect();
+
+    if request.method == "GET" && matches!(segments.as_slice(), [] | ["console"]) {
+        return HttpResponse::html(crate::console::INDEX_HTML);
+    }
+
+    let user = match authenticate(server, &request).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let priority = request
+        .headers
+        .get("x-mikudb-priority")
+        .and_then(|v| Priority::parse(v))
+        .unwrap_or_default();
+    // 每个请求独立选择数据库,USE 语句不会影响后续请求
+    let database = request
+        .headers
+        .get("x-mikudb-database")
+        .cloned()
+        .or_else(|| server.config().default_database.clone())
+        .unwrap_or_else(|| DEFAULT_DATABASE.to_string());
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["api", "collections"]) => {
+            run_statement(server, &user, priority, &database, &Statement::ShowCollections, None).await
+        }
+        ("GET", ["api", "collections", name, "documents"]) => {
+            let limit = request.query.get("limit").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_PAGE_SIZE);
+            let skip = request.query.get("skip").and_then(|v| v.parse().ok());
+            let statement = Statement::Find(FindStatement {
+                collection: name.to_string(),
+                limit: Some(limit),
+                skip,
+                ..Default::default()
+            });
+            run_statement(server, &user, priority, &database, &statement, None).await
+        }
+        ("GET", ["api", "collections", name, "indexes"]) => {
+            run_statement(server, &user, priority, &database, &Statement::ShowIndexes(name.to_string()), None).await
+        }
+        ("POST", ["api", "query"]) => handle_query(server, &user, priority, &database, &request.body).await,
+        ("GET", ["api", "metrics"]) => handle_metrics(server, &user).await,
+        ("GET", ["metrics"]) => match require(&user, "", "", Permission::Read) {
+            Ok(()) => HttpResponse::text(metrics::CONTENT_TYPE, metrics::render(server)),
+            Err(response) => response,
+        },
+        ("GET", ["api", "users"]) => run_statement(server, &user, priority, &database, &Statement::ShowUsers, None).await,
+        ("POST", ["api", "users"]) => handle_create_user(server, &user, &request.body).await,
+        ("DELETE", ["api", "users", name]) => {
+            run_statement(server, &user, priority, &database, &Statement::DropUser(name.to_string()), None).await
+        }
+        (_, ["api", ..]) => HttpResponse::error(404, format!("No route for {} {}", request.method, request.path)),
+        _ => HttpResponse::error(404, "Not found"),
+    }
+}
+
+/// # Brief
+/// 使用 HTTP Basic 认证解析当前用户
+///
+/// 认证未启用时视为 root 用户。
+async fn authenticate(server: &Arc<Server>, request: &HttpRequest) -> Result<User, HttpResponse> {
+    if !server.config().auth.enabled {
+        return Ok(User {
+            username: "anonymous".to_string(),
+            password_hash: String::new(),
+            roles: vec!["root".to_string()],
+            databases: vec![],
+        });
+    }
+
+    let unauthorized = |msg: &str| {
+        HttpResponse::error(401, msg).with_header("WWW-Authenticate", "Basic realm=\"MikuDB\"")
+    };
+
+    let Some(credentials) = request
+        .headers
+        .get("authorization")
+        .and_then(|v| v.strip_prefix("Basic "))
+        .and_then(|v| BASE64.decode(v.trim()).ok())
+        .and_then(|v| String::from_utf8(v).ok())
+    else {
+        return Err(unauthorized("Not authenticated"));
+    };
+
+    let Some((username, password)) = credentials.split_once(':') else {
+        return Err(unauthorized("Malformed credentials"));
+    };
+
+    match server.user_manager().authenticate(username, password).await {
+        Ok(user) => Ok(user),
+        Err(e) => {
+            warn!("HTTP authentication failed for '{}': {}", username, e);
+            Err(unauthorized("Authentication failed"))
+        }
+    }
+}
+
+/// # Brief
+/// 获取语句操作的集合名,不针对单个集合的语句返回空字符串
+fn statement_collection(statement: &Statement) -> &str {
+    match statement {
+        Statement::Find(find) => &find.collection,
+        Statement::Aggregate(aggregate) => &aggregate.collection,
+        Statement::ShowIndexes(name)
+        | Statement::ShowSchema(name)
+        | Statement::Stats(name)
+        | Statement::DropCollection(name)
+        | Statement::Analyze(name)
+        | Statement::Compact(name) => name,
+        Statement::DryRun(inner) => statement_collection(inner),
+        _ => scheduler::write_target(statement).map(|(collection, _)| collection).unwrap_or(""),
+    }
+}
+
+/// # Brief
+/// 检查用户对数据库和集合的权限,失败时返回 403 响应
+///
+/// # Arguments
+/// * `database` - 数据库名,空字符串表示服务器级操作
+/// * `collection` - 集合名,可为空
+fn require(user: &User, database: &str, collection: &str, permission: Permission) -> Result<(), HttpResponse> {
+    if check_permission(user, database, collection, permission) {
+        Ok(())
+    } else {
+        Err(HttpResponse::error(403, format!(
+            "User '{}' lacks {:?} permission",
+            user.username, permission
+        )))
+    }
+}
+
+async fn run_statement(
+    server: &Arc<Server>,
+    user: &User,
+    priority: Priority,
+    database: &str,
+    statement: &Statement,
+    meta: Option<WriteMetadata>,
+) -> HttpResponse {
+    if let Err(response) = require(user, database, statement_collection(statement), statement_permission(statement)) {
+        return response;
+    }
+
+    if matches!(statement, Statement::ClusterInit | Statement::ClusterJoin(_)) {
+        return HttpResponse::query(server.cluster().execute(statement).await);
+    }
+
+    if let Some(query_log) = server.query_log() {
+        query_log.record(statement);
+    }
+
+    let metadata = match server.cluster().write_metadata(meta) {
+        Ok(metadata) => metadata,
+        Err(e) => return HttpResponse::error(400, e.to_string()),
+    };
+
+    let _permit = server.scheduler().acquire(priority).await;
+    if let Some((collection, documents)) = scheduler::write_target(statement) {
+        server.scheduler().throttle_write(collection, documents).await;
+    }
+
+    HttpResponse::query(execute_statement(
+        server.storage(),
+        server.storage_pool(),
+        server.user_manager(),
+        server.op_stats(),
+        server.functions(),
+        server.config(),
+        false,
+        database,
+        statement,
+        None,
+        metadata,
+    ).await)
+}
+
+#[derive(Deserialize)]
+struct QueryBody {
+    query: String,
+    #[serde(default)]
+    meta: Option<WriteMetadata>,
+}
+
+async fn handle_query(
+    server: &Arc<Server>,
+    user: &User,
+    priority: Priority,
+    database: &str,
+    body: &[u8],
+) -> HttpResponse {
+    let body: QueryBody = match serde_json::from_slice(body) {
+        Ok(body) => body,
+        Err(e) => return HttpResponse::error(400, format!("Invalid query request: {}", e)),
+    };
+
+    match Parser::parse(&body.query) {
+        Ok(statement) => run_statement(server, user, priority, database, &statement, body.meta).await,
+        Err(e) => HttpResponse::error(400, format!("Parse error: {}", e)),
+    }
+}
+
+async fn handle_metrics(server: &Arc<Server>, user: &User) -> HttpResponse {
+    if let Err(response) = require(user, "", "", Permission::Read) {
+        return response;
+    }
+
+    let stats = server.stats();
+    let mut alerts = Vec::new();
+    let scrub = server.scrubber().map(|s| s.stats());
+    if let Some(ref scrub) = scrub {
+        if scrub.corruptions_detected > 0 {
+            alerts.push(format!(
+                "Storage scrub detected {} corruption finding(s); see the _scrub_report collection",
+                scrub.corruptions_detected
+            ));
+        }
+    }
+
+    // 遍历集合和读取存储统计会访问 RocksDB,放到存储线程池中执行
+    let storage = server.storage().clone();
+    let storage_metrics = server.storage_pool().run(move || {
+        // 启用字段类型记录的集合的类型漂移计数
+        let mut type_drift = serde_json::Map::new();
+        for name in storage.list_collections().unwrap_or_default() {
+            let Ok(collection) = storage.get_collection(&name) else {
+                continue;
+            };
+            if collection.schema_options().enabled() {
+                let stats = collection.stats();
+                type_drift.insert(name, serde_json::json!({
+                    "drift": stats.type_drift_count,
+                    "rejected": stats.type_rejected_count,
+                }));
+            }
+        }
+        (type_drift, storage.get_approximate_size(), storage.wal_stats())
+    });
+    let (type_drift, storage_size, wal) = match storage_metrics.await {
+        Ok(metrics) => metrics,
+        Err(e) => return HttpResponse::error(500, e.to_string()),
+    };
+
+    HttpResponse::json(200, &serde_json::json!({
+        "uptime_secs": stats.uptime_secs,
+        "total_connections": stats.total_connections,
+        "total_requests": stats.total_requests,
+        "active_sessions": stats.active_sessions,
+        "storage_size_bytes": storage_size,
+        "scheduler": server.scheduler().stats(),
+        "storage_pool": server.storage_pool().stats(),
+        "cursors": server.cursors().stats(),
+        "scrub": scrub,
+        "ttl": server.ttl_sweeper().map(|s| s.stats()),
+        "wal": wal,
+        "type_drift": type_drift,
+        "collection_ops": server.op_stats().snapshot_all(),
+        "alerts": alerts,
+    }))
+}
+
+#[derive(Deserialize)]
+struct CreateUserBody {
+    username: String,
+    password: String,
+    #[serde(default)]
+    roles: Vec<String>,
+}
+
+async fn handle_create_user(server: &Arc<Server>, user: &User, body: &[u8]) -> HttpResponse {
+    if let Err(response) = require(user, "", "", Permission::Admin) {
+        return response;
+    }
+
+    let body: CreateUserBody = match serde_json::from_slice(body) {
+        Ok(body) => body,
+        Err(e) => return HttpResponse::error(400, format!("Invalid user request: {}", e)),
+    };
+
+    let roles = if body.roles.is_empty() { vec!["readWrite".to_string()] } else { body.roles };
+    let roles = roles
+        .into_iter()
+        .map(|role| RoleAssignment { role, db: "*".to_string() })
+        .collect();
+
+    match server.user_manager().create_user(&body.username, &body.password, roles).await {
+        Ok(()) => HttpResponse::json(201, &QueryResponse {
+            success: true,
+            affected: 1,
+            documents: vec![],
+            cursor_id: None,
+            message: Some(format!("User '{}' created successfully", body.username)),
+            errors: vec![],
+            stats: None,
+            error_kind: None,
+        }),
+        Err(e) => HttpResponse::error(400, format!("Error creating user: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+
+    async fn start(timeout_ms: u64) -> (tempfile::TempDir, Arc<Server>) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = ServerConfig {
+            data_dir: dir.path().to_path_buf(),
+            timeout_ms,
+            ..Default::default()
+        };
+        config.preflight.enabled = false;
+        config.auth.enabled = true;
+        let server = Server::new(config).await.unwrap();
+        let roles = vec![RoleAssignment { role: "read".to_string(), db: DEFAULT_DATABASE.to_string() }];
+        server.user_manager().create_user("reader", "secret", roles).await.unwrap();
+        (dir, Arc::new(server))
+    }
+
+    fn query_request(database: &str, query: &str) -> HttpRequest {
+        let headers = [
+            ("authorization".to_string(), format!("Basic {}", BASE64.encode("reader:secret"))),
+            ("x-mikudb-database".to_string(), database.to_string()),
+        ];
+        HttpRequest {
+            method: "POST".to_string(),
+            path: "/api/query".to_string(),
+            query: HashMap::new(),
+            headers: headers.into_iter().collect(),
+            body: serde_json::to_vec(&serde_json::json!({ "query": query })).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_database_scoped_user() {
+        let (_dir, server) = start(30_000).await;
+        let response = route(&server, query_request(DEFAULT_DATABASE, "SHOW STATUS")).await;
+        assert_eq!(response.status, 200);
+        let response = route(&server, query_request("other", "SHOW STATUS")).await;
+        assert_eq!(response.status, 403);
+        let response = route(&server, query_request(DEFAULT_DATABASE, "COMPACT users")).await;
+        assert_eq!(response.status, 403);
+    }
+
+    #[tokio::test]
+    async fn test_request_read_timeout() {
+        let (_dir, server) = start(50).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let connection = tokio::spawn(handle_connection(stream, server));
+
+        // 只发送部分请求头,服务器应在超时后返回 408
+        client.write_all(b"GET /api/metrics HTTP/1.1\r\n").await.unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).await.unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 408 Request Timeout"));
+        connection.await.unwrap().unwrap();
+    }
+}
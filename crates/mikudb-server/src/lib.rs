@@ -2,9 +2,22 @@ pub mod config;
 pub mod server;
 pub mod network;
 pub mod protocol;
+pub mod cursor;
 pub mod handler;
 pub mod auth;
 pub mod session;
+pub mod metrics;
+pub mod scheduler;
+pub mod storage_pool;
+pub mod preflight;
+pub mod logging;
+pub mod state;
+pub mod cluster;
+
+#[cfg(feature = "console")]
+pub mod console;
+#[cfg(feature = "console")]
+pub mod http;
 
 #[cfg(target_os = "linux")]
 pub mod openeuler;
@@ -12,10 +25,18 @@ pub mod openeuler;
 #[cfg(feature = "tls")]
 pub mod tls;
 
+#[cfg(feature = "redis-notify")]
+pub mod notify;
+
 pub use config::ServerConfig;
 pub use server::Server;
 pub use session::{Session, SessionManager};
+pub use cursor::{CursorLimitPolicy, CursorLimits, CursorManager, CursorOwner};
 pub use auth::{UserManager, Privilege, RoleAssignment};
+pub use scheduler::{Priority, RequestScheduler};
+pub use storage_pool::StoragePool;
+pub use logging::init_logging;
+pub use cluster::ClusterManager;
 
 use thiserror::Error;
 
@@ -54,24 +75,30 @@ pub enum ServerError {
     #[error("Timeout")]
     Timeout,
 
+    #[error("Cursor limit exceeded: {0}")]
+    CursorLimit(String),
+
+    #[error("Preflight check failed: {0}")]
+    Preflight(String),
+
+    #[error("Cluster error: {0}")]
+    Cluster(#[from] mikudb_cluster::ClusterError),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
-pub type ServerResult<T> = Result<T, ServerError>;
-
-pub fn init_logging(level: &str) {
-    use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+impl ServerError {
+    /// # Brief
+    /// 获取结构化的校验错误(来自存储层模式约束或查询层写入校验)
+    pub fn validation_details(&self) -> Option<&[mikudb_storage::ValidationDetail]> {
+        match self {
+            ServerError::Storage(e) => e.validation_details(),
+            ServerError::Query(e) => e.validation_details(),
+            _ => None,
+        }
+    }
+}
 
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new(level));
+pub type ServerResult<T> = Result<T, ServerError>;
 
-    tracing_subscriber::registry()
-        .with(fmt::layer()
-            .with_target(true)
-            .with_thread_ids(true)
-            .with_file(true)
-            .with_line_number(true))
-        .with(filter)
-        .init();
-}
@@ -0,0 +1,275 @@
+//! 日志模块
+//!
+//! 本模块负责服务器日志输出和运行时调整:
+//! - 初始化 tracing 订阅器(格式化输出 + EnvFilter)
+//! - 通过可重载的过滤器在运行时调整日志级别(ADMIN SET LOG LEVEL),无需重启
+//! - 按模块路径前缀单独调整级别,例如只打开 mikudb_storage 的 debug 日志
+//! - SIGUSR1 信号切换所有 MikuDB 模块的 debug 日志
+//! - 导出运行时调整过的过滤规则,服务器重启后恢复
+
+use crate::{ServerError, ServerResult};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use tracing::info;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// SIGUSR1 打开的过滤指令,目标前缀匹配所有 mikudb_* 模块
+const SIGNAL_DEBUG_DIRECTIVE: &str = "mikudb=debug";
+
+static LOG_CONTROL: OnceLock<LogControl> = OnceLock::new();
+
+/// 可重载的日志过滤器及其状态
+struct LogControl {
+    /// 过滤器重载句柄
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// 启动时的过滤规则(RUST_LOG 或 --log-level)
+    startup: String,
+    /// 当前过滤规则
+    state: Mutex<FilterState>,
+}
+
+/// 过滤规则的组成部分
+#[derive(Clone)]
+struct FilterState {
+    /// 默认规则
+    default: String,
+    /// 按模块路径前缀设置的级别
+    targets: BTreeMap<String, String>,
+    /// 是否由 SIGUSR1 打开了 debug 日志
+    signal_debug: bool,
+}
+
+impl FilterState {
+    fn new(default: String) -> Self {
+        Self {
+            default,
+            targets: BTreeMap::new(),
+            signal_debug: false,
+        }
+    }
+
+    /// # Brief
+    /// 拼接为 EnvFilter 指令字符串
+    ///
+    /// EnvFilter 按目标前缀长度选择最具体的指令,因此按模块设置的级别
+    /// 总是优先于默认规则和 SIGUSR1 指令。
+    fn directives(&self) -> String {
+        let mut parts = vec![self.default.clone()];
+        if self.signal_debug {
+            parts.push(SIGNAL_DEBUG_DIRECTIVE.to_string());
+        }
+        parts.extend(self.targets.iter().map(|(target, level)| format!("{}={}", target, level)));
+        parts.join(",")
+    }
+}
+
+/// # Brief
+/// 初始化日志订阅器
+///
+/// 优先使用 RUST_LOG 环境变量,否则使用传入的级别。过滤器可在运行时重载。
+///
+/// # Arguments
+/// * `level` - 默认日志级别
+pub fn init_logging(level: &str) {
+    use tracing_subscriber::{fmt, prelude::*};
+
+    let startup = std::env::var(EnvFilter::DEFAULT_ENV)
+        .ok()
+        .filter(|s| EnvFilter::try_new(s).is_ok())
+        .unwrap_or_else(|| level.to_string());
+    let (filter, handle) = reload::Layer::new(EnvFilter::new(&startup));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer()
+            .with_target(true)
+            .with_thread_ids(true)
+            .with_file(true)
+            .with_line_number(true))
+        .init();
+
+    let _ = LOG_CONTROL.set(LogControl {
+        handle,
+        state: Mutex::new(FilterState::new(startup.clone())),
+        startup,
+    });
+}
+
+fn control() -> ServerResult<&'static LogControl> {
+    LOG_CONTROL
+        .get()
+        .ok_or_else(|| ServerError::Internal("Logging was not initialized with a reloadable filter".to_string()))
+}
+
+fn apply(control: &LogControl, state: &FilterState) -> ServerResult<String> {
+    let directives = state.directives();
+    let filter = EnvFilter::try_new(&directives)
+        .map_err(|e| ServerError::Config(format!("Invalid log filter '{}': {}", directives, e)))?;
+    control
+        .handle
+        .reload(filter)
+        .map_err(|e| ServerError::Internal(format!("Failed to reload log filter: {}", e)))?;
+    Ok(directives)
+}
+
+/// # Brief
+/// 运行时调整日志级别
+///
+/// # Arguments
+/// * `level` - 日志级别(trace/debug/info/warn/error/off)
+/// * `target` - 模块路径前缀,为 None 时替换默认规则
+///
+/// # Returns
+/// 生效后的过滤规则
+pub fn set_log_level(level: &str, target: Option<&str>) -> ServerResult<String> {
+    let level = level
+        .parse::<LevelFilter>()
+        .map_err(|_| ServerError::Config(format!("Unknown log level '{}'", level)))?
+        .to_string()
+        .to_lowercase();
+    if let Some(target) = target {
+        let valid = !target.is_empty()
+            && target.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':');
+        if !valid {
+            return Err(ServerError::Config(format!("Invalid log target '{}'", target)));
+        }
+    }
+
+    let control = control()?;
+    let mut state = control.state.lock();
+    let mut next = state.clone();
+    match target {
+        Some(target) => {
+            next.targets.insert(target.to_string(), level);
+        }
+        None => next.default = level,
+    }
+
+    let directives = apply(control, &next)?;
+    *state = next;
+    info!("Log filter changed to '{}'", directives);
+    Ok(directives)
+}
+
+/// # Brief
+/// 恢复启动时的日志过滤规则
+///
+/// 同时清除按模块设置的级别和 SIGUSR1 打开的 debug 日志。
+///
+/// # Returns
+/// 生效后的过滤规则
+pub fn reset_log_level() -> ServerResult<String> {
+    let control = control()?;
+    let mut state = control.state.lock();
+    let next = FilterState::new(control.startup.clone());
+    let directives = apply(control, &next)?;
+    *state = next;
+    info!("Log filter reset to '{}'", directives);
+    Ok(directives)
+}
+
+/// # Brief
+/// 切换所有 MikuDB 模块的 debug 日志
+///
+/// # Returns
+/// 切换后是否处于 debug 状态
+pub fn toggle_debug() -> ServerResult<bool> {
+    let control = control()?;
+    let mut state = control.state.lock();
+    state.signal_debug = !state.signal_debug;
+    if let Err(e) = apply(control, &state) {
+        state.signal_debug = !state.signal_debug;
+        return Err(e);
+    }
+    info!("MikuDB debug logging {}", if state.signal_debug { "enabled" } else { "disabled" });
+    Ok(state.signal_debug)
+}
+
+/// # Brief
+/// 获取当前生效的过滤规则
+///
+/// # Returns
+/// 日志未初始化时返回 None
+pub fn current_filter() -> Option<String> {
+    LOG_CONTROL.get().map(|control| control.state.lock().directives())
+}
+
+/// 运行时调整过的日志过滤规则,随运行状态快照保存
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogSettings {
+    /// 保存时的启动规则,启动规则改变后不再恢复
+    pub startup: String,
+    /// 默认规则
+    pub default: String,
+    /// 按模块路径前缀设置的级别
+    pub targets: BTreeMap<String, String>,
+}
+
+/// # Brief
+/// 导出通过 ADMIN SET LOG LEVEL 调整过的过滤规则
+///
+/// SIGUSR1 打开的 debug 日志是临时排查手段,不导出。
+///
+/// # Returns
+/// 日志未初始化或规则与启动时相同时返回 None
+pub fn settings() -> Option<LogSettings> {
+    let control = LOG_CONTROL.get()?;
+    let state = control.state.lock();
+    if state.default == control.startup && state.targets.is_empty() {
+        return None;
+    }
+    Some(LogSettings {
+        startup: control.startup.clone(),
+        default: state.default.clone(),
+        targets: state.targets.clone(),
+    })
+}
+
+/// # Brief
+/// 恢复保存的过滤规则
+///
+/// 启动规则(RUST_LOG 或 --log-level)与保存时不同说明运维人员修改了配置,此时以配置为准。
+///
+/// # Arguments
+/// * `settings` - `settings` 导出的规则
+///
+/// # Returns
+/// 恢复后的过滤规则;启动规则已改变时返回 None
+pub fn restore_settings(settings: &LogSettings) -> ServerResult<Option<String>> {
+    let control = control()?;
+    if settings.startup != control.startup {
+        return Ok(None);
+    }
+    let mut state = control.state.lock();
+    let mut next = state.clone();
+    next.default = settings.default.clone();
+    next.targets = settings.targets.clone();
+    let directives = apply(control, &next)?;
+    *state = next;
+    Ok(Some(directives))
+}
+
+/// # Brief
+/// 安装 SIGUSR1 处理: 每次收到信号切换一次 MikuDB 模块的 debug 日志
+///
+/// 用法: `kill -USR1 $(pidof mikudb-server)`
+#[cfg(unix)]
+pub fn install_signal_toggle() -> std::io::Result<()> {
+    use signal_hook::consts::SIGUSR1;
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGUSR1])?;
+    std::thread::Builder::new()
+        .name("mikudb-log-signal".to_string())
+        .spawn(move || {
+            for _ in signals.forever() {
+                if let Err(e) = toggle_debug() {
+                    tracing::warn!("Failed to toggle debug logging: {}", e);
+                }
+            }
+        })?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use clap::Parser;
 use mikudb_server::{Server, ServerConfig};
+use mikudb_storage::{snapshot, StorageEngine, StorageOptions};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::{error, info};
@@ -27,6 +28,22 @@ struct Args {
 
     #[arg(long)]
     daemon: bool,
+
+    /// Restore a BACKUP SNAPSHOT directory into the (empty) data directory before starting
+    #[arg(long)]
+    restore_snapshot: Option<PathBuf>,
+
+    /// Apply an incremental backup after --restore-snapshot (repeat in backup order)
+    #[arg(long, requires = "restore_snapshot")]
+    restore_increment: Vec<PathBuf>,
+
+    /// Bootstrap a single-node cluster (no-op once this node belongs to a cluster)
+    #[arg(long, conflicts_with = "join")]
+    cluster_init: bool,
+
+    /// Join a cluster through a member's cluster port, e.g. mikudb://host:3941 (no-op once joined)
+    #[arg(long, value_name = "URI")]
+    join: Option<String>,
 }
 
 #[tokio::main]
@@ -35,6 +52,11 @@ async fn main() -> anyhow::Result<()> {
 
     mikudb_server::init_logging(&args.log_level);
 
+    #[cfg(unix)]
+    if let Err(e) = mikudb_server::logging::install_signal_toggle() {
+        tracing::warn!("Failed to install SIGUSR1 log toggle: {}", e);
+    }
+
     mikudb_core::print_banner();
 
     let config = if let Some(config_path) = &args.config {
@@ -49,10 +71,52 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    if let Some(snapshot_dir) = &args.restore_snapshot {
+        let options = StorageOptions {
+            data_dir: config.data_dir.clone(),
+            wal_dir: config.storage.wal_dir.clone(),
+            wal_archive_dir: config.storage.wal_archive_dir.clone(),
+            ..Default::default()
+        };
+        if args.restore_increment.is_empty() {
+            let manifest = snapshot::restore_snapshot(snapshot_dir, &options)?;
+            info!(
+                "Restored snapshot {:?} taken at {} ({} collections)",
+                snapshot_dir,
+                manifest.created_at,
+                manifest.collections.len()
+            );
+        } else {
+            // 应用增量需要打开存储引擎,完成后关闭再由服务器重新打开
+            drop(StorageEngine::restore_with_increments(snapshot_dir, &args.restore_increment, options)?);
+            info!(
+                "Restored snapshot {:?} with {} incremental backup(s)",
+                snapshot_dir,
+                args.restore_increment.len()
+            );
+        }
+    }
+
     info!("Starting MikuDB server on {}:{}", config.bind, config.port);
 
     let server = Arc::new(Server::new(config).await?);
 
+    // 集群身份已保存时服务器启动时已重新加入,初始化和加入参数只在首次启动时生效
+    if args.cluster_init || args.join.is_some() {
+        match (server.cluster().identity(), &args.join) {
+            (Some(identity), _) => info!(
+                "Node {} already belongs to cluster {}, ignoring --cluster-init/--join",
+                identity.node_id, identity.cluster_id
+            ),
+            (None, Some(uri)) => {
+                server.cluster().join(uri).await?;
+            }
+            (None, None) => {
+                server.cluster().init().await?;
+            }
+        }
+    }
+
     tokio::select! {
         result = server.clone().run() => {
             if let Err(e) = result {
@@ -60,11 +124,33 @@ async fn main() -> anyhow::Result<()> {
                 return Err(anyhow::anyhow!("{}", e));
             }
         }
-        _ = tokio::signal::ctrl_c() => {
+        _ = shutdown_signal() => {
             info!("Received shutdown signal");
         }
     }
 
+    // 停止后台任务并保存运行状态
+    server.shutdown();
+
     info!("MikuDB server stopped");
     Ok(())
 }
+
+/// 等待 Ctrl+C 或 SIGTERM(systemd 停止服务时发送)
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("Failed to install SIGTERM handler: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
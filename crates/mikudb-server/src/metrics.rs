@@ -0,0 +1,479 @@
+//! 指标注册表模块
+//!
+//! 定义 `GET /metrics` 以 Prometheus 文本格式输出的全部指标:
+//! - 指标名称和标签名集中定义为常量,输出时按注册表校验,不允许临时拼出的名称
+//! - 名称遵循 Prometheus 约定: `mikudb_` 前缀,计数器以 `_total` 结尾,带单位的指标以单位结尾(`_seconds`、`_bytes`)
+//! - `DASHBOARD_QUERIES` 给出 Grafana 面板可以直接使用的 PromQL 示例
+//!
+//! 名称、类型和标签是对外契约,面板和告警规则按它们查询;已发布的指标只能新增,不能改名或改标签,
+//! 单元测试锁定完整列表,改动会使测试失败。
+//!
+//! `collection` 标签是集合在存储中的名称: 默认数据库的集合不带前缀,其他数据库为 `<数据库>.<集合>`,
+//! 与 `/api/metrics` 的 `collection_ops` 一致。
+
+use crate::server::Server;
+use mikudb_query::opstats::{LatencyHistogram, LATENCY_BUCKETS_SECS};
+use mikudb_storage::DEFAULT_DATABASE;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Prometheus 文本格式的 Content-Type
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 指标名称
+pub mod names {
+    pub const UPTIME_SECONDS: &str = "mikudb_uptime_seconds";
+    pub const CONNECTIONS_TOTAL: &str = "mikudb_connections_total";
+    pub const REQUESTS_TOTAL: &str = "mikudb_requests_total";
+    pub const ACTIVE_SESSIONS: &str = "mikudb_active_sessions";
+    pub const QUERY_DURATION_SECONDS: &str = "mikudb_query_duration_seconds";
+    pub const COLLECTION_SCANS_TOTAL: &str = "mikudb_collection_scans_total";
+    pub const INDEX_HITS_TOTAL: &str = "mikudb_index_hits_total";
+    pub const DOCUMENTS_EXAMINED_TOTAL: &str = "mikudb_documents_examined_total";
+    pub const DOCUMENTS_RETURNED_TOTAL: &str = "mikudb_documents_returned_total";
+    pub const DOCUMENTS_WRITTEN_TOTAL: &str = "mikudb_documents_written_total";
+    pub const STORAGE_BYTES: &str = "mikudb_storage_bytes";
+    pub const DOCUMENTS: &str = "mikudb_documents";
+    pub const TYPE_DRIFT_TOTAL: &str = "mikudb_type_drift_total";
+    pub const SCHEDULER_RUNNING: &str = "mikudb_scheduler_running";
+    pub const SCHEDULER_QUEUED: &str = "mikudb_scheduler_queued";
+    pub const SCHEDULER_COMPLETED_TOTAL: &str = "mikudb_scheduler_completed_total";
+    pub const THROTTLED_WRITES_TOTAL: &str = "mikudb_throttled_writes_total";
+    pub const STORAGE_POOL_QUEUE_DEPTH: &str = "mikudb_storage_pool_queue_depth";
+    pub const STORAGE_POOL_ACTIVE: &str = "mikudb_storage_pool_active";
+    pub const WAL_COMMITS_TOTAL: &str = "mikudb_wal_commits_total";
+    pub const WAL_SYNCS_TOTAL: &str = "mikudb_wal_syncs_total";
+    pub const WAL_BYTES_TOTAL: &str = "mikudb_wal_bytes_total";
+}
+
+/// 标签名称
+pub mod labels {
+    /// 操作类型: find、insert、update、delete、aggregate
+    pub const TYPE: &str = "type";
+    /// 集合在存储中的名称
+    pub const COLLECTION: &str = "collection";
+    /// 数据库名称
+    pub const DB: &str = "db";
+    /// 请求优先级: interactive、batch
+    pub const PRIORITY: &str = "priority";
+}
+
+/// 指标类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricType {
+    /// # Brief
+    /// `# TYPE` 行中的名称
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+            MetricType::Histogram => "histogram",
+        }
+    }
+}
+
+/// 注册的指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricDesc {
+    pub name: &'static str,
+    pub kind: MetricType,
+    /// `# HELP` 行的说明
+    pub help: &'static str,
+    /// 标签名,样本按此顺序输出标签
+    pub labels: &'static [&'static str],
+}
+
+const fn metric(
+    name: &'static str,
+    kind: MetricType,
+    help: &'static str,
+    labels: &'static [&'static str],
+) -> MetricDesc {
+    MetricDesc { name, kind, help, labels }
+}
+
+/// 全部指标,按输出顺序排列
+pub const METRICS: &[MetricDesc] = {
+    use labels::*;
+    use names::*;
+    use MetricType::*;
+    &[
+        metric(UPTIME_SECONDS, Gauge, "Seconds since the server started.", &[]),
+        metric(CONNECTIONS_TOTAL, Counter, "Client connections accepted.", &[]),
+        metric(REQUESTS_TOTAL, Counter, "Protocol requests processed.", &[]),
+        metric(ACTIVE_SESSIONS, Gauge, "Sessions currently open.", &[]),
+        metric(QUERY_DURATION_SECONDS, Histogram, "Statement execution time by operation type and collection.", &[TYPE, COLLECTION]),
+        metric(COLLECTION_SCANS_TOTAL, Counter, "Full collection scans.", &[COLLECTION]),
+        metric(INDEX_HITS_TOTAL, Counter, "Lookups served by an index.", &[COLLECTION]),
+        metric(DOCUMENTS_EXAMINED_TOTAL, Counter, "Documents read while evaluating statements.", &[COLLECTION]),
+        metric(DOCUMENTS_RETURNED_TOTAL, Counter, "Documents returned to clients.", &[COLLECTION]),
+        metric(DOCUMENTS_WRITTEN_TOTAL, Counter, "Documents inserted, modified or deleted.", &[COLLECTION]),
+        metric(STORAGE_BYTES, Gauge, "Encoded document bytes stored per database.", &[DB]),
+        metric(DOCUMENTS, Gauge, "Documents stored per database.", &[DB]),
+        metric(TYPE_DRIFT_TOTAL, Counter, "Writes that changed the dominant type of a tracked field.", &[COLLECTION]),
+        metric(SCHEDULER_RUNNING, Gauge, "Statements currently executing.", &[]),
+        metric(SCHEDULER_QUEUED, Gauge, "Statements waiting for an execution slot.", &[PRIORITY]),
+        metric(SCHEDULER_COMPLETED_TOTAL, Counter, "Statements completed by the scheduler.", &[PRIORITY]),
+        metric(THROTTLED_WRITES_TOTAL, Counter, "Write requests delayed by the write rate limit.", &[]),
+        metric(STORAGE_POOL_QUEUE_DEPTH, Gauge, "Storage tasks waiting for a worker thread.", &[]),
+        metric(STORAGE_POOL_ACTIVE, Gauge, "Storage tasks currently running.", &[]),
+        metric(WAL_COMMITS_TOTAL, Counter, "Transactions committed to the write-ahead log.", &[]),
+        metric(WAL_SYNCS_TOTAL, Counter, "fsync calls on the write-ahead log.", &[]),
+        metric(WAL_BYTES_TOTAL, Counter, "Bytes appended to the write-ahead log.", &[]),
+    ]
+};
+
+/// # Brief
+/// 按名称查找注册的指标
+pub fn find(name: &str) -> Option<&'static MetricDesc> {
+    METRICS.iter().find(|metric| metric.name == name)
+}
+
+/// 面板示例查询
+#[derive(Debug, Clone, Copy)]
+pub struct DashboardQuery {
+    /// 面板标题
+    pub title: &'static str,
+    /// PromQL 表达式
+    pub promql: &'static str,
+}
+
+/// Grafana 面板示例查询
+pub const DASHBOARD_QUERIES: &[DashboardQuery] = &[
+    DashboardQuery {
+        title: "Query latency p99 by type",
+        promql: "histogram_quantile(0.99, sum by (le, type) (rate(mikudb_query_duration_seconds_bucket[5m])))",
+    },
+    DashboardQuery {
+        title: "Slowest collections (p95)",
+        promql: "topk(5, histogram_quantile(0.95, sum by (le, collection) (rate(mikudb_query_duration_seconds_bucket[5m]))))",
+    },
+    DashboardQuery {
+        title: "Operations per second by type",
+        promql: "sum by (type) (rate(mikudb_query_duration_seconds_count[1m]))",
+    },
+    DashboardQuery {
+        title: "Full scan ratio",
+        promql: "sum(rate(mikudb_collection_scans_total[5m])) / clamp_min(sum(rate(mikudb_collection_scans_total[5m])) + sum(rate(mikudb_index_hits_total[5m])), 1)",
+    },
+    DashboardQuery {
+        title: "Storage size by database",
+        promql: "sum by (db) (mikudb_storage_bytes)",
+    },
+    DashboardQuery {
+        title: "Scheduler queue by priority",
+        promql: "sum by (priority) (mikudb_scheduler_queued)",
+    },
+    DashboardQuery {
+        title: "Request rate",
+        promql: "rate(mikudb_requests_total[1m])",
+    },
+    DashboardQuery {
+        title: "WAL commits per fsync",
+        promql: "rate(mikudb_wal_commits_total[5m]) / clamp_min(rate(mikudb_wal_syncs_total[5m]), 1)",
+    },
+];
+
+/// Prometheus 文本格式输出
+///
+/// 同一指标的样本必须连续输出: 先调用 `family` 输出 `# HELP` / `# TYPE`,再输出样本。
+#[derive(Debug, Default)]
+pub struct Exposition {
+    out: String,
+}
+
+impl Exposition {
+    /// # Brief
+    /// 创建空的输出
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Brief
+    /// 输出指标的 `# HELP` 和 `# TYPE` 行
+    ///
+    /// # Panics
+    /// 指标未在 `METRICS` 中注册时 panic
+    pub fn family(&mut self, name: &str) -> &mut Self {
+        let metric = find(name).unwrap_or_else(|| panic!("metric {} is not registered", name));
+        let _ = writeln!(self.out, "# HELP {} {}", metric.name, metric.help);
+        let _ = writeln!(self.out, "# TYPE {} {}", metric.name, metric.kind.as_str());
+        self
+    }
+
+    /// # Brief
+    /// 输出一个计数器或仪表样本
+    ///
+    /// # Arguments
+    /// * `name` - 注册的指标名称
+    /// * `label_values` - 按注册顺序的标签值
+    /// * `value` - 样本值
+    pub fn sample(&mut self, name: &str, label_values: &[&str], value: f64) -> &mut Self {
+        let metric = Self::checked(name, label_values);
+        self.line(metric.name, "", metric.labels, label_values, None, value);
+        self
+    }
+
+    /// # Brief
+    /// 输出一个没有标签的指标,包括 `# HELP` / `# TYPE` 行
+    pub fn single(&mut self, name: &str, value: f64) -> &mut Self {
+        self.family(name).sample(name, &[], value)
+    }
+
+    /// # Brief
+    /// 输出一组直方图样本(`_bucket`、`_sum`、`_count`)
+    ///
+    /// # Arguments
+    /// * `name` - 注册的直方图名称
+    /// * `label_values` - 按注册顺序的标签值
+    /// * `histogram` - 累计计数与 `LATENCY_BUCKETS_SECS` 对应的直方图
+    pub fn histogram(&mut self, name: &str, label_values: &[&str], histogram: &LatencyHistogram) -> &mut Self {
+        let metric = Self::checked(name, label_values);
+        for (bound, count) in LATENCY_BUCKETS_SECS.iter().zip(&histogram.buckets) {
+            let le = bound.to_string();
+            self.line(metric.name, "_bucket", metric.labels, label_values, Some(&le), *count as f64);
+        }
+        self.line(metric.name, "_bucket", metric.labels, label_values, Some("+Inf"), histogram.count as f64);
+        self.line(metric.name, "_sum", metric.labels, label_values, None, histogram.sum_secs);
+        self.line(metric.name, "_count", metric.labels, label_values, None, histogram.count as f64);
+        self
+    }
+
+    /// # Brief
+    /// 取出输出的文本
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    fn checked(name: &str, label_values: &[&str]) -> &'static MetricDesc {
+        let metric = find(name).unwrap_or_else(|| panic!("metric {} is not registered", name));
+        assert_eq!(metric.labels.len(), label_values.len(), "wrong label count for {}", name);
+        metric
+    }
+
+    fn line(&mut self, name: &str, suffix: &str, labels: &[&str], values: &[&str], le: Option<&str>, value: f64) {
+        self.out.push_str(name);
+        self.out.push_str(suffix);
+        let pairs = labels.iter().zip(values).map(|(label, value)| (*label, *value));
+        let pairs: Vec<(&str, &str)> = pairs.chain(le.map(|le| ("le", le))).collect();
+        if !pairs.is_empty() {
+            self.out.push('{');
+            for (i, (label, value)) in pairs.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{}=\"{}\"", label, escape_label(value));
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+}
+
+/// 转义标签值中的反斜杠、双引号和换行
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// # Brief
+/// 采集服务器的全部指标
+///
+/// # Returns
+/// Prometheus 文本格式
+pub fn render(server: &Server) -> String {
+    use names::*;
+
+    let mut out = Exposition::new();
+    let stats = server.stats();
+    out.single(UPTIME_SECONDS, stats.uptime_secs as f64);
+    out.single(CONNECTIONS_TOTAL, stats.total_connections as f64);
+    out.single(REQUESTS_TOTAL, stats.total_requests as f64);
+    out.single(ACTIVE_SESSIONS, stats.active_sessions as f64);
+
+    out.family(QUERY_DURATION_SECONDS);
+    for (collection, kind, histogram) in server.op_stats().latency_histograms() {
+        out.histogram(QUERY_DURATION_SECONDS, &[kind.name(), &collection], &histogram);
+    }
+    let collection_ops = server.op_stats().snapshot_all();
+    for name in [
+        COLLECTION_SCANS_TOTAL,
+        INDEX_HITS_TOTAL,
+        DOCUMENTS_EXAMINED_TOTAL,
+        DOCUMENTS_RETURNED_TOTAL,
+        DOCUMENTS_WRITTEN_TOTAL,
+    ] {
+        out.family(name);
+        for ops in &collection_ops {
+            let value = match name {
+                COLLECTION_SCANS_TOTAL => ops.scans,
+                INDEX_HITS_TOTAL => ops.index_hits,
+                DOCUMENTS_EXAMINED_TOTAL => ops.docs_examined,
+                DOCUMENTS_RETURNED_TOTAL => ops.docs_returned,
+                _ => ops.docs_written,
+            };
+            out.sample(name, &[&ops.collection], value as f64);
+        }
+    }
+
+    // 按数据库汇总集合的存储量,同时记录启用类型记录的集合的漂移数
+    let storage = server.storage();
+    let mut databases: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    let mut type_drift = Vec::new();
+    for name in storage.list_collections().unwrap_or_default() {
+        let Ok(collection) = storage.get_collection(&name) else {
+            continue;
+        };
+        let collection_stats = collection.stats();
+        let database = name.split_once('.').map_or(DEFAULT_DATABASE, |(database, _)| database);
+        let entry = databases.entry(database.to_string()).or_default();
+        entry.0 += collection_stats.total_size;
+        entry.1 += collection_stats.doc_count;
+        if collection.schema_options().enabled() {
+            type_drift.push((name, collection_stats.type_drift_count));
+        }
+    }
+    out.family(STORAGE_BYTES);
+    for (database, (bytes, _)) in &databases {
+        out.sample(STORAGE_BYTES, &[database], *bytes as f64);
+    }
+    out.family(DOCUMENTS);
+    for (database, (_, documents)) in &databases {
+        out.sample(DOCUMENTS, &[database], *documents as f64);
+    }
+    out.family(TYPE_DRIFT_TOTAL);
+    for (collection, drift) in &type_drift {
+        out.sample(TYPE_DRIFT_TOTAL, &[collection], *drift as f64);
+    }
+
+    let scheduler = server.scheduler().stats();
+    out.single(SCHEDULER_RUNNING, scheduler.running as f64);
+    out.family(SCHEDULER_QUEUED)
+        .sample(SCHEDULER_QUEUED, &["interactive"], scheduler.interactive_queued as f64)
+        .sample(SCHEDULER_QUEUED, &["batch"], scheduler.batch_queued as f64);
+    out.family(SCHEDULER_COMPLETED_TOTAL)
+        .sample(SCHEDULER_COMPLETED_TOTAL, &["interactive"], scheduler.interactive_completed as f64)
+        .sample(SCHEDULER_COMPLETED_TOTAL, &["batch"], scheduler.batch_completed as f64);
+    out.single(THROTTLED_WRITES_TOTAL, scheduler.throttled_writes as f64);
+
+    let pool = server.storage_pool().stats();
+    out.single(STORAGE_POOL_QUEUE_DEPTH, pool.queue_depth as f64);
+    out.single(STORAGE_POOL_ACTIVE, pool.active as f64);
+
+    if let Some(wal) = storage.wal_stats() {
+        out.single(WAL_COMMITS_TOTAL, wal.commits as f64);
+        out.single(WAL_SYNCS_TOTAL, wal.syncs as f64);
+        out.single(WAL_BYTES_TOTAL, wal.bytes as f64);
+    }
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// 已发布的指标,面板依赖这些名称、类型和标签,只能追加
+    const PUBLISHED: &[(&str, &str, &[&str])] = &[
+        ("mikudb_uptime_seconds", "gauge", &[]),
+        ("mikudb_connections_total", "counter", &[]),
+        ("mikudb_requests_total", "counter", &[]),
+        ("mikudb_active_sessions", "gauge", &[]),
+        ("mikudb_query_duration_seconds", "histogram", &["type", "collection"]),
+        ("mikudb_collection_scans_total", "counter", &["collection"]),
+        ("mikudb_index_hits_total", "counter", &["collection"]),
+        ("mikudb_documents_examined_total", "counter", &["collection"]),
+        ("mikudb_documents_returned_total", "counter", &["collection"]),
+        ("mikudb_documents_written_total", "counter", &["collection"]),
+        ("mikudb_storage_bytes", "gauge", &["db"]),
+        ("mikudb_documents", "gauge", &["db"]),
+        ("mikudb_type_drift_total", "counter", &["collection"]),
+        ("mikudb_scheduler_running", "gauge", &[]),
+        ("mikudb_scheduler_queued", "gauge", &["priority"]),
+        ("mikudb_scheduler_completed_total", "counter", &["priority"]),
+        ("mikudb_throttled_writes_total", "counter", &[]),
+        ("mikudb_storage_pool_queue_depth", "gauge", &[]),
+        ("mikudb_storage_pool_active", "gauge", &[]),
+        ("mikudb_wal_commits_total", "counter", &[]),
+        ("mikudb_wal_syncs_total", "counter", &[]),
+        ("mikudb_wal_bytes_total", "counter", &[]),
+    ];
+
+    #[test]
+    fn test_published_metrics_are_stable() {
+        let registered: Vec<_> = METRICS.iter().map(|m| (m.name, m.kind.as_str(), m.labels)).collect();
+        assert_eq!(registered, PUBLISHED);
+        assert_eq!(LATENCY_BUCKETS_SECS, [0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0]);
+    }
+
+    #[test]
+    fn test_naming_conventions() {
+        let mut seen = HashSet::new();
+        for metric in METRICS {
+            assert!(seen.insert(metric.name), "duplicate metric {}", metric.name);
+            assert!(metric.name.starts_with("mikudb_"), "{}", metric.name);
+            assert!(metric.name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_'));
+            assert_eq!(metric.kind == MetricType::Counter, metric.name.ends_with("_total"), "{}", metric.name);
+            if metric.kind == MetricType::Histogram {
+                assert!(metric.name.ends_with("_seconds"), "{}", metric.name);
+            }
+            assert!(!metric.help.is_empty());
+            for label in metric.labels {
+                assert!([labels::TYPE, labels::COLLECTION, labels::DB, labels::PRIORITY].contains(label));
+            }
+        }
+    }
+
+    #[test]
+    fn test_dashboard_queries_use_registered_metrics() {
+        for query in DASHBOARD_QUERIES {
+            let mut rest = query.promql;
+            let mut found = false;
+            while let Some(start) = rest.find("mikudb_") {
+                let tail = &rest[start..];
+                let end = tail.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(tail.len());
+                let name = &tail[..end];
+                let base = ["_bucket", "_sum", "_count"]
+                    .iter()
+                    .find_map(|suffix| name.strip_suffix(suffix).filter(|base| find(base).is_some()))
+                    .unwrap_or(name);
+                assert!(find(base).is_some(), "{} uses unregistered metric {}", query.title, name);
+                found = true;
+                rest = &tail[end..];
+            }
+            assert!(found, "{} does not use any metric", query.title);
+        }
+    }
+
+    #[test]
+    fn test_exposition_format() {
+        let mut out = Exposition::new();
+        out.single(names::REQUESTS_TOTAL, 42.0);
+        out.family(names::STORAGE_BYTES).sample(names::STORAGE_BYTES, &["sh\"op"], 1024.0);
+        let histogram = LatencyHistogram {
+            buckets: vec![1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3],
+            count: 3,
+            sum_secs: 12.5,
+        };
+        out.family(names::QUERY_DURATION_SECONDS)
+            .histogram(names::QUERY_DURATION_SECONDS, &["find", "users"], &histogram);
+        let text = out.finish();
+
+        assert!(text.starts_with(
+            "# HELP mikudb_requests_total Protocol requests processed.\n\
+             # TYPE mikudb_requests_total counter\n\
+             mikudb_requests_total 42\n"
+        ));
+        assert!(text.contains("mikudb_storage_bytes{db=\"sh\\\"op\"} 1024\n"));
+        assert!(text.contains("# TYPE mikudb_query_duration_seconds histogram\n"));
+        assert!(text.contains("mikudb_query_duration_seconds_bucket{type=\"find\",collection=\"users\",le=\"0.0005\"} 1\n"));
+        assert!(text.contains("mikudb_query_duration_seconds_bucket{type=\"find\",collection=\"users\",le=\"+Inf\"} 3\n"));
+        assert!(text.contains("mikudb_query_duration_seconds_sum{type=\"find\",collection=\"users\"} 12.5\n"));
+        assert!(text.ends_with("mikudb_query_duration_seconds_count{type=\"find\",collection=\"users\"} 3\n"));
+    }
+}
@@ -0,0 +1,184 @@
+//! 写入通知模块
+//!
+//! 跟随存储引擎的变更流,把已提交的写入发布到 Redis 频道,供外部缓存层失效使用:
+//! - 每个事件发布一条紧凑 JSON: `{"db": "shop", "collection": "users", "id": "…", "op": "update"}`
+//! - 无法逐个列出文档的写入(清空、删除集合、恢复)发布 `op` 为 `invalidate`、没有 `id` 的消息
+//! - 写入附带元数据时消息带有 `meta` 字段(`origin`、`app`、`request_id`)
+//! - 系统集合(用户、角色等)的写入不发布
+//! - Redis 不可用时丢弃期间的通知并定期重连;变更流中的事件被淘汰时发布一条 `resync` 消息
+
+use crate::config::NotifyConfig;
+use crate::{ServerError, ServerResult};
+use mikudb_storage::backup::is_system_collection;
+use mikudb_storage::{ChangeEvent, ChangeKind, StorageEngine, StorageError, DEFAULT_DATABASE};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 等待新事件的最长时间,也是检查停止标志的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Redis 不可用时两次重连的间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// # Brief
+/// 把变更事件转换为通知消息
+///
+/// # Arguments
+/// * `event` - 变更流事件
+/// * `databases` - 只通知这些数据库,为空表示全部
+///
+/// # Returns
+/// JSON 消息;系统集合或未选中的数据库返回 None
+pub fn notification(event: &ChangeEvent, databases: &[String]) -> Option<String> {
+    let (db, collection) = match event.collection.split_once('.') {
+        Some((db, collection)) => (db, collection),
+        None => (DEFAULT_DATABASE, event.collection.as_str()),
+    };
+    if is_system_collection(collection) || !(databases.is_empty() || databases.iter().any(|d| d == db)) {
+        return None;
+    }
+    let op = match event.kind {
+        ChangeKind::Insert => "insert",
+        ChangeKind::Update => "update",
+        ChangeKind::Delete => "delete",
+        ChangeKind::Invalidate => "invalidate",
+    };
+    let mut message = serde_json::json!({"db": db, "collection": collection, "op": op});
+    if let Some(id) = event.id {
+        message["id"] = serde_json::Value::String(id.to_hex());
+    }
+    if let Some(metadata) = &event.metadata {
+        message["meta"] = serde_json::to_value(metadata.as_ref()).ok()?;
+    }
+    Some(message.to_string())
+}
+
+/// Redis 写入通知
+///
+/// 后台线程按恢复令牌读取变更流,逐条 PUBLISH 到配置的频道。
+pub struct RedisNotifier {
+    storage: Arc<StorageEngine>,
+    client: redis::Client,
+    channel: String,
+    databases: Vec<String>,
+    stop: AtomicBool,
+}
+
+impl RedisNotifier {
+    /// # Brief
+    /// 创建通知器,只校验 Redis 地址,连接在后台线程中建立
+    ///
+    /// # Arguments
+    /// * `storage` - 存储引擎
+    /// * `config` - 写入通知配置
+    pub fn new(storage: Arc<StorageEngine>, config: &NotifyConfig) -> ServerResult<Self> {
+        let client = redis::Client::open(config.redis_url.as_str())
+            .map_err(|e| ServerError::Config(format!("Invalid notify.redis_url: {}", e)))?;
+        Ok(Self {
+            storage,
+            client,
+            channel: config.channel.clone(),
+            databases: config.databases.clone(),
+            stop: AtomicBool::new(false),
+        })
+    }
+
+    /// # Brief
+    /// 启动后台发布线程,从当前令牌开始,之前的写入不发布
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        std::thread::Builder::new()
+            .name("mikudb-redis-notify".to_string())
+            .spawn(move || {
+                info!("Redis write notifications publishing to channel {}", self.channel);
+                let changes = self.storage.change_stream().clone();
+                let mut token = changes.current_token();
+                let mut connection = None;
+                while !self.stop.load(Ordering::SeqCst) {
+                    let events = match changes.changes_since(token, None, POLL_INTERVAL) {
+                        Ok(events) => events,
+                        Err(StorageError::ResumeTokenExpired(_)) => {
+                            // 发布跟不上写入,订阅者需要整体失效
+                            warn!("Redis notifier fell behind the change stream, publishing resync");
+                            token = changes.current_token();
+                            self.publish(&mut connection, &serde_json::json!({"op": "resync"}).to_string());
+                            continue;
+                        }
+                        Err(e) => {
+                            warn!("Redis notifier failed to read changes: {}", e);
+                            std::thread::sleep(POLL_INTERVAL);
+                            continue;
+                        }
+                    };
+                    for event in &events {
+                        token = event.token;
+                        if let Some(message) = notification(event, &self.databases) {
+                            self.publish(&mut connection, &message);
+                        }
+                    }
+                }
+                info!("Redis write notifications stopped");
+            })
+            .expect("failed to spawn Redis notifier thread")
+    }
+
+    /// # Brief
+    /// 停止后台发布线程,在下一次等待超时后退出
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    fn publish(&self, connection: &mut Option<redis::Connection>, message: &str) {
+        if connection.is_none() {
+            match self.client.get_connection_with_timeout(RECONNECT_INTERVAL) {
+                Ok(conn) => *connection = Some(conn),
+                Err(e) => {
+                    warn!("Redis notifier cannot connect, dropping notification: {}", e);
+                    std::thread::sleep(RECONNECT_INTERVAL);
+                    return;
+                }
+            }
+        }
+        if let Some(conn) = connection.as_mut() {
+            let published: redis::RedisResult<i64> =
+                redis::cmd("PUBLISH").arg(&self.channel).arg(message).query(conn);
+            if let Err(e) = published {
+                warn!("Redis notifier publish failed: {}", e);
+                *connection = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mikudb_common::ObjectId;
+    use mikudb_storage::WriteMetadata;
+
+    fn event(collection: &str, id: Option<ObjectId>, kind: ChangeKind) -> ChangeEvent {
+        ChangeEvent { token: 1, collection: collection.to_string(), id, kind, metadata: None }
+    }
+
+    #[test]
+    fn test_notification() {
+        let id = ObjectId::new();
+        let message = notification(&event("shop.users", Some(id), ChangeKind::Update), &[]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(value, serde_json::json!({"db": "shop", "collection": "users", "id": id.to_hex(), "op": "update"}));
+
+        let mut tagged = event("shop.users", Some(id), ChangeKind::Insert);
+        tagged.metadata = Some(Arc::new(WriteMetadata { app: Some("sync".to_string()), ..Default::default() }));
+        let value: serde_json::Value = serde_json::from_str(&notification(&tagged, &[]).unwrap()).unwrap();
+        assert_eq!(value["meta"], serde_json::json!({"origin": null, "app": "sync", "request_id": null}));
+
+        let message = notification(&event("logs", None, ChangeKind::Invalidate), &[]).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&message).unwrap();
+        assert_eq!(value, serde_json::json!({"db": "default", "collection": "logs", "op": "invalidate"}));
+
+        assert!(notification(&event("_users", Some(id), ChangeKind::Insert), &[]).is_none());
+        assert!(notification(&event("shop.users", Some(id), ChangeKind::Delete), &["crm".to_string()]).is_none());
+    }
+}
@@ -0,0 +1,517 @@
+//! 启动预检模块
+//!
+//! 在打开存储引擎之前检查运行环境,把部署问题转换为带修复建议的错误,
+//! 而不是在运行中途以难以理解的方式失败:
+//! - 数据目录是否可创建、可写
+//! - 数据目录所在磁盘的可用空间
+//! - 文件描述符上限(ulimit -n)是否满足最大连接数
+//! - 系统时钟是否合理(ObjectId 依赖时间戳)
+//! - 监听端口是否可用
+//! - 数据目录是否已被其他进程锁定(RocksDB LOCK 文件)
+//!
+//! 建议中包含 OpenEuler 上常用的 sysctl、systemd 和 SELinux 命令。
+
+use crate::config::ServerConfig;
+use crate::{ServerError, ServerResult};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+/// 为 RocksDB 文件、WAL 和内部管道预留的文件描述符数
+const RESERVED_FDS: u64 = 1024;
+
+/// 合理时钟的下限(2020-01-01T00:00:00Z),早于该时间说明 RTC 未同步
+const MIN_SANE_UNIX_SECS: u64 = 1_577_836_800;
+
+/// 允许系统时间落后于数据文件修改时间的幅度
+const CLOCK_SKEW_TOLERANCE: Duration = Duration::from_secs(300);
+
+/// 单项检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// 通过
+    Pass,
+    /// 可以启动,但存在隐患
+    Warn,
+    /// 拒绝启动
+    Fail,
+}
+
+/// 单项检查
+#[derive(Debug, Clone)]
+pub struct PreflightCheck {
+    /// 检查项名称
+    pub name: &'static str,
+    /// 检查结果
+    pub status: CheckStatus,
+    /// 结果说明
+    pub message: String,
+    /// 修复建议
+    pub hints: Vec<String>,
+}
+
+impl PreflightCheck {
+    fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Pass, message: message.into(), hints: Vec::new() }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>, hints: Vec<String>) -> Self {
+        Self { name, status: CheckStatus::Warn, message: message.into(), hints }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>, hints: Vec<String>) -> Self {
+        Self { name, status: CheckStatus::Fail, message: message.into(), hints }
+    }
+}
+
+/// 预检报告
+#[derive(Debug, Clone, Default)]
+pub struct PreflightReport {
+    /// 所有检查项(按执行顺序)
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    /// # Brief
+    /// 获取所有失败的检查项
+    pub fn failures(&self) -> impl Iterator<Item = &PreflightCheck> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail)
+    }
+
+    /// # Brief
+    /// 是否存在失败的检查项
+    pub fn has_failures(&self) -> bool {
+        self.failures().next().is_some()
+    }
+
+    /// # Brief
+    /// 按检查结果输出日志,失败和警告项附带修复建议
+    pub fn log(&self) {
+        for check in &self.checks {
+            match check.status {
+                CheckStatus::Pass => info!("Preflight [{}] ok: {}", check.name, check.message),
+                CheckStatus::Warn => warn!("Preflight [{}] warning: {}", check.name, check.message),
+                CheckStatus::Fail => error!("Preflight [{}] FAILED: {}", check.name, check.message),
+            }
+            for hint in &check.hints {
+                match check.status {
+                    CheckStatus::Fail => error!("    hint: {}", hint),
+                    _ => warn!("    hint: {}", hint),
+                }
+            }
+        }
+    }
+
+    /// # Brief
+    /// 转换为启动结果,存在失败项时返回汇总错误
+    ///
+    /// # Returns
+    /// 全部通过(或仅有警告)时返回 Ok
+    pub fn into_result(self) -> ServerResult<()> {
+        let failed: Vec<String> = self
+            .failures()
+            .map(|c| format!("{}: {}", c.name, c.message))
+            .collect();
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(ServerError::Preflight(failed.join("; ")))
+        }
+    }
+}
+
+/// # Brief
+/// 执行所有启动预检
+///
+/// 数据目录不存在时会被创建。端口检查会短暂绑定监听地址后立即释放。
+///
+/// # Arguments
+/// * `config` - 服务器配置
+///
+/// # Returns
+/// 预检报告
+pub fn run(config: &ServerConfig) -> PreflightReport {
+    let data_dir = config.data_dir.as_path();
+    let mut checks = vec![check_data_dir(data_dir)];
+    let data_dir_ok = checks[0].status != CheckStatus::Fail;
+
+    if data_dir_ok {
+        #[cfg(target_os = "linux")]
+        checks.push(check_disk_space(data_dir, config.preflight.min_free_disk_mb));
+        #[cfg(target_os = "linux")]
+        checks.push(check_data_dir_lock(data_dir));
+    }
+    #[cfg(target_os = "linux")]
+    checks.push(check_open_files(config.max_connections));
+    checks.push(check_clock(data_dir_ok.then_some(data_dir)));
+    checks.push(check_port("port", &config.bind, config.port));
+    if config.http.enabled {
+        checks.push(check_port("http_port", &config.http.bind, config.http.port));
+    }
+
+    PreflightReport { checks }
+}
+
+/// # Brief
+/// 检查数据目录可创建且可写
+fn check_data_dir(data_dir: &Path) -> PreflightCheck {
+    const NAME: &str = "data_dir";
+    let display = data_dir.display();
+
+    if let Err(e) = std::fs::create_dir_all(data_dir) {
+        return PreflightCheck::fail(
+            NAME,
+            format!("cannot create data directory {}: {}", display, e),
+            permission_hints(data_dir, &e),
+        );
+    }
+
+    let probe = data_dir.join(".mikudb_preflight");
+    let result = std::fs::write(&probe, b"preflight").and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => PreflightCheck::pass(NAME, format!("{} is writable", display)),
+        Err(e) => PreflightCheck::fail(
+            NAME,
+            format!("data directory {} is not writable: {}", display, e),
+            permission_hints(data_dir, &e),
+        ),
+    }
+}
+
+fn permission_hints(data_dir: &Path, err: &std::io::Error) -> Vec<String> {
+    let display = data_dir.display();
+    let mut hints = Vec::new();
+    match err.kind() {
+        ErrorKind::PermissionDenied => {
+            hints.push(format!("sudo chown -R mikudb:mikudb {}", display));
+            hints.push(format!("sudo chmod 750 {}", display));
+            hints.push(format!(
+                "if SELinux is enforcing (getenforce), check the label with `ls -Zd {}` and run `sudo restorecon -Rv {}`",
+                display, display
+            ));
+        }
+        _ => {
+            hints.push(format!("check that the filesystem is mounted read-write with `findmnt -T {}`", display));
+            hints.push(format!("or set `data_dir` in the config file to a writable location (current: {})", display));
+        }
+    }
+    hints
+}
+
+/// # Brief
+/// 检查数据目录所在磁盘的可用空间
+#[cfg(target_os = "linux")]
+fn check_disk_space(data_dir: &Path, min_free_mb: u64) -> PreflightCheck {
+    const NAME: &str = "disk_space";
+
+    let stat = match nix::sys::statvfs::statvfs(data_dir) {
+        Ok(stat) => stat,
+        Err(e) => {
+            return PreflightCheck::warn(NAME, format!("cannot query free space: {}", e), Vec::new());
+        }
+    };
+    let available_mb = (stat.blocks_available() as u64)
+        .saturating_mul(stat.fragment_size() as u64)
+        / (1024 * 1024);
+
+    if available_mb < min_free_mb {
+        PreflightCheck::fail(
+            NAME,
+            format!("only {}MB available under {}, at least {}MB required", available_mb, data_dir.display(), min_free_mb),
+            vec![
+                format!("free space on the volume (`df -h {}`) or move `data_dir` to a larger disk", data_dir.display()),
+                "lower `preflight.min_free_disk_mb` if this is a test machine".to_string(),
+            ],
+        )
+    } else if available_mb < min_free_mb.saturating_mul(2) {
+        PreflightCheck::warn(
+            NAME,
+            format!("{}MB available under {}, close to the {}MB minimum", available_mb, data_dir.display(), min_free_mb),
+            vec!["compaction needs temporary space; add capacity before the disk fills up".to_string()],
+        )
+    } else {
+        PreflightCheck::pass(NAME, format!("{}MB available", available_mb))
+    }
+}
+
+/// # Brief
+/// 检查数据目录是否被其他进程锁定
+///
+/// RocksDB 通过 fcntl 记录锁锁定 LOCK 文件,这里用 F_GETLK 查询持有者而不加锁。
+#[cfg(target_os = "linux")]
+fn check_data_dir_lock(data_dir: &Path) -> PreflightCheck {
+    use std::os::unix::io::AsRawFd;
+
+    const NAME: &str = "data_dir_lock";
+    let lock_path = data_dir.join("LOCK");
+
+    let file = match std::fs::OpenOptions::new().read(true).write(true).open(&lock_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return PreflightCheck::pass(NAME, "no existing LOCK file");
+        }
+        Err(e) => {
+            return PreflightCheck::fail(
+                NAME,
+                format!("cannot open {}: {}", lock_path.display(), e),
+                permission_hints(data_dir, &e),
+            );
+        }
+    };
+
+    // SAFETY: flock 结构体为 POD,全零是合法初始值;fd 在调用期间有效
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    let rc = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) };
+    if rc != 0 {
+        let e = std::io::Error::last_os_error();
+        return PreflightCheck::warn(NAME, format!("cannot query lock on {}: {}", lock_path.display(), e), Vec::new());
+    }
+
+    if lock.l_type == libc::F_UNLCK as libc::c_short {
+        PreflightCheck::pass(NAME, "data directory is not in use")
+    } else {
+        PreflightCheck::fail(
+            NAME,
+            format!("{} is locked by process {}", data_dir.display(), lock.l_pid),
+            vec![
+                format!("another mikudb-server is using this data directory; inspect it with `ps -fp {}`", lock.l_pid),
+                "stop the running instance with `sudo systemctl stop mikudb`, or point `data_dir` elsewhere".to_string(),
+                "do not delete the LOCK file while the other process is running".to_string(),
+            ],
+        )
+    }
+}
+
+/// # Brief
+/// 检查文件描述符上限是否满足最大连接数
+///
+/// 软限制不足时先尝试提升到硬限制。
+#[cfg(target_os = "linux")]
+fn check_open_files(max_connections: usize) -> PreflightCheck {
+    const NAME: &str = "open_files";
+    let required = max_connections as u64 + RESERVED_FDS;
+
+    // SAFETY: rlimit 为 POD,getrlimit/setrlimit 只读写传入的结构体
+    let mut limit: libc::rlimit = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        let e = std::io::Error::last_os_error();
+        return PreflightCheck::warn(NAME, format!("cannot read RLIMIT_NOFILE: {}", e), Vec::new());
+    }
+
+    let soft = limit.rlim_cur as u64;
+    let hard = limit.rlim_max as u64;
+    if soft >= required {
+        return PreflightCheck::pass(NAME, format!("ulimit -n = {} (need {})", soft, required));
+    }
+
+    let raised = required.min(hard);
+    if raised > soft {
+        limit.rlim_cur = raised as libc::rlim_t;
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } == 0 {
+            if raised >= required {
+                return PreflightCheck::pass(NAME, format!("raised ulimit -n from {} to {}", soft, raised));
+            }
+            info!("Raised open file limit from {} to hard limit {}", soft, raised);
+        }
+    }
+
+    let current = raised.max(soft);
+    PreflightCheck::warn(
+        NAME,
+        format!(
+            "open file limit {} is below max_connections ({}) + {} reserved; connections beyond ~{} will fail with EMFILE",
+            current,
+            max_connections,
+            RESERVED_FDS,
+            current.saturating_sub(RESERVED_FDS)
+        ),
+        vec![
+            format!("systemd: set `LimitNOFILE={}` in the [Service] section of mikudb.service, then `sudo systemctl daemon-reload`", required),
+            format!("shell: `ulimit -n {}` before starting the server", required),
+            format!("/etc/security/limits.conf: `mikudb soft nofile {}` and `mikudb hard nofile {}`", required, required),
+            format!("if the hard limit is capped, raise `sysctl -w fs.nr_open={}` and `fs.file-max`", required.max(1_048_576)),
+            "or lower `max_connections` in the config file".to_string(),
+        ],
+    )
+}
+
+/// # Brief
+/// 检查系统时钟
+///
+/// ObjectId 和归档策略依赖时间戳:时钟早于 2020 年视为未同步并拒绝启动,
+/// 时钟明显落后于已有数据文件时给出警告(时间回拨会打乱 `_id` 顺序)。
+fn check_clock(data_dir: Option<&Path>) -> PreflightCheck {
+    const NAME: &str = "clock";
+    let sync_hints = || {
+        vec![
+            "sudo systemctl enable --now chronyd".to_string(),
+            "check synchronisation with `chronyc tracking` or `timedatectl status`".to_string(),
+        ]
+    };
+
+    let now = SystemTime::now();
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    if since_epoch.as_secs() < MIN_SANE_UNIX_SECS {
+        return PreflightCheck::fail(
+            NAME,
+            format!("system clock reads {}s since the Unix epoch, which is before 2020", since_epoch.as_secs()),
+            sync_hints(),
+        );
+    }
+
+    let newest = data_dir
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max();
+    if let Some(newest) = newest {
+        if let Ok(ahead) = newest.duration_since(now) {
+            if ahead > CLOCK_SKEW_TOLERANCE {
+                return PreflightCheck::warn(
+                    NAME,
+                    format!("system clock is {}s behind the newest file in the data directory; it may have been set back", ahead.as_secs()),
+                    sync_hints(),
+                );
+            }
+        }
+    }
+
+    PreflightCheck::pass(NAME, "system clock looks sane")
+}
+
+/// # Brief
+/// 检查监听端口可用
+///
+/// # Arguments
+/// * `name` - 检查项名称
+/// * `bind` - 绑定地址
+/// * `port` - 端口号
+fn check_port(name: &'static str, bind: &str, port: u16) -> PreflightCheck {
+    let addr = format!("{}:{}", bind, port);
+    let err = match std::net::TcpListener::bind((bind, port)) {
+        Ok(_) => return PreflightCheck::pass(name, format!("{} is available", addr)),
+        Err(e) => e,
+    };
+
+    let hints = match err.kind() {
+        ErrorKind::AddrInUse => vec![
+            format!("find the process holding it with `sudo ss -lntp | grep :{}`", port),
+            "stop the other instance or change the port in the config file".to_string(),
+        ],
+        ErrorKind::PermissionDenied => vec![
+            format!("ports below 1024 need privileges: `sudo sysctl -w net.ipv4.ip_unprivileged_port_start={}`", port),
+            "or grant the binary `sudo setcap cap_net_bind_service=+ep $(command -v mikudb-server)`".to_string(),
+            "or use the default port 3939".to_string(),
+        ],
+        ErrorKind::AddrNotAvailable => vec![
+            format!("{} is not an address of this host; check `ip addr` or bind to 0.0.0.0", bind),
+        ],
+        _ => Vec::new(),
+    };
+    PreflightCheck::fail(name, format!("cannot bind {}: {}", addr, err), hints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 路径中间是普通文件,数据目录无法创建(root 用户同样失败)
+    fn blocked_data_dir(dir: &tempfile::TempDir) -> std::path::PathBuf {
+        let file = dir.path().join("not-a-directory");
+        std::fs::write(&file, b"").unwrap();
+        file.join("data")
+    }
+
+    #[test]
+    fn test_data_dir_cannot_be_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = check_data_dir(&blocked_data_dir(&dir));
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.message.starts_with("cannot create data directory"), "{}", check.message);
+        assert!(check.hints.iter().any(|hint| hint.contains("data_dir")));
+
+        let check = check_data_dir(dir.path());
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(!dir.path().join(".mikudb_preflight").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_data_dir_not_writable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // root 不受目录权限限制
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o500)).unwrap();
+        let check = check_data_dir(dir.path());
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o700)).unwrap();
+
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.message.contains("is not writable"), "{}", check.message);
+        assert!(check.hints.iter().any(|hint| hint.starts_with("sudo chown")));
+    }
+
+    #[test]
+    fn test_port_in_use() {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let check = check_port("port", "127.0.0.1", port);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hints.iter().any(|hint| hint.contains(&format!("grep :{}", port))));
+
+        drop(listener);
+        assert_eq!(check_port("port", "127.0.0.1", port).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_bind_address_not_on_host() {
+        // 192.0.2.0/24 为文档保留地址,不会配置在本机
+        let check = check_port("http_port", "192.0.2.1", 0);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert_eq!(check.name, "http_port");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_disk_space_below_minimum() {
+        let dir = tempfile::tempdir().unwrap();
+        let check = check_disk_space(dir.path(), u64::MAX);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.hints.iter().any(|hint| hint.contains("min_free_disk_mb")));
+        assert_eq!(check_disk_space(dir.path(), 0).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_run_reports_every_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let mut config = ServerConfig {
+            data_dir: blocked_data_dir(&dir),
+            bind: "127.0.0.1".to_string(),
+            port: listener.local_addr().unwrap().port(),
+            ..Default::default()
+        };
+        config.http.enabled = false;
+
+        let report = run(&config);
+        let failed: Vec<_> = report.failures().map(|check| check.name).collect();
+        assert_eq!(failed, vec!["data_dir", "port"]);
+        // 数据目录不可用时跳过依赖它的检查
+        assert!(report.checks.iter().all(|check| check.name != "disk_space" && check.name != "data_dir_lock"));
+
+        match report.into_result() {
+            Err(ServerError::Preflight(message)) => {
+                assert!(message.starts_with("data_dir: ") && message.contains("; port: "), "{}", message);
+            }
+            other => panic!("expected a preflight error, got {:?}", other),
+        }
+    }
+}
@@ -2,24 +2,44 @@
 //!
 //! 本模块定义了 MikuDB 客户端-服务器通信的二进制协议,包括:
 //! - 协议版本和魔术字节
-//! - 操作码(OpCode)枚举
-//! - 消息头(MessageHeader)结构
+//! - 操作码(OpCode)枚举,包括游标的 GetMore/KillCursor
+//! - 握手(Hello): 客户端连接后查询服务器版本和能力,无需认证
+//! - 带参数的语句(Execute): 查询文本与参数值分开传递,避免拼接字符串造成注入
+//! - 消息头(MessageHeader)结构,标志位可请求批处理优先级或并发处理
+//! - 请求多路复用: 带 `FLAG_CONCURRENT` 的请求在同一连接上并发执行,响应按 `response_to` 匹配,可能乱序返回
 //! - 消息(Message)编解码
 //! - 请求/响应数据结构
 
 use bytes::{Buf, BufMut, BytesMut};
+use mikudb_query::{Params, StatementStats};
+use mikudb_storage::{ValidationDetail, WriteMetadata};
 use serde::{Deserialize, Serialize};
 use std::io::{self};
 
 /// MikuWire 协议版本号
 pub const PROTOCOL_VERSION: u8 = 1;
 
+/// 服务器支持的协议版本,Hello 响应中返回
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[u8] = &[PROTOCOL_VERSION];
+
 /// 协议魔术字节,用于识别 MikuDB 协议消息
 pub const MAGIC_BYTES: &[u8; 4] = b"MIKU";
 
 /// 最大消息大小限制(64 MB),防止内存耗尽攻击
 pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
 
+/// 消息头标志位: 以批处理优先级调度该请求
+pub const FLAG_BATCH_PRIORITY: u16 = 0x0001;
+
+/// 消息头标志位: 该请求与同一连接上的其他请求相互独立,可以并发执行、乱序响应
+///
+/// 不带此标志的请求保持顺序语义: 等之前的请求全部完成后才执行,之后的请求等它完成后才开始。
+/// 认证和切换数据库(UseDatabase)总是按顺序执行。
+pub const FLAG_CONCURRENT: u16 = 0x0002;
+
+/// 单个连接上同时执行的并发请求上限,达到上限时暂停读取该连接
+pub const MAX_CONCURRENT_REQUESTS: usize = 64;
+
 /// 操作码枚举
 ///
 /// 定义了所有支持的客户端-服务器操作类型。
@@ -27,9 +47,11 @@ pub const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum OpCode {
-    // 心跳检测 (0x01-0x0F)
+    // 心跳检测与握手 (0x01-0x0F)
     Ping = 0x01,
     Pong = 0x02,
+    /// 查询服务器版本、协议版本和能力,认证前即可发送
+    Hello = 0x03,
 
     // 认证操作 (0x10-0x1F)
     Auth = 0x10,
@@ -42,6 +64,12 @@ pub enum OpCode {
     Delete = 0x23,
     Find = 0x24,
     Aggregate = 0x25,
+    /// 中断同一会话中正在执行的请求(从另一个连接发送,或在同一连接上并发发送)
+    KillOp = 0x26,
+    /// 批量执行插入、更新、删除,返回每个操作的结果和失败原因
+    BulkWrite = 0x27,
+    /// 执行带参数占位符的 MQL 语句,参数值单独传递并绑定到解析后的语句
+    Execute = 0x28,
 
     // 集合操作 (0x30-0x3F)
     CreateCollection = 0x30,
@@ -62,12 +90,22 @@ pub enum OpCode {
     Commit = 0x51,
     Rollback = 0x52,
 
+    // 管理操作 (0x60-0x6F)
+    /// 导出增量备份,需要 root 角色
+    BackupIncremental = 0x60,
+
     // 响应类型 (0x80-0x8F)
     Response = 0x80,
     Error = 0x81,
     Cursor = 0x82,
+    /// GetMore 的旧名,载荷相同
     CursorNext = 0x83,
+    /// 关闭单个游标,KillCursor 的旧形式
     CursorClose = 0x84,
+    /// 读取游标的下一批结果
+    GetMore = 0x85,
+    /// 关闭一个或多个游标
+    KillCursor = 0x86,
 }
 
 impl TryFrom<u8> for OpCode {
@@ -87,6 +125,7 @@ impl TryFrom<u8> for OpCode {
         match value {
             0x01 => Ok(OpCode::Ping),
             0x02 => Ok(OpCode::Pong),
+            0x03 => Ok(OpCode::Hello),
             0x10 => Ok(OpCode::Auth),
             0x11 => Ok(OpCode::AuthResponse),
             0x20 => Ok(OpCode::Query),
@@ -95,6 +134,9 @@ impl TryFrom<u8> for OpCode {
             0x23 => Ok(OpCode::Delete),
             0x24 => Ok(OpCode::Find),
             0x25 => Ok(OpCode::Aggregate),
+            0x26 => Ok(OpCode::KillOp),
+            0x27 => Ok(OpCode::BulkWrite),
+            0x28 => Ok(OpCode::Execute),
             0x30 => Ok(OpCode::CreateCollection),
             0x31 => Ok(OpCode::DropCollection),
             0x32 => Ok(OpCode::ListCollections),
@@ -108,11 +150,14 @@ impl TryFrom<u8> for OpCode {
             0x50 => Ok(OpCode::BeginTransaction),
             0x51 => Ok(OpCode::Commit),
             0x52 => Ok(OpCode::Rollback),
+            0x60 => Ok(OpCode::BackupIncremental),
             0x80 => Ok(OpCode::Response),
             0x81 => Ok(OpCode::Error),
             0x82 => Ok(OpCode::Cursor),
             0x83 => Ok(OpCode::CursorNext),
             0x84 => Ok(OpCode::CursorClose),
+            0x85 => Ok(OpCode::GetMore),
+            0x86 => Ok(OpCode::KillCursor),
             _ => Err(()),
         }
     }
@@ -126,7 +171,7 @@ impl TryFrom<u8> for OpCode {
 /// - opcode (1 字节): 操作码
 /// - request_id (4 字节): 请求唯一标识
 /// - response_to (4 字节): 响应对应的请求 ID
-/// - flags (2 字节): 标志位(预留)
+/// - flags (2 字节): 标志位(bit 0: 批处理优先级,bit 1: 并发处理,其余预留)
 /// - payload_len (4 字节): 负载长度
 #[derive(Debug, Clone)]
 pub struct MessageHeader {
@@ -322,6 +367,51 @@ impl Message {
     }
 }
 
+/// 握手响应 (Hello)
+///
+/// 客户端据此协商协议版本和可用功能,而不是假定服务器的能力。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelloResponse {
+    /// 服务器版本
+    pub server_version: String,
+    /// 支持的协议版本,客户端应选择双方都支持的最高版本
+    pub protocol_versions: Vec<u8>,
+    /// 单条消息负载的最大字节数
+    pub max_message_size: usize,
+    /// 服务器启用的功能
+    pub features: ServerFeatures,
+    /// 执行数据操作前是否需要认证
+    pub auth_required: bool,
+    /// 当前连接是否已认证
+    pub authenticated: bool,
+    /// 未执行 USE 时使用的默认数据库
+    pub default_database: Option<String>,
+}
+
+/// 服务器功能
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerFeatures {
+    /// 是否启用 TLS
+    pub tls: bool,
+    /// 支持的消息压缩算法,为空表示不压缩
+    pub compression: Vec<String>,
+    /// 是否作为集群成员运行
+    pub cluster: bool,
+    /// 是否可以使用 WASM 自定义函数
+    pub wasm_udf: bool,
+    /// 是否支持游标分批返回(GetMore/KillCursor)
+    pub cursors: bool,
+    /// 是否支持请求多路复用(`FLAG_CONCURRENT`)
+    #[serde(default)]
+    pub multiplexing: bool,
+    /// 是否支持批量写入(BulkWrite)
+    #[serde(default)]
+    pub bulk_write: bool,
+    /// 是否支持带参数的语句(Execute)
+    #[serde(default)]
+    pub prepared_statements: bool,
+}
+
 /// 认证请求
 ///
 /// 客户端发送的认证信息,JSON 序列化后作为消息负载。
@@ -330,6 +420,9 @@ pub struct AuthRequest {
     pub username: String,
     pub password: String,
     pub database: Option<String>,
+    /// 会话默认优先级("interactive" / "batch")
+    #[serde(default)]
+    pub priority: Option<crate::scheduler::Priority>,
 }
 
 /// 认证响应
@@ -344,11 +437,108 @@ pub struct AuthResponse {
 
 /// MQL 查询请求
 ///
-/// 包含数据库名称和 MQL 语句字符串。
+/// 包含数据库名称和 MQL 语句字符串。带 `params` 时语句中的 `$n` 占位符绑定为对应的值,
+/// 用户输入不需要拼接进语句文本。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryRequest {
     pub database: String,
     pub query: String,
+    /// 查询超时(毫秒),None 或 0 表示不限制
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// 游标首批文档数提示,语句中的 BATCH SIZE 优先
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    /// 只解析并返回格式化后的语句(在 message 中),不执行
+    #[serde(default)]
+    pub format_only: bool,
+    /// 位置参数值(扩展 JSON),依次绑定语句中的 `$1`、`$2`……;为空时按普通语句解析
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<serde_json::Value>,
+    /// 写入元数据(发起节点、应用标签、请求 ID),记录在变更事件中,不写入文档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<WriteMetadata>,
+}
+
+impl QueryRequest {
+    /// # Brief
+    /// 把扩展 JSON 参数转换为位置参数
+    ///
+    /// # Returns
+    /// 位置参数;值无法转换时返回错误信息
+    pub fn params(&self) -> Result<Params, String> {
+        self.params.iter().map(parameter_value).collect::<Result<_, _>>().map(Params::Positional)
+    }
+}
+
+/// 带参数的查询请求 (Execute)
+///
+/// `query` 中的 `$1` / `:name` 占位符绑定为 `params` 中的值:
+/// 数组按位置绑定,对象按名称绑定。值使用扩展 JSON,`{"$date": ...}`、`{"$oid": ...}` 等保留类型。
+/// 服务器按语句文本缓存解析结果,同一连接重复执行同一语句时不再解析。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecuteRequest {
+    pub database: String,
+    pub query: String,
+    /// 参数值,数组(位置参数)或对象(命名参数)
+    #[serde(default)]
+    pub params: serde_json::Value,
+    /// 查询超时(毫秒),None 或 0 表示不限制
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// 游标首批文档数提示,语句中的 BATCH SIZE 优先
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    /// 写入元数据(发起节点、应用标签、请求 ID),记录在变更事件中,不写入文档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<WriteMetadata>,
+}
+
+impl ExecuteRequest {
+    /// # Brief
+    /// 把扩展 JSON 参数转换为绑定用的参数值
+    ///
+    /// # Returns
+    /// 参数值;`params` 既不是数组、对象也不是 null,或值无法转换时返回错误信息
+    pub fn params(&self) -> Result<Params, String> {
+        match &self.params {
+            serde_json::Value::Null => Ok(Params::default()),
+            serde_json::Value::Array(values) => {
+                values.iter().map(parameter_value).collect::<Result<_, _>>().map(Params::Positional)
+            }
+            serde_json::Value::Object(values) => values
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), parameter_value(value)?)))
+                .collect::<Result<_, String>>()
+                .map(Params::Named),
+            _ => Err("params must be an array or an object".to_string()),
+        }
+    }
+}
+
+/// 把一个扩展 JSON 参数值转换为 BOML 值
+fn parameter_value(value: &serde_json::Value) -> Result<mikudb_boml::BomlValue, String> {
+    mikudb_boml::from_extended_json(value).map_err(|e| format!("Invalid parameter value: {}", e))
+}
+
+/// 中断请求
+///
+/// 通过会话 ID 和客户端请求 ID 定位要中断的请求,只能中断同一用户的会话。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillOpRequest {
+    pub session_id: u64,
+    pub request_id: u32,
+}
+
+/// 增量备份请求
+///
+/// 导出 `since_lsn` 之后的 WAL 段到服务器端目录,响应的文档为增量清单。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupIncrementalRequest {
+    /// 起始 LSN: 快照清单的 `wal_lsn` 或上一次增量清单的 `until_lsn`
+    pub since_lsn: u64,
+    /// 增量备份目录,备份根目录(`storage.backup_dir`)下的相对路径
+    pub path: String,
 }
 
 /// 查询响应
@@ -361,6 +551,33 @@ pub struct QueryResponse {
     pub documents: Vec<serde_json::Value>,
     pub cursor_id: Option<u64>,
     pub message: Option<String>,
+    /// 结构化的校验错误(字段 JSON Pointer、期望/实际类型、规则 ID),仅写入被拒绝时存在
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ValidationDetail>,
+    /// 语句的资源统计,会话中执行 `SET return_stats = true` 后存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<StatementStats>,
+    /// 失败原因的分类,仅 `success` 为 false 时存在,客户端据此区分退出码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+}
+
+/// 查询失败的分类
+///
+/// 随失败的 `QueryResponse` 返回,客户端不必再匹配错误消息文本。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// 语句或请求无法解析
+    Parse,
+    /// 用户缺少执行该语句所需的权限,或未认证
+    Permission,
+    /// 语句执行超过超时时间
+    Timeout,
+    /// 请求被 KillOp 中断
+    Interrupted,
+    /// 语句执行失败
+    Execution,
 }
 
 /// 插入请求
@@ -371,6 +588,9 @@ pub struct InsertRequest {
     pub database: String,
     pub collection: String,
     pub documents: Vec<serde_json::Value>,
+    /// 写入元数据(发起节点、应用标签、请求 ID),记录在变更事件中,不写入文档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<WriteMetadata>,
 }
 
 /// 更新请求
@@ -386,6 +606,9 @@ pub struct UpdateRequest {
     pub multi: bool,
     /// 如果不存在则插入
     pub upsert: bool,
+    /// 写入元数据(发起节点、应用标签、请求 ID),记录在变更事件中,不写入文档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<WriteMetadata>,
 }
 
 /// 删除请求
@@ -398,6 +621,56 @@ pub struct DeleteRequest {
     pub filter: serde_json::Value,
     /// 是否删除多个文档(false 只删除第一个匹配的)
     pub multi: bool,
+    /// 写入元数据(发起节点、应用标签、请求 ID),记录在变更事件中,不写入文档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<WriteMetadata>,
+}
+
+/// 批量写入中的单个操作
+///
+/// JSON 形式为 `{"insert": {...}}`、`{"update": {...}}` 或 `{"delete": {...}}`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkOperation {
+    Insert {
+        document: serde_json::Value,
+    },
+    Update {
+        filter: serde_json::Value,
+        update: serde_json::Value,
+        #[serde(default)]
+        multi: bool,
+        #[serde(default)]
+        upsert: bool,
+    },
+    Delete {
+        filter: serde_json::Value,
+        #[serde(default)]
+        multi: bool,
+    },
+}
+
+/// 批量写入请求 (BulkWrite)
+///
+/// 有序执行(默认)在第一个失败的操作处停止,无序执行跳过失败的操作继续。
+/// 响应的第一个文档是按操作下标给出的结果和 `write_errors`。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkWriteRequest {
+    pub database: String,
+    pub collection: String,
+    pub operations: Vec<BulkOperation>,
+    #[serde(default = "default_ordered")]
+    pub ordered: bool,
+    /// 插入的文档是规范扩展 JSON,按原类型还原
+    #[serde(default)]
+    pub extended_json: bool,
+    /// 写入元数据(发起节点、应用标签、请求 ID),记录在变更事件中,不写入文档
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<WriteMetadata>,
+}
+
+fn default_ordered() -> bool {
+    true
 }
 
 /// 查找请求
@@ -412,4 +685,34 @@ pub struct FindRequest {
     pub sort: Option<serde_json::Value>,
     pub limit: Option<u32>,
     pub skip: Option<u32>,
+    /// 游标首批文档数提示,None 表示一次返回全部结果
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+    /// 以规范扩展 JSON 返回文档(ObjectId、DateTime、各数值类型等不丢失)
+    #[serde(default)]
+    pub extended_json: bool,
+}
+
+/// 获取游标下一批结果的请求 (GetMore,兼容旧的 CursorNext)
+///
+/// 指定 `batch_size` 时本批按该大小返回,否则按上一批大小翻倍(自适应批量)。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetMoreRequest {
+    pub cursor_id: u64,
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+}
+
+/// 关闭游标请求 (CursorClose)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorCloseRequest {
+    pub cursor_id: u64,
+}
+
+/// 关闭游标请求 (KillCursor)
+///
+/// 只能关闭当前会话打开的游标,响应的 `affected` 为实际关闭的数量。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillCursorRequest {
+    pub cursor_ids: Vec<u64>,
 }
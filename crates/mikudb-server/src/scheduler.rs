@@ -0,0 +1,388 @@
+//! 请求调度模块
+//!
+//! 在混合负载下保护交互式查询不被批量导入拖慢:
+//! - 请求优先级: 交互式 (Interactive) / 批处理 (Batch)
+//! - 分离的等待队列,按权重轮转调度 (默认每 4 个交互式请求放行 1 个批处理请求)
+//! - 按集合的写入速率限制 (令牌桶,单位: 文档/秒)
+//!
+//! 优先级来源:
+//! - 消息头 flags 中的 `FLAG_BATCH_PRIORITY` 位
+//! - 会话级默认值 (认证请求中的 `priority` 字段)
+//! - HTTP 请求头 `X-MikuDB-Priority`
+
+use crate::config::SchedulerConfig;
+use mikudb_query::Statement;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// 请求优先级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// 交互式请求(默认)
+    #[default]
+    Interactive,
+    /// 批处理请求(批量导入、离线任务)
+    Batch,
+}
+
+impl Priority {
+    fn index(self) -> usize {
+        match self {
+            Priority::Interactive => 0,
+            Priority::Batch => 1,
+        }
+    }
+
+    /// # Brief
+    /// 从字符串解析优先级,不区分大小写
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "interactive" => Some(Priority::Interactive),
+            "batch" => Some(Priority::Batch),
+            _ => None,
+        }
+    }
+}
+
+/// 调度器内部状态
+struct QueueState {
+    /// 正在执行的请求数
+    running: usize,
+    /// 等待队列 [interactive, batch]
+    queues: [VecDeque<oneshot::Sender<()>>; 2],
+    /// 本轮剩余的放行额度 [interactive, batch]
+    credits: [u32; 2],
+}
+
+/// 令牌桶
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last: Instant::now(),
+        }
+    }
+
+    /// # Brief
+    /// 扣除令牌并返回需要等待的时长
+    ///
+    /// 允许令牌为负(欠账),后续请求需要等待更久,从而把平均速率压到上限。
+    fn take(&mut self, n: u64) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.tokens -= n as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// 调度器统计
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerStats {
+    pub max_concurrent: usize,
+    pub running: usize,
+    pub interactive_queued: usize,
+    pub batch_queued: usize,
+    pub interactive_completed: u64,
+    pub batch_completed: u64,
+    /// 因写入限速而延迟的请求数
+    pub throttled_writes: u64,
+}
+
+/// 请求调度器
+pub struct RequestScheduler {
+    max_concurrent: usize,
+    weights: [u32; 2],
+    state: Mutex<QueueState>,
+    completed: [AtomicU64; 2],
+    default_write_limit: Option<u64>,
+    write_limits: HashMap<String, u64>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    throttled_writes: AtomicU64,
+}
+
+/// 执行许可
+///
+/// 释放时唤醒下一个等待的请求。
+pub struct SchedulerPermit {
+    scheduler: Arc<RequestScheduler>,
+    priority: Priority,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        self.scheduler.completed[self.priority.index()].fetch_add(1, Ordering::Relaxed);
+        self.scheduler.release();
+    }
+}
+
+/// 排队中的等待者
+///
+/// 请求在排队期间被取消时,如果槽位恰好已转交给它,需要归还槽位。
+struct PendingSlot<'a> {
+    scheduler: &'a RequestScheduler,
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for PendingSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            rx.close();
+            if rx.try_recv().is_ok() {
+                self.scheduler.release();
+            }
+        }
+    }
+}
+
+impl RequestScheduler {
+    /// # Brief
+    /// 根据配置创建调度器
+    pub fn new(config: &SchedulerConfig) -> Self {
+        let weights = [config.interactive_weight.max(1), config.batch_weight.max(1)];
+        Self {
+            max_concurrent: config.max_concurrent.max(1),
+            weights,
+            state: Mutex::new(QueueState {
+                running: 0,
+                queues: [VecDeque::new(), VecDeque::new()],
+                credits: weights,
+            }),
+            completed: [AtomicU64::new(0), AtomicU64::new(0)],
+            default_write_limit: config.default_collection_write_limit,
+            write_limits: config.collection_write_limits.clone(),
+            buckets: Mutex::new(HashMap::new()),
+            throttled_writes: AtomicU64::new(0),
+        }
+    }
+
+    /// # Brief
+    /// 获取执行许可
+    ///
+    /// 有空闲槽位且没有排队请求时立即返回,否则进入对应优先级的队列等待。
+    ///
+    /// # Arguments
+    /// * `priority` - 请求优先级
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> SchedulerPermit {
+        let rx = {
+            let mut state = self.state.lock();
+            let queued = state.queues.iter().any(|q| !q.is_empty());
+            if state.running < self.max_concurrent && !queued {
+                state.running += 1;
+                None
+            } else {
+                let (tx, rx) = oneshot::channel();
+                state.queues[priority.index()].push_back(tx);
+                Some(rx)
+            }
+        };
+
+        if let Some(rx) = rx {
+            let mut pending = PendingSlot {
+                scheduler: self,
+                rx: Some(rx),
+            };
+            if let Some(rx) = pending.rx.as_mut() {
+                let _ = rx.await;
+            }
+            // 槽位已转交,由下面的许可负责释放
+            pending.rx = None;
+        }
+
+        SchedulerPermit {
+            scheduler: self.clone(),
+            priority,
+        }
+    }
+
+    /// # Brief
+    /// 释放槽位,按权重把槽位转交给下一个等待者
+    fn release(&self) {
+        let mut state = self.state.lock();
+        loop {
+            let Some(tx) = self.pick_next(&mut state) else {
+                state.running -= 1;
+                return;
+            };
+            // 等待者已取消(连接断开)时继续挑选下一个
+            if tx.send(()).is_ok() {
+                return;
+            }
+        }
+    }
+
+    fn pick_next(&self, state: &mut QueueState) -> Option<oneshot::Sender<()>> {
+        if state.queues.iter().all(|q| q.is_empty()) {
+            return None;
+        }
+        loop {
+            for class in 0..2 {
+                if state.credits[class] > 0 {
+                    if let Some(tx) = state.queues[class].pop_front() {
+                        state.credits[class] -= 1;
+                        return Some(tx);
+                    }
+                }
+            }
+            // 有请求排队但额度耗尽,开始新一轮
+            state.credits = self.weights;
+        }
+    }
+
+    /// # Brief
+    /// 按集合写入速率限制等待
+    ///
+    /// 未配置限速的集合立即返回。
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `documents` - 本次写入的文档数
+    pub async fn throttle_write(&self, collection: &str, documents: u64) {
+        let Some(limit) = self.write_limits.get(collection).copied().or(self.default_write_limit) else {
+            return;
+        };
+        if limit == 0 {
+            return;
+        }
+
+        let wait = self
+            .buckets
+            .lock()
+            .entry(collection.to_string())
+            .or_insert_with(|| TokenBucket::new(limit))
+            .take(documents.max(1));
+
+        if !wait.is_zero() {
+            self.throttled_writes.fetch_add(1, Ordering::Relaxed);
+            debug!("Throttling write to {} for {:?}", collection, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// # Brief
+    /// 获取调度统计
+    pub fn stats(&self) -> SchedulerStats {
+        let state = self.state.lock();
+        SchedulerStats {
+            max_concurrent: self.max_concurrent,
+            running: state.running,
+            interactive_queued: state.queues[0].len(),
+            batch_queued: state.queues[1].len(),
+            interactive_completed: self.completed[0].load(Ordering::Relaxed),
+            batch_completed: self.completed[1].load(Ordering::Relaxed),
+            throttled_writes: self.throttled_writes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// # Brief
+/// 获取写语句的目标集合和写入文档数
+///
+/// # Returns
+/// 非写语句返回 None
+pub fn write_target(statement: &Statement) -> Option<(&str, u64)> {
+    match statement {
+        Statement::Insert(insert) => Some((insert.collection.as_str(), insert.documents.len() as u64)),
+        Statement::InsertSelect(insert) => Some((insert.collection.as_str(), 1)),
+        Statement::Update(update) => Some((update.collection.as_str(), 1)),
+        Statement::Delete(delete) => Some((delete.collection.as_str(), 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 单槽位调度器,排队请求按放行顺序记录优先级
+    fn scheduler(interactive_weight: u32, batch_weight: u32) -> Arc<RequestScheduler> {
+        Arc::new(RequestScheduler::new(&SchedulerConfig {
+            max_concurrent: 1,
+            interactive_weight,
+            batch_weight,
+            ..SchedulerConfig::default()
+        }))
+    }
+
+    /// 在槽位被占用时排入 `priorities` 中的请求,释放槽位后返回放行顺序
+    async fn dispatch_order(scheduler: &Arc<RequestScheduler>, priorities: &[Priority]) -> Vec<Priority> {
+        let holder = scheduler.acquire(Priority::Interactive).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (queued, &priority) in priorities.iter().enumerate() {
+            let waiter = scheduler.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move {
+                let _permit = waiter.acquire(priority).await;
+                order.lock().push(priority);
+            }));
+            // 逐个排队,保证同一队列内的顺序
+            while {
+                let stats = scheduler.stats();
+                stats.interactive_queued + stats.batch_queued <= queued
+            } {
+                tokio::task::yield_now().await;
+            }
+        }
+        drop(holder);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        Arc::try_unwrap(order).ok().unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_is_weight_proportional() {
+        let scheduler = scheduler(4, 1);
+        let mut queued = vec![Priority::Batch; 4];
+        queued.extend([Priority::Interactive; 16]);
+
+        let order = dispatch_order(&scheduler, &queued).await;
+        let round: Vec<_> = [Priority::Interactive; 4].into_iter().chain([Priority::Batch]).collect();
+        assert_eq!(order, round.repeat(4));
+
+        let stats = scheduler.stats();
+        assert_eq!(stats.running, 0);
+        assert_eq!(stats.interactive_completed, 17);
+        assert_eq!(stats.batch_completed, 4);
+    }
+
+    #[tokio::test]
+    async fn test_low_weight_queue_is_not_starved() {
+        let scheduler = scheduler(4, 1);
+        // 批处理请求排在 50 个交互式请求之后,仍在第一轮内放行
+        let mut queued = vec![Priority::Interactive; 50];
+        queued.push(Priority::Batch);
+
+        let order = dispatch_order(&scheduler, &queued).await;
+        assert_eq!(order.iter().position(|&p| p == Priority::Batch), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_idle_queue_does_not_hold_back_the_other() {
+        // 只有批处理请求排队时不受交互式权重限制
+        let scheduler = scheduler(4, 1);
+        let order = dispatch_order(&scheduler, &[Priority::Batch; 6]).await;
+        assert_eq!(order, vec![Priority::Batch; 6]);
+        assert_eq!(scheduler.stats().batch_completed, 6);
+    }
+}
@@ -1,20 +1,30 @@
 //! 数据库服务器主模块
 //!
 //! 本模块实现 MikuDB 服务器核心逻辑:
-//! - 服务器生命周期管理(启动、运行、关闭)
+//! - 服务器生命周期管理(启动预检、启动、运行、关闭)
 //! - 连接池管理(使用 Semaphore 限制并发连接数)
 //! - 存储引擎初始化
 //! - 会话管理
 //! - 统计信息收集
+//! - HTTP 接口和后台巡检启动
+//! - 集群成员身份(启动时按保存的身份重新加入集群)
+//! - 运行状态快照(关闭时保存,启动时恢复)
 
+use crate::cluster::ClusterManager;
 use crate::config::ServerConfig;
+use crate::cursor::CursorManager;
 use crate::handler::ClientHandler;
 use crate::network::TcpListener;
 use crate::session::SessionManager;
 use crate::auth::UserManager;
+use crate::scheduler::RequestScheduler;
+use crate::storage_pool::StoragePool;
 use crate::{ServerError, ServerResult};
 use mikudb_core::Database;
-use mikudb_storage::{StorageEngine, StorageOptions};
+use mikudb_query::advisor::{AdvisorOptions, IndexAdvisor, QueryLog};
+use mikudb_query::sandbox::{self, SandboxLimits};
+use mikudb_query::{FunctionRegistry, OpStats};
+use mikudb_storage::{CompactionScheduler, ScrubOptions, Scrubber, StorageEngine, StorageOptions, TtlSweeper};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
@@ -42,6 +52,31 @@ pub struct Server {
     session_manager: Arc<SessionManager>,
     /// 用户管理器(共享)
     user_manager: Arc<UserManager>,
+    /// 请求调度器(共享)
+    scheduler: Arc<RequestScheduler>,
+    /// 存储线程池(共享)
+    storage_pool: Arc<StoragePool>,
+    /// 存储巡检器(启用巡检时存在)
+    scrubber: Option<Arc<Scrubber>>,
+    /// TTL 索引清理器(启用文档过期时存在)
+    ttl_sweeper: Option<Arc<TtlSweeper>>,
+    /// 定时压缩器(配置了压缩间隔时存在)
+    compaction: Option<Arc<CompactionScheduler>>,
+    /// 索引顾问(启用顾问时存在)
+    advisor: Option<Arc<IndexAdvisor>>,
+    /// 查询日志(启用顾问时存在)
+    query_log: Option<Arc<QueryLog>>,
+    /// Redis 写入通知(启用写入通知时存在)
+    #[cfg(feature = "redis-notify")]
+    notifier: Option<Arc<crate::notify::RedisNotifier>>,
+    /// 按集合的操作统计
+    op_stats: Arc<OpStats>,
+    /// 自定义函数注册表(启用 UDF 沙箱时包含保存的 WASM 函数)
+    functions: Arc<FunctionRegistry>,
+    /// 服务器端游标管理器
+    cursors: Arc<CursorManager>,
+    /// 集群成员管理(共享)
+    cluster: Arc<ClusterManager>,
     /// 连接信号量,限制最大并发连接数
     connection_semaphore: Arc<Semaphore>,
     /// 服务器运行状态
@@ -58,7 +93,8 @@ impl Server {
     /// # Brief
     /// 创建新的服务器实例
     ///
-    /// 初始化存储引擎、会话管理器和连接池。
+    /// 先执行启动预检(数据目录、磁盘空间、文件描述符、时钟、端口、数据目录锁),
+    /// 再初始化存储引擎、会话管理器和连接池。
     /// 在 Linux 系统上会应用 OpenEuler 性能优化。
     ///
     /// # Arguments
@@ -67,6 +103,13 @@ impl Server {
     /// # Returns
     /// 初始化好的服务器实例
     pub async fn new(config: ServerConfig) -> ServerResult<Self> {
+        // 启动预检:在打开存储引擎前暴露环境问题
+        if config.preflight.enabled {
+            let report = crate::preflight::run(&config);
+            report.log();
+            report.into_result()?;
+        }
+
         // 创建数据目录(如果不存在)
         std::fs::create_dir_all(&config.data_dir)?;
 
@@ -80,12 +123,27 @@ impl Server {
         let storage_opts = StorageOptions {
             data_dir: config.data_dir.clone(),
             cache_size: config.parse_cache_size(),
+            document_cache_size: config.parse_document_cache_size(),
+            document_cache_policy: config.document_cache_policy(),
+            wal_sync: config.wal_sync_policy(),
+            wal_dir: config.storage.wal_dir.clone(),
+            wal_archive_dir: config.storage.wal_archive_dir.clone(),
+            ttl_sweep_interval: std::time::Duration::from_secs(config.expiry.ttl_interval_secs.max(1)),
+            compaction_interval: (config.storage.compaction_interval_secs > 0)
+                .then(|| std::time::Duration::from_secs(config.storage.compaction_interval_secs)),
             ..Default::default()
         };
 
         info!("Initializing storage engine at {:?}", config.data_dir);
         let storage = Arc::new(StorageEngine::open(storage_opts)?);
 
+        // 未执行 USE 的会话使用配置的默认数据库,第一次启动时创建
+        if let Some(database) = &config.default_database {
+            if !storage.database_exists(database)? {
+                storage.create_database(database)?;
+            }
+        }
+
         let session_manager = Arc::new(SessionManager::new(
             std::time::Duration::from_secs(3600),
         ));
@@ -96,20 +154,112 @@ impl Server {
             user_manager.initialize().await?;
         }
 
+        let scrubber = config.scrub.enabled.then(|| {
+            Arc::new(Scrubber::new(storage.clone(), ScrubOptions {
+                max_bytes_per_sec: config.scrub.max_bytes_per_sec,
+                interval: std::time::Duration::from_secs(config.scrub.interval_secs),
+                check_indexes: config.scrub.check_indexes,
+            }))
+        });
+
+        let ttl_sweeper = config
+            .expiry
+            .enabled
+            .then(|| Arc::new(TtlSweeper::new(storage.clone())));
+
+        let compaction = CompactionScheduler::new(storage.clone()).map(Arc::new);
+
+        let query_log = config
+            .advisor
+            .enabled
+            .then(|| Arc::new(QueryLog::new(config.advisor.max_query_shapes)));
+        let advisor = query_log.as_ref().map(|log| {
+            Arc::new(IndexAdvisor::new(storage.clone(), log.clone(), AdvisorOptions {
+                interval: std::time::Duration::from_secs(config.advisor.interval_secs),
+                sample_size: config.advisor.sample_size,
+                min_documents: config.advisor.min_documents,
+                min_queries: config.advisor.min_queries,
+            }))
+        });
+
+        let scheduler = Arc::new(RequestScheduler::new(&config.scheduler));
+        let storage_pool = Arc::new(StoragePool::new(config.storage.blocking_threads)?);
+
         let connection_semaphore = Arc::new(Semaphore::new(config.max_connections));
 
-        Ok(Self {
+        let cursors = Arc::new(
+            CursorManager::new(std::time::Duration::from_secs(config.cursor_timeout_secs))
+                .with_limits(config.cursor_limits()),
+        );
+
+        let functions = Arc::new(FunctionRegistry::new());
+        if config.udf.enabled {
+            if sandbox::available() {
+                functions.enable_sandbox(SandboxLimits {
+                    fuel: config.udf.fuel,
+                    memory_bytes: config.udf.memory_limit_mb * 1024 * 1024,
+                    max_module_bytes: config.udf.max_module_kb * 1024,
+                });
+                let loaded = functions.load_stored(&storage)?;
+                info!("UDF sandbox enabled, loaded {} function(s)", loaded);
+            } else {
+                warn!("udf.enabled is set but this build does not include the wasm-udf feature");
+            }
+        }
+
+        #[cfg(feature = "redis-notify")]
+        let notifier = match config.notify.enabled {
+            true => Some(Arc::new(crate::notify::RedisNotifier::new(storage.clone(), &config.notify)?)),
+            false => None,
+        };
+        #[cfg(not(feature = "redis-notify"))]
+        if config.notify.enabled {
+            warn!("notify.enabled is set but this build does not include the redis-notify feature");
+        }
+
+        // 之前初始化或加入过集群的节点以保存的身份重新加入
+        let cluster = Arc::new(ClusterManager::new(&config));
+        if let Some(identity) = cluster.resume().await? {
+            info!(
+                "Rejoined cluster {} as node {} (machine id {})",
+                identity.cluster_id, identity.node_id, identity.machine_id
+            );
+        }
+
+        let server = Self {
             config,
             databases: RwLock::new(HashMap::new()),
             storage,
             session_manager,
             user_manager,
+            scheduler,
+            storage_pool,
+            scrubber,
+            ttl_sweeper,
+            compaction,
+            advisor,
+            query_log,
+            #[cfg(feature = "redis-notify")]
+            notifier,
+            op_stats: Arc::new(OpStats::new()),
+            functions,
+            cursors,
+            cluster,
             connection_semaphore,
             running: AtomicBool::new(false),
             connections_count: AtomicU64::new(0),
             requests_count: AtomicU64::new(0),
             start_time: std::time::Instant::now(),
-        })
+        };
+
+        // 恢复上次正常关闭时保存的统计和查询形态,失败时从空状态开始
+        if server.config.persist_state {
+            if let Err(e) = crate::state::restore(&server) {
+                warn!("Failed to restore runtime state: {}", e);
+            }
+        }
+
+        Ok(server)
     }
 
     /// # Brief
@@ -135,6 +285,63 @@ impl Server {
             info!("TLS enabled - accepting encrypted connections");
         }
 
+        // 启动后台存储巡检
+        if let Some(ref scrubber) = self.scrubber {
+            scrubber.clone().start();
+        }
+
+        // 启动 TTL 索引清理
+        if let Some(ref sweeper) = self.ttl_sweeper {
+            sweeper.clone().start();
+        }
+
+        // 启动定时压缩
+        if let Some(ref compaction) = self.compaction {
+            compaction.clone().start();
+        }
+
+        // 启动索引顾问
+        if let Some(ref advisor) = self.advisor {
+            advisor.clone().start();
+        }
+
+        // 把已提交的写入发布到 Redis
+        #[cfg(feature = "redis-notify")]
+        if let Some(ref notifier) = self.notifier {
+            notifier.clone().start();
+        }
+
+        // 周期执行已保存的归档策略
+        if self.config.tiering.enabled {
+            let server = self.clone();
+            tokio::spawn(async move {
+                server.run_archive_policies().await;
+            });
+        }
+
+        // 周期删除按字段过期的文档
+        if self.config.expiry.enabled {
+            let server = self.clone();
+            tokio::spawn(async move {
+                server.run_expire_policies().await;
+            });
+        }
+
+        // 启动 HTTP 接口(REST API / Web 控制台)
+        #[cfg(feature = "console")]
+        if self.config.http.enabled {
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = crate::http::serve(server).await {
+                    error!("HTTP listener error: {}", e);
+                }
+            });
+        }
+        #[cfg(not(feature = "console"))]
+        if self.config.http.enabled {
+            warn!("http.enabled is set but this build does not include the console feature");
+        }
+
         // 在 Linux 上同时启用 Unix Socket 支持
         #[cfg(target_os = "linux")]
         if let Some(ref socket_path) = self.config.unix_socket {
@@ -190,6 +397,13 @@ impl Server {
                             server.storage.clone(),
                             server.session_manager.clone(),
                             server.user_manager.clone(),
+                            server.scheduler.clone(),
+                            server.storage_pool.clone(),
+                            server.query_log.clone(),
+                            server.op_stats.clone(),
+                            server.functions.clone(),
+                            server.cursors.clone(),
+                            server.cluster.clone(),
                             server.config.clone(),
                         );
 
@@ -219,6 +433,13 @@ impl Server {
                                 server.storage.clone(),
                                 server.session_manager.clone(),
                                 server.user_manager.clone(),
+                                server.scheduler.clone(),
+                                server.storage_pool.clone(),
+                                server.query_log.clone(),
+                                server.op_stats.clone(),
+                                server.functions.clone(),
+                                server.cursors.clone(),
+                                server.cluster.clone(),
                                 server.config.clone(),
                             );
 
@@ -242,13 +463,97 @@ impl Server {
         Ok(())
     }
 
+    /// # Brief
+    /// 按配置间隔循环执行所有归档策略,服务器停止后退出
+    async fn run_archive_policies(&self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            self.config.tiering.interval_secs.max(1),
+        ));
+        interval.tick().await;
+
+        while self.running.load(Ordering::SeqCst) {
+            interval.tick().await;
+
+            let storage = self.storage.clone();
+            let result = self.storage_pool.run(move || -> mikudb_storage::StorageResult<u64> {
+                let mut moved = 0;
+                for policy in storage.archive_policies()? {
+                    match storage.apply_archive_policy(&policy) {
+                        Ok(n) => moved += n,
+                        Err(e) => warn!("Archive policy {} -> {} failed: {}", policy.source, policy.archive, e),
+                    }
+                }
+                Ok(moved)
+            }).await;
+
+            match result {
+                Ok(Ok(moved)) => debug!("Archive policies moved {} document(s)", moved),
+                Ok(Err(e)) => warn!("Failed to load archive policies: {}", e),
+                Err(e) => warn!("Failed to run archive policies: {}", e),
+            }
+        }
+    }
+
+    /// # Brief
+    /// 按配置间隔循环执行所有过期策略,服务器停止后退出
+    async fn run_expire_policies(&self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            self.config.expiry.interval_secs.max(1),
+        ));
+        interval.tick().await;
+
+        while self.running.load(Ordering::SeqCst) {
+            interval.tick().await;
+
+            let storage = self.storage.clone();
+            let result = self.storage_pool.run(move || -> mikudb_storage::StorageResult<u64> {
+                let mut removed = 0;
+                for policy in storage.expire_policies()? {
+                    match storage.apply_expire_policy(&policy) {
+                        Ok(n) => removed += n,
+                        Err(e) => warn!("Expire policy for {} failed: {}", policy.collection, e),
+                    }
+                }
+                Ok(removed)
+            }).await;
+
+            match result {
+                Ok(Ok(removed)) => debug!("Expire policies removed {} document(s)", removed),
+                Ok(Err(e)) => warn!("Failed to load expire policies: {}", e),
+                Err(e) => warn!("Failed to run expire policies: {}", e),
+            }
+        }
+    }
+
     /// # Brief
     /// 关闭服务器
     ///
     /// 设置运行状态为 false,主循环将在下次检查时退出。
+    /// 停止后台任务后保存运行状态快照(`persist_state` 开启时)。
     pub fn shutdown(&self) {
         info!("Shutting down server...");
         self.running.store(false, Ordering::SeqCst);
+        if let Some(ref scrubber) = self.scrubber {
+            scrubber.stop();
+        }
+        if let Some(ref sweeper) = self.ttl_sweeper {
+            sweeper.stop();
+        }
+        if let Some(ref compaction) = self.compaction {
+            compaction.stop();
+        }
+        if let Some(ref advisor) = self.advisor {
+            advisor.stop();
+        }
+        #[cfg(feature = "redis-notify")]
+        if let Some(ref notifier) = self.notifier {
+            notifier.stop();
+        }
+        if self.config.persist_state {
+            if let Err(e) = crate::state::save(self) {
+                warn!("Failed to save runtime state: {}", e);
+            }
+        }
     }
 
     /// # Brief
@@ -265,6 +570,91 @@ impl Server {
         }
     }
 
+    /// # Brief
+    /// 获取服务器配置
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// # Brief
+    /// 获取共享的存储引擎
+    pub fn storage(&self) -> &Arc<StorageEngine> {
+        &self.storage
+    }
+
+    /// # Brief
+    /// 获取集群成员管理器
+    pub fn cluster(&self) -> &Arc<ClusterManager> {
+        &self.cluster
+    }
+
+    /// # Brief
+    /// 获取共享的用户管理器
+    pub fn user_manager(&self) -> &Arc<UserManager> {
+        &self.user_manager
+    }
+
+    /// # Brief
+    /// 获取请求调度器
+    pub fn scheduler(&self) -> &Arc<RequestScheduler> {
+        &self.scheduler
+    }
+
+    /// # Brief
+    /// 获取存储线程池
+    pub fn storage_pool(&self) -> &Arc<StoragePool> {
+        &self.storage_pool
+    }
+
+    /// # Brief
+    /// 获取存储巡检器
+    pub fn scrubber(&self) -> Option<&Arc<Scrubber>> {
+        self.scrubber.as_ref()
+    }
+
+    /// # Brief
+    /// 获取 TTL 索引清理器
+    pub fn ttl_sweeper(&self) -> Option<&Arc<TtlSweeper>> {
+        self.ttl_sweeper.as_ref()
+    }
+
+    /// # Brief
+    /// 获取查询日志
+    pub fn query_log(&self) -> Option<&Arc<QueryLog>> {
+        self.query_log.as_ref()
+    }
+
+    /// # Brief
+    /// 获取按集合的操作统计
+    pub fn op_stats(&self) -> &Arc<OpStats> {
+        &self.op_stats
+    }
+
+    /// # Brief
+    /// 获取自定义函数注册表
+    pub fn functions(&self) -> &Arc<FunctionRegistry> {
+        &self.functions
+    }
+
+    /// # Brief
+    /// 获取服务器端游标管理器
+    pub fn cursors(&self) -> &Arc<CursorManager> {
+        &self.cursors
+    }
+
+    /// # Brief
+    /// 获取会话管理器
+    pub fn session_manager(&self) -> &Arc<SessionManager> {
+        &self.session_manager
+    }
+
+    /// # Brief
+    /// 恢复上次运行的累计连接数和请求数(服务器重启时)
+    pub(crate) fn resume_counters(&self, connections: u64, requests: u64) {
+        self.connections_count.fetch_add(connections, Ordering::SeqCst);
+        self.requests_count.fetch_add(requests, Ordering::Relaxed);
+    }
+
     /// # Brief
     /// 增加请求计数器
     ///
@@ -277,7 +667,7 @@ impl Server {
 /// 服务器统计信息
 ///
 /// 包含服务器运行时的各项指标。
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct ServerStats {
     pub uptime_secs: u64,
     pub total_connections: u64,
@@ -321,6 +711,13 @@ async fn handle_tls_connection(
                 server.storage.clone(),
                 server.session_manager.clone(),
                 server.user_manager.clone(),
+                server.scheduler.clone(),
+                server.storage_pool.clone(),
+                server.query_log.clone(),
+                server.op_stats.clone(),
+                server.functions.clone(),
+                server.cursors.clone(),
+                server.cluster.clone(),
                 server.config.clone(),
             );
             handler.handle().await?;
@@ -4,11 +4,15 @@
 //! - 会话创建和销毁
 //! - 会话超时检测和清理
 //! - 事务状态跟踪
+//! - 会话级请求优先级
+//! - 正在执行的请求登记,支持 KillOp 中断
+//! - 会话 ID 编号在服务器重启后延续
 //! - 并发安全的会话访问(使用 DashMap)
 
+use crate::scheduler::Priority;
 use dashmap::DashMap;
 use parking_lot::RwLock;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -33,6 +37,10 @@ pub struct Session {
     last_activity: RwLock<Instant>,
     /// 当前事务 ID(可变)
     transaction_id: RwLock<Option<u64>>,
+    /// 会话默认请求优先级(可变)
+    priority: RwLock<Priority>,
+    /// 正在执行的请求 (客户端请求 ID -> 中断标志)
+    operations: DashMap<u32, Arc<AtomicBool>>,
 }
 
 impl Session {
@@ -55,6 +63,8 @@ impl Session {
             created_at: Instant::now(),
             last_activity: RwLock::new(Instant::now()),
             transaction_id: RwLock::new(None),
+            priority: RwLock::new(Priority::default()),
+            operations: DashMap::new(),
         }
     }
 
@@ -129,6 +139,56 @@ impl Session {
         *self.transaction_id.write() = txn_id;
     }
 
+    /// # Brief
+    /// 获取会话默认优先级
+    pub fn priority(&self) -> Priority {
+        *self.priority.read()
+    }
+
+    /// # Brief
+    /// 设置会话默认优先级
+    ///
+    /// # Arguments
+    /// * `priority` - 未在消息头中指定优先级的请求使用此值
+    pub fn set_priority(&self, priority: Priority) {
+        *self.priority.write() = priority;
+    }
+
+    /// # Brief
+    /// 登记正在执行的请求
+    ///
+    /// # Arguments
+    /// * `request_id` - 客户端请求 ID
+    ///
+    /// # Returns
+    /// 请求的中断标志,KillOp 或超时时被置位
+    pub fn begin_operation(&self, request_id: u32) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.operations.insert(request_id, flag.clone());
+        flag
+    }
+
+    /// # Brief
+    /// 请求执行结束,取消登记
+    pub fn end_operation(&self, request_id: u32) {
+        self.operations.remove(&request_id);
+    }
+
+    /// # Brief
+    /// 中断正在执行的请求
+    ///
+    /// # Returns
+    /// true 表示找到并中断了该请求
+    pub fn kill_operation(&self, request_id: u32) -> bool {
+        match self.operations.get(&request_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// # Brief
     /// 检查会话是否在事务中
     ///
@@ -166,6 +226,23 @@ impl SessionManager {
         }
     }
 
+    /// # Brief
+    /// 下一个新会话将分配的 ID
+    pub fn next_session_id(&self) -> u64 {
+        SESSION_ID_COUNTER.load(Ordering::SeqCst)
+    }
+
+    /// # Brief
+    /// 从保存的编号继续分配会话 ID
+    ///
+    /// 重启后会话 ID 不从 1 重新开始,客户端持有的旧会话 ID 不会误中断新会话的请求。
+    ///
+    /// # Arguments
+    /// * `next` - 上次关闭时的下一个会话 ID
+    pub fn resume_session_ids(&self, next: u64) {
+        SESSION_ID_COUNTER.fetch_max(next, Ordering::SeqCst);
+    }
+
     /// # Brief
     /// 创建新会话
     ///
@@ -0,0 +1,183 @@
+//! 运行状态快照模块
+//!
+//! 服务器正常关闭时把内存中的运行状态写入存储引擎的系统列族,下次启动时恢复,
+//! 例行重启后不丢失运维上下文:
+//! - 按集合的操作统计(含耗时直方图),已不存在的集合不恢复
+//! - 索引顾问记录的查询形态,重启后不必重新积累就能给出建议
+//! - 游标因超过上限被关闭、拒绝的次数
+//! - 会话 ID 编号、累计连接数和请求数
+//! - 通过 ADMIN SET LOG LEVEL 调整过的日志级别(启动参数未改变时)
+//!
+//! 打开的游标和会话属于已经断开的连接,不保存。快照只在正常关闭时写入,
+//! 进程异常退出后启动时恢复的是上一次正常关闭时的状态。由 `persist_state` 配置项控制。
+
+use crate::logging::{self, LogSettings};
+use crate::server::Server;
+use crate::ServerResult;
+use mikudb_query::{QueryShape, SavedOpStats};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// 快照在系统列族中的键
+const STATE_KEY: &str = "server_state";
+
+/// 快照格式版本,格式不兼容时递增,旧版本的快照被忽略
+const STATE_VERSION: u32 = 1;
+
+/// 运行状态快照
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// 快照格式版本
+    pub version: u32,
+    /// 保存时间(Unix 秒)
+    pub saved_at: i64,
+    /// 按集合的操作统计
+    pub op_stats: Vec<SavedOpStats>,
+    /// 索引顾问记录的查询形态及出现次数
+    pub query_shapes: Vec<(QueryShape, u64)>,
+    /// 因超过上限被关闭的游标数
+    pub cursors_closed_by_limit: u64,
+    /// 因超过上限被拒绝的游标数
+    pub cursors_rejected: u64,
+    /// 下一个会话 ID
+    pub next_session_id: u64,
+    /// 累计连接数
+    pub total_connections: u64,
+    /// 累计请求数
+    pub total_requests: u64,
+    /// 运行时调整过的日志过滤规则
+    pub log: Option<LogSettings>,
+}
+
+/// # Brief
+/// 采集服务器当前的运行状态
+pub fn capture(server: &Server) -> StateSnapshot {
+    let stats = server.stats();
+    let cursors = server.cursors().stats();
+    StateSnapshot {
+        version: STATE_VERSION,
+        saved_at: chrono::Utc::now().timestamp(),
+        op_stats: server.op_stats().save(),
+        query_shapes: server.query_log().map(|log| log.snapshot()).unwrap_or_default(),
+        cursors_closed_by_limit: cursors.closed_by_limit,
+        cursors_rejected: cursors.rejected,
+        next_session_id: server.session_manager().next_session_id(),
+        total_connections: stats.total_connections,
+        total_requests: stats.total_requests,
+        log: logging::settings(),
+    }
+}
+
+/// # Brief
+/// 保存运行状态到存储引擎
+///
+/// # Arguments
+/// * `server` - 服务器实例
+pub fn save(server: &Server) -> ServerResult<()> {
+    let snapshot = capture(server);
+    let content = serde_json::to_vec(&snapshot).map_err(|e| crate::ServerError::Internal(e.to_string()))?;
+    server.storage().save_system_state(STATE_KEY, &content)?;
+    info!(
+        "Saved runtime state ({} collection stats, {} query shapes)",
+        snapshot.op_stats.len(),
+        snapshot.query_shapes.len()
+    );
+    Ok(())
+}
+
+/// # Brief
+/// 恢复上次关闭时保存的运行状态
+///
+/// 计数与启动后已经产生的计数累加。快照无法解析或版本不同时忽略。
+///
+/// # Arguments
+/// * `server` - 服务器实例
+///
+/// # Returns
+/// 恢复的快照;没有可用的快照时返回 None
+pub fn restore(server: &Server) -> ServerResult<Option<StateSnapshot>> {
+    let Some(content) = server.storage().system_state(STATE_KEY)? else {
+        return Ok(None);
+    };
+    let snapshot = match serde_json::from_slice::<StateSnapshot>(&content) {
+        Ok(snapshot) if snapshot.version == STATE_VERSION => snapshot,
+        Ok(snapshot) => {
+            info!("Ignoring runtime state saved in format version {}", snapshot.version);
+            return Ok(None);
+        }
+        Err(e) => {
+            info!("Ignoring unreadable runtime state: {}", e);
+            return Ok(None);
+        }
+    };
+
+    // 关闭期间可能通过恢复备份等方式删除了集合
+    let op_stats: Vec<_> = snapshot
+        .op_stats
+        .iter()
+        .filter(|stats| server.storage().get_collection(&stats.collection).is_ok())
+        .cloned()
+        .collect();
+    server.op_stats().restore(&op_stats);
+    if let Some(log) = server.query_log() {
+        log.restore(snapshot.query_shapes.clone());
+    }
+    server
+        .cursors()
+        .restore_counters(snapshot.cursors_closed_by_limit, snapshot.cursors_rejected);
+    server.session_manager().resume_session_ids(snapshot.next_session_id);
+    server.resume_counters(snapshot.total_connections, snapshot.total_requests);
+    if let Some(settings) = &snapshot.log {
+        match logging::restore_settings(settings)? {
+            Some(directives) => info!("Restored log filter '{}'", directives),
+            None => info!("Log filter saved at shutdown not restored: startup filter changed"),
+        }
+    }
+
+    info!(
+        "Restored runtime state saved at {} ({} collection stats, {} query shapes)",
+        chrono::DateTime::from_timestamp(snapshot.saved_at, 0)
+            .map(|at| at.to_rfc3339())
+            .unwrap_or_default(),
+        op_stats.len(),
+        snapshot.query_shapes.len()
+    );
+    Ok(Some(snapshot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use mikudb_query::opstats::OpKind;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_save_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = ServerConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        config.preflight.enabled = false;
+        config.auth.enabled = false;
+
+        {
+            let server = Server::new(config.clone()).await.unwrap();
+            server.storage().get_or_create_collection("users").unwrap();
+            server.op_stats().record_op("users", OpKind::Find, Duration::from_millis(1));
+            server.op_stats().record_op("dropped", OpKind::Find, Duration::from_millis(1));
+            server.cursors().restore_counters(2, 1);
+            server.increment_requests();
+            save(&server).unwrap();
+        }
+
+        let server = Server::new(config).await.unwrap();
+        let users = server.op_stats().snapshot("users").unwrap();
+        assert_eq!(users.finds, 1);
+        assert!(server.op_stats().snapshot("dropped").is_none());
+        let cursors = server.cursors().stats();
+        assert_eq!((cursors.closed_by_limit, cursors.rejected), (2, 1));
+        assert!(server.stats().total_requests >= 1);
+    }
+}
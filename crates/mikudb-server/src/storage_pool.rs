@@ -0,0 +1,280 @@
+//! 存储线程池模块
+//!
+//! RocksDB 调用是同步阻塞的,直接在 tokio 工作线程上执行会在磁盘繁忙时拖住执行器,
+//! 导致网络 I/O 无法及时处理。本模块提供专用的存储线程池:
+//! - 固定数量的工作线程,数量由 `storage.blocking_threads` 配置(0 表示按 CPU 核数)
+//! - 异步提交任务,通过 oneshot 通道取回结果
+//! - 队列深度、执行中任务数、排队耗时等指标
+
+use crate::{ServerError, ServerResult};
+use crossbeam::channel::{self, Receiver, Sender};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+use tokio::sync::oneshot;
+use tracing::{debug, error, info};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// 线程池计数器
+#[derive(Default)]
+struct PoolCounters {
+    queued: AtomicUsize,
+    peak_queued: AtomicUsize,
+    active: AtomicUsize,
+    completed: AtomicU64,
+    total_wait_micros: AtomicU64,
+}
+
+/// 存储线程池统计
+#[derive(Debug, Clone, Serialize)]
+pub struct StoragePoolStats {
+    /// 工作线程数
+    pub threads: usize,
+    /// 当前排队任务数
+    pub queue_depth: usize,
+    /// 历史最大排队任务数
+    pub peak_queue_depth: usize,
+    /// 正在执行的任务数
+    pub active: usize,
+    /// 已完成任务数
+    pub completed: u64,
+    /// 平均排队耗时(微秒)
+    pub avg_wait_micros: u64,
+}
+
+/// 存储线程池
+pub struct StoragePool {
+    threads: usize,
+    sender: Mutex<Option<Sender<Job>>>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+    counters: Arc<PoolCounters>,
+}
+
+impl StoragePool {
+    /// # Brief
+    /// 创建存储线程池并启动工作线程
+    ///
+    /// # Arguments
+    /// * `threads` - 工作线程数,0 表示使用 CPU 核数
+    pub fn new(threads: usize) -> ServerResult<Self> {
+        let threads = if threads == 0 {
+            std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4)
+        } else {
+            threads
+        };
+
+        let (sender, receiver) = channel::unbounded::<Job>();
+        let mut workers = Vec::with_capacity(threads);
+        for i in 0..threads {
+            let receiver: Receiver<Job> = receiver.clone();
+            let handle = std::thread::Builder::new()
+                .name(format!("mikudb-storage-{}", i))
+                .spawn(move || {
+                    for job in receiver.iter() {
+                        job();
+                    }
+                })?;
+            workers.push(handle);
+        }
+
+        info!("Storage pool started with {} threads", threads);
+
+        Ok(Self {
+            threads,
+            sender: Mutex::new(Some(sender)),
+            workers: Mutex::new(workers),
+            counters: Arc::new(PoolCounters::default()),
+        })
+    }
+
+    /// # Brief
+    /// 在存储线程池中执行阻塞操作
+    ///
+    /// # Arguments
+    /// * `f` - 阻塞的存储操作
+    ///
+    /// # Returns
+    /// 操作的返回值;线程池已关闭或任务 panic 时返回 Internal 错误
+    pub async fn run<F, T>(&self, f: F) -> ServerResult<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let counters = self.counters.clone();
+        let submitted = Instant::now();
+
+        let job: Job = Box::new(move || {
+            counters.queued.fetch_sub(1, Ordering::Relaxed);
+            counters.active.fetch_add(1, Ordering::Relaxed);
+            counters
+                .total_wait_micros
+                .fetch_add(submitted.elapsed().as_micros() as u64, Ordering::Relaxed);
+
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+
+            counters.active.fetch_sub(1, Ordering::Relaxed);
+            counters.completed.fetch_add(1, Ordering::Relaxed);
+            match result {
+                Ok(value) => {
+                    let _ = tx.send(value);
+                }
+                Err(_) => error!("Storage task panicked"),
+            }
+        });
+
+        {
+            let sender = self.sender.lock();
+            let Some(sender) = sender.as_ref() else {
+                return Err(ServerError::Internal("Storage pool is shut down".to_string()));
+            };
+            let depth = self.counters.queued.fetch_add(1, Ordering::Relaxed) + 1;
+            self.counters.peak_queued.fetch_max(depth, Ordering::Relaxed);
+            if sender.send(job).is_err() {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                return Err(ServerError::Internal("Storage pool is shut down".to_string()));
+            }
+        }
+
+        rx.await
+            .map_err(|_| ServerError::Internal("Storage task aborted".to_string()))
+    }
+
+    /// # Brief
+    /// 获取线程池统计
+    pub fn stats(&self) -> StoragePoolStats {
+        let completed = self.counters.completed.load(Ordering::Relaxed);
+        let total_wait = self.counters.total_wait_micros.load(Ordering::Relaxed);
+        StoragePoolStats {
+            threads: self.threads,
+            queue_depth: self.counters.queued.load(Ordering::Relaxed),
+            peak_queue_depth: self.counters.peak_queued.load(Ordering::Relaxed),
+            active: self.counters.active.load(Ordering::Relaxed),
+            completed,
+            avg_wait_micros: total_wait.checked_div(completed).unwrap_or(0),
+        }
+    }
+
+    /// # Brief
+    /// 关闭线程池
+    ///
+    /// 停止接收新任务,等待已排队的任务执行完毕后退出工作线程。
+    pub fn shutdown(&self) {
+        if self.sender.lock().take().is_none() {
+            return;
+        }
+        for handle in self.workers.lock().drain(..) {
+            let _ = handle.join();
+        }
+        debug!("Storage pool stopped");
+    }
+}
+
+impl Drop for StoragePool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// 等待线程池统计满足条件
+    async fn wait_for(pool: &StoragePool, condition: impl Fn(&StoragePoolStats) -> bool) {
+        for _ in 0..500 {
+            if condition(&pool.stats()) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("storage pool never reached the expected state: {:?}", pool.stats());
+    }
+
+    #[tokio::test]
+    async fn test_saturated_pool_queues_tasks() {
+        let pool = Arc::new(StoragePool::new(2).unwrap());
+        let (release, gate) = mpsc::channel::<()>();
+        let gate = Arc::new(std::sync::Mutex::new(gate));
+
+        // 占满两个工作线程,其余任务进入队列
+        let mut tasks = Vec::new();
+        for i in 0..5 {
+            let pool = pool.clone();
+            let gate = gate.clone();
+            tasks.push(tokio::spawn(async move {
+                pool.run(move || {
+                    gate.lock().unwrap().recv().unwrap();
+                    i
+                })
+                .await
+            }));
+        }
+        wait_for(&pool, |stats| stats.active == 2 && stats.queue_depth == 3).await;
+        assert_eq!(pool.stats().completed, 0);
+        assert!(pool.stats().peak_queue_depth >= 3);
+
+        for _ in 0..5 {
+            release.send(()).unwrap();
+        }
+        let mut results = Vec::new();
+        for task in tasks {
+            results.push(task.await.unwrap().unwrap());
+        }
+        results.sort();
+        assert_eq!(results, vec![0, 1, 2, 3, 4]);
+
+        let stats = pool.stats();
+        assert_eq!(stats.queue_depth, 0);
+        assert_eq!(stats.active, 0);
+        assert_eq!(stats.completed, 5);
+    }
+
+    #[tokio::test]
+    async fn test_queued_tasks_run_in_submission_order() {
+        let pool = Arc::new(StoragePool::new(1).unwrap());
+        let (release, gate) = mpsc::channel::<()>();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let blocker = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.run(move || gate.recv().unwrap()).await })
+        };
+        wait_for(&pool, |stats| stats.active == 1).await;
+
+        let mut tasks = Vec::new();
+        for i in 0..4 {
+            let submitter = pool.clone();
+            let order = order.clone();
+            tasks.push(tokio::spawn(async move { submitter.run(move || order.lock().push(i)).await }));
+            wait_for(&pool, |stats| stats.queue_depth == i + 1).await;
+        }
+
+        release.send(()).unwrap();
+        blocker.await.unwrap().unwrap();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        assert_eq!(*order.lock(), vec![0, 1, 2, 3]);
+        assert!(pool.stats().avg_wait_micros > 0);
+    }
+
+    #[tokio::test]
+    async fn test_panicked_task_and_shutdown() {
+        let pool = StoragePool::new(1).unwrap();
+        assert_eq!(pool.run(|| 39).await.unwrap(), 39);
+        // panic 的任务返回错误,工作线程继续服务
+        assert!(pool.run(|| panic!("boom")).await.is_err());
+        assert_eq!(pool.run(|| 1).await.unwrap(), 1);
+
+        pool.shutdown();
+        assert!(pool.run(|| 2).await.is_err());
+        assert_eq!(pool.stats().completed, 3);
+    }
+}
@@ -0,0 +1,160 @@
+//! 热点共享结构的多线程扩展性基准
+//!
+//! 每个基准在 1、4、16、64、128 个线程和本机核数个线程上执行同样的操作总量,比较每秒操作数随线程数的变化:
+//! - `collection_handle`: 并发获取集合实例(每条语句都会调用)
+//! - `list_indexes`: 并发读取集合的索引定义(每次写入都会调用)
+//! - `lru_cache`: 90% 读 10% 写的缓存访问,对比默认分片与单分片
+//!
+//! 运行: `cargo bench -p mikudb-storage --bench concurrency_bench`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mikudb_storage::cache::LruCache;
+use mikudb_storage::{
+    IndexDefinition, IndexField, IndexOrder, IndexType, KeyEncoding, OversizedKeyPolicy, StorageEngine, StorageOptions,
+};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 每次迭代所有线程合计执行的操作数
+const OPS_PER_ITER: u64 = 64 * 1024;
+
+const COLLECTIONS: usize = 16;
+
+fn thread_counts() -> Vec<usize> {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut counts = vec![1, 4, 16, 64, 128];
+    if !counts.contains(&cores) {
+        counts.push(cores);
+        counts.sort_unstable();
+    }
+    counts
+}
+
+/// # Brief
+/// 在 `threads` 个线程上合计执行 `ops` 次 `op`,返回总耗时
+fn run_parallel(threads: usize, ops: u64, op: impl Fn(u64) + Sync) -> Duration {
+    let per_thread = ops / threads as u64;
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        for t in 0..threads as u64 {
+            let op = &op;
+            scope.spawn(move || {
+                for i in 0..per_thread {
+                    op(t.wrapping_mul(0x9E37_79B9).wrapping_add(i));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn open_engine(dir: &tempfile::TempDir) -> Arc<StorageEngine> {
+    let engine = StorageEngine::open(StorageOptions {
+        data_dir: dir.path().to_path_buf(),
+        ..Default::default()
+    })
+    .unwrap();
+    for c in 0..COLLECTIONS {
+        let name = format!("c{}", c);
+        engine.create_collection(&name).unwrap();
+        engine
+            .indexes()
+            .create_index(IndexDefinition {
+                name: format!("{}_n", name),
+                collection: name,
+                fields: vec![IndexField {
+                    path: "n".to_string(),
+                    order: IndexOrder::Ascending,
+                }],
+                index_type: IndexType::BTree,
+                unique: false,
+                sparse: false,
+                ttl_seconds: None,
+                key_encoding: KeyEncoding::Memcomparable,
+                max_key_size: None,
+                oversized_keys: OversizedKeyPolicy::Reject,
+            })
+            .unwrap();
+    }
+    Arc::new(engine)
+}
+
+fn bench_engine_maps(c: &mut Criterion) {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = open_engine(&dir);
+    let names: Vec<String> = (0..COLLECTIONS).map(|c| format!("c{}", c)).collect();
+
+    let mut group = c.benchmark_group("collection_handle");
+    group.throughput(Throughput::Elements(OPS_PER_ITER));
+    for threads in thread_counts() {
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| {
+                        run_parallel(threads, OPS_PER_ITER, |i| {
+                            black_box(engine.get_collection(&names[i as usize % COLLECTIONS]).unwrap());
+                        })
+                    })
+                    .sum()
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("list_indexes");
+    group.throughput(Throughput::Elements(OPS_PER_ITER));
+    for threads in thread_counts() {
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| {
+                        run_parallel(threads, OPS_PER_ITER, |i| {
+                            black_box(engine.indexes().list_indexes(&names[i as usize % COLLECTIONS]));
+                        })
+                    })
+                    .sum()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_lru_cache(c: &mut Criterion) {
+    const KEYS: u64 = 100_000;
+    const CAPACITY: usize = 64 * 1024 * 1024;
+
+    let mut group = c.benchmark_group("lru_cache");
+    group.throughput(Throughput::Elements(OPS_PER_ITER));
+    let caches = [
+        ("sharded", LruCache::<u64, Vec<u8>>::new(CAPACITY)),
+        ("single_shard", LruCache::<u64, Vec<u8>>::with_shards(CAPACITY, 1)),
+    ];
+    for (label, cache) in &caches {
+        for key in 0..KEYS {
+            cache.insert(key, vec![0; 64], 64);
+        }
+        for threads in thread_counts() {
+            group.bench_with_input(BenchmarkId::new(*label, threads), &threads, |b, &threads| {
+                b.iter_custom(|iters| {
+                    (0..iters)
+                        .map(|_| {
+                            run_parallel(threads, OPS_PER_ITER, |i| {
+                                let key = i % KEYS;
+                                if i % 10 == 0 {
+                                    cache.insert(key, vec![0; 64], 64);
+                                } else {
+                                    black_box(cache.get(&key));
+                                }
+                            })
+                        })
+                        .sum()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_engine_maps, bench_lru_cache);
+criterion_main!(benches);
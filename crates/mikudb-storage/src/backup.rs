@@ -0,0 +1,609 @@
+//! 逻辑备份与恢复模块
+//!
+//! 在同一个 RocksDB 快照上导出所有集合和元数据,保证备份内容一致:
+//! - 普通集合的原始键值(文档、字段类型统计等)
+//! - 系统集合: 用户与角色 (`admin:*`)、`_` 开头的内部集合、`system.*` 集合
+//! - 元数据 CF(集合登记、模式选项、归档策略)和系统 CF
+//! - 序列 CF(序列定义和已发放的编号个数)
+//!
+//! 清单记录快照的 RocksDB 序列号,可以从该序列号之后的写入继续追赶。
+//!
+//! 备份可以排除系统集合;恢复可以只恢复元数据(系统集合、系统 CF 以及它们的元数据),
+//! 用于让重建的服务器拥有相同的用户和权限状态。恢复某个集合时先清空该集合再写入,
+//! 避免与新服务器自动创建的默认数据(例如默认管理员)混在一起。
+//!
+//! 外部路径上的归档集合位于独立的存储实例中,不包含在备份内。
+//! 需要更快的整库备份时可以使用物理快照,见 [`crate::snapshot`]。
+//!
+//! 备份目录布局:
+//! - `manifest.json`: 备份清单
+//! - `metadata.dat`: 元数据 CF
+//! - `system.dat`: 系统 CF(包含系统数据时)
+//! - `sequences.dat`: 序列 CF
+//! - `collections/<n>.dat`: 各集合的数据,序号与清单中的顺序一致
+//!
+//! `.dat` 文件由连续的记录组成,每条记录为 `键长度(u32 LE) 键 值长度(u32 LE) 值`。
+
+use crate::engine::{pinned_snapshot, StorageEngine, METADATA_CF, SYSTEM_CF};
+use crate::sequence::SEQUENCE_CF;
+use crate::snapshot::SNAPSHOT_MANIFEST_FILE;
+use crate::{StorageError, StorageResult};
+use rocksdb::{IteratorMode, WriteBatch};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tracing::info;
+
+/// 备份格式版本
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// 备份清单文件名
+const MANIFEST_FILE: &str = "manifest.json";
+/// 元数据 CF 导出文件名
+const METADATA_FILE: &str = "metadata.dat";
+/// 系统 CF 导出文件名
+const SYSTEM_FILE: &str = "system.dat";
+/// 序列 CF 导出文件名
+const SEQUENCES_FILE: &str = "sequences.dat";
+/// 集合数据目录
+const COLLECTIONS_DIR: &str = "collections";
+
+/// 恢复时每批写入的记录数
+const RESTORE_BATCH_SIZE: usize = 1024;
+
+/// # Brief
+/// 判断是否为系统集合
+///
+/// 用户与角色保存在 `admin` 数据库的集合中(`admin:users`),
+/// `_` 开头的集合由服务器内部使用(巡检报告、索引顾问等)。
+///
+/// # Arguments
+/// * `name` - 集合名称
+pub fn is_system_collection(name: &str) -> bool {
+    name.starts_with('_') || name.starts_with("admin:") || name.starts_with("system.")
+}
+
+/// # Brief
+/// 把语句中的备份路径解析为备份根目录下的路径
+///
+/// 服务器上 BACKUP / RESTORE 的路径来自客户端,只接受根目录下的相对路径,
+/// 避免读写数据目录或系统中的任意位置。
+///
+/// # Arguments
+/// * `root` - 备份根目录
+/// * `path` - 语句中的路径
+///
+/// # Returns
+/// `root` 下的路径;路径为空、是绝对路径或包含 `..` 时返回 InvalidArgument
+pub fn resolve_path(root: &Path, path: &str) -> StorageResult<PathBuf> {
+    let relative = Path::new(path);
+    if path.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(StorageError::InvalidArgument(format!(
+            "Backup path must be relative to the backup directory without '..': {}",
+            path
+        )));
+    }
+    Ok(root.join(relative))
+}
+
+/// 备份配置
+#[derive(Debug, Clone)]
+pub struct BackupOptions {
+    /// 是否包含系统集合和系统 CF(用户、角色等),默认包含
+    pub include_system: bool,
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
+        Self { include_system: true }
+    }
+}
+
+/// 恢复范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestoreScope {
+    /// 恢复备份中的全部内容
+    #[default]
+    All,
+    /// 只恢复系统集合、系统 CF 及其元数据,普通集合保持不变
+    Metadata,
+}
+
+/// 恢复配置
+#[derive(Debug, Clone, Default)]
+pub struct RestoreOptions {
+    /// 恢复范围
+    pub scope: RestoreScope,
+}
+
+/// 备份清单中的集合条目
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupCollection {
+    /// 集合名称
+    pub name: String,
+    /// 是否为系统集合
+    pub system: bool,
+    /// 导出的键值条数
+    pub entries: u64,
+    /// 数据文件(相对备份目录)
+    pub file: String,
+}
+
+/// 备份清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// 备份格式版本
+    pub version: u32,
+    /// 备份时间(RFC 3339)
+    pub created_at: String,
+    /// 是否包含系统数据
+    pub include_system: bool,
+    /// 集合列表
+    pub collections: Vec<BackupCollection>,
+    /// 元数据条数
+    pub metadata_entries: u64,
+    /// 系统 CF 条数
+    pub system_entries: u64,
+    /// 序列 CF 条数,早期备份没有序列文件时为 0
+    #[serde(default)]
+    pub sequence_entries: u64,
+    /// 备份快照的 RocksDB 序列号,早期备份为 0
+    #[serde(default)]
+    pub snapshot_sequence: u64,
+}
+
+impl BackupManifest {
+    /// # Brief
+    /// 读取备份目录中的清单
+    ///
+    /// # Arguments
+    /// * `dir` - 备份目录
+    pub fn load(dir: &Path) -> StorageResult<Self> {
+        if !dir.join(MANIFEST_FILE).exists() && dir.join(SNAPSHOT_MANIFEST_FILE).exists() {
+            return Err(StorageError::Internal(format!(
+                "{} is a snapshot, restore it offline into an empty data directory",
+                dir.display()
+            )));
+        }
+        let content = fs::read(dir.join(MANIFEST_FILE))?;
+        let manifest: Self = serde_json::from_slice(&content)
+            .map_err(|e| StorageError::Corruption(format!("Invalid backup manifest: {}", e)))?;
+        if manifest.version != BACKUP_FORMAT_VERSION {
+            return Err(StorageError::Corruption(format!(
+                "Unsupported backup format version {}",
+                manifest.version
+            )));
+        }
+        Ok(manifest)
+    }
+}
+
+/// 恢复结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RestoreReport {
+    /// 已恢复的集合
+    pub collections: Vec<String>,
+    /// 写入的集合键值条数
+    pub entries: u64,
+    /// 写入的元数据条数
+    pub metadata_entries: u64,
+    /// 写入的系统 CF 条数
+    pub system_entries: u64,
+    /// 写入的序列 CF 条数
+    pub sequence_entries: u64,
+}
+
+/// # Brief
+/// 创建备份
+///
+/// 所有数据从同一个快照读取,备份期间的写入不会出现在备份中。
+/// 目标目录不存在时自动创建,已有备份清单时拒绝覆盖。
+///
+/// # Arguments
+/// * `engine` - 存储引擎
+/// * `dir` - 备份目录
+/// * `options` - 备份配置
+///
+/// # Returns
+/// 备份清单
+pub fn create_backup(engine: &StorageEngine, dir: &Path, options: &BackupOptions) -> StorageResult<BackupManifest> {
+    if dir.join(MANIFEST_FILE).exists() {
+        return Err(StorageError::Internal(format!("Backup already exists at {}", dir.display())));
+    }
+    fs::create_dir_all(dir.join(COLLECTIONS_DIR))?;
+
+    let db = engine.db();
+    let (snapshot, sequence) = pinned_snapshot(db);
+
+    let mut names = engine.list_collections()?;
+    names.sort();
+
+    let mut collections = Vec::new();
+    for name in names {
+        let system = is_system_collection(&name);
+        if system && !options.include_system {
+            continue;
+        }
+        // 外部路径上的归档集合在本实例中没有对应的 CF
+        let Some(cf) = db.cf_handle(&name) else {
+            continue;
+        };
+        let file = format!("{}/{}.dat", COLLECTIONS_DIR, collections.len());
+        let entries = export(dir.join(&file), snapshot.iterator_cf(&cf, IteratorMode::Start))?;
+        collections.push(BackupCollection { name, system, entries, file });
+    }
+
+    let metadata_cf = db.cf_handle(METADATA_CF).ok_or_else(|| {
+        StorageError::Internal("Metadata CF not found".to_string())
+    })?;
+    let included: HashSet<&str> = collections.iter().map(|c| c.name.as_str()).collect();
+    let metadata = snapshot
+        .iterator_cf(&metadata_cf, IteratorMode::Start)
+        .filter(|item| match item {
+            // 不导出被排除集合的元数据
+            Ok((key, _)) => metadata_collection(key).map_or(true, |name| included.contains(name)),
+            Err(_) => true,
+        });
+    let metadata_entries = export(dir.join(METADATA_FILE), metadata)?;
+
+    let system_entries = if options.include_system {
+        let system_cf = db.cf_handle(SYSTEM_CF).ok_or_else(|| {
+            StorageError::Internal("System CF not found".to_string())
+        })?;
+        export(dir.join(SYSTEM_FILE), snapshot.iterator_cf(&system_cf, IteratorMode::Start))?
+    } else {
+        0
+    };
+
+    let sequence_cf = db.cf_handle(SEQUENCE_CF).ok_or_else(|| {
+        StorageError::Internal("Sequence CF not found".to_string())
+    })?;
+    let sequence_entries = export(dir.join(SEQUENCES_FILE), snapshot.iterator_cf(&sequence_cf, IteratorMode::Start))?;
+
+    let manifest = BackupManifest {
+        version: BACKUP_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        include_system: options.include_system,
+        collections,
+        metadata_entries,
+        system_entries,
+        sequence_entries,
+        snapshot_sequence: sequence,
+    };
+    let content = serde_json::to_vec_pretty(&manifest).map_err(|e| StorageError::Internal(e.to_string()))?;
+    fs::write(dir.join(MANIFEST_FILE), content)?;
+
+    info!(
+        "Backup written to {} ({} collections, system data {})",
+        dir.display(),
+        manifest.collections.len(),
+        if options.include_system { "included" } else { "excluded" }
+    );
+    Ok(manifest)
+}
+
+/// # Brief
+/// 从备份恢复
+///
+/// 每个被恢复的集合先清空再写入备份中的内容;只恢复元数据时普通集合和序列保持不变。
+/// 属于某个集合的元数据(`<前缀>:<集合名>`)只在该集合被恢复时写入。
+///
+/// # Arguments
+/// * `engine` - 存储引擎
+/// * `dir` - 备份目录
+/// * `options` - 恢复配置
+///
+/// # Returns
+/// 恢复结果
+pub fn restore_backup(engine: &StorageEngine, dir: &Path, options: &RestoreOptions) -> StorageResult<RestoreReport> {
+    let manifest = BackupManifest::load(dir)?;
+    let db = engine.db();
+    let mut report = RestoreReport::default();
+
+    for collection in &manifest.collections {
+        if options.scope == RestoreScope::Metadata && !collection.system {
+            continue;
+        }
+        let target = engine.get_or_create_collection(&collection.name)?;
+        let cf = db.cf_handle(&collection.name).ok_or_else(|| {
+            StorageError::CollectionNotFound(collection.name.clone())
+        })?;
+
+        clear_cf(db, &cf)?;
+        report.entries += import(&dir.join(&collection.file), |batch, key, value| batch.put_cf(&cf, key, value), db)?;
+        // 恢复的文档绕过了索引维护和文档缓存
+        target.invalidate_cache();
+        engine.indexes().rebuild_indexes(&target)?;
+        report.collections.push(collection.name.clone());
+    }
+
+    let restored: HashSet<&str> = report.collections.iter().map(String::as_str).collect();
+    let metadata_cf = db.cf_handle(METADATA_CF).ok_or_else(|| {
+        StorageError::Internal("Metadata CF not found".to_string())
+    })?;
+    report.metadata_entries = import(
+        &dir.join(METADATA_FILE),
+        |batch, key, value| {
+            let wanted = match metadata_collection(key) {
+                Some(name) => restored.contains(name),
+                None => options.scope == RestoreScope::All,
+            };
+            if wanted {
+                batch.put_cf(&metadata_cf, key, value);
+            }
+        },
+        db,
+    )?;
+
+    if manifest.include_system {
+        let system_cf = db.cf_handle(SYSTEM_CF).ok_or_else(|| {
+            StorageError::Internal("System CF not found".to_string())
+        })?;
+        clear_cf(db, &system_cf)?;
+        report.system_entries = import(&dir.join(SYSTEM_FILE), |batch, key, value| batch.put_cf(&system_cf, key, value), db)?;
+    }
+
+    let sequences_file = dir.join(SEQUENCES_FILE);
+    if options.scope == RestoreScope::All && sequences_file.exists() {
+        let sequence_cf = db.cf_handle(SEQUENCE_CF).ok_or_else(|| {
+            StorageError::Internal("Sequence CF not found".to_string())
+        })?;
+        clear_cf(db, &sequence_cf)?;
+        report.sequence_entries = import(&sequences_file, |batch, key, value| batch.put_cf(&sequence_cf, key, value), db)?;
+        engine.evict_sequences();
+    }
+
+    // 丢弃缓存的集合实例,下次访问时重新加载模式选项和统计
+    engine.evict_rollups();
+    for name in &report.collections {
+        engine.evict_collection(name);
+    }
+
+    info!(
+        "Restored {} collection(s) from {} ({:?})",
+        report.collections.len(),
+        dir.display(),
+        options.scope
+    );
+    Ok(report)
+}
+
+/// # Brief
+/// 元数据键所属的集合
+///
+/// 元数据键形如 `<前缀>:<集合名>`(`collection:`、`schema:`、`archive:`)。
+fn metadata_collection(key: &[u8]) -> Option<&str> {
+    std::str::from_utf8(key).ok()?.split_once(':').map(|(_, name)| name)
+}
+
+/// # Brief
+/// 把键值迭代器写入导出文件
+///
+/// # Returns
+/// 写入的记录数
+fn export<I>(path: impl AsRef<Path>, items: I) -> StorageResult<u64>
+where
+    I: Iterator<Item = Result<(Box<[u8]>, Box<[u8]>), rocksdb::Error>>,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut count = 0u64;
+    for item in items {
+        let (key, value) = item?;
+        write_record(&mut writer, &key, &value)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// # Brief
+/// 逐条读取导出文件并分批写入
+///
+/// # Arguments
+/// * `path` - 导出文件
+/// * `put` - 把一条记录加入写批次
+/// * `db` - RocksDB 实例
+///
+/// # Returns
+/// 读取的记录数
+fn import<F>(path: &Path, mut put: F, db: &rocksdb::DB) -> StorageResult<u64>
+where
+    F: FnMut(&mut WriteBatch, &[u8], &[u8]),
+{
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut batch = WriteBatch::default();
+    let mut count = 0u64;
+    while let Some((key, value)) = read_record(&mut reader)? {
+        put(&mut batch, &key, &value);
+        count += 1;
+        if batch.len() >= RESTORE_BATCH_SIZE {
+            db.write(std::mem::take(&mut batch))?;
+        }
+    }
+    if !batch.is_empty() {
+        db.write(batch)?;
+    }
+    Ok(count)
+}
+
+/// # Brief
+/// 删除 CF 中的所有键
+fn clear_cf(db: &rocksdb::DB, cf: &impl rocksdb::AsColumnFamilyRef) -> StorageResult<()> {
+    let mut batch = WriteBatch::default();
+    for item in db.iterator_cf(cf, IteratorMode::Start) {
+        let (key, _) = item?;
+        batch.delete_cf(cf, key);
+        if batch.len() >= RESTORE_BATCH_SIZE {
+            db.write(std::mem::take(&mut batch))?;
+        }
+    }
+    if !batch.is_empty() {
+        db.write(batch)?;
+    }
+    Ok(())
+}
+
+fn write_record(writer: &mut impl Write, key: &[u8], value: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(key.len() as u32).to_le_bytes())?;
+    writer.write_all(key)?;
+    writer.write_all(&(value.len() as u32).to_le_bytes())?;
+    writer.write_all(value)
+}
+
+/// # Brief
+/// 读取一条记录
+///
+/// # Returns
+/// 文件结束时返回 None,记录不完整时返回 Corruption
+fn read_record(reader: &mut impl Read) -> StorageResult<Option<(Vec<u8>, Vec<u8>)>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let key = read_bytes(reader, u32::from_le_bytes(len) as usize)?;
+    reader
+        .read_exact(&mut len)
+        .map_err(|_| StorageError::Corruption("Truncated backup record".to_string()))?;
+    let value = read_bytes(reader, u32::from_le_bytes(len) as usize)?;
+    Ok(Some((key, value)))
+}
+
+fn read_bytes(reader: &mut impl Read, len: usize) -> StorageResult<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| StorageError::Corruption("Truncated backup record".to_string()))?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::StorageOptions;
+    use crate::schema::SchemaOptions;
+    use mikudb_boml::Document;
+    use tempfile::tempdir;
+
+    fn open(dir: &Path) -> StorageEngine {
+        StorageEngine::open(StorageOptions {
+            data_dir: dir.to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn insert(engine: &StorageEngine, collection: &str, field: &str, value: &str) {
+        let mut doc = Document::new();
+        doc.insert(field, value);
+        engine.get_or_create_collection(collection).unwrap().insert(&mut doc).unwrap();
+    }
+
+    fn names(engine: &StorageEngine, collection: &str, field: &str) -> Vec<String> {
+        let mut names: Vec<String> = engine
+            .get_collection(collection)
+            .unwrap()
+            .find_all()
+            .unwrap()
+            .iter()
+            .filter_map(|d| d.get(field).and_then(|v| v.as_str()).map(String::from))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let root = Path::new("/var/lib/mikudb/backups");
+        assert_eq!(resolve_path(root, "daily/1").unwrap(), root.join("daily/1"));
+        assert_eq!(resolve_path(root, "./daily").unwrap(), root.join("./daily"));
+        for path in ["", "/etc", "../data", "daily/../../data"] {
+            assert!(matches!(resolve_path(root, path), Err(StorageError::InvalidArgument(_))), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_is_system_collection() {
+        assert!(is_system_collection("admin:users"));
+        assert!(is_system_collection("_scrub_report"));
+        assert!(is_system_collection("system.views"));
+        assert!(!is_system_collection("users"));
+        assert!(!is_system_collection("shop:orders"));
+    }
+
+    #[test]
+    fn test_backup_and_full_restore() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().join("backup");
+
+        let source = open(&dir.path().join("source"));
+        insert(&source, "admin:users", "username", "alice");
+        insert(&source, "orders", "item", "book");
+        insert(&source, "orders", "item", "pen");
+        source
+            .set_schema_options("orders", SchemaOptions { track_types: true, strict_types: true })
+            .unwrap();
+        source.create_sequence("order_no", 1000, 1).unwrap();
+        source.next_sequence_value("order_no").unwrap();
+
+        let manifest = create_backup(&source, &backup_dir, &BackupOptions::default()).unwrap();
+        assert!(manifest.include_system);
+        assert_eq!(manifest.collections.len(), 2);
+        assert!(create_backup(&source, &backup_dir, &BackupOptions::default()).is_err());
+
+        let target = open(&dir.path().join("target"));
+        insert(&target, "admin:users", "username", "admin");
+        let report = restore_backup(&target, &backup_dir, &RestoreOptions::default()).unwrap();
+
+        assert_eq!(report.collections.len(), 2);
+        assert_eq!(names(&target, "admin:users", "username"), vec!["alice"]);
+        assert_eq!(names(&target, "orders", "item"), vec!["book", "pen"]);
+        assert!(target.get_collection("orders").unwrap().schema_options().strict_types);
+        assert_eq!(target.next_sequence_value("order_no").unwrap(), 1001);
+    }
+
+    #[test]
+    fn test_backup_without_system() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().join("backup");
+
+        let source = open(&dir.path().join("source"));
+        insert(&source, "admin:users", "username", "alice");
+        insert(&source, "orders", "item", "book");
+
+        let options = BackupOptions { include_system: false };
+        let manifest = create_backup(&source, &backup_dir, &options).unwrap();
+        assert!(!manifest.include_system);
+        assert_eq!(manifest.collections.len(), 1);
+        assert_eq!(manifest.collections[0].name, "orders");
+
+        let target = open(&dir.path().join("target"));
+        restore_backup(&target, &backup_dir, &RestoreOptions::default()).unwrap();
+        let collections = target.list_collections().unwrap();
+        assert!(collections.contains(&"orders".to_string()));
+        assert!(!collections.contains(&"admin:users".to_string()));
+    }
+
+    #[test]
+    fn test_restore_metadata_only() {
+        let dir = tempdir().unwrap();
+        let backup_dir = dir.path().join("backup");
+
+        let source = open(&dir.path().join("source"));
+        insert(&source, "admin:users", "username", "alice");
+        insert(&source, "orders", "item", "book");
+        create_backup(&source, &backup_dir, &BackupOptions::default()).unwrap();
+
+        let target = open(&dir.path().join("target"));
+        insert(&target, "orders", "item", "lamp");
+        let options = RestoreOptions { scope: RestoreScope::Metadata };
+        let report = restore_backup(&target, &backup_dir, &options).unwrap();
+
+        assert_eq!(report.collections, vec!["admin:users".to_string()]);
+        assert_eq!(names(&target, "admin:users", "username"), vec!["alice"]);
+        assert_eq!(names(&target, "orders", "item"), vec!["lamp"]);
+    }
+}
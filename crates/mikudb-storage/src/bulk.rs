@@ -0,0 +1,137 @@
+//! 批量写入模块
+//!
+//! 一次提交多个插入、替换、删除操作:
+//! - 有序执行遇到第一个失败的操作即停止,之后的操作不执行
+//! - 无序执行跳过失败的操作继续执行其余操作
+//!
+//! 每个操作单独写入并维护索引和预聚合,批量整体不是原子的。
+//! 结果按操作下标给出每个操作的计数和失败原因。
+
+use mikudb_boml::Document;
+use mikudb_common::ObjectId;
+use serde::{Deserialize, Serialize};
+
+/// 批量写入中的单个操作
+#[derive(Debug, Clone)]
+pub enum WriteOperation {
+    /// 插入文档,没有 `_id` 时自动生成
+    Insert(Document),
+    /// 用新内容替换 `_id` 对应的文档,`upsert` 时文档不存在则插入
+    Replace {
+        id: ObjectId,
+        document: Document,
+        upsert: bool,
+    },
+    /// 删除 `_id` 对应的文档
+    Delete(ObjectId),
+}
+
+/// 单个操作的执行结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationResult {
+    /// 操作在批量中的下标
+    pub index: usize,
+    pub inserted: u64,
+    pub matched: u64,
+    pub modified: u64,
+    pub deleted: u64,
+    pub upserted: u64,
+    /// 插入或 upsert 产生的 `_id`,序列化为十六进制字符串
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "hex_ids")]
+    pub ids: Vec<ObjectId>,
+}
+
+impl OperationResult {
+    /// # Brief
+    /// 创建下标为 `index` 的空结果
+    pub fn new(index: usize) -> Self {
+        Self { index, ..Default::default() }
+    }
+
+    /// # Brief
+    /// 把另一个结果的计数和 `_id` 累加到当前结果
+    pub fn merge(&mut self, other: &OperationResult) {
+        self.inserted += other.inserted;
+        self.matched += other.matched;
+        self.modified += other.modified;
+        self.deleted += other.deleted;
+        self.upserted += other.upserted;
+        self.ids.extend_from_slice(&other.ids);
+    }
+}
+
+/// 失败操作的错误信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteError {
+    /// 失败操作在批量中的下标
+    pub index: usize,
+    /// 错误码,唯一键重复为 11000
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<u32>,
+    pub message: String,
+}
+
+/// 批量写入结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BulkWriteResult {
+    pub inserted: u64,
+    pub matched: u64,
+    pub modified: u64,
+    pub deleted: u64,
+    pub upserted: u64,
+    /// 成功操作的结果,按下标排列
+    pub results: Vec<OperationResult>,
+    /// 失败操作的错误,按下标排列
+    pub write_errors: Vec<WriteError>,
+}
+
+impl BulkWriteResult {
+    /// # Brief
+    /// 记录一个成功操作的结果并累加总数
+    pub fn record(&mut self, result: OperationResult) {
+        self.inserted += result.inserted;
+        self.matched += result.matched;
+        self.modified += result.modified;
+        self.deleted += result.deleted;
+        self.upserted += result.upserted;
+        self.results.push(result);
+    }
+
+    /// # Brief
+    /// 记录一个失败操作
+    pub fn record_error(&mut self, index: usize, error: &crate::StorageError) {
+        self.write_errors.push(WriteError {
+            index,
+            code: error.code(),
+            message: error.to_string(),
+        });
+    }
+
+    /// # Brief
+    /// 所有操作是否都成功
+    pub fn is_ok(&self) -> bool {
+        self.write_errors.is_empty()
+    }
+
+    /// # Brief
+    /// 写入的文档总数(插入、修改、删除、upsert)
+    pub fn affected(&self) -> u64 {
+        self.inserted + self.modified + self.deleted + self.upserted
+    }
+}
+
+mod hex_ids {
+    use mikudb_common::ObjectId;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(ids: &[ObjectId], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ids.iter().map(ObjectId::to_hex))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<ObjectId>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| ObjectId::from_hex(hex).map_err(D::Error::custom))
+            .collect()
+    }
+}
@@ -1,45 +1,113 @@
 //! 缓存模块
 //!
 //! 本模块实现多级缓存系统:
-//! - **LRU 缓存**: 通用 LRU 淘汰策略缓存
-//! - **文档缓存**: 缓存文档数据,提升热点数据访问性能
+//! - **LRU 缓存**: 通用缓存,默认按 LRU 淘汰,也可以按访问次数(LFU)淘汰
+//! - **文档缓存**: 按 (集合, `_id`) 缓存解码后的文档,`Collection::get` / `find_by_ids` 命中时不读取 RocksDB 也不解码
 //! - **查询缓存**: 缓存查询结果,减少重复查询开销
 //!
 //! 特性:
-//! - 线程安全:使用 DashMap 和 parking_lot::Mutex 保证并发访问
-//! - 容量控制:基于字节大小限制,自动淘汰最久未使用的条目
+//! - 线程安全:按键的哈希分片,每个分片独立加锁并维护自己的淘汰顺序,不同分片的访问互不阻塞
+//! - 容量控制:基于字节大小限制,各分片平分容量,按淘汰策略自动淘汰分片内的条目
 //! - 统计信息:记录命中率、缓存大小等指标
 //! - 集合失效:支持按集合批量失效缓存
+//! - 失效版本:每个分片记录失效次数,读取未命中后只在期间没有失效时写入缓存,不会缓存被并发写入覆盖的文档
 
-use dashmap::DashMap;
+use mikudb_boml::Document;
+use mikudb_common::ObjectId;
 use parking_lot::Mutex;
-use std::collections::VecDeque;
-use std::hash::Hash;
-use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// 每个分片至少分到的容量(字节)
+///
+/// 容量较小的缓存使用较少的分片,避免单个条目放不进分片;只有一个分片时是精确的 LRU。
+pub const MIN_SHARD_CAPACITY: usize = 64 * 1024;
+
+/// 默认分片数的上限
+const MAX_SHARDS: usize = 256;
+
+/// 缓存淘汰策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// 淘汰最久未使用的条目
+    #[default]
+    Lru,
+    /// 淘汰访问次数最少的条目,次数相同时淘汰最久未使用的
+    Lfu,
+}
+
+impl EvictionPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvictionPolicy::Lru => "lru",
+            EvictionPolicy::Lfu => "lfu",
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "lru" => Ok(EvictionPolicy::Lru),
+            "lfu" => Ok(EvictionPolicy::Lfu),
+            other => Err(format!("Unknown eviction policy: {} (expected lru or lfu)", other)),
+        }
+    }
+}
 
 /// LRU 缓存
 ///
-/// 实现 Least Recently Used (最近最少使用) 淘汰策略的线程安全缓存。
-/// 使用 DashMap 提供高并发读写能力,使用 VecDeque 维护访问顺序。
+/// 实现 Least Recently Used (最近最少使用) 淘汰策略的线程安全缓存,也可以按 [`EvictionPolicy::Lfu`] 淘汰。
+/// 按键的哈希分成若干分片,每个分片用一把锁保护键值表和淘汰顺序,
+/// 命中时只锁一个分片,淘汰顺序的调整是 O(log n)。
 pub struct LruCache<K, V>
 where
     K: Hash + Eq + Clone,
     V: Clone,
 {
-    /// 缓存数据:键 -> 缓存条目
-    map: DashMap<K, CacheEntry<V>>,
-    /// 访问顺序:队首为最久未使用,队尾为最近使用
-    order: Mutex<VecDeque<K>>,
+    /// 分片,数量为 2 的幂
+    shards: Box<[Mutex<LruShard<K, V>>]>,
+    /// 分片选择使用的哈希
+    hasher: RandomState,
     /// 容量限制(字节)
     capacity: usize,
-    /// 缓存命中次数
-    hits: AtomicU64,
-    /// 缓存未命中次数
-    misses: AtomicU64,
-    /// 当前缓存大小(字节)
-    size: AtomicUsize,
+    policy: EvictionPolicy,
 }
 
+/// LRU 分片
+struct LruShard<K, V> {
+    /// 缓存数据:键 -> 缓存条目
+    map: HashMap<K, CacheEntry<V>>,
+    /// 淘汰顺序:排序键 -> 键,排序键最小的最先淘汰
+    order: BTreeMap<Rank, K>,
+    /// 下一个访问序号
+    tick: u64,
+    /// 分片容量(字节)
+    capacity: usize,
+    /// 当前大小(字节)
+    size: usize,
+    hits: u64,
+    misses: u64,
+    policy: EvictionPolicy,
+    /// 失效次数,移除或清空条目时加一
+    generation: u64,
+}
+
+/// 淘汰顺序的排序键: (访问次数, 访问序号),LRU 时访问次数总为 0
+type Rank = (u64, u64);
+
 /// 缓存条目
 ///
 /// 封装缓存值和其占用的字节大小。
@@ -48,6 +116,66 @@ struct CacheEntry<V> {
     value: V,
     /// 条目大小(字节)
     size: usize,
+    /// 访问次数,插入时为 1
+    frequency: u64,
+    /// 在淘汰顺序中的排序键
+    rank: Rank,
+}
+
+impl<K, V> LruShard<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            map: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            capacity,
+            size: 0,
+            hits: 0,
+            misses: 0,
+            policy,
+            generation: 0,
+        }
+    }
+
+    /// 分配新的访问序号,返回访问次数为 `frequency` 时的排序键
+    fn next_rank(&mut self, frequency: u64) -> Rank {
+        self.tick += 1;
+        match self.policy {
+            EvictionPolicy::Lru => (0, self.tick),
+            EvictionPolicy::Lfu => (frequency, self.tick),
+        }
+    }
+
+    fn remove(&mut self, key: &K) -> Option<CacheEntry<V>> {
+        let entry = self.map.remove(key)?;
+        self.order.remove(&entry.rank);
+        self.size -= entry.size;
+        Some(entry)
+    }
+
+    fn put(&mut self, key: K, value: V, size: usize) {
+        let frequency = self.remove(&key).map_or(1, |old| old.frequency + 1);
+        let rank = self.next_rank(frequency);
+        self.order.insert(rank, key.clone());
+        self.map.insert(key, CacheEntry { value, size, frequency, rank });
+        self.size += size;
+        self.evict();
+    }
+
+    /// 按淘汰顺序淘汰条目直到不超过容量
+    fn evict(&mut self) {
+        while self.size > self.capacity {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.map.remove(&key) {
+                self.size -= entry.size;
+            }
+        }
+    }
 }
 
 impl<K, V> LruCache<K, V>
@@ -58,23 +186,55 @@ where
     /// # Brief
     /// 创建 LRU 缓存
     ///
+    /// 分片数按 CPU 核数和容量决定,见 [`MIN_SHARD_CAPACITY`]。
+    ///
     /// # Arguments
     /// * `capacity` - 缓存容量限制(字节)
     pub fn new(capacity: usize) -> Self {
+        Self::with_policy(capacity, EvictionPolicy::Lru)
+    }
+
+    /// # Brief
+    /// 创建使用指定淘汰策略的缓存,分片数与 `new` 相同
+    ///
+    /// # Arguments
+    /// * `capacity` - 缓存容量限制(字节)
+    /// * `policy` - 淘汰策略
+    pub fn with_policy(capacity: usize, policy: EvictionPolicy) -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let shards = (cores * 4).min(MAX_SHARDS).min(capacity / MIN_SHARD_CAPACITY);
+        Self::build(capacity, shards, policy)
+    }
+
+    /// # Brief
+    /// 创建指定分片数的 LRU 缓存
+    ///
+    /// # Arguments
+    /// * `capacity` - 缓存容量限制(字节),各分片平分
+    /// * `shards` - 分片数,向上取整为 2 的幂,至少为 1
+    pub fn with_shards(capacity: usize, shards: usize) -> Self {
+        Self::build(capacity, shards, EvictionPolicy::Lru)
+    }
+
+    fn build(capacity: usize, shards: usize, policy: EvictionPolicy) -> Self {
+        let shards = shards.max(1).next_power_of_two();
         Self {
-            map: DashMap::new(),
-            order: Mutex::new(VecDeque::new()),
+            shards: (0..shards).map(|_| Mutex::new(LruShard::new(capacity / shards, policy))).collect(),
+            hasher: RandomState::new(),
             capacity,
-            hits: AtomicU64::new(0),
-            misses: AtomicU64::new(0),
-            size: AtomicUsize::new(0),
+            policy,
         }
     }
 
+    fn shard(&self, key: &K) -> &Mutex<LruShard<K, V>> {
+        let hash = self.hasher.hash_one(key) as usize;
+        &self.shards[hash & (self.shards.len() - 1)]
+    }
+
     /// # Brief
     /// 获取缓存值
     ///
-    /// 命中时更新访问顺序,将键移到队尾(标记为最近使用)。
+    /// 命中时更新淘汰顺序,将键标记为最近使用并增加访问次数。
     ///
     /// # Arguments
     /// * `key` - 缓存键
@@ -82,16 +242,27 @@ where
     /// # Returns
     /// 缓存值(如果存在)
     pub fn get(&self, key: &K) -> Option<V> {
-        if let Some(entry) = self.map.get(key) {
-            // 命中,增加命中计数
-            self.hits.fetch_add(1, Ordering::Relaxed);
-            // 更新访问顺序
-            self.touch(key.clone());
-            Some(entry.value.clone())
-        } else {
-            // 未命中,增加未命中计数
-            self.misses.fetch_add(1, Ordering::Relaxed);
-            None
+        let mut shard = self.shard(key).lock();
+        let frequency = match shard.map.get(key) {
+            Some(entry) => entry.frequency + 1,
+            None => 0,
+        };
+        let rank = shard.next_rank(frequency);
+        let shard = &mut *shard;
+        match shard.map.get_mut(key) {
+            Some(entry) => {
+                shard.hits += 1;
+                // 更新淘汰顺序
+                shard.order.remove(&entry.rank);
+                shard.order.insert(rank, key.clone());
+                entry.frequency = frequency;
+                entry.rank = rank;
+                Some(entry.value.clone())
+            }
+            None => {
+                shard.misses += 1;
+                None
+            }
         }
     }
 
@@ -99,124 +270,128 @@ where
     /// 插入缓存条目
     ///
     /// 如果键已存在,更新值并调整大小。
-    /// 如果插入后超过容量限制,自动淘汰最久未使用的条目。
+    /// 如果插入后超过分片容量,按淘汰策略自动淘汰分片内的条目。
     ///
     /// # Arguments
     /// * `key` - 缓存键
     /// * `value` - 缓存值
     /// * `size` - 条目大小(字节)
     pub fn insert(&self, key: K, value: V, size: usize) {
-        let entry = CacheEntry { value, size };
-
-        if let Some(old) = self.map.insert(key.clone(), entry) {
-            // 键已存在,减去旧值的大小
-            self.size.fetch_sub(old.size, Ordering::Relaxed);
-        } else {
-            // 新键,添加到访问顺序队列
-            let mut order = self.order.lock();
-            order.push_back(key.clone());
-        }
-
-        // 增加新值的大小
-        self.size.fetch_add(size, Ordering::Relaxed);
+        self.shard(&key).lock().put(key, value, size);
+    }
 
-        // 如果超过容量限制,淘汰最久未使用的条目
-        while self.size.load(Ordering::Relaxed) > self.capacity {
-            self.evict_one();
-        }
+    /// # Brief
+    /// 获取键所在分片的失效版本
+    ///
+    /// 在读取数据源之前获取,之后传给 `insert_if_current`。
+    pub fn generation(&self, key: &K) -> u64 {
+        self.shard(key).lock().generation
     }
 
     /// # Brief
-    /// 移除缓存条目
+    /// 键所在分片自 `generation` 之后没有失效过时插入条目
     ///
     /// # Arguments
     /// * `key` - 缓存键
+    /// * `value` - 缓存值
+    /// * `size` - 条目大小(字节)
+    /// * `generation` - 读取数据源之前 `generation` 返回的版本
     ///
     /// # Returns
-    /// 移除的值(如果存在)
-    pub fn remove(&self, key: &K) -> Option<V> {
-        if let Some((_, entry)) = self.map.remove(key) {
-            // 减小缓存大小
-            self.size.fetch_sub(entry.size, Ordering::Relaxed);
-
-            // 从访问顺序队列中移除
-            let mut order = self.order.lock();
-            order.retain(|k| k != key);
-
-            Some(entry.value)
-        } else {
-            None
+    /// 插入时返回 true,期间有失效时不插入并返回 false
+    pub fn insert_if_current(&self, key: K, value: V, size: usize, generation: u64) -> bool {
+        let mut shard = self.shard(&key).lock();
+        if shard.generation != generation {
+            return false;
         }
-    }
-
-    pub fn contains(&self, key: &K) -> bool {
-        self.map.contains_key(key)
-    }
-
-    pub fn clear(&self) {
-        self.map.clear();
-        self.order.lock().clear();
-        self.size.store(0, Ordering::Relaxed);
+        shard.put(key, value, size);
+        true
     }
 
     /// # Brief
-    /// 更新访问顺序
+    /// 移除缓存条目
     ///
-    /// 将键移到队尾,标记为最近使用。
+    /// 无论条目是否存在,都使所在分片的失效版本加一。
     ///
     /// # Arguments
     /// * `key` - 缓存键
-    fn touch(&self, key: K) {
-        let mut order = self.order.lock();
-        // 从队列中移除键
-        order.retain(|k| k != &key);
-        // 添加到队尾(最近使用)
-        order.push_back(key);
+    ///
+    /// # Returns
+    /// 移除的值(如果存在)
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let mut shard = self.shard(key).lock();
+        shard.generation += 1;
+        shard.remove(key).map(|entry| entry.value)
     }
 
     /// # Brief
-    /// 淘汰一个条目
+    /// 移除所有满足条件的条目
     ///
-    /// 移除队首(最久未使用)的条目。
-    fn evict_one(&self) {
-        let key = {
-            let mut order = self.order.lock();
-            // 弹出队首(最久未使用)
-            order.pop_front()
-        };
-
-        if let Some(key) = key {
-            if let Some((_, entry)) = self.map.remove(&key) {
-                // 减小缓存大小
-                self.size.fetch_sub(entry.size, Ordering::Relaxed);
+    /// 逐个分片加锁处理,不会同时阻塞所有分片。
+    pub fn remove_if(&self, mut predicate: impl FnMut(&K) -> bool) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock();
+            shard.generation += 1;
+            let keys: Vec<K> = shard.map.keys().filter(|key| predicate(key)).cloned().collect();
+            for key in keys {
+                shard.remove(&key);
             }
         }
     }
 
+    pub fn contains(&self, key: &K) -> bool {
+        self.shard(key).lock().map.contains_key(key)
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.lock();
+            shard.generation += 1;
+            shard.map.clear();
+            shard.order.clear();
+            shard.size = 0;
+        }
+    }
+
     pub fn len(&self) -> usize {
-        self.map.len()
+        self.shards.iter().map(|shard| shard.lock().map.len()).sum()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.map.is_empty()
+        self.len() == 0
     }
 
     pub fn size(&self) -> usize {
-        self.size.load(Ordering::Relaxed)
+        self.shards.iter().map(|shard| shard.lock().size).sum()
     }
 
     pub fn capacity(&self) -> usize {
         self.capacity
     }
 
+    /// 分片数
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
     /// # Brief
     /// 获取缓存统计信息
     ///
     /// # Returns
     /// 缓存统计信息(命中率、大小、条目数等)
     pub fn stats(&self) -> CacheStats {
-        let hits = self.hits.load(Ordering::Relaxed);
-        let misses = self.misses.load(Ordering::Relaxed);
+        let (mut hits, mut misses, mut size, mut entries) = (0, 0, 0, 0);
+        for shard in self.shards.iter() {
+            let shard = shard.lock();
+            hits += shard.hits;
+            misses += shard.misses;
+            size += shard.size;
+            entries += shard.map.len();
+        }
         let total = hits + misses;
         // 计算命中率
         let hit_rate = if total > 0 {
@@ -229,9 +404,10 @@ where
             hits,
             misses,
             hit_rate,
-            size: self.size.load(Ordering::Relaxed),
+            size,
             capacity: self.capacity,
-            entries: self.map.len(),
+            entries,
+            policy: self.policy,
         }
     }
 }
@@ -251,25 +427,38 @@ pub struct CacheStats {
     pub capacity: usize,
     /// 缓存条目数
     pub entries: usize,
+    /// 淘汰策略
+    pub policy: EvictionPolicy,
 }
 
 /// 文档缓存
 ///
-/// 缓存文档数据,使用 "集合名:键" 作为缓存键。
+/// 按 (集合名, `_id`) 缓存解码后的文档,多个集合共用一个容量。
+/// 集合在写入完成后使被修改的文档失效,读取未命中时通过失效版本避免缓存过期的文档。
 pub struct DocumentCache {
-    /// 内部 LRU 缓存
-    cache: LruCache<Vec<u8>, Vec<u8>>,
+    /// 内部缓存
+    cache: LruCache<(Arc<str>, ObjectId), Document>,
 }
 
 impl DocumentCache {
     /// # Brief
-    /// 创建文档缓存
+    /// 创建按 LRU 淘汰的文档缓存
     ///
     /// # Arguments
     /// * `capacity_bytes` - 缓存容量限制(字节)
     pub fn new(capacity_bytes: usize) -> Self {
+        Self::with_policy(capacity_bytes, EvictionPolicy::Lru)
+    }
+
+    /// # Brief
+    /// 创建使用指定淘汰策略的文档缓存
+    ///
+    /// # Arguments
+    /// * `capacity_bytes` - 缓存容量限制(字节)
+    /// * `policy` - 淘汰策略
+    pub fn with_policy(capacity_bytes: usize, policy: EvictionPolicy) -> Self {
         Self {
-            cache: LruCache::new(capacity_bytes),
+            cache: LruCache::with_policy(capacity_bytes, policy),
         }
     }
 
@@ -278,32 +467,39 @@ impl DocumentCache {
     ///
     /// # Arguments
     /// * `collection` - 集合名
-    /// * `key` - 文档键
+    /// * `id` - 文档 ID
     ///
     /// # Returns
-    /// 文档数据(如果存在)
-    pub fn get(&self, collection: &str, key: &[u8]) -> Option<Vec<u8>> {
-        let cache_key = Self::make_key(collection, key);
-        self.cache.get(&cache_key)
+    /// 文档(如果已缓存)
+    pub fn get(&self, collection: &Arc<str>, id: &ObjectId) -> Option<Document> {
+        self.cache.get(&(collection.clone(), *id))
+    }
+
+    /// # Brief
+    /// 获取文档所在分片的失效版本,读取 RocksDB 之前调用
+    pub fn generation(&self, collection: &Arc<str>, id: &ObjectId) -> u64 {
+        self.cache.generation(&(collection.clone(), *id))
     }
 
     /// # Brief
-    /// 插入文档到缓存
+    /// 缓存从 RocksDB 读取的文档
     ///
     /// # Arguments
     /// * `collection` - 集合名
-    /// * `key` - 文档键
-    /// * `value` - 文档数据
-    pub fn insert(&self, collection: &str, key: &[u8], value: Vec<u8>) {
-        let cache_key = Self::make_key(collection, key);
-        // 缓存大小 = 键大小 + 值大小
-        let size = cache_key.len() + value.len();
-        self.cache.insert(cache_key, value, size);
+    /// * `doc` - 文档,没有 `_id` 时不缓存
+    /// * `size` - 文档编码后的字节数,用于容量统计
+    /// * `generation` - 读取之前 `generation` 返回的版本,期间文档被修改时不缓存
+    pub fn insert(&self, collection: &Arc<str>, doc: &Document, size: usize, generation: u64) {
+        if let Some(id) = doc.id() {
+            let size = collection.len() + 12 + size;
+            self.cache.insert_if_current((collection.clone(), *id), doc.clone(), size, generation);
+        }
     }
 
-    pub fn remove(&self, collection: &str, key: &[u8]) {
-        let cache_key = Self::make_key(collection, key);
-        self.cache.remove(&cache_key);
+    /// # Brief
+    /// 使一个文档失效
+    pub fn remove(&self, collection: &Arc<str>, id: &ObjectId) {
+        self.cache.remove(&(collection.clone(), *id));
     }
 
     /// # Brief
@@ -314,24 +510,7 @@ impl DocumentCache {
     /// # Arguments
     /// * `collection` - 集合名
     pub fn invalidate_collection(&self, collection: &str) {
-        let prefix = format!("{}:", collection);
-        // 收集所有匹配前缀的键
-        let keys_to_remove: Vec<Vec<u8>> = self
-            .cache
-            .map
-            .iter()
-            .filter(|entry| {
-                entry
-                    .key()
-                    .starts_with(prefix.as_bytes())
-            })
-            .map(|entry| entry.key().clone())
-            .collect();
-
-        // 批量删除
-        for key in keys_to_remove {
-            self.cache.remove(&key);
-        }
+        self.cache.remove_if(|(name, _)| name.as_ref() == collection);
     }
 
     pub fn clear(&self) {
@@ -342,23 +521,8 @@ impl DocumentCache {
         self.cache.stats()
     }
 
-    /// # Brief
-    /// 构造缓存键
-    ///
-    /// 格式: "集合名:文档键"
-    ///
-    /// # Arguments
-    /// * `collection` - 集合名
-    /// * `key` - 文档键
-    ///
-    /// # Returns
-    /// 缓存键(字节数组)
-    fn make_key(collection: &str, key: &[u8]) -> Vec<u8> {
-        let mut cache_key = Vec::with_capacity(collection.len() + 1 + key.len());
-        cache_key.extend_from_slice(collection.as_bytes());
-        cache_key.push(b':');  // 使用冒号分隔集合名和键
-        cache_key.extend_from_slice(key);
-        cache_key
+    pub fn policy(&self) -> EvictionPolicy {
+        self.cache.policy()
     }
 }
 
@@ -449,13 +613,73 @@ mod tests {
     }
 
     #[test]
-    fn test_document_cache() {
-        let cache = DocumentCache::new(1024);
+    fn test_sharded_cache() {
+        let cache: LruCache<u64, u64> = LruCache::with_shards(16 * 100, 16);
+        assert_eq!(cache.shard_count(), 16);
+        for i in 0..1000 {
+            cache.insert(i, i, 10);
+        }
+        // 每个分片最多容纳 10 个条目,最近插入的条目保留
+        assert!(cache.len() <= 160);
+        assert!(cache.size() <= cache.capacity());
+        assert_eq!(cache.get(&999), Some(999));
+
+        std::thread::scope(|scope| {
+            for t in 0..8u64 {
+                let cache = &cache;
+                scope.spawn(move || {
+                    for i in 0..1000 {
+                        cache.insert(t * 1000 + i, i, 10);
+                        cache.get(&(t * 1000 + i / 2));
+                    }
+                });
+            }
+        });
+        assert!(cache.size() <= cache.capacity());
+        assert_eq!(cache.stats().entries, cache.len());
+    }
 
-        cache.insert("test", b"doc1", vec![1, 2, 3]);
-        assert_eq!(cache.get("test", b"doc1"), Some(vec![1, 2, 3]));
+    #[test]
+    fn test_lfu_eviction() {
+        let cache: LruCache<String, String> = LruCache::with_policy(25, EvictionPolicy::Lfu);
 
-        cache.remove("test", b"doc1");
-        assert_eq!(cache.get("test", b"doc1"), None);
+        cache.insert("key1".to_string(), "value1".to_string(), 10);
+        cache.insert("key2".to_string(), "value2".to_string(), 10);
+        cache.get(&"key1".to_string());
+        cache.insert("key3".to_string(), "value3".to_string(), 10);
+
+        // key2 只访问过一次,先于更早插入但访问更多的 key1 被淘汰
+        assert!(cache.get(&"key1".to_string()).is_some());
+        assert!(cache.get(&"key2".to_string()).is_none());
+        assert_eq!(cache.stats().policy, EvictionPolicy::Lfu);
+    }
+
+    #[test]
+    fn test_document_cache() {
+        let cache = DocumentCache::new(1024);
+        let test: Arc<str> = Arc::from("test");
+        let other: Arc<str> = Arc::from("other");
+
+        let mut doc = Document::new();
+        doc.insert("name", "miku");
+        let id = *doc.id().unwrap();
+        cache.insert(&test, &doc, 16, cache.generation(&test, &id));
+        assert_eq!(cache.get(&test, &id).unwrap().get_str("name"), Some("miku"));
+
+        cache.remove(&test, &id);
+        assert!(cache.get(&test, &id).is_none());
+
+        // 读取期间文档失效,读到的旧文档不会进入缓存
+        let generation = cache.generation(&test, &id);
+        cache.remove(&test, &id);
+        cache.insert(&test, &doc, 16, generation);
+        assert!(cache.get(&test, &id).is_none());
+
+        cache.insert(&test, &doc, 16, cache.generation(&test, &id));
+        cache.insert(&other, &doc, 16, cache.generation(&other, &id));
+        cache.invalidate_collection("test");
+        assert!(cache.get(&test, &id).is_none());
+        assert!(cache.get(&other, &id).is_some());
+        assert_eq!(cache.stats().hits, 2);
     }
 }
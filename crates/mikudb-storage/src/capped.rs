@@ -0,0 +1,76 @@
+//! 固定大小集合模块
+//!
+//! 固定大小集合限制总大小和/或文档数,超出时按插入顺序删除最早的文档,适合日志和操作日志:
+//! - 限制持久化在元数据中 (`capped:{name}`),只能在创建集合时指定
+//! - 插入顺序即 `_id` 顺序;自动生成的 `_id` 以创建时间开头,显式指定较小 `_id` 的文档会被优先删除
+//! - 大小按文档的存储字节数计算,单个文档不能超过总大小限制
+
+use serde::{Deserialize, Serialize};
+
+/// 固定大小集合限制在元数据 CF 中的键前缀
+pub(crate) const CAPPED_KEY_PREFIX: &str = "capped:";
+
+/// 固定大小集合的限制
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CappedOptions {
+    /// 文档存储字节数之和的上限
+    pub max_size: Option<u64>,
+    /// 文档数上限
+    pub max_documents: Option<u64>,
+}
+
+impl CappedOptions {
+    pub(crate) fn metadata_key(collection: &str) -> String {
+        format!("{}{}", CAPPED_KEY_PREFIX, collection)
+    }
+
+    /// # Brief
+    /// 检查限制是否有效
+    ///
+    /// # Returns
+    /// 没有任何限制或限制为 0 时返回错误说明
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.max_size, self.max_documents) {
+            (None, None) => Err("Capped collection needs SIZE or MAX".to_string()),
+            (Some(0), _) => Err("Capped collection SIZE must be positive".to_string()),
+            (_, Some(0)) => Err("Capped collection MAX must be positive".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// # Brief
+    /// 给定的文档数和总大小是否超出限制
+    pub fn exceeded(&self, usage: &CappedUsage) -> bool {
+        self.max_size.is_some_and(|max| usage.size > max)
+            || self.max_documents.is_some_and(|max| usage.count > max)
+    }
+}
+
+/// 固定大小集合的当前用量
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CappedUsage {
+    /// 文档数
+    pub count: u64,
+    /// 文档存储字节数之和
+    pub size: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeded() {
+        let options = CappedOptions {
+            max_size: Some(100),
+            max_documents: Some(3),
+        };
+        assert!(!options.exceeded(&CappedUsage { count: 3, size: 100 }));
+        assert!(options.exceeded(&CappedUsage { count: 4, size: 10 }));
+        assert!(options.exceeded(&CappedUsage { count: 1, size: 101 }));
+
+        assert!(CappedOptions::default().validate().is_err());
+        assert!(CappedOptions { max_size: Some(0), max_documents: None }.validate().is_err());
+        assert!(CappedOptions { max_size: None, max_documents: Some(1) }.validate().is_ok());
+    }
+}
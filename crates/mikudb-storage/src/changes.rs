@@ -0,0 +1,292 @@
+//! 变更流模块
+//!
+//! 记录集合文档的写入,供进程内的订阅者(如 `mikudb_core::CacheLoader`)增量同步:
+//! - 每次写入记录一个事件: 集合名、文档 ID、变更类型,不复制文档内容,订阅者按 ID 回读
+//! - 事件按写入顺序编号,编号即恢复令牌: 从某个令牌继续可以拿到其后的全部事件
+//! - 只在内存中保留最近的事件,被淘汰的令牌无法继续,订阅者需要重新拉取快照
+//! - 无法逐个列出文档的写入(按时间范围删除、清空、删除集合、从备份恢复)记为 `Invalidate`
+//! - 令牌从打开引擎时的微秒时间戳开始,重启前保存的令牌会判定为过期而不是误用
+//! - 写入可以附带元数据(发起节点、应用标签、请求 ID),只记录在事件中,不写入文档;
+//!   同步程序据此过滤掉自己写入的变更,避免双向同步形成回环
+
+use crate::{StorageError, StorageResult};
+use mikudb_common::ObjectId;
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 默认保留的事件数量
+pub const DEFAULT_CHANGE_CAPACITY: usize = 65536;
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// 插入文档
+    Insert,
+    /// 更新文档(包括 upsert 和 `+=` 增量)
+    Update,
+    /// 删除文档
+    Delete,
+    /// 集合中任意文档都可能已改变,订阅者需要重新拉取快照
+    Invalidate,
+}
+
+/// 元数据每个字段的最大字节数
+pub const MAX_WRITE_METADATA_LEN: usize = 256;
+
+thread_local! {
+    /// 当前线程上正在执行的写入附带的元数据
+    static CURRENT_METADATA: RefCell<Option<Arc<WriteMetadata>>> = const { RefCell::new(None) };
+}
+
+/// 写入附带的元数据
+///
+/// 由客户端随请求提供,记录在变更事件和集群复制日志中,不写入文档本身。
+/// 复制日志使用 bincode 编码,字段不能按取值省略。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteMetadata {
+    /// 发起写入的节点
+    #[serde(default)]
+    pub origin: Option<String>,
+    /// 应用标签,如同步程序的名称
+    #[serde(default)]
+    pub app: Option<String>,
+    /// 客户端的请求 ID
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+impl WriteMetadata {
+    /// # Brief
+    /// 是否没有任何字段
+    pub fn is_empty(&self) -> bool {
+        self.origin.is_none() && self.app.is_none() && self.request_id.is_none()
+    }
+
+    /// # Brief
+    /// 检查字段长度
+    ///
+    /// # Returns
+    /// 任一字段超过 `MAX_WRITE_METADATA_LEN` 字节时返回 `InvalidArgument`
+    pub fn validate(&self) -> StorageResult<()> {
+        for (name, value) in [("origin", &self.origin), ("app", &self.app), ("request_id", &self.request_id)] {
+            if value.as_ref().is_some_and(|v| v.len() > MAX_WRITE_METADATA_LEN) {
+                return Err(StorageError::InvalidArgument(format!(
+                    "Write metadata field '{}' exceeds {} bytes",
+                    name, MAX_WRITE_METADATA_LEN
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// # Brief
+    /// 在附带元数据的上下文中执行写入
+    ///
+    /// `f` 在当前线程上产生的所有变更事件都带有该元数据,返回后恢复之前的上下文。
+    ///
+    /// # Arguments
+    /// * `metadata` - 写入元数据,None 表示不附带
+    /// * `f` - 执行写入的闭包
+    pub fn scope<R>(metadata: Option<Arc<WriteMetadata>>, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<Arc<WriteMetadata>>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_METADATA.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+
+        let previous = CURRENT_METADATA.with(|current| current.replace(metadata));
+        let _restore = Restore(previous);
+        f()
+    }
+
+    /// # Brief
+    /// 当前线程上正在执行的写入附带的元数据
+    pub fn current() -> Option<Arc<WriteMetadata>> {
+        CURRENT_METADATA.with(|current| current.borrow().clone())
+    }
+}
+
+/// 变更事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeEvent {
+    /// 恢复令牌,单调递增
+    pub token: u64,
+    /// 集合名称
+    pub collection: String,
+    /// 文档 ID,`Invalidate` 时为 None
+    pub id: Option<ObjectId>,
+    /// 变更类型
+    pub kind: ChangeKind,
+    /// 写入附带的元数据
+    pub metadata: Option<Arc<WriteMetadata>>,
+}
+
+struct ChangeBuffer {
+    events: VecDeque<ChangeEvent>,
+    /// 最后一个已分配的令牌
+    last_token: u64,
+}
+
+/// 变更流
+///
+/// 固定容量的事件环形缓冲区,写入方追加事件,订阅者按令牌读取并可阻塞等待新事件。
+pub struct ChangeStream {
+    buffer: Mutex<ChangeBuffer>,
+    notify: Condvar,
+    capacity: usize,
+}
+
+impl ChangeStream {
+    /// # Brief
+    /// 创建变更流
+    ///
+    /// # Arguments
+    /// * `capacity` - 保留的事件数量,至少为 1
+    pub fn new(capacity: usize) -> Self {
+        let epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        Self {
+            buffer: Mutex::new(ChangeBuffer {
+                events: VecDeque::new(),
+                last_token: epoch,
+            }),
+            notify: Condvar::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// # Brief
+    /// 获取当前令牌,从它继续可以拿到之后的所有写入
+    pub fn current_token(&self) -> u64 {
+        self.buffer.lock().last_token
+    }
+
+    /// # Brief
+    /// 追加一个事件并唤醒等待的订阅者,事件带有当前线程的写入元数据
+    pub(crate) fn record(&self, collection: &str, id: Option<ObjectId>, kind: ChangeKind) {
+        let metadata = WriteMetadata::current();
+        let mut buffer = self.buffer.lock();
+        buffer.last_token += 1;
+        let token = buffer.last_token;
+        if buffer.events.len() == self.capacity {
+            buffer.events.pop_front();
+        }
+        buffer.events.push_back(ChangeEvent {
+            token,
+            collection: collection.to_string(),
+            id,
+            kind,
+            metadata,
+        });
+        drop(buffer);
+        self.notify.notify_all();
+    }
+
+    /// # Brief
+    /// 读取令牌之后的事件,没有新事件时最多等待 `timeout`
+    ///
+    /// # Arguments
+    /// * `token` - 恢复令牌,只返回编号更大的事件
+    /// * `collection` - 只返回该集合的事件,None 表示全部
+    /// * `timeout` - 没有新事件时的最长等待时间
+    ///
+    /// # Returns
+    /// 令牌之后的事件(超时时为空);令牌之后的事件已被淘汰时返回 `ResumeTokenExpired`
+    pub fn changes_since(
+        &self,
+        token: u64,
+        collection: Option<&str>,
+        timeout: Duration,
+    ) -> StorageResult<Vec<ChangeEvent>> {
+        let mut buffer = self.buffer.lock();
+        if buffer.last_token == token {
+            self.notify.wait_for(&mut buffer, timeout);
+        }
+
+        let oldest = buffer.events.front().map_or(buffer.last_token + 1, |e| e.token);
+        if token > buffer.last_token || token + 1 < oldest {
+            return Err(StorageError::ResumeTokenExpired(token));
+        }
+
+        let skip = (token + 1 - oldest) as usize;
+        Ok(buffer
+            .events
+            .iter()
+            .skip(skip)
+            .filter(|e| collection.map_or(true, |name| e.collection == name))
+            .cloned()
+            .collect())
+    }
+}
+
+impl Default for ChangeStream {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHANGE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_and_expire() {
+        let stream = ChangeStream::new(3);
+        let start = stream.current_token();
+        let id = ObjectId::new();
+
+        stream.record("users", Some(id), ChangeKind::Insert);
+        stream.record("orders", None, ChangeKind::Invalidate);
+        let events = stream.changes_since(start, Some("users"), Duration::ZERO).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, Some(id));
+        assert_eq!(events[0].kind, ChangeKind::Insert);
+
+        let resumed = stream.changes_since(events[0].token, None, Duration::ZERO).unwrap();
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].kind, ChangeKind::Invalidate);
+        assert!(stream.changes_since(stream.current_token(), None, Duration::from_millis(10)).unwrap().is_empty());
+
+        stream.record("users", Some(id), ChangeKind::Update);
+        stream.record("users", Some(id), ChangeKind::Delete);
+        assert!(matches!(
+            stream.changes_since(start, None, Duration::ZERO),
+            Err(StorageError::ResumeTokenExpired(_))
+        ));
+        assert_eq!(stream.changes_since(start + 1, None, Duration::ZERO).unwrap().len(), 3);
+        assert!(stream.changes_since(start - 1_000_000, None, Duration::ZERO).is_err());
+    }
+
+    #[test]
+    fn test_write_metadata_scope() {
+        let stream = ChangeStream::new(8);
+        let start = stream.current_token();
+        let metadata = Arc::new(WriteMetadata {
+            app: Some("sync".to_string()),
+            ..Default::default()
+        });
+
+        WriteMetadata::scope(Some(metadata.clone()), || {
+            stream.record("users", Some(ObjectId::new()), ChangeKind::Insert);
+            // 嵌套的作用域结束后恢复外层的元数据
+            WriteMetadata::scope(None, || stream.record("users", None, ChangeKind::Invalidate));
+            stream.record("users", Some(ObjectId::new()), ChangeKind::Delete);
+        });
+        stream.record("users", Some(ObjectId::new()), ChangeKind::Update);
+
+        let events = stream.changes_since(start, None, Duration::ZERO).unwrap();
+        let tagged: Vec<_> = events.iter().map(|e| e.metadata.as_deref().and_then(|m| m.app.as_deref())).collect();
+        assert_eq!(tagged, vec![Some("sync"), None, Some("sync"), None]);
+
+        let long = WriteMetadata { request_id: Some("x".repeat(MAX_WRITE_METADATA_LEN + 1)), ..Default::default() };
+        assert!(long.validate().is_err());
+        assert!(metadata.validate().is_ok());
+    }
+}
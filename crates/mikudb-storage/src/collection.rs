@@ -1,14 +1,51 @@
 //! 集合模块
 //!
-//! 提供文档集合的 CRUD 操作，包括批量操作和迭代器支持。
-
+//! 提供文档集合的 CRUD 操作，包括批量操作、迭代器支持和按创建时间的范围删除。
+//! 数值字段的 `+=` 可以通过合并算子只写入增量,见 [`crate::merge`]。
+//! 由存储引擎创建的集合把每次写入记录到变更流,见 [`crate::changes`]。
+//! 较大的文档可以按 `StorageOptions::document_compression` 以 LZ4/Zstd 压缩后写入。
+//! `find_all_projected` 只解码投影需要的字段,宽文档上避免解码整个文档。
+//! `lock_for_modify` 让只更新一个文档的读取-修改-写入在同一集合上依次执行。
+//! `scan_snapshot` 在固定的 RocksDB 快照上遍历文档并给出快照序列号,供导出和复制初始化使用。
+//! 启用 WAL 时每次写入先作为一个事务组提交到 WAL,再应用到 RocksDB,见 [`crate::wal`]。
+//! 存在活跃读快照时写入同时保存文档的前像,`get_as_of`/`find_all_as_of` 按快照读取,见 [`crate::mvcc`]。
+//! 固定大小集合在插入后按插入顺序删除最早的文档,直到回到限制以内,见 [`crate::capped`]。
+//! `insert_many_indexed` 把一批文档和它们的索引项放入同一个 WriteBatch 原子写入。
+//! 配置文档缓存后 `get` / `find_by_ids` 先查缓存,每次写入完成后使被修改的文档失效,见 [`crate::cache::DocumentCache`]。
+//! 每个集合隐式拥有 `_id` 上的唯一索引: 插入在按 `_id` 分段的锁内检查文档是否存在,
+//! 并发插入相同 `_id` 时只有一个成功,其余返回 `StorageError::DuplicateKey`。
+
+use crate::cache::DocumentCache;
+use crate::capped::{CappedOptions, CappedUsage};
+use crate::compaction::{self, CompactionReport};
+use crate::index::IndexEngine;
+use crate::changes::{ChangeKind, ChangeStream};
+use crate::engine::pinned_snapshot;
+use crate::merge::{self, RULE_UPDATE_INC};
+use crate::mvcc::{ReadSnapshot, VersionStore, VersionedWrite};
+use crate::wal::{RecordType, WalRecord, WriteAheadLog};
+use crate::schema::{FieldSummary, SchemaOptions, SchemaRegistry, ValidationDetail, SEED_SAMPLE_SIZE};
 use crate::{StorageError, StorageResult};
-use mikudb_boml::{codec, BomlValue, Document};
+use mikudb_boml::{codec, BomlValue, Document, DocumentCompression};
 use mikudb_common::ObjectId;
-use parking_lot::RwLock;
-use rocksdb::{BoundColumnFamily, IteratorMode, ReadOptions, WriteBatch, WriteOptions, DB};
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use rocksdb::{BoundColumnFamily, Direction, IteratorMode, ReadOptions, Snapshot, WriteBatch, WriteOptions, DB};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// 批量读取时每次 multi_get 的键数量
+///
+/// 过大的批次会一次性固定大量 block cache 数据,过小则无法摊薄调用开销。
+pub const MULTI_GET_CHUNK_SIZE: usize = 256;
+
+/// `_id` 锁的分段数,不同 `_id` 的插入只在落到同一分段时互相等待
+const ID_LOCK_STRIPES: usize = 64;
+
+/// `_id` 隐式唯一索引在唯一键重复错误中的名称
+pub const ID_INDEX_NAME: &str = "_id_";
 
 /// 文档集合
 ///
@@ -17,6 +54,24 @@ pub struct Collection {
     name: String,
     db: Arc<DB>,
     stats: RwLock<CollectionStats>,
+    schema_options: RwLock<SchemaOptions>,
+    schema: RwLock<SchemaRegistry>,
+    changes: Option<Arc<ChangeStream>>,
+    compression: DocumentCompression,
+    modify_lock: Mutex<()>,
+    wal: Option<Arc<WriteAheadLog>>,
+    versions: Option<Arc<VersionStore>>,
+    capped: Option<CappedOptions>,
+    /// 固定大小集合的用量,第一次插入时扫描得到,之后随插入和淘汰增量维护
+    capped_usage: Mutex<Option<CappedUsage>>,
+    /// 更新或删除改变了用量,下次插入时重新扫描
+    capped_stale: AtomicBool,
+    /// 按 `_id` 分段的锁,插入和 upsert 从检查文档是否存在到写入完成期间持有
+    id_locks: Box<[Mutex<()>]>,
+    /// 解码后的文档缓存,与引擎的其他集合共用
+    doc_cache: Option<Arc<DocumentCache>>,
+    /// 文档缓存键中的集合名
+    cache_name: Arc<str>,
 }
 
 #[derive(Debug, Default)]
@@ -42,9 +97,268 @@ impl Collection {
     /// 新的 Collection 实例
     pub fn new(name: String, db: Arc<DB>) -> Self {
         Self {
+            cache_name: Arc::from(name.as_str()),
             name,
             db,
             stats: RwLock::new(CollectionStats::default()),
+            schema_options: RwLock::new(SchemaOptions::default()),
+            schema: RwLock::new(SchemaRegistry::default()),
+            changes: None,
+            compression: DocumentCompression::None,
+            modify_lock: Mutex::new(()),
+            wal: None,
+            versions: None,
+            capped: None,
+            capped_usage: Mutex::new(None),
+            capped_stale: AtomicBool::new(false),
+            id_locks: (0..ID_LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+            doc_cache: None,
+        }
+    }
+
+    /// # Brief
+    /// 把之后的写入记录到变更流
+    pub(crate) fn with_change_stream(mut self, changes: Arc<ChangeStream>) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
+    /// # Brief
+    /// 设置新写入文档的压缩算法
+    pub(crate) fn with_compression(mut self, compression: DocumentCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// # Brief
+    /// 之后的写入先提交到 WAL 再应用
+    pub(crate) fn with_wal(mut self, wal: Option<Arc<WriteAheadLog>>) -> Self {
+        self.wal = wal;
+        self
+    }
+
+    /// # Brief
+    /// 存在活跃读快照时为之后的写入保存前像
+    pub(crate) fn with_versions(mut self, versions: Arc<VersionStore>) -> Self {
+        self.versions = Some(versions);
+        self
+    }
+
+    /// # Brief
+    /// 通过共用的文档缓存读取单个文档
+    pub(crate) fn with_document_cache(mut self, cache: Option<Arc<DocumentCache>>) -> Self {
+        self.doc_cache = cache;
+        self
+    }
+
+    /// # Brief
+    /// 使集合在文档缓存中的全部文档失效,绕过集合直接写入列族(如恢复备份)后调用
+    pub(crate) fn invalidate_cache(&self) {
+        if let Some(cache) = &self.doc_cache {
+            cache.invalidate_collection(&self.name);
+        }
+    }
+
+    /// # Brief
+    /// 设置固定大小集合的限制
+    pub(crate) fn with_capped(mut self, capped: Option<CappedOptions>) -> Self {
+        self.capped = capped;
+        self
+    }
+
+    /// 固定大小集合的限制,普通集合为 None
+    pub fn capped_options(&self) -> Option<CappedOptions> {
+        self.capped
+    }
+
+    /// # Brief
+    /// 固定大小集合在写入前锁定用量,普通集合返回 None
+    ///
+    /// 插入在 `begin_write` 之前获取,淘汰时再次开始写入不会与读快照的门控交错加锁。
+    fn lock_capped(&self) -> Option<MutexGuard<'_, Option<CappedUsage>>> {
+        self.capped.map(|_| self.capped_usage.lock())
+    }
+
+    /// # Brief
+    /// 更新或删除后标记固定大小集合的用量需要重新扫描
+    fn forget_capped_usage(&self) {
+        if self.capped.is_some() {
+            self.capped_stale.store(true, Ordering::Release);
+        }
+    }
+
+    /// # Brief
+    /// 固定大小集合不接受超过总大小限制的单个文档
+    fn check_capped_size(&self, size: usize) -> StorageResult<()> {
+        match self.capped.and_then(|capped| capped.max_size) {
+            Some(max) if size as u64 > max => Err(StorageError::InvalidArgument(format!(
+                "Document of {} bytes exceeds the size of capped collection {} ({} bytes)",
+                size, self.name, max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// # Brief
+    /// 插入后按插入顺序删除最早的文档,直到回到限制以内
+    ///
+    /// 与 `delete_created_before` 一样,被淘汰文档的索引项不会同步删除。
+    ///
+    /// # Arguments
+    /// * `usage` - `lock_capped` 返回的用量,插入期间一直持有
+    /// * `added` - 本次插入的 (文档数, 字节数)
+    ///
+    /// # Returns
+    /// 淘汰的文档数量
+    fn evict_capped(&self, mut usage: MutexGuard<'_, Option<CappedUsage>>, added: CappedUsage) -> StorageResult<u64> {
+        let Some(options) = self.capped else {
+            return Ok(0);
+        };
+        let cf = self.cf()?;
+        let cached = usage.take().filter(|_| !self.capped_stale.swap(false, Ordering::AcqRel));
+        let mut current = match cached {
+            Some(cached) => CappedUsage {
+                count: cached.count + added.count,
+                size: cached.size + added.size,
+            },
+            // 扫描结果已包含本次插入的文档
+            None => self.db.prefix_iterator_cf(&cf, [b'd']).try_fold(CappedUsage::default(), |usage, item| {
+                let (_, value) = item?;
+                Ok::<_, StorageError>(CappedUsage {
+                    count: usage.count + 1,
+                    size: usage.size + value.len() as u64,
+                })
+            })?,
+        };
+        if !options.exceeded(&current) {
+            *usage = Some(current);
+            return Ok(0);
+        }
+
+        let versioned = self.begin_write();
+        let mut batch = WriteBatch::default();
+        let mut records = Vec::new();
+        let mut evicted = Vec::new();
+        for item in self.db.prefix_iterator_cf(&cf, [b'd']) {
+            if !options.exceeded(&current) {
+                break;
+            }
+            let (key, value) = item?;
+            let Some(id) = Self::id_from_key(&key) else {
+                continue;
+            };
+            versioned.save(&mut batch, &self.name, &id, Some(&value))?;
+            batch.delete_cf(&cf, &key);
+            records.push(WalRecord::new_delete(0, &self.name, key.to_vec()));
+            current.count -= 1;
+            current.size = current.size.saturating_sub(value.len() as u64);
+            evicted.push(id);
+        }
+        self.write(batch, records)?;
+        for id in &evicted {
+            self.record_change(Some(*id), ChangeKind::Delete);
+        }
+        *usage = Some(current);
+
+        let count = evicted.len() as u64;
+        let mut stats = self.stats.write();
+        stats.doc_count = stats.doc_count.saturating_sub(count);
+        stats.delete_count += count;
+
+        debug!("Evicted {} documents from capped collection {}", count, self.name);
+        Ok(count)
+    }
+
+    /// # Brief
+    /// 开始一次写入,返回的守卫在读取修改前的文档到写入完成期间持有
+    fn begin_write(&self) -> VersionedWrite<'_> {
+        match &self.versions {
+            Some(versions) => versions.begin_write(),
+            None => VersionedWrite::untracked(),
+        }
+    }
+
+    /// # Brief
+    /// 原子地写入一批修改
+    ///
+    /// 启用 WAL 时 `records` 与 `batch` 作为一个事务组提交,否则直接写入 RocksDB。
+    ///
+    /// # Arguments
+    /// * `batch` - RocksDB 修改
+    /// * `records` - 描述同一修改的 WAL 记录
+    fn write(&self, batch: WriteBatch, records: Vec<WalRecord>) -> StorageResult<()> {
+        // 写入完成后才使缓存失效,读取未命中时据失效版本丢弃写入前读到的文档
+        let invalidated: Option<Vec<Option<ObjectId>>> = self.doc_cache.as_ref().map(|_| {
+            records
+                .iter()
+                .map(|record| match record.record_type {
+                    RecordType::DeleteRange => None,
+                    _ => Self::id_from_key(&record.key),
+                })
+                .collect()
+        });
+        match &self.wal {
+            Some(wal) => {
+                wal.commit(records, batch)?;
+            }
+            None => {
+                let mut write_opts = WriteOptions::default();
+                write_opts.set_sync(false);
+                self.db.write_opt(batch, &write_opts)?;
+            }
+        }
+        if let (Some(cache), Some(invalidated)) = (&self.doc_cache, invalidated) {
+            for id in invalidated {
+                match id {
+                    Some(id) => cache.remove(&self.cache_name, &id),
+                    None => cache.invalidate_collection(&self.name),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// # Brief
+    /// 获取集合的修改锁
+    ///
+    /// 只更新一个文档的 UPDATE(FIND AND MODIFY)在选取文档到写入完成期间持有该锁,
+    /// 同一集合上的这类更新依次执行,不会选中同一个文档。普通写入不获取该锁。
+    pub fn lock_for_modify(&self) -> MutexGuard<'_, ()> {
+        self.modify_lock.lock()
+    }
+
+    /// # Brief
+    /// 锁定一组 `_id` 所在的分段
+    ///
+    /// 分段按序号升序加锁,同时插入多个文档的写入之间不会死锁。
+    /// 必须在 `lock_capped` 和 `begin_write` 之前获取。
+    ///
+    /// # Arguments
+    /// * `ids` - 要写入的文档 ID
+    ///
+    /// # Returns
+    /// 各分段的锁守卫,写入完成后释放
+    fn lock_ids(&self, ids: &[ObjectId]) -> Vec<MutexGuard<'_, ()>> {
+        let mut stripes: Vec<usize> = ids
+            .iter()
+            .map(|id| (xxh3_64(id.as_bytes()) % ID_LOCK_STRIPES as u64) as usize)
+            .collect();
+        stripes.sort_unstable();
+        stripes.dedup();
+        stripes.into_iter().map(|stripe| self.id_locks[stripe].lock()).collect()
+    }
+
+    fn duplicate_id(&self, id: &ObjectId) -> StorageError {
+        StorageError::DuplicateKey {
+            collection: self.name.clone(),
+            index: ID_INDEX_NAME.to_string(),
+            key: format!("{{ _id: {} }}", id),
+        }
+    }
+
+    fn record_change(&self, id: Option<ObjectId>, kind: ChangeKind) {
+        if let Some(changes) = &self.changes {
+            changes.record(&self.name, id, kind);
         }
     }
 
@@ -52,6 +366,19 @@ impl Collection {
         &self.name
     }
 
+    /// # Brief
+    /// 手动压缩集合的列族,回收已删除和被覆盖的文档占用的空间
+    ///
+    /// 只压缩集合本身;连同索引一起压缩见 `StorageEngine::compact_collection`。
+    ///
+    /// # Returns
+    /// 压缩前后的 SST 文件大小
+    pub fn compact(&self) -> StorageResult<CompactionReport> {
+        compaction::timed(&self.name, |report| {
+            compaction::compact_column_family(&self.db, &self.name, report)
+        })
+    }
+
     fn cf(&self) -> StorageResult<Arc<BoundColumnFamily>> {
         self.db
             .cf_handle(&self.name)
@@ -84,71 +411,136 @@ impl Collection {
     /// * `doc` - 要插入的文档（会自动生成 ID）
     ///
     /// # Returns
-    /// 成功返回文档的 ObjectId，如果文档已存在则返回错误
+    /// 成功返回文档的 ObjectId，`_id` 已存在时返回 `StorageError::DuplicateKey`
     pub fn insert(&self, doc: &mut Document) -> StorageResult<ObjectId> {
         let id = *doc.ensure_id();
         let key = Self::doc_key(&id);
 
         let cf = self.cf()?;
+        let _id_lock = self.lock_ids(std::slice::from_ref(&id));
+        let capped = self.lock_capped();
+        let versioned = self.begin_write();
 
-        let existing = self.db.get_cf(&cf, &key)?;
-        if existing.is_some() {
-            return Err(StorageError::DocumentExists(id.to_string()));
+        if self.db.get_pinned_cf(&cf, &key)?.is_some() {
+            return Err(self.duplicate_id(&id));
         }
 
-        let value = codec::encode_document(&doc.to_boml_value())?;
+        self.check_types(std::slice::from_ref(doc))?;
 
-        let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(false);
+        let value = codec::encode_document_with(&doc.to_boml_value(), self.compression)?;
+        self.check_capped_size(value.len())?;
 
-        self.db.put_cf_opt(&cf, &key, &value, &write_opts)?;
+        let mut batch = WriteBatch::default();
+        versioned.save(&mut batch, &self.name, &id, None)?;
+        batch.put_cf(&cf, &key, &value);
+        let value_len = value.len() as u64;
+        self.write(batch, vec![WalRecord::new_insert(0, &self.name, key, value)])?;
+
+        self.record_types(std::slice::from_ref(doc));
+        self.record_change(Some(id), ChangeKind::Insert);
 
         let mut stats = self.stats.write();
         stats.doc_count += 1;
-        stats.total_size += value.len() as u64;
+        stats.total_size += value_len;
         stats.insert_count += 1;
+        drop(stats);
 
         trace!("Inserted document {} into {}", id, self.name);
+        drop(versioned);
+        if let Some(capped) = capped {
+            self.evict_capped(capped, CappedUsage { count: 1, size: value_len })?;
+        }
         Ok(id)
     }
 
     /// 批量插入文档
     ///
     /// # Brief
-    /// 使用 WriteBatch 批量插入多个文档，性能更高
+    /// 使用 WriteBatch 批量插入多个文档，性能更高。
+    /// 任何一个 `_id` 已存在或在本批中重复时整批都不写入。
     ///
     /// # Arguments
     /// * `docs` - 要插入的文档切片
     ///
     /// # Returns
-    /// 成功返回所有文档的 ObjectId 向量
+    /// 成功返回所有文档的 ObjectId 向量，`_id` 重复时返回 `StorageError::DuplicateKey`
     pub fn insert_many(&self, docs: &mut [Document]) -> StorageResult<Vec<ObjectId>> {
+        self.insert_batch(docs, None)
+    }
+
+    /// 批量插入文档并维护索引
+    ///
+    /// # Brief
+    /// 与 `insert_many` 相同,但文档和它们在集合全部 BTree、哈希索引上的索引项在同一个
+    /// WriteBatch 中原子写入:违反唯一索引或 `_id` 重复时文档和索引项都不写入,不需要撤销。
+    ///
+    /// # Arguments
+    /// * `docs` - 要插入的文档切片
+    /// * `indexes` - 集合所在引擎的索引
+    ///
+    /// # Returns
+    /// 成功返回所有文档的 ObjectId 向量
+    pub fn insert_many_indexed(&self, docs: &mut [Document], indexes: &IndexEngine) -> StorageResult<Vec<ObjectId>> {
+        self.insert_batch(docs, Some(indexes))
+    }
+
+    fn insert_batch(&self, docs: &mut [Document], indexes: Option<&IndexEngine>) -> StorageResult<Vec<ObjectId>> {
         let cf = self.cf()?;
+        self.check_types(docs)?;
+        let ids: Vec<ObjectId> = docs.iter_mut().map(|doc| *doc.ensure_id()).collect();
+        let mut seen = HashSet::with_capacity(ids.len());
+        if let Some(id) = ids.iter().find(|id| !seen.insert(**id)) {
+            return Err(self.duplicate_id(id));
+        }
+
+        let _id_lock = self.lock_ids(&ids);
+        let capped = self.lock_capped();
+        let versioned = self.begin_write();
+        for chunk in ids.chunks(MULTI_GET_CHUNK_SIZE) {
+            let keys = chunk.iter().map(|id| (&cf, Self::doc_key(id)));
+            for (id, existing) in chunk.iter().zip(self.db.multi_get_cf(keys)) {
+                if existing?.is_some() {
+                    return Err(self.duplicate_id(id));
+                }
+            }
+        }
+
         let mut batch = WriteBatch::default();
-        let mut ids = Vec::with_capacity(docs.len());
+        let mut records = Vec::with_capacity(docs.len());
         let mut total_size = 0u64;
 
-        for doc in docs.iter_mut() {
-            let id = *doc.ensure_id();
-            let key = Self::doc_key(&id);
-            let value = codec::encode_document(&doc.to_boml_value())?;
+        for (doc, id) in docs.iter().zip(&ids) {
+            let key = Self::doc_key(id);
+            let value = codec::encode_document_with(&doc.to_boml_value(), self.compression)?;
+            self.check_capped_size(value.len())?;
 
+            versioned.save(&mut batch, &self.name, id, None)?;
             batch.put_cf(&cf, &key, &value);
             total_size += value.len() as u64;
-            ids.push(id);
+            records.push(WalRecord::new_insert(0, &self.name, key, value));
+        }
+        if let Some(indexes) = indexes {
+            indexes.stage_documents(&self.name, docs, &mut batch)?;
         }
 
-        let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(false);
-
-        self.db.write_opt(batch, &write_opts)?;
+        self.write(batch, records)?;
+        self.record_types(docs);
+        for id in &ids {
+            self.record_change(Some(*id), ChangeKind::Insert);
+        }
 
         let mut stats = self.stats.write();
         stats.doc_count += ids.len() as u64;
         stats.total_size += total_size;
         stats.insert_count += ids.len() as u64;
+        drop(stats);
 
         debug!("Inserted {} documents into {}", ids.len(), self.name);
+        drop(versioned);
+        if let Some(capped) = capped {
+            let added = CappedUsage { count: ids.len() as u64, size: total_size };
+            self.evict_capped(capped, added)?;
+        }
         Ok(ids)
     }
 
@@ -163,6 +555,16 @@ impl Collection {
     /// # Returns
     /// `Some(Document)` 如果文档存在，否则 `None`
     pub fn get(&self, id: &ObjectId) -> StorageResult<Option<Document>> {
+        let generation = match &self.doc_cache {
+            Some(cache) => {
+                if let Some(doc) = cache.get(&self.cache_name, id) {
+                    return Ok(Some(doc));
+                }
+                cache.generation(&self.cache_name, id)
+            }
+            None => 0,
+        };
+
         let cf = self.cf()?;
         let key = Self::doc_key(id);
 
@@ -173,6 +575,9 @@ impl Collection {
             Some(data) => {
                 let value = codec::decode_document(&data)?;
                 let doc = Document::from_boml_value(value)?;
+                if let Some(cache) = &self.doc_cache {
+                    cache.insert(&self.cache_name, &doc, data.len(), generation);
+                }
                 Ok(Some(doc))
             }
             None => Ok(None),
@@ -193,18 +598,21 @@ impl Collection {
     pub fn update(&self, id: &ObjectId, doc: &Document) -> StorageResult<()> {
         let cf = self.cf()?;
         let key = Self::doc_key(id);
+        let versioned = self.begin_write();
 
         let existing = self.db.get_cf(&cf, &key)?;
         if existing.is_none() {
             return Err(StorageError::DocumentNotFound(id.to_string()));
         }
 
-        let value = codec::encode_document(&doc.to_boml_value())?;
-
-        let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(false);
+        let value = codec::encode_document_with(&doc.to_boml_value(), self.compression)?;
 
-        self.db.put_cf_opt(&cf, &key, &value, &write_opts)?;
+        let mut batch = WriteBatch::default();
+        versioned.save(&mut batch, &self.name, id, existing.as_deref())?;
+        batch.put_cf(&cf, &key, &value);
+        self.write(batch, vec![WalRecord::new_update(0, &self.name, key, value)])?;
+        self.record_change(Some(*id), ChangeKind::Update);
+        self.forget_capped_usage();
 
         let mut stats = self.stats.write();
         stats.update_count += 1;
@@ -213,6 +621,66 @@ impl Collection {
         Ok(())
     }
 
+    /// 增加文档的数值字段
+    ///
+    /// # Brief
+    /// 写入一个合并操作数而不是整个文档,由合并算子在读取时把增量累加到文档上。
+    /// 并发的增量互不覆盖。写入前用借用解码检查字段类型,与 `+=` 的校验规则一致:
+    /// 字段不存在时从 0 开始,字段或增量不是数值时返回 SchemaViolation,整数结果溢出时返回 Overflow。
+    ///
+    /// # Arguments
+    /// * `id` - 文档的 ObjectId
+    /// * `deltas` - 顶层字段及其增量(Int32、Int64 或 Float64)
+    ///
+    /// # Returns
+    /// 文档存在返回 `true`,不存在返回 `false`
+    pub fn increment(&self, id: &ObjectId, deltas: &[(String, BomlValue)]) -> StorageResult<bool> {
+        let cf = self.cf()?;
+        let key = Self::doc_key(id);
+        let versioned = self.begin_write();
+
+        let Some(data) = self.db.get_pinned_cf(&cf, &key)? else {
+            return Ok(false);
+        };
+        let payload = codec::document_payload(&data)?;
+        let current = codec::decode_borrowed(&payload)?;
+        let mut details = Vec::new();
+        for (field, delta) in deltas {
+            let current = current.get(field).map_or(BomlValue::Int64(0), |value| value.to_boml_value());
+            if merge::add_numeric(&current, delta).is_some() {
+                continue;
+            }
+            if merge::is_numeric(&current) && merge::is_numeric(delta) {
+                return Err(StorageError::Overflow(format!("{} += {} on field '{}'", current, delta, field)));
+            }
+            // 字段本身不是数值时报告字段类型,否则报告增量的类型
+            let actual = if merge::is_numeric(&current) { delta } else { &current };
+            details.push(ValidationDetail::new(field, "number", actual.type_name(), RULE_UPDATE_INC));
+        }
+        if !details.is_empty() {
+            return Err(StorageError::SchemaViolation {
+                collection: self.name.clone(),
+                details,
+            });
+        }
+
+        let operand = merge::encode_increments(deltas)?;
+        let mut batch = WriteBatch::default();
+        versioned.save(&mut batch, &self.name, id, Some(&data))?;
+        drop(data);
+
+        batch.merge_cf(&cf, &key, &operand);
+        self.write(batch, vec![WalRecord::new_merge(0, &self.name, key, operand)])?;
+        self.record_change(Some(*id), ChangeKind::Update);
+        self.forget_capped_usage();
+
+        let mut stats = self.stats.write();
+        stats.update_count += 1;
+
+        trace!("Incremented {} field(s) of document {} in {}", deltas.len(), id, self.name);
+        Ok(true)
+    }
+
     /// 插入或更新文档
     ///
     /// # Brief
@@ -228,13 +696,25 @@ impl Collection {
         let cf = self.cf()?;
         let key = Self::doc_key(&id);
 
-        let value = codec::encode_document(&doc.to_boml_value())?;
-
-        let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(false);
+        let value = codec::encode_document_with(&doc.to_boml_value(), self.compression)?;
+        self.check_capped_size(value.len())?;
 
+        let _id_lock = self.lock_ids(std::slice::from_ref(&id));
+        let capped = self.lock_capped();
+        let versioned = self.begin_write();
         let existing = self.db.get_cf(&cf, &key)?;
-        self.db.put_cf_opt(&cf, &key, &value, &write_opts)?;
+        let mut batch = WriteBatch::default();
+        versioned.save(&mut batch, &self.name, &id, existing.as_deref())?;
+        batch.put_cf(&cf, &key, &value);
+        let value_len = value.len() as u64;
+        let record = if existing.is_some() {
+            WalRecord::new_update(0, &self.name, key, value)
+        } else {
+            WalRecord::new_insert(0, &self.name, key, value)
+        };
+        self.write(batch, vec![record])?;
+        let kind = if existing.is_some() { ChangeKind::Update } else { ChangeKind::Insert };
+        self.record_change(Some(id), kind);
 
         let mut stats = self.stats.write();
         if existing.is_some() {
@@ -243,8 +723,17 @@ impl Collection {
             stats.doc_count += 1;
             stats.insert_count += 1;
         }
-        stats.total_size += value.len() as u64;
-
+        stats.total_size += value_len;
+        drop(stats);
+
+        drop(versioned);
+        match (capped, existing) {
+            (Some(_), Some(_)) => self.forget_capped_usage(),
+            (Some(capped), None) => {
+                self.evict_capped(capped, CappedUsage { count: 1, size: value_len })?;
+            }
+            (None, _) => {}
+        }
         Ok(id)
     }
 
@@ -261,16 +750,19 @@ impl Collection {
     pub fn delete(&self, id: &ObjectId) -> StorageResult<bool> {
         let cf = self.cf()?;
         let key = Self::doc_key(id);
+        let versioned = self.begin_write();
 
         let existing = self.db.get_cf(&cf, &key)?;
         if existing.is_none() {
             return Ok(false);
         }
 
-        let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(false);
-
-        self.db.delete_cf_opt(&cf, &key, &write_opts)?;
+        let mut batch = WriteBatch::default();
+        versioned.save(&mut batch, &self.name, id, existing.as_deref())?;
+        batch.delete_cf(&cf, &key);
+        self.write(batch, vec![WalRecord::new_delete(0, &self.name, key)])?;
+        self.record_change(Some(*id), ChangeKind::Delete);
+        self.forget_capped_usage();
 
         let mut stats = self.stats.write();
         stats.doc_count = stats.doc_count.saturating_sub(1);
@@ -292,21 +784,28 @@ impl Collection {
     /// 实际删除的文档数量
     pub fn delete_many(&self, ids: &[ObjectId]) -> StorageResult<u64> {
         let cf = self.cf()?;
+        let versioned = self.begin_write();
         let mut batch = WriteBatch::default();
-        let mut count = 0u64;
+        let mut records = Vec::new();
+        let mut deleted = Vec::new();
 
         for id in ids {
             let key = Self::doc_key(id);
-            if self.db.get_cf(&cf, &key)?.is_some() {
+            if let Some(existing) = self.db.get_cf(&cf, &key)? {
+                versioned.save(&mut batch, &self.name, id, Some(&existing))?;
                 batch.delete_cf(&cf, &key);
-                count += 1;
+                records.push(WalRecord::new_delete(0, &self.name, key));
+                deleted.push(*id);
             }
         }
 
+        let count = deleted.len() as u64;
         if count > 0 {
-            let mut write_opts = WriteOptions::default();
-            write_opts.set_sync(false);
-            self.db.write_opt(batch, &write_opts)?;
+            self.write(batch, records)?;
+            for id in deleted {
+                self.record_change(Some(id), ChangeKind::Delete);
+            }
+            self.forget_capped_usage();
 
             let mut stats = self.stats.write();
             stats.doc_count = stats.doc_count.saturating_sub(count);
@@ -317,6 +816,90 @@ impl Collection {
         Ok(count)
     }
 
+    /// 删除早于指定时间创建的文档
+    ///
+    /// # Brief
+    /// 文档键的 `_id` 部分以大端序的创建时间开头,早于某一时间的文档在键空间中
+    /// 是连续的一段,因此用一个范围墓碑 (delete_range) 删除,不逐个读取和删除文档。
+    ///
+    /// 只遍历该范围的键来统计删除数量,不解码文档。索引项不会同步删除,
+    /// 回表时会跳过已删除的文档,可以之后通过 `IndexEngine::prune_dangling` 清理。
+    ///
+    /// # Arguments
+    /// * `cutoff_secs` - Unix 秒,创建时间早于它的文档被删除
+    ///
+    /// # Returns
+    /// 删除的文档数量
+    pub fn delete_created_before(&self, cutoff_secs: u64) -> StorageResult<u64> {
+        let cf = self.cf()?;
+        let (start, end) = Self::created_before_range(cutoff_secs);
+        let versioned = self.begin_write();
+
+        let mut read_opts = ReadOptions::default();
+        read_opts.set_iterate_upper_bound(end.clone());
+        let mut batch = WriteBatch::default();
+        let mut count = 0u64;
+        for item in self.db.iterator_cf_opt(&cf, read_opts, IteratorMode::From(&start, Direction::Forward)) {
+            let (key, value) = item?;
+            // 存在活跃读快照时才需要逐个保存被删除文档的前像
+            if let Some(id) = Self::id_from_key(&key).filter(|_| versioned.is_tracking()) {
+                versioned.save(&mut batch, &self.name, &id, Some(&value))?;
+            }
+            count += 1;
+        }
+
+        if count > 0 {
+            batch.delete_range_cf(&cf, &start, &end);
+            self.write(batch, vec![WalRecord::new_delete_range(0, &self.name, start, end)])?;
+            self.record_change(None, ChangeKind::Invalidate);
+            self.forget_capped_usage();
+
+            let mut stats = self.stats.write();
+            stats.doc_count = stats.doc_count.saturating_sub(count);
+            stats.delete_count += count;
+        }
+
+        debug!("Range-deleted {} documents from {} created before {}", count, self.name, cutoff_secs);
+        Ok(count)
+    }
+
+    /// 查找早于指定时间创建的文档
+    ///
+    /// # Brief
+    /// 只扫描对应的键范围,而不是整个集合
+    ///
+    /// # Arguments
+    /// * `cutoff_secs` - Unix 秒
+    ///
+    /// # Returns
+    /// 按创建时间排序的文档向量
+    pub fn find_created_before(&self, cutoff_secs: u64) -> StorageResult<Vec<Document>> {
+        let cf = self.cf()?;
+        let (start, end) = Self::created_before_range(cutoff_secs);
+
+        let mut read_opts = ReadOptions::default();
+        read_opts.set_iterate_upper_bound(end);
+        let mut docs = Vec::new();
+        for item in self.db.iterator_cf_opt(&cf, read_opts, IteratorMode::From(&start, Direction::Forward)) {
+            let (key, value) = item?;
+            if key.len() == 13 {
+                let boml_value = codec::decode_document(&value)?;
+                docs.push(Document::from_boml_value(boml_value)?);
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// 创建时间早于 `cutoff_secs` 的文档键范围 `[start, end)`
+    fn created_before_range(cutoff_secs: u64) -> (Vec<u8>, Vec<u8>) {
+        let cutoff = cutoff_secs.min(u32::MAX as u64) as u32;
+        let mut end = Vec::with_capacity(5);
+        end.push(b'd');
+        end.extend_from_slice(&cutoff.to_be_bytes());
+        (vec![b'd'], end)
+    }
+
     /// 查找所有文档
     ///
     /// # Brief
@@ -343,10 +926,159 @@ impl Collection {
         Ok(docs)
     }
 
+    /// 按读快照获取文档
+    ///
+    /// # Brief
+    /// 返回文档在快照开始时的内容,快照之后的修改、插入和删除都不可见
+    ///
+    /// # Arguments
+    /// * `id` - 文档的 ObjectId
+    /// * `snapshot` - 读快照,见 [`crate::StorageEngine::begin_snapshot`]
+    ///
+    /// # Returns
+    /// 快照开始时文档存在返回 `Some(Document)`,否则 `None`
+    pub fn get_as_of(&self, id: &ObjectId, snapshot: &ReadSnapshot) -> StorageResult<Option<Document>> {
+        let cf = self.cf()?;
+        // 文档和前像在同一个 RocksDB 快照上读取,避免读取之间提交的写入
+        let db_snapshot = self.db.snapshot();
+        let read_opts = || {
+            let mut read_opts = ReadOptions::default();
+            read_opts.set_snapshot(&db_snapshot);
+            read_opts
+        };
+
+        let current = self.db.get_cf_opt(&cf, Self::doc_key(id), &read_opts())?;
+        let data = match snapshot.store().previous_at(&self.name, id, snapshot.version(), read_opts())? {
+            Some(previous) => previous,
+            None => current,
+        };
+        match data {
+            Some(data) => Ok(Some(Document::from_boml_value(codec::decode_document(&data)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 按读快照查找所有文档
+    ///
+    /// # Brief
+    /// 返回快照开始时集合中的全部文档,按主键排序
+    ///
+    /// # Arguments
+    /// * `snapshot` - 读快照
+    ///
+    /// # Returns
+    /// 文档向量
+    pub fn find_all_as_of(&self, snapshot: &ReadSnapshot) -> StorageResult<Vec<Document>> {
+        let cf = self.cf()?;
+        let db_snapshot = self.db.snapshot();
+        let read_opts = || {
+            let mut read_opts = ReadOptions::default();
+            read_opts.set_snapshot(&db_snapshot);
+            read_opts
+        };
+
+        let mut previous = snapshot.store().previous_all_at(&self.name, snapshot.version(), read_opts())?;
+        let mut visible = std::collections::BTreeMap::new();
+        let mut current_opts = read_opts();
+        current_opts.set_iterate_upper_bound(vec![b'd' + 1]);
+        for item in self.db.iterator_cf_opt(&cf, current_opts, IteratorMode::From(b"d", Direction::Forward)) {
+            let (key, value) = item?;
+            let Some(id) = Self::id_from_key(&key) else {
+                continue;
+            };
+            match previous.remove(id.as_bytes()) {
+                Some(Some(data)) => visible.insert(*id.as_bytes(), data),
+                Some(None) => None,
+                None => visible.insert(*id.as_bytes(), value.to_vec()),
+            };
+        }
+        // 快照之后被删除的文档只剩前像
+        for (id, data) in previous {
+            if let Some(data) = data {
+                visible.insert(id, data);
+            }
+        }
+
+        let mut docs = Vec::with_capacity(visible.len());
+        for data in visible.into_values() {
+            docs.push(Document::from_boml_value(codec::decode_document(&data)?)?);
+        }
+        Ok(docs)
+    }
+
+    /// 按投影查找所有文档
+    ///
+    /// # Brief
+    /// 与 [`Collection::find_all`] 相同,但每个文档只解码 `fields` 用到的顶层字段和 `_id`,
+    /// 其余字段在 BOML 数据中直接跳过
+    ///
+    /// # Arguments
+    /// * `fields` - 需要的字段路径
+    ///
+    /// # Returns
+    /// 只包含所需字段的文档向量
+    pub fn find_all_projected(&self, fields: &[String]) -> StorageResult<Vec<Document>> {
+        let cf = self.cf()?;
+        let mut fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        fields.push("_id");
+        let mut docs = Vec::new();
+
+        let prefix = [b'd'];
+        let iter = self.db.prefix_iterator_cf(&cf, prefix);
+
+        for item in iter {
+            let (key, value) = item?;
+            if key.len() == 13 && key[0] == b'd' {
+                let boml_value = codec::decode_document_projected(&value, &fields)?;
+                docs.push(Document::from_boml_value(boml_value)?);
+            }
+        }
+
+        Ok(docs)
+    }
+
+    /// 按主键顺序分批读取文档
+    ///
+    /// # Brief
+    /// 从 `after` 之后(不含)开始最多读取 `limit` 个文档,供服务器端游标逐批扫描大集合。
+    /// 每次调用都读取最新数据,两批之间插入且 ID 更大的文档会出现在后续批次中。
+    ///
+    /// # Arguments
+    /// * `after` - 上一批最后一个文档的 ID,None 表示从头开始
+    /// * `limit` - 本批最多返回的文档数
+    ///
+    /// # Returns
+    /// (文档 ID, 文档) 列表,按 ID 升序
+    pub fn scan_after(&self, after: Option<&ObjectId>, limit: usize) -> StorageResult<Vec<(ObjectId, Document)>> {
+        let cf = self.cf()?;
+        let mut read_opts = ReadOptions::default();
+        read_opts.set_iterate_upper_bound(vec![b'd' + 1]);
+
+        let start = after.map_or_else(|| vec![b'd'], Self::doc_key);
+        let mut docs = Vec::with_capacity(limit.min(MULTI_GET_CHUNK_SIZE));
+        for item in self.db.iterator_cf_opt(&cf, read_opts, IteratorMode::From(&start, Direction::Forward)) {
+            if docs.len() >= limit {
+                break;
+            }
+            let (key, value) = item?;
+            let Some(id) = Self::id_from_key(&key) else {
+                continue;
+            };
+            if after == Some(&id) {
+                continue;
+            }
+            let boml_value = codec::decode_document(&value)?;
+            docs.push((id, Document::from_boml_value(boml_value)?));
+        }
+
+        Ok(docs)
+    }
+
     /// 根据 ID 列表查找文档
     ///
     /// # Brief
-    /// 使用 multi_get 批量获取多个文档，性能更高
+    /// 按 MULTI_GET_CHUNK_SIZE 分块调用 multi_get 批量获取文档,结果保持 `ids` 的顺序。
+    /// 已被删除的文档直接跳过,不会报错。配置文档缓存时只读取未命中的文档。
     ///
     /// # Arguments
     /// * `ids` - ObjectId 列表
@@ -357,15 +1089,41 @@ impl Collection {
         let cf = self.cf()?;
         let mut docs = Vec::with_capacity(ids.len());
 
-        for id in ids {
-            let key = Self::doc_key(id);
-            if let Some(data) = self.db.get_cf(&cf, &key)? {
-                let boml_value = codec::decode_document(&data)?;
-                let doc = Document::from_boml_value(boml_value)?;
-                docs.push(doc);
+        for chunk in ids.chunks(MULTI_GET_CHUNK_SIZE) {
+            // 命中的文档按位置放入 slots,未命中的记下位置和失效版本后一次 multi_get
+            let mut slots: Vec<Option<Document>> = Vec::with_capacity(chunk.len());
+            let mut misses = Vec::with_capacity(chunk.len());
+            for (i, id) in chunk.iter().enumerate() {
+                match &self.doc_cache {
+                    Some(cache) => match cache.get(&self.cache_name, id) {
+                        Some(doc) => slots.push(Some(doc)),
+                        None => {
+                            slots.push(None);
+                            misses.push((i, cache.generation(&self.cache_name, id)));
+                        }
+                    },
+                    None => {
+                        slots.push(None);
+                        misses.push((i, 0));
+                    }
+                }
             }
+
+            let keys: Vec<Vec<u8>> = misses.iter().map(|(i, _)| Self::doc_key(&chunk[*i])).collect();
+            for (result, (i, generation)) in self.db.batched_multi_get_cf(&cf, &keys, false).into_iter().zip(misses) {
+                if let Some(data) = result? {
+                    let boml_value = codec::decode_document(&data)?;
+                    let doc = Document::from_boml_value(boml_value)?;
+                    if let Some(cache) = &self.doc_cache {
+                        cache.insert(&self.cache_name, &doc, data.len(), generation);
+                    }
+                    slots[i] = Some(doc);
+                }
+            }
+            docs.extend(slots.into_iter().flatten());
         }
 
+        trace!("Resolved {} of {} ids in {}", docs.len(), ids.len(), self.name);
         Ok(docs)
     }
 
@@ -421,19 +1179,27 @@ impl Collection {
     pub fn clear(&self) -> StorageResult<u64> {
         let cf = self.cf()?;
         let prefix = [b'd'];
+        let versioned = self.begin_write();
         let iter = self.db.prefix_iterator_cf(&cf, &prefix);
 
         let mut batch = WriteBatch::default();
+        let mut records = Vec::new();
         let mut count = 0u64;
 
         for item in iter {
-            let (key, _) = item?;
+            let (key, value) = item?;
+            if let Some(id) = Self::id_from_key(&key) {
+                versioned.save(&mut batch, &self.name, &id, Some(&value))?;
+            }
             batch.delete_cf(&cf, &key);
+            records.push(WalRecord::new_delete(0, &self.name, key.to_vec()));
             count += 1;
         }
 
         if count > 0 {
-            self.db.write(batch)?;
+            self.write(batch, records)?;
+            self.record_change(None, ChangeKind::Invalidate);
+            self.forget_capped_usage();
 
             let mut stats = self.stats.write();
             stats.doc_count = 0;
@@ -458,6 +1224,30 @@ impl Collection {
         })
     }
 
+    /// 在一致快照上扫描文档
+    ///
+    /// # Brief
+    /// 固定一个 RocksDB 快照后按主键顺序遍历全部文档,扫描期间的写入不会出现在结果中。
+    /// 导出和复制初始化先扫描快照,再从 `sequence()` 之后的写入继续追赶,结果即内部一致。
+    /// 扫描不填充 block cache,避免大批量导出挤出热点数据。
+    ///
+    /// # Returns
+    /// 持有快照的迭代器,迭代器释放时快照随之释放
+    pub fn scan_snapshot(&self) -> StorageResult<SnapshotScan<'_>> {
+        let cf = self.cf()?;
+        let (snapshot, sequence) = pinned_snapshot(&self.db);
+        let mut read_opts = ReadOptions::default();
+        read_opts.set_iterate_upper_bound(vec![b'd' + 1]);
+        read_opts.fill_cache(false);
+        read_opts.set_snapshot(&snapshot);
+        let inner = self.db.iterator_cf_opt(&cf, read_opts, IteratorMode::From(b"d", Direction::Forward));
+        Ok(SnapshotScan {
+            inner,
+            _snapshot: snapshot,
+            sequence,
+        })
+    }
+
     /// 获取集合统计信息
     ///
     /// # Brief
@@ -474,6 +1264,89 @@ impl Collection {
             insert_count: stats.insert_count,
             update_count: stats.update_count,
             delete_count: stats.delete_count,
+            type_drift_count: self.schema.read().drift_count(),
+            type_rejected_count: self.schema.read().rejected_count(),
+        }
+    }
+
+    /// 获取模式选项
+    pub fn schema_options(&self) -> SchemaOptions {
+        *self.schema_options.read()
+    }
+
+    /// 设置模式选项
+    ///
+    /// # Brief
+    /// 只修改内存中的选项,持久化由 `StorageEngine::set_schema_options` 负责
+    pub fn set_schema_options(&self, options: SchemaOptions) {
+        *self.schema_options.write() = options;
+    }
+
+    /// 获取字段类型统计
+    ///
+    /// # Brief
+    /// 返回登记表中每个字段的主类型和各类型出现次数,未启用类型记录时返回空列表
+    pub fn schema_summary(&self) -> StorageResult<Vec<FieldSummary>> {
+        if !self.schema_options().enabled() {
+            return Ok(Vec::new());
+        }
+        self.seed_schema()?;
+        Ok(self.schema.read().summary())
+    }
+
+    /// 从已有文档抽样重建字段类型登记表(只执行一次)
+    fn seed_schema(&self) -> StorageResult<()> {
+        if self.schema.read().is_seeded() {
+            return Ok(());
+        }
+        let mut schema = self.schema.write();
+        if schema.is_seeded() {
+            return Ok(());
+        }
+        for doc in self.iter()?.take(SEED_SAMPLE_SIZE) {
+            schema.record(&doc?);
+        }
+        schema.mark_seeded();
+        Ok(())
+    }
+
+    /// 检查写入的文档是否改变字段的主类型
+    ///
+    /// # Brief
+    /// 发现类型漂移时记录警告并计数;开启 strict_types 时拒绝整批写入
+    fn check_types(&self, docs: &[Document]) -> StorageResult<()> {
+        let options = self.schema_options();
+        if !options.enabled() {
+            return Ok(());
+        }
+        self.seed_schema()?;
+
+        let mut schema = self.schema.write();
+        for doc in docs {
+            let drift = schema.check(doc);
+            if drift.is_empty() {
+                continue;
+            }
+            schema.add_drift(drift.len() as u64, options.strict_types);
+            if options.strict_types {
+                return Err(StorageError::SchemaViolation {
+                    collection: self.name.clone(),
+                    details: drift.iter().map(ValidationDetail::from).collect(),
+                });
+            }
+            let message = drift.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("; ");
+            warn!("Type drift in {}: {}", self.name, message);
+        }
+        Ok(())
+    }
+
+    /// 把已写入文档的字段类型计入登记表
+    fn record_types(&self, docs: &[Document]) {
+        if self.schema_options().enabled() {
+            let mut schema = self.schema.write();
+            for doc in docs {
+                schema.record(doc);
+            }
         }
     }
 }
@@ -509,6 +1382,45 @@ impl<'a> Iterator for CollectionIterator<'a> {
     }
 }
 
+/// 快照扫描迭代器
+///
+/// 由 [`Collection::scan_snapshot`] 创建,按主键升序返回 (文档 ID, 文档)
+pub struct SnapshotScan<'a> {
+    // 字段按声明顺序释放,迭代器必须先于它读取的快照释放
+    inner: rocksdb::DBIteratorWithThreadMode<'a, DB>,
+    _snapshot: Snapshot<'a>,
+    sequence: u64,
+}
+
+impl SnapshotScan<'_> {
+    /// # Brief
+    /// 快照对应的 RocksDB 序列号
+    ///
+    /// # Returns
+    /// 不大于该序列号的写入都包含在扫描结果中,复制从其后的写入继续
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+impl Iterator for SnapshotScan<'_> {
+    type Item = StorageResult<(ObjectId, Document)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (key, value) = match self.inner.next()? {
+                Ok(item) => item,
+                Err(e) => return Some(Err(StorageError::RocksDb(e))),
+            };
+            let Some(id) = Collection::id_from_key(&key) else {
+                continue;
+            };
+            let doc = codec::decode_document(&value).and_then(Document::from_boml_value);
+            return Some(doc.map(|doc| (id, doc)).map_err(StorageError::Boml));
+        }
+    }
+}
+
 /// 集合统计信息快照
 ///
 /// 包含集合的各种统计数据
@@ -520,6 +1432,8 @@ pub struct CollectionStatsSnapshot {
     pub insert_count: u64,
     pub update_count: u64,
     pub delete_count: u64,
+    pub type_drift_count: u64,
+    pub type_rejected_count: u64,
 }
 
 #[cfg(test)]
@@ -554,6 +1468,53 @@ mod tests {
         assert_eq!(retrieved.get_i32("value"), Some(42));
     }
 
+    #[test]
+    fn test_find_by_ids_preserves_order() {
+        let (_engine, collection) = setup();
+
+        let mut ids = Vec::new();
+        for i in 0..(MULTI_GET_CHUNK_SIZE as i32 + 10) {
+            let mut doc = Document::new();
+            doc.insert("seq", i);
+            ids.push(collection.insert(&mut doc).unwrap());
+        }
+        ids.reverse();
+        ids.insert(3, ObjectId::new());
+        collection.delete(&ids[0]).unwrap();
+
+        let docs = collection.find_by_ids(&ids).unwrap();
+        assert_eq!(docs.len(), MULTI_GET_CHUNK_SIZE + 9);
+        let seqs: Vec<i32> = docs.iter().map(|d| d.get_i32("seq").unwrap()).collect();
+        let expected: Vec<i32> = (0..(MULTI_GET_CHUNK_SIZE as i32 + 9)).rev().collect();
+        assert_eq!(seqs, expected);
+    }
+
+    #[test]
+    fn test_strict_types_rejects_drift() {
+        let (_engine, collection) = setup();
+
+        let mut doc = Document::new();
+        doc.insert("age", 16);
+        collection.insert(&mut doc).unwrap();
+
+        collection.set_schema_options(SchemaOptions { track_types: true, strict_types: false });
+        let mut drifted = Document::new();
+        drifted.insert("age", "sixteen");
+        collection.insert(&mut drifted).unwrap();
+        assert_eq!(collection.stats().type_drift_count, 1);
+
+        collection.set_schema_options(SchemaOptions { track_types: true, strict_types: true });
+        let mut rejected = Document::new();
+        rejected.insert("age", "seventeen");
+        let err = collection.insert(&mut rejected).unwrap_err();
+        assert_eq!(
+            err.validation_details(),
+            Some(&[ValidationDetail::new("age", "int", "string", crate::schema::RULE_STRICT_TYPES)][..])
+        );
+        assert_eq!(collection.stats().type_rejected_count, 1);
+        assert_eq!(collection.count_scan().unwrap(), 2);
+    }
+
     #[test]
     fn test_update() {
         let (_engine, collection) = setup();
@@ -570,6 +1531,50 @@ mod tests {
         assert_eq!(retrieved.get_str("name"), Some("updated"));
     }
 
+    #[test]
+    fn test_increment() {
+        let (_engine, collection) = setup();
+
+        let mut doc = Document::new();
+        doc.insert("name", "page");
+        doc.insert("views", 10i64);
+        let id = collection.insert(&mut doc).unwrap();
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..25 {
+                        let deltas = [("views".to_string(), BomlValue::Int64(1))];
+                        assert!(collection.increment(&id, &deltas).unwrap());
+                    }
+                });
+            }
+        });
+        let deltas = [("likes".to_string(), BomlValue::Int32(2))];
+        collection.increment(&id, &deltas).unwrap();
+
+        let retrieved = collection.get(&id).unwrap().unwrap();
+        assert_eq!(retrieved.id(), Some(&id));
+        assert_eq!(retrieved.get_str("name"), Some("page"));
+        assert_eq!(retrieved.get("views").and_then(BomlValue::as_i64), Some(110));
+        assert_eq!(retrieved.get("likes").and_then(BomlValue::as_i64), Some(2));
+
+        let deltas = [("name".to_string(), BomlValue::Int64(1))];
+        let err = collection.increment(&id, &deltas).unwrap_err();
+        assert_eq!(
+            err.validation_details(),
+            Some(&[ValidationDetail::new("name", "number", "string", RULE_UPDATE_INC)][..])
+        );
+        assert!(!collection.increment(&ObjectId::new(), &[("views".to_string(), BomlValue::Int64(1))]).unwrap());
+
+        // 溢出时整个增量被拒绝,字段保持原值
+        let deltas = [("likes".to_string(), BomlValue::Int32(1)), ("views".to_string(), BomlValue::Int64(i64::MAX))];
+        assert!(matches!(collection.increment(&id, &deltas), Err(StorageError::Overflow(_))));
+        let retrieved = collection.get(&id).unwrap().unwrap();
+        assert_eq!(retrieved.get("likes").and_then(BomlValue::as_i64), Some(2));
+        assert_eq!(retrieved.get("views").and_then(BomlValue::as_i64), Some(110));
+    }
+
     #[test]
     fn test_delete() {
         let (_engine, collection) = setup();
@@ -582,6 +1587,28 @@ mod tests {
         assert!(collection.get(&id).unwrap().is_none());
     }
 
+    #[test]
+    fn test_delete_created_before() {
+        let (_engine, collection) = setup();
+
+        for ts in [1_000u32, 2_000, 3_000] {
+            let mut bytes = [7u8; 12];
+            bytes[0..4].copy_from_slice(&ts.to_be_bytes());
+            let mut doc = Document::with_id(ObjectId::from_bytes(bytes));
+            doc.insert("ts", ts as i64);
+            collection.insert(&mut doc).unwrap();
+        }
+        let mut recent = Document::new();
+        collection.insert(&mut recent).unwrap();
+
+        let old = collection.find_created_before(3_000).unwrap();
+        assert_eq!(old.iter().map(|d| d.get_i64("ts").unwrap()).collect::<Vec<_>>(), vec![1_000, 2_000]);
+
+        assert_eq!(collection.delete_created_before(3_001).unwrap(), 3);
+        assert_eq!(collection.count_scan().unwrap(), 1);
+        assert_eq!(collection.delete_created_before(3_001).unwrap(), 0);
+    }
+
     #[test]
     fn test_insert_many() {
         let (_engine, collection) = setup();
@@ -600,4 +1627,174 @@ mod tests {
         let all = collection.find_all().unwrap();
         assert_eq!(all.len(), 100);
     }
+
+    #[test]
+    fn test_insert_duplicate_id() {
+        let (_engine, collection) = setup();
+
+        let id = collection.insert(&mut Document::new()).unwrap();
+        let mut doc = Document::with_id(id);
+        doc.insert("name", "second");
+        let err = collection.insert(&mut doc).unwrap_err();
+        assert_eq!(err.code(), Some(crate::DUPLICATE_KEY_CODE));
+        assert!(err.to_string().contains(ID_INDEX_NAME));
+        assert!(collection.get(&id).unwrap().unwrap().get_str("name").is_none());
+    }
+
+    #[test]
+    fn test_concurrent_duplicate_inserts() {
+        let (_engine, collection) = setup();
+        let id = ObjectId::new();
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let collection = collection.clone();
+                std::thread::spawn(move || {
+                    let mut doc = Document::with_id(id);
+                    doc.insert("writer", i);
+                    collection.insert(&mut doc)
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(results
+            .iter()
+            .filter_map(|r| r.as_ref().err())
+            .all(|e| matches!(e, StorageError::DuplicateKey { .. })));
+        assert_eq!(collection.count_scan().unwrap(), 1);
+        assert_eq!(collection.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_insert_many_duplicate_ids() {
+        let (_engine, collection) = setup();
+        let existing = collection.insert(&mut Document::new()).unwrap();
+
+        let mut docs = vec![Document::new(), Document::with_id(existing)];
+        let err = collection.insert_many(&mut docs).unwrap_err();
+        assert!(matches!(err, StorageError::DuplicateKey { .. }));
+
+        let id = ObjectId::new();
+        let mut docs = vec![Document::with_id(id), Document::new(), Document::with_id(id)];
+        let err = collection.insert_many(&mut docs).unwrap_err();
+        assert!(matches!(err, StorageError::DuplicateKey { .. }));
+
+        // 出错的批次整批都不写入
+        assert_eq!(collection.count_scan().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_insert_many_indexed() {
+        use crate::index::{IndexDefinition, IndexField, IndexOrder, IndexType, KeyEncoding, OversizedKeyPolicy};
+
+        let (engine, collection) = setup();
+        let indexes = engine.indexes();
+        indexes
+            .create_index(IndexDefinition {
+                name: "test_email".to_string(),
+                collection: "test".to_string(),
+                fields: vec![IndexField { path: "email".to_string(), order: IndexOrder::Ascending }],
+                index_type: IndexType::BTree,
+                unique: true,
+                sparse: false,
+                ttl_seconds: None,
+                key_encoding: KeyEncoding::Memcomparable,
+                max_key_size: None,
+                oversized_keys: OversizedKeyPolicy::Reject,
+            })
+            .unwrap();
+
+        let user = |email: &str| {
+            let mut doc = Document::new();
+            doc.insert("email", email);
+            doc
+        };
+        let mut docs: Vec<Document> = (0..100).map(|i| user(&format!("user{}@example.com", i))).collect();
+        let ids = collection.insert_many_indexed(&mut docs, &indexes).unwrap();
+        let found = indexes.lookup("test_email", &[BomlValue::from("user42@example.com")]).unwrap();
+        assert_eq!(found, vec![ids[42]]);
+
+        // 与已有文档或同一批中的文档重复时,文档和索引项都不写入
+        let mut docs = vec![user("new@example.com"), user("user7@example.com")];
+        let err = collection.insert_many_indexed(&mut docs, &indexes).unwrap_err();
+        assert_eq!(err.code(), Some(crate::DUPLICATE_KEY_CODE));
+        let mut docs = vec![user("twin@example.com"), user("twin@example.com")];
+        assert!(collection.insert_many_indexed(&mut docs, &indexes).is_err());
+
+        assert_eq!(collection.count_scan().unwrap(), 100);
+        assert!(indexes.lookup("test_email", &[BomlValue::from("new@example.com")]).unwrap().is_empty());
+        assert!(indexes.lookup("test_email", &[BomlValue::from("twin@example.com")]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scan_after() {
+        let (_engine, collection) = setup();
+
+        let mut docs: Vec<Document> = (0..25)
+            .map(|i| {
+                let mut doc = Document::new();
+                doc.insert("index", i);
+                doc
+            })
+            .collect();
+        let mut ids = collection.insert_many(&mut docs).unwrap();
+        ids.sort_by_key(|id| *id.as_bytes());
+
+        let mut scanned = Vec::new();
+        let mut after = None;
+        loop {
+            let batch = collection.scan_after(after.as_ref(), 10).unwrap();
+            if batch.is_empty() {
+                break;
+            }
+            after = batch.last().map(|(id, _)| *id);
+            scanned.extend(batch.into_iter().map(|(id, _)| id));
+        }
+        assert_eq!(scanned, ids);
+    }
+
+    #[test]
+    fn test_find_all_projected() {
+        let (_engine, collection) = setup();
+
+        let mut doc = Document::new();
+        doc.insert("name", "Miku");
+        doc.insert("age", 16);
+        doc.insert("bio", "x".repeat(1000));
+        let id = collection.insert(&mut doc).unwrap();
+
+        let docs = collection.find_all_projected(&["age".to_string()]).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].id(), Some(&id));
+        assert_eq!(docs[0].get("age"), Some(&BomlValue::Int32(16)));
+        assert!(docs[0].get("bio").is_none());
+    }
+
+    #[test]
+    fn test_scan_snapshot() {
+        let (_engine, collection) = setup();
+
+        let mut docs: Vec<Document> = (0..5)
+            .map(|i| {
+                let mut doc = Document::new();
+                doc.insert("n", i);
+                doc
+            })
+            .collect();
+        let mut ids = collection.insert_many(&mut docs).unwrap();
+        ids.sort_by_key(|id| *id.as_bytes());
+
+        let scan = collection.scan_snapshot().unwrap();
+        let sequence = scan.sequence();
+        let mut later = Document::new();
+        later.insert("n", 5);
+        collection.insert(&mut later).unwrap();
+        collection.delete(&ids[0]).unwrap();
+
+        let scanned: Vec<ObjectId> = scan.map(|item| item.unwrap().0).collect();
+        assert_eq!(scanned, ids);
+        assert!(collection.scan_snapshot().unwrap().sequence() > sequence);
+    }
 }
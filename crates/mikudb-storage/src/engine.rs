@@ -2,6 +2,15 @@
 //!
 //! 基于 RocksDB 的高性能存储引擎实现。
 //!
+//! 支持三种打开方式(`OpenMode`):
+//! - 读写: 默认方式,独占数据目录
+//! - 只读: 离线工具和备份校验使用,打开时的数据快照,不执行崩溃恢复
+//! - 从实例: 与主实例在同一主机上共享数据目录,按需追赶主实例的 WAL,用于不复制数据的分析查询
+//!
+//! 一个实例可以有多个数据库。默认数据库的集合直接以集合名作为列族名,其他数据库的集合
+//! 以 `<数据库>.<集合>` 作为列族名(见 [`qualified_collection_name`]);
+//! 序列、自定义函数和索引名在所有数据库间共享。
+//!
 //! # OpenEuler 适配亮点
 //!
 //! - 支持 Direct I/O 优化，减少内存拷贝
@@ -10,25 +19,111 @@
 //! - 针对 ARM64 架构优化的块大小配置
 
 use crate::{StorageError, StorageResult};
-use crate::wal::WriteAheadLog;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use crate::wal::{WalStats, WalSyncPolicy, WriteAheadLog};
 use crate::recovery::{RecoveryManager, RecoveryStats};
-use mikudb_boml::{codec, BomlValue, Document};
+use crate::bulk::{BulkWriteResult, OperationResult, WriteOperation};
+use crate::cache::{CacheStats, DocumentCache, EvictionPolicy};
+use crate::capped::CappedOptions;
+use crate::compaction::{self, CompactionReport, CompactionStats, CompactionStatsSnapshot};
+use crate::expiry::{ExpirePolicy, EXPIRE_KEY_PREFIX};
+use crate::changes::{ChangeKind, ChangeStream};
+use crate::index::{IndexEngine, INDEX_META_CF};
+use crate::merge;
+use crate::mvcc::{ReadSnapshot, VersionStore, VERSIONS_CF};
+use crate::rollup::{GroupChange, RollupDefinition, ROLLUP_KEY_PREFIX};
+use crate::schema::SchemaOptions;
+use crate::snapshot::{self, IncrementManifest, SnapshotManifest};
+use crate::sequence::{self, SequenceCounter, SequenceDefinition, SEQUENCE_CF, SEQUENCE_DEFINITION_PREFIX, SEQUENCE_MERGE_OPERATOR};
+use crate::tiering::{self, ArchivePolicy, ARCHIVE_KEY_PREFIX};
+use mikudb_boml::{codec, BomlValue, Document, DocumentCompression};
 use mikudb_common::config::CompressionType;
 use mikudb_common::platform::{linux, Platform};
 use mikudb_common::{CollectionName, DatabaseName, DocumentId, ObjectId};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rocksdb::{
-    BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle,
-    DBCompressionType, Env, Options, ReadOptions, WriteOptions, DB,
+    BlockBasedOptions, BoundColumnFamily, Cache, ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle,
+    Env, Options, ReadOptions, Snapshot, WriteBatch, WriteOptions, DB,
 };
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
-const METADATA_CF: &str = "_metadata";
-const SYSTEM_CF: &str = "_system";
+pub(crate) const METADATA_CF: &str = "_metadata";
+pub(crate) const SYSTEM_CF: &str = "_system";
 const DEFAULT_CF: &str = "default";
+/// 集合统计信息的元数据键前缀
+const STATS_KEY_PREFIX: &str = "stats:";
+/// 自定义函数模块的元数据键前缀
+const FUNCTION_KEY_PREFIX: &str = "function:";
+/// 创建快照时确认序列号的最大尝试次数
+const SNAPSHOT_SEQUENCE_ATTEMPTS: usize = 16;
+/// 默认数据库,其中的集合名不带前缀
+pub const DEFAULT_DATABASE: &str = "default";
+/// 数据库登记的元数据键前缀
+const DATABASE_KEY_PREFIX: &str = "database:";
+
+/// # Brief
+/// 数据库中的集合在存储中的名称
+///
+/// 默认数据库的集合名不变,其他数据库的集合名为 `<数据库>.<集合>`,
+/// 集合的索引、预聚合、归档等都按这个名称登记。
+///
+/// # Arguments
+/// * `database` - 数据库名称
+/// * `collection` - 集合在数据库中的名称
+pub fn qualified_collection_name(database: &str, collection: &str) -> String {
+    if database == DEFAULT_DATABASE {
+        collection.to_string()
+    } else {
+        format!("{}.{}", database, collection)
+    }
+}
+
+/// # Brief
+/// 创建 RocksDB 快照并确定它的序列号
+///
+/// Rust 绑定没有读取快照序列号的接口,这里在创建快照前后各读取一次最新序列号,相同即为快照的序列号。
+/// 持续写入使多次尝试都不相同时,返回最后一次创建前读到的序列号: 不大于它的写入一定在快照中,
+/// 紧随其后的少量写入也可能已包含在内。
+///
+/// # Arguments
+/// * `db` - RocksDB 实例
+///
+/// # Returns
+/// (快照, 序列号)
+pub(crate) fn pinned_snapshot(db: &DB) -> (Snapshot<'_>, u64) {
+    let mut attempt = 1;
+    loop {
+        let before = db.latest_sequence_number();
+        let snapshot = db.snapshot();
+        if db.latest_sequence_number() == before || attempt == SNAPSHOT_SEQUENCE_ATTEMPTS {
+            return (snapshot, before);
+        }
+        attempt += 1;
+    }
+}
+
+/// 存储引擎的打开方式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OpenMode {
+    /// 读写,独占数据目录
+    #[default]
+    ReadWrite,
+    /// 只读: 不创建目录和列族,不执行崩溃恢复,创建或删除集合、序列等操作返回 `ReadOnly` 错误。
+    /// 不占用数据目录的锁,可以在服务器运行时打开,只能看到打开时已落盘的数据
+    ReadOnly,
+    /// RocksDB 从实例: 读取同一主机上主实例的数据目录,
+    /// 调用 `StorageEngine::catch_up_with_primary` 时追赶主实例的 WAL
+    Secondary {
+        /// 从实例自己的日志和 MANIFEST 副本目录,不能与数据目录相同
+        secondary_dir: PathBuf,
+    },
+}
 
 /// 存储引擎配置选项
 ///
@@ -36,6 +131,8 @@ const DEFAULT_CF: &str = "default";
 #[derive(Debug, Clone)]
 pub struct StorageOptions {
     pub data_dir: PathBuf,
+    /// 打开方式,默认读写
+    pub open_mode: OpenMode,
     pub cache_size: usize,
     pub write_buffer_size: usize,
     pub max_write_buffer_number: i32,
@@ -44,7 +141,24 @@ pub struct StorageOptions {
     pub enable_statistics: bool,
     pub paranoid_checks: bool,
     pub enable_wal: bool,
-    pub wal_sync_on_write: bool,
+    /// WAL 落盘策略,也决定序列等元数据写入是否同步
+    pub wal_sync: WalSyncPolicy,
+    /// WAL 目录,默认为 `data_dir/wal`
+    pub wal_dir: Option<PathBuf>,
+    /// WAL 段归档目录,设置后检查点保留写满的 WAL 段,供增量备份使用
+    pub wal_archive_dir: Option<PathBuf>,
+    /// 集合文档的 BOML 压缩算法,与 RocksDB 的块压缩相互独立,只影响新写入的文档
+    pub document_compression: DocumentCompression,
+    /// 按集合覆盖 `document_compression`
+    pub collection_compression: HashMap<String, DocumentCompression>,
+    /// TTL 索引后台清理的间隔,见 `crate::ttl::TtlSweeper`
+    pub ttl_sweep_interval: Duration,
+    /// 定时压缩所有集合的间隔,None 时只在执行 COMPACT 时压缩,见 `crate::compaction::CompactionScheduler`
+    pub compaction_interval: Option<Duration>,
+    /// 解码后文档缓存的容量(字节),0 表示不缓存;从实例不使用文档缓存
+    pub document_cache_size: usize,
+    /// 文档缓存的淘汰策略
+    pub document_cache_policy: EvictionPolicy,
 
     #[cfg(target_os = "linux")]
     pub use_direct_reads: bool,
@@ -70,6 +184,7 @@ impl Default for StorageOptions {
 
         Self {
             data_dir: PathBuf::from("/var/lib/mikudb/data"),
+            open_mode: OpenMode::ReadWrite,
             cache_size: 1024 * 1024 * 1024,
             write_buffer_size: 64 * 1024 * 1024,
             max_write_buffer_number: 4,
@@ -78,7 +193,15 @@ impl Default for StorageOptions {
             enable_statistics: true,
             paranoid_checks: true,
             enable_wal: true,
-            wal_sync_on_write: false,
+            wal_sync: WalSyncPolicy::default(),
+            wal_dir: None,
+            wal_archive_dir: None,
+            document_compression: DocumentCompression::None,
+            collection_compression: HashMap::new(),
+            ttl_sweep_interval: Duration::from_secs(60),
+            compaction_interval: None,
+            document_cache_size: 0,
+            document_cache_policy: EvictionPolicy::Lru,
 
             #[cfg(target_os = "linux")]
             use_direct_reads,
@@ -126,6 +249,21 @@ impl StorageOptions {
 
         opts
     }
+
+    /// # Brief
+    /// 获取集合使用的文档压缩算法
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    ///
+    /// # Returns
+    /// `collection_compression` 中的设置,没有时为 `document_compression`
+    pub fn document_compression_for(&self, collection: &str) -> DocumentCompression {
+        self.collection_compression
+            .get(collection)
+            .copied()
+            .unwrap_or(self.document_compression)
+    }
 }
 
 /// 存储引擎
@@ -134,9 +272,28 @@ impl StorageOptions {
 pub struct StorageEngine {
     db: Arc<DB>,
     options: StorageOptions,
-    collections: RwLock<HashMap<String, Arc<crate::collection::Collection>>>,
+    /// 已打开的集合实例,按名称分片加锁,并发访问不同集合时互不阻塞
+    collections: DashMap<String, Arc<crate::collection::Collection>>,
     block_cache: Arc<Cache>,
     wal: Option<Arc<WriteAheadLog>>,
+    /// 外部路径上的归档存储实例(路径 -> 引擎)
+    archive_engines: RwLock<HashMap<PathBuf, Arc<StorageEngine>>>,
+    /// 已加载的序列(名称 -> 定义和已发放的编号个数)
+    sequences: DashMap<String, Arc<SequenceCounter>>,
+    /// 集合写入的变更流
+    changes: Arc<ChangeStream>,
+    /// 读快照需要的文档旧版本
+    versions: Arc<VersionStore>,
+    /// BTree 和哈希索引
+    indexes: Arc<IndexEngine>,
+    /// 已加载的预聚合定义,创建、删除或恢复备份后置为 None
+    rollups: RwLock<Option<Arc<Vec<RollupDefinition>>>>,
+    /// 串行化预聚合分组的读取-修改-写回
+    rollup_lock: Mutex<()>,
+    /// 手动和定时集合压缩的累计统计
+    compaction_stats: CompactionStats,
+    /// 所有集合共用的解码后文档缓存
+    document_cache: Option<Arc<DocumentCache>>,
 }
 
 impl StorageEngine {
@@ -155,7 +312,11 @@ impl StorageEngine {
     /// # Returns
     /// 成功返回 StorageEngine，失败返回错误
     pub fn open(options: StorageOptions) -> StorageResult<Self> {
-        std::fs::create_dir_all(&options.data_dir)?;
+        match &options.open_mode {
+            OpenMode::ReadWrite => std::fs::create_dir_all(&options.data_dir)?,
+            OpenMode::ReadOnly => {}
+            OpenMode::Secondary { secondary_dir } => std::fs::create_dir_all(secondary_dir)?,
+        }
 
         let platform = Platform::current();
         if platform.is_openeuler() {
@@ -185,15 +346,15 @@ impl StorageEngine {
         db_opts.create_if_missing(true);
         db_opts.create_missing_column_families(true);
         db_opts.set_max_open_files(options.max_open_files);
+        if let OpenMode::Secondary { .. } = options.open_mode {
+            // 主实例随时可能删除 SST 文件,从实例必须保持所有文件打开
+            db_opts.set_max_open_files(-1);
+        }
         db_opts.set_write_buffer_size(options.write_buffer_size);
         db_opts.set_max_write_buffer_number(options.max_write_buffer_number);
         db_opts.set_min_write_buffer_number_to_merge(2);
 
-        let compression = match options.compression {
-            CompressionType::None => DBCompressionType::None,
-            CompressionType::Lz4 => DBCompressionType::Lz4,
-            CompressionType::Zstd => DBCompressionType::Zstd,
-        };
+        let compression = tiering::to_rocksdb_compression(options.compression);
         db_opts.set_compression_type(compression);
 
         db_opts.set_compaction_style(DBCompactionStyle::Level);
@@ -237,13 +398,26 @@ impl StorageEngine {
             .map(|name| {
                 let mut cf_opts = Options::default();
                 cf_opts.set_compression_type(compression);
+                match name.as_str() {
+                    SEQUENCE_CF => {
+                        cf_opts.set_merge_operator_associative(SEQUENCE_MERGE_OPERATOR, sequence::add_merge);
+                    }
+                    DEFAULT_CF | METADATA_CF | SYSTEM_CF | INDEX_META_CF | VERSIONS_CF => {}
+                    _ => Self::set_document_merge_operator(&mut cf_opts, options.document_compression_for(name)),
+                }
                 ColumnFamilyDescriptor::new(name, cf_opts)
             })
             .collect();
 
-        let db = if cf_descriptors.is_empty() {
+        let db = if let OpenMode::ReadOnly = options.open_mode {
+            DB::open_cf_descriptors_read_only(&db_opts, &options.data_dir, cf_descriptors, false)?
+        } else if let OpenMode::Secondary { secondary_dir } = &options.open_mode {
+            DB::open_cf_descriptors_as_secondary(&db_opts, &options.data_dir, secondary_dir, cf_descriptors)?
+        } else if cf_descriptors.is_empty() {
             let mut cf_opts = Options::default();
             cf_opts.set_compression_type(compression);
+            let mut sequence_opts = cf_opts.clone();
+            sequence_opts.set_merge_operator_associative(SEQUENCE_MERGE_OPERATOR, sequence::add_merge);
 
             DB::open_cf_descriptors(
                 &db_opts,
@@ -252,6 +426,7 @@ impl StorageEngine {
                     ColumnFamilyDescriptor::new(DEFAULT_CF, cf_opts.clone()),
                     ColumnFamilyDescriptor::new(METADATA_CF, cf_opts.clone()),
                     ColumnFamilyDescriptor::new(SYSTEM_CF, cf_opts),
+                    ColumnFamilyDescriptor::new(SEQUENCE_CF, sequence_opts),
                 ],
             )?
         } else {
@@ -260,12 +435,34 @@ impl StorageEngine {
 
         let db = Arc::new(db);
 
-        info!("Storage engine opened at {:?}", options.data_dir);
+        info!("Storage engine opened at {:?} ({:?})", options.data_dir, options.open_mode);
+
+        let writable = options.open_mode == OpenMode::ReadWrite;
+        if writable {
+            // 打开时所有 CF 都使用默认压缩,需要恢复本地归档集合的压缩设置
+            Self::restore_archive_compression(&db)?;
+
+            // 早期的数据目录没有索引元数据 CF,按需创建
+            if db.cf_handle(INDEX_META_CF).is_none() {
+                let mut cf_opts = Options::default();
+                cf_opts.set_compression_type(compression);
+                db.create_cf(INDEX_META_CF, &cf_opts)?;
+            }
+        }
+        let indexes = Arc::new(IndexEngine::new(db.clone()));
+        let versions = Arc::new(VersionStore::open(db.clone(), writable)?);
+        if db.cf_handle(INDEX_META_CF).is_some() {
+            indexes.load_indexes()?;
+        }
 
-        // 初始化 WAL 并执行崩溃恢复
-        let wal = if options.enable_wal {
-            let wal_path = options.data_dir.join("wal").join("mikudb.wal");
-            let wal = Arc::new(WriteAheadLog::open(wal_path, options.wal_sync_on_write)?);
+        // 初始化 WAL 并执行崩溃恢复;只读和从实例不能写入,由主实例负责恢复
+        let wal = if options.enable_wal && writable {
+            let wal_dir = options.wal_dir.clone().unwrap_or_else(|| options.data_dir.join("wal"));
+            let mut wal = WriteAheadLog::open(wal_dir.join("mikudb.wal"), options.wal_sync)?.with_db(db.clone());
+            if let Some(archive_dir) = &options.wal_archive_dir {
+                wal = wal.with_archive_dir(archive_dir.clone())?;
+            }
+            let wal = Arc::new(wal);
 
             info!("WAL enabled, performing crash recovery...");
             let recovery = RecoveryManager::new(db.clone(), wal.clone());
@@ -291,25 +488,90 @@ impl StorageEngine {
 
             Some(wal)
         } else {
-            info!("WAL disabled, crash recovery unavailable");
+            if writable {
+                info!("WAL disabled, crash recovery unavailable");
+            }
             None
         };
 
+        // 从实例追赶主实例时不经过集合写入,缓存无法失效
+        let document_cache = (options.document_cache_size > 0
+            && !matches!(options.open_mode, OpenMode::Secondary { .. }))
+        .then(|| Arc::new(DocumentCache::with_policy(options.document_cache_size, options.document_cache_policy)));
+
         Ok(Self {
             db,
             options,
-            collections: RwLock::new(HashMap::new()),
+            collections: DashMap::new(),
             block_cache: Arc::new(block_cache),
             wal,
+            archive_engines: RwLock::new(HashMap::new()),
+            sequences: DashMap::new(),
+            changes: Arc::new(ChangeStream::default()),
+            versions,
+            indexes,
+            rollups: RwLock::new(None),
+            rollup_lock: Mutex::new(()),
+            compaction_stats: CompactionStats::default(),
+            document_cache,
         })
     }
 
+    fn restore_archive_compression(db: &DB) -> StorageResult<()> {
+        let metadata_cf = db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+
+        for policy in Self::read_archive_policies(db, &metadata_cf)? {
+            if policy.path.is_some() {
+                continue;
+            }
+            if let Some(cf) = db.cf_handle(&policy.archive) {
+                db.set_options_cf(
+                    &cf,
+                    &[("compression", tiering::compression_option_name(policy.compression))],
+                )?;
+                debug!("Restored {:?} compression for archive {}", policy.compression, policy.archive);
+            }
+        }
+        Ok(())
+    }
+
+    fn read_archive_policies(db: &DB, metadata_cf: &impl rocksdb::AsColumnFamilyRef) -> StorageResult<Vec<ArchivePolicy>> {
+        let mut policies = Vec::new();
+        for item in db.prefix_iterator_cf(metadata_cf, ARCHIVE_KEY_PREFIX.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(ARCHIVE_KEY_PREFIX.as_bytes()) {
+                break;
+            }
+            match serde_json::from_slice::<ArchivePolicy>(&value) {
+                Ok(policy) => policies.push(policy),
+                Err(e) => warn!("Skipping invalid archive policy {:?}: {}", String::from_utf8_lossy(&key), e),
+            }
+        }
+        Ok(policies)
+    }
+
+    /// # Brief
+    /// 为集合 CF 注册文档合并算子,`Collection::increment` 依赖它。
+    /// 合并后的文档按集合的压缩算法重新编码
+    fn set_document_merge_operator(cf_opts: &mut Options, compression: DocumentCompression) {
+        cf_opts.set_merge_operator(
+            merge::DOCUMENT_MERGE_OPERATOR,
+            move |key: &[u8], existing: Option<&[u8]>, operands: &rocksdb::MergeOperands| {
+                merge::increment_full_merge(key, existing, operands, compression)
+            },
+            merge::increment_partial_merge,
+        );
+    }
+
     fn get_existing_cf_names(path: &Path) -> StorageResult<Vec<String>> {
         if !path.exists() {
             return Ok(vec![
                 DEFAULT_CF.to_string(),
                 METADATA_CF.to_string(),
                 SYSTEM_CF.to_string(),
+                SEQUENCE_CF.to_string(),
             ]);
         }
 
@@ -322,12 +584,16 @@ impl StorageEngine {
                 if !result.contains(&SYSTEM_CF.to_string()) {
                     result.push(SYSTEM_CF.to_string());
                 }
+                if !result.contains(&SEQUENCE_CF.to_string()) {
+                    result.push(SEQUENCE_CF.to_string());
+                }
                 Ok(result)
             }
             Err(_) => Ok(vec![
                 DEFAULT_CF.to_string(),
                 METADATA_CF.to_string(),
                 SYSTEM_CF.to_string(),
+                SEQUENCE_CF.to_string(),
             ]),
         }
     }
@@ -343,25 +609,70 @@ impl StorageEngine {
     /// # Returns
     /// 成功返回集合的 Arc 引用，如果集合已存在则返回错误
     pub fn create_collection(&self, name: &str) -> StorageResult<Arc<crate::collection::Collection>> {
-        let mut collections = self.collections.write();
+        self.create_collection_with_options(name, &Options::default(), None)
+    }
+
+    /// 创建固定大小集合
+    ///
+    /// # Brief
+    /// 创建限制总大小和/或文档数的集合,超出时按插入顺序删除最早的文档
+    ///
+    /// # Arguments
+    /// * `name` - 集合名称
+    /// * `options` - 限制,至少指定一项且不能为 0
+    ///
+    /// # Returns
+    /// 成功返回集合的 Arc 引用;限制无效时返回 InvalidArgument,集合已存在时返回错误
+    pub fn create_capped_collection(
+        &self,
+        name: &str,
+        options: CappedOptions,
+    ) -> StorageResult<Arc<crate::collection::Collection>> {
+        options.validate().map_err(StorageError::InvalidArgument)?;
+        self.create_collection_with_options(name, &Options::default(), Some(options))
+    }
 
-        if collections.contains_key(name) {
+    fn create_collection_with_options(
+        &self,
+        name: &str,
+        cf_opts: &Options,
+        capped: Option<CappedOptions>,
+    ) -> StorageResult<Arc<crate::collection::Collection>> {
+        self.check_writable()?;
+        // 持有该名称所在分片的锁直到创建完成,同名的并发创建只有一个成功
+        let Entry::Vacant(cached) = self.collections.entry(name.to_string()) else {
+            return Err(StorageError::CollectionExists(name.to_string()));
+        };
+        if self.db.cf_handle(name).is_some() {
             return Err(StorageError::CollectionExists(name.to_string()));
         }
 
-        let cf_opts = Options::default();
+        let mut cf_opts = cf_opts.clone();
+        Self::set_document_merge_operator(&mut cf_opts, self.options.document_compression_for(name));
         self.db.create_cf(name, &cf_opts)?;
 
-        let collection = Arc::new(crate::collection::Collection::new(
-            name.to_string(),
-            self.db.clone(),
-        ));
+        let collection = Arc::new(
+            crate::collection::Collection::new(name.to_string(), self.db.clone())
+                .with_change_stream(self.changes.clone())
+                .with_compression(self.options.document_compression_for(name))
+                .with_wal(self.wal.clone())
+                .with_versions(self.versions.clone())
+                .with_document_cache(self.document_cache.clone())
+                .with_capped(capped),
+        );
 
-        collections.insert(name.to_string(), collection.clone());
+        cached.insert(collection.clone());
 
         let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
             StorageError::Internal("Metadata CF not found".to_string())
         })?;
+        if let Some(capped) = capped {
+            self.db.put_cf(
+                &metadata_cf,
+                CappedOptions::metadata_key(name).as_bytes(),
+                serde_json::to_vec(&capped).unwrap(),
+            )?;
+        }
         let key = format!("collection:{}", name);
         let metadata = serde_json::json!({
             "name": name,
@@ -388,20 +699,29 @@ impl StorageEngine {
     /// # Returns
     /// 成功返回集合的 Arc 引用，如果集合不存在则返回错误
     pub fn get_collection(&self, name: &str) -> StorageResult<Arc<crate::collection::Collection>> {
-        let collections = self.collections.read();
-
-        if let Some(collection) = collections.get(name) {
+        if let Some(collection) = self.collections.get(name) {
             return Ok(collection.clone());
         }
-        drop(collections);
 
+        // 并发的首次访问只创建一个集合实例
+        let cached = match self.collections.entry(name.to_string()) {
+            Entry::Occupied(cached) => return Ok(cached.get().clone()),
+            Entry::Vacant(cached) => cached,
+        };
         if self.db.cf_handle(name).is_some() {
-            let mut collections = self.collections.write();
-            let collection = Arc::new(crate::collection::Collection::new(
-                name.to_string(),
-                self.db.clone(),
-            ));
-            collections.insert(name.to_string(), collection.clone());
+            let collection = Arc::new(
+                crate::collection::Collection::new(name.to_string(), self.db.clone())
+                    .with_change_stream(self.changes.clone())
+                    .with_compression(self.options.document_compression_for(name))
+                    .with_wal(self.wal.clone())
+                    .with_versions(self.versions.clone())
+                    .with_document_cache(self.document_cache.clone())
+                    .with_capped(self.capped_options(name)?),
+            );
+            if let Some(options) = self.read_schema_options(name)? {
+                collection.set_schema_options(options);
+            }
+            cached.insert(collection.clone());
             return Ok(collection);
         }
 
@@ -437,17 +757,51 @@ impl StorageEngine {
     /// # Returns
     /// 成功返回 Ok(())，失败返回错误
     pub fn drop_collection(&self, name: &str) -> StorageResult<()> {
-        let mut collections = self.collections.write();
+        self.check_writable()?;
+        if let Some(policy) = self.archive_policy(name)? {
+            if let Some(ref path) = policy.path {
+                self.archive_engine(path, policy.compression)?.drop_collection(name)?;
+            }
+            let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+                StorageError::Internal("Metadata CF not found".to_string())
+            })?;
+            self.db.delete_cf(&metadata_cf, ArchivePolicy::metadata_key(name).as_bytes())?;
+            if policy.path.is_some() {
+                info!("Dropped archive collection: {}", name);
+                return Ok(());
+            }
+        }
 
-        collections.remove(name);
+        for definition in self.indexes.list_indexes(name) {
+            self.indexes.drop_index(&definition.name)?;
+        }
 
+        // 删除列族期间持有该名称所在分片的锁,避免并发的 get_collection 重新缓存集合
+        let cached = self.collections.entry(name.to_string());
         self.db.drop_cf(name)?;
+        if let Entry::Occupied(cached) = cached {
+            cached.remove();
+        }
+        if let Some(cache) = &self.document_cache {
+            cache.invalidate_collection(name);
+        }
+        self.changes.record(name, None, ChangeKind::Invalidate);
 
         let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
             StorageError::Internal("Metadata CF not found".to_string())
         })?;
         let key = format!("collection:{}", name);
         self.db.delete_cf(&metadata_cf, key.as_bytes())?;
+        self.db.delete_cf(&metadata_cf, SchemaOptions::metadata_key(name).as_bytes())?;
+        self.db.delete_cf(&metadata_cf, ExpirePolicy::metadata_key(name).as_bytes())?;
+        self.db.delete_cf(&metadata_cf, CappedOptions::metadata_key(name).as_bytes())?;
+        self.db.delete_cf(&metadata_cf, format!("{}{}", STATS_KEY_PREFIX, name).as_bytes())?;
+        // 删除预聚合集合或其源集合时删除预聚合定义,源集合上的预聚合集合保留为普通集合
+        self.db.delete_cf(&metadata_cf, RollupDefinition::metadata_key(name).as_bytes())?;
+        for rollup in self.list_rollups()?.into_iter().filter(|rollup| rollup.source == name) {
+            self.db.delete_cf(&metadata_cf, RollupDefinition::metadata_key(&rollup.name).as_bytes())?;
+        }
+        self.evict_rollups();
 
         info!("Dropped collection: {}", name);
         Ok(())
@@ -481,107 +835,1740 @@ impl StorageEngine {
         Ok(collections)
     }
 
-    /// 压缩数据库
+    /// 创建数据库
     ///
     /// # Brief
-    /// 触发 RocksDB 的全库压缩操作以回收空间
+    /// 登记一个新的数据库,其中的集合在第一次写入或 CREATE COLLECTION 时创建
     ///
-    /// # Returns
-    /// 成功返回 Ok(())
-    pub fn compact(&self) -> StorageResult<()> {
-        info!("Starting compaction");
-        self.db.compact_range::<&[u8], &[u8]>(None, None);
-        info!("Compaction completed");
+    /// 数据库名不能为空、不超过 64 个字符且不能包含 `.`。默认数据库中已有以 `<name>.`
+    /// 开头的集合时不能创建该数据库,否则这些集合会被归入新数据库。
+    ///
+    /// # Arguments
+    /// * `name` - 数据库名称
+    pub fn create_database(&self, name: &str) -> StorageResult<()> {
+        self.check_writable()?;
+        DatabaseName::new(name).map_err(|e| StorageError::InvalidArgument(e.to_string()))?;
+        if name.contains('.') {
+            return Err(StorageError::InvalidArgument(format!(
+                "Database name cannot contain '.': {}",
+                name
+            )));
+        }
+        if self.database_exists(name)? {
+            return Err(StorageError::DatabaseExists(name.to_string()));
+        }
+        let prefix = format!("{}.", name);
+        if self.list_collections()?.iter().any(|collection| collection.starts_with(&prefix)) {
+            return Err(StorageError::InvalidArgument(format!(
+                "Collections named {}* already exist in the default database",
+                prefix
+            )));
+        }
+
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        let metadata = serde_json::json!({
+            "name": name,
+            "created_at": chrono::Utc::now().to_rfc3339(),
+        });
+        self.db.put_cf(
+            &metadata_cf,
+            format!("{}{}", DATABASE_KEY_PREFIX, name).as_bytes(),
+            serde_json::to_vec(&metadata).unwrap(),
+        )?;
+
+        info!("Created database: {}", name);
         Ok(())
     }
 
-    /// 刷新数据到磁盘
+    /// 删除数据库
     ///
     /// # Brief
-    /// 将内存中的数据刷新到磁盘，确保数据持久化
+    /// 删除数据库及其中的所有集合
+    ///
+    /// # Arguments
+    /// * `name` - 数据库名称,不能是默认数据库
     ///
     /// # Returns
-    /// 成功返回 Ok(())
-    pub fn flush(&self) -> StorageResult<()> {
-        self.db.flush()?;
-        Ok(())
+    /// 删除的集合数
+    pub fn drop_database(&self, name: &str) -> StorageResult<usize> {
+        self.check_writable()?;
+        if name == DEFAULT_DATABASE {
+            return Err(StorageError::InvalidArgument(
+                "The default database cannot be dropped".to_string(),
+            ));
+        }
+        if !self.database_exists(name)? {
+            return Err(StorageError::DatabaseNotFound(name.to_string()));
+        }
+
+        let collections = self.database_collections(name)?;
+        for collection in &collections {
+            match self.drop_collection(&qualified_collection_name(name, collection)) {
+                // 归档集合随源集合的归档策略一起删除时可能已不存在
+                Ok(()) | Err(StorageError::CollectionNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        self.db.delete_cf(&metadata_cf, format!("{}{}", DATABASE_KEY_PREFIX, name).as_bytes())?;
+
+        info!("Dropped database {} with {} collection(s)", name, collections.len());
+        Ok(collections.len())
     }
 
-    /// 获取 RocksDB 统计信息
-    ///
     /// # Brief
-    /// 返回 RocksDB 的详细统计信息
+    /// 判断数据库是否存在,默认数据库总是存在
+    pub fn database_exists(&self, name: &str) -> StorageResult<bool> {
+        if name == DEFAULT_DATABASE {
+            return Ok(true);
+        }
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        Ok(self
+            .db
+            .get_cf(&metadata_cf, format!("{}{}", DATABASE_KEY_PREFIX, name).as_bytes())?
+            .is_some())
+    }
+
+    /// 列出所有数据库
     ///
     /// # Returns
-    /// 统计信息字符串，如果未启用则返回 None
-    pub fn get_statistics(&self) -> Option<String> {
-        self.db.property_value("rocksdb.stats").ok().flatten()
+    /// 按名称排序的数据库名称,包括默认数据库
+    pub fn list_databases(&self) -> StorageResult<Vec<String>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+
+        let mut databases = vec![DEFAULT_DATABASE.to_string()];
+        for item in self.db.prefix_iterator_cf(&metadata_cf, DATABASE_KEY_PREFIX.as_bytes()) {
+            let (key, _) = item?;
+            match std::str::from_utf8(&key).ok().and_then(|key| key.strip_prefix(DATABASE_KEY_PREFIX)) {
+                Some(name) => databases.push(name.to_string()),
+                None => break,
+            }
+        }
+        databases.sort();
+        Ok(databases)
     }
 
-    /// 获取数据库约估大小
+    /// 列出数据库中的集合
     ///
-    /// # Brief
-    /// 返回数据库数据的约估大小（字节）
+    /// # Arguments
+    /// * `database` - 数据库名称
     ///
     /// # Returns
-    /// 约估的字节数
-    pub fn get_approximate_size(&self) -> u64 {
-        self.db
-            .property_int_value("rocksdb.estimate-live-data-size")
-            .unwrap_or(Some(0))
-            .unwrap_or(0)
+    /// 集合在数据库中的名称(不带数据库前缀)
+    pub fn database_collections(&self, database: &str) -> StorageResult<Vec<String>> {
+        let collections = self.list_collections()?;
+        if database != DEFAULT_DATABASE {
+            let prefix = format!("{}.", database);
+            return Ok(collections
+                .iter()
+                .filter_map(|collection| collection.strip_prefix(&prefix).map(str::to_string))
+                .collect());
+        }
+
+        let prefixes: Vec<String> = self
+            .list_databases()?
+            .into_iter()
+            .filter(|name| name != DEFAULT_DATABASE)
+            .map(|name| format!("{}.", name))
+            .collect();
+        Ok(collections
+            .into_iter()
+            .filter(|collection| !prefixes.iter().any(|prefix| collection.starts_with(prefix.as_str())))
+            .collect())
     }
 
-    /// 获取数据库路径
+    /// 设置集合的模式选项
     ///
     /// # Brief
-    /// 返回数据库文件的存储路径
+    /// 持久化集合的字段类型记录/严格类型选项并立即生效
+    ///
+    /// # Arguments
+    /// * `name` - 集合名称
+    /// * `options` - 模式选项
+    pub fn set_schema_options(&self, name: &str, options: SchemaOptions) -> StorageResult<()> {
+        self.check_writable()?;
+        let collection = self.get_collection(name)?;
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        self.db.put_cf(
+            &metadata_cf,
+            SchemaOptions::metadata_key(name).as_bytes(),
+            serde_json::to_vec(&options).unwrap(),
+        )?;
+        collection.set_schema_options(options);
+        info!("Schema options for {}: {:?}", name, options);
+        Ok(())
+    }
+
+    /// 固定大小集合的限制
     ///
     /// # Returns
-    /// 数据库路径的引用
-    pub fn path(&self) -> &Path {
-        self.db.path()
+    /// 普通集合返回 None
+    pub fn capped_options(&self, name: &str) -> StorageResult<Option<CappedOptions>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        match self.db.get_cf(&metadata_cf, CappedOptions::metadata_key(name).as_bytes())? {
+            Some(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(|e| StorageError::Corruption(format!("Invalid capped options for {}: {}", name, e))),
+            None => Ok(None),
+        }
     }
-}
 
-impl Drop for StorageEngine {
-    fn drop(&mut self) {
-        info!("Closing storage engine");
-        if let Err(e) = self.flush() {
-            warn!("Error flushing on close: {}", e);
+    fn read_schema_options(&self, name: &str) -> StorageResult<Option<SchemaOptions>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        match self.db.get_cf(&metadata_cf, SchemaOptions::metadata_key(name).as_bytes())? {
+            Some(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(|e| StorageError::Corruption(format!("Invalid schema options for {}: {}", name, e))),
+            None => Ok(None),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::tempdir;
+    /// 设置集合的过期字段
+    ///
+    /// # Brief
+    /// 持久化或清除集合的过期策略,由后台任务通过 `apply_expire_policy` 执行
+    ///
+    /// # Arguments
+    /// * `name` - 集合名称
+    /// * `field` - 过期时间字段,为 None 时关闭过期
+    pub fn set_expire_policy(&self, name: &str, field: Option<&str>) -> StorageResult<()> {
+        self.check_writable()?;
+        self.get_collection(name)?;
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        let key = ExpirePolicy::metadata_key(name);
+        match field {
+            Some(field) => {
+                let policy = ExpirePolicy {
+                    collection: name.to_string(),
+                    field: field.to_string(),
+                };
+                self.db.put_cf(&metadata_cf, key.as_bytes(), serde_json::to_vec(&policy).unwrap())?;
+                info!("Documents in {} now expire after field {}", name, field);
+            }
+            None => {
+                self.db.delete_cf(&metadata_cf, key.as_bytes())?;
+                info!("Document expiry disabled for {}", name);
+            }
+        }
+        Ok(())
+    }
 
-    #[test]
-    fn test_open_storage() {
-        let dir = tempdir().unwrap();
-        let options = StorageOptions {
-            data_dir: dir.path().to_path_buf(),
-            ..Default::default()
-        };
+    /// 获取集合的过期策略
+    ///
+    /// # Returns
+    /// 未设置过期字段时返回 None
+    pub fn expire_policy(&self, name: &str) -> StorageResult<Option<ExpirePolicy>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        match self.db.get_cf(&metadata_cf, ExpirePolicy::metadata_key(name).as_bytes())? {
+            Some(value) => serde_json::from_slice(&value)
+                .map(Some)
+                .map_err(|e| StorageError::Corruption(format!("Invalid expire policy for {}: {}", name, e))),
+            None => Ok(None),
+        }
+    }
 
-        let engine = StorageEngine::open(options).unwrap();
-        assert!(engine.list_collections().unwrap().is_empty());
+    /// 保存集合的统计信息
+    ///
+    /// # Brief
+    /// 统计信息由查询层序列化,存储层只负责持久化,集合删除时一并清除
+    ///
+    /// # Arguments
+    /// * `name` - 集合名称
+    /// * `stats` - 序列化后的统计信息
+    pub fn set_collection_stats(&self, name: &str, stats: &[u8]) -> StorageResult<()> {
+        self.check_writable()?;
+        self.get_collection(name)?;
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        self.db.put_cf(&metadata_cf, format!("{}{}", STATS_KEY_PREFIX, name).as_bytes(), stats)?;
+        Ok(())
     }
 
-    #[test]
-    fn test_create_collection() {
-        let dir = tempdir().unwrap();
-        let options = StorageOptions {
-            data_dir: dir.path().to_path_buf(),
-            ..Default::default()
-        };
+    /// 获取集合的统计信息
+    ///
+    /// # Returns
+    /// 未执行过统计时返回 None
+    pub fn collection_stats(&self, name: &str) -> StorageResult<Option<Vec<u8>>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        Ok(self.db.get_cf(&metadata_cf, format!("{}{}", STATS_KEY_PREFIX, name).as_bytes())?)
+    }
 
-        let engine = StorageEngine::open(options).unwrap();
-        engine.create_collection("test").unwrap();
+    /// 保存服务器的运行状态
+    ///
+    /// # Brief
+    /// 写入系统列族,存储层不解析内容;同名的状态会被覆盖
+    ///
+    /// # Arguments
+    /// * `key` - 状态名称
+    /// * `state` - 序列化后的状态
+    pub fn save_system_state(&self, key: &str, state: &[u8]) -> StorageResult<()> {
+        self.check_writable()?;
+        let system_cf = self.db.cf_handle(SYSTEM_CF).ok_or_else(|| {
+            StorageError::Internal("System CF not found".to_string())
+        })?;
+        self.db.put_cf(&system_cf, key.as_bytes(), state)?;
+        Ok(())
+    }
 
-        let collections = engine.list_collections().unwrap();
-        assert!(collections.contains(&"test".to_string()));
+    /// 读取保存的服务器运行状态
+    ///
+    /// # Returns
+    /// 未保存过时返回 None
+    pub fn system_state(&self, key: &str) -> StorageResult<Option<Vec<u8>>> {
+        let system_cf = self.db.cf_handle(SYSTEM_CF).ok_or_else(|| {
+            StorageError::Internal("System CF not found".to_string())
+        })?;
+        Ok(self.db.get_cf(&system_cf, key.as_bytes())?)
+    }
+
+    /// 保存自定义函数的模块
+    ///
+    /// # Brief
+    /// 存储层不解析模块内容,由查询层在服务器启动时编译并注册;同名函数会被覆盖
+    ///
+    /// # Arguments
+    /// * `name` - 函数名(小写)
+    /// * `module` - 模块二进制
+    pub fn save_function(&self, name: &str, module: &[u8]) -> StorageResult<()> {
+        self.check_writable()?;
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        self.db.put_cf(&metadata_cf, format!("{}{}", FUNCTION_KEY_PREFIX, name).as_bytes(), module)?;
+        info!("Saved function {} ({} bytes)", name, module.len());
+        Ok(())
+    }
+
+    /// 删除自定义函数的模块
+    ///
+    /// # Returns
+    /// 函数存在时返回 true
+    pub fn drop_function(&self, name: &str) -> StorageResult<bool> {
+        self.check_writable()?;
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        let key = format!("{}{}", FUNCTION_KEY_PREFIX, name);
+        if self.db.get_cf(&metadata_cf, key.as_bytes())?.is_none() {
+            return Ok(false);
+        }
+        self.db.delete_cf(&metadata_cf, key.as_bytes())?;
+        info!("Dropped function {}", name);
+        Ok(true)
+    }
+
+    /// 列出所有保存的自定义函数,返回 (函数名, 模块) 并按函数名排序
+    pub fn stored_functions(&self) -> StorageResult<Vec<(String, Vec<u8>)>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        let mut functions = Vec::new();
+        for item in self.db.prefix_iterator_cf(&metadata_cf, FUNCTION_KEY_PREFIX.as_bytes()) {
+            let (key, value) = item?;
+            let Some(name) = key.strip_prefix(FUNCTION_KEY_PREFIX.as_bytes()) else {
+                break;
+            };
+            functions.push((String::from_utf8_lossy(name).into_owned(), value.to_vec()));
+        }
+        Ok(functions)
+    }
+
+    /// 列出所有过期策略
+    pub fn expire_policies(&self) -> StorageResult<Vec<ExpirePolicy>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        let mut policies = Vec::new();
+        for item in self.db.prefix_iterator_cf(&metadata_cf, EXPIRE_KEY_PREFIX.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(EXPIRE_KEY_PREFIX.as_bytes()) {
+                break;
+            }
+            match serde_json::from_slice::<ExpirePolicy>(&value) {
+                Ok(policy) => policies.push(policy),
+                Err(e) => warn!("Skipping invalid expire policy {:?}: {}", String::from_utf8_lossy(&key), e),
+            }
+        }
+        Ok(policies)
+    }
+
+    /// 执行过期策略
+    ///
+    /// # Brief
+    /// 删除集合中过期字段早于当前时间的文档
+    ///
+    /// # Arguments
+    /// * `policy` - 过期策略
+    ///
+    /// # Returns
+    /// 删除的文档数
+    pub fn apply_expire_policy(&self, policy: &ExpirePolicy) -> StorageResult<u64> {
+        let collection = self.get_collection(&policy.collection)?;
+        let now = chrono::Utc::now();
+
+        let mut removed = 0u64;
+        for doc in collection.find_all()? {
+            if !policy.is_expired(&doc, now) {
+                continue;
+            }
+            if let Some(id) = doc.id() {
+                if collection.delete(id)? {
+                    removed += 1;
+                }
+            }
+        }
+
+        if removed > 0 {
+            info!("Expired {} document(s) from {}", removed, policy.collection);
+        }
+        Ok(removed)
+    }
+
+    /// 创建序列
+    ///
+    /// # Brief
+    /// 持久化序列定义并把已发放个数置 0,同名序列已存在时返回错误
+    ///
+    /// # Arguments
+    /// * `name` - 序列名称
+    /// * `start` - 第一个编号
+    /// * `increment` - 相邻编号的差,不能为 0
+    pub fn create_sequence(&self, name: &str, start: i64, increment: i64) -> StorageResult<SequenceDefinition> {
+        self.check_writable()?;
+        if increment == 0 {
+            return Err(StorageError::InvalidArgument("Sequence increment must not be 0".to_string()));
+        }
+        let cf = self.sequence_cf()?;
+        // 持有该名称所在分片的锁,与 load_sequence 互斥
+        let cached = self.sequences.entry(name.to_string());
+        if self.db.get_cf(&cf, SequenceDefinition::definition_key(name).as_bytes())?.is_some() {
+            return Err(StorageError::SequenceExists(name.to_string()));
+        }
+
+        let definition = SequenceDefinition {
+            name: name.to_string(),
+            start,
+            increment,
+        };
+        // 计数键用 put 覆盖,清除同名旧序列可能残留的合并操作数
+        let mut batch = WriteBatch::default();
+        batch.put_cf(
+            &cf,
+            SequenceDefinition::definition_key(name).as_bytes(),
+            serde_json::to_vec(&definition).unwrap(),
+        );
+        batch.put_cf(&cf, SequenceDefinition::counter_key(name).as_bytes(), sequence::encode_count(0));
+        self.db.write_opt(batch, &self.sequence_write_options())?;
+
+        if let Entry::Occupied(cached) = cached {
+            cached.remove();
+        }
+        info!("Created sequence {} (start {}, increment {})", name, start, increment);
+        Ok(definition)
+    }
+
+    /// 删除序列
+    ///
+    /// # Returns
+    /// 序列不存在时返回 SequenceNotFound
+    pub fn drop_sequence(&self, name: &str) -> StorageResult<()> {
+        self.check_writable()?;
+        let cf = self.sequence_cf()?;
+        let cached = self.sequences.entry(name.to_string());
+        let key = SequenceDefinition::definition_key(name);
+        if self.db.get_cf(&cf, key.as_bytes())?.is_none() {
+            return Err(StorageError::SequenceNotFound(name.to_string()));
+        }
+
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(&cf, key.as_bytes());
+        batch.delete_cf(&cf, SequenceDefinition::counter_key(name).as_bytes());
+        self.db.write_opt(batch, &self.sequence_write_options())?;
+
+        if let Entry::Occupied(cached) = cached {
+            cached.remove();
+        }
+        info!("Dropped sequence {}", name);
+        Ok(())
+    }
+
+    /// 获取序列的下一个编号
+    ///
+    /// # Brief
+    /// 在内存中原子地领取编号,再以合并操作(+1)写入 WAL 和计数键,
+    /// 不读取旧值,并发调用不会拿到重复编号。写入失败时该编号作废,序列中留下空洞。
+    ///
+    /// # Arguments
+    /// * `name` - 序列名称
+    pub fn next_sequence_value(&self, name: &str) -> StorageResult<i64> {
+        let counter = self.load_sequence(name)?;
+        let count = counter.issued.fetch_add(1, Ordering::SeqCst) + 1;
+        let value = counter.definition.value_at(count).ok_or_else(|| {
+            StorageError::InvalidArgument(format!("Sequence {} is exhausted", name))
+        })?;
+
+        let cf = self.sequence_cf()?;
+        self.db.merge_cf_opt(
+            &cf,
+            SequenceDefinition::counter_key(name).as_bytes(),
+            sequence::encode_count(1),
+            &self.sequence_write_options(),
+        )?;
+        Ok(value)
+    }
+
+    /// 获取序列最近发放的编号
+    ///
+    /// # Returns
+    /// 尚未发放过编号时返回 None
+    pub fn current_sequence_value(&self, name: &str) -> StorageResult<Option<i64>> {
+        let counter = self.load_sequence(name)?;
+        Ok(counter.definition.value_at(counter.issued.load(Ordering::SeqCst)))
+    }
+
+    /// 列出所有序列定义,按名称排序
+    pub fn list_sequences(&self) -> StorageResult<Vec<SequenceDefinition>> {
+        let cf = self.sequence_cf()?;
+        let mut definitions = Vec::new();
+        for item in self.db.prefix_iterator_cf(&cf, SEQUENCE_DEFINITION_PREFIX.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(SEQUENCE_DEFINITION_PREFIX.as_bytes()) {
+                break;
+            }
+            match serde_json::from_slice::<SequenceDefinition>(&value) {
+                Ok(definition) => definitions.push(definition),
+                Err(e) => warn!("Skipping invalid sequence {:?}: {}", String::from_utf8_lossy(&key), e),
+            }
+        }
+        Ok(definitions)
+    }
+
+    /// 丢弃缓存的序列计数,下次访问时从磁盘重新加载(恢复备份后调用)
+    pub(crate) fn evict_sequences(&self) {
+        self.sequences.clear();
+    }
+
+    fn load_sequence(&self, name: &str) -> StorageResult<Arc<SequenceCounter>> {
+        if let Some(counter) = self.sequences.get(name) {
+            return Ok(counter.clone());
+        }
+
+        let cf = self.sequence_cf()?;
+        let cached = match self.sequences.entry(name.to_string()) {
+            Entry::Occupied(cached) => return Ok(cached.get().clone()),
+            Entry::Vacant(cached) => cached,
+        };
+        let definition = match self.db.get_cf(&cf, SequenceDefinition::definition_key(name).as_bytes())? {
+            Some(value) => serde_json::from_slice::<SequenceDefinition>(&value)
+                .map_err(|e| StorageError::Corruption(format!("Invalid sequence {}: {}", name, e)))?,
+            None => return Err(StorageError::SequenceNotFound(name.to_string())),
+        };
+        let issued = self
+            .db
+            .get_cf(&cf, SequenceDefinition::counter_key(name).as_bytes())?
+            .map_or(0, |value| sequence::decode_count(&value));
+
+        let counter = Arc::new(SequenceCounter {
+            definition,
+            issued: AtomicU64::new(issued),
+        });
+        cached.insert(counter.clone());
+        Ok(counter)
+    }
+
+    fn sequence_cf(&self) -> StorageResult<Arc<BoundColumnFamily<'_>>> {
+        self.db.cf_handle(SEQUENCE_CF).ok_or_else(|| {
+            StorageError::Internal("Sequence CF not found".to_string())
+        })
+    }
+
+    fn sequence_write_options(&self) -> WriteOptions {
+        let mut write_opts = WriteOptions::default();
+        write_opts.set_sync(self.options.wal_sync == WalSyncPolicy::Always);
+        write_opts
+    }
+
+    /// 创建预聚合
+    ///
+    /// # Brief
+    /// 持久化定义,创建与预聚合同名的集合,并从源集合的现有文档计算全部分组
+    ///
+    /// # Arguments
+    /// * `definition` - 预聚合定义
+    ///
+    /// # Returns
+    /// 分组数
+    pub fn create_rollup(&self, definition: RollupDefinition) -> StorageResult<u64> {
+        self.check_writable()?;
+        definition.validate()?;
+        self.get_collection(&definition.source)?;
+        self.create_collection(&definition.name)?;
+
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        self.db.put_cf(
+            &metadata_cf,
+            RollupDefinition::metadata_key(&definition.name).as_bytes(),
+            serde_json::to_vec(&definition).unwrap(),
+        )?;
+        self.evict_rollups();
+
+        let groups = self.rebuild_rollup(&definition.name)?;
+        info!("Created rollup {} on {} ({} groups)", definition.name, definition.source, groups);
+        Ok(groups)
+    }
+
+    /// 删除预聚合的定义和集合
+    pub fn drop_rollup(&self, name: &str) -> StorageResult<()> {
+        if self.rollup(name)?.is_none() {
+            return Err(StorageError::InvalidArgument(format!("Rollup not found: {}", name)));
+        }
+        self.drop_collection(name)?;
+        info!("Dropped rollup {}", name);
+        Ok(())
+    }
+
+    /// 获取预聚合定义
+    pub fn rollup(&self, name: &str) -> StorageResult<Option<RollupDefinition>> {
+        Ok(self.loaded_rollups()?.iter().find(|rollup| rollup.name == name).cloned())
+    }
+
+    /// 列出所有预聚合定义,按名称排序
+    pub fn list_rollups(&self) -> StorageResult<Vec<RollupDefinition>> {
+        Ok(self.loaded_rollups()?.to_vec())
+    }
+
+    /// 列出源集合上的预聚合定义
+    pub fn rollups_on(&self, source: &str) -> StorageResult<Vec<RollupDefinition>> {
+        Ok(self.loaded_rollups()?.iter().filter(|rollup| rollup.source == source).cloned().collect())
+    }
+
+    /// 重新计算预聚合
+    ///
+    /// # Brief
+    /// 清空预聚合集合,扫描源集合重新计算全部分组
+    ///
+    /// # Returns
+    /// 分组数
+    pub fn rebuild_rollup(&self, name: &str) -> StorageResult<u64> {
+        let definition = self
+            .rollup(name)?
+            .ok_or_else(|| StorageError::InvalidArgument(format!("Rollup not found: {}", name)))?;
+        let _guard = self.rollup_lock.lock();
+        let docs = self.get_collection(&definition.source)?.find_all()?;
+        let groups = definition.summarize(&docs)?;
+
+        let target = self.get_collection(name)?;
+        target.clear()?;
+        let count = groups.len() as u64;
+        for mut group in groups.into_values() {
+            target.upsert(&mut group)?;
+        }
+        Ok(count)
+    }
+
+    /// 维护源集合上的预聚合
+    ///
+    /// # Brief
+    /// 源文档写入成功后调用,把旧内容移出分组、新内容计入分组。
+    /// 移出的值是分组当前的 MIN/MAX 时重新扫描源集合计算该分组。
+    ///
+    /// # Arguments
+    /// * `source` - 源集合名称
+    /// * `removed` - 删除或更新前的文档,插入时为 None
+    /// * `added` - 插入或更新后的文档,删除时为 None
+    pub fn apply_rollups(&self, source: &str, removed: Option<&Document>, added: Option<&Document>) -> StorageResult<()> {
+        let rollups = self.rollups_on(source)?;
+        if rollups.is_empty() {
+            return Ok(());
+        }
+        let _guard = self.rollup_lock.lock();
+        for definition in &rollups {
+            let target = self.get_collection(&definition.name)?;
+            // 重新计算的分组已经包含源集合中的新内容,不能再计入一次
+            let mut recomputed = Vec::new();
+            let changes = removed.map(|doc| (doc, true)).into_iter().chain(added.map(|doc| (doc, false)));
+            for (doc, removing) in changes {
+                let key = definition.group_key(doc);
+                let id = RollupDefinition::group_id(&key)?;
+                if recomputed.contains(&id) {
+                    continue;
+                }
+                match definition.apply(target.get(&id)?, &key, doc, removing)? {
+                    GroupChange::Updated(mut group) => {
+                        target.upsert(&mut group)?;
+                    }
+                    GroupChange::Removed => {
+                        target.delete(&id)?;
+                    }
+                    GroupChange::Recompute => {
+                        let docs = self.get_collection(source)?.find_all()?;
+                        let members = docs.iter().filter(|doc| definition.group_key(doc) == key);
+                        match definition.summarize(members)?.remove(&id) {
+                            Some(mut group) => {
+                                target.upsert(&mut group)?;
+                            }
+                            None => {
+                                target.delete(&id)?;
+                            }
+                        }
+                        recomputed.push(id);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 批量写入
+    ///
+    /// # Brief
+    /// 按顺序执行插入、替换、删除操作,每个操作维护集合索引和预聚合。
+    /// 单个操作失败记录到结果的 `write_errors` 中:有序执行时停止,无序执行时继续。
+    ///
+    /// # Arguments
+    /// * `name` - 集合名称(不存在时创建)
+    /// * `operations` - 写操作列表
+    /// * `ordered` - 是否在第一个失败的操作处停止
+    ///
+    /// # Returns
+    /// 每个操作的结果和失败原因;只有集合无法打开时返回错误
+    pub fn bulk_write(&self, name: &str, operations: Vec<WriteOperation>, ordered: bool) -> StorageResult<BulkWriteResult> {
+        let collection = self.get_or_create_collection(name)?;
+        let mut result = BulkWriteResult::default();
+        for (index, operation) in operations.into_iter().enumerate() {
+            match self.write_one(&collection, index, operation) {
+                Ok(done) => result.record(done),
+                Err(e) => {
+                    result.record_error(index, &e);
+                    if ordered {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn write_one(&self, collection: &crate::collection::Collection, index: usize, operation: WriteOperation) -> StorageResult<OperationResult> {
+        let mut result = OperationResult::new(index);
+        match operation {
+            WriteOperation::Insert(mut doc) => {
+                let ids = collection.insert_many_indexed(std::slice::from_mut(&mut doc), &self.indexes)?;
+                self.apply_rollups(collection.name(), None, Some(&doc))?;
+                result.inserted = 1;
+                result.ids = ids;
+            }
+            WriteOperation::Replace { id, mut document, upsert } => {
+                document.set_id(id);
+                match collection.get(&id)? {
+                    Some(original) => {
+                        result.matched = 1;
+                        if original != document {
+                            self.indexes.unindex_document(collection.name(), &original)?;
+                            let written = self
+                                .indexes
+                                .index_document(collection.name(), &document)
+                                .and_then(|()| match collection.update(&id, &document) {
+                                    Ok(()) => Ok(()),
+                                    Err(e) => {
+                                        self.indexes.unindex_document(collection.name(), &document)?;
+                                        Err(e)
+                                    }
+                                });
+                            if let Err(e) = written {
+                                self.indexes.index_document(collection.name(), &original)?;
+                                return Err(e);
+                            }
+                            self.apply_rollups(collection.name(), Some(&original), Some(&document))?;
+                            result.modified = 1;
+                        }
+                    }
+                    None if upsert => {
+                        collection.insert_many_indexed(std::slice::from_mut(&mut document), &self.indexes)?;
+                        self.apply_rollups(collection.name(), None, Some(&document))?;
+                        result.upserted = 1;
+                        result.ids.push(id);
+                    }
+                    None => {}
+                }
+            }
+            WriteOperation::Delete(id) => {
+                if let Some(original) = collection.get(&id)? {
+                    self.indexes.unindex_document(collection.name(), &original)?;
+                    if collection.delete(&id)? {
+                        self.apply_rollups(collection.name(), Some(&original), None)?;
+                        result.deleted = 1;
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn loaded_rollups(&self) -> StorageResult<Arc<Vec<RollupDefinition>>> {
+        if let Some(rollups) = self.rollups.read().as_ref() {
+            return Ok(rollups.clone());
+        }
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        let mut definitions = Vec::new();
+        for item in self.db.prefix_iterator_cf(&metadata_cf, ROLLUP_KEY_PREFIX.as_bytes()) {
+            let (key, value) = item?;
+            if !key.starts_with(ROLLUP_KEY_PREFIX.as_bytes()) {
+                break;
+            }
+            match serde_json::from_slice::<RollupDefinition>(&value) {
+                Ok(definition) => definitions.push(definition),
+                Err(e) => warn!("Skipping invalid rollup {:?}: {}", String::from_utf8_lossy(&key), e),
+            }
+        }
+        let definitions = Arc::new(definitions);
+        *self.rollups.write() = Some(definitions.clone());
+        Ok(definitions)
+    }
+
+    /// 丢弃缓存的预聚合定义,下次访问时从磁盘重新加载(恢复备份后调用)
+    pub(crate) fn evict_rollups(&self) {
+        self.rollups.write().take();
+    }
+
+    /// 创建或更新归档集合
+    ///
+    /// # Brief
+    /// 按归档策略准备归档集合并持久化策略
+    ///
+    /// 策略未指定路径时,归档集合是本库中的一个独立 CF,使用策略中的压缩算法;
+    /// 指定路径时,在该路径上打开(或复用)独立的存储实例并在其中创建集合。
+    ///
+    /// # Arguments
+    /// * `policy` - 归档策略
+    ///
+    /// # Returns
+    /// 归档集合的 Arc 引用
+    pub fn create_archive(&self, policy: &ArchivePolicy) -> StorageResult<Arc<crate::collection::Collection>> {
+        if policy.source == policy.archive {
+            return Err(StorageError::Internal(
+                "Archive collection must differ from its source".to_string(),
+            ));
+        }
+
+        let collection = match policy.path {
+            Some(ref path) => {
+                let engine = self.archive_engine(path, policy.compression)?;
+                engine.create_archive(&ArchivePolicy {
+                    path: None,
+                    ..policy.clone()
+                })?
+            }
+            None => match self.db.cf_handle(&policy.archive) {
+                Some(cf) => {
+                    self.db.set_options_cf(
+                        &cf,
+                        &[("compression", tiering::compression_option_name(policy.compression))],
+                    )?;
+                    drop(cf);
+                    self.get_collection(&policy.archive)?
+                }
+                None => {
+                    let mut cf_opts = Options::default();
+                    cf_opts.set_compression_type(tiering::to_rocksdb_compression(policy.compression));
+                    self.create_collection_with_options(&policy.archive, &cf_opts, None)?
+                }
+            },
+        };
+
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        self.db.put_cf(
+            &metadata_cf,
+            ArchivePolicy::metadata_key(&policy.archive).as_bytes(),
+            serde_json::to_vec(policy).map_err(|e| StorageError::Internal(e.to_string()))?,
+        )?;
+
+        Ok(collection)
+    }
+
+    /// 获取归档策略
+    ///
+    /// # Arguments
+    /// * `archive` - 归档集合名称
+    ///
+    /// # Returns
+    /// 不是归档集合时返回 None
+    pub fn archive_policy(&self, archive: &str) -> StorageResult<Option<ArchivePolicy>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        match self.db.get_cf(&metadata_cf, ArchivePolicy::metadata_key(archive).as_bytes())? {
+            Some(value) => serde_json::from_slice(&value)
+                .map(Some)
+                .map_err(|e| StorageError::Corruption(format!("Invalid archive policy: {}", e))),
+            None => Ok(None),
+        }
+    }
+
+    /// 列出所有归档策略
+    pub fn archive_policies(&self) -> StorageResult<Vec<ArchivePolicy>> {
+        let metadata_cf = self.db.cf_handle(METADATA_CF).ok_or_else(|| {
+            StorageError::Internal("Metadata CF not found".to_string())
+        })?;
+        Self::read_archive_policies(&self.db, &metadata_cf)
+    }
+
+    /// 获取归档集合
+    ///
+    /// # Brief
+    /// 根据归档策略定位归档集合,外部路径上的集合会打开对应的存储实例
+    ///
+    /// # Arguments
+    /// * `policy` - 归档策略
+    pub fn archive_collection(&self, policy: &ArchivePolicy) -> StorageResult<Arc<crate::collection::Collection>> {
+        match policy.path {
+            Some(ref path) => self.archive_engine(path, policy.compression)?.get_collection(&policy.archive),
+            None => self.get_collection(&policy.archive),
+        }
+    }
+
+    /// 获取源集合的所有归档集合
+    ///
+    /// # Arguments
+    /// * `source` - 源集合名称
+    ///
+    /// # Returns
+    /// 归档集合列表,按策略名称排序
+    pub fn archives_of(&self, source: &str) -> StorageResult<Vec<Arc<crate::collection::Collection>>> {
+        self.archive_policies()?
+            .iter()
+            .filter(|policy| policy.source == source)
+            .map(|policy| self.archive_collection(policy))
+            .collect()
+    }
+
+    /// 执行归档策略
+    ///
+    /// # Brief
+    /// 把源集合中创建时间早于阈值的文档迁移到归档集合
+    ///
+    /// 文档创建时间取自 `_id` 中的时间戳。先写入归档集合再从源集合删除,
+    /// 中途失败时重新执行即可,不会丢失文档。
+    ///
+    /// # Arguments
+    /// * `policy` - 归档策略
+    ///
+    /// # Returns
+    /// 迁移的文档数
+    pub fn apply_archive_policy(&self, policy: &ArchivePolicy) -> StorageResult<u64> {
+        let source = self.get_collection(&policy.source)?;
+        let archive = self.archive_collection(policy)?;
+
+        let now = chrono::Utc::now().timestamp().max(0) as u64;
+        let cutoff = now.saturating_sub(policy.older_than_secs);
+
+        let mut moved = 0u64;
+        for mut doc in source.find_all()? {
+            let Some(id) = doc.id().copied() else {
+                continue;
+            };
+            if u64::from(id.timestamp()) >= cutoff {
+                continue;
+            }
+            archive.upsert(&mut doc)?;
+            source.delete(&id)?;
+            moved += 1;
+        }
+
+        info!(
+            "Archived {} document(s) from {} to {}",
+            moved, policy.source, policy.archive
+        );
+        Ok(moved)
+    }
+
+    fn archive_engine(
+        &self,
+        path: &Path,
+        compression: CompressionType,
+    ) -> StorageResult<Arc<StorageEngine>> {
+        if let Some(engine) = self.archive_engines.read().get(path) {
+            return Ok(engine.clone());
+        }
+
+        let mut engines = self.archive_engines.write();
+        if let Some(engine) = engines.get(path) {
+            return Ok(engine.clone());
+        }
+
+        info!("Opening archive storage at {:?}", path);
+        let engine = Arc::new(StorageEngine::open(StorageOptions {
+            data_dir: path.to_path_buf(),
+            compression,
+            ..self.options.clone()
+        })?);
+        engines.insert(path.to_path_buf(), engine.clone());
+        Ok(engine)
+    }
+
+    /// 压缩数据库
+    ///
+    /// # Brief
+    /// 触发 RocksDB 的全库压缩操作以回收空间
+    ///
+    /// # Returns
+    /// 成功返回 Ok(())
+    pub fn compact(&self) -> StorageResult<()> {
+        info!("Starting compaction");
+        self.db.compact_range::<&[u8], &[u8]>(None, None);
+        info!("Compaction completed");
+        Ok(())
+    }
+
+    /// 压缩集合
+    ///
+    /// # Brief
+    /// 手动压缩集合的列族及其所有索引的列族,回收已删除和被覆盖的数据占用的空间
+    ///
+    /// # Arguments
+    /// * `name` - 集合名称
+    ///
+    /// # Returns
+    /// 压缩前后的 SST 文件大小;集合不存在时返回错误
+    pub fn compact_collection(&self, name: &str) -> StorageResult<CompactionReport> {
+        self.check_writable()?;
+        let collection = self.get_collection(name)?;
+        info!("Starting compaction of collection {}", name);
+        let report = compaction::timed(name, |report| {
+            *report = collection.compact()?;
+            for definition in self.indexes.list_indexes(name) {
+                compaction::compact_column_family(&self.db, &format!("idx_{}", definition.name), report)?;
+            }
+            Ok(())
+        })?;
+        self.compaction_stats
+            .record_compaction(report.reclaimed_bytes(), Duration::from_millis(report.duration_ms));
+        info!(
+            "Compacted collection {}: reclaimed {} byte(s) in {} ms",
+            name,
+            report.reclaimed_bytes(),
+            report.duration_ms
+        );
+        Ok(report)
+    }
+
+    /// # Brief
+    /// 获取集合压缩的累计统计
+    pub fn compaction_stats(&self) -> CompactionStatsSnapshot {
+        self.compaction_stats.snapshot()
+    }
+
+    /// # Brief
+    /// 获取文档缓存的命中统计,未启用文档缓存时返回 None
+    pub fn document_cache_stats(&self) -> Option<CacheStats> {
+        self.document_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// 刷新数据到磁盘
+    ///
+    /// # Brief
+    /// 将内存中的数据刷新到磁盘，确保数据持久化
+    ///
+    /// # Returns
+    /// 成功返回 Ok(())
+    pub fn flush(&self) -> StorageResult<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// 获取 RocksDB 统计信息
+    ///
+    /// # Brief
+    /// 返回 RocksDB 的详细统计信息
+    ///
+    /// # Returns
+    /// 统计信息字符串，如果未启用则返回 None
+    pub fn get_statistics(&self) -> Option<String> {
+        self.db.property_value("rocksdb.stats").ok().flatten()
+    }
+
+    /// 获取数据库约估大小
+    ///
+    /// # Brief
+    /// 返回数据库数据的约估大小（字节）
+    ///
+    /// # Returns
+    /// 约估的字节数
+    pub fn get_approximate_size(&self) -> u64 {
+        self.db
+            .property_int_value("rocksdb.estimate-live-data-size")
+            .unwrap_or(Some(0))
+            .unwrap_or(0)
+    }
+
+    /// # Brief
+    /// 获取集合写入的变更流
+    pub fn change_stream(&self) -> &Arc<ChangeStream> {
+        &self.changes
+    }
+
+    /// # Brief
+    /// 开始一个读快照
+    ///
+    /// 快照开始后的写入为被修改的文档保存旧版本,`Collection::get_as_of` 和
+    /// `Collection::find_all_as_of` 按快照读取。快照释放后旧版本被回收
+    pub fn begin_snapshot(&self) -> ReadSnapshot {
+        self.versions.begin_snapshot()
+    }
+
+    /// # Brief
+    /// 获取文档版本存储
+    pub fn versions(&self) -> &Arc<VersionStore> {
+        &self.versions
+    }
+
+    /// # Brief
+    /// 获取打开时使用的配置
+    pub fn options(&self) -> &StorageOptions {
+        &self.options
+    }
+
+    /// # Brief
+    /// 获取索引引擎
+    pub fn indexes(&self) -> &IndexEngine {
+        &self.indexes
+    }
+
+    /// # Brief
+    /// 获取 WAL 累计统计
+    ///
+    /// # Returns
+    /// 未启用 WAL 或以只读方式打开时返回 None
+    pub fn wal_stats(&self) -> Option<WalStats> {
+        self.wal.as_ref().map(|wal| wal.stats())
+    }
+
+    /// 获取 WAL,未启用或以只读方式打开时为 None
+    pub(crate) fn wal(&self) -> Option<&Arc<WriteAheadLog>> {
+        self.wal.as_ref()
+    }
+
+    /// # Brief
+    /// 创建时间点一致的物理快照,见 [`crate::snapshot`]
+    ///
+    /// # Arguments
+    /// * `path` - 快照目录,已有快照时返回错误
+    ///
+    /// # Returns
+    /// 快照清单
+    pub fn create_snapshot(&self, path: impl AsRef<Path>) -> StorageResult<SnapshotManifest> {
+        snapshot::create_snapshot(self, path.as_ref())
+    }
+
+    /// # Brief
+    /// 把快照恢复到 `options.data_dir` 并打开
+    ///
+    /// # Arguments
+    /// * `path` - 快照目录
+    /// * `options` - 存储引擎配置,数据目录中不能已有数据库
+    ///
+    /// # Returns
+    /// 打开的存储引擎
+    pub fn restore_from(path: impl AsRef<Path>, options: StorageOptions) -> StorageResult<Self> {
+        snapshot::restore_snapshot(path.as_ref(), &options)?;
+        Self::open(options)
+    }
+
+    /// # Brief
+    /// 导出 `since_lsn` 之后的 WAL 段作为增量备份,见 [`crate::snapshot`]
+    ///
+    /// # Arguments
+    /// * `since_lsn` - 起始 LSN: 快照清单的 `wal_lsn` 或上一次增量备份的 `until_lsn`
+    /// * `path` - 增量备份目录,已有备份时返回错误
+    ///
+    /// # Returns
+    /// 增量备份清单
+    pub fn backup_incremental(&self, since_lsn: u64, path: impl AsRef<Path>) -> StorageResult<IncrementManifest> {
+        snapshot::backup_incremental(self, since_lsn, path.as_ref())
+    }
+
+    /// # Brief
+    /// 把快照和一组增量备份依次恢复到 `options.data_dir` 并打开
+    ///
+    /// # Arguments
+    /// * `snapshot` - 快照目录
+    /// * `increments` - 增量备份目录,按备份顺序排列
+    /// * `options` - 存储引擎配置,数据目录中不能已有数据库
+    ///
+    /// # Returns
+    /// 打开的存储引擎
+    pub fn restore_with_increments<P: AsRef<Path>>(
+        snapshot: impl AsRef<Path>,
+        increments: &[P],
+        options: StorageOptions,
+    ) -> StorageResult<Self> {
+        let engine = Self::restore_from(snapshot, options)?;
+        for increment in increments {
+            snapshot::apply_increment(&engine, increment.as_ref())?;
+        }
+        Ok(engine)
+    }
+
+    /// # Brief
+    /// 删除结束 LSN 不大于 `before_lsn` 的 WAL 归档段
+    ///
+    /// # Returns
+    /// 删除的段数,未启用 WAL 或未配置归档目录时为 0
+    pub fn prune_wal_archive(&self, before_lsn: u64) -> StorageResult<usize> {
+        match &self.wal {
+            Some(wal) => wal.prune_archive(before_lsn),
+            None => Ok(0),
+        }
+    }
+
+    /// 获取底层 RocksDB 实例
+    pub(crate) fn db(&self) -> &Arc<DB> {
+        &self.db
+    }
+
+    /// 丢弃缓存的集合实例
+    ///
+    /// 直接改写集合数据(例如从备份恢复)后调用,下次访问时重新加载模式选项。
+    pub(crate) fn evict_collection(&self, name: &str) {
+        self.collections.remove(name);
+        self.changes.record(name, None, ChangeKind::Invalidate);
+    }
+
+    /// 获取数据库路径
+    ///
+    /// # Brief
+    /// 返回数据库文件的存储路径
+    ///
+    /// # Returns
+    /// 数据库路径的引用
+    pub fn path(&self) -> &Path {
+        self.db.path()
+    }
+
+    /// 获取打开方式
+    pub fn open_mode(&self) -> &OpenMode {
+        &self.options.open_mode
+    }
+
+    /// 追赶主实例
+    ///
+    /// # Brief
+    /// 从实例重放主实例新写入的 WAL 和 MANIFEST,之后的读取可以看到主实例已提交的写入。
+    /// 主实例在打开从实例之后新建的集合需要重新打开从实例才能访问
+    ///
+    /// # Returns
+    /// 不是从实例时返回 InvalidArgument
+    pub fn catch_up_with_primary(&self) -> StorageResult<()> {
+        if !matches!(self.options.open_mode, OpenMode::Secondary { .. }) {
+            return Err(StorageError::InvalidArgument(
+                "catch_up_with_primary requires a secondary instance".to_string(),
+            ));
+        }
+        self.db.try_catch_up_with_primary()?;
+        Ok(())
+    }
+
+    /// 只读和从实例拒绝修改集合、序列等元数据
+    fn check_writable(&self) -> StorageResult<()> {
+        match self.options.open_mode {
+            OpenMode::ReadWrite => Ok(()),
+            _ => Err(StorageError::ReadOnly),
+        }
+    }
+}
+
+impl Drop for StorageEngine {
+    fn drop(&mut self) {
+        info!("Closing storage engine");
+        if let Err(e) = self.flush() {
+            warn!("Error flushing on close: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_open_storage() {
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let engine = StorageEngine::open(options).unwrap();
+        assert!(engine.list_collections().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_collection() {
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let engine = StorageEngine::open(options).unwrap();
+        engine.create_collection("test").unwrap();
+
+        let collections = engine.list_collections().unwrap();
+        assert!(collections.contains(&"test".to_string()));
+    }
+
+    #[test]
+    fn test_databases() {
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let engine = StorageEngine::open(options).unwrap();
+        engine.create_collection("users").unwrap();
+        engine.create_database("shop").unwrap();
+        assert!(matches!(engine.create_database("shop"), Err(StorageError::DatabaseExists(_))));
+        assert!(engine.create_database("a.b").is_err());
+        engine.create_collection(&qualified_collection_name("shop", "users")).unwrap();
+        engine.create_collection(&qualified_collection_name("shop", "orders")).unwrap();
+
+        assert_eq!(engine.list_databases().unwrap(), vec!["default", "shop"]);
+        assert_eq!(engine.database_collections(DEFAULT_DATABASE).unwrap(), vec!["users"]);
+        let mut collections = engine.database_collections("shop").unwrap();
+        collections.sort();
+        assert_eq!(collections, vec!["orders", "users"]);
+
+        assert_eq!(engine.drop_database("shop").unwrap(), 2);
+        assert!(!engine.database_exists("shop").unwrap());
+        assert_eq!(engine.list_collections().unwrap(), vec!["users"]);
+        assert!(engine.drop_database(DEFAULT_DATABASE).is_err());
+    }
+
+    #[test]
+    fn test_expire_policy() {
+        use mikudb_boml::{BomlValue, Document};
+
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        let engine = StorageEngine::open(options).unwrap();
+        let sessions = engine.create_collection("sessions").unwrap();
+        let now = chrono::Utc::now();
+        for offset in [-60, 3600] {
+            let mut doc = Document::new();
+            doc.insert("expires_at", BomlValue::DateTime(now + chrono::Duration::seconds(offset)));
+            sessions.insert(&mut doc).unwrap();
+        }
+
+        engine.set_expire_policy("sessions", Some("expires_at")).unwrap();
+        let policies = engine.expire_policies().unwrap();
+        assert_eq!(policies.len(), 1);
+        assert_eq!(engine.apply_expire_policy(&policies[0]).unwrap(), 1);
+        assert_eq!(sessions.count().unwrap(), 1);
+
+        engine.set_expire_policy("sessions", None).unwrap();
+        assert!(engine.expire_policy("sessions").unwrap().is_none());
+        assert!(engine.set_expire_policy("missing", Some("expires_at")).is_err());
+    }
+
+    #[test]
+    fn test_capped_collection() {
+        use mikudb_boml::{BomlValue, Document};
+
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let seqs = |collection: &crate::collection::Collection| -> Vec<i64> {
+            collection
+                .find_all()
+                .unwrap()
+                .iter()
+                .map(|doc| doc.get("seq").and_then(BomlValue::as_i64).unwrap())
+                .collect()
+        };
+        let doc = |seq: i64| {
+            let mut doc = Document::new();
+            doc.insert("seq", BomlValue::Int64(seq));
+            doc
+        };
+
+        {
+            let engine = StorageEngine::open(options.clone()).unwrap();
+            assert!(engine.create_capped_collection("bad", CappedOptions::default()).is_err());
+            let capped = CappedOptions { max_size: None, max_documents: Some(3) };
+            let logs = engine.create_capped_collection("logs", capped).unwrap();
+            for seq in 0..5 {
+                logs.insert(&mut doc(seq)).unwrap();
+            }
+            assert_eq!(seqs(&logs), vec![2, 3, 4]);
+
+            let mut batch: Vec<Document> = (5..9).map(doc).collect();
+            logs.insert_many(&mut batch).unwrap();
+            assert_eq!(seqs(&logs), vec![6, 7, 8]);
+        }
+
+        // 限制随元数据持久化,删除后重新统计用量
+        let engine = StorageEngine::open(options).unwrap();
+        let logs = engine.get_collection("logs").unwrap();
+        assert_eq!(logs.capped_options().unwrap().max_documents, Some(3));
+        let first = logs.find_all().unwrap()[0].id().copied().unwrap();
+        logs.delete(&first).unwrap();
+        logs.insert(&mut doc(9)).unwrap();
+        assert_eq!(seqs(&logs), vec![7, 8, 9]);
+        logs.insert(&mut doc(10)).unwrap();
+        assert_eq!(seqs(&logs), vec![8, 9, 10]);
+
+        let capped = CappedOptions { max_size: Some(200), max_documents: None };
+        let small = engine.create_capped_collection("small", capped).unwrap();
+        let mut large = Document::new();
+        large.insert("payload", BomlValue::String("x".repeat(500).into()));
+        assert!(matches!(small.insert(&mut large), Err(StorageError::InvalidArgument(_))));
+        for seq in 0..20 {
+            small.insert(&mut doc(seq)).unwrap();
+        }
+        let kept = seqs(&small);
+        assert!(kept.len() < 20 && kept.ends_with(&[19]));
+
+        engine.drop_collection("logs").unwrap();
+        assert!(engine.capped_options("logs").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_document_cache() {
+        use mikudb_boml::Document;
+
+        let dir = tempdir().unwrap();
+        let engine = StorageEngine::open(StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            document_cache_size: 1024 * 1024,
+            document_cache_policy: EvictionPolicy::Lfu,
+            ..Default::default()
+        })
+        .unwrap();
+        let users = engine.create_collection("users").unwrap();
+        let mut doc = Document::new();
+        doc.insert("name", "miku");
+        let id = users.insert(&mut doc).unwrap();
+
+        users.get(&id).unwrap();
+        users.get(&id).unwrap();
+        let stats = engine.document_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.policy, EvictionPolicy::Lfu);
+
+        // 写入使缓存的文档失效,之后读到新内容
+        doc.insert("name", "rin");
+        users.update(&id, &doc).unwrap();
+        assert_eq!(users.get(&id).unwrap().unwrap().get_str("name"), Some("rin"));
+        users.increment(&id, &[("visits".to_string(), mikudb_boml::BomlValue::Int64(2))]).unwrap();
+        assert_eq!(users.find_by_ids(&[id]).unwrap()[0].get_i64("visits"), Some(2));
+        users.delete(&id).unwrap();
+        assert!(users.get(&id).unwrap().is_none());
+
+        users.insert(&mut doc).unwrap();
+        users.get(&id).unwrap();
+        engine.drop_collection("users").unwrap();
+        let users = engine.create_collection("users").unwrap();
+        assert!(users.get(&id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_compact_collection() {
+        use crate::index::{IndexDefinition, IndexField, IndexOrder, IndexType, KeyEncoding, OversizedKeyPolicy};
+        use mikudb_boml::Document;
+
+        let dir = tempdir().unwrap();
+        let engine = StorageEngine::open(StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let events = engine.create_collection("events").unwrap();
+        engine
+            .indexes()
+            .create_index(IndexDefinition {
+                name: "events_kind".to_string(),
+                collection: "events".to_string(),
+                fields: vec![IndexField { path: "kind".to_string(), order: IndexOrder::Ascending }],
+                index_type: IndexType::BTree,
+                unique: false,
+                sparse: false,
+                ttl_seconds: None,
+                key_encoding: KeyEncoding::Memcomparable,
+                max_key_size: None,
+                oversized_keys: OversizedKeyPolicy::Reject,
+            })
+            .unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..200 {
+            let mut doc = Document::new();
+            doc.insert("kind", format!("kind-{}", i % 10));
+            doc.insert("payload", "x".repeat(200));
+            ids.push(events.insert(&mut doc).unwrap());
+        }
+        events.compact().unwrap();
+        for id in &ids {
+            events.delete(id).unwrap();
+        }
+
+        let report = engine.compact_collection("events").unwrap();
+        assert_eq!(report.column_families, vec!["events".to_string(), "idx_events_kind".to_string()]);
+        assert!(report.reclaimed_bytes() > 0);
+        assert_eq!(engine.compaction_stats().compactions_completed, 1);
+        assert!(matches!(engine.compact_collection("missing"), Err(StorageError::CollectionNotFound(_))));
+    }
+
+    #[test]
+    fn test_bulk_write() {
+        use crate::bulk::WriteOperation;
+        use crate::index::{IndexDefinition, IndexField, IndexOrder, IndexType, KeyEncoding, OversizedKeyPolicy};
+
+        let dir = tempdir().unwrap();
+        let engine = StorageEngine::open(StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        engine.create_collection("users").unwrap();
+        engine
+            .indexes()
+            .create_index(IndexDefinition {
+                name: "users_email".to_string(),
+                collection: "users".to_string(),
+                fields: vec![IndexField { path: "email".to_string(), order: IndexOrder::Ascending }],
+                index_type: IndexType::BTree,
+                unique: true,
+                sparse: false,
+                ttl_seconds: None,
+                key_encoding: KeyEncoding::Memcomparable,
+                max_key_size: None,
+                oversized_keys: OversizedKeyPolicy::Reject,
+            })
+            .unwrap();
+        let user = |email: &str| {
+            let mut doc = Document::new();
+            doc.insert("email", email);
+            doc
+        };
+
+        let mut first = user("a");
+        let id = *first.ensure_id();
+        let operations = vec![
+            WriteOperation::Insert(first),
+            WriteOperation::Insert(user("a")),
+            WriteOperation::Insert(user("b")),
+        ];
+        let ordered = engine.bulk_write("users", operations.clone(), true).unwrap();
+        assert_eq!(ordered.inserted, 1);
+        assert_eq!(ordered.write_errors.len(), 1);
+        assert_eq!(ordered.write_errors[0].index, 1);
+        assert_eq!(ordered.write_errors[0].code, Some(crate::DUPLICATE_KEY_CODE));
+
+        let unordered = engine.bulk_write("users", operations[1..].to_vec(), false).unwrap();
+        assert_eq!(unordered.inserted, 1);
+        assert_eq!(unordered.results[0].index, 1);
+        assert_eq!(unordered.write_errors[0].index, 0);
+
+        let missing = ObjectId::new();
+        let result = engine
+            .bulk_write(
+                "users",
+                vec![
+                    WriteOperation::Replace { id, document: user("c"), upsert: false },
+                    WriteOperation::Insert(user("a")),
+                    WriteOperation::Replace { id: missing, document: user("d"), upsert: false },
+                    WriteOperation::Replace { id: missing, document: user("d"), upsert: true },
+                    WriteOperation::Delete(id),
+                ],
+                true,
+            )
+            .unwrap();
+        assert!(result.is_ok());
+        assert_eq!((result.matched, result.modified, result.upserted, result.deleted), (1, 1, 1, 1));
+        assert_eq!(result.results[3].ids, vec![missing]);
+        assert!(engine.get_collection("users").unwrap().get(&id).unwrap().is_none());
+        assert!(engine.bulk_write("users", vec![WriteOperation::Insert(user("c"))], true).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_sequence() {
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        {
+            let engine = Arc::new(StorageEngine::open(options.clone()).unwrap());
+            engine.create_sequence("order_no", 1000, 1).unwrap();
+            assert!(engine.create_sequence("order_no", 1, 1).is_err());
+            assert!(engine.create_sequence("bad", 1, 0).is_err());
+            assert_eq!(engine.current_sequence_value("order_no").unwrap(), None);
+
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let engine = engine.clone();
+                    std::thread::spawn(move || {
+                        (0..25).map(|_| engine.next_sequence_value("order_no").unwrap()).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            let mut values: Vec<i64> = handles.into_iter().flat_map(|h| h.join().unwrap()).collect();
+            values.sort();
+            assert_eq!(values, (1000..1100).collect::<Vec<_>>());
+            assert!(engine.next_sequence_value("missing").is_err());
+        }
+
+        let engine = StorageEngine::open(options).unwrap();
+        assert_eq!(engine.current_sequence_value("order_no").unwrap(), Some(1099));
+        assert_eq!(engine.next_sequence_value("order_no").unwrap(), 1100);
+        assert_eq!(engine.list_sequences().unwrap().len(), 1);
+
+        engine.drop_sequence("order_no").unwrap();
+        assert!(engine.list_sequences().unwrap().is_empty());
+        engine.create_sequence("order_no", 1, 1).unwrap();
+        assert_eq!(engine.next_sequence_value("order_no").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_stored_functions() {
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+
+        {
+            let engine = StorageEngine::open(options.clone()).unwrap();
+            engine.save_function("score", b"v1").unwrap();
+            engine.save_function("rank", b"rank").unwrap();
+            engine.save_function("score", b"v2").unwrap();
+        }
+
+        let engine = StorageEngine::open(options).unwrap();
+        assert_eq!(
+            engine.stored_functions().unwrap(),
+            vec![("rank".to_string(), b"rank".to_vec()), ("score".to_string(), b"v2".to_vec())]
+        );
+        assert!(engine.drop_function("rank").unwrap());
+        assert!(!engine.drop_function("rank").unwrap());
+        assert_eq!(engine.stored_functions().unwrap().len(), 1);
+        assert!(engine.list_collections().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_document_compression() {
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            collection_compression: HashMap::from([("articles".to_string(), DocumentCompression::Zstd)]),
+            ..Default::default()
+        };
+        let id = {
+            let engine = StorageEngine::open(options.clone()).unwrap();
+            let stored_version = |name: &str, id: &ObjectId| {
+                let cf = engine.db.cf_handle(name).unwrap();
+                let mut key = vec![b'd'];
+                key.extend_from_slice(id.as_bytes());
+                engine.db.get_cf(&cf, key).unwrap().unwrap()[4]
+            };
+
+            let mut doc = Document::new();
+            doc.insert("body", "lorem ipsum ".repeat(200));
+            doc.insert("views", 1);
+            let articles = engine.create_collection("articles").unwrap();
+            let plain = engine.create_collection("plain").unwrap();
+            let id = articles.insert(&mut doc.clone()).unwrap();
+            let plain_id = plain.insert(&mut doc).unwrap();
+            assert_eq!(stored_version("articles", &id), 2);
+            assert_eq!(stored_version("plain", &plain_id), 1);
+
+            articles.increment(&id, &[("views".to_string(), BomlValue::Int32(2))]).unwrap();
+            engine.compact().unwrap();
+            assert_eq!(stored_version("articles", &id), 2);
+            id
+        };
+
+        let engine = StorageEngine::open(options).unwrap();
+        let stored = engine.get_collection("articles").unwrap().get(&id).unwrap().unwrap();
+        assert_eq!(stored.get("views").and_then(BomlValue::as_i64), Some(3));
+        assert_eq!(stored.get_str("body").map(str::len), Some(2400));
+    }
+
+    #[test]
+    fn test_open_modes() {
+        use mikudb_boml::{BomlValue, Document};
+
+        let dir = tempdir().unwrap();
+        let secondary_dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let primary = StorageEngine::open(options.clone()).unwrap();
+        let users = primary.create_collection("users").unwrap();
+        let mut doc = Document::new();
+        doc.insert("name", "miku");
+        let first = users.insert(&mut doc).unwrap();
+        primary.flush().unwrap();
+
+        let read_only = StorageEngine::open(StorageOptions {
+            open_mode: OpenMode::ReadOnly,
+            ..options.clone()
+        })
+        .unwrap();
+        assert!(read_only.get_collection("users").unwrap().get(&first).unwrap().is_some());
+        assert!(matches!(read_only.create_collection("logs"), Err(StorageError::ReadOnly)));
+        assert!(read_only.get_collection("users").unwrap().insert(&mut Document::new()).is_err());
+
+        let secondary = StorageEngine::open(StorageOptions {
+            open_mode: OpenMode::Secondary {
+                secondary_dir: secondary_dir.path().to_path_buf(),
+            },
+            ..options
+        })
+        .unwrap();
+        let mut doc = Document::new();
+        doc.insert("name", BomlValue::String("rin".into()));
+        let second = users.insert(&mut doc).unwrap();
+        secondary.catch_up_with_primary().unwrap();
+        assert!(secondary.get_collection("users").unwrap().get(&second).unwrap().is_some());
+        assert!(read_only.catch_up_with_primary().is_err());
     }
 }
@@ -0,0 +1,82 @@
+//! 文档过期模块
+//!
+//! 按集合指定一个过期时间字段,不需要创建 TTL 索引:
+//! - 过期策略持久化在元数据中 (`expire:{name}`),随备份一起导出
+//! - 字段值早于当前时间的文档由服务器后台任务定期删除
+//! - 字段值支持 DateTime、Timestamp(毫秒)和 RFC 3339 字符串,其他类型或缺失字段的文档永不过期
+
+use chrono::{DateTime, Utc};
+use mikudb_boml::{BomlValue, Document};
+use serde::{Deserialize, Serialize};
+
+/// 过期策略在元数据 CF 中的键前缀
+pub(crate) const EXPIRE_KEY_PREFIX: &str = "expire:";
+
+/// 过期策略
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpirePolicy {
+    /// 集合名称
+    pub collection: String,
+    /// 过期时间字段,支持点号分隔的嵌套路径
+    pub field: String,
+}
+
+impl ExpirePolicy {
+    pub(crate) fn metadata_key(collection: &str) -> String {
+        format!("{}{}", EXPIRE_KEY_PREFIX, collection)
+    }
+
+    /// # Brief
+    /// 读取文档的过期时间
+    ///
+    /// # Returns
+    /// 字段缺失或类型不是时间时返回 None
+    pub fn expires_at(&self, doc: &Document) -> Option<DateTime<Utc>> {
+        match doc.get_path(&self.field)? {
+            BomlValue::DateTime(dt) => Some(*dt),
+            BomlValue::Timestamp(millis) => DateTime::from_timestamp_millis(*millis),
+            BomlValue::String(s) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc)),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 判断文档在给定时间是否已过期
+    pub fn is_expired(&self, doc: &Document, now: DateTime<Utc>) -> bool {
+        self.expires_at(doc).is_some_and(|at| at <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_is_expired() {
+        let policy = ExpirePolicy {
+            collection: "sessions".to_string(),
+            field: "expires_at".to_string(),
+        };
+        let now = Utc::now();
+
+        let mut past = Document::new();
+        past.insert("expires_at", BomlValue::DateTime(now - Duration::seconds(5)));
+        assert!(policy.is_expired(&past, now));
+
+        let mut future = Document::new();
+        future.insert("expires_at", BomlValue::Timestamp((now + Duration::hours(1)).timestamp_millis()));
+        assert!(!policy.is_expired(&future, now));
+
+        let mut text = Document::new();
+        text.insert("expires_at", BomlValue::String("2000-01-01T00:00:00Z".into()));
+        assert!(policy.is_expired(&text, now));
+
+        let mut other = Document::new();
+        other.insert("expires_at", BomlValue::Int64(0));
+        assert!(!policy.is_expired(&other, now));
+        assert!(!policy.is_expired(&Document::new(), now));
+    }
+}
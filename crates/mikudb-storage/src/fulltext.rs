@@ -1,14 +1,16 @@
 //! 全文索引模块
 //!
 //! 实现基础的全文搜索功能:
-//! - **中文分词**: 基于字符 N-gram 分词
+//! - **可插拔分词**: 每个索引单独选择分词器、停用词和词干提取(见 `tokenizer` 模块)
+//! - **中文分词**: 基于字符 N-gram 或 jieba 词典分词
 //! - **倒排索引**: 词项 -> 文档 ID 列表
-//! - **布尔查询**: AND、OR、NOT 操作
+//! - **布尔查询**: AND、OR 操作,开启压缩倒排列表时在 Roaring Bitmap 上求交并
 //! - **短语匹配**: 精确短语搜索
 //! - **相关性评分**: TF-IDF 算法
 //!
 //! # 分词策略
 //!
+//! - **默认**: Unicode 单词边界分词,标点被丢弃
 //! - **中文**: Bi-gram (2-gram) 分词,例如 "数据库" -> ["数据", "据库"]
 //! - **英文**: 空格分词 + 小写化,例如 "Hello World" -> ["hello", "world"]
 //! - **混合**: 识别中英文并分别处理
@@ -22,7 +24,12 @@
 //!     frequencies: Vec<u32>,        // 每个文档中的词频
 //! }
 //! ```
+//!
+//! 开启 `compressed_postings` 后,词项 -> 文档号位图还会持久化到 `posting` 模块的
+//! 压缩倒排列表中,布尔查询直接使用位图运算。
 
+use crate::posting::PostingStore;
+use crate::tokenizer::{StopWords, TextAnalyzer, Tokenizer, TokenizerType};
 use crate::{StorageError, StorageResult};
 use mikudb_boml::BomlValue;
 use mikudb_common::ObjectId;
@@ -50,29 +57,31 @@ pub struct FullTextIndexDefinition {
     pub max_token_length: usize,
     /// 是否存储位置信息(用于短语查询)
     pub store_positions: bool,
-}
-
-/// 分词器类型
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
-pub enum TokenizerType {
-    /// 简单分词器(空格分词)
-    Simple,
-    /// 中文 N-gram 分词器
-    ChineseNGram,
-    /// 混合分词器(自动识别中英文)
-    Mixed,
+    /// 停用词
+    #[serde(default)]
+    pub stopwords: StopWords,
+    /// 词干提取语言(如 `english`),None 表示不提取词干
+    #[serde(default)]
+    pub stemmer: Option<String>,
+    /// 是否把倒排列表持久化为 Roaring Bitmap(文档 ID 映射为整数文档号)
+    #[serde(default)]
+    pub compressed_postings: bool,
 }
 
 /// 全文索引引擎
 pub struct FullTextIndex {
     /// 索引定义
     definition: FullTextIndexDefinition,
+    /// 文本分析器(分词、停用词、词干提取)
+    analyzer: TextAnalyzer,
     /// RocksDB 实例
     db: Arc<DB>,
     /// 倒排索引缓存(词项 -> 倒排列表)
     inverted_index: RwLock<BTreeMap<String, PostingList>>,
     /// 文档统计(用于 TF-IDF)
     doc_stats: RwLock<DocumentStats>,
+    /// 压缩倒排列表(开启 compressed_postings 时)
+    postings: Option<PostingStore>,
 }
 
 /// 倒排列表
@@ -136,13 +145,56 @@ struct DocumentStats {
 
 impl FullTextIndex {
     /// 创建全文索引
-    pub fn new(definition: FullTextIndexDefinition, db: Arc<DB>) -> Self {
-        Self {
+    ///
+    /// 使用定义中指定的分词器;分词器不可用或停用词、词干选项无效时返回 InvalidArgument 错误。
+    pub fn new(definition: FullTextIndexDefinition, db: Arc<DB>) -> StorageResult<Self> {
+        let tokenizer = definition.tokenizer.build()?;
+        Self::with_tokenizer(definition, db, tokenizer)
+    }
+
+    /// 使用自定义分词器创建全文索引
+    ///
+    /// # Arguments
+    /// * `definition` - 索引定义(其中的 `tokenizer` 字段被忽略)
+    /// * `db` - RocksDB 实例
+    /// * `tokenizer` - 分词器实现
+    pub fn with_tokenizer(
+        definition: FullTextIndexDefinition,
+        db: Arc<DB>,
+        tokenizer: Box<dyn Tokenizer>,
+    ) -> StorageResult<Self> {
+        let analyzer = TextAnalyzer::new(
+            tokenizer,
+            &definition.stopwords,
+            definition.stemmer.as_deref(),
+            definition.min_token_length,
+            definition.max_token_length,
+        )?;
+        debug!(
+            "Full-text index {} uses tokenizer '{}'",
+            definition.name,
+            analyzer.tokenizer_name()
+        );
+
+        let postings = if definition.compressed_postings {
+            Some(PostingStore::open(db.clone(), &definition.name)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
             definition,
+            analyzer,
             db,
+            postings,
             inverted_index: RwLock::new(BTreeMap::new()),
             doc_stats: RwLock::new(DocumentStats::default()),
-        }
+        })
+    }
+
+    /// 获取索引定义
+    pub fn definition(&self) -> &FullTextIndexDefinition {
+        &self.definition
     }
 
     /// 索引文档
@@ -162,6 +214,11 @@ impl FullTextIndex {
 
         let term_count = term_positions.len();
 
+        if let Some(postings) = &self.postings {
+            let terms: Vec<String> = term_positions.keys().cloned().collect();
+            postings.add_document(&doc_id, &terms)?;
+        }
+
         // 更新倒排索引
         let mut inverted_index = self.inverted_index.write();
         for (term, positions) in term_positions {
@@ -190,11 +247,18 @@ impl FullTextIndex {
         let mut inverted_index = self.inverted_index.write();
 
         // 从所有倒排列表中删除文档
-        inverted_index.retain(|_, posting_list| {
-            posting_list.remove_document(doc_id);
+        let mut removed_terms = Vec::new();
+        inverted_index.retain(|term, posting_list| {
+            if posting_list.remove_document(doc_id) {
+                removed_terms.push(term.clone());
+            }
             !posting_list.doc_ids.is_empty() // 删除空的倒排列表
         });
 
+        if let Some(postings) = &self.postings {
+            postings.remove_document(doc_id, &removed_terms)?;
+        }
+
         // 更新文档统计
         let mut doc_stats = self.doc_stats.write();
         if doc_stats.doc_lengths.remove(doc_id).is_some() {
@@ -245,6 +309,77 @@ impl FullTextIndex {
         Ok(results)
     }
 
+    /// 布尔 AND 查询
+    ///
+    /// # Arguments
+    /// * `query` - 查询字符串,分词后的所有词项都必须出现
+    ///
+    /// # Returns
+    /// 匹配的文档 ID 列表
+    pub fn search_all(&self, query: &str) -> StorageResult<Vec<ObjectId>> {
+        let tokens = self.tokenize(query);
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if let Some(postings) = &self.postings {
+            return postings.resolve(&postings.intersect(&tokens)?);
+        }
+
+        let inverted_index = self.inverted_index.read();
+        let mut lists = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            match inverted_index.get(token) {
+                Some(posting_list) => lists.push(posting_list),
+                None => return Ok(Vec::new()),
+            }
+        }
+        lists.sort_by_key(|posting_list| posting_list.doc_ids.len());
+
+        let rest: Vec<HashSet<&ObjectId>> = lists[1..]
+            .iter()
+            .map(|posting_list| posting_list.doc_ids.iter().collect())
+            .collect();
+        Ok(lists[0]
+            .doc_ids
+            .iter()
+            .filter(|id| rest.iter().all(|set| set.contains(id)))
+            .copied()
+            .collect())
+    }
+
+    /// 布尔 OR 查询
+    ///
+    /// # Arguments
+    /// * `query` - 查询字符串,分词后的任一词项出现即匹配
+    ///
+    /// # Returns
+    /// 匹配的文档 ID 列表
+    pub fn search_any(&self, query: &str) -> StorageResult<Vec<ObjectId>> {
+        let tokens = self.tokenize(query);
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if let Some(postings) = &self.postings {
+            return postings.resolve(&postings.union(&tokens)?);
+        }
+
+        let inverted_index = self.inverted_index.read();
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        for token in &tokens {
+            if let Some(posting_list) = inverted_index.get(token) {
+                for doc_id in &posting_list.doc_ids {
+                    if seen.insert(*doc_id) {
+                        results.push(*doc_id);
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
     /// 短语搜索
     ///
     /// # Arguments
@@ -338,98 +473,7 @@ impl FullTextIndex {
 
     /// 分词
     fn tokenize(&self, text: &str) -> Vec<String> {
-        match self.definition.tokenizer {
-            TokenizerType::Simple => self.tokenize_simple(text),
-            TokenizerType::ChineseNGram => self.tokenize_chinese_ngram(text),
-            TokenizerType::Mixed => self.tokenize_mixed(text),
-        }
-    }
-
-    /// 简单分词器(空格分词)
-    fn tokenize_simple(&self, text: &str) -> Vec<String> {
-        text.split_whitespace()
-            .map(|s| s.to_lowercase())
-            .filter(|s| {
-                s.len() >= self.definition.min_token_length
-                    && s.len() <= self.definition.max_token_length
-            })
-            .collect()
-    }
-
-    /// 中文 N-gram 分词器
-    fn tokenize_chinese_ngram(&self, text: &str) -> Vec<String> {
-        let chars: Vec<char> = text.chars().collect();
-        let mut tokens = Vec::new();
-
-        // Bi-gram (2-gram)
-        for i in 0..chars.len().saturating_sub(1) {
-            let token: String = chars[i..i + 2].iter().collect();
-            if token.chars().all(|c| c.is_alphanumeric() || c > '\u{4E00}') {
-                tokens.push(token.to_lowercase());
-            }
-        }
-
-        // 单字符
-        for ch in chars {
-            if ch.is_alphanumeric() || ch > '\u{4E00}' {
-                tokens.push(ch.to_lowercase().to_string());
-            }
-        }
-
-        tokens
-    }
-
-    /// 混合分词器(中英文混合)
-    fn tokenize_mixed(&self, text: &str) -> Vec<String> {
-        let mut tokens = Vec::new();
-        let mut current_word = String::new();
-        let mut is_chinese_mode = false;
-
-        for ch in text.chars() {
-            let is_chinese = ch > '\u{4E00}' && ch < '\u{9FFF}';
-
-            if is_chinese {
-                // 保存当前英文词
-                if !current_word.is_empty() && !is_chinese_mode {
-                    tokens.push(current_word.to_lowercase());
-                    current_word.clear();
-                }
-
-                // 中文字符
-                if !current_word.is_empty() {
-                    // Bi-gram
-                    tokens.push(format!("{}{}", current_word, ch).to_lowercase());
-                }
-                tokens.push(ch.to_lowercase().to_string());
-                current_word = ch.to_string();
-                is_chinese_mode = true;
-            } else if ch.is_alphanumeric() {
-                if is_chinese_mode {
-                    current_word.clear();
-                    is_chinese_mode = false;
-                }
-                current_word.push(ch);
-            } else {
-                // 分隔符
-                if !current_word.is_empty() {
-                    tokens.push(current_word.to_lowercase());
-                    current_word.clear();
-                }
-                is_chinese_mode = false;
-            }
-        }
-
-        if !current_word.is_empty() {
-            tokens.push(current_word.to_lowercase());
-        }
-
-        tokens
-            .into_iter()
-            .filter(|s| {
-                s.len() >= self.definition.min_token_length
-                    && s.len() <= self.definition.max_token_length
-            })
-            .collect()
+        self.analyzer.analyze(text)
     }
 
     /// 计算 IDF (Inverse Document Frequency)
@@ -466,10 +510,13 @@ mod tests {
             min_token_length: 1,
             max_token_length: 100,
             store_positions: true,
+            stopwords: StopWords::None,
+            stemmer: None,
+            compressed_postings: false,
         };
 
         let db = Arc::new(rocksdb::DB::open_default(tempfile::tempdir().unwrap().path()).unwrap());
-        let index = FullTextIndex::new(definition, db);
+        let index = FullTextIndex::new(definition, db).unwrap();
 
         let tokens = index.tokenize("Hello World Rust Database");
         assert_eq!(tokens, vec!["hello", "world", "rust", "database"]);
@@ -485,10 +532,13 @@ mod tests {
             min_token_length: 1,
             max_token_length: 100,
             store_positions: true,
+            stopwords: StopWords::None,
+            stemmer: None,
+            compressed_postings: false,
         };
 
         let db = Arc::new(rocksdb::DB::open_default(tempfile::tempdir().unwrap().path()).unwrap());
-        let index = FullTextIndex::new(definition, db);
+        let index = FullTextIndex::new(definition, db).unwrap();
 
         let tokens = index.tokenize("数据库");
         assert!(tokens.contains(&"数据".to_string()));
@@ -505,10 +555,13 @@ mod tests {
             min_token_length: 1,
             max_token_length: 100,
             store_positions: true,
+            stopwords: StopWords::None,
+            stemmer: None,
+            compressed_postings: false,
         };
 
         let db = Arc::new(rocksdb::DB::open_default(tempfile::tempdir().unwrap().path()).unwrap());
-        let index = FullTextIndex::new(definition, db);
+        let index = FullTextIndex::new(definition, db).unwrap();
 
         let doc1 = ObjectId::new();
         let doc2 = ObjectId::new();
@@ -536,10 +589,13 @@ mod tests {
             min_token_length: 1,
             max_token_length: 100,
             store_positions: true,
+            stopwords: StopWords::None,
+            stemmer: None,
+            compressed_postings: false,
         };
 
         let db = Arc::new(rocksdb::DB::open_default(tempfile::tempdir().unwrap().path()).unwrap());
-        let index = FullTextIndex::new(definition, db);
+        let index = FullTextIndex::new(definition, db).unwrap();
 
         let doc1 = ObjectId::new();
         let doc2 = ObjectId::new();
@@ -557,4 +613,41 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0], doc1);
     }
+
+    #[test]
+    fn test_boolean_search_with_compressed_postings() {
+        for compressed in [false, true] {
+            let definition = FullTextIndexDefinition {
+                name: "bool".to_string(),
+                collection: "docs".to_string(),
+                fields: vec!["content".to_string()],
+                tokenizer: TokenizerType::Simple,
+                min_token_length: 1,
+                max_token_length: 100,
+                store_positions: false,
+                stopwords: StopWords::None,
+                stemmer: None,
+                compressed_postings: compressed,
+            };
+
+            let dir = tempfile::tempdir().unwrap();
+            let db = Arc::new(rocksdb::DB::open_default(dir.path()).unwrap());
+            let index = FullTextIndex::new(definition, db).unwrap();
+
+            let doc1 = ObjectId::new();
+            let doc2 = ObjectId::new();
+            let doc3 = ObjectId::new();
+            index.index_document(doc1, "rust storage engine").unwrap();
+            index.index_document(doc2, "rust query engine").unwrap();
+            index.index_document(doc3, "storage tiering").unwrap();
+
+            assert_eq!(index.search_all("rust engine").unwrap(), vec![doc1, doc2]);
+            assert_eq!(index.search_all("rust tiering").unwrap(), vec![]);
+            assert_eq!(index.search_any("query tiering").unwrap(), vec![doc2, doc3]);
+
+            index.delete_document(&doc1).unwrap();
+            assert_eq!(index.search_all("rust engine").unwrap(), vec![doc2]);
+            assert_eq!(index.search_any("storage").unwrap(), vec![doc3]);
+        }
+    }
 }
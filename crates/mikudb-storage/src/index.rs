@@ -5,7 +5,15 @@
 //! - **复合索引**: 支持多字段索引
 //! - **唯一索引**: 保证键的唯一性
 //! - **稀疏索引**: 只索引非空字段的文档
-//! - **TTL 索引**: 自动过期删除文档
+//! - **TTL 索引**: 过期的索引项指向的文档由 [`crate::ttl::TtlSweeper`] 在后台删除,同时维护其他索引
+//! - **批量回表**: 索引扫描得到的文档 ID 通过分块 multi_get 读取,保持索引顺序
+//! - **延迟清理**: 范围删除后残留的索引项在回表时跳过,之后批量回收
+//! - **写入维护**: `index_document` / `unindex_document` 同步维护集合上的全部 BTree 和哈希索引,
+//!   唯一键冲突时撤销本次已写入的索引项
+//! - **保序键**: 索引键使用 `mikudb_boml::keyenc` 编码,负数、浮点数和复合键的范围扫描按值排序,
+//!   降序字段按位取反
+//! - **键长度上限**: BTree 索引键超过 `max_key_size` 时按索引的 [`OversizedKeyPolicy`]
+//!   拒绝写入、截断并追加哈希或不索引该文档,截断和跳过计入 [`IndexEngine::oversized_keys`]
 //!
 //! # 索引持久化
 //!
@@ -18,18 +26,23 @@
 //! - 使用 xxHash3 计算哈希索引键,在鲲鹏 CPU 上性能优异
 //! - 支持 Direct I/O 优化索引读写
 
+use crate::collection::Collection;
+use crate::schema::{ValidationDetail, RULE_INDEX_KEY_SIZE};
 use crate::{StorageError, StorageResult};
-use mikudb_boml::{BomlValue, Document};
+use mikudb_boml::{keyenc, BomlValue, Document};
 use mikudb_common::ObjectId;
-use parking_lot::RwLock;
+use dashmap::DashMap;
 use rocksdb::{BoundColumnFamily, IteratorMode, WriteBatch, WriteOptions, DB};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::{debug, info, warn};
 use xxhash_rust::xxh3::xxh3_64;
 
+/// 索引元数据 ColumnFamily
+pub(crate) const INDEX_META_CF: &str = "_index_meta";
+
 /// 索引定义
 ///
 /// 描述索引的元数据和配置
@@ -49,6 +62,169 @@ pub struct IndexDefinition {
     pub sparse: bool,
     /// TTL 配置(秒数,None 表示不过期)
     pub ttl_seconds: Option<u64>,
+    /// 索引键编码格式,元数据中缺失时为早期格式
+    #[serde(default = "legacy_key_encoding")]
+    pub key_encoding: KeyEncoding,
+    /// BTree 索引键(不含文档 ID)的最大字节数,None 表示不限制(早期创建的索引)
+    #[serde(default)]
+    pub max_key_size: Option<usize>,
+    /// 索引键超过 `max_key_size` 时的处理方式
+    #[serde(default)]
+    pub oversized_keys: OversizedKeyPolicy,
+}
+
+/// 新建 BTree 索引的默认键长度上限(字节)
+pub const DEFAULT_MAX_INDEX_KEY_SIZE: usize = 1024;
+
+/// 允许设置的最小键长度上限,截断的键至少保留 8 字节前缀和 8 字节哈希
+pub const MIN_MAX_INDEX_KEY_SIZE: usize = 16;
+
+/// 截断键末尾的哈希长度
+const TRUNCATED_HASH_LEN: usize = 8;
+
+/// 索引键超过长度上限时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OversizedKeyPolicy {
+    /// 拒绝写入,返回校验错误
+    #[default]
+    Reject,
+    /// 保留键的前 `max_key_size - 8` 字节并追加完整键的 xxHash3,
+    /// 等值查找仍然精确,范围查询在截断处退化为前缀匹配,由调用方重新过滤
+    Truncate,
+    /// 不索引该文档(与稀疏索引相同),查询计划不再用该索引做范围查找
+    Skip,
+}
+
+impl OversizedKeyPolicy {
+    /// # Brief
+    /// 策略在 SQL 和 SHOW INDEXES 中的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OversizedKeyPolicy::Reject => "reject",
+            OversizedKeyPolicy::Truncate => "truncate",
+            OversizedKeyPolicy::Skip => "skip",
+        }
+    }
+}
+
+impl IndexDefinition {
+    /// # Brief
+    /// 判断给定的键值在索引中是否有索引项
+    ///
+    /// 稀疏索引不索引含 Null 的键,SKIP 策略不索引超长的键;
+    /// 查询计划据此判断能否用该索引做等值查找。
+    ///
+    /// # Arguments
+    /// * `key_values` - 全部索引字段的值
+    pub fn accepts(&self, key_values: &[BomlValue]) -> bool {
+        if self.sparse && key_values.iter().any(|v| matches!(v, BomlValue::Null)) {
+            return false;
+        }
+        match self.key_limit() {
+            Some(max) if self.oversized_keys == OversizedKeyPolicy::Skip => {
+                btree_key(key_values, self).len() <= max
+            }
+            _ => true,
+        }
+    }
+
+    /// 生效的键长度上限,只约束 BTree 索引
+    fn key_limit(&self) -> Option<usize> {
+        self.max_key_size.filter(|_| self.index_type == IndexType::BTree)
+    }
+}
+
+/// 索引键编码格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyEncoding {
+    /// 早期格式: 负数和浮点数不保序,只为已有索引保留,重建索引后升级
+    Legacy,
+    /// 保序编码(`mikudb_boml::keyenc`)
+    #[default]
+    Memcomparable,
+}
+
+fn legacy_key_encoding() -> KeyEncoding {
+    KeyEncoding::Legacy
+}
+
+/// BTree 索引的保序键: 各字段自定界直接拼接,降序字段按位取反
+fn btree_key(key_values: &[BomlValue], definition: &IndexDefinition) -> Vec<u8> {
+    let mut key = Vec::new();
+    for (value, field) in key_values.iter().zip(&definition.fields) {
+        match field.order {
+            IndexOrder::Ascending => keyenc::encode_value(value, &mut key),
+            IndexOrder::Descending => keyenc::encode_descending(value, &mut key),
+        }
+    }
+    key
+}
+
+/// 截断超长的键: 保留前缀并追加完整键的哈希,使不同的长键仍然对应不同的索引项
+fn truncate_key(key: &[u8], max: usize) -> Vec<u8> {
+    let mut truncated = key[..max - TRUNCATED_HASH_LEN].to_vec();
+    truncated.extend_from_slice(&xxh3_64(key).to_be_bytes());
+    truncated
+}
+
+/// 按键长度上限调整查找和删除使用的索引键,与写入时的处理一致
+///
+/// REJECT 策略下超长的键不会被写入,原样返回即可;SKIP 策略下超长的键没有索引项,返回 None。
+fn fit_key(definition: &IndexDefinition, key: Vec<u8>) -> Option<Vec<u8>> {
+    match definition.key_limit() {
+        Some(max) if key.len() > max => match definition.oversized_keys {
+            OversizedKeyPolicy::Reject => Some(key),
+            OversizedKeyPolicy::Truncate => Some(truncate_key(&key, max)),
+            OversizedKeyPolicy::Skip => None,
+        },
+        _ => Some(key),
+    }
+}
+
+/// 写入时同步维护的索引: BTree 和哈希索引
+fn maintained(definition: &IndexDefinition) -> bool {
+    matches!(definition.index_type, IndexType::BTree | IndexType::Hash)
+}
+
+/// 唯一键重复错误中显示的键,形如 `{ email: "a@b.c" }`
+fn duplicate_key(fields: &[IndexField], values: &[BomlValue]) -> String {
+    let pairs: Vec<String> = fields
+        .iter()
+        .zip(values)
+        .map(|(field, value)| format!("{}: {}", field.path, value))
+        .collect();
+    format!("{{ {} }}", pairs.join(", "))
+}
+
+/// 一个文档在一个索引上的索引项
+struct IndexEntry {
+    /// 不含文档 ID 的索引键,唯一索引按它检查重复
+    index_key: Vec<u8>,
+    /// 写入索引 CF 的键: index_key + doc_id
+    key: Vec<u8>,
+    /// 值: 空,TTL 索引为过期时间戳
+    value: Vec<u8>,
+    /// 索引字段的值,用于唯一键重复错误
+    key_values: Vec<BomlValue>,
+}
+
+impl IndexEntry {
+    fn duplicate(&self, definition: &IndexDefinition) -> StorageError {
+        StorageError::DuplicateKey {
+            collection: definition.collection.clone(),
+            index: definition.name.clone(),
+            key: duplicate_key(&definition.fields, &self.key_values),
+        }
+    }
+}
+
+/// 一次 TTL 清理的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TtlCleanup {
+    /// 删除的文档数
+    pub deleted_documents: u64,
+    /// 回收的过期索引项数,包括文档已不存在的索引项
+    pub removed_entries: u64,
 }
 
 /// 索引字段
@@ -87,8 +263,12 @@ pub enum IndexType {
 /// 管理所有索引的创建、删除、查询和维护
 pub struct IndexEngine {
     db: Arc<DB>,
-    /// 索引元数据缓存
-    index_defs: RwLock<HashMap<String, IndexDefinition>>,
+    /// 索引元数据缓存,按集合名分片
+    ///
+    /// 每次写入都要取出集合的索引,按集合存放使其只读取一个分片,不同集合的写入互不阻塞。
+    index_defs: DashMap<String, Vec<IndexDefinition>>,
+    /// 各索引自启动以来被截断或跳过的超长键数量
+    oversized: DashMap<String, u64>,
 }
 
 impl IndexEngine {
@@ -99,7 +279,8 @@ impl IndexEngine {
     pub fn new(db: Arc<DB>) -> Self {
         Self {
             db,
-            index_defs: RwLock::new(HashMap::new()),
+            index_defs: DashMap::new(),
+            oversized: DashMap::new(),
         }
     }
 
@@ -107,12 +288,11 @@ impl IndexEngine {
     ///
     /// 从 `_index_meta` CF 加载所有索引定义到内存
     pub fn load_indexes(&self) -> StorageResult<()> {
-        let meta_cf = self.db.cf_handle("_index_meta").ok_or_else(|| {
+        let meta_cf = self.db.cf_handle(INDEX_META_CF).ok_or_else(|| {
             StorageError::Internal("Index metadata CF not found".to_string())
         })?;
 
-        let mut index_defs = self.index_defs.write();
-
+        let mut loaded = 0;
         let iter = self.db.iterator_cf(&meta_cf, IteratorMode::Start);
         for item in iter {
             let (key, value) = item?;
@@ -122,10 +302,18 @@ impl IndexEngine {
             let definition: IndexDefinition = serde_json::from_slice(&value)
                 .map_err(|e| StorageError::Corruption(format!("Invalid index definition: {}", e)))?;
 
-            index_defs.insert(index_name, definition);
+            if definition.key_encoding == KeyEncoding::Legacy && definition.index_type == IndexType::BTree {
+                warn!(
+                    "Index {} uses the legacy key encoding, range scans over negative numbers or floats \
+                     may be out of order; drop and recreate it to upgrade",
+                    index_name
+                );
+            }
+            self.index_defs.entry(definition.collection.clone()).or_default().push(definition);
+            loaded += 1;
         }
 
-        info!("Loaded {} indexes", index_defs.len());
+        info!("Loaded {} indexes", loaded);
         Ok(())
     }
 
@@ -137,15 +325,26 @@ impl IndexEngine {
     /// # Returns
     /// 成功或错误
     pub fn create_index(&self, definition: IndexDefinition) -> StorageResult<()> {
+        // 新建索引总是使用当前的键编码,BTree 索引总有键长度上限(哈希索引的键固定为 8 字节)
+        let default_limit = (definition.index_type == IndexType::BTree).then_some(DEFAULT_MAX_INDEX_KEY_SIZE);
+        let definition = IndexDefinition {
+            key_encoding: KeyEncoding::Memcomparable,
+            max_key_size: definition.max_key_size.or(default_limit),
+            ..definition
+        };
+        if let Some(max) = definition.max_key_size.filter(|max| *max < MIN_MAX_INDEX_KEY_SIZE) {
+            return Err(StorageError::InvalidArgument(format!(
+                "Index key size limit must be at least {} bytes, got {}",
+                MIN_MAX_INDEX_KEY_SIZE, max
+            )));
+        }
+
         // 检查索引是否已存在
-        {
-            let index_defs = self.index_defs.read();
-            if index_defs.contains_key(&definition.name) {
-                return Err(StorageError::Internal(format!(
-                    "Index {} already exists",
-                    definition.name
-                )));
-            }
+        if self.get_index(&definition.name).is_some() {
+            return Err(StorageError::Internal(format!(
+                "Index {} already exists",
+                definition.name
+            )));
         }
 
         // 创建索引 ColumnFamily
@@ -156,7 +355,7 @@ impl IndexEngine {
         self.db.create_cf(&cf_name, &opts)?;
 
         // 保存索引元数据
-        let meta_cf = self.db.cf_handle("_index_meta").ok_or_else(|| {
+        let meta_cf = self.db.cf_handle(INDEX_META_CF).ok_or_else(|| {
             StorageError::Internal("Index metadata CF not found".to_string())
         })?;
 
@@ -166,7 +365,7 @@ impl IndexEngine {
         self.db.put_cf(&meta_cf, definition.name.as_bytes(), &meta_bytes)?;
 
         // 添加到缓存
-        self.index_defs.write().insert(definition.name.clone(), definition.clone());
+        self.index_defs.entry(definition.collection.clone()).or_default().push(definition.clone());
 
         info!("Created index: {}", definition.name);
         Ok(())
@@ -178,13 +377,19 @@ impl IndexEngine {
     /// * `name` - 索引名称
     pub fn drop_index(&self, name: &str) -> StorageResult<bool> {
         // 从缓存移除
-        let removed = self.index_defs.write().remove(name).is_some();
+        let removed = self.index_defs.iter_mut().any(|mut defs| {
+            let before = defs.len();
+            defs.retain(|def| def.name != name);
+            defs.len() != before
+        });
+        self.index_defs.retain(|_, defs| !defs.is_empty());
         if !removed {
             return Ok(false);
         }
+        self.oversized.remove(name);
 
         // 删除元数据
-        let meta_cf = self.db.cf_handle("_index_meta").ok_or_else(|| {
+        let meta_cf = self.db.cf_handle(INDEX_META_CF).ok_or_else(|| {
             StorageError::Internal("Index metadata CF not found".to_string())
         })?;
         self.db.delete_cf(&meta_cf, name.as_bytes())?;
@@ -201,17 +406,23 @@ impl IndexEngine {
 
     /// 获取索引定义
     pub fn get_index(&self, name: &str) -> Option<IndexDefinition> {
-        self.index_defs.read().get(name).cloned()
+        self.index_defs
+            .iter()
+            .find_map(|defs| defs.iter().find(|def| def.name == name).cloned())
     }
 
     /// 列出集合的所有索引
     pub fn list_indexes(&self, collection: &str) -> Vec<IndexDefinition> {
-        self.index_defs
-            .read()
-            .values()
-            .filter(|def| def.collection == collection)
-            .cloned()
-            .collect()
+        self.index_defs.get(collection).map(|defs| defs.clone()).unwrap_or_default()
+    }
+
+    /// # Brief
+    /// 索引自启动以来被截断或跳过的超长键数量
+    ///
+    /// # Arguments
+    /// * `name` - 索引名称
+    pub fn oversized_keys(&self, name: &str) -> u64 {
+        self.oversized.get(name).map_or(0, |count| *count)
     }
 
     /// 插入文档到索引
@@ -230,35 +441,97 @@ impl IndexEngine {
             StorageError::Internal(format!("Index {} not found", index_name))
         })?;
 
-        // 提取索引键
-        let key_values = self.extract_key_values(&definition.fields, doc)?;
-
-        // 稀疏索引: 如果任何字段缺失,跳过索引
-        if definition.sparse && key_values.iter().any(|v| matches!(v, BomlValue::Null)) {
+        let Some(entry) = self.build_entry(&definition, doc, doc_id)? else {
             return Ok(());
+        };
+
+        // 唯一索引检查
+        if definition.unique && self.lookup_internal(&definition, &entry.index_key)?.is_some() {
+            return Err(entry.duplicate(&definition));
         }
 
-        let index_key = self.build_index_key(&key_values, &definition)?;
+        // 插入到索引
+        let cf = self.index_cf(&definition)?;
+        self.db.put_cf(&cf, &entry.key, &entry.value)?;
 
-        // 唯一索引检查
-        if definition.unique {
-            if self.lookup_internal(&definition, &index_key)?.is_some() {
-                return Err(StorageError::Internal(format!(
-                    "Duplicate key error for unique index {}",
-                    index_name
-                )));
+        Ok(())
+    }
+
+    /// 把一批新文档在集合全部 BTree 和哈希索引上的索引项加入写入批次
+    ///
+    /// # Brief
+    /// 供 `Collection::insert_many_indexed` 让文档和索引项在同一个 WriteBatch 中原子写入。
+    /// 唯一索引同时检查已有的索引项和本批中的其他文档,任何冲突时不修改 `batch`。
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `docs` - 已有 `_id` 的新文档
+    /// * `batch` - 文档所在的写入批次
+    pub(crate) fn stage_documents(
+        &self,
+        collection: &str,
+        docs: &[Document],
+        batch: &mut WriteBatch,
+    ) -> StorageResult<()> {
+        let mut staged = Vec::new();
+        for definition in self.list_indexes(collection).into_iter().filter(maintained) {
+            let cf = self.index_cf(&definition)?;
+            let mut batch_keys = HashSet::new();
+            for doc in docs {
+                let Some(id) = doc.id() else {
+                    continue;
+                };
+                let Some(entry) = self.build_entry(&definition, doc, id)? else {
+                    continue;
+                };
+                if definition.unique
+                    && (!batch_keys.insert(entry.index_key.clone())
+                        || self.lookup_internal(&definition, &entry.index_key)?.is_some())
+                {
+                    return Err(entry.duplicate(&definition));
+                }
+                staged.push((cf.clone(), entry));
             }
         }
+        for (cf, entry) in staged {
+            batch.put_cf(&cf, &entry.key, &entry.value);
+        }
+        Ok(())
+    }
 
-        // 插入到索引
-        let cf_name = format!("idx_{}", index_name);
-        let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
+    fn index_cf(&self, definition: &IndexDefinition) -> StorageResult<Arc<BoundColumnFamily<'_>>> {
+        let cf_name = format!("idx_{}", definition.name);
+        self.db.cf_handle(&cf_name).ok_or_else(|| {
             StorageError::Internal(format!("Index CF {} not found", cf_name))
-        })?;
+        })
+    }
+
+    /// 计算文档在索引上的索引项
+    ///
+    /// # Returns
+    /// 稀疏索引中缺失字段的文档和 SKIP 策略下键超长的文档返回 None
+    fn build_entry(
+        &self,
+        definition: &IndexDefinition,
+        doc: &Document,
+        doc_id: &ObjectId,
+    ) -> StorageResult<Option<IndexEntry>> {
+        // 提取索引键
+        let key_values = self.extract_key_values(&definition.fields, doc)?;
+
+        // 稀疏索引: 如果任何字段缺失,跳过索引
+        if definition.sparse && key_values.iter().any(|v| matches!(v, BomlValue::Null)) {
+            return Ok(None);
+        }
+
+        let index_key = self.build_index_key(&key_values, definition)?;
+        let Some(index_key) = self.admit_key(definition, index_key, &key_values)? else {
+            return Ok(None);
+        };
 
         // 键: index_key + doc_id, 值: 空(或 TTL 时间戳)
-        let mut full_key = index_key;
-        full_key.extend_from_slice(doc_id.as_bytes());
+        let mut key = index_key.clone();
+        key.extend_from_slice(doc_id.as_bytes());
 
         let value = if let Some(ttl_seconds) = definition.ttl_seconds {
             let expire_time = SystemTime::now()
@@ -271,9 +544,48 @@ impl IndexEngine {
             vec![]
         };
 
-        self.db.put_cf(&cf, &full_key, &value)?;
+        Ok(Some(IndexEntry { index_key, key, value, key_values }))
+    }
 
-        Ok(())
+    /// 检查要写入的索引键长度,超长时按索引的策略拒绝、截断或跳过
+    fn admit_key(
+        &self,
+        definition: &IndexDefinition,
+        index_key: Vec<u8>,
+        key_values: &[BomlValue],
+    ) -> StorageResult<Option<Vec<u8>>> {
+        let Some(max) = definition.key_limit().filter(|max| index_key.len() > *max) else {
+            return Ok(Some(index_key));
+        };
+
+        if definition.oversized_keys == OversizedKeyPolicy::Reject {
+            // 报告编码后最长的字段
+            let field = definition
+                .fields
+                .iter()
+                .zip(key_values)
+                .max_by_key(|(_, value)| keyenc::encode_key(std::slice::from_ref(*value)).len())
+                .map_or("", |(field, _)| field.path.as_str());
+            return Err(StorageError::SchemaViolation {
+                collection: definition.collection.clone(),
+                details: vec![ValidationDetail::new(
+                    field,
+                    format!("key of at most {} bytes in index {}", max, definition.name),
+                    format!("{} bytes", index_key.len()),
+                    RULE_INDEX_KEY_SIZE,
+                )],
+            });
+        }
+
+        *self.oversized.entry(definition.name.clone()).or_default() += 1;
+        warn!(
+            "Index key of {} bytes exceeds the {}-byte limit of index {} ({})",
+            index_key.len(),
+            max,
+            definition.name,
+            definition.oversized_keys.as_str()
+        );
+        Ok(fit_key(definition, index_key))
     }
 
     /// 从索引删除文档
@@ -292,7 +604,9 @@ impl IndexEngine {
             return Ok(());
         }
 
-        let index_key = self.build_index_key(&key_values, &definition)?;
+        let Some(index_key) = fit_key(&definition, self.build_index_key(&key_values, &definition)?) else {
+            return Ok(());
+        };
 
         let cf_name = format!("idx_{}", index_name);
         let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
@@ -314,7 +628,7 @@ impl IndexEngine {
     /// * `key_values` - 索引键值列表
     ///
     /// # Returns
-    /// 匹配的文档 ID 列表
+    /// 匹配的文档 ID 列表,按索引顺序排列
     pub fn lookup(
         &self,
         index_name: &str,
@@ -324,22 +638,58 @@ impl IndexEngine {
             StorageError::Internal(format!("Index {} not found", index_name))
         })?;
 
-        let index_key = self.build_index_key(key_values, &definition)?;
-
-        if let Some(doc_id) = self.lookup_internal(&definition, &index_key)? {
-            Ok(vec![doc_id])
-        } else {
-            // 哈希索引: 精确查找
-            if matches!(definition.index_type, IndexType::Hash) {
-                return Ok(vec![]);
-            }
+        let Some(index_key) = fit_key(&definition, self.build_index_key(key_values, &definition)?) else {
+            return Ok(Vec::new());
+        };
 
-            // BTree 索引: 范围查找(前缀匹配)
-            self.range_scan(&definition, &index_key, &index_key, true)
+        if definition.unique {
+            return Ok(self.lookup_internal(&definition, &index_key)?.into_iter().collect());
         }
+
+        // 非唯一索引: 前缀匹配收集所有文档 ID
+        self.prefix_scan(&definition, &index_key)
+    }
+
+    /// 查找索引并批量读取文档
+    ///
+    /// # Brief
+    /// 先扫描索引收集全部文档 ID,再通过 Collection::find_by_ids 分块 multi_get 读取文档,
+    /// 结果保持索引顺序。
+    ///
+    /// 读取是乐观的: 索引扫描与文档读取之间没有快照,期间被删除的文档会被跳过,
+    /// 被更新的文档返回新版本,调用方需要重新应用过滤条件。
+    ///
+    /// # Arguments
+    /// * `collection` - 索引所属集合
+    /// * `index_name` - 索引名称
+    /// * `key_values` - 索引键值列表
+    ///
+    /// # Returns
+    /// 匹配的文档列表
+    pub fn lookup_documents(
+        &self,
+        collection: &Collection,
+        index_name: &str,
+        key_values: &[BomlValue],
+    ) -> StorageResult<Vec<Document>> {
+        let doc_ids = self.lookup(index_name, key_values)?;
+        collection.find_by_ids(&doc_ids)
     }
 
     /// 范围查询
+    ///
+    /// 边界按索引顺序给出(降序字段的起点是较大的值),可以只给出复合索引的前几个字段。
+    /// TRUNCATE 策略下超过截断长度的边界只保留截断处之前的前缀并包含边界,
+    /// 结果可能多出前缀相同的键,调用方需要重新应用过滤条件。
+    ///
+    /// # Arguments
+    /// * `index_name` - 索引名称
+    /// * `start_key` - 起点,None 表示从头开始
+    /// * `end_key` - 终点,None 表示到末尾
+    /// * `inclusive` - 是否包含与边界相等的键
+    ///
+    /// # Returns
+    /// 范围内的文档 ID,按索引顺序排列
     pub fn range_query(
         &self,
         index_name: &str,
@@ -357,39 +707,65 @@ impl IndexEngine {
             ));
         }
 
-        let start_bytes = if let Some(start) = start_key {
-            self.build_index_key(start, &definition)?
-        } else {
-            vec![]
-        };
+        let mut start_bytes = start_key.map(|start| self.build_index_key(start, &definition)).transpose()?;
+        let mut end_bytes = end_key.map(|end| self.build_index_key(end, &definition)).transpose()?;
+
+        let mut inclusive = inclusive;
+        if let Some(max) = definition.key_limit().filter(|_| definition.oversized_keys == OversizedKeyPolicy::Truncate) {
+            // 截断的键在前缀之后的顺序是哈希顺序,边界只能比较到截断处
+            let prefix_len = max - TRUNCATED_HASH_LEN;
+            for bound in [&mut start_bytes, &mut end_bytes].into_iter().flatten() {
+                if bound.len() > prefix_len {
+                    bound.truncate(prefix_len);
+                    inclusive = true;
+                }
+            }
+        }
 
-        let end_bytes = if let Some(end) = end_key {
-            self.build_index_key(end, &definition)?
-        } else {
-            vec![0xFF; 256] // 最大键
-        };
+        self.range_scan(&definition, start_bytes.as_deref(), end_bytes.as_deref(), inclusive)
+    }
 
-        self.range_scan(&definition, &start_bytes, &end_bytes, inclusive)
+    /// 范围查询并批量读取文档
+    ///
+    /// 与 lookup_documents 相同,读取是乐观的,结果保持索引顺序。
+    pub fn range_query_documents(
+        &self,
+        collection: &Collection,
+        index_name: &str,
+        start_key: Option<&[BomlValue]>,
+        end_key: Option<&[BomlValue]>,
+        inclusive: bool,
+    ) -> StorageResult<Vec<Document>> {
+        let doc_ids = self.range_query(index_name, start_key, end_key, inclusive)?;
+        collection.find_by_ids(&doc_ids)
     }
 
     /// 清理过期的 TTL 索引项
     ///
-    /// 扫描所有 TTL 索引,删除已过期的文档
-    pub fn cleanup_expired_ttl(&self) -> StorageResult<u64> {
+    /// 扫描所有 TTL 索引,删除过期索引项指向的文档,并把文档从集合上的全部索引中移除;
+    /// 文档已不存在时只回收索引项。集合已删除的 TTL 索引被跳过。
+    ///
+    /// # Arguments
+    /// * `collection` - 按名称获取集合
+    ///
+    /// # Returns
+    /// 本次删除的文档数和回收的索引项数
+    pub fn cleanup_expired_ttl<F>(&self, mut collection: F) -> StorageResult<TtlCleanup>
+    where
+        F: FnMut(&str) -> StorageResult<Arc<Collection>>,
+    {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
 
-        let mut total_deleted = 0u64;
+        let mut cleanup = TtlCleanup::default();
 
         // 查找所有 TTL 索引
         let ttl_indexes: Vec<_> = self
             .index_defs
-            .read()
-            .values()
-            .filter(|def| def.ttl_seconds.is_some())
-            .cloned()
+            .iter()
+            .flat_map(|defs| defs.iter().filter(|def| def.ttl_seconds.is_some()).cloned().collect::<Vec<_>>())
             .collect();
 
         for definition in ttl_indexes {
@@ -398,33 +774,259 @@ impl IndexEngine {
                 StorageError::Internal(format!("Index CF {} not found", cf_name))
             })?;
 
-            let mut batch = WriteBatch::default();
-            let mut count = 0u64;
-
-            let iter = self.db.iterator_cf(&cf, IteratorMode::Start);
-            for item in iter {
+            let mut expired = Vec::new();
+            for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
                 let (key, value) = item?;
-
-                if value.len() >= 8 {
+                if value.len() >= 8 && key.len() >= 12 {
                     let expire_time = u64::from_le_bytes(value[0..8].try_into().unwrap());
                     if now >= expire_time {
-                        batch.delete_cf(&cf, &key);
-                        count += 1;
+                        expired.push(key);
                     }
                 }
             }
+            if expired.is_empty() {
+                continue;
+            }
+
+            let target = match collection(&definition.collection) {
+                Ok(target) => target,
+                Err(StorageError::CollectionNotFound(_)) => {
+                    warn!("Skipping TTL index {}: collection {} not found", definition.name, definition.collection);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let mut deleted = 0u64;
+            let mut batch = WriteBatch::default();
+            for key in &expired {
+                // 键: index_key + doc_id
+                let mut bytes = [0u8; 12];
+                bytes.copy_from_slice(&key[key.len() - 12..]);
+                let id = ObjectId::from_bytes(bytes);
+
+                let _guard = target.lock_for_modify();
+                if let Some(doc) = target.get(&id)? {
+                    if target.delete(&id)? {
+                        self.unindex_document(&definition.collection, &doc)?;
+                        deleted += 1;
+                    }
+                }
+                // 文档不存在或索引项已过时时直接回收
+                batch.delete_cf(&cf, key);
+            }
+            self.db.write(batch)?;
+
+            info!(
+                "TTL index {} expired {} document(s), removed {} entr(ies)",
+                definition.name,
+                deleted,
+                expired.len()
+            );
+            cleanup.deleted_documents += deleted;
+            cleanup.removed_entries += expired.len() as u64;
+        }
+
+        Ok(cleanup)
+    }
+
+    /// 清理指向已删除文档的索引项
+    ///
+    /// 范围删除 (`Collection::delete_created_before`) 不逐个维护索引,
+    /// 残留的索引项在回表时被跳过,由这里延迟批量回收。
+    ///
+    /// # Arguments
+    /// * `collection` - 索引所属的集合
+    ///
+    /// # Returns
+    /// 删除的索引项数量
+    pub fn prune_dangling(&self, collection: &Collection) -> StorageResult<u64> {
+        let mut removed = 0u64;
+
+        for definition in self.list_indexes(collection.name()) {
+            let cf_name = format!("idx_{}", definition.name);
+            let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
+                StorageError::Internal(format!("Index CF {} not found", cf_name))
+            })?;
+
+            let mut batch = WriteBatch::default();
+            let mut count = 0u64;
+            for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+                let (key, _) = item?;
+                if key.len() < 12 {
+                    continue;
+                }
+                let doc_id_bytes: [u8; 12] = key[key.len() - 12..].try_into().unwrap();
+                if !collection.exists(&ObjectId::from_bytes(doc_id_bytes))? {
+                    batch.delete_cf(&cf, &key);
+                    count += 1;
+                }
+            }
 
             if count > 0 {
                 self.db.write(batch)?;
-                info!(
-                    "Cleaned up {} expired documents from TTL index {}",
-                    count, definition.name
-                );
-                total_deleted += count;
+                info!("Pruned {} dangling entries from index {}", count, definition.name);
+                removed += count;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// 用集合中已有的文档填充索引
+    ///
+    /// # Brief
+    /// 先清空索引再逐个插入文档,用于创建索引和恢复数据之后重建索引。
+    ///
+    /// # Arguments
+    /// * `index_name` - 索引名称
+    /// * `collection` - 索引所属的集合
+    ///
+    /// # Returns
+    /// 写入索引的文档数量,唯一索引中出现重复键时返回错误
+    pub fn build_index(&self, index_name: &str, collection: &Collection) -> StorageResult<u64> {
+        let cf_name = format!("idx_{}", index_name);
+        let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
+            StorageError::Internal(format!("Index CF {} not found", cf_name))
+        })?;
+
+        let mut batch = WriteBatch::default();
+        for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+            let (key, _) = item?;
+            batch.delete_cf(&cf, key);
+        }
+        if !batch.is_empty() {
+            self.db.write(batch)?;
+        }
+
+        let mut count = 0u64;
+        for doc in collection.iter()? {
+            let doc = doc?;
+            if let Some(id) = doc.id() {
+                self.insert_document(index_name, &doc, id)?;
+                count += 1;
+            }
+        }
+
+        info!("Built index {} over {} documents", index_name, count);
+        Ok(count)
+    }
+
+    /// 重建集合上的全部索引
+    ///
+    /// # Arguments
+    /// * `collection` - 集合
+    pub fn rebuild_indexes(&self, collection: &Collection) -> StorageResult<()> {
+        for definition in self.list_indexes(collection.name()) {
+            if maintained(&definition) {
+                self.build_index(&definition.name, collection)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 把文档写入集合上的全部 BTree 和哈希索引
+    ///
+    /// 任一索引写入失败(如唯一键冲突)时,删除本次已写入的索引项后返回错误。
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `doc` - 带 `_id` 的文档
+    pub fn index_document(&self, collection: &str, doc: &Document) -> StorageResult<()> {
+        let Some(id) = doc.id() else {
+            return Ok(());
+        };
+
+        let definitions: Vec<IndexDefinition> =
+            self.list_indexes(collection).into_iter().filter(maintained).collect();
+        for (i, definition) in definitions.iter().enumerate() {
+            if let Err(e) = self.insert_document(&definition.name, doc, id) {
+                for done in &definitions[..i] {
+                    self.delete_document(&done.name, doc, id)?;
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// 从集合上的全部 BTree 和哈希索引删除文档
+    ///
+    /// # Arguments
+    /// * `collection` - 集合名称
+    /// * `doc` - 删除或修改前的文档
+    pub fn unindex_document(&self, collection: &str, doc: &Document) -> StorageResult<()> {
+        let Some(id) = doc.id() else {
+            return Ok(());
+        };
+
+        for definition in self.list_indexes(collection) {
+            if maintained(&definition) {
+                self.delete_document(&definition.name, doc, id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 检查文档的索引项是否存在
+    ///
+    /// 稀疏索引中缺失字段的文档和 SKIP 策略下键超长的文档不需要索引项,视为存在。
+    ///
+    /// # Arguments
+    /// * `index_name` - 索引名称
+    /// * `doc` - 文档
+    /// * `doc_id` - 文档 ID
+    pub fn has_entry(
+        &self,
+        index_name: &str,
+        doc: &Document,
+        doc_id: &ObjectId,
+    ) -> StorageResult<bool> {
+        let definition = self.get_index(index_name).ok_or_else(|| {
+            StorageError::Internal(format!("Index {} not found", index_name))
+        })?;
+
+        let key_values = self.extract_key_values(&definition.fields, doc)?;
+        if definition.sparse && key_values.iter().any(|v| matches!(v, BomlValue::Null)) {
+            return Ok(true);
+        }
+        let Some(mut full_key) = fit_key(&definition, self.build_index_key(&key_values, &definition)?) else {
+            return Ok(true);
+        };
+
+        let cf_name = format!("idx_{}", index_name);
+        let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
+            StorageError::Internal(format!("Index CF {} not found", cf_name))
+        })?;
+
+        full_key.extend_from_slice(doc_id.as_bytes());
+
+        Ok(self.db.get_cf(&cf, &full_key)?.is_some())
+    }
+
+    /// 遍历索引的所有索引项
+    ///
+    /// # Arguments
+    /// * `index_name` - 索引名称
+    /// * `f` - 回调,参数为原始索引键和其指向的文档 ID
+    pub fn for_each_entry<F>(&self, index_name: &str, mut f: F) -> StorageResult<()>
+    where
+        F: FnMut(&[u8], ObjectId) -> StorageResult<()>,
+    {
+        let cf_name = format!("idx_{}", index_name);
+        let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
+            StorageError::Internal(format!("Index CF {} not found", cf_name))
+        })?;
+
+        for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+            let (key, _) = item?;
+            if key.len() >= 12 {
+                let doc_id_bytes: [u8; 12] = key[key.len() - 12..].try_into().unwrap();
+                f(&key, ObjectId::from_bytes(doc_id_bytes))?;
             }
         }
 
-        Ok(total_deleted)
+        Ok(())
     }
 
     // ========== 内部辅助方法 ==========
@@ -447,24 +1049,14 @@ impl IndexEngine {
     }
 
     /// 获取嵌套字段值
+    ///
+    /// 与查询过滤使用相同的路径解析(`Document::get_path`),数组元素通过下标访问,
+    /// 保证索引键与过滤条件看到的是同一个值。
     fn get_nested_field(&self, doc: &Document, path: &str) -> BomlValue {
-        let parts: Vec<&str> = path.split('.').collect();
-        let mut current = doc.to_boml_value();
-
-        for part in parts {
-            match current {
-                BomlValue::Document(ref map) => {
-                    if let Some(value) = map.get(part) {
-                        current = value.clone();
-                    } else {
-                        return BomlValue::Null;
-                    }
-                }
-                _ => return BomlValue::Null,
-            }
+        if path == "_id" {
+            return doc.id().map_or(BomlValue::Null, |id| BomlValue::ObjectId(*id));
         }
-
-        current
+        doc.get_path(path).cloned().unwrap_or(BomlValue::Null)
     }
 
     /// 构建索引键
@@ -476,24 +1068,24 @@ impl IndexEngine {
         match definition.index_type {
             IndexType::Hash => {
                 // 哈希索引: 使用 xxHash3 计算哈希值
-                let mut hasher_input = Vec::new();
-                for value in key_values {
-                    hasher_input.extend(self.value_to_bytes(value));
-                }
+                let hasher_input = match definition.key_encoding {
+                    KeyEncoding::Memcomparable => keyenc::encode_key(key_values),
+                    KeyEncoding::Legacy => key_values.iter().flat_map(|v| self.legacy_value_bytes(v)).collect(),
+                };
                 let hash = xxh3_64(&hasher_input);
                 Ok(hash.to_be_bytes().to_vec())
             }
-            IndexType::BTree => {
-                // BTree 索引: 直接序列化键值(保持排序)
+            IndexType::BTree if definition.key_encoding == KeyEncoding::Legacy => {
                 let mut key = Vec::new();
                 for (i, value) in key_values.iter().enumerate() {
                     if i > 0 {
                         key.push(0x00); // 分隔符
                     }
-                    key.extend(self.value_to_bytes(value));
+                    key.extend(self.legacy_value_bytes(value));
                 }
                 Ok(key)
             }
+            IndexType::BTree => Ok(btree_key(key_values, definition)),
             _ => Err(StorageError::Internal(format!(
                 "Unsupported index type: {:?}",
                 definition.index_type
@@ -501,8 +1093,8 @@ impl IndexEngine {
         }
     }
 
-    /// 将 BomlValue 序列化为字节
-    fn value_to_bytes(&self, value: &BomlValue) -> Vec<u8> {
+    /// 早期格式的键值序列化,负数和浮点数不保序,只用于已有索引
+    fn legacy_value_bytes(&self, value: &BomlValue) -> Vec<u8> {
         match value {
             BomlValue::Null => vec![0x00],
             BomlValue::Boolean(false) => vec![0x01, 0x00],
@@ -567,13 +1159,44 @@ impl IndexEngine {
         Ok(None)
     }
 
+    /// 前缀扫描(非唯一索引的等值查找)
+    fn prefix_scan(
+        &self,
+        definition: &IndexDefinition,
+        index_key: &[u8],
+    ) -> StorageResult<Vec<ObjectId>> {
+        let cf_name = format!("idx_{}", definition.name);
+        let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
+            StorageError::Internal(format!("Index CF {} not found", cf_name))
+        })?;
+
+        let mut doc_ids = Vec::new();
+        let iter = self.db.iterator_cf(&cf, IteratorMode::From(index_key, rocksdb::Direction::Forward));
+
+        for item in iter {
+            let (key, _) = item?;
+            // 索引项为 index_key + doc_id,长度不符说明是更长的键值恰好以此为前缀
+            if !key.starts_with(index_key) {
+                break;
+            }
+            if key.len() == index_key.len() + 12 {
+                let doc_id_bytes: [u8; 12] = key[index_key.len()..].try_into().unwrap();
+                doc_ids.push(ObjectId::from_bytes(doc_id_bytes));
+            }
+        }
+
+        Ok(doc_ids)
+    }
+
     /// 范围扫描
+    ///
+    /// 索引项为 index_key + doc_id,以边界为前缀的索引项即与边界相等的键。
     fn range_scan(
         &self,
         definition: &IndexDefinition,
-        start_key: &[u8],
-        end_key: &[u8],
-        _inclusive: bool,
+        start_key: Option<&[u8]>,
+        end_key: Option<&[u8]>,
+        inclusive: bool,
     ) -> StorageResult<Vec<ObjectId>> {
         let cf_name = format!("idx_{}", definition.name);
         let cf = self.db.cf_handle(&cf_name).ok_or_else(|| {
@@ -582,17 +1205,26 @@ impl IndexEngine {
 
         let mut doc_ids = Vec::new();
 
-        let iter = self.db.iterator_cf(
-            &cf,
-            IteratorMode::From(start_key, rocksdb::Direction::Forward),
-        );
+        let mode = match start_key {
+            Some(start) => IteratorMode::From(start, rocksdb::Direction::Forward),
+            None => IteratorMode::Start,
+        };
 
-        for item in iter {
+        for item in self.db.iterator_cf(&cf, mode) {
             let (key, _) = item?;
 
+            if let Some(start) = start_key {
+                if !inclusive && key.starts_with(start) {
+                    continue;
+                }
+            }
+
             // 检查是否超出范围
-            if key.as_ref() > end_key {
-                break;
+            if let Some(end) = end_key {
+                let at_end = key.starts_with(end);
+                if (at_end && !inclusive) || (!at_end && key.as_ref() > end) {
+                    break;
+                }
             }
 
             // 提取 doc_id
@@ -610,6 +1242,7 @@ impl IndexEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use tempfile::tempdir;
 
     #[test]
@@ -644,6 +1277,9 @@ mod tests {
             unique: false,
             sparse: false,
             ttl_seconds: None,
+            key_encoding: KeyEncoding::Memcomparable,
+            max_key_size: None,
+            oversized_keys: OversizedKeyPolicy::Reject,
         };
 
         engine.create_index(definition.clone()).unwrap();
@@ -685,6 +1321,9 @@ mod tests {
             unique: true,
             sparse: false,
             ttl_seconds: None,
+            key_encoding: KeyEncoding::Memcomparable,
+            max_key_size: None,
+            oversized_keys: OversizedKeyPolicy::Reject,
         };
 
         engine.create_index(definition).unwrap();
@@ -697,7 +1336,233 @@ mod tests {
 
         // 尝试插入相同键应该失败
         let id2 = ObjectId::new();
-        let result = engine.insert_document("unique_idx", &doc1, &id2);
-        assert!(result.is_err());
+        let err = engine.insert_document("unique_idx", &doc1, &id2).unwrap_err();
+        assert_eq!(err.code(), Some(crate::DUPLICATE_KEY_CODE));
+        assert!(err.to_string().starts_with("E11000 duplicate key error"));
+    }
+
+    #[test]
+    fn test_lookup_documents_non_unique() {
+        let dir = tempdir().unwrap();
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+
+        let db = Arc::new(
+            rocksdb::DB::open_cf_descriptors(
+                &opts,
+                dir.path(),
+                vec![
+                    rocksdb::ColumnFamilyDescriptor::new("_index_meta", rocksdb::Options::default()),
+                    rocksdb::ColumnFamilyDescriptor::new("users", rocksdb::Options::default()),
+                ],
+            )
+            .unwrap(),
+        );
+
+        let engine = IndexEngine::new(db.clone());
+        let collection = Collection::new("users".to_string(), db);
+
+        let definition = IndexDefinition {
+            name: "city_idx".to_string(),
+            collection: "users".to_string(),
+            fields: vec![IndexField {
+                path: "city".to_string(),
+                order: IndexOrder::Ascending,
+            }],
+            index_type: IndexType::BTree,
+            unique: false,
+            sparse: false,
+            ttl_seconds: None,
+            key_encoding: KeyEncoding::Memcomparable,
+            max_key_size: None,
+            oversized_keys: OversizedKeyPolicy::Reject,
+        };
+        engine.create_index(definition).unwrap();
+
+        let mut ids = Vec::new();
+        for (i, city) in ["Tokyo", "Osaka", "Tokyo", "Tokyo2", "Tokyo"].iter().enumerate() {
+            let mut doc = Document::new();
+            doc.insert("city", *city);
+            doc.insert("seq", i as i32);
+            let id = collection.insert(&mut doc).unwrap();
+            engine.insert_document("city_idx", &doc, &id).unwrap();
+            ids.push(id);
+        }
+
+        let key = [BomlValue::String("Tokyo".into())];
+        let found = engine.lookup("city_idx", &key).unwrap();
+        assert_eq!(found.len(), 3);
+
+        collection.delete(&ids[2]).unwrap();
+        let docs = engine.lookup_documents(&collection, "city_idx", &key).unwrap();
+        let mut seqs: Vec<i32> = docs.iter().map(|d| d.get_i32("seq").unwrap()).collect();
+        seqs.sort();
+        assert_eq!(seqs, vec![0, 4]);
+
+        assert_eq!(engine.prune_dangling(&collection).unwrap(), 1);
+        assert_eq!(engine.lookup("city_idx", &key).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_range_query_order() {
+        let dir = tempdir().unwrap();
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+
+        let db = Arc::new(
+            rocksdb::DB::open_cf_descriptors(
+                &opts,
+                dir.path(),
+                vec![rocksdb::ColumnFamilyDescriptor::new(
+                    "_index_meta",
+                    rocksdb::Options::default(),
+                )],
+            )
+            .unwrap(),
+        );
+
+        let engine = IndexEngine::new(db);
+        let field = |path: &str, order| IndexField { path: path.to_string(), order };
+        for (name, order) in [("score_asc", IndexOrder::Ascending), ("score_desc", IndexOrder::Descending)] {
+            engine
+                .create_index(IndexDefinition {
+                    name: name.to_string(),
+                    collection: "players".to_string(),
+                    fields: vec![field("score", order)],
+                    index_type: IndexType::BTree,
+                    unique: false,
+                    sparse: false,
+                    ttl_seconds: None,
+                    key_encoding: KeyEncoding::Legacy,
+                    max_key_size: None,
+                    oversized_keys: OversizedKeyPolicy::Reject,
+                })
+                .unwrap();
+        }
+        assert_eq!(engine.get_index("score_asc").unwrap().key_encoding, KeyEncoding::Memcomparable);
+
+        let scores = [
+            BomlValue::Int32(3),
+            BomlValue::Float64(-1.5),
+            BomlValue::Int64(-20),
+            BomlValue::Float64(2.5),
+            BomlValue::Int32(0),
+            BomlValue::Int64(-2),
+        ];
+        let mut by_id = HashMap::new();
+        for score in &scores {
+            let mut doc = Document::new();
+            doc.insert("score", score.clone());
+            let id = ObjectId::new();
+            engine.insert_document("score_asc", &doc, &id).unwrap();
+            engine.insert_document("score_desc", &doc, &id).unwrap();
+            by_id.insert(id, score.as_f64().unwrap());
+        }
+        let values = |ids: Vec<ObjectId>| ids.iter().map(|id| by_id[id]).collect::<Vec<f64>>();
+
+        let all = engine.range_query("score_asc", None, None, true).unwrap();
+        assert_eq!(values(all), vec![-20.0, -2.0, -1.5, 0.0, 2.5, 3.0]);
+
+        let start = [BomlValue::Int32(-2)];
+        let end = [BomlValue::Float64(2.5)];
+        let inclusive = engine.range_query("score_asc", Some(&start), Some(&end), true).unwrap();
+        assert_eq!(values(inclusive), vec![-2.0, -1.5, 0.0, 2.5]);
+        let exclusive = engine.range_query("score_asc", Some(&start), Some(&end), false).unwrap();
+        assert_eq!(values(exclusive), vec![-1.5, 0.0]);
+
+        let desc = engine.range_query("score_desc", Some(&end), Some(&start), true).unwrap();
+        assert_eq!(values(desc), vec![2.5, 0.0, -1.5, -2.0]);
+
+        // 整数和值相等的浮点数是同一个键
+        assert_eq!(engine.lookup("score_asc", &[BomlValue::Float64(3.0)]).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_oversized_keys() {
+        let dir = tempdir().unwrap();
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+
+        let db = Arc::new(
+            rocksdb::DB::open_cf_descriptors(
+                &opts,
+                dir.path(),
+                vec![rocksdb::ColumnFamilyDescriptor::new(
+                    "_index_meta",
+                    rocksdb::Options::default(),
+                )],
+            )
+            .unwrap(),
+        );
+
+        let engine = IndexEngine::new(db);
+        for (name, policy) in [
+            ("bio_reject", OversizedKeyPolicy::Reject),
+            ("bio_truncate", OversizedKeyPolicy::Truncate),
+            ("bio_skip", OversizedKeyPolicy::Skip),
+        ] {
+            engine
+                .create_index(IndexDefinition {
+                    name: name.to_string(),
+                    collection: "users".to_string(),
+                    fields: vec![IndexField { path: "bio".to_string(), order: IndexOrder::Ascending }],
+                    index_type: IndexType::BTree,
+                    unique: false,
+                    sparse: false,
+                    ttl_seconds: None,
+                    key_encoding: KeyEncoding::Memcomparable,
+                    max_key_size: Some(32),
+                    oversized_keys: policy,
+                })
+                .unwrap();
+        }
+
+        let doc = |bio: &str| {
+            let mut doc = Document::new();
+            doc.insert("bio", bio);
+            doc
+        };
+        let long_a = format!("{}a", "x".repeat(60));
+        let long_b = format!("{}b", "x".repeat(60));
+        let short = doc("short");
+        let (a, b) = (doc(&long_a), doc(&long_b));
+        let ids = [ObjectId::new(), ObjectId::new(), ObjectId::new()];
+
+        let err = engine.insert_document("bio_reject", &a, &ids[0]).unwrap_err();
+        let details = err.validation_details().unwrap();
+        assert_eq!(details[0].path, "/bio");
+        assert_eq!(details[0].rule, crate::schema::RULE_INDEX_KEY_SIZE);
+        engine.insert_document("bio_reject", &short, &ids[2]).unwrap();
+
+        for name in ["bio_truncate", "bio_skip"] {
+            engine.insert_document(name, &a, &ids[0]).unwrap();
+            engine.insert_document(name, &b, &ids[1]).unwrap();
+            engine.insert_document(name, &short, &ids[2]).unwrap();
+        }
+
+        // 截断的键仍然按完整值等值查找,范围查询在截断处之后退化为前缀匹配
+        assert_eq!(engine.lookup("bio_truncate", &[BomlValue::from(long_b.as_str())]).unwrap(), vec![ids[1]]);
+        assert!(engine.has_entry("bio_truncate", &a, &ids[0]).unwrap());
+        let start = [BomlValue::from(long_b.as_str())];
+        let range: HashSet<ObjectId> =
+            engine.range_query("bio_truncate", Some(&start), None, false).unwrap().into_iter().collect();
+        assert_eq!(range, ids[..2].iter().copied().collect());
+        engine.delete_document("bio_truncate", &a, &ids[0]).unwrap();
+        assert!(engine.lookup("bio_truncate", &[BomlValue::from(long_a.as_str())]).unwrap().is_empty());
+
+        // 跳过的文档没有索引项
+        assert!(engine.lookup("bio_skip", &[BomlValue::from(long_a.as_str())]).unwrap().is_empty());
+        assert_eq!(engine.range_query("bio_skip", None, None, true).unwrap(), vec![ids[2]]);
+        assert!(engine.has_entry("bio_skip", &a, &ids[0]).unwrap());
+        let skip = engine.get_index("bio_skip").unwrap();
+        assert!(skip.accepts(&[BomlValue::String("short".into())]));
+        assert!(!skip.accepts(&[BomlValue::from(long_a.as_str())]));
+
+        assert_eq!(engine.oversized_keys("bio_truncate"), 2);
+        assert_eq!(engine.oversized_keys("bio_skip"), 2);
+        assert_eq!(engine.oversized_keys("bio_reject"), 0);
     }
 }
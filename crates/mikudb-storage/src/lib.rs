@@ -1,11 +1,28 @@
 //! 存储层模块
 //!
 //! 本模块提供 MikuDB 的底层存储功能:
-//! - **StorageEngine**: 基于 RocksDB 的存储引擎
+//! - **StorageEngine**: 基于 RocksDB 的存储引擎,支持只读和从实例打开方式;多个数据库共用一个实例,集合名按数据库加前缀隔离
 //! - **Collection**: 文档集合管理
-//! - **WAL**: 预写式日志,保证持久性和崩溃恢复
-//! - **Cache**: LRU 缓存系统(文档缓存、查询缓存)
-//! - **Compaction**: LSM-tree 压缩配置和统计
+//! - **WAL**: 预写式日志,组提交写入后应用,保证持久性和崩溃恢复
+//! - **Cache**: 按键分片加锁的 LRU/LFU 缓存系统(解码后的文档缓存、查询缓存)
+//! - **Compaction**: LSM-tree 压缩配置和统计,集合及其索引的手动压缩与定时压缩
+//! - **Scrub**: 后台存储完整性巡检
+//! - **Tiering**: 冷热数据分层与归档集合
+//! - **Schema**: 可选的字段类型登记表与类型漂移检测
+//! - **Expiry**: 按字段的文档过期策略,无需创建 TTL 索引
+//! - **Capped**: 固定大小集合,超出总大小或文档数限制时按插入顺序删除最早的文档
+//! - **Sequence**: 基于合并算子的持久化序列,并发取号无需读取-修改-写回
+//! - **Merge**: 集合的文档合并算子,数值字段 `+=` 只写入增量,读取时合并
+//! - **Tokenizer**: 全文索引的可插拔分词器、停用词和词干提取
+//! - **Changes**: 进程内变更流,按恢复令牌增量读取集合的写入
+//! - **Backup**: 基于快照的逻辑备份与恢复(包含用户、角色等系统数据)
+//! - **Posting**: 基于 Roaring Bitmap 的压缩倒排列表,支持增量段合并与 AND/OR 求交并
+//! - **Perf**: 按线程统计从存储读取的字节数
+//! - **Ttl**: TTL 索引的后台清理,删除过期文档并维护集合的其他索引
+//! - **Rollup**: 写入时增量维护的预聚合集合,按字段或截断后的时间分组
+//! - **Mvcc**: 读快照期间保存被修改文档的旧版本,供快照隔离的事务按开始时的版本读取
+//! - **Bulk**: 有序或无序执行的批量插入、替换、删除,返回每个操作的结果和失败原因
+//! - **Snapshot**: 基于 RocksDB 检查点的物理快照,记录 WAL 位置,离线恢复到空数据目录;基于 WAL 段的增量备份
 //!
 //! # OpenEuler 适配亮点
 //!
@@ -22,12 +39,51 @@ pub mod compaction;
 pub mod recovery;
 pub mod index;
 pub mod fulltext;
-
-pub use collection::Collection;
-pub use engine::{StorageEngine, StorageOptions};
+pub mod tokenizer;
+pub mod scrub;
+pub mod tiering;
+pub mod schema;
+pub mod expiry;
+pub mod capped;
+pub mod sequence;
+pub mod merge;
+pub mod changes;
+pub mod backup;
+pub mod posting;
+pub mod perf;
+pub mod ttl;
+pub mod snapshot;
+pub mod rollup;
+pub mod mvcc;
+pub mod bulk;
+
+pub use collection::{Collection, SnapshotScan};
+pub use engine::{qualified_collection_name, OpenMode, StorageEngine, StorageOptions, DEFAULT_DATABASE};
 pub use recovery::{RecoveryManager, RecoveryStats};
-pub use index::{IndexDefinition, IndexEngine, IndexField, IndexOrder, IndexType};
-pub use fulltext::{FullTextIndex, FullTextIndexDefinition, IndexStats, TokenizerType};
+pub use wal::{WalStats, WalSyncPolicy};
+pub use index::{
+    IndexDefinition, IndexEngine, IndexField, IndexOrder, IndexType, KeyEncoding, OversizedKeyPolicy, TtlCleanup,
+    DEFAULT_MAX_INDEX_KEY_SIZE,
+};
+pub use fulltext::{FullTextIndex, FullTextIndexDefinition, IndexStats};
+pub use tokenizer::{StopWords, TextAnalyzer, Tokenizer, TokenizerType};
+pub use scrub::{ScrubOptions, ScrubReport, ScrubStats, Scrubber};
+pub use snapshot::{IncrementManifest, SnapshotManifest};
+pub use mvcc::{ReadSnapshot, VersionStore};
+pub use backup::{BackupManifest, BackupOptions, RestoreOptions, RestoreReport, RestoreScope};
+pub use tiering::ArchivePolicy;
+pub use expiry::ExpirePolicy;
+pub use capped::CappedOptions;
+pub use sequence::SequenceDefinition;
+pub use rollup::{RollupAggregate, RollupDefinition, RollupFunction, RollupKey, TimeBucket};
+pub use changes::{ChangeEvent, ChangeKind, ChangeStream, WriteMetadata, MAX_WRITE_METADATA_LEN};
+pub use schema::{FieldSummary, SchemaOptions, ValidationDetail};
+pub use posting::{PostingStats, PostingStore};
+pub use perf::ReadBytesMeter;
+pub use ttl::{TtlSweepStats, TtlSweeper};
+pub use bulk::{BulkWriteResult, OperationResult, WriteError, WriteOperation};
+pub use cache::{CacheStats, DocumentCache, EvictionPolicy};
+pub use compaction::{CompactionReport, CompactionScheduler, CompactionStatsSnapshot};
 
 use thiserror::Error;
 
@@ -61,9 +117,35 @@ pub enum StorageError {
     #[error("Document not found: {0}")]
     DocumentNotFound(String),
 
-    /// 文档已存在
-    #[error("Document already exists: {0}")]
-    DocumentExists(String),
+    /// 唯一键重复: 插入的 `_id` 已存在,或写入违反唯一索引
+    ///
+    /// 错误码为 [`DUPLICATE_KEY_CODE`],消息以 `E11000` 开头。
+    #[error("E11000 duplicate key error collection: {collection} index: {index} dup key: {key}")]
+    DuplicateKey {
+        collection: String,
+        index: String,
+        key: String,
+    },
+
+    /// 序列不存在
+    #[error("Sequence not found: {0}")]
+    SequenceNotFound(String),
+
+    /// 序列已存在
+    #[error("Sequence already exists: {0}")]
+    SequenceExists(String),
+
+    /// 数据库不存在
+    #[error("Database not found: {0}")]
+    DatabaseNotFound(String),
+
+    /// 数据库已存在
+    #[error("Database already exists: {0}")]
+    DatabaseExists(String),
+
+    /// 恢复令牌之后的事件已被淘汰,需要重新拉取快照
+    #[error("Resume token expired: {0}")]
+    ResumeTokenExpired(u64),
 
     /// 无效的键
     #[error("Invalid key: {0}")]
@@ -85,10 +167,84 @@ pub enum StorageError {
     #[error("Storage full")]
     StorageFull,
 
+    /// 写入违反集合的模式约束(strict_types)
+    #[error("Schema violation in {collection}: {}", schema::join_details(.details))]
+    SchemaViolation {
+        /// 集合名称
+        collection: String,
+        /// 每个出错字段的校验错误
+        details: Vec<ValidationDetail>,
+    },
+
+    /// 存储以只读或从实例方式打开,不能写入
+    #[error("Storage is opened read-only")]
+    ReadOnly,
+
+    /// 无效的参数(如未知的分词器或停用词表)
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    /// 整数运算溢出(如 `+=` 超出字段类型的范围)
+    #[error("Arithmetic overflow: {0}")]
+    Overflow(String),
+
     /// 内部错误
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+impl StorageError {
+    /// # Brief
+    /// 获取结构化的校验错误(仅模式约束错误有)
+    pub fn validation_details(&self) -> Option<&[ValidationDetail]> {
+        match self {
+            StorageError::SchemaViolation { details, .. } => Some(details),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 获取错误码,目前只有唯一键重复有错误码
+    ///
+    /// # Returns
+    /// 唯一键重复返回 [`DUPLICATE_KEY_CODE`],其余返回 None
+    pub fn code(&self) -> Option<u32> {
+        match self {
+            StorageError::DuplicateKey { .. } => Some(DUPLICATE_KEY_CODE),
+            _ => None,
+        }
+    }
+}
+
+/// 唯一键重复的错误码,与错误消息开头的 `E11000` 对应
+pub const DUPLICATE_KEY_CODE: u32 = 11000;
+
+/// 转换为统一错误类型,保留可重试的分类
+///
+/// 写冲突和 RocksDB 的 Busy/TryAgain 转换为写冲突,RocksDB 超时转换为超时,
+/// 文档不存在、唯一键重复、模式约束分别转换为对应的错误,无效参数和溢出转换为校验错误,
+/// 其余转换为存储错误。
+impl From<StorageError> for mikudb_common::MikuError {
+    fn from(e: StorageError) -> Self {
+        use mikudb_common::MikuError;
+        match e {
+            StorageError::Io(e) => MikuError::Io(e),
+            StorageError::WriteConflict => MikuError::WriteConflict(e.to_string()),
+            #[cfg(feature = "rocksdb")]
+            StorageError::RocksDb(ref inner) => match inner.kind() {
+                rocksdb::ErrorKind::Busy | rocksdb::ErrorKind::TryAgain => MikuError::WriteConflict(e.to_string()),
+                rocksdb::ErrorKind::TimedOut => MikuError::Timeout(e.to_string()),
+                _ => MikuError::Storage(e.to_string()),
+            },
+            StorageError::DocumentNotFound(id) => MikuError::NotFound(id),
+            StorageError::DuplicateKey { .. } => MikuError::DuplicateKey(e.to_string()),
+            StorageError::SchemaViolation { .. } | StorageError::InvalidArgument(_) | StorageError::Overflow(_) => {
+                MikuError::Validation(e.to_string())
+            }
+            other => MikuError::Storage(other.to_string()),
+        }
+    }
+}
+
 /// 存储操作结果类型
 pub type StorageResult<T> = Result<T, StorageError>;
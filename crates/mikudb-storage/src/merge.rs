@@ -0,0 +1,138 @@
+//! 文档合并算子模块
+//!
+//! 计数器一类的集合(浏览量、点赞数)频繁对同一文档做 `+=`,
+//! 读取-修改-写回整个文档既慢又会在并发时丢失更新。集合 CF 注册了一个合并算子:
+//! - `Collection::increment` 只写入一个很小的增量操作数(字段 -> 增量的 BOML 文档)
+//! - 读取、迭代和压缩时由 RocksDB 把增量累加到文档上,读到的总是合并后的文档
+//! - 多个增量操作数在压缩时先相加(部分合并),不必等到读取
+//! - 增量语义与执行器的 `+=` 相同: 只作用于顶层字段,缺失的字段从 0 开始
+
+use mikudb_boml::{codec, BomlValue, Document, DocumentCompression};
+use rocksdb::MergeOperands;
+use tracing::warn;
+
+/// 合并算子名称,写入 SST 文件,不可修改
+pub(crate) const DOCUMENT_MERGE_OPERATOR: &str = "mikudb.document.increment";
+
+/// 对非数值字段执行 `+=` 时的校验规则 ID
+pub const RULE_UPDATE_INC: &str = "update.inc";
+
+/// # Brief
+/// 判断值能否作为 `+=` 的操作数
+pub fn is_numeric(value: &BomlValue) -> bool {
+    matches!(value, BomlValue::Int32(_) | BomlValue::Int64(_) | BomlValue::Float64(_))
+}
+
+/// # Brief
+/// 数值相加,规则与执行器的 `+=` 相同
+///
+/// # Returns
+/// 类型不能相加或整数溢出时返回 None
+pub fn add_numeric(a: &BomlValue, b: &BomlValue) -> Option<BomlValue> {
+    match (a, b) {
+        (BomlValue::Int32(x), BomlValue::Int32(y)) => x.checked_add(*y).map(BomlValue::Int32),
+        (BomlValue::Int64(x), BomlValue::Int64(y)) => x.checked_add(*y).map(BomlValue::Int64),
+        (BomlValue::Int32(x), BomlValue::Int64(y)) => (*x as i64).checked_add(*y).map(BomlValue::Int64),
+        (BomlValue::Int64(x), BomlValue::Int32(y)) => x.checked_add(*y as i64).map(BomlValue::Int64),
+        (BomlValue::Float64(x), BomlValue::Float64(y)) => Some(BomlValue::Float64(x + y)),
+        (BomlValue::Float64(x), BomlValue::Int32(y)) => Some(BomlValue::Float64(x + *y as f64)),
+        (BomlValue::Float64(x), BomlValue::Int64(y)) => Some(BomlValue::Float64(x + *y as f64)),
+        _ => None,
+    }
+}
+
+/// # Brief
+/// 编码增量操作数
+pub(crate) fn encode_increments(deltas: &[(String, BomlValue)]) -> mikudb_boml::BomlResult<Vec<u8>> {
+    let mut operand = Document::without_id();
+    for (field, delta) in deltas {
+        operand.insert(field.as_str(), delta.clone());
+    }
+    codec::encode_document(&operand.to_boml_value())
+}
+
+fn decode(data: &[u8]) -> Option<Document> {
+    match codec::decode_document(data).and_then(Document::from_boml_value) {
+        Ok(doc) => Some(doc),
+        Err(e) => {
+            warn!("Ignoring undecodable value in merge: {}", e);
+            None
+        }
+    }
+}
+
+/// # Brief
+/// 完整合并: 把所有增量依次累加到文档上
+///
+/// 合并失败会让读取返回错误,所以这里从不失败: 无法相加的增量被跳过并记录警告,
+/// 无法解码的文档原样保留。文档不存在(增量写入前被并发删除)时从空文档开始。
+/// 合并结果按集合的压缩算法编码。
+pub(crate) fn increment_full_merge(
+    _key: &[u8],
+    existing: Option<&[u8]>,
+    operands: &MergeOperands,
+    compression: DocumentCompression,
+) -> Option<Vec<u8>> {
+    let mut doc = match existing {
+        Some(data) => match decode(data) {
+            Some(doc) => doc,
+            None => return Some(data.to_vec()),
+        },
+        None => Document::without_id(),
+    };
+
+    for deltas in operands.iter().filter_map(decode) {
+        for (field, delta) in deltas.iter() {
+            let current = doc.get(field).cloned().unwrap_or(BomlValue::Int64(0));
+            match add_numeric(&current, delta) {
+                Some(sum) => doc.insert(field, sum),
+                None => warn!(
+                    "Skipping increment of field {} ({} += {})",
+                    field,
+                    current.type_name(),
+                    delta.type_name()
+                ),
+            }
+        }
+    }
+
+    codec::encode_document_with(&doc.to_boml_value(), compression).ok()
+}
+
+/// # Brief
+/// 部分合并: 把多个增量操作数相加为一个
+///
+/// # Returns
+/// 同一字段的增量不能相加时返回 None,RocksDB 会保留原操作数留到完整合并时处理
+pub(crate) fn increment_partial_merge(
+    _key: &[u8],
+    _existing: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let mut total = Document::without_id();
+    for op in operands.iter() {
+        for (field, delta) in decode(op)?.iter() {
+            let sum = match total.get(field) {
+                Some(current) => add_numeric(current, delta)?,
+                None => delta.clone(),
+            };
+            total.insert(field, sum);
+        }
+    }
+    codec::encode_document(&total.to_boml_value()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_numeric() {
+        assert_eq!(add_numeric(&BomlValue::Int32(1), &BomlValue::Int32(2)), Some(BomlValue::Int32(3)));
+        assert_eq!(add_numeric(&BomlValue::Int32(1), &BomlValue::Int64(2)), Some(BomlValue::Int64(3)));
+        assert_eq!(add_numeric(&BomlValue::Float64(0.5), &BomlValue::Int32(1)), Some(BomlValue::Float64(1.5)));
+        assert_eq!(add_numeric(&BomlValue::Int32(i32::MAX), &BomlValue::Int32(1)), None);
+        assert_eq!(add_numeric(&BomlValue::Int32(1), &BomlValue::Float64(1.0)), None);
+        assert_eq!(add_numeric(&BomlValue::String("1".into()), &BomlValue::Int32(1)), None);
+    }
+}
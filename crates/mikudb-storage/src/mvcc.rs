@@ -0,0 +1,426 @@
+//! 多版本文档模块
+//!
+//! 为快照隔离的读取保留文档的旧版本:
+//! - 每次写入从版本时钟取一个递增的版本号
+//! - 存在活跃的读快照时,写入把文档修改前的内容(前像)以 `(集合, _id, 版本号)` 为键写入
+//!   `_versions` 列族,与文档修改在同一个 WriteBatch 中原子提交;新插入的文档记录为"不存在"
+//! - 读快照记录开始时的版本号 S。读取文档时查找该文档版本号大于 S 的第一条前像,
+//!   它就是文档在 S 时的内容;没有这样的前像说明 S 之后文档没有被修改,当前内容即快照内容
+//! - 垃圾回收删除版本号不大于最早活跃快照的前像,最后一个读快照释放时自动执行一次
+//!
+//! 没有活跃读快照时写入不读取前像,也不写入 `_versions`。
+//! 旧版本只服务于进程内的读快照,不写入 WAL,打开存储引擎时清空。
+
+use crate::{StorageError, StorageResult};
+use mikudb_common::ObjectId;
+use parking_lot::{Mutex, RwLock, RwLockReadGuard};
+use rocksdb::{Direction, IteratorMode, Options, ReadOptions, WriteBatch, DB};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// 保存文档旧版本的列族
+pub(crate) const VERSIONS_CF: &str = "_versions";
+
+/// 前像标记: 版本写入前文档不存在
+const TAG_ABSENT: u8 = 0;
+/// 前像标记: 之后是文档修改前的存储字节
+const TAG_PRESENT: u8 = 1;
+
+/// 文档版本存储
+///
+/// 由存储引擎创建,所有集合共享同一个版本时钟。
+pub struct VersionStore {
+    db: Arc<DB>,
+    /// 最近一次写入的版本号
+    clock: AtomicU64,
+    /// 写入期间持有读锁;开始读快照时获取写锁,等待进行中的写入完成
+    gate: RwLock<()>,
+    /// 活跃读快照的版本号及其数量
+    active: Mutex<BTreeMap<u64, usize>>,
+    active_count: AtomicUsize,
+}
+
+impl VersionStore {
+    /// # Brief
+    /// 清空上次运行留下的旧版本并创建版本存储
+    ///
+    /// 只读和从实例不会写入,不创建 `_versions` 列族
+    ///
+    /// # Arguments
+    /// * `db` - RocksDB 实例
+    /// * `writable` - 是否以读写方式打开
+    pub(crate) fn open(db: Arc<DB>, writable: bool) -> StorageResult<Self> {
+        if writable {
+            if db.cf_handle(VERSIONS_CF).is_some() {
+                db.drop_cf(VERSIONS_CF)?;
+            }
+            db.create_cf(VERSIONS_CF, &Options::default())?;
+        }
+        Ok(Self {
+            db,
+            clock: AtomicU64::new(0),
+            gate: RwLock::new(()),
+            active: Mutex::new(BTreeMap::new()),
+            active_count: AtomicUsize::new(0),
+        })
+    }
+
+    /// # Brief
+    /// 开始一次写入
+    ///
+    /// 返回的守卫需要在读取前像到写入完成期间一直持有
+    pub(crate) fn begin_write(&self) -> VersionedWrite<'_> {
+        let gate = self.gate.read();
+        let version = self.clock.fetch_add(1, Ordering::SeqCst) + 1;
+        let tracking = self.active_count.load(Ordering::SeqCst) > 0;
+        VersionedWrite {
+            store: tracking.then_some((self, version)),
+            _gate: Some(gate),
+        }
+    }
+
+    /// # Brief
+    /// 开始一个读快照
+    ///
+    /// 等待进行中的写入完成后记录当前版本号,之后的写入都会保存前像,直到快照释放
+    ///
+    /// # Returns
+    /// 读快照,释放时注销
+    pub fn begin_snapshot(self: &Arc<Self>) -> ReadSnapshot {
+        let _gate = self.gate.write();
+        let version = self.clock.load(Ordering::SeqCst);
+        *self.active.lock().entry(version).or_insert(0) += 1;
+        self.active_count.fetch_add(1, Ordering::SeqCst);
+        ReadSnapshot {
+            store: self.clone(),
+            version,
+        }
+    }
+
+    fn release(&self, version: u64) {
+        let mut active = self.active.lock();
+        if let Some(count) = active.get_mut(&version) {
+            *count -= 1;
+            if *count == 0 {
+                active.remove(&version);
+            }
+        }
+        let remaining = self.active_count.fetch_sub(1, Ordering::SeqCst) - 1;
+        drop(active);
+
+        if remaining == 0 {
+            if let Err(e) = self.gc() {
+                warn!("Version garbage collection failed: {}", e);
+            }
+        }
+    }
+
+    /// # Brief
+    /// 活跃读快照的数量
+    pub fn active_snapshots(&self) -> usize {
+        self.active_count.load(Ordering::SeqCst)
+    }
+
+    /// # Brief
+    /// 删除不再被任何活跃读快照需要的前像
+    ///
+    /// 版本号不大于最早活跃快照(没有活跃快照时为当前版本号)的前像都可以删除。
+    /// 回收期间仍在提交的写入留下的前像由下一次回收删除。
+    ///
+    /// # Returns
+    /// 删除的前像数量
+    pub fn gc(&self) -> StorageResult<u64> {
+        let Some(cf) = self.db.cf_handle(VERSIONS_CF) else {
+            return Ok(0);
+        };
+        let horizon = {
+            let active = self.active.lock();
+            active.keys().next().copied().unwrap_or_else(|| self.clock.load(Ordering::SeqCst))
+        };
+
+        let mut batch = WriteBatch::default();
+        let mut removed = 0u64;
+        for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+            let (key, _) = item?;
+            if version_of(&key).is_some_and(|version| version <= horizon) {
+                batch.delete_cf(&cf, &key);
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            self.db.write(batch)?;
+            debug!("Removed {} document versions up to version {}", removed, horizon);
+        }
+        Ok(removed)
+    }
+
+    /// # Brief
+    /// 查找文档在 `version` 之后的第一条前像
+    ///
+    /// # Returns
+    /// `None` 表示之后没有修改;`Some(None)` 表示文档在该版本时不存在
+    pub(crate) fn previous_at(
+        &self,
+        collection: &str,
+        id: &ObjectId,
+        version: u64,
+        read_opts: ReadOptions,
+    ) -> StorageResult<Option<Option<Vec<u8>>>> {
+        let Some(cf) = self.db.cf_handle(VERSIONS_CF) else {
+            return Ok(None);
+        };
+        let prefix = document_prefix(collection, id);
+        let mut start = prefix.clone();
+        start.extend_from_slice(&(version + 1).to_be_bytes());
+
+        let mut iter = self.db.iterator_cf_opt(&cf, read_opts, IteratorMode::From(&start, Direction::Forward));
+        match iter.next() {
+            Some(item) => {
+                let (key, value) = item?;
+                if key.len() == prefix.len() + 8 && key.starts_with(&prefix) {
+                    Ok(Some(decode_previous(&value)?))
+                } else {
+                    Ok(None)
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// # Brief
+    /// 查找集合中每个在 `version` 之后被修改的文档的第一条前像
+    ///
+    /// # Returns
+    /// 文档 ID 到前像的映射,前像为 None 表示文档在该版本时不存在
+    pub(crate) fn previous_all_at(
+        &self,
+        collection: &str,
+        version: u64,
+        read_opts: ReadOptions,
+    ) -> StorageResult<HashMap<[u8; 12], Option<Vec<u8>>>> {
+        let mut previous = HashMap::new();
+        let Some(cf) = self.db.cf_handle(VERSIONS_CF) else {
+            return Ok(previous);
+        };
+        let mut prefix = collection.as_bytes().to_vec();
+        prefix.push(0);
+
+        for item in self.db.iterator_cf_opt(&cf, read_opts, IteratorMode::From(&prefix, Direction::Forward)) {
+            let (key, value) = item?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let Some(rest) = key.get(prefix.len()..).filter(|rest| rest.len() == 20) else {
+                continue;
+            };
+            let mut id = [0u8; 12];
+            id.copy_from_slice(&rest[..12]);
+            // 同一文档的前像按版本号升序排列,保留第一条晚于快照的
+            if version_of(&key).is_some_and(|v| v > version) && !previous.contains_key(&id) {
+                previous.insert(id, decode_previous(&value)?);
+            }
+        }
+        Ok(previous)
+    }
+}
+
+/// 一次写入的版本守卫
+///
+/// 持有期间不会开始新的读快照
+pub(crate) struct VersionedWrite<'a> {
+    store: Option<(&'a VersionStore, u64)>,
+    _gate: Option<RwLockReadGuard<'a, ()>>,
+}
+
+impl VersionedWrite<'_> {
+    /// # Brief
+    /// 不记录版本的写入(集合不属于存储引擎时使用)
+    pub(crate) fn untracked() -> Self {
+        Self { store: None, _gate: None }
+    }
+
+    /// # Brief
+    /// 本次写入是否需要保存前像
+    pub(crate) fn is_tracking(&self) -> bool {
+        self.store.is_some()
+    }
+
+    /// # Brief
+    /// 存在活跃读快照时把文档修改前的存储字节加入同一批次
+    ///
+    /// # Arguments
+    /// * `batch` - 文档修改所在的批次
+    /// * `collection` - 集合名称
+    /// * `id` - 文档 ID
+    /// * `previous` - 修改前的存储字节,文档不存在时为 None
+    pub(crate) fn save(
+        &self,
+        batch: &mut WriteBatch,
+        collection: &str,
+        id: &ObjectId,
+        previous: Option<&[u8]>,
+    ) -> StorageResult<()> {
+        let Some((store, version)) = self.store else {
+            return Ok(());
+        };
+        let cf = store.db.cf_handle(VERSIONS_CF).ok_or_else(|| {
+            StorageError::Internal("Versions CF not found".to_string())
+        })?;
+        let mut key = document_prefix(collection, id);
+        key.extend_from_slice(&version.to_be_bytes());
+        let value = match previous {
+            Some(data) => {
+                let mut value = Vec::with_capacity(data.len() + 1);
+                value.push(TAG_PRESENT);
+                value.extend_from_slice(data);
+                value
+            }
+            None => vec![TAG_ABSENT],
+        };
+        batch.put_cf(&cf, key, value);
+        Ok(())
+    }
+}
+
+/// 读快照
+///
+/// 持有期间之后的写入都保存前像,释放时注销,最后一个快照释放后回收旧版本
+pub struct ReadSnapshot {
+    store: Arc<VersionStore>,
+    version: u64,
+}
+
+impl ReadSnapshot {
+    /// # Brief
+    /// 快照开始时的版本号
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub(crate) fn store(&self) -> &VersionStore {
+        &self.store
+    }
+}
+
+impl Drop for ReadSnapshot {
+    fn drop(&mut self) {
+        self.store.release(self.version);
+    }
+}
+
+impl std::fmt::Debug for ReadSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadSnapshot").field("version", &self.version).finish()
+    }
+}
+
+fn document_prefix(collection: &str, id: &ObjectId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(collection.len() + 21);
+    key.extend_from_slice(collection.as_bytes());
+    key.push(0);
+    key.extend_from_slice(id.as_bytes());
+    key
+}
+
+fn version_of(key: &[u8]) -> Option<u64> {
+    let bytes: [u8; 8] = key.get(key.len().checked_sub(8)?..)?.try_into().ok()?;
+    Some(u64::from_be_bytes(bytes))
+}
+
+fn decode_previous(value: &[u8]) -> StorageResult<Option<Vec<u8>>> {
+    match value.split_first() {
+        Some((&TAG_ABSENT, _)) => Ok(None),
+        Some((&TAG_PRESENT, data)) => Ok(Some(data.to_vec())),
+        _ => Err(StorageError::Corruption("Invalid document version".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{StorageEngine, StorageOptions};
+    use mikudb_boml::Document;
+
+    fn open(dir: &tempfile::TempDir) -> StorageEngine {
+        StorageEngine::open(StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn value_of(doc: &Document) -> i64 {
+        doc.get("v").and_then(|v| v.as_i64()).unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_reads_versions_as_of_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = open(&dir);
+        let coll = engine.create_collection("c").unwrap();
+
+        let mut kept = Document::new();
+        kept.insert("v", 1i64);
+        let kept_id = coll.insert(&mut kept).unwrap();
+        let mut removed = Document::new();
+        removed.insert("v", 2i64);
+        let removed_id = coll.insert(&mut removed).unwrap();
+
+        let snapshot = engine.begin_snapshot();
+
+        let mut changed = Document::new();
+        changed.insert("_id", kept_id);
+        changed.insert("v", 10i64);
+        coll.update(&kept_id, &changed).unwrap();
+        coll.increment(&kept_id, &[("v".to_string(), mikudb_boml::BomlValue::Int64(5))]).unwrap();
+        coll.delete(&removed_id).unwrap();
+        let mut added = Document::new();
+        added.insert("v", 3i64);
+        let added_id = coll.insert(&mut added).unwrap();
+
+        assert_eq!(value_of(&coll.get(&kept_id).unwrap().unwrap()), 15);
+        assert_eq!(value_of(&coll.get_as_of(&kept_id, &snapshot).unwrap().unwrap()), 1);
+        assert_eq!(value_of(&coll.get_as_of(&removed_id, &snapshot).unwrap().unwrap()), 2);
+        assert!(coll.get_as_of(&added_id, &snapshot).unwrap().is_none());
+
+        let mut values: Vec<i64> = coll.find_all_as_of(&snapshot).unwrap().iter().map(value_of).collect();
+        values.sort_unstable();
+        assert_eq!(values, vec![1, 2]);
+
+        // 快照之后开始的快照看到最新内容
+        let later = engine.begin_snapshot();
+        assert_eq!(value_of(&coll.get_as_of(&kept_id, &later).unwrap().unwrap()), 15);
+        assert!(coll.get_as_of(&removed_id, &later).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_versions_collected_after_release() {
+        let dir = tempfile::tempdir().unwrap();
+        let engine = open(&dir);
+        let coll = engine.create_collection("c").unwrap();
+
+        let mut doc = Document::new();
+        doc.insert("v", 1i64);
+        let id = coll.insert(&mut doc).unwrap();
+
+        let first = engine.begin_snapshot();
+        coll.increment(&id, &[("v".to_string(), mikudb_boml::BomlValue::Int64(1))]).unwrap();
+        let second = engine.begin_snapshot();
+        coll.increment(&id, &[("v".to_string(), mikudb_boml::BomlValue::Int64(1))]).unwrap();
+        assert_eq!(engine.versions().active_snapshots(), 2);
+
+        // 释放较早的快照后,只有第二个快照还需要的前像保留
+        drop(first);
+        assert_eq!(engine.versions().gc().unwrap(), 1);
+        assert_eq!(value_of(&coll.get_as_of(&id, &second).unwrap().unwrap()), 2);
+
+        drop(second);
+        assert_eq!(engine.versions().active_snapshots(), 0);
+        assert_eq!(engine.versions().gc().unwrap(), 0);
+
+        // 没有活跃快照时写入不保存前像
+        coll.increment(&id, &[("v".to_string(), mikudb_boml::BomlValue::Int64(1))]).unwrap();
+        assert_eq!(engine.versions().gc().unwrap(), 0);
+    }
+}
@@ -0,0 +1,66 @@
+//! 读取字节计数模块
+//!
+//! 基于 RocksDB 线程局部的 PerfContext 统计当前线程从存储读取的字节数
+//! (点查、批量点查和迭代器),用于按语句报告资源消耗。
+
+use rocksdb::perf::{set_perf_stats, PerfContext, PerfMetric, PerfStatsLevel};
+
+/// 当前线程的读取字节计数器
+///
+/// 创建时为当前线程开启计数级别的性能统计并清零,释放时关闭。
+/// 计数只覆盖创建它的线程,不能跨线程使用。
+pub struct ReadBytesMeter {
+    context: PerfContext,
+}
+
+impl ReadBytesMeter {
+    /// # Brief
+    /// 开始统计当前线程的读取字节数
+    pub fn start() -> Self {
+        set_perf_stats(PerfStatsLevel::EnableCount);
+        let mut context = PerfContext::default();
+        context.reset();
+        Self { context }
+    }
+
+    /// # Brief
+    /// 自 `start` 以来读取的字节数
+    pub fn bytes_read(&self) -> u64 {
+        self.context.metric(PerfMetric::GetReadBytes)
+            + self.context.metric(PerfMetric::MultigetReadBytes)
+            + self.context.metric(PerfMetric::IterReadBytes)
+    }
+}
+
+impl Drop for ReadBytesMeter {
+    fn drop(&mut self) {
+        set_perf_stats(PerfStatsLevel::Disable);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{StorageEngine, StorageOptions};
+    use mikudb_boml::Document;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_bytes_meter() {
+        let dir = tempdir().unwrap();
+        let engine = StorageEngine::open(StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap();
+        let collection = engine.create_collection("events").unwrap();
+        let mut doc = Document::new();
+        doc.insert("payload", "x".repeat(256));
+        collection.insert(&mut doc).unwrap();
+
+        let meter = ReadBytesMeter::start();
+        assert_eq!(meter.bytes_read(), 0);
+        collection.find_all().unwrap();
+        assert!(meter.bytes_read() >= 256);
+    }
+}
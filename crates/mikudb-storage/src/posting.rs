@@ -0,0 +1,593 @@
+//! 压缩倒排列表模块
+//!
+//! 为全文索引、多键索引提供基于 Roaring Bitmap 的持久化倒排列表:
+//! - **整数文档号**: ObjectId 映射为递增的 u32 文档号,倒排列表只保存文档号
+//! - **增量段**: 写入只追加很小的增量段(新增位图 + 删除位图),不改写已有的基础位图
+//! - **LSM 式合并**: 某个词项的增量段超过 MAX_DELTA_SEGMENTS 时合并进基础位图
+//! - **布尔运算**: AND/OR 直接在压缩位图上求交、求并,结果再批量映射回 ObjectId
+//!
+//! # 存储布局
+//!
+//! 所有数据存储在独立的 `posting_{name}` ColumnFamily 中,词项编码为 `u16 长度 + UTF-8 字节`:
+//!
+//! ```text
+//! m{ObjectId}           -> u32 文档号
+//! r{u32 文档号}          -> ObjectId
+//! n                     -> 下一个可分配的文档号
+//! s                     -> 下一个增量段序号
+//! b{term}               -> 基础位图
+//! d{term}{u64 序号}      -> 增量段: u32 新增位图长度 + 新增位图 + 删除位图
+//! ```
+//!
+//! 数字均为大端序,同一词项的增量段按序号排列,读取时依次应用到基础位图上。
+//! 文档号不会回收,删除文档只会把它从倒排列表中移除。
+
+use crate::collection::MULTI_GET_CHUNK_SIZE;
+use crate::{StorageError, StorageResult};
+use mikudb_common::ObjectId;
+use parking_lot::Mutex;
+use rocksdb::{BoundColumnFamily, IteratorMode, WriteBatch, DB};
+use roaring::RoaringBitmap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// 单个词项允许积累的增量段数量,超过后合并进基础位图
+pub const MAX_DELTA_SEGMENTS: usize = 16;
+
+const KEY_NEXT_DOC: &[u8] = b"n";
+const KEY_NEXT_SEQ: &[u8] = b"s";
+
+/// 压缩倒排列表存储
+pub struct PostingStore {
+    /// 所属索引名称
+    name: String,
+    /// RocksDB 实例
+    db: Arc<DB>,
+    /// ColumnFamily 名称
+    cf_name: String,
+    /// 写入状态,串行化文档号分配、增量段写入与合并
+    writer: Mutex<WriterState>,
+}
+
+/// 写入状态
+struct WriterState {
+    /// 下一个可分配的文档号
+    next_doc: u32,
+    /// 下一个增量段序号
+    next_seq: u64,
+    /// 每个词项当前的增量段数量(首次写入时从磁盘统计)
+    delta_counts: HashMap<String, usize>,
+}
+
+/// 倒排列表存储统计
+#[derive(Debug, Clone, Default)]
+pub struct PostingStats {
+    /// 已分配的文档号数量
+    pub doc_numbers: u32,
+    /// 有基础位图的词项数
+    pub terms: usize,
+    /// 尚未合并的增量段数
+    pub delta_segments: usize,
+    /// 基础位图和增量段占用的字节数(未计入 RocksDB 块压缩)
+    pub posting_bytes: u64,
+}
+
+impl PostingStore {
+    /// # Brief
+    /// 打开倒排列表存储,ColumnFamily 不存在时创建
+    ///
+    /// # Arguments
+    /// * `db` - RocksDB 实例
+    /// * `name` - 索引名称
+    pub fn open(db: Arc<DB>, name: &str) -> StorageResult<Self> {
+        let cf_name = format!("posting_{}", name);
+        if db.cf_handle(&cf_name).is_none() {
+            let mut opts = rocksdb::Options::default();
+            opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+            db.create_cf(&cf_name, &opts)?;
+            info!("Created posting store: {}", cf_name);
+        }
+
+        let cf = db.cf_handle(&cf_name).ok_or_else(|| {
+            StorageError::Internal(format!("Posting CF {} not found", cf_name))
+        })?;
+        let next_doc = match db.get_cf(&cf, KEY_NEXT_DOC)? {
+            Some(bytes) => u32::from_be_bytes(fixed(&bytes, "next doc number")?),
+            None => 0,
+        };
+        let next_seq = match db.get_cf(&cf, KEY_NEXT_SEQ)? {
+            Some(bytes) => u64::from_be_bytes(fixed(&bytes, "next delta sequence")?),
+            None => 0,
+        };
+        drop(cf);
+
+        Ok(Self {
+            name: name.to_string(),
+            db,
+            cf_name,
+            writer: Mutex::new(WriterState {
+                next_doc,
+                next_seq,
+                delta_counts: HashMap::new(),
+            }),
+        })
+    }
+
+    /// # Brief
+    /// 查询文档对应的整数文档号
+    pub fn doc_number(&self, doc_id: &ObjectId) -> StorageResult<Option<u32>> {
+        let cf = self.cf()?;
+        match self.db.get_cf(&cf, map_key(doc_id))? {
+            Some(bytes) => Ok(Some(u32::from_be_bytes(fixed(&bytes, "doc number")?))),
+            None => Ok(None),
+        }
+    }
+
+    /// # Brief
+    /// 把文档加入多个词项的倒排列表
+    ///
+    /// 文档还没有文档号时分配一个;每个词项追加一个增量段,所有写入在同一个 WriteBatch 中提交。
+    ///
+    /// # Arguments
+    /// * `doc_id` - 文档 ID
+    /// * `terms` - 文档包含的词项
+    ///
+    /// # Returns
+    /// 文档号
+    pub fn add_document(&self, doc_id: &ObjectId, terms: &[String]) -> StorageResult<u32> {
+        let cf = self.cf()?;
+        let mut writer = self.writer.lock();
+        let mut batch = WriteBatch::default();
+
+        let number = match self.doc_number(doc_id)? {
+            Some(number) => number,
+            None => {
+                let number = writer.next_doc;
+                writer.next_doc = number.checked_add(1).ok_or(StorageError::StorageFull)?;
+                batch.put_cf(&cf, map_key(doc_id), number.to_be_bytes());
+                batch.put_cf(&cf, reverse_key(number), doc_id.as_bytes());
+                batch.put_cf(&cf, KEY_NEXT_DOC, writer.next_doc.to_be_bytes());
+                number
+            }
+        };
+
+        let mut delta = RoaringBitmap::new();
+        delta.insert(number);
+        let touched = self.write_deltas(&cf, &mut writer, &mut batch, terms, &delta, &RoaringBitmap::new())?;
+        self.db.write(batch)?;
+
+        self.compact_full_terms(&cf, &mut writer, touched)?;
+        Ok(number)
+    }
+
+    /// # Brief
+    /// 把文档从多个词项的倒排列表中移除
+    ///
+    /// # Returns
+    /// 文档没有文档号(从未加入)时返回 false
+    pub fn remove_document(&self, doc_id: &ObjectId, terms: &[String]) -> StorageResult<bool> {
+        let cf = self.cf()?;
+        let mut writer = self.writer.lock();
+
+        let number = match self.doc_number(doc_id)? {
+            Some(number) => number,
+            None => return Ok(false),
+        };
+
+        let mut batch = WriteBatch::default();
+        let mut delta = RoaringBitmap::new();
+        delta.insert(number);
+        let touched = self.write_deltas(&cf, &mut writer, &mut batch, terms, &RoaringBitmap::new(), &delta)?;
+        self.db.write(batch)?;
+
+        self.compact_full_terms(&cf, &mut writer, touched)?;
+        Ok(true)
+    }
+
+    /// # Brief
+    /// 读取词项的完整倒排列表(基础位图 + 所有增量段)
+    pub fn postings(&self, term: &str) -> StorageResult<RoaringBitmap> {
+        let cf = self.cf()?;
+        let snapshot = self.db.snapshot();
+
+        let mut bitmap = match snapshot.get_cf(&cf, base_key(term)?)? {
+            Some(bytes) => decode_bitmap(&bytes)?,
+            None => RoaringBitmap::new(),
+        };
+
+        let prefix = delta_prefix(term)?;
+        for item in snapshot.iterator_cf(&cf, IteratorMode::From(&prefix, rocksdb::Direction::Forward)) {
+            let (key, value) = item?;
+            if !is_delta_of(&key, &prefix) {
+                break;
+            }
+            apply_delta(&mut bitmap, &value)?;
+        }
+
+        Ok(bitmap)
+    }
+
+    /// # Brief
+    /// 求多个词项倒排列表的交集(AND)
+    ///
+    /// 从最短的列表开始求交,结果为空时提前结束。
+    pub fn intersect(&self, terms: &[String]) -> StorageResult<RoaringBitmap> {
+        if terms.is_empty() {
+            return Ok(RoaringBitmap::new());
+        }
+
+        let mut bitmaps = terms
+            .iter()
+            .map(|term| self.postings(term))
+            .collect::<StorageResult<Vec<_>>>()?;
+        bitmaps.sort_by_key(|bitmap| bitmap.len());
+
+        let mut iter = bitmaps.into_iter();
+        let mut result = iter.next().unwrap_or_default();
+        for bitmap in iter {
+            if result.is_empty() {
+                break;
+            }
+            result &= bitmap;
+        }
+        Ok(result)
+    }
+
+    /// # Brief
+    /// 求多个词项倒排列表的并集(OR)
+    pub fn union(&self, terms: &[String]) -> StorageResult<RoaringBitmap> {
+        let mut result = RoaringBitmap::new();
+        for term in terms {
+            result |= self.postings(term)?;
+        }
+        Ok(result)
+    }
+
+    /// # Brief
+    /// 把文档号位图映射回 ObjectId
+    ///
+    /// 按 MULTI_GET_CHUNK_SIZE 分块 multi_get,结果按文档号升序(即首次索引的顺序)。
+    pub fn resolve(&self, bitmap: &RoaringBitmap) -> StorageResult<Vec<ObjectId>> {
+        let cf = self.cf()?;
+        let numbers: Vec<u32> = bitmap.iter().collect();
+        let mut doc_ids = Vec::with_capacity(numbers.len());
+
+        for chunk in numbers.chunks(MULTI_GET_CHUNK_SIZE) {
+            let keys: Vec<Vec<u8>> = chunk.iter().map(|n| reverse_key(*n)).collect();
+            for result in self.db.batched_multi_get_cf(&cf, &keys, false) {
+                if let Some(bytes) = result? {
+                    doc_ids.push(ObjectId::from_bytes(fixed(&bytes, "doc id")?));
+                }
+            }
+        }
+
+        Ok(doc_ids)
+    }
+
+    /// # Brief
+    /// 把词项的所有增量段合并进基础位图
+    pub fn compact_term(&self, term: &str) -> StorageResult<()> {
+        let cf = self.cf()?;
+        let mut writer = self.writer.lock();
+        self.compact_locked(&cf, &mut writer, term)
+    }
+
+    /// # Brief
+    /// 合并所有词项的增量段
+    ///
+    /// # Returns
+    /// 被合并的词项数
+    pub fn compact_all(&self) -> StorageResult<usize> {
+        let cf = self.cf()?;
+        let mut writer = self.writer.lock();
+
+        let mut terms = Vec::new();
+        for item in self.db.iterator_cf(&cf, IteratorMode::From(b"d", rocksdb::Direction::Forward)) {
+            let (key, _) = item?;
+            if key.first() != Some(&b'd') {
+                break;
+            }
+            let term = term_of_delta(&key)?;
+            if terms.last() != Some(&term) {
+                terms.push(term);
+            }
+        }
+
+        for term in &terms {
+            self.compact_locked(&cf, &mut writer, term)?;
+        }
+        Ok(terms.len())
+    }
+
+    /// # Brief
+    /// 统计存储占用
+    pub fn stats(&self) -> StorageResult<PostingStats> {
+        let cf = self.cf()?;
+        let mut stats = PostingStats {
+            doc_numbers: self.writer.lock().next_doc,
+            ..Default::default()
+        };
+
+        for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+            let (key, value) = item?;
+            match key.first() {
+                Some(b'b') => {
+                    stats.terms += 1;
+                    stats.posting_bytes += value.len() as u64;
+                }
+                Some(b'd') => {
+                    stats.delta_segments += 1;
+                    stats.posting_bytes += value.len() as u64;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(stats)
+    }
+
+    // ========== 内部辅助方法 ==========
+
+    fn cf(&self) -> StorageResult<Arc<BoundColumnFamily<'_>>> {
+        self.db.cf_handle(&self.cf_name).ok_or_else(|| {
+            StorageError::Internal(format!("Posting CF {} not found", self.cf_name))
+        })
+    }
+
+    /// 为每个词项写入一个增量段,返回增量段数量达到上限的词项
+    fn write_deltas(
+        &self,
+        cf: &Arc<BoundColumnFamily>,
+        writer: &mut WriterState,
+        batch: &mut WriteBatch,
+        terms: &[String],
+        added: &RoaringBitmap,
+        removed: &RoaringBitmap,
+    ) -> StorageResult<Vec<String>> {
+        let value = encode_delta(added, removed)?;
+        let mut full = Vec::new();
+
+        for term in terms {
+            let mut key = delta_prefix(term)?;
+            key.extend_from_slice(&writer.next_seq.to_be_bytes());
+            writer.next_seq += 1;
+            batch.put_cf(cf, key, &value);
+
+            let count = match writer.delta_counts.get(term) {
+                Some(count) => *count,
+                None => self.count_deltas(cf, term)?,
+            } + 1;
+            writer.delta_counts.insert(term.clone(), count);
+            if count >= MAX_DELTA_SEGMENTS {
+                full.push(term.clone());
+            }
+        }
+        batch.put_cf(cf, KEY_NEXT_SEQ, writer.next_seq.to_be_bytes());
+
+        Ok(full)
+    }
+
+    fn compact_full_terms(
+        &self,
+        cf: &Arc<BoundColumnFamily>,
+        writer: &mut WriterState,
+        terms: Vec<String>,
+    ) -> StorageResult<()> {
+        for term in terms {
+            self.compact_locked(cf, writer, &term)?;
+        }
+        Ok(())
+    }
+
+    /// 合并词项的增量段,调用方必须持有写锁
+    fn compact_locked(
+        &self,
+        cf: &Arc<BoundColumnFamily>,
+        writer: &mut WriterState,
+        term: &str,
+    ) -> StorageResult<()> {
+        let base = base_key(term)?;
+        let mut bitmap = match self.db.get_cf(cf, &base)? {
+            Some(bytes) => decode_bitmap(&bytes)?,
+            None => RoaringBitmap::new(),
+        };
+
+        let mut batch = WriteBatch::default();
+        let mut merged = 0usize;
+        let prefix = delta_prefix(term)?;
+        for item in self.db.iterator_cf(cf, IteratorMode::From(&prefix, rocksdb::Direction::Forward)) {
+            let (key, value) = item?;
+            if !is_delta_of(&key, &prefix) {
+                break;
+            }
+            apply_delta(&mut bitmap, &value)?;
+            batch.delete_cf(cf, &key);
+            merged += 1;
+        }
+
+        if bitmap.is_empty() {
+            batch.delete_cf(cf, &base);
+        } else {
+            batch.put_cf(cf, &base, encode_bitmap(&bitmap)?);
+        }
+        self.db.write(batch)?;
+        writer.delta_counts.insert(term.to_string(), 0);
+
+        debug!(
+            "Compacted {} delta segments of term '{}' in posting store {} ({} docs)",
+            merged,
+            term,
+            self.name,
+            bitmap.len()
+        );
+        Ok(())
+    }
+
+    fn count_deltas(&self, cf: &Arc<BoundColumnFamily>, term: &str) -> StorageResult<usize> {
+        let prefix = delta_prefix(term)?;
+        let mut count = 0;
+        for item in self.db.iterator_cf(cf, IteratorMode::From(&prefix, rocksdb::Direction::Forward)) {
+            let (key, _) = item?;
+            if !is_delta_of(&key, &prefix) {
+                break;
+            }
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+fn fixed<const N: usize>(bytes: &[u8], what: &str) -> StorageResult<[u8; N]> {
+    bytes
+        .try_into()
+        .map_err(|_| StorageError::Corruption(format!("Invalid {} in posting store", what)))
+}
+
+fn map_key(doc_id: &ObjectId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(13);
+    key.push(b'm');
+    key.extend_from_slice(doc_id.as_bytes());
+    key
+}
+
+fn reverse_key(number: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(5);
+    key.push(b'r');
+    key.extend_from_slice(&number.to_be_bytes());
+    key
+}
+
+fn term_key(tag: u8, term: &str) -> StorageResult<Vec<u8>> {
+    let len = u16::try_from(term.len())
+        .map_err(|_| StorageError::InvalidKey(format!("Term too long: {} bytes", term.len())))?;
+    let mut key = Vec::with_capacity(3 + term.len() + 8);
+    key.push(tag);
+    key.extend_from_slice(&len.to_be_bytes());
+    key.extend_from_slice(term.as_bytes());
+    Ok(key)
+}
+
+fn base_key(term: &str) -> StorageResult<Vec<u8>> {
+    term_key(b'b', term)
+}
+
+fn delta_prefix(term: &str) -> StorageResult<Vec<u8>> {
+    term_key(b'd', term)
+}
+
+fn is_delta_of(key: &[u8], prefix: &[u8]) -> bool {
+    key.len() == prefix.len() + 8 && key.starts_with(prefix)
+}
+
+fn term_of_delta(key: &[u8]) -> StorageResult<String> {
+    let corrupt = || StorageError::Corruption("Invalid delta key in posting store".to_string());
+    if key.len() < 3 + 8 {
+        return Err(corrupt());
+    }
+    let len = u16::from_be_bytes([key[1], key[2]]) as usize;
+    let term = key.get(3..3 + len).ok_or_else(corrupt)?;
+    String::from_utf8(term.to_vec()).map_err(|_| corrupt())
+}
+
+fn encode_bitmap(bitmap: &RoaringBitmap) -> StorageResult<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(bitmap.serialized_size());
+    bitmap.serialize_into(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn decode_bitmap(bytes: &[u8]) -> StorageResult<RoaringBitmap> {
+    RoaringBitmap::deserialize_from(bytes)
+        .map_err(|e| StorageError::Corruption(format!("Invalid posting bitmap: {}", e)))
+}
+
+fn encode_delta(added: &RoaringBitmap, removed: &RoaringBitmap) -> StorageResult<Vec<u8>> {
+    let added = encode_bitmap(added)?;
+    let removed = encode_bitmap(removed)?;
+    let mut bytes = Vec::with_capacity(4 + added.len() + removed.len());
+    bytes.extend_from_slice(&(added.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&added);
+    bytes.extend_from_slice(&removed);
+    Ok(bytes)
+}
+
+fn apply_delta(bitmap: &mut RoaringBitmap, bytes: &[u8]) -> StorageResult<()> {
+    let corrupt = || StorageError::Corruption("Invalid delta segment in posting store".to_string());
+    let len = u32::from_be_bytes(bytes.get(..4).ok_or_else(corrupt)?.try_into().unwrap()) as usize;
+    let added = bytes.get(4..4 + len).ok_or_else(corrupt)?;
+    let removed = &bytes[4 + len..];
+
+    *bitmap |= decode_bitmap(added)?;
+    *bitmap -= decode_bitmap(removed)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn open_db(path: &std::path::Path) -> Arc<DB> {
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let cfs = DB::list_cf(&opts, path).unwrap_or_default();
+        Arc::new(DB::open_cf(&opts, path, cfs).unwrap())
+    }
+
+    fn terms(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_boolean_operations() {
+        let dir = tempdir().unwrap();
+        let store = PostingStore::open(open_db(dir.path()), "body").unwrap();
+
+        let a = ObjectId::new();
+        let b = ObjectId::new();
+        let c = ObjectId::new();
+        store.add_document(&a, &terms(&["miku", "db"])).unwrap();
+        store.add_document(&b, &terms(&["miku", "rust"])).unwrap();
+        store.add_document(&c, &terms(&["db"])).unwrap();
+
+        let and = store.intersect(&terms(&["miku", "db"])).unwrap();
+        assert_eq!(store.resolve(&and).unwrap(), vec![a]);
+
+        let or = store.union(&terms(&["rust", "db"])).unwrap();
+        assert_eq!(store.resolve(&or).unwrap(), vec![a, b, c]);
+
+        assert!(store.remove_document(&a, &terms(&["miku", "db"])).unwrap());
+        assert!(store.intersect(&terms(&["miku", "db"])).unwrap().is_empty());
+        assert_eq!(store.resolve(&store.postings("db").unwrap()).unwrap(), vec![c]);
+    }
+
+    #[test]
+    fn test_delta_compaction_and_reopen() {
+        let dir = tempdir().unwrap();
+        let mut ids = Vec::new();
+        {
+            let store = PostingStore::open(open_db(dir.path()), "body").unwrap();
+            for _ in 0..(MAX_DELTA_SEGMENTS + 3) {
+                let id = ObjectId::new();
+                store.add_document(&id, &terms(&["hot"])).unwrap();
+                ids.push(id);
+            }
+            store.remove_document(&ids[1], &terms(&["hot"])).unwrap();
+
+            let stats = store.stats().unwrap();
+            assert_eq!(stats.terms, 1);
+            assert_eq!(stats.delta_segments, 4);
+        }
+
+        let store = PostingStore::open(open_db(dir.path()), "body").unwrap();
+        let id = ObjectId::new();
+        assert_eq!(store.add_document(&id, &terms(&["hot"])).unwrap(), ids.len() as u32);
+        ids.push(id);
+        ids.remove(1);
+
+        assert_eq!(store.compact_all().unwrap(), 1);
+        assert_eq!(store.stats().unwrap().delta_segments, 0);
+        assert_eq!(store.resolve(&store.postings("hot").unwrap()).unwrap(), ids);
+    }
+}
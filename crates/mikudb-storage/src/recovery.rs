@@ -1,37 +1,30 @@
 //! 崩溃恢复模块
 //!
 //! 实现基于 WAL 的崩溃恢复机制:
-//! - **事务状态恢复**: 识别未完成的事务并回滚
-//! - **数据一致性**: 重放已提交事务的操作
-//! - **幂等性保证**: 确保重放操作可以安全执行多次
+//! - **事务状态恢复**: 识别未完成的事务并丢弃
+//! - **数据一致性**: 按 WAL 顺序重放已提交事务的操作
+//! - **幂等性保证**: 跳过元数据中记录的已应用 LSN 之前的提交,合并操作不会重复应用
 //!
 //! # 恢复流程
 //!
-//! 1. 扫描 WAL 文件,收集所有事务的状态
-//! 2. 识别已提交(Committed)、已中止(Aborted)和未完成(Pending)的事务
-//! 3. 重放已提交事务的所有操作(Insert/Update/Delete)
+//! 1. 读取元数据列族中已应用到数据库的 WAL LSN
+//! 2. 按 WAL 顺序扫描记录,按事务缓存数据操作
+//! 3. 遇到 LSN 不小于已应用 LSN 的提交记录时,把该事务的操作和新的已应用 LSN 写入同一批次
 //! 4. 忽略已中止和未完成的事务
-//! 5. 清空 WAL 文件或创建 checkpoint
-
-use crate::wal::{RecordType, WalRecord, WriteAheadLog};
-use crate::{StorageError, StorageResult};
-use mikudb_boml::codec;
-use rocksdb::{BoundColumnFamily, WriteBatch, WriteOptions, DB};
-use std::collections::{HashMap, HashSet};
+//! 5. 同步 RocksDB 日志后清空 WAL 文件
+//! 6. WAL 的 LSN 小于已应用 LSN 时(例如从快照恢复),把 WAL 推进到已应用 LSN
+//!
+//! 增量备份中的 WAL 段使用相同的重放逻辑(见 [`replay_segment`])。
+
+use crate::engine::METADATA_CF;
+use crate::wal::{RecordType, WalRecord, WriteAheadLog, WAL_APPLIED_KEY};
+use crate::StorageResult;
+use rocksdb::{WriteBatch, WriteOptions, DB};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 
-/// 事务状态
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum TransactionState {
-    /// 事务进行中(已开始,未提交/中止)
-    Pending,
-    /// 事务已提交
-    Committed,
-    /// 事务已中止
-    Aborted,
-}
-
 /// 恢复管理器
 ///
 /// 负责从 WAL 中恢复数据库状态
@@ -60,179 +53,197 @@ impl RecoveryManager {
     pub fn recover(&self) -> StorageResult<RecoveryStats> {
         info!("Starting crash recovery from WAL...");
 
-        // 第一遍扫描: 收集所有事务的状态
-        let tx_states = self.scan_transaction_states()?;
-
-        debug!(
-            "Found {} committed, {} aborted, {} pending transactions",
-            tx_states.iter().filter(|(_, s)| **s == TransactionState::Committed).count(),
-            tx_states.iter().filter(|(_, s)| **s == TransactionState::Aborted).count(),
-            tx_states.iter().filter(|(_, s)| **s == TransactionState::Pending).count()
-        );
-
-        // 第二遍扫描: 重放已提交事务的操作
-        let stats = self.replay_committed_transactions(&tx_states)?;
+        let applied_lsn = applied_lsn(&self.db)?;
+        let stats = replay_committed_transactions(&self.db, applied_lsn, |callback| {
+            self.wal.replay_with_lsn(callback)
+        })?;
 
         // 恢复完成后,截断 WAL
-        if stats.total_replayed > 0 {
+        if self.wal.has_records() {
             info!(
-                "Recovery completed: {} operations replayed, truncating WAL",
-                stats.total_replayed
+                "Recovery completed: {} operations replayed, {} transaction(s) already applied, truncating WAL",
+                stats.total_replayed, stats.transactions_skipped
             );
+            // 之前应用时没有同步 RocksDB 日志,截断前先使其持久化
+            self.db.flush_wal(true)?;
             self.wal.truncate()?;
         } else {
             info!("No operations to replay, WAL is clean");
         }
 
+        // 数据库从快照恢复或 WAL 被删除时,新的记录不能落在已应用的 LSN 之前
+        if self.wal.current_lsn() < applied_lsn {
+            info!("Advancing WAL to applied LSN {}", applied_lsn);
+            self.wal.advance_to(applied_lsn)?;
+        }
+
         Ok(stats)
     }
+}
 
-    /// 扫描 WAL 并收集所有事务的状态
-    ///
-    /// # Returns
-    /// 事务 ID 到状态的映射
-    fn scan_transaction_states(&self) -> StorageResult<HashMap<u64, TransactionState>> {
-        let mut tx_states = HashMap::new();
-
-        self.wal.replay(|record| {
-            match record.record_type {
-                RecordType::BeginTx => {
-                    tx_states.insert(record.tx_id, TransactionState::Pending);
-                }
-                RecordType::CommitTx => {
-                    tx_states.insert(record.tx_id, TransactionState::Committed);
-                }
-                RecordType::AbortTx => {
-                    tx_states.insert(record.tx_id, TransactionState::Aborted);
-                }
-                _ => {
-                    // Insert/Update/Delete 操作,确保事务存在
-                    tx_states.entry(record.tx_id).or_insert(TransactionState::Pending);
-                }
-            }
-            Ok(())
-        })?;
-
-        Ok(tx_states)
-    }
+/// # Brief
+/// 重放一个 WAL 段中已提交且尚未应用的事务
+///
+/// 用于应用增量备份: 段与数据库的 WAL 使用同一 LSN 序列,已应用 LSN 之前的提交被跳过。
+///
+/// # Arguments
+/// * `db` - RocksDB 实例,记录涉及的集合必须已经存在
+/// * `path` - 段文件路径
+///
+/// # Returns
+/// 恢复统计信息
+pub(crate) fn replay_segment(db: &DB, path: &Path) -> StorageResult<RecoveryStats> {
+    let applied = applied_lsn(db)?;
+    replay_committed_transactions(db, applied, |callback| WriteAheadLog::read_segment(path, callback))
+}
 
-    /// 重放已提交事务的操作
-    ///
-    /// # Arguments
-    /// * `tx_states` - 事务状态映射
-    ///
-    /// # Returns
-    /// 恢复统计信息
-    fn replay_committed_transactions(
-        &self,
-        tx_states: &HashMap<u64, TransactionState>,
-    ) -> StorageResult<RecoveryStats> {
-        let mut stats = RecoveryStats::default();
-
-        // 收集已提交的事务 ID
-        let committed_txs: HashSet<u64> = tx_states
-            .iter()
-            .filter(|(_, state)| **state == TransactionState::Committed)
-            .map(|(tx_id, _)| *tx_id)
-            .collect();
-
-        if committed_txs.is_empty() {
-            return Ok(stats);
-        }
+/// 读取已应用到数据库的 WAL LSN 上界
+///
+/// # Returns
+/// 没有元数据列族或尚未记录时返回 0
+pub(crate) fn applied_lsn(db: &DB) -> StorageResult<u64> {
+    let Some(cf) = db.cf_handle(METADATA_CF) else {
+        return Ok(0);
+    };
+    Ok(db
+        .get_cf(&cf, WAL_APPLIED_KEY)?
+        .and_then(|v| v.try_into().ok())
+        .map(u64::from_le_bytes)
+        .unwrap_or(0))
+}
 
-        // 按事务分组操作
-        let mut tx_operations: HashMap<u64, Vec<WalRecord>> = HashMap::new();
-
-        self.wal.replay(|record| {
-            // 只处理已提交事务的数据操作
-            if committed_txs.contains(&record.tx_id) {
-                match record.record_type {
-                    RecordType::Insert | RecordType::Update | RecordType::Delete => {
-                        tx_operations
-                            .entry(record.tx_id)
-                            .or_insert_with(Vec::new)
-                            .push(record);
-                    }
-                    _ => {}
-                }
+/// 按 WAL 顺序重放已提交事务的操作
+///
+/// # Arguments
+/// * `db` - RocksDB 实例
+/// * `applied_lsn` - 已应用到数据库的 LSN 上界,之前的提交被跳过
+/// * `replay` - 以 (LSN, 记录) 回调依次读取记录
+///
+/// # Returns
+/// 恢复统计信息
+fn replay_committed_transactions<R>(db: &DB, applied_lsn: u64, replay: R) -> StorageResult<RecoveryStats>
+where
+    R: FnOnce(&mut dyn FnMut(u64, WalRecord) -> StorageResult<()>) -> StorageResult<u64>,
+{
+    let mut stats = RecoveryStats::default();
+    // 未结束事务的数据操作
+    let mut pending: HashMap<u64, Vec<WalRecord>> = HashMap::new();
+
+    replay(&mut |lsn, record| {
+        match record.record_type {
+            RecordType::BeginTx => {
+                pending.entry(record.tx_id).or_default();
             }
-            Ok(())
-        })?;
-
-        // 重放每个已提交事务的操作
-        for (tx_id, operations) in tx_operations {
-            match self.replay_transaction_operations(tx_id, &operations) {
-                Ok(tx_stats) => {
-                    stats.transactions_recovered += 1;
-                    stats.inserts_replayed += tx_stats.inserts;
-                    stats.updates_replayed += tx_stats.updates;
-                    stats.deletes_replayed += tx_stats.deletes;
-                    stats.total_replayed += tx_stats.total();
+            RecordType::CommitTx => {
+                let operations = pending.remove(&record.tx_id).unwrap_or_default();
+                if lsn < applied_lsn {
+                    stats.transactions_skipped += 1;
+                    return Ok(());
                 }
-                Err(e) => {
-                    error!("Failed to replay transaction {}: {}", tx_id, e);
-                    stats.errors_encountered += 1;
+                match replay_transaction_operations(db, record.tx_id, lsn + 1, &operations) {
+                    Ok(tx_stats) => {
+                        stats.transactions_recovered += 1;
+                        stats.inserts_replayed += tx_stats.inserts;
+                        stats.updates_replayed += tx_stats.updates;
+                        stats.deletes_replayed += tx_stats.deletes;
+                        stats.merges_replayed += tx_stats.merges;
+                        stats.total_replayed += tx_stats.total();
+                    }
+                    Err(e) => {
+                        error!("Failed to replay transaction {}: {}", record.tx_id, e);
+                        stats.errors_encountered += 1;
+                    }
                 }
             }
+            RecordType::AbortTx => {
+                pending.remove(&record.tx_id);
+            }
+            RecordType::Checkpoint => {}
+            _ => pending.entry(record.tx_id).or_default().push(record),
         }
+        Ok(())
+    })?;
 
-        Ok(stats)
+    if !pending.is_empty() {
+        debug!("Discarded {} unfinished transaction(s)", pending.len());
     }
 
-    /// 重放单个事务的所有操作
-    ///
-    /// # Arguments
-    /// * `tx_id` - 事务 ID
-    /// * `operations` - 事务的操作列表
-    ///
-    /// # Returns
-    /// 该事务的统计信息
-    fn replay_transaction_operations(
-        &self,
-        tx_id: u64,
-        operations: &[WalRecord],
-    ) -> StorageResult<TransactionRecoveryStats> {
-        let mut batch = WriteBatch::default();
-        let mut stats = TransactionRecoveryStats::default();
-
-        for record in operations {
-            // 获取集合的 ColumnFamily
-            let cf = self.db.cf_handle(&record.collection).ok_or_else(|| {
-                StorageError::CollectionNotFound(record.collection.clone())
-            })?;
-
-            match record.record_type {
-                RecordType::Insert | RecordType::Update => {
-                    // Insert 和 Update 都使用 put 操作(幂等)
-                    batch.put_cf(&cf, &record.key, &record.value);
-
-                    if record.record_type == RecordType::Insert {
-                        stats.inserts += 1;
-                    } else {
-                        stats.updates += 1;
-                    }
-                }
-                RecordType::Delete => {
-                    batch.delete_cf(&cf, &record.key);
-                    stats.deletes += 1;
+    Ok(stats)
+}
+
+/// 重放单个事务的所有操作
+///
+/// # Arguments
+/// * `db` - RocksDB 实例
+/// * `tx_id` - 事务 ID
+/// * `end_lsn` - 提交记录之后的 LSN,与操作一起记录为已应用
+/// * `operations` - 事务的操作列表
+///
+/// # Returns
+/// 该事务的统计信息
+fn replay_transaction_operations(
+    db: &DB,
+    tx_id: u64,
+    end_lsn: u64,
+    operations: &[WalRecord],
+) -> StorageResult<TransactionRecoveryStats> {
+    let mut batch = WriteBatch::default();
+    let mut stats = TransactionRecoveryStats::default();
+
+    for record in operations {
+        // 获取集合的 ColumnFamily,集合在写入之后被删除时跳过
+        let Some(cf) = db.cf_handle(&record.collection) else {
+            warn!(
+                "Skipping WAL record of transaction {} for dropped collection {}",
+                tx_id, record.collection
+            );
+            continue;
+        };
+
+        match record.record_type {
+            RecordType::Insert | RecordType::Update => {
+                // Insert 和 Update 都使用 put 操作(幂等)
+                batch.put_cf(&cf, &record.key, &record.value);
+
+                if record.record_type == RecordType::Insert {
+                    stats.inserts += 1;
+                } else {
+                    stats.updates += 1;
                 }
-                _ => unreachable!("Only data operations should be replayed"),
             }
+            RecordType::Delete => {
+                batch.delete_cf(&cf, &record.key);
+                stats.deletes += 1;
+            }
+            RecordType::DeleteRange => {
+                batch.delete_range_cf(&cf, &record.key, &record.value);
+                stats.deletes += 1;
+            }
+            RecordType::Merge => {
+                batch.merge_cf(&cf, &record.key, &record.value);
+                stats.merges += 1;
+            }
+            _ => unreachable!("Only data operations should be replayed"),
         }
+    }
 
-        // 批量写入
-        let mut write_opts = WriteOptions::default();
-        write_opts.set_sync(true); // 确保恢复操作持久化
+    // 与操作原子地记录已应用的 LSN,再次恢复时不会重复应用
+    if let Some(cf) = db.cf_handle(METADATA_CF) {
+        batch.put_cf(&cf, WAL_APPLIED_KEY, end_lsn.to_le_bytes());
+    }
 
-        self.db.write_opt(batch, &write_opts)?;
+    // 批量写入
+    let mut write_opts = WriteOptions::default();
+    write_opts.set_sync(true); // 确保恢复操作持久化
 
-        debug!(
-            "Replayed transaction {}: {} inserts, {} updates, {} deletes",
-            tx_id, stats.inserts, stats.updates, stats.deletes
-        );
+    db.write_opt(batch, &write_opts)?;
 
-        Ok(stats)
-    }
+    debug!(
+        "Replayed transaction {}: {} inserts, {} updates, {} deletes, {} merges",
+        tx_id, stats.inserts, stats.updates, stats.deletes, stats.merges
+    );
+
+    Ok(stats)
 }
 
 /// 恢复统计信息
@@ -240,12 +251,16 @@ impl RecoveryManager {
 pub struct RecoveryStats {
     /// 恢复的事务数量
     pub transactions_recovered: u64,
+    /// 已应用到数据库而跳过的事务数量
+    pub transactions_skipped: u64,
     /// 重放的插入操作数
     pub inserts_replayed: u64,
     /// 重放的更新操作数
     pub updates_replayed: u64,
-    /// 重放的删除操作数
+    /// 重放的删除操作数(含范围删除)
     pub deletes_replayed: u64,
+    /// 重放的合并操作数
+    pub merges_replayed: u64,
     /// 遇到的错误数
     pub errors_encountered: u64,
     /// 总重放操作数
@@ -258,18 +273,19 @@ struct TransactionRecoveryStats {
     inserts: u64,
     updates: u64,
     deletes: u64,
+    merges: u64,
 }
 
 impl TransactionRecoveryStats {
     fn total(&self) -> u64 {
-        self.inserts + self.updates + self.deletes
+        self.inserts + self.updates + self.deletes + self.merges
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::wal::WalRecord;
+    use crate::wal::{WalRecord, WalSyncPolicy};
     use tempfile::tempdir;
 
     #[test]
@@ -290,7 +306,7 @@ mod tests {
         );
 
         // 创建 WAL 并写入事务记录
-        let wal = Arc::new(WriteAheadLog::open(&wal_path, true).unwrap());
+        let wal = Arc::new(WriteAheadLog::open(&wal_path, WalSyncPolicy::Always).unwrap());
 
         // 事务 1: 已提交
         wal.append(&WalRecord::new_begin_tx(1)).unwrap();
@@ -344,7 +360,7 @@ mod tests {
             rocksdb::DB::open_cf_descriptors(&opts, &db_path, vec![cf_descriptor]).unwrap(),
         );
 
-        let wal = Arc::new(WriteAheadLog::open(&wal_path, true).unwrap());
+        let wal = Arc::new(WriteAheadLog::open(&wal_path, WalSyncPolicy::Always).unwrap());
 
         // 事务: 已中止(不应该重放)
         wal.append(&WalRecord::new_begin_tx(1)).unwrap();
@@ -368,4 +384,44 @@ mod tests {
         let cf = db.cf_handle("test_collection").unwrap();
         assert!(db.get_cf(&cf, b"key1").unwrap().is_none());
     }
+
+    #[test]
+    fn test_recovery_skips_applied_transactions() {
+        let dir = tempdir().unwrap();
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut cf_opts = rocksdb::Options::default();
+        cf_opts.set_merge_operator_associative("append", |_, existing, operands| {
+            let mut value = existing.map(|v| v.to_vec()).unwrap_or_default();
+            operands.iter().for_each(|op| value.extend_from_slice(op));
+            Some(value)
+        });
+        let cfs = vec![
+            rocksdb::ColumnFamilyDescriptor::new(METADATA_CF, rocksdb::Options::default()),
+            rocksdb::ColumnFamilyDescriptor::new("test_collection", cf_opts),
+        ];
+        let db = Arc::new(rocksdb::DB::open_cf_descriptors(&opts, dir.path().join("db"), cfs).unwrap());
+        let wal = Arc::new(WriteAheadLog::open(dir.path().join("test.wal"), WalSyncPolicy::Always).unwrap());
+
+        // LSN 0-1: 已应用的事务;LSN 2-3: 未应用的事务
+        for (tx_id, operand) in [(0, b"a"), (2, b"b")] {
+            wal.append(&WalRecord::new_merge(tx_id, "test_collection", b"key".to_vec(), operand.to_vec()))
+                .unwrap();
+            wal.append(&WalRecord::new_commit_tx(tx_id)).unwrap();
+        }
+        let cf = db.cf_handle("test_collection").unwrap();
+        db.merge_cf(&cf, b"key", b"a").unwrap();
+        db.put_cf(&db.cf_handle(METADATA_CF).unwrap(), WAL_APPLIED_KEY, 2u64.to_le_bytes()).unwrap();
+
+        let stats = RecoveryManager::new(db.clone(), wal.clone()).recover().unwrap();
+        assert_eq!((stats.transactions_skipped, stats.transactions_recovered, stats.merges_replayed), (1, 1, 1));
+        assert_eq!(db.get_cf(&cf, b"key").unwrap(), Some(b"ab".to_vec()));
+        assert!(!wal.has_records());
+
+        // 再次恢复不会重复应用
+        let stats = RecoveryManager::new(db.clone(), wal).recover().unwrap();
+        assert_eq!(stats.total_replayed, 0);
+        assert_eq!(db.get_cf(&cf, b"key").unwrap(), Some(b"ab".to_vec()));
+    }
 }
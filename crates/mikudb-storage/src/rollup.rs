@@ -0,0 +1,407 @@
+//! 预聚合模块
+//!
+//! 仪表盘反复对原始事件做相同的分组聚合,预聚合集合(rollup)把结果保存在一个很小的集合中,
+//! 由写入路径增量维护,查询时直接读取:
+//! - 定义持久化在元数据中 (`rollup:{name}`),预聚合集合与定义同名,是普通集合,可以直接 FIND
+//! - 分组键可以是字段路径,也可以是按分钟、小时、天、周、月、年截断的时间字段
+//! - 每个分组一个文档,`_id` 由分组键的哈希得到,`_count` 记录分组中的源文档数,减到 0 时删除文档
+//! - COUNT、SUM、AVG 插入时累加、删除时扣减,AVG 的中间状态保存在 `_avg` 字段中
+//! - MIN、MAX 插入时比较即可;删除的值恰好是当前极值时重新扫描源集合计算该分组
+//! - 聚合语义与 GROUP BY 相同: SUM 把非数值视为 0,AVG 只统计有该字段的文档
+
+use crate::{StorageError, StorageResult};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Timelike, Utc};
+use mikudb_boml::{codec, keyenc, BomlValue, Document};
+use mikudb_common::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// 预聚合定义在元数据 CF 中的键前缀
+pub(crate) const ROLLUP_KEY_PREFIX: &str = "rollup:";
+
+/// 分组中源文档数的字段名
+pub const ROLLUP_COUNT_FIELD: &str = "_count";
+
+/// AVG 中间状态(和与计数)的字段名
+const ROLLUP_AVG_FIELD: &str = "_avg";
+
+/// 时间截断粒度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeBucket {
+    Minute,
+    Hour,
+    Day,
+    /// 按 ISO 周,从周一开始
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeBucket {
+    /// # Brief
+    /// 按名称解析截断粒度(不区分大小写)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "minute" => Some(TimeBucket::Minute),
+            "hour" => Some(TimeBucket::Hour),
+            "day" => Some(TimeBucket::Day),
+            "week" => Some(TimeBucket::Week),
+            "month" => Some(TimeBucket::Month),
+            "year" => Some(TimeBucket::Year),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 获取截断粒度的名称(小写)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeBucket::Minute => "minute",
+            TimeBucket::Hour => "hour",
+            TimeBucket::Day => "day",
+            TimeBucket::Week => "week",
+            TimeBucket::Month => "month",
+            TimeBucket::Year => "year",
+        }
+    }
+
+    /// # Brief
+    /// 把时间截断到所在区间的起点(UTC)
+    pub fn truncate(&self, dt: DateTime<Utc>) -> DateTime<Utc> {
+        let date = dt.date_naive();
+        let start_of = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_utc();
+        match self {
+            TimeBucket::Minute => dt - Duration::nanoseconds(dt.second() as i64 * 1_000_000_000 + dt.nanosecond() as i64),
+            TimeBucket::Hour => start_of(date) + Duration::hours(dt.hour() as i64),
+            TimeBucket::Day => start_of(date),
+            TimeBucket::Week => start_of(date - Duration::days(date.weekday().num_days_from_monday() as i64)),
+            TimeBucket::Month => start_of(date.with_day(1).unwrap_or(date)),
+            TimeBucket::Year => start_of(NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date)),
+        }
+    }
+}
+
+/// 分组键
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupKey {
+    /// 预聚合文档中的字段名,支持点号分隔的嵌套路径
+    pub field: String,
+    /// 源文档中的字段路径
+    pub path: String,
+    /// 时间截断粒度,None 表示直接使用字段值
+    pub bucket: Option<TimeBucket>,
+}
+
+/// 预聚合函数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RollupFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl RollupFunction {
+    /// # Brief
+    /// 获取聚合函数的名称(大写)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RollupFunction::Count => "COUNT",
+            RollupFunction::Sum => "SUM",
+            RollupFunction::Avg => "AVG",
+            RollupFunction::Min => "MIN",
+            RollupFunction::Max => "MAX",
+        }
+    }
+}
+
+/// 预聚合的输出字段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupAggregate {
+    /// 输出字段名
+    pub name: String,
+    /// 聚合函数
+    pub function: RollupFunction,
+    /// 源字段路径,COUNT 可以省略
+    pub path: Option<String>,
+}
+
+/// 预聚合定义
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollupDefinition {
+    /// 预聚合名称,也是保存结果的集合名
+    pub name: String,
+    /// 源集合
+    pub source: String,
+    /// 分组键,为空时整个集合是一个分组
+    pub group_by: Vec<RollupKey>,
+    /// 输出字段
+    pub aggregates: Vec<RollupAggregate>,
+}
+
+/// 把一个源文档计入或移出分组的结果
+pub(crate) enum GroupChange {
+    /// 分组文档已更新
+    Updated(Document),
+    /// 分组已没有源文档,需要删除
+    Removed,
+    /// 删除的值是当前的 MIN/MAX,需要重新扫描源集合
+    Recompute,
+}
+
+impl RollupDefinition {
+    pub(crate) fn metadata_key(name: &str) -> String {
+        format!("{}{}", ROLLUP_KEY_PREFIX, name)
+    }
+
+    /// # Brief
+    /// 检查定义: 至少一个输出字段,输出字段不重名、不与内部字段冲突,SUM/AVG/MIN/MAX 需要源字段
+    pub fn validate(&self) -> StorageResult<()> {
+        if self.name == self.source {
+            return Err(StorageError::InvalidArgument(format!(
+                "Rollup {} cannot be stored in its source collection",
+                self.name
+            )));
+        }
+        if self.aggregates.is_empty() {
+            return Err(StorageError::InvalidArgument(format!("Rollup {} has no aggregates", self.name)));
+        }
+        let mut seen = Vec::new();
+        let fields = self.group_by.iter().map(|key| &key.field).chain(self.aggregates.iter().map(|agg| &agg.name));
+        for field in fields {
+            let root = field.split('.').next().unwrap_or(field);
+            if root == "_id" || root == ROLLUP_COUNT_FIELD || root == ROLLUP_AVG_FIELD {
+                return Err(StorageError::InvalidArgument(format!("Rollup field {} is reserved", field)));
+            }
+            if seen.contains(&field) {
+                return Err(StorageError::InvalidArgument(format!("Duplicate rollup field {}", field)));
+            }
+            seen.push(field);
+        }
+        for agg in &self.aggregates {
+            if agg.function != RollupFunction::Count && agg.path.is_none() {
+                return Err(StorageError::InvalidArgument(format!(
+                    "Rollup aggregate {} requires a field",
+                    agg.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// # Brief
+    /// 计算源文档的分组键
+    ///
+    /// # Returns
+    /// 以分组键字段组成的文档(不含 `_id`),缺失的字段和无法截断的时间为 Null
+    pub fn group_key(&self, doc: &Document) -> Document {
+        let mut key = Document::without_id();
+        for k in &self.group_by {
+            let value = doc.get_path(&k.path).cloned().unwrap_or(BomlValue::Null);
+            let value = match k.bucket {
+                Some(bucket) => to_datetime(&value).map_or(BomlValue::Null, |dt| BomlValue::DateTime(bucket.truncate(dt))),
+                None => value,
+            };
+            let _ = key.set_path(&k.field, value);
+        }
+        key
+    }
+
+    /// # Brief
+    /// 由分组键得到分组文档的 `_id`
+    pub fn group_id(key: &Document) -> StorageResult<ObjectId> {
+        let encoded = codec::encode_document(&key.to_boml_value())?;
+        let hash = xxhash_rust::xxh3::xxh3_128(&encoded).to_be_bytes();
+        let mut bytes = [0u8; 12];
+        bytes.copy_from_slice(&hash[..12]);
+        Ok(ObjectId::from_bytes(bytes))
+    }
+
+    /// # Brief
+    /// 把源文档计入(`removing` 为 false)或移出分组
+    ///
+    /// # Arguments
+    /// * `group` - 当前的分组文档,分组不存在时为 None
+    /// * `key` - 源文档的分组键
+    /// * `doc` - 源文档
+    /// * `removing` - 是否移出
+    pub(crate) fn apply(&self, group: Option<Document>, key: &Document, doc: &Document, removing: bool) -> StorageResult<GroupChange> {
+        let sign = if removing { -1 } else { 1 };
+        let mut group = match group {
+            Some(group) => group,
+            None if removing => return Ok(GroupChange::Recompute),
+            None => {
+                let mut group = key.clone();
+                group.set_id(Self::group_id(key)?);
+                group
+            }
+        };
+
+        let count = group.get(ROLLUP_COUNT_FIELD).and_then(BomlValue::as_i64).unwrap_or(0) + sign;
+        if count <= 0 {
+            return Ok(GroupChange::Removed);
+        }
+        group.insert(ROLLUP_COUNT_FIELD, count);
+
+        for agg in &self.aggregates {
+            let value = agg.path.as_deref().and_then(|path| doc.get_path(path));
+            match agg.function {
+                RollupFunction::Count => {
+                    let current = group.get_path(&agg.name).and_then(BomlValue::as_i64).unwrap_or(0);
+                    let _ = group.set_path(&agg.name, current + sign);
+                }
+                RollupFunction::Sum => {
+                    let current = group.get_path(&agg.name).and_then(BomlValue::as_f64).unwrap_or(0.0);
+                    let delta = value.and_then(BomlValue::as_f64).unwrap_or(0.0);
+                    let _ = group.set_path(&agg.name, current + delta * sign as f64);
+                }
+                RollupFunction::Avg => {
+                    let Some(value) = value else {
+                        continue;
+                    };
+                    let state = format!("{}.{}", ROLLUP_AVG_FIELD, agg.name);
+                    let sum = group.get_path(&format!("{}.sum", state)).and_then(BomlValue::as_f64).unwrap_or(0.0)
+                        + value.as_f64().unwrap_or(0.0) * sign as f64;
+                    let n = group.get_path(&format!("{}.count", state)).and_then(BomlValue::as_i64).unwrap_or(0) + sign;
+                    let _ = group.set_path(&format!("{}.sum", state), sum);
+                    let _ = group.set_path(&format!("{}.count", state), n);
+                    let _ = group.set_path(&agg.name, if n > 0 { sum / n as f64 } else { 0.0 });
+                }
+                RollupFunction::Min | RollupFunction::Max => {
+                    let Some(value) = value.filter(|v| !matches!(v, BomlValue::Null)) else {
+                        continue;
+                    };
+                    let wanted = if agg.function == RollupFunction::Min { Ordering::Less } else { Ordering::Greater };
+                    let current = group.get_path(&agg.name).filter(|v| !matches!(v, BomlValue::Null));
+                    if removing {
+                        if current.map_or(true, |current| compare(value, current) != Some(wanted.reverse())) {
+                            return Ok(GroupChange::Recompute);
+                        }
+                    } else if current.map_or(true, |current| compare(value, current) == Some(wanted)) {
+                        let _ = group.set_path(&agg.name, value.clone());
+                    }
+                }
+            }
+        }
+        Ok(GroupChange::Updated(group))
+    }
+
+    /// # Brief
+    /// 从源文档从头计算所有分组
+    ///
+    /// # Returns
+    /// 分组 `_id` 到分组文档的映射
+    pub(crate) fn summarize<'a, I>(&self, docs: I) -> StorageResult<HashMap<ObjectId, Document>>
+    where
+        I: IntoIterator<Item = &'a Document>,
+    {
+        let mut groups: HashMap<ObjectId, Document> = HashMap::new();
+        for doc in docs {
+            let key = self.group_key(doc);
+            let id = Self::group_id(&key)?;
+            if let GroupChange::Updated(group) = self.apply(groups.remove(&id), &key, doc, false)? {
+                groups.insert(id, group);
+            }
+        }
+        Ok(groups)
+    }
+}
+
+/// # Brief
+/// 读取时间值: DateTime、Timestamp(毫秒)或 RFC 3339 字符串
+fn to_datetime(value: &BomlValue) -> Option<DateTime<Utc>> {
+    match value {
+        BomlValue::DateTime(dt) => Some(*dt),
+        BomlValue::Timestamp(millis) => DateTime::from_timestamp_millis(*millis),
+        BomlValue::String(s) => DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+/// # Brief
+/// 比较 MIN/MAX 的候选值,数值之间、字符串之间、时间之间可以比较;数值按索引键的顺序比较
+fn compare(a: &BomlValue, b: &BomlValue) -> Option<Ordering> {
+    match (a, b) {
+        (BomlValue::String(a), BomlValue::String(b)) => Some(a.cmp(b)),
+        (BomlValue::DateTime(a), BomlValue::DateTime(b)) => Some(a.cmp(b)),
+        (BomlValue::Timestamp(a), BomlValue::Timestamp(b)) => Some(a.cmp(b)),
+        _ => Some(keyenc::compare_f64(a.as_f64()?, b.as_f64()?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn sales_rollup() -> RollupDefinition {
+        RollupDefinition {
+            name: "daily_sales".to_string(),
+            source: "sales".to_string(),
+            group_by: vec![
+                RollupKey { field: "ts".to_string(), path: "ts".to_string(), bucket: Some(TimeBucket::Day) },
+                RollupKey { field: "product".to_string(), path: "product".to_string(), bucket: None },
+            ],
+            aggregates: vec![
+                RollupAggregate { name: "total".to_string(), function: RollupFunction::Sum, path: Some("amount".to_string()) },
+                RollupAggregate { name: "avg".to_string(), function: RollupFunction::Avg, path: Some("amount".to_string()) },
+                RollupAggregate { name: "max".to_string(), function: RollupFunction::Max, path: Some("amount".to_string()) },
+            ],
+        }
+    }
+
+    fn sale(hour: u32, product: &str, amount: i64) -> Document {
+        let mut doc = Document::new();
+        doc.insert("ts", BomlValue::DateTime(Utc.with_ymd_and_hms(2024, 6, 1, hour, 30, 0).unwrap()));
+        doc.insert("product", product);
+        doc.insert("amount", amount);
+        doc
+    }
+
+    #[test]
+    fn test_time_bucket_truncate() {
+        let dt = Utc.with_ymd_and_hms(2024, 6, 5, 13, 45, 12).unwrap();
+        assert_eq!(TimeBucket::Minute.truncate(dt), Utc.with_ymd_and_hms(2024, 6, 5, 13, 45, 0).unwrap());
+        assert_eq!(TimeBucket::Hour.truncate(dt), Utc.with_ymd_and_hms(2024, 6, 5, 13, 0, 0).unwrap());
+        assert_eq!(TimeBucket::Day.truncate(dt), Utc.with_ymd_and_hms(2024, 6, 5, 0, 0, 0).unwrap());
+        assert_eq!(TimeBucket::Week.truncate(dt), Utc.with_ymd_and_hms(2024, 6, 3, 0, 0, 0).unwrap());
+        assert_eq!(TimeBucket::Month.truncate(dt), Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap());
+        assert_eq!(TimeBucket::Year.truncate(dt), Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(TimeBucket::parse("DAY"), Some(TimeBucket::Day));
+        assert_eq!(TimeBucket::parse("fortnight"), None);
+    }
+
+    #[test]
+    fn test_apply_and_remove() {
+        let rollup = sales_rollup();
+        rollup.validate().unwrap();
+        let (a, b) = (sale(9, "miku", 10), sale(20, "miku", 30));
+        let key = rollup.group_key(&a);
+        assert_eq!(key, rollup.group_key(&b));
+
+        let groups = rollup.summarize([&a, &b]).unwrap();
+        assert_eq!(groups.len(), 1);
+        let group = groups.into_values().next().unwrap();
+        assert_eq!(group.get(ROLLUP_COUNT_FIELD), Some(&BomlValue::Int64(2)));
+        assert_eq!(group.get("total"), Some(&BomlValue::Float64(40.0)));
+        assert_eq!(group.get("avg"), Some(&BomlValue::Float64(20.0)));
+        assert_eq!(group.get("max"), Some(&BomlValue::Int64(30)));
+
+        // 删除非极值只扣减,删除极值需要重新计算,删除最后一个文档删除分组
+        let GroupChange::Updated(without_a) = rollup.apply(Some(group.clone()), &key, &a, true).unwrap() else {
+            panic!("expected an updated group");
+        };
+        assert_eq!(without_a.get("total"), Some(&BomlValue::Float64(30.0)));
+        assert_eq!(without_a.get("avg"), Some(&BomlValue::Float64(30.0)));
+        assert!(matches!(rollup.apply(Some(group), &key, &b, true).unwrap(), GroupChange::Recompute));
+        assert!(matches!(rollup.apply(Some(without_a), &key, &b, true).unwrap(), GroupChange::Removed));
+
+        let mut invalid = sales_rollup();
+        invalid.aggregates.push(RollupAggregate { name: "_count".to_string(), function: RollupFunction::Count, path: None });
+        assert!(invalid.validate().is_err());
+    }
+}
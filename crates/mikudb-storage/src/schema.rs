@@ -0,0 +1,334 @@
+//! 软模式模块
+//!
+//! 为集合维护可选的字段类型登记表(soft schema):
+//! - 根据写入的文档统计每个顶层字段各类型出现的次数,出现最多的类型为主类型
+//! - 新文档改变字段的主类型(例如 age 从 int 变为 string)时记录警告并计数
+//! - 开启 `strict_types` 时直接拒绝这类写入,尽早暴露应用程序的 bug
+//! - 拒绝写入时返回结构化的校验错误(JSON Pointer 字段路径、期望/实际类型、规则 ID)
+//!
+//! 登记表只保存在内存中,首次使用时从集合中抽样已有文档重建;
+//! 集合的模式选项持久化在元数据中 (`schema:{collection}`)。
+
+use mikudb_boml::{BomlValue, Document};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// 模式选项在元数据 CF 中的键前缀
+pub(crate) const SCHEMA_KEY_PREFIX: &str = "schema:";
+
+/// 重建登记表时最多抽样的文档数
+pub(crate) const SEED_SAMPLE_SIZE: usize = 1000;
+
+/// 集合的模式选项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaOptions {
+    /// 记录字段类型并对类型漂移发出警告
+    #[serde(default)]
+    pub track_types: bool,
+    /// 拒绝改变字段主类型的写入(隐含 track_types)
+    #[serde(default)]
+    pub strict_types: bool,
+}
+
+impl SchemaOptions {
+    /// # Brief
+    /// 是否需要维护字段类型登记表
+    pub fn enabled(&self) -> bool {
+        self.track_types || self.strict_types
+    }
+
+    pub(crate) fn metadata_key(collection: &str) -> String {
+        format!("{}{}", SCHEMA_KEY_PREFIX, collection)
+    }
+}
+
+/// strict_types 规则 ID
+pub const RULE_STRICT_TYPES: &str = "strict_types";
+
+/// 索引键长度上限规则 ID
+pub const RULE_INDEX_KEY_SIZE: &str = "index_key_size";
+
+/// 单条校验错误
+///
+/// 随 StorageError/QueryError 传递到协议层,供应用程序精确定位出错的字段。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationDetail {
+    /// 出错字段的 JSON Pointer 路径(RFC 6901),例如 `/address/city`
+    pub path: String,
+    /// 期望的类型或约束
+    pub expected: String,
+    /// 实际的类型或值
+    pub actual: String,
+    /// 触发的规则 ID
+    pub rule: String,
+}
+
+impl ValidationDetail {
+    /// # Brief
+    /// 创建校验错误
+    ///
+    /// # Arguments
+    /// * `field` - 点分隔的字段路径,会被转换为 JSON Pointer
+    /// * `expected` - 期望的类型或约束
+    /// * `actual` - 实际的类型或值
+    /// * `rule` - 规则 ID
+    pub fn new(field: &str, expected: impl Into<String>, actual: impl Into<String>, rule: &str) -> Self {
+        Self {
+            path: json_pointer(field),
+            expected: expected.into(),
+            actual: actual.into(),
+            rule: rule.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: expected {}, got {} (rule {})",
+            self.path, self.expected, self.actual, self.rule
+        )
+    }
+}
+
+/// # Brief
+/// 把点分隔的字段路径转换为 JSON Pointer
+///
+/// 按 RFC 6901 转义: `~` 写作 `~0`,`/` 写作 `~1`。
+///
+/// # Arguments
+/// * `field` - 字段路径,例如 `address.city`
+///
+/// # Returns
+/// JSON Pointer,例如 `/address/city`
+pub fn json_pointer(field: &str) -> String {
+    field.split('.').map(pointer_segment).collect()
+}
+
+fn pointer_segment(segment: &str) -> String {
+    format!("/{}", segment.replace('~', "~0").replace('/', "~1"))
+}
+
+/// # Brief
+/// 把多条校验错误拼接为一行消息
+pub fn join_details(details: &[ValidationDetail]) -> String {
+    details.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("; ")
+}
+
+/// 类型漂移: 字段的新值类型与主类型不一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDrift {
+    /// 字段名
+    pub field: String,
+    /// 当前主类型
+    pub expected: &'static str,
+    /// 新写入值的类型
+    pub actual: &'static str,
+}
+
+impl fmt::Display for TypeDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "field '{}' is {} but was {}", self.field, self.actual, self.expected)
+    }
+}
+
+impl From<&TypeDrift> for ValidationDetail {
+    fn from(drift: &TypeDrift) -> Self {
+        // 字段名本身可能包含 '.',不按路径拆分
+        Self {
+            path: pointer_segment(&drift.field),
+            expected: drift.expected.to_string(),
+            actual: drift.actual.to_string(),
+            rule: RULE_STRICT_TYPES.to_string(),
+        }
+    }
+}
+
+/// 字段类型统计
+#[derive(Debug, Clone)]
+pub struct FieldSummary {
+    /// 字段名
+    pub field: String,
+    /// 主类型
+    pub dominant: &'static str,
+    /// 各类型出现次数,按次数降序
+    pub types: Vec<(&'static str, u64)>,
+}
+
+/// 字段类型登记表
+#[derive(Debug, Default)]
+pub struct SchemaRegistry {
+    fields: HashMap<String, HashMap<&'static str, u64>>,
+    seeded: bool,
+    drift_count: u64,
+    rejected_count: u64,
+}
+
+impl SchemaRegistry {
+    /// # Brief
+    /// 获取值的类型类别
+    ///
+    /// 不同宽度的整数/浮点数归为同一类别,避免把 int32 -> int64 视为漂移。
+    pub fn type_class(value: &BomlValue) -> &'static str {
+        match value {
+            BomlValue::Int32(_) | BomlValue::Int64(_) | BomlValue::Int128(_) => "int",
+            BomlValue::Float32(_) | BomlValue::Float64(_) => "double",
+            other => other.type_name(),
+        }
+    }
+
+    pub(crate) fn is_seeded(&self) -> bool {
+        self.seeded
+    }
+
+    pub(crate) fn mark_seeded(&mut self) {
+        self.seeded = true;
+    }
+
+    /// # Brief
+    /// 获取字段的主类型
+    pub fn dominant(&self, field: &str) -> Option<&'static str> {
+        self.fields.get(field).and_then(dominant_of)
+    }
+
+    /// # Brief
+    /// 检查文档是否改变了任何字段的主类型(不修改登记表)
+    ///
+    /// null 值不参与检查,允许可选字段为空。
+    pub fn check(&self, doc: &Document) -> Vec<TypeDrift> {
+        doc.iter()
+            .filter(|(_, value)| !value.is_null())
+            .filter_map(|(field, value)| {
+                let expected = self.dominant(field)?;
+                let actual = Self::type_class(value);
+                (expected != actual).then(|| TypeDrift {
+                    field: field.to_string(),
+                    expected,
+                    actual,
+                })
+            })
+            .collect()
+    }
+
+    /// # Brief
+    /// 把文档的字段类型计入登记表
+    pub fn record(&mut self, doc: &Document) {
+        for (field, value) in doc.iter() {
+            if value.is_null() {
+                continue;
+            }
+            *self
+                .fields
+                .entry(field.to_string())
+                .or_default()
+                .entry(Self::type_class(value))
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub(crate) fn add_drift(&mut self, count: u64, rejected: bool) {
+        self.drift_count += count;
+        if rejected {
+            self.rejected_count += 1;
+        }
+    }
+
+    /// # Brief
+    /// 累计检测到的类型漂移次数
+    pub fn drift_count(&self) -> u64 {
+        self.drift_count
+    }
+
+    /// # Brief
+    /// 因 strict_types 被拒绝的写入次数
+    pub fn rejected_count(&self) -> u64 {
+        self.rejected_count
+    }
+
+    /// # Brief
+    /// 获取所有字段的类型统计,按字段名排序
+    pub fn summary(&self) -> Vec<FieldSummary> {
+        let mut summary: Vec<FieldSummary> = self
+            .fields
+            .iter()
+            .filter_map(|(field, counts)| {
+                let mut types: Vec<(&'static str, u64)> =
+                    counts.iter().map(|(t, c)| (*t, *c)).collect();
+                types.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+                Some(FieldSummary {
+                    field: field.clone(),
+                    dominant: dominant_of(counts)?,
+                    types,
+                })
+            })
+            .collect();
+        summary.sort_by(|a, b| a.field.cmp(&b.field));
+        summary
+    }
+}
+
+/// # Brief
+/// 出现次数最多的类型,次数相同时按类型名取最小者以保证结果稳定
+fn dominant_of(counts: &HashMap<&'static str, u64>) -> Option<&'static str> {
+    counts
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(t, _)| *t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(age: impl Into<BomlValue>) -> Document {
+        let mut doc = Document::new();
+        doc.insert("name", "miku");
+        doc.insert("age", age);
+        doc
+    }
+
+    #[test]
+    fn test_detects_dominant_type_change() {
+        let mut registry = SchemaRegistry::default();
+        registry.record(&doc(16));
+        registry.record(&doc(17i64));
+
+        assert_eq!(registry.dominant("age"), Some("int"));
+        assert!(registry.check(&doc(18)).is_empty());
+
+        let drift = registry.check(&doc("sixteen"));
+        assert_eq!(
+            drift,
+            vec![TypeDrift { field: "age".to_string(), expected: "int", actual: "string" }]
+        );
+    }
+
+    #[test]
+    fn test_validation_detail_json_pointer() {
+        assert_eq!(json_pointer("age"), "/age");
+        assert_eq!(json_pointer("address.city"), "/address/city");
+        assert_eq!(json_pointer("a/b.c~d"), "/a~1b/c~0d");
+
+        let drift = TypeDrift { field: "age".to_string(), expected: "int", actual: "string" };
+        let detail = ValidationDetail::from(&drift);
+        assert_eq!(detail, ValidationDetail::new("age", "int", "string", RULE_STRICT_TYPES));
+        assert_eq!(detail.to_string(), "/age: expected int, got string (rule strict_types)");
+    }
+
+    #[test]
+    fn test_null_and_new_fields_are_not_drift() {
+        let mut registry = SchemaRegistry::default();
+        registry.record(&doc(16));
+
+        let mut extra = doc(BomlValue::Null);
+        extra.insert("email", "miku@example.com");
+        assert!(registry.check(&extra).is_empty());
+
+        let summary = registry.summary();
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].field, "age");
+        assert_eq!(summary[0].types, vec![("int", 1)]);
+    }
+}
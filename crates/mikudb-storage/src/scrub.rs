@@ -0,0 +1,509 @@
+//! 存储完整性巡检模块
+//!
+//! 后台巡检器以受控的 I/O 速率逐个遍历集合,检查:
+//! - 文档键格式是否合法
+//! - BOML 文档魔数、版本和 xxh3 校验和
+//! - 文档 `_id` 与存储键是否一致
+//! - 索引一致性(文档缺失索引项、索引项指向不存在的文档)
+//!
+//! 每轮巡检的结果写入 `_scrub_report` 集合,累计指标可通过 `Scrubber::stats` 获取,
+//! 供服务器在指标接口中发出告警。
+
+use crate::engine::StorageEngine;
+use crate::index::IndexEngine;
+use crate::{StorageError, StorageResult};
+use mikudb_boml::{codec, BomlValue, Document};
+use mikudb_common::ObjectId;
+use parking_lot::{Condvar, Mutex, RwLock};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// 巡检报告集合名称
+pub const SCRUB_REPORT_COLLECTION: &str = "_scrub_report";
+
+/// 单份报告中保留的最大问题条数
+const MAX_FINDINGS_PER_REPORT: usize = 1000;
+
+/// 巡检配置
+#[derive(Debug, Clone)]
+pub struct ScrubOptions {
+    /// I/O 预算(每秒最多读取的字节数,0 表示不限速)
+    pub max_bytes_per_sec: u64,
+    /// 两轮巡检之间的间隔
+    pub interval: Duration,
+    /// 是否检查索引一致性
+    pub check_indexes: bool,
+}
+
+impl Default for ScrubOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes_per_sec: 8 * 1024 * 1024,
+            interval: Duration::from_secs(24 * 3600),
+            check_indexes: true,
+        }
+    }
+}
+
+/// 问题类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FindingKind {
+    /// 文档键格式非法
+    InvalidKey,
+    /// 文档无法解码(魔数/版本/校验和错误)
+    CorruptDocument,
+    /// 文档 `_id` 与存储键不一致
+    IdMismatch,
+    /// 文档缺少索引项
+    MissingIndexEntry,
+    /// 索引项指向不存在的文档
+    DanglingIndexEntry,
+}
+
+impl FindingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FindingKind::InvalidKey => "invalid_key",
+            FindingKind::CorruptDocument => "corrupt_document",
+            FindingKind::IdMismatch => "id_mismatch",
+            FindingKind::MissingIndexEntry => "missing_index_entry",
+            FindingKind::DanglingIndexEntry => "dangling_index_entry",
+        }
+    }
+}
+
+/// 巡检发现的问题
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrubFinding {
+    /// 所属集合
+    pub collection: String,
+    /// 问题类型
+    pub kind: FindingKind,
+    /// 相关存储键(十六进制)
+    pub key: String,
+    /// 详细描述
+    pub detail: String,
+}
+
+/// 单轮巡检报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScrubReport {
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub collections_scanned: u64,
+    pub documents_scanned: u64,
+    pub index_entries_scanned: u64,
+    pub bytes_scanned: u64,
+    /// 发现的问题总数(可能多于 `findings` 中保留的条数)
+    pub findings_total: u64,
+    pub findings: Vec<ScrubFinding>,
+}
+
+impl ScrubReport {
+    fn record(&mut self, collection: &str, kind: FindingKind, key: &[u8], detail: impl Into<String>) {
+        let detail = detail.into();
+        warn!("Scrub: {} in {} (key {}): {}", kind.as_str(), collection, hex_key(key), detail);
+        self.findings_total += 1;
+        if self.findings.len() < MAX_FINDINGS_PER_REPORT {
+            self.findings.push(ScrubFinding {
+                collection: collection.to_string(),
+                kind,
+                key: hex_key(key),
+                detail,
+            });
+        }
+    }
+
+    /// # Brief
+    /// 是否发现了数据损坏
+    pub fn has_corruption(&self) -> bool {
+        self.findings_total > 0
+    }
+
+    /// # Brief
+    /// 转换为可写入 `_scrub_report` 集合的文档
+    pub fn to_document(&self) -> Document {
+        let mut doc = Document::new();
+        doc.insert("started_at", BomlValue::Timestamp(self.started_at));
+        doc.insert("finished_at", BomlValue::Timestamp(self.finished_at));
+        doc.insert("collections_scanned", self.collections_scanned as i64);
+        doc.insert("documents_scanned", self.documents_scanned as i64);
+        doc.insert("index_entries_scanned", self.index_entries_scanned as i64);
+        doc.insert("bytes_scanned", self.bytes_scanned as i64);
+        doc.insert("findings_total", self.findings_total as i64);
+        let findings: Vec<BomlValue> = self
+            .findings
+            .iter()
+            .map(|f| {
+                let mut d = Document::without_id();
+                d.insert("collection", f.collection.as_str());
+                d.insert("kind", f.kind.as_str());
+                d.insert("key", f.key.as_str());
+                d.insert("detail", f.detail.as_str());
+                BomlValue::from(d)
+            })
+            .collect();
+        doc.insert("findings", BomlValue::Array(findings));
+        doc
+    }
+}
+
+/// 巡检累计统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScrubStats {
+    /// 已完成的巡检轮数
+    pub runs: u64,
+    /// 是否正在巡检
+    pub running: bool,
+    /// 累计发现的问题数
+    pub corruptions_detected: u64,
+    /// 上一轮完成时间(毫秒时间戳)
+    pub last_finished_at: Option<i64>,
+    /// 上一轮扫描的文档数
+    pub last_documents_scanned: u64,
+    /// 上一轮发现的问题数
+    pub last_findings: u64,
+}
+
+/// I/O 限速器
+///
+/// 按累计读取字节数和经过时间计算应睡眠的时长。
+struct IoThrottle {
+    max_bytes_per_sec: u64,
+    started: Instant,
+    consumed: u64,
+}
+
+impl IoThrottle {
+    fn new(max_bytes_per_sec: u64) -> Self {
+        Self {
+            max_bytes_per_sec,
+            started: Instant::now(),
+            consumed: 0,
+        }
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        if self.max_bytes_per_sec == 0 {
+            return;
+        }
+        self.consumed += bytes as u64;
+        let expected = Duration::from_secs_f64(self.consumed as f64 / self.max_bytes_per_sec as f64);
+        let elapsed = self.started.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+    }
+}
+
+/// 存储巡检器
+pub struct Scrubber {
+    engine: Arc<StorageEngine>,
+    options: ScrubOptions,
+    stats: RwLock<ScrubStats>,
+    stop: AtomicBool,
+    /// 用于中断间隔等待
+    wakeup: (Mutex<()>, Condvar),
+}
+
+impl Scrubber {
+    /// # Brief
+    /// 创建巡检器
+    ///
+    /// # Arguments
+    /// * `engine` - 存储引擎
+    /// * `options` - 巡检配置
+    pub fn new(engine: Arc<StorageEngine>, options: ScrubOptions) -> Self {
+        Self {
+            engine,
+            options,
+            stats: RwLock::new(ScrubStats::default()),
+            stop: AtomicBool::new(false),
+            wakeup: (Mutex::new(()), Condvar::new()),
+        }
+    }
+
+    /// # Brief
+    /// 获取累计统计
+    pub fn stats(&self) -> ScrubStats {
+        self.stats.read().clone()
+    }
+
+    /// # Brief
+    /// 执行一轮完整巡检
+    ///
+    /// 遍历所有集合,返回本轮报告,不写入 `_scrub_report`。
+    pub fn run_once(&self) -> StorageResult<ScrubReport> {
+        self.stats.write().running = true;
+        let result = self.scrub_all();
+        let mut stats = self.stats.write();
+        stats.running = false;
+
+        let report = result?;
+        stats.runs += 1;
+        stats.corruptions_detected += report.findings_total;
+        stats.last_finished_at = Some(report.finished_at);
+        stats.last_documents_scanned = report.documents_scanned;
+        stats.last_findings = report.findings_total;
+        Ok(report)
+    }
+
+    /// # Brief
+    /// 执行一轮巡检并将报告写入 `_scrub_report` 集合
+    pub fn run_and_record(&self) -> StorageResult<ScrubReport> {
+        let report = self.run_once()?;
+        let collection = self.engine.get_or_create_collection(SCRUB_REPORT_COLLECTION)?;
+        collection.insert(&mut report.to_document())?;
+
+        if report.has_corruption() {
+            error!(
+                "Scrub finished with {} finding(s) across {} collection(s)",
+                report.findings_total, report.collections_scanned
+            );
+        } else {
+            info!(
+                "Scrub finished: {} document(s), {} index entr(ies), no corruption",
+                report.documents_scanned, report.index_entries_scanned
+            );
+        }
+        Ok(report)
+    }
+
+    /// # Brief
+    /// 启动后台巡检线程
+    ///
+    /// 立即执行第一轮,之后按 `interval` 周期执行,直到调用 `stop`。
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        std::thread::Builder::new()
+            .name("mikudb-scrubber".to_string())
+            .spawn(move || {
+                info!(
+                    "Scrubber started (budget {} B/s, interval {:?})",
+                    self.options.max_bytes_per_sec, self.options.interval
+                );
+                while !self.stop.load(Ordering::SeqCst) {
+                    if let Err(e) = self.run_and_record() {
+                        error!("Scrub failed: {}", e);
+                    }
+
+                    let mut guard = self.wakeup.0.lock();
+                    if !self.stop.load(Ordering::SeqCst) {
+                        self.wakeup.1.wait_for(&mut guard, self.options.interval);
+                    }
+                }
+                info!("Scrubber stopped");
+            })
+            .expect("failed to spawn scrubber thread")
+    }
+
+    /// # Brief
+    /// 停止后台巡检
+    ///
+    /// 当前轮次会在处理完正在扫描的集合后退出。
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _guard = self.wakeup.0.lock();
+        self.wakeup.1.notify_all();
+    }
+
+    fn scrub_all(&self) -> StorageResult<ScrubReport> {
+        let mut report = ScrubReport {
+            started_at: chrono::Utc::now().timestamp_millis(),
+            ..Default::default()
+        };
+        let mut throttle = IoThrottle::new(self.options.max_bytes_per_sec);
+
+        let indexes = if self.options.check_indexes {
+            let engine = IndexEngine::new(self.engine.db().clone());
+            match engine.load_indexes() {
+                Ok(()) => Some(engine),
+                Err(e) => {
+                    debug!("Scrub: index checks skipped: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        for name in self.engine.list_collections()? {
+            if self.stop.load(Ordering::SeqCst) {
+                break;
+            }
+            self.scrub_collection(&name, indexes.as_ref(), &mut throttle, &mut report)?;
+            report.collections_scanned += 1;
+        }
+
+        report.finished_at = chrono::Utc::now().timestamp_millis();
+        Ok(report)
+    }
+
+    fn scrub_collection(
+        &self,
+        name: &str,
+        indexes: Option<&IndexEngine>,
+        throttle: &mut IoThrottle,
+        report: &mut ScrubReport,
+    ) -> StorageResult<()> {
+        let db = self.engine.db();
+        let Some(cf) = db.cf_handle(name) else {
+            // 集合在巡检期间被删除
+            return Ok(());
+        };
+        let index_defs = indexes.map(|i| i.list_indexes(name)).unwrap_or_default();
+
+        debug!("Scrubbing collection {}", name);
+
+        for item in db.prefix_iterator_cf(&cf, [b'd']) {
+            let (key, value) = item?;
+            if key.first() != Some(&b'd') {
+                break;
+            }
+            throttle.consume(key.len() + value.len());
+            report.documents_scanned += 1;
+            report.bytes_scanned += (key.len() + value.len()) as u64;
+
+            if key.len() != 13 {
+                report.record(name, FindingKind::InvalidKey, &key, format!("Unexpected key length {}", key.len()));
+                continue;
+            }
+            let key_id = ObjectId::from_bytes(key[1..13].try_into().unwrap());
+
+            let doc = match codec::decode_document(&value)
+                .map_err(StorageError::from)
+                .and_then(|v| Document::from_boml_value(v).map_err(StorageError::from))
+            {
+                Ok(doc) => doc,
+                Err(e) => {
+                    report.record(name, FindingKind::CorruptDocument, &key, e.to_string());
+                    continue;
+                }
+            };
+
+            if let Some(id) = doc.id() {
+                if *id != key_id {
+                    report.record(name, FindingKind::IdMismatch, &key, format!("Document _id is {}", id));
+                }
+            }
+
+            if let Some(indexes) = indexes {
+                for def in &index_defs {
+                    match indexes.has_entry(&def.name, &doc, &key_id) {
+                        Ok(true) => {}
+                        Ok(false) => report.record(
+                            name,
+                            FindingKind::MissingIndexEntry,
+                            &key,
+                            format!("No entry in index {}", def.name),
+                        ),
+                        Err(e) => debug!("Scrub: cannot check index {}: {}", def.name, e),
+                    }
+                }
+            }
+        }
+
+        if let Some(indexes) = indexes {
+            for def in &index_defs {
+                let result = indexes.for_each_entry(&def.name, |entry_key, doc_id| {
+                    throttle.consume(entry_key.len());
+                    report.index_entries_scanned += 1;
+                    let mut doc_key = Vec::with_capacity(13);
+                    doc_key.push(b'd');
+                    doc_key.extend_from_slice(doc_id.as_bytes());
+                    if db.get_cf(&cf, &doc_key)?.is_none() {
+                        report.record(
+                            name,
+                            FindingKind::DanglingIndexEntry,
+                            entry_key,
+                            format!("Index {} points to missing document {}", def.name, doc_id),
+                        );
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    debug!("Scrub: cannot scan index {}: {}", def.name, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn hex_key(key: &[u8]) -> String {
+    key.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::StorageOptions;
+    use tempfile::tempdir;
+
+    fn setup() -> (tempfile::TempDir, Arc<StorageEngine>) {
+        let dir = tempdir().unwrap();
+        let options = StorageOptions {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let engine = Arc::new(StorageEngine::open(options).unwrap());
+        (dir, engine)
+    }
+
+    fn unthrottled() -> ScrubOptions {
+        ScrubOptions {
+            max_bytes_per_sec: 0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_clean_scrub() {
+        let (_dir, engine) = setup();
+        let collection = engine.create_collection("users").unwrap();
+        for i in 0..10 {
+            let mut doc = Document::new();
+            doc.insert("n", i);
+            collection.insert(&mut doc).unwrap();
+        }
+
+        let scrubber = Scrubber::new(engine, unthrottled());
+        let report = scrubber.run_once().unwrap();
+        assert_eq!(report.documents_scanned, 10);
+        assert!(!report.has_corruption());
+        assert_eq!(scrubber.stats().runs, 1);
+    }
+
+    #[test]
+    fn test_detects_checksum_mismatch() {
+        let (_dir, engine) = setup();
+        let collection = engine.create_collection("users").unwrap();
+        let mut doc = Document::new();
+        doc.insert("name", "Miku");
+        let id = collection.insert(&mut doc).unwrap();
+
+        // 直接篡改存储值的最后一个字节(校验和)
+        let db = engine.db();
+        let cf = db.cf_handle("users").unwrap();
+        let mut key = vec![b'd'];
+        key.extend_from_slice(id.as_bytes());
+        let mut value = db.get_cf(&cf, &key).unwrap().unwrap();
+        let last = value.len() - 1;
+        value[last] ^= 0xFF;
+        db.put_cf(&cf, &key, &value).unwrap();
+        drop(cf);
+
+        let scrubber = Scrubber::new(engine.clone(), unthrottled());
+        let report = scrubber.run_and_record().unwrap();
+        assert_eq!(report.findings_total, 1);
+        assert_eq!(report.findings[0].kind, FindingKind::CorruptDocument);
+        assert_eq!(scrubber.stats().corruptions_detected, 1);
+
+        let reports = engine.get_collection(SCRUB_REPORT_COLLECTION).unwrap().find_all().unwrap();
+        assert_eq!(reports.len(), 1);
+    }
+}
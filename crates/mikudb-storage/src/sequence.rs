@@ -0,0 +1,117 @@
+//! 序列模块
+//!
+//! 为应用提供自增编号(订单号、流水号等),避免在应用层做"读取-加一-写回":
+//! - 序列保存在独立的 `_sequences` CF 中,定义键为 `m:{name}`,计数键为 `v:{name}`
+//! - 计数键只通过 RocksDB 合并算子(加法)更新,并发取号不会互相覆盖
+//! - 合并操作写入 RocksDB 的 WAL,进程崩溃后已发放的编号不会重复
+//! - 计数键保存已发放的编号个数,编号由 `start + (个数 - 1) * increment` 计算
+
+use rocksdb::MergeOperands;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::AtomicU64;
+
+/// 序列 CF 名称
+pub(crate) const SEQUENCE_CF: &str = "_sequences";
+
+/// 合并算子名称,写入 SST 文件,不可修改
+pub(crate) const SEQUENCE_MERGE_OPERATOR: &str = "mikudb.sequence.add";
+
+/// 序列定义键前缀
+pub(crate) const SEQUENCE_DEFINITION_PREFIX: &str = "m:";
+
+/// 序列计数键前缀
+const SEQUENCE_COUNTER_PREFIX: &str = "v:";
+
+/// 序列定义
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SequenceDefinition {
+    /// 序列名称
+    pub name: String,
+    /// 第一个编号
+    pub start: i64,
+    /// 相邻编号的差,不为 0
+    pub increment: i64,
+}
+
+impl SequenceDefinition {
+    pub(crate) fn definition_key(name: &str) -> String {
+        format!("{}{}", SEQUENCE_DEFINITION_PREFIX, name)
+    }
+
+    pub(crate) fn counter_key(name: &str) -> String {
+        format!("{}{}", SEQUENCE_COUNTER_PREFIX, name)
+    }
+
+    /// # Brief
+    /// 计算第 `count` 个编号(从 1 开始)
+    ///
+    /// # Returns
+    /// `count` 为 0 或编号超出 i64 范围时返回 None
+    pub fn value_at(&self, count: u64) -> Option<i64> {
+        let steps = i64::try_from(count.checked_sub(1)?).ok()?;
+        self.increment.checked_mul(steps)?.checked_add(self.start)
+    }
+}
+
+/// 已加载到内存的序列
+pub(crate) struct SequenceCounter {
+    /// 序列定义
+    pub(crate) definition: SequenceDefinition,
+    /// 已发放的编号个数,领取编号时原子递增
+    pub(crate) issued: AtomicU64,
+}
+
+/// # Brief
+/// 编码计数值或合并操作数
+pub(crate) fn encode_count(count: u64) -> [u8; 8] {
+    count.to_le_bytes()
+}
+
+/// # Brief
+/// 解码计数值,长度不对时视为 0
+pub(crate) fn decode_count(bytes: &[u8]) -> u64 {
+    bytes.try_into().map(u64::from_le_bytes).unwrap_or(0)
+}
+
+/// # Brief
+/// 加法合并算子,把所有操作数累加到已有计数上
+///
+/// 加法满足结合律,同一个函数同时用作完整合并和部分合并。
+pub(crate) fn add_merge(_key: &[u8], existing: Option<&[u8]>, operands: &MergeOperands) -> Option<Vec<u8>> {
+    let total = operands
+        .iter()
+        .fold(existing.map_or(0, decode_count), |acc, op| acc.wrapping_add(decode_count(op)));
+    Some(encode_count(total).to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_at() {
+        let seq = SequenceDefinition {
+            name: "order_no".to_string(),
+            start: 1000,
+            increment: 5,
+        };
+        assert_eq!(seq.value_at(0), None);
+        assert_eq!(seq.value_at(1), Some(1000));
+        assert_eq!(seq.value_at(3), Some(1010));
+
+        let down = SequenceDefinition {
+            name: "countdown".to_string(),
+            start: 10,
+            increment: -1,
+        };
+        assert_eq!(down.value_at(11), Some(0));
+
+        let near_max = SequenceDefinition {
+            name: "big".to_string(),
+            start: i64::MAX,
+            increment: 1,
+        };
+        assert_eq!(near_max.value_at(1), Some(i64::MAX));
+        assert_eq!(near_max.value_at(2), None);
+    }
+}
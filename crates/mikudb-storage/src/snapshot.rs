@@ -0,0 +1,461 @@
+//! 物理快照模块
+//!
+//! 在服务器运行期间创建时间点一致的物理快照,用于运维备份:
+//! - 数据部分是 RocksDB 检查点,SST 文件在同一文件系统上以硬链接方式共享,创建很快
+//! - 创建检查点时暂停 WAL 组提交,快照恰好包含清单中 `wal_lsn` 之前提交的全部写入
+//! - 清单记录 RocksDB 序列号、WAL 位置、集合列表和索引定义,索引数据本身在检查点中
+//!
+//! 与逻辑备份(见 [`crate::backup`])不同,快照只能离线恢复到空的数据目录:
+//! `restore_snapshot` 复制检查点文件,之后打开的存储引擎从 `wal_lsn` 继续写入 WAL。
+//!
+//! 外部路径上的归档集合位于独立的存储实例中,不包含在快照内。
+//!
+//! 快照目录布局:
+//! - `snapshot.json`: 快照清单
+//! - `db/`: RocksDB 检查点
+//!
+//! # 增量备份
+//!
+//! 增量备份导出从快照(或上一次增量备份)的 WAL 位置到当前的 WAL 段,
+//! 需要配置 WAL 归档目录,否则只能导出当前 WAL 文件中还未被检查点截断的部分。
+//! 恢复时先恢复快照,再按顺序重放各增量中已提交的事务:
+//! - 记录涉及而快照中没有的集合会自动创建,应用后重建受影响集合的索引
+//! - 集合选项、索引定义、序列等元数据的修改不经过 WAL,不包含在增量中
+//!
+//! 增量备份目录布局:
+//! - `increment.json`: 增量清单
+//! - `<起始 LSN>-<结束 LSN>.wal`: WAL 段
+
+use crate::engine::{StorageEngine, StorageOptions};
+use crate::index::IndexDefinition;
+use crate::recovery::{self, RecoveryStats};
+use crate::wal::WriteAheadLog;
+use crate::{StorageError, StorageResult};
+use rocksdb::checkpoint::Checkpoint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// 快照格式版本
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// 快照清单文件名
+pub(crate) const SNAPSHOT_MANIFEST_FILE: &str = "snapshot.json";
+/// 增量清单文件名
+const INCREMENT_MANIFEST_FILE: &str = "increment.json";
+/// 检查点目录
+const CHECKPOINT_DIR: &str = "db";
+/// WAL 文件名,与存储引擎打开时使用的一致
+const WAL_FILE: &str = "mikudb.wal";
+
+/// 快照清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// 快照格式版本
+    pub version: u32,
+    /// 快照时间(RFC 3339)
+    pub created_at: String,
+    /// 检查点的 RocksDB 序列号
+    pub sequence: u64,
+    /// 快照包含的 WAL 位置: LSN 小于它的提交都已包含,未启用 WAL 时为 None
+    pub wal_lsn: Option<u64>,
+    /// 集合列表
+    pub collections: Vec<String>,
+    /// 索引定义
+    pub indexes: Vec<IndexDefinition>,
+}
+
+impl SnapshotManifest {
+    /// # Brief
+    /// 读取快照目录中的清单
+    ///
+    /// # Arguments
+    /// * `dir` - 快照目录
+    pub fn load(dir: &Path) -> StorageResult<Self> {
+        let content = fs::read(dir.join(SNAPSHOT_MANIFEST_FILE))?;
+        let manifest: Self = serde_json::from_slice(&content)
+            .map_err(|e| StorageError::Corruption(format!("Invalid snapshot manifest: {}", e)))?;
+        if manifest.version != SNAPSHOT_FORMAT_VERSION {
+            return Err(StorageError::Corruption(format!(
+                "Unsupported snapshot format version {}",
+                manifest.version
+            )));
+        }
+        Ok(manifest)
+    }
+}
+
+/// 增量备份清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncrementManifest {
+    /// 快照格式版本
+    pub version: u32,
+    /// 备份时间(RFC 3339)
+    pub created_at: String,
+    /// 起始 LSN,应用前数据库必须已经包含它之前的提交
+    pub since_lsn: u64,
+    /// 结束 LSN,下一次增量备份的起点
+    pub until_lsn: u64,
+    /// 按顺序排列的 WAL 段文件名
+    pub segments: Vec<String>,
+}
+
+impl IncrementManifest {
+    /// # Brief
+    /// 读取增量备份目录中的清单
+    ///
+    /// # Arguments
+    /// * `dir` - 增量备份目录
+    pub fn load(dir: &Path) -> StorageResult<Self> {
+        let content = fs::read(dir.join(INCREMENT_MANIFEST_FILE))?;
+        let manifest: Self = serde_json::from_slice(&content)
+            .map_err(|e| StorageError::Corruption(format!("Invalid increment manifest: {}", e)))?;
+        if manifest.version != SNAPSHOT_FORMAT_VERSION {
+            return Err(StorageError::Corruption(format!(
+                "Unsupported increment format version {}",
+                manifest.version
+            )));
+        }
+        Ok(manifest)
+    }
+}
+
+/// # Brief
+/// 创建物理快照
+///
+/// 目标目录不存在时自动创建,已有快照时拒绝覆盖。
+/// 创建检查点期间新的写入排队等待,读取不受影响。
+///
+/// # Arguments
+/// * `engine` - 存储引擎
+/// * `dir` - 快照目录
+///
+/// # Returns
+/// 快照清单
+pub fn create_snapshot(engine: &StorageEngine, dir: &Path) -> StorageResult<SnapshotManifest> {
+    if dir.join(SNAPSHOT_MANIFEST_FILE).exists() || dir.join(CHECKPOINT_DIR).exists() {
+        return Err(StorageError::Internal(format!("Snapshot already exists at {}", dir.display())));
+    }
+    fs::create_dir_all(dir)?;
+
+    let mut collections = engine.list_collections()?;
+    collections.sort();
+    let mut indexes: Vec<IndexDefinition> = collections
+        .iter()
+        .flat_map(|name| engine.indexes().list_indexes(name))
+        .collect();
+    indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let db = engine.db();
+    let checkpoint = || -> StorageResult<u64> {
+        Checkpoint::new(db)?.create_checkpoint(dir.join(CHECKPOINT_DIR))?;
+        Ok(db.latest_sequence_number())
+    };
+    let (sequence, wal_lsn) = match engine.wal() {
+        Some(wal) => wal.paused(|lsn| checkpoint().map(|sequence| (sequence, Some(lsn))))?,
+        None => (checkpoint()?, None),
+    };
+
+    let manifest = SnapshotManifest {
+        version: SNAPSHOT_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        sequence,
+        wal_lsn,
+        collections,
+        indexes,
+    };
+    let content = serde_json::to_vec_pretty(&manifest).map_err(|e| StorageError::Internal(e.to_string()))?;
+    fs::write(dir.join(SNAPSHOT_MANIFEST_FILE), content)?;
+
+    info!(
+        "Snapshot written to {} (sequence {}, {} collections)",
+        dir.display(),
+        manifest.sequence,
+        manifest.collections.len()
+    );
+    Ok(manifest)
+}
+
+/// # Brief
+/// 导出增量备份
+///
+/// # Arguments
+/// * `engine` - 存储引擎,必须启用 WAL
+/// * `since_lsn` - 起始 LSN
+/// * `dir` - 增量备份目录,不存在时自动创建
+///
+/// # Returns
+/// 增量清单;`since_lsn` 之后的 WAL 段已被丢弃时返回错误,需要重新创建快照
+pub fn backup_incremental(engine: &StorageEngine, since_lsn: u64, dir: &Path) -> StorageResult<IncrementManifest> {
+    let wal = engine
+        .wal()
+        .ok_or_else(|| StorageError::InvalidArgument("Incremental backup requires the WAL".to_string()))?;
+    if dir.join(INCREMENT_MANIFEST_FILE).exists() {
+        return Err(StorageError::Internal(format!("Incremental backup already exists at {}", dir.display())));
+    }
+
+    let (until_lsn, segments) = wal.export_since(since_lsn, dir)?;
+    let manifest = IncrementManifest {
+        version: SNAPSHOT_FORMAT_VERSION,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        since_lsn,
+        until_lsn,
+        segments,
+    };
+    let content = serde_json::to_vec_pretty(&manifest).map_err(|e| StorageError::Internal(e.to_string()))?;
+    fs::write(dir.join(INCREMENT_MANIFEST_FILE), content)?;
+
+    info!(
+        "Incremental backup written to {} (LSN {}..{}, {} segment(s))",
+        dir.display(),
+        since_lsn,
+        until_lsn,
+        manifest.segments.len()
+    );
+    Ok(manifest)
+}
+
+/// # Brief
+/// 把增量备份应用到存储引擎
+///
+/// 重放各段中尚未应用的提交,记录涉及的集合不存在时先创建,最后重建这些集合的索引
+/// 并把 WAL 推进到增量的结束位置。数据库已包含整个增量时不做任何事。
+///
+/// # Arguments
+/// * `engine` - 从快照恢复的存储引擎,WAL 中不能有新的写入
+/// * `dir` - 增量备份目录
+///
+/// # Returns
+/// 重放统计;增量与数据库之间有缺口时返回错误
+pub(crate) fn apply_increment(engine: &StorageEngine, dir: &Path) -> StorageResult<RecoveryStats> {
+    let manifest = IncrementManifest::load(dir)?;
+    let db = engine.db();
+    let applied = recovery::applied_lsn(db)?;
+    if manifest.since_lsn > applied {
+        return Err(StorageError::InvalidArgument(format!(
+            "Increment {} starts at LSN {} but the database is at LSN {}, apply the earlier increments first",
+            dir.display(),
+            manifest.since_lsn,
+            applied
+        )));
+    }
+    if manifest.until_lsn <= applied {
+        info!("Increment {} is already applied", dir.display());
+        return Ok(RecoveryStats::default());
+    }
+
+    // 先创建快照之后才出现的集合,否则它们的记录会被当作已删除集合跳过
+    let mut touched = BTreeSet::new();
+    for segment in &manifest.segments {
+        WriteAheadLog::read_segment(&dir.join(segment), |_, record| {
+            if !record.collection.is_empty() {
+                touched.insert(record.collection);
+            }
+            Ok(())
+        })?;
+    }
+    for name in &touched {
+        engine.get_or_create_collection(name)?;
+    }
+
+    let mut stats = RecoveryStats::default();
+    for segment in &manifest.segments {
+        let segment_stats = recovery::replay_segment(db, &dir.join(segment))?;
+        stats.transactions_recovered += segment_stats.transactions_recovered;
+        stats.transactions_skipped += segment_stats.transactions_skipped;
+        stats.inserts_replayed += segment_stats.inserts_replayed;
+        stats.updates_replayed += segment_stats.updates_replayed;
+        stats.deletes_replayed += segment_stats.deletes_replayed;
+        stats.merges_replayed += segment_stats.merges_replayed;
+        stats.errors_encountered += segment_stats.errors_encountered;
+        stats.total_replayed += segment_stats.total_replayed;
+    }
+
+    for name in &touched {
+        engine.evict_collection(name);
+        if !engine.indexes().list_indexes(name).is_empty() {
+            engine.indexes().rebuild_indexes(&*engine.get_collection(name)?)?;
+        }
+    }
+    if let Some(wal) = engine.wal() {
+        wal.advance_to(recovery::applied_lsn(db)?)?;
+    }
+
+    info!(
+        "Applied increment {} (LSN {}..{}): {} transaction(s), {} operation(s)",
+        dir.display(),
+        manifest.since_lsn,
+        manifest.until_lsn,
+        stats.transactions_recovered,
+        stats.total_replayed
+    );
+    Ok(stats)
+}
+
+/// # Brief
+/// 把快照恢复到空的数据目录
+///
+/// 数据目录中已有数据库或 WAL 文件时返回错误,不会覆盖现有数据。
+///
+/// # Arguments
+/// * `dir` - 快照目录
+/// * `options` - 之后打开存储引擎使用的配置,决定数据目录和 WAL 目录
+///
+/// # Returns
+/// 快照清单
+pub fn restore_snapshot(dir: &Path, options: &StorageOptions) -> StorageResult<SnapshotManifest> {
+    let manifest = SnapshotManifest::load(dir)?;
+    let data_dir = &options.data_dir;
+    if data_dir.join("CURRENT").exists() {
+        return Err(StorageError::Internal(format!("Database already exists at {}", data_dir.display())));
+    }
+    let wal_path = options.wal_dir.clone().unwrap_or_else(|| data_dir.join("wal")).join(WAL_FILE);
+    if wal_path.exists() {
+        return Err(StorageError::Internal(format!(
+            "WAL file {} belongs to another database",
+            wal_path.display()
+        )));
+    }
+
+    fs::create_dir_all(data_dir)?;
+    let mut files = 0usize;
+    for entry in fs::read_dir(dir.join(CHECKPOINT_DIR))? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            fs::copy(entry.path(), data_dir.join(entry.file_name()))?;
+            files += 1;
+        }
+    }
+
+    info!(
+        "Restored snapshot {} into {} ({} files, sequence {})",
+        dir.display(),
+        data_dir.display(),
+        files,
+        manifest.sequence
+    );
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{IndexField, IndexOrder, IndexType, KeyEncoding, OversizedKeyPolicy};
+    use mikudb_boml::{BomlValue, Document};
+    use tempfile::tempdir;
+
+    fn options(dir: &Path) -> StorageOptions {
+        StorageOptions {
+            data_dir: dir.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let dir = tempdir().unwrap();
+        let snapshot_dir = dir.path().join("snapshot");
+
+        let source = StorageEngine::open(options(&dir.path().join("source"))).unwrap();
+        let users = source.create_collection("users").unwrap();
+        source
+            .indexes()
+            .create_index(IndexDefinition {
+                name: "users_name".to_string(),
+                collection: "users".to_string(),
+                fields: vec![IndexField { path: "name".to_string(), order: IndexOrder::Ascending }],
+                index_type: IndexType::BTree,
+                unique: false,
+                sparse: false,
+                ttl_seconds: None,
+                key_encoding: KeyEncoding::Memcomparable,
+                max_key_size: None,
+                oversized_keys: OversizedKeyPolicy::Reject,
+            })
+            .unwrap();
+        let mut doc = Document::new();
+        doc.insert("name", "miku");
+        users.insert(&mut doc).unwrap();
+        source.indexes().index_document("users", &doc).unwrap();
+
+        let manifest = source.create_snapshot(&snapshot_dir).unwrap();
+        assert_eq!(manifest.collections, vec!["users"]);
+        assert_eq!(manifest.indexes.len(), 1);
+        assert_eq!(manifest.wal_lsn, Some(2));
+        assert!(source.create_snapshot(&snapshot_dir).is_err());
+
+        // 快照之后的写入不在快照中
+        users.insert(&mut Document::new()).unwrap();
+
+        let target_dir = dir.path().join("target");
+        let target = StorageEngine::restore_from(&snapshot_dir, options(&target_dir)).unwrap();
+        let restored = target.get_collection("users").unwrap();
+        assert_eq!(restored.count_scan().unwrap(), 1);
+        let ids = target.indexes().lookup("users_name", &[BomlValue::from("miku")]).unwrap();
+        assert_eq!(ids.len(), 1);
+
+        // 新的 WAL 从快照位置继续,重新打开时不会跳过恢复后的写入
+        restored.insert(&mut Document::new()).unwrap();
+        drop((restored, target));
+        let reopened = StorageEngine::open(options(&target_dir)).unwrap();
+        assert_eq!(reopened.get_collection("users").unwrap().count_scan().unwrap(), 2);
+
+        assert!(StorageEngine::restore_from(&snapshot_dir, options(&target_dir)).is_err());
+    }
+
+    #[test]
+    fn test_incremental_backup_and_restore() {
+        let dir = tempdir().unwrap();
+        let snapshot_dir = dir.path().join("snapshot");
+        let first_dir = dir.path().join("inc1");
+        let second_dir = dir.path().join("inc2");
+
+        let source = StorageEngine::open(StorageOptions {
+            wal_archive_dir: Some(dir.path().join("archive")),
+            ..options(&dir.path().join("source"))
+        })
+        .unwrap();
+        let users = source.create_collection("users").unwrap();
+        users.insert(&mut Document::new()).unwrap();
+        let snapshot = source.create_snapshot(&snapshot_dir).unwrap();
+
+        // 检查点把第一段 WAL 移入归档目录,增量需要同时导出归档段和当前文件
+        let id = users.insert(&mut Document::new()).unwrap();
+        source.wal().unwrap().checkpoint().unwrap();
+        source.create_collection("orders").unwrap().insert(&mut Document::new()).unwrap();
+        let first = source.backup_incremental(snapshot.wal_lsn.unwrap(), &first_dir).unwrap();
+        assert_eq!(first.segments.len(), 2);
+        assert!(source.backup_incremental(first.since_lsn, &first_dir).is_err());
+
+        users.delete(&id).unwrap();
+        let second = source.backup_incremental(first.until_lsn, &second_dir).unwrap();
+        assert_eq!(second.since_lsn, first.until_lsn);
+
+        // 跳过第一个增量会留下缺口
+        assert!(StorageEngine::restore_with_increments(
+            &snapshot_dir,
+            &[&second_dir],
+            options(&dir.path().join("gap"))
+        )
+        .is_err());
+
+        let target_dir = dir.path().join("target");
+        let target =
+            StorageEngine::restore_with_increments(&snapshot_dir, &[&first_dir, &second_dir], options(&target_dir))
+                .unwrap();
+        assert_eq!(target.get_collection("users").unwrap().count_scan().unwrap(), 1);
+        assert_eq!(target.get_collection("orders").unwrap().count_scan().unwrap(), 1);
+
+        // 再次应用已包含的增量不做任何事
+        let stats = apply_increment(&target, &first_dir).unwrap();
+        assert_eq!(stats.total_replayed, 0);
+
+        // 恢复后的写入在重新打开时不会被跳过
+        target.get_collection("orders").unwrap().insert(&mut Document::new()).unwrap();
+        drop(target);
+        let reopened = StorageEngine::open(options(&target_dir)).unwrap();
+        assert_eq!(reopened.get_collection("orders").unwrap().count_scan().unwrap(), 2);
+    }
+}
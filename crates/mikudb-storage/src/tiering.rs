@@ -0,0 +1,100 @@
+//! 冷热数据分层模块
+//!
+//! 把历史数据从热集合迁移到压缩率更高的归档集合:
+//! - 归档集合可以使用独立的压缩算法(通常为 zstd)
+//! - 归档集合可以放在同一数据目录,也可以放在其他路径/磁盘上的独立 RocksDB 实例中
+//! - 归档策略持久化在元数据中 (`archive:{name}`),重启后自动恢复压缩设置和外部路径
+//! - 查询时可以通过源集合找到它的所有归档集合,实现热+冷数据的透明合并
+
+use mikudb_common::config::CompressionType;
+use rocksdb::DBCompressionType;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 归档元数据在元数据 CF 中的键前缀
+pub(crate) const ARCHIVE_KEY_PREFIX: &str = "archive:";
+
+/// 归档策略
+///
+/// 描述一个源集合到归档集合的迁移规则。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivePolicy {
+    /// 源(热)集合名称
+    pub source: String,
+    /// 归档(冷)集合名称
+    pub archive: String,
+    /// 归档阈值(秒),文档创建时间早于 now - older_than_secs 时被归档
+    pub older_than_secs: u64,
+    /// 归档集合使用的压缩算法
+    pub compression: CompressionType,
+    /// 归档集合所在目录,为空时与主数据库共用数据目录
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+impl ArchivePolicy {
+    pub(crate) fn metadata_key(archive: &str) -> String {
+        format!("{}{}", ARCHIVE_KEY_PREFIX, archive)
+    }
+}
+
+/// # Brief
+/// 转换为 RocksDB 压缩类型
+pub(crate) fn to_rocksdb_compression(compression: CompressionType) -> DBCompressionType {
+    match compression {
+        CompressionType::None => DBCompressionType::None,
+        CompressionType::Lz4 => DBCompressionType::Lz4,
+        CompressionType::Zstd => DBCompressionType::Zstd,
+    }
+}
+
+/// # Brief
+/// 获取 SetOptions 使用的压缩类型名称
+pub(crate) fn compression_option_name(compression: CompressionType) -> &'static str {
+    match compression {
+        CompressionType::None => "kNoCompression",
+        CompressionType::Lz4 => "kLZ4Compression",
+        CompressionType::Zstd => "kZSTD",
+    }
+}
+
+/// # Brief
+/// 解析压缩算法名称,不区分大小写
+///
+/// # Returns
+/// 未知名称返回 None
+pub fn parse_compression(name: &str) -> Option<CompressionType> {
+    match name.to_ascii_lowercase().as_str() {
+        "none" => Some(CompressionType::None),
+        "lz4" => Some(CompressionType::Lz4),
+        "zstd" => Some(CompressionType::Zstd),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_compression() {
+        assert_eq!(parse_compression("ZSTD"), Some(CompressionType::Zstd));
+        assert_eq!(parse_compression("lz4"), Some(CompressionType::Lz4));
+        assert_eq!(parse_compression("snappy"), None);
+    }
+
+    #[test]
+    fn test_policy_roundtrip() {
+        let policy = ArchivePolicy {
+            source: "events".to_string(),
+            archive: "events_archive".to_string(),
+            older_than_secs: 90 * 86400,
+            compression: CompressionType::Zstd,
+            path: Some(PathBuf::from("/mnt/cold")),
+        };
+        let json = serde_json::to_vec(&policy).unwrap();
+        let decoded: ArchivePolicy = serde_json::from_slice(&json).unwrap();
+        assert_eq!(decoded, policy);
+        assert_eq!(ArchivePolicy::metadata_key("events_archive"), "archive:events_archive");
+    }
+}
@@ -0,0 +1,428 @@
+//! 分词器模块
+//!
+//! 为全文索引提供可插拔的分词接口:
+//! - **Tokenizer trait**: 把文本切分为词项,可自行实现并通过 `FullTextIndex::with_tokenizer` 使用
+//! - **Unicode 分词**(默认): 按 Unicode 标准 (UAX #29) 的单词边界切分
+//! - **jieba 中文分词**: 基于词典的中文分词,需要启用 `jieba` feature
+//! - **文本分析器**: 分词后统一进行小写化、长度过滤、停用词过滤和词干提取
+//!
+//! 每个全文索引可以单独选择分词器:
+//!
+//! ```text
+//! CREATE TEXT INDEX idx_body ON articles (body) WITH TOKENIZER 'jieba' STOPWORDS 'chinese'
+//! CREATE TEXT INDEX idx_title ON articles (title) WITH STOPWORDS 'english' STEMMER 'english'
+//! ```
+
+use crate::{StorageError, StorageResult};
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// 内置英文停用词表
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "if", "in", "into", "is",
+    "it", "no", "not", "of", "on", "or", "such", "that", "the", "their", "then", "there",
+    "these", "they", "this", "to", "was", "will", "with",
+];
+
+/// 内置中文停用词表
+const CHINESE_STOPWORDS: &[&str] = &[
+    "的", "了", "和", "是", "就", "都", "而", "及", "与", "着", "或", "一个", "没有", "我们",
+    "你们", "他们", "在", "也", "这", "那", "之", "以", "为", "于", "上", "把", "被", "对",
+];
+
+/// 分词器接口
+///
+/// 分词器只负责切分文本,小写化、停用词和词干提取由 [`TextAnalyzer`] 统一处理。
+pub trait Tokenizer: Send + Sync {
+    /// # Brief
+    /// 分词器名称
+    fn name(&self) -> &'static str;
+
+    /// # Brief
+    /// 把文本切分为词项
+    ///
+    /// # Arguments
+    /// * `text` - 原始文本
+    ///
+    /// # Returns
+    /// 按出现顺序排列的词项,词项在结果中的下标即其位置
+    fn tokenize(&self, text: &str) -> Vec<String>;
+}
+
+/// 分词器类型
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TokenizerType {
+    /// Unicode 单词边界分词器(默认)
+    #[default]
+    Unicode,
+    /// 简单分词器(空格分词)
+    Simple,
+    /// 中文 N-gram 分词器
+    ChineseNGram,
+    /// 混合分词器(自动识别中英文)
+    Mixed,
+    /// jieba 中文分词器(需要 `jieba` feature)
+    Jieba,
+}
+
+impl TokenizerType {
+    /// # Brief
+    /// 根据名称解析分词器类型(不区分大小写)
+    ///
+    /// # Arguments
+    /// * `name` - `unicode`、`simple`、`ngram`、`mixed` 或 `jieba`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "unicode" | "default" => Some(Self::Unicode),
+            "simple" | "whitespace" => Some(Self::Simple),
+            "ngram" | "chinese_ngram" => Some(Self::ChineseNGram),
+            "mixed" => Some(Self::Mixed),
+            "jieba" => Some(Self::Jieba),
+            _ => None,
+        }
+    }
+
+    /// # Brief
+    /// 创建该类型的分词器
+    ///
+    /// # Returns
+    /// 分词器实例;未启用 `jieba` feature 时选择 jieba 返回 InvalidArgument 错误
+    pub fn build(self) -> StorageResult<Box<dyn Tokenizer>> {
+        match self {
+            Self::Unicode => Ok(Box::new(UnicodeTokenizer)),
+            Self::Simple => Ok(Box::new(WhitespaceTokenizer)),
+            Self::ChineseNGram => Ok(Box::new(NGramTokenizer)),
+            Self::Mixed => Ok(Box::new(MixedTokenizer)),
+            #[cfg(feature = "jieba")]
+            Self::Jieba => Ok(Box::new(JiebaTokenizer)),
+            #[cfg(not(feature = "jieba"))]
+            Self::Jieba => Err(StorageError::InvalidArgument(
+                "Tokenizer 'jieba' requires the server to be built with the jieba feature"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// 停用词配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopWords {
+    /// 不过滤停用词
+    #[default]
+    None,
+    /// 内置停用词表 (`english` / `chinese`)
+    Builtin(String),
+    /// 自定义停用词列表
+    Custom(Vec<String>),
+}
+
+impl StopWords {
+    /// # Brief
+    /// 展开为停用词集合
+    ///
+    /// # Returns
+    /// 小写化后的停用词集合,未知的内置表名返回 InvalidArgument 错误
+    pub fn resolve(&self) -> StorageResult<HashSet<String>> {
+        let words: Vec<String> = match self {
+            Self::None => Vec::new(),
+            Self::Builtin(name) => match name.to_ascii_lowercase().as_str() {
+                "english" | "en" => ENGLISH_STOPWORDS.iter().map(|w| w.to_string()).collect(),
+                "chinese" | "zh" => CHINESE_STOPWORDS.iter().map(|w| w.to_string()).collect(),
+                _ => {
+                    return Err(StorageError::InvalidArgument(format!(
+                        "Unknown stopword list '{}'",
+                        name
+                    )))
+                }
+            },
+            Self::Custom(words) => words.clone(),
+        };
+        Ok(words.into_iter().map(|w| w.to_lowercase()).collect())
+    }
+}
+
+/// # Brief
+/// 根据语言名称获取词干提取算法
+fn stem_algorithm(language: &str) -> Option<Algorithm> {
+    let algorithm = match language.to_ascii_lowercase().as_str() {
+        "english" | "en" => Algorithm::English,
+        "french" | "fr" => Algorithm::French,
+        "german" | "de" => Algorithm::German,
+        "spanish" | "es" => Algorithm::Spanish,
+        "italian" | "it" => Algorithm::Italian,
+        "portuguese" | "pt" => Algorithm::Portuguese,
+        "russian" | "ru" => Algorithm::Russian,
+        "dutch" | "nl" => Algorithm::Dutch,
+        "swedish" | "sv" => Algorithm::Swedish,
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+/// 文本分析器
+///
+/// 分词 -> 小写化 -> 长度过滤 -> 停用词过滤 -> 词干提取。
+/// 被过滤掉的词项不占用位置,因此短语查询会跳过停用词。
+pub struct TextAnalyzer {
+    tokenizer: Box<dyn Tokenizer>,
+    stopwords: HashSet<String>,
+    stemmer: Option<Stemmer>,
+    min_token_length: usize,
+    max_token_length: usize,
+}
+
+impl TextAnalyzer {
+    /// # Brief
+    /// 创建文本分析器
+    ///
+    /// # Arguments
+    /// * `tokenizer` - 分词器
+    /// * `stopwords` - 停用词配置
+    /// * `stemmer` - 词干提取语言(如 `english`),None 表示不提取词干
+    /// * `min_token_length` / `max_token_length` - 词项字节长度范围
+    pub fn new(
+        tokenizer: Box<dyn Tokenizer>,
+        stopwords: &StopWords,
+        stemmer: Option<&str>,
+        min_token_length: usize,
+        max_token_length: usize,
+    ) -> StorageResult<Self> {
+        let stemmer = stemmer
+            .map(|language| {
+                stem_algorithm(language).map(Stemmer::create).ok_or_else(|| {
+                    StorageError::InvalidArgument(format!("Unsupported stemmer language '{}'", language))
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            tokenizer,
+            stopwords: stopwords.resolve()?,
+            stemmer,
+            min_token_length,
+            max_token_length,
+        })
+    }
+
+    /// # Brief
+    /// 分词器名称
+    pub fn tokenizer_name(&self) -> &'static str {
+        self.tokenizer.name()
+    }
+
+    /// # Brief
+    /// 分析文本,返回最终写入倒排索引的词项
+    pub fn analyze(&self, text: &str) -> Vec<String> {
+        self.tokenizer
+            .tokenize(text)
+            .into_iter()
+            .map(|token| token.to_lowercase())
+            .filter(|token| {
+                token.len() >= self.min_token_length
+                    && token.len() <= self.max_token_length
+                    && !self.stopwords.contains(token)
+            })
+            .map(|token| match &self.stemmer {
+                Some(stemmer) => stemmer.stem(&token).into_owned(),
+                None => token,
+            })
+            .collect()
+    }
+}
+
+/// Unicode 单词边界分词器
+///
+/// 按 UAX #29 切分,标点和空白被丢弃;中日韩文字按单字切分。
+pub struct UnicodeTokenizer;
+
+impl Tokenizer for UnicodeTokenizer {
+    fn name(&self) -> &'static str {
+        "unicode"
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        text.unicode_words().map(str::to_string).collect()
+    }
+}
+
+/// 简单分词器(空格分词)
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn name(&self) -> &'static str {
+        "simple"
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+}
+
+/// 中文 N-gram 分词器
+///
+/// 输出所有 Bi-gram,随后输出所有单字。
+pub struct NGramTokenizer;
+
+impl Tokenizer for NGramTokenizer {
+    fn name(&self) -> &'static str {
+        "ngram"
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut tokens = Vec::new();
+
+        // Bi-gram (2-gram)
+        for window in chars.windows(2) {
+            if window.iter().all(|c| c.is_alphanumeric() || *c > '\u{4E00}') {
+                tokens.push(window.iter().collect());
+            }
+        }
+
+        // 单字符
+        for ch in chars {
+            if ch.is_alphanumeric() || ch > '\u{4E00}' {
+                tokens.push(ch.to_string());
+            }
+        }
+
+        tokens
+    }
+}
+
+/// 混合分词器(中英文混合)
+///
+/// 英文和数字按连续字符成词,中文输出单字及相邻两字的 Bi-gram。
+pub struct MixedTokenizer;
+
+impl Tokenizer for MixedTokenizer {
+    fn name(&self) -> &'static str {
+        "mixed"
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut current_word = String::new();
+        let mut is_chinese_mode = false;
+
+        for ch in text.chars() {
+            let is_chinese = ch > '\u{4E00}' && ch < '\u{9FFF}';
+
+            if is_chinese {
+                // 保存当前英文词
+                if !current_word.is_empty() && !is_chinese_mode {
+                    tokens.push(std::mem::take(&mut current_word));
+                }
+
+                // 中文字符
+                if !current_word.is_empty() {
+                    // Bi-gram
+                    tokens.push(format!("{}{}", current_word, ch));
+                }
+                tokens.push(ch.to_string());
+                current_word = ch.to_string();
+                is_chinese_mode = true;
+            } else if ch.is_alphanumeric() {
+                if is_chinese_mode {
+                    current_word.clear();
+                    is_chinese_mode = false;
+                }
+                current_word.push(ch);
+            } else {
+                // 分隔符
+                if !current_word.is_empty() {
+                    tokens.push(std::mem::take(&mut current_word));
+                }
+                is_chinese_mode = false;
+            }
+        }
+
+        if !current_word.is_empty() {
+            tokens.push(current_word);
+        }
+
+        tokens
+    }
+}
+
+/// jieba 中文分词器
+///
+/// 使用搜索引擎模式 (`cut_for_search`),长词会额外切出其中的短词以提高召回。
+/// 词典在第一次使用时加载,所有索引共享同一份。
+#[cfg(feature = "jieba")]
+pub struct JiebaTokenizer;
+
+#[cfg(feature = "jieba")]
+impl Tokenizer for JiebaTokenizer {
+    fn name(&self) -> &'static str {
+        "jieba"
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        static JIEBA: std::sync::OnceLock<jieba_rs::Jieba> = std::sync::OnceLock::new();
+
+        JIEBA
+            .get_or_init(jieba_rs::Jieba::new)
+            .cut_for_search(text, true)
+            .into_iter()
+            .filter(|word| word.chars().any(char::is_alphanumeric))
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(tokenizer: TokenizerType, stopwords: StopWords, stemmer: Option<&str>) -> TextAnalyzer {
+        TextAnalyzer::new(tokenizer.build().unwrap(), &stopwords, stemmer, 1, 100).unwrap()
+    }
+
+    #[test]
+    fn test_unicode_tokenizer_drops_punctuation() {
+        let analyzer = build(TokenizerType::Unicode, StopWords::None, None);
+        assert_eq!(
+            analyzer.analyze("Hello, World! MikuDB's engine"),
+            vec!["hello", "world", "mikudb's", "engine"]
+        );
+        assert_eq!(analyzer.analyze("数据库"), vec!["数", "据", "库"]);
+    }
+
+    #[test]
+    fn test_stopwords_and_stemming() {
+        let analyzer = build(
+            TokenizerType::Unicode,
+            StopWords::Builtin("english".to_string()),
+            Some("english"),
+        );
+        assert_eq!(
+            analyzer.analyze("The databases are running on the servers"),
+            vec!["databas", "run", "server"]
+        );
+
+        let analyzer = build(TokenizerType::Simple, StopWords::Custom(vec!["Rust".to_string()]), None);
+        assert_eq!(analyzer.analyze("rust database"), vec!["database"]);
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        assert_eq!(TokenizerType::from_name("JIEBA"), Some(TokenizerType::Jieba));
+        assert_eq!(TokenizerType::from_name("snowball"), None);
+
+        let tokenizer = TokenizerType::Unicode.build().unwrap();
+        assert!(TextAnalyzer::new(tokenizer, &StopWords::None, Some("klingon"), 1, 100).is_err());
+        assert!(StopWords::Builtin("latin".to_string()).resolve().is_err());
+    }
+
+    #[cfg(feature = "jieba")]
+    #[test]
+    fn test_jieba_tokenizer() {
+        let analyzer = build(TokenizerType::Jieba, StopWords::Builtin("chinese".to_string()), None);
+        let tokens = analyzer.analyze("我们在开发分布式数据库");
+        assert!(tokens.contains(&"数据库".to_string()));
+        assert!(!tokens.contains(&"我们".to_string()));
+    }
+}
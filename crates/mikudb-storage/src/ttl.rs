@@ -0,0 +1,191 @@
+//! TTL 后台清理模块
+//!
+//! `IndexEngine::cleanup_expired_ttl` 找出 TTL 索引中已过期的索引项,
+//! 后台清理器按 `StorageOptions::ttl_sweep_interval` 周期调用它:
+//! - 删除过期索引项指向的文档,并从集合上的其他索引中移除该文档
+//! - 文档已被删除时只回收残留的索引项
+//! - 累计的轮数、删除文档数和回收索引项数通过 `TtlSweeper::stats` 获取,供指标接口使用
+
+use crate::engine::StorageEngine;
+use crate::index::TtlCleanup;
+use crate::StorageResult;
+use parking_lot::{Condvar, Mutex, RwLock};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// TTL 清理累计统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TtlSweepStats {
+    /// 已完成的清理轮数
+    pub runs: u64,
+    /// 失败的清理轮数
+    pub failures: u64,
+    /// 累计删除的文档数
+    pub deleted_documents: u64,
+    /// 累计回收的索引项数
+    pub removed_entries: u64,
+    /// 上一轮完成时间(毫秒时间戳)
+    pub last_finished_at: Option<i64>,
+    /// 上一轮删除的文档数
+    pub last_deleted_documents: u64,
+}
+
+/// TTL 后台清理器
+pub struct TtlSweeper {
+    engine: Arc<StorageEngine>,
+    interval: Duration,
+    stats: RwLock<TtlSweepStats>,
+    stop: AtomicBool,
+    /// 用于中断间隔等待
+    wakeup: (Mutex<()>, Condvar),
+}
+
+impl TtlSweeper {
+    /// # Brief
+    /// 创建清理器,间隔取自存储引擎的 `StorageOptions::ttl_sweep_interval`
+    ///
+    /// # Arguments
+    /// * `engine` - 存储引擎
+    pub fn new(engine: Arc<StorageEngine>) -> Self {
+        let interval = engine.options().ttl_sweep_interval;
+        Self {
+            engine,
+            interval,
+            stats: RwLock::new(TtlSweepStats::default()),
+            stop: AtomicBool::new(false),
+            wakeup: (Mutex::new(()), Condvar::new()),
+        }
+    }
+
+    /// # Brief
+    /// 获取累计统计
+    pub fn stats(&self) -> TtlSweepStats {
+        self.stats.read().clone()
+    }
+
+    /// # Brief
+    /// 执行一轮清理并记录统计
+    ///
+    /// # Returns
+    /// 本轮删除的文档数和回收的索引项数
+    pub fn run_once(&self) -> StorageResult<TtlCleanup> {
+        let engine = &self.engine;
+        let result = engine.indexes().cleanup_expired_ttl(|name| engine.get_collection(name));
+
+        let mut stats = self.stats.write();
+        match &result {
+            Ok(cleanup) => {
+                stats.runs += 1;
+                stats.deleted_documents += cleanup.deleted_documents;
+                stats.removed_entries += cleanup.removed_entries;
+                stats.last_deleted_documents = cleanup.deleted_documents;
+                stats.last_finished_at = Some(chrono::Utc::now().timestamp_millis());
+            }
+            Err(_) => stats.failures += 1,
+        }
+        result
+    }
+
+    /// # Brief
+    /// 启动后台清理线程
+    ///
+    /// 按 `interval` 周期执行,直到调用 `stop`。
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        std::thread::Builder::new()
+            .name("mikudb-ttl-sweeper".to_string())
+            .spawn(move || {
+                info!("TTL sweeper started (interval {:?})", self.interval);
+                while !self.stop.load(Ordering::SeqCst) {
+                    {
+                        let mut guard = self.wakeup.0.lock();
+                        if !self.stop.load(Ordering::SeqCst) {
+                            self.wakeup.1.wait_for(&mut guard, self.interval);
+                        }
+                    }
+                    if self.stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    match self.run_once() {
+                        Ok(cleanup) => debug!(
+                            "TTL sweep deleted {} document(s), removed {} entr(ies)",
+                            cleanup.deleted_documents, cleanup.removed_entries
+                        ),
+                        Err(e) => error!("TTL sweep failed: {}", e),
+                    }
+                }
+                info!("TTL sweeper stopped");
+            })
+            .expect("failed to spawn TTL sweeper thread")
+    }
+
+    /// # Brief
+    /// 停止后台清理
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+        let _guard = self.wakeup.0.lock();
+        self.wakeup.1.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::StorageOptions;
+    use crate::index::{IndexDefinition, IndexField, IndexOrder, IndexType, KeyEncoding, OversizedKeyPolicy};
+    use mikudb_boml::{BomlValue, Document};
+    use tempfile::tempdir;
+
+    fn index(name: &str, field: &str, ttl_seconds: Option<u64>) -> IndexDefinition {
+        IndexDefinition {
+            name: name.to_string(),
+            collection: "sessions".to_string(),
+            fields: vec![IndexField {
+                path: field.to_string(),
+                order: IndexOrder::Ascending,
+            }],
+            index_type: IndexType::BTree,
+            unique: false,
+            sparse: false,
+            ttl_seconds,
+            key_encoding: KeyEncoding::Memcomparable,
+            max_key_size: None,
+            oversized_keys: OversizedKeyPolicy::Reject,
+        }
+    }
+
+    #[test]
+    fn test_sweep_deletes_documents_and_index_entries() {
+        let dir = tempdir().unwrap();
+        let engine = Arc::new(
+            StorageEngine::open(StorageOptions {
+                data_dir: dir.path().to_path_buf(),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let sessions = engine.create_collection("sessions").unwrap();
+        let indexes = engine.indexes();
+        indexes.create_index(index("sessions_ttl", "token", Some(0))).unwrap();
+        indexes.create_index(index("sessions_user", "user", None)).unwrap();
+
+        let mut doc = Document::new();
+        doc.insert("token", "abc");
+        doc.insert("user", "miku");
+        sessions.insert(&mut doc).unwrap();
+        indexes.index_document("sessions", &doc).unwrap();
+
+        let sweeper = TtlSweeper::new(engine.clone());
+        let cleanup = sweeper.run_once().unwrap();
+        assert_eq!(cleanup, TtlCleanup { deleted_documents: 1, removed_entries: 1 });
+        assert_eq!(sessions.count().unwrap(), 0);
+        assert!(indexes.lookup("sessions_user", &[BomlValue::from("miku")]).unwrap().is_empty());
+
+        assert_eq!(sweeper.run_once().unwrap(), TtlCleanup::default());
+        let stats = sweeper.stats();
+        assert_eq!((stats.runs, stats.deleted_documents, stats.last_deleted_documents), (2, 1, 0));
+    }
+}
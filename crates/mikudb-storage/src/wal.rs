@@ -4,12 +4,21 @@
 //! - **原子性保证**: 所有操作先写入 WAL,再应用到数据库
 //! - **崩溃恢复**: 通过重放 WAL 记录恢复未提交的事务
 //! - **校验和保护**: 使用 xxHash3 校验和,防止数据损坏
-//! - **文件轮转**: 超过大小限制时自动轮转 WAL 文件
+//! - **组提交**: 并发的提交合并为一次写入和一次 fsync,再按 LSN 顺序应用到 RocksDB
+//! - **落盘策略**: `WalSyncPolicy` 支持每次提交同步、按间隔同步和不同步
+//! - **检查点**: 超过大小限制时同步 RocksDB 日志并截断 WAL,已应用的 LSN 记录在元数据中,重放时跳过
+//! - **尾部修复**: 打开时截掉崩溃留下的不完整记录,之后的追加从有效位置继续
+//! - **段归档**: 配置归档目录时,检查点截断前把 WAL 文件保留为 `<起始 LSN>-<结束 LSN>.wal` 段,供增量备份导出
+//!
+//! # WAL 文件格式
+//!
+//! 文件头为魔数 "MWAL"、版本号 (1 字节) 和文件中第一条记录的 LSN (8 字节,版本 2 起),
+//! 之后每条记录以 4 字节长度前缀开头。
 //!
 //! # WAL 记录格式
 //!
 //! 每条记录包含:
-//! - 记录类型 (1 字节): Insert/Update/Delete/BeginTx/CommitTx/AbortTx/Checkpoint
+//! - 记录类型 (1 字节): Insert/Update/Delete/Merge/DeleteRange/BeginTx/CommitTx/AbortTx/Checkpoint
 //! - 事务 ID (8 字节)
 //! - 集合名长度 (2 字节) + 集合名
 //! - 键长度 (4 字节) + 键数据
@@ -22,20 +31,31 @@
 //! - 支持 Direct I/O 写入,减少内存拷贝
 
 use crate::{StorageError, StorageResult};
+use crate::engine::METADATA_CF;
 use bytes::{BufMut, BytesMut};
-use parking_lot::Mutex;
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use rocksdb::{WriteBatch, WriteOptions, DB};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 use xxhash_rust::xxh3::xxh3_64;
 
 /// WAL 魔数字节 "MWAL"
 const WAL_MAGIC: [u8; 4] = [0x4D, 0x57, 0x41, 0x4C];
 /// WAL 文件格式版本号
-const WAL_VERSION: u8 = 1;
+const WAL_VERSION: u8 = 2;
+/// 文件头大小 (13 字节: magic(4) + version(1) + base_lsn(8))
+const HEADER_SIZE: u64 = 13;
+/// 元数据列族中记录已应用到数据库的 WAL LSN 上界的键
+pub(crate) const WAL_APPLIED_KEY: &[u8] = b"wal:applied_lsn";
+/// 归档段文件扩展名
+const SEGMENT_EXTENSION: &str = "wal";
 /// 记录头大小 (17 字节: type(1) + tx_id(8) + collection_len(2) + key_len(4) + value_len(4) - 不包括变长数据)
 const RECORD_HEADER_SIZE: usize = 17;
 
@@ -51,6 +71,10 @@ pub enum RecordType {
     Update = 2,
     /// 删除操作
     Delete = 3,
+    /// 合并操作 (值为合并操作数)
+    Merge = 4,
+    /// 范围删除 (键为起始键,值为结束键,不含)
+    DeleteRange = 5,
     /// 事务开始
     BeginTx = 10,
     /// 事务提交
@@ -75,6 +99,8 @@ impl RecordType {
             1 => Some(Self::Insert),
             2 => Some(Self::Update),
             3 => Some(Self::Delete),
+            4 => Some(Self::Merge),
+            5 => Some(Self::DeleteRange),
             10 => Some(Self::BeginTx),
             11 => Some(Self::CommitTx),
             12 => Some(Self::AbortTx),
@@ -155,6 +181,42 @@ impl WalRecord {
         }
     }
 
+    /// # Brief
+    /// 创建合并记录
+    ///
+    /// # Arguments
+    /// * `tx_id` - 事务 ID
+    /// * `collection` - 集合名
+    /// * `key` - 文档键
+    /// * `operand` - 合并操作数
+    pub fn new_merge(tx_id: u64, collection: &str, key: Vec<u8>, operand: Vec<u8>) -> Self {
+        Self {
+            record_type: RecordType::Merge,
+            tx_id,
+            collection: collection.to_string(),
+            key,
+            value: operand,
+        }
+    }
+
+    /// # Brief
+    /// 创建范围删除记录
+    ///
+    /// # Arguments
+    /// * `tx_id` - 事务 ID
+    /// * `collection` - 集合名
+    /// * `start` - 起始键(包含)
+    /// * `end` - 结束键(不含)
+    pub fn new_delete_range(tx_id: u64, collection: &str, start: Vec<u8>, end: Vec<u8>) -> Self {
+        Self {
+            record_type: RecordType::DeleteRange,
+            tx_id,
+            collection: collection.to_string(),
+            key: start,
+            value: end,
+        }
+    }
+
     pub fn new_begin_tx(tx_id: u64) -> Self {
         Self {
             record_type: RecordType::BeginTx,
@@ -227,6 +289,14 @@ impl WalRecord {
         buf.to_vec()
     }
 
+    /// # Brief
+    /// 编码记录并附加长度前缀,追加到 `buf`
+    fn encode_framed(&self, buf: &mut Vec<u8>) {
+        let encoded = self.encode();
+        buf.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&encoded);
+    }
+
     /// # Brief
     /// 从字节数组解码记录
     ///
@@ -295,38 +365,111 @@ impl WalRecord {
     }
 }
 
+/// WAL 落盘策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalSyncPolicy {
+    /// 每组提交都 fsync,提交返回时记录已经落盘
+    Always,
+    /// 距上次 fsync 超过间隔时由下一组提交 fsync,其余提交只写入操作系统缓存
+    Interval(Duration),
+    /// 只写入操作系统缓存,由操作系统决定落盘时机
+    Never,
+}
+
+impl Default for WalSyncPolicy {
+    fn default() -> Self {
+        Self::Interval(Duration::from_millis(100))
+    }
+}
+
+/// WAL 累计统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WalStats {
+    /// 提交次数
+    pub commits: u64,
+    /// 写入的提交组数,`commits / groups` 即平均每组合并的提交数
+    pub groups: u64,
+    /// 写入的记录数
+    pub records: u64,
+    /// 写入的字节数
+    pub bytes: u64,
+    /// fsync 次数
+    pub syncs: u64,
+    /// 检查点(截断 WAL)次数
+    pub checkpoints: u64,
+}
+
+/// 等待组提交的写入
+struct PendingCommit {
+    /// 已编码的记录
+    bytes: Vec<u8>,
+    records: u64,
+    /// 最后一条记录之后的 LSN
+    end_lsn: u64,
+    /// 记录落盘后应用到 RocksDB 的修改,只追加记录时为 None
+    batch: Option<WriteBatch>,
+}
+
+/// 组提交状态
+struct GroupState {
+    /// 等待组长写入的提交
+    queue: Vec<PendingCommit>,
+    /// 下一条记录的 LSN
+    next_lsn: u64,
+    /// 已写入文件并应用的 LSN 上界(不含)
+    written_lsn: u64,
+    /// 是否有线程正在作为组长写入
+    leader: bool,
+    last_sync: Instant,
+    /// 应用到 RocksDB 失败的提交(结束 LSN -> 错误),由提交者取走
+    errors: HashMap<u64, StorageError>,
+    /// WAL 文件写入失败后拒绝之后的提交
+    failed: Option<String>,
+    stats: WalStats,
+}
+
 /// 预写式日志 (Write-Ahead Log)
 ///
 /// 用于保证数据库的持久性和崩溃恢复能力。
+/// 并发的提交按到达顺序排队,由队首的线程(组长)一次写入整组记录、按策略 fsync,
+/// 再按 LSN 顺序把各提交的修改应用到 RocksDB,同一批写入中记录已应用的 LSN。
 pub struct WriteAheadLog {
     /// WAL 文件路径
     path: PathBuf,
     /// 缓冲写入器 (线程安全)
     writer: Mutex<BufWriter<File>>,
-    /// LSN (Log Sequence Number) 日志序列号
-    lsn: AtomicU64,
+    /// 组提交状态
+    state: Mutex<GroupState>,
+    /// 一组写入完成时通知等待的提交者
+    group_done: Condvar,
     /// WAL 文件大小
     file_size: AtomicU64,
-    /// 文件转转阈值 (64MB)
+    /// 超过该大小时在组提交后执行检查点 (64MB)
     max_file_size: u64,
-    /// 是否每次写入后同步到磁盘
-    sync_on_write: bool,
+    /// 落盘策略
+    sync_policy: WalSyncPolicy,
+    /// 提交的修改应用到的数据库,未绑定时只能追加记录
+    db: Option<Arc<DB>>,
+    /// 当前文件中第一条记录的 LSN
+    base_lsn: AtomicU64,
+    /// 截断前保留 WAL 段的目录,未设置时截断直接丢弃记录
+    archive_dir: Option<PathBuf>,
 }
 
 impl WriteAheadLog {
     /// # Brief
     /// 打开或创建 WAL 文件
     ///
-    /// 如果文件不存在,创建新文件并写入魔数字节和版本号。
-    /// 如果文件存在,恢复 LSN 以便继续追加写入。
+    /// 如果文件不存在,创建新文件并写入文件头。
+    /// 如果文件存在,恢复 LSN 以便继续追加写入,并截掉末尾不完整或校验失败的记录。
     ///
     /// # Arguments
     /// * `path` - WAL 文件路径
-    /// * `sync_on_write` - 是否每次写入后同步到磁盘
+    /// * `sync_policy` - 落盘策略
     ///
     /// # Returns
     /// WriteAheadLog 实例或错误
-    pub fn open(path: impl AsRef<Path>, sync_on_write: bool) -> StorageResult<Self> {
+    pub fn open(path: impl AsRef<Path>, sync_policy: WalSyncPolicy) -> StorageResult<Self> {
         let path = path.as_ref().to_path_buf();
 
         // 创建父目录
@@ -334,25 +477,23 @@ impl WriteAheadLog {
             std::fs::create_dir_all(parent)?;
         }
 
-        // 打开或创建文件
-        let file = OpenOptions::new()
-            .create(true)
-            .read(true)
-            .write(true)
-            .open(&path)?;
-
-        let metadata = file.metadata()?;
-        let file_size = metadata.len();
-
-        // 如果文件为空,写入魔数字节和版本号;否则恢复 LSN
-        let lsn = if file_size > 0 {
-            Self::recover_lsn(&path)?
+        let file_size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+        // 如果文件为空,写入文件头;否则恢复 LSN
+        let (base, lsn, file_size) = if file_size > 0 {
+            let (base, lsn, valid_end) = Self::recover_lsn(&path)?;
+            if valid_end < file_size {
+                warn!(
+                    "Discarding {} byte(s) of incomplete WAL records at the end of {:?}",
+                    file_size - valid_end,
+                    path
+                );
+                OpenOptions::new().write(true).open(&path)?.set_len(valid_end)?;
+            }
+            (base, lsn, valid_end)
         } else {
-            let mut writer = BufWriter::new(file);
-            writer.write_all(&WAL_MAGIC)?;  // 写入 "MWAL"
-            writer.write_all(&[WAL_VERSION])?;  // 写入版本号 1
-            writer.flush()?;
-            0
+            Self::write_new_file(&path, 0)?;
+            (0, 0, HEADER_SIZE)
         };
 
         // 以追加模式重新打开文件
@@ -363,63 +504,160 @@ impl WriteAheadLog {
         Ok(Self {
             path,
             writer: Mutex::new(BufWriter::new(file)),
-            lsn: AtomicU64::new(lsn),
+            state: Mutex::new(GroupState {
+                queue: Vec::new(),
+                next_lsn: lsn,
+                written_lsn: lsn,
+                leader: false,
+                last_sync: Instant::now(),
+                errors: HashMap::new(),
+                failed: None,
+                stats: WalStats::default(),
+            }),
+            group_done: Condvar::new(),
             file_size: AtomicU64::new(file_size),
             max_file_size: 64 * 1024 * 1024,
-            sync_on_write,
+            sync_policy,
+            db: None,
+            base_lsn: AtomicU64::new(base),
+            archive_dir: None,
         })
     }
 
     /// # Brief
-    /// 从 WAL 文件恢复 LSN
+    /// 绑定提交的修改应用到的数据库
+    pub(crate) fn with_db(mut self, db: Arc<DB>) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// # Brief
+    /// 设置 WAL 段归档目录,目录不存在时创建
+    pub(crate) fn with_archive_dir(mut self, dir: PathBuf) -> StorageResult<Self> {
+        std::fs::create_dir_all(&dir)?;
+        self.archive_dir = Some(dir);
+        Ok(self)
+    }
+
+    /// # Brief
+    /// 原子地创建只有文件头的 WAL 文件
     ///
-    /// 扫描整个 WAL 文件,计算记录数量以恢复 LSN。
+    /// 先写入临时文件并同步,再重命名覆盖目标文件,崩溃时目标文件要么是旧内容要么是新文件头。
     ///
     /// # Arguments
     /// * `path` - WAL 文件路径
+    /// * `base_lsn` - 文件中第一条记录的 LSN
+    fn write_new_file(path: &Path, base_lsn: u64) -> StorageResult<()> {
+        let tmp_path = path.with_extension("wal.tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&WAL_MAGIC)?;
+        file.write_all(&[WAL_VERSION])?;
+        file.write_all(&base_lsn.to_le_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// # Brief
+    /// 读取并校验文件头
     ///
     /// # Returns
-    /// 恢复的 LSN 值
-    fn recover_lsn(path: &Path) -> StorageResult<u64> {
-        let mut file = File::open(path)?;
+    /// (文件中第一条记录的 LSN, 文件头长度);版本 1 的文件头没有 LSN,从 0 开始
+    fn read_header(file: &mut File) -> StorageResult<(u64, u64)> {
         let mut header = [0u8; 5];
         file.read_exact(&mut header)?;
 
         // 验证魔数字节
-        if &header[0..4] != WAL_MAGIC {
+        if header[0..4] != WAL_MAGIC {
             return Err(StorageError::Corruption("Invalid WAL magic".to_string()));
         }
 
-        let mut lsn = 0u64;
-        let mut pos = 5u64;  // 跳过魔数字节和版本号
+        match header[4] {
+            1 => Ok((0, 5)),
+            WAL_VERSION => {
+                let mut base = [0u8; 8];
+                file.read_exact(&mut base)?;
+                Ok((u64::from_le_bytes(base), HEADER_SIZE))
+            }
+            version => Err(StorageError::Corruption(format!("Unsupported WAL version {}", version))),
+        }
+    }
+
+    /// # Brief
+    /// 从 WAL 文件恢复 LSN
+    ///
+    /// 扫描并校验所有记录,遇到不完整或校验失败的记录时停止。
+    ///
+    /// # Arguments
+    /// * `path` - WAL 文件路径
+    ///
+    /// # Returns
+    /// (文件中第一条记录的 LSN, 下一条记录的 LSN, 最后一条有效记录的结束位置)
+    fn recover_lsn(path: &Path) -> StorageResult<(u64, u64, u64)> {
+        let mut file = File::open(path)?;
+        let (base, header_len) = Self::read_header(&mut file)?;
+        let mut count = 0u64;
+        let valid_end = Self::scan_records(&mut file, header_len, |_| {
+            count += 1;
+            Ok(())
+        })?;
+        Ok((base, base + count, valid_end))
+    }
+
+    /// # Brief
+    /// 从文件头之后依次解码记录
+    ///
+    /// # Arguments
+    /// * `file` - 已读过文件头的 WAL 文件
+    /// * `start` - 文件头长度
+    /// * `callback` - 处理每条记录的回调函数
+    ///
+    /// # Returns
+    /// 最后一条有效记录的结束位置
+    fn scan_records<F>(file: &mut File, start: u64, mut callback: F) -> StorageResult<u64>
+    where
+        F: FnMut(WalRecord) -> StorageResult<()>,
+    {
         let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut pos = start;
 
-        // 扫描所有记录
         while pos < file_size {
             let mut len_buf = [0u8; 4];
-            if file.read_exact(&mut len_buf).is_err() {
-                break;  // 文件末尾
+            if reader.read_exact(&mut len_buf).is_err() {
+                warn!("Incomplete WAL record header at position {}", pos);
+                break;
             }
 
             let record_len = u32::from_le_bytes(len_buf) as usize;
-            // 验证记录长度
             if record_len == 0 || pos + 4 + record_len as u64 > file_size {
-                break;  // 无效记录
+                warn!("Incomplete WAL record at position {}", pos);
+                break;
+            }
+
+            // 读取记录内容
+            let mut record_buf = vec![0u8; record_len];
+            reader.read_exact(&mut record_buf)?;
+
+            // 解码并调用回调
+            match WalRecord::decode(&record_buf) {
+                Ok(record) => callback(record)?,
+                Err(e) => {
+                    warn!("Failed to decode WAL record at {}: {}", pos, e);
+                    break; // 遇到损坏记录,停止
+                }
             }
 
-            // 跳过记录内容,计数
-            file.seek(SeekFrom::Current(record_len as i64))?;
             pos += 4 + record_len as u64;
-            lsn += 1;
         }
 
-        Ok(lsn)
+        Ok(pos)
     }
 
     /// # Brief
     /// 追加记录到 WAL
     ///
-    /// 将记录编码并写入 WAL 文件,返回分配的 LSN。
+    /// 记录与并发的提交一起组提交,按落盘策略同步,不应用到数据库。
     ///
     /// # Arguments
     /// * `record` - 要追加的 WAL 记录
@@ -427,29 +665,249 @@ impl WriteAheadLog {
     /// # Returns
     /// 分配的 LSN
     pub fn append(&self, record: &WalRecord) -> StorageResult<u64> {
-        let encoded = record.encode();
-        // 原子地分配 LSN
-        let lsn = self.lsn.fetch_add(1, Ordering::SeqCst);
+        let mut bytes = Vec::new();
+        record.encode_framed(&mut bytes);
+        self.submit(bytes, 1, None)
+    }
 
-        let mut writer = self.writer.lock();
+    /// # Brief
+    /// 提交一组修改
+    ///
+    /// 记录追加一条提交记录后作为一个事务写入 WAL,落盘后把 `batch` 应用到数据库。
+    /// 并发的提交合并为一组写入和一次 fsync;返回时 `batch` 已经应用。
+    ///
+    /// # Arguments
+    /// * `records` - 描述修改的数据记录,事务 ID 由 WAL 分配
+    /// * `batch` - 对应的 RocksDB 修改
+    ///
+    /// # Returns
+    /// 分配的事务 ID;WAL 未绑定数据库或写入失败时返回错误
+    pub fn commit(&self, mut records: Vec<WalRecord>, batch: WriteBatch) -> StorageResult<u64> {
+        if self.db.is_none() {
+            return Err(StorageError::Internal("WAL is not attached to a database".to_string()));
+        }
+        records.push(WalRecord::new_commit_tx(0));
+        let count = records.len() as u64;
+
+        let mut state = self.state.lock();
+        // 事务 ID 取第一条记录的 LSN,在同一文件序列中唯一
+        let tx_id = state.next_lsn;
+        let mut bytes = Vec::new();
+        for record in &mut records {
+            record.tx_id = tx_id;
+            record.encode_framed(&mut bytes);
+        }
+        self.enqueue(&mut state, bytes, count, Some(batch))?;
+        Ok(tx_id)
+    }
+
+    fn submit(&self, bytes: Vec<u8>, records: u64, batch: Option<WriteBatch>) -> StorageResult<u64> {
+        let mut state = self.state.lock();
+        let lsn = state.next_lsn;
+        self.enqueue(&mut state, bytes, records, batch)?;
+        Ok(lsn)
+    }
 
-        // 写入记录长度 (4 字节,小端)
-        let len_bytes = (encoded.len() as u32).to_le_bytes();
-        writer.write_all(&len_bytes)?;
-        // 写入记录内容
-        writer.write_all(&encoded)?;
+    /// # Brief
+    /// 把提交加入队列并等待它被写入
+    ///
+    /// 没有组长时当前线程成为组长,取走整个队列写入;否则等待组长完成后再检查。
+    fn enqueue(
+        &self,
+        state: &mut MutexGuard<'_, GroupState>,
+        bytes: Vec<u8>,
+        records: u64,
+        batch: Option<WriteBatch>,
+    ) -> StorageResult<()> {
+        if let Some(reason) = &state.failed {
+            return Err(StorageError::Internal(format!("WAL unavailable: {}", reason)));
+        }
+        state.next_lsn += records;
+        let end_lsn = state.next_lsn;
+        state.queue.push(PendingCommit { bytes, records, end_lsn, batch });
+
+        loop {
+            if state.written_lsn >= end_lsn {
+                return match state.errors.remove(&end_lsn) {
+                    Some(e) => Err(e),
+                    None => Ok(()),
+                };
+            }
+            if let Some(reason) = &state.failed {
+                return Err(StorageError::Internal(format!("WAL unavailable: {}", reason)));
+            }
+            if state.leader {
+                self.group_done.wait(state);
+                continue;
+            }
 
-        // 如果配置了同步写入,刷新并同步到磁盘
-        if self.sync_on_write {
+            state.leader = true;
+            let group = std::mem::take(&mut state.queue);
+            let sync = match self.sync_policy {
+                WalSyncPolicy::Always => true,
+                WalSyncPolicy::Interval(interval) => state.last_sync.elapsed() >= interval,
+                WalSyncPolicy::Never => false,
+            };
+            let commits = group.iter().filter(|c| c.batch.is_some()).count() as u64;
+            let records: u64 = group.iter().map(|c| c.records).sum();
+            let bytes: u64 = group.iter().map(|c| c.bytes.len() as u64).sum();
+
+            let outcome = MutexGuard::unlocked(state, || self.write_group(group, sync));
+            state.leader = false;
+            match outcome {
+                Ok((upto, errors)) => {
+                    state.written_lsn = upto;
+                    state.errors.extend(errors);
+                    if sync {
+                        state.last_sync = Instant::now();
+                        state.stats.syncs += 1;
+                    }
+                    state.stats.commits += commits;
+                    state.stats.groups += 1;
+                    state.stats.records += records;
+                    state.stats.bytes += bytes;
+                }
+                Err(e) => {
+                    error!("WAL write failed, rejecting further commits: {}", e);
+                    state.failed = Some(e.to_string());
+                }
+            }
+            self.group_done.notify_all();
+        }
+    }
+
+    /// # Brief
+    /// 组长写入一组提交
+    ///
+    /// 写入并按需同步 WAL,再按 LSN 顺序应用各提交的修改;WAL 超过大小限制时执行检查点。
+    ///
+    /// # Returns
+    /// (写入后的 LSN 上界, 应用失败的提交);WAL 文件写入失败时返回错误
+    fn write_group(&self, group: Vec<PendingCommit>, sync: bool) -> StorageResult<(u64, Vec<(u64, StorageError)>)> {
+        let upto = group.last().map_or(0, |c| c.end_lsn);
+        {
+            let mut writer = self.writer.lock();
+            for commit in &group {
+                writer.write_all(&commit.bytes)?;
+                self.file_size.fetch_add(commit.bytes.len() as u64, Ordering::Relaxed);
+            }
             writer.flush()?;
-            writer.get_ref().sync_data()?;
+            if sync {
+                writer.get_ref().sync_data()?;
+            }
         }
 
-        // 更新文件大小
-        self.file_size
-            .fetch_add(4 + encoded.len() as u64, Ordering::Relaxed);
+        let mut errors = Vec::new();
+        if let Some(db) = &self.db {
+            let metadata_cf = db.cf_handle(METADATA_CF);
+            let mut write_opts = WriteOptions::default();
+            write_opts.set_sync(false);
+            for commit in group {
+                let Some(mut batch) = commit.batch else {
+                    continue;
+                };
+                if let Some(cf) = &metadata_cf {
+                    batch.put_cf(cf, WAL_APPLIED_KEY, commit.end_lsn.to_le_bytes());
+                }
+                if let Err(e) = db.write_opt(batch, &write_opts) {
+                    errors.push((commit.end_lsn, e.into()));
+                }
+            }
 
-        Ok(lsn)
+            if self.should_rotate() {
+                match self.checkpoint_at(db, upto) {
+                    Ok(()) => self.state.lock().stats.checkpoints += 1,
+                    Err(e) => warn!("WAL checkpoint failed: {}", e),
+                }
+            }
+        }
+
+        Ok((upto, errors))
+    }
+
+    /// # Brief
+    /// 执行检查点
+    ///
+    /// 同步 RocksDB 自身的日志,使已应用的修改持久化,然后截断 WAL。
+    /// 调用方必须保证没有其他线程在写入 WAL。
+    ///
+    /// # Arguments
+    /// * `db` - 已应用修改的数据库
+    /// * `base_lsn` - 截断后下一条记录的 LSN
+    fn checkpoint_at(&self, db: &DB, base_lsn: u64) -> StorageResult<()> {
+        db.flush_wal(true)?;
+        self.truncate_at(base_lsn)
+    }
+
+    /// # Brief
+    /// 执行检查点并截断 WAL
+    ///
+    /// 等待进行中的组提交完成,期间新的提交排队等待。
+    pub fn checkpoint(&self) -> StorageResult<()> {
+        let db = self
+            .db
+            .as_ref()
+            .ok_or_else(|| StorageError::Internal("WAL is not attached to a database".to_string()))?;
+        let base = self.pause_writes();
+        let result = self.checkpoint_at(db, base);
+        self.resume_writes(result.is_ok());
+        result
+    }
+
+    /// # Brief
+    /// 在暂停写入期间执行 `f`
+    ///
+    /// 等待进行中的组提交完成,期间新的提交排队等待。
+    ///
+    /// # Arguments
+    /// * `f` - 参数为已写入并应用到数据库的 LSN 上界
+    pub(crate) fn paused<R>(&self, f: impl FnOnce(u64) -> R) -> R {
+        let base = self.pause_writes();
+        let result = f(base);
+        self.resume_writes(false);
+        result
+    }
+
+    /// # Brief
+    /// 把下一条记录的 LSN 推进到 `lsn`
+    ///
+    /// 只在 WAL 中没有记录时使用,例如从快照恢复后数据库已应用的 LSN 大于新建 WAL 的 LSN。
+    ///
+    /// # Arguments
+    /// * `lsn` - 下一条记录的 LSN,不大于当前 LSN 时不做任何事
+    pub(crate) fn advance_to(&self, lsn: u64) -> StorageResult<()> {
+        self.paused(|current| {
+            if current >= lsn {
+                return Ok(());
+            }
+            if !self.state.lock().queue.is_empty() || self.has_records() {
+                return Err(StorageError::Internal("Cannot advance a WAL that has records".to_string()));
+            }
+            self.truncate_at(lsn)?;
+            let mut state = self.state.lock();
+            state.next_lsn = lsn;
+            state.written_lsn = lsn;
+            Ok(())
+        })
+    }
+
+    /// 成为组长以阻止其他写入,返回下一条写入记录的 LSN
+    fn pause_writes(&self) -> u64 {
+        let mut state = self.state.lock();
+        while state.leader {
+            self.group_done.wait(&mut state);
+        }
+        state.leader = true;
+        state.written_lsn
+    }
+
+    fn resume_writes(&self, checkpointed: bool) {
+        let mut state = self.state.lock();
+        state.leader = false;
+        if checkpointed {
+            state.stats.checkpoints += 1;
+        }
+        self.group_done.notify_all();
     }
 
     /// # Brief
@@ -467,9 +925,21 @@ impl WriteAheadLog {
     /// 获取当前 LSN
     ///
     /// # Returns
-    /// 当前的日志序列号
+    /// 下一条记录将分配的日志序列号
     pub fn current_lsn(&self) -> u64 {
-        self.lsn.load(Ordering::SeqCst)
+        self.state.lock().next_lsn
+    }
+
+    /// # Brief
+    /// 获取累计统计
+    pub fn stats(&self) -> WalStats {
+        self.state.lock().stats.clone()
+    }
+
+    /// # Brief
+    /// 获取落盘策略
+    pub fn sync_policy(&self) -> WalSyncPolicy {
+        self.sync_policy
     }
 
     /// # Brief
@@ -481,6 +951,12 @@ impl WriteAheadLog {
         self.file_size.load(Ordering::Relaxed)
     }
 
+    /// # Brief
+    /// WAL 文件中是否有记录
+    pub fn has_records(&self) -> bool {
+        self.file_size() > HEADER_SIZE
+    }
+
     /// # Brief
     /// 判断是否应该转转 WAL 文件
     ///
@@ -501,6 +977,13 @@ impl WriteAheadLog {
     /// # Returns
     /// 归档文件路径
     pub fn rotate(&self) -> StorageResult<PathBuf> {
+        let base = self.pause_writes();
+        let result = self.rotate_at(base);
+        self.resume_writes(false);
+        result
+    }
+
+    fn rotate_at(&self, base_lsn: u64) -> StorageResult<PathBuf> {
         let mut writer = self.writer.lock();
         // 刷新并同步当前文件
         writer.flush()?;
@@ -513,26 +996,14 @@ impl WriteAheadLog {
             .as_millis();
 
         let archive_path = self.path.with_extension(format!("wal.{}", timestamp));
-        // 重命名当前 WAL 文件
-        std::fs::rename(&self.path, &archive_path)?;
-
-        // 创建新的 WAL 文件
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&self.path)?;
-
-        let mut new_writer = BufWriter::new(file);
-        new_writer.write_all(&WAL_MAGIC)?;
-        new_writer.write_all(&[WAL_VERSION])?;
-        new_writer.flush()?;
+        // 先复制再原子替换,任何时刻 WAL 路径上都有完整的文件
+        std::fs::copy(&self.path, &archive_path)?;
+        Self::write_new_file(&self.path, base_lsn)?;
 
         // 更新写入器
         *writer = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
-
-        // 重置文件大小 (5 字节 = 魔数字节 + 版本号)
-        self.file_size.store(5, Ordering::Relaxed);
+        self.file_size.store(HEADER_SIZE, Ordering::Relaxed);
+        self.base_lsn.store(base_lsn, Ordering::Relaxed);
 
         info!("WAL rotated to {:?}", archive_path);
         Ok(archive_path)
@@ -553,96 +1024,221 @@ impl WriteAheadLog {
     where
         F: FnMut(WalRecord) -> StorageResult<()>,
     {
-        let mut file = File::open(&self.path)?;
-        let mut header = [0u8; 5];
-        file.read_exact(&mut header)?;
+        self.replay_with_lsn(|_, record| callback(record))
+    }
 
-        // 验证魔数字节
-        if &header[0..4] != WAL_MAGIC {
-            return Err(StorageError::Corruption("Invalid WAL magic".to_string()));
-        }
+    /// # Brief
+    /// 重放 WAL 记录,回调同时得到每条记录的 LSN
+    ///
+    /// # Arguments
+    /// * `callback` - 处理每条记录的回调函数,参数为 (LSN, 记录)
+    ///
+    /// # Returns
+    /// 重放的记录数量
+    pub fn replay_with_lsn<F>(&self, callback: F) -> StorageResult<u64>
+    where
+        F: FnMut(u64, WalRecord) -> StorageResult<()>,
+    {
+        let count = Self::read_segment(&self.path, callback)?;
+        info!("Replayed {} WAL records", count);
+        Ok(count)
+    }
+
+    /// # Brief
+    /// 依次读取 WAL 文件或归档段中的记录
+    ///
+    /// # Arguments
+    /// * `path` - WAL 文件或段文件路径
+    /// * `callback` - 处理每条记录的回调函数,参数为 (LSN, 记录)
+    ///
+    /// # Returns
+    /// 读取的记录数量
+    pub(crate) fn read_segment<F>(path: &Path, mut callback: F) -> StorageResult<u64>
+    where
+        F: FnMut(u64, WalRecord) -> StorageResult<()>,
+    {
+        let mut file = File::open(path)?;
+        let (base, header_len) = Self::read_header(&mut file)?;
 
         let mut count = 0u64;
-        let file_size = file.metadata()?.len();
-        let mut pos = 5u64;
+        Self::scan_records(&mut file, header_len, |record| {
+            callback(base + count, record)?;
+            count += 1;
+            Ok(())
+        })?;
+        Ok(count)
+    }
 
-        // 扫描并重放所有记录
-        while pos < file_size {
-            let mut len_buf = [0u8; 4];
-            if file.read_exact(&mut len_buf).is_err() {
-                break;  // 文件末尾
+    /// # Brief
+    /// 导出 `since_lsn` 之后的 WAL 段
+    ///
+    /// 暂停写入期间把覆盖 `[since_lsn, 当前 LSN)` 的归档段和当前文件复制到 `dir`,
+    /// 段之间首尾相接;段中可能包含 `since_lsn` 之前的记录,应用时按已应用 LSN 跳过。
+    ///
+    /// # Arguments
+    /// * `since_lsn` - 起始 LSN,通常是快照或上一次增量备份的结束位置
+    /// * `dir` - 目标目录
+    ///
+    /// # Returns
+    /// (导出的结束 LSN, 按顺序排列的段文件名);所需的段已被丢弃时返回错误
+    pub(crate) fn export_since(&self, since_lsn: u64, dir: &Path) -> StorageResult<(u64, Vec<String>)> {
+        self.paused(|upto| {
+            if since_lsn > upto {
+                return Err(StorageError::InvalidArgument(format!(
+                    "LSN {} is beyond the end of the WAL ({})",
+                    since_lsn, upto
+                )));
             }
-
-            let record_len = u32::from_le_bytes(len_buf) as usize;
-            if record_len == 0 {
-                break;  // 无效记录
+            {
+                let mut writer = self.writer.lock();
+                writer.flush()?;
+                writer.get_ref().sync_data()?;
             }
 
-            // 读取记录内容
-            let mut record_buf = vec![0u8; record_len];
-            if file.read_exact(&mut record_buf).is_err() {
-                warn!("Incomplete WAL record at position {}", pos);
-                break;
+            let mut segments: Vec<(u64, u64, PathBuf)> = self
+                .archived_segments()?
+                .into_iter()
+                .filter(|(_, end, _)| *end > since_lsn)
+                .collect();
+            let base = self.base_lsn.load(Ordering::Relaxed);
+            if upto > base {
+                segments.push((base, upto, self.path.clone()));
             }
+            segments.sort();
 
-            // 解码并调用回调
-            match WalRecord::decode(&record_buf) {
-                Ok(record) => {
-                    callback(record)?;
-                    count += 1;
-                }
-                Err(e) => {
-                    warn!("Failed to decode WAL record at {}: {}", pos, e);
-                    break;  // 遇到损坏记录,停止重放
+            let mut covered = since_lsn;
+            for (first, end, _) in &segments {
+                if *first > covered {
+                    break;
                 }
+                covered = covered.max(*end);
+            }
+            if covered < upto {
+                return Err(StorageError::InvalidArgument(format!(
+                    "WAL records after LSN {} are no longer available, take a new snapshot",
+                    covered
+                )));
             }
 
-            pos += 4 + record_len as u64;
+            std::fs::create_dir_all(dir)?;
+            let mut names = Vec::with_capacity(segments.len());
+            for (first, end, path) in segments {
+                let name = segment_name(first, end);
+                std::fs::copy(&path, dir.join(&name))?;
+                names.push(name);
+            }
+            Ok((upto, names))
+        })
+    }
+
+    /// # Brief
+    /// 删除结束 LSN 不大于 `before_lsn` 的归档段
+    ///
+    /// 新的快照完成后,之前的段不再需要。
+    ///
+    /// # Returns
+    /// 删除的段数,未配置归档目录时为 0
+    pub fn prune_archive(&self, before_lsn: u64) -> StorageResult<usize> {
+        let mut removed = 0;
+        for (_, end, path) in self.archived_segments()? {
+            if end <= before_lsn {
+                std::fs::remove_file(path)?;
+                removed += 1;
+            }
         }
+        Ok(removed)
+    }
 
-        info!("Replayed {} WAL records", count);
-        Ok(count)
+    /// 归档目录中的段: (起始 LSN, 结束 LSN, 路径)
+    fn archived_segments(&self) -> StorageResult<Vec<(u64, u64, PathBuf)>> {
+        let Some(dir) = &self.archive_dir else {
+            return Ok(Vec::new());
+        };
+        let mut segments = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let Some((first, end)) = path.file_name().and_then(|n| n.to_str()).and_then(parse_segment_name) {
+                segments.push((first, end, path));
+            }
+        }
+        Ok(segments)
+    }
+
+    /// # Brief
+    /// 把当前文件保留为归档段
+    ///
+    /// 先同步再创建硬链接(跨文件系统时复制),随后截断替换 WAL 路径上的文件,段内容不受影响。
+    fn archive_current(&self, writer: &mut BufWriter<File>, end_lsn: u64) -> StorageResult<()> {
+        let Some(dir) = &self.archive_dir else {
+            return Ok(());
+        };
+        let base = self.base_lsn.load(Ordering::Relaxed);
+        if end_lsn <= base || !self.has_records() {
+            return Ok(());
+        }
+        writer.get_ref().sync_all()?;
+        let target = dir.join(segment_name(base, end_lsn));
+        if !target.exists() && std::fs::hard_link(&self.path, &target).is_err() {
+            std::fs::copy(&self.path, &target)?;
+        }
+        info!("WAL segment archived to {:?}", target);
+        Ok(())
     }
 
     /// # Brief
     /// 截断 WAL 文件
     ///
-    /// 清空 WAL 文件,仅保留魔数字节和版本号。
+    /// 清空 WAL 文件,仅保留文件头;LSN 继续递增,不会重新从 0 开始。
     /// 在成功执行 checkpoint 后调用。
     ///
     /// # Returns
     /// 成功或错误
     pub fn truncate(&self) -> StorageResult<()> {
+        let base = self.pause_writes();
+        let result = self.truncate_at(base);
+        self.resume_writes(false);
+        result
+    }
+
+    fn truncate_at(&self, base_lsn: u64) -> StorageResult<()> {
         let mut writer = self.writer.lock();
         writer.flush()?;
-        writer.get_ref().sync_all()?;
-        drop(writer);
+        self.archive_current(&mut writer, base_lsn)?;
 
-        // 截断文件并重新写入头部
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(&self.path)?;
-
-        let mut new_writer = BufWriter::new(file);
-        new_writer.write_all(&WAL_MAGIC)?;
-        new_writer.write_all(&[WAL_VERSION])?;
-        new_writer.flush()?;
+        // 原子地替换为只有文件头的新文件
+        Self::write_new_file(&self.path, base_lsn)?;
 
         // 更新写入器
-        let mut writer = self.writer.lock();
         *writer = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        self.file_size.store(HEADER_SIZE, Ordering::Relaxed);
+        self.base_lsn.store(base_lsn, Ordering::Relaxed);
 
-        // 重置文件大小和 LSN
-        self.file_size.store(5, Ordering::Relaxed);
-        self.lsn.store(0, Ordering::SeqCst);
-
-        info!("WAL truncated at {:?}", self.path);
+        info!("WAL truncated at {:?}, next LSN {}", self.path, base_lsn);
         Ok(())
     }
 }
 
+/// 归档段文件名: `<起始 LSN>-<结束 LSN>.wal`,LSN 补零到 20 位,按名称排序即按 LSN 排序
+fn segment_name(first: u64, end: u64) -> String {
+    format!("{:020}-{:020}.{}", first, end, SEGMENT_EXTENSION)
+}
+
+/// 解析归档段文件名,返回 (起始 LSN, 结束 LSN)
+fn parse_segment_name(name: &str) -> Option<(u64, u64)> {
+    let stem = name.strip_suffix(SEGMENT_EXTENSION)?.strip_suffix('.')?;
+    let (first, end) = stem.split_once('-')?;
+    Some((first.parse().ok()?, end.parse().ok()?))
+}
+
+impl Drop for WriteAheadLog {
+    fn drop(&mut self) {
+        // 间隔同步和不同步策略下,关闭时尽量把最后的记录落盘
+        if let Err(e) = self.sync() {
+            warn!("Failed to sync WAL on close: {}", e);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -653,7 +1249,7 @@ mod tests {
         let dir = tempdir().unwrap();
         let wal_path = dir.path().join("test.wal");
 
-        let wal = WriteAheadLog::open(&wal_path, true).unwrap();
+        let wal = WriteAheadLog::open(&wal_path, WalSyncPolicy::Always).unwrap();
 
         let record1 = WalRecord::new_insert(1, "test", vec![1, 2, 3], vec![4, 5, 6]);
         let record2 = WalRecord::new_update(1, "test", vec![1, 2, 3], vec![7, 8, 9]);
@@ -676,4 +1272,83 @@ mod tests {
         assert_eq!(records[1].record_type, RecordType::Update);
         assert_eq!(records[2].record_type, RecordType::Delete);
     }
+
+    #[test]
+    fn test_wal_discards_torn_tail() {
+        let dir = tempdir().unwrap();
+        let wal_path = dir.path().join("test.wal");
+
+        {
+            let wal = WriteAheadLog::open(&wal_path, WalSyncPolicy::Never).unwrap();
+            wal.append(&WalRecord::new_insert(1, "test", vec![1], vec![2])).unwrap();
+            wal.append(&WalRecord::new_insert(1, "test", vec![3], vec![4])).unwrap();
+        }
+        // 模拟写入第二条记录时崩溃
+        let len = std::fs::metadata(&wal_path).unwrap().len();
+        OpenOptions::new().write(true).open(&wal_path).unwrap().set_len(len - 3).unwrap();
+
+        let wal = WriteAheadLog::open(&wal_path, WalSyncPolicy::Never).unwrap();
+        assert_eq!(wal.current_lsn(), 1);
+        assert_eq!(wal.append(&WalRecord::new_delete(2, "test", vec![1])).unwrap(), 1);
+
+        let mut types = Vec::new();
+        wal.replay(|r| {
+            types.push(r.record_type);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(types, vec![RecordType::Insert, RecordType::Delete]);
+    }
+
+    #[test]
+    fn test_group_commit_applies_in_order() {
+        let dir = tempdir().unwrap();
+        let mut opts = rocksdb::Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = Arc::new(DB::open_cf(&opts, dir.path().join("db"), [METADATA_CF, "c"]).unwrap());
+        let wal = Arc::new(
+            WriteAheadLog::open(dir.path().join("test.wal"), WalSyncPolicy::Interval(Duration::from_secs(60)))
+                .unwrap()
+                .with_db(db.clone()),
+        );
+
+        let handles: Vec<_> = (0..8u8)
+            .map(|t| {
+                let (wal, db) = (wal.clone(), db.clone());
+                std::thread::spawn(move || {
+                    for i in 0..50u8 {
+                        let mut batch = WriteBatch::default();
+                        batch.put_cf(&db.cf_handle("c").unwrap(), [t, i], [i]);
+                        let record = WalRecord::new_insert(0, "c", vec![t, i], vec![i]);
+                        wal.commit(vec![record], batch).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let stats = wal.stats();
+        assert_eq!((stats.commits, stats.records), (400, 800));
+        assert!(stats.groups <= stats.commits);
+        assert_eq!(wal.current_lsn(), 800);
+        let applied = db.get_cf(&db.cf_handle(METADATA_CF).unwrap(), WAL_APPLIED_KEY).unwrap().unwrap();
+        assert_eq!(u64::from_le_bytes(applied.try_into().unwrap()), 800);
+        assert_eq!(db.get_cf(&db.cf_handle("c").unwrap(), [7, 49]).unwrap(), Some(vec![49]));
+
+        // 截断后 LSN 继续递增
+        wal.truncate().unwrap();
+        assert!(!wal.has_records());
+        let tx_id = wal.commit(vec![WalRecord::new_delete(0, "c", vec![7, 49])], WriteBatch::default()).unwrap();
+        assert_eq!(tx_id, 800);
+        let mut lsns = Vec::new();
+        wal.replay_with_lsn(|lsn, _| {
+            lsns.push(lsn);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(lsns, vec![800, 801]);
+    }
 }